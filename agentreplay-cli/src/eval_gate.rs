@@ -0,0 +1,591 @@
+// Copyright 2025 AgentReplay (https://github.com/agentreplay)
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program. If not, see <https://www.gnu.org/licenses/>.
+
+//! CI gate on eval results (`agentreplay eval run`)
+//!
+//! Talks to a running server. With a target (`--target-url` or
+//! `--openai-model`) it starts a simulation of the dataset and waits for the
+//! run to finish; otherwise it gates the dataset's latest finished run, i.e.
+//! results recorded by the agent's own test harness.
+//!
+//! The gate fails when the run did not complete, when its pass rate is below
+//! `--fail-below`, or when it dropped more than `--max-regression` below the
+//! baseline run (`--baseline`, else the previous completed run of the
+//! dataset). Under GitHub Actions the failures are also reported as `::error`
+//! annotations and a markdown summary is appended to `$GITHUB_STEP_SUMMARY`:
+//!
+//! ```yaml
+//! - run: agentreplay eval run --dataset $DATASET_ID --target-url http://localhost:8000/agent --fail-below 0.8
+//!   env:
+//!     AGENTREPLAY_URL: ${{ secrets.AGENTREPLAY_URL }}
+//!     AGENTREPLAY_API_KEY: ${{ secrets.AGENTREPLAY_API_KEY }}
+//! ```
+
+use agentreplay_core::TaskAggregate;
+use anyhow::{Context, Result};
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::io::Write;
+use std::time::{Duration, Instant};
+
+/// Failing tasks listed in reports and annotations
+const MAX_FAILING_TASKS: usize = 20;
+
+/// What `eval run` gates on
+pub struct GateOptions {
+    pub server: String,
+    pub api_key: Option<String>,
+    pub dataset_id: String,
+    /// Simulation target (see the server's `SimulationTarget`); recorded
+    /// results are gated when `None`
+    pub target: Option<serde_json::Value>,
+    pub agent_id: String,
+    pub trials: u32,
+    /// Gate this run instead of the dataset's latest
+    pub run_id: Option<String>,
+    pub baseline_id: Option<String>,
+    pub fail_below: f64,
+    pub max_regression: Option<f64>,
+    /// How long to wait for a simulation to finish
+    pub timeout: Duration,
+}
+
+/// Eval run as served by `GET /api/v1/evals/runs/:id`
+#[derive(Debug, Clone, Deserialize)]
+pub struct RunDetail {
+    pub id: String,
+    pub name: String,
+    pub status: String,
+    pub started_at: u64,
+    pub pass_rate: f64,
+    pub passed_count: usize,
+    pub failed_count: usize,
+    #[serde(default)]
+    pub task_aggregates: Vec<TaskAggregate>,
+    #[serde(default)]
+    pub aggregated_metrics: HashMap<String, f64>,
+    #[serde(default)]
+    pub results: Vec<TrialOutcome>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct TrialOutcome {
+    pub test_case_id: String,
+    pub passed: bool,
+    #[serde(default)]
+    pub error: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct RunList {
+    runs: Vec<RunListEntry>,
+}
+
+#[derive(Debug, Deserialize)]
+struct RunListEntry {
+    id: String,
+    status: String,
+    started_at: u64,
+}
+
+#[derive(Debug, Deserialize)]
+struct SimulationStarted {
+    run_id: String,
+}
+
+/// Run the gate compared against
+#[derive(Debug, Clone, Serialize)]
+pub struct Baseline {
+    pub run_id: String,
+    pub pass_rate: f64,
+}
+
+/// Task that failed at least one trial
+#[derive(Debug, Clone, Serialize)]
+pub struct FailingTask {
+    pub test_case_id: String,
+    pub trials: usize,
+    pub passed: usize,
+    /// First error reported for the task, if any
+    pub error: Option<String>,
+}
+
+/// Outcome of the gate, printed with `--json`
+#[derive(Debug, Clone, Serialize)]
+pub struct GateReport {
+    pub dataset_id: String,
+    pub run_id: String,
+    pub run_name: String,
+    pub status: String,
+    pub pass_rate: f64,
+    pub passed_count: usize,
+    pub failed_count: usize,
+    pub fail_below: f64,
+    pub baseline: Option<Baseline>,
+    pub metrics: HashMap<String, f64>,
+    pub failing_tasks: Vec<FailingTask>,
+    /// Why the gate failed, empty when it passed
+    pub failures: Vec<String>,
+    pub passed: bool,
+}
+
+impl GateReport {
+    pub fn evaluate(
+        dataset_id: &str,
+        run: &RunDetail,
+        baseline: Option<&RunDetail>,
+        fail_below: f64,
+        max_regression: Option<f64>,
+    ) -> Self {
+        let mut failures = Vec::new();
+        if run.status != "completed" {
+            failures.push(format!("Run {} is {}", run.id, run.status));
+        }
+        if run.passed_count + run.failed_count == 0 {
+            failures.push(format!("Run {} has no results", run.id));
+        } else if run.pass_rate < fail_below {
+            failures.push(format!(
+                "Pass rate {:.1}% is below {:.1}%",
+                run.pass_rate * 100.0,
+                fail_below * 100.0
+            ));
+        }
+        if let Some(baseline) = baseline {
+            let allowed = max_regression.unwrap_or(0.0);
+            let drop = baseline.pass_rate - run.pass_rate;
+            if drop > allowed + f64::EPSILON {
+                failures.push(format!(
+                    "Pass rate dropped {:.1} points from {:.1}% in baseline run {}",
+                    drop * 100.0,
+                    baseline.pass_rate * 100.0,
+                    baseline.id
+                ));
+            }
+        }
+
+        let mut failing_tasks: Vec<FailingTask> = run
+            .task_aggregates
+            .iter()
+            .filter(|task| task.passed < task.trials)
+            .map(|task| {
+                let test_case_id = format!("0x{:x}", task.test_case_id);
+                let error = run
+                    .results
+                    .iter()
+                    .filter(|r| r.test_case_id == test_case_id && !r.passed)
+                    .find_map(|r| r.error.clone());
+                FailingTask {
+                    test_case_id,
+                    trials: task.trials,
+                    passed: task.passed,
+                    error,
+                }
+            })
+            .collect();
+        failing_tasks.sort_by(|a, b| {
+            (a.passed as f64 / a.trials.max(1) as f64)
+                .total_cmp(&(b.passed as f64 / b.trials.max(1) as f64))
+        });
+        failing_tasks.truncate(MAX_FAILING_TASKS);
+
+        Self {
+            dataset_id: dataset_id.to_string(),
+            run_id: run.id.clone(),
+            run_name: run.name.clone(),
+            status: run.status.clone(),
+            pass_rate: run.pass_rate,
+            passed_count: run.passed_count,
+            failed_count: run.failed_count,
+            fail_below,
+            baseline: baseline.map(|b| Baseline {
+                run_id: b.id.clone(),
+                pass_rate: b.pass_rate,
+            }),
+            metrics: run.aggregated_metrics.clone(),
+            failing_tasks,
+            passed: failures.is_empty(),
+            failures,
+        }
+    }
+
+    pub fn print(&self) {
+        let verdict = if self.passed {
+            "✓ PASSED"
+        } else {
+            "✗ FAILED"
+        };
+        println!("{} eval gate: {} ({})", verdict, self.run_name, self.run_id);
+        println!(
+            "  Pass rate: {:.1}% ({}/{}), threshold {:.1}%",
+            self.pass_rate * 100.0,
+            self.passed_count,
+            self.passed_count + self.failed_count,
+            self.fail_below * 100.0
+        );
+        if let Some(baseline) = &self.baseline {
+            println!(
+                "  Baseline:  {:.1}% ({})",
+                baseline.pass_rate * 100.0,
+                baseline.run_id
+            );
+        }
+        let mut metrics: Vec<_> = self.metrics.iter().collect();
+        metrics.sort_by(|a, b| a.0.cmp(b.0));
+        for (name, value) in metrics {
+            println!("  {:<24} {:.3}", name, value);
+        }
+        if !self.failing_tasks.is_empty() {
+            println!("  Failing tasks:");
+            for task in &self.failing_tasks {
+                println!(
+                    "    {}  {}/{} trials passed{}",
+                    task.test_case_id,
+                    task.passed,
+                    task.trials,
+                    task.error
+                        .as_ref()
+                        .map(|e| format!(": {}", e))
+                        .unwrap_or_default()
+                );
+            }
+        }
+        for failure in &self.failures {
+            println!("  ✗ {}", failure);
+        }
+    }
+
+    /// GitHub Actions workflow commands for the gate's failures
+    pub fn github_annotations(&self) -> Vec<String> {
+        let mut lines: Vec<String> = self
+            .failures
+            .iter()
+            .map(|failure| format!("::error title=Eval gate::{}", escape_annotation(failure)))
+            .collect();
+        lines.extend(self.failing_tasks.iter().map(|task| {
+            let message = format!(
+                "{}/{} trials passed{}",
+                task.passed,
+                task.trials,
+                task.error
+                    .as_ref()
+                    .map(|e| format!(": {}", e))
+                    .unwrap_or_default()
+            );
+            format!(
+                "::warning title=Eval task {}::{}",
+                task.test_case_id,
+                escape_annotation(&message)
+            )
+        }));
+        lines
+    }
+
+    /// Markdown for `$GITHUB_STEP_SUMMARY`
+    pub fn step_summary(&self) -> String {
+        let mut md = format!(
+            "### {} Eval gate: {}\n\n| | |\n|---|---|\n| Run | `{}` |\n| Pass rate | {:.1}% ({}/{}) |\n| Threshold | {:.1}% |\n",
+            if self.passed { "✅" } else { "❌" },
+            self.run_name,
+            self.run_id,
+            self.pass_rate * 100.0,
+            self.passed_count,
+            self.passed_count + self.failed_count,
+            self.fail_below * 100.0
+        );
+        if let Some(baseline) = &self.baseline {
+            md.push_str(&format!(
+                "| Baseline | {:.1}% (`{}`) |\n",
+                baseline.pass_rate * 100.0,
+                baseline.run_id
+            ));
+        }
+        if !self.failing_tasks.is_empty() {
+            md.push_str("\n| Failing task | Passed | Error |\n|---|---|---|\n");
+            for task in &self.failing_tasks {
+                md.push_str(&format!(
+                    "| `{}` | {}/{} | {} |\n",
+                    task.test_case_id,
+                    task.passed,
+                    task.trials,
+                    task.error.as_deref().unwrap_or("").replace('|', "\\|")
+                ));
+            }
+        }
+        for failure in &self.failures {
+            md.push_str(&format!("\n- {}", failure));
+        }
+        md.push('\n');
+        md
+    }
+
+    /// Emit annotations and the step summary when running under GitHub Actions
+    pub fn report_to_github(&self) -> Result<()> {
+        if std::env::var("GITHUB_ACTIONS").as_deref() != Ok("true") {
+            return Ok(());
+        }
+        for line in self.github_annotations() {
+            println!("{}", line);
+        }
+        if let Ok(path) = std::env::var("GITHUB_STEP_SUMMARY") {
+            let mut file = std::fs::OpenOptions::new()
+                .create(true)
+                .append(true)
+                .open(&path)
+                .with_context(|| format!("Failed to open {}", path))?;
+            file.write_all(self.step_summary().as_bytes())?;
+        }
+        Ok(())
+    }
+}
+
+/// Escape data for a GitHub Actions workflow command
+fn escape_annotation(message: &str) -> String {
+    message
+        .replace('%', "%25")
+        .replace('\r', "%0D")
+        .replace('\n', "%0A")
+}
+
+/// Minimal client for the server's eval endpoints
+struct ServerClient {
+    base: String,
+    api_key: Option<String>,
+    http: reqwest::Client,
+}
+
+impl ServerClient {
+    fn new(base: &str, api_key: Option<String>) -> Self {
+        Self {
+            base: base.trim_end_matches('/').to_string(),
+            api_key,
+            http: reqwest::Client::new(),
+        }
+    }
+
+    async fn send<T: DeserializeOwned>(&self, request: reqwest::RequestBuilder) -> Result<T> {
+        let request = match &self.api_key {
+            Some(key) => request.header("X-API-Key", key),
+            None => request,
+        };
+        let response = request
+            .send()
+            .await
+            .with_context(|| format!("Failed to reach the server at {}", self.base))?;
+        let status = response.status();
+        if !status.is_success() {
+            let body = response.text().await.unwrap_or_default();
+            anyhow::bail!("Server returned {}: {}", status, body);
+        }
+        response
+            .json()
+            .await
+            .context("Invalid response from server")
+    }
+
+    async fn get<T: DeserializeOwned>(&self, path: &str) -> Result<T> {
+        self.send(self.http.get(format!("{}{}", self.base, path)))
+            .await
+    }
+
+    async fn post<T: DeserializeOwned>(&self, path: &str, body: &serde_json::Value) -> Result<T> {
+        self.send(self.http.post(format!("{}{}", self.base, path)).json(body))
+            .await
+    }
+
+    async fn run(&self, run_id: &str) -> Result<RunDetail> {
+        self.get(&format!("/api/v1/evals/runs/{}", run_id)).await
+    }
+
+    /// Finished runs of a dataset, newest first
+    async fn finished_runs(&self, dataset_id: &str, status: Option<&str>) -> Result<Vec<String>> {
+        let mut path = format!(
+            "/api/v1/evals/runs?dataset_id={}&sort_by=started_at&sort_order=desc&page_size=100",
+            dataset_id
+        );
+        if let Some(status) = status {
+            path.push_str(&format!("&status={}", status));
+        }
+        let list: RunList = self.get(&path).await?;
+        let mut runs: Vec<RunListEntry> = list
+            .runs
+            .into_iter()
+            .filter(|r| r.status != "running")
+            .collect();
+        runs.sort_by(|a, b| b.started_at.cmp(&a.started_at));
+        Ok(runs.into_iter().map(|r| r.id).collect())
+    }
+}
+
+/// Run or look up the gated eval run and check it
+pub async fn run_gate(options: &GateOptions) -> Result<GateReport> {
+    let client = ServerClient::new(&options.server, options.api_key.clone());
+
+    let run = match (&options.target, &options.run_id) {
+        (Some(target), _) => {
+            let started: SimulationStarted = client
+                .post(
+                    "/api/v1/simulations",
+                    &serde_json::json!({
+                        "dataset_id": options.dataset_id,
+                        "target": target,
+                        "agent_id": options.agent_id,
+                        "trials": options.trials,
+                        "name": format!("CI gate: {}", options.agent_id),
+                    }),
+                )
+                .await
+                .context("Failed to start simulation")?;
+            wait_for_run(&client, &started.run_id, options.timeout).await?
+        }
+        (None, Some(run_id)) => client.run(run_id).await?,
+        (None, None) => {
+            let latest = client
+                .finished_runs(&options.dataset_id, None)
+                .await?
+                .into_iter()
+                .next()
+                .with_context(|| {
+                    format!("Dataset {} has no finished eval runs", options.dataset_id)
+                })?;
+            client.run(&latest).await?
+        }
+    };
+
+    let baseline = match (&options.baseline_id, options.max_regression) {
+        (Some(baseline_id), _) => Some(client.run(baseline_id).await?),
+        (None, Some(_)) => {
+            let previous = client
+                .finished_runs(&options.dataset_id, Some("completed"))
+                .await?
+                .into_iter()
+                .find(|id| id != &run.id);
+            match previous {
+                Some(id) => Some(client.run(&id).await?),
+                None => None,
+            }
+        }
+        (None, None) => None,
+    };
+
+    Ok(GateReport::evaluate(
+        &options.dataset_id,
+        &run,
+        baseline.as_ref(),
+        options.fail_below,
+        options.max_regression,
+    ))
+}
+
+async fn wait_for_run(client: &ServerClient, run_id: &str, timeout: Duration) -> Result<RunDetail> {
+    let started = Instant::now();
+    loop {
+        let run = client.run(run_id).await?;
+        if run.status != "running" {
+            return Ok(run);
+        }
+        if started.elapsed() > timeout {
+            anyhow::bail!(
+                "Run {} still running after {}s ({} results so far)",
+                run_id,
+                timeout.as_secs(),
+                run.passed_count + run.failed_count
+            );
+        }
+        tokio::time::sleep(Duration::from_secs(2)).await;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn run(id: &str, status: &str, passed: usize, failed: usize) -> RunDetail {
+        let total = passed + failed;
+        RunDetail {
+            id: id.to_string(),
+            name: "nightly".to_string(),
+            status: status.to_string(),
+            started_at: 0,
+            pass_rate: if total == 0 {
+                0.0
+            } else {
+                passed as f64 / total as f64
+            },
+            passed_count: passed,
+            failed_count: failed,
+            task_aggregates: Vec::new(),
+            aggregated_metrics: HashMap::new(),
+            results: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn test_gate_threshold_and_status() {
+        let report = GateReport::evaluate("0x1", &run("0xa", "completed", 9, 1), None, 0.8, None);
+        assert!(report.passed);
+        assert!(report.github_annotations().is_empty());
+
+        let report = GateReport::evaluate("0x1", &run("0xa", "completed", 7, 3), None, 0.8, None);
+        assert!(!report.passed);
+        assert_eq!(report.failures, vec!["Pass rate 70.0% is below 80.0%"]);
+
+        let report = GateReport::evaluate("0x1", &run("0xa", "failed", 0, 0), None, 0.8, None);
+        assert_eq!(report.failures.len(), 2);
+    }
+
+    #[test]
+    fn test_gate_regression_against_baseline() {
+        let current = run("0xb", "completed", 17, 3);
+        let baseline = run("0xa", "completed", 19, 1);
+
+        let report = GateReport::evaluate("0x1", &current, Some(&baseline), 0.8, Some(0.15));
+        assert!(report.passed);
+
+        let report = GateReport::evaluate("0x1", &current, Some(&baseline), 0.8, Some(0.0));
+        assert!(!report.passed);
+        assert!(report.failures[0].contains("dropped 10.0 points"));
+        assert!(report
+            .step_summary()
+            .contains("| Baseline | 95.0% (`0xa`) |"));
+    }
+
+    #[test]
+    fn test_failing_tasks_are_annotated() {
+        let mut current = run("0xb", "completed", 1, 2);
+        current.task_aggregates = serde_json::from_value(serde_json::json!([
+            {"test_case_id": 1, "trials": 2, "passed": 1, "pass_rate": 0.5},
+            {"test_case_id": 2, "trials": 1, "passed": 0, "pass_rate": 0.0}
+        ]))
+        .unwrap();
+        current.results = vec![TrialOutcome {
+            test_case_id: "0x2".to_string(),
+            passed: false,
+            error: Some("Agent call failed: 500\nboom".to_string()),
+        }];
+
+        let report = GateReport::evaluate("0x1", &current, None, 0.5, None);
+        assert_eq!(report.failing_tasks[0].test_case_id, "0x2");
+        let annotations = report.github_annotations();
+        assert_eq!(
+            annotations[0],
+            "::error title=Eval gate::Pass rate 33.3%25 is below 50.0%25"
+        );
+        assert_eq!(
+            annotations[1],
+            "::warning title=Eval task 0x2::0/1 trials passed: Agent call failed: 500%0Aboom"
+        );
+    }
+}
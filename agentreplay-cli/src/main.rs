@@ -19,13 +19,27 @@
 
 use anyhow::{Context, Result};
 use clap::{Parser, Subcommand};
-use agentreplay_core::{AgentFlowEdge, SpanType};
+use agentreplay_core::diagnostics::{list_bundles, upload_bundle, DIAGNOSTICS_DIR_NAME};
+use agentreplay_core::service::{self, ServiceManager, ServiceSpec};
+use agentreplay_core::{AgentFlowEdge, DiagnosticBundle, DiagnosticsCollector, LogSource, SpanType};
 use agentreplay_plugins::{PluginConfig, PluginManager, UninstallMode};
+use agentreplay_index::VectorIndex;
 use agentreplay_query::Agentreplay;
-use std::path::PathBuf;
+use agentreplay_storage::benchmark_store::{
+    BenchmarkComparison, BenchmarkMetric, BenchmarkRun, BenchmarkStore, MachineFingerprint,
+};
+use agentreplay_storage::{
+    AFFReader, DataDirLock, DirLockOutcome, IntegrityReport, UnifiedStorage,
+};
+use std::path::{Path, PathBuf};
 use std::process::Command;
 use tracing::{info, Level};
 
+mod eval_gate;
+mod migrate;
+mod remote;
+mod top;
+
 #[derive(Parser)]
 #[command(name = "agentreplay")]
 #[command(about = "Agentreplay - AgentFlow Format Database", long_about = None)]
@@ -42,6 +56,14 @@ struct Cli {
     #[arg(long)]
     json: bool,
 
+    /// Run get/query/children/ancestors/stats against this server instead of --db-path
+    #[arg(long, value_name = "URL")]
+    remote: Option<String>,
+
+    /// API key for --remote and `top` (default: $AGENTREPLAY_API_KEY)
+    #[arg(long)]
+    api_key: Option<String>,
+
     #[command(subcommand)]
     command: Commands,
 }
@@ -138,6 +160,15 @@ enum Commands {
     /// Get database statistics
     Stats,
 
+    /// Live dashboard of a server's ingestion rate, errors, recent spans and cost
+    ///
+    /// Connects to --remote, else $AGENTREPLAY_URL or http://127.0.0.1:47100.
+    Top {
+        /// Only show spans of this project
+        #[arg(long)]
+        project: Option<u16>,
+    },
+
     /// Load test data
     LoadTest {
         /// Number of edges to generate
@@ -149,11 +180,25 @@ enum Commands {
         agents: u64,
     },
 
-    /// Benchmark write performance
+    /// Benchmark write performance (or query latency with `benchmark query`)
+    #[command(args_conflicts_with_subcommands = true)]
     Benchmark {
+        #[command(subcommand)]
+        kind: Option<BenchmarkKind>,
+
         /// Number of writes
         #[arg(default_value = "10000")]
         writes: usize,
+
+        /// Percent change that counts as a regression versus the previous run
+        #[arg(long, default_value = "10.0")]
+        threshold: f64,
+    },
+
+    /// Recorded benchmark history and regression checks
+    Benchmarks {
+        #[command(subcommand)]
+        command: BenchmarksCommands,
     },
 
     /// Backup and restore commands
@@ -167,6 +212,247 @@ enum Commands {
         #[command(subcommand)]
         command: PluginCommands,
     },
+
+    /// Crash diagnostics commands
+    Diagnostics {
+        #[command(subcommand)]
+        command: DiagnosticsCommands,
+    },
+
+    /// Run eval suites against a server and gate on the results (for CI)
+    Eval {
+        #[command(subcommand)]
+        command: EvalCommands,
+    },
+
+    /// Move a whole instance between hosts or across breaking storage versions
+    ///
+    /// Archives projects, traces, prompts, datasets, evals, saved views and
+    /// settings. Run while the server is stopped.
+    Migrate {
+        #[command(subcommand)]
+        command: MigrateCommands,
+    },
+
+    /// Run the ingestion server as a per-user background service
+    ///
+    /// Uses launchd on macOS, systemd on Linux and Task Scheduler on Windows,
+    /// so traces are captured even when the desktop app isn't open.
+    Service {
+        #[command(subcommand)]
+        command: ServiceCommands,
+    },
+
+    /// Check stored records and AFF files for corruption and rebuild indexes
+    ///
+    /// Run while the server is stopped; --repair, --rebuild-indexes and
+    /// --reingest refuse to run while another process holds the database.
+    Fsck {
+        /// Delete corrupt records and rebuild the indexes around them
+        #[arg(long)]
+        repair: bool,
+
+        /// Rebuild the secondary, causal and vector indexes from the raw records
+        #[arg(long)]
+        rebuild_indexes: bool,
+
+        /// Additional AFF files to check (*.aff files in the database directory are always checked)
+        #[arg(long = "aff")]
+        aff_files: Vec<PathBuf>,
+
+        /// Re-ingest intact AFF edges that are missing from the database
+        #[arg(long)]
+        reingest: bool,
+    },
+}
+
+#[derive(Subcommand, Clone)]
+enum EvalCommands {
+    /// Evaluate a dataset and exit nonzero when the results regress
+    ///
+    /// With --target-url or --openai-model the dataset's tasks are simulated
+    /// against the agent; otherwise the dataset's latest finished run is gated.
+    Run {
+        /// Eval dataset ID (hex)
+        #[arg(long)]
+        dataset: String,
+
+        /// Minimum pass rate (0-1)
+        #[arg(long, default_value = "0.8")]
+        fail_below: f64,
+
+        /// Server URL (default: $AGENTREPLAY_URL or http://127.0.0.1:47100)
+        #[arg(long)]
+        server: Option<String>,
+
+        /// API key (default: $AGENTREPLAY_API_KEY)
+        #[arg(long)]
+        api_key: Option<String>,
+
+        /// HTTP agent endpoint to simulate the dataset against
+        #[arg(long, conflicts_with_all = ["openai_model", "run"])]
+        target_url: Option<String>,
+
+        /// Header sent to the HTTP agent, as NAME=VALUE (repeatable)
+        #[arg(long = "header", requires = "target_url")]
+        headers: Vec<String>,
+
+        /// Model of an OpenAI-compatible endpoint to simulate the dataset against
+        #[arg(long, conflicts_with = "run")]
+        openai_model: Option<String>,
+
+        /// Base URL of the OpenAI-compatible endpoint
+        #[arg(long, requires = "openai_model")]
+        base_url: Option<String>,
+
+        /// Vault key the server authenticates the OpenAI-compatible endpoint with
+        #[arg(long, requires = "openai_model")]
+        key_alias: Option<String>,
+
+        /// Agent ID recorded on the simulation run
+        #[arg(long, default_value = "ci")]
+        agent_id: String,
+
+        /// Trials per task when simulating
+        #[arg(long, default_value = "1")]
+        trials: u32,
+
+        /// Gate this eval run (hex) instead of the dataset's latest
+        #[arg(long)]
+        run: Option<String>,
+
+        /// Run (hex) to compare against (default: previous completed run, with --max-regression)
+        #[arg(long)]
+        baseline: Option<String>,
+
+        /// Allowed pass rate drop from the baseline (0-1)
+        #[arg(long)]
+        max_regression: Option<f64>,
+
+        /// Seconds to wait for a simulation to finish
+        #[arg(long, default_value = "1800")]
+        timeout: u64,
+    },
+}
+
+#[derive(Subcommand, Clone)]
+enum ServiceCommands {
+    /// Register agentreplay-server to start at login, serving --db-path
+    Install {
+        /// Server binary (default: agentreplay-server next to this binary, else on PATH)
+        #[arg(long)]
+        server_bin: Option<PathBuf>,
+
+        /// HTTP listen address
+        #[arg(long, default_value = "127.0.0.1:47100")]
+        http_addr: String,
+
+        /// Server configuration file (TOML)
+        #[arg(long)]
+        config: Option<PathBuf>,
+
+        /// Only register the service; it starts at next login
+        #[arg(long)]
+        no_start: bool,
+    },
+
+    /// Stop and remove the service
+    Uninstall,
+
+    /// Show whether the service is installed and running
+    Status,
+}
+
+#[derive(Subcommand, Clone)]
+enum MigrateCommands {
+    /// Write a versioned archive (.tar.gz) of the instance
+    Export {
+        /// Export every database, registry and setting (required)
+        #[arg(long)]
+        all: bool,
+
+        /// Output path for the archive
+        #[arg(short, long)]
+        output: Option<PathBuf>,
+    },
+
+    /// Import an archive, remapping IDs that collide with existing data
+    Import {
+        /// Path to the archive
+        path: PathBuf,
+
+        /// Validate the archive and report what would change without writing
+        #[arg(long)]
+        dry_run: bool,
+
+        /// Give every imported trace, dataset and run a new ID
+        #[arg(long)]
+        remap_ids: bool,
+    },
+}
+
+#[derive(Subcommand, Clone)]
+enum BenchmarkKind {
+    /// Measure query latency on a synthetic dataset in a scratch database
+    Query {
+        /// Traces in the synthetic dataset
+        #[arg(long, default_value = "2000")]
+        traces: usize,
+
+        /// Spans per trace (one root plus a binary tree of children)
+        #[arg(long, default_value = "8")]
+        spans_per_trace: usize,
+
+        /// Timed runs of each query type
+        #[arg(long, default_value = "200")]
+        iterations: usize,
+
+        /// Percent change that counts as a regression versus the previous run
+        #[arg(long, default_value = "10.0")]
+        threshold: f64,
+    },
+}
+
+#[derive(Subcommand, Clone)]
+enum BenchmarksCommands {
+    /// List recorded benchmark runs
+    List {
+        /// Only show runs of this suite (e.g. "cli/write", "index/hnsw")
+        #[arg(long)]
+        suite: Option<String>,
+
+        /// Only show runs recorded on this machine
+        #[arg(long)]
+        this_machine: bool,
+    },
+
+    /// Compare the latest run of a suite against the previous run on this machine
+    Compare {
+        /// Benchmark suite to compare
+        #[arg(default_value = "cli/write")]
+        suite: String,
+
+        /// Percent change that counts as a regression
+        #[arg(long, default_value = "10.0")]
+        threshold: f64,
+    },
+}
+
+#[derive(Subcommand, Clone)]
+enum DiagnosticsCommands {
+    /// Collect crash bundles and recent logs into a single ZIP file
+    Collect {
+        /// Output path for the ZIP file
+        #[arg(short, long)]
+        output: Option<PathBuf>,
+
+        /// Upload the collected ZIP to this URL (opt-in)
+        #[arg(long)]
+        upload: Option<String>,
+    },
+
+    /// List crash bundles written by the server and desktop app
+    List,
 }
 
 #[derive(Subcommand)]
@@ -376,6 +662,81 @@ async fn main() -> Result<()> {
         return handle_backup_command(command.clone(), &cli.db_path, cli.json).await;
     }
 
+    // Handle benchmark history commands separately (only read the benchmark store)
+    if let Commands::Benchmarks { command } = &cli.command {
+        return handle_benchmarks_command(command.clone(), &cli.db_path, cli.json);
+    }
+
+    // Handle query benchmarks separately (they run against a scratch database)
+    if let Commands::Benchmark {
+        kind: Some(kind), ..
+    } = &cli.command
+    {
+        return handle_benchmark_kind(kind.clone(), &cli.db_path, cli.json).await;
+    }
+
+    // Handle diagnostics commands separately (must work even if the database won't open)
+    if let Commands::Diagnostics { command } = &cli.command {
+        return handle_diagnostics_command(command.clone(), &cli.db_path, cli.json).await;
+    }
+
+    // Handle eval commands separately (they talk to a running server)
+    if let Commands::Eval { command } = &cli.command {
+        return handle_eval_command(command.clone(), cli.json).await;
+    }
+
+    // Handle the background service separately (it only registers the server binary)
+    if let Commands::Service { command } = &cli.command {
+        return handle_service_command(command.clone(), &cli.db_path, cli.json);
+    }
+
+    // Handle migrations separately (they open every database of the instance themselves)
+    if let Commands::Migrate { command } = &cli.command {
+        return handle_migrate_command(command.clone(), &cli.db_path, cli.json);
+    }
+
+    // Handle fsck separately (works on the raw records before the indexes are trusted)
+    if let Commands::Fsck {
+        repair,
+        rebuild_indexes,
+        aff_files,
+        reingest,
+    } = &cli.command
+    {
+        return handle_fsck_command(
+            *repair,
+            *rebuild_indexes,
+            aff_files.clone(),
+            *reingest,
+            &cli.db_path,
+            cli.json,
+        )
+        .await;
+    }
+
+    // Handle the dashboard separately (it follows a server's live trace stream)
+    if let Commands::Top { project } = &cli.command {
+        let server = cli
+            .remote
+            .clone()
+            .or_else(|| std::env::var("AGENTREPLAY_URL").ok())
+            .unwrap_or_else(|| "http://127.0.0.1:47100".to_string());
+        let api_key = cli
+            .api_key
+            .clone()
+            .or_else(|| std::env::var("AGENTREPLAY_API_KEY").ok());
+        return top::run(&server, api_key, *project).await;
+    }
+
+    // Handle remote inspection separately (the server's database is read over its REST API)
+    if let Some(url) = &cli.remote {
+        let api_key = cli
+            .api_key
+            .clone()
+            .or_else(|| std::env::var("AGENTREPLAY_API_KEY").ok());
+        return handle_remote_command(cli.command, url, api_key, cli.json).await;
+    }
+
     // Open database
     let db = Agentreplay::open(&cli.db_path).context("Failed to open database")?;
 
@@ -598,7 +959,9 @@ async fn main() -> Result<()> {
             db.sync()?;
         }
 
-        Commands::Benchmark { writes } => {
+        Commands::Benchmark {
+            writes, threshold, ..
+        } => {
             info!("Benchmarking {} writes", writes);
 
             let mut latencies = Vec::new();
@@ -622,6 +985,20 @@ async fn main() -> Result<()> {
 
             let throughput = writes as f64 / total_duration.as_secs_f64();
 
+            // Record the run so later runs on this machine can be compared against it
+            let run =
+                BenchmarkRun::new(BENCH_SUITE_CLI_WRITE, serde_json::json!({ "writes": writes }))
+                    .with_metric(BenchmarkMetric::higher_is_better(
+                        "throughput",
+                        throughput,
+                        "writes/s",
+                    ))
+                    .with_latency_percentiles("latency", &mut latencies);
+            let store = BenchmarkStore::open(cli.db_path.join(BENCHMARKS_DIR_NAME))
+                .context("Failed to open benchmark store")?;
+            let comparison = store.compare_to_previous(&run, threshold)?;
+            store.record(&run)?;
+
             println!("Benchmark Results ({} writes)", writes);
             println!("================================");
             println!("Throughput: {:.0} writes/sec", throughput);
@@ -630,9 +1007,22 @@ async fn main() -> Result<()> {
             println!("  P99:  {:?}", p99);
             println!("  P999: {:?}", p999);
             println!("Total time: {:.2}s", total_duration.as_secs_f64());
+            println!();
+
+            match comparison {
+                Some(comparison) => print_benchmark_comparison(&comparison),
+                None => println!("No previous run on this machine to compare against"),
+            }
         }
 
         Commands::Backup { .. } => unreachable!(), // Handled above
+        Commands::Benchmarks { .. } => unreachable!(), // Handled above
+        Commands::Diagnostics { .. } => unreachable!(), // Handled above
+        Commands::Fsck { .. } => unreachable!(), // Handled above
+        Commands::Eval { .. } => unreachable!(), // Handled above
+        Commands::Top { .. } => unreachable!(), // Handled above
+        Commands::Migrate { .. } => unreachable!(), // Handled above
+        Commands::Service { .. } => unreachable!(), // Handled above
     }
 
     Ok(())
@@ -2037,3 +2427,1005 @@ fn add_dir_to_zip<W: std::io::Write + std::io::Seek>(
     }
     Ok(())
 }
+
+/// Benchmark store directory, relative to the database directory
+const BENCHMARKS_DIR_NAME: &str = "benchmarks";
+
+/// Suite name for `agentreplay benchmark`
+const BENCH_SUITE_CLI_WRITE: &str = "cli/write";
+
+/// Suite name for `agentreplay benchmark query`
+const BENCH_SUITE_CLI_QUERY: &str = "cli/query";
+
+/// Embedding dimension of the synthetic query-benchmark dataset
+const BENCH_QUERY_VECTOR_DIM: usize = 64;
+
+/// Print a metric-by-metric comparison, flagging regressions
+fn print_benchmark_comparison(comparison: &BenchmarkComparison) {
+    println!(
+        "Compared to {} (threshold {:.1}%)",
+        comparison.baseline_run_id, comparison.threshold_pct
+    );
+    for delta in &comparison.deltas {
+        println!(
+            "  {} {:<16} {:>12.2} -> {:>12.2} {:<8} ({:+.1}%)",
+            if delta.regression { "✗" } else { "✓" },
+            delta.name,
+            delta.baseline,
+            delta.current,
+            delta.unit,
+            delta.change_pct
+        );
+    }
+    if comparison.has_regressions() {
+        println!("⚠ Regression detected");
+    } else {
+        println!("✓ No regressions");
+    }
+}
+
+/// Handle benchmark subcommands
+async fn handle_benchmark_kind(
+    kind: BenchmarkKind,
+    db_path: &std::path::Path,
+    json_output: bool,
+) -> Result<()> {
+    match kind {
+        BenchmarkKind::Query {
+            traces,
+            spans_per_trace,
+            iterations,
+            threshold,
+        } => {
+            if traces == 0 || spans_per_trace == 0 || iterations == 0 {
+                anyhow::bail!("--traces, --spans-per-trace and --iterations must be positive");
+            }
+
+            let scratch = std::env::temp_dir()
+                .join(format!("agentreplay-bench-query-{}", std::process::id()));
+            let result = run_query_benchmark(&scratch, traces, spans_per_trace, iterations).await;
+            let _ = std::fs::remove_dir_all(&scratch);
+            let run = result?;
+
+            let store = BenchmarkStore::open(db_path.join(BENCHMARKS_DIR_NAME))
+                .context("Failed to open benchmark store")?;
+            let comparison = store.compare_to_previous(&run, threshold)?;
+            store.record(&run)?;
+
+            if json_output {
+                let output = serde_json::json!({
+                    "run": run,
+                    "comparison": comparison,
+                });
+                println!("{}", serde_json::to_string_pretty(&output)?);
+            } else {
+                println!(
+                    "Query Benchmark ({} traces x {} spans, {} iterations)",
+                    traces, spans_per_trace, iterations
+                );
+                println!("================================");
+                for metric in &run.metrics {
+                    println!(
+                        "  {:<24} {:>12.2} {}",
+                        metric.name, metric.value, metric.unit
+                    );
+                }
+                println!();
+
+                match &comparison {
+                    Some(comparison) => print_benchmark_comparison(comparison),
+                    None => println!("No previous run on this machine to compare against"),
+                }
+            }
+
+            // Fail CI jobs when any query got slower than the threshold allows
+            if comparison.is_some_and(|c| c.has_regressions()) {
+                std::process::exit(1);
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// Deterministic xorshift generator so every run queries the same dataset
+struct BenchRng(u64);
+
+impl BenchRng {
+    fn next(&mut self) -> u64 {
+        self.0 ^= self.0 << 13;
+        self.0 ^= self.0 >> 7;
+        self.0 ^= self.0 << 17;
+        self.0
+    }
+
+    fn below(&mut self, n: usize) -> usize {
+        (self.next() % n as u64) as usize
+    }
+
+    fn vector(&mut self) -> agentreplay_index::Embedding {
+        let values: Vec<f32> = (0..BENCH_QUERY_VECTOR_DIM)
+            .map(|_| (self.next() % 2000) as f32 / 1000.0 - 1.0)
+            .collect();
+        agentreplay_index::Embedding::from(values)
+    }
+}
+
+/// Time `iterations` runs of `op`
+fn time_query(
+    iterations: usize,
+    mut op: impl FnMut() -> Result<()>,
+) -> Result<Vec<std::time::Duration>> {
+    let mut samples = Vec::with_capacity(iterations);
+    for _ in 0..iterations {
+        let start = std::time::Instant::now();
+        op()?;
+        samples.push(start.elapsed());
+    }
+    Ok(samples)
+}
+
+/// Build the synthetic dataset in `scratch` and time each query type
+async fn run_query_benchmark(
+    scratch: &std::path::Path,
+    traces: usize,
+    spans_per_trace: usize,
+    iterations: usize,
+) -> Result<BenchmarkRun> {
+    let db = Agentreplay::open(scratch).context("Failed to open scratch database")?;
+    let mut rng = BenchRng(0x9E37_79B9_7F4A_7C15);
+
+    // One trace per second, spans 1ms apart, ending now
+    const TRACE_SPACING_US: u64 = 1_000_000;
+    let now_us = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)?
+        .as_micros() as u64;
+    let base_us = now_us - traces as u64 * TRACE_SPACING_US;
+
+    let mut roots = Vec::with_capacity(traces);
+    let mut leaves = Vec::with_capacity(traces);
+    let ingest_start = std::time::Instant::now();
+    for t in 0..traces {
+        let trace_start = base_us + t as u64 * TRACE_SPACING_US;
+        let mut spans: Vec<AgentFlowEdge> = Vec::with_capacity(spans_per_trace);
+        for i in 0..spans_per_trace {
+            let (span_type, parent) = if i == 0 {
+                (SpanType::Root, 0)
+            } else {
+                (SpanType::ToolCall, spans[(i - 1) / 2].edge_id)
+            };
+            let mut edge = AgentFlowEdge::new(1, 0, (t % 16) as u64, t as u64, span_type, parent);
+            edge.timestamp_us = trace_start + i as u64 * 1000;
+            edge.duration_us = 500 + rng.below(5000) as u32;
+            edge.token_count = 50 + rng.below(2000) as u32;
+            edge.checksum = edge.compute_checksum();
+            spans.push(edge);
+        }
+
+        db.insert_with_vector(spans[0], rng.vector()).await?;
+        db.insert_batch(&spans[1..]).await?;
+        roots.push(spans[0].edge_id);
+        leaves.push(spans[spans_per_trace - 1].edge_id);
+    }
+    let ingest_secs = ingest_start.elapsed().as_secs_f64();
+    let total_spans = traces * spans_per_trace;
+
+    let span_us = traces as u64 * TRACE_SPACING_US;
+    let scan_window_us = (span_us / 100).max(TRACE_SPACING_US);
+    let analytics_window_us = (span_us / 10).max(TRACE_SPACING_US);
+    let window = |rng: &mut BenchRng, width: u64| {
+        let start = base_us + rng.next() % span_us.saturating_sub(width).max(1);
+        (start, start + width)
+    };
+
+    let mut temporal = time_query(iterations, || {
+        let (start, end) = window(&mut rng, scan_window_us);
+        db.query_temporal_range_for_tenant(start, end, 1)?;
+        Ok(())
+    })?;
+    let mut children = time_query(iterations, || {
+        db.get_children(roots[rng.below(traces)])?;
+        Ok(())
+    })?;
+    let mut ancestors = time_query(iterations, || {
+        db.get_ancestors(leaves[rng.below(traces)])?;
+        Ok(())
+    })?;
+    let mut semantic = time_query(iterations, || {
+        db.semantic_search(&rng.vector(), 10)?;
+        Ok(())
+    })?;
+    let mut analytics = time_query(iterations, || {
+        let (start, end) = window(&mut rng, analytics_window_us);
+        db.query_metrics(1, 0, start, end);
+        db.query_metrics_timeseries(0, start, end);
+        Ok(())
+    })?;
+
+    db.close()?;
+
+    Ok(BenchmarkRun::new(
+        BENCH_SUITE_CLI_QUERY,
+        serde_json::json!({
+            "traces": traces,
+            "spans_per_trace": spans_per_trace,
+            "iterations": iterations,
+            "vector_dim": BENCH_QUERY_VECTOR_DIM,
+        }),
+    )
+    .with_metric(BenchmarkMetric::higher_is_better(
+        "ingest_throughput",
+        total_spans as f64 / ingest_secs,
+        "spans/s",
+    ))
+    .with_latency_percentiles("temporal_range", &mut temporal)
+    .with_latency_percentiles("children", &mut children)
+    .with_latency_percentiles("ancestors", &mut ancestors)
+    .with_latency_percentiles("semantic_search", &mut semantic)
+    .with_latency_percentiles("analytics", &mut analytics))
+}
+
+/// Handle benchmark history commands
+fn handle_benchmarks_command(
+    command: BenchmarksCommands,
+    db_path: &std::path::Path,
+    json_output: bool,
+) -> Result<()> {
+    let store = BenchmarkStore::open(db_path.join(BENCHMARKS_DIR_NAME))
+        .context("Failed to open benchmark store")?;
+    let machine = MachineFingerprint::current();
+
+    match command {
+        BenchmarksCommands::List {
+            suite,
+            this_machine,
+        } => {
+            let runs: Vec<_> = store
+                .list_runs()?
+                .into_iter()
+                .filter(|r| suite.as_ref().map_or(true, |s| &r.suite == s))
+                .filter(|r| !this_machine || r.machine.fingerprint == machine.fingerprint)
+                .collect();
+
+            if json_output {
+                println!("{}", serde_json::to_string_pretty(&runs)?);
+                return Ok(());
+            }
+
+            if runs.is_empty() {
+                println!("No benchmark runs recorded");
+                return Ok(());
+            }
+
+            for run in &runs {
+                let metrics: Vec<String> = run
+                    .metrics
+                    .iter()
+                    .map(|m| format!("{}={:.2}{}", m.name, m.value, m.unit))
+                    .collect();
+                println!(
+                    "{}  {:<12} {} ({})  {}",
+                    run.run_id,
+                    run.suite,
+                    run.machine.hostname,
+                    run.machine.fingerprint,
+                    metrics.join(" ")
+                );
+            }
+        }
+
+        BenchmarksCommands::Compare { suite, threshold } => {
+            let latest = store
+                .runs_for_suite(&suite, Some(&machine.fingerprint))?
+                .into_iter()
+                .max_by_key(|r| r.timestamp_us)
+                .with_context(|| format!("No '{}' runs recorded on this machine", suite))?;
+            let comparison = store
+                .compare_to_previous(&latest, threshold)?
+                .with_context(|| format!("Only one '{}' run recorded on this machine", suite))?;
+
+            if json_output {
+                println!("{}", serde_json::to_string_pretty(&comparison)?);
+            } else {
+                print_benchmark_comparison(&comparison);
+            }
+
+            if comparison.has_regressions() {
+                std::process::exit(1);
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// Outcome of checking one AFF file
+struct AffCheck {
+    path: PathBuf,
+    error: Option<String>,
+    header_valid: bool,
+    edges: Vec<AgentFlowEdge>,
+    corrupt: Vec<(u64, String)>,
+    missing: u64,
+}
+
+impl AffCheck {
+    fn is_clean(&self) -> bool {
+        self.error.is_none() && self.header_valid && self.corrupt.is_empty() && self.missing == 0
+    }
+
+    fn to_json(&self) -> serde_json::Value {
+        serde_json::json!({
+            "path": self.path.display().to_string(),
+            "error": self.error,
+            "header_valid": self.header_valid,
+            "valid_edges": self.edges.len(),
+            "corrupt_edges": self
+                .corrupt
+                .iter()
+                .map(|(index, reason)| serde_json::json!({ "index": index, "reason": reason }))
+                .collect::<Vec<_>>(),
+            "missing_edges": self.missing,
+        })
+    }
+}
+
+fn check_aff_file(path: PathBuf) -> AffCheck {
+    let scan = AFFReader::open(&path).and_then(|mut reader| reader.scan());
+    match scan {
+        Ok(scan) => AffCheck {
+            path,
+            error: None,
+            header_valid: scan.header_valid,
+            edges: scan.edges,
+            corrupt: scan.corrupt,
+            missing: scan.missing,
+        },
+        Err(e) => AffCheck {
+            path,
+            error: Some(e.to_string()),
+            header_valid: false,
+            edges: Vec::new(),
+            corrupt: Vec::new(),
+            missing: 0,
+        },
+    }
+}
+
+/// Rebuild the HNSW graph from its stored vectors, dropping edges that no longer exist
+///
+/// Returns (kept, dropped), or `None` when there is no vector index.
+fn rebuild_vector_index(
+    storage: &UnifiedStorage,
+    path: &std::path::Path,
+) -> Result<Option<(usize, usize)>> {
+    if !path.exists() {
+        return Ok(None);
+    }
+    let old = VectorIndex::load_from_disk(path)
+        .with_context(|| format!("Failed to load vector index {:?}", path))?;
+    let fresh = VectorIndex::new(old.metric());
+
+    let (mut kept, mut dropped) = (0, 0);
+    for (edge_id, vector) in old.entries() {
+        if storage.get(edge_id)?.is_some() {
+            fresh.add(edge_id, vector).map_err(anyhow::Error::msg)?;
+            kept += 1;
+        } else {
+            dropped += 1;
+        }
+    }
+
+    fresh
+        .save_to_disk(path)
+        .with_context(|| format!("Failed to save vector index {:?}", path))?;
+    Ok(Some((kept, dropped)))
+}
+
+fn print_integrity_report(report: &IntegrityReport) {
+    println!("Records:");
+    println!("  Scanned:           {}", report.records_scanned);
+    println!("  Valid:             {}", report.valid_edges);
+    println!("  Corrupt:           {}", report.corrupt.len());
+    println!("  Dangling index:    {}", report.dangling_index_entries);
+    println!("  Unindexed:         {}", report.unindexed_edges);
+    for record in &report.corrupt {
+        println!("  ✗ {} ({:?}: {})", record.key, record.kind, record.detail);
+    }
+}
+
+async fn handle_remote_command(
+    command: Commands,
+    url: &str,
+    api_key: Option<String>,
+    json_output: bool,
+) -> Result<()> {
+    let client = remote::RemoteClient::new(url, api_key, json_output);
+
+    // The server scopes requests to the API key's tenant
+    let no_tenant = |tenant: Option<u64>| {
+        if tenant.is_some() {
+            anyhow::bail!("--tenant is not supported with --remote; the API key determines the tenant");
+        }
+        Ok(())
+    };
+
+    match command {
+        Commands::Get { edge_id, tenant } => {
+            no_tenant(tenant)?;
+            client.get(parse_hex_u128(&edge_id)?).await
+        }
+        Commands::Query {
+            start,
+            end,
+            agent,
+            session,
+            tenant,
+            project,
+        } => {
+            no_tenant(tenant)?;
+            client
+                .query(remote::QueryFilter {
+                    start,
+                    end,
+                    agent,
+                    session,
+                    project,
+                })
+                .await
+        }
+        Commands::Children { edge_id, tenant } => {
+            no_tenant(tenant)?;
+            client.children(parse_hex_u128(&edge_id)?).await
+        }
+        Commands::Ancestors { edge_id, tenant } => {
+            no_tenant(tenant)?;
+            client.ancestors(parse_hex_u128(&edge_id)?).await
+        }
+        Commands::Stats => client.stats().await,
+        _ => anyhow::bail!("--remote only supports get, query, children, ancestors and stats"),
+    }
+}
+
+async fn handle_eval_command(command: EvalCommands, json_output: bool) -> Result<()> {
+    match command {
+        EvalCommands::Run {
+            dataset,
+            fail_below,
+            server,
+            api_key,
+            target_url,
+            headers,
+            openai_model,
+            base_url,
+            key_alias,
+            agent_id,
+            trials,
+            run,
+            baseline,
+            max_regression,
+            timeout,
+        } => {
+            if !(0.0..=1.0).contains(&fail_below) {
+                anyhow::bail!("--fail-below must be between 0 and 1");
+            }
+            let target = match (target_url, openai_model) {
+                (Some(url), _) => {
+                    let headers = headers
+                        .iter()
+                        .map(|h| {
+                            h.split_once('=')
+                                .map(|(k, v)| (k.trim().to_string(), v.trim().to_string()))
+                                .with_context(|| format!("Invalid header '{}', expected NAME=VALUE", h))
+                        })
+                        .collect::<Result<std::collections::HashMap<_, _>>>()?;
+                    Some(serde_json::json!({ "type": "http", "url": url, "headers": headers }))
+                }
+                (None, Some(model)) => Some(serde_json::json!({
+                    "type": "openai",
+                    "model": model,
+                    "base_url": base_url,
+                    "key_alias": key_alias,
+                })),
+                (None, None) => None,
+            };
+
+            let options = eval_gate::GateOptions {
+                server: server
+                    .or_else(|| std::env::var("AGENTREPLAY_URL").ok())
+                    .unwrap_or_else(|| "http://127.0.0.1:47100".to_string()),
+                api_key: api_key.or_else(|| std::env::var("AGENTREPLAY_API_KEY").ok()),
+                dataset_id: dataset,
+                target,
+                agent_id,
+                trials,
+                run_id: run,
+                baseline_id: baseline,
+                fail_below,
+                max_regression,
+                timeout: std::time::Duration::from_secs(timeout),
+            };
+            let report = eval_gate::run_gate(&options).await?;
+
+            if json_output {
+                println!("{}", serde_json::to_string_pretty(&report)?);
+            } else {
+                report.print();
+            }
+            report.report_to_github()?;
+
+            if !report.passed {
+                std::process::exit(1);
+            }
+        }
+    }
+
+    Ok(())
+}
+
+fn handle_migrate_command(
+    command: MigrateCommands,
+    db_path: &PathBuf,
+    json_output: bool,
+) -> Result<()> {
+    match command {
+        MigrateCommands::Export { all, output } => {
+            if !all {
+                anyhow::bail!("Only full exports are supported; pass --all");
+            }
+            let output = output.unwrap_or_else(|| {
+                let timestamp = chrono::Utc::now().format("%Y%m%d-%H%M%S");
+                PathBuf::from(format!("agentreplay-migration-{}.tar.gz", timestamp))
+            });
+            let manifest = migrate::export_archive(db_path, &output)?;
+
+            if json_output {
+                let output = serde_json::json!({
+                    "path": output.display().to_string(),
+                    "manifest": manifest,
+                });
+                println!("{}", serde_json::to_string_pretty(&output)?);
+            } else {
+                println!("✓ Migration archive written: {}", output.display());
+                println!(
+                    "  Format: {} v{} (agentreplay {})",
+                    manifest.format, manifest.version, manifest.agentreplay_version
+                );
+                for db in &manifest.databases {
+                    println!(
+                        "  {}: {} traces ({} payloads, {} eval metrics), {} datasets, {} eval runs",
+                        db.name, db.traces, db.payloads, db.eval_metrics, db.datasets, db.eval_runs
+                    );
+                }
+                println!("  Files: {}", manifest.files.join(", "));
+            }
+        }
+
+        MigrateCommands::Import {
+            path,
+            dry_run,
+            remap_ids,
+        } => {
+            let report = migrate::import_archive(&path, db_path, dry_run, remap_ids)?;
+
+            if json_output {
+                println!("{}", serde_json::to_string_pretty(&report)?);
+            } else {
+                let verb = if dry_run { "Would import" } else { "✓ Imported" };
+                println!(
+                    "{} archive from agentreplay {} into {:?}",
+                    verb, report.source_version, db_path
+                );
+                for db in &report.databases {
+                    println!(
+                        "  {} → {}: {} traces ({} remapped), {} eval metrics, {} datasets ({} remapped), {} eval runs ({} remapped)",
+                        db.source,
+                        db.target,
+                        db.traces,
+                        db.traces_remapped,
+                        db.eval_metrics,
+                        db.datasets,
+                        db.datasets_remapped,
+                        db.eval_runs,
+                        db.eval_runs_remapped
+                    );
+                }
+                for (file, status) in &report.files {
+                    println!("  {}: {}", file, status);
+                }
+                if report.dangling_references > 0 {
+                    println!(
+                        "  ⚠️  {} references to traces or datasets missing from the archive",
+                        report.dangling_references
+                    );
+                }
+                if !report.errors.is_empty() {
+                    println!("  ✗ {} invalid lines skipped:", report.errors.len());
+                    for error in &report.errors {
+                        println!("    {}", error);
+                    }
+                }
+            }
+        }
+    }
+
+    Ok(())
+}
+
+async fn handle_fsck_command(
+    repair: bool,
+    rebuild_indexes: bool,
+    aff_files: Vec<PathBuf>,
+    reingest: bool,
+    db_path: &PathBuf,
+    json_output: bool,
+) -> Result<()> {
+    if !db_path.exists() {
+        anyhow::bail!("Database not found at {:?}", db_path);
+    }
+
+    // Modifying the database under a running server or desktop app would
+    // race its writes; hold the data directory lock until done
+    let _lock = if repair || rebuild_indexes || reingest {
+        match DataDirLock::acquire(db_path, "fsck", env!("CARGO_PKG_VERSION"))? {
+            DirLockOutcome::Acquired(lock) => Some(lock),
+            DirLockOutcome::HeldBy(owner) => anyhow::bail!(
+                "Database {:?} is in use by {} {} (pid {} on {}); stop it before repairing",
+                db_path,
+                owner.app,
+                owner.version,
+                owner.pid,
+                owner.hostname,
+            ),
+        }
+    } else {
+        None
+    };
+
+    // Step 1: Verify primary records, optionally dropping the corrupt ones
+    let storage = UnifiedStorage::open(db_path).context("Failed to open storage")?;
+    let report = storage.verify_integrity()?;
+
+    let removed = if repair && !report.corrupt.is_empty() {
+        let keys: Vec<String> = report.corrupt.iter().map(|r| r.key.clone()).collect();
+        storage.remove_raw_records(&keys)?
+    } else {
+        0
+    };
+
+    // Step 2: Rebuild secondary and vector indexes from what is left
+    let rebuild = rebuild_indexes || (repair && !report.is_clean());
+    let (index_stats, vector_stats) = if rebuild {
+        let stats = storage.rebuild_secondary_indexes()?;
+        let vectors = rebuild_vector_index(&storage, &db_path.join("vector.index"))?;
+        (Some(stats), vectors)
+    } else {
+        (None, None)
+    };
+    storage.sync()?;
+    drop(storage);
+
+    // Step 3: Drop the causal index so opening the engine rebuilds it
+    if rebuild {
+        for name in ["causal.index", "causal.wal"] {
+            let path = db_path.join(name);
+            if path.exists() {
+                std::fs::remove_file(&path)
+                    .with_context(|| format!("Failed to remove {:?}", path))?;
+            }
+        }
+    }
+
+    // Step 4: Check AFF files
+    let mut aff_paths = aff_files;
+    for entry in std::fs::read_dir(db_path)? {
+        let path = entry?.path();
+        if path.extension().and_then(|e| e.to_str()) == Some("aff") && !aff_paths.contains(&path) {
+            aff_paths.push(path);
+        }
+    }
+    let aff_checks: Vec<AffCheck> = aff_paths.into_iter().map(check_aff_file).collect();
+
+    // Step 5: Open the engine to rebuild the causal index and re-ingest salvaged edges
+    let mut reingested = 0usize;
+    if rebuild || (reingest && aff_checks.iter().any(|c| !c.edges.is_empty())) {
+        let db = Agentreplay::open(db_path).context("Failed to open database")?;
+        if reingest {
+            for check in &aff_checks {
+                let mut missing = Vec::new();
+                for edge in &check.edges {
+                    if db.get(edge.edge_id)?.is_none() {
+                        missing.push(*edge);
+                    }
+                }
+                if !missing.is_empty() {
+                    db.insert_batch(&missing).await?;
+                    reingested += missing.len();
+                }
+            }
+        }
+        db.close()?;
+    }
+
+    let records_resolved = report.corrupt.is_empty() || repair;
+    let indexes_resolved =
+        (report.dangling_index_entries == 0 && report.unindexed_edges == 0) || rebuild;
+    let clean = records_resolved && indexes_resolved && aff_checks.iter().all(AffCheck::is_clean);
+
+    if json_output {
+        let output = serde_json::json!({
+            "db_path": db_path.display().to_string(),
+            "records": report,
+            "removed_records": removed,
+            "index_rebuild": index_stats,
+            "vector_index": vector_stats.map(|(kept, dropped)| serde_json::json!({
+                "kept": kept,
+                "dropped": dropped,
+            })),
+            "causal_index_rebuilt": rebuild,
+            "aff_files": aff_checks.iter().map(AffCheck::to_json).collect::<Vec<_>>(),
+            "reingested_edges": reingested,
+            "clean": clean,
+        });
+        println!("{}", serde_json::to_string_pretty(&output)?);
+    } else {
+        println!("Database check: {:?}", db_path);
+        println!("{}", "=".repeat(60));
+        print_integrity_report(&report);
+        if removed > 0 {
+            println!("✓ Removed {} corrupt records", removed);
+        }
+        if let Some(stats) = &index_stats {
+            println!(
+                "✓ Rebuilt secondary indexes ({} edges, {} records skipped)",
+                stats.edges_indexed, stats.records_skipped
+            );
+            println!("✓ Causal index will be rebuilt from storage");
+        }
+        if let Some((kept, dropped)) = vector_stats {
+            println!(
+                "✓ Rebuilt vector index ({} kept, {} dropped)",
+                kept, dropped
+            );
+        }
+        for check in &aff_checks {
+            let status = if check.is_clean() { "✓" } else { "✗" };
+            println!("{} {}", status, check.path.display());
+            if let Some(error) = &check.error {
+                println!("    unreadable: {}", error);
+                continue;
+            }
+            if !check.header_valid {
+                println!("    header checksum mismatch");
+            }
+            println!("    valid edges: {}", check.edges.len());
+            for (index, reason) in &check.corrupt {
+                println!("    edge #{}: {}", index, reason);
+            }
+            if check.missing > 0 {
+                println!("    truncated: {} edges missing", check.missing);
+            }
+        }
+        if reingested > 0 {
+            println!("✓ Re-ingested {} edges from AFF files", reingested);
+        }
+    }
+
+    if !clean {
+        anyhow::bail!(
+            "Corruption found; re-run with --repair (and --aff/--reingest to salvage AFF data)"
+        );
+    }
+    Ok(())
+}
+
+/// Directories that may contain diagnostic bundles: the server data dir and
+/// the desktop app's log directory
+fn diagnostics_dirs(db_path: &std::path::Path) -> Vec<PathBuf> {
+    let mut dirs = vec![db_path.join(DIAGNOSTICS_DIR_NAME)];
+    if let Some(desktop_dir) = desktop_log_dir() {
+        dirs.push(desktop_dir.join(DIAGNOSTICS_DIR_NAME));
+    }
+    dirs
+}
+
+/// Desktop app log directory (~/.agentreplay)
+fn desktop_log_dir() -> Option<PathBuf> {
+    std::env::var("HOME")
+        .or_else(|_| std::env::var("USERPROFILE"))
+        .ok()
+        .map(|home| PathBuf::from(home).join(".agentreplay"))
+}
+
+/// Handle diagnostics commands
+fn handle_service_command(command: ServiceCommands, db_path: &Path, json_output: bool) -> Result<()> {
+    let status = match command {
+        ServiceCommands::Install {
+            server_bin,
+            http_addr,
+            config,
+            no_start,
+        } => {
+            let server_bin = server_bin
+                .or_else(service::find_server_binary)
+                .context("agentreplay-server not found next to this binary or on PATH; pass --server-bin")?;
+            // The service doesn't run from this directory, so every path must be absolute
+            std::fs::create_dir_all(db_path)?;
+            let spec = ServiceSpec {
+                server_bin: server_bin.canonicalize()?,
+                data_dir: db_path.canonicalize()?,
+                http_addr,
+                config: config.map(|path| path.canonicalize()).transpose()?,
+            };
+            let status = service::install(&spec, !no_start)
+                .context("Failed to register the service")?;
+            if !json_output {
+                println!("Registered {} with {}", spec.server_bin.display(), status.manager.name());
+                println!("  Data directory: {}", spec.data_dir.display());
+                println!("  Listening on:   {}", spec.http_addr);
+                println!("  Log:            {}", spec.log_path().display());
+                if status.manager == ServiceManager::Systemd {
+                    println!("  To keep it running after logout: loginctl enable-linger");
+                }
+            }
+            status
+        }
+        ServiceCommands::Uninstall => {
+            let removed = service::uninstall().context("Failed to remove the service")?;
+            if !json_output {
+                if removed {
+                    println!("Service removed");
+                } else {
+                    println!("Service is not installed");
+                }
+            }
+            service::status()?
+        }
+        ServiceCommands::Status => service::status()?,
+    };
+
+    if json_output {
+        println!("{}", serde_json::to_string_pretty(&status)?);
+    } else {
+        println!(
+            "Service ({}): {}",
+            status.manager.name(),
+            match (status.installed, status.running) {
+                (true, true) => "installed, running",
+                (true, false) => "installed, not running",
+                _ => "not installed",
+            }
+        );
+        println!("  Definition: {}", status.definition.display());
+    }
+    Ok(())
+}
+
+async fn handle_diagnostics_command(
+    command: DiagnosticsCommands,
+    db_path: &PathBuf,
+    json_output: bool,
+) -> Result<()> {
+    let mut bundles = Vec::new();
+    for dir in diagnostics_dirs(db_path) {
+        bundles.extend(list_bundles(&dir)?);
+    }
+
+    match command {
+        DiagnosticsCommands::List => {
+            if json_output {
+                let entries: Vec<serde_json::Value> = bundles
+                    .iter()
+                    .map(|path| {
+                        let bundle = DiagnosticBundle::load(path).ok();
+                        serde_json::json!({
+                            "path": path.display().to_string(),
+                            "component": bundle.as_ref().map(|b| b.component.clone()),
+                            "created_at_ms": bundle.as_ref().map(|b| b.created_at_ms),
+                            "panic": bundle.as_ref().and_then(|b| b.panic.as_ref().map(|p| p.message.clone())),
+                        })
+                    })
+                    .collect();
+                println!("{}", serde_json::to_string_pretty(&entries)?);
+            } else {
+                println!("Diagnostic bundles ({}):", bundles.len());
+                println!("{}", "=".repeat(60));
+                for path in &bundles {
+                    match DiagnosticBundle::load(path) {
+                        Ok(bundle) => {
+                            let date = chrono::DateTime::from_timestamp_millis(bundle.created_at_ms as i64)
+                                .map(|d| d.format("%Y-%m-%d %H:%M:%S").to_string())
+                                .unwrap_or_else(|| bundle.created_at_ms.to_string());
+                            let summary = bundle
+                                .panic
+                                .as_ref()
+                                .map(|p| format!("panic: {}", p.message))
+                                .unwrap_or_else(|| "snapshot".to_string());
+                            println!("  [{}] {} - {}", bundle.component, date, summary);
+                            println!("    {}", path.display());
+                        }
+                        Err(e) => println!("  {} (unreadable: {})", path.display(), e),
+                    }
+                }
+                if bundles.is_empty() {
+                    println!("  No diagnostic bundles found.");
+                }
+            }
+        }
+
+        DiagnosticsCommands::Collect { output, upload } => {
+            // Fresh snapshot describing the CLI's view of the environment
+            let collector = DiagnosticsCollector::new(
+                "cli",
+                env!("CARGO_PKG_VERSION"),
+                db_path.join(DIAGNOSTICS_DIR_NAME),
+                desktop_log_dir()
+                    .map(|dir| LogSource::File(dir.join("agentreplay-desktop.log")))
+                    .unwrap_or(LogSource::None),
+            );
+            collector.set_config_summary(serde_json::json!({
+                "db_path": db_path.display().to_string(),
+                "db_exists": db_path.exists(),
+                "db_size_bytes": get_dir_size(db_path).ok(),
+                "bundle_count": bundles.len(),
+            }));
+            let snapshot = collector.collect(None);
+
+            let timestamp = std::time::SystemTime::now()
+                .duration_since(std::time::UNIX_EPOCH)?
+                .as_secs();
+            let output_path = output
+                .unwrap_or_else(|| PathBuf::from(format!("agentreplay_diagnostics_{}.zip", timestamp)));
+
+            let file = std::fs::File::create(&output_path)?;
+            let mut zip = zip::ZipWriter::new(file);
+            let options: zip::write::SimpleFileOptions = zip::write::SimpleFileOptions::default()
+                .compression_method(zip::CompressionMethod::Deflated);
+
+            use std::io::Write;
+            zip.start_file(snapshot.file_name(), options)?;
+            zip.write_all(&serde_json::to_vec_pretty(&snapshot)?)?;
+
+            for path in &bundles {
+                let name = format!(
+                    "bundles/{}",
+                    path.file_name().unwrap_or_default().to_string_lossy()
+                );
+                zip.start_file(&name, options)?;
+                let mut file = std::fs::File::open(path)?;
+                std::io::copy(&mut file, &mut zip)?;
+            }
+            zip.finish()?;
+
+            let size = std::fs::metadata(&output_path)?.len();
+
+            if let Some(url) = &upload {
+                upload_bundle(&output_path, url)
+                    .await
+                    .context("Failed to upload diagnostics")?;
+            }
+
+            if json_output {
+                println!(
+                    "{}",
+                    serde_json::json!({
+                        "success": true,
+                        "output": output_path.display().to_string(),
+                        "bundles": bundles.len(),
+                        "size_bytes": size,
+                        "uploaded_to": upload,
+                    })
+                );
+            } else {
+                println!("✓ Collected diagnostics: {}", output_path.display());
+                println!("  Crash bundles: {}", bundles.len());
+                println!("  Size: {} bytes", size);
+                if let Some(url) = upload {
+                    println!("  Uploaded to: {}", url);
+                }
+            }
+        }
+    }
+
+    Ok(())
+}
@@ -0,0 +1,782 @@
+// Copyright 2025 AgentReplay (https://github.com/agentreplay)
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program. If not, see <https://www.gnu.org/licenses/>.
+
+//! Workspace migration archives (`agentreplay migrate export|import`)
+//!
+//! An archive is a gzipped tar of JSON documents that describe the instance
+//! independently of the on-disk storage format, so it survives upgrades across
+//! breaking storage versions:
+//!
+//! ```text
+//! manifest.json                     format name, archive version, counts
+//! databases/<name>/traces.jsonl     one edge per line, with payload and eval metrics
+//! databases/<name>/datasets.json    eval datasets
+//! databases/<name>/eval_runs.json   eval runs and their results
+//! files/<path>                      registries and settings, relative to the data dir
+//! ```
+//!
+//! `<name>` is `main` for the root database and `project_<id>` for the
+//! per-project databases under `projects/`.
+//!
+//! Import remaps trace, dataset and run IDs that already exist in the target
+//! (or all of them with `remap_all`) and rewrites every reference to them.
+//! Projects whose ID is taken by a differently named project get the next free
+//! ID. Run it while the server is stopped.
+
+use agentreplay_core::clock::now_us;
+use agentreplay_core::{AgentFlowEdge, EvalDataset, EvalMetric, EvalRun};
+use agentreplay_query::Agentreplay;
+use anyhow::{Context, Result};
+use flate2::read::GzDecoder;
+use flate2::write::GzEncoder;
+use flate2::Compression;
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+use std::fs::File;
+use std::io::{BufRead, BufReader, BufWriter, Write};
+use std::path::{Path, PathBuf};
+
+/// Identifies migration archives in the manifest
+pub const ARCHIVE_FORMAT: &str = "agentreplay-migration";
+
+/// Bumped whenever the archive layout changes incompatibly
+pub const ARCHIVE_VERSION: u32 = 1;
+
+const MAIN_DB: &str = "main";
+
+/// Registries and settings carried alongside the databases (relative to the data dir)
+const METADATA_FILES: &[&str] = &[
+    "projects/projects_registry.json",
+    "saved_views.json",
+    "prompt_templates.json",
+    "agent_registry.json",
+    "session_registry.json",
+    "scripts.json",
+];
+
+/// Edges written per batch on import
+const IMPORT_BATCH: usize = 1000;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Manifest {
+    pub format: String,
+    pub version: u32,
+    pub agentreplay_version: String,
+    pub created_at_us: u64,
+    pub databases: Vec<DatabaseSummary>,
+    pub files: Vec<String>,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct DatabaseSummary {
+    pub name: String,
+    pub project_id: Option<u16>,
+    pub traces: usize,
+    pub payloads: usize,
+    pub eval_metrics: usize,
+    pub datasets: usize,
+    pub eval_runs: usize,
+}
+
+/// One line of `traces.jsonl`
+#[derive(Debug, Serialize, Deserialize)]
+struct TraceRecord {
+    edge: AgentFlowEdge,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    payload_hex: Option<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    eval_metrics: Vec<MetricRecord>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct MetricRecord {
+    name: String,
+    value: f64,
+    evaluator: String,
+    timestamp_us: u64,
+}
+
+/// What an import did (or would do, for a dry run)
+#[derive(Debug, Default, Serialize)]
+pub struct ImportReport {
+    pub dry_run: bool,
+    pub source_version: String,
+    pub databases: Vec<DatabaseImport>,
+    /// Metadata files: path → "created", "merged (N added)" or "kept existing"
+    pub files: Vec<(String, String)>,
+    /// Lines that failed validation and are skipped
+    pub errors: Vec<String>,
+    /// References to traces or datasets that are neither in the archive nor the target
+    pub dangling_references: usize,
+}
+
+#[derive(Debug, Default, Serialize)]
+pub struct DatabaseImport {
+    pub source: String,
+    pub target: String,
+    pub traces: usize,
+    pub traces_remapped: usize,
+    pub eval_metrics: usize,
+    pub datasets: usize,
+    pub datasets_remapped: usize,
+    pub eval_runs: usize,
+    pub eval_runs_remapped: usize,
+}
+
+fn to_hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+fn from_hex(hex: &str) -> Result<Vec<u8>> {
+    if hex.len() % 2 != 0 {
+        anyhow::bail!("odd-length hex payload");
+    }
+    (0..hex.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(&hex[i..i + 2], 16).context("invalid hex payload"))
+        .collect()
+}
+
+/// Databases of an instance: the root one plus `projects/project_<id>`
+fn discover_databases(data_dir: &Path) -> Result<Vec<(String, Option<u16>, PathBuf)>> {
+    let mut databases = vec![(MAIN_DB.to_string(), None, data_dir.to_path_buf())];
+    let projects_dir = data_dir.join("projects");
+    if projects_dir.is_dir() {
+        for entry in std::fs::read_dir(&projects_dir)? {
+            let path = entry?.path();
+            let name = path.file_name().and_then(|n| n.to_str()).unwrap_or("");
+            if let Some(id) = name
+                .strip_prefix("project_")
+                .and_then(|id| id.parse::<u16>().ok())
+            {
+                if path.is_dir() {
+                    databases.push((name.to_string(), Some(id), path));
+                }
+            }
+        }
+    }
+    databases.sort_by_key(|(_, project_id, _)| *project_id);
+    Ok(databases)
+}
+
+fn append_bytes<W: Write>(builder: &mut tar::Builder<W>, path: &str, data: &[u8]) -> Result<()> {
+    let mut header = tar::Header::new_gnu();
+    header.set_size(data.len() as u64);
+    header.set_mode(0o644);
+    header.set_mtime(now_us() / 1_000_000);
+    header.set_cksum();
+    builder.append_data(&mut header, path, data)?;
+    Ok(())
+}
+
+/// Write a complete archive of the instance at `data_dir`
+pub fn export_archive(data_dir: &Path, output: &Path) -> Result<Manifest> {
+    if !data_dir.exists() {
+        anyhow::bail!("Database not found at {:?}", data_dir);
+    }
+
+    let file = File::create(output).with_context(|| format!("Failed to create {:?}", output))?;
+    let mut builder = tar::Builder::new(GzEncoder::new(file, Compression::default()));
+    let mut manifest = Manifest {
+        format: ARCHIVE_FORMAT.to_string(),
+        version: ARCHIVE_VERSION,
+        agentreplay_version: env!("CARGO_PKG_VERSION").to_string(),
+        created_at_us: now_us(),
+        databases: Vec::new(),
+        files: Vec::new(),
+    };
+
+    for (name, project_id, path) in discover_databases(data_dir)? {
+        let db = Agentreplay::open(&path)
+            .with_context(|| format!("Failed to open database {:?}", path))?;
+        let mut summary = DatabaseSummary {
+            name: name.clone(),
+            project_id,
+            ..Default::default()
+        };
+
+        // Traces are staged on disk: a full instance does not fit in memory
+        let staging = output.with_extension(format!("{}.traces.tmp", name));
+        {
+            let mut writer = BufWriter::new(File::create(&staging)?);
+            for edge in db.query_temporal_range(0, u64::MAX)? {
+                let payload_hex = if edge.has_payload != 0 {
+                    db.get_payload(edge.edge_id)?.map(|p| to_hex(&p))
+                } else {
+                    None
+                };
+                let eval_metrics: Vec<MetricRecord> = db
+                    .get_eval_metrics(edge.edge_id)?
+                    .iter()
+                    .map(|m| MetricRecord {
+                        name: m.get_metric_name().to_string(),
+                        value: m.metric_value,
+                        evaluator: m.get_evaluator().to_string(),
+                        timestamp_us: m.timestamp_us,
+                    })
+                    .collect();
+
+                summary.traces += 1;
+                summary.payloads += payload_hex.is_some() as usize;
+                summary.eval_metrics += eval_metrics.len();
+                let record = TraceRecord {
+                    edge,
+                    payload_hex,
+                    eval_metrics,
+                };
+                serde_json::to_writer(&mut writer, &record)?;
+                writer.write_all(b"\n")?;
+            }
+            writer.flush()?;
+        }
+        builder.append_path_with_name(&staging, format!("databases/{}/traces.jsonl", name))?;
+        std::fs::remove_file(&staging)?;
+
+        let datasets = db.list_eval_datasets()?;
+        let runs = db.list_eval_runs(None)?;
+        summary.datasets = datasets.len();
+        summary.eval_runs = runs.len();
+        append_bytes(
+            &mut builder,
+            &format!("databases/{}/datasets.json", name),
+            &serde_json::to_vec(&datasets)?,
+        )?;
+        append_bytes(
+            &mut builder,
+            &format!("databases/{}/eval_runs.json", name),
+            &serde_json::to_vec(&runs)?,
+        )?;
+        db.close()?;
+
+        manifest.databases.push(summary);
+    }
+
+    for file in METADATA_FILES {
+        let path = data_dir.join(file);
+        if path.is_file() {
+            builder.append_path_with_name(&path, format!("files/{}", file))?;
+            manifest.files.push(file.to_string());
+        }
+    }
+
+    append_bytes(
+        &mut builder,
+        "manifest.json",
+        &serde_json::to_vec_pretty(&manifest)?,
+    )?;
+    builder.into_inner()?.finish()?;
+    Ok(manifest)
+}
+
+/// Read and check the manifest of an unpacked archive
+fn read_manifest(staging: &Path) -> Result<Manifest> {
+    let raw = std::fs::read(staging.join("manifest.json"))
+        .context("Archive has no manifest.json - not a migration archive")?;
+    let manifest: Manifest = serde_json::from_slice(&raw).context("Invalid manifest.json")?;
+    if manifest.format != ARCHIVE_FORMAT {
+        anyhow::bail!("Not a migration archive (format '{}')", manifest.format);
+    }
+    if manifest.version > ARCHIVE_VERSION {
+        anyhow::bail!(
+            "Archive version {} was written by agentreplay {} and is newer than this CLI supports (version {})",
+            manifest.version,
+            manifest.agentreplay_version,
+            ARCHIVE_VERSION
+        );
+    }
+    Ok(manifest)
+}
+
+/// Give archived projects a target ID, moving past IDs held by differently named projects
+fn plan_project_ids(
+    archived: &[(u16, String)],
+    existing: &HashMap<u16, String>,
+) -> HashMap<u16, u16> {
+    let mut taken: HashSet<u16> = existing.keys().copied().collect();
+    taken.extend(archived.iter().map(|(id, _)| *id));
+    let mut next = taken.iter().max().map_or(1, |max| max.saturating_add(1));
+
+    let mut plan = HashMap::new();
+    for (id, name) in archived {
+        let target = match existing.get(id) {
+            Some(existing_name) if existing_name != name => {
+                let target = next;
+                next = next.saturating_add(1);
+                target
+            }
+            _ => *id,
+        };
+        plan.insert(*id, target);
+    }
+    plan
+}
+
+/// `project_id` → `name` of a projects registry document
+fn registry_names(registry: &serde_json::Value) -> HashMap<u16, String> {
+    registry
+        .as_array()
+        .map(|projects| {
+            projects
+                .iter()
+                .filter_map(|p| {
+                    let id = p.get("project_id")?.as_u64()? as u16;
+                    let name = p.get("name")?.as_str()?.to_string();
+                    Some((id, name))
+                })
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+fn read_json_file(path: &Path) -> Result<Option<serde_json::Value>> {
+    if !path.is_file() {
+        return Ok(None);
+    }
+    let raw = std::fs::read(path)?;
+    Ok(Some(
+        serde_json::from_slice(&raw).with_context(|| format!("Invalid JSON in {:?}", path))?,
+    ))
+}
+
+/// Append the objects of `incoming` whose `key` is not in `target` yet
+fn merge_json_arrays(
+    target: &mut Vec<serde_json::Value>,
+    incoming: Vec<serde_json::Value>,
+    key: &str,
+) -> usize {
+    let existing: HashSet<String> = target
+        .iter()
+        .filter_map(|v| v.get(key).map(|k| k.to_string()))
+        .collect();
+    let mut added = 0;
+    for value in incoming {
+        let new = value
+            .get(key)
+            .map_or(true, |k| !existing.contains(&k.to_string()));
+        if new {
+            target.push(value);
+            added += 1;
+        }
+    }
+    added
+}
+
+/// Archived database contents, validated line by line
+struct StagedDatabase {
+    name: String,
+    project_id: Option<u16>,
+    traces_path: PathBuf,
+    datasets: Vec<EvalDataset>,
+    runs: Vec<EvalRun>,
+}
+
+fn for_each_trace(
+    path: &Path,
+    label: &str,
+    errors: &mut Vec<String>,
+    mut f: impl FnMut(TraceRecord) -> Result<()>,
+) -> Result<()> {
+    let reader = BufReader::new(File::open(path)?);
+    for (idx, line) in reader.lines().enumerate() {
+        let line = line?;
+        if line.trim().is_empty() {
+            continue;
+        }
+        match serde_json::from_str::<TraceRecord>(&line) {
+            Ok(record) if !record.edge.verify_checksum() => {
+                errors.push(format!("{}:{}: edge checksum mismatch", label, idx + 1));
+            }
+            Ok(record) => f(record)?,
+            Err(e) => errors.push(format!("{}:{}: {}", label, idx + 1, e)),
+        }
+    }
+    Ok(())
+}
+
+/// Insert a batch of edges with their payloads, then their eval metrics
+fn write_batch(
+    target: &Agentreplay,
+    edges: &mut Vec<AgentFlowEdge>,
+    payloads: &mut Vec<(u128, Vec<u8>)>,
+    metrics: &mut Vec<(u128, Vec<EvalMetric>)>,
+) -> Result<()> {
+    let refs: Vec<(u128, &[u8])> = payloads.iter().map(|(id, p)| (*id, p.as_slice())).collect();
+    target.insert_batch_with_payloads(edges, &refs)?;
+    for (edge_id, batch) in metrics.drain(..) {
+        target.store_eval_metrics(edge_id, batch)?;
+    }
+    edges.clear();
+    payloads.clear();
+    Ok(())
+}
+
+/// Import an archive into the instance at `data_dir`
+pub fn import_archive(
+    archive: &Path,
+    data_dir: &Path,
+    dry_run: bool,
+    remap_all: bool,
+) -> Result<ImportReport> {
+    let staging = std::env::temp_dir().join(format!(
+        "agentreplay-migrate-{}-{}",
+        std::process::id(),
+        now_us()
+    ));
+    std::fs::create_dir_all(&staging)?;
+    let result = File::open(archive)
+        .with_context(|| format!("Failed to open {:?}", archive))
+        .and_then(|file| {
+            tar::Archive::new(GzDecoder::new(file))
+                .unpack(&staging)
+                .context("Failed to unpack archive")
+        })
+        .and_then(|_| import_staged(&staging, data_dir, dry_run, remap_all));
+    let _ = std::fs::remove_dir_all(&staging);
+    result
+}
+
+fn import_staged(
+    staging: &Path,
+    data_dir: &Path,
+    dry_run: bool,
+    remap_all: bool,
+) -> Result<ImportReport> {
+    let manifest = read_manifest(staging)?;
+    let mut report = ImportReport {
+        dry_run,
+        source_version: manifest.agentreplay_version.clone(),
+        ..Default::default()
+    };
+
+    // Projects: keep IDs unless a differently named project already holds them
+    let registry_file = "projects/projects_registry.json";
+    let archived_registry = read_json_file(&staging.join("files").join(registry_file))?;
+    let target_registry = read_json_file(&data_dir.join(registry_file))?;
+    let archived_names = archived_registry
+        .as_ref()
+        .map(registry_names)
+        .unwrap_or_default();
+    let archived_projects: Vec<(u16, String)> = manifest
+        .databases
+        .iter()
+        .filter_map(|db| db.project_id)
+        .map(|id| (id, archived_names.get(&id).cloned().unwrap_or_default()))
+        .collect();
+    let project_plan = plan_project_ids(
+        &archived_projects,
+        &target_registry
+            .as_ref()
+            .map(registry_names)
+            .unwrap_or_default(),
+    );
+
+    let mut staged = Vec::new();
+    for summary in &manifest.databases {
+        let dir = staging.join("databases").join(&summary.name);
+        let read = |file: &str| -> Result<Vec<u8>> {
+            std::fs::read(dir.join(file))
+                .with_context(|| format!("Archive is missing databases/{}/{}", summary.name, file))
+        };
+        staged.push(StagedDatabase {
+            name: summary.name.clone(),
+            project_id: summary.project_id,
+            traces_path: dir.join("traces.jsonl"),
+            datasets: serde_json::from_slice(&read("datasets.json")?)
+                .with_context(|| format!("Invalid databases/{}/datasets.json", summary.name))?,
+            runs: serde_json::from_slice(&read("eval_runs.json")?)
+                .with_context(|| format!("Invalid databases/{}/eval_runs.json", summary.name))?,
+        });
+    }
+
+    // Pass 1: decide new IDs for everything that collides with the target
+    let mut edge_ids: HashMap<u128, u128> = HashMap::new();
+    let mut dataset_ids: HashMap<u128, u128> = HashMap::new();
+    let mut run_ids: HashMap<u128, u128> = HashMap::new();
+    let mut targets = Vec::new();
+    for db in &staged {
+        let target_dir = match db.project_id {
+            None => data_dir.to_path_buf(),
+            Some(id) => data_dir
+                .join("projects")
+                .join(format!("project_{}", project_plan[&id])),
+        };
+        let target = if target_dir.exists() {
+            Some(
+                Agentreplay::open(&target_dir)
+                    .with_context(|| format!("Failed to open target database {:?}", target_dir))?,
+            )
+        } else {
+            None
+        };
+
+        let mut import = DatabaseImport {
+            source: db.name.clone(),
+            target: target_dir.display().to_string(),
+            datasets: db.datasets.len(),
+            eval_runs: db.runs.len(),
+            ..Default::default()
+        };
+        let label = format!("databases/{}/traces.jsonl", db.name);
+        for_each_trace(&db.traces_path, &label, &mut report.errors, |record| {
+            let id = record.edge.edge_id;
+            let taken = match &target {
+                Some(target) => target.get(id)?.is_some(),
+                None => false,
+            };
+            import.traces += 1;
+            import.eval_metrics += record.eval_metrics.len();
+            if remap_all || taken {
+                edge_ids.insert(id, AgentFlowEdge::generate_id());
+                import.traces_remapped += 1;
+            } else {
+                edge_ids.insert(id, id);
+            }
+            Ok(())
+        })?;
+        for dataset in &db.datasets {
+            let taken = match &target {
+                Some(target) => target.get_eval_dataset(dataset.id)?.is_some(),
+                None => false,
+            };
+            let new_id = if remap_all || taken {
+                import.datasets_remapped += 1;
+                AgentFlowEdge::generate_id()
+            } else {
+                dataset.id
+            };
+            dataset_ids.insert(dataset.id, new_id);
+        }
+        for run in &db.runs {
+            let taken = match &target {
+                Some(target) => target.get_eval_run(run.id)?.is_some(),
+                None => false,
+            };
+            let new_id = if remap_all || taken {
+                import.eval_runs_remapped += 1;
+                AgentFlowEdge::generate_id()
+            } else {
+                run.id
+            };
+            run_ids.insert(run.id, new_id);
+        }
+        if let Some(target) = target {
+            target.close()?;
+        }
+        targets.push(target_dir);
+        report.databases.push(import);
+    }
+
+    // Parents, run datasets and run traces must resolve in the archive or the target
+    for db in &staged {
+        let label = format!("databases/{}/traces.jsonl", db.name);
+        let mut scratch = Vec::new();
+        for_each_trace(&db.traces_path, &label, &mut scratch, |record| {
+            let parent = record.edge.causal_parent;
+            if parent != 0 && !edge_ids.contains_key(&parent) {
+                report.dangling_references += 1;
+            }
+            Ok(())
+        })?;
+        for run in &db.runs {
+            if !dataset_ids.contains_key(&run.dataset_id) {
+                report.dangling_references += 1;
+            }
+            report.dangling_references += run
+                .results
+                .iter()
+                .filter_map(|r| r.trace_id)
+                .filter(|id| !edge_ids.contains_key(id))
+                .count();
+        }
+    }
+
+    if dry_run {
+        for file in &manifest.files {
+            let status = if data_dir.join(file).exists() {
+                "would merge"
+            } else {
+                "would create"
+            };
+            report.files.push((file.clone(), status.to_string()));
+        }
+        return Ok(report);
+    }
+
+    // Pass 2: write everything with IDs and references rewritten
+    let remap_edge = |id: u128| edge_ids.get(&id).copied().unwrap_or(id);
+    for (db, target_dir) in staged.iter().zip(&targets) {
+        std::fs::create_dir_all(target_dir)?;
+        let target = Agentreplay::open(target_dir)
+            .with_context(|| format!("Failed to open target database {:?}", target_dir))?;
+
+        let mut edges = Vec::with_capacity(IMPORT_BATCH);
+        let mut payloads: Vec<(u128, Vec<u8>)> = Vec::new();
+        let mut metrics: Vec<(u128, Vec<EvalMetric>)> = Vec::new();
+        let label = format!("databases/{}/traces.jsonl", db.name);
+        let mut scratch = Vec::new();
+        for_each_trace(&db.traces_path, &label, &mut scratch, |record| {
+            let mut edge = record.edge;
+            edge.edge_id = remap_edge(edge.edge_id);
+            if edge.causal_parent != 0 {
+                edge.causal_parent = remap_edge(edge.causal_parent);
+            }
+            edge.project_id = project_plan
+                .get(&edge.project_id)
+                .copied()
+                .unwrap_or(edge.project_id);
+            edge.checksum = edge.compute_checksum();
+
+            if let Some(hex) = &record.payload_hex {
+                payloads.push((edge.edge_id, from_hex(hex)?));
+            }
+            let batch: Vec<EvalMetric> = record
+                .eval_metrics
+                .iter()
+                .filter_map(|m| {
+                    EvalMetric::new(edge.edge_id, &m.name, m.value, &m.evaluator, m.timestamp_us)
+                })
+                .collect();
+            if !batch.is_empty() {
+                metrics.push((edge.edge_id, batch));
+            }
+            edges.push(edge);
+            if edges.len() >= IMPORT_BATCH {
+                write_batch(&target, &mut edges, &mut payloads, &mut metrics)?;
+            }
+            Ok(())
+        })?;
+        if !edges.is_empty() {
+            write_batch(&target, &mut edges, &mut payloads, &mut metrics)?;
+        }
+
+        for dataset in &db.datasets {
+            let mut dataset = dataset.clone();
+            dataset.id = dataset_ids[&dataset.id];
+            target.store_eval_dataset(dataset)?;
+        }
+        for run in &db.runs {
+            let mut run = run.clone();
+            run.id = run_ids[&run.id];
+            run.dataset_id = dataset_ids
+                .get(&run.dataset_id)
+                .copied()
+                .unwrap_or(run.dataset_id);
+            for result in &mut run.results {
+                result.trace_id = result.trace_id.map(remap_edge);
+            }
+            target.store_eval_run(run)?;
+        }
+        target.sync()?;
+        target.close()?;
+    }
+
+    // Registries and settings: merge lists by ID, never overwrite existing settings
+    for file in &manifest.files {
+        let incoming = staging.join("files").join(file);
+        let destination = data_dir.join(file);
+        let status = match (read_json_file(&destination)?, read_json_file(&incoming)?) {
+            (None, Some(mut value)) => {
+                if file == registry_file {
+                    remap_registry(&mut value, &project_plan);
+                }
+                if let Some(parent) = destination.parent() {
+                    std::fs::create_dir_all(parent)?;
+                }
+                std::fs::write(&destination, serde_json::to_vec_pretty(&value)?)?;
+                "created".to_string()
+            }
+            (Some(serde_json::Value::Array(mut existing)), Some(mut value)) => {
+                let key = if file == registry_file {
+                    remap_registry(&mut value, &project_plan);
+                    "project_id"
+                } else {
+                    "id"
+                };
+                let incoming = match value {
+                    serde_json::Value::Array(items) => items,
+                    _ => Vec::new(),
+                };
+                let added = merge_json_arrays(&mut existing, incoming, key);
+                std::fs::write(&destination, serde_json::to_vec_pretty(&existing)?)?;
+                format!("merged ({} added)", added)
+            }
+            _ => "kept existing".to_string(),
+        };
+        report.files.push((file.clone(), status));
+    }
+
+    Ok(report)
+}
+
+/// Rewrite `project_id`s of a projects registry document
+fn remap_registry(registry: &mut serde_json::Value, plan: &HashMap<u16, u16>) {
+    for project in registry.as_array_mut().into_iter().flatten() {
+        let Some(id) = project.get("project_id").and_then(|v| v.as_u64()) else {
+            continue;
+        };
+        if let Some(new_id) = plan.get(&(id as u16)) {
+            project["project_id"] = serde_json::json!(new_id);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_hex_round_trip() {
+        let payload = br#"{"gen_ai.system":"openai"}"#;
+        assert_eq!(from_hex(&to_hex(payload)).unwrap(), payload);
+        assert!(from_hex("abc").is_err());
+        assert!(from_hex("zz").is_err());
+    }
+
+    #[test]
+    fn test_plan_project_ids() {
+        let existing: HashMap<u16, String> =
+            [(1, "default".to_string()), (2, "chatbot".to_string())]
+                .into_iter()
+                .collect();
+        let archived = vec![
+            (1, "default".to_string()),
+            (2, "search".to_string()),
+            (5, "evals".to_string()),
+        ];
+
+        let plan = plan_project_ids(&archived, &existing);
+        // Same name merges, a clash moves past every known ID, free IDs are kept
+        assert_eq!(plan[&1], 1);
+        assert_eq!(plan[&2], 6);
+        assert_eq!(plan[&5], 5);
+    }
+
+    #[test]
+    fn test_merge_json_arrays_by_key() {
+        let mut target = vec![serde_json::json!({"id": "a", "name": "kept"})];
+        let incoming = vec![
+            serde_json::json!({"id": "a", "name": "ignored"}),
+            serde_json::json!({"id": "b", "name": "added"}),
+        ];
+
+        assert_eq!(merge_json_arrays(&mut target, incoming, "id"), 1);
+        assert_eq!(target.len(), 2);
+        assert_eq!(target[0]["name"], "kept");
+
+        let mut registry = serde_json::json!([{"project_id": 2, "name": "search"}]);
+        remap_registry(&mut registry, &[(2, 6)].into_iter().collect());
+        assert_eq!(registry[0]["project_id"], 6);
+    }
+}
@@ -0,0 +1,307 @@
+// Copyright 2025 AgentReplay (https://github.com/agentreplay)
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program. If not, see <https://www.gnu.org/licenses/>.
+
+//! Remote inspection (`agentreplay --remote <url> ...`)
+//!
+//! Runs `get`, `query`, `children`, `ancestors` and `stats` against a
+//! server's REST API instead of a local database directory, so production
+//! data can be inspected without copying the database. The server scopes
+//! every request to the tenant of the API key.
+
+use anyhow::{Context, Result};
+use serde::de::DeserializeOwned;
+use serde::Deserialize;
+
+/// Spans listed by `query`, matching the local output
+const QUERY_PAGE_SIZE: usize = 20;
+
+/// Span as served by the `/api/v1/traces` endpoints
+#[derive(Debug, Deserialize)]
+struct Span {
+    span_id: String,
+    #[serde(default)]
+    parent_span_id: Option<String>,
+    #[serde(default)]
+    tenant_id: u64,
+    #[serde(default)]
+    project_id: u16,
+    #[serde(default)]
+    agent_id: u64,
+    #[serde(default)]
+    session_id: u64,
+    #[serde(default)]
+    span_type: String,
+    #[serde(default)]
+    timestamp_us: u64,
+    #[serde(default)]
+    token_count: u32,
+    #[serde(default)]
+    confidence: Option<f32>,
+}
+
+#[derive(Debug, Deserialize)]
+struct SpanList {
+    traces: Vec<serde_json::Value>,
+    total: usize,
+}
+
+#[derive(Debug, Deserialize)]
+struct Stats {
+    causal_nodes: usize,
+    causal_edges: usize,
+    vector_count: usize,
+    memtable_size: usize,
+    memtable_entries: usize,
+    immutable_memtables: usize,
+    wal_sequence: u64,
+}
+
+/// Filters of the `query` command
+pub struct QueryFilter {
+    pub start: u64,
+    pub end: u64,
+    pub agent: Option<u64>,
+    pub session: Option<u64>,
+    pub project: Option<u16>,
+}
+
+/// Client for the server's trace endpoints
+pub struct RemoteClient {
+    base: String,
+    api_key: Option<String>,
+    http: reqwest::Client,
+    json: bool,
+}
+
+impl RemoteClient {
+    pub fn new(base: &str, api_key: Option<String>, json: bool) -> Self {
+        Self {
+            base: base.trim_end_matches('/').to_string(),
+            api_key,
+            http: reqwest::Client::new(),
+            json,
+        }
+    }
+
+    /// GET `path`; `None` when the server answers 404
+    async fn get_value(
+        &self,
+        path: &str,
+        query: &[(&str, String)],
+    ) -> Result<Option<serde_json::Value>> {
+        let mut request = self.http.get(format!("{}{}", self.base, path)).query(query);
+        if let Some(key) = &self.api_key {
+            request = request.header("X-API-Key", key);
+        }
+        let response = request
+            .send()
+            .await
+            .with_context(|| format!("Failed to reach the server at {}", self.base))?;
+        let status = response.status();
+        if status == reqwest::StatusCode::NOT_FOUND {
+            return Ok(None);
+        }
+        if !status.is_success() {
+            let body = response.text().await.unwrap_or_default();
+            // Problem-details bodies carry the message in `detail`
+            let detail = serde_json::from_str::<serde_json::Value>(&body)
+                .ok()
+                .and_then(|b| b.get("detail").and_then(|d| d.as_str()).map(str::to_string))
+                .unwrap_or(body);
+            anyhow::bail!("Server returned {}: {}", status, detail);
+        }
+        response
+            .json()
+            .await
+            .map(Some)
+            .context("Invalid response from server")
+    }
+
+    /// Decode a response, or print it as is with `--json` and return `None`
+    fn parse<T: DeserializeOwned>(&self, value: Option<serde_json::Value>) -> Result<Option<T>> {
+        let Some(value) = value else {
+            println!("✗ Edge not found");
+            return Ok(None);
+        };
+        if self.json {
+            println!("{}", serde_json::to_string_pretty(&value)?);
+            return Ok(None);
+        }
+        Ok(Some(
+            serde_json::from_value(value).context("Invalid response from server")?,
+        ))
+    }
+
+    pub async fn get(&self, edge_id: u128) -> Result<()> {
+        let path = format!("/api/v1/traces/{:#x}", edge_id);
+        let value = self.get_value(&path, &[]).await?;
+        let Some(span) = self.parse::<Span>(value)? else {
+            return Ok(());
+        };
+
+        println!("Edge {}:", span.span_id);
+        println!("  Tenant: {}, Project: {}", span.tenant_id, span.project_id);
+        println!("  Timestamp: {} μs", span.timestamp_us);
+        println!("  Agent: {}", span.agent_id);
+        println!("  Session: {}", span.session_id);
+        println!("  Type: {}", span.span_type);
+        println!(
+            "  Parent: {}",
+            span.parent_span_id.as_deref().unwrap_or("0x0")
+        );
+        if let Some(confidence) = span.confidence {
+            println!("  Confidence: {:.2}", confidence);
+        }
+        println!("  Tokens: {}", span.token_count);
+        Ok(())
+    }
+
+    pub async fn query(&self, filter: QueryFilter) -> Result<()> {
+        let mut query = vec![
+            ("start_ts", filter.start.to_string()),
+            ("end_ts", filter.end.to_string()),
+            ("limit", QUERY_PAGE_SIZE.to_string()),
+            ("sort_by", "timestamp".to_string()),
+        ];
+        if let Some(agent) = filter.agent {
+            query.push(("agent_id", agent.to_string()));
+        }
+        if let Some(session) = filter.session {
+            query.push(("session_id", session.to_string()));
+        }
+        if let Some(project) = filter.project {
+            query.push(("project_id", project.to_string()));
+        }
+
+        let value = self.get_value("/api/v1/traces", &query).await?;
+        let Some(list) = self.parse::<SpanList>(value)? else {
+            return Ok(());
+        };
+        let spans = list
+            .traces
+            .into_iter()
+            .map(serde_json::from_value)
+            .collect::<Result<Vec<Span>, _>>()
+            .context("Invalid response from server")?;
+
+        println!("Found {} edges:", list.total);
+        for (i, span) in spans.iter().enumerate() {
+            println!(
+                "  {}. {} - Tenant:{} Project:{} Agent:{} Session:{} Type:{}",
+                i + 1,
+                span.span_id,
+                span.tenant_id,
+                span.project_id,
+                span.agent_id,
+                span.session_id,
+                span.span_type
+            );
+        }
+        if list.total > spans.len() {
+            println!("  ... and {} more", list.total - spans.len());
+        }
+        Ok(())
+    }
+
+    pub async fn children(&self, edge_id: u128) -> Result<()> {
+        let path = format!("/api/v1/traces/{:#x}/children", edge_id);
+        let value = self.get_value(&path, &[]).await?;
+        let Some(children) = self.parse::<Vec<Span>>(value)? else {
+            return Ok(());
+        };
+
+        println!("Children of {:#x}: {}", edge_id, children.len());
+        for child in children {
+            println!("  {} - Type:{}", child.span_id, child.span_type);
+        }
+        Ok(())
+    }
+
+    pub async fn ancestors(&self, edge_id: u128) -> Result<()> {
+        let path = format!("/api/v1/traces/{:#x}/ancestors", edge_id);
+        let value = self.get_value(&path, &[]).await?;
+        let Some(ancestors) = self.parse::<Vec<Span>>(value)? else {
+            return Ok(());
+        };
+
+        println!("Ancestors of {:#x}: {}", edge_id, ancestors.len());
+        for ancestor in ancestors {
+            println!("  {} - Type:{}", ancestor.span_id, ancestor.span_type);
+        }
+        Ok(())
+    }
+
+    pub async fn stats(&self) -> Result<()> {
+        let value = self.get_value("/api/v1/stats", &[]).await?;
+        let Some(stats) = self.parse::<Stats>(value)? else {
+            return Ok(());
+        };
+
+        println!("Agentreplay Statistics ({})", self.base);
+        println!("=====================");
+        println!();
+        println!("Memory:");
+        println!(
+            "  Memtable: {} entries ({} bytes)",
+            stats.memtable_entries, stats.memtable_size
+        );
+        println!("  Immutable memtables: {}", stats.immutable_memtables);
+        println!();
+        println!("Storage:");
+        println!("  WAL sequence: {}", stats.wal_sequence);
+        println!();
+        println!("Indexes:");
+        println!(
+            "  Causal graph: {} nodes, {} edges",
+            stats.causal_nodes, stats.causal_edges
+        );
+        println!("  Vector index: {} embeddings", stats.vector_count);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_server_span() {
+        let client = RemoteClient::new("http://localhost:47100/", None, false);
+        assert_eq!(client.base, "http://localhost:47100");
+
+        let value = serde_json::json!({
+            "trace_id": "0x7b",
+            "span_id": "0x1a2b",
+            "parent_span_id": "0x1a2a",
+            "tenant_id": 1,
+            "project_id": 3,
+            "agent_id": 9,
+            "agent_name": "planner",
+            "session_id": 123,
+            "span_type": "3",
+            "timestamp_us": 1_700_000_000_000_000u64,
+            "token_count": 42,
+            "status": "completed",
+        });
+        let span: Span = client.parse(Some(value)).unwrap().unwrap();
+        assert_eq!(span.span_id, "0x1a2b");
+        assert_eq!(span.parent_span_id.as_deref(), Some("0x1a2a"));
+        assert_eq!(span.project_id, 3);
+        assert_eq!(span.token_count, 42);
+        assert!(span.confidence.is_none());
+
+        assert!(client.parse::<Span>(None).unwrap().is_none());
+    }
+}
@@ -0,0 +1,532 @@
+// Copyright 2025 AgentReplay (https://github.com/agentreplay)
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program. If not, see <https://www.gnu.org/licenses/>.
+
+//! Live terminal dashboard (`agentreplay top`)
+//!
+//! Subscribes to the server's trace stream (`/api/v1/traces/stream`, SSE)
+//! and shows the ingestion rate, error rate, recent spans and cost per model
+//! family. Rates cover the last minute; totals cover what arrived since the
+//! dashboard started. The stream is reconnected when the server restarts.
+
+use anyhow::Result;
+use ratatui::crossterm::event::{self, Event, KeyCode, KeyEventKind, KeyModifiers};
+use ratatui::layout::{Constraint, Layout};
+use ratatui::style::{Color, Modifier, Style};
+use ratatui::text::{Line, Span};
+use ratatui::widgets::{Block, Paragraph, Row, Sparkline, Table};
+use ratatui::{DefaultTerminal, Frame};
+use serde::Deserialize;
+use std::collections::{HashMap, VecDeque};
+use std::time::{Duration, Instant};
+use tokio::sync::mpsc;
+
+/// Window of the rate and error figures
+const WINDOW: Duration = Duration::from_secs(60);
+/// Spans kept for the recent list
+const RECENT_SPANS: usize = 200;
+/// Redraw interval
+const TICK: Duration = Duration::from_millis(250);
+/// Wait before reconnecting a dropped stream
+const RECONNECT_DELAY: Duration = Duration::from_secs(2);
+
+/// Span as sent on the trace stream
+#[derive(Debug, Clone, Deserialize)]
+pub struct TraceEvent {
+    pub edge_id: String,
+    pub timestamp_us: u64,
+    pub span_type: String,
+    #[serde(default)]
+    pub duration_ms: f64,
+    #[serde(default)]
+    pub tokens: u32,
+    #[serde(default)]
+    pub cost: f64,
+    #[serde(default)]
+    pub status: String,
+    #[serde(default)]
+    pub agent_id: u64,
+    #[serde(default)]
+    pub model_family: Option<String>,
+}
+
+impl TraceEvent {
+    fn is_error(&self) -> bool {
+        self.status == "error"
+    }
+}
+
+/// Messages from the stream reader to the UI
+enum Update {
+    Connected,
+    Disconnected(String),
+    Span(TraceEvent),
+}
+
+#[derive(Debug, Default, Clone)]
+pub struct ModelUsage {
+    pub spans: u64,
+    pub tokens: u64,
+    pub cost: f64,
+}
+
+/// Figures shown by the dashboard
+pub struct Dashboard {
+    started: Instant,
+    connected: bool,
+    status: String,
+    /// Arrival time and error flag of the spans within `WINDOW`
+    window: VecDeque<(Instant, bool)>,
+    /// Newest first
+    recent: VecDeque<TraceEvent>,
+    models: HashMap<String, ModelUsage>,
+    total_spans: u64,
+    total_cost: f64,
+}
+
+impl Dashboard {
+    pub fn new(now: Instant) -> Self {
+        Self {
+            started: now,
+            connected: false,
+            status: "connecting".to_string(),
+            window: VecDeque::new(),
+            recent: VecDeque::new(),
+            models: HashMap::new(),
+            total_spans: 0,
+            total_cost: 0.0,
+        }
+    }
+
+    fn apply(&mut self, update: Update, now: Instant) {
+        match update {
+            Update::Connected => {
+                self.connected = true;
+                self.status = "live".to_string();
+            }
+            Update::Disconnected(reason) => {
+                self.connected = false;
+                self.status = format!("reconnecting: {}", reason);
+            }
+            Update::Span(event) => self.record(event, now),
+        }
+    }
+
+    pub fn record(&mut self, event: TraceEvent, now: Instant) {
+        self.window.push_back((now, event.is_error()));
+        self.total_spans += 1;
+        self.total_cost += event.cost;
+
+        let model = event.model_family.as_deref().unwrap_or("unknown");
+        let usage = self.models.entry(model.to_string()).or_default();
+        usage.spans += 1;
+        usage.tokens += u64::from(event.tokens);
+        usage.cost += event.cost;
+
+        self.recent.push_front(event);
+        self.recent.truncate(RECENT_SPANS);
+    }
+
+    /// Drop spans that left the window
+    pub fn expire(&mut self, now: Instant) {
+        while self
+            .window
+            .front()
+            .is_some_and(|(at, _)| now.duration_since(*at) >= WINDOW)
+        {
+            self.window.pop_front();
+        }
+    }
+
+    /// Spans per second over the window, or since start when that's shorter
+    pub fn ingest_rate(&self, now: Instant) -> f64 {
+        let elapsed = now.duration_since(self.started).min(WINDOW);
+        self.window.len() as f64 / elapsed.as_secs_f64().max(1.0)
+    }
+
+    /// Share of the spans within the window that failed
+    pub fn error_rate(&self) -> f64 {
+        if self.window.is_empty() {
+            return 0.0;
+        }
+        let errors = self.window.iter().filter(|(_, error)| *error).count();
+        errors as f64 / self.window.len() as f64
+    }
+
+    /// Spans per second across the window, oldest second first
+    pub fn per_second(&self, now: Instant) -> Vec<u64> {
+        let mut buckets = vec![0; WINDOW.as_secs() as usize];
+        for (at, _) in &self.window {
+            let age = now.duration_since(*at).as_secs() as usize;
+            if age < buckets.len() {
+                let index = buckets.len() - 1 - age;
+                buckets[index] += 1;
+            }
+        }
+        buckets
+    }
+
+    /// Model families, most expensive first
+    pub fn models_by_cost(&self) -> Vec<(&str, &ModelUsage)> {
+        let mut models: Vec<_> = self
+            .models
+            .iter()
+            .map(|(name, usage)| (name.as_str(), usage))
+            .collect();
+        models.sort_by(|a, b| b.1.cost.total_cmp(&a.1.cost).then(a.0.cmp(b.0)));
+        models
+    }
+}
+
+/// Incremental parser for `text/event-stream` bodies
+#[derive(Default)]
+struct SseParser {
+    buffer: Vec<u8>,
+    data: Vec<String>,
+}
+
+impl SseParser {
+    /// Feed a chunk of the body; returns the data of each event it completed
+    fn push(&mut self, chunk: &[u8]) -> Vec<String> {
+        self.buffer.extend_from_slice(chunk);
+        let mut events = Vec::new();
+        while let Some(end) = self.buffer.iter().position(|&b| b == b'\n') {
+            let line: Vec<u8> = self.buffer.drain(..=end).collect();
+            let line = String::from_utf8_lossy(&line);
+            let line = line.trim_end_matches(['\n', '\r']);
+            if line.is_empty() {
+                if !self.data.is_empty() {
+                    events.push(self.data.join("\n"));
+                    self.data.clear();
+                }
+            } else if let Some(value) = line.strip_prefix("data:") {
+                self.data
+                    .push(value.strip_prefix(' ').unwrap_or(value).to_string());
+            }
+            // Comments (keep-alives) and other fields are ignored
+        }
+        events
+    }
+}
+
+/// Run the dashboard until the user quits
+pub async fn run(server: &str, api_key: Option<String>, project: Option<u16>) -> Result<()> {
+    let server = server.trim_end_matches('/').to_string();
+    let url = match project {
+        Some(project) => format!("{}/api/v1/traces/stream?project_id={}", server, project),
+        None => format!("{}/api/v1/traces/stream", server),
+    };
+
+    let (tx, rx) = mpsc::unbounded_channel();
+    let reader = tokio::spawn(stream_spans(url, api_key, tx));
+    let result = tokio::task::spawn_blocking(move || run_ui(&server, rx)).await?;
+    reader.abort();
+    result
+}
+
+/// Forward spans from the trace stream, reconnecting until the UI is gone
+async fn stream_spans(url: String, api_key: Option<String>, tx: mpsc::UnboundedSender<Update>) {
+    let client = reqwest::Client::new();
+    loop {
+        let reason = match read_stream(&client, &url, api_key.as_deref(), &tx).await {
+            Ok(()) => "stream closed".to_string(),
+            Err(e) => e.to_string(),
+        };
+        if tx.send(Update::Disconnected(reason)).is_err() {
+            return;
+        }
+        tokio::time::sleep(RECONNECT_DELAY).await;
+    }
+}
+
+async fn read_stream(
+    client: &reqwest::Client,
+    url: &str,
+    api_key: Option<&str>,
+    tx: &mpsc::UnboundedSender<Update>,
+) -> Result<()> {
+    let mut request = client.get(url).header("Accept", "text/event-stream");
+    if let Some(key) = api_key {
+        request = request.header("X-API-Key", key);
+    }
+    let mut response = request.send().await?.error_for_status()?;
+    let _ = tx.send(Update::Connected);
+
+    let mut parser = SseParser::default();
+    while let Some(chunk) = response.chunk().await? {
+        for data in parser.push(&chunk) {
+            // Events from newer servers may not parse; skip rather than disconnect
+            let Ok(event) = serde_json::from_str::<TraceEvent>(&data) else {
+                continue;
+            };
+            if tx.send(Update::Span(event)).is_err() {
+                return Ok(());
+            }
+        }
+    }
+    Ok(())
+}
+
+fn run_ui(server: &str, mut rx: mpsc::UnboundedReceiver<Update>) -> Result<()> {
+    let mut terminal = ratatui::init();
+    let result = ui_loop(&mut terminal, server, &mut rx);
+    ratatui::restore();
+    result
+}
+
+fn ui_loop(
+    terminal: &mut DefaultTerminal,
+    server: &str,
+    rx: &mut mpsc::UnboundedReceiver<Update>,
+) -> Result<()> {
+    let mut dashboard = Dashboard::new(Instant::now());
+    loop {
+        let now = Instant::now();
+        while let Ok(update) = rx.try_recv() {
+            dashboard.apply(update, now);
+        }
+        dashboard.expire(now);
+        terminal.draw(|frame| draw(frame, &dashboard, server, now))?;
+
+        if event::poll(TICK)? {
+            if let Event::Key(key) = event::read()? {
+                let quit = matches!(key.code, KeyCode::Char('q') | KeyCode::Esc)
+                    || (key.code == KeyCode::Char('c')
+                        && key.modifiers.contains(KeyModifiers::CONTROL));
+                if key.kind == KeyEventKind::Press && quit {
+                    return Ok(());
+                }
+            }
+        }
+    }
+}
+
+fn draw(frame: &mut Frame, dashboard: &Dashboard, server: &str, now: Instant) {
+    let [header, stats, body, footer] = Layout::vertical([
+        Constraint::Length(1),
+        Constraint::Length(5),
+        Constraint::Min(5),
+        Constraint::Length(1),
+    ])
+    .areas(frame.area());
+
+    let status_color = if dashboard.connected {
+        Color::Green
+    } else {
+        Color::Yellow
+    };
+    let uptime = now.duration_since(dashboard.started).as_secs();
+    frame.render_widget(
+        Line::from(vec![
+            Span::styled(
+                " agentreplay top ",
+                Style::new().add_modifier(Modifier::BOLD),
+            ),
+            Span::raw(format!("{}  ", server)),
+            Span::styled(dashboard.status.as_str(), Style::new().fg(status_color)),
+            Span::raw(format!(
+                "  up {:02}:{:02}:{:02}",
+                uptime / 3600,
+                uptime / 60 % 60,
+                uptime % 60
+            )),
+        ]),
+        header,
+    );
+
+    let [rate_area, error_area, cost_area] = Layout::horizontal([
+        Constraint::Percentage(50),
+        Constraint::Percentage(25),
+        Constraint::Percentage(25),
+    ])
+    .areas(stats);
+
+    let per_second = dashboard.per_second(now);
+    frame.render_widget(
+        Sparkline::default()
+            .block(Block::bordered().title(format!(
+                " Ingestion {:.1} spans/s ",
+                dashboard.ingest_rate(now)
+            )))
+            .data(&per_second)
+            .style(Style::new().fg(Color::Cyan)),
+        rate_area,
+    );
+
+    let error_rate = dashboard.error_rate();
+    let error_color = if error_rate > 0.05 {
+        Color::Red
+    } else {
+        Color::Green
+    };
+    frame.render_widget(
+        Paragraph::new(vec![
+            Line::styled(
+                format!("{:.1}%", error_rate * 100.0),
+                Style::new().fg(error_color).add_modifier(Modifier::BOLD),
+            ),
+            Line::raw(format!("of {} spans in 60s", dashboard.window.len())),
+        ])
+        .block(Block::bordered().title(" Errors ")),
+        error_area,
+    );
+
+    frame.render_widget(
+        Paragraph::new(vec![
+            Line::styled(
+                format!("${:.4}", dashboard.total_cost),
+                Style::new().add_modifier(Modifier::BOLD),
+            ),
+            Line::raw(format!("{} spans since start", dashboard.total_spans)),
+        ])
+        .block(Block::bordered().title(" Cost ")),
+        cost_area,
+    );
+
+    let [recent_area, models_area] =
+        Layout::horizontal([Constraint::Percentage(65), Constraint::Percentage(35)]).areas(body);
+
+    let recent = dashboard.recent.iter().map(|span| {
+        let time = chrono::DateTime::from_timestamp_micros(span.timestamp_us as i64)
+            .map(|t| {
+                t.with_timezone(&chrono::Local)
+                    .format("%H:%M:%S")
+                    .to_string()
+            })
+            .unwrap_or_default();
+        let style = if span.is_error() {
+            Style::new().fg(Color::Red)
+        } else {
+            Style::new()
+        };
+        Row::new(vec![
+            time,
+            span.edge_id.clone(),
+            span.span_type.clone(),
+            span.agent_id.to_string(),
+            format!("{:.0}", span.duration_ms),
+            span.tokens.to_string(),
+            span.status.clone(),
+        ])
+        .style(style)
+    });
+    frame.render_widget(
+        Table::new(
+            recent,
+            [
+                Constraint::Length(8),
+                Constraint::Min(18),
+                Constraint::Length(12),
+                Constraint::Length(8),
+                Constraint::Length(8),
+                Constraint::Length(7),
+                Constraint::Length(8),
+            ],
+        )
+        .header(
+            Row::new(["Time", "Span", "Type", "Agent", "ms", "Tokens", "Status"])
+                .style(Style::new().add_modifier(Modifier::BOLD)),
+        )
+        .block(Block::bordered().title(" Recent spans ")),
+        recent_area,
+    );
+
+    let models = dashboard.models_by_cost().into_iter().map(|(name, usage)| {
+        Row::new(vec![
+            name.to_string(),
+            usage.spans.to_string(),
+            usage.tokens.to_string(),
+            format!("${:.4}", usage.cost),
+        ])
+    });
+    frame.render_widget(
+        Table::new(
+            models,
+            [
+                Constraint::Min(10),
+                Constraint::Length(7),
+                Constraint::Length(9),
+                Constraint::Length(10),
+            ],
+        )
+        .header(
+            Row::new(["Model", "Spans", "Tokens", "Cost"])
+                .style(Style::new().add_modifier(Modifier::BOLD)),
+        )
+        .block(Block::bordered().title(" Cost by model ")),
+        models_area,
+    );
+
+    frame.render_widget(
+        Line::styled(" q quit", Style::new().fg(Color::DarkGray)),
+        footer,
+    );
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn span(status: &str, model: Option<&str>, cost: f64) -> TraceEvent {
+        TraceEvent {
+            edge_id: "0x1".to_string(),
+            timestamp_us: 1_700_000_000_000_000,
+            span_type: "Response".to_string(),
+            duration_ms: 12.0,
+            tokens: 100,
+            cost,
+            status: status.to_string(),
+            agent_id: 1,
+            model_family: model.map(str::to_string),
+        }
+    }
+
+    #[test]
+    fn test_sse_parser_splits_events_across_chunks() {
+        let mut parser = SseParser::default();
+        assert!(parser.push(b"data: {\"a\":").is_empty());
+        assert!(parser.push(b"1}\r\n").is_empty());
+        assert_eq!(
+            parser.push(b"\r\n:keep-alive\n\ndata:x\n\n"),
+            vec!["{\"a\":1}", "x"]
+        );
+    }
+
+    #[test]
+    fn test_dashboard_rates_and_costs() {
+        let start = Instant::now();
+        let mut dashboard = Dashboard::new(start);
+        dashboard.record(span("success", Some("gpt"), 0.01), start);
+        dashboard.record(span("error", Some("claude"), 0.03), start);
+        dashboard.record(span("success", None, 0.0), start + Duration::from_secs(30));
+
+        let now = start + Duration::from_secs(30);
+        assert!((dashboard.error_rate() - 1.0 / 3.0).abs() < 1e-9);
+        assert!((dashboard.ingest_rate(now) - 0.1).abs() < 1e-9);
+        let per_second = dashboard.per_second(now);
+        assert_eq!(per_second[59], 1);
+        assert_eq!(per_second[29], 2);
+
+        let models = dashboard.models_by_cost();
+        assert_eq!(models[0].0, "claude");
+        assert_eq!(models[2].0, "unknown");
+
+        // The first two spans leave the window; totals are kept
+        dashboard.expire(start + Duration::from_secs(60));
+        assert_eq!(dashboard.window.len(), 1);
+        assert_eq!(dashboard.error_rate(), 0.0);
+        assert_eq!(dashboard.total_spans, 3);
+        assert!((dashboard.total_cost - 0.04).abs() < 1e-9);
+    }
+}
@@ -0,0 +1,41 @@
+// Copyright 2025 AgentReplay (https://github.com/agentreplay)
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program. If not, see <https://www.gnu.org/licenses/>.
+
+//! Wall-clock time since the Unix epoch
+//!
+//! A clock set before 1970 reads as 0 instead of panicking.
+
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+fn since_epoch() -> Duration {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+}
+
+/// Seconds since the Unix epoch
+pub fn now_secs() -> u64 {
+    since_epoch().as_secs()
+}
+
+/// Milliseconds since the Unix epoch
+pub fn now_ms() -> u64 {
+    since_epoch().as_millis() as u64
+}
+
+/// Microseconds since the Unix epoch
+pub fn now_us() -> u64 {
+    since_epoch().as_micros() as u64
+}
@@ -0,0 +1,467 @@
+// Copyright 2025 AgentReplay (https://github.com/agentreplay)
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program. If not, see <https://www.gnu.org/licenses/>.
+
+//! Crash and panic diagnostics
+//!
+//! Captures panics into a local diagnostic bundle so that crashes in the
+//! server or desktop app can be inspected (and optionally shared) after the
+//! fact. A bundle is a single JSON file containing:
+//!
+//! - The panic message, location, thread and backtrace
+//! - The most recent log lines (from an in-memory [`LogTail`] or a log file)
+//! - A redacted configuration summary supplied by the host process
+//! - Basic platform information
+//!
+//! Bundles are written to `<data_dir>/diagnostics/` and picked up by
+//! `agentreplay diagnostics collect`.
+
+use crate::clock::now_ms;
+use parking_lot::{Mutex, RwLock};
+use serde::{Deserialize, Serialize};
+use std::collections::VecDeque;
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// Directory name (relative to the data directory) where bundles are stored
+pub const DIAGNOSTICS_DIR_NAME: &str = "diagnostics";
+
+/// Bundle format version, bumped on incompatible changes
+pub const DIAGNOSTIC_BUNDLE_VERSION: u32 = 1;
+
+/// Default number of log lines retained for a bundle
+pub const DEFAULT_LOG_TAIL_LINES: usize = 500;
+
+/// Details of a captured panic
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PanicReport {
+    /// Panic payload rendered as a string
+    pub message: String,
+    /// Source location (`file:line:column`) if available
+    pub location: Option<String>,
+    /// Name of the panicking thread
+    pub thread: String,
+    /// Captured backtrace (always captured, regardless of RUST_BACKTRACE)
+    pub backtrace: String,
+}
+
+/// A self-contained diagnostic bundle
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DiagnosticBundle {
+    /// Bundle format version
+    pub bundle_version: u32,
+    /// Component that produced the bundle (e.g. "server", "desktop", "cli")
+    pub component: String,
+    /// Version of the producing component
+    pub version: String,
+    /// When the bundle was created (milliseconds since epoch)
+    pub created_at_ms: u64,
+    /// Operating system
+    pub os: String,
+    /// CPU architecture
+    pub arch: String,
+    /// Process ID of the producing process
+    pub pid: u32,
+    /// Panic details, if the bundle was produced by the panic hook
+    pub panic: Option<PanicReport>,
+    /// Most recent log lines, oldest first
+    pub log_tail: Vec<String>,
+    /// Redacted configuration summary
+    pub config_summary: serde_json::Value,
+}
+
+impl DiagnosticBundle {
+    /// File name used when writing this bundle to disk
+    pub fn file_name(&self) -> String {
+        let kind = if self.panic.is_some() {
+            "crash"
+        } else {
+            "snapshot"
+        };
+        format!("{}-{}-{}.json", kind, self.component, self.created_at_ms)
+    }
+
+    /// Load a bundle from a JSON file
+    pub fn load(path: &Path) -> io::Result<Self> {
+        let content = std::fs::read(path)?;
+        serde_json::from_slice(&content).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))
+    }
+}
+
+/// Bounded in-memory ring buffer of recent log lines
+///
+/// Plug [`LogTail::writer`] into a `tracing_subscriber::fmt` layer so that
+/// every formatted event is mirrored to stdout and retained for bundles.
+#[derive(Debug)]
+pub struct LogTail {
+    lines: Mutex<VecDeque<String>>,
+    capacity: usize,
+}
+
+impl LogTail {
+    pub fn new(capacity: usize) -> Arc<Self> {
+        Arc::new(Self {
+            lines: Mutex::new(VecDeque::with_capacity(capacity)),
+            capacity: capacity.max(1),
+        })
+    }
+
+    /// Append a single line, evicting the oldest line when full
+    pub fn push(&self, line: impl Into<String>) {
+        let mut lines = self.lines.lock();
+        if lines.len() == self.capacity {
+            lines.pop_front();
+        }
+        lines.push_back(line.into());
+    }
+
+    /// Copy of the retained lines, oldest first
+    pub fn snapshot(&self) -> Vec<String> {
+        self.lines.lock().iter().cloned().collect()
+    }
+
+    /// Create a writer that tees output to stdout and into this tail
+    pub fn writer(self: &Arc<Self>) -> LogTailWriter {
+        LogTailWriter {
+            tail: Arc::clone(self),
+            buf: Vec::new(),
+        }
+    }
+}
+
+/// `io::Write` adapter returned by [`LogTail::writer`]
+///
+/// Complete lines are pushed into the tail when the writer is dropped,
+/// which `tracing_subscriber` does after every event.
+pub struct LogTailWriter {
+    tail: Arc<LogTail>,
+    buf: Vec<u8>,
+}
+
+impl Write for LogTailWriter {
+    fn write(&mut self, data: &[u8]) -> io::Result<usize> {
+        self.buf.extend_from_slice(data);
+        io::stdout().write(data)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        io::stdout().flush()
+    }
+}
+
+impl Drop for LogTailWriter {
+    fn drop(&mut self) {
+        if self.buf.is_empty() {
+            return;
+        }
+        for line in String::from_utf8_lossy(&self.buf).lines() {
+            if !line.is_empty() {
+                self.tail.push(line);
+            }
+        }
+    }
+}
+
+/// Read the last `max_lines` lines of a log file
+pub fn read_log_file_tail(path: &Path, max_lines: usize) -> io::Result<Vec<String>> {
+    let content = std::fs::read(path)?;
+    let text = String::from_utf8_lossy(&content);
+    let lines: Vec<&str> = text.lines().collect();
+    let start = lines.len().saturating_sub(max_lines);
+    Ok(lines[start..].iter().map(|l| l.to_string()).collect())
+}
+
+/// Where a collector gets its log lines from
+#[derive(Debug, Clone)]
+pub enum LogSource {
+    /// In-memory ring buffer (server)
+    Memory(Arc<LogTail>),
+    /// Tail of a log file on disk (desktop app)
+    File(PathBuf),
+    /// No log capture
+    None,
+}
+
+/// Collects diagnostic bundles and installs the panic hook
+pub struct DiagnosticsCollector {
+    component: String,
+    version: String,
+    output_dir: PathBuf,
+    log_source: LogSource,
+    max_log_lines: usize,
+    config_summary: RwLock<serde_json::Value>,
+}
+
+impl DiagnosticsCollector {
+    /// Create a collector writing bundles into `output_dir`
+    pub fn new(
+        component: impl Into<String>,
+        version: impl Into<String>,
+        output_dir: impl Into<PathBuf>,
+        log_source: LogSource,
+    ) -> Self {
+        Self {
+            component: component.into(),
+            version: version.into(),
+            output_dir: output_dir.into(),
+            log_source,
+            max_log_lines: DEFAULT_LOG_TAIL_LINES,
+            config_summary: RwLock::new(serde_json::Value::Null),
+        }
+    }
+
+    /// Directory bundles are written to
+    pub fn output_dir(&self) -> &Path {
+        &self.output_dir
+    }
+
+    /// Replace the configuration summary included in future bundles
+    ///
+    /// Callers are responsible for redacting secrets before handing the
+    /// summary over.
+    pub fn set_config_summary(&self, summary: serde_json::Value) {
+        *self.config_summary.write() = summary;
+    }
+
+    /// Build a bundle from the current state
+    pub fn collect(&self, panic: Option<PanicReport>) -> DiagnosticBundle {
+        let log_tail = match &self.log_source {
+            LogSource::Memory(tail) => tail.snapshot(),
+            LogSource::File(path) => {
+                read_log_file_tail(path, self.max_log_lines).unwrap_or_default()
+            }
+            LogSource::None => Vec::new(),
+        };
+
+        DiagnosticBundle {
+            bundle_version: DIAGNOSTIC_BUNDLE_VERSION,
+            component: self.component.clone(),
+            version: self.version.clone(),
+            created_at_ms: now_ms(),
+            os: std::env::consts::OS.to_string(),
+            arch: std::env::consts::ARCH.to_string(),
+            pid: std::process::id(),
+            panic,
+            log_tail,
+            // try_read: the panic may have happened while the lock was held
+            config_summary: self
+                .config_summary
+                .try_read()
+                .map(|s| s.clone())
+                .unwrap_or(serde_json::Value::Null),
+        }
+    }
+
+    /// Write a bundle to the output directory, returning its path
+    pub fn write_bundle(&self, bundle: &DiagnosticBundle) -> io::Result<PathBuf> {
+        std::fs::create_dir_all(&self.output_dir)?;
+        let path = self.output_dir.join(bundle.file_name());
+        let json = serde_json::to_vec_pretty(bundle)
+            .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+        std::fs::write(&path, json)?;
+        Ok(path)
+    }
+
+    /// Resolve a bundle by file name or by the path [`Self::write_bundle`]
+    /// returned
+    ///
+    /// Anything that does not resolve to a bundle inside the output
+    /// directory, including through `..` or symlinks, is refused.
+    pub fn bundle_path(&self, id: &str) -> io::Result<PathBuf> {
+        let dir = self.output_dir.canonicalize()?;
+        let path = dir.join(id).canonicalize()?;
+        if path.parent() != Some(dir.as_path()) || path.extension().is_none_or(|e| e != "json") {
+            return Err(io::Error::new(
+                io::ErrorKind::PermissionDenied,
+                format!("{} is not a diagnostic bundle", id),
+            ));
+        }
+        Ok(path)
+    }
+
+    /// Install a process-wide panic hook that writes a crash bundle
+    ///
+    /// The previously installed hook still runs afterwards, so the default
+    /// stderr output is preserved.
+    pub fn install_panic_hook(self: Arc<Self>) {
+        let previous = std::panic::take_hook();
+        std::panic::set_hook(Box::new(move |info| {
+            let message = if let Some(s) = info.payload().downcast_ref::<&str>() {
+                s.to_string()
+            } else if let Some(s) = info.payload().downcast_ref::<String>() {
+                s.clone()
+            } else {
+                "Box<dyn Any>".to_string()
+            };
+
+            let report = PanicReport {
+                message,
+                location: info
+                    .location()
+                    .map(|l| format!("{}:{}:{}", l.file(), l.line(), l.column())),
+                thread: std::thread::current()
+                    .name()
+                    .unwrap_or("<unnamed>")
+                    .to_string(),
+                backtrace: std::backtrace::Backtrace::force_capture().to_string(),
+            };
+
+            let bundle = self.collect(Some(report));
+            match self.write_bundle(&bundle) {
+                Ok(path) => eprintln!(
+                    "Agentreplay crashed. Diagnostic bundle written to {}",
+                    path.display()
+                ),
+                Err(e) => eprintln!(
+                    "Agentreplay crashed. Failed to write diagnostic bundle: {}",
+                    e
+                ),
+            }
+
+            previous(info);
+        }));
+    }
+}
+
+/// List bundle files in a diagnostics directory, newest first
+pub fn list_bundles(dir: &Path) -> io::Result<Vec<PathBuf>> {
+    if !dir.exists() {
+        return Ok(Vec::new());
+    }
+
+    let mut bundles: Vec<(SystemTime, PathBuf)> = Vec::new();
+    for entry in std::fs::read_dir(dir)? {
+        let entry = entry?;
+        let path = entry.path();
+        if path.extension().map(|e| e == "json").unwrap_or(false) {
+            let modified = entry.metadata()?.modified().unwrap_or(UNIX_EPOCH);
+            bundles.push((modified, path));
+        }
+    }
+
+    bundles.sort_by_key(|(modified, _)| std::cmp::Reverse(*modified));
+    Ok(bundles.into_iter().map(|(_, p)| p).collect())
+}
+
+/// Upload a bundle file to a collection endpoint via HTTP POST
+///
+/// Uploading is always opt-in; nothing leaves the machine unless the user
+/// supplies an endpoint.
+pub async fn upload_bundle(path: &Path, url: &str) -> crate::Result<()> {
+    let body = tokio::fs::read(path).await?;
+    let response = reqwest::Client::new()
+        .post(url)
+        .header("content-type", "application/octet-stream")
+        .body(body)
+        .send()
+        .await
+        .map_err(|e| crate::AgentreplayError::Internal(format!("Upload failed: {}", e)))?;
+
+    if !response.status().is_success() {
+        return Err(crate::AgentreplayError::Internal(format!(
+            "Upload rejected with status {}",
+            response.status()
+        )));
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_log_tail_evicts_oldest() {
+        let tail = LogTail::new(2);
+        tail.push("a");
+        tail.push("b");
+        tail.push("c");
+        assert_eq!(tail.snapshot(), vec!["b".to_string(), "c".to_string()]);
+    }
+
+    #[test]
+    fn test_log_tail_writer_splits_lines() {
+        let tail = LogTail::new(10);
+        {
+            let mut writer = tail.writer();
+            writer.write_all(b"first line\nsecond line\n").unwrap();
+        }
+        assert_eq!(tail.snapshot(), vec!["first line", "second line"]);
+    }
+
+    #[test]
+    fn test_bundle_roundtrip() {
+        let dir = tempfile::tempdir().unwrap();
+        let tail = LogTail::new(10);
+        tail.push("something happened");
+
+        let collector = DiagnosticsCollector::new(
+            "server",
+            "0.1.0",
+            dir.path().join(DIAGNOSTICS_DIR_NAME),
+            LogSource::Memory(tail),
+        );
+        collector.set_config_summary(serde_json::json!({"listen_addr": "127.0.0.1:47100"}));
+
+        let bundle = collector.collect(Some(PanicReport {
+            message: "boom".to_string(),
+            location: Some("src/lib.rs:1:1".to_string()),
+            thread: "main".to_string(),
+            backtrace: String::new(),
+        }));
+        let path = collector.write_bundle(&bundle).unwrap();
+        assert!(path
+            .file_name()
+            .unwrap()
+            .to_string_lossy()
+            .starts_with("crash-server-"));
+
+        let loaded = DiagnosticBundle::load(&path).unwrap();
+        assert_eq!(loaded.panic.unwrap().message, "boom");
+        assert_eq!(loaded.log_tail, vec!["something happened"]);
+        assert_eq!(loaded.config_summary["listen_addr"], "127.0.0.1:47100");
+
+        let listed = list_bundles(collector.output_dir()).unwrap();
+        assert_eq!(listed, vec![path]);
+    }
+
+    #[test]
+    fn test_bundle_path_stays_in_output_dir() {
+        let dir = tempfile::tempdir().unwrap();
+        let collector = DiagnosticsCollector::new(
+            "desktop",
+            "0.1.0",
+            dir.path().join(DIAGNOSTICS_DIR_NAME),
+            LogSource::None,
+        );
+        let path = collector.write_bundle(&collector.collect(None)).unwrap();
+        let canonical = path.canonicalize().unwrap();
+        let name = path.file_name().unwrap().to_str().unwrap();
+        assert_eq!(collector.bundle_path(name).unwrap(), canonical);
+        assert_eq!(
+            collector.bundle_path(path.to_str().unwrap()).unwrap(),
+            canonical
+        );
+
+        let secret = dir.path().join("secret.json");
+        std::fs::write(&secret, b"{}").unwrap();
+        for id in ["../secret.json", secret.to_str().unwrap(), "."] {
+            let err = collector.bundle_path(id).unwrap_err();
+            assert_eq!(err.kind(), io::ErrorKind::PermissionDenied);
+        }
+    }
+}
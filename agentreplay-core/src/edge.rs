@@ -351,6 +351,9 @@ impl Default for HybridLogicalClock {
 
 /// Flag bits for AgentFlowEdge.flags field
 pub const FLAG_DELETED: u32 = 1 << 0; // Tombstone marker for deletions
+pub const FLAG_HAS_LINKS: u32 = 1 << 1; // Span links stored alongside (see span_link)
+pub const FLAG_IN_PROGRESS: u32 = 1 << 2; // Span started, finalize update still pending
+pub const FLAG_ABANDONED: u32 = 1 << 3; // Closed by the server after no finalize arrived
 
 /// Sensitivity flags for PII and redaction control
 pub const SENSITIVITY_NONE: u8 = 0;
@@ -1003,6 +1006,27 @@ impl AgentFlowEdge {
         self.checksum = self.compute_checksum();
     }
 
+    /// Check if this edge has span links beyond its causal parent
+    pub fn has_links(&self) -> bool {
+        (self.flags & FLAG_HAS_LINKS) != 0
+    }
+
+    /// Mark this edge as carrying span links
+    pub fn mark_has_links(&mut self) {
+        self.flags |= FLAG_HAS_LINKS;
+        self.checksum = self.compute_checksum();
+    }
+
+    /// Check if this edge is a started span whose finalize update has not arrived
+    pub fn is_in_progress(&self) -> bool {
+        (self.flags & FLAG_IN_PROGRESS) != 0
+    }
+
+    /// Check if this edge was closed by the server instead of finalized
+    pub fn is_abandoned(&self) -> bool {
+        (self.flags & FLAG_ABANDONED) != 0
+    }
+
     /// Create a tombstone for deleting an edge by ID
     pub fn tombstone(edge_id: u128, timestamp_us: u64, tenant_id: u64) -> Self {
         let mut edge = AgentFlowEdge {
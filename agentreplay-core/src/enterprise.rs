@@ -54,6 +54,60 @@ pub struct Experiment {
     pub end_time: Option<u64>,
     pub created_at: u64,
     pub updated_at: u64,
+    /// Automatic analysis settings; `None` leaves stopping to the user
+    #[serde(default)]
+    pub auto_stop: Option<ExperimentAutoStop>,
+}
+
+/// Sequential analysis settings for an experiment
+///
+/// When enabled, every recorded result re-runs the Bayesian analysis and the
+/// experiment is completed once a variant's probability of beating control on
+/// the primary metric crosses `threshold` (or falls below `1 - threshold`).
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ExperimentAutoStop {
+    #[serde(default = "default_auto_stop_enabled")]
+    pub enabled: bool,
+    /// Metric that drives the stop decision (defaults to the first metric)
+    #[serde(default)]
+    pub primary_metric: Option<String>,
+    /// Variant used as the baseline (defaults to the first variant)
+    #[serde(default)]
+    pub control_variant_id: Option<String>,
+    /// Probability of superiority required to stop (e.g. 0.95)
+    #[serde(default = "default_auto_stop_threshold")]
+    pub threshold: f64,
+    /// Results each variant needs before a decision is allowed
+    #[serde(default = "default_auto_stop_min_samples")]
+    pub min_samples_per_variant: usize,
+    /// Metrics where a smaller value is better (latency, cost, ...)
+    #[serde(default)]
+    pub lower_is_better: Vec<String>,
+}
+
+fn default_auto_stop_enabled() -> bool {
+    true
+}
+
+fn default_auto_stop_threshold() -> f64 {
+    0.95
+}
+
+fn default_auto_stop_min_samples() -> usize {
+    100
+}
+
+impl Default for ExperimentAutoStop {
+    fn default() -> Self {
+        Self {
+            enabled: default_auto_stop_enabled(),
+            primary_metric: None,
+            control_variant_id: None,
+            threshold: default_auto_stop_threshold(),
+            min_samples_per_variant: default_auto_stop_min_samples(),
+            lower_is_better: Vec::new(),
+        }
+    }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
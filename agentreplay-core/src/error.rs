@@ -80,6 +80,9 @@ pub enum AgentreplayError {
 
     #[error("Write stall timeout: {0}")]
     WriteStall(String),
+
+    #[error("Tenant isolation violation: {0}")]
+    TenantIsolation(String),
 }
 
 pub type Result<T> = std::result::Result<T, AgentreplayError>;
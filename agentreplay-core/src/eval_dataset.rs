@@ -35,6 +35,10 @@ pub struct TestCase {
     /// Optional TaskDefinitionV2 with explicit success criteria and graders
     #[serde(default)]
     pub task_definition_v2: Option<TaskDefinitionV2>,
+
+    /// Tool calls the agent is expected to make (optional)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub reference_trajectory: Option<ReferenceTrajectory>,
 }
 
 impl TestCase {
@@ -46,6 +50,7 @@ impl TestCase {
             expected_output: None,
             metadata: HashMap::new(),
             task_definition_v2: None,
+            reference_trajectory: None,
         }
     }
 
@@ -57,6 +62,7 @@ impl TestCase {
             expected_output: Some(expected_output),
             metadata: HashMap::new(),
             task_definition_v2: None,
+            reference_trajectory: None,
         }
     }
 
@@ -70,6 +76,39 @@ impl TestCase {
         self.task_definition_v2 = Some(task_definition_v2);
         self
     }
+
+    pub fn with_reference_trajectory(mut self, trajectory: ReferenceTrajectory) -> Self {
+        self.reference_trajectory = Some(trajectory);
+        self
+    }
+}
+
+/// How a trace's tool calls must line up with a reference trajectory
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum TrajectoryMatchMode {
+    /// The same tools in the same order, and nothing else
+    #[default]
+    Exact,
+    /// The reference tools in order, with other calls allowed in between
+    InOrder,
+    /// Every reference tool, in any order
+    AnyOrder,
+}
+
+/// Expected sequence of tool calls for a test case
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct ReferenceTrajectory {
+    /// Tool names, in the order the agent should call them
+    pub tools: Vec<String>,
+    #[serde(default)]
+    pub mode: TrajectoryMatchMode,
+}
+
+impl ReferenceTrajectory {
+    pub fn new(tools: Vec<String>, mode: TrajectoryMatchMode) -> Self {
+        Self { tools, mode }
+    }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
@@ -830,6 +869,24 @@ mod tests {
         assert!(tc.metadata.is_empty());
     }
 
+    #[test]
+    fn test_reference_trajectory_serde() {
+        let tc: TestCase = serde_json::from_str(
+            r#"{"id": 1, "input": "book a flight", "expected_output": null,
+                "reference_trajectory": {"tools": ["search_flights", "book"], "mode": "in_order"}}"#,
+        )
+        .unwrap();
+        let trajectory = tc.reference_trajectory.unwrap();
+        assert_eq!(trajectory.tools, vec!["search_flights", "book"]);
+        assert_eq!(trajectory.mode, TrajectoryMatchMode::InOrder);
+
+        // Mode defaults to exact; test cases without a trajectory omit it
+        let trajectory: ReferenceTrajectory = serde_json::from_str(r#"{"tools": []}"#).unwrap();
+        assert_eq!(trajectory.mode, TrajectoryMatchMode::Exact);
+        let json = serde_json::to_value(TestCase::new(2, "hi".to_string())).unwrap();
+        assert!(json.get("reference_trajectory").is_none());
+    }
+
     #[test]
     fn test_create_test_case_with_expected_output() {
         let tc = TestCase::with_expected_output(
@@ -16,6 +16,7 @@
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 
+use crate::actionable_feedback::Severity;
 use crate::{ActionableFeedback, AssertionResult, JudgeVote};
 
 /// Type-safe metric values for evaluation outputs
@@ -31,6 +32,60 @@ pub enum MetricValueV1 {
     Json(serde_json::Value),
 }
 
+/// A character range of one span's text flagged by an evaluator
+///
+/// Offsets count characters, not bytes, into the `trace_part` of the span:
+/// `input` (the prompt), `output` (the completion) or `tool_arguments`.
+/// UIs underline the range - the hallucinated sentence, the malformed
+/// argument - with `message` as its tooltip.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SpanHighlight {
+    pub span_id: u128,
+    pub trace_part: String,
+    pub start_offset: usize,
+    pub end_offset: usize,
+    pub severity: Severity,
+    pub message: String,
+}
+
+impl SpanHighlight {
+    pub const INPUT: &'static str = "input";
+    pub const OUTPUT: &'static str = "output";
+    pub const TOOL_ARGUMENTS: &'static str = "tool_arguments";
+
+    pub fn new(
+        span_id: u128,
+        trace_part: &str,
+        range: std::ops::Range<usize>,
+        severity: Severity,
+        message: impl Into<String>,
+    ) -> Self {
+        Self {
+            span_id,
+            trace_part: trace_part.to_string(),
+            start_offset: range.start,
+            end_offset: range.end,
+            severity,
+            message: message.into(),
+        }
+    }
+
+    /// Character range of the first occurrence of `needle` in `text`
+    pub fn find(text: &str, needle: &str) -> Option<std::ops::Range<usize>> {
+        if needle.is_empty() {
+            return None;
+        }
+        let byte_start = text.find(needle)?;
+        let start = text[..byte_start].chars().count();
+        Some(start..start + needle.chars().count())
+    }
+
+    /// Whether the range lies within a text of `len` characters
+    pub fn is_valid(&self, len: usize) -> bool {
+        self.start_offset < self.end_offset && self.end_offset <= len
+    }
+}
+
 /// Versioned evaluation result contract
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct EvalResultV1 {
@@ -75,4 +130,33 @@ pub struct EvalResultV1 {
     /// Contains failure modes, improvement suggestions, and similar passing traces
     #[serde(skip_serializing_if = "Option::is_none")]
     pub actionable_feedback: Option<ActionableFeedback>,
-}
\ No newline at end of file
+
+    /// Span-level ranges the evaluator flagged
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub highlights: Vec<SpanHighlight>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_highlight_find_counts_characters() {
+        let text = "Café au lait costs €3. It was invented in 1850.";
+        let range = SpanHighlight::find(text, "invented in 1850").unwrap();
+        assert_eq!(range, 30..46);
+        assert_eq!(
+            text.chars()
+                .skip(range.start)
+                .take(range.len())
+                .collect::<String>(),
+            "invented in 1850"
+        );
+        assert!(SpanHighlight::find(text, "1851").is_none());
+        assert!(SpanHighlight::find(text, "").is_none());
+
+        let highlight = SpanHighlight::new(1, SpanHighlight::OUTPUT, range, Severity::Major, "x");
+        assert!(highlight.is_valid(text.chars().count()));
+        assert!(!highlight.is_valid(40));
+    }
+}
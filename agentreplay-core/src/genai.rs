@@ -53,6 +53,8 @@ pub struct TokenUsage {
     pub cache_creation_tokens: Option<u32>, // Anthropic cache creation
     pub total_tokens: u32,
     pub cost_usd: Option<f64>, // Pre-computed cost
+    /// Pre-computed prompt-caching savings (negative if cache writes cost more)
+    pub cache_savings_usd: Option<f64>,
 }
 
 impl TokenUsage {
@@ -66,6 +68,14 @@ impl TokenUsage {
         self.input_tokens
             .saturating_sub(self.cache_read_tokens.unwrap_or(0))
     }
+
+    /// Fraction of input tokens served from the prompt cache
+    pub fn cache_hit_rate(&self) -> f64 {
+        if self.input_tokens == 0 {
+            return 0.0;
+        }
+        (self.cache_read_tokens.unwrap_or(0) as f64 / self.input_tokens as f64).min(1.0)
+    }
 }
 
 /// Model parameters for reproducibility
@@ -189,5 +199,6 @@ mod tests {
 
         usage.cache_read_tokens = Some(30);
         assert_eq!(usage.effective_input_tokens(), 70);
+        assert!((usage.cache_hit_rate() - 0.3).abs() < 1e-9);
     }
 }
@@ -150,6 +150,22 @@ pub enum InsightType {
         current_tokens: u64,
         change_percent: f64,
     },
+
+    /// Tool calls whose arguments violate the registered input schema
+    ToolContractViolation {
+        tool_name: String,
+        violation_count: usize,
+        sample_error: String,
+    },
+
+    /// Production inputs diverge from the golden datasets evals cover
+    DatasetDrift {
+        datasets: Vec<String>,
+        mmd: f64,
+        centroid_shift: f64,
+        /// Fraction of production inputs close to some test case
+        coverage: f64,
+    },
 }
 
 /// Configuration for insight generation
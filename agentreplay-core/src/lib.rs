@@ -17,9 +17,11 @@
 //!
 //! Fundamental data structures and types for the AgentFlow Format.
 
+pub mod clock;
 pub mod coding_session;
 pub mod config;
 pub mod context;
+pub mod diagnostics;
 pub mod edge;
 pub mod enterprise;
 pub mod error;
@@ -45,8 +47,11 @@ pub mod prompt;
 pub mod quality;
 pub mod resilience;
 pub mod saved_view;
+pub mod service;
 pub mod session;
 pub mod session_summary;
+pub mod span_link;
+pub mod storage_metrics;
 pub mod tool;
 pub mod tool_definition;
 
@@ -60,11 +65,13 @@ pub use coding_session::{
     generate_observation_id, generate_session_id,
 };
 pub use config::{TimestampConfig, DEFAULT_MAX_TIMESTAMP, DEFAULT_MIN_TIMESTAMP};
+pub use diagnostics::{DiagnosticBundle, DiagnosticsCollector, LogSource, LogTail, PanicReport};
 pub use edge::{
     checked_timestamp_add, checked_timestamp_sub, validate_timestamp, AgentFlowEdge, Environment,
-    HlcTimestamp, HybridLogicalClock, SpanType, AFF_SCHEMA_VERSION, FLAG_DELETED, HLC_LOGICAL_BITS,
-    HLC_LOGICAL_MASK, HLC_MAX_DRIFT_MS, HLC_MAX_LOGICAL, SENSITIVITY_INTERNAL, SENSITIVITY_NONE,
-    SENSITIVITY_NO_EMBED, SENSITIVITY_PII, SENSITIVITY_SECRET,
+    HlcTimestamp, HybridLogicalClock, SpanType, AFF_SCHEMA_VERSION, FLAG_ABANDONED, FLAG_DELETED,
+    FLAG_HAS_LINKS, FLAG_IN_PROGRESS, HLC_LOGICAL_BITS, HLC_LOGICAL_MASK, HLC_MAX_DRIFT_MS,
+    HLC_MAX_LOGICAL, SENSITIVITY_INTERNAL, SENSITIVITY_NONE, SENSITIVITY_NO_EMBED, SENSITIVITY_PII,
+    SENSITIVITY_SECRET,
 };
 pub use actionable_feedback::*;
 pub use enterprise::*;
@@ -72,11 +79,11 @@ pub use error::{AgentreplayError, Result};
 pub use eval::{evaluators, metrics, EvalMetric};
 pub use eval_dataset::{
     AssertionResult, EvalDataset, EvalRun, GraderPolicyV2, GraderResult, GraderSpecV2,
-    GraderThresholdV2, JudgeVote, OverallResult, PassRateCI, RunResult, RunStatus,
-    SideEffectExpectationV2, StateExpectationV2, SuccessCriteriaV2, TaskAggregate,
-    TaskDefinitionV2, TestCase, TrialResult,
+    GraderThresholdV2, JudgeVote, OverallResult, PassRateCI, ReferenceTrajectory, RunResult,
+    RunStatus, SideEffectExpectationV2, StateExpectationV2, SuccessCriteriaV2, TaskAggregate,
+    TaskDefinitionV2, TestCase, TrajectoryMatchMode, TrialResult,
 };
-pub use eval_result::{EvalResultV1, MetricValueV1};
+pub use eval_result::{EvalResultV1, MetricValueV1, SpanHighlight};
 pub use eval_trace::{
     ContentPartV1, EnvironmentStateV2, EvalTraceV1, MessageV1, OutcomeV1, OutcomeV2,
     SideEffectV2, SpanSummaryV1, TraceRefV1, TranscriptEventV1, TraceStatsV1,
@@ -100,6 +107,7 @@ pub use model_pricing::{
 };
 pub use prompt::{Completion, ModelParameters, Prompt, PromptCompletion, PromptRole};
 pub use saved_view::{SavedView, SavedViewRegistry};
+pub use span_link::{normalize_links, LinkKind, SpanLink, MAX_SPAN_LINKS};
 pub use tool::{AgentMetadata, ToolMetadata};
 pub use tool_definition::{
     ExecutionConfig, ExecutionContext, HttpMethod, MCPTransport, MockResponse, RateLimit,
@@ -20,17 +20,30 @@
 //! - TOML custom overrides
 //! - Priority-based resolution (custom > upstream > builtin)
 //! - Thread-safe caching
+//! - Scheduled upstream sync with recorded price changes
+//! - Effective-dated overrides, so historical costs stay reproducible
 
+use crate::clock::now_secs;
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 use std::path::{Path, PathBuf};
 use std::sync::Arc;
+use std::time::Duration;
 use tokio::sync::RwLock;
 
 /// Default URL for LiteLLM pricing data
 pub const LITELLM_PRICING_URL: &str =
     "https://raw.githubusercontent.com/BerriAI/litellm/main/model_prices_and_context_window.json";
 
+/// How often the background task refreshes pricing from LiteLLM
+pub const PRICING_SYNC_INTERVAL: Duration = Duration::from_secs(24 * 60 * 60);
+
+/// Attempts per scheduled sync before waiting for the next round
+const SYNC_ATTEMPTS: u64 = 3;
+
+/// Wait before the next round when every attempt of a sync failed
+const SYNC_FAILURE_BACKOFF: Duration = Duration::from_secs(60 * 60);
+
 /// Model pricing information
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ModelPricing {
@@ -157,6 +170,84 @@ pub struct CustomPricingOverride {
     pub litellm_provider: Option<String>,
     #[serde(default)]
     pub source: Option<String>,
+    /// Unix seconds from which this price applies (`None` = always).
+    /// A newer override takes over from its own date, so costs of
+    /// earlier requests keep the price they were made at.
+    #[serde(default)]
+    pub effective_from: Option<u64>,
+}
+
+impl CustomPricingOverride {
+    /// Start of the period this override covers
+    pub fn starts_at(&self) -> u64 {
+        self.effective_from.unwrap_or(0)
+    }
+
+    /// Apply this override on top of the model's base pricing
+    pub fn to_pricing(&self, base: Option<&ModelPricing>) -> ModelPricing {
+        let mut pricing = base.cloned().unwrap_or_default();
+        pricing.input_cost_per_token = self.input_cost_per_token;
+        pricing.output_cost_per_token = self.output_cost_per_token;
+        if self.max_tokens.is_some() {
+            pricing.max_tokens = self.max_tokens;
+        }
+        if self.litellm_provider.is_some() {
+            pricing.litellm_provider = self.litellm_provider.clone();
+        }
+        pricing.source = self.source.clone().or(Some("Custom".to_string()));
+        pricing.priority = PricingPriority::Custom;
+        pricing
+    }
+
+    fn validate(&self) -> Result<(), PricingError> {
+        if self.model_id.trim().is_empty() {
+            return Err(PricingError::Invalid("model_id is required".to_string()));
+        }
+        for (name, cost) in [
+            ("input_cost_per_token", self.input_cost_per_token),
+            ("output_cost_per_token", self.output_cost_per_token),
+        ] {
+            if !cost.is_finite() || cost < 0.0 {
+                return Err(PricingError::Invalid(format!(
+                    "{} must be a non-negative number",
+                    name
+                )));
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Upstream price of a model from a point in time onward
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PricePoint {
+    /// Unix seconds from which this price applies
+    pub effective_from: u64,
+    pub input_cost_per_token: f64,
+    pub output_cost_per_token: f64,
+    #[serde(default)]
+    pub source: Option<String>,
+}
+
+impl PricePoint {
+    fn new(effective_from: u64, pricing: &ModelPricing) -> Self {
+        Self {
+            effective_from,
+            input_cost_per_token: pricing.input_cost_per_token,
+            output_cost_per_token: pricing.output_cost_per_token,
+            source: pricing.source.clone(),
+        }
+    }
+
+    /// The point in effect at `at`; before the first recorded change the
+    /// oldest known price applies
+    fn effective_at(points: &[PricePoint], at: u64) -> Option<&PricePoint> {
+        points
+            .iter()
+            .rev()
+            .find(|p| p.effective_from <= at)
+            .or(points.first())
+    }
 }
 
 /// Metadata about the pricing registry sync
@@ -166,6 +257,12 @@ pub struct PricingRegistryMetadata {
     pub last_sync_at: Option<u64>,
     /// Version or ETag from last sync
     pub last_sync_version: Option<String>,
+    /// Last sync attempt, successful or not (Unix seconds)
+    #[serde(default)]
+    pub last_attempt_at: Option<u64>,
+    /// Error of the last attempt, cleared by the next successful sync
+    #[serde(default)]
+    pub last_sync_error: Option<String>,
     /// Number of models in upstream data
     pub upstream_model_count: usize,
     /// Number of custom overrides
@@ -174,11 +271,24 @@ pub struct PricingRegistryMetadata {
     pub total_model_count: usize,
 }
 
+/// What is persisted next to the upstream cache about its sync
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct UpstreamSyncStamp {
+    #[serde(default)]
+    last_sync_at: Option<u64>,
+    #[serde(default)]
+    last_sync_version: Option<String>,
+}
+
 /// Thread-safe model pricing registry
 #[derive(Clone)]
 pub struct ModelPricingRegistry {
-    /// Cached pricing data (model_id -> pricing)
+    /// Built-in and upstream pricing (model_id -> pricing)
     models: Arc<RwLock<HashMap<String, ModelPricing>>>,
+    /// Custom overrides per model, ordered by effective date
+    overrides: Arc<RwLock<HashMap<String, Vec<CustomPricingOverride>>>>,
+    /// Upstream price changes per model, oldest first
+    history: Arc<RwLock<HashMap<String, Vec<PricePoint>>>>,
     /// Registry metadata
     metadata: Arc<RwLock<PricingRegistryMetadata>>,
     /// Data directory for storing pricing files
@@ -190,6 +300,8 @@ impl ModelPricingRegistry {
     pub fn new(data_dir: impl Into<PathBuf>) -> Self {
         Self {
             models: Arc::new(RwLock::new(HashMap::new())),
+            overrides: Arc::new(RwLock::new(HashMap::new())),
+            history: Arc::new(RwLock::new(HashMap::new())),
             metadata: Arc::new(RwLock::new(PricingRegistryMetadata::default())),
             data_dir: data_dir.into(),
         }
@@ -213,10 +325,13 @@ impl ModelPricingRegistry {
         // 1. Load builtins first
         self.load_builtins().await;
 
-        // 2. Try to load cached upstream data
+        // 2. Try to load cached upstream data and its price history
         if let Err(e) = self.load_cached_upstream().await {
             tracing::debug!("No cached upstream pricing: {}", e);
         }
+        if let Err(e) = self.load_history().await {
+            tracing::debug!("No pricing history: {}", e);
+        }
 
         // 3. Load custom overrides
         if let Err(e) = self.load_custom_overrides().await {
@@ -227,39 +342,38 @@ impl ModelPricingRegistry {
         Ok(())
     }
 
-    /// Get pricing for a model (with fallback resolution)
+    /// Get the current pricing for a model (with fallback resolution)
     pub async fn get_pricing(&self, model_id: &str) -> Option<ModelPricing> {
-        let models = self.models.read().await;
-
-        // Try exact match first
-        if let Some(pricing) = models.get(model_id) {
-            return Some(pricing.clone());
-        }
-
-        // Try normalized model name (lowercase, no version)
-        let normalized = Self::normalize_model_name(model_id);
-        if let Some(pricing) = models.get(&normalized) {
-            return Some(pricing.clone());
-        }
+        self.get_pricing_at(model_id, now_secs()).await
+    }
 
-        // Try prefix matching for versioned models
-        let prefix_matches: Vec<_> = models
-            .iter()
-            .filter(|(k, _)| model_id.starts_with(k.as_str()) || k.starts_with(model_id))
-            .collect();
+    /// Get the pricing that applied to a model at `at` (Unix seconds)
+    ///
+    /// A custom override effective at that time wins; otherwise the upstream
+    /// price recorded for that time, falling back to the current one.
+    pub async fn get_pricing_at(&self, model_id: &str, at: u64) -> Option<ModelPricing> {
+        let models = self.models.read().await;
+        let overrides = self.overrides.read().await;
+        let key = Self::resolve_model_key(&models, &overrides, model_id)?;
+        let base = models.get(&key);
 
-        if let Some((_, pricing)) = prefix_matches.first() {
-            return Some((*pricing).clone());
+        if let Some(custom) = overrides
+            .get(&key)
+            .and_then(|list| list.iter().rev().find(|o| o.starts_at() <= at))
+        {
+            return Some(custom.to_pricing(base));
         }
 
-        // Try provider-prefixed matching (e.g., "openai/gpt-4o" -> "gpt-4o")
-        if let Some(stripped) = model_id.split('/').next_back() {
-            if let Some(pricing) = models.get(stripped) {
-                return Some(pricing.clone());
-            }
+        let mut pricing = base?.clone();
+        let history = self.history.read().await;
+        if let Some(point) = history
+            .get(&key)
+            .and_then(|points| PricePoint::effective_at(points, at))
+        {
+            pricing.input_cost_per_token = point.input_cost_per_token;
+            pricing.output_cost_per_token = point.output_cost_per_token;
         }
-
-        None
+        Some(pricing)
     }
 
     /// Calculate cost for a model
@@ -269,14 +383,214 @@ impl ModelPricingRegistry {
         input_tokens: u32,
         output_tokens: u32,
     ) -> f64 {
-        self.get_pricing(model_id)
+        self.calculate_cost_at(model_id, now_secs(), input_tokens, output_tokens)
+            .await
+    }
+
+    /// Calculate cost for a model with the pricing in effect at `at`
+    /// (Unix seconds), so recomputed historical costs stay the same
+    pub async fn calculate_cost_at(
+        &self,
+        model_id: &str,
+        at: u64,
+        input_tokens: u32,
+        output_tokens: u32,
+    ) -> f64 {
+        self.get_pricing_at(model_id, at)
             .await
             .map(|p| p.calculate_cost(input_tokens, output_tokens))
             .unwrap_or(0.0)
     }
 
     /// Sync pricing data from LiteLLM upstream
+    ///
+    /// The outcome is recorded in the metadata either way.
     pub async fn sync_from_upstream(&self) -> Result<SyncResult, PricingError> {
+        let now = now_secs();
+        let result = self.fetch_upstream(now).await;
+
+        let mut metadata = self.metadata.write().await;
+        metadata.last_attempt_at = Some(now);
+        metadata.last_sync_error = result.as_ref().err().map(|e| e.to_string());
+        result
+    }
+
+    /// Sync pricing data from LiteLLM (alias for sync_from_upstream)
+    /// Returns the number of models synced
+    pub async fn sync_from_litellm(&self) -> Result<usize, PricingError> {
+        let result = self.sync_from_upstream().await?;
+        Ok(result.total)
+    }
+
+    /// Keep pricing fresh: sync whenever the last successful sync is older
+    /// than `interval`, retrying failures with backoff
+    ///
+    /// Runs until the future is dropped, so callers select it against
+    /// their shutdown signal.
+    pub async fn run_auto_sync(&self, interval: Duration) {
+        let interval = interval.max(Duration::from_secs(60));
+        loop {
+            let age = self
+                .last_sync_time()
+                .await
+                .map(|last| now_secs().saturating_sub(last));
+            let wait = match age {
+                Some(age) if age < interval.as_secs() => {
+                    Duration::from_secs(interval.as_secs() - age)
+                }
+                _ => match self.sync_with_retries().await {
+                    Ok(_) => interval,
+                    Err(e) => {
+                        // Cached and builtin prices keep working meanwhile
+                        tracing::info!(
+                            "Keeping current model pricing (LiteLLM sync unavailable: {})",
+                            e
+                        );
+                        interval.min(SYNC_FAILURE_BACKOFF)
+                    }
+                },
+            };
+            tokio::time::sleep(wait).await;
+        }
+    }
+
+    /// Get the timestamp of the last successful sync
+    pub async fn last_sync_time(&self) -> Option<u64> {
+        let meta = self.metadata.read().await;
+        meta.last_sync_at
+    }
+
+    /// Add a custom pricing override
+    ///
+    /// Replaces an existing override for the same model and effective date.
+    pub async fn add_custom_override(
+        &self,
+        override_data: CustomPricingOverride,
+    ) -> Result<(), PricingError> {
+        override_data.validate()?;
+        Self::insert_override(&mut *self.overrides.write().await, override_data);
+        self.update_metadata().await;
+        Ok(())
+    }
+
+    /// Remove custom pricing overrides of a model: the one starting at
+    /// `effective_from`, or all of them when `None`
+    ///
+    /// Returns how many were removed; the model falls back to its
+    /// upstream or builtin pricing once none are left.
+    pub async fn remove_custom_override(
+        &self,
+        model_id: &str,
+        effective_from: Option<u64>,
+    ) -> usize {
+        let removed = {
+            let mut overrides = self.overrides.write().await;
+            let Some(list) = overrides.get_mut(model_id) else {
+                return 0;
+            };
+            let before = list.len();
+            if let Some(start) = effective_from {
+                list.retain(|o| o.starts_at() != start);
+            } else {
+                list.clear();
+            }
+            let removed = before - list.len();
+            if list.is_empty() {
+                overrides.remove(model_id);
+            }
+            removed
+        };
+        self.update_metadata().await;
+        removed
+    }
+
+    /// All custom overrides, by model and then effective date
+    pub async fn list_custom_overrides(&self) -> Vec<CustomPricingOverride> {
+        let overrides = self.overrides.read().await;
+        let mut entries: Vec<_> = overrides.values().flatten().cloned().collect();
+        entries.sort_by(|a, b| {
+            a.model_id
+                .cmp(&b.model_id)
+                .then(a.starts_at().cmp(&b.starts_at()))
+        });
+        entries
+    }
+
+    /// Recorded upstream price changes of a model, oldest first
+    pub async fn price_history(&self, model_id: &str) -> Vec<PricePoint> {
+        self.history
+            .read()
+            .await
+            .get(model_id)
+            .cloned()
+            .unwrap_or_default()
+    }
+
+    /// Save custom pricing overrides to file
+    pub async fn save_custom_overrides(&self) -> Result<(), PricingError> {
+        let custom_entries = self.list_custom_overrides().await;
+
+        // Save to TOML file
+        let custom_file = self.data_dir.join("custom_pricing.toml");
+
+        #[derive(serde::Serialize)]
+        struct CustomPricingFile {
+            overrides: Vec<CustomPricingOverride>,
+        }
+
+        let content = toml::to_string_pretty(&CustomPricingFile {
+            overrides: custom_entries,
+        })
+        .map_err(|e| PricingError::Parse(format!("Failed to serialize: {}", e)))?;
+
+        tokio::fs::create_dir_all(&self.data_dir)
+            .await
+            .map_err(|e| PricingError::Io(e.to_string()))?;
+        std::fs::write(&custom_file, content).map_err(|e| {
+            PricingError::Io(format!("Failed to write {}: {}", custom_file.display(), e))
+        })?;
+
+        Ok(())
+    }
+
+    /// Get all available models, with the custom overrides in effect now
+    pub async fn list_models(&self) -> Vec<(String, ModelPricing)> {
+        let now = now_secs();
+        let models = self.models.read().await;
+        let overrides = self.overrides.read().await;
+
+        let mut listed: HashMap<String, ModelPricing> = models.clone();
+        for (model_id, list) in overrides.iter() {
+            if let Some(custom) = list.iter().rev().find(|o| o.starts_at() <= now) {
+                listed.insert(model_id.clone(), custom.to_pricing(models.get(model_id)));
+            }
+        }
+        listed.into_iter().collect()
+    }
+
+    /// Get models for a specific provider
+    pub async fn list_models_by_provider(&self, provider: &str) -> Vec<(String, ModelPricing)> {
+        self.list_models()
+            .await
+            .into_iter()
+            .filter(|(_, v)| {
+                v.litellm_provider
+                    .as_deref()
+                    .map(|p| p.eq_ignore_ascii_case(provider))
+                    .unwrap_or(false)
+            })
+            .collect()
+    }
+
+    /// Get registry metadata
+    pub async fn metadata(&self) -> PricingRegistryMetadata {
+        self.metadata.read().await.clone()
+    }
+
+    // ============ Private methods ============
+
+    /// Fetch the LiteLLM JSON, apply it and cache it with its history
+    async fn fetch_upstream(&self, now: u64) -> Result<SyncResult, PricingError> {
         tracing::info!("Syncing pricing from LiteLLM...");
 
         let client = reqwest::Client::builder()
@@ -297,54 +611,36 @@ impl ModelPricingRegistry {
             )));
         }
 
-        let json: HashMap<String, serde_json::Value> = response
-            .json()
+        let version = response
+            .headers()
+            .get(reqwest::header::ETAG)
+            .and_then(|v| v.to_str().ok())
+            .map(|s| s.to_string());
+        let body = response
+            .text()
             .await
-            .map_err(|e| PricingError::Parse(e.to_string()))?;
-
-        let mut added = 0;
-        let mut updated = 0;
-
-        let mut models = self.models.write().await;
+            .map_err(|e| PricingError::Network(e.to_string()))?;
+        let json: HashMap<String, serde_json::Value> =
+            serde_json::from_str(&body).map_err(|e| PricingError::Parse(e.to_string()))?;
 
-        for (model_id, value) in json {
-            // Skip sample_spec and other non-model entries
-            if model_id == "sample_spec" || model_id.starts_with("_") {
-                continue;
-            }
+        let (added, updated) = self.apply_upstream(json, Some(now)).await;
 
-            // Parse the pricing data
-            if let Ok(mut pricing) = Self::parse_litellm_model(&value) {
-                pricing.priority = PricingPriority::Upstream;
-                pricing.source = Some("LiteLLM".to_string());
-
-                // Only update if we don't have a custom override
-                if let Some(existing) = models.get(&model_id) {
-                    if existing.priority < PricingPriority::Custom {
-                        models.insert(model_id, pricing);
-                        updated += 1;
-                    }
-                } else {
-                    models.insert(model_id, pricing);
-                    added += 1;
-                }
-            }
+        {
+            let mut metadata = self.metadata.write().await;
+            metadata.last_sync_at = Some(now);
+            metadata.last_sync_version = version;
         }
 
-        drop(models);
-
         // Cache the result
-        self.save_cached_upstream().await?;
+        self.save_cached_upstream(&body).await?;
+        self.save_history().await?;
         self.update_metadata().await;
 
         let result = SyncResult {
             added,
             updated,
             total: added + updated,
-            timestamp: std::time::SystemTime::now()
-                .duration_since(std::time::UNIX_EPOCH)
-                .unwrap_or_default()
-                .as_secs(),
+            timestamp: now,
         };
 
         tracing::info!(
@@ -356,113 +652,135 @@ impl ModelPricingRegistry {
         Ok(result)
     }
 
-    /// Sync pricing data from LiteLLM (alias for sync_from_upstream)
-    /// Returns the number of models synced
-    pub async fn sync_from_litellm(&self) -> Result<usize, PricingError> {
-        let result = self.sync_from_upstream().await?;
-        Ok(result.total)
+    /// Sync, retrying failed attempts with increasing delays
+    async fn sync_with_retries(&self) -> Result<SyncResult, PricingError> {
+        let mut attempt = 1;
+        loop {
+            match self.sync_from_upstream().await {
+                Ok(result) => return Ok(result),
+                Err(e) if attempt < SYNC_ATTEMPTS => {
+                    tracing::debug!(
+                        "LiteLLM pricing sync attempt {} failed: {}, retrying...",
+                        attempt,
+                        e
+                    );
+                    tokio::time::sleep(Duration::from_secs(5 * attempt)).await;
+                    attempt += 1;
+                }
+                Err(e) => return Err(e),
+            }
+        }
     }
 
-    /// Get the timestamp of the last successful sync
-    pub async fn last_sync_time(&self) -> Option<u64> {
-        let meta = self.metadata.read().await;
-        meta.last_sync_at
-    }
+    /// Merge LiteLLM entries into the registry; returns (added, updated)
+    ///
+    /// With `changed_at`, a price that differs from the one it replaces is
+    /// recorded in the history at that time (preceded by the old price when
+    /// the model had no history yet).
+    async fn apply_upstream(
+        &self,
+        json: HashMap<String, serde_json::Value>,
+        changed_at: Option<u64>,
+    ) -> (usize, usize) {
+        let mut added = 0;
+        let mut updated = 0;
 
-    /// Add a custom pricing override
-    pub async fn add_custom_override(&self, override_data: CustomPricingOverride) {
         let mut models = self.models.write().await;
+        let mut history = self.history.write().await;
 
-        let pricing = ModelPricing {
-            input_cost_per_token: override_data.input_cost_per_token,
-            output_cost_per_token: override_data.output_cost_per_token,
-            max_tokens: override_data.max_tokens,
-            litellm_provider: override_data.litellm_provider,
-            source: override_data.source.or(Some("Custom".to_string())),
-            priority: PricingPriority::Custom,
-            ..Default::default()
-        };
-
-        models.insert(override_data.model_id, pricing);
-    }
+        for (model_id, value) in json {
+            // Skip sample_spec and other non-model entries
+            if model_id == "sample_spec" || model_id.starts_with("_") {
+                continue;
+            }
 
-    /// Remove a custom pricing override
-    pub async fn remove_custom_override(&self, model_id: &str) {
-        let mut models = self.models.write().await;
-        // Only remove if it's a custom entry
-        if let Some(pricing) = models.get(model_id) {
-            if pricing.source.as_deref() == Some("Custom") || 
-               pricing.source.as_deref() == Some("custom") {
-                models.remove(model_id);
+            // Parse the pricing data
+            let Ok(mut pricing) = Self::parse_litellm_model(&value) else {
+                continue;
+            };
+            pricing.priority = PricingPriority::Upstream;
+            pricing.source = Some("LiteLLM".to_string());
+
+            match models.get(&model_id) {
+                Some(existing) => {
+                    if let Some(at) = changed_at {
+                        if existing.input_cost_per_token != pricing.input_cost_per_token
+                            || existing.output_cost_per_token != pricing.output_cost_per_token
+                        {
+                            let points = history.entry(model_id.clone()).or_default();
+                            if points.is_empty() {
+                                points.push(PricePoint::new(0, existing));
+                            }
+                            points.push(PricePoint::new(at, &pricing));
+                        }
+                    }
+                    updated += 1;
+                }
+                None => added += 1,
             }
+            models.insert(model_id, pricing);
         }
-    }
 
-    /// Save custom pricing overrides to file
-    pub async fn save_custom_overrides(&self) -> Result<(), PricingError> {
-        let models = self.models.read().await;
-        
-        // Collect all custom entries
-        let custom_entries: Vec<CustomPricingOverride> = models
-            .iter()
-            .filter(|(_, p)| p.source.as_deref() == Some("Custom") || 
-                            p.source.as_deref() == Some("custom"))
-            .map(|(model_id, pricing)| CustomPricingOverride {
-                model_id: model_id.clone(),
-                input_cost_per_token: pricing.input_cost_per_token,
-                output_cost_per_token: pricing.output_cost_per_token,
-                max_tokens: pricing.max_tokens,
-                litellm_provider: pricing.litellm_provider.clone(),
-                source: pricing.source.clone(),
-            })
-            .collect();
-        
-        drop(models); // Release lock before file I/O
-        
-        // Save to TOML file
-        let custom_file = self.data_dir.join("custom_pricing.toml");
-        
-        #[derive(serde::Serialize)]
-        struct CustomPricingFile {
-            overrides: Vec<CustomPricingOverride>,
-        }
-        
-        let content = toml::to_string_pretty(&CustomPricingFile { overrides: custom_entries })
-            .map_err(|e| PricingError::Parse(format!("Failed to serialize: {}", e)))?;
-        
-        std::fs::write(&custom_file, content)
-            .map_err(|e| PricingError::Io(format!("Failed to write {}: {}", custom_file.display(), e)))?;
-        
-        Ok(())
+        (added, updated)
     }
 
-    /// Get all available models
-    pub async fn list_models(&self) -> Vec<(String, ModelPricing)> {
-        let models = self.models.read().await;
-        models.iter().map(|(k, v)| (k.clone(), v.clone())).collect()
+    /// Insert keeping the model's overrides ordered by effective date
+    fn insert_override(
+        overrides: &mut HashMap<String, Vec<CustomPricingOverride>>,
+        override_data: CustomPricingOverride,
+    ) {
+        let list = overrides.entry(override_data.model_id.clone()).or_default();
+        list.retain(|o| o.starts_at() != override_data.starts_at());
+        let index = list.partition_point(|o| o.starts_at() < override_data.starts_at());
+        list.insert(index, override_data);
     }
 
-    /// Get models for a specific provider
-    pub async fn list_models_by_provider(&self, provider: &str) -> Vec<(String, ModelPricing)> {
-        let models = self.models.read().await;
-        models
-            .iter()
-            .filter(|(_, v)| {
-                v.litellm_provider
-                    .as_deref()
-                    .map(|p| p.eq_ignore_ascii_case(provider))
-                    .unwrap_or(false)
-            })
-            .map(|(k, v)| (k.clone(), v.clone()))
-            .collect()
-    }
+    /// Find the registry key a model name prices as: exact, normalized,
+    /// longest known prefix, then without a provider prefix
+    fn resolve_model_key(
+        models: &HashMap<String, ModelPricing>,
+        overrides: &HashMap<String, Vec<CustomPricingOverride>>,
+        model_id: &str,
+    ) -> Option<String> {
+        if model_id.is_empty() {
+            return None;
+        }
+        let known = |key: &str| models.contains_key(key) || overrides.contains_key(key);
 
-    /// Get registry metadata
-    pub async fn metadata(&self) -> PricingRegistryMetadata {
-        self.metadata.read().await.clone()
-    }
+        if known(model_id) {
+            return Some(model_id.to_string());
+        }
 
-    // ============ Private methods ============
+        // Try normalized model name (lowercase, no version)
+        let normalized = Self::normalize_model_name(model_id);
+        if known(&normalized) {
+            return Some(normalized);
+        }
+
+        // Try prefix matching for versioned models; picking the longest
+        // (then shortest extension) keeps the result stable across calls
+        let keys = || models.keys().chain(overrides.keys());
+        let prefix = keys()
+            .filter(|k| model_id.starts_with(k.as_str()))
+            .max_by(|a, b| a.len().cmp(&b.len()).then_with(|| b.cmp(a)));
+        let extension = || {
+            keys()
+                .filter(|k| k.starts_with(model_id))
+                .min_by(|a, b| a.len().cmp(&b.len()).then_with(|| a.cmp(b)))
+        };
+        if let Some(key) = prefix.or_else(extension) {
+            return Some(key.clone());
+        }
+
+        // Try provider-prefixed matching (e.g., "openai/gpt-4o" -> "gpt-4o")
+        if let Some(stripped) = model_id.split('/').next_back() {
+            if known(stripped) {
+                return Some(stripped.to_string());
+            }
+        }
+
+        None
+    }
 
     /// Load built-in pricing data
     async fn load_builtins(&self) {
@@ -857,7 +1175,8 @@ impl ModelPricingRegistry {
 
     /// Load cached upstream data from disk
     async fn load_cached_upstream(&self) -> Result<(), PricingError> {
-        let cache_path = self.data_dir.join("models/upstream/litellm_models.json");
+        let cache_dir = self.data_dir.join("models/upstream");
+        let cache_path = cache_dir.join("litellm_models.json");
 
         if !cache_path.exists() {
             return Err(PricingError::NotFound(
@@ -872,57 +1191,82 @@ impl ModelPricingRegistry {
         let json: HashMap<String, serde_json::Value> =
             serde_json::from_str(&content).map_err(|e| PricingError::Parse(e.to_string()))?;
 
-        let mut models = self.models.write().await;
-
-        for (model_id, value) in json {
-            if model_id == "sample_spec" || model_id.starts_with("_") {
-                continue;
-            }
+        // Same data as the last sync, so there are no price changes to record
+        self.apply_upstream(json, None).await;
 
-            if let Ok(mut pricing) = Self::parse_litellm_model(&value) {
-                pricing.priority = PricingPriority::Upstream;
-
-                // Only update if we don't have a higher priority entry
-                if let Some(existing) = models.get(&model_id) {
-                    if existing.priority <= pricing.priority {
-                        models.insert(model_id, pricing);
-                    }
-                } else {
-                    models.insert(model_id, pricing);
-                }
-            }
+        if let Ok(stamp) = tokio::fs::read_to_string(cache_dir.join("_metadata.toml")).await {
+            let stamp: UpstreamSyncStamp =
+                toml::from_str(&stamp).map_err(|e| PricingError::Parse(e.to_string()))?;
+            let mut metadata = self.metadata.write().await;
+            metadata.last_sync_at = stamp.last_sync_at;
+            metadata.last_sync_version = stamp.last_sync_version;
         }
 
         Ok(())
     }
 
     /// Save upstream data to cache
-    async fn save_cached_upstream(&self) -> Result<(), PricingError> {
+    async fn save_cached_upstream(&self, body: &str) -> Result<(), PricingError> {
         let cache_dir = self.data_dir.join("models/upstream");
         tokio::fs::create_dir_all(&cache_dir)
             .await
             .map_err(|e| PricingError::Io(e.to_string()))?;
 
-        // We save the full registry state (we'd need to track upstream separately for proper caching)
-        // For now, this is just metadata update
-        let metadata_path = cache_dir.join("_metadata.toml");
-        let metadata = format!(
-            "last_sync_at = {}\n",
-            std::time::SystemTime::now()
-                .duration_since(std::time::UNIX_EPOCH)
-                .unwrap_or_default()
-                .as_secs()
-        );
+        tokio::fs::write(cache_dir.join("litellm_models.json"), body)
+            .await
+            .map_err(|e| PricingError::Io(e.to_string()))?;
 
-        tokio::fs::write(&metadata_path, metadata)
+        let stamp = {
+            let metadata = self.metadata.read().await;
+            UpstreamSyncStamp {
+                last_sync_at: metadata.last_sync_at,
+                last_sync_version: metadata.last_sync_version.clone(),
+            }
+        };
+        let metadata = toml::to_string(&stamp).map_err(|e| PricingError::Parse(e.to_string()))?;
+        tokio::fs::write(cache_dir.join("_metadata.toml"), metadata)
             .await
             .map_err(|e| PricingError::Io(e.to_string()))?;
 
         Ok(())
     }
 
-    /// Load custom TOML overrides
+    /// Load recorded upstream price changes
+    async fn load_history(&self) -> Result<(), PricingError> {
+        let path = self.data_dir.join("models/history.json");
+        let content = tokio::fs::read_to_string(&path)
+            .await
+            .map_err(|e| PricingError::Io(e.to_string()))?;
+        let history: HashMap<String, Vec<PricePoint>> =
+            serde_json::from_str(&content).map_err(|e| PricingError::Parse(e.to_string()))?;
+        *self.history.write().await = history;
+        Ok(())
+    }
+
+    /// Persist recorded upstream price changes
+    async fn save_history(&self) -> Result<(), PricingError> {
+        let dir = self.data_dir.join("models");
+        tokio::fs::create_dir_all(&dir)
+            .await
+            .map_err(|e| PricingError::Io(e.to_string()))?;
+        let content = serde_json::to_string(&*self.history.read().await)
+            .map_err(|e| PricingError::Parse(e.to_string()))?;
+        tokio::fs::write(dir.join("history.json"), content)
+            .await
+            .map_err(|e| PricingError::Io(e.to_string()))?;
+        Ok(())
+    }
+
+    /// Load custom TOML overrides: those saved by `save_custom_overrides`,
+    /// then any files in `models/custom`
     async fn load_custom_overrides(&self) -> Result<(), PricingError> {
+        let saved_file = self.data_dir.join("custom_pricing.toml");
+        if saved_file.exists() {
+            if let Err(e) = self.load_saved_overrides(&saved_file).await {
+                tracing::warn!("Failed to load custom pricing {:?}: {}", saved_file, e);
+            }
+        }
+
         let custom_dir = self.data_dir.join("models/custom");
 
         if !custom_dir.exists() {
@@ -949,6 +1293,29 @@ impl ModelPricingRegistry {
         Ok(())
     }
 
+    /// Load the `overrides = [...]` file written by `save_custom_overrides`
+    async fn load_saved_overrides(&self, path: &Path) -> Result<(), PricingError> {
+        let content = tokio::fs::read_to_string(path)
+            .await
+            .map_err(|e| PricingError::Io(e.to_string()))?;
+
+        #[derive(Deserialize)]
+        struct CustomPricingFile {
+            #[serde(default)]
+            overrides: Vec<CustomPricingOverride>,
+        }
+
+        let file: CustomPricingFile =
+            toml::from_str(&content).map_err(|e| PricingError::Parse(e.to_string()))?;
+
+        let mut overrides = self.overrides.write().await;
+        for override_data in file.overrides {
+            Self::insert_override(&mut overrides, override_data);
+        }
+
+        Ok(())
+    }
+
     /// Load a single custom override TOML file
     async fn load_custom_override_file(&self, path: &Path) -> Result<(), PricingError> {
         let content = tokio::fs::read_to_string(path)
@@ -970,15 +1337,18 @@ impl ModelPricingRegistry {
             litellm_provider: Option<String>,
             #[serde(default)]
             source: Option<String>,
+            #[serde(default)]
+            effective_from: Option<u64>,
         }
 
         let file: CustomOverrideFile =
             toml::from_str(&content).map_err(|e| PricingError::Parse(e.to_string()))?;
 
-        let mut models = self.models.write().await;
+        let mut overrides = self.overrides.write().await;
 
         for (model_id, override_data) in file.models {
-            let pricing = ModelPricing {
+            let override_data = CustomPricingOverride {
+                model_id,
                 input_cost_per_token: override_data.input_cost_per_token,
                 output_cost_per_token: override_data.output_cost_per_token,
                 max_tokens: override_data.max_tokens,
@@ -986,10 +1356,9 @@ impl ModelPricingRegistry {
                 source: override_data
                     .source
                     .or(Some(format!("Custom: {:?}", path.file_name()))),
-                priority: PricingPriority::Custom,
-                ..Default::default()
+                effective_from: override_data.effective_from,
             };
-            models.insert(model_id, pricing);
+            Self::insert_override(&mut overrides, override_data);
         }
 
         Ok(())
@@ -998,17 +1367,19 @@ impl ModelPricingRegistry {
     /// Update registry metadata
     async fn update_metadata(&self) {
         let models = self.models.read().await;
+        let overrides = self.overrides.read().await;
         let mut metadata = self.metadata.write().await;
 
-        metadata.total_model_count = models.len();
+        metadata.total_model_count = models.len()
+            + overrides
+                .keys()
+                .filter(|k| !models.contains_key(k.as_str()))
+                .count();
         metadata.upstream_model_count = models
             .values()
             .filter(|p| p.priority == PricingPriority::Upstream)
             .count();
-        metadata.custom_override_count = models
-            .values()
-            .filter(|p| p.priority == PricingPriority::Custom)
-            .count();
+        metadata.custom_override_count = overrides.values().map(Vec::len).sum();
     }
 }
 
@@ -1115,4 +1486,139 @@ mod tests {
         assert!(PricingPriority::Custom > PricingPriority::Upstream);
         assert!(PricingPriority::Upstream > PricingPriority::Builtin);
     }
+
+    fn custom(model_id: &str, input: f64, effective_from: Option<u64>) -> CustomPricingOverride {
+        CustomPricingOverride {
+            model_id: model_id.to_string(),
+            input_cost_per_token: input,
+            output_cost_per_token: input * 2.0,
+            max_tokens: None,
+            litellm_provider: None,
+            source: Some("Custom".to_string()),
+            effective_from,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_effective_dated_overrides() {
+        let dir = tempfile::tempdir().unwrap();
+        let registry = ModelPricingRegistry::new(dir.path());
+        registry.initialize().await.unwrap();
+
+        registry
+            .add_custom_override(custom("gpt-4o", 1e-6, Some(1_000)))
+            .await
+            .unwrap();
+        registry
+            .add_custom_override(custom("gpt-4o", 2e-6, Some(2_000)))
+            .await
+            .unwrap();
+
+        let price_at = |at| {
+            let registry = registry.clone();
+            async move {
+                registry
+                    .get_pricing_at("gpt-4o-2024-08-06", at)
+                    .await
+                    .unwrap()
+                    .input_cost_per_token
+            }
+        };
+        assert_eq!(price_at(500).await, 2.5e-6);
+        assert_eq!(price_at(1_500).await, 1e-6);
+        assert_eq!(price_at(2_500).await, 2e-6);
+        // Base fields survive the override
+        let pricing = registry.get_pricing_at("gpt-4o", 1_500).await.unwrap();
+        assert_eq!(pricing.priority, PricingPriority::Custom);
+        assert_eq!(pricing.max_input_tokens, Some(128000));
+
+        assert_eq!(
+            registry.remove_custom_override("gpt-4o", Some(2_000)).await,
+            1
+        );
+        assert_eq!(price_at(2_500).await, 1e-6);
+        assert_eq!(registry.remove_custom_override("gpt-4o", None).await, 1);
+        assert_eq!(price_at(2_500).await, 2.5e-6);
+    }
+
+    #[tokio::test]
+    async fn test_overrides_survive_restart() {
+        let dir = tempfile::tempdir().unwrap();
+        let registry = ModelPricingRegistry::new(dir.path());
+        registry.initialize().await.unwrap();
+        registry
+            .add_custom_override(custom("my-model", 1e-6, None))
+            .await
+            .unwrap();
+        registry
+            .add_custom_override(custom("my-model", 3e-6, Some(5_000)))
+            .await
+            .unwrap();
+        registry.save_custom_overrides().await.unwrap();
+
+        let reloaded = ModelPricingRegistry::new(dir.path());
+        reloaded.initialize().await.unwrap();
+        let overrides = reloaded.list_custom_overrides().await;
+        assert_eq!(overrides.len(), 2);
+        assert_eq!(overrides[1].effective_from, Some(5_000));
+        assert_eq!(
+            reloaded.calculate_cost_at("my-model", 10, 1_000, 0).await,
+            1e-3
+        );
+        assert_eq!(reloaded.metadata().await.custom_override_count, 2);
+    }
+
+    #[tokio::test]
+    async fn test_upstream_price_changes_are_recorded() {
+        let registry = ModelPricingRegistry::new("/tmp/test_pricing");
+        registry.load_builtins().await;
+
+        let upstream = |input: f64| {
+            let mut json = HashMap::new();
+            json.insert(
+                "gpt-4o".to_string(),
+                serde_json::json!({ "input_cost_per_token": input, "output_cost_per_token": 1e-5 }),
+            );
+            json
+        };
+        // Same price as the builtin: nothing to record
+        registry.apply_upstream(upstream(2.5e-6), Some(100)).await;
+        assert!(registry.price_history("gpt-4o").await.is_empty());
+
+        registry.apply_upstream(upstream(2e-6), Some(200)).await;
+        let history = registry.price_history("gpt-4o").await;
+        assert_eq!(history.len(), 2);
+        assert_eq!(history[1].effective_from, 200);
+
+        let input_at = |at| {
+            let registry = registry.clone();
+            async move {
+                registry
+                    .get_pricing_at("gpt-4o", at)
+                    .await
+                    .unwrap()
+                    .input_cost_per_token
+            }
+        };
+        assert_eq!(input_at(150).await, 2.5e-6);
+        assert_eq!(input_at(250).await, 2e-6);
+    }
+
+    #[tokio::test]
+    async fn test_prefix_match_is_longest() {
+        let registry = ModelPricingRegistry::new("/tmp/test_pricing");
+        registry.load_builtins().await;
+        let pricing = registry.get_pricing("gpt-4o-mini-preview").await.unwrap();
+        assert_eq!(pricing.input_cost_per_token, 0.15e-6);
+    }
+
+    #[tokio::test]
+    async fn test_invalid_override_rejected() {
+        let registry = ModelPricingRegistry::new("/tmp/test_pricing");
+        let err = registry
+            .add_custom_override(custom("gpt-4o", -1.0, None))
+            .await;
+        assert!(matches!(err, Err(PricingError::Invalid(_))));
+        assert!(registry.list_custom_overrides().await.is_empty());
+    }
 }
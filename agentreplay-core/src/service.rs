@@ -0,0 +1,476 @@
+// Copyright 2025 AgentReplay (https://github.com/agentreplay)
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program. If not, see <https://www.gnu.org/licenses/>.
+
+//! Background service registration
+//!
+//! Registers `agentreplay-server` as a per-user service so traces are
+//! ingested even when the desktop app isn't open:
+//!
+//! - macOS: a launchd agent in `~/Library/LaunchAgents`
+//! - Linux: a systemd user unit in `~/.config/systemd/user`
+//! - Windows: a Task Scheduler task that runs at logon
+//!
+//! None of these need administrator rights. Used by `agentreplay service`
+//! and the desktop app's settings.
+
+use serde::{Deserialize, Serialize};
+use std::io;
+use std::path::PathBuf;
+use std::process::{Command, Output};
+
+/// launchd job label
+pub const LAUNCHD_LABEL: &str = "dev.agentreplay.server";
+
+/// systemd user unit name
+pub const SYSTEMD_UNIT: &str = "agentreplay.service";
+
+/// Task Scheduler task name
+pub const WINDOWS_TASK_NAME: &str = "Agentreplay Server";
+
+/// Name of the server binary the service runs
+pub const SERVER_BINARY: &str = "agentreplay-server";
+
+/// Service manager of the current platform
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ServiceManager {
+    Launchd,
+    Systemd,
+    TaskScheduler,
+}
+
+impl ServiceManager {
+    /// The service manager used on this platform, if supported
+    pub fn current() -> Option<Self> {
+        if cfg!(target_os = "macos") {
+            Some(ServiceManager::Launchd)
+        } else if cfg!(target_os = "linux") {
+            Some(ServiceManager::Systemd)
+        } else if cfg!(windows) {
+            Some(ServiceManager::TaskScheduler)
+        } else {
+            None
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            ServiceManager::Launchd => "launchd",
+            ServiceManager::Systemd => "systemd",
+            ServiceManager::TaskScheduler => "Task Scheduler",
+        }
+    }
+
+    /// File the service is defined in: the launchd plist, the systemd unit,
+    /// or the launcher script the scheduled task runs
+    pub fn definition_path(self) -> io::Result<PathBuf> {
+        Ok(match self {
+            ServiceManager::Launchd => home_dir()?
+                .join("Library/LaunchAgents")
+                .join(format!("{}.plist", LAUNCHD_LABEL)),
+            ServiceManager::Systemd => {
+                let config = match std::env::var_os("XDG_CONFIG_HOME") {
+                    Some(dir) if !dir.is_empty() => PathBuf::from(dir),
+                    _ => home_dir()?.join(".config"),
+                };
+                config.join("systemd/user").join(SYSTEMD_UNIT)
+            }
+            ServiceManager::TaskScheduler => {
+                let local = match std::env::var_os("LOCALAPPDATA") {
+                    Some(dir) => PathBuf::from(dir),
+                    None => home_dir()?.join("AppData").join("Local"),
+                };
+                local.join("Agentreplay").join("agentreplay-service.cmd")
+            }
+        })
+    }
+}
+
+/// What the service runs
+#[derive(Debug, Clone)]
+pub struct ServiceSpec {
+    /// Absolute path of the `agentreplay-server` binary
+    pub server_bin: PathBuf,
+    /// Absolute path of the data directory
+    pub data_dir: PathBuf,
+    /// HTTP listen address, e.g. `127.0.0.1:47100`
+    pub http_addr: String,
+    /// Server configuration file (TOML)
+    pub config: Option<PathBuf>,
+}
+
+impl ServiceSpec {
+    /// Server output is appended here
+    pub fn log_path(&self) -> PathBuf {
+        self.data_dir.join("logs").join("server.log")
+    }
+
+    /// Full command line, binary first
+    fn command_line(&self) -> Vec<String> {
+        let mut args = vec![
+            self.server_bin.display().to_string(),
+            "--data-dir".to_string(),
+            self.data_dir.display().to_string(),
+            "--http-addr".to_string(),
+            self.http_addr.clone(),
+        ];
+        if let Some(config) = &self.config {
+            args.push("--config".to_string());
+            args.push(config.display().to_string());
+        }
+        args
+    }
+}
+
+/// Current state of the service
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ServiceStatus {
+    pub manager: ServiceManager,
+    pub installed: bool,
+    pub running: bool,
+    /// See [`ServiceManager::definition_path`]
+    pub definition: PathBuf,
+}
+
+/// Register the service, replacing any previous registration
+///
+/// With `start` the server is started right away, otherwise at next login.
+pub fn install(spec: &ServiceSpec, start: bool) -> io::Result<ServiceStatus> {
+    let manager = current_manager()?;
+    let definition = manager.definition_path()?;
+    if let Some(dir) = spec.log_path().parent() {
+        std::fs::create_dir_all(dir)?;
+    }
+    if let Some(dir) = definition.parent() {
+        std::fs::create_dir_all(dir)?;
+    }
+
+    match manager {
+        ServiceManager::Launchd => {
+            // Unload first so launchd picks up the new definition
+            let _ = launchctl(&["unload", &definition.display().to_string()]);
+            std::fs::write(&definition, render_launchd_plist(spec))?;
+            if start {
+                run(launchctl(&[
+                    "load",
+                    "-w",
+                    &definition.display().to_string(),
+                ]))?;
+            }
+        }
+        ServiceManager::Systemd => {
+            std::fs::write(&definition, render_systemd_unit(spec))?;
+            run(systemctl(&["daemon-reload"]))?;
+            run(systemctl(&["enable", SYSTEMD_UNIT]))?;
+            if start {
+                // Restart so a running server picks up the new definition
+                run(systemctl(&["restart", SYSTEMD_UNIT]))?;
+            }
+        }
+        ServiceManager::TaskScheduler => {
+            std::fs::write(&definition, render_windows_launcher(spec))?;
+            let launcher = format!("\"{}\"", definition.display());
+            run(schtasks(&[
+                "/Create",
+                "/F",
+                "/TN",
+                WINDOWS_TASK_NAME,
+                "/SC",
+                "ONLOGON",
+                "/RL",
+                "LIMITED",
+                "/TR",
+                &launcher,
+            ]))?;
+            if start {
+                run(schtasks(&["/Run", "/TN", WINDOWS_TASK_NAME]))?;
+            }
+        }
+    }
+
+    status()
+}
+
+/// Stop and remove the service; returns false if it wasn't installed
+pub fn uninstall() -> io::Result<bool> {
+    let manager = current_manager()?;
+    let definition = manager.definition_path()?;
+    let installed = status()?.installed;
+
+    match manager {
+        ServiceManager::Launchd => {
+            if definition.exists() {
+                let _ = launchctl(&["unload", "-w", &definition.display().to_string()]);
+            }
+        }
+        ServiceManager::Systemd => {
+            if definition.exists() {
+                let _ = systemctl(&["disable", "--now", SYSTEMD_UNIT]);
+            }
+        }
+        ServiceManager::TaskScheduler => {
+            if installed {
+                let _ = schtasks(&["/End", "/TN", WINDOWS_TASK_NAME]);
+                run(schtasks(&["/Delete", "/F", "/TN", WINDOWS_TASK_NAME]))?;
+            }
+        }
+    }
+
+    if definition.exists() {
+        std::fs::remove_file(&definition)?;
+    }
+    if manager == ServiceManager::Systemd && installed {
+        run(systemctl(&["daemon-reload"]))?;
+    }
+    Ok(installed)
+}
+
+/// Whether the service is installed and running
+pub fn status() -> io::Result<ServiceStatus> {
+    let manager = current_manager()?;
+    let definition = manager.definition_path()?;
+
+    let (installed, running) = match manager {
+        ServiceManager::Launchd => {
+            let running = launchctl(&["list", LAUNCHD_LABEL])
+                .map(|out| {
+                    out.status.success() && String::from_utf8_lossy(&out.stdout).contains("\"PID\"")
+                })
+                .unwrap_or(false);
+            (definition.exists(), running)
+        }
+        ServiceManager::Systemd => {
+            let running = systemctl(&["is-active", "--quiet", SYSTEMD_UNIT])
+                .map(|out| out.status.success())
+                .unwrap_or(false);
+            (definition.exists(), running)
+        }
+        ServiceManager::TaskScheduler => {
+            match schtasks(&["/Query", "/TN", WINDOWS_TASK_NAME, "/FO", "LIST"]) {
+                Ok(out) if out.status.success() => (
+                    true,
+                    String::from_utf8_lossy(&out.stdout).contains("Running"),
+                ),
+                _ => (false, false),
+            }
+        }
+    };
+
+    Ok(ServiceStatus {
+        manager,
+        installed,
+        running,
+        definition,
+    })
+}
+
+/// `agentreplay-server` next to the running executable, else on `PATH`
+pub fn find_server_binary() -> Option<PathBuf> {
+    let name = format!("{}{}", SERVER_BINARY, std::env::consts::EXE_SUFFIX);
+    let sibling = std::env::current_exe()
+        .ok()
+        .and_then(|exe| exe.parent().map(|dir| dir.join(&name)));
+    let on_path: Vec<PathBuf> = std::env::var_os("PATH")
+        .map(|path| {
+            std::env::split_paths(&path)
+                .map(|dir| dir.join(&name))
+                .collect()
+        })
+        .unwrap_or_default();
+    sibling
+        .into_iter()
+        .chain(on_path)
+        .find(|path| path.is_file())
+}
+
+/// launchd agent definition
+pub fn render_launchd_plist(spec: &ServiceSpec) -> String {
+    let args: String = spec
+        .command_line()
+        .iter()
+        .map(|arg| format!("        <string>{}</string>\n", xml_escape(arg)))
+        .collect();
+    let log = xml_escape(&spec.log_path().display().to_string());
+    format!(
+        r#"<?xml version="1.0" encoding="UTF-8"?>
+<!DOCTYPE plist PUBLIC "-//Apple//DTD PLIST 1.0//EN" "http://www.apple.com/DTDs/PropertyList-1.0.dtd">
+<plist version="1.0">
+<dict>
+    <key>Label</key>
+    <string>{label}</string>
+    <key>ProgramArguments</key>
+    <array>
+{args}    </array>
+    <key>RunAtLoad</key>
+    <true/>
+    <key>KeepAlive</key>
+    <dict>
+        <key>SuccessfulExit</key>
+        <false/>
+    </dict>
+    <key>ThrottleInterval</key>
+    <integer>30</integer>
+    <key>StandardOutPath</key>
+    <string>{log}</string>
+    <key>StandardErrorPath</key>
+    <string>{log}</string>
+</dict>
+</plist>
+"#,
+        label = LAUNCHD_LABEL,
+        args = args,
+        log = log,
+    )
+}
+
+/// systemd user unit
+pub fn render_systemd_unit(spec: &ServiceSpec) -> String {
+    let exec = spec
+        .command_line()
+        .iter()
+        .map(|arg| systemd_quote(arg))
+        .collect::<Vec<_>>()
+        .join(" ");
+    let log = spec.log_path().display().to_string().replace('%', "%%");
+    format!(
+        "[Unit]\n\
+         Description=Agentreplay ingestion server\n\
+         After=network.target\n\
+         \n\
+         [Service]\n\
+         ExecStart={exec}\n\
+         Restart=on-failure\n\
+         RestartSec=30\n\
+         StandardOutput=append:{log}\n\
+         StandardError=append:{log}\n\
+         \n\
+         [Install]\n\
+         WantedBy=default.target\n",
+        exec = exec,
+        log = log,
+    )
+}
+
+/// Batch script the scheduled task runs, so server output lands in the log
+pub fn render_windows_launcher(spec: &ServiceSpec) -> String {
+    let command = spec
+        .command_line()
+        .iter()
+        .map(|arg| format!("\"{}\"", arg.replace('%', "%%")))
+        .collect::<Vec<_>>()
+        .join(" ");
+    let log = spec.log_path().display().to_string().replace('%', "%%");
+    format!("@echo off\r\n{} >> \"{}\" 2>&1\r\n", command, log)
+}
+
+fn current_manager() -> io::Result<ServiceManager> {
+    ServiceManager::current().ok_or_else(|| {
+        io::Error::new(
+            io::ErrorKind::Unsupported,
+            "Background service is not supported on this platform",
+        )
+    })
+}
+
+fn home_dir() -> io::Result<PathBuf> {
+    std::env::var_os("HOME")
+        .or_else(|| std::env::var_os("USERPROFILE"))
+        .map(PathBuf::from)
+        .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, "Home directory not found"))
+}
+
+fn launchctl(args: &[&str]) -> io::Result<Output> {
+    Command::new("launchctl").args(args).output()
+}
+
+fn systemctl(args: &[&str]) -> io::Result<Output> {
+    Command::new("systemctl").arg("--user").args(args).output()
+}
+
+fn schtasks(args: &[&str]) -> io::Result<Output> {
+    Command::new("schtasks").args(args).output()
+}
+
+/// Turn a failed command into an error carrying its stderr
+fn run(output: io::Result<Output>) -> io::Result<()> {
+    let output = output?;
+    if output.status.success() {
+        return Ok(());
+    }
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    Err(io::Error::other(format!(
+        "Service manager command failed ({}): {}",
+        output.status,
+        stderr.trim()
+    )))
+}
+
+fn xml_escape(value: &str) -> String {
+    value
+        .replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+/// Quote one `ExecStart=` word; `%` and `$` would otherwise be expanded
+fn systemd_quote(value: &str) -> String {
+    let escaped = value
+        .replace('\\', "\\\\")
+        .replace('"', "\\\"")
+        .replace('%', "%%")
+        .replace('$', "$$");
+    format!("\"{}\"", escaped)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn spec() -> ServiceSpec {
+        ServiceSpec {
+            server_bin: PathBuf::from("/opt/agent replay/agentreplay-server"),
+            data_dir: PathBuf::from("/data/100%"),
+            http_addr: "127.0.0.1:47100".to_string(),
+            config: Some(PathBuf::from("/etc/a&b.toml")),
+        }
+    }
+
+    #[test]
+    fn test_launchd_plist() {
+        let plist = render_launchd_plist(&spec());
+        assert!(plist.contains("<string>dev.agentreplay.server</string>"));
+        assert!(plist.contains("<string>/opt/agent replay/agentreplay-server</string>"));
+        assert!(plist.contains("<string>/etc/a&amp;b.toml</string>"));
+        assert!(plist.contains("<string>/data/100%/logs/server.log</string>"));
+    }
+
+    #[test]
+    fn test_systemd_unit() {
+        let unit = render_systemd_unit(&spec());
+        assert!(unit.contains(
+            "ExecStart=\"/opt/agent replay/agentreplay-server\" \"--data-dir\" \"/data/100%%\""
+        ));
+        assert!(unit.contains("StandardOutput=append:/data/100%%/logs/server.log\n"));
+        assert!(unit.contains("WantedBy=default.target"));
+    }
+
+    #[test]
+    fn test_windows_launcher() {
+        let script = render_windows_launcher(&spec());
+        assert!(script.starts_with("@echo off\r\n\"/opt/agent replay/agentreplay-server\""));
+        assert!(script.ends_with(">> \"/data/100%%/logs/server.log\" 2>&1\r\n"));
+    }
+}
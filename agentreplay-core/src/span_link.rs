@@ -0,0 +1,120 @@
+// Copyright 2025 AgentReplay (https://github.com/agentreplay)
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program. If not, see <https://www.gnu.org/licenses/>.
+
+//! Span links
+//!
+//! An [`AgentFlowEdge`](crate::AgentFlowEdge) has a single `causal_parent`,
+//! which models call nesting. Links add further cause references on top of
+//! that: a synthesis step that merges the output of N parallel sub-agents
+//! keeps its orchestrator as parent and links to each sub-agent span.
+//!
+//! Links live outside the fixed-size edge. Edges that carry links have
+//! [`FLAG_HAS_LINKS`](crate::FLAG_HAS_LINKS) set so readers only look them
+//! up where they exist.
+//!
+//! # Key Encoding
+//!
+//! - Links of a span: `idx/links/{edge_id:032x}`
+//! - Reverse lookup: `idx/linked_from/{linked_span_id:032x}/{edge_id:032x}`
+
+use serde::{Deserialize, Serialize};
+
+/// Most links kept per span; further links are dropped at ingest
+pub const MAX_SPAN_LINKS: usize = 256;
+
+/// How the linking span relates to the linked one
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum LinkKind {
+    /// The linked span's output is merged into this span (fan-in)
+    #[default]
+    FanIn,
+    /// This span was triggered by the linked span but does not consume its
+    /// result, e.g. a queued job started by an earlier step
+    FollowsFrom,
+}
+
+impl LinkKind {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            LinkKind::FanIn => "fan_in",
+            LinkKind::FollowsFrom => "follows_from",
+        }
+    }
+}
+
+/// A cause reference from one span to another
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct SpanLink {
+    /// Edge ID of the linked span
+    pub span_id: u128,
+    #[serde(default)]
+    pub kind: LinkKind,
+}
+
+impl SpanLink {
+    pub fn new(span_id: u128, kind: LinkKind) -> Self {
+        Self { span_id, kind }
+    }
+}
+
+/// Drop self-links and duplicates and cap the list at [`MAX_SPAN_LINKS`]
+///
+/// Link order is kept, so the first reference to a span wins.
+pub fn normalize_links(edge_id: u128, links: Vec<SpanLink>) -> Vec<SpanLink> {
+    let mut seen = std::collections::HashSet::new();
+    links
+        .into_iter()
+        .filter(|link| link.span_id != 0 && link.span_id != edge_id)
+        .filter(|link| seen.insert(link.span_id))
+        .take(MAX_SPAN_LINKS)
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_normalize_links() {
+        let links = vec![
+            SpanLink::new(2, LinkKind::FanIn),
+            SpanLink::new(1, LinkKind::FanIn),
+            SpanLink::new(0, LinkKind::FanIn),
+            SpanLink::new(2, LinkKind::FollowsFrom),
+            SpanLink::new(3, LinkKind::FollowsFrom),
+        ];
+        let normalized = normalize_links(1, links);
+        assert_eq!(
+            normalized,
+            vec![
+                SpanLink::new(2, LinkKind::FanIn),
+                SpanLink::new(3, LinkKind::FollowsFrom),
+            ]
+        );
+
+        let many = (1..=1000).map(|id| SpanLink::new(id, LinkKind::FanIn));
+        assert_eq!(normalize_links(0, many.collect()).len(), MAX_SPAN_LINKS);
+    }
+
+    #[test]
+    fn test_link_kind_serde() {
+        let link: SpanLink = serde_json::from_str(r#"{"span_id": 7}"#).unwrap();
+        assert_eq!(link.kind, LinkKind::FanIn);
+
+        let json = serde_json::to_string(&SpanLink::new(7, LinkKind::FollowsFrom)).unwrap();
+        assert!(json.contains(r#""kind":"follows_from""#));
+    }
+}
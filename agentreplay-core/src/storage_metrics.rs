@@ -18,8 +18,10 @@
 //! Comprehensive metrics for LSM-tree storage engine operations,
 //! including write amplification, compaction efficiency, and I/O tracking.
 
+use serde::Serialize;
 use std::sync::atomic::{AtomicU64, Ordering};
 use std::sync::Arc;
+use std::time::Duration;
 
 /// Write amplification metrics for storage operations
 ///
@@ -237,7 +239,7 @@ impl WriteAmplificationMetrics {
 }
 
 /// Comprehensive write amplification report
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize)]
 pub struct WriteAmplificationReport {
     /// Overall write amplification (physical / logical)
     pub overall_wa: f64,
@@ -326,6 +328,259 @@ impl std::fmt::Display for WriteAmplificationReport {
     }
 }
 
+/// Number of power-of-two histogram buckets (covers values up to 2^39)
+const HISTOGRAM_BUCKETS: usize = 40;
+
+/// Lock-free histogram with power-of-two buckets
+///
+/// Bucket `i` counts values whose bit length is `i` (bucket 0 holds zeros),
+/// so percentiles are accurate to within 2x. That is enough to tell a 2ms
+/// fsync from a 200ms one without locking on the write path.
+#[derive(Debug)]
+struct Log2Histogram {
+    buckets: [AtomicU64; HISTOGRAM_BUCKETS],
+    count: AtomicU64,
+    sum: AtomicU64,
+    max: AtomicU64,
+}
+
+impl Log2Histogram {
+    fn new() -> Self {
+        Self {
+            buckets: std::array::from_fn(|_| AtomicU64::new(0)),
+            count: AtomicU64::new(0),
+            sum: AtomicU64::new(0),
+            max: AtomicU64::new(0),
+        }
+    }
+
+    fn bucket_index(value: u64) -> usize {
+        ((u64::BITS - value.leading_zeros()) as usize).min(HISTOGRAM_BUCKETS - 1)
+    }
+
+    fn record(&self, value: u64) {
+        self.buckets[Self::bucket_index(value)].fetch_add(1, Ordering::Relaxed);
+        self.count.fetch_add(1, Ordering::Relaxed);
+        self.sum.fetch_add(value, Ordering::Relaxed);
+        self.max.fetch_max(value, Ordering::Relaxed);
+    }
+
+    fn count(&self) -> u64 {
+        self.count.load(Ordering::Relaxed)
+    }
+
+    fn sum(&self) -> u64 {
+        self.sum.load(Ordering::Relaxed)
+    }
+
+    fn max(&self) -> u64 {
+        self.max.load(Ordering::Relaxed)
+    }
+
+    fn mean(&self) -> f64 {
+        let count = self.count();
+        if count == 0 {
+            0.0
+        } else {
+            self.sum() as f64 / count as f64
+        }
+    }
+
+    /// Upper bound of the bucket holding the `p`th percentile (`0.0..=1.0`)
+    ///
+    /// Capped at the largest recorded value, so the result never exceeds
+    /// what was actually observed.
+    fn percentile(&self, p: f64) -> u64 {
+        let count = self.count();
+        if count == 0 {
+            return 0;
+        }
+        let rank = ((count as f64 * p.clamp(0.0, 1.0)).ceil() as u64).max(1);
+        let max = self.max();
+        let mut seen = 0;
+        for (i, bucket) in self.buckets.iter().enumerate() {
+            seen += bucket.load(Ordering::Relaxed);
+            if seen >= rank {
+                let upper = match i {
+                    0 => 0,
+                    i if i == HISTOGRAM_BUCKETS - 1 => max,
+                    i => (1u64 << i) - 1,
+                };
+                return upper.min(max);
+            }
+        }
+        max
+    }
+
+    fn reset(&self) {
+        for bucket in self.buckets.iter() {
+            bucket.store(0, Ordering::Relaxed);
+        }
+        self.count.store(0, Ordering::Relaxed);
+        self.sum.store(0, Ordering::Relaxed);
+        self.max.store(0, Ordering::Relaxed);
+    }
+}
+
+/// Fsync (commit) latency tracking
+///
+/// Every durable commit pays for an fsync, so slow ingestion on a laptop
+/// disk or network volume usually shows up here first.
+#[derive(Debug, Clone)]
+pub struct FsyncMetrics {
+    /// Latencies in microseconds
+    latency_us: Arc<Log2Histogram>,
+}
+
+impl Default for FsyncMetrics {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl FsyncMetrics {
+    pub fn new() -> Self {
+        Self {
+            latency_us: Arc::new(Log2Histogram::new()),
+        }
+    }
+
+    /// Record how long one fsync took
+    pub fn record(&self, latency: Duration) {
+        self.latency_us
+            .record(u64::try_from(latency.as_micros()).unwrap_or(u64::MAX));
+    }
+
+    /// Number of fsyncs recorded
+    pub fn count(&self) -> u64 {
+        self.latency_us.count()
+    }
+
+    /// Generate latency report (percentiles are bucket upper bounds)
+    pub fn report(&self) -> FsyncLatencyReport {
+        let ms = |us: u64| us as f64 / 1000.0;
+        FsyncLatencyReport {
+            count: self.latency_us.count(),
+            mean_ms: self.latency_us.mean() / 1000.0,
+            p50_ms: ms(self.latency_us.percentile(0.50)),
+            p95_ms: ms(self.latency_us.percentile(0.95)),
+            p99_ms: ms(self.latency_us.percentile(0.99)),
+            max_ms: ms(self.latency_us.max()),
+        }
+    }
+
+    /// Reset all metrics to zero
+    pub fn reset(&self) {
+        self.latency_us.reset();
+    }
+}
+
+/// Fsync latency summary
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct FsyncLatencyReport {
+    pub count: u64,
+    pub mean_ms: f64,
+    pub p50_ms: f64,
+    pub p95_ms: f64,
+    pub p99_ms: f64,
+    pub max_ms: f64,
+}
+
+/// Group commit batch size tracking
+///
+/// Each commit covers every write since the previous one. Small batches
+/// under heavy load mean fsyncs are not being amortized.
+#[derive(Debug, Clone)]
+pub struct GroupCommitMetrics {
+    /// Writes per commit
+    batch_sizes: Arc<Log2Histogram>,
+}
+
+impl Default for GroupCommitMetrics {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl GroupCommitMetrics {
+    pub fn new() -> Self {
+        Self {
+            batch_sizes: Arc::new(Log2Histogram::new()),
+        }
+    }
+
+    /// Record a commit covering `writes` operations
+    pub fn record_commit(&self, writes: u64) {
+        self.batch_sizes.record(writes);
+    }
+
+    /// Generate batch size report (percentiles are bucket upper bounds)
+    pub fn report(&self) -> GroupCommitReport {
+        GroupCommitReport {
+            commits: self.batch_sizes.count(),
+            total_writes: self.batch_sizes.sum(),
+            avg_batch_size: self.batch_sizes.mean(),
+            p50_batch_size: self.batch_sizes.percentile(0.50),
+            p99_batch_size: self.batch_sizes.percentile(0.99),
+            max_batch_size: self.batch_sizes.max(),
+        }
+    }
+
+    /// Reset all metrics to zero
+    pub fn reset(&self) {
+        self.batch_sizes.reset();
+    }
+}
+
+/// Group commit batch size summary
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct GroupCommitReport {
+    pub commits: u64,
+    pub total_writes: u64,
+    pub avg_batch_size: f64,
+    pub p50_batch_size: u64,
+    pub p99_batch_size: u64,
+    pub max_batch_size: u64,
+}
+
+/// Write path telemetry for a storage engine
+///
+/// Bundles write amplification, fsync latency and group commit sizes so the
+/// storage layer can record all three from its commit path and health
+/// endpoints can report them together.
+#[derive(Debug, Clone, Default)]
+pub struct WriteTelemetry {
+    pub write_amplification: WriteAmplificationMetrics,
+    pub fsync: FsyncMetrics,
+    pub group_commit: GroupCommitMetrics,
+}
+
+impl WriteTelemetry {
+    /// Generate combined report
+    pub fn report(&self) -> WriteTelemetryReport {
+        WriteTelemetryReport {
+            write_amplification: self.write_amplification.report(),
+            fsync: self.fsync.report(),
+            group_commit: self.group_commit.report(),
+        }
+    }
+
+    /// Reset all metrics to zero
+    pub fn reset(&self) {
+        self.write_amplification.reset();
+        self.fsync.reset();
+        self.group_commit.reset();
+    }
+}
+
+/// Combined write path report
+#[derive(Debug, Clone, Serialize)]
+pub struct WriteTelemetryReport {
+    pub write_amplification: WriteAmplificationReport,
+    pub fsync: FsyncLatencyReport,
+    pub group_commit: GroupCommitReport,
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -402,4 +657,51 @@ mod tests {
         assert_eq!(metrics.total_physical_writes(), 0);
         assert_eq!(metrics.write_amplification_factor(), 0.0);
     }
+
+    #[test]
+    fn test_fsync_percentiles() {
+        let metrics = FsyncMetrics::new();
+        for _ in 0..98 {
+            metrics.record(Duration::from_micros(1_500));
+        }
+        metrics.record(Duration::from_millis(40));
+        metrics.record(Duration::from_millis(250));
+
+        let report = metrics.report();
+        assert_eq!(report.count, 100);
+        // 1.5ms falls in the [1024us, 2047us] bucket
+        assert_eq!(report.p50_ms, 2.047);
+        assert!(report.p99_ms >= 40.0 && report.p99_ms < 80.0);
+        assert_eq!(report.max_ms, 250.0);
+        assert!(report.mean_ms > 1.5 && report.mean_ms < 5.0);
+    }
+
+    #[test]
+    fn test_group_commit_report() {
+        let metrics = GroupCommitMetrics::new();
+        metrics.record_commit(1);
+        metrics.record_commit(100);
+        metrics.record_commit(100);
+
+        let report = metrics.report();
+        assert_eq!(report.commits, 3);
+        assert_eq!(report.total_writes, 201);
+        assert_eq!(report.avg_batch_size, 67.0);
+        // 100 falls in the [64, 127] bucket, capped at the observed max
+        assert_eq!(report.p50_batch_size, 100);
+        assert_eq!(report.max_batch_size, 100);
+    }
+
+    #[test]
+    fn test_empty_histogram_reports_zero() {
+        let telemetry = WriteTelemetry::default();
+        let report = telemetry.report();
+        assert_eq!(report.fsync.count, 0);
+        assert_eq!(report.fsync.p99_ms, 0.0);
+        assert_eq!(report.group_commit.p50_batch_size, 0);
+
+        telemetry.fsync.record(Duration::from_millis(3));
+        telemetry.reset();
+        assert_eq!(telemetry.fsync.count(), 0);
+    }
 }
@@ -231,6 +231,7 @@ impl Evaluator for CustomMetric {
             cost: Some(0.0),      // Custom metrics are free
             duration_ms: Some(0), // Very fast computation
             actionable_feedback: None,
+            highlights: Vec::new(),
         })
     }
 
@@ -325,6 +326,7 @@ impl MetricRegistry {
                         cost: None,
                         duration_ms: None,
                         actionable_feedback: None,
+                        highlights: Vec::new(),
                     });
                 }
             }
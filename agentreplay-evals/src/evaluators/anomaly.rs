@@ -578,6 +578,7 @@ impl Evaluator for AnomalyDetector {
             cost: None,
             duration_ms: Some(duration_ms),
             actionable_feedback: None,
+            highlights: Vec::new(),
         })
     }
 }
@@ -451,6 +451,7 @@ impl Evaluator for CIPTraceEvaluator {
             cost: Some(result.total_cost_usd),
             duration_ms: Some(result.duration_ms),
             actionable_feedback: None,
+            highlights: Vec::new(),
         })
     }
 
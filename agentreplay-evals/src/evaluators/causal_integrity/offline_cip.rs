@@ -759,6 +759,7 @@ impl Evaluator for OfflineCIPTraceEvaluator {
             } else {
                 None
             },
+            highlights: Vec::new(),
         })
     }
 
@@ -0,0 +1,317 @@
+// Copyright 2025 AgentReplay (https://github.com/agentreplay)
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program. If not, see <https://www.gnu.org/licenses/>.
+
+//! Low-confidence answer detection from token logprobs
+
+use crate::{EvalError, EvalResult, Evaluator, EvaluatorMetadata, MetricValue, TraceContext};
+use async_trait::async_trait;
+use std::collections::HashMap;
+use std::time::Instant;
+
+/// Metadata key holding the answer's token logprobs
+pub const LOGPROBS_METADATA_KEY: &str = "logprobs";
+
+/// Least likely tokens reported in the result
+const MAX_UNCERTAIN_TOKENS: usize = 5;
+
+/// Low-confidence answer detector
+///
+/// Reads the token logprobs of the final answer from
+/// `metadata["logprobs"]` — an array of numbers, or of objects with a
+/// `logprob` (and optional `token`) field as captured at ingest — and flags
+/// the answer for review when its sequence confidence (geometric mean token
+/// probability) is low or too many tokens were uncertain.
+pub struct LowConfidenceDetector {
+    min_sequence_confidence: f64,
+    token_prob_threshold: f64,
+    max_low_confidence_ratio: f64,
+}
+
+impl LowConfidenceDetector {
+    /// Create a new low-confidence detector
+    pub fn new() -> Self {
+        Self {
+            min_sequence_confidence: 0.7,
+            token_prob_threshold: 0.5,
+            max_low_confidence_ratio: 0.2,
+        }
+    }
+
+    /// Set the minimum sequence confidence (default: 0.7)
+    pub fn with_min_sequence_confidence(mut self, confidence: f64) -> Self {
+        self.min_sequence_confidence = confidence;
+        self
+    }
+
+    /// Set the probability below which a token is uncertain (default: 0.5)
+    pub fn with_token_prob_threshold(mut self, probability: f64) -> Self {
+        self.token_prob_threshold = probability;
+        self
+    }
+
+    /// Set the maximum fraction of uncertain tokens (default: 0.2)
+    pub fn with_max_low_confidence_ratio(mut self, ratio: f64) -> Self {
+        self.max_low_confidence_ratio = ratio;
+        self
+    }
+
+    /// `(token, logprob)` pairs from the metadata value
+    fn parse_logprobs(value: &serde_json::Value) -> Option<Vec<(Option<String>, f64)>> {
+        value
+            .as_array()?
+            .iter()
+            .map(|entry| match entry.as_f64() {
+                Some(logprob) => Some((None, logprob)),
+                None => Some((
+                    entry
+                        .get("token")
+                        .and_then(|t| t.as_str())
+                        .map(String::from),
+                    entry.get("logprob")?.as_f64()?,
+                )),
+            })
+            .collect()
+    }
+}
+
+impl Default for LowConfidenceDetector {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[async_trait]
+impl Evaluator for LowConfidenceDetector {
+    fn id(&self) -> &str {
+        "low_confidence_v1"
+    }
+
+    async fn evaluate(&self, trace: &TraceContext) -> Result<EvalResult, EvalError> {
+        let start = Instant::now();
+
+        let value = trace.metadata.get(LOGPROBS_METADATA_KEY).ok_or_else(|| {
+            EvalError::InvalidInput(
+                "No token logprobs in trace metadata (enable the logprobs ingestion stage)"
+                    .to_string(),
+            )
+        })?;
+        let tokens = Self::parse_logprobs(value)
+            .filter(|tokens| !tokens.is_empty())
+            .ok_or_else(|| {
+                EvalError::InvalidInput(
+                    "metadata.logprobs must be a non-empty array of logprobs".to_string(),
+                )
+            })?;
+
+        let count = tokens.len() as f64;
+        let mean_logprob = tokens.iter().map(|(_, lp)| lp).sum::<f64>() / count;
+        let sequence_confidence = mean_logprob.exp();
+        let perplexity = (-mean_logprob).exp();
+        let min_token_prob = tokens
+            .iter()
+            .map(|(_, lp)| lp.exp())
+            .fold(f64::INFINITY, f64::min);
+        let threshold_logprob = self.token_prob_threshold.ln();
+        let low_confidence_tokens = tokens
+            .iter()
+            .filter(|(_, lp)| *lp < threshold_logprob)
+            .count();
+        let low_confidence_ratio = low_confidence_tokens as f64 / count;
+
+        let confident = sequence_confidence >= self.min_sequence_confidence;
+        let few_uncertain = low_confidence_ratio <= self.max_low_confidence_ratio;
+        let passed = confident && few_uncertain;
+
+        let mut uncertain: Vec<&(Option<String>, f64)> = tokens
+            .iter()
+            .filter(|(_, lp)| *lp < threshold_logprob)
+            .collect();
+        uncertain.sort_by(|a, b| a.1.total_cmp(&b.1));
+        let uncertain_tokens: Vec<MetricValue> = uncertain
+            .iter()
+            .filter_map(|(token, _)| token.clone())
+            .take(MAX_UNCERTAIN_TOKENS)
+            .map(MetricValue::String)
+            .collect();
+
+        let mut metrics = HashMap::new();
+        metrics.insert(
+            "sequence_confidence".to_string(),
+            MetricValue::Float(sequence_confidence),
+        );
+        metrics.insert("perplexity".to_string(), MetricValue::Float(perplexity));
+        metrics.insert(
+            "min_token_prob".to_string(),
+            MetricValue::Float(min_token_prob),
+        );
+        metrics.insert(
+            "low_confidence_tokens".to_string(),
+            MetricValue::Int(low_confidence_tokens as i64),
+        );
+        metrics.insert(
+            "low_confidence_ratio".to_string(),
+            MetricValue::Float(low_confidence_ratio),
+        );
+        metrics.insert(
+            "token_count".to_string(),
+            MetricValue::Int(tokens.len() as i64),
+        );
+        metrics.insert("needs_review".to_string(), MetricValue::Bool(!passed));
+        if !uncertain_tokens.is_empty() {
+            metrics.insert(
+                "uncertain_tokens".to_string(),
+                MetricValue::Array(uncertain_tokens),
+            );
+        }
+
+        let explanation = if passed {
+            format!(
+                "Answer is confident: sequence confidence {:.2}, {} of {} tokens below p={:.2}.",
+                sequence_confidence,
+                low_confidence_tokens,
+                tokens.len(),
+                self.token_prob_threshold
+            )
+        } else {
+            let mut reasons = Vec::new();
+            if !confident {
+                reasons.push(format!(
+                    "sequence confidence {:.2} < {:.2}",
+                    sequence_confidence, self.min_sequence_confidence
+                ));
+            }
+            if !few_uncertain {
+                reasons.push(format!(
+                    "{:.0}% of tokens below p={:.2} (max {:.0}%)",
+                    low_confidence_ratio * 100.0,
+                    self.token_prob_threshold,
+                    self.max_low_confidence_ratio * 100.0
+                ));
+            }
+            format!(
+                "Low-confidence answer, flagged for review: {}.",
+                reasons.join("; ")
+            )
+        };
+
+        Ok(EvalResult {
+            evaluator_id: self.id().to_string(),
+            evaluator_type: Some("statistical".to_string()),
+            metrics,
+            passed,
+            explanation: Some(explanation),
+            assertions: Vec::new(),
+            judge_votes: Vec::new(),
+            evidence_refs: Vec::new(),
+            confidence: 1.0,
+            cost: Some(0.0),
+            duration_ms: Some(start.elapsed().as_millis() as u64),
+            actionable_feedback: None,
+            highlights: Vec::new(),
+        })
+    }
+
+    fn metadata(&self) -> EvaluatorMetadata {
+        EvaluatorMetadata {
+            name: "Low-Confidence Detector".to_string(),
+            version: "1.0.0".to_string(),
+            description:
+                "Flags answers with low token-level confidence (from provider logprobs) for review."
+                    .to_string(),
+            cost_per_eval: Some(0.0),
+            avg_latency_ms: Some(1),
+            tags: vec![
+                "confidence".to_string(),
+                "logprobs".to_string(),
+                "uncertainty".to_string(),
+            ],
+            author: Some("Agentreplay".to_string()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn trace_with(logprobs: serde_json::Value) -> TraceContext {
+        TraceContext {
+            trace_id: 1,
+            edges: Vec::new(),
+            input: None,
+            output: Some("Paris".to_string()),
+            context: None,
+            metadata: HashMap::from([(LOGPROBS_METADATA_KEY.to_string(), logprobs)]),
+            eval_trace: None,
+            timestamp_us: 0,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_confident_answer_passes() {
+        let detector = LowConfidenceDetector::new();
+        let result = detector
+            .evaluate(&trace_with(json!([-0.01, -0.05, -0.2, -0.02])))
+            .await
+            .unwrap();
+        assert!(result.passed);
+        assert!(matches!(
+            result.metrics.get("needs_review"),
+            Some(MetricValue::Bool(false))
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_uncertain_answer_flagged() {
+        let detector = LowConfidenceDetector::new();
+        let result = detector
+            .evaluate(&trace_with(json!([
+                {"token": "The", "logprob": -0.05},
+                {"token": " answer", "logprob": -1.6},
+                {"token": " is", "logprob": -0.1},
+                {"token": " 42", "logprob": -2.3},
+            ])))
+            .await
+            .unwrap();
+        assert!(!result.passed);
+        assert!(matches!(
+            result.metrics.get("needs_review"),
+            Some(MetricValue::Bool(true))
+        ));
+        match result.metrics.get("uncertain_tokens") {
+            Some(MetricValue::Array(tokens)) => {
+                assert!(matches!(&tokens[0], MetricValue::String(t) if t == " 42"))
+            }
+            other => panic!("unexpected uncertain_tokens: {:?}", other),
+        }
+
+        // A lenient detector accepts the same answer
+        let lenient = LowConfidenceDetector::new()
+            .with_min_sequence_confidence(0.3)
+            .with_max_low_confidence_ratio(0.5);
+        let trace = trace_with(json!([-0.05, -1.6, -0.1, -2.3]));
+        assert!(lenient.evaluate(&trace).await.unwrap().passed);
+    }
+
+    #[tokio::test]
+    async fn test_missing_logprobs() {
+        let detector = LowConfidenceDetector::new();
+        let mut trace = trace_with(json!([]));
+        assert!(detector.evaluate(&trace).await.is_err());
+        trace.metadata.clear();
+        assert!(detector.evaluate(&trace).await.is_err());
+    }
+}
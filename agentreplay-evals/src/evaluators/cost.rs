@@ -238,6 +238,7 @@ impl Evaluator for CostAnalyzer {
             cost: Some(0.0),  // No cost for local computation
             duration_ms: Some(eval_duration_ms),
             actionable_feedback: None,
+            highlights: Vec::new(),
         })
     }
 
@@ -588,6 +588,7 @@ impl Evaluator for GEval {
             } else {
                 None
             },
+            highlights: Vec::new(),
         })
     }
 
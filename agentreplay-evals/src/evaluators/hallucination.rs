@@ -19,7 +19,7 @@
 use crate::llm_client::LLMError;
 use crate::{
     llm_client::LLMClient, EvalError, EvalResult, Evaluator, EvaluatorMetadata, MetricValue,
-    TraceContext,
+    Severity, SpanHighlight, TraceContext,
 };
 use async_trait::async_trait;
 use serde::{Deserialize, Serialize};
@@ -268,6 +268,7 @@ impl Evaluator for HallucinationDetector {
                 cost: Some(total_cost),
                 duration_ms: Some(start.elapsed().as_millis() as u64),
                 actionable_feedback: None,
+                highlights: Vec::new(),
             });
         }
 
@@ -335,6 +336,30 @@ impl Evaluator for HallucinationDetector {
             0.0
         };
 
+        // Point at contradicted/unsupported claims the judge quoted verbatim
+        let highlights = verifications
+            .iter()
+            .filter_map(|v| {
+                let (severity, label) = match v.status {
+                    ClaimStatus::Contradicted => (Severity::Major, "Contradicted by context"),
+                    ClaimStatus::Unsupported => (Severity::Minor, "Not supported by context"),
+                    _ => return None,
+                };
+                let range = SpanHighlight::find(output, &v.claim)?;
+                let message = match &v.evidence {
+                    Some(evidence) => format!("{}: {}", label, evidence),
+                    None => label.to_string(),
+                };
+                Some(SpanHighlight::new(
+                    trace.trace_id,
+                    SpanHighlight::OUTPUT,
+                    range,
+                    severity,
+                    message,
+                ))
+            })
+            .collect();
+
         Ok(EvalResult {
             evaluator_id: self.id().to_string(),
             evaluator_type: Some("llm_judge".to_string()),
@@ -348,6 +373,7 @@ impl Evaluator for HallucinationDetector {
             cost: Some(total_cost),
             duration_ms: Some(duration_ms),
             actionable_feedback: None,
+            highlights,
         })
     }
 
@@ -466,4 +492,71 @@ mod tests {
             panic!("Missing claims_supported metric");
         }
     }
+
+    struct ContradictingLLMClient;
+
+    #[async_trait]
+    impl LLMClient for ContradictingLLMClient {
+        async fn evaluate(&self, prompt: String) -> Result<LLMResponse, LLMError> {
+            let content = if prompt.contains("Extract all factual claims") {
+                r#"{"claims": ["Lyon is the capital of France"]}"#
+            } else {
+                r#"{
+                    "verifications": [
+                        {
+                            "claim": "Lyon is the capital of France",
+                            "status": "Contradicted",
+                            "evidence": "Paris is the capital of France.",
+                            "confidence": 0.9
+                        }
+                    ]
+                }"#
+            };
+            Ok(LLMResponse {
+                content: content.to_string(),
+                usage: TokenUsage {
+                    prompt_tokens: 0,
+                    completion_tokens: 0,
+                    total_tokens: 0,
+                },
+                model: "mock-model".to_string(),
+            })
+        }
+
+        fn model_name(&self) -> &str {
+            "mock-model"
+        }
+
+        fn cost_per_token(&self) -> (f64, f64) {
+            (0.0, 0.0)
+        }
+    }
+
+    #[tokio::test]
+    async fn test_contradicted_claim_is_highlighted() {
+        let detector = HallucinationDetector::new(Arc::new(ContradictingLLMClient));
+        let output = "Sure! Lyon is the capital of France.";
+
+        let trace = TraceContext {
+            trace_id: 7,
+            edges: vec![],
+            input: Some("What is the capital of France?".to_string()),
+            output: Some(output.to_string()),
+            context: Some(vec!["Paris is the capital of France.".to_string()]),
+            metadata: HashMap::new(),
+            eval_trace: None,
+            timestamp_us: 0,
+        };
+
+        let result = detector.evaluate(&trace).await.unwrap();
+
+        assert!(!result.passed);
+        assert_eq!(result.highlights.len(), 1);
+        let highlight = &result.highlights[0];
+        assert_eq!(highlight.span_id, 7);
+        assert_eq!(highlight.trace_part, SpanHighlight::OUTPUT);
+        assert_eq!(highlight.start_offset..highlight.end_offset, 6..35);
+        assert_eq!(highlight.severity, Severity::Major);
+        assert!(highlight.is_valid(output.chars().count()));
+    }
 }
@@ -249,6 +249,7 @@ impl Evaluator for LatencyBenchmark {
             cost: Some(0.0), // No cost for local computation
             duration_ms: Some(eval_duration_ms),
             actionable_feedback: None,
+            highlights: Vec::new(),
         })
     }
 
@@ -19,20 +19,24 @@ pub mod anomaly;
 pub mod calibration;
 pub mod causal_integrity;
 pub mod classification;
+pub mod confidence;
 pub mod cost;
 pub mod diversity;
 pub mod g_eval;
 pub mod hallucination;
 pub mod latency;
 pub mod perplexity;
+pub mod rag_suite;
 pub mod ragas;
 pub mod reference;
 pub mod relevance;
+pub mod structured_output;
 pub mod synthetic;
 pub mod task_completion;
 pub mod tool_correctness;
 pub mod toxicity;
 pub mod trajectory_efficiency;
+pub mod trajectory_match;
 
 pub use anomaly::{AnomalyDetector, PersistedAnomalyState};
 pub use calibration::{
@@ -47,6 +51,7 @@ pub use classification::{
     multiclass_mcc, ClassificationAnalyzer, ClassificationMetrics, ConfusionMatrix, PRCurve,
     PRPoint, ROCCurve, ROCPoint, ThresholdObjective, ThresholdResult,
 };
+pub use confidence::LowConfidenceDetector;
 pub use cost::CostAnalyzer;
 pub use diversity::{analyze_zipf, DiversityAnalyzer, DiversityMetrics, ZipfAnalysis};
 pub use g_eval::GEval;
@@ -56,6 +61,9 @@ pub use g_eval::{
 pub use hallucination::HallucinationDetector;
 pub use latency::LatencyBenchmark;
 pub use perplexity::{NGramPerplexity, PerplexityEvaluator, PerplexityResult};
+pub use rag_suite::{
+    CitationCheck, CitationStatus, RagSuiteEvaluator, RagSuiteReport, SentenceAttribution,
+};
 pub use ragas::RagasEvaluator;
 pub use ragas::{ClaimVerification, NLIVerdict, QAGFaithfulnessEvaluator, QAGFaithfulnessResult};
 pub use ragas::{EmbeddingAnswerRelevanceEvaluator, EmbeddingRelevanceResult};
@@ -63,6 +71,7 @@ pub use reference::{
     BertScoreResult, PrimaryMetric, ReferenceEvaluator, ReferenceMetrics, RougeScore,
 };
 pub use relevance::RelevanceEvaluator;
+pub use structured_output::{StructuredOutputEvaluator, StructuredOutputStrictness};
 pub use synthetic::{
     Difficulty, PerturbationStrategy, SelectionCriteria, SyntheticDatasetGenerator, TestCase,
 };
@@ -76,6 +85,7 @@ pub use tool_correctness::{
 };
 pub use toxicity::{ToxicityClassification, ToxicityDetector};
 pub use trajectory_efficiency::TrajectoryEfficiencyEvaluator;
+pub use trajectory_match::{TrajectoryMatch, TrajectoryMatchEvaluator, TrajectoryStep};
 pub mod local;
 pub mod streaming;
 mod test_embeddings;
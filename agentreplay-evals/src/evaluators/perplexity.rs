@@ -312,6 +312,7 @@ impl Evaluator for PerplexityEvaluator {
             cost: self.metadata().cost_per_eval,
             duration_ms: Some(start.elapsed().as_millis() as u64),
             actionable_feedback: None,
+            highlights: Vec::new(),
         })
     }
 }
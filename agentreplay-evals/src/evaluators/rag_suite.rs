@@ -0,0 +1,712 @@
+// Copyright 2025 AgentReplay (https://github.com/agentreplay)
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program. If not, see <https://www.gnu.org/licenses/>.
+
+//! Deterministic RAG suite: retrieval quality, sentence attribution and
+//! citation checking
+//!
+//! Complements the LLM-judged [`RagasEvaluator`](super::RagasEvaluator)
+//! with checks that need no model and point at exact sentences:
+//!
+//! - Context precision: rank-weighted share of retrieved chunks that support
+//!   the ground truth (or, without one, the answer)
+//! - Context recall: share of ground-truth sentences some chunk supports
+//! - Faithfulness: share of answer sentences attributed to a chunk
+//! - Citation accuracy: share of quoted spans that appear verbatim in the
+//!   retrieved context - in the chunk a trailing `[n]` marker cites, if any
+//!
+//! A sentence is supported by a chunk when at least `attribution_threshold`
+//! of its content words occur in the chunk.
+
+use crate::{
+    EvalError, EvalResult, Evaluator, EvaluatorMetadata, MetricValue, Severity, SpanHighlight,
+    TraceContext,
+};
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+use std::ops::Range;
+use std::time::Instant;
+
+/// Metadata key holding the reference answer used for context recall
+pub const GROUND_TRUTH_METADATA_KEY: &str = "ground_truth";
+
+/// Sentences with fewer content words are too short to attribute
+const MIN_SENTENCE_WORDS: usize = 3;
+
+/// Quotes with fewer words are treated as scare quotes, not citations
+const MIN_QUOTE_WORDS: usize = 3;
+
+const STOPWORDS: &[&str] = &[
+    "a", "an", "and", "are", "as", "at", "be", "been", "but", "by", "can", "did", "do", "does",
+    "for", "from", "had", "has", "have", "he", "her", "his", "i", "if", "in", "into", "is", "it",
+    "its", "may", "not", "of", "on", "or", "our", "she", "so", "than", "that", "the", "their",
+    "them", "then", "there", "these", "they", "this", "those", "to", "was", "we", "were", "which",
+    "while", "who", "will", "with", "would", "you", "your",
+];
+
+/// Chunk attribution of one answer sentence
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SentenceAttribution {
+    pub sentence: String,
+    /// Character offsets of the sentence in the answer
+    pub start: usize,
+    pub end: usize,
+    /// Best-supporting chunk, if its support reaches the threshold
+    pub chunk: Option<usize>,
+    /// Share of the sentence's content words found in the best chunk
+    pub support: f64,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CitationStatus {
+    /// Appears in the cited chunk, or in any chunk if none is cited
+    Verified,
+    /// Appears in the context, but not in the cited chunk
+    WrongSource,
+    /// Appears nowhere in the retrieved context
+    NotFound,
+}
+
+/// Verification of one quoted span of the answer
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CitationCheck {
+    pub quote: String,
+    /// Character offsets of the quote (without quote marks) in the answer
+    pub start: usize,
+    pub end: usize,
+    /// Chunk named by a `[n]` marker after the quote (0-based)
+    pub cited_chunk: Option<usize>,
+    /// Chunk the quote was found in
+    pub found_in: Option<usize>,
+    pub status: CitationStatus,
+}
+
+/// RAG suite scores with the attributions and citations behind them
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RagSuiteReport {
+    pub context_precision: f64,
+    /// Only computed with a ground truth
+    pub context_recall: Option<f64>,
+    /// Answers with no attributable sentence are vacuously faithful
+    pub faithfulness: f64,
+    /// Only computed when the answer quotes something
+    pub citation_accuracy: Option<f64>,
+    /// Chunks that support the reference, in retrieval order
+    pub relevant_chunks: Vec<usize>,
+    pub sentences: Vec<SentenceAttribution>,
+    pub citations: Vec<CitationCheck>,
+}
+
+impl RagSuiteReport {
+    /// Analyze an answer against its retrieved context
+    pub fn analyze(
+        answer: &str,
+        context: &[String],
+        ground_truth: Option<&str>,
+        attribution_threshold: f64,
+    ) -> Self {
+        let chunk_words: Vec<HashSet<String>> = context.iter().map(|c| content_words(c)).collect();
+
+        let sentences: Vec<SentenceAttribution> = split_sentences(answer)
+            .into_iter()
+            .filter_map(|(range, sentence)| {
+                let words = content_words(&sentence);
+                if words.len() < MIN_SENTENCE_WORDS {
+                    return None;
+                }
+                let (best, support) = best_chunk(&words, &chunk_words)?;
+                Some(SentenceAttribution {
+                    sentence,
+                    start: range.start,
+                    end: range.end,
+                    chunk: (support >= attribution_threshold).then_some(best),
+                    support,
+                })
+            })
+            .collect();
+
+        let faithfulness = if sentences.is_empty() {
+            1.0
+        } else {
+            sentences.iter().filter(|s| s.chunk.is_some()).count() as f64 / sentences.len() as f64
+        };
+
+        // Precision judges the chunks against the ground truth when there is
+        // one, otherwise against what the answer actually used
+        let reference_words: Vec<HashSet<String>> = match ground_truth {
+            Some(truth) => split_sentences(truth)
+                .into_iter()
+                .map(|(_, s)| content_words(&s))
+                .filter(|w| w.len() >= MIN_SENTENCE_WORDS)
+                .collect(),
+            None => sentences
+                .iter()
+                .map(|s| content_words(&s.sentence))
+                .collect(),
+        };
+        let relevant_chunks: Vec<usize> = chunk_words
+            .iter()
+            .enumerate()
+            .filter(|(_, chunk)| {
+                reference_words
+                    .iter()
+                    .any(|words| support(words, chunk) >= attribution_threshold)
+            })
+            .map(|(i, _)| i)
+            .collect();
+        let context_precision = average_precision(&relevant_chunks);
+
+        let context_recall = ground_truth
+            .filter(|_| !reference_words.is_empty())
+            .map(|_| {
+                let recalled = reference_words
+                    .iter()
+                    .filter(|words| {
+                        best_chunk(words, &chunk_words)
+                            .is_some_and(|(_, s)| s >= attribution_threshold)
+                    })
+                    .count();
+                recalled as f64 / reference_words.len() as f64
+            });
+
+        let citations: Vec<CitationCheck> = extract_quotes(answer)
+            .into_iter()
+            .map(|(range, quote, cited_chunk)| check_quote(quote, range, cited_chunk, context))
+            .collect();
+        let citation_accuracy = (!citations.is_empty()).then(|| {
+            citations
+                .iter()
+                .filter(|c| c.status == CitationStatus::Verified)
+                .count() as f64
+                / citations.len() as f64
+        });
+
+        Self {
+            context_precision,
+            context_recall,
+            faithfulness,
+            citation_accuracy,
+            relevant_chunks,
+            sentences,
+            citations,
+        }
+    }
+
+    /// Computed scores by metric name
+    pub fn scores(&self) -> HashMap<String, f64> {
+        let mut scores = HashMap::from([
+            ("context_precision".to_string(), self.context_precision),
+            ("faithfulness".to_string(), self.faithfulness),
+        ]);
+        if let Some(recall) = self.context_recall {
+            scores.insert("context_recall".to_string(), recall);
+        }
+        if let Some(accuracy) = self.citation_accuracy {
+            scores.insert("citation_accuracy".to_string(), accuracy);
+        }
+        scores
+    }
+
+    /// Harmonic mean of the scores, so one failing metric sinks the result
+    pub fn overall(&self) -> f64 {
+        let scores: Vec<f64> = self.scores().into_values().collect();
+        let sum_reciprocals: f64 = scores.iter().map(|s| 1.0 / s.max(0.001)).sum();
+        scores.len() as f64 / sum_reciprocals
+    }
+
+    /// Quotes that appear nowhere in the retrieved context
+    pub fn fabricated_quotes(&self) -> usize {
+        self.citations
+            .iter()
+            .filter(|c| c.status == CitationStatus::NotFound)
+            .count()
+    }
+
+    pub fn attributed_sentences(&self) -> usize {
+        self.sentences.iter().filter(|s| s.chunk.is_some()).count()
+    }
+
+    /// One-line account of the attributions and citations
+    pub fn summary(&self) -> String {
+        format!(
+            "{}/{} sentences attributed to retrieved chunks; {} relevant chunks; {}/{} quotes verified",
+            self.attributed_sentences(),
+            self.sentences.len(),
+            self.relevant_chunks.len(),
+            self.citations
+                .iter()
+                .filter(|c| c.status == CitationStatus::Verified)
+                .count(),
+            self.citations.len(),
+        )
+    }
+}
+
+/// Lowercased content words, without stopwords and plural `s`
+fn content_words(text: &str) -> HashSet<String> {
+    text.split(|c: char| !c.is_alphanumeric())
+        .filter(|w| !w.is_empty())
+        .map(str::to_lowercase)
+        .filter(|w| !STOPWORDS.contains(&w.as_str()))
+        .map(|w| {
+            if w.len() > 3 && w.ends_with('s') && !w.ends_with("ss") {
+                w[..w.len() - 1].to_string()
+            } else {
+                w
+            }
+        })
+        .collect()
+}
+
+/// Share of `words` that occur in `chunk`
+fn support(words: &HashSet<String>, chunk: &HashSet<String>) -> f64 {
+    if words.is_empty() {
+        return 0.0;
+    }
+    words.intersection(chunk).count() as f64 / words.len() as f64
+}
+
+/// Index and support of the chunk that best supports `words`
+fn best_chunk(words: &HashSet<String>, chunks: &[HashSet<String>]) -> Option<(usize, f64)> {
+    chunks
+        .iter()
+        .map(|chunk| support(words, chunk))
+        .enumerate()
+        .max_by(|a, b| a.1.total_cmp(&b.1).then(b.0.cmp(&a.0)))
+}
+
+/// Mean precision@k over the ranks of the relevant chunks
+fn average_precision(relevant: &[usize]) -> f64 {
+    if relevant.is_empty() {
+        return 0.0;
+    }
+    let sum: f64 = relevant
+        .iter()
+        .enumerate()
+        .map(|(hits, &rank)| (hits + 1) as f64 / (rank + 1) as f64)
+        .sum();
+    sum / relevant.len() as f64
+}
+
+/// Sentences with their character ranges, split at terminal punctuation
+/// followed by whitespace and at line breaks
+fn split_sentences(text: &str) -> Vec<(Range<usize>, String)> {
+    let chars: Vec<char> = text.chars().collect();
+    let mut sentences = Vec::new();
+    let mut start = 0;
+    for (i, &c) in chars.iter().enumerate() {
+        let boundary = c == '\n'
+            || (matches!(c, '.' | '!' | '?')
+                && chars.get(i + 1).map_or(true, |c| c.is_whitespace()));
+        if !boundary && i + 1 < chars.len() {
+            continue;
+        }
+        let mut range = start..i + 1;
+        while range.start < range.end && chars[range.start].is_whitespace() {
+            range.start += 1;
+        }
+        while range.end > range.start && chars[range.end - 1].is_whitespace() {
+            range.end -= 1;
+        }
+        if !range.is_empty() {
+            sentences.push((range.clone(), chars[range].iter().collect()));
+        }
+        start = i + 1;
+    }
+    sentences
+}
+
+/// Quoted spans of at least [`MIN_QUOTE_WORDS`] words on one line, with
+/// their character ranges and the 0-based chunk a trailing `[n]` cites
+fn extract_quotes(text: &str) -> Vec<(Range<usize>, String, Option<usize>)> {
+    let chars: Vec<char> = text.chars().collect();
+    let mut quotes = Vec::new();
+    let mut i = 0;
+    while i < chars.len() {
+        let close = match chars[i] {
+            '"' => '"',
+            '\u{201C}' => '\u{201D}',
+            _ => {
+                i += 1;
+                continue;
+            }
+        };
+        let Some(len) = chars[i + 1..]
+            .iter()
+            .take_while(|&&c| c != '\n')
+            .position(|&c| c == close)
+        else {
+            i += 1;
+            continue;
+        };
+        let range = i + 1..i + 1 + len;
+        let quote: String = chars[range.clone()].iter().collect();
+        i = range.end + 1;
+
+        if quote.split_whitespace().count() < MIN_QUOTE_WORDS {
+            continue;
+        }
+        quotes.push((range, quote, citation_marker(&chars[i..])));
+    }
+    quotes
+}
+
+/// `[n]` (1-based) at the start of `rest`, after optional spaces and
+/// punctuation, as a 0-based chunk index
+fn citation_marker(rest: &[char]) -> Option<usize> {
+    let rest: String = rest
+        .iter()
+        .skip_while(|c| matches!(**c, ' ' | ',' | '.' | ';' | ':'))
+        .take(8)
+        .collect();
+    let inner = rest.strip_prefix('[')?;
+    let number = &inner[..inner.find(']')?];
+    number.trim().parse::<usize>().ok()?.checked_sub(1)
+}
+
+/// Lowercase, unify typographic quotes and dashes, collapse whitespace
+fn normalize(text: &str) -> String {
+    let mapped: String = text
+        .chars()
+        .map(|c| match c {
+            '\u{2018}' | '\u{2019}' => '\'',
+            '\u{201C}' | '\u{201D}' => '"',
+            '\u{2013}' | '\u{2014}' => '-',
+            c => c,
+        })
+        .collect::<String>()
+        .replace('\u{2026}', "...")
+        .to_lowercase();
+    mapped.split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+/// Whether the quote's fragments (split at ellipses) occur in order
+fn contains_quote(chunk: &str, fragments: &[String]) -> bool {
+    let mut rest = chunk;
+    for fragment in fragments {
+        match rest.find(fragment.as_str()) {
+            Some(at) => rest = &rest[at + fragment.len()..],
+            None => return false,
+        }
+    }
+    true
+}
+
+fn check_quote(
+    quote: String,
+    range: Range<usize>,
+    cited_chunk: Option<usize>,
+    context: &[String],
+) -> CitationCheck {
+    let fragments: Vec<String> = normalize(&quote)
+        .split("...")
+        .map(|f| {
+            f.trim_matches(|c: char| c.is_whitespace() || ",.;:!?".contains(c))
+                .to_string()
+        })
+        .filter(|f| !f.is_empty())
+        .collect();
+    let chunks: Vec<String> = context.iter().map(|c| normalize(c)).collect();
+    let found = |i: usize| chunks.get(i).is_some_and(|c| contains_quote(c, &fragments));
+
+    let (found_in, status) = match cited_chunk {
+        Some(cited) if found(cited) => (Some(cited), CitationStatus::Verified),
+        _ => match (0..chunks.len()).find(|&i| found(i)) {
+            Some(i) if cited_chunk.is_some() => (Some(i), CitationStatus::WrongSource),
+            Some(i) => (Some(i), CitationStatus::Verified),
+            None => (None, CitationStatus::NotFound),
+        },
+    };
+
+    CitationCheck {
+        quote,
+        start: range.start,
+        end: range.end,
+        cited_chunk,
+        found_in,
+        status,
+    }
+}
+
+/// Rule-based RAG suite over the trace's output and retrieved context
+pub struct RagSuiteEvaluator {
+    attribution_threshold: f64,
+    threshold: f64,
+}
+
+impl RagSuiteEvaluator {
+    pub fn new() -> Self {
+        Self {
+            attribution_threshold: 0.5,
+            threshold: 0.7, // Same pass mark as the LLM-judged RAGAS score
+        }
+    }
+
+    /// Share of a sentence's content words a chunk must contain to support it
+    pub fn with_attribution_threshold(mut self, threshold: f64) -> Self {
+        self.attribution_threshold = threshold;
+        self
+    }
+
+    pub fn with_threshold(mut self, threshold: f64) -> Self {
+        self.threshold = threshold;
+        self
+    }
+}
+
+impl Default for RagSuiteEvaluator {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[async_trait]
+impl Evaluator for RagSuiteEvaluator {
+    fn id(&self) -> &str {
+        "rag_suite_v1"
+    }
+
+    async fn evaluate(&self, trace: &TraceContext) -> Result<EvalResult, EvalError> {
+        let start = Instant::now();
+
+        let answer = trace
+            .output
+            .as_deref()
+            .ok_or_else(|| EvalError::MissingField("output".to_string()))?;
+        let context = trace
+            .context
+            .as_deref()
+            .filter(|c| !c.is_empty())
+            .ok_or_else(|| EvalError::MissingField("context".to_string()))?;
+        let ground_truth = [GROUND_TRUTH_METADATA_KEY, "expected_output"]
+            .iter()
+            .find_map(|key| trace.metadata.get(*key).and_then(|v| v.as_str()));
+
+        let report =
+            RagSuiteReport::analyze(answer, context, ground_truth, self.attribution_threshold);
+        let score = report.overall();
+        let fabricated = report.fabricated_quotes();
+        let passed = score >= self.threshold && fabricated == 0;
+
+        let mut metrics: HashMap<String, MetricValue> = report
+            .scores()
+            .into_iter()
+            .map(|(name, value)| (name, MetricValue::Float(value)))
+            .collect();
+        metrics.insert("score".to_string(), MetricValue::Float(score));
+        metrics.insert(
+            "attributed_sentences".to_string(),
+            MetricValue::Int(report.attributed_sentences() as i64),
+        );
+        metrics.insert(
+            "checked_sentences".to_string(),
+            MetricValue::Int(report.sentences.len() as i64),
+        );
+        metrics.insert(
+            "fabricated_quotes".to_string(),
+            MetricValue::Int(fabricated as i64),
+        );
+        if let Ok(json) = serde_json::to_value(&report) {
+            metrics.insert("report".to_string(), MetricValue::Json(json));
+        }
+
+        let mut highlights: Vec<SpanHighlight> = report
+            .sentences
+            .iter()
+            .filter(|s| s.chunk.is_none())
+            .map(|s| {
+                SpanHighlight::new(
+                    trace.trace_id,
+                    SpanHighlight::OUTPUT,
+                    s.start..s.end,
+                    Severity::Minor,
+                    "Not supported by any retrieved chunk",
+                )
+            })
+            .collect();
+        highlights.extend(report.citations.iter().filter_map(|c| {
+            let (severity, message) = match (c.status, c.found_in, c.cited_chunk) {
+                (CitationStatus::NotFound, _, _) => (
+                    Severity::Major,
+                    "Quoted text does not appear in the retrieved context".to_string(),
+                ),
+                (CitationStatus::WrongSource, Some(found), Some(cited)) => (
+                    Severity::Minor,
+                    format!("Quote is from chunk [{}], not [{}]", found + 1, cited + 1),
+                ),
+                _ => return None,
+            };
+            Some(SpanHighlight::new(
+                trace.trace_id,
+                SpanHighlight::OUTPUT,
+                c.start..c.end,
+                severity,
+                message,
+            ))
+        }));
+
+        Ok(EvalResult {
+            evaluator_id: self.id().to_string(),
+            evaluator_type: Some("rule-based".to_string()),
+            metrics,
+            passed,
+            explanation: Some(report.summary()),
+            assertions: Vec::new(),
+            judge_votes: Vec::new(),
+            evidence_refs: Vec::new(),
+            confidence: 0.7, // Lexical overlap misses paraphrases
+            cost: Some(0.0),
+            duration_ms: Some(start.elapsed().as_millis() as u64),
+            actionable_feedback: None,
+            highlights,
+        })
+    }
+
+    fn metadata(&self) -> EvaluatorMetadata {
+        EvaluatorMetadata {
+            name: "RAG Suite".to_string(),
+            version: "1.0.0".to_string(),
+            description: "Scores context precision/recall and faithfulness by attributing each answer sentence to a retrieved chunk, and checks that quoted spans appear in the retrieved context.".to_string(),
+            cost_per_eval: Some(0.0),
+            avg_latency_ms: Some(1),
+            tags: vec![
+                "rag".to_string(),
+                "faithfulness".to_string(),
+                "citations".to_string(),
+                "deterministic".to_string(),
+            ],
+            author: Some("Agentreplay".to_string()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn context() -> Vec<String> {
+        vec![
+            "The Eiffel Tower was completed in 1889 for the World's Fair in Paris.".to_string(),
+            "Bananas are rich in potassium and vitamin B6.".to_string(),
+            "Gustave Eiffel's company designed and built the tower, which is \u{2014} at 330 metres \u{2014} the tallest structure in Paris.".to_string(),
+        ]
+    }
+
+    #[test]
+    fn test_sentence_attribution() {
+        let answer = "The Eiffel Tower was completed in 1889 for the World's Fair. \
+                      Gustave Eiffel's company designed the tower. \
+                      It was painted gold by Napoleon in 1750.";
+        let report = RagSuiteReport::analyze(answer, &context(), None, 0.5);
+
+        let chunks: Vec<_> = report.sentences.iter().map(|s| s.chunk).collect();
+        assert_eq!(chunks, vec![Some(0), Some(2), None]);
+        assert!((report.faithfulness - 2.0 / 3.0).abs() < 1e-9);
+
+        // Chunks 0 and 2 are used: AP = (1/1 + 2/3) / 2
+        assert_eq!(report.relevant_chunks, vec![0, 2]);
+        assert!((report.context_precision - 5.0 / 6.0).abs() < 1e-9);
+        assert!(report.context_recall.is_none());
+
+        let unsupported = &report.sentences[2];
+        let text: String = answer
+            .chars()
+            .skip(unsupported.start)
+            .take(unsupported.end - unsupported.start)
+            .collect();
+        assert_eq!(text, "It was painted gold by Napoleon in 1750.");
+    }
+
+    #[test]
+    fn test_citation_checking() {
+        let answer = "Sources say it \u{201C}was completed in 1889 for the World\u{2019}s Fair\u{201D} [1]. \
+                      It is \"the tallest structure in Paris\" [1], and \"made entirely of gold leaf\". \
+                      Locals call it \"the tower\".";
+        let report = RagSuiteReport::analyze(answer, &context(), None, 0.5);
+
+        let statuses: Vec<_> = report
+            .citations
+            .iter()
+            .map(|c| (c.status, c.cited_chunk, c.found_in))
+            .collect();
+        assert_eq!(
+            statuses,
+            vec![
+                (CitationStatus::Verified, Some(0), Some(0)),
+                (CitationStatus::WrongSource, Some(0), Some(2)),
+                (CitationStatus::NotFound, None, None),
+            ]
+        );
+        assert_eq!(report.fabricated_quotes(), 1);
+        assert!((report.citation_accuracy.unwrap() - 1.0 / 3.0).abs() < 1e-9);
+
+        let quote = &report.citations[2];
+        let text: String = answer
+            .chars()
+            .skip(quote.start)
+            .take(quote.end - quote.start)
+            .collect();
+        assert_eq!(text, "made entirely of gold leaf");
+
+        // Ellipses may elide text between fragments of one chunk
+        let elided = RagSuiteReport::analyze(
+            "He wrote \"Gustave Eiffel's company ... the tallest structure\".",
+            &context(),
+            None,
+            0.5,
+        );
+        assert_eq!(elided.citations[0].status, CitationStatus::Verified);
+    }
+
+    #[tokio::test]
+    async fn test_evaluator_with_ground_truth() {
+        let trace = TraceContext {
+            trace_id: 9,
+            edges: vec![],
+            input: Some("When was the Eiffel Tower finished?".to_string()),
+            output: Some(
+                "The Eiffel Tower was completed in 1889, as the \"tallest structure in Rome\"."
+                    .to_string(),
+            ),
+            context: Some(context()),
+            metadata: HashMap::from([(
+                GROUND_TRUTH_METADATA_KEY.to_string(),
+                serde_json::json!("The Eiffel Tower was completed in 1889 for the World's Fair."),
+            )]),
+            eval_trace: None,
+            timestamp_us: 0,
+        };
+
+        let result = RagSuiteEvaluator::new().evaluate(&trace).await.unwrap();
+        assert!(!result.passed, "a fabricated quote fails the trace");
+        assert!(matches!(
+            result.metrics.get("context_recall"),
+            Some(MetricValue::Float(recall)) if *recall == 1.0
+        ));
+        assert!(matches!(
+            result.metrics.get("fabricated_quotes"),
+            Some(MetricValue::Int(1))
+        ));
+        assert_eq!(result.highlights.len(), 1);
+        assert_eq!(result.highlights[0].severity, Severity::Major);
+
+        let missing = TraceContext {
+            context: None,
+            ..trace
+        };
+        assert!(RagSuiteEvaluator::new().evaluate(&missing).await.is_err());
+    }
+}
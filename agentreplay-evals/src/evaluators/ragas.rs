@@ -322,6 +322,7 @@ impl Evaluator for RagasEvaluator {
             cost: Some(total_cost),
             duration_ms: Some(duration_ms),
             actionable_feedback: None,
+            highlights: Vec::new(),
         })
     }
 
@@ -679,6 +680,7 @@ impl Evaluator for QAGFaithfulnessEvaluator {
             cost: Some(cost),
             duration_ms: Some(duration_ms),
             actionable_feedback: None,
+            highlights: Vec::new(),
         })
     }
 
@@ -948,6 +950,7 @@ impl Evaluator for EmbeddingAnswerRelevanceEvaluator {
             cost: Some(cost),
             duration_ms: Some(duration_ms),
             actionable_feedback: None,
+            highlights: Vec::new(),
         })
     }
 
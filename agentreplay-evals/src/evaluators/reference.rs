@@ -617,6 +617,7 @@ impl Evaluator for ReferenceEvaluator {
             cost: Some(0.0),
             duration_ms: Some(duration_ms),
             actionable_feedback: None,
+            highlights: Vec::new(),
         })
     }
 }
@@ -220,6 +220,7 @@ impl Evaluator for RelevanceEvaluator {
             cost: Some(0.0),  // No cost for keyword-based approach
             duration_ms: Some(duration_ms),
             actionable_feedback: None,
+            highlights: Vec::new(),
         })
     }
 
@@ -0,0 +1,695 @@
+// Copyright 2025 AgentReplay (https://github.com/agentreplay)
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program. If not, see <https://www.gnu.org/licenses/>.
+
+//! Structured output validation against a JSON schema or function signature
+//!
+//! Each output is scored on three steps:
+//!
+//! | Score | Output                                                  |
+//! |-------|---------------------------------------------------------|
+//! | 0.0   | does not parse as JSON                                  |
+//! | 0.5   | parses, but violates the schema                         |
+//! | 0.8   | validates, but has fields the schema does not declare   |
+//! | 1.0   | validates with no extra fields                          |
+//!
+//! [`StructuredOutputStrictness`] picks the step required to pass.
+
+use super::tool_correctness::error_range;
+use crate::{
+    EvalError, EvalResult, Evaluator, EvaluatorMetadata, MetricValue, Severity, SpanHighlight,
+    TraceContext,
+};
+use agentreplay_core::TranscriptEventV1;
+use async_trait::async_trait;
+use jsonschema::JSONSchema;
+use serde_json::Value;
+use std::collections::HashMap;
+use std::time::Instant;
+
+/// Metadata key holding the expected output's JSON schema, or an OpenAI
+/// `response_format` object wrapping one
+pub const OUTPUT_SCHEMA_METADATA_KEY: &str = "output_schema";
+
+/// Metadata key holding the function(s) the model may call, as
+/// `{name, parameters}` or OpenAI `{type: "function", function: {...}}`
+/// objects, alone or in an array
+pub const FUNCTION_SIGNATURE_METADATA_KEY: &str = "function_signature";
+
+/// Schema errors reported in the result
+const MAX_REPORTED_ERRORS: usize = 10;
+
+/// How structured an output must be to pass
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StructuredOutputStrictness {
+    /// Valid JSON
+    Parses,
+    /// Valid JSON that satisfies the schema
+    Validates,
+    /// Satisfies the schema and has no undeclared fields
+    NoExtraFields,
+}
+
+/// What an output was checked against
+enum Expected {
+    Schema(Value),
+    Functions(Vec<(String, Value)>),
+}
+
+/// One checked output: the completion or a function call's arguments
+struct Checked {
+    label: String,
+    span_id: u128,
+    part: &'static str,
+    score: f64,
+    parses: bool,
+    errors: Vec<String>,
+    extra_fields: Vec<String>,
+    highlight: Option<SpanHighlight>,
+}
+
+/// Structured output evaluator
+///
+/// Validates the trace output against `metadata["output_schema"]`, or the
+/// model's function calls against `metadata["function_signature"]`. Calls
+/// come from the transcript when the trace has one; otherwise the output
+/// itself is read as `{"name", "arguments"}` or as bare arguments.
+/// Markdown code fences around JSON are tolerated.
+pub struct StructuredOutputEvaluator {
+    strictness: StructuredOutputStrictness,
+    schema: Option<Value>,
+}
+
+impl StructuredOutputEvaluator {
+    /// Create a new evaluator requiring schema-valid output
+    pub fn new() -> Self {
+        Self {
+            strictness: StructuredOutputStrictness::Validates,
+            schema: None,
+        }
+    }
+
+    /// Set the step required to pass (default: `Validates`)
+    pub fn with_strictness(mut self, strictness: StructuredOutputStrictness) -> Self {
+        self.strictness = strictness;
+        self
+    }
+
+    /// Validate against this schema instead of the trace's
+    pub fn with_schema(mut self, schema: Value) -> Self {
+        self.schema = Some(schema);
+        self
+    }
+
+    fn expected(&self, trace: &TraceContext) -> Result<Expected, EvalError> {
+        if let Some(schema) = &self.schema {
+            return Ok(Expected::Schema(unwrap_response_format(schema)));
+        }
+        if let Some(schema) = trace.metadata.get(OUTPUT_SCHEMA_METADATA_KEY) {
+            return Ok(Expected::Schema(unwrap_response_format(&parse_if_string(
+                schema,
+            ))));
+        }
+        if let Some(value) = trace.metadata.get(FUNCTION_SIGNATURE_METADATA_KEY) {
+            let value = parse_if_string(value);
+            let entries = match &value {
+                Value::Array(entries) => entries.clone(),
+                other => vec![other.clone()],
+            };
+            let functions: Vec<(String, Value)> = entries.iter().filter_map(function).collect();
+            if functions.is_empty() {
+                return Err(EvalError::InvalidInput(
+                    "metadata.function_signature has no function with a name".to_string(),
+                ));
+            }
+            return Ok(Expected::Functions(functions));
+        }
+        Err(EvalError::MissingField(format!(
+            "metadata.{} or metadata.{}",
+            OUTPUT_SCHEMA_METADATA_KEY, FUNCTION_SIGNATURE_METADATA_KEY
+        )))
+    }
+
+    fn output_span(trace: &TraceContext) -> u128 {
+        trace
+            .edges
+            .iter()
+            .max_by_key(|e| e.timestamp_us)
+            .map(|e| e.edge_id)
+            .unwrap_or(trace.trace_id)
+    }
+
+    /// Check the trace's function calls
+    fn check_calls(
+        trace: &TraceContext,
+        functions: &[(String, Value)],
+    ) -> Result<Vec<Checked>, EvalError> {
+        let calls: Vec<(String, String, u128)> = trace
+            .eval_trace
+            .iter()
+            .flat_map(|t| &t.transcript)
+            .filter_map(|event| match event {
+                TranscriptEventV1::ToolCall {
+                    name,
+                    arguments,
+                    span_id,
+                    ..
+                } => Some((
+                    name.clone(),
+                    arguments.clone().unwrap_or_else(|| "{}".to_string()),
+                    span_id
+                        .as_deref()
+                        .and_then(|id| u128::from_str_radix(id.trim_start_matches("0x"), 16).ok())
+                        .unwrap_or(trace.trace_id),
+                )),
+                _ => None,
+            })
+            .collect();
+
+        if !calls.is_empty() {
+            return Ok(calls
+                .iter()
+                .map(|(name, arguments, span_id)| {
+                    let Some((_, schema)) = functions.iter().find(|(f, _)| f == name) else {
+                        return Checked::undeclared(name, *span_id);
+                    };
+                    check(
+                        format!("call to {}", name),
+                        *span_id,
+                        SpanHighlight::TOOL_ARGUMENTS,
+                        arguments,
+                        schema,
+                    )
+                })
+                .collect());
+        }
+
+        // No transcript: the output is the call, or its arguments
+        let output = trace
+            .output
+            .as_deref()
+            .ok_or_else(|| EvalError::MissingField("output or tool calls".to_string()))?;
+        let span_id = Self::output_span(trace);
+        let call: Option<Value> = serde_json::from_str(strip_code_fence(output)).ok();
+        if let Some(name) = call
+            .as_ref()
+            .and_then(|c| c.get("name"))
+            .and_then(Value::as_str)
+        {
+            let Some((_, schema)) = functions.iter().find(|(f, _)| f == name) else {
+                return Ok(vec![Checked::undeclared(name, span_id)]);
+            };
+            let arguments = match call.as_ref().and_then(|c| c.get("arguments")) {
+                Some(Value::String(s)) => s.clone(),
+                Some(other) => other.to_string(),
+                None => "{}".to_string(),
+            };
+            return Ok(vec![check(
+                format!("call to {}", name),
+                span_id,
+                SpanHighlight::OUTPUT,
+                &arguments,
+                schema,
+            )]);
+        }
+        match functions {
+            [(name, schema)] => Ok(vec![check(
+                format!("call to {}", name),
+                span_id,
+                SpanHighlight::OUTPUT,
+                output,
+                schema,
+            )]),
+            _ => Err(EvalError::InvalidInput(
+                "Output does not name which of several functions it calls".to_string(),
+            )),
+        }
+    }
+}
+
+impl Default for StructuredOutputEvaluator {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Checked {
+    fn undeclared(name: &str, span_id: u128) -> Self {
+        Self {
+            label: format!("call to {}", name),
+            span_id,
+            part: SpanHighlight::TOOL_ARGUMENTS,
+            score: 0.0,
+            parses: true,
+            errors: vec![format!("'{}' is not a declared function", name)],
+            extra_fields: Vec::new(),
+            highlight: None,
+        }
+    }
+}
+
+/// Score `text` against `schema`
+fn check(label: String, span_id: u128, part: &'static str, text: &str, schema: &Value) -> Checked {
+    let mut checked = Checked {
+        label,
+        span_id,
+        part,
+        score: 0.0,
+        parses: false,
+        errors: Vec::new(),
+        extra_fields: Vec::new(),
+        highlight: None,
+    };
+
+    let value: Value = match serde_json::from_str(strip_code_fence(text)) {
+        Ok(value) => value,
+        Err(e) => {
+            checked.errors.push(format!("not valid JSON: {}", e));
+            // Positions refer to the unfenced text, so only highlight bare JSON
+            if strip_code_fence(text) == text.trim() {
+                let range = error_range(text.trim(), e.line(), e.column());
+                let offset = text.chars().take_while(|c| c.is_whitespace()).count();
+                checked.highlight = Some(SpanHighlight::new(
+                    span_id,
+                    part,
+                    range.start + offset..range.end + offset,
+                    Severity::Major,
+                    format!("Malformed JSON: {}", e),
+                ));
+            }
+            return checked;
+        }
+    };
+    checked.parses = true;
+
+    let compiled = match JSONSchema::compile(schema) {
+        Ok(compiled) => compiled,
+        Err(e) => {
+            checked.errors.push(format!("schema is invalid: {}", e));
+            checked.score = 0.5;
+            return checked;
+        }
+    };
+    if let Err(errors) = compiled.validate(&value) {
+        checked.errors.extend(errors.map(|e| {
+            let path = e.instance_path.to_string();
+            if path.is_empty() {
+                e.to_string()
+            } else {
+                format!("{}: {}", path, e)
+            }
+        }));
+        checked.score = 0.5;
+        return checked;
+    }
+
+    extra_fields(&value, schema, "", &mut checked.extra_fields);
+    checked.score = if checked.extra_fields.is_empty() {
+        1.0
+    } else {
+        0.8
+    };
+    checked
+}
+
+/// Paths of object fields `schema` does not declare
+///
+/// Only `properties` and `items` are followed; fields allowed by an
+/// explicit `additionalProperties` or `patternProperties` are not extra.
+fn extra_fields(value: &Value, schema: &Value, path: &str, out: &mut Vec<String>) {
+    match value {
+        Value::Object(map) => {
+            let Some(properties) = schema.get("properties").and_then(Value::as_object) else {
+                return;
+            };
+            let open = schema
+                .get("additionalProperties")
+                .is_some_and(|a| a != &Value::Bool(false))
+                || schema.get("patternProperties").is_some();
+            for (key, field) in map {
+                let field_path = format!("{}/{}", path, key);
+                match properties.get(key) {
+                    Some(field_schema) => extra_fields(field, field_schema, &field_path, out),
+                    None if !open => out.push(field_path),
+                    None => {}
+                }
+            }
+        }
+        Value::Array(items) => {
+            if let Some(item_schema) = schema.get("items").filter(|s| s.is_object()) {
+                for (i, item) in items.iter().enumerate() {
+                    extra_fields(item, item_schema, &format!("{}/{}", path, i), out);
+                }
+            }
+        }
+        _ => {}
+    }
+}
+
+/// Metadata values captured from span attributes may be JSON strings
+fn parse_if_string(value: &Value) -> Value {
+    match value {
+        Value::String(s) => serde_json::from_str(s).unwrap_or_else(|_| value.clone()),
+        other => other.clone(),
+    }
+}
+
+/// The schema inside an OpenAI `response_format`, or `schema` itself
+fn unwrap_response_format(schema: &Value) -> Value {
+    match schema.get("type").and_then(Value::as_str) {
+        Some("json_schema") => schema
+            .get("json_schema")
+            .and_then(|s| s.get("schema"))
+            .cloned()
+            .unwrap_or_else(|| serde_json::json!({})),
+        Some("json_object") if schema.get("properties").is_none() => {
+            serde_json::json!({ "type": "object" })
+        }
+        _ => schema.clone(),
+    }
+}
+
+/// `(name, parameters schema)` of a function definition
+fn function(entry: &Value) -> Option<(String, Value)> {
+    let function = entry.get("function").unwrap_or(entry);
+    let name = function.get("name")?.as_str()?.to_string();
+    let parameters = function
+        .get("parameters")
+        .or_else(|| function.get("input_schema"))
+        .cloned()
+        .unwrap_or_else(|| serde_json::json!({}));
+    Some((name, parameters))
+}
+
+fn strip_code_fence(text: &str) -> &str {
+    let trimmed = text.trim();
+    trimmed
+        .strip_prefix("```json")
+        .or_else(|| trimmed.strip_prefix("```"))
+        .and_then(|rest| rest.strip_suffix("```"))
+        .map(str::trim)
+        .unwrap_or(trimmed)
+}
+
+#[async_trait]
+impl Evaluator for StructuredOutputEvaluator {
+    fn id(&self) -> &str {
+        "structured_output_v1"
+    }
+
+    async fn evaluate(&self, trace: &TraceContext) -> Result<EvalResult, EvalError> {
+        let start = Instant::now();
+
+        let (source, checked) = match self.expected(trace)? {
+            Expected::Schema(schema) => {
+                let output = trace
+                    .output
+                    .as_deref()
+                    .ok_or_else(|| EvalError::MissingField("output".to_string()))?;
+                let checked = check(
+                    "output".to_string(),
+                    Self::output_span(trace),
+                    SpanHighlight::OUTPUT,
+                    output,
+                    &schema,
+                );
+                ("schema", vec![checked])
+            }
+            Expected::Functions(functions) => {
+                ("function_signature", Self::check_calls(trace, &functions)?)
+            }
+        };
+
+        let required = match self.strictness {
+            StructuredOutputStrictness::Parses => 0.5,
+            StructuredOutputStrictness::Validates => 0.8,
+            StructuredOutputStrictness::NoExtraFields => 1.0,
+        };
+        // A trace is as structured as its worst output
+        let score = checked.iter().map(|c| c.score).fold(1.0, f64::min);
+        let passed = checked.iter().all(|c| c.score >= required);
+
+        let errors: Vec<String> = checked
+            .iter()
+            .flat_map(|c| c.errors.iter().map(move |e| format!("{}: {}", c.label, e)))
+            .collect();
+        let extra: Vec<String> = checked
+            .iter()
+            .flat_map(|c| {
+                c.extra_fields
+                    .iter()
+                    .map(move |f| format!("{}: {}", c.label, f))
+            })
+            .collect();
+
+        let mut metrics = HashMap::new();
+        metrics.insert("score".to_string(), MetricValue::Float(score));
+        metrics.insert(
+            "parses".to_string(),
+            MetricValue::Bool(checked.iter().all(|c| c.parses)),
+        );
+        metrics.insert(
+            "schema_valid".to_string(),
+            MetricValue::Bool(checked.iter().all(|c| c.score >= 0.8)),
+        );
+        metrics.insert(
+            "extra_fields".to_string(),
+            MetricValue::Int(extra.len() as i64),
+        );
+        metrics.insert(
+            "outputs_checked".to_string(),
+            MetricValue::Int(checked.len() as i64),
+        );
+        metrics.insert(
+            "source".to_string(),
+            MetricValue::String(source.to_string()),
+        );
+        if !errors.is_empty() {
+            metrics.insert(
+                "errors".to_string(),
+                MetricValue::Array(
+                    errors
+                        .iter()
+                        .take(MAX_REPORTED_ERRORS)
+                        .cloned()
+                        .map(MetricValue::String)
+                        .collect(),
+                ),
+            );
+        }
+        if !extra.is_empty() {
+            metrics.insert(
+                "extra_field_paths".to_string(),
+                MetricValue::Array(extra.iter().cloned().map(MetricValue::String).collect()),
+            );
+        }
+
+        let explanation = if errors.is_empty() && extra.is_empty() {
+            format!(
+                "All {} structured output(s) match the {}.",
+                checked.len(),
+                source.replace('_', " ")
+            )
+        } else {
+            let mut problems: Vec<&str> = errors.iter().map(String::as_str).collect();
+            problems.extend(extra.iter().map(|f| f.as_str()));
+            format!(
+                "Structured output score {:.1}: {}{}",
+                score,
+                problems
+                    .iter()
+                    .take(3)
+                    .copied()
+                    .collect::<Vec<_>>()
+                    .join("; "),
+                if problems.len() > 3 { "; ..." } else { "" }
+            )
+        };
+
+        let highlights: Vec<SpanHighlight> =
+            checked.iter().filter_map(|c| c.highlight.clone()).collect();
+        let evidence_refs = checked
+            .iter()
+            .filter(|c| c.score < 1.0)
+            .map(|c| format!("{:#x}:{}", c.span_id, c.part))
+            .collect();
+
+        Ok(EvalResult {
+            evaluator_id: self.id().to_string(),
+            evaluator_type: Some("rule-based".to_string()),
+            metrics,
+            passed,
+            explanation: Some(explanation),
+            assertions: Vec::new(),
+            judge_votes: Vec::new(),
+            evidence_refs,
+            confidence: 1.0,
+            cost: Some(0.0),
+            duration_ms: Some(start.elapsed().as_millis() as u64),
+            actionable_feedback: None,
+            highlights,
+        })
+    }
+
+    fn metadata(&self) -> EvaluatorMetadata {
+        EvaluatorMetadata {
+            name: "Structured Output Validator".to_string(),
+            version: "1.0.0".to_string(),
+            description: "Validates JSON outputs and function-call arguments against the trace's schema or function signature, scoring whether they parse, validate and avoid undeclared fields.".to_string(),
+            cost_per_eval: Some(0.0),
+            avg_latency_ms: Some(1),
+            tags: vec![
+                "structured-output".to_string(),
+                "json-schema".to_string(),
+                "function-calling".to_string(),
+                "deterministic".to_string(),
+            ],
+            author: Some("Agentreplay".to_string()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn trace(output: &str, key: &str, expected: Value) -> TraceContext {
+        TraceContext {
+            trace_id: 7,
+            edges: vec![],
+            input: None,
+            output: Some(output.to_string()),
+            context: None,
+            metadata: HashMap::from([(key.to_string(), expected)]),
+            eval_trace: None,
+            timestamp_us: 0,
+        }
+    }
+
+    fn score(result: &EvalResult) -> f64 {
+        match result.metrics.get("score") {
+            Some(MetricValue::Float(score)) => *score,
+            other => panic!("unexpected score {:?}", other),
+        }
+    }
+
+    fn schema() -> Value {
+        json!({
+            "type": "object",
+            "properties": {
+                "answer": { "type": "string" },
+                "sources": { "type": "array", "items": { "type": "string" } }
+            },
+            "required": ["answer"]
+        })
+    }
+
+    #[tokio::test]
+    async fn test_schema_scoring() {
+        let evaluator = StructuredOutputEvaluator::new();
+        let cases = [
+            ("```json\n{\"answer\": \"42\"}\n```", 1.0, true),
+            ("{\"answer\": \"42\", \"confidence\": 0.9}", 0.8, true),
+            ("{\"sources\": []}", 0.5, false),
+            ("{\"answer\": 42,}", 0.0, false),
+        ];
+        for (output, expected, passed) in cases {
+            let result = evaluator
+                .evaluate(&trace(output, OUTPUT_SCHEMA_METADATA_KEY, schema()))
+                .await
+                .unwrap();
+            assert_eq!(score(&result), expected, "{}", output);
+            assert_eq!(result.passed, passed, "{}", output);
+        }
+
+        let strict = StructuredOutputEvaluator::new()
+            .with_strictness(StructuredOutputStrictness::NoExtraFields);
+        let result = strict
+            .evaluate(&trace(
+                "{\"answer\": \"42\", \"confidence\": 0.9}",
+                OUTPUT_SCHEMA_METADATA_KEY,
+                schema(),
+            ))
+            .await
+            .unwrap();
+        assert!(!result.passed);
+        assert!(matches!(
+            result.metrics.get("extra_field_paths"),
+            Some(MetricValue::Array(paths))
+                if matches!(paths.as_slice(), [MetricValue::String(p)] if p == "output: /confidence")
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_malformed_output_is_highlighted() {
+        let output = "{\"answer\": 42,}";
+        let result = StructuredOutputEvaluator::new()
+            .evaluate(&trace(output, OUTPUT_SCHEMA_METADATA_KEY, schema()))
+            .await
+            .unwrap();
+        assert_eq!(result.highlights.len(), 1);
+        assert_eq!(result.highlights[0].trace_part, SpanHighlight::OUTPUT);
+        assert!(result.highlights[0].is_valid(output.chars().count()));
+    }
+
+    #[tokio::test]
+    async fn test_response_format_and_function_signature() {
+        let response_format = json!({
+            "type": "json_schema",
+            "json_schema": { "name": "answer", "strict": true, "schema": schema() }
+        });
+        let result = StructuredOutputEvaluator::new()
+            .evaluate(&trace(
+                "{\"answer\": \"yes\"}",
+                OUTPUT_SCHEMA_METADATA_KEY,
+                Value::String(response_format.to_string()),
+            ))
+            .await
+            .unwrap();
+        assert_eq!(score(&result), 1.0);
+
+        let tools = json!([{
+            "type": "function",
+            "function": {
+                "name": "get_weather",
+                "parameters": {
+                    "type": "object",
+                    "properties": { "city": { "type": "string" } },
+                    "required": ["city"]
+                }
+            }
+        }]);
+        let result = StructuredOutputEvaluator::new()
+            .evaluate(&trace(
+                "{\"name\": \"get_weather\", \"arguments\": \"{\\\"town\\\": \\\"Oslo\\\"}\"}",
+                FUNCTION_SIGNATURE_METADATA_KEY,
+                tools.clone(),
+            ))
+            .await
+            .unwrap();
+        assert_eq!(score(&result), 0.5);
+        assert!(!result.passed);
+
+        let result = StructuredOutputEvaluator::new()
+            .evaluate(&trace(
+                "{\"name\": \"get_time\", \"arguments\": {}}",
+                FUNCTION_SIGNATURE_METADATA_KEY,
+                tools,
+            ))
+            .await
+            .unwrap();
+        assert_eq!(score(&result), 0.0);
+    }
+}
@@ -506,6 +506,7 @@ impl Evaluator for TaskCompletionEvaluator {
             cost: Some(cost),
             duration_ms: Some(duration_ms),
             actionable_feedback: None,
+            highlights: Vec::new(),
         })
     }
 
@@ -687,6 +688,7 @@ impl StatefulTaskCompletionEvaluator {
             cost: textual_result.cost,
             duration_ms: Some(duration_ms),
             actionable_feedback: None,
+            highlights: Vec::new(),
         })
     }
 
@@ -29,10 +29,11 @@
 
 use crate::{
     llm_client::{LLMClient, LLMError},
-    EvalError, EvalResult, Evaluator, EvaluatorMetadata, MetricValue, TraceContext,
+    EvalError, EvalResult, Evaluator, EvaluatorMetadata, MetricValue, Severity, SpanHighlight,
+    TraceContext,
 };
 use async_trait::async_trait;
-use agentreplay_core::SpanType;
+use agentreplay_core::{SpanType, TranscriptEventV1};
 use serde::{Deserialize, Serialize};
 use std::collections::{HashMap, HashSet};
 use std::sync::Arc;
@@ -164,6 +165,45 @@ impl ToolCorrectnessEvaluator {
             .collect()
     }
 
+    /// Highlight tool call arguments that are not valid JSON, from the parse error to end of line
+    fn malformed_argument_highlights(trace: &TraceContext) -> Vec<SpanHighlight> {
+        let Some(eval_trace) = &trace.eval_trace else {
+            return Vec::new();
+        };
+
+        eval_trace
+            .transcript
+            .iter()
+            .filter_map(|event| {
+                let TranscriptEventV1::ToolCall {
+                    name,
+                    arguments: Some(arguments),
+                    span_id,
+                    ..
+                } = event
+                else {
+                    return None;
+                };
+                if arguments.trim().is_empty() {
+                    return None;
+                }
+                let err = serde_json::from_str::<serde_json::Value>(arguments).err()?;
+
+                let span_id = span_id
+                    .as_deref()
+                    .and_then(|id| u128::from_str_radix(id.trim_start_matches("0x"), 16).ok())
+                    .unwrap_or(trace.trace_id);
+                Some(SpanHighlight::new(
+                    span_id,
+                    SpanHighlight::TOOL_ARGUMENTS,
+                    error_range(arguments, err.line(), err.column()),
+                    Severity::Major,
+                    format!("Malformed arguments for tool '{}': {}", name, err),
+                ))
+            })
+            .collect()
+    }
+
     /// Calculate tool name precision, recall, and F1
     fn calculate_tool_metrics(
         &self,
@@ -395,6 +435,12 @@ impl Evaluator for ToolCorrectnessEvaluator {
             Some(0.0)
         };
 
+        let highlights = Self::malformed_argument_highlights(trace);
+        metrics.insert(
+            "malformed_tool_arguments".to_string(),
+            MetricValue::Int(highlights.len() as i64),
+        );
+
         let passed = final_score >= self.threshold;
 
         let explanation = format!(
@@ -425,6 +471,7 @@ impl Evaluator for ToolCorrectnessEvaluator {
             cost,
             duration_ms: Some(duration_ms),
             actionable_feedback: None,
+            highlights,
         })
     }
 
@@ -445,3 +492,91 @@ impl Evaluator for ToolCorrectnessEvaluator {
         }
     }
 }
+
+/// Character range from a serde_json error position (1-based line/column) to the end of that line
+pub(crate) fn error_range(text: &str, line: usize, column: usize) -> std::ops::Range<usize> {
+    let mut offset = 0;
+    for (idx, current) in text.split('\n').enumerate() {
+        let len = current.chars().count();
+        if idx + 1 == line {
+            let start = offset + column.saturating_sub(1).min(len.saturating_sub(1));
+            return start..(offset + len).max(start + 1);
+        }
+        offset += len + 1;
+    }
+    0..text.chars().count().max(1)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use agentreplay_core::{EvalTraceV1, OutcomeV1, TraceStatsV1};
+
+    fn trace_with_tool_call(arguments: &str) -> TraceContext {
+        TraceContext {
+            trace_id: 1,
+            edges: vec![],
+            input: None,
+            output: None,
+            context: None,
+            metadata: HashMap::new(),
+            eval_trace: Some(EvalTraceV1 {
+                schema_version: "1".to_string(),
+                trace_id: "0x1".to_string(),
+                trace_ref: None,
+                session_id: 0,
+                spans: vec![],
+                transcript: vec![TranscriptEventV1::ToolCall {
+                    id: "call-1".to_string(),
+                    name: "search".to_string(),
+                    arguments: Some(arguments.to_string()),
+                    timestamp_us: 0,
+                    span_id: Some("0x2a".to_string()),
+                    metadata: HashMap::new(),
+                }],
+                outcome: OutcomeV1 {
+                    status: "ok".to_string(),
+                    error: None,
+                    messages: vec![],
+                    output_text: None,
+                    metadata: HashMap::new(),
+                },
+                outcome_v2: None,
+                stats: TraceStatsV1 {
+                    total_tokens: 0,
+                    input_tokens: 0,
+                    output_tokens: 0,
+                    cost_usd: None,
+                    latency_ms: None,
+                },
+            }),
+            timestamp_us: 0,
+        }
+    }
+
+    #[test]
+    fn test_malformed_arguments_are_highlighted() {
+        let arguments = "{\n  \"query\": \"rust\",\n  \"limit\": ten\n}";
+        let highlights = ToolCorrectnessEvaluator::malformed_argument_highlights(
+            &trace_with_tool_call(arguments),
+        );
+
+        assert_eq!(highlights.len(), 1);
+        let highlight = &highlights[0];
+        assert_eq!(highlight.span_id, 0x2a);
+        assert_eq!(highlight.trace_part, SpanHighlight::TOOL_ARGUMENTS);
+        assert_eq!(highlight.severity, Severity::Major);
+        assert!(highlight.is_valid(arguments.chars().count()));
+        let flagged: String = arguments
+            .chars()
+            .skip(highlight.start_offset)
+            .take(highlight.end_offset - highlight.start_offset)
+            .collect();
+        assert!(!flagged.is_empty() && "  \"limit\": ten".ends_with(&flagged));
+
+        let valid = ToolCorrectnessEvaluator::malformed_argument_highlights(&trace_with_tool_call(
+            "{\"query\": \"rust\"}",
+        ));
+        assert!(valid.is_empty());
+    }
+}
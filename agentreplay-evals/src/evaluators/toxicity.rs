@@ -458,6 +458,7 @@ impl ToxicityDetector {
             cost: llm_cost,
             duration_ms: Some(duration_ms),
             actionable_feedback: None,
+            highlights: Vec::new(),
         })
     }
 
@@ -547,6 +548,7 @@ impl ToxicityDetector {
             cost: Some(0.0), // No cost for keyword-based approach
             duration_ms: Some(duration_ms),
             actionable_feedback: None,
+            highlights: Vec::new(),
         })
     }
 }
@@ -354,6 +354,7 @@ impl Evaluator for TrajectoryEfficiencyEvaluator {
             cost: Some(0.0),  // No cost - purely deterministic
             duration_ms: Some(duration_ms),
             actionable_feedback: None,
+            highlights: Vec::new(),
         })
     }
 
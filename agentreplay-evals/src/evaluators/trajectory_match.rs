@@ -0,0 +1,445 @@
+// Copyright 2025 AgentReplay (https://github.com/agentreplay)
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program. If not, see <https://www.gnu.org/licenses/>.
+
+//! Trajectory match against a test case's reference tool sequence
+//!
+//! Each reference step passes or fails on its own, so a planning regression
+//! shows up as the step the agent got wrong:
+//!
+//! - `exact`: step `i` is the `i`-th call; extra calls fail the trace
+//! - `in_order`: steps are matched in order, other calls may come between
+//! - `any_order`: each step is matched to a distinct call with its name
+
+use crate::{EvalError, EvalResult, Evaluator, EvaluatorMetadata, MetricValue, TraceContext};
+use agentreplay_core::{
+    AssertionResult, ReferenceTrajectory, TrajectoryMatchMode, TranscriptEventV1,
+};
+use async_trait::async_trait;
+use serde::Serialize;
+use std::collections::HashMap;
+use std::time::Instant;
+
+/// Metadata key holding a serialized [`ReferenceTrajectory`]
+pub const REFERENCE_TRAJECTORY_METADATA_KEY: &str = "reference_trajectory";
+
+/// Outcome of one reference step
+#[derive(Debug, Clone, Serialize)]
+pub struct TrajectoryStep {
+    pub expected: String,
+    /// Position of the matched call in the actual sequence
+    pub matched_call: Option<usize>,
+    /// Tool called at this position (exact mode only)
+    pub actual: Option<String>,
+    pub passed: bool,
+}
+
+/// Step-level comparison of a tool call sequence with a reference
+#[derive(Debug, Clone, Serialize)]
+pub struct TrajectoryMatch {
+    pub mode: TrajectoryMatchMode,
+    pub steps: Vec<TrajectoryStep>,
+    /// Positions of calls past the reference's end (exact mode only)
+    pub unexpected_calls: Vec<usize>,
+    pub score: f64,
+    pub passed: bool,
+}
+
+impl TrajectoryMatch {
+    pub fn compare(reference: &ReferenceTrajectory, actual: &[String]) -> Self {
+        let mut used = vec![false; actual.len()];
+        let mut next = 0;
+        let steps: Vec<TrajectoryStep> = reference
+            .tools
+            .iter()
+            .enumerate()
+            .map(|(i, expected)| {
+                let matched_call = match reference.mode {
+                    TrajectoryMatchMode::Exact => (actual.get(i) == Some(expected)).then_some(i),
+                    TrajectoryMatchMode::InOrder => {
+                        let found = (next..actual.len()).find(|&j| &actual[j] == expected);
+                        if let Some(j) = found {
+                            next = j + 1;
+                        }
+                        found
+                    }
+                    TrajectoryMatchMode::AnyOrder => {
+                        let found = (0..actual.len()).find(|&j| !used[j] && &actual[j] == expected);
+                        if let Some(j) = found {
+                            used[j] = true;
+                        }
+                        found
+                    }
+                };
+                TrajectoryStep {
+                    expected: expected.clone(),
+                    matched_call,
+                    actual: match reference.mode {
+                        TrajectoryMatchMode::Exact => actual.get(i).cloned(),
+                        _ => None,
+                    },
+                    passed: matched_call.is_some(),
+                }
+            })
+            .collect();
+
+        let unexpected_calls: Vec<usize> = match reference.mode {
+            TrajectoryMatchMode::Exact => (reference.tools.len()..actual.len()).collect(),
+            _ => Vec::new(),
+        };
+
+        let matched = steps.iter().filter(|s| s.passed).count();
+        let denominator = steps.len() + unexpected_calls.len();
+        let score = if denominator == 0 {
+            1.0
+        } else {
+            matched as f64 / denominator as f64
+        };
+
+        Self {
+            mode: reference.mode,
+            passed: matched == steps.len() && unexpected_calls.is_empty(),
+            steps,
+            unexpected_calls,
+            score,
+        }
+    }
+}
+
+/// Evaluator comparing a trace's tool calls with a reference trajectory
+///
+/// The reference comes from [`with_reference`](Self::with_reference) or,
+/// failing that, the trace's `reference_trajectory` metadata.
+pub struct TrajectoryMatchEvaluator {
+    reference: Option<ReferenceTrajectory>,
+}
+
+impl TrajectoryMatchEvaluator {
+    pub fn new() -> Self {
+        Self { reference: None }
+    }
+
+    pub fn with_reference(mut self, reference: ReferenceTrajectory) -> Self {
+        self.reference = Some(reference);
+        self
+    }
+
+    /// Tool calls in the trace's transcript, by time, with their span ids
+    fn tool_calls(trace: &TraceContext) -> Result<Vec<(String, Option<String>)>, EvalError> {
+        let eval_trace = trace
+            .eval_trace
+            .as_ref()
+            .ok_or_else(|| EvalError::MissingField("eval_trace".to_string()))?;
+
+        let mut calls: Vec<(u64, String, Option<String>)> = eval_trace
+            .transcript
+            .iter()
+            .filter_map(|event| match event {
+                TranscriptEventV1::ToolCall {
+                    name,
+                    timestamp_us,
+                    span_id,
+                    ..
+                } => Some((*timestamp_us, name.clone(), span_id.clone())),
+                _ => None,
+            })
+            .collect();
+        calls.sort_by_key(|(timestamp_us, _, _)| *timestamp_us);
+        Ok(calls
+            .into_iter()
+            .map(|(_, name, span)| (name, span))
+            .collect())
+    }
+}
+
+impl Default for TrajectoryMatchEvaluator {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[async_trait]
+impl Evaluator for TrajectoryMatchEvaluator {
+    fn id(&self) -> &str {
+        "trajectory_match_v1"
+    }
+
+    async fn evaluate(&self, trace: &TraceContext) -> Result<EvalResult, EvalError> {
+        let start = Instant::now();
+
+        let reference = match &self.reference {
+            Some(reference) => reference.clone(),
+            None => {
+                let value = trace
+                    .metadata
+                    .get(REFERENCE_TRAJECTORY_METADATA_KEY)
+                    .ok_or_else(|| {
+                        EvalError::MissingField(REFERENCE_TRAJECTORY_METADATA_KEY.to_string())
+                    })?;
+                serde_json::from_value(value.clone()).map_err(|e| {
+                    EvalError::InvalidInput(format!("Invalid reference trajectory: {}", e))
+                })?
+            }
+        };
+
+        let calls = Self::tool_calls(trace)?;
+        let names: Vec<String> = calls.iter().map(|(name, _)| name.clone()).collect();
+        let comparison = TrajectoryMatch::compare(&reference, &names);
+
+        let evidence =
+            |position: usize| -> Vec<String> { calls[position].1.iter().cloned().collect() };
+        let mut assertions: Vec<AssertionResult> = comparison
+            .steps
+            .iter()
+            .enumerate()
+            .map(|(i, step)| {
+                let message = match (step.matched_call, &step.actual) {
+                    (Some(position), _) => {
+                        format!("{} called at position {}", step.expected, position + 1)
+                    }
+                    (None, Some(actual)) => format!("expected {}, got {}", step.expected, actual),
+                    (None, None) => format!("{} was never called", step.expected),
+                };
+                AssertionResult {
+                    id: format!("step_{}", i + 1),
+                    passed: step.passed,
+                    evidence_refs: step.matched_call.map(evidence).unwrap_or_default(),
+                    message: Some(message),
+                }
+            })
+            .collect();
+        assertions.extend(
+            comparison
+                .unexpected_calls
+                .iter()
+                .map(|&position| AssertionResult {
+                    id: format!("unexpected_{}", position + 1),
+                    passed: false,
+                    evidence_refs: evidence(position),
+                    message: Some(format!("unexpected call to {}", names[position])),
+                }),
+        );
+
+        let matched = comparison.steps.iter().filter(|s| s.passed).count();
+        let mut metrics = HashMap::new();
+        metrics.insert("score".to_string(), MetricValue::Float(comparison.score));
+        metrics.insert(
+            "matched_steps".to_string(),
+            MetricValue::Int(matched as i64),
+        );
+        metrics.insert(
+            "expected_steps".to_string(),
+            MetricValue::Int(comparison.steps.len() as i64),
+        );
+        metrics.insert(
+            "actual_steps".to_string(),
+            MetricValue::Int(names.len() as i64),
+        );
+        metrics.insert(
+            "actual_trajectory".to_string(),
+            MetricValue::Array(names.iter().cloned().map(MetricValue::String).collect()),
+        );
+
+        let first_failure = assertions.iter().find(|a| !a.passed);
+        let explanation = match first_failure {
+            None => format!(
+                "{}/{} reference steps matched ({:?} match)",
+                matched,
+                comparison.steps.len(),
+                reference.mode
+            ),
+            Some(failure) => format!(
+                "{}/{} reference steps matched ({:?} match); first failure at {}: {}",
+                matched,
+                comparison.steps.len(),
+                reference.mode,
+                failure.id,
+                failure.message.as_deref().unwrap_or_default()
+            ),
+        };
+        let evidence_refs = calls.iter().filter_map(|(_, span)| span.clone()).collect();
+
+        Ok(EvalResult {
+            evaluator_id: self.id().to_string(),
+            evaluator_type: Some("rule-based".to_string()),
+            metrics,
+            passed: comparison.passed,
+            explanation: Some(explanation),
+            assertions,
+            judge_votes: Vec::new(),
+            evidence_refs,
+            confidence: 1.0,
+            cost: Some(0.0),
+            duration_ms: Some(start.elapsed().as_millis() as u64),
+            actionable_feedback: None,
+            highlights: Vec::new(),
+        })
+    }
+
+    fn metadata(&self) -> EvaluatorMetadata {
+        EvaluatorMetadata {
+            name: "Trajectory Match".to_string(),
+            version: "1.0.0".to_string(),
+            description: "Compares the trace's tool call sequence with a reference trajectory (exact, in-order subsequence or any-order match) and reports pass/fail per step.".to_string(),
+            cost_per_eval: Some(0.0),
+            avg_latency_ms: Some(1),
+            tags: vec![
+                "agent".to_string(),
+                "trajectory".to_string(),
+                "tool-calling".to_string(),
+                "deterministic".to_string(),
+            ],
+            author: Some("Agentreplay".to_string()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use agentreplay_core::{EvalTraceV1, OutcomeV1, TraceStatsV1};
+
+    fn names(tools: &[&str]) -> Vec<String> {
+        tools.iter().map(|t| t.to_string()).collect()
+    }
+
+    fn reference(tools: &[&str], mode: TrajectoryMatchMode) -> ReferenceTrajectory {
+        ReferenceTrajectory::new(names(tools), mode)
+    }
+
+    fn passed_steps(comparison: &TrajectoryMatch) -> Vec<bool> {
+        comparison.steps.iter().map(|s| s.passed).collect()
+    }
+
+    #[test]
+    fn test_match_modes() {
+        let actual = names(&["search", "lookup", "book", "email"]);
+
+        let exact = TrajectoryMatch::compare(
+            &reference(&["search", "book", "email"], TrajectoryMatchMode::Exact),
+            &actual,
+        );
+        assert_eq!(passed_steps(&exact), vec![true, false, false]);
+        assert_eq!(exact.steps[1].actual.as_deref(), Some("lookup"));
+        assert_eq!(exact.unexpected_calls, vec![3]);
+        assert!((exact.score - 0.25).abs() < 1e-9);
+        assert!(!exact.passed);
+
+        let in_order = TrajectoryMatch::compare(
+            &reference(&["search", "book", "email"], TrajectoryMatchMode::InOrder),
+            &actual,
+        );
+        assert_eq!(passed_steps(&in_order), vec![true, true, true]);
+        assert_eq!(in_order.steps[1].matched_call, Some(2));
+        assert!(in_order.passed);
+
+        // Booking before searching breaks the order, not the set
+        let reversed = names(&["book", "search"]);
+        let in_order = TrajectoryMatch::compare(
+            &reference(&["search", "book"], TrajectoryMatchMode::InOrder),
+            &reversed,
+        );
+        assert_eq!(passed_steps(&in_order), vec![true, false]);
+        let any_order = TrajectoryMatch::compare(
+            &reference(&["search", "book"], TrajectoryMatchMode::AnyOrder),
+            &reversed,
+        );
+        assert!(any_order.passed);
+
+        // Each reference step needs its own call
+        let any_order = TrajectoryMatch::compare(
+            &reference(&["search", "search"], TrajectoryMatchMode::AnyOrder),
+            &reversed,
+        );
+        assert_eq!(passed_steps(&any_order), vec![true, false]);
+        assert!((any_order.score - 0.5).abs() < 1e-9);
+    }
+
+    #[tokio::test]
+    async fn test_evaluator_step_assertions() {
+        let call = |name: &str, timestamp_us: u64| TranscriptEventV1::ToolCall {
+            id: format!("call_{}", timestamp_us),
+            name: name.to_string(),
+            arguments: None,
+            timestamp_us,
+            span_id: Some(format!("0x{:x}", timestamp_us)),
+            metadata: HashMap::new(),
+        };
+        let eval_trace = EvalTraceV1 {
+            schema_version: "1".to_string(),
+            trace_id: "0x1".to_string(),
+            trace_ref: None,
+            session_id: 0,
+            spans: vec![],
+            transcript: vec![call("book", 20), call("search", 10)],
+            outcome: OutcomeV1 {
+                status: "ok".to_string(),
+                error: None,
+                messages: vec![],
+                output_text: None,
+                metadata: HashMap::new(),
+            },
+            outcome_v2: None,
+            stats: TraceStatsV1 {
+                total_tokens: 0,
+                input_tokens: 0,
+                output_tokens: 0,
+                cost_usd: None,
+                latency_ms: None,
+            },
+        };
+        let trace = TraceContext {
+            trace_id: 1,
+            edges: vec![],
+            input: None,
+            output: None,
+            context: None,
+            metadata: HashMap::from([(
+                REFERENCE_TRAJECTORY_METADATA_KEY.to_string(),
+                serde_json::json!({"tools": ["search", "pay", "book"], "mode": "in_order"}),
+            )]),
+            eval_trace: Some(eval_trace),
+            timestamp_us: 0,
+        };
+
+        let result = TrajectoryMatchEvaluator::new()
+            .evaluate(&trace)
+            .await
+            .unwrap();
+        assert!(!result.passed);
+        let steps: Vec<_> = result
+            .assertions
+            .iter()
+            .map(|a| (a.id.as_str(), a.passed))
+            .collect();
+        assert_eq!(
+            steps,
+            vec![("step_1", true), ("step_2", false), ("step_3", true)]
+        );
+        assert_eq!(result.assertions[0].evidence_refs, vec!["0xa"]);
+        assert_eq!(
+            result.assertions[1].message.as_deref(),
+            Some("pay was never called")
+        );
+
+        let missing = TraceContext {
+            metadata: HashMap::new(),
+            ..trace
+        };
+        assert!(TrajectoryMatchEvaluator::new()
+            .evaluate(&missing)
+            .await
+            .is_err());
+    }
+}
@@ -77,7 +77,9 @@ pub use actionable_feedback::{
     ActionableFeedback, FailureCategory, FailureLocation, FailureMode, FeedbackBuilder,
     ImprovementSuggestion, Severity, SimilarPassingTrace,
 };
-pub use agentreplay_core::{EvalResultV1 as EvalResult, MetricValueV1 as MetricValue};
+pub use agentreplay_core::{
+    EvalResultV1 as EvalResult, MetricValueV1 as MetricValue, SpanHighlight,
+};
 pub use comparator::{
     Comparator, ComparisonResult, EffectSize, MetricComparison, RecommendedAction, Winner,
 };
@@ -92,9 +94,9 @@ pub use progressive_eval::{
 };
 pub use registry::{EvaluatorRegistry, TaskEvaluationOutput};
 pub use statistics::{
-    Bootstrap, BootstrapCI, BootstrapMethod, InterRaterReliability, KappaInterpretation,
-    KappaResult, PowerAnalysis, PowerAnalyzer, PowerInterpretation, RetrospectivePower, TestType,
-    WeightedKappaResult,
+    probability_of_superiority, Bootstrap, BootstrapCI, BootstrapMethod, InterRaterReliability,
+    KappaInterpretation, KappaResult, PowerAnalysis, PowerAnalyzer, PowerInterpretation,
+    RetrospectivePower, TestType, WeightedKappaResult,
 };
 pub use trial_sandbox::{apply_outcome_v2, TrialRunner, TrialSandbox};
 pub use trace_summarizer::{HierarchicalSummary, SpanSummary, SummarizerConfig, TraceSummarizer};
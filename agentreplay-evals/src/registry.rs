@@ -551,6 +551,7 @@ mod tests {
                 cost: Some(0.0001),
                 duration_ms: None,
                 actionable_feedback: None,
+                highlights: Vec::new(),
             })
         }
 
@@ -332,6 +332,77 @@ impl Bootstrap {
             acceleration: 0.0,
         }
     }
+
+    /// Estimate P(mean(A) > mean(B)) from independent resamples of each group
+    ///
+    /// Ties count as half a win so identical groups land on 0.5.
+    pub fn superiority_probability(&self, group_a: &[f64], group_b: &[f64]) -> f64 {
+        if group_a.is_empty() || group_b.is_empty() || self.n_resamples == 0 {
+            return 0.5;
+        }
+
+        let mut rng: Box<dyn RngCore> = match self.seed {
+            Some(s) => Box::new(StdRng::seed_from_u64(s)),
+            None => Box::new(thread_rng()),
+        };
+
+        let mut wins = 0.0;
+        for _ in 0..self.n_resamples {
+            let mean_a = (0..group_a.len())
+                .map(|_| group_a[rng.gen_range(0..group_a.len())])
+                .sum::<f64>()
+                / group_a.len() as f64;
+            let mean_b = (0..group_b.len())
+                .map(|_| group_b[rng.gen_range(0..group_b.len())])
+                .sum::<f64>()
+                / group_b.len() as f64;
+
+            if mean_a > mean_b {
+                wins += 1.0;
+            } else if mean_a == mean_b {
+                wins += 0.5;
+            }
+        }
+
+        wins / self.n_resamples as f64
+    }
+}
+
+/// Posterior probability that group A's true mean exceeds group B's
+///
+/// Uses flat priors, so each mean's posterior is approximately
+/// `N(sample_mean, s² / n)` and the difference is normal as well. This is
+/// cheap enough to re-evaluate after every observation, which is what
+/// sequential experiment monitoring needs; pair it with
+/// [`Bootstrap::difference_ci`] when a distribution-free interval is wanted.
+pub fn probability_of_superiority(group_a: &[f64], group_b: &[f64]) -> f64 {
+    if group_a.len() < 2 || group_b.len() < 2 {
+        return 0.5;
+    }
+
+    let (mean_a, var_a) = mean_and_variance(group_a);
+    let (mean_b, var_b) = mean_and_variance(group_b);
+    let se = (var_a / group_a.len() as f64 + var_b / group_b.len() as f64).sqrt();
+
+    if se == 0.0 {
+        return if mean_a > mean_b {
+            1.0
+        } else if mean_a < mean_b {
+            0.0
+        } else {
+            0.5
+        };
+    }
+
+    normal_cdf((mean_a - mean_b) / se)
+}
+
+/// Sample mean and unbiased variance
+fn mean_and_variance(data: &[f64]) -> (f64, f64) {
+    let n = data.len() as f64;
+    let mean = data.iter().sum::<f64>() / n;
+    let variance = data.iter().map(|x| (x - mean).powi(2)).sum::<f64>() / (n - 1.0);
+    (mean, variance)
 }
 
 // ============================================================================
@@ -907,6 +978,20 @@ mod tests {
         assert!(ci.upper <= 10.0);
     }
 
+    #[test]
+    fn test_probability_of_superiority() {
+        let control = vec![0.50, 0.52, 0.48, 0.51, 0.49, 0.50, 0.53, 0.47];
+        let better: Vec<f64> = control.iter().map(|x| x + 0.1).collect();
+
+        assert!(probability_of_superiority(&better, &control) > 0.99);
+        assert!(probability_of_superiority(&control, &better) < 0.01);
+        assert!((probability_of_superiority(&control, &control) - 0.5).abs() < 1e-9);
+
+        let bootstrap = Bootstrap::new().with_seed(7).with_resamples(500);
+        assert!(bootstrap.superiority_probability(&better, &control) > 0.99);
+        assert!((bootstrap.superiority_probability(&control, &control) - 0.5).abs() < 0.1);
+    }
+
     #[test]
     fn test_power_analysis() {
         let analyzer = PowerAnalyzer::new();
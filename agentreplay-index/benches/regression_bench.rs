@@ -0,0 +1,160 @@
+// Copyright 2025 AgentReplay (https://github.com/agentreplay)
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program. If not, see <https://www.gnu.org/licenses/>.
+
+//! HNSW Regression Benchmark
+//!
+//! Single-shot run that measures insert throughput, search latency percentiles
+//! and recall@10, records the result in the benchmark store, and compares it
+//! with the previous run on the same machine.
+//!
+//! The store lives in `$AGENTREPLAY_BENCH_DIR` (default: `./agentreplay-data/benchmarks`),
+//! so `agentreplay benchmarks compare index/hnsw` can inspect the history.
+//!
+//! Run with: `cargo bench --bench regression_bench`
+
+use agentreplay_index::hnsw::{HnswConfig, HnswIndex};
+use agentreplay_storage::benchmark_store::{
+    BenchmarkComparison, BenchmarkMetric, BenchmarkRun, BenchmarkStore,
+    DEFAULT_REGRESSION_THRESHOLD_PCT,
+};
+use rand::Rng;
+use std::collections::HashSet;
+use std::time::Instant;
+
+const SUITE: &str = "index/hnsw";
+const DIM: usize = 128;
+const CORPUS_SIZE: usize = 10_000;
+const NUM_QUERIES: usize = 200;
+const K: usize = 10;
+
+fn generate_random_vector(dim: usize) -> Vec<f32> {
+    let mut rng = rand::thread_rng();
+    (0..dim).map(|_| rng.gen::<f32>()).collect()
+}
+
+fn cosine_distance(a: &[f32], b: &[f32]) -> f32 {
+    let dot: f32 = a.iter().zip(b.iter()).map(|(x, y)| x * y).sum();
+    let norm_a: f32 = a.iter().map(|x| x * x).sum::<f32>().sqrt();
+    let norm_b: f32 = b.iter().map(|x| x * x).sum::<f32>().sqrt();
+    1.0 - (dot / (norm_a * norm_b + 1e-8))
+}
+
+/// Brute-force exact nearest neighbor search (ground truth)
+fn exact_search(query: &[f32], corpus: &[Vec<f32>], k: usize) -> HashSet<u128> {
+    let mut distances: Vec<(usize, f32)> = corpus
+        .iter()
+        .enumerate()
+        .map(|(idx, vec)| (idx, cosine_distance(query, vec)))
+        .collect();
+
+    distances.sort_by(|a, b| a.1.partial_cmp(&b.1).unwrap());
+    distances
+        .into_iter()
+        .take(k)
+        .map(|(idx, _)| idx as u128)
+        .collect()
+}
+
+fn print_comparison(comparison: &BenchmarkComparison) {
+    println!("Compared to {}", comparison.baseline_run_id);
+    for delta in &comparison.deltas {
+        println!(
+            "  {} {:<16} {:>12.2} -> {:>12.2} {:<8} ({:+.1}%)",
+            if delta.regression { "✗" } else { "✓" },
+            delta.name,
+            delta.baseline,
+            delta.current,
+            delta.unit,
+            delta.change_pct
+        );
+    }
+}
+
+fn main() {
+    let corpus: Vec<Vec<f32>> = (0..CORPUS_SIZE)
+        .map(|_| generate_random_vector(DIM))
+        .collect();
+    let index = HnswIndex::new(DIM, HnswConfig::default());
+
+    // Insert throughput
+    let start = Instant::now();
+    for (i, vec) in corpus.iter().enumerate() {
+        index.insert(i as u128, vec.clone()).unwrap();
+    }
+    let insert_throughput = CORPUS_SIZE as f64 / start.elapsed().as_secs_f64();
+
+    // Search latency and recall@K
+    let mut latencies = Vec::with_capacity(NUM_QUERIES);
+    let mut total_recall = 0.0;
+    for _ in 0..NUM_QUERIES {
+        let query = generate_random_vector(DIM);
+
+        let search_start = Instant::now();
+        let results = index.search(&query, K).unwrap();
+        latencies.push(search_start.elapsed());
+
+        let truth = exact_search(&query, &corpus, K);
+        let found = results.iter().filter(|(id, _)| truth.contains(id)).count();
+        total_recall += found as f64 / K as f64;
+    }
+    let recall = total_recall / NUM_QUERIES as f64;
+
+    let run = BenchmarkRun::new(
+        SUITE,
+        serde_json::json!({
+            "dim": DIM,
+            "corpus_size": CORPUS_SIZE,
+            "queries": NUM_QUERIES,
+            "k": K,
+        }),
+    )
+    .with_metric(BenchmarkMetric::higher_is_better(
+        "insert_throughput",
+        insert_throughput,
+        "vec/s",
+    ))
+    .with_metric(BenchmarkMetric::higher_is_better(
+        "recall_at_10",
+        recall,
+        "ratio",
+    ))
+    .with_latency_percentiles("search_latency", &mut latencies);
+
+    println!("HNSW Regression Benchmark ({} x {}d)", CORPUS_SIZE, DIM);
+    for metric in &run.metrics {
+        println!(
+            "  {:<20} {:>12.2} {}",
+            metric.name, metric.value, metric.unit
+        );
+    }
+
+    let dir = std::env::var("AGENTREPLAY_BENCH_DIR")
+        .unwrap_or_else(|_| "./agentreplay-data/benchmarks".to_string());
+    let store = BenchmarkStore::open(&dir).expect("Failed to open benchmark store");
+    let comparison = store
+        .compare_to_previous(&run, DEFAULT_REGRESSION_THRESHOLD_PCT)
+        .expect("Failed to read benchmark store");
+    store.record(&run).expect("Failed to record benchmark run");
+
+    match comparison {
+        Some(comparison) => {
+            print_comparison(&comparison);
+            if comparison.has_regressions() {
+                println!("⚠ Regression detected");
+            }
+        }
+        None => println!("No previous run on this machine to compare against"),
+    }
+}
@@ -0,0 +1,161 @@
+// Copyright 2025 AgentReplay (https://github.com/agentreplay)
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program. If not, see <https://www.gnu.org/licenses/>.
+
+//! Latency-budgeted ef_search selection
+//!
+//! HNSW search cost grows roughly linearly with `ef_search`. The tuner keeps an
+//! exponentially weighted estimate of the cost per unit of `ef` observed on
+//! recent queries and picks, per query, the largest `ef` within the
+//! [`AdaptiveSearchConfig`] bounds that should fit in the time left of the
+//! caller's budget. Under load the per-ef cost rises, so `ef` (and recall)
+//! drops until searches fit again. The budget's deadline still stops a search
+//! that runs over.
+
+use crate::AdaptiveSearchConfig;
+use parking_lot::Mutex;
+use serde::Serialize;
+use std::time::Duration;
+
+/// EWMA weight of the newest latency sample
+const SMOOTHING: f64 = 0.2;
+/// Fraction of the remaining budget to target, leaving room for fetching
+/// the matched edges
+const HEADROOM: f64 = 0.8;
+
+/// Parameters actually used for a latency-budgeted search, reported back to
+/// callers
+#[derive(Debug, Clone, Serialize)]
+pub struct EffectiveSearchParams {
+    /// ef_search chosen for this query
+    pub ef_search: usize,
+    pub budget_ms: u64,
+    /// Time spent on the whole search, including embedding and fetching
+    pub elapsed_ms: f64,
+    pub within_budget: bool,
+    /// Whether the deadline cut the search short, lowering recall
+    pub truncated: bool,
+}
+
+/// Picks ef_search per query from a latency budget
+pub struct AdaptiveEfTuner {
+    config: AdaptiveSearchConfig,
+    /// Smoothed search cost in microseconds per unit of ef (None until first sample)
+    us_per_ef: Mutex<Option<f64>>,
+}
+
+impl AdaptiveEfTuner {
+    pub fn new(config: AdaptiveSearchConfig) -> Self {
+        Self {
+            config,
+            us_per_ef: Mutex::new(None),
+        }
+    }
+
+    /// Choose ef_search for a query returning `k` results in `remaining` time
+    ///
+    /// Before any search has been timed the index's own `default_ef` is
+    /// used. The result is never below `k`.
+    pub fn choose_ef(&self, remaining: Duration, k: usize, default_ef: usize) -> usize {
+        let floor = self.config.min_ef.max(k);
+        let ceiling = self.config.max_ef.max(floor);
+
+        match *self.us_per_ef.lock() {
+            Some(cost) if cost > 0.0 => {
+                let target_us = remaining.as_secs_f64() * 1_000_000.0 * HEADROOM;
+                ((target_us / cost) as usize).clamp(floor, ceiling)
+            }
+            _ => default_ef.clamp(floor, ceiling),
+        }
+    }
+
+    /// Feed back the observed latency of a search run with `ef`
+    ///
+    /// A search the deadline cut short would have taken longer than it did,
+    /// so its sample counts double to make `ef` back off.
+    pub fn record(&self, ef: usize, elapsed: Duration, truncated: bool) {
+        if ef == 0 {
+            return;
+        }
+        let mut sample = elapsed.as_secs_f64() * 1_000_000.0 / ef as f64;
+        if truncated {
+            sample *= 2.0;
+        }
+        let mut cost = self.us_per_ef.lock();
+        *cost = Some(match *cost {
+            Some(prev) => prev + SMOOTHING * (sample - prev),
+            None => sample,
+        });
+    }
+}
+
+impl Default for AdaptiveEfTuner {
+    fn default() -> Self {
+        Self::new(AdaptiveSearchConfig::default())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn tuner() -> AdaptiveEfTuner {
+        AdaptiveEfTuner::new(AdaptiveSearchConfig {
+            min_ef: 16,
+            max_ef: 400,
+            ..Default::default()
+        })
+    }
+
+    #[test]
+    fn test_default_ef_before_samples() {
+        let tuner = tuner();
+        let budget = Duration::from_millis(50);
+        assert_eq!(tuner.choose_ef(budget, 10, 100), 100);
+        assert_eq!(tuner.choose_ef(budget, 10, 1000), 400);
+        // Never below k
+        assert_eq!(tuner.choose_ef(budget, 500, 100), 500);
+    }
+
+    #[test]
+    fn test_ef_shrinks_when_searches_get_slower() {
+        let tuner = tuner();
+        let budget = Duration::from_millis(10);
+
+        // 10us per ef unit -> 8ms target / 10us = 800, clamped to max_ef
+        tuner.record(100, Duration::from_millis(1), false);
+        assert_eq!(tuner.choose_ef(budget, 10, 100), 400);
+
+        // Sustained load: 200us per ef unit
+        for _ in 0..50 {
+            tuner.record(100, Duration::from_millis(20), false);
+        }
+        let ef = tuner.choose_ef(budget, 10, 100);
+        assert!(ef < 100, "ef should drop under load, got {}", ef);
+        assert!(ef >= 16);
+
+        // Less time left means a smaller ef
+        assert!(tuner.choose_ef(Duration::from_millis(5), 10, 100) < ef);
+    }
+
+    #[test]
+    fn test_truncated_searches_back_off() {
+        let full = tuner();
+        let cut = tuner();
+        full.record(100, Duration::from_millis(1), false);
+        cut.record(100, Duration::from_millis(1), true);
+        let budget = Duration::from_millis(2);
+        assert!(cut.choose_ef(budget, 10, 100) < full.choose_ef(budget, 10, 100));
+    }
+}
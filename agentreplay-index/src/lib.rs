@@ -46,6 +46,7 @@
 // Agentreplay-specific modules (not in sochdb-index)
 // =============================================================================
 
+pub mod adaptive;
 pub mod causal;
 pub mod compression;
 pub mod concept;
@@ -57,6 +58,7 @@ pub mod vector;
 pub mod vector_hnsw;
 
 // Re-export agentreplay-specific types
+pub use adaptive::{AdaptiveEfTuner, EffectiveSearchParams};
 pub use causal::{CausalIndex, CausalStats};
 pub use compression::{CompressionLevel, QuantizedVectorI8, StoredVector};
 pub use concept::{ConceptEntry, ConceptExtractionConfig, ConceptExtractor, ConceptIndex, ConceptQuery, ExtractedConcept};
@@ -26,6 +26,7 @@ use std::fs::File;
 use std::io::{self, BufReader, BufWriter, Read, Write};
 use std::path::Path;
 use std::sync::atomic::{AtomicUsize, Ordering as AtomicOrdering};
+use std::time::Instant;
 
 pub type Embedding = Array1<f32>;
 
@@ -242,7 +243,24 @@ impl VectorIndex {
 
     /// Search for k nearest neighbors with O(log N) HNSW algorithm
     pub fn search(&self, query: &Embedding, k: usize) -> Result<Vec<(u128, f32)>, String> {
-        self.search_internal(query, k, true)
+        self.search_internal(query, k, self.ef_search(), None, true)
+            .map(|(results, _)| results)
+    }
+
+    /// Search with a per-query `ef` that stops expanding candidates at
+    /// `deadline`
+    ///
+    /// Past the deadline the layer-0 search returns the best candidates found
+    /// so far, trading recall for latency under load. The flag reports
+    /// whether the search was cut short.
+    pub fn search_until(
+        &self,
+        query: &Embedding,
+        k: usize,
+        ef: usize,
+        deadline: Instant,
+    ) -> Result<(Vec<(u128, f32)>, bool), String> {
+        self.search_internal(query, k, ef, Some(deadline), true)
     }
 
     /// Batch search for multiple queries (more efficient than individual searches)
@@ -254,7 +272,10 @@ impl VectorIndex {
         // Process all queries, potentially in parallel
         queries
             .iter()
-            .map(|query| self.search_internal(query, k, false))
+            .map(|query| {
+                self.search_internal(query, k, self.ef_search(), None, false)
+                    .map(|(results, _)| results)
+            })
             .collect()
     }
 
@@ -263,8 +284,10 @@ impl VectorIndex {
         &self,
         query: &Embedding,
         k: usize,
+        ef: usize,
+        deadline: Option<Instant>,
         _enable_prefetch: bool,
-    ) -> Result<Vec<(u128, f32)>, String> {
+    ) -> Result<(Vec<(u128, f32)>, bool), String> {
         // Validate dimension
         if let Some(expected_dim) = self.expected_dim {
             if query.len() != expected_dim {
@@ -278,7 +301,7 @@ impl VectorIndex {
 
         let nodes = self.nodes.read();
         if nodes.is_empty() {
-            return Ok(Vec::new());
+            return Ok((Vec::new(), false));
         }
 
         let mut curr_nearest = vec![self.entry_point.load(AtomicOrdering::Acquire)];
@@ -290,8 +313,8 @@ impl VectorIndex {
         }
 
         // Search layer 0 with ef_search parameter
-        let ef = *self.ef_search.read();
-        let candidates = self.search_layer_internal(&nodes, query, &curr_nearest, ef.max(k), 0);
+        let (candidates, truncated) =
+            self.search_layer_until(&nodes, query, &curr_nearest, ef.max(k), 0, deadline);
 
         // Convert to result format and limit to k
         let mut results: Vec<(u128, f32)> = candidates
@@ -305,7 +328,7 @@ impl VectorIndex {
         results.sort_by(|a, b| a.1.total_cmp(&b.1));
         results.truncate(k);
 
-        Ok(results)
+        Ok((results, truncated))
     }
 
     /// Search within a specific layer (internal algorithm) with prefetching
@@ -317,6 +340,21 @@ impl VectorIndex {
         num_closest: usize,
         layer: usize,
     ) -> Vec<usize> {
+        self.search_layer_until(nodes, query, entry_points, num_closest, layer, None)
+            .0
+    }
+
+    /// Layer search that stops expanding candidates once `deadline` passes
+    fn search_layer_until(
+        &self,
+        nodes: &[HNSWNode],
+        query: &Embedding,
+        entry_points: &[usize],
+        num_closest: usize,
+        layer: usize,
+        deadline: Option<Instant>,
+    ) -> (Vec<usize>, bool) {
+        let mut truncated = false;
         let mut visited = HashSet::new();
         let mut candidates = BinaryHeap::new();
         let mut w = BinaryHeap::new();
@@ -336,6 +374,11 @@ impl VectorIndex {
         }
 
         while let Some(c) = candidates.pop() {
+            if deadline.is_some_and(|deadline| Instant::now() >= deadline) {
+                truncated = true;
+                break;
+            }
+
             // If c is farther than furthest in w, we're done
             if let Some(Reverse(furthest)) = w.peek() {
                 if c.distance > furthest.distance {
@@ -398,7 +441,10 @@ impl VectorIndex {
             }
         }
 
-        w.into_iter().map(|Reverse(c)| c.node_idx).collect()
+        (
+            w.into_iter().map(|Reverse(c)| c.node_idx).collect(),
+            truncated,
+        )
     }
 
     /// Select neighbors using enhanced RNG heuristic (CoreNN-inspired)
@@ -566,6 +612,23 @@ impl VectorIndex {
         nodes.iter().map(|n| n.edge_id).collect()
     }
 
+    /// Get the raw vectors stored in the index, in insertion order
+    ///
+    /// Used to rebuild the HNSW graph from scratch (e.g. after dropping
+    /// entries whose edges no longer exist).
+    pub fn entries(&self) -> Vec<(u128, Embedding)> {
+        let nodes = self.nodes.read();
+        nodes
+            .iter()
+            .map(|n| (n.edge_id, n.vector.clone()))
+            .collect()
+    }
+
+    /// Distance metric used by this index
+    pub fn metric(&self) -> DistanceMetric {
+        self.metric
+    }
+
     /// Clear all vectors
     pub fn clear(&self) {
         self.nodes.write().clear();
@@ -578,6 +641,11 @@ impl VectorIndex {
         *self.ef_search.write() = ef;
     }
 
+    /// Current search quality parameter
+    pub fn ef_search(&self) -> usize {
+        *self.ef_search.read()
+    }
+
     /// Save index to disk (version 2 with HNSW graph)
     pub fn save_to_disk<P: AsRef<Path>>(&self, path: P) -> io::Result<()> {
         let nodes = self.nodes.read();
@@ -870,6 +938,28 @@ mod tests {
         assert!(results.is_empty());
     }
 
+    #[test]
+    fn test_search_until_deadline() {
+        let index = VectorIndex::new(DistanceMetric::Euclidean);
+        for i in 0..200u128 {
+            index.add(i, arr1(&[i as f32, 0.0])).unwrap();
+        }
+        let query = arr1(&[150.0, 0.0]);
+
+        let far = Instant::now() + std::time::Duration::from_secs(60);
+        let ef = index.ef_search();
+        let (results, truncated) = index.search_until(&query, 5, ef, far).unwrap();
+        assert!(!truncated);
+        assert_eq!(results, index.search(&query, 5).unwrap());
+        let (results, _) = index.search_until(&query, 5, 5, far).unwrap();
+        assert_eq!(results.len(), 5);
+
+        // An expired deadline returns what the upper layers found
+        let (results, truncated) = index.search_until(&query, 5, ef, Instant::now()).unwrap();
+        assert!(truncated);
+        assert!(results.len() <= 1);
+    }
+
     #[test]
     fn test_hnsw_single() {
         let index = VectorIndex::new(DistanceMetric::Euclidean);
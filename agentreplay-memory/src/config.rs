@@ -36,12 +36,22 @@ pub struct MemoryConfig {
     /// Token budget for context packing
     pub context_token_budget: usize,
 
+    /// Model whose tokenizer measures packed context (None = character heuristic)
+    pub context_model: Option<String>,
+
     /// Enable semantic search (requires embedding model)
     pub enable_semantic_search: bool,
 
     /// Number of results to return from semantic search
     pub semantic_search_k: usize,
 
+    /// Default trade-off between relevance (0.0) and recency (1.0) for
+    /// workspace retrieval and context packing
+    pub recency_weight: f32,
+
+    /// Age in hours at which an observation's recency score halves
+    pub recency_half_life_hours: f64,
+
     /// Embedding model to use (if semantic search enabled)
     pub embedding_model: EmbeddingModel,
 
@@ -65,8 +75,11 @@ impl Default for MemoryConfig {
             max_session_summaries: 1_000,
             auto_summarize_sessions: true,
             context_token_budget: 4_000,
+            context_model: None,
             enable_semantic_search: true,
             semantic_search_k: 10,
+            recency_weight: 0.3,
+            recency_half_life_hours: 72.0,
             embedding_model: EmbeddingModel::default(),
             enable_compression: true,
             retention_policy: RetentionPolicy::default(),
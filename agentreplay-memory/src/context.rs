@@ -20,8 +20,12 @@
 
 use crate::observation::{Observation, ObservationCategory};
 use crate::session::SessionSummary;
+use crate::tokenizer::Tokenizer;
 use serde::{Deserialize, Serialize};
 
+/// Smallest remaining budget worth filling with a truncated section
+const MIN_PARTIAL_TOKENS: usize = 25;
+
 /// Specification for what context to pack
 #[derive(Debug, Clone, Default, Serialize, Deserialize)]
 pub struct ContextSpec {
@@ -33,8 +37,18 @@ pub struct ContextSpec {
     pub sections: Vec<ContextSection>,
     /// Semantic query to prioritize relevant content
     pub semantic_query: Option<String>,
+    /// Trade-off between query relevance (0.0) and recency (1.0) when
+    /// ranking observations; `None` uses the engine default
+    #[serde(default)]
+    pub recency_weight: Option<f32>,
     /// Output format
     pub format: ContextFormat,
+    /// Target model, used to pick the tokenizer that measures sections
+    #[serde(default)]
+    pub model: Option<String>,
+    /// How sections are cut when they don't all fit the budget
+    #[serde(default)]
+    pub truncation: TruncationStrategy,
 }
 
 /// Sections that can be included in context
@@ -63,6 +77,38 @@ impl Default for ContextSection {
     }
 }
 
+/// How sections are kept when they exceed the token budget
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, Default)]
+#[serde(rename_all = "snake_case", tag = "strategy")]
+pub enum TruncationStrategy {
+    /// Fill sections in spec order; the first section that doesn't fit is
+    /// truncated and everything after it is dropped
+    #[default]
+    Sequential,
+    /// Fill sections in the given priority order so lower-priority sections
+    /// are truncated or dropped first. Sections missing from `order` rank
+    /// last, in spec order. Output keeps the spec order.
+    Priority { order: Vec<ContextSection> },
+}
+
+impl TruncationStrategy {
+    /// Priority order that keeps decisions and preferences over general
+    /// observations and session history
+    pub fn keep_decisions() -> Self {
+        Self::Priority {
+            order: vec![
+                ContextSection::Decisions,
+                ContextSection::Preferences,
+                ContextSection::Patterns,
+                ContextSection::Todos,
+                ContextSection::RollingSummary,
+                ContextSection::Observations,
+                ContextSection::RecentSessions,
+            ],
+        }
+    }
+}
+
 /// Output format for packed context
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, Default)]
 #[serde(rename_all = "snake_case")]
@@ -91,7 +137,10 @@ impl ContextSpec {
                 ContextSection::Observations,
             ],
             semantic_query: None,
+            recency_weight: None,
             format: ContextFormat::Mdc,
+            model: None,
+            truncation: TruncationStrategy::Sequential,
         }
     }
 
@@ -113,11 +162,29 @@ impl ContextSpec {
         self
     }
 
+    /// Set the relevance/recency trade-off for ranking observations
+    pub fn recency_weight(mut self, weight: f32) -> Self {
+        self.recency_weight = Some(weight);
+        self
+    }
+
     /// Set output format
     pub fn format(mut self, format: ContextFormat) -> Self {
         self.format = format;
         self
     }
+
+    /// Set the target model whose tokenizer measures the budget
+    pub fn model(mut self, model: impl Into<String>) -> Self {
+        self.model = Some(model.into());
+        self
+    }
+
+    /// Set the truncation strategy
+    pub fn truncation(mut self, truncation: TruncationStrategy) -> Self {
+        self.truncation = truncation;
+        self
+    }
 }
 
 /// Packed context ready for injection
@@ -129,10 +196,16 @@ pub struct PackedContext {
     pub content: String,
     /// Format of the content
     pub format: ContextFormat,
-    /// Approximate token count
+    /// Token count of the rendered content, measured with the tokenizer
     pub token_count: usize,
+    /// Token budget the content was packed into
+    pub token_budget: usize,
+    /// Model whose tokenizer measured the content (`None` = heuristic)
+    pub model: Option<String>,
     /// Sections included
     pub sections_included: Vec<ContextSection>,
+    /// Sections dropped because the budget ran out
+    pub sections_dropped: Vec<ContextSection>,
     /// Number of observations included
     pub observation_count: usize,
     /// Number of sessions included
@@ -143,10 +216,10 @@ pub struct PackedContext {
 
 /// Context packer that assembles context from memory
 pub struct ContextPacker {
-    /// Token budget
+    /// Default token budget, used when the spec doesn't set one
     token_budget: usize,
-    /// Approximate tokens per character (for estimation)
-    tokens_per_char: f32,
+    /// Tokenizer used when the spec doesn't name a model
+    tokenizer: Tokenizer,
 }
 
 impl Default for ContextPacker {
@@ -160,52 +233,118 @@ impl ContextPacker {
     pub fn new(token_budget: usize) -> Self {
         Self {
             token_budget,
-            tokens_per_char: 0.25, // Rough approximation
+            tokenizer: Tokenizer::heuristic(),
         }
     }
 
+    /// Measure with the tokenizer of `model` by default
+    pub fn with_model(mut self, model: &str) -> Self {
+        self.tokenizer = Tokenizer::for_model(model);
+        self
+    }
+
     /// Pack context from observations and session summaries
+    ///
+    /// The spec's budget and model take precedence over the packer defaults.
+    /// Format overhead (frontmatter, headings) counts against the budget.
     pub fn pack(
         &self,
         spec: &ContextSpec,
         observations: &[Observation],
         sessions: &[SessionSummary],
     ) -> PackedContext {
-        let mut sections_content: Vec<(ContextSection, String)> = Vec::new();
-        let mut total_tokens = 0;
+        let tokenizer = match &spec.model {
+            Some(model) => Tokenizer::for_model(model),
+            None => self.tokenizer.clone(),
+        };
+        let budget = if spec.token_budget > 0 {
+            spec.token_budget
+        } else {
+            self.token_budget
+        };
+
+        let rendered: Vec<(ContextSection, String)> = spec
+            .sections
+            .iter()
+            .map(|section| {
+                (
+                    *section,
+                    self.render_section(*section, observations, sessions),
+                )
+            })
+            .filter(|(_, content)| !content.is_empty())
+            .collect();
+
+        let fill_order: Vec<usize> = match &spec.truncation {
+            TruncationStrategy::Sequential => (0..rendered.len()).collect(),
+            TruncationStrategy::Priority { order } => {
+                let mut indices: Vec<usize> = (0..rendered.len()).collect();
+                indices.sort_by_key(|&i| {
+                    order
+                        .iter()
+                        .position(|s| *s == rendered[i].0)
+                        .unwrap_or(order.len())
+                });
+                indices
+            }
+        };
+
+        let overhead = tokenizer.count(&self.format_output(&spec.format, &[], &spec.workspace_id));
+        let mut remaining = budget.saturating_sub(overhead);
+        let mut kept: Vec<Option<String>> = vec![None; rendered.len()];
         let mut truncated = false;
 
-        for section in &spec.sections {
-            let content = self.render_section(*section, observations, sessions);
-            let section_tokens = self.estimate_tokens(&content);
-
-            if total_tokens + section_tokens <= self.token_budget {
-                total_tokens += section_tokens;
-                sections_content.push((*section, content));
-            } else {
-                // Try to include partial content
-                let remaining = self.token_budget.saturating_sub(total_tokens);
-                if remaining > 100 {
-                    let truncated_content =
-                        self.truncate_to_tokens(&content, remaining);
-                    total_tokens += self.estimate_tokens(&truncated_content);
-                    sections_content.push((*section, truncated_content));
+        for (position, &i) in fill_order.iter().enumerate() {
+            let content = &rendered[i].1;
+            // Sections are joined with a newline
+            let section_tokens = tokenizer.count(content) + 1;
+
+            if section_tokens <= remaining {
+                remaining -= section_tokens;
+                kept[i] = Some(content.clone());
+                continue;
+            }
+
+            truncated = true;
+            if remaining > MIN_PARTIAL_TOKENS {
+                let partial = tokenizer.truncate(content, remaining - 1);
+                remaining = remaining.saturating_sub(tokenizer.count(&partial) + 1);
+                kept[i] = Some(partial);
+            }
+            if spec.truncation == TruncationStrategy::Sequential {
+                // Keep the prefix order: nothing after the cut is included
+                for &rest in &fill_order[position + 1..] {
+                    kept[rest] = None;
                 }
-                truncated = true;
                 break;
             }
         }
 
+        let mut sections_content = Vec::new();
+        let mut sections_dropped = Vec::new();
+        for ((section, _), content) in rendered.iter().zip(kept) {
+            match content {
+                Some(content) => sections_content.push((*section, content)),
+                None => sections_dropped.push(*section),
+            }
+        }
+
         let content = self.format_output(&spec.format, &sections_content, &spec.workspace_id);
         let sections_included: Vec<ContextSection> =
             sections_content.iter().map(|(s, _)| *s).collect();
 
         PackedContext {
             workspace_id: spec.workspace_id.clone(),
+            token_count: tokenizer.count(&content),
             content,
             format: spec.format,
-            token_count: total_tokens,
+            token_budget: budget,
+            model: match &tokenizer {
+                Tokenizer::Bpe { model, .. } => Some(model.clone()),
+                Tokenizer::Heuristic { .. } => None,
+            },
             sections_included,
+            sections_dropped,
             observation_count: observations.len(),
             session_count: sessions.len(),
             truncated,
@@ -366,24 +505,6 @@ alwaysApply: true
             ContextFormat::Text => body,
         }
     }
-
-    fn estimate_tokens(&self, text: &str) -> usize {
-        (text.len() as f32 * self.tokens_per_char) as usize
-    }
-
-    fn truncate_to_tokens(&self, text: &str, max_tokens: usize) -> String {
-        let max_chars = (max_tokens as f32 / self.tokens_per_char) as usize;
-        if text.len() <= max_chars {
-            return text.to_string();
-        }
-        // Find a good break point
-        let truncated = &text[..max_chars.min(text.len())];
-        if let Some(pos) = truncated.rfind('\n') {
-            format!("{}...", &text[..pos])
-        } else {
-            format!("{}...", truncated)
-        }
-    }
 }
 
 #[cfg(test)]
@@ -424,6 +545,56 @@ mod tests {
         assert_eq!(packed.observation_count, 3);
     }
 
+    #[test]
+    fn test_priority_truncation_keeps_decisions() {
+        let mut observations: Vec<Observation> = (0..40)
+            .map(|i| {
+                Observation::new("ws", "s1")
+                    .content(format!(
+                        "General note number {} about the build pipeline",
+                        i
+                    ))
+                    .category(ObservationCategory::Note)
+            })
+            .collect();
+        observations.push(
+            Observation::new("ws", "s1")
+                .content("Use explicit error handling")
+                .category(ObservationCategory::Decision),
+        );
+
+        let spec = ContextSpec::for_workspace("ws")
+            .sections(vec![
+                ContextSection::Observations,
+                ContextSection::Decisions,
+            ])
+            .format(ContextFormat::Markdown)
+            .model("gpt-4o")
+            .token_budget(120);
+
+        let sequential = ContextPacker::default().pack(&spec, &observations, &[]);
+        assert!(sequential.truncated);
+        assert!(!sequential.content.contains("explicit error handling"));
+        assert_eq!(sequential.sections_dropped, vec![ContextSection::Decisions]);
+
+        let spec = spec.truncation(TruncationStrategy::keep_decisions());
+        let packed = ContextPacker::default().pack(&spec, &observations, &[]);
+        assert!(packed.truncated);
+        assert!(packed.content.contains("explicit error handling"));
+        // Output keeps spec order even though decisions were filled first
+        assert_eq!(
+            packed.sections_included,
+            vec![ContextSection::Observations, ContextSection::Decisions]
+        );
+        assert_eq!(packed.model.as_deref(), Some("gpt-4o"));
+        assert_eq!(packed.token_budget, 120);
+        assert_eq!(
+            packed.token_count,
+            Tokenizer::for_model("gpt-4o").count(&packed.content)
+        );
+        assert!(packed.token_count <= 120);
+    }
+
     #[test]
     fn test_mdc_format() {
         let observations = vec![Observation::new("my-project", "s1")
@@ -21,8 +21,10 @@ use crate::config::MemoryConfig;
 use crate::context::{ContextPacker, ContextSpec, PackedContext};
 use crate::error::{MemoryError, MemoryResult};
 use crate::observation::{Observation, ObservationId, ObservationQuery};
+use crate::retrieval::WorkspaceRetriever;
 use crate::session::{SessionId, SessionMemory, SessionSummary};
 use crate::storage::MemoryStore;
+use chrono::Utc;
 use std::collections::HashMap;
 use std::path::PathBuf;
 use std::sync::Arc;
@@ -49,7 +51,10 @@ impl MemoryEngine {
         std::fs::create_dir_all(&config.data_dir)?;
 
         let store = MemoryStore::new(&config.data_dir).await?;
-        let context_packer = ContextPacker::new(config.context_token_budget);
+        let mut context_packer = ContextPacker::new(config.context_token_budget);
+        if let Some(model) = &config.context_model {
+            context_packer = context_packer.with_model(model);
+        }
 
         Ok(Self {
             config,
@@ -205,25 +210,60 @@ impl MemoryEngine {
         query: Option<&str>,
         k: usize,
     ) -> MemoryResult<Vec<Observation>> {
-        let mut obs_query = ObservationQuery::for_workspace(workspace_id).limit(k);
-
-        if let Some(q) = query {
-            if self.config.enable_semantic_search {
-                obs_query.semantic_query = Some(q.to_string());
-            } else {
-                obs_query.text_query = Some(q.to_string());
-            }
-        }
+        self.retrieve_workspace(workspace_id, query, k, None).await
+    }
 
-        self.store.query_observations(&obs_query).await
+    /// Search observations across every session in a workspace
+    ///
+    /// Results are ranked by query relevance, recency and category
+    /// importance; `recency_weight` (0.0 = relevance only, 1.0 = recency
+    /// only) overrides the configured default. When a query is given,
+    /// observations matching none of its terms are dropped.
+    pub async fn retrieve_workspace(
+        &self,
+        workspace_id: &str,
+        query: Option<&str>,
+        k: usize,
+        recency_weight: Option<f32>,
+    ) -> MemoryResult<Vec<Observation>> {
+        let observations = self
+            .store
+            .query_observations(&ObservationQuery::for_workspace(workspace_id))
+            .await?;
+
+        let has_query = query.is_some_and(|q| !q.trim().is_empty());
+        let ranked = self
+            .retriever(recency_weight)
+            .rank(observations, query, Utc::now())
+            .into_iter()
+            .filter(|scored| !has_query || scored.score.relevance > 0.0)
+            .take(k)
+            .map(|scored| {
+                let mut observation = scored.observation;
+                observation.relevance_score = Some(scored.score.total);
+                observation
+            })
+            .collect();
+
+        Ok(ranked)
     }
 
     /// Pack context into a formatted output
+    ///
+    /// Observations are ordered with the workspace retriever, using the
+    /// spec's semantic query and recency weight, so each section keeps its
+    /// highest-ranked entries when it is capped or truncated.
     pub async fn pack_context(&self, spec: ContextSpec) -> MemoryResult<PackedContext> {
         let observations = self
             .store
             .query_observations(&ObservationQuery::for_workspace(&spec.workspace_id))
             .await?;
+        let observations: Vec<Observation> = self
+            .retriever(spec.recency_weight)
+            .rank(observations, spec.semantic_query.as_deref(), Utc::now())
+            .into_iter()
+            .map(|scored| scored.observation)
+            .collect();
 
         let sessions = self
             .store
@@ -233,6 +273,11 @@ impl MemoryEngine {
         Ok(self.context_packer.pack(&spec, &observations, &sessions))
     }
 
+    fn retriever(&self, recency_weight: Option<f32>) -> WorkspaceRetriever {
+        WorkspaceRetriever::new(self.config.recency_half_life_hours)
+            .recency_weight(recency_weight.unwrap_or(self.config.recency_weight))
+    }
+
     /// Export context as MDC file
     pub async fn export_mdc(&self, workspace_id: &str) -> MemoryResult<String> {
         let spec = ContextSpec::for_workspace(workspace_id)
@@ -363,6 +408,41 @@ mod tests {
         assert_eq!(summary.message_count, 2);
     }
 
+    #[tokio::test]
+    async fn test_workspace_retrieval() {
+        let engine = create_test_engine().await;
+
+        let obs1 = Observation::new("ws-1", "s-1")
+            .content("Retry flaky HTTP calls with jittered backoff")
+            .category(ObservationCategory::Decision);
+        let obs2 = Observation::new("ws-1", "s-2")
+            .content("HTTP client timeout is 30 seconds")
+            .category(ObservationCategory::Fact);
+        let obs3 = Observation::new("ws-1", "s-3")
+            .content("Prefer tabs over spaces")
+            .category(ObservationCategory::Preference);
+        let other = Observation::new("ws-2", "s-4").content("HTTP retries are disabled");
+
+        for obs in [obs1, obs2, obs3, other] {
+            engine.write_observation(obs).await.unwrap();
+        }
+
+        let results = engine
+            .retrieve_workspace("ws-1", Some("http retry backoff"), 10, Some(0.0))
+            .await
+            .unwrap();
+        assert_eq!(results.len(), 2);
+        assert_eq!(results[0].session_id, "s-1");
+        assert_eq!(results[1].session_id, "s-2");
+        assert!(results[0].relevance_score.unwrap() > results[1].relevance_score.unwrap());
+
+        let all = engine
+            .retrieve_workspace("ws-1", None, 10, None)
+            .await
+            .unwrap();
+        assert_eq!(all.len(), 3);
+    }
+
     #[tokio::test]
     async fn test_context_export() {
         let engine = create_test_engine().await;
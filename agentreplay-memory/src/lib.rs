@@ -19,6 +19,8 @@
 //! - **Session Memory**: Store and retrieve observations from coding sessions
 //! - **Context Compression**: Hierarchical summarization to keep context compact
 //! - **Semantic Retrieval**: Find relevant context using embeddings + HNSW
+//! - **Workspace Retrieval**: Rank memory across all sessions of a workspace
+//!   by relevance, recency and importance
 //! - **Context Export**: Generate MDC files for injection into editors
 //!
 //! # Architecture
@@ -82,13 +84,17 @@ pub mod context;
 pub mod engine;
 pub mod error;
 pub mod observation;
+pub mod retrieval;
 pub mod session;
 pub mod storage;
+pub mod tokenizer;
 
 // Re-exports
 pub use config::MemoryConfig;
-pub use context::{ContextPacker, ContextSection, ContextSpec, PackedContext};
+pub use context::{ContextPacker, ContextSection, ContextSpec, PackedContext, TruncationStrategy};
 pub use engine::MemoryEngine;
 pub use error::{MemoryError, MemoryResult};
 pub use observation::{Observation, ObservationCategory, ObservationId, ObservationQuery};
+pub use retrieval::{RetrievalScore, ScoredObservation, WorkspaceRetriever};
 pub use session::{SessionId, SessionMemory, SessionSummary};
+pub use tokenizer::Tokenizer;
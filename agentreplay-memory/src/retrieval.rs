@@ -0,0 +1,222 @@
+// Copyright 2025 AgentReplay (https://github.com/agentreplay)
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program. If not, see <https://www.gnu.org/licenses/>.
+
+//! Workspace-level retrieval
+//!
+//! Ranks observations from every session in a workspace. Each observation is
+//! scored as
+//!
+//! ```text
+//! score = ((1 - w) · relevance + w · recency) · importance
+//! ```
+//!
+//! where `w` is the recency weight, `relevance` is the fraction of query terms
+//! found in the content and tags, `recency` decays with a configurable
+//! half-life and `importance` depends on the observation category.
+
+use crate::observation::{Observation, ObservationCategory};
+use chrono::{DateTime, Utc};
+use std::collections::HashSet;
+
+/// Score breakdown for a single observation
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct RetrievalScore {
+    /// Fraction of query terms matched (1.0 when there is no query)
+    pub relevance: f32,
+    /// Exponential recency decay in `[0, 1]`
+    pub recency: f32,
+    /// Category importance in `[0.5, 1]`
+    pub importance: f32,
+    /// Combined score used for ranking
+    pub total: f32,
+}
+
+/// An observation with its retrieval score
+#[derive(Debug, Clone)]
+pub struct ScoredObservation {
+    pub observation: Observation,
+    pub score: RetrievalScore,
+}
+
+/// Ranks workspace observations by relevance, recency and importance
+#[derive(Debug, Clone)]
+pub struct WorkspaceRetriever {
+    /// Trade-off between relevance (0.0) and recency (1.0)
+    recency_weight: f32,
+    /// Age at which recency drops to one half
+    half_life_hours: f64,
+}
+
+impl WorkspaceRetriever {
+    /// Create a retriever with the given recency half-life
+    pub fn new(half_life_hours: f64) -> Self {
+        Self {
+            recency_weight: 0.3,
+            half_life_hours: half_life_hours.max(f64::EPSILON),
+        }
+    }
+
+    /// Set the recency/relevance trade-off, clamped to `[0, 1]`
+    pub fn recency_weight(mut self, weight: f32) -> Self {
+        self.recency_weight = weight.clamp(0.0, 1.0);
+        self
+    }
+
+    /// Score one observation against pre-tokenized query terms
+    pub fn score(
+        &self,
+        observation: &Observation,
+        query_terms: &HashSet<String>,
+        now: DateTime<Utc>,
+    ) -> RetrievalScore {
+        let relevance = if query_terms.is_empty() {
+            1.0
+        } else {
+            let mut terms = tokenize(&observation.content);
+            for tag in &observation.tags {
+                terms.extend(tokenize(tag));
+            }
+            query_terms.intersection(&terms).count() as f32 / query_terms.len() as f32
+        };
+
+        let age_hours = (now - observation.created_at).num_seconds().max(0) as f64 / 3600.0;
+        let recency = 0.5f64.powf(age_hours / self.half_life_hours) as f32;
+        let importance = category_importance(observation.category);
+
+        let w = self.recency_weight;
+        RetrievalScore {
+            relevance,
+            recency,
+            importance,
+            total: ((1.0 - w) * relevance + w * recency) * importance,
+        }
+    }
+
+    /// Rank observations, best first
+    pub fn rank(
+        &self,
+        observations: Vec<Observation>,
+        query: Option<&str>,
+        now: DateTime<Utc>,
+    ) -> Vec<ScoredObservation> {
+        let query_terms = query.map(tokenize).unwrap_or_default();
+
+        let mut scored: Vec<ScoredObservation> = observations
+            .into_iter()
+            .map(|observation| {
+                let score = self.score(&observation, &query_terms, now);
+                ScoredObservation { observation, score }
+            })
+            .collect();
+
+        scored.sort_by(|a, b| {
+            b.score
+                .total
+                .partial_cmp(&a.score.total)
+                .unwrap_or(std::cmp::Ordering::Equal)
+                .then_with(|| b.observation.created_at.cmp(&a.observation.created_at))
+        });
+        scored
+    }
+}
+
+impl Default for WorkspaceRetriever {
+    fn default() -> Self {
+        Self::new(72.0)
+    }
+}
+
+/// How much an observation category matters when packing context
+pub fn category_importance(category: ObservationCategory) -> f32 {
+    match category {
+        ObservationCategory::Decision | ObservationCategory::Preference => 1.0,
+        ObservationCategory::Pattern => 0.9,
+        ObservationCategory::Issue | ObservationCategory::Insight => 0.8,
+        ObservationCategory::Fact | ObservationCategory::Todo => 0.7,
+        ObservationCategory::Note => 0.5,
+    }
+}
+
+fn tokenize(text: &str) -> HashSet<String> {
+    text.split(|c: char| !c.is_alphanumeric())
+        .filter(|t| t.len() >= 2)
+        .map(|t| t.to_lowercase())
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::Duration;
+
+    fn observation(content: &str, category: ObservationCategory, age_hours: i64) -> Observation {
+        let mut obs = Observation::new("ws", "s1")
+            .content(content)
+            .category(category);
+        obs.created_at = Utc::now() - Duration::hours(age_hours);
+        obs
+    }
+
+    #[test]
+    fn test_recency_weight_trade_off() {
+        let observations = vec![
+            observation(
+                "Retry database connections with backoff",
+                ObservationCategory::Decision,
+                24 * 30,
+            ),
+            observation(
+                "Switched the CI runner image",
+                ObservationCategory::Decision,
+                1,
+            ),
+        ];
+        let now = Utc::now();
+
+        let semantic = WorkspaceRetriever::default().recency_weight(0.0);
+        let ranked = semantic.rank(observations.clone(), Some("database retry"), now);
+        assert!(ranked[0].observation.content.starts_with("Retry"));
+        assert_eq!(ranked[0].score.relevance, 1.0);
+        assert_eq!(ranked[1].score.relevance, 0.0);
+
+        let recent = WorkspaceRetriever::default().recency_weight(1.0);
+        let ranked = recent.rank(observations, Some("database retry"), now);
+        assert!(ranked[0].observation.content.starts_with("Switched"));
+    }
+
+    #[test]
+    fn test_importance_and_decay() {
+        let retriever = WorkspaceRetriever::new(24.0).recency_weight(1.0);
+        let now = Utc::now();
+
+        let day_old = observation("note", ObservationCategory::Decision, 24);
+        let score = retriever.score(&day_old, &HashSet::new(), now);
+        assert!((score.recency - 0.5).abs() < 0.01);
+
+        let ranked = retriever.rank(
+            vec![
+                observation("a note", ObservationCategory::Note, 2),
+                observation("a decision", ObservationCategory::Decision, 2),
+            ],
+            None,
+            now,
+        );
+        assert_eq!(
+            ranked[0].observation.category,
+            ObservationCategory::Decision
+        );
+        assert_eq!(ranked[1].score.importance, 0.5);
+    }
+}
@@ -0,0 +1,199 @@
+// Copyright 2025 AgentReplay (https://github.com/agentreplay)
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program. If not, see <https://www.gnu.org/licenses/>.
+
+//! Tokenizers for context budgeting
+//!
+//! Models known to tiktoken are measured with their own BPE encoding; other
+//! models (Claude, Gemini, local models) use `cl100k_base` as a close
+//! approximation. The character heuristic is kept for callers that don't name
+//! a model.
+
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex, OnceLock};
+use tiktoken_rs::CoreBPE;
+
+/// Suffix appended to truncated text
+const ELLIPSIS: &str = "...";
+
+/// Token counter used by the context packer
+#[derive(Clone)]
+pub enum Tokenizer {
+    /// BPE encoding of a specific model
+    Bpe {
+        /// Model the encoding was resolved for
+        model: String,
+        bpe: Arc<CoreBPE>,
+    },
+    /// Character-ratio approximation
+    Heuristic {
+        /// Approximate tokens per character
+        tokens_per_char: f32,
+    },
+}
+
+impl std::fmt::Debug for Tokenizer {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Bpe { model, .. } => f.debug_struct("Bpe").field("model", model).finish(),
+            Self::Heuristic { tokens_per_char } => f
+                .debug_struct("Heuristic")
+                .field("tokens_per_char", tokens_per_char)
+                .finish(),
+        }
+    }
+}
+
+impl Default for Tokenizer {
+    fn default() -> Self {
+        Self::heuristic()
+    }
+}
+
+impl Tokenizer {
+    /// Character heuristic (~4 characters per token)
+    pub fn heuristic() -> Self {
+        Self::Heuristic {
+            tokens_per_char: 0.25,
+        }
+    }
+
+    /// Tokenizer for a target model
+    ///
+    /// Encodings are loaded once per model and shared afterwards. Falls back to
+    /// the heuristic only if no BPE encoding can be loaded at all.
+    pub fn for_model(model: &str) -> Self {
+        static CACHE: OnceLock<Mutex<HashMap<String, Arc<CoreBPE>>>> = OnceLock::new();
+
+        let cache = CACHE.get_or_init(|| Mutex::new(HashMap::new()));
+        let mut cache = cache.lock().unwrap_or_else(|e| e.into_inner());
+        if let Some(bpe) = cache.get(model) {
+            return Self::Bpe {
+                model: model.to_string(),
+                bpe: bpe.clone(),
+            };
+        }
+
+        match tiktoken_rs::get_bpe_from_model(model).or_else(|_| tiktoken_rs::cl100k_base()) {
+            Ok(bpe) => {
+                let bpe = Arc::new(bpe);
+                cache.insert(model.to_string(), bpe.clone());
+                Self::Bpe {
+                    model: model.to_string(),
+                    bpe,
+                }
+            }
+            Err(e) => {
+                tracing::warn!("No tokenizer for model {}: {}", model, e);
+                Self::heuristic()
+            }
+        }
+    }
+
+    /// Number of tokens in `text`
+    pub fn count(&self, text: &str) -> usize {
+        match self {
+            Self::Bpe { bpe, .. } => bpe.encode_with_special_tokens(text).len(),
+            Self::Heuristic { tokens_per_char } => (text.len() as f32 * tokens_per_char) as usize,
+        }
+    }
+
+    /// Truncate `text` to at most `max_tokens`, ending with `...`
+    ///
+    /// Prefers cutting at a line break when that keeps at least half of the
+    /// allowed text.
+    pub fn truncate(&self, text: &str, max_tokens: usize) -> String {
+        if self.count(text) <= max_tokens {
+            return text.to_string();
+        }
+        let budget = max_tokens.saturating_sub(self.count(ELLIPSIS));
+        if budget == 0 {
+            return String::new();
+        }
+
+        let mut prefix = self.prefix(text, budget);
+        while !prefix.is_empty() {
+            let cut = match prefix.rfind('\n') {
+                Some(pos) if pos >= prefix.len() / 2 => &prefix[..pos],
+                _ => prefix.as_str(),
+            };
+            let truncated = format!("{}{}", cut, ELLIPSIS);
+            // Re-encoding can merge differently at the boundary; shrink until it fits
+            if self.count(&truncated) <= max_tokens {
+                return truncated;
+            }
+            let mut end = prefix.len() - 1;
+            while !prefix.is_char_boundary(end) {
+                end -= 1;
+            }
+            prefix.truncate(end);
+        }
+        String::new()
+    }
+
+    /// Longest prefix of `text` that fits in `budget` tokens
+    fn prefix(&self, text: &str, budget: usize) -> String {
+        match self {
+            Self::Bpe { bpe, .. } => {
+                let tokens = bpe.encode_with_special_tokens(text);
+                // A cut inside a multi-byte character doesn't decode; back off
+                (1..=budget.min(tokens.len()))
+                    .rev()
+                    .find_map(|n| bpe.decode(tokens[..n].to_vec()).ok())
+                    .unwrap_or_default()
+            }
+            Self::Heuristic { tokens_per_char } => {
+                let mut end = ((budget as f32 / tokens_per_char) as usize).min(text.len());
+                while !text.is_char_boundary(end) {
+                    end -= 1;
+                }
+                text[..end].to_string()
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_bpe_count_and_truncate() {
+        let tokenizer = Tokenizer::for_model("gpt-4o");
+        assert!(matches!(tokenizer, Tokenizer::Bpe { .. }));
+        assert_eq!(tokenizer.count(""), 0);
+
+        let text = "The quick brown fox jumps over the lazy dog. ".repeat(20);
+        let truncated = tokenizer.truncate(&text, 30);
+        assert!(tokenizer.count(&truncated) <= 30);
+        assert!(truncated.ends_with("..."));
+        assert_eq!(tokenizer.truncate("short", 30), "short");
+    }
+
+    #[test]
+    fn test_unknown_model_falls_back_to_cl100k() {
+        let tokenizer = Tokenizer::for_model("claude-sonnet-4");
+        assert!(matches!(tokenizer, Tokenizer::Bpe { .. }));
+        assert!(tokenizer.count("hello world") > 0);
+    }
+
+    #[test]
+    fn test_heuristic_truncate_respects_char_boundaries() {
+        let tokenizer = Tokenizer::heuristic();
+        let text = "héllo wörld ".repeat(50);
+        let truncated = tokenizer.truncate(&text, 20);
+        assert!(tokenizer.count(&truncated) <= 20);
+        assert!(truncated.ends_with("..."));
+    }
+}
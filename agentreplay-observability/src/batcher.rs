@@ -30,7 +30,7 @@ use std::time::Duration;
 use tokio::sync::mpsc;
 use tokio::sync::Mutex;
 use tokio::time::interval;
-use tracing::{debug, error, warn};
+use tracing::{debug, error, warn, Instrument};
 
 /// Link to another span for distributed tracing
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -124,13 +124,17 @@ impl AsyncBatchExporter {
     pub fn new(config: BatcherConfig) -> Self {
         let (sender, receiver) = mpsc::channel(config.channel_buffer_size);
 
-        // Spawn background worker task
+        // Spawn background worker task; it runs in an export span so that
+        // its HTTP requests are not self-traced
         let worker_config = config.clone();
-        tokio::spawn(async move {
-            if let Err(e) = batch_worker(receiver, worker_config).await {
-                error!("Batch worker error: {}", e);
+        tokio::spawn(
+            async move {
+                if let Err(e) = batch_worker(receiver, worker_config).await {
+                    error!("Batch worker error: {}", e);
+                }
             }
-        });
+            .instrument(crate::self_trace::export_span()),
+        );
 
         Self { sender, config }
     }
@@ -191,6 +195,13 @@ impl AsyncBatchExporter {
         }
     }
 
+    /// Queue a span as is, without sampling
+    ///
+    /// Returns false if the buffer is full or the worker has stopped.
+    pub fn try_send(&self, span: AgentreplaySpan) -> bool {
+        self.sender.try_send(span).is_ok()
+    }
+
     /// Get batch configuration
     pub fn config(&self) -> &BatcherConfig {
         &self.config
@@ -265,7 +276,9 @@ async fn flush_batch(
     // Send to Agentreplay's REST API: POST /api/v1/traces
     let endpoint = format!("{}/api/v1/traces", config.agentreplay_endpoint);
 
-    let mut request = client.post(&endpoint).json(&buffer);
+    let mut request = client
+        .post(&endpoint)
+        .json(&serde_json::json!({ "spans": buffer }));
 
     // Add API key if configured
     if let Some(api_key) = &config.api_key {
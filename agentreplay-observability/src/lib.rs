@@ -24,14 +24,21 @@ pub mod config;
 pub mod genai_conventions;
 pub mod genai_instrumentation;
 pub mod metrics;
+pub mod self_trace;
 pub mod span_mapper;
-pub mod storage_metrics;
 pub mod tracer;
 
+pub use agentreplay_core::storage_metrics;
+
 pub use metrics::{MetricKey, MetricValue, MetricsAggregator};
-pub use storage_metrics::{WriteAmplificationMetrics, WriteAmplificationReport};
+pub use storage_metrics::{
+    FsyncLatencyReport, FsyncMetrics, GroupCommitMetrics, GroupCommitReport,
+    WriteAmplificationMetrics, WriteAmplificationReport, WriteTelemetry, WriteTelemetryReport,
+};
 
 use anyhow::Result;
+use batcher::{AsyncBatchExporter, BatcherConfig};
+use self_trace::{SelfTraceLayer, SelfTraceOptions};
 use serde::{Deserialize, Serialize};
 use std::time::Instant;
 use tracing::{span, Level, Span};
@@ -62,6 +69,9 @@ pub struct ObservabilityConfig {
 
     /// Project identifier
     pub project: Option<String>,
+
+    /// Sampling and rate limits of the spans sent to Agentreplay
+    pub self_trace: SelfTraceOptions,
 }
 
 impl Default for ObservabilityConfig {
@@ -74,6 +84,7 @@ impl Default for ObservabilityConfig {
             enabled: true,
             api_key: None,
             project: None,
+            self_trace: SelfTraceOptions::default(),
         }
     }
 }
@@ -88,6 +99,7 @@ impl ObservabilityConfig {
     /// - AGENTREPLAY_PROJECT: Project identifier
     /// - AGENTREPLAY_SERVICE_NAME: Service name
     /// - AGENTREPLAY_ENVIRONMENT: Environment (production/staging/development)
+    /// - AGENTREPLAY_SAMPLE_RATE: Fraction of traces sent (default: 0.1)
     pub fn from_env() -> Self {
         let mut config = Self::default();
 
@@ -121,6 +133,14 @@ impl ObservabilityConfig {
             config.environment = environment;
         }
 
+        // Trace sampling
+        if let Some(rate) = std::env::var("AGENTREPLAY_SAMPLE_RATE")
+            .ok()
+            .and_then(|rate| rate.parse::<f64>().ok())
+        {
+            config.self_trace.sample_rate = rate.clamp(0.0, 1.0);
+        }
+
         config
     }
 }
@@ -136,27 +156,55 @@ pub fn init_observability(config: ObservabilityConfig) -> Result<()> {
         return Ok(());
     }
 
-    // Set up basic tracing subscriber without external exporters
-    // Traces will be batched and sent to Agentreplay's own REST API
+    // Spans are batched and sent to POST /api/v1/traces; the exporter's
+    // worker needs a tokio runtime
+    let runtime = tokio::runtime::Handle::try_current().is_ok();
+    let self_trace = runtime.then(|| {
+        let exporter = AsyncBatchExporter::new(BatcherConfig {
+            agentreplay_endpoint: config.agentreplay_endpoint.clone(),
+            api_key: config.api_key.clone(),
+            ..Default::default()
+        });
+        SelfTraceLayer::new(exporter, self_trace_options(&config))
+    });
+
     tracing_subscriber::registry()
         .with(EnvFilter::from_default_env())
         .with(tracing_subscriber::fmt::layer())
+        .with(self_trace)
         .init();
 
+    if !runtime {
+        tracing::warn!("No tokio runtime; spans will not be sent to Agentreplay");
+    }
     tracing::info!(
         service = %config.service_name,
         environment = %config.environment,
         agentreplay_endpoint = %config.agentreplay_endpoint,
+        sample_rate = config.self_trace.sample_rate,
         "Agentreplay self-hosting observability initialized"
     );
 
-    // TODO: Set up batching layer that sends traces to Agentreplay's REST API
-    // This will use the batcher module to efficiently batch and send traces
-    // to POST /api/v1/traces endpoint with proper authentication
-
     Ok(())
 }
 
+/// Self-trace options with the service's identity added to every span
+fn self_trace_options(config: &ObservabilityConfig) -> SelfTraceOptions {
+    let mut options = config.self_trace.clone();
+    let identity = [
+        ("service.name", Some(config.service_name.clone())),
+        ("service.version", Some(config.version.clone())),
+        ("deployment.environment", Some(config.environment.clone())),
+        ("project", config.project.clone()),
+    ];
+    for (key, value) in identity {
+        if let Some(value) = value {
+            options.attributes.entry(key.to_string()).or_insert(value);
+        }
+    }
+    options
+}
+
 /// GenAI semantic convention attributes
 pub mod gen_ai {
     pub const OPERATION_NAME: &str = "gen_ai.operation.name";
@@ -0,0 +1,415 @@
+// Copyright 2025 AgentReplay (https://github.com/agentreplay)
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program. If not, see <https://www.gnu.org/licenses/>.
+
+//! Self-tracing: a process's own `tracing` spans as Agentreplay spans
+//!
+//! [`SelfTraceLayer`] is a `tracing_subscriber` layer that turns closed
+//! spans into [`AgentreplaySpan`]s and hands them to a [`SpanSink`]: the
+//! [`AsyncBatchExporter`] for processes reporting to a server over HTTP, or
+//! a channel for the server tracing itself.
+//!
+//! Exporting spans creates spans of its own (HTTP requests, ingestion),
+//! which would be exported in turn. To keep that from feeding on itself:
+//! - nothing inside an [`export_span`] is exported, so export work must run
+//!   within one
+//! - whole traces are sampled at `sample_rate`, decided at the root span
+//! - at most `max_spans_per_second` spans are exported; the rest are dropped
+//! - `targets`, when set, limits export to spans of those module prefixes
+//!
+//! Spans disabled by the subscriber's filter never reach the layer.
+
+use agentreplay_core::clock::now_us;
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Mutex;
+
+use tokio::sync::mpsc;
+use tracing::field::{Field, Visit};
+use tracing::span::{Attributes, Id, Record};
+use tracing::Subscriber;
+use tracing_subscriber::layer::{Context, Layer};
+use tracing_subscriber::registry::LookupSpan;
+
+use crate::batcher::{AgentreplaySpan, AsyncBatchExporter};
+
+/// Name of the span wrapping export work
+pub const EXPORT_SPAN_NAME: &str = "agentreplay.self_trace.export";
+
+/// A span under which nothing is exported
+///
+/// At error level so that log filters keep it; a filtered-out export span
+/// would let export work trace itself.
+pub fn export_span() -> tracing::Span {
+    tracing::error_span!("agentreplay.self_trace.export")
+}
+
+/// Destination of exported spans
+pub trait SpanSink: Send + Sync + 'static {
+    /// Queue a span without blocking; false if it was dropped
+    fn export(&self, span: AgentreplaySpan) -> bool;
+}
+
+impl SpanSink for AsyncBatchExporter {
+    fn export(&self, span: AgentreplaySpan) -> bool {
+        self.try_send(span)
+    }
+}
+
+impl SpanSink for mpsc::Sender<AgentreplaySpan> {
+    fn export(&self, span: AgentreplaySpan) -> bool {
+        self.try_send(span).is_ok()
+    }
+}
+
+/// What [`SelfTraceLayer`] exports
+#[derive(Debug, Clone)]
+pub struct SelfTraceOptions {
+    /// Fraction of traces exported (0.0 - 1.0)
+    pub sample_rate: f64,
+    /// Most spans exported per second
+    pub max_spans_per_second: u32,
+    /// Module path prefixes of exported spans; empty exports every span
+    pub targets: Vec<String>,
+    /// Attributes set on every span, e.g. the tenant and project to store
+    /// them in; they take precedence over span fields of the same name
+    pub attributes: HashMap<String, String>,
+}
+
+impl Default for SelfTraceOptions {
+    fn default() -> Self {
+        Self {
+            sample_rate: 0.1,
+            max_spans_per_second: 100,
+            targets: Vec::new(),
+            attributes: HashMap::new(),
+        }
+    }
+}
+
+/// Trace membership of every span the layer sees
+#[derive(Debug, Clone, Copy)]
+struct TraceContext {
+    trace_id: u64,
+    sampled: bool,
+    /// Inside an export span
+    suppressed: bool,
+    /// Nearest exported ancestor, the parent of spans exported below this one
+    exported_parent: Option<u64>,
+}
+
+/// A span that will be exported when it closes
+struct PendingSpan {
+    span_id: u64,
+    start_us: u64,
+    fields: HashMap<String, String>,
+}
+
+/// Layer exporting closed spans to a [`SpanSink`]
+pub struct SelfTraceLayer<K> {
+    sink: K,
+    options: SelfTraceOptions,
+    next_id: AtomicU64,
+    /// Current second and spans exported in it
+    window: Mutex<(u64, u32)>,
+    dropped: AtomicU64,
+}
+
+impl<K: SpanSink> SelfTraceLayer<K> {
+    pub fn new(sink: K, options: SelfTraceOptions) -> Self {
+        Self {
+            sink,
+            options,
+            next_id: AtomicU64::new(now_us()),
+            window: Mutex::new((0, 0)),
+            dropped: AtomicU64::new(0),
+        }
+    }
+
+    /// Spans dropped by the rate cap or a full sink
+    pub fn dropped(&self) -> u64 {
+        self.dropped.load(Ordering::Relaxed)
+    }
+
+    /// A nonzero id, unique within the process and spread over the range
+    fn new_id(&self) -> u64 {
+        splitmix64(self.next_id.fetch_add(1, Ordering::Relaxed)).max(1)
+    }
+
+    fn sampled(&self, trace_id: u64) -> bool {
+        (trace_id as f64 / u64::MAX as f64) < self.options.sample_rate
+    }
+
+    fn exports_target(&self, target: &str) -> bool {
+        self.options.targets.is_empty()
+            || self
+                .options
+                .targets
+                .iter()
+                .any(|prefix| target.starts_with(prefix.as_str()))
+    }
+
+    /// Take a slot in the current second's budget
+    fn admit(&self) -> bool {
+        let second = now_us() / 1_000_000;
+        let mut window = self.window.lock().unwrap_or_else(|e| e.into_inner());
+        if window.0 != second {
+            *window = (second, 0);
+        }
+        if window.1 >= self.options.max_spans_per_second {
+            return false;
+        }
+        window.1 += 1;
+        true
+    }
+}
+
+impl<S, K> Layer<S> for SelfTraceLayer<K>
+where
+    S: Subscriber + for<'a> LookupSpan<'a>,
+    K: SpanSink,
+{
+    fn on_new_span(&self, attrs: &Attributes<'_>, id: &Id, ctx: Context<'_, S>) {
+        let Some(span) = ctx.span(id) else {
+            return;
+        };
+        let parent = span.parent().and_then(|parent| {
+            let extensions = parent.extensions();
+            let context = *extensions.get::<TraceContext>()?;
+            let exported = extensions.get::<PendingSpan>().map(|p| p.span_id);
+            Some(TraceContext {
+                exported_parent: exported.or(context.exported_parent),
+                ..context
+            })
+        });
+        let is_export = attrs.metadata().name() == EXPORT_SPAN_NAME;
+        let context = match parent {
+            Some(parent) => TraceContext {
+                suppressed: parent.suppressed || is_export,
+                ..parent
+            },
+            None => {
+                let trace_id = self.new_id();
+                TraceContext {
+                    trace_id,
+                    sampled: self.sampled(trace_id),
+                    suppressed: is_export,
+                    exported_parent: None,
+                }
+            }
+        };
+
+        let mut extensions = span.extensions_mut();
+        if context.sampled && !context.suppressed && self.exports_target(attrs.metadata().target())
+        {
+            let mut fields = HashMap::new();
+            attrs.record(&mut FieldVisitor(&mut fields));
+            extensions.insert(PendingSpan {
+                span_id: self.new_id(),
+                start_us: now_us(),
+                fields,
+            });
+        }
+        extensions.insert(context);
+    }
+
+    fn on_record(&self, id: &Id, values: &Record<'_>, ctx: Context<'_, S>) {
+        let Some(span) = ctx.span(id) else {
+            return;
+        };
+        let mut extensions = span.extensions_mut();
+        if let Some(pending) = extensions.get_mut::<PendingSpan>() {
+            values.record(&mut FieldVisitor(&mut pending.fields));
+        }
+    }
+
+    fn on_close(&self, id: Id, ctx: Context<'_, S>) {
+        let Some(span) = ctx.span(&id) else {
+            return;
+        };
+        let Some(pending) = span.extensions_mut().remove::<PendingSpan>() else {
+            return;
+        };
+        let Some(context) = span.extensions().get::<TraceContext>().copied() else {
+            return;
+        };
+        if !self.admit() {
+            self.dropped.fetch_add(1, Ordering::Relaxed);
+            return;
+        }
+
+        let mut attributes = pending.fields;
+        attributes.insert(
+            "code.namespace".to_string(),
+            span.metadata().target().to_string(),
+        );
+        attributes.extend(self.options.attributes.clone());
+        let exported = AgentreplaySpan {
+            span_id: format!("{:#x}", pending.span_id),
+            trace_id: format!("{:#x}", context.trace_id),
+            parent_span_id: context.exported_parent.map(|id| format!("{:#x}", id)),
+            name: span.name().to_string(),
+            start_time: pending.start_us,
+            end_time: Some(now_us().max(pending.start_us)),
+            attributes,
+            traceparent: None,
+            tracestate: None,
+            span_flags: 0x01,
+            span_links: None,
+        };
+        if !self.sink.export(exported) {
+            self.dropped.fetch_add(1, Ordering::Relaxed);
+        }
+    }
+}
+
+/// Collects span fields as strings
+struct FieldVisitor<'a>(&'a mut HashMap<String, String>);
+
+impl Visit for FieldVisitor<'_> {
+    fn record_str(&mut self, field: &Field, value: &str) {
+        self.0.insert(field.name().to_string(), value.to_string());
+    }
+
+    fn record_i64(&mut self, field: &Field, value: i64) {
+        self.0.insert(field.name().to_string(), value.to_string());
+    }
+
+    fn record_u64(&mut self, field: &Field, value: u64) {
+        self.0.insert(field.name().to_string(), value.to_string());
+    }
+
+    fn record_f64(&mut self, field: &Field, value: f64) {
+        self.0.insert(field.name().to_string(), value.to_string());
+    }
+
+    fn record_bool(&mut self, field: &Field, value: bool) {
+        self.0.insert(field.name().to_string(), value.to_string());
+    }
+
+    fn record_debug(&mut self, field: &Field, value: &dyn std::fmt::Debug) {
+        self.0
+            .insert(field.name().to_string(), format!("{:?}", value));
+    }
+}
+
+fn splitmix64(x: u64) -> u64 {
+    let mut z = x.wrapping_add(0x9e37_79b9_7f4a_7c15);
+    z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+    z ^ (z >> 31)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Arc;
+    use tracing_subscriber::layer::SubscriberExt;
+
+    /// Sink keeping exported spans in memory
+    #[derive(Clone, Default)]
+    struct Collect(Arc<Mutex<Vec<AgentreplaySpan>>>);
+
+    impl SpanSink for Collect {
+        fn export(&self, span: AgentreplaySpan) -> bool {
+            self.0.lock().unwrap().push(span);
+            true
+        }
+    }
+
+    fn collect(options: SelfTraceOptions, f: impl FnOnce()) -> Vec<AgentreplaySpan> {
+        let sink = Collect::default();
+        let subscriber =
+            tracing_subscriber::registry().with(SelfTraceLayer::new(sink.clone(), options));
+        tracing::subscriber::with_default(subscriber, f);
+        let spans = sink.0.lock().unwrap().clone();
+        spans
+    }
+
+    fn options(sample_rate: f64) -> SelfTraceOptions {
+        SelfTraceOptions {
+            sample_rate,
+            max_spans_per_second: 1000,
+            targets: Vec::new(),
+            attributes: HashMap::from([("project_id".to_string(), "65535".to_string())]),
+        }
+    }
+
+    #[test]
+    fn test_exports_span_tree() {
+        let spans = collect(options(1.0), || {
+            let root = tracing::info_span!("ingest", project_id = 7, spans = tracing::field::Empty);
+            let _root = root.enter();
+            root.record("spans", 3);
+            let _child = tracing::info_span!("governor").entered();
+            tracing::info_span!("store").in_scope(|| {});
+        });
+
+        assert_eq!(spans.len(), 3);
+        let by_name: HashMap<_, _> = spans.iter().map(|s| (s.name.as_str(), s)).collect();
+        let (root, governor, store) = (by_name["ingest"], by_name["governor"], by_name["store"]);
+        assert!(spans.iter().all(|s| s.trace_id == root.trace_id));
+        assert_eq!(root.parent_span_id, None);
+        assert_eq!(governor.parent_span_id.as_ref(), Some(&root.span_id));
+        assert_eq!(store.parent_span_id.as_ref(), Some(&governor.span_id));
+        assert_eq!(root.attributes["spans"], "3");
+        // Configured attributes win over span fields
+        assert_eq!(root.attributes["project_id"], "65535");
+        assert!(root.end_time.unwrap() >= root.start_time);
+    }
+
+    #[test]
+    fn test_export_spans_are_not_exported() {
+        let spans = collect(options(1.0), || {
+            export_span().in_scope(|| {
+                tracing::info_span!("flush").in_scope(|| {
+                    tracing::info_span!("http_request").in_scope(|| {});
+                });
+            });
+            tracing::info_span!("query").in_scope(|| {});
+        });
+        let names: Vec<_> = spans.iter().map(|s| s.name.as_str()).collect();
+        assert_eq!(names, ["query"]);
+    }
+
+    #[test]
+    fn test_sampling_targets_and_rate_cap() {
+        // Sampling keeps or drops whole traces
+        let spans = collect(options(0.5), || {
+            for _ in 0..200 {
+                tracing::info_span!("root").in_scope(|| {
+                    tracing::info_span!("child").in_scope(|| {});
+                });
+            }
+        });
+        let roots = spans.iter().filter(|s| s.name == "root").count();
+        assert!((40..160).contains(&roots), "{} roots sampled", roots);
+        assert_eq!(spans.len(), roots * 2);
+        assert!(collect(options(0.0), || tracing::info_span!("root").in_scope(|| {})).is_empty());
+
+        let mut targeted = options(1.0);
+        targeted.targets = vec!["agentreplay_server::api".to_string()];
+        assert!(collect(targeted, || tracing::info_span!("root").in_scope(|| {})).is_empty());
+
+        let mut capped = options(1.0);
+        capped.max_spans_per_second = 5;
+        let spans = collect(capped, || {
+            for _ in 0..50 {
+                tracing::info_span!("root").in_scope(|| {});
+            }
+        });
+        // The loop may straddle a second boundary
+        assert!((5..=10).contains(&spans.len()), "{} spans", spans.len());
+    }
+}
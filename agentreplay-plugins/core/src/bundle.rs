@@ -24,7 +24,7 @@ use crate::manifest::{
     InstallScope, PluginManifest, TargetKind, InstallOp,
 };
 use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap};
 use std::path::{Path, PathBuf};
 
 // ============================================================================
@@ -134,6 +134,24 @@ impl VariableContext {
         self.variables
             .insert("cursor_mcp_path".to_string(), cursor_mcp_path);
 
+        // Editor hook configuration paths
+        let claude_settings_path = match scope {
+            InstallScope::Project => format!("{}/.claude/settings.json", self.get("project_dir")),
+            InstallScope::Local => {
+                format!("{}/.claude/settings.local.json", self.get("project_dir"))
+            }
+            _ => format!("{}/.claude/settings.json", self.get("home")),
+        };
+        self.variables
+            .insert("claude_settings_path".to_string(), claude_settings_path);
+
+        let cursor_hooks_path = match scope {
+            InstallScope::Project => format!("{}/.cursor/hooks.json", self.get("project_dir")),
+            _ => format!("{}/.cursor/hooks.json", self.get("home")),
+        };
+        self.variables
+            .insert("cursor_hooks_path".to_string(), cursor_hooks_path);
+
         self
     }
 
@@ -158,6 +176,22 @@ impl VariableContext {
         }
         result
     }
+
+    /// Substitute variables in every string of a JSON value
+    pub fn substitute_json(&self, value: &serde_json::Value) -> serde_json::Value {
+        match value {
+            serde_json::Value::String(s) => serde_json::Value::String(self.substitute(s)),
+            serde_json::Value::Array(items) => {
+                serde_json::Value::Array(items.iter().map(|v| self.substitute_json(v)).collect())
+            }
+            serde_json::Value::Object(obj) => serde_json::Value::Object(
+                obj.iter()
+                    .map(|(k, v)| (k.clone(), self.substitute_json(v)))
+                    .collect(),
+            ),
+            other => other.clone(),
+        }
+    }
 }
 
 // ============================================================================
@@ -394,6 +428,11 @@ pub enum ResolvedInstallOp {
         create_if_missing: bool,
         initial_content: Option<serde_json::Value>,
     },
+    JsonArrayAppend {
+        file: PathBuf,
+        entries: BTreeMap<String, Vec<serde_json::Value>>,
+        initial_content: Option<serde_json::Value>,
+    },
     Copy {
         src: PathBuf,
         dst: PathBuf,
@@ -502,6 +541,15 @@ pub enum ReverseOp {
     RestoreJson { file: PathBuf, original: serde_json::Value },
     /// Remove keys from JSON
     RemoveJsonKeys { file: PathBuf, keys: Vec<String> },
+    /// Remove appended entries from JSON arrays, keyed by JSON Pointer
+    RemoveJsonArrayEntries {
+        file: PathBuf,
+        entries: BTreeMap<String, Vec<serde_json::Value>>,
+        /// Content the file was created with; the file is deleted if only
+        /// this is left
+        #[serde(default)]
+        created_from: Option<serde_json::Value>,
+    },
     /// No reverse possible
     None { reason: String },
 }
@@ -557,6 +605,14 @@ impl InstallReceipt {
             .join("receipts")
             .join(format!("{}_{}.json", plugin_id, target_id))
     }
+
+    /// Directory holding backups of files overwritten by a given install
+    pub fn backup_dir(agentreplay_data_dir: &Path, plugin_id: &str, target_id: &str) -> PathBuf {
+        agentreplay_data_dir
+            .join("receipts")
+            .join("backups")
+            .join(format!("{}_{}", plugin_id, target_id))
+    }
 }
 
 /// Verify an installation against its receipt
@@ -587,11 +643,32 @@ pub fn verify_installation(receipt: &InstallReceipt) -> VerifyResult {
         }
     }
 
+    // Config entries (e.g. editor hooks) must still be registered
+    let mut missing_entries = 0;
+    for op in &receipt.operations_performed {
+        let Some(ReverseOp::RemoveJsonArrayEntries { file, entries, .. }) = &op.reverse else {
+            continue;
+        };
+        let missing = match read_json_or(file, None) {
+            Ok(json) if file.exists() => json_array_missing(&json, entries),
+            _ => entries.values().map(Vec::len).sum(),
+        };
+        if missing > 0 {
+            missing_entries += missing;
+            issues.push(format!(
+                "{} entries are missing from {}",
+                missing,
+                file.display()
+            ));
+        }
+    }
+
     VerifyResult {
         valid: issues.is_empty(),
         verified_files,
         missing_files,
         modified_files,
+        missing_entries,
         issues,
     }
 }
@@ -603,6 +680,8 @@ pub struct VerifyResult {
     pub verified_files: usize,
     pub missing_files: usize,
     pub modified_files: usize,
+    #[serde(default)]
+    pub missing_entries: usize,
     pub issues: Vec<String>,
 }
 
@@ -751,6 +830,28 @@ fn plan_install_ops(
                     },
                 )
             }
+            InstallOp::JsonArrayAppend { file, entries, description, initial_content } => {
+                let resolved_file = PathBuf::from(context.substitute(file));
+                let resolved_entries = entries
+                    .iter()
+                    .map(|(pointer, values)| {
+                        (
+                            pointer.clone(),
+                            values.iter().map(|v| context.substitute_json(v)).collect(),
+                        )
+                    })
+                    .collect();
+                (
+                    "json_array_append".to_string(),
+                    vec![resolved_file.clone()],
+                    description.clone(),
+                    ResolvedInstallOp::JsonArrayAppend {
+                        file: resolved_file,
+                        entries: resolved_entries,
+                        initial_content: initial_content.clone(),
+                    },
+                )
+            }
             InstallOp::Copy { src, dst, overwrite, description } => {
                 let src_path = assets_path.join(src);
                 let dst_path = PathBuf::from(context.substitute(dst));
@@ -1198,6 +1299,11 @@ async fn execute_install_op(op: &ResolvedInstallOp) -> PluginResult<()> {
                 PluginError::InstallFailed(format!("Failed to write {}: {}", file.display(), e))
             })?;
         }
+        ResolvedInstallOp::JsonArrayAppend { file, entries, initial_content } => {
+            let json = read_json_or(file, initial_content.as_ref())?;
+            let (json, _) = json_array_append(json, entries)?;
+            write_json(file, &json)?;
+        }
         ResolvedInstallOp::Copy { src, dst, overwrite } => {
             if dst.exists() && !*overwrite {
                 return Err(PluginError::InstallFailed(format!(
@@ -1451,6 +1557,612 @@ fn json_pointer_remove(doc: &mut serde_json::Value, parts: &[&str]) -> PluginRes
     Ok(())
 }
 
+// ============================================================================
+// JSON Array Entries
+// ============================================================================
+
+/// Read a JSON file, or start from `initial` (an empty object by default)
+/// if it doesn't exist
+fn read_json_or(
+    file: &Path,
+    initial: Option<&serde_json::Value>,
+) -> PluginResult<serde_json::Value> {
+    if !file.exists() {
+        return Ok(initial
+            .cloned()
+            .unwrap_or_else(|| serde_json::Value::Object(serde_json::Map::new())));
+    }
+    let content = std::fs::read_to_string(file).map_err(|e| {
+        PluginError::InstallFailed(format!("Failed to read {}: {}", file.display(), e))
+    })?;
+    serde_json::from_str(&content).map_err(|e| {
+        PluginError::InstallFailed(format!("Invalid JSON in {}: {}", file.display(), e))
+    })
+}
+
+fn write_json(file: &Path, json: &serde_json::Value) -> PluginResult<()> {
+    if let Some(parent) = file.parent() {
+        std::fs::create_dir_all(parent).ok();
+    }
+    let content = serde_json::to_string_pretty(json)
+        .map_err(|e| PluginError::InstallFailed(format!("Failed to serialize JSON: {}", e)))?;
+    std::fs::write(file, content).map_err(|e| {
+        PluginError::InstallFailed(format!("Failed to write {}: {}", file.display(), e))
+    })
+}
+
+/// Split a JSON Pointer into unescaped reference tokens (RFC 6901)
+fn pointer_tokens(pointer: &str) -> Vec<String> {
+    pointer
+        .trim_start_matches('/')
+        .split('/')
+        .filter(|t| !t.is_empty())
+        .map(|t| t.replace("~1", "/").replace("~0", "~"))
+        .collect()
+}
+
+/// Append `entries` to the arrays they are keyed by, creating missing
+/// arrays and objects on the way
+///
+/// Entries equal to an existing item are skipped, so appending twice is a
+/// no-op. Returns the document and the entries that were actually added.
+fn json_array_append(
+    mut doc: serde_json::Value,
+    entries: &BTreeMap<String, Vec<serde_json::Value>>,
+) -> PluginResult<(serde_json::Value, BTreeMap<String, Vec<serde_json::Value>>)> {
+    let mut appended = BTreeMap::new();
+
+    for (pointer, values) in entries {
+        let mut current = &mut doc;
+        for token in pointer_tokens(pointer) {
+            let serde_json::Value::Object(obj) = current else {
+                return Err(PluginError::InstallFailed(format!(
+                    "Cannot create array at {}: parent is not an object",
+                    pointer
+                )));
+            };
+            current = obj
+                .entry(token)
+                .or_insert_with(|| serde_json::Value::Object(serde_json::Map::new()));
+        }
+        if current.as_object().is_some_and(|obj| obj.is_empty()) {
+            *current = serde_json::Value::Array(Vec::new());
+        }
+        let serde_json::Value::Array(items) = current else {
+            return Err(PluginError::InstallFailed(format!(
+                "Value at {} is not an array",
+                pointer
+            )));
+        };
+
+        let mut added = Vec::new();
+        for value in values {
+            if !items.contains(value) {
+                items.push(value.clone());
+                added.push(value.clone());
+            }
+        }
+        if !added.is_empty() {
+            appended.insert(pointer.clone(), added);
+        }
+    }
+
+    Ok((doc, appended))
+}
+
+/// Remove `entries` from the arrays they are keyed by, dropping arrays that
+/// end up empty
+fn json_array_remove(
+    mut doc: serde_json::Value,
+    entries: &BTreeMap<String, Vec<serde_json::Value>>,
+) -> serde_json::Value {
+    for (pointer, values) in entries {
+        let tokens = pointer_tokens(pointer);
+        let Some((last, parents)) = tokens.split_last() else {
+            continue;
+        };
+        let mut parent = Some(&mut doc);
+        for token in parents {
+            parent = parent.and_then(|v| v.get_mut(token.as_str()));
+        }
+        let Some(serde_json::Value::Object(obj)) = parent else {
+            continue;
+        };
+        let emptied = match obj.get_mut(last.as_str()) {
+            Some(serde_json::Value::Array(items)) => {
+                items.retain(|item| !values.contains(item));
+                items.is_empty()
+            }
+            _ => false,
+        };
+        if emptied {
+            obj.remove(last.as_str());
+        }
+    }
+    doc
+}
+
+/// Entries from `entries` that are no longer present in `doc`
+fn json_array_missing(
+    doc: &serde_json::Value,
+    entries: &BTreeMap<String, Vec<serde_json::Value>>,
+) -> usize {
+    entries
+        .iter()
+        .map(|(pointer, values)| {
+            let items = pointer_tokens(pointer)
+                .iter()
+                .try_fold(doc, |v, token| v.get(token.as_str()))
+                .and_then(|v| v.as_array());
+            values
+                .iter()
+                .filter(|value| !items.is_some_and(|items| items.contains(value)))
+                .count()
+        })
+        .sum()
+}
+
+// ============================================================================
+// Tracked Installation
+// ============================================================================
+
+/// Undo information gathered before an operation runs
+#[derive(Default)]
+struct PreparedOperation {
+    reverse: Option<ReverseOp>,
+    /// Files the operation writes, with the backup of any previous content
+    written_files: Vec<(PathBuf, Option<PathBuf>)>,
+    /// Directories the operation creates
+    created_dirs: Vec<PathBuf>,
+    json_modification: Option<JsonModification>,
+}
+
+/// Builds an [`InstallReceipt`] while an install plan executes
+struct ReceiptRecorder {
+    receipt: InstallReceipt,
+    backup_dir: PathBuf,
+    backups: usize,
+}
+
+impl ReceiptRecorder {
+    /// Back up `path` if it exists and note the directories writing it creates
+    fn prepare_file(&mut self, path: &Path, prepared: &mut PreparedOperation) -> PluginResult<()> {
+        let backup = if path.is_file() {
+            std::fs::create_dir_all(&self.backup_dir)?;
+            let name = path
+                .file_name()
+                .map(|n| n.to_string_lossy().to_string())
+                .unwrap_or_default();
+            let backup = self.backup_dir.join(format!("{}_{}", self.backups, name));
+            self.backups += 1;
+            std::fs::copy(path, &backup)?;
+            Some(backup)
+        } else {
+            None
+        };
+        prepared.created_dirs.extend(missing_ancestors(path));
+        prepared.written_files.push((path.to_path_buf(), backup));
+        Ok(())
+    }
+
+    /// Reverse for an op that rewrites a JSON config file in place
+    fn prepare_json(
+        &mut self,
+        file: &Path,
+        added_keys: Vec<String>,
+        prepared: &mut PreparedOperation,
+    ) -> PluginResult<()> {
+        prepared.reverse = Some(if file.exists() {
+            ReverseOp::RestoreJson {
+                file: file.to_path_buf(),
+                original: read_json_or(file, None)?,
+            }
+        } else {
+            ReverseOp::DeleteFile {
+                path: file.to_path_buf(),
+            }
+        });
+        prepared.created_dirs.extend(missing_ancestors(file));
+        prepared.json_modification = Some(JsonModification {
+            file: file.to_path_buf(),
+            added_keys,
+            original_values: HashMap::new(),
+            removed_keys: Vec::new(),
+        });
+        Ok(())
+    }
+
+    fn prepare_operation(&mut self, op: &PlannedOperation) -> PluginResult<PreparedOperation> {
+        let mut prepared = PreparedOperation::default();
+        match op.operation {
+            CopyStrategy::CopyDir => {
+                prepared
+                    .created_dirs
+                    .extend(missing_ancestors(&op.destination));
+                if !op.destination.exists() {
+                    prepared.created_dirs.push(op.destination.clone());
+                }
+                for entry in walkdir::WalkDir::new(&op.source).min_depth(1) {
+                    let entry = entry.map_err(|e| PluginError::InstallFailed(e.to_string()))?;
+                    let Ok(relative) = entry.path().strip_prefix(&op.source) else {
+                        continue;
+                    };
+                    let dst = op.destination.join(relative);
+                    if entry.file_type().is_dir() {
+                        if !dst.exists() {
+                            prepared.created_dirs.push(dst);
+                        }
+                    } else {
+                        self.prepare_file(&dst, &mut prepared)?;
+                    }
+                }
+            }
+            CopyStrategy::MergeJson => {
+                let keys = read_json_or(&op.source, None)?
+                    .as_object()
+                    .map(|obj| obj.keys().cloned().collect())
+                    .unwrap_or_default();
+                self.prepare_json(&op.destination, keys, &mut prepared)?;
+            }
+            CopyStrategy::Copy | CopyStrategy::AppendText | CopyStrategy::Symlink => {
+                self.prepare_file(&op.destination, &mut prepared)?;
+            }
+        }
+        Ok(prepared)
+    }
+
+    fn prepare_install_op(&mut self, op: &ResolvedInstallOp) -> PluginResult<PreparedOperation> {
+        let mut prepared = PreparedOperation::default();
+        match op {
+            ResolvedInstallOp::JsonMerge { file, object, .. } => {
+                let keys = object
+                    .as_object()
+                    .map(|obj| obj.keys().cloned().collect())
+                    .unwrap_or_default();
+                self.prepare_json(file, keys, &mut prepared)?;
+            }
+            ResolvedInstallOp::JsonPatch { file, patch, .. } => {
+                let paths = patch.iter().map(|p| p.path.clone()).collect();
+                self.prepare_json(file, paths, &mut prepared)?;
+            }
+            ResolvedInstallOp::JsonArrayAppend {
+                file,
+                entries,
+                initial_content,
+            } => {
+                // Only entries that aren't there yet are ours to remove later
+                let original = read_json_or(file, initial_content.as_ref())?;
+                let created_from = (!file.exists()).then(|| original.clone());
+                let (_, appended) = json_array_append(original, entries)?;
+                prepared.created_dirs.extend(missing_ancestors(file));
+                prepared.json_modification = Some(JsonModification {
+                    file: file.clone(),
+                    added_keys: appended.keys().cloned().collect(),
+                    original_values: HashMap::new(),
+                    removed_keys: Vec::new(),
+                });
+                prepared.reverse = Some(ReverseOp::RemoveJsonArrayEntries {
+                    file: file.clone(),
+                    entries: appended,
+                    created_from,
+                });
+            }
+            ResolvedInstallOp::Copy { dst, .. } | ResolvedInstallOp::Symlink { dst, .. } => {
+                self.prepare_file(dst, &mut prepared)?;
+            }
+            ResolvedInstallOp::AppendText { file, .. } => {
+                self.prepare_file(file, &mut prepared)?;
+            }
+            ResolvedInstallOp::Mkdir { path } => {
+                prepared.created_dirs.extend(missing_ancestors(path));
+                if !path.exists() {
+                    prepared.created_dirs.push(path.clone());
+                }
+            }
+            ResolvedInstallOp::Exec { .. } => {
+                prepared.reverse = Some(ReverseOp::None {
+                    reason: "Commands cannot be undone".to_string(),
+                });
+            }
+        }
+        Ok(prepared)
+    }
+
+    /// Record an operation once it has run
+    fn record(&mut self, index: usize, op_type: &str, prepared: PreparedOperation, success: bool) {
+        // Files written before a failure may still need cleaning up
+        for (path, backup_path) in prepared.written_files {
+            if let Ok(content) = std::fs::read(&path) {
+                self.receipt.created_files.push(CreatedFile {
+                    path,
+                    content_hash: sha256_hash(&content),
+                    was_overwrite: backup_path.is_some(),
+                    backup_path,
+                });
+            }
+        }
+        for dir in prepared.created_dirs {
+            if dir.exists() && !self.receipt.created_dirs.contains(&dir) {
+                self.receipt.created_dirs.push(dir);
+            }
+        }
+        if success {
+            self.receipt
+                .json_modifications
+                .extend(prepared.json_modification);
+        }
+        self.receipt.operations_performed.push(PerformedOperation {
+            index,
+            op_type: op_type.to_string(),
+            reverse: prepared.reverse,
+            success,
+        });
+    }
+}
+
+/// Ancestors of `path` that don't exist yet, outermost first
+fn missing_ancestors(path: &Path) -> Vec<PathBuf> {
+    let mut missing: Vec<PathBuf> = path
+        .ancestors()
+        .skip(1)
+        .take_while(|p| !p.as_os_str().is_empty() && !p.exists())
+        .map(Path::to_path_buf)
+        .collect();
+    missing.reverse();
+    missing
+}
+
+/// Execute an installation plan and save a receipt for verify/uninstall
+///
+/// Unlike [`execute_install_plan`], files that get overwritten are backed up
+/// under `data_dir` first, so [`uninstall_bundle_target`] can restore them.
+/// An existing installation of the target is uninstalled first, which makes
+/// this an update. The receipt is saved even if some operations fail, so a
+/// partial install can be rolled back.
+pub async fn install_bundle_target(
+    plan: &InstallPlan,
+    plugin_id: &str,
+    data_dir: &Path,
+) -> PluginResult<(InstallExecutionResult, InstallReceipt)> {
+    if InstallReceipt::receipt_path(data_dir, plugin_id, &plan.target_id).exists() {
+        let previous = uninstall_bundle_target(data_dir, plugin_id, &plan.target_id)?;
+        if !previous.success {
+            return Err(PluginError::InstallFailed(format!(
+                "Failed to remove the previous installation: {}",
+                previous
+                    .failed
+                    .iter()
+                    .map(|f| f.error.as_str())
+                    .collect::<Vec<_>>()
+                    .join("; ")
+            )));
+        }
+    }
+
+    let mut recorder = ReceiptRecorder {
+        receipt: InstallReceipt::new(plugin_id.to_string(), plan.target_id.clone(), plan.scope),
+        backup_dir: InstallReceipt::backup_dir(data_dir, plugin_id, &plan.target_id),
+        backups: 0,
+    };
+    let mut completed = Vec::new();
+    let mut failed = Vec::new();
+    let mut commands_run = Vec::new();
+
+    for (index, op) in plan.operations.iter().enumerate() {
+        let label = format!("{} -> {}", op.source.display(), op.destination.display());
+        let result = match recorder.prepare_operation(op) {
+            Ok(prepared) => {
+                let result = execute_operation(op).await;
+                let op_type = format!("{:?}", op.operation).to_lowercase();
+                recorder.record(index, &op_type, prepared, result.is_ok());
+                result
+            }
+            Err(e) => Err(e),
+        };
+        match result {
+            Ok(()) => completed.push(format!("{:?}: {}", op.operation, label)),
+            Err(e) => failed.push(OperationError {
+                operation: label,
+                error: e.to_string(),
+            }),
+        }
+    }
+
+    for install_op in &plan.install_ops {
+        let index = plan.operations.len() + install_op.index;
+        let result = match recorder.prepare_install_op(&install_op.resolved) {
+            Ok(prepared) => {
+                let result = execute_install_op(&install_op.resolved).await;
+                recorder.record(index, &install_op.op_type, prepared, result.is_ok());
+                result
+            }
+            Err(e) => Err(e),
+        };
+        match result {
+            Ok(()) => {
+                let desc = install_op
+                    .description
+                    .as_deref()
+                    .unwrap_or(&install_op.op_type);
+                completed.push(format!("{}: {}", install_op.op_type, desc));
+            }
+            Err(e) => failed.push(OperationError {
+                operation: format!("{}[{}]", install_op.op_type, install_op.index),
+                error: e.to_string(),
+            }),
+        }
+    }
+
+    for cmd in &plan.commands {
+        if cmd.auto_run {
+            commands_run.push(execute_command(cmd).await);
+        }
+    }
+
+    let receipt = recorder.receipt;
+    receipt.save(&InstallReceipt::receipt_path(
+        data_dir,
+        plugin_id,
+        &plan.target_id,
+    ))?;
+
+    Ok((
+        InstallExecutionResult {
+            success: failed.is_empty(),
+            completed_operations: completed,
+            failed_operations: failed,
+            commands_run,
+        },
+        receipt,
+    ))
+}
+
+/// Result of uninstalling a bundle target
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BundleUninstallResult {
+    /// Whether everything was reverted
+    pub success: bool,
+    /// Reverted changes
+    pub reverted: Vec<String>,
+    /// Changes that could not be reverted
+    pub failed: Vec<OperationError>,
+}
+
+fn apply_reverse_op(reverse: &ReverseOp) -> PluginResult<Option<String>> {
+    match reverse {
+        ReverseOp::DeleteFile { path } => {
+            if path.exists() {
+                std::fs::remove_file(path)?;
+                return Ok(Some(format!("Deleted {}", path.display())));
+            }
+        }
+        ReverseOp::RestoreFile { path, backup } => {
+            std::fs::copy(backup, path)?;
+            return Ok(Some(format!("Restored {}", path.display())));
+        }
+        ReverseOp::RemoveDir { path } => {
+            if std::fs::remove_dir(path).is_ok() {
+                return Ok(Some(format!("Removed {}", path.display())));
+            }
+        }
+        ReverseOp::RestoreJson { file, original } => {
+            write_json(file, original)?;
+            return Ok(Some(format!("Restored {}", file.display())));
+        }
+        ReverseOp::RemoveJsonKeys { file, keys } => {
+            if file.exists() {
+                let mut json = read_json_or(file, None)?;
+                if let Some(obj) = json.as_object_mut() {
+                    for key in keys {
+                        obj.remove(key);
+                    }
+                }
+                write_json(file, &json)?;
+                return Ok(Some(format!("Removed keys from {}", file.display())));
+            }
+        }
+        ReverseOp::RemoveJsonArrayEntries {
+            file,
+            entries,
+            created_from,
+        } => {
+            if file.exists() && !entries.is_empty() {
+                let json = json_array_remove(read_json_or(file, None)?, entries);
+                if created_from.as_ref() == Some(&json) {
+                    std::fs::remove_file(file)?;
+                    return Ok(Some(format!("Deleted {}", file.display())));
+                }
+                write_json(file, &json)?;
+                return Ok(Some(format!("Removed entries from {}", file.display())));
+            }
+        }
+        ReverseOp::None { .. } => {}
+    }
+    Ok(None)
+}
+
+/// Revert an installation recorded in `receipt`
+///
+/// Operations are undone in reverse order, then written files are restored
+/// from their backups or deleted, then created directories are removed if
+/// they are empty.
+pub fn uninstall_installation(receipt: &InstallReceipt) -> BundleUninstallResult {
+    let mut reverses: Vec<ReverseOp> = receipt
+        .operations_performed
+        .iter()
+        .rev()
+        .filter_map(|op| op.reverse.clone())
+        .collect();
+    reverses.extend(
+        receipt
+            .created_files
+            .iter()
+            .rev()
+            .map(|file| match &file.backup_path {
+                Some(backup) => ReverseOp::RestoreFile {
+                    path: file.path.clone(),
+                    backup: backup.clone(),
+                },
+                None => ReverseOp::DeleteFile {
+                    path: file.path.clone(),
+                },
+            }),
+    );
+    reverses.extend(
+        receipt
+            .created_dirs
+            .iter()
+            .rev()
+            .map(|path| ReverseOp::RemoveDir { path: path.clone() }),
+    );
+
+    let mut reverted = Vec::new();
+    let mut failed = Vec::new();
+    for reverse in &reverses {
+        match apply_reverse_op(reverse) {
+            Ok(Some(done)) => reverted.push(done),
+            Ok(None) => {}
+            Err(e) => failed.push(OperationError {
+                operation: format!("{:?}", reverse),
+                error: e.to_string(),
+            }),
+        }
+    }
+
+    BundleUninstallResult {
+        success: failed.is_empty(),
+        reverted,
+        failed,
+    }
+}
+
+/// Uninstall a bundle target using its saved receipt
+///
+/// The receipt and backups are deleted once everything has been reverted.
+pub fn uninstall_bundle_target(
+    data_dir: &Path,
+    plugin_id: &str,
+    target_id: &str,
+) -> PluginResult<BundleUninstallResult> {
+    let receipt_path = InstallReceipt::receipt_path(data_dir, plugin_id, target_id);
+    if !receipt_path.exists() {
+        return Err(PluginError::NotInstalled(format!(
+            "{} ({})",
+            plugin_id, target_id
+        )));
+    }
+    let receipt = InstallReceipt::load(&receipt_path)?;
+    let result = uninstall_installation(&receipt);
+    if result.success {
+        std::fs::remove_file(&receipt_path)?;
+        let backup_dir = InstallReceipt::backup_dir(data_dir, plugin_id, target_id);
+        if backup_dir.exists() {
+            std::fs::remove_dir_all(backup_dir)?;
+        }
+    }
+    Ok(result)
+}
+
 // ============================================================================
 // Install Instructions
 // ============================================================================
@@ -1642,4 +2354,124 @@ mod tests {
         assert!(result.get("a").is_none());
         assert_eq!(result["b"], 2);
     }
+
+    #[test]
+    fn test_json_array_append_is_idempotent() {
+        let doc = serde_json::json!({
+            "hooks": {"Stop": [{"command": "user-hook"}]}
+        });
+        let entries = BTreeMap::from([
+            (
+                "/hooks/Stop".to_string(),
+                vec![serde_json::json!({"command": "ours"})],
+            ),
+            (
+                "/hooks/PostToolUse".to_string(),
+                vec![serde_json::json!({"command": "ours"})],
+            ),
+        ]);
+
+        let (doc, appended) = json_array_append(doc, &entries).unwrap();
+        assert_eq!(appended.len(), 2);
+        assert_eq!(doc["hooks"]["Stop"].as_array().unwrap().len(), 2);
+        assert_eq!(json_array_missing(&doc, &entries), 0);
+
+        let (doc, appended) = json_array_append(doc, &entries).unwrap();
+        assert!(appended.is_empty());
+        assert_eq!(doc["hooks"]["Stop"].as_array().unwrap().len(), 2);
+
+        // Removal keeps the user's entries and drops arrays left empty
+        let doc = json_array_remove(doc, &entries);
+        assert_eq!(
+            doc["hooks"]["Stop"],
+            serde_json::json!([{"command": "user-hook"}])
+        );
+        assert!(doc["hooks"].get("PostToolUse").is_none());
+        assert_eq!(json_array_missing(&doc, &entries), 2);
+    }
+
+    #[tokio::test]
+    async fn test_install_verify_uninstall_roundtrip() {
+        let dir = tempfile::tempdir().unwrap();
+        let data_dir = dir.path().join("data");
+        let assets = dir.path().join("assets");
+        std::fs::create_dir_all(&assets).unwrap();
+        std::fs::write(assets.join("hook.sh"), "#!/bin/sh\n").unwrap();
+
+        let settings = dir.path().join("editor/settings.json");
+        std::fs::create_dir_all(settings.parent().unwrap()).unwrap();
+        std::fs::write(&settings, r#"{"theme": "dark"}"#).unwrap();
+        let script = dir.path().join("editor/hooks/hook.sh");
+
+        let entries = BTreeMap::from([(
+            "/hooks/Stop".to_string(),
+            vec![serde_json::json!({"command": script.display().to_string()})],
+        )]);
+        let plan = InstallPlan {
+            target_id: "editor".to_string(),
+            display_name: "Editor".to_string(),
+            scope: InstallScope::User,
+            operations: Vec::new(),
+            install_ops: vec![
+                PlannedInstallOp {
+                    index: 0,
+                    op_type: "copy".to_string(),
+                    target_files: vec![script.clone()],
+                    description: None,
+                    resolved: ResolvedInstallOp::Copy {
+                        src: assets.join("hook.sh"),
+                        dst: script.clone(),
+                        overwrite: true,
+                    },
+                },
+                PlannedInstallOp {
+                    index: 1,
+                    op_type: "json_array_append".to_string(),
+                    target_files: vec![settings.clone()],
+                    description: None,
+                    resolved: ResolvedInstallOp::JsonArrayAppend {
+                        file: settings.clone(),
+                        entries,
+                        initial_content: None,
+                    },
+                },
+            ],
+            commands: Vec::new(),
+            warnings: Vec::new(),
+            total_bytes: 0,
+        };
+
+        let (result, receipt) = install_bundle_target(&plan, "hooks", &data_dir)
+            .await
+            .unwrap();
+        assert!(result.success, "{:?}", result.failed_operations);
+        assert!(script.exists());
+        assert!(verify_installation(&receipt).valid);
+
+        // Removing the hook entry by hand shows up in verification
+        std::fs::write(&settings, r#"{"theme": "dark"}"#).unwrap();
+        let verify = verify_installation(&receipt);
+        assert!(!verify.valid);
+        assert_eq!(verify.missing_entries, 1);
+
+        // Reinstalling replaces the previous installation
+        let (_, receipt) = install_bundle_target(&plan, "hooks", &data_dir)
+            .await
+            .unwrap();
+        assert!(verify_installation(&receipt).valid);
+        assert!(receipt
+            .created_files
+            .iter()
+            .all(|f| f.backup_path.is_none()));
+
+        let uninstall = uninstall_bundle_target(&data_dir, "hooks", "editor").unwrap();
+        assert!(uninstall.success, "{:?}", uninstall.failed);
+        assert!(!script.exists());
+        assert!(!script.parent().unwrap().exists());
+        let settings_json: serde_json::Value =
+            serde_json::from_str(&std::fs::read_to_string(&settings).unwrap()).unwrap();
+        assert_eq!(settings_json["theme"], "dark");
+        assert!(settings_json["hooks"].get("Stop").is_none());
+        assert!(!InstallReceipt::receipt_path(&data_dir, "hooks", "editor").exists());
+    }
 }
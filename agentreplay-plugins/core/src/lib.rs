@@ -94,10 +94,11 @@ pub mod wasm;
 // Re-exports
 pub use bundle::{
     create_install_plan, detect_target, execute_install_plan, get_bundle_info,
-    load_install_instructions, verify_installation, BundleInfo, BundleTargetInfo, 
-    CreatedFile, DetectionResult, InstallExecutionResult, InstallPlan, InstallReceipt,
-    JsonModification, PerformedOperation, PlannedCommand, PlannedInstallOp, PlannedOperation,
-    ResolvedInstallOp, ReverseOp, VariableContext, VariableInfo, VerifyResult,
+    install_bundle_target, load_install_instructions, uninstall_bundle_target,
+    uninstall_installation, verify_installation, BundleInfo, BundleTargetInfo,
+    BundleUninstallResult, CreatedFile, DetectionResult, InstallExecutionResult, InstallPlan,
+    InstallReceipt, JsonModification, PerformedOperation, PlannedCommand, PlannedInstallOp,
+    PlannedOperation, ResolvedInstallOp, ReverseOp, VariableContext, VariableInfo, VerifyResult,
 };
 pub use capabilities::{Capability, CapabilitySet, GrantedCapabilities};
 pub use error::{PluginError, PluginResult};
@@ -20,7 +20,7 @@
 use crate::error::{PluginError, PluginResult};
 use crate::MANIFEST_FILENAME;
 use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap};
 use std::collections::HashSet;
 use std::path::Path;
 
@@ -471,6 +471,21 @@ pub enum InstallOp {
         #[serde(default = "default_true")]
         create_parents: bool,
     },
+    /// Append entries to arrays in a JSON file, skipping entries already present
+    /// Unlike json_merge this keeps existing array items (e.g. the user's own hooks)
+    JsonArrayAppend {
+        /// Target file path (supports variable templates)
+        file: String,
+        /// JSON Pointer of each array mapped to the entries to append;
+        /// missing arrays and parent objects are created
+        entries: BTreeMap<String, Vec<serde_json::Value>>,
+        /// Description for this operation
+        #[serde(default)]
+        description: Option<String>,
+        /// Initial content if the file doesn't exist
+        #[serde(default)]
+        initial_content: Option<serde_json::Value>,
+    },
     /// Apply JSON Patch (RFC 6902) operations to a file
     /// More precise than merge for specific modifications
     JsonPatch {
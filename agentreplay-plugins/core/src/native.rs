@@ -330,6 +330,7 @@ impl Evaluator for JsonEvaluatorPlugin {
             cost: None,
             duration_ms: None,
             actionable_feedback: None,
+            highlights: Vec::new(),
         })
     }
 
@@ -15,6 +15,7 @@
 
 pub mod observation_prompts;
 pub mod context;
+pub mod template;
 
 use anyhow::Result;
 use parking_lot::RwLock;
@@ -25,6 +26,8 @@ use std::collections::HashMap;
 use std::sync::Arc;
 use thiserror::Error;
 
+pub use template::{TemplateEngine, TemplateValidation, TemplateVariables};
+
 #[derive(Error, Debug)]
 pub enum PromptError {
     #[error("Prompt not found")]
@@ -35,6 +38,11 @@ pub enum PromptError {
     StorageError(String),
     #[error("Validation error: {0}")]
     ValidationError(String),
+    #[error("Template error: {message}")]
+    TemplateError {
+        message: String,
+        line: Option<usize>,
+    },
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -0,0 +1,414 @@
+// Copyright 2025 AgentReplay (https://github.com/agentreplay)
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program. If not, see <https://www.gnu.org/licenses/>.
+
+//! Jinja2-style Prompt Templating
+//!
+//! Renders prompt templates with [minijinja], so prompts can use
+//! conditionals, loops, filters and includes on top of plain `{{ variable }}`
+//! substitution.
+//!
+//! # Sandboxing
+//!
+//! - No filesystem loader: `{% include %}` only resolves registered partials
+//! - Rendering is metered with fuel so runaway loops fail instead of hanging
+//! - Rendered output is capped at [`MAX_OUTPUT_BYTES`]
+//!
+//! # Validation
+//!
+//! [`TemplateEngine::validate`] statically collects the variables a template
+//! reads and checks them (and optionally sample values) against the prompt's
+//! [`VariableSchema`] map.
+
+use crate::{PromptError, VariableSchema, VariableType};
+use minijinja::{Environment, UndefinedBehavior};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+/// Variables passed to a template; values may be strings, numbers, lists or maps.
+pub type TemplateVariables = HashMap<String, serde_json::Value>;
+
+/// Instruction budget for a single render.
+pub const DEFAULT_FUEL: u64 = 100_000;
+
+/// Largest rendered prompt accepted, in bytes.
+pub const MAX_OUTPUT_BYTES: usize = 1024 * 1024;
+
+/// Names minijinja provides as globals; never reported as undefined.
+const BUILTIN_GLOBALS: &[&str] = &["range", "dict", "debug", "namespace", "loop", "self"];
+
+/// Sandboxed template renderer.
+#[derive(Debug, Clone)]
+pub struct TemplateEngine {
+    partials: HashMap<String, String>,
+    fuel: u64,
+    strict: bool,
+}
+
+/// A syntax or render problem, with the template line when known.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TemplateIssue {
+    pub message: String,
+    pub line: Option<usize>,
+}
+
+/// Result of checking a template against its variable schema.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct TemplateValidation {
+    pub valid: bool,
+    pub syntax_error: Option<TemplateIssue>,
+    /// Variables the template reads that the schema does not define
+    pub undefined_variables: Vec<String>,
+    /// Schema variables the template never reads
+    pub unused_variables: Vec<String>,
+    /// Required schema variables without a value or default
+    pub missing_required: Vec<String>,
+    /// Supplied values that fail the schema's type, enum or regex checks
+    pub invalid_values: Vec<String>,
+    pub render_error: Option<TemplateIssue>,
+}
+
+impl Default for TemplateEngine {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl TemplateEngine {
+    pub fn new() -> Self {
+        Self {
+            partials: HashMap::new(),
+            fuel: DEFAULT_FUEL,
+            strict: false,
+        }
+    }
+
+    /// Register a template that others can `{% include %}` by name.
+    pub fn with_partial(mut self, name: impl Into<String>, source: impl Into<String>) -> Self {
+        self.partials.insert(name.into(), source.into());
+        self
+    }
+
+    pub fn with_partials<I, K, V>(mut self, partials: I) -> Self
+    where
+        I: IntoIterator<Item = (K, V)>,
+        K: Into<String>,
+        V: Into<String>,
+    {
+        self.partials
+            .extend(partials.into_iter().map(|(k, v)| (k.into(), v.into())));
+        self
+    }
+
+    pub fn with_fuel(mut self, fuel: u64) -> Self {
+        self.fuel = fuel;
+        self
+    }
+
+    /// Fail on undefined variables instead of rendering them as empty.
+    pub fn strict(mut self, strict: bool) -> Self {
+        self.strict = strict;
+        self
+    }
+
+    fn environment(&self) -> Result<Environment<'_>, minijinja::Error> {
+        let mut env = Environment::new();
+        env.set_fuel(Some(self.fuel));
+        env.set_undefined_behavior(if self.strict {
+            UndefinedBehavior::Strict
+        } else {
+            UndefinedBehavior::Lenient
+        });
+        for (name, source) in &self.partials {
+            env.add_template(name, source)?;
+        }
+        Ok(env)
+    }
+
+    /// Render `source` with the given variables.
+    pub fn render(
+        &self,
+        source: &str,
+        variables: &TemplateVariables,
+    ) -> Result<String, PromptError> {
+        let env = self.environment().map_err(template_error)?;
+        let rendered = env
+            .template_from_str(source)
+            .and_then(|t| t.render(variables))
+            .map_err(template_error)?;
+
+        if rendered.len() > MAX_OUTPUT_BYTES {
+            return Err(PromptError::TemplateError {
+                message: format!("Rendered prompt exceeds {} bytes", MAX_OUTPUT_BYTES),
+                line: None,
+            });
+        }
+        Ok(rendered)
+    }
+
+    /// Top-level variables the template reads, in order of first appearance.
+    ///
+    /// Loop variables and `{% set %}` names are excluded since the template
+    /// defines them itself.
+    pub fn variables(&self, source: &str) -> Result<Vec<String>, PromptError> {
+        let env = self.environment().map_err(template_error)?;
+        let template = env.template_from_str(source).map_err(template_error)?;
+
+        let mut names: Vec<String> = template
+            .undeclared_variables(false)
+            .into_iter()
+            .filter(|name| !BUILTIN_GLOBALS.contains(&name.as_str()))
+            .collect();
+        names.sort_by_key(|name| {
+            (
+                source.find(name.as_str()).unwrap_or(usize::MAX),
+                name.clone(),
+            )
+        });
+        Ok(names)
+    }
+
+    /// Check a template against its schema, and `values` against both when given.
+    pub fn validate(
+        &self,
+        source: &str,
+        schema: &HashMap<String, VariableSchema>,
+        values: Option<&TemplateVariables>,
+    ) -> TemplateValidation {
+        let used = match self.variables(source) {
+            Ok(used) => used,
+            Err(e) => {
+                return TemplateValidation {
+                    syntax_error: Some(issue(e)),
+                    ..Default::default()
+                }
+            }
+        };
+
+        let mut validation = TemplateValidation {
+            undefined_variables: used
+                .iter()
+                .filter(|name| !schema.contains_key(*name))
+                .cloned()
+                .collect(),
+            ..Default::default()
+        };
+        validation.unused_variables = schema
+            .keys()
+            .filter(|name| !used.contains(name))
+            .cloned()
+            .collect();
+        validation.unused_variables.sort();
+
+        if let Some(values) = values {
+            let mut names: Vec<&String> = schema.keys().collect();
+            names.sort();
+            for name in names {
+                let var = &schema[name];
+                match values.get(name) {
+                    Some(value) => {
+                        if let Some(problem) = check_value(var, value) {
+                            validation
+                                .invalid_values
+                                .push(format!("{}: {}", name, problem));
+                        }
+                    }
+                    None if var.required && var.default_value.is_none() => {
+                        validation.missing_required.push(name.clone());
+                    }
+                    None => {}
+                }
+            }
+
+            // Fill defaults so a strict render only trips on genuinely missing data
+            let mut context = values.clone();
+            for (name, var) in schema {
+                if let Some(default) = &var.default_value {
+                    context
+                        .entry(name.clone())
+                        .or_insert_with(|| serde_json::Value::String(default.clone()));
+                }
+            }
+            if let Err(e) = self.clone().strict(true).render(source, &context) {
+                validation.render_error = Some(issue(e));
+            }
+        }
+
+        validation.valid = validation.syntax_error.is_none()
+            && validation.undefined_variables.is_empty()
+            && validation.missing_required.is_empty()
+            && validation.invalid_values.is_empty()
+            && validation.render_error.is_none();
+        validation
+    }
+}
+
+fn template_error(e: minijinja::Error) -> PromptError {
+    PromptError::TemplateError {
+        message: e.to_string(),
+        line: e.line(),
+    }
+}
+
+fn issue(e: PromptError) -> TemplateIssue {
+    match e {
+        PromptError::TemplateError { message, line } => TemplateIssue { message, line },
+        other => TemplateIssue {
+            message: other.to_string(),
+            line: None,
+        },
+    }
+}
+
+/// Type, enum and regex checks for one supplied value.
+fn check_value(var: &VariableSchema, value: &serde_json::Value) -> Option<String> {
+    let text = match value {
+        serde_json::Value::String(s) => s.clone(),
+        other => other.to_string(),
+    };
+
+    match var.var_type {
+        VariableType::Number if !value.is_number() && text.parse::<f64>().is_err() => {
+            return Some(format!("expected a number, got {}", value));
+        }
+        VariableType::Boolean if !value.is_boolean() && text != "true" && text != "false" => {
+            return Some(format!("expected a boolean, got {}", value));
+        }
+        _ => {}
+    }
+
+    if let Some(allowed) = &var.allowed_values {
+        if !allowed.contains(&text) {
+            return Some(format!("'{}' is not one of {:?}", text, allowed));
+        }
+    }
+
+    if let Some(pattern) = &var.validation_regex {
+        match regex::Regex::new(pattern) {
+            Ok(re) if !re.is_match(&text) => {
+                return Some(format!("'{}' does not match /{}/", text, pattern));
+            }
+            Err(e) => return Some(format!("invalid validation regex: {}", e)),
+            Ok(_) => {}
+        }
+    }
+
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn vars(value: serde_json::Value) -> TemplateVariables {
+        serde_json::from_value(value).unwrap()
+    }
+
+    fn schema_var(name: &str, var_type: VariableType, required: bool) -> VariableSchema {
+        VariableSchema {
+            name: name.to_string(),
+            var_type,
+            required,
+            default_value: None,
+            validation_regex: None,
+            allowed_values: None,
+        }
+    }
+
+    #[test]
+    fn test_render_conditionals_loops_filters_and_includes() {
+        let engine = TemplateEngine::new().with_partial("signature", "-- {{ team | upper }}");
+        let source = "Hi {{ name }}!\n\
+            {% if urgent %}URGENT: {% endif %}{% for item in items %}[{{ item }}]{% endfor %}\n\
+            {% include 'signature' %}";
+
+        let rendered = engine
+            .render(
+                source,
+                &vars(json!({
+                    "name": "Ada",
+                    "urgent": true,
+                    "items": ["a", "b"],
+                    "team": "support"
+                })),
+            )
+            .unwrap();
+
+        assert_eq!(rendered, "Hi Ada!\nURGENT: [a][b]\n-- SUPPORT");
+    }
+
+    #[test]
+    fn test_sandbox_limits() {
+        let engine = TemplateEngine::new().with_fuel(1_000);
+        let err = engine
+            .render(
+                "{% for i in range(10000) %}x{% endfor %}",
+                &TemplateVariables::new(),
+            )
+            .unwrap_err();
+        assert!(matches!(err, PromptError::TemplateError { .. }));
+
+        // Only registered partials can be included
+        assert!(engine
+            .render("{% include '/etc/passwd' %}", &TemplateVariables::new())
+            .is_err());
+    }
+
+    #[test]
+    fn test_variables_in_order() {
+        let engine = TemplateEngine::new();
+        let names = engine
+            .variables("{{ name }} {% for doc in docs %}{{ doc.title }}{% endfor %} {{ range(3) }}")
+            .unwrap();
+        assert_eq!(names, vec!["name", "docs"]);
+    }
+
+    #[test]
+    fn test_validate_against_schema() {
+        let engine = TemplateEngine::new();
+        let mut schema = HashMap::new();
+        schema.insert(
+            "name".to_string(),
+            schema_var("name", VariableType::String, true),
+        );
+        schema.insert(
+            "count".to_string(),
+            schema_var("count", VariableType::Number, true),
+        );
+        schema.insert(
+            "tone".to_string(),
+            schema_var("tone", VariableType::String, false),
+        );
+
+        let source = "{{ name }} has {{ count }} items for {{ customer }}";
+        let report = engine.validate(source, &schema, None);
+        assert!(!report.valid);
+        assert_eq!(report.undefined_variables, vec!["customer"]);
+        assert_eq!(report.unused_variables, vec!["tone"]);
+
+        let report = engine.validate(
+            "{{ name }} has {{ count }} items",
+            &schema,
+            Some(&vars(json!({ "count": "many" }))),
+        );
+        assert_eq!(report.missing_required, vec!["name"]);
+        assert_eq!(report.invalid_values.len(), 1);
+        assert!(report.render_error.is_some());
+
+        let report = engine.validate("line one\n{% if %}", &schema, None);
+        assert_eq!(report.syntax_error.unwrap().line, Some(2));
+        assert!(!report.valid);
+    }
+}
@@ -15,9 +15,13 @@
 
 //! Aggregation support for analytics queries
 //!
-//! Provides structures for pre-computed aggregations.
+//! Provides structures for pre-computed aggregations, and the group-by
+//! engine behind aggregate queries: callers feed one [`AggregationRow`] per
+//! span to a [`GroupByAggregator`] and get one [`GroupRow`] per time bucket
+//! and combination of dimension values.
 
 use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
 
 /// Aggregation key for grouping metrics
 #[derive(Debug, Clone, Hash, Eq, PartialEq, Serialize, Deserialize)]
@@ -57,3 +61,259 @@ impl AggregationValue {
         }
     }
 }
+
+/// Dimension a group-by query splits on
+#[derive(Debug, Clone, Copy, Hash, Eq, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum GroupDimension {
+    Model,
+    Agent,
+    PromptVersion,
+    Project,
+}
+
+impl GroupDimension {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            GroupDimension::Model => "model",
+            GroupDimension::Agent => "agent",
+            GroupDimension::PromptVersion => "prompt_version",
+            GroupDimension::Project => "project",
+        }
+    }
+}
+
+/// Metric computed for each group
+#[derive(Debug, Clone, Copy, Hash, Eq, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum GroupMetric {
+    Count,
+    Tokens,
+    Cost,
+    #[serde(rename = "p95_latency")]
+    P95Latency,
+    ErrorRate,
+}
+
+impl GroupMetric {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            GroupMetric::Count => "count",
+            GroupMetric::Tokens => "tokens",
+            GroupMetric::Cost => "cost",
+            GroupMetric::P95Latency => "p95_latency_ms",
+            GroupMetric::ErrorRate => "error_rate",
+        }
+    }
+}
+
+/// Shape of a group-by query
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct GroupByQuery {
+    pub dimensions: Vec<GroupDimension>,
+    pub metrics: Vec<GroupMetric>,
+    /// Width of time buckets; `None` aggregates the whole range at once
+    pub bucket_us: Option<u64>,
+}
+
+/// One span's contribution to a group-by query
+#[derive(Debug, Clone, Default)]
+pub struct AggregationRow {
+    pub timestamp_us: u64,
+    /// Values of the query's dimensions, in query order
+    pub dimensions: Vec<String>,
+    pub tokens: u64,
+    pub cost: f64,
+    pub latency_us: u64,
+    pub is_error: bool,
+}
+
+/// Aggregated metrics of one bucket and combination of dimension values
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct GroupRow {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub bucket_start_us: Option<u64>,
+    /// Dimension name -> value
+    pub group: BTreeMap<String, String>,
+    /// Metric name -> value
+    pub metrics: BTreeMap<String, f64>,
+}
+
+#[derive(Debug, Default)]
+struct GroupAccum {
+    count: u64,
+    tokens: u64,
+    cost: f64,
+    errors: u64,
+    latencies_us: Vec<u64>,
+}
+
+/// Incremental group-by over [`AggregationRow`]s
+pub struct GroupByAggregator {
+    query: GroupByQuery,
+    keep_latencies: bool,
+    groups: HashMap<(Option<u64>, Vec<String>), GroupAccum>,
+}
+
+impl GroupByAggregator {
+    pub fn new(query: GroupByQuery) -> Self {
+        let keep_latencies = query.metrics.contains(&GroupMetric::P95Latency);
+        Self {
+            query,
+            keep_latencies,
+            groups: HashMap::new(),
+        }
+    }
+
+    pub fn query(&self) -> &GroupByQuery {
+        &self.query
+    }
+
+    /// Number of groups so far
+    pub fn group_count(&self) -> usize {
+        self.groups.len()
+    }
+
+    pub fn add(&mut self, row: AggregationRow) {
+        let bucket = self
+            .query
+            .bucket_us
+            .filter(|width| *width > 0)
+            .map(|width| row.timestamp_us - row.timestamp_us % width);
+        let accum = self.groups.entry((bucket, row.dimensions)).or_default();
+        accum.count += 1;
+        accum.tokens += row.tokens;
+        accum.cost += row.cost;
+        if row.is_error {
+            accum.errors += 1;
+        }
+        if self.keep_latencies {
+            accum.latencies_us.push(row.latency_us);
+        }
+    }
+
+    /// Groups ordered by bucket, then by dimension values
+    pub fn finish(self) -> Vec<GroupRow> {
+        let mut keyed: Vec<_> = self.groups.into_iter().collect();
+        keyed.sort_by(|(a, _), (b, _)| a.cmp(b));
+        keyed
+            .into_iter()
+            .map(|((bucket_start_us, values), mut accum)| {
+                let group = self
+                    .query
+                    .dimensions
+                    .iter()
+                    .map(|d| d.as_str().to_string())
+                    .zip(values)
+                    .collect();
+                let metrics = self
+                    .query
+                    .metrics
+                    .iter()
+                    .map(|metric| {
+                        let value = match metric {
+                            GroupMetric::Count => accum.count as f64,
+                            GroupMetric::Tokens => accum.tokens as f64,
+                            GroupMetric::Cost => accum.cost,
+                            GroupMetric::P95Latency => {
+                                percentile(&mut accum.latencies_us, 0.95) as f64 / 1000.0
+                            }
+                            GroupMetric::ErrorRate => {
+                                accum.errors as f64 / accum.count.max(1) as f64
+                            }
+                        };
+                        (metric.as_str().to_string(), value)
+                    })
+                    .collect();
+                GroupRow {
+                    bucket_start_us,
+                    group,
+                    metrics,
+                }
+            })
+            .collect()
+    }
+}
+
+/// Nearest-rank percentile; 0 for no values
+fn percentile(values: &mut [u64], p: f64) -> u64 {
+    if values.is_empty() {
+        return 0;
+    }
+    values.sort_unstable();
+    let rank = (p * values.len() as f64).ceil() as usize;
+    values[rank.clamp(1, values.len()) - 1]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn row(timestamp_us: u64, model: &str, latency_ms: u64, is_error: bool) -> AggregationRow {
+        AggregationRow {
+            timestamp_us,
+            dimensions: vec![model.to_string()],
+            tokens: 10,
+            cost: 0.5,
+            latency_us: latency_ms * 1000,
+            is_error,
+        }
+    }
+
+    #[test]
+    fn test_group_by_with_buckets() {
+        let mut aggregator = GroupByAggregator::new(GroupByQuery {
+            dimensions: vec![GroupDimension::Model],
+            metrics: vec![
+                GroupMetric::Count,
+                GroupMetric::Tokens,
+                GroupMetric::Cost,
+                GroupMetric::P95Latency,
+                GroupMetric::ErrorRate,
+            ],
+            bucket_us: Some(3_600_000_000),
+        });
+        for latency in 1..=20 {
+            aggregator.add(row(100, "gpt-4o", latency, latency > 15));
+        }
+        aggregator.add(row(200, "claude", 7, false));
+        aggregator.add(row(3_600_000_100, "gpt-4o", 3, false));
+        assert_eq!(aggregator.group_count(), 3);
+
+        let rows = aggregator.finish();
+        assert_eq!(rows.len(), 3);
+        assert_eq!(rows[0].bucket_start_us, Some(0));
+        assert_eq!(rows[0].group["model"], "claude");
+
+        let gpt = &rows[1];
+        assert_eq!(gpt.group["model"], "gpt-4o");
+        assert_eq!(gpt.metrics["count"], 20.0);
+        assert_eq!(gpt.metrics["tokens"], 200.0);
+        assert_eq!(gpt.metrics["cost"], 10.0);
+        assert_eq!(gpt.metrics["p95_latency_ms"], 19.0);
+        assert_eq!(gpt.metrics["error_rate"], 0.25);
+
+        assert_eq!(rows[2].bucket_start_us, Some(3_600_000_000));
+        assert_eq!(rows[2].metrics["count"], 1.0);
+    }
+
+    #[test]
+    fn test_group_by_without_dimensions_or_buckets() {
+        let mut aggregator = GroupByAggregator::new(GroupByQuery {
+            dimensions: Vec::new(),
+            metrics: vec![GroupMetric::Count],
+            bucket_us: None,
+        });
+        aggregator.add(AggregationRow::default());
+        aggregator.add(AggregationRow {
+            timestamp_us: 5_000_000_000,
+            ..Default::default()
+        });
+
+        let rows = aggregator.finish();
+        assert_eq!(rows.len(), 1);
+        assert_eq!(rows[0].bucket_start_us, None);
+        assert!(rows[0].group.is_empty());
+        assert_eq!(rows[0].metrics["count"], 2.0);
+    }
+}
@@ -0,0 +1,213 @@
+// Copyright 2025 AgentReplay (https://github.com/agentreplay)
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program. If not, see <https://www.gnu.org/licenses/>.
+
+//! Moving cold traces to the archive tier and restoring them
+//!
+//! Archiving copies each tenant's old edges and payloads into a
+//! [`TraceArchive`] chunk before deleting them from the hot store; rehydration
+//! writes a chunk back and drops it from the archive. Restored traces are hot
+//! again until the next archive pass moves them out.
+
+use crate::Agentreplay;
+use agentreplay_core::{AgentFlowEdge, AgentreplayError, Result};
+use agentreplay_storage::{ArchiveChunk, ArchivedRange, TraceArchive};
+use std::collections::BTreeMap;
+use tracing::{info, warn};
+
+impl Agentreplay {
+    /// Move traces older than `before_timestamp_us` into the archive
+    ///
+    /// Writes one chunk per tenant and returns the archived ranges. Edges are
+    /// only deleted from the hot store once their chunk has been stored.
+    pub async fn archive_traces_before(
+        &self,
+        archive: &TraceArchive,
+        before_timestamp_us: u64,
+    ) -> Result<Vec<ArchivedRange>> {
+        let old_edges = self
+            .storage()
+            .range_scan(0, before_timestamp_us.saturating_sub(1))?;
+        self.archive_edges(archive, old_edges).await
+    }
+
+    /// Move one tenant's traces older than `before_timestamp_us` into the
+    /// archive, leaving other tenants' traces in the hot store
+    pub async fn archive_tenant_traces_before(
+        &self,
+        archive: &TraceArchive,
+        tenant_id: u64,
+        before_timestamp_us: u64,
+    ) -> Result<Vec<ArchivedRange>> {
+        let old_edges = self.query_temporal_range_for_tenant(
+            0,
+            before_timestamp_us.saturating_sub(1),
+            tenant_id,
+        )?;
+        self.archive_edges(archive, old_edges).await
+    }
+
+    async fn archive_edges(
+        &self,
+        archive: &TraceArchive,
+        old_edges: Vec<AgentFlowEdge>,
+    ) -> Result<Vec<ArchivedRange>> {
+        let mut by_tenant: BTreeMap<u64, Vec<AgentFlowEdge>> = BTreeMap::new();
+        for edge in old_edges {
+            by_tenant.entry(edge.tenant_id).or_default().push(edge);
+        }
+
+        let mut ranges = Vec::with_capacity(by_tenant.len());
+        for (tenant_id, edges) in by_tenant {
+            let with_payload: Vec<u128> = edges
+                .iter()
+                .filter(|e| e.has_payload != 0)
+                .map(|e| e.edge_id)
+                .collect();
+            let payloads = self
+                .get_payloads_batch(&with_payload)?
+                .into_iter()
+                .filter_map(|(edge_id, data)| data.map(|data| (edge_id, data)))
+                .collect();
+
+            let chunk = ArchiveChunk { edges, payloads };
+            let range = archive.store(tenant_id, &chunk)?;
+
+            let mut deleted = 0usize;
+            for edge in &chunk.edges {
+                if let Err(e) = self.delete(edge.edge_id, edge.tenant_id).await {
+                    warn!(
+                        edge_id = %format!("{:#x}", edge.edge_id),
+                        error = %e,
+                        "Failed to delete archived edge from hot storage"
+                    );
+                } else {
+                    deleted += 1;
+                }
+            }
+
+            info!(
+                tenant_id,
+                range_id = %range.id,
+                edges = range.edge_count,
+                deleted,
+                "Archived traces"
+            );
+            ranges.push(range);
+        }
+
+        Ok(ranges)
+    }
+
+    /// Restore an archived range into the hot store
+    ///
+    /// Returns the number of edges restored. The range is removed from the
+    /// archive once its edges and payloads are written.
+    pub fn rehydrate_archived_range(
+        &self,
+        archive: &TraceArchive,
+        range_id: &str,
+    ) -> Result<usize> {
+        let range = archive.get_range(range_id).ok_or_else(|| {
+            AgentreplayError::NotFound(format!("Archived range '{}' not found", range_id))
+        })?;
+        let chunk = archive.load(&range)?;
+
+        let payloads: Vec<(u128, &[u8])> = chunk
+            .payloads
+            .iter()
+            .map(|(edge_id, data)| (*edge_id, data.as_slice()))
+            .collect();
+        self.insert_batch_with_payloads(&chunk.edges, &payloads)?;
+        archive.remove(range_id)?;
+
+        info!(
+            tenant_id = range.tenant_id,
+            range_id,
+            edges = chunk.edges.len(),
+            "Rehydrated archived traces"
+        );
+        Ok(chunk.edges.len())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use agentreplay_core::SpanType;
+    use agentreplay_storage::{LocalFsBackend, StorageBackend};
+    use std::sync::Arc;
+    use tempfile::tempdir;
+
+    #[tokio::test]
+    async fn test_archive_and_rehydrate() {
+        let dir = tempdir().unwrap();
+        let db = Agentreplay::open(dir.path().join("db")).unwrap();
+        let backend: Arc<dyn StorageBackend> =
+            Arc::new(LocalFsBackend::new(dir.path().join("archive")).unwrap());
+        let archive = TraceArchive::open(backend).unwrap();
+
+        for i in 1..=10u64 {
+            let mut edge = AgentFlowEdge::new(1 + i % 2, 0, 1, 1, SpanType::Root, 0);
+            edge.timestamp_us = i * 1000;
+            edge.checksum = edge.compute_checksum();
+            db.insert(edge).await.unwrap();
+        }
+
+        let ranges = db.archive_traces_before(&archive, 5000).await.unwrap();
+        assert_eq!(ranges.len(), 2);
+        assert_eq!(ranges.iter().map(|r| r.edge_count).sum::<usize>(), 4);
+        assert_eq!(db.query_temporal_range(0, 4999).unwrap().len(), 0);
+        assert_eq!(db.query_temporal_range(5000, 10_000).unwrap().len(), 6);
+
+        let cold = archive.overlapping(1, 0, 3000);
+        assert_eq!(cold.len(), 1);
+        assert_eq!(
+            db.rehydrate_archived_range(&archive, &cold[0].id).unwrap(),
+            2
+        );
+        assert_eq!(
+            db.query_temporal_range_for_tenant(0, 4999, 1)
+                .unwrap()
+                .len(),
+            2
+        );
+        assert_eq!(archive.ranges().len(), 1);
+        assert!(db.rehydrate_archived_range(&archive, &cold[0].id).is_err());
+
+        // A tenant-scoped pass leaves other tenants' traces hot
+        let mut other = AgentFlowEdge::new(2, 0, 1, 1, SpanType::Root, 0);
+        other.timestamp_us = 100;
+        other.checksum = other.compute_checksum();
+        db.insert(other).await.unwrap();
+        let ranges = db
+            .archive_tenant_traces_before(&archive, 1, 5000)
+            .await
+            .unwrap();
+        assert_eq!(ranges.len(), 1);
+        assert_eq!(ranges[0].tenant_id, 1);
+        assert_eq!(
+            db.query_temporal_range_for_tenant(0, 4999, 1)
+                .unwrap()
+                .len(),
+            0
+        );
+        assert_eq!(
+            db.query_temporal_range_for_tenant(0, 4999, 2)
+                .unwrap()
+                .len(),
+            1
+        );
+    }
+}
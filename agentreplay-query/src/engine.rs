@@ -22,15 +22,21 @@ use agentreplay_core::{
     EvalDataset, EvalMetric, EvalRun, Experiment, ExperimentResult, AgentreplayError,
     PromptTemplate, Result, SpanType,
 };
-use agentreplay_index::{CausalIndex, DistanceMetric, Embedding, VectorIndex};
-use agentreplay_storage::UnifiedStorage;
+use agentreplay_index::{
+    AdaptiveEfTuner, CausalIndex, DistanceMetric, EffectiveSearchParams, Embedding, VectorIndex,
+};
+use agentreplay_storage::{
+    BackupManager, BackupMetadata, BackupOptions, UnifiedStorage, WalEntry, WalRecord, WalShipper,
+};
 use moka::sync::Cache;
 use std::collections::HashMap;
 use std::path::Path;
 use std::sync::{Arc, RwLock};
-use std::time::Duration;
+use std::time::{Duration, Instant};
 use tracing::{info, warn};
 
+use crate::tenancy::{IsolationMode, TenantQuery, TenantScope};
+
 /// Main Agentreplay database interface
 ///
 /// **Thread Safety:**
@@ -52,9 +58,12 @@ use tracing::{info, warn};
 /// - SochDB: ACID-compliant storage with MVCC (feature: `sochdb`)
 /// - InMemory: Fast in-memory storage for testing
 pub struct Agentreplay {
-    pub(crate) storage: Arc<UnifiedStorage>,
-    causal_index: Arc<CausalIndex>,
-    vector_index: Arc<VectorIndex>,
+    /// Swapped as a whole when a replica loads a newer snapshot
+    storage: RwLock<Arc<UnifiedStorage>>,
+    causal_index: RwLock<Arc<CausalIndex>>,
+    vector_index: RwLock<Arc<VectorIndex>>,
+    /// Picks ef_search per query for latency-budgeted semantic search
+    ef_tuner: Arc<AdaptiveEfTuner>,
     /// Eval metrics storage: edge_id -> Vec<EvalMetric>
     /// Thread-safe LRU cache for evaluation metrics with automatic eviction
     /// CRITICAL FIX: Replaced unbounded HashMap with bounded Cache to prevent OOM
@@ -84,6 +93,10 @@ pub struct Agentreplay {
     pub(crate) coding_sessions: Arc<RwLock<HashMap<u128, CodingSession>>>,
     /// Coding observations storage: session_id -> Vec<CodingObservation>
     pub(crate) coding_observations: Arc<RwLock<HashMap<u128, Vec<CodingObservation>>>>,
+    /// Receives a logical copy of every write when WAL shipping is enabled
+    wal_shipper: RwLock<Option<Arc<WalShipper>>>,
+    /// Whether scans without a tenant are refused
+    isolation: RwLock<IsolationMode>,
 }
 
 impl Agentreplay {
@@ -205,9 +218,10 @@ impl Agentreplay {
         );
 
         Ok(Self {
-            storage,
-            causal_index,
-            vector_index,
+            storage: RwLock::new(storage),
+            causal_index: RwLock::new(causal_index),
+            vector_index: RwLock::new(vector_index),
+            ef_tuner: Arc::new(AdaptiveEfTuner::default()),
             // CRITICAL FIX: Initialize Cache with bounded capacity and TTL
             // Prevents OOM in long-running services evaluating millions of traces
             eval_metrics: Arc::new(
@@ -226,6 +240,8 @@ impl Agentreplay {
             compliance_reports: Arc::new(RwLock::new(HashMap::new())),
             coding_sessions: Arc::new(RwLock::new(HashMap::new())),
             coding_observations: Arc::new(RwLock::new(HashMap::new())),
+            wal_shipper: RwLock::new(None),
+            isolation: RwLock::new(IsolationMode::default()),
         })
     }
 
@@ -234,18 +250,19 @@ impl Agentreplay {
         // Fix parent_count: should count actual parents, not just 0/1
         // This was a bug where parent_count used children.len() instead of actual parent count
         let mut edge = edge;
-        let parent_ids = self.causal_index.get_parents(edge.edge_id);
+        let parent_ids = self.causal_index().get_parents(edge.edge_id);
         edge.parent_count = parent_ids.len().min(255) as u8; // Cap at u8::MAX
 
         // Recompute checksum after updating parent_count
         edge.checksum = edge.compute_checksum();
 
         // Write to storage
-        self.storage.put(edge)?;
+        self.storage().put(edge)?;
 
         // Update causal index
-        self.causal_index.index(&edge);
+        self.causal_index().index(&edge);
 
+        self.ship(|| vec![WalEntry::Edge(edge)]);
         Ok(())
     }
 
@@ -261,21 +278,32 @@ impl Agentreplay {
 
         // Fix parent_count: should count actual parents, not just 0/1
         let mut edge = edge;
-        let parent_ids = self.causal_index.get_parents(edge.edge_id);
+        let parent_ids = self.causal_index().get_parents(edge.edge_id);
         edge.parent_count = parent_ids.len().min(255) as u8; // Cap at u8::MAX
 
         // Recompute checksum after updating parent_count
         edge.checksum = edge.compute_checksum();
 
         // Write to storage
-        self.storage.put(edge)?;
+        self.storage().put(edge)?;
 
         // Update causal index (always)
-        self.causal_index.index(&edge);
+        self.causal_index().index(&edge);
+
+        self.ship(|| {
+            let mut entries = vec![WalEntry::Edge(edge)];
+            if !edge.should_not_embed() {
+                entries.push(WalEntry::Vector {
+                    edge_id,
+                    values: vector.to_vec(),
+                });
+            }
+            entries
+        });
 
         // Update vector index only if SENSITIVITY_NO_EMBED is not set
         if !edge.should_not_embed() {
-            self.vector_index
+            self.vector_index()
                 .add(edge_id, vector)
                 .map_err(AgentreplayError::InvalidArgument)?;
         }
@@ -296,7 +324,7 @@ impl Agentreplay {
             .iter()
             .map(|edge| {
                 let mut edge = *edge;
-                let parent_ids = self.causal_index.get_parents(edge.edge_id);
+                let parent_ids = self.causal_index().get_parents(edge.edge_id);
                 edge.parent_count = parent_ids.len().min(255) as u8; // Cap at u8::MAX
                 edge.checksum = edge.compute_checksum(); // Recompute checksum
                 edge
@@ -304,13 +332,14 @@ impl Agentreplay {
             .collect();
 
         // Write to storage in batch
-        self.storage.put_batch(&fixed_edges)?;
+        self.storage().put_batch(&fixed_edges)?;
 
         // Update causal index for all edges
         for edge in &fixed_edges {
-            self.causal_index.index(edge);
+            self.causal_index().index(edge);
         }
 
+        self.ship(|| fixed_edges.iter().copied().map(WalEntry::Edge).collect());
         Ok(())
     }
 
@@ -330,18 +359,16 @@ impl Agentreplay {
             .iter()
             .map(|edge| {
                 let mut edge = *edge;
-                let parent_ids = self.causal_index.get_parents(edge.edge_id);
+                let parent_ids = self.causal_index().get_parents(edge.edge_id);
                 edge.parent_count = parent_ids.len().min(255) as u8;
                 edge.checksum = edge.compute_checksum();
                 edge
             })
             .collect();
 
-        // Single combined write: payloads + edges under one lock + one commit
-        self.storage.put_batch_with_payloads(&fixed_edges, payloads)?;
-
         // Task 4: Extract denormalized filter attributes from payloads
-        // This enables O(1) provider/model/route filtering without payload I/O
+        // This enables O(1) provider/model/route filtering without payload I/O.
+        // Written before the edges so the rollups attribute them to their model.
         for (edge_id, payload_bytes) in payloads {
             if let Ok(attrs) = serde_json::from_slice::<serde_json::Value>(payload_bytes) {
                 let provider = attrs.get("gen_ai.system")
@@ -359,7 +386,7 @@ impl Agentreplay {
 
                 if provider.is_some() || model.is_some() || operation.is_some() {
                     // Best-effort: don't fail the batch if attrs storage fails
-                    let _ = self.storage.put_edge_attrs(
+                    let _ = self.storage().put_edge_attrs(
                         *edge_id,
                         provider,
                         model,
@@ -369,11 +396,24 @@ impl Agentreplay {
             }
         }
 
+        // Single combined write: payloads + edges under one lock + one commit
+        self.storage().put_batch_with_payloads(&fixed_edges, payloads)?;
+
         // Update causal index for all edges
         for edge in &fixed_edges {
-            self.causal_index.index(edge);
+            self.causal_index().index(edge);
         }
 
+        self.ship(|| {
+            payloads
+                .iter()
+                .map(|(edge_id, data)| WalEntry::Payload {
+                    edge_id: *edge_id,
+                    data: data.to_vec(),
+                })
+                .chain(fixed_edges.iter().copied().map(WalEntry::Edge))
+                .collect()
+        });
         Ok(fixed_edges.len())
     }
 
@@ -383,7 +423,8 @@ impl Agentreplay {
     /// filtered out from query results. The actual data is removed during
     /// compaction.
     pub async fn delete(&self, edge_id: u128, tenant_id: u64) -> Result<()> {
-        self.storage.delete(edge_id, tenant_id)?;
+        self.storage().delete(edge_id, tenant_id)?;
+        self.ship(|| vec![WalEntry::Delete { edge_id, tenant_id }]);
         // Note: Causal and vector indexes are not updated here.
         // They will naturally "disappear" when queries filter out deleted edges.
         // For production, we might want to actively remove from indexes.
@@ -395,7 +436,9 @@ impl Agentreplay {
     /// Writes tombstone markers for all edges belonging to the specified project.
     /// Returns the number of edges deleted.
     pub async fn delete_by_project(&self, project_id: u16) -> Result<u64> {
-        self.storage.delete_by_project(project_id)
+        let deleted = self.storage().delete_by_project(project_id)?;
+        self.ship(|| vec![WalEntry::DeleteProject { project_id }]);
+        Ok(deleted)
     }
 
     /// Query pre-aggregated metrics for analytics dashboard
@@ -414,7 +457,7 @@ impl Agentreplay {
         start_ts: u64,
         end_ts: u64,
     ) -> agentreplay_storage::MetricsBucket {
-        self.storage.query_metrics(tenant_id, project_id, start_ts, end_ts)
+        self.storage().query_metrics(tenant_id, project_id, start_ts, end_ts)
     }
 
     /// Query pre-aggregated metrics with time buckets for time series charts
@@ -430,7 +473,7 @@ impl Agentreplay {
     ) -> Vec<(u64, agentreplay_storage::MetricsBucket)> {
         // tenant_id=0 means all tenants. project_id is passed from the analytics UI.
         // project_id=0 means "all projects" (wildcard).
-        self.storage.query_metrics_timeseries(0, project_id as u16, start_ts, end_ts)
+        self.storage().query_metrics_timeseries(0, project_id as u16, start_ts, end_ts)
     }
 
     /// Query sharded metrics with DDSketch percentiles and HyperLogLog cardinality
@@ -457,7 +500,7 @@ impl Agentreplay {
         _project_id: Option<u16>,
     ) -> Vec<agentreplay_storage::MetricsBucketSnapshot> {
         // Use storage query_metrics and convert to bucket snapshot
-        let bucket = self.storage.query_metrics(0, 0, start_us, end_us);
+        let bucket = self.storage().query_metrics(0, 0, start_us, end_us);
         let avg_duration = bucket.avg_duration_ms() as u64 * 1000;
         vec![agentreplay_storage::MetricsBucketSnapshot {
             timestamp_us: bucket.timestamp_us,
@@ -500,7 +543,7 @@ impl Agentreplay {
         end_us: u64,
         _project_id: Option<u16>,
     ) -> agentreplay_storage::ShardedMetricsSummary {
-        let bucket = self.storage.query_metrics(0, 0, start_us, end_us);
+        let bucket = self.storage().query_metrics(0, 0, start_us, end_us);
         agentreplay_storage::ShardedMetricsSummary {
             total_requests: bucket.request_count,
             total_errors: bucket.error_count,
@@ -516,7 +559,7 @@ impl Agentreplay {
     ///
     /// Uses the secondary session index built during ingestion.
     pub fn get_session_edges(&self, session_id: u64) -> Vec<u128> {
-        self.storage.get_session_edges(session_id)
+        self.storage().get_session_edges(session_id)
             .unwrap_or_default()
             .into_iter()
             .map(|e| e.edge_id)
@@ -527,7 +570,7 @@ impl Agentreplay {
     ///
     /// Uses the secondary project index built during ingestion.
     pub fn get_project_edges(&self, project_id: u16) -> Vec<u128> {
-        self.storage.get_project_edges(project_id)
+        self.storage().get_project_edges(project_id)
             .unwrap_or_default()
             .into_iter()
             .map(|e| e.edge_id)
@@ -536,13 +579,13 @@ impl Agentreplay {
 
     /// Get count of edges for a project (O(1))
     pub fn get_project_edge_count(&self, project_id: u16) -> usize {
-        self.storage.get_project_edge_count(project_id)
+        self.storage().get_project_edge_count(project_id)
             .unwrap_or(0) as usize
     }
 
     /// Get an edge by ID
     pub fn get(&self, edge_id: u128) -> Result<Option<AgentFlowEdge>> {
-        self.storage.get(edge_id)
+        self.storage().get(edge_id)
     }
 
     /// Get an edge by ID with tenant isolation
@@ -550,7 +593,7 @@ impl Agentreplay {
     /// **Tenant Safety:** Returns None if edge doesn't belong to the specified tenant.
     /// Use this in multi-tenant deployments to prevent data leakage.
     pub fn get_for_tenant(&self, edge_id: u128, tenant_id: u64) -> Result<Option<AgentFlowEdge>> {
-        self.storage.get_for_tenant(edge_id, tenant_id)
+        self.storage().get_for_tenant(edge_id, tenant_id)
     }
 
     /// Store variable-size attributes/metadata for an edge
@@ -565,7 +608,31 @@ impl Agentreplay {
     /// db.put_payload(edge_id, &data)?;
     /// ```
     pub fn put_payload(&self, edge_id: u128, data: &[u8]) -> Result<()> {
-        self.storage.put_payload(edge_id, data)
+        self.storage().put_payload(edge_id, data)?;
+        self.ship(|| {
+            vec![WalEntry::Payload {
+                edge_id,
+                data: data.to_vec(),
+            }]
+        });
+        Ok(())
+    }
+
+    /// Store the cost of an edge recomputed after ingest (micro-USD)
+    ///
+    /// The rollups count it instead of a cost derived from the edge's
+    /// tokens. Returns false when the edge does not exist.
+    pub fn put_edge_cost(&self, edge_id: u128, cost_micros: u64) -> Result<bool> {
+        let stored = self.storage().put_edge_cost(edge_id, cost_micros)?;
+        if stored {
+            self.ship(|| {
+                vec![WalEntry::Cost {
+                    edge_id,
+                    cost_micros,
+                }]
+            });
+        }
+        Ok(stored)
     }
 
     /// Batch-write multiple payloads in a single transaction.
@@ -573,7 +640,17 @@ impl Agentreplay {
     /// Dramatically more efficient than calling `put_payload` in a loop because
     /// it amortizes write-lock acquisition and fsync cost across the entire batch.
     pub fn put_payloads_batch(&self, payloads: &[(u128, &[u8])]) -> Result<()> {
-        self.storage.put_payloads_batch(payloads)
+        self.storage().put_payloads_batch(payloads)?;
+        self.ship(|| {
+            payloads
+                .iter()
+                .map(|(edge_id, data)| WalEntry::Payload {
+                    edge_id: *edge_id,
+                    data: data.to_vec(),
+                })
+                .collect()
+        });
+        Ok(())
     }
 
     /// Retrieve attributes/metadata for an edge
@@ -589,49 +666,361 @@ impl Agentreplay {
     /// }
     /// ```
     pub fn get_payload(&self, edge_id: u128) -> Result<Option<Vec<u8>>> {
-        self.storage.get_payload(edge_id)
+        self.storage().get_payload(edge_id)
     }
 
     /// Batch-fetch multiple payloads (Task 10)
     ///
     /// **Performance:** Amortizes LSM lookup overhead across N payloads.
     pub fn get_payloads_batch(&self, edge_ids: &[u128]) -> Result<Vec<(u128, Option<Vec<u8>>)>> {
-        self.storage.get_payloads_batch(edge_ids)
+        self.storage().get_payloads_batch(edge_ids)
     }
 
     /// Get denormalized filter attributes for an edge (Task 4)
     ///
     /// Returns `(provider, model, operation_name)` without payload I/O.
     pub fn get_edge_attrs(&self, edge_id: u128) -> Result<(String, String, String)> {
-        self.storage.get_edge_attrs(edge_id)
+        self.storage().get_edge_attrs(edge_id)
+    }
+
+    /// Store denormalized filter attributes (provider, model, operation) for an edge
+    ///
+    /// Write them before the edge itself so its rollups carry the model.
+    pub fn put_edge_attrs(
+        &self,
+        edge_id: u128,
+        provider: Option<&str>,
+        model: Option<&str>,
+        operation_name: Option<&str>,
+    ) -> Result<()> {
+        self.storage()
+            .put_edge_attrs(edge_id, provider, model, operation_name)
     }
 
     /// Get denormalized filter attributes for multiple edges (Task 4)
     pub fn get_edge_attrs_batch(&self, edge_ids: &[u128]) -> Result<std::collections::HashMap<u128, (String, String, String)>> {
-        self.storage.get_edge_attrs_batch(edge_ids)
+        self.storage().get_edge_attrs_batch(edge_ids)
+    }
+
+    /// Store the ingest-time enrichment (model family, provider, SDK, country) of an edge
+    pub fn put_edge_enrichment(
+        &self,
+        edge_id: u128,
+        enrichment: &agentreplay_storage::EdgeEnrichment,
+    ) -> Result<()> {
+        self.storage().put_edge_enrichment(edge_id, enrichment)
+    }
+
+    /// Get the ingest-time enrichment of an edge without payload I/O
+    pub fn get_edge_enrichment(
+        &self,
+        edge_id: u128,
+    ) -> Result<Option<agentreplay_storage::EdgeEnrichment>> {
+        self.storage().get_edge_enrichment(edge_id)
+    }
+
+    /// Store the analysis of a session
+    pub fn put_session_analysis(
+        &self,
+        analysis: &agentreplay_storage::SessionAnalysis,
+    ) -> Result<()> {
+        self.storage().put_session_analysis(analysis)
+    }
+
+    /// Get the stored analysis of a session
+    pub fn get_session_analysis(
+        &self,
+        session_id: u64,
+    ) -> Result<Option<agentreplay_storage::SessionAnalysis>> {
+        self.storage().get_session_analysis(session_id)
+    }
+
+    /// Store the running summary of a session
+    pub fn put_session_summary(&self, summary: &agentreplay_storage::SessionRollup) -> Result<()> {
+        self.storage().put_session_summary(summary)
+    }
+
+    /// Get the running summary of a tenant's session
+    pub fn get_session_summary(
+        &self,
+        tenant_id: u64,
+        session_id: u64,
+    ) -> Result<Option<agentreplay_storage::SessionRollup>> {
+        self.storage().get_session_summary(tenant_id, session_id)
+    }
+
+    /// All session summaries of a tenant
+    pub fn list_session_summaries(
+        &self,
+        tenant_id: u64,
+    ) -> Result<Vec<agentreplay_storage::SessionRollup>> {
+        self.storage().list_session_summaries(tenant_id)
+    }
+
+    /// Whether all pre-existing sessions have summaries
+    pub fn session_summaries_ready(&self) -> Result<bool> {
+        self.storage().session_summaries_ready()
+    }
+
+    /// Record that the session summary backfill has completed
+    pub fn mark_session_summaries_ready(&self) -> Result<()> {
+        self.storage().mark_session_summaries_ready()
+    }
+
+    /// Link a session or trace to a conversation
+    pub fn put_conversation_link(
+        &self,
+        link: &agentreplay_storage::ConversationLink,
+    ) -> Result<()> {
+        self.storage().put_conversation_link(link)
+    }
+
+    /// Store feedback on a trace
+    pub fn put_feedback(&self, feedback: &agentreplay_storage::FeedbackRecord) -> Result<()> {
+        self.storage().put_feedback(feedback)
+    }
+
+    /// Feedback of a tenant, on one trace or on all
+    pub fn list_feedback(
+        &self,
+        tenant_id: u64,
+        edge_id: Option<u128>,
+    ) -> Result<Vec<agentreplay_storage::FeedbackRecord>> {
+        self.storage().list_feedback(tenant_id, edge_id)
+    }
+
+    /// Store (or replace) a vault key
+    pub fn put_provider_key(&self, key: &agentreplay_storage::ProviderKeyRecord) -> Result<()> {
+        self.storage().put_provider_key(key)
+    }
+
+    /// Get a tenant's vault key by alias
+    pub fn get_provider_key(
+        &self,
+        tenant_id: u64,
+        alias: &str,
+    ) -> Result<Option<agentreplay_storage::ProviderKeyRecord>> {
+        self.storage().get_provider_key(tenant_id, alias)
+    }
+
+    /// All vault keys of a tenant
+    pub fn list_provider_keys(
+        &self,
+        tenant_id: u64,
+    ) -> Result<Vec<agentreplay_storage::ProviderKeyRecord>> {
+        self.storage().list_provider_keys(tenant_id)
+    }
+
+    /// Remove a vault key; returns whether it existed
+    pub fn delete_provider_key(&self, tenant_id: u64, alias: &str) -> Result<bool> {
+        self.storage().delete_provider_key(tenant_id, alias)
+    }
+
+    /// Record a provider call made with a vault key
+    pub fn put_key_usage(&self, usage: &agentreplay_storage::KeyUsageRecord) -> Result<()> {
+        self.storage().put_key_usage(usage)
+    }
+
+    /// Vault key usage of a tenant within `[start_ts, end_ts)`
+    pub fn list_key_usage(
+        &self,
+        tenant_id: u64,
+        start_ts: u64,
+        end_ts: u64,
+    ) -> Result<Vec<agentreplay_storage::KeyUsageRecord>> {
+        self.storage().list_key_usage(tenant_id, start_ts, end_ts)
+    }
+
+    /// Store (or replace) an annotation queue
+    pub fn put_annotation_queue(
+        &self,
+        queue: &agentreplay_storage::AnnotationQueueRecord,
+    ) -> Result<()> {
+        self.storage().put_annotation_queue(queue)
+    }
+
+    /// Get a tenant's annotation queue
+    pub fn get_annotation_queue(
+        &self,
+        tenant_id: u64,
+        queue_id: &str,
+    ) -> Result<Option<agentreplay_storage::AnnotationQueueRecord>> {
+        self.storage().get_annotation_queue(tenant_id, queue_id)
+    }
+
+    /// All annotation queues of a tenant
+    pub fn list_annotation_queues(
+        &self,
+        tenant_id: u64,
+    ) -> Result<Vec<agentreplay_storage::AnnotationQueueRecord>> {
+        self.storage().list_annotation_queues(tenant_id)
+    }
+
+    /// Store (or replace) an item of an annotation queue
+    pub fn put_annotation_item(
+        &self,
+        item: &agentreplay_storage::AnnotationQueueItem,
+    ) -> Result<()> {
+        self.storage().put_annotation_item(item)
+    }
+
+    /// Items of an annotation queue
+    pub fn list_annotation_items(
+        &self,
+        queue_id: &str,
+    ) -> Result<Vec<agentreplay_storage::AnnotationQueueItem>> {
+        self.storage().list_annotation_items(queue_id)
+    }
+
+    /// Store (or replace) a reviewer's labels for a queue item
+    pub fn put_annotation_label(&self, label: &agentreplay_storage::AnnotationLabel) -> Result<()> {
+        self.storage().put_annotation_label(label)
+    }
+
+    /// All labels recorded in an annotation queue
+    pub fn list_annotation_labels(
+        &self,
+        queue_id: &str,
+    ) -> Result<Vec<agentreplay_storage::AnnotationLabel>> {
+        self.storage().list_annotation_labels(queue_id)
+    }
+
+    /// Store (or replace) an evaluator's highlights for a span
+    pub fn put_span_highlights(
+        &self,
+        record: &agentreplay_storage::SpanHighlightRecord,
+    ) -> Result<()> {
+        self.storage().put_span_highlights(record)
+    }
+
+    /// Highlights attached to a span, one record per evaluator
+    pub fn list_span_highlights(
+        &self,
+        span_id: u128,
+    ) -> Result<Vec<agentreplay_storage::SpanHighlightRecord>> {
+        self.storage().list_span_highlights(span_id)
+    }
+
+    /// Sessions and traces linked to a conversation
+    pub fn list_conversation_links(
+        &self,
+        tenant_id: u64,
+        conversation_id: &str,
+    ) -> Result<Vec<agentreplay_storage::ConversationLink>> {
+        self.storage()
+            .list_conversation_links(tenant_id, conversation_id)
+    }
+
+    /// Store the captured token logprobs of an edge, returning its confidence summary
+    pub fn put_edge_logprobs(
+        &self,
+        edge_id: u128,
+        tokens: &[agentreplay_storage::TokenLogprob],
+    ) -> Result<agentreplay_storage::LogprobSummary> {
+        self.storage().put_edge_logprobs(edge_id, tokens)
+    }
+
+    /// Store the span links of an edge
+    pub fn put_edge_links(
+        &self,
+        edge_id: u128,
+        links: &[agentreplay_core::SpanLink],
+    ) -> Result<()> {
+        self.storage().put_edge_links(edge_id, links)
+    }
+
+    /// Get the span links of an edge
+    pub fn get_edge_links(&self, edge_id: u128) -> Result<Vec<agentreplay_core::SpanLink>> {
+        self.storage().get_edge_links(edge_id)
+    }
+
+    /// IDs of the edges that link to an edge
+    pub fn get_linking_edges(&self, edge_id: u128) -> Result<Vec<u128>> {
+        self.storage().get_linking_edges(edge_id)
+    }
+
+    /// Get the captured token logprobs of an edge
+    pub fn get_edge_logprobs(
+        &self,
+        edge_id: u128,
+    ) -> Result<Option<agentreplay_storage::SpanLogprobs>> {
+        self.storage().get_edge_logprobs(edge_id)
+    }
+
+    /// Get the confidence summary of an edge's logprobs
+    pub fn get_logprob_summary(
+        &self,
+        edge_id: u128,
+    ) -> Result<Option<agentreplay_storage::LogprobSummary>> {
+        self.storage().get_logprob_summary(edge_id)
     }
 
     /// Get session edges using the session index (Task 5)
     ///
     /// **Performance:** O(log N + K_session) instead of full scan.
     pub fn get_session_edges_full(&self, session_id: u64) -> Result<Vec<AgentFlowEdge>> {
-        self.storage.get_session_edges(session_id)
+        self.storage().get_session_edges(session_id)
     }
 
     /// Get the pre-computed dashboard summary (Task 9)
     pub fn get_dashboard_summary(&self) -> agentreplay_storage::DashboardSummary {
-        self.storage.get_dashboard_summary()
+        self.storage().get_dashboard_summary()
+    }
+
+    /// Summarize `[start_ts, end_ts)` from the materialized rollups, merging
+    /// raw edges for the partial minutes at the window edges
+    pub fn summarize_rollups(
+        &self,
+        start_ts: u64,
+        end_ts: u64,
+        filter: &agentreplay_storage::RollupFilter,
+    ) -> Result<agentreplay_storage::RollupSummary> {
+        self.storage().summarize_rollups(start_ts, end_ts, filter)
     }
 
     /// Maximum number of edges to return without pagination
     const MAX_UNPAGINATED_RESULTS: usize = 10_000;
 
+    /// Refuse scans without a tenant from now on, or allow them again
+    ///
+    /// See [`IsolationMode`]. Cross-tenant reads remain possible through
+    /// [`Self::scoped`] with [`TenantScope::AllTenants`].
+    pub fn set_isolation_mode(&self, mode: IsolationMode) {
+        *self.isolation.write().unwrap() = mode;
+    }
+
+    pub fn isolation_mode(&self) -> IsolationMode {
+        *self.isolation.read().unwrap()
+    }
+
+    /// Queries limited to the tenants of `scope`
+    ///
+    /// **Tenant Safety:** Prefer this over the tenant-optional methods; the
+    /// scope makes reading across tenants explicit at the call site.
+    pub fn scoped(&self, scope: TenantScope) -> TenantQuery<'_> {
+        TenantQuery::new(self, scope)
+    }
+
     /// Query edges in a temporal range
     ///
     /// **Warning:** This method returns all matching edges. For large ranges,
     /// use `query_temporal_range_paginated` to avoid OOM.
+    ///
+    /// **Tenant Safety:** Reads every tenant, so it fails in strict isolation
+    /// mode. Use [`Self::scoped`] instead.
     pub fn query_temporal_range(&self, start_ts: u64, end_ts: u64) -> Result<Vec<AgentFlowEdge>> {
-        self.storage.range_scan(start_ts, end_ts)
+        self.scan_all_tenants("query_temporal_range", start_ts, end_ts)
+    }
+
+    /// Range scan across tenants, unless strict isolation forbids it
+    pub(crate) fn scan_all_tenants(
+        &self,
+        operation: &str,
+        start_ts: u64,
+        end_ts: u64,
+    ) -> Result<Vec<AgentFlowEdge>> {
+        self.isolation_mode().require_tenant(operation, None)?;
+        self.storage().range_scan(start_ts, end_ts)
     }
 
     /// Query edges in a temporal range with pagination
@@ -662,8 +1051,7 @@ impl Agentreplay {
 
         // Materialize only the time-bounded results (Task 2 optimization)
         let mut edges: Vec<AgentFlowEdge> = self
-            .storage
-            .range_scan(start_ts, end_ts)?
+            .scan_all_tenants("query_temporal_range_paginated", start_ts, end_ts)?
             .into_iter()
             .skip(offset)
             .take(limit + 1) // Take one extra to check if more exist
@@ -686,7 +1074,7 @@ impl Agentreplay {
         end_ts: u64,
         tenant_id: u64,
     ) -> Result<Vec<AgentFlowEdge>> {
-        self.storage
+        self.storage()
             .range_scan_filtered(start_ts, end_ts, Some(tenant_id), None)
     }
 
@@ -703,14 +1091,15 @@ impl Agentreplay {
         cursor: Option<&str>,
         descending: bool,
     ) -> Result<(Vec<AgentFlowEdge>, Option<String>)> {
-        self.storage
+        self.storage()
             .query_temporal_range_for_tenant_paginated(start_ts, end_ts, tenant_id, limit, cursor, descending)
     }
 
     /// Query edges with optional tenant and project filtering
     ///
     /// **Multi-tenancy:** Supports fine-grained filtering by tenant and project.
-    /// Pass None for tenant_id to query all tenants (admin mode).
+    /// Pass None for tenant_id to query all tenants (admin mode); strict
+    /// isolation mode refuses that.
     /// Pass None for project_id to query all projects within a tenant.
     pub fn query_filtered(
         &self,
@@ -719,7 +1108,9 @@ impl Agentreplay {
         tenant_id: Option<u64>,
         project_id: Option<u16>,
     ) -> Result<Vec<AgentFlowEdge>> {
-        self.storage
+        self.isolation_mode()
+            .require_tenant("query_filtered", tenant_id)?;
+        self.storage()
             .range_scan_filtered(start_ts, end_ts, tenant_id, project_id)
     }
 
@@ -735,7 +1126,7 @@ impl Agentreplay {
     /// let results = db.query_without_pii(start, end)?;
     /// ```
     pub fn query_without_pii(&self, start_ts: u64, end_ts: u64) -> Result<Vec<AgentFlowEdge>> {
-        let mut results = self.storage.range_scan(start_ts, end_ts)?;
+        let mut results = self.scan_all_tenants("query_without_pii", start_ts, end_ts)?;
         results.retain(|e| !e.has_pii());
         Ok(results)
     }
@@ -745,7 +1136,7 @@ impl Agentreplay {
     /// **Security:** Filters out edges with SECRET sensitivity flags.
     /// Use this when returning data that should not expose credentials or secrets.
     pub fn query_without_secrets(&self, start_ts: u64, end_ts: u64) -> Result<Vec<AgentFlowEdge>> {
-        let mut results = self.storage.range_scan(start_ts, end_ts)?;
+        let mut results = self.scan_all_tenants("query_without_secrets", start_ts, end_ts)?;
         results.retain(|e| !e.has_secrets());
         Ok(results)
     }
@@ -755,7 +1146,7 @@ impl Agentreplay {
     /// **Comprehensive Privacy:** Filters out all sensitive data (PII + secrets).
     /// Use this for public-facing APIs or analytics that should only see non-sensitive data.
     pub fn query_public_only(&self, start_ts: u64, end_ts: u64) -> Result<Vec<AgentFlowEdge>> {
-        let mut results = self.storage.range_scan(start_ts, end_ts)?;
+        let mut results = self.scan_all_tenants("query_public_only", start_ts, end_ts)?;
         results.retain(|e| !e.has_pii() && !e.has_secrets());
         Ok(results)
     }
@@ -780,7 +1171,7 @@ impl Agentreplay {
         start_ts: u64,
         end_ts: u64,
     ) -> Result<impl Iterator<Item = AgentFlowEdge>> {
-        let edges = self.storage.range_scan(start_ts, end_ts)?;
+        let edges = self.scan_all_tenants("query_temporal_range_iter", start_ts, end_ts)?;
         Ok(edges.into_iter())
     }
 
@@ -794,17 +1185,17 @@ impl Agentreplay {
         end_ts: u64,
         tenant_id: u64,
     ) -> Result<impl Iterator<Item = AgentFlowEdge>> {
-        let edges = self.storage.range_scan_filtered(start_ts, end_ts, Some(tenant_id), None)?;
+        let edges = self.storage().range_scan_filtered(start_ts, end_ts, Some(tenant_id), None)?;
         Ok(edges.into_iter())
     }
 
     /// Get all children of an edge in the causal graph
     pub fn get_children(&self, edge_id: u128) -> Result<Vec<AgentFlowEdge>> {
-        let child_ids = self.causal_index.get_children(edge_id);
+        let child_ids = self.causal_index().get_children(edge_id);
         let mut children = Vec::new();
 
         for child_id in child_ids {
-            if let Some(edge) = self.storage.get(child_id)? {
+            if let Some(edge) = self.storage().get(child_id)? {
                 children.push(edge);
             }
         }
@@ -842,14 +1233,14 @@ impl Agentreplay {
         let mut all_child_ids = HashSet::new();
 
         for &parent_id in parent_ids {
-            let child_ids = self.causal_index.get_children(parent_id);
+            let child_ids = self.causal_index().get_children(parent_id);
             all_child_ids.extend(child_ids.iter().copied());
             parent_to_children.insert(parent_id, child_ids);
         }
 
         // Batch fetch all child edges at once and create a map
         let child_ids_vec: Vec<u128> = all_child_ids.into_iter().collect();
-        let edges_vec = self.storage.get_many(&child_ids_vec)?;
+        let edges_vec = self.storage().get_many(&child_ids_vec)?;
         let edges_map: HashMap<u128, AgentFlowEdge> = child_ids_vec.into_iter()
             .zip(edges_vec.into_iter())
             .filter_map(|(id, opt)| opt.map(|e| (id, e)))
@@ -890,11 +1281,11 @@ impl Agentreplay {
 
     /// Get all parents of an edge in the causal graph
     pub fn get_parents(&self, edge_id: u128) -> Result<Vec<AgentFlowEdge>> {
-        let parent_ids = self.causal_index.get_parents(edge_id);
+        let parent_ids = self.causal_index().get_parents(edge_id);
         let mut parents = Vec::new();
 
         for parent_id in parent_ids {
-            if let Some(edge) = self.storage.get(parent_id)? {
+            if let Some(edge) = self.storage().get(parent_id)? {
                 parents.push(edge);
             }
         }
@@ -917,11 +1308,11 @@ impl Agentreplay {
 
     /// Get all descendants of an edge (full subtree)
     pub fn get_descendants(&self, edge_id: u128) -> Result<Vec<AgentFlowEdge>> {
-        let descendant_ids = self.causal_index.get_descendants(edge_id);
+        let descendant_ids = self.causal_index().get_descendants(edge_id);
         let mut descendants = Vec::new();
 
         for desc_id in descendant_ids {
-            if let Some(edge) = self.storage.get(desc_id)? {
+            if let Some(edge) = self.storage().get(desc_id)? {
                 descendants.push(edge);
             }
         }
@@ -961,7 +1352,7 @@ impl Agentreplay {
     ) -> Result<Vec<(AgentFlowEdge, usize)>> {
         // 1. Get all descendant IDs with depths from causal index (O(N) traversal)
         let descendants_with_depth = self
-            .causal_index
+            .causal_index()
             .get_descendants_with_depth(edge_id, max_depth, max_nodes);
 
         if descendants_with_depth.is_empty() {
@@ -972,7 +1363,7 @@ impl Agentreplay {
         let edge_ids: Vec<u128> = descendants_with_depth.iter().map(|(id, _)| *id).collect();
 
         // 3. Batch fetch all edges from storage and create a map
-        let edges_vec = self.storage.get_many(&edge_ids)?;
+        let edges_vec = self.storage().get_many(&edge_ids)?;
         let edges_map: std::collections::HashMap<u128, AgentFlowEdge> = edge_ids.iter()
             .zip(edges_vec.into_iter())
             .filter_map(|(id, opt)| opt.map(|e| (*id, e)))
@@ -993,11 +1384,11 @@ impl Agentreplay {
 
     /// Get all ancestors of an edge (full path to root)
     pub fn get_ancestors(&self, edge_id: u128) -> Result<Vec<AgentFlowEdge>> {
-        let ancestor_ids = self.causal_index.get_ancestors(edge_id);
+        let ancestor_ids = self.causal_index().get_ancestors(edge_id);
         let mut ancestors = Vec::new();
 
         for anc_id in ancestor_ids {
-            if let Some(edge) = self.storage.get(anc_id)? {
+            if let Some(edge) = self.storage().get(anc_id)? {
                 ancestors.push(edge);
             }
         }
@@ -1021,13 +1412,13 @@ impl Agentreplay {
     /// Get path between two edges
     pub fn get_path(&self, from: u128, to: u128) -> Result<Vec<AgentFlowEdge>> {
         let path_ids = self
-            .causal_index
+            .causal_index()
             .get_path(from, to)
             .ok_or_else(|| AgentreplayError::NotFound(format!("No path from {} to {}", from, to)))?;
 
         let mut path = Vec::new();
         for edge_id in path_ids {
-            if let Some(edge) = self.storage.get(edge_id)? {
+            if let Some(edge) = self.storage().get(edge_id)? {
                 path.push(edge);
             }
         }
@@ -1038,13 +1429,13 @@ impl Agentreplay {
     /// Semantic search using vector similarity
     pub fn semantic_search(&self, query: &Embedding, k: usize) -> Result<Vec<AgentFlowEdge>> {
         let results = self
-            .vector_index
+            .vector_index()
             .search(query, k)
             .map_err(AgentreplayError::InvalidArgument)?;
         let mut edges = Vec::new();
 
         for (edge_id, _score) in results {
-            if let Some(edge) = self.storage.get(edge_id)? {
+            if let Some(edge) = self.storage().get(edge_id)? {
                 edges.push(edge);
             }
         }
@@ -1068,7 +1459,7 @@ impl Agentreplay {
         // Request more results to account for tenant filtering
         let expanded_k = k * 3;
         let results = self
-            .vector_index
+            .vector_index()
             .search(query, expanded_k)
             .map_err(AgentreplayError::InvalidArgument)?;
         
@@ -1078,7 +1469,7 @@ impl Agentreplay {
             if tenant_edges.len() >= k {
                 break;
             }
-            if let Some(edge) = self.storage.get(edge_id)? {
+            if let Some(edge) = self.storage().get(edge_id)? {
                 // **TENANT ISOLATION**: Only include edges from the caller's tenant
                 if edge.tenant_id == tenant_id {
                     tenant_edges.push(edge);
@@ -1089,12 +1480,73 @@ impl Agentreplay {
         Ok(tenant_edges)
     }
 
+    /// Tenant-safe semantic search within a latency budget
+    ///
+    /// The budget counts from `started`, so time the caller already spent
+    /// (e.g. embedding the query) is included. ef_search is chosen per query
+    /// from recently observed search latency and the time left, trading
+    /// recall for latency under load. The deadline is a backstop: past it the
+    /// vector search stops expanding candidates and no more edges are
+    /// fetched. What was found is returned with the parameters actually used.
+    pub fn semantic_search_with_budget(
+        &self,
+        query: &Embedding,
+        k: usize,
+        tenant_id: u64,
+        started: Instant,
+        budget: Duration,
+    ) -> Result<(Vec<AgentFlowEdge>, EffectiveSearchParams)> {
+        let deadline = started + budget;
+        // Request more results to account for tenant filtering
+        let expanded_k = k * 3;
+        let index = self.vector_index();
+        let ef = self.ef_tuner.choose_ef(
+            deadline.saturating_duration_since(Instant::now()),
+            expanded_k,
+            index.ef_search(),
+        );
+
+        let search_started = Instant::now();
+        let (results, mut truncated) = index
+            .search_until(query, expanded_k, ef, deadline)
+            .map_err(AgentreplayError::InvalidArgument)?;
+        self.ef_tuner
+            .record(ef, search_started.elapsed(), truncated);
+
+        let mut tenant_edges = Vec::with_capacity(k);
+        for (edge_id, _score) in results {
+            if tenant_edges.len() >= k {
+                break;
+            }
+            if Instant::now() >= deadline {
+                truncated = true;
+                break;
+            }
+            if let Some(edge) = self.storage().get(edge_id)? {
+                // **TENANT ISOLATION**: Only include edges from the caller's tenant
+                if edge.tenant_id == tenant_id {
+                    tenant_edges.push(edge);
+                }
+            }
+        }
+
+        let elapsed = started.elapsed();
+        let params = EffectiveSearchParams {
+            ef_search: ef,
+            budget_ms: budget.as_millis() as u64,
+            elapsed_ms: elapsed.as_secs_f64() * 1_000.0,
+            within_budget: elapsed <= budget,
+            truncated,
+        };
+        Ok((tenant_edges, params))
+    }
+
     /// Raw vector search returning IDs and scores
     ///
     /// Use this when you only need IDs (e.g. for retrieving payloads)
     /// and don't need the full AgentFlowEdge records.
     pub fn search_vectors(&self, query: &Embedding, k: usize) -> Result<Vec<(u128, f32)>> {
-        self.vector_index
+        self.vector_index()
             .search(query, k)
             .map_err(AgentreplayError::InvalidArgument)
     }
@@ -1105,7 +1557,7 @@ impl Agentreplay {
     /// arbitrary content embeddings without creating full AgentFlowEdge records.
     pub fn store_embedding(&self, id: u128, embedding: &[f32]) -> Result<()> {
         let embedding_array = Embedding::from_vec(embedding.to_vec());
-        self.vector_index
+        self.vector_index()
             .add(id, embedding_array)
             .map_err(AgentreplayError::InvalidArgument)
     }
@@ -1132,7 +1584,7 @@ impl Agentreplay {
         start_ts: u64,
         end_ts: u64,
     ) -> Result<Vec<AgentFlowEdge>> {
-        let all_edges = self.storage.range_scan(start_ts, end_ts)?;
+        let all_edges = self.scan_all_tenants("filter_by_span_type", start_ts, end_ts)?;
 
         Ok(all_edges
             .into_iter()
@@ -1147,7 +1599,7 @@ impl Agentreplay {
         start_ts: u64,
         end_ts: u64,
     ) -> Result<Vec<AgentFlowEdge>> {
-        let all_edges = self.storage.range_scan(start_ts, end_ts)?;
+        let all_edges = self.scan_all_tenants("filter_by_agent", start_ts, end_ts)?;
 
         Ok(all_edges
             .into_iter()
@@ -1162,7 +1614,7 @@ impl Agentreplay {
         start_ts: u64,
         end_ts: u64,
     ) -> Result<Vec<AgentFlowEdge>> {
-        let all_edges = self.storage.range_scan(start_ts, end_ts)?;
+        let all_edges = self.scan_all_tenants("filter_by_session", start_ts, end_ts)?;
 
         Ok(all_edges
             .into_iter()
@@ -1172,14 +1624,14 @@ impl Agentreplay {
 
     /// Get database statistics
     pub fn stats(&self) -> DatabaseStats {
-        let storage_stats = self.storage.stats();
-        let causal_stats = self.causal_index.stats();
+        let storage_stats = self.storage().stats();
+        let causal_stats = self.causal_index().stats();
 
         DatabaseStats {
             storage: storage_stats,
             causal_nodes: causal_stats.num_nodes,
             causal_edges: causal_stats.num_edges,
-            vector_count: self.vector_index.len(),
+            vector_count: self.vector_index().len(),
         }
     }
 
@@ -1188,22 +1640,22 @@ impl Agentreplay {
     /// Returns stats including active snapshots, peak usage, and cleanup efficiency.
     /// Useful for detecting long-running queries holding old versions.
     pub fn mvcc_stats(&self) -> agentreplay_storage::VersionSetStatsSnapshot {
-        self.storage.mvcc_stats()
+        self.storage().mvcc_stats()
     }
 
     /// Get LSM storage statistics for health monitoring
     pub fn storage_stats(&self) -> agentreplay_storage::LSMStats {
-        self.storage.stats()
+        self.storage().stats()
     }
 
     /// Get a reference to the causal index (for MCP server)
     pub fn causal_index(&self) -> Arc<CausalIndex> {
-        self.causal_index.clone()
+        self.causal_index.read().unwrap().clone()
     }
 
     /// Get a reference to the vector index (for MCP server)
     pub fn vector_index(&self) -> Arc<VectorIndex> {
-        self.vector_index.clone()
+        self.vector_index.read().unwrap().clone()
     }
 
     /// List all vector IDs in the index (for memory listing)
@@ -1211,7 +1663,7 @@ impl Agentreplay {
     /// Returns edge IDs in insertion order. Used by the memory list endpoint
     /// to enumerate all stored memories without requiring a search query.
     pub fn list_all_vector_ids(&self) -> Vec<u128> {
-        self.vector_index.all_edge_ids()
+        self.vector_index().all_edge_ids()
     }
 
     // =================================================================
@@ -1535,7 +1987,95 @@ impl Agentreplay {
     /// db.sync()?;  // Ensure it's durable before proceeding
     /// ```
     pub fn sync(&self) -> Result<()> {
-        self.storage.sync()
+        self.storage().sync()
+    }
+
+    pub(crate) fn storage(&self) -> Arc<UnifiedStorage> {
+        self.storage.read().unwrap().clone()
+    }
+
+    /// Serve the data of `other` from now on, e.g. a newer snapshot on a
+    /// replica
+    ///
+    /// Storage, indexes and the eval and prompt data loaded from disk are
+    /// swapped one by one. Calls already running finish against the old
+    /// storage, which is closed once the last of them drops it.
+    pub fn replace_data(&self, other: Agentreplay) {
+        *self.storage.write().unwrap() = other.storage();
+        *self.causal_index.write().unwrap() = other.causal_index();
+        *self.vector_index.write().unwrap() = other.vector_index();
+        *self.eval_datasets.write().unwrap() =
+            std::mem::take(&mut *other.eval_datasets.write().unwrap());
+        *self.eval_runs.write().unwrap() = std::mem::take(&mut *other.eval_runs.write().unwrap());
+        *self.prompt_templates.write().unwrap() =
+            std::mem::take(&mut *other.prompt_templates.write().unwrap());
+    }
+
+    /// Ship a logical copy of subsequent writes to a warm standby
+    ///
+    /// Pass `None` to stop shipping. Writes made through [`Self::apply_wal`]
+    /// are never shipped, so a standby can be promoted by installing a
+    /// shipper after it has drained the log.
+    pub fn set_wal_shipper(&self, shipper: Option<Arc<WalShipper>>) {
+        *self.wal_shipper.write().unwrap() = shipper;
+    }
+
+    fn ship(&self, entries: impl FnOnce() -> Vec<WalEntry>) {
+        if let Some(shipper) = self.wal_shipper.read().unwrap().as_ref() {
+            shipper.append(entries());
+        }
+    }
+
+    /// Replay shipped WAL records on a standby, in LSN order
+    ///
+    /// Consecutive edges are written as one batch. Replaying a record twice is
+    /// harmless, so callers may persist their applied LSN after the fact.
+    pub async fn apply_wal(&self, records: &[WalRecord]) -> Result<()> {
+        let mut edges: Vec<AgentFlowEdge> = Vec::new();
+        for record in records {
+            if let WalEntry::Edge(edge) = &record.entry {
+                edges.push(*edge);
+                continue;
+            }
+            if !edges.is_empty() {
+                self.storage().put_batch(&edges)?;
+                for edge in edges.drain(..) {
+                    self.causal_index().index(&edge);
+                }
+            }
+            match &record.entry {
+                WalEntry::Payload { edge_id, data } => self.storage().put_payload(*edge_id, data)?,
+                WalEntry::Vector { edge_id, values } => {
+                    let vector = Embedding::from(values.clone());
+                    if let Err(e) = self.vector_index().add(*edge_id, vector) {
+                        warn!(
+                            "WAL replay: vector for edge {:#x} not indexed: {}",
+                            edge_id, e
+                        );
+                    }
+                }
+                WalEntry::Delete { edge_id, tenant_id } => {
+                    self.storage().delete(*edge_id, *tenant_id)?
+                }
+                WalEntry::DeleteProject { project_id } => {
+                    self.storage().delete_by_project(*project_id)?;
+                }
+                WalEntry::Cost {
+                    edge_id,
+                    cost_micros,
+                } => {
+                    self.storage().put_edge_cost(*edge_id, *cost_micros)?;
+                }
+                WalEntry::Edge(_) => {}
+            }
+        }
+        if !edges.is_empty() {
+            self.storage().put_batch(&edges)?;
+            for edge in &edges {
+                self.causal_index().index(edge);
+            }
+        }
+        Ok(())
     }
 
     /// Checkpoint: flush memtable to persistent storage and truncate WAL.
@@ -1544,12 +2084,39 @@ impl Agentreplay {
     /// startup (WAL replay rebuilds the entire memtable in memory).
     /// Should be called periodically (e.g. every 5 minutes or when WAL exceeds threshold).
     pub fn checkpoint(&self) -> Result<()> {
-        self.storage.checkpoint()
+        self.storage().checkpoint()
     }
 
     /// Get the current WAL file size in bytes.
     pub fn wal_size_bytes(&self) -> u64 {
-        self.storage.wal_size_bytes()
+        self.storage().wal_size_bytes()
+    }
+
+    /// Compaction progress, space amplification and tombstone counts
+    pub fn compaction_stats(&self) -> Result<agentreplay_storage::CompactionStats> {
+        self.storage().compaction_stats()
+    }
+
+    /// Run a manual compaction (orphan payload cleanup + checkpoint)
+    pub fn compact(&self) -> Result<agentreplay_storage::CompactionRun> {
+        self.storage().compact()
+    }
+
+    /// Take an online backup into `backup_dir` without pausing ingestion
+    ///
+    /// See [`agentreplay_storage::backup`] for the consistency guarantees.
+    pub fn create_backup(
+        &self,
+        backup_dir: &Path,
+        name: Option<&str>,
+        options: &BackupOptions,
+    ) -> Result<BackupMetadata> {
+        BackupManager::new(backup_dir).create_backup(&self.storage(), name, options)
+    }
+
+    /// WAL vs logical bytes, fsync latency and group commit batch sizes
+    pub fn write_telemetry(&self) -> agentreplay_storage::WriteTelemetryReport {
+        self.storage().write_telemetry()
     }
 
 
@@ -1559,7 +2126,7 @@ impl Agentreplay {
     /// Persists in-memory minute/hour buckets to disk for analytics queries.
     /// Should be called periodically (e.g. every minute).
     pub fn flush_metrics(&self) -> Result<()> {
-        self.storage.flush_metrics()
+        self.storage().flush_metrics()
     }
 
     /// Sync only the vector index to disk
@@ -1571,8 +2138,8 @@ impl Agentreplay {
     /// Performance: ~10-100ms depending on index size. Call periodically (e.g., every 5 minutes)
     /// or after significant memory ingestion batches.
     pub fn sync_vector_index(&self) -> Result<()> {
-        let vector_path = self.storage.data_dir().join("vector.index");
-        self.vector_index.save_to_disk(&vector_path).map_err(|e| {
+        let vector_path = self.storage().data_dir().join("vector.index");
+        self.vector_index().save_to_disk(&vector_path).map_err(|e| {
             AgentreplayError::Index(format!("Failed to save vector index: {}", e))
         })
     }
@@ -1604,7 +2171,7 @@ impl Agentreplay {
     /// ```
     pub fn close(&self) -> Result<()> {
         info!(
-            db_path = %self.storage.data_dir().display(),
+            db_path = %self.storage().data_dir().display(),
             "Closing Agentreplay database..."
         );
 
@@ -1615,7 +2182,7 @@ impl Agentreplay {
         let mut vector_ok = false;
 
         // Step 1: Sync storage (WAL durability) - CRITICAL for data integrity
-        match self.storage.sync() {
+        match self.storage().sync() {
             Ok(()) => {
                 sync_ok = true;
                 info!("Storage synced successfully");
@@ -1629,7 +2196,7 @@ impl Agentreplay {
 
         // Step 2: Save causal index to disk for fast restart
         // Attempt even if sync failed - may still succeed
-        match self.causal_index.save_to_disk() {
+        match self.causal_index().save_to_disk() {
             Ok(()) => {
                 causal_ok = true;
                 info!("Causal index saved successfully");
@@ -1643,8 +2210,8 @@ impl Agentreplay {
 
         // Step 3: Save vector index to disk for fast restart
         // Attempt even if previous steps failed
-        let vector_path = self.storage.data_dir().join("vector.index");
-        match self.vector_index.save_to_disk(&vector_path) {
+        let vector_path = self.storage().data_dir().join("vector.index");
+        match self.vector_index().save_to_disk(&vector_path) {
             Ok(()) => {
                 vector_ok = true;
                 info!(path = %vector_path.display(), "Vector index saved successfully");
@@ -1686,7 +2253,7 @@ impl Agentreplay {
 
     /// Persist eval datasets to JSON file
     fn persist_eval_datasets(&self, datasets: &HashMap<u128, EvalDataset>) -> Result<()> {
-        let path = self.storage.data_dir().join("eval_datasets.json");
+        let path = self.storage().data_dir().join("eval_datasets.json");
         // Convert to Vec for JSON serialization (HashMap<u128, _> keys don't serialize well)
         let datasets_vec: Vec<&EvalDataset> = datasets.values().collect();
         let json = serde_json::to_string_pretty(&datasets_vec).map_err(|e| {
@@ -1698,7 +2265,7 @@ impl Agentreplay {
 
     /// Persist eval runs to JSON file
     fn persist_eval_runs(&self, runs: &HashMap<u128, EvalRun>) -> Result<()> {
-        let path = self.storage.data_dir().join("eval_runs.json");
+        let path = self.storage().data_dir().join("eval_runs.json");
         // Convert to Vec for JSON serialization (HashMap<u128, _> keys don't serialize well)
         let runs_vec: Vec<&EvalRun> = runs.values().collect();
         let json = serde_json::to_string_pretty(&runs_vec)
@@ -1767,7 +2334,7 @@ impl Agentreplay {
 
     /// Persist prompt templates to JSON file
     pub fn persist_prompt_templates(&self, templates: &HashMap<u128, PromptTemplate>) -> Result<()> {
-        let path = self.storage.data_dir().join("prompt_templates.json");
+        let path = self.storage().data_dir().join("prompt_templates.json");
         let templates_vec: Vec<&PromptTemplate> = templates.values().collect();
         let json = serde_json::to_string_pretty(&templates_vec).map_err(|e| {
             AgentreplayError::Internal(format!("Failed to serialize prompt templates: {}", e))
@@ -1829,6 +2396,7 @@ pub struct QueryBuilder {
     db: Arc<Agentreplay>,
     start_ts: Option<u64>,
     end_ts: Option<u64>,
+    tenant_id: Option<u64>,
     agent_id: Option<u64>,
     session_id: Option<u64>,
     span_type: Option<SpanType>,
@@ -1858,6 +2426,7 @@ impl QueryBuilder {
             db,
             start_ts: None,
             end_ts: None,
+            tenant_id: None,
             agent_id: None,
             session_id: None,
             span_type: None,
@@ -1877,6 +2446,12 @@ impl QueryBuilder {
         self
     }
 
+    /// Only edges of this tenant; required in strict isolation mode
+    pub fn tenant(mut self, tenant_id: u64) -> Self {
+        self.tenant_id = Some(tenant_id);
+        self
+    }
+
     pub fn agent(mut self, agent_id: u64) -> Self {
         self.agent_id = Some(agent_id);
         self
@@ -1963,7 +2538,7 @@ impl QueryBuilder {
         let start = self.start_ts.unwrap_or(0);
         let end = self.end_ts.unwrap_or(u64::MAX);
 
-        let mut results = self.db.query_temporal_range(start, end)?;
+        let mut results = self.db.query_filtered(start, end, self.tenant_id, None)?;
 
         if let Some(agent_id) = self.agent_id {
             results.retain(|e| e.agent_id == agent_id);
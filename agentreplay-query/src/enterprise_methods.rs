@@ -297,7 +297,7 @@ impl Agentreplay {
 
     /// Get cost statistics for a time range
     pub fn get_cost_stats(&self, start_time: u64, end_time: u64) -> Result<CostStats> {
-        let edges = self.storage.range_scan(start_time, end_time)?;
+        let edges = self.scan_all_tenants("get_cost_stats", start_time, end_time)?;
 
         let mut total_cost = 0.0;
         let mut total_tokens = 0u64;
@@ -388,7 +388,7 @@ impl Agentreplay {
         start_time: u64,
         end_time: u64,
     ) -> Result<Vec<AgentFlowEdge>> {
-        self.storage.range_scan(start_time, end_time)
+        self.scan_all_tenants("list_traces_in_range", start_time, end_time)
     }
 
     /// Get eval metrics for a time period
@@ -428,7 +428,7 @@ impl Agentreplay {
         _model: Option<&str>, // Model filtering requires payload lookup, not implemented yet
     ) -> Result<Vec<DataPoint>> {
         // Get all traces in the time range
-        let edges = self.storage.range_scan(start_time, end_time)?;
+        let edges = self.scan_all_tenants("get_timeseries_data", start_time, end_time)?;
 
         // Filter edges based on criteria
         let filtered_edges: Vec<_> = edges
@@ -522,7 +522,7 @@ impl Agentreplay {
 
     /// Get a single aggregated metric value for a time range
     pub fn get_metric_value(&self, metric: &str, start_time: u64, end_time: u64) -> Result<f64> {
-        let edges = self.storage.range_scan(start_time, end_time)?;
+        let edges = self.scan_all_tenants("get_metric_value", start_time, end_time)?;
 
         if edges.is_empty() {
             return Ok(0.0);
@@ -608,7 +608,7 @@ impl Agentreplay {
         end_time: u64,
         group_by: &str,
     ) -> Result<HashMap<String, (f64, usize)>> {
-        let edges = self.storage.range_scan(start_time, end_time)?;
+        let edges = self.scan_all_tenants("get_grouped_metrics", start_time, end_time)?;
 
         // Group edges by the specified dimension
         let mut groups: HashMap<String, Vec<&AgentFlowEdge>> = HashMap::new();
@@ -660,7 +660,7 @@ impl Agentreplay {
         start_time: u64,
         end_time: u64,
     ) -> Result<Vec<f64>> {
-        let edges = self.storage.range_scan(start_time, end_time)?;
+        let edges = self.scan_all_tenants("get_timeseries_values", start_time, end_time)?;
 
         let values: Vec<f64> = match metric {
             "latency" | "duration" => edges
@@ -19,6 +19,7 @@
 
 pub mod aggregation;
 pub mod annotations;
+pub mod archive;
 pub mod comparison;
 pub mod cost_engine;
 pub mod dataset_manager;
@@ -26,12 +27,17 @@ pub mod engine;
 pub mod enterprise_methods;
 pub mod merge;
 pub mod nl_query_parser;
+pub mod parallel;
 pub mod prompt_manager;
 pub mod retention;
 pub mod semantic;
 pub mod session;
+pub mod tenancy;
 
-pub use aggregation::{AggregationKey, AggregationType, AggregationValue};
+pub use aggregation::{
+    AggregationKey, AggregationRow, AggregationType, AggregationValue, GroupByAggregator,
+    GroupByQuery, GroupDimension, GroupMetric, GroupRow,
+};
 pub use cost_engine::{CostCalculator, ModelPricing};
 pub use engine::{
     DatabaseStats,
@@ -42,11 +48,17 @@ pub use engine::{
     MAX_QUERY_LIMIT,
     MAX_TIME_RANGE_SECS,
 };
-pub use merge::KWayMerge;
+pub use merge::{KWayMerge, MergeKey};
 pub use nl_query_parser::{NLQueryParser, ParsedQuery, QueryIntent};
-pub use retention::{RetentionConfig, RetentionManager, RetentionPolicy, RetentionStats};
+pub use parallel::{
+    build_merge_view, overlap_clusters, BranchSpan, BranchStats, ParallelGroup, TraceMergeView,
+};
+pub use retention::{
+    RetentionConfig, RetentionManager, RetentionPolicy, RetentionStats, SizeRetentionEstimate,
+};
 pub use semantic::{
     QueryFilters, SemanticQuery, SemanticSearchConfig, SemanticSearchError, SemanticSearchResult,
     TimeRange,
 };
 pub use session::{MessageMetadata, MessageType, SessionMessage, SessionTimeline, TimelineEvent};
+pub use tenancy::{IsolationMode, TenantQuery, TenantScope};
@@ -13,50 +13,123 @@
 // You should have received a copy of the GNU Affero General Public License
 // along with this program. If not, see <https://www.gnu.org/licenses/>.
 
-//! Streaming K-way merge for observations.
+//! Streaming K-way merge for time-ordered records.
+//!
+//! Each input iterator must already be sorted by its [`MergeKey`]; the merge
+//! yields the union in ascending key order without buffering the inputs.
+//! Used to combine observation streams and to federate span queries across
+//! per-project databases.
 
 use agentreplay_core::observation::Observation;
+use agentreplay_core::AgentFlowEdge;
 use std::cmp::{Ordering, Reverse};
 use std::collections::BinaryHeap;
 
-pub struct KWayMerge<I: Iterator<Item = Observation>> {
+/// Ordering key used by [`KWayMerge`].
+pub trait MergeKey {
+    type Key: Ord;
+
+    fn merge_key(&self) -> Self::Key;
+}
+
+impl MergeKey for Observation {
+    type Key = u64;
+
+    fn merge_key(&self) -> u64 {
+        self.created_at.packed()
+    }
+}
+
+/// Spans merge by timestamp, with the edge ID breaking ties so the output
+/// is deterministic regardless of input order.
+impl MergeKey for AgentFlowEdge {
+    type Key = (u64, u128);
+
+    fn merge_key(&self) -> (u64, u128) {
+        (self.timestamp_us, self.edge_id)
+    }
+}
+
+pub struct KWayMerge<I>
+where
+    I: Iterator,
+    I::Item: MergeKey,
+{
     heap: BinaryHeap<Reverse<HeapEntry<I>>>,
 }
 
-struct HeapEntry<I: Iterator<Item = Observation>> {
-    current: Observation,
+struct HeapEntry<I>
+where
+    I: Iterator,
+    I::Item: MergeKey,
+{
+    key: <I::Item as MergeKey>::Key,
+    current: I::Item,
     iterator: I,
 }
 
-impl<I: Iterator<Item = Observation>> Ord for HeapEntry<I> {
+impl<I> HeapEntry<I>
+where
+    I: Iterator,
+    I::Item: MergeKey,
+{
+    fn new(current: I::Item, iterator: I) -> Self {
+        Self {
+            key: current.merge_key(),
+            current,
+            iterator,
+        }
+    }
+}
+
+impl<I> Ord for HeapEntry<I>
+where
+    I: Iterator,
+    I::Item: MergeKey,
+{
     fn cmp(&self, other: &Self) -> Ordering {
-        let lhs = self.current.created_at.packed();
-        let rhs = other.current.created_at.packed();
-        lhs.cmp(&rhs)
+        self.key.cmp(&other.key)
     }
 }
 
-impl<I: Iterator<Item = Observation>> PartialOrd for HeapEntry<I> {
+impl<I> PartialOrd for HeapEntry<I>
+where
+    I: Iterator,
+    I::Item: MergeKey,
+{
     fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
         Some(self.cmp(other))
     }
 }
 
-impl<I: Iterator<Item = Observation>> PartialEq for HeapEntry<I> {
+impl<I> PartialEq for HeapEntry<I>
+where
+    I: Iterator,
+    I::Item: MergeKey,
+{
     fn eq(&self, other: &Self) -> bool {
-        self.current.created_at.packed() == other.current.created_at.packed()
+        self.key == other.key
     }
 }
 
-impl<I: Iterator<Item = Observation>> Eq for HeapEntry<I> {}
+impl<I> Eq for HeapEntry<I>
+where
+    I: Iterator,
+    I::Item: MergeKey,
+{
+}
 
-impl<I: Iterator<Item = Observation>> KWayMerge<I> {
+impl<I> KWayMerge<I>
+where
+    I: Iterator,
+    I::Item: MergeKey,
+{
     pub fn new(iterators: Vec<I>) -> Self {
         let mut heap = BinaryHeap::with_capacity(iterators.len());
 
         for mut iter in iterators {
             if let Some(current) = iter.next() {
-                heap.push(Reverse(HeapEntry { current, iterator: iter }));
+                heap.push(Reverse(HeapEntry::new(current, iter)));
             }
         }
 
@@ -64,16 +137,23 @@ impl<I: Iterator<Item = Observation>> KWayMerge<I> {
     }
 }
 
-impl<I: Iterator<Item = Observation>> Iterator for KWayMerge<I> {
-    type Item = Observation;
+impl<I> Iterator for KWayMerge<I>
+where
+    I: Iterator,
+    I::Item: MergeKey,
+{
+    type Item = I::Item;
 
     fn next(&mut self) -> Option<Self::Item> {
-        let Reverse(mut entry) = self.heap.pop()?;
-        let result = entry.current;
-
-        if let Some(next) = entry.iterator.next() {
-            entry.current = next;
-            self.heap.push(Reverse(entry));
+        let Reverse(entry) = self.heap.pop()?;
+        let HeapEntry {
+            current: result,
+            mut iterator,
+            ..
+        } = entry;
+
+        if let Some(next) = iterator.next() {
+            self.heap.push(Reverse(HeapEntry::new(next, iterator)));
         }
 
         Some(result)
@@ -83,3 +163,46 @@ impl<I: Iterator<Item = Observation>> Iterator for KWayMerge<I> {
         (self.heap.len(), None)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use agentreplay_core::SpanType;
+
+    fn edge_at(timestamp_us: u64, project_id: u16) -> AgentFlowEdge {
+        let mut edge = AgentFlowEdge::new(1, project_id, 1, 1, SpanType::Root, 0);
+        edge.timestamp_us = timestamp_us;
+        edge
+    }
+
+    #[test]
+    fn test_merges_edges_in_timestamp_order() {
+        let a = vec![edge_at(10, 1), edge_at(30, 1), edge_at(50, 1)];
+        let b = vec![edge_at(20, 2), edge_at(40, 2)];
+        let c: Vec<AgentFlowEdge> = Vec::new();
+
+        let merged: Vec<AgentFlowEdge> =
+            KWayMerge::new(vec![a.into_iter(), b.into_iter(), c.into_iter()]).collect();
+
+        let timestamps: Vec<u64> = merged.iter().map(|e| e.timestamp_us).collect();
+        assert_eq!(timestamps, vec![10, 20, 30, 40, 50]);
+        let projects: Vec<u16> = merged.iter().map(|e| e.project_id).collect();
+        assert_eq!(projects, vec![1, 2, 1, 2, 1]);
+    }
+
+    #[test]
+    fn test_equal_timestamps_break_ties_by_edge_id() {
+        let x = edge_at(10, 1);
+        let y = edge_at(10, 2);
+        let (lo, hi) = if x.edge_id < y.edge_id {
+            (x, y)
+        } else {
+            (y, x)
+        };
+
+        let merged: Vec<u128> = KWayMerge::new(vec![vec![hi].into_iter(), vec![lo].into_iter()])
+            .map(|e| e.edge_id)
+            .collect();
+        assert_eq!(merged, vec![lo.edge_id, hi.edge_id]);
+    }
+}
@@ -205,6 +205,8 @@ pub enum StructuralFilter {
     HasError { has_error: bool },
     /// Filter by project ID
     ProjectId { id: u16 },
+    /// Filter by model name (substring, e.g. "gpt-4")
+    Model { name: String },
 }
 
 /// Aggregation specification
@@ -230,6 +232,9 @@ pub enum AggregationFunction {
     Percentile,
 }
 
+/// Span cost in USD from which "expensive" or "costly" applies
+pub const EXPENSIVE_COST_USD: f64 = 0.05;
+
 /// Natural language query parser
 pub struct NLQueryParser {
     /// Intent keywords
@@ -240,6 +245,8 @@ pub struct NLQueryParser {
     numeric_patterns: Vec<NumericPattern>,
     /// Query templates
     templates: Vec<QueryTemplate>,
+    /// Model name mentions
+    model_pattern: regex::Regex,
 }
 
 struct TimePattern {
@@ -296,6 +303,10 @@ impl NLQueryParser {
             time_patterns: Self::build_time_patterns(),
             numeric_patterns: Self::build_numeric_patterns(),
             templates: Self::build_templates(),
+            model_pattern: regex::Regex::new(
+                r"(?i)\b((?:gpt|claude|gemini|llama|mistral|deepseek)(?:-?[\w.]+)*|o[134](?:-mini|-preview)?)\b",
+            )
+            .unwrap(),
         }
     }
 
@@ -369,6 +380,20 @@ impl NLQueryParser {
                     })
                 },
             },
+            // "over $N", "more than $N", "above $N" (cost)
+            NumericPattern {
+                regex: regex::Regex::new(r"(?i)(?:over|more\s+than|above)\s+\$(\d+(?:\.\d+)?)")
+                    .unwrap(),
+                field: "cost_usd",
+                extract: |caps| {
+                    let value: f64 = caps.get(1)?.as_str().parse().ok()?;
+                    Some(NumericFilter {
+                        field: "cost_usd".to_string(),
+                        operator: ComparisonOp::GreaterThan,
+                        value,
+                    })
+                },
+            },
             // "less than N ms"
             NumericPattern {
                 regex: regex::Regex::new(r"(?i)less\s+than\s+(\d+)\s*ms").unwrap(),
@@ -570,6 +595,17 @@ impl NLQueryParser {
                 }
             }
         }
+
+        let query_lower = query.to_lowercase();
+        if (query_lower.contains("expensive") || query_lower.contains("costly"))
+            && !filters.iter().any(|f| f.field == "cost_usd")
+        {
+            filters.push(NumericFilter {
+                field: "cost_usd".to_string(),
+                operator: ComparisonOp::GreaterThanOrEqual,
+                value: EXPENSIVE_COST_USD,
+            });
+        }
         filters
     }
 
@@ -603,6 +639,17 @@ impl NLQueryParser {
             });
         }
 
+        // Model mentions
+        for caps in self.model_pattern.captures_iter(query) {
+            let name = caps[1].to_lowercase();
+            if !filters
+                .iter()
+                .any(|f| matches!(f, StructuralFilter::Model { name: n } if *n == name))
+            {
+                filters.push(StructuralFilter::Model { name });
+            }
+        }
+
         filters
     }
 
@@ -663,6 +710,28 @@ impl NLQueryParser {
                 StructuralFilter::ProjectId { id } => {
                     filters.project_id = Some(*id);
                 }
+                StructuralFilter::Model { name } => {
+                    let models = filters.models.get_or_insert_with(Vec::new);
+                    models.push(name.clone());
+                }
+                _ => {}
+            }
+        }
+
+        // Apply numeric filters
+        for filter in &parsed.numeric_filters {
+            let lower_bound = match filter.operator {
+                ComparisonOp::GreaterThan | ComparisonOp::GreaterThanOrEqual => true,
+                ComparisonOp::LessThan | ComparisonOp::LessThanOrEqual => false,
+                ComparisonOp::Equal | ComparisonOp::NotEqual => continue,
+            };
+            match (filter.field.as_str(), lower_bound) {
+                ("duration_ms", true) => filters.min_duration_ms = Some(filter.value),
+                ("duration_ms", false) => filters.max_duration_ms = Some(filter.value),
+                ("token_count", true) => filters.min_tokens = Some(filter.value as u64),
+                ("token_count", false) => filters.max_tokens = Some(filter.value as u64),
+                ("cost_usd", true) => filters.min_cost_usd = Some(filter.value),
+                ("cost_usd", false) => filters.max_cost_usd = Some(filter.value),
                 _ => {}
             }
         }
@@ -790,6 +859,31 @@ mod tests {
         assert!(semantic.filters.time_range.is_some());
     }
 
+    #[test]
+    fn test_model_and_cost_extraction() {
+        let parser = NLQueryParser::new();
+
+        let parsed = parser.parse("show me expensive gpt-4 failures yesterday");
+        assert_eq!(parsed.intent, QueryIntent::Search);
+        assert!(matches!(
+            parsed.temporal,
+            Some(TemporalConstraint::Named { ref period }) if period == "yesterday"
+        ));
+
+        let filters = parser.to_semantic_query(&parsed).filters;
+        assert_eq!(filters.models, Some(vec!["gpt-4".to_string()]));
+        assert_eq!(filters.min_cost_usd, Some(EXPENSIVE_COST_USD));
+        assert_eq!(filters.has_error, Some(true));
+        assert!(filters.time_range.is_some());
+
+        let parsed = parser.parse("claude-3-opus calls costing more than $0.25 over 3 seconds");
+        let filters = parser.to_semantic_query(&parsed).filters;
+        assert_eq!(filters.models, Some(vec!["claude-3-opus".to_string()]));
+        assert_eq!(filters.min_cost_usd, Some(0.25));
+        assert_eq!(filters.min_duration_ms, Some(3000.0));
+        assert_eq!(filters.has_error, None);
+    }
+
     #[test]
     fn test_parsed_query_serialization() {
         let parsed = ParsedQuery {
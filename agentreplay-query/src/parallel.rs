@@ -0,0 +1,343 @@
+// Copyright 2025 AgentReplay (https://github.com/agentreplay)
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program. If not, see <https://www.gnu.org/licenses/>.
+
+//! Fan-out/fan-in merge view for parallel branches
+//!
+//! Parallel tool calls are stored as siblings under a common parent. Siblings
+//! tagged with the same batch group ID (assigned at ingest, or inferred here
+//! from overlapping time ranges for untagged data) form a [`ParallelGroup`]:
+//! the group fans out when its first branch starts and fans in when its
+//! slowest branch - including that branch's descendants - finishes.
+//!
+//! The critical path follows every sequential child but only the slowest
+//! branch of each group, since faster branches have slack and do not delay
+//! the parent.
+
+use serde::Serialize;
+use std::collections::{HashMap, HashSet};
+
+/// Minimal span data needed to build the merge view
+#[derive(Debug, Clone)]
+pub struct BranchSpan {
+    pub edge_id: u128,
+    /// Parent edge ID (0 for roots)
+    pub parent_id: u128,
+    pub start_us: u64,
+    pub duration_us: u64,
+    /// Batch group ID assigned at ingest, if any
+    pub group_id: Option<String>,
+    pub label: String,
+}
+
+impl BranchSpan {
+    fn end_us(&self) -> u64 {
+        self.start_us.saturating_add(self.duration_us)
+    }
+}
+
+/// One branch of a parallel group
+#[derive(Debug, Clone, Serialize)]
+pub struct BranchStats {
+    pub edge_id: u128,
+    pub label: String,
+    pub start_us: u64,
+    /// From branch start until the branch and all its descendants finish
+    pub latency_us: u64,
+    /// Spans in this branch's subtree (including the branch span)
+    pub span_count: usize,
+    /// How much later this branch could have finished without delaying fan-in
+    pub slack_us: u64,
+}
+
+/// Siblings that ran concurrently between a fan-out and a fan-in
+#[derive(Debug, Clone, Serialize)]
+pub struct ParallelGroup {
+    pub group_id: String,
+    pub parent_id: u128,
+    /// Whether the group was tagged at ingest or inferred from overlap
+    pub inferred: bool,
+    pub fan_out_us: u64,
+    pub fan_in_us: u64,
+    /// Wall-clock time of the group (fan-in minus fan-out)
+    pub wall_time_us: u64,
+    /// Sum of branch latencies, i.e. the time had the branches run serially
+    pub serial_time_us: u64,
+    /// Branches ordered by start time
+    pub branches: Vec<BranchStats>,
+    /// Edge ID of the branch gating fan-in
+    pub slowest_branch: u128,
+}
+
+impl ParallelGroup {
+    /// Effective concurrency: serial time / wall time
+    pub fn speedup(&self) -> f64 {
+        if self.wall_time_us == 0 {
+            return 1.0;
+        }
+        self.serial_time_us as f64 / self.wall_time_us as f64
+    }
+}
+
+/// Merge view of a whole trace
+#[derive(Debug, Clone, Serialize, Default)]
+pub struct TraceMergeView {
+    pub groups: Vec<ParallelGroup>,
+    /// Spans gating trace completion, in execution order
+    pub critical_path: Vec<u128>,
+    /// End-to-end latency of the trace, which the critical path determines
+    pub critical_path_us: u64,
+}
+
+/// Cluster intervals whose time ranges overlap
+///
+/// Returns index clusters (into `intervals`) with at least two members, each
+/// ordered by start time. Touching intervals (end == next start) are
+/// sequential, not parallel.
+pub fn overlap_clusters(intervals: &[(u64, u64)]) -> Vec<Vec<usize>> {
+    let mut order: Vec<usize> = (0..intervals.len()).collect();
+    order.sort_by_key(|&i| (intervals[i].0, intervals[i].1));
+
+    let mut clusters = Vec::new();
+    let mut current: Vec<usize> = Vec::new();
+    let mut current_end = 0;
+    for i in order {
+        let (start, end) = intervals[i];
+        if !current.is_empty() && start < current_end {
+            current.push(i);
+            current_end = current_end.max(end);
+            continue;
+        }
+        if current.len() >= 2 {
+            clusters.push(std::mem::take(&mut current));
+        }
+        current = vec![i];
+        current_end = end;
+    }
+    if current.len() >= 2 {
+        clusters.push(current);
+    }
+    clusters
+}
+
+/// Build the merge view for the spans of one trace
+pub fn build_merge_view(spans: &[BranchSpan]) -> TraceMergeView {
+    let by_id: HashMap<u128, &BranchSpan> = spans.iter().map(|s| (s.edge_id, s)).collect();
+    let mut children: HashMap<u128, Vec<&BranchSpan>> = HashMap::new();
+    for span in spans {
+        let parent = if by_id.contains_key(&span.parent_id) {
+            span.parent_id
+        } else {
+            0
+        };
+        children.entry(parent).or_default().push(span);
+    }
+    for siblings in children.values_mut() {
+        siblings.sort_by_key(|s| (s.start_us, s.edge_id));
+    }
+
+    // Subtree end time and size per span (iterative post-order)
+    let mut subtree: HashMap<u128, (u64, usize)> = HashMap::new();
+    let mut stack: Vec<(u128, bool)> = children
+        .get(&0)
+        .map(|roots| roots.iter().map(|s| (s.edge_id, false)).collect())
+        .unwrap_or_default();
+    while let Some((id, expanded)) = stack.pop() {
+        if subtree.contains_key(&id) {
+            continue;
+        }
+        let kids = children.get(&id).map(Vec::as_slice).unwrap_or(&[]);
+        if !expanded {
+            stack.push((id, true));
+            stack.extend(kids.iter().map(|k| (k.edge_id, false)));
+            continue;
+        }
+        let mut end = by_id[&id].end_us();
+        let mut count = 1;
+        for kid in kids {
+            if let Some(&(kid_end, kid_count)) = subtree.get(&kid.edge_id) {
+                end = end.max(kid_end);
+                count += kid_count;
+            }
+        }
+        subtree.insert(id, (end, count));
+    }
+    let subtree_end = |s: &BranchSpan| subtree.get(&s.edge_id).map_or(s.end_us(), |v| v.0);
+
+    let mut view = TraceMergeView::default();
+    for (&parent_id, siblings) in &children {
+        let mut tagged: HashMap<&str, Vec<&BranchSpan>> = HashMap::new();
+        let mut untagged = Vec::new();
+        for span in siblings {
+            match span.group_id.as_deref() {
+                Some(group) => tagged.entry(group).or_default().push(*span),
+                None => untagged.push(*span),
+            }
+        }
+
+        let mut members: Vec<(String, bool, Vec<&BranchSpan>)> = tagged
+            .into_iter()
+            .filter(|(_, branches)| branches.len() >= 2)
+            .map(|(group, branches)| (group.to_string(), false, branches))
+            .collect();
+        let intervals: Vec<(u64, u64)> = untagged
+            .iter()
+            .map(|s| (s.start_us, subtree_end(s)))
+            .collect();
+        for cluster in overlap_clusters(&intervals) {
+            let branches: Vec<&BranchSpan> = cluster.iter().map(|&i| untagged[i]).collect();
+            let group_id = format!("inferred-{:x}-{:x}", parent_id, branches[0].edge_id);
+            members.push((group_id, true, branches));
+        }
+
+        for (group_id, inferred, branches) in members {
+            let fan_out_us = branches.iter().map(|b| b.start_us).min().unwrap_or(0);
+            let fan_in_us = branches.iter().map(|b| subtree_end(b)).max().unwrap_or(0);
+            let mut stats: Vec<BranchStats> = branches
+                .iter()
+                .map(|b| {
+                    let end = subtree_end(b);
+                    BranchStats {
+                        edge_id: b.edge_id,
+                        label: b.label.clone(),
+                        start_us: b.start_us,
+                        latency_us: end.saturating_sub(b.start_us),
+                        span_count: subtree.get(&b.edge_id).map_or(1, |v| v.1),
+                        slack_us: fan_in_us.saturating_sub(end),
+                    }
+                })
+                .collect();
+            stats.sort_by_key(|b| (b.start_us, b.edge_id));
+            let slowest_branch = stats
+                .iter()
+                .find(|b| b.slack_us == 0)
+                .map_or(0, |b| b.edge_id);
+
+            view.groups.push(ParallelGroup {
+                group_id,
+                parent_id,
+                inferred,
+                fan_out_us,
+                fan_in_us,
+                wall_time_us: fan_in_us.saturating_sub(fan_out_us),
+                serial_time_us: stats.iter().map(|b| b.latency_us).sum(),
+                branches: stats,
+                slowest_branch,
+            });
+        }
+    }
+    view.groups.sort_by_key(|g| (g.fan_out_us, g.parent_id));
+
+    // Critical path: sequential children all gate their parent; within a
+    // group only the slowest branch does
+    let mut off_path: HashSet<u128> = HashSet::new();
+    for group in &view.groups {
+        off_path.extend(
+            group
+                .branches
+                .iter()
+                .filter(|b| b.edge_id != group.slowest_branch)
+                .map(|b| b.edge_id),
+        );
+    }
+    let mut stack: Vec<u128> = children
+        .get(&0)
+        .map(|roots| roots.iter().rev().map(|s| s.edge_id).collect())
+        .unwrap_or_default();
+    while let Some(id) = stack.pop() {
+        if off_path.contains(&id) {
+            continue;
+        }
+        view.critical_path.push(id);
+        if let Some(kids) = children.get(&id) {
+            stack.extend(kids.iter().rev().map(|k| k.edge_id));
+        }
+    }
+    view.critical_path_us = children
+        .get(&0)
+        .map(|roots| {
+            let start = roots.iter().map(|s| s.start_us).min().unwrap_or(0);
+            let end = roots.iter().map(|s| subtree_end(s)).max().unwrap_or(0);
+            end.saturating_sub(start)
+        })
+        .unwrap_or(0);
+
+    view
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn span(id: u128, parent: u128, start: u64, duration: u64, group: Option<&str>) -> BranchSpan {
+        BranchSpan {
+            edge_id: id,
+            parent_id: parent,
+            start_us: start,
+            duration_us: duration,
+            group_id: group.map(String::from),
+            label: format!("span-{}", id),
+        }
+    }
+
+    #[test]
+    fn test_overlap_clusters() {
+        let clusters = overlap_clusters(&[(0, 10), (5, 20), (20, 30), (40, 50), (45, 46)]);
+        assert_eq!(clusters, vec![vec![0, 1], vec![3, 4]]);
+    }
+
+    #[test]
+    fn test_tagged_group_slowest_branch() {
+        let spans = vec![
+            span(1, 0, 0, 1000, None),
+            span(2, 1, 100, 200, Some("g1")),
+            span(3, 1, 110, 100, Some("g1")),
+            // Branch 4 is short itself but its child runs long
+            span(4, 1, 120, 50, Some("g1")),
+            span(5, 4, 150, 500, None),
+            span(6, 1, 700, 100, None),
+        ];
+        let view = build_merge_view(&spans);
+
+        assert_eq!(view.groups.len(), 1);
+        let group = &view.groups[0];
+        assert!(!group.inferred);
+        assert_eq!(group.fan_out_us, 100);
+        assert_eq!(group.fan_in_us, 650);
+        assert_eq!(group.slowest_branch, 4);
+        assert_eq!(group.branches[2].span_count, 2);
+        assert_eq!(group.branches[0].slack_us, 350);
+
+        // Faster branches are not on the critical path
+        assert_eq!(view.critical_path, vec![1, 4, 5, 6]);
+        assert_eq!(view.critical_path_us, 1000);
+    }
+
+    #[test]
+    fn test_inferred_group_for_untagged_siblings() {
+        let spans = vec![
+            span(1, 0, 0, 500, None),
+            span(2, 1, 10, 100, None),
+            span(3, 1, 20, 300, None),
+            span(4, 1, 400, 50, None),
+        ];
+        let view = build_merge_view(&spans);
+
+        assert_eq!(view.groups.len(), 1);
+        assert!(view.groups[0].inferred);
+        assert_eq!(view.groups[0].slowest_branch, 3);
+        assert!(view.groups[0].speedup() > 1.0);
+        assert_eq!(view.critical_path, vec![1, 3, 4]);
+    }
+}
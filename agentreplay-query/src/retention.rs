@@ -29,12 +29,25 @@
 //! - `retention_days: 0` or `None` = unlimited retention (keep forever)
 //! - `retention_days: 30` = default, delete data older than 30 days
 //! - Settings are persisted to `~/.agentreplay/retention-config.json`
+//!
+//! ## Size-based retention
+//!
+//! Desktop installs can also cap the database size. When the data directory
+//! grows past the cap, the oldest traces are evicted until the estimated size
+//! is back under [`SIZE_EVICTION_LOW_WATERMARK`] of the cap. Per-trace size is
+//! estimated as the directory size divided by the live trace count.
+//!
+//! Trace counts come from the storage's hour and day rollups rather than a
+//! scan of every edge, so the estimate is cheap enough to run every few
+//! minutes. Rollups keep counting deleted traces, so buckets below the
+//! cutoff of the latest bulk delete, saved in [`RETENTION_FLOOR_FILE`], are
+//! left out. Evictions cut at bucket boundaries.
 
 use crate::Agentreplay;
 use agentreplay_core::Result;
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 use std::sync::atomic::{AtomicU64, Ordering};
 use std::time::{SystemTime, UNIX_EPOCH};
 use tracing::{info, warn};
@@ -94,6 +107,102 @@ pub struct RetentionStats {
     pub sstables_compacted: usize,
 }
 
+/// Fraction of the size cap that size-based eviction brings the database
+/// down to, so the next few ingested traces don't trigger it again
+pub const SIZE_EVICTION_LOW_WATERMARK: f64 = 0.9;
+
+/// File in the data directory holding the cutoff of the latest bulk delete
+pub const RETENTION_FLOOR_FILE: &str = "retention_floor";
+
+/// Traces recorded in one rollup bucket
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TraceBucket {
+    pub start_us: u64,
+    pub end_us: u64,
+    pub traces: u64,
+}
+
+/// How much space size-based eviction would reclaim
+#[derive(Debug, Clone, Serialize, Deserialize, Default, PartialEq)]
+pub struct SizeRetentionEstimate {
+    /// Current size of the data directory
+    pub disk_bytes: u64,
+    /// Size cap (None = unlimited)
+    pub max_bytes: Option<u64>,
+    pub live_traces: usize,
+    /// Average on-disk footprint of a trace, including payloads and indexes
+    pub avg_trace_bytes: u64,
+    /// Bytes over the cap (0 when under it)
+    pub excess_bytes: u64,
+    /// Oldest traces that would be evicted
+    pub evictable_traces: usize,
+    /// Estimated bytes freed by evicting them, once compaction runs
+    pub reclaimable_bytes: u64,
+    /// Traces with a timestamp below this would be evicted
+    pub eviction_cutoff_us: Option<u64>,
+    pub oldest_trace_us: Option<u64>,
+}
+
+impl SizeRetentionEstimate {
+    /// Estimate eviction for a database of `disk_bytes` holding the traces
+    /// of `buckets`, oldest first
+    pub fn compute(disk_bytes: u64, max_bytes: Option<u64>, buckets: &[TraceBucket]) -> Self {
+        let live_traces: u64 = buckets.iter().map(|b| b.traces).sum();
+        let avg_trace_bytes = if live_traces > 0 {
+            disk_bytes / live_traces
+        } else {
+            0
+        };
+        let mut estimate = Self {
+            disk_bytes,
+            max_bytes,
+            live_traces: live_traces as usize,
+            avg_trace_bytes,
+            oldest_trace_us: buckets.first().map(|b| b.start_us),
+            ..Self::default()
+        };
+
+        let Some(max_bytes) = max_bytes else {
+            return estimate;
+        };
+        if disk_bytes <= max_bytes || avg_trace_bytes == 0 {
+            return estimate;
+        }
+        estimate.excess_bytes = disk_bytes - max_bytes;
+
+        let target = (max_bytes as f64 * SIZE_EVICTION_LOW_WATERMARK) as u64;
+        let count = (disk_bytes - target).div_ceil(avg_trace_bytes);
+        // Whole buckets go, up to the one that reaches the count
+        let mut evictable = 0;
+        for bucket in buckets {
+            evictable += bucket.traces;
+            estimate.eviction_cutoff_us = Some(bucket.end_us);
+            if evictable >= count {
+                break;
+            }
+        }
+
+        estimate.evictable_traces = evictable as usize;
+        estimate.reclaimable_bytes = (evictable * avg_trace_bytes).min(disk_bytes);
+        estimate
+    }
+}
+
+/// Total size of the files under a directory
+pub fn dir_size_bytes(path: &Path) -> u64 {
+    let Ok(entries) = std::fs::read_dir(path) else {
+        return 0;
+    };
+    entries
+        .flatten()
+        .map(|entry| match entry.metadata() {
+            Ok(meta) if meta.is_dir() => dir_size_bytes(&entry.path()),
+            Ok(meta) => meta.len(),
+            Err(_) => 0,
+        })
+        .sum()
+}
+
 /// Retention metrics for observability
 #[derive(Debug, Default)]
 pub struct RetentionMetrics {
@@ -276,7 +385,9 @@ impl Agentreplay {
 
         // Query all edges older than the cutoff
         // Note: This is done in batches to avoid memory exhaustion
-        let old_edges = self.storage.range_scan(0, before_timestamp_us)?;
+        let old_edges = self.storage().range_scan(0, before_timestamp_us)?;
+        // Size estimates stop counting the rollups of what is deleted here
+        self.raise_retention_floor(before_timestamp_us);
 
         if old_edges.is_empty() {
             stats.cleanup_duration_ms = start_time
@@ -327,10 +438,86 @@ impl Agentreplay {
         Ok(stats)
     }
 
+    /// Size of the database directory on disk
+    pub fn disk_usage_bytes(&self) -> u64 {
+        dir_size_bytes(self.storage().data_dir())
+    }
+
+    /// Estimate what capping the database at `max_bytes` would evict
+    pub fn estimate_size_retention(&self, max_bytes: Option<u64>) -> Result<SizeRetentionEstimate> {
+        let disk_bytes = self.disk_usage_bytes();
+        let floor_us = self.retention_floor_us();
+        let buckets: Vec<TraceBucket> = self
+            .storage()
+            .edge_counts_by_bucket()
+            .into_iter()
+            .filter(|(_, end_us, _)| *end_us > floor_us)
+            .map(|(start_us, end_us, traces)| TraceBucket {
+                start_us: start_us.max(floor_us),
+                end_us,
+                traces,
+            })
+            .collect();
+        Ok(SizeRetentionEstimate::compute(
+            disk_bytes, max_bytes, &buckets,
+        ))
+    }
+
+    /// Cutoff of the latest bulk delete; older rollup counts are stale
+    fn retention_floor_us(&self) -> u64 {
+        let path = self.storage().data_dir().join(RETENTION_FLOOR_FILE);
+        std::fs::read_to_string(path)
+            .ok()
+            .and_then(|floor| floor.trim().parse().ok())
+            .unwrap_or(0)
+    }
+
+    fn raise_retention_floor(&self, cutoff_us: u64) {
+        if cutoff_us <= self.retention_floor_us() {
+            return;
+        }
+        let path = self.storage().data_dir().join(RETENTION_FLOOR_FILE);
+        if let Err(e) = std::fs::write(&path, cutoff_us.to_string()) {
+            warn!(error = %e, "Failed to save the retention floor");
+        }
+    }
+
+    /// Evict the oldest traces when the database is larger than `max_bytes`
+    ///
+    /// Deletes are tombstones, so a compaction runs right after to reclaim
+    /// their space; `disk_freed_bytes` is what it measured, or the estimate
+    /// when it could not run (e.g. one was already in progress).
+    pub async fn evict_to_size(&self, max_bytes: u64) -> Result<RetentionStats> {
+        let estimate = self.estimate_size_retention(Some(max_bytes))?;
+        let Some(cutoff_us) = estimate.eviction_cutoff_us else {
+            return Ok(RetentionStats::default());
+        };
+
+        info!(
+            disk_bytes = estimate.disk_bytes,
+            max_bytes = max_bytes,
+            evicting = estimate.evictable_traces,
+            "Database over size limit, evicting oldest traces"
+        );
+        let mut stats = self.delete_traces_before(cutoff_us).await?;
+        stats.disk_freed_bytes = match self.compact() {
+            Ok(run) if run.error.is_none() => run.bytes_reclaimed(),
+            Ok(run) => {
+                warn!(error = ?run.error, "Compaction after eviction failed");
+                estimate.reclaimable_bytes
+            }
+            Err(e) => {
+                warn!(error = %e, "Compaction after eviction did not run");
+                estimate.reclaimable_bytes
+            }
+        };
+        Ok(stats)
+    }
+
     /// Get the total number of traces in the database
     pub async fn trace_count(&self) -> usize {
         // Use iter_all_edges for accurate count
-        match self.storage.iter_all_edges() {
+        match self.storage().iter_all_edges() {
             Ok(edges) => edges.len(),
             Err(_) => 0,
         }
@@ -338,7 +525,7 @@ impl Agentreplay {
 
     /// Get the oldest trace timestamp in the database
     pub async fn oldest_trace_timestamp(&self) -> Option<u64> {
-        match self.storage.iter_all_edges() {
+        match self.storage().iter_all_edges() {
             Ok(edges) => edges.iter().map(|e| e.timestamp_us).min(),
             Err(_) => None,
         }
@@ -346,7 +533,7 @@ impl Agentreplay {
 
     /// Get the newest trace timestamp in the database
     pub async fn newest_trace_timestamp(&self) -> Option<u64> {
-        match self.storage.iter_all_edges() {
+        match self.storage().iter_all_edges() {
             Ok(edges) => edges.iter().map(|e| e.timestamp_us).max(),
             Err(_) => None,
         }
@@ -513,4 +700,61 @@ mod tests {
             .unwrap();
         assert_eq!(dev.retention_days, Some(7));
     }
+
+    /// One-trace buckets of 100us at the given starts
+    fn buckets(starts: &[u64]) -> Vec<TraceBucket> {
+        starts
+            .iter()
+            .map(|&start_us| TraceBucket {
+                start_us,
+                end_us: start_us + 100,
+                traces: 1,
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_size_estimate_under_limit() {
+        let buckets = buckets(&[10, 110, 210]);
+        let estimate = SizeRetentionEstimate::compute(3_000, Some(10_000), &buckets);
+        assert_eq!(estimate.avg_trace_bytes, 1_000);
+        assert_eq!(estimate.oldest_trace_us, Some(10));
+        assert_eq!(estimate.evictable_traces, 0);
+        assert_eq!(estimate.eviction_cutoff_us, None);
+
+        let unlimited = SizeRetentionEstimate::compute(3_000, None, &buckets);
+        assert_eq!(unlimited.excess_bytes, 0);
+        assert_eq!(unlimited.eviction_cutoff_us, None);
+    }
+
+    #[test]
+    fn test_size_estimate_evicts_oldest_to_watermark() {
+        // 10 traces of ~1000 bytes, capped at 8000: free down to 7200
+        let starts: Vec<u64> = (1..=10).map(|i| i * 100).collect();
+        let estimate = SizeRetentionEstimate::compute(10_000, Some(8_000), &buckets(&starts));
+        assert_eq!(estimate.excess_bytes, 2_000);
+        assert_eq!(estimate.evictable_traces, 3);
+        assert_eq!(estimate.reclaimable_bytes, 3_000);
+        assert_eq!(estimate.eviction_cutoff_us, Some(400));
+    }
+
+    #[test]
+    fn test_size_estimate_evicts_whole_buckets() {
+        let buckets = [
+            TraceBucket {
+                start_us: 0,
+                end_us: 100,
+                traces: 3,
+            },
+            TraceBucket {
+                start_us: 100,
+                end_us: 200,
+                traces: 1,
+            },
+        ];
+        let estimate = SizeRetentionEstimate::compute(4_000, Some(3_500), &buckets);
+        // One trace would be enough, but its bucket holds three
+        assert_eq!(estimate.evictable_traces, 3);
+        assert_eq!(estimate.eviction_cutoff_us, Some(100));
+    }
 }
@@ -52,7 +52,7 @@
 //! })?;
 //! ```
 
-use agentreplay_core::AgentFlowEdge;
+use agentreplay_core::{AgentFlowEdge, SpanType};
 use moka::sync::Cache;
 use serde::{Deserialize, Serialize};
 use std::collections::HashSet;
@@ -162,6 +162,34 @@ pub struct QueryFilters {
     /// Filter by project ID
     #[serde(default, skip_serializing_if = "Option::is_none")]
     pub project_id: Option<u16>,
+
+    /// Filter by model name (case-insensitive substring, any of)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub models: Option<Vec<String>>,
+
+    /// Minimum span cost in USD
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub min_cost_usd: Option<f64>,
+
+    /// Maximum span cost in USD
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_cost_usd: Option<f64>,
+
+    /// Minimum span duration in milliseconds
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub min_duration_ms: Option<f64>,
+
+    /// Maximum span duration in milliseconds
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_duration_ms: Option<f64>,
+
+    /// Minimum token count
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub min_tokens: Option<u64>,
+
+    /// Maximum token count
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_tokens: Option<u64>,
 }
 
 impl QueryFilters {
@@ -173,6 +201,37 @@ impl QueryFilters {
             || self.span_types.is_some()
             || self.has_error.is_some()
             || self.project_id.is_some()
+            || self.models.is_some()
+            || self.min_cost_usd.is_some()
+            || self.max_cost_usd.is_some()
+            || self.min_duration_ms.is_some()
+            || self.max_duration_ms.is_some()
+            || self.min_tokens.is_some()
+            || self.max_tokens.is_some()
+    }
+
+    /// Whether model or cost filters are set, which need the span payload
+    /// and are not checked by [`matches`](Self::matches)
+    pub fn needs_payload(&self) -> bool {
+        self.models.is_some() || self.min_cost_usd.is_some() || self.max_cost_usd.is_some()
+    }
+
+    /// Check a span's model and cost against the payload-level filters
+    pub fn matches_payload(&self, model: Option<&str>, cost_usd: f64) -> bool {
+        if let Some(ref models) = self.models {
+            let Some(model) = model.map(str::to_lowercase) else {
+                return false;
+            };
+            if !models.iter().any(|m| model.contains(&m.to_lowercase())) {
+                return false;
+            }
+        }
+        if self.min_cost_usd.is_some_and(|min| cost_usd < min)
+            || self.max_cost_usd.is_some_and(|max| cost_usd > max)
+        {
+            return false;
+        }
+        true
     }
 
     /// Check if an edge matches the filters
@@ -215,12 +274,28 @@ impl QueryFilters {
 
         // Check error state
         if let Some(has_error) = self.has_error {
-            let edge_has_error = edge.flags & 0x01 != 0; // Assuming bit 0 is error flag
+            let edge_has_error = edge.get_span_type() == SpanType::Error;
             if has_error != edge_has_error {
                 return false;
             }
         }
 
+        // Check duration
+        let duration_ms = edge.duration_us as f64 / 1000.0;
+        if self.min_duration_ms.is_some_and(|min| duration_ms < min)
+            || self.max_duration_ms.is_some_and(|max| duration_ms > max)
+        {
+            return false;
+        }
+
+        // Check token count
+        let tokens = edge.token_count as u64;
+        if self.min_tokens.is_some_and(|min| tokens < min)
+            || self.max_tokens.is_some_and(|max| tokens > max)
+        {
+            return false;
+        }
+
         true
     }
 }
@@ -630,6 +705,36 @@ mod tests {
         assert!(filters.has_any());
     }
 
+    #[test]
+    fn test_query_filters_matches() {
+        let mut edge = AgentFlowEdge::new(1, 0, 1, 1, SpanType::Error, 0);
+        edge.duration_us = 2_500_000;
+        edge.token_count = 800;
+
+        let filters = QueryFilters {
+            has_error: Some(true),
+            min_duration_ms: Some(2000.0),
+            max_tokens: Some(1000),
+            ..Default::default()
+        };
+        assert!(filters.matches(&edge));
+        assert!(!filters.needs_payload());
+
+        edge.span_type = SpanType::Response as u32;
+        assert!(!filters.matches(&edge));
+
+        let filters = QueryFilters {
+            models: Some(vec!["GPT-4".to_string()]),
+            min_cost_usd: Some(0.05),
+            ..Default::default()
+        };
+        assert!(filters.needs_payload());
+        assert!(filters.matches_payload(Some("gpt-4o-2024-08-06"), 0.12));
+        assert!(!filters.matches_payload(Some("gpt-4o"), 0.01));
+        assert!(!filters.matches_payload(Some("claude-3-opus"), 0.12));
+        assert!(!filters.matches_payload(None, 0.12));
+    }
+
     #[test]
     fn test_cosine_similarity() {
         let a = vec![1.0, 0.0, 0.0];
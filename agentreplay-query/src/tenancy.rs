@@ -0,0 +1,196 @@
+// Copyright 2025 AgentReplay (https://github.com/agentreplay)
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program. If not, see <https://www.gnu.org/licenses/>.
+
+//! Tenant-scoped queries
+//!
+//! Several [`Agentreplay`] methods scan every tenant unless given a tenant
+//! filter, which makes forgetting the filter a data leak. A
+//! [`TenantQuery`] always carries a [`TenantScope`], so reading across
+//! tenants has to be spelled out as [`TenantScope::AllTenants`]:
+//!
+//! ```ignore
+//! let edges = db.scoped(TenantScope::Tenant(auth.tenant_id)).range(start, end)?;
+//! // Maintenance jobs opt in explicitly
+//! let edges = db.scoped(TenantScope::AllTenants).range(start, end)?;
+//! ```
+//!
+//! In [`IsolationMode::Strict`] the tenant-optional methods of
+//! [`Agentreplay`] refuse to scan without a tenant, so the remaining
+//! implicit cross-tenant reads fail loudly instead of leaking.
+
+use agentreplay_core::{AgentFlowEdge, AgentreplayError, Result};
+
+use crate::engine::Agentreplay;
+
+/// Tenants a query may read
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum TenantScope {
+    /// One tenant, normally the one authenticated for the request
+    Tenant(u64),
+    /// Every tenant; for admin tooling and background maintenance
+    AllTenants,
+}
+
+impl TenantScope {
+    /// The tenant filter to pass to storage
+    pub fn tenant_id(&self) -> Option<u64> {
+        match self {
+            TenantScope::Tenant(tenant_id) => Some(*tenant_id),
+            TenantScope::AllTenants => None,
+        }
+    }
+
+    pub fn contains(&self, tenant_id: u64) -> bool {
+        match self {
+            TenantScope::Tenant(scope) => *scope == tenant_id,
+            TenantScope::AllTenants => true,
+        }
+    }
+}
+
+/// Whether queries without a tenant are allowed
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum IsolationMode {
+    /// Tenant filters are optional; `None` reads every tenant
+    #[default]
+    Permissive,
+    /// Every scan needs a tenant or an explicit [`TenantScope::AllTenants`]
+    Strict,
+}
+
+impl IsolationMode {
+    /// Fail when `tenant_id` is missing in strict mode
+    pub(crate) fn require_tenant(&self, operation: &str, tenant_id: Option<u64>) -> Result<()> {
+        if *self == IsolationMode::Strict && tenant_id.is_none() {
+            return Err(AgentreplayError::TenantIsolation(format!(
+                "{} needs a tenant; use Agentreplay::scoped with TenantScope::AllTenants \
+                 to read across tenants",
+                operation
+            )));
+        }
+        Ok(())
+    }
+}
+
+/// Read access to the edges of a [`TenantScope`]
+///
+/// Results are filtered by tenant after storage returns them as well, so a
+/// storage path that ignores the filter still cannot leak another tenant's
+/// edges.
+#[derive(Clone, Copy)]
+pub struct TenantQuery<'a> {
+    db: &'a Agentreplay,
+    scope: TenantScope,
+}
+
+impl<'a> TenantQuery<'a> {
+    pub(crate) fn new(db: &'a Agentreplay, scope: TenantScope) -> Self {
+        Self { db, scope }
+    }
+
+    pub fn scope(&self) -> TenantScope {
+        self.scope
+    }
+
+    /// Edges in `[start_ts, end_ts]`
+    pub fn range(&self, start_ts: u64, end_ts: u64) -> Result<Vec<AgentFlowEdge>> {
+        self.range_for_project(start_ts, end_ts, None)
+    }
+
+    /// Edges in `[start_ts, end_ts]`, optionally of one project
+    pub fn range_for_project(
+        &self,
+        start_ts: u64,
+        end_ts: u64,
+        project_id: Option<u16>,
+    ) -> Result<Vec<AgentFlowEdge>> {
+        let edges = self.db.storage().range_scan_filtered(
+            start_ts,
+            end_ts,
+            self.scope.tenant_id(),
+            project_id,
+        )?;
+        Ok(self.keep(edges))
+    }
+
+    /// An edge, or `None` if it belongs to a tenant outside the scope
+    pub fn get(&self, edge_id: u128) -> Result<Option<AgentFlowEdge>> {
+        Ok(self
+            .db
+            .get(edge_id)?
+            .filter(|edge| self.scope.contains(edge.tenant_id)))
+    }
+
+    /// Payload of an edge in the scope
+    pub fn payload(&self, edge_id: u128) -> Result<Option<Vec<u8>>> {
+        match self.get(edge_id)? {
+            Some(_) => self.db.get_payload(edge_id),
+            None => Ok(None),
+        }
+    }
+
+    /// Edges of a session
+    pub fn session_edges(&self, session_id: u64) -> Result<Vec<AgentFlowEdge>> {
+        Ok(self.keep(self.db.get_session_edges_full(session_id)?))
+    }
+
+    pub fn children(&self, edge_id: u128) -> Result<Vec<AgentFlowEdge>> {
+        Ok(self.keep(self.db.get_children(edge_id)?))
+    }
+
+    pub fn parents(&self, edge_id: u128) -> Result<Vec<AgentFlowEdge>> {
+        Ok(self.keep(self.db.get_parents(edge_id)?))
+    }
+
+    pub fn descendants(&self, edge_id: u128) -> Result<Vec<AgentFlowEdge>> {
+        Ok(self.keep(self.db.get_descendants(edge_id)?))
+    }
+
+    pub fn ancestors(&self, edge_id: u128) -> Result<Vec<AgentFlowEdge>> {
+        Ok(self.keep(self.db.get_ancestors(edge_id)?))
+    }
+
+    fn keep(&self, mut edges: Vec<AgentFlowEdge>) -> Vec<AgentFlowEdge> {
+        edges.retain(|edge| self.scope.contains(edge.tenant_id));
+        edges
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_scope() {
+        assert_eq!(TenantScope::Tenant(3).tenant_id(), Some(3));
+        assert!(TenantScope::Tenant(3).contains(3));
+        assert!(!TenantScope::Tenant(3).contains(4));
+        assert_eq!(TenantScope::AllTenants.tenant_id(), None);
+        assert!(TenantScope::AllTenants.contains(4));
+    }
+
+    #[test]
+    fn test_strict_requires_tenant() {
+        let strict = IsolationMode::Strict;
+        assert!(strict.require_tenant("scan", Some(1)).is_ok());
+        assert!(matches!(
+            strict.require_tenant("scan", None),
+            Err(AgentreplayError::TenantIsolation(_))
+        ));
+        assert!(IsolationMode::Permissive
+            .require_tenant("scan", None)
+            .is_ok());
+    }
+}
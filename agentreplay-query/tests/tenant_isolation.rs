@@ -0,0 +1,211 @@
+//! Randomized checks that tenant-scoped queries never return another
+//! tenant's data, including through sessions and causal links shared
+//! across tenants.
+
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use agentreplay_core::{AgentFlowEdge, AgentreplayError, SpanType};
+use agentreplay_query::engine::Agentreplay;
+use agentreplay_query::{IsolationMode, QueryBuilder, TenantScope};
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+use tempfile::tempdir;
+
+const TENANTS: u64 = 4;
+const EDGES: usize = 400;
+const QUERIES: usize = 200;
+const WINDOW_US: u64 = 3_600_000_000;
+
+struct Fixture {
+    db: Arc<Agentreplay>,
+    edges: Vec<AgentFlowEdge>,
+    start_us: u64,
+    _dir: tempfile::TempDir,
+}
+
+/// Edges of every tenant with colliding session ids and parents that
+/// often belong to another tenant
+async fn fixture(rng: &mut StdRng) -> Fixture {
+    let dir = tempdir().unwrap();
+    let db = Arc::new(Agentreplay::open(dir.path()).unwrap());
+    let start_us = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap()
+        .as_micros() as u64
+        - WINDOW_US;
+
+    let mut edges: Vec<AgentFlowEdge> = Vec::with_capacity(EDGES);
+    for _ in 0..EDGES {
+        let parent = if edges.is_empty() || rng.gen_bool(0.3) {
+            0
+        } else {
+            edges[rng.gen_range(0..edges.len())].edge_id
+        };
+        let mut edge = AgentFlowEdge::new(
+            rng.gen_range(1..=TENANTS),
+            rng.gen_range(0..3),
+            rng.gen_range(1..5),
+            rng.gen_range(1..10),
+            SpanType::Root,
+            parent,
+        );
+        edge.timestamp_us = start_us + rng.gen_range(0..WINDOW_US);
+        edge.checksum = edge.compute_checksum();
+        edges.push(edge);
+    }
+    db.insert_batch(&edges).await.unwrap();
+    for edge in &edges {
+        db.put_payload(
+            edge.edge_id,
+            format!("tenant-{}", edge.tenant_id).as_bytes(),
+        )
+        .unwrap();
+    }
+
+    Fixture {
+        db,
+        edges,
+        start_us,
+        _dir: dir,
+    }
+}
+
+fn assert_tenant(edges: &[AgentFlowEdge], tenant_id: u64, what: &str) {
+    for edge in edges {
+        assert_eq!(
+            edge.tenant_id, tenant_id,
+            "{} returned edge {:#x} of tenant {} to tenant {}",
+            what, edge.edge_id, edge.tenant_id, tenant_id
+        );
+    }
+}
+
+#[tokio::test]
+async fn test_scoped_queries_never_cross_tenants() {
+    let mut rng = StdRng::seed_from_u64(0x7e4a_4715);
+    let fixture = fixture(&mut rng).await;
+    let db = &fixture.db;
+
+    let mut owners: HashMap<u128, u64> = HashMap::new();
+    let mut per_tenant: HashMap<u64, usize> = HashMap::new();
+    for edge in &fixture.edges {
+        owners.insert(edge.edge_id, edge.tenant_id);
+        *per_tenant.entry(edge.tenant_id).or_default() += 1;
+    }
+
+    for tenant_id in 1..=TENANTS {
+        let all = db
+            .scoped(TenantScope::Tenant(tenant_id))
+            .range(0, u64::MAX)
+            .unwrap();
+        assert_tenant(&all, tenant_id, "range");
+        assert_eq!(all.len(), per_tenant.get(&tenant_id).copied().unwrap_or(0));
+    }
+
+    for _ in 0..QUERIES {
+        let tenant_id = rng.gen_range(1..=TENANTS + 1);
+        let scoped = db.scoped(TenantScope::Tenant(tenant_id));
+        let probe = &fixture.edges[rng.gen_range(0..fixture.edges.len())];
+        let start = fixture.start_us + rng.gen_range(0..WINDOW_US);
+        let end = start + rng.gen_range(0..WINDOW_US);
+        let project_id = rng.gen_bool(0.5).then(|| rng.gen_range(0..3));
+
+        let range = scoped.range_for_project(start, end, project_id).unwrap();
+        assert_tenant(&range, tenant_id, "range_for_project");
+        assert!(range
+            .iter()
+            .all(|e| project_id.is_none() || project_id == Some(e.project_id)));
+
+        let owned = owners[&probe.edge_id] == tenant_id;
+        assert_eq!(scoped.get(probe.edge_id).unwrap().is_some(), owned);
+        assert_eq!(scoped.payload(probe.edge_id).unwrap().is_some(), owned);
+        if let Some(payload) = scoped.payload(probe.edge_id).unwrap() {
+            assert_eq!(payload, format!("tenant-{}", tenant_id).into_bytes());
+        }
+
+        assert_tenant(
+            &scoped.session_edges(probe.session_id).unwrap(),
+            tenant_id,
+            "session_edges",
+        );
+        assert_tenant(
+            &scoped.children(probe.edge_id).unwrap(),
+            tenant_id,
+            "children",
+        );
+        assert_tenant(
+            &scoped.parents(probe.edge_id).unwrap(),
+            tenant_id,
+            "parents",
+        );
+        assert_tenant(
+            &scoped.descendants(probe.edge_id).unwrap(),
+            tenant_id,
+            "descendants",
+        );
+        assert_tenant(
+            &scoped.ancestors(probe.edge_id).unwrap(),
+            tenant_id,
+            "ancestors",
+        );
+        assert_tenant(
+            &db.query_filtered(start, end, Some(tenant_id), project_id)
+                .unwrap(),
+            tenant_id,
+            "query_filtered",
+        );
+    }
+}
+
+#[tokio::test]
+async fn test_strict_mode_requires_tenant() {
+    let mut rng = StdRng::seed_from_u64(0x5721_c7);
+    let fixture = fixture(&mut rng).await;
+    let db = &fixture.db;
+    db.set_isolation_mode(IsolationMode::Strict);
+
+    assert!(matches!(
+        db.query_temporal_range(0, u64::MAX),
+        Err(AgentreplayError::TenantIsolation(_))
+    ));
+    assert!(matches!(
+        db.query_filtered(0, u64::MAX, None, Some(1)),
+        Err(AgentreplayError::TenantIsolation(_))
+    ));
+    assert!(db
+        .query_temporal_range_paginated(0, u64::MAX, 10, 0)
+        .is_err());
+    assert!(db.filter_by_session(1, 0, u64::MAX).is_err());
+    assert!(QueryBuilder::new(db.clone())
+        .time_range(fixture.start_us, fixture.start_us + WINDOW_US)
+        .execute()
+        .is_err());
+
+    for tenant_id in 1..=TENANTS {
+        let edges = QueryBuilder::new(db.clone())
+            .tenant(tenant_id)
+            .time_range(fixture.start_us, fixture.start_us + WINDOW_US)
+            .no_limit()
+            .execute()
+            .unwrap();
+        assert_tenant(&edges, tenant_id, "QueryBuilder");
+        assert_tenant(
+            &db.query_filtered(0, u64::MAX, Some(tenant_id), None)
+                .unwrap(),
+            tenant_id,
+            "query_filtered",
+        );
+    }
+
+    // Reading across tenants still works when asked for explicitly
+    let all = db
+        .scoped(TenantScope::AllTenants)
+        .range(0, u64::MAX)
+        .unwrap();
+    assert_eq!(all.len(), fixture.edges.len());
+
+    db.set_isolation_mode(IsolationMode::Permissive);
+    assert!(db.query_temporal_range(0, u64::MAX).is_ok());
+}
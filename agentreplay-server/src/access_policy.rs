@@ -0,0 +1,393 @@
+// Copyright 2025 AgentReplay (https://github.com/agentreplay)
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program. If not, see <https://www.gnu.org/licenses/>.
+
+//! Row-level access policies by span attribute
+//!
+//! Tenants keep organisations apart; access policies keep apart the teams
+//! sharing a tenant. Each policy in `[access_policies]` names the callers
+//! it applies to (users, roles, tenants, projects) and the spans they may
+//! read: allowed values of span attributes such as `team`, allowed
+//! environments, and whether spans flagged as containing PII or secrets
+//! are hidden. A caller several policies apply to reads the union of what
+//! they allow.
+//!
+//! [`access_policy_middleware`] resolves the caller's policies once per
+//! request into an [`AccessFilter`] request extension. Trace and span
+//! reads check spans against it: listings, search results and span lists
+//! drop the spans the caller may not read, and a trace whose root span is
+//! not readable is not found. Aggregate analytics are not filtered.
+//!
+//! Callers with at least `exempt_role` are never restricted, and nobody is
+//! while the feature is disabled.
+
+use std::sync::Arc;
+
+use agentreplay_core::{AgentFlowEdge, Environment};
+use axum::{body::Body, extract::State, http::Request, middleware::Next, response::Response};
+use serde_json::Value;
+
+use crate::api::AppState;
+use crate::auth::AuthContext;
+use crate::config::{AccessPolicy, AccessPolicyConfig};
+
+/// Which spans an applicable policy lets the caller read
+#[derive(Debug)]
+struct SpanRule {
+    attributes: Vec<(String, Vec<String>)>,
+    environments: Vec<u8>,
+    deny_pii: bool,
+    deny_secrets: bool,
+}
+
+impl SpanRule {
+    fn new(policy: &AccessPolicy) -> Self {
+        Self {
+            attributes: policy
+                .attributes
+                .iter()
+                .map(|(key, values)| (key.clone(), values.clone()))
+                .collect(),
+            environments: policy
+                .environments
+                .iter()
+                .map(|env| Environment::parse(env) as u8)
+                .collect(),
+            deny_pii: policy.deny_pii,
+            deny_secrets: policy.deny_secrets,
+        }
+    }
+
+    fn permits(&self, edge: &AgentFlowEdge, attributes: Option<&Value>) -> bool {
+        if (self.deny_pii && edge.has_pii()) || (self.deny_secrets && edge.has_secrets()) {
+            return false;
+        }
+        if !self.environments.is_empty() && !self.environments.contains(&edge.environment) {
+            return false;
+        }
+        self.attributes.iter().all(|(key, allowed)| {
+            attributes
+                .and_then(|attributes| attributes.get(key))
+                .is_some_and(|value| attribute_matches(value, allowed))
+        })
+    }
+}
+
+/// Whether a span attribute, or any element of a list attribute, is one
+/// of the allowed values
+fn attribute_matches(value: &Value, allowed: &[String]) -> bool {
+    match value {
+        Value::String(s) => allowed.iter().any(|a| a == s),
+        Value::Number(_) | Value::Bool(_) => allowed.contains(&value.to_string()),
+        Value::Array(values) => values.iter().any(|v| attribute_matches(v, allowed)),
+        _ => false,
+    }
+}
+
+/// Spans the caller of a request may read
+///
+/// The default filter is unrestricted.
+#[derive(Debug, Clone, Default)]
+pub struct AccessFilter {
+    /// Rules of the applicable policies; `None` when unrestricted, empty
+    /// when nothing is readable
+    rules: Option<Arc<[Arc<SpanRule>]>>,
+}
+
+impl AccessFilter {
+    pub fn is_unrestricted(&self) -> bool {
+        self.rules.is_none()
+    }
+
+    /// Whether checking a span needs its payload attributes
+    pub fn needs_attributes(&self) -> bool {
+        self.rules
+            .as_ref()
+            .is_some_and(|rules| rules.iter().any(|rule| !rule.attributes.is_empty()))
+    }
+
+    /// Check a span against its decoded payload attributes
+    pub fn permits(&self, edge: &AgentFlowEdge, attributes: Option<&Value>) -> bool {
+        match &self.rules {
+            None => true,
+            Some(rules) => rules.iter().any(|rule| rule.permits(edge, attributes)),
+        }
+    }
+
+    /// Check a span, loading its payload with `load` only if a rule needs it
+    pub fn permits_with(
+        &self,
+        edge: &AgentFlowEdge,
+        load: impl FnOnce() -> Option<Vec<u8>>,
+    ) -> bool {
+        if self.is_unrestricted() {
+            return true;
+        }
+        let attributes = if self.needs_attributes() && edge.has_payload != 0 {
+            load().and_then(|bytes| serde_json::from_slice::<Value>(&bytes).ok())
+        } else {
+            None
+        };
+        self.permits(edge, attributes.as_ref())
+    }
+
+    /// Check a span whose payload is in its project's database, or the main
+    /// one without per-project storage
+    pub fn permits_stored(&self, state: &AppState, edge: &AgentFlowEdge) -> bool {
+        self.permits_with(edge, || stored_payload(state, edge))
+    }
+
+    /// Drop the spans the caller may not read
+    pub fn retain(&self, state: &AppState, edges: &mut Vec<AgentFlowEdge>) {
+        self.retain_with(edges, |edge| stored_payload(state, edge));
+    }
+
+    /// Drop the spans the caller may not read, loading payloads with `load`
+    pub fn retain_with(
+        &self,
+        edges: &mut Vec<AgentFlowEdge>,
+        load: impl Fn(&AgentFlowEdge) -> Option<Vec<u8>>,
+    ) {
+        if !self.is_unrestricted() {
+            edges.retain(|edge| self.permits_with(edge, || load(edge)));
+        }
+    }
+}
+
+/// Payload of a span from its project's database, or the main one without
+/// per-project storage
+pub fn stored_payload(state: &AppState, edge: &AgentFlowEdge) -> Option<Vec<u8>> {
+    if edge.has_payload == 0 {
+        return None;
+    }
+    match &state.project_manager {
+        Some(pm) => pm
+            .get_or_open_project(edge.project_id)
+            .ok()
+            .and_then(|db| db.get_payload(edge.edge_id).ok())
+            .flatten(),
+        None => state.db.get_payload(edge.edge_id).ok().flatten(),
+    }
+}
+
+#[cfg(test)]
+impl AccessFilter {
+    /// Filter letting the caller read only spans of the given `team`s
+    pub(crate) fn for_teams(teams: &[&str]) -> Self {
+        let rule = SpanRule {
+            attributes: vec![(
+                "team".to_string(),
+                teams.iter().map(|team| team.to_string()).collect(),
+            )],
+            environments: Vec::new(),
+            deny_pii: false,
+            deny_secrets: false,
+        };
+        Self {
+            rules: Some(vec![Arc::new(rule)].into()),
+        }
+    }
+}
+
+/// Configured access policies
+pub struct AccessPolicies {
+    config: AccessPolicyConfig,
+    rules: Vec<Arc<SpanRule>>,
+}
+
+impl AccessPolicies {
+    pub fn new(config: AccessPolicyConfig) -> Self {
+        let rules = config
+            .policies
+            .iter()
+            .map(|policy| Arc::new(SpanRule::new(policy)))
+            .collect();
+        Self { config, rules }
+    }
+
+    /// Filter of the policies that apply to `auth`
+    pub fn filter_for(&self, auth: &AuthContext) -> AccessFilter {
+        if !self.config.enabled || auth.role >= self.config.exempt_role {
+            return AccessFilter::default();
+        }
+        let rules: Vec<Arc<SpanRule>> = self
+            .config
+            .policies
+            .iter()
+            .zip(&self.rules)
+            .filter(|(policy, _)| applies_to(policy, auth))
+            .map(|(_, rule)| rule.clone())
+            .collect();
+        if rules.is_empty() && self.config.allow_unmatched {
+            return AccessFilter::default();
+        }
+        AccessFilter {
+            rules: Some(rules.into()),
+        }
+    }
+}
+
+impl Default for AccessPolicies {
+    fn default() -> Self {
+        Self::new(AccessPolicyConfig::default())
+    }
+}
+
+fn applies_to(policy: &AccessPolicy, auth: &AuthContext) -> bool {
+    (policy.users.is_empty()
+        || auth
+            .user_id
+            .as_ref()
+            .is_some_and(|user| policy.users.contains(user)))
+        && (policy.roles.is_empty() || policy.roles.contains(&auth.role))
+        && (policy.tenants.is_empty() || policy.tenants.contains(&auth.tenant_id))
+        && (policy.projects.is_empty()
+            || auth
+                .project_id
+                .is_some_and(|project| policy.projects.contains(&project)))
+}
+
+/// Attach the caller's [`AccessFilter`] to the request
+pub async fn access_policy_middleware(
+    State(state): State<AppState>,
+    mut request: Request<Body>,
+    next: Next,
+) -> Response {
+    let filter = request
+        .extensions()
+        .get::<AuthContext>()
+        .map(|auth| state.access_policies.filter_for(auth))
+        .unwrap_or_default();
+    request.extensions_mut().insert(filter);
+    next.run(request).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::auth::Role;
+    use agentreplay_core::SpanType;
+    use serde_json::json;
+
+    fn policies() -> AccessPolicies {
+        AccessPolicies::new(
+            toml::from_str(
+                r#"
+                enabled = true
+
+                [[policies]]
+                name = "search"
+                users = ["ana"]
+                attributes = { team = ["search"] }
+                environments = ["production"]
+
+                [[policies]]
+                name = "viewers"
+                roles = ["viewer"]
+                attributes = { team = ["search", "ranking"] }
+                deny_pii = true
+                "#,
+            )
+            .unwrap(),
+        )
+    }
+
+    fn auth(user: Option<&str>, role: Role) -> AuthContext {
+        AuthContext {
+            tenant_id: 1,
+            project_id: None,
+            user_id: user.map(String::from),
+            role,
+        }
+    }
+
+    fn edge(environment: Environment) -> AgentFlowEdge {
+        let mut edge = AgentFlowEdge::new(1, 0, 1, 1, SpanType::Root, 0);
+        edge.environment = environment as u8;
+        edge.has_payload = 1;
+        edge
+    }
+
+    #[test]
+    fn test_attribute_rules() {
+        let filter = policies().filter_for(&auth(Some("ana"), Role::Member));
+        assert!(filter.needs_attributes());
+        let prod = edge(Environment::Production);
+        assert!(filter.permits(&prod, Some(&json!({"team": "search"}))));
+        assert!(filter.permits(&prod, Some(&json!({"team": ["infra", "search"]}))));
+        assert!(!filter.permits(&prod, Some(&json!({"team": "ranking"}))));
+        assert!(!filter.permits(&prod, Some(&json!({}))));
+        assert!(!filter.permits(&prod, None));
+        assert!(!filter.permits(
+            &edge(Environment::Staging),
+            Some(&json!({"team": "search"}))
+        ));
+        assert!(filter.permits_with(&prod, || Some(br#"{"team":"search"}"#.to_vec())));
+        assert!(!filter.permits_with(&prod, || None));
+    }
+
+    #[test]
+    fn test_policies_apply_by_principal() {
+        let policies = policies();
+
+        // Several applicable policies grant the union of their rules
+        let viewer = policies.filter_for(&auth(Some("ana"), Role::Viewer));
+        let mut staging = edge(Environment::Staging);
+        assert!(viewer.permits(&staging, Some(&json!({"team": "ranking"}))));
+        staging.mark_pii();
+        assert!(!viewer.permits(&staging, Some(&json!({"team": "ranking"}))));
+        assert!(viewer.permits(
+            &edge(Environment::Production),
+            Some(&json!({"team": "search"}))
+        ));
+
+        // Admins and callers no policy names are unrestricted by default
+        assert!(policies
+            .filter_for(&auth(Some("ana"), Role::Admin))
+            .is_unrestricted());
+        assert!(policies
+            .filter_for(&auth(Some("bo"), Role::Member))
+            .is_unrestricted());
+    }
+
+    #[test]
+    fn test_unmatched_callers_can_be_denied() {
+        let mut config: AccessPolicyConfig = toml::from_str(
+            r#"
+            enabled = true
+            allow_unmatched = false
+
+            [[policies]]
+            name = "ops"
+            projects = [7]
+            "#,
+        )
+        .unwrap();
+        let policies = AccessPolicies::new(config.clone());
+        let outsider = policies.filter_for(&auth(None, Role::Member));
+        assert!(!outsider.is_unrestricted());
+        assert!(!outsider.permits(&edge(Environment::Production), None));
+
+        let mut scoped = auth(None, Role::Member);
+        scoped.project_id = Some(7);
+        assert!(policies
+            .filter_for(&scoped)
+            .permits(&edge(Environment::Production), None));
+
+        config.enabled = false;
+        assert!(AccessPolicies::new(config)
+            .filter_for(&auth(None, Role::Viewer))
+            .is_unrestricted());
+    }
+}
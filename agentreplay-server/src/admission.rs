@@ -27,22 +27,25 @@
 //! 1. **Token Bucket Rate Limiter**: Smooth traffic to sustainable rate
 //! 2. **Backpressure Headers**: Signal clients to slow down (Retry-After)
 //! 3. **Adaptive Throttling**: Adjust limits based on system health
+//! 4. **Queue Admission**: Reject (429) or sample batches once the ingestion
+//!    queue passes its high watermark, instead of failing when it is full
 //!
 //! ## HTTP Response Codes
 //!
 //! - 201: Request accepted
 //! - 429: Too Many Requests (with Retry-After header)
-//! - 503: Service Unavailable (system under heavy load)
+//! - 503: Service Unavailable (system under heavy load, or ingestion paused)
 
+use crate::api::error::{ErrorCode, ProblemDetails};
 use axum::{
     body::Body,
-    http::{header, HeaderValue, Response, StatusCode},
+    http::{Response, StatusCode},
     response::IntoResponse,
 };
-use serde::Serialize;
+use serde::{Deserialize, Serialize};
 use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
 use std::sync::Arc;
-use std::sync::Mutex;
+use std::sync::{Mutex, RwLock};
 use std::time::{Duration, Instant};
 
 /// Configuration for admission control
@@ -248,6 +251,176 @@ impl AdmissionController {
     }
 }
 
+/// What to do with a batch that would push the ingestion queue past its high watermark
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum OverloadPolicy {
+    /// Reject the whole batch with 429 + Retry-After
+    #[default]
+    Reject,
+    /// Accept an evenly spaced subset of the batch that fits below the watermark
+    Sample,
+}
+
+/// Configuration for queue-depth based admission
+#[derive(Clone, Debug)]
+pub struct QueueAdmissionConfig {
+    /// Fraction of queue capacity above which new batches are rejected or sampled
+    pub high_watermark: f64,
+    /// Behaviour above the watermark
+    pub policy: OverloadPolicy,
+    /// Lower bound for the Retry-After hint
+    pub min_retry_after_ms: u64,
+    /// Upper bound for the Retry-After hint
+    pub max_retry_after_ms: u64,
+}
+
+impl Default for QueueAdmissionConfig {
+    fn default() -> Self {
+        Self {
+            high_watermark: 0.9,
+            policy: OverloadPolicy::Reject,
+            min_retry_after_ms: 1_000,
+            max_retry_after_ms: 30_000,
+        }
+    }
+}
+
+/// Outcome of a queue admission check
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum QueueDecision {
+    /// Enqueue every item of the batch
+    AdmitAll,
+    /// Enqueue only `keep` of `total` items, evenly spaced
+    Sample { keep: usize, total: usize },
+}
+
+impl QueueDecision {
+    /// Whether the item at `idx` (0-based position in the batch) should be enqueued
+    pub fn keeps(&self, idx: usize) -> bool {
+        match *self {
+            QueueDecision::AdmitAll => true,
+            QueueDecision::Sample { keep, total } => {
+                // Exactly `keep` indices in 0..total satisfy this, spread evenly
+                (idx + 1) * keep / total > idx * keep / total
+            }
+        }
+    }
+}
+
+/// Admission control based on ingestion queue depth
+///
+/// Queue depth and drain rate are supplied by the caller so the same policy
+/// works for the server's ingestion actor and the desktop app's queue.
+pub struct QueueAdmission {
+    config: RwLock<QueueAdmissionConfig>,
+    requests_rejected: AtomicU64,
+    spans_rejected: AtomicU64,
+    spans_sampled_out: AtomicU64,
+}
+
+/// Queue depth and admission counters, for metrics endpoints
+#[derive(Debug, Clone, Serialize)]
+pub struct QueueMetrics {
+    pub queue_depth: usize,
+    pub queue_capacity: usize,
+    pub utilization: f64,
+    pub high_watermark: f64,
+    pub policy: OverloadPolicy,
+    pub requests_rejected: u64,
+    pub spans_rejected: u64,
+    pub spans_sampled_out: u64,
+}
+
+impl QueueAdmission {
+    pub fn new(config: QueueAdmissionConfig) -> Self {
+        Self {
+            config: RwLock::new(config),
+            requests_rejected: AtomicU64::new(0),
+            spans_rejected: AtomicU64::new(0),
+            spans_sampled_out: AtomicU64::new(0),
+        }
+    }
+
+    /// Replace the watermark and overload policy, e.g. on config reload
+    ///
+    /// Counters are kept.
+    pub fn reconfigure(&self, config: QueueAdmissionConfig) {
+        *self.config.write().unwrap() = config;
+    }
+
+    /// Decide whether a batch of `batch_size` items may be enqueued
+    ///
+    /// `drain_rate` is the recent dequeue rate in items/sec (0 if unknown) and
+    /// is used to estimate how long until the queue is back under the watermark.
+    pub fn admit(
+        &self,
+        batch_size: usize,
+        queue_depth: usize,
+        queue_capacity: usize,
+        drain_rate: f64,
+    ) -> Result<QueueDecision, RejectionReason> {
+        let config = self.config.read().unwrap().clone();
+        let watermark = (queue_capacity as f64 * config.high_watermark) as usize;
+        let room = watermark.saturating_sub(queue_depth);
+
+        if batch_size <= room {
+            return Ok(QueueDecision::AdmitAll);
+        }
+
+        if config.policy == OverloadPolicy::Sample && room > 0 {
+            self.spans_sampled_out
+                .fetch_add((batch_size - room) as u64, Ordering::Relaxed);
+            return Ok(QueueDecision::Sample {
+                keep: room,
+                total: batch_size,
+            });
+        }
+
+        self.requests_rejected.fetch_add(1, Ordering::Relaxed);
+        self.spans_rejected
+            .fetch_add(batch_size as u64, Ordering::Relaxed);
+
+        // Time for the queue to drain enough to fit this batch
+        let excess = (queue_depth + batch_size).saturating_sub(watermark) as f64;
+        let retry_after_ms = if drain_rate > 0.0 {
+            (excess / drain_rate * 1000.0) as u64
+        } else {
+            config.min_retry_after_ms
+        };
+
+        Err(RejectionReason::QueueFull {
+            retry_after_ms: retry_after_ms
+                .clamp(config.min_retry_after_ms, config.max_retry_after_ms),
+        })
+    }
+
+    /// Current queue depth and admission counters
+    pub fn metrics(&self, queue_depth: usize, queue_capacity: usize) -> QueueMetrics {
+        let config = self.config.read().unwrap();
+        QueueMetrics {
+            queue_depth,
+            queue_capacity,
+            utilization: if queue_capacity > 0 {
+                queue_depth as f64 / queue_capacity as f64
+            } else {
+                0.0
+            },
+            high_watermark: config.high_watermark,
+            policy: config.policy,
+            requests_rejected: self.requests_rejected.load(Ordering::Relaxed),
+            spans_rejected: self.spans_rejected.load(Ordering::Relaxed),
+            spans_sampled_out: self.spans_sampled_out.load(Ordering::Relaxed),
+        }
+    }
+}
+
+impl Default for QueueAdmission {
+    fn default() -> Self {
+        Self::new(QueueAdmissionConfig::default())
+    }
+}
+
 /// Reason for request rejection
 #[derive(Debug)]
 pub enum RejectionReason {
@@ -255,60 +428,52 @@ pub enum RejectionReason {
     RateLimited { retry_after_ms: u64 },
     /// System circuit breaker is open
     CircuitOpen { retry_after_ms: u64 },
+    /// Ingestion queue is above its high watermark
+    QueueFull { retry_after_ms: u64 },
+    /// Ingestion was paused by the operator
+    Paused { retry_after_ms: u64 },
 }
 
-/// Response body for rejected requests
-#[derive(Debug, Serialize)]
-pub struct RejectionResponse {
-    pub error: String,
-    pub code: String,
-    pub retry_after_ms: u64,
+impl RejectionReason {
+    pub fn code(&self) -> ErrorCode {
+        match self {
+            RejectionReason::RateLimited { .. } => ErrorCode::RateLimited,
+            RejectionReason::CircuitOpen { .. } => ErrorCode::Unavailable,
+            RejectionReason::QueueFull { .. } => ErrorCode::QueueFull,
+            RejectionReason::Paused { .. } => ErrorCode::Unavailable,
+        }
+    }
 }
 
 impl IntoResponse for RejectionReason {
     fn into_response(self) -> Response<Body> {
-        match self {
-            RejectionReason::RateLimited { retry_after_ms } => {
-                let body = RejectionResponse {
-                    error: "Rate limit exceeded. Please slow down.".to_string(),
-                    code: "RATE_LIMITED".to_string(),
-                    retry_after_ms,
-                };
-
-                Response::builder()
-                    .status(StatusCode::TOO_MANY_REQUESTS)
-                    .header(
-                        header::RETRY_AFTER,
-                        HeaderValue::from_str(&format!("{}", retry_after_ms / 1000 + 1)).unwrap(),
-                    )
-                    .header(
-                        header::CONTENT_TYPE,
-                        HeaderValue::from_static("application/json"),
-                    )
-                    .body(Body::from(serde_json::to_string(&body).unwrap_or_default()))
-                    .unwrap()
-            }
-            RejectionReason::CircuitOpen { retry_after_ms } => {
-                let body = RejectionResponse {
-                    error: "Service temporarily unavailable due to high load.".to_string(),
-                    code: "SERVICE_UNAVAILABLE".to_string(),
-                    retry_after_ms,
-                };
-
-                Response::builder()
-                    .status(StatusCode::SERVICE_UNAVAILABLE)
-                    .header(
-                        header::RETRY_AFTER,
-                        HeaderValue::from_str(&format!("{}", retry_after_ms / 1000 + 1)).unwrap(),
-                    )
-                    .header(
-                        header::CONTENT_TYPE,
-                        HeaderValue::from_static("application/json"),
-                    )
-                    .body(Body::from(serde_json::to_string(&body).unwrap_or_default()))
-                    .unwrap()
-            }
-        }
+        let code = self.code();
+        let (status, detail, retry_after_ms) = match self {
+            RejectionReason::RateLimited { retry_after_ms } => (
+                StatusCode::TOO_MANY_REQUESTS,
+                "Rate limit exceeded. Please slow down.",
+                retry_after_ms,
+            ),
+            RejectionReason::CircuitOpen { retry_after_ms } => (
+                StatusCode::SERVICE_UNAVAILABLE,
+                "Service temporarily unavailable due to high load.",
+                retry_after_ms,
+            ),
+            RejectionReason::QueueFull { retry_after_ms } => (
+                StatusCode::TOO_MANY_REQUESTS,
+                "Ingestion queue is full. Please retry later.",
+                retry_after_ms,
+            ),
+            RejectionReason::Paused { retry_after_ms } => (
+                StatusCode::SERVICE_UNAVAILABLE,
+                "Ingestion is paused. Please retry later.",
+                retry_after_ms,
+            ),
+        };
+
+        ProblemDetails::new(status, code, detail)
+            .with_retry_after_ms(retry_after_ms)
+            .into_response()
     }
 }
 
@@ -369,4 +534,67 @@ mod tests {
         let result = controller.should_admit(1);
         assert!(matches!(result, Err(RejectionReason::CircuitOpen { .. })));
     }
+
+    #[test]
+    fn test_queue_admission_reject() {
+        let admission = QueueAdmission::default();
+
+        // 80/100 used, watermark at 90: 10 fit, 11 don't
+        assert_eq!(
+            admission.admit(10, 80, 100, 0.0).unwrap(),
+            QueueDecision::AdmitAll
+        );
+        match admission.admit(11, 80, 100, 1.0) {
+            Err(RejectionReason::QueueFull { retry_after_ms }) => assert!(retry_after_ms >= 1_000),
+            other => panic!("expected QueueFull, got {:?}", other),
+        }
+
+        let metrics = admission.metrics(80, 100);
+        assert_eq!(metrics.requests_rejected, 1);
+        assert_eq!(metrics.spans_rejected, 11);
+    }
+
+    #[test]
+    fn test_queue_admission_sample() {
+        let admission = QueueAdmission::new(QueueAdmissionConfig {
+            policy: OverloadPolicy::Sample,
+            ..Default::default()
+        });
+
+        let decision = admission.admit(40, 80, 100, 0.0).unwrap();
+        assert_eq!(
+            decision,
+            QueueDecision::Sample {
+                keep: 10,
+                total: 40
+            }
+        );
+        assert_eq!((0..40).filter(|&i| decision.keeps(i)).count(), 10);
+        assert_eq!(admission.metrics(80, 100).spans_sampled_out, 30);
+
+        // No room at all: sampling degrades to rejection
+        assert!(admission.admit(5, 95, 100, 0.0).is_err());
+    }
+
+    #[test]
+    fn test_queue_admission_reconfigure() {
+        let admission = QueueAdmission::default();
+        assert!(admission.admit(40, 80, 100, 0.0).is_err());
+
+        admission.reconfigure(QueueAdmissionConfig {
+            high_watermark: 0.5,
+            policy: OverloadPolicy::Sample,
+            ..Default::default()
+        });
+        assert_eq!(
+            admission.admit(40, 30, 100, 0.0).unwrap(),
+            QueueDecision::Sample {
+                keep: 20,
+                total: 40
+            }
+        );
+        let metrics = admission.metrics(30, 100);
+        assert_eq!(metrics.policy, OverloadPolicy::Sample);
+        assert_eq!(metrics.requests_rejected, 1);
+    }
 }
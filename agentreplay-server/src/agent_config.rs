@@ -0,0 +1,492 @@
+// Copyright 2025 AgentReplay (https://github.com/agentreplay)
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program. If not, see <https://www.gnu.org/licenses/>.
+
+//! Remote configuration for instrumented agents
+//!
+//! SDKs poll `GET /api/v1/agents/:id/config` to learn the sampling rates,
+//! redaction rules and feature flags chosen by operators, so telemetry
+//! volume can be tuned without redeploying the application.
+//!
+//! Configs belong to a tenant: every agent of a tenant gets the tenant's
+//! default config unless an admin set one for that agent specifically.
+//! Responses carry an ETag derived from the resolved
+//! config; SDKs send it back in `If-None-Match` and receive `304 Not
+//! Modified` until something changes.
+
+use parking_lot::RwLock;
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs::{self, File};
+use std::io::{BufReader, BufWriter};
+use std::path::{Path, PathBuf};
+use tracing::{info, warn};
+
+/// Agent config store file name inside the data directory
+pub const AGENT_CONFIGS_FILE: &str = "agent_configs.json";
+/// How often SDKs are asked to poll when a config does not say
+pub const DEFAULT_POLL_INTERVAL_SECS: u64 = 60;
+/// Bounds accepted for `poll_interval_secs`
+pub const MIN_POLL_INTERVAL_SECS: u64 = 5;
+pub const MAX_POLL_INTERVAL_SECS: u64 = 86_400;
+
+const DEFAULT_REDACTION_REPLACEMENT: &str = "[REDACTED]";
+
+/// Telemetry settings pushed to an agent's SDK
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct AgentConfig {
+    /// Fraction of traces the SDK records (0.0 to 1.0)
+    #[serde(default = "default_sampling_rate")]
+    pub sampling_rate: f64,
+    /// Per span type overrides of `sampling_rate`, e.g. `{"tool": 0.1}`
+    #[serde(default)]
+    pub span_sampling: BTreeMap<String, f64>,
+    /// Rules the SDK applies to payloads and attributes before export
+    #[serde(default)]
+    pub redaction: Vec<RedactionRule>,
+    /// Named SDK features switched on or off server-side
+    #[serde(default)]
+    pub features: BTreeMap<String, bool>,
+    /// Seconds between polls the SDK should use
+    #[serde(default = "default_poll_interval")]
+    pub poll_interval_secs: u64,
+}
+
+impl Default for AgentConfig {
+    fn default() -> Self {
+        Self {
+            sampling_rate: default_sampling_rate(),
+            span_sampling: BTreeMap::new(),
+            redaction: Vec::new(),
+            features: BTreeMap::new(),
+            poll_interval_secs: default_poll_interval(),
+        }
+    }
+}
+
+fn default_sampling_rate() -> f64 {
+    1.0
+}
+
+fn default_poll_interval() -> u64 {
+    DEFAULT_POLL_INTERVAL_SECS
+}
+
+/// A redaction rule evaluated by the SDK
+///
+/// `pattern` is a regex replaced in string values; `attributes` lists keys
+/// whose values are replaced wholesale. A rule may use either or both.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RedactionRule {
+    pub name: String,
+    #[serde(default)]
+    pub pattern: Option<String>,
+    #[serde(default)]
+    pub attributes: Vec<String>,
+    #[serde(default = "default_replacement")]
+    pub replacement: String,
+}
+
+fn default_replacement() -> String {
+    DEFAULT_REDACTION_REPLACEMENT.to_string()
+}
+
+/// Where a resolved config came from
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ConfigSource {
+    /// Set for this agent specifically
+    Agent,
+    /// The tenant's default
+    Default,
+}
+
+/// The config an agent receives, with its version tag
+#[derive(Debug, Clone, Serialize)]
+pub struct ResolvedAgentConfig {
+    pub agent_id: u64,
+    pub source: ConfigSource,
+    /// Opaque version, also sent as the ETag
+    pub version: String,
+    #[serde(flatten)]
+    pub config: AgentConfig,
+}
+
+impl ResolvedAgentConfig {
+    /// Quoted ETag header value
+    pub fn etag(&self) -> String {
+        format!("\"{}\"", self.version)
+    }
+
+    /// Whether an `If-None-Match` header value matches this version
+    pub fn matches(&self, if_none_match: &str) -> bool {
+        if_none_match.split(',').any(|tag| {
+            let tag = tag.trim();
+            let tag = tag.strip_prefix("W/").unwrap_or(tag);
+            tag == "*" || tag.trim_matches('"') == self.version
+        })
+    }
+}
+
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
+struct TenantConfigs {
+    #[serde(default)]
+    default: AgentConfig,
+    #[serde(default)]
+    agents: BTreeMap<u64, AgentConfig>,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct StoredConfigs {
+    #[serde(default)]
+    tenants: BTreeMap<u64, TenantConfigs>,
+}
+
+/// Stores each tenant's default and per-agent remote configs
+pub struct AgentConfigStore {
+    configs: RwLock<StoredConfigs>,
+    storage_path: PathBuf,
+}
+
+impl AgentConfigStore {
+    /// Create a store, loading configs from `storage_path`
+    pub fn new(storage_path: impl AsRef<Path>) -> Self {
+        let store = Self {
+            configs: RwLock::new(StoredConfigs::default()),
+            storage_path: storage_path.as_ref().to_path_buf(),
+        };
+
+        if let Err(e) = store.load_from_disk() {
+            warn!(
+                "Failed to load agent configs from disk: {}. Using defaults.",
+                e
+            );
+        }
+
+        store
+    }
+
+    /// The config `agent_id` of `tenant_id` should use right now
+    pub fn resolve(&self, tenant_id: u64, agent_id: u64) -> ResolvedAgentConfig {
+        let configs = self.configs.read();
+        let tenant = configs.tenants.get(&tenant_id);
+        let (source, config) = match tenant.and_then(|t| t.agents.get(&agent_id)) {
+            Some(config) => (ConfigSource::Agent, config.clone()),
+            None => (
+                ConfigSource::Default,
+                tenant.map(|t| t.default.clone()).unwrap_or_default(),
+            ),
+        };
+        drop(configs);
+
+        ResolvedAgentConfig {
+            agent_id,
+            version: config_version(tenant_id, agent_id, source, &config),
+            source,
+            config,
+        }
+    }
+
+    pub fn default_config(&self, tenant_id: u64) -> AgentConfig {
+        self.configs
+            .read()
+            .tenants
+            .get(&tenant_id)
+            .map(|t| t.default.clone())
+            .unwrap_or_default()
+    }
+
+    /// Agents of a tenant with their own config, by ID
+    pub fn overrides(&self, tenant_id: u64) -> BTreeMap<u64, AgentConfig> {
+        self.configs
+            .read()
+            .tenants
+            .get(&tenant_id)
+            .map(|t| t.agents.clone())
+            .unwrap_or_default()
+    }
+
+    /// Replace the default config used by a tenant's agents without their own
+    pub fn set_default(&self, tenant_id: u64, config: AgentConfig) -> Result<AgentConfig, String> {
+        validate(&config)?;
+        self.configs
+            .write()
+            .tenants
+            .entry(tenant_id)
+            .or_default()
+            .default = config.clone();
+        self.save_to_disk()?;
+
+        info!("Updated default agent config of tenant {}", tenant_id);
+        Ok(config)
+    }
+
+    /// Set the config for one agent, replacing the default for it
+    pub fn set(
+        &self,
+        tenant_id: u64,
+        agent_id: u64,
+        config: AgentConfig,
+    ) -> Result<ResolvedAgentConfig, String> {
+        validate(&config)?;
+        self.configs
+            .write()
+            .tenants
+            .entry(tenant_id)
+            .or_default()
+            .agents
+            .insert(agent_id, config);
+        self.save_to_disk()?;
+
+        info!(
+            "Updated remote config for agent {} of tenant {}",
+            agent_id, tenant_id
+        );
+        Ok(self.resolve(tenant_id, agent_id))
+    }
+
+    /// Drop an agent's own config; returns false if it had none
+    pub fn remove(&self, tenant_id: u64, agent_id: u64) -> Result<bool, String> {
+        let removed = self
+            .configs
+            .write()
+            .tenants
+            .get_mut(&tenant_id)
+            .and_then(|t| t.agents.remove(&agent_id))
+            .is_some();
+        if removed {
+            self.save_to_disk()?;
+        }
+        Ok(removed)
+    }
+
+    fn load_from_disk(&self) -> Result<(), String> {
+        if !self.storage_path.exists() {
+            return Ok(());
+        }
+
+        let file = File::open(&self.storage_path)
+            .map_err(|e| format!("Failed to open agent configs file: {}", e))?;
+        let stored: StoredConfigs = serde_json::from_reader(BufReader::new(file))
+            .map_err(|e| format!("Failed to parse agent configs JSON: {}", e))?;
+
+        info!(
+            "Loaded remote agent configs of {} tenants",
+            stored.tenants.len()
+        );
+        *self.configs.write() = stored;
+        Ok(())
+    }
+
+    /// Save configs to disk (write to temp file then rename)
+    fn save_to_disk(&self) -> Result<(), String> {
+        if let Some(parent) = self.storage_path.parent() {
+            fs::create_dir_all(parent)
+                .map_err(|e| format!("Failed to create agent configs directory: {}", e))?;
+        }
+
+        let temp_path = self.storage_path.with_extension("json.tmp");
+        {
+            let file = File::create(&temp_path)
+                .map_err(|e| format!("Failed to create temp agent configs file: {}", e))?;
+            serde_json::to_writer_pretty(BufWriter::new(file), &*self.configs.read())
+                .map_err(|e| format!("Failed to serialize agent configs: {}", e))?;
+        }
+
+        fs::rename(&temp_path, &self.storage_path)
+            .map_err(|e| format!("Failed to rename temp file: {}", e))?;
+
+        Ok(())
+    }
+}
+
+/// Version tag for a resolved config
+///
+/// Covers everything in the response body, so any change an SDK could
+/// observe produces a new ETag.
+fn config_version(
+    tenant_id: u64,
+    agent_id: u64,
+    source: ConfigSource,
+    config: &AgentConfig,
+) -> String {
+    let mut hasher = blake3::Hasher::new();
+    hasher.update(&tenant_id.to_le_bytes());
+    hasher.update(&agent_id.to_le_bytes());
+    hasher.update(&[source as u8]);
+    hasher.update(&serde_json::to_vec(config).unwrap_or_default());
+    hasher.finalize().to_hex()[..16].to_string()
+}
+
+fn validate(config: &AgentConfig) -> Result<(), String> {
+    let rate_ok = |rate: f64| (0.0..=1.0).contains(&rate);
+
+    if !rate_ok(config.sampling_rate) {
+        return Err("sampling_rate must be between 0.0 and 1.0".to_string());
+    }
+    for (span_type, rate) in &config.span_sampling {
+        if !rate_ok(*rate) {
+            return Err(format!(
+                "span_sampling.{} must be between 0.0 and 1.0",
+                span_type
+            ));
+        }
+    }
+
+    if !(MIN_POLL_INTERVAL_SECS..=MAX_POLL_INTERVAL_SECS).contains(&config.poll_interval_secs) {
+        return Err(format!(
+            "poll_interval_secs must be between {} and {}",
+            MIN_POLL_INTERVAL_SECS, MAX_POLL_INTERVAL_SECS
+        ));
+    }
+
+    for rule in &config.redaction {
+        if rule.name.trim().is_empty() {
+            return Err("Redaction rule name cannot be empty".to_string());
+        }
+        if rule.pattern.is_none() && rule.attributes.is_empty() {
+            return Err(format!(
+                "Redaction rule '{}' needs a pattern or attributes",
+                rule.name
+            ));
+        }
+        if let Some(pattern) = &rule.pattern {
+            Regex::new(pattern).map_err(|e| {
+                format!(
+                    "Redaction rule '{}' has an invalid pattern: {}",
+                    rule.name, e
+                )
+            })?;
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    fn sampled(rate: f64) -> AgentConfig {
+        AgentConfig {
+            sampling_rate: rate,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_agents_fall_back_to_default() {
+        let temp_dir = TempDir::new().unwrap();
+        let store = AgentConfigStore::new(temp_dir.path().join(AGENT_CONFIGS_FILE));
+
+        let resolved = store.resolve(1, 7);
+        assert_eq!(resolved.source, ConfigSource::Default);
+        assert_eq!(resolved.config, AgentConfig::default());
+
+        store.set_default(1, sampled(0.5)).unwrap();
+        store.set(1, 9, sampled(0.1)).unwrap();
+
+        assert_eq!(store.resolve(1, 7).config.sampling_rate, 0.5);
+        let own = store.resolve(1, 9);
+        assert_eq!(own.source, ConfigSource::Agent);
+        assert_eq!(own.config.sampling_rate, 0.1);
+
+        // Other tenants keep their own configs
+        assert_eq!(store.resolve(2, 7).config, AgentConfig::default());
+        assert_eq!(store.resolve(2, 9).source, ConfigSource::Default);
+        assert!(!store.remove(2, 9).unwrap());
+
+        assert!(store.remove(1, 9).unwrap());
+        assert!(!store.remove(1, 9).unwrap());
+        assert_eq!(store.resolve(1, 9).source, ConfigSource::Default);
+    }
+
+    #[test]
+    fn test_version_changes_only_with_config() {
+        let temp_dir = TempDir::new().unwrap();
+        let store = AgentConfigStore::new(temp_dir.path().join(AGENT_CONFIGS_FILE));
+
+        let first = store.resolve(1, 1);
+        assert_eq!(first.version, store.resolve(1, 1).version);
+        assert_ne!(first.version, store.resolve(1, 2).version);
+
+        store.set(1, 1, sampled(0.25)).unwrap();
+        let changed = store.resolve(1, 1);
+        assert_ne!(first.version, changed.version);
+
+        assert!(changed.matches(&changed.etag()));
+        assert!(changed.matches(&format!("W/{}, \"other\"", changed.etag())));
+        assert!(changed.matches("*"));
+        assert!(!changed.matches(&first.etag()));
+    }
+
+    #[test]
+    fn test_configs_persist_across_reload() {
+        let temp_dir = TempDir::new().unwrap();
+        let path = temp_dir.path().join(AGENT_CONFIGS_FILE);
+
+        let store = AgentConfigStore::new(&path);
+        let mut config = sampled(0.2);
+        config.features.insert("capture_prompts".to_string(), false);
+        config.redaction.push(RedactionRule {
+            name: "emails".to_string(),
+            pattern: Some(r"[\w.+-]+@[\w-]+\.\w+".to_string()),
+            attributes: Vec::new(),
+            replacement: default_replacement(),
+        });
+        let version = store.set(1, 3, config.clone()).unwrap().version;
+
+        let reloaded = AgentConfigStore::new(&path);
+        let resolved = reloaded.resolve(1, 3);
+        assert_eq!(resolved.config, config);
+        assert_eq!(resolved.version, version);
+    }
+
+    #[test]
+    fn test_rejects_invalid_configs() {
+        let store = AgentConfigStore::new(TempDir::new().unwrap().path().join("c.json"));
+
+        assert!(store.set(1, 1, sampled(1.5)).is_err());
+
+        let mut span_rate = sampled(1.0);
+        span_rate.span_sampling.insert("tool".to_string(), -0.1);
+        assert!(store.set(1, 1, span_rate).is_err());
+
+        let mut poll = sampled(1.0);
+        poll.poll_interval_secs = 1;
+        assert!(store.set_default(1, poll).is_err());
+
+        let mut bad_rule = sampled(1.0);
+        bad_rule.redaction.push(RedactionRule {
+            name: "broken".to_string(),
+            pattern: Some("(unclosed".to_string()),
+            attributes: Vec::new(),
+            replacement: default_replacement(),
+        });
+        assert!(store.set(1, 1, bad_rule).is_err());
+
+        let mut empty_rule = sampled(1.0);
+        empty_rule.redaction.push(RedactionRule {
+            name: "nothing".to_string(),
+            pattern: None,
+            attributes: Vec::new(),
+            replacement: default_replacement(),
+        });
+        assert!(store.set(1, 1, empty_rule).is_err());
+
+        assert_eq!(store.resolve(1, 1).source, ConfigSource::Default);
+    }
+}
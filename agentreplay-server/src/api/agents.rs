@@ -13,13 +13,15 @@
 // You should have received a copy of the GNU Affero General Public License
 // along with this program. If not, see <https://www.gnu.org/licenses/>.
 
+use crate::agent_config::{AgentConfig, ResolvedAgentConfig};
 use crate::agent_registry::AgentMetadata;
-use crate::api::AppState;
+use crate::api::{ApiError, AppState};
+use crate::auth::{AuthContext, Role};
 use axum::{
     extract::{Path, State},
-    http::StatusCode,
+    http::{header, HeaderMap, StatusCode},
     response::{IntoResponse, Response},
-    Json,
+    Extension, Json,
 };
 use serde::{Deserialize, Serialize};
 
@@ -239,3 +241,108 @@ pub async fn delete_agent(
         Err(e) => Err(ErrorResponse { error: e }),
     }
 }
+
+/// Default config together with the agents that override it
+#[derive(Debug, Serialize)]
+pub struct DefaultAgentConfigResponse {
+    pub config: AgentConfig,
+    /// IDs of agents with their own config
+    pub overridden_agents: Vec<u64>,
+}
+
+/// GET /api/v1/agents/:agent_id/config
+///
+/// Remote config polled by SDKs. Send the last `ETag` in `If-None-Match`
+/// to get `304 Not Modified` while the config is unchanged. Agents without
+/// their own config receive the caller's tenant's default.
+pub async fn get_agent_config(
+    State(state): State<AppState>,
+    Extension(auth): Extension<AuthContext>,
+    Path(agent_id): Path<u64>,
+    headers: HeaderMap,
+) -> Response {
+    let resolved = state.agent_configs.resolve(auth.tenant_id, agent_id);
+    let etag = resolved.etag();
+    let cache_headers = [
+        (header::ETAG, etag),
+        (header::CACHE_CONTROL, "no-cache".to_string()),
+    ];
+
+    let unchanged = headers
+        .get(header::IF_NONE_MATCH)
+        .and_then(|value| value.to_str().ok())
+        .is_some_and(|value| resolved.matches(value));
+    if unchanged {
+        return (StatusCode::NOT_MODIFIED, cache_headers).into_response();
+    }
+
+    (cache_headers, Json(resolved)).into_response()
+}
+
+/// PUT /api/v1/agents/:agent_id/config
+///
+/// Set the config for one agent; it replaces the default for that agent.
+pub async fn put_agent_config(
+    State(state): State<AppState>,
+    Extension(auth): Extension<AuthContext>,
+    Path(agent_id): Path<u64>,
+    Json(config): Json<AgentConfig>,
+) -> Result<Json<ResolvedAgentConfig>, ApiError> {
+    auth.require_role(Role::Admin)?;
+    state
+        .agent_configs
+        .set(auth.tenant_id, agent_id, config)
+        .map(Json)
+        .map_err(ApiError::BadRequest)
+}
+
+/// DELETE /api/v1/agents/:agent_id/config
+///
+/// Drop the agent's own config so it falls back to the default.
+pub async fn delete_agent_config(
+    State(state): State<AppState>,
+    Extension(auth): Extension<AuthContext>,
+    Path(agent_id): Path<u64>,
+) -> Result<StatusCode, ApiError> {
+    auth.require_role(Role::Admin)?;
+    match state.agent_configs.remove(auth.tenant_id, agent_id) {
+        Ok(true) => Ok(StatusCode::NO_CONTENT),
+        Ok(false) => Err(ApiError::NotFound(format!(
+            "Agent {} has no config of its own",
+            agent_id
+        ))),
+        Err(e) => Err(ApiError::Internal(e)),
+    }
+}
+
+/// GET /api/v1/agents/default/config
+pub async fn get_default_agent_config(
+    State(state): State<AppState>,
+    Extension(auth): Extension<AuthContext>,
+) -> Json<DefaultAgentConfigResponse> {
+    Json(DefaultAgentConfigResponse {
+        config: state.agent_configs.default_config(auth.tenant_id),
+        overridden_agents: state
+            .agent_configs
+            .overrides(auth.tenant_id)
+            .into_keys()
+            .collect(),
+    })
+}
+
+/// PUT /api/v1/agents/default/config
+///
+/// Replace the config used by every agent of the caller's tenant without
+/// its own.
+pub async fn put_default_agent_config(
+    State(state): State<AppState>,
+    Extension(auth): Extension<AuthContext>,
+    Json(config): Json<AgentConfig>,
+) -> Result<Json<AgentConfig>, ApiError> {
+    auth.require_role(Role::Admin)?;
+    state
+        .agent_configs
+        .set_default(auth.tenant_id, config)
+        .map(Json)
+        .map_err(ApiError::BadRequest)
+}
@@ -0,0 +1,328 @@
+// Copyright 2025 AgentReplay (https://github.com/agentreplay)
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program. If not, see <https://www.gnu.org/licenses/>.
+
+//! Group-by aggregation over spans
+//!
+//! `POST /api/v1/query/aggregate` groups the spans of a time range by model,
+//! agent, prompt version and/or project, optionally per time bucket, and
+//! returns count, tokens, cost, p95 latency and error rate per group. The
+//! grouping runs server-side in [`agentreplay_query::aggregation`], so
+//! dashboards no longer page through raw traces to build charts. Payloads are
+//! only read when a requested dimension or metric needs them.
+
+use std::sync::Arc;
+
+use agentreplay_core::clock::now_us;
+use agentreplay_core::{AgentFlowEdge, SpanType};
+use agentreplay_query::{
+    Agentreplay, AggregationRow, GroupByAggregator, GroupByQuery, GroupDimension, GroupMetric,
+    GroupRow,
+};
+use axum::{extract::State, Extension, Json};
+use serde::{Deserialize, Serialize};
+
+use crate::api::{ApiError, AppState};
+use crate::auth::AuthContext;
+use crate::otel_genai::{GenAIPayload, ModelPricing};
+use crate::scaling::run_query;
+use crate::session_analysis::{attr_string, ATTR_PROMPT_NAME, ATTR_PROMPT_VERSION};
+
+const DEFAULT_LOOKBACK_US: u64 = 24 * 3_600_000_000; // 24 hours
+const MAX_RANGE_US: u64 = 365 * 24 * 3_600_000_000;
+/// Bound on groups held in memory by one query
+const MAX_GROUPS: usize = 10_000;
+
+#[derive(Debug, Clone, Copy, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum TimeBucket {
+    Minute,
+    Hour,
+    Day,
+}
+
+impl TimeBucket {
+    fn width_us(&self) -> u64 {
+        match self {
+            TimeBucket::Minute => 60_000_000,
+            TimeBucket::Hour => 3_600_000_000,
+            TimeBucket::Day => 86_400_000_000,
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+pub struct AggregateRequest {
+    #[serde(default)]
+    pub group_by: Vec<GroupDimension>,
+    #[serde(default = "default_metrics")]
+    pub metrics: Vec<GroupMetric>,
+    #[serde(default)]
+    pub bucket: Option<TimeBucket>,
+    #[serde(default)]
+    pub start_ts: Option<u64>,
+    #[serde(default)]
+    pub end_ts: Option<u64>,
+    #[serde(default)]
+    pub project_id: Option<u16>,
+    #[serde(default)]
+    pub agent_id: Option<u64>,
+    /// Only spans whose model contains this string
+    #[serde(default)]
+    pub model: Option<String>,
+}
+
+fn default_metrics() -> Vec<GroupMetric> {
+    vec![GroupMetric::Count]
+}
+
+#[derive(Debug, Serialize)]
+pub struct AggregateResponse {
+    pub start_ts: u64,
+    pub end_ts: u64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub bucket: Option<TimeBucket>,
+    pub spans_scanned: usize,
+    pub groups: Vec<GroupRow>,
+}
+
+/// What each span must be resolved to for a request
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct Needs {
+    pub(crate) model: bool,
+    pub(crate) prompt_version: bool,
+    pub(crate) cost: bool,
+}
+
+impl Needs {
+    fn of(request: &AggregateRequest) -> Self {
+        Self {
+            model: request.model.is_some() || request.group_by.contains(&GroupDimension::Model),
+            prompt_version: request.group_by.contains(&GroupDimension::PromptVersion),
+            cost: request.metrics.contains(&GroupMetric::Cost),
+        }
+    }
+}
+
+/// POST /api/v1/query/aggregate
+pub async fn aggregate_query(
+    State(state): State<AppState>,
+    Extension(auth): Extension<AuthContext>,
+    Json(request): Json<AggregateRequest>,
+) -> Result<Json<AggregateResponse>, ApiError> {
+    let end_ts = request.end_ts.unwrap_or_else(now_us);
+    let start_ts = request
+        .start_ts
+        .unwrap_or(end_ts.saturating_sub(DEFAULT_LOOKBACK_US));
+    if end_ts <= start_ts {
+        return Err(ApiError::BadRequest(
+            "end_ts must be greater than start_ts".into(),
+        ));
+    }
+    if end_ts - start_ts > MAX_RANGE_US {
+        return Err(ApiError::BadRequest(
+            "time range cannot exceed 365 days".into(),
+        ));
+    }
+    if request.metrics.is_empty() {
+        return Err(ApiError::BadRequest(
+            "metrics must name at least one metric".into(),
+        ));
+    }
+    for (i, dimension) in request.group_by.iter().enumerate() {
+        if request.group_by[..i].contains(dimension) {
+            return Err(ApiError::BadRequest(format!(
+                "group_by lists '{}' more than once",
+                dimension.as_str()
+            )));
+        }
+    }
+
+    let shards = super::metrics::project_shards(&state, request.project_id)?;
+    let agent_registry = state.agent_registry.clone();
+    let tenant_id = auth.tenant_id;
+    let response = run_query(&state, move || -> Result<AggregateResponse, ApiError> {
+        let needs = Needs::of(&request);
+        let mut aggregator = GroupByAggregator::new(GroupByQuery {
+            dimensions: request.group_by.clone(),
+            metrics: request.metrics.clone(),
+            bucket_us: request.bucket.map(|b| b.width_us()),
+        });
+        let mut spans_scanned = 0;
+        for db in shards {
+            let edges = db
+                .query_temporal_range_for_tenant(start_ts, end_ts, tenant_id)
+                .map_err(|e| ApiError::Internal(e.to_string()))?;
+            for edge in &edges {
+                if request.project_id.is_some_and(|p| edge.project_id != p)
+                    || request.agent_id.is_some_and(|a| edge.agent_id != a)
+                {
+                    continue;
+                }
+                let span = resolve_span(&db, edge, needs);
+                if let Some(ref filter) = request.model {
+                    if !span
+                        .model
+                        .as_deref()
+                        .is_some_and(|m| m.contains(filter.as_str()))
+                    {
+                        continue;
+                    }
+                }
+
+                spans_scanned += 1;
+                let dimensions = request
+                    .group_by
+                    .iter()
+                    .map(|dimension| match dimension {
+                        GroupDimension::Model => {
+                            span.model.clone().unwrap_or_else(|| "unknown".to_string())
+                        }
+                        GroupDimension::Agent => agent_registry.get_display_name(edge.agent_id),
+                        GroupDimension::PromptVersion => span
+                            .prompt_version
+                            .clone()
+                            .unwrap_or_else(|| "unversioned".to_string()),
+                        GroupDimension::Project => edge.project_id.to_string(),
+                    })
+                    .collect();
+                aggregator.add(AggregationRow {
+                    timestamp_us: edge.timestamp_us,
+                    dimensions,
+                    tokens: edge.token_count as u64,
+                    cost: span.cost,
+                    latency_us: edge.duration_us as u64,
+                    is_error: edge.get_span_type() == SpanType::Error,
+                });
+                if aggregator.group_count() > MAX_GROUPS {
+                    return Err(ApiError::BadRequest(format!(
+                        "query produces more than {} groups; narrow the range, use a coarser bucket or fewer dimensions",
+                        MAX_GROUPS
+                    )));
+                }
+            }
+        }
+
+        Ok(AggregateResponse {
+            start_ts,
+            end_ts,
+            bucket: request.bucket,
+            spans_scanned,
+            groups: aggregator.finish(),
+        })
+    })
+    .await
+    .map_err(|e| ApiError::Internal(format!("Aggregate query task panicked: {}", e)))??;
+
+    Ok(Json(response))
+}
+
+/// Payload-derived attributes of a span
+#[derive(Debug, Default)]
+pub(crate) struct ResolvedSpan {
+    pub(crate) model: Option<String>,
+    pub(crate) prompt_version: Option<String>,
+    pub(crate) cost: f64,
+}
+
+/// Resolve what `needs` asks for, preferring the denormalized attrs index
+/// over a payload read
+pub(crate) fn resolve_span(
+    db: &Arc<Agentreplay>,
+    edge: &AgentFlowEdge,
+    needs: Needs,
+) -> ResolvedSpan {
+    let mut span = ResolvedSpan::default();
+    if needs.model {
+        let (_, model, _) = db.get_edge_attrs(edge.edge_id).unwrap_or_default();
+        if !model.is_empty() {
+            span.model = Some(model);
+        }
+    }
+    let needs_payload = needs.cost || needs.prompt_version || (needs.model && span.model.is_none());
+    if !needs_payload || edge.has_payload == 0 {
+        return span;
+    }
+    let Some(payload) = db
+        .get_payload(edge.edge_id)
+        .ok()
+        .flatten()
+        .and_then(|bytes| serde_json::from_slice::<GenAIPayload>(&bytes).ok())
+    else {
+        return span;
+    };
+
+    let payload_model = payload
+        .response_model
+        .clone()
+        .or_else(|| payload.request_model.clone());
+    if needs.cost {
+        if let Some(ref model) = payload_model {
+            let pricing =
+                ModelPricing::for_model(payload.system.as_deref().unwrap_or("openai"), model);
+            span.cost = payload.calculate_cost(&pricing);
+        }
+    }
+    if needs.prompt_version {
+        span.prompt_version =
+            attr_string(&payload, ATTR_PROMPT_NAME).map(|name| {
+                match attr_string(&payload, ATTR_PROMPT_VERSION) {
+                    Some(version) => format!("{}@{}", name, version),
+                    None => name,
+                }
+            });
+    }
+    if span.model.is_none() {
+        span.model = payload_model;
+    }
+    span
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_request_parsing_and_needs() {
+        let request: AggregateRequest = serde_json::from_value(serde_json::json!({
+            "group_by": ["model", "prompt_version"],
+            "metrics": ["count", "p95_latency", "error_rate"],
+            "bucket": "hour"
+        }))
+        .unwrap();
+        assert_eq!(
+            request.group_by,
+            vec![GroupDimension::Model, GroupDimension::PromptVersion]
+        );
+        assert_eq!(request.bucket.map(|b| b.width_us()), Some(3_600_000_000));
+
+        let needs = Needs::of(&request);
+        assert!(needs.model && needs.prompt_version && !needs.cost);
+
+        let request: AggregateRequest = serde_json::from_value(serde_json::json!({
+            "group_by": ["agent"]
+        }))
+        .unwrap();
+        assert_eq!(request.metrics, vec![GroupMetric::Count]);
+        let needs = Needs::of(&request);
+        assert!(!needs.model && !needs.prompt_version && !needs.cost);
+
+        assert!(
+            serde_json::from_value::<AggregateRequest>(serde_json::json!({
+                "group_by": ["region"]
+            }))
+            .is_err()
+        );
+    }
+}
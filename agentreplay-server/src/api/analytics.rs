@@ -18,14 +18,18 @@
 // Enhanced time-series analytics API endpoints
 
 use super::query::AppState;
+use crate::auth::AuthContext;
+use crate::project_manager::{FederationSummary, ProjectManager, ProjectSelection};
 use axum::{
-    extract::{Query, State},
+    extract::{Extension, Query, State},
     http::StatusCode,
     Json,
 };
 use agentreplay_core::{AgentFlowEdge, SpanType};
+use agentreplay_query::TenantScope;
 use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap};
+use std::sync::Arc;
 use tracing::debug;
 
 // Use DataPoint from core
@@ -49,6 +53,9 @@ pub struct TimeSeriesQuery {
     pub agent_id: Option<u64>,
     #[serde(default)]
     pub model: Option<String>,
+    /// Fan out across project databases: `all` or comma-separated IDs
+    #[serde(default)]
+    pub projects: Option<ProjectSelection>,
 }
 
 fn default_granularity() -> String {
@@ -61,6 +68,9 @@ pub struct TimeSeriesResponse {
     pub granularity: String,
     pub data_points: Vec<DataPoint>,
     pub summary: TimeSeriesSummary,
+    /// Projects covered when the query was federated
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub federation: Option<FederationSummary>,
 }
 
 #[derive(Debug, Serialize)]
@@ -98,6 +108,9 @@ pub struct ComparativeAnalysisQuery {
     pub start_time: u64,
     pub end_time: u64,
     pub group_by: String, // "agent", "model", "project", "environment"
+    /// Fan out across project databases: `all` or comma-separated IDs
+    #[serde(default)]
+    pub projects: Option<ProjectSelection>,
 }
 
 #[derive(Debug, Serialize)]
@@ -105,6 +118,9 @@ pub struct ComparativeAnalysisResponse {
     pub metric: String,
     pub groups: HashMap<String, GroupMetrics>,
     pub total: f64,
+    /// Projects covered when the query was federated
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub federation: Option<FederationSummary>,
 }
 
 #[derive(Debug, Serialize)]
@@ -202,6 +218,75 @@ fn calculate_summary(data_points: &[DataPoint]) -> TimeSeriesSummary {
     }
 }
 
+/// Resolve the project manager for a request that selected projects
+fn federation_target(
+    state: &AppState,
+    projects: Option<ProjectSelection>,
+) -> Result<Option<(Arc<ProjectManager>, ProjectSelection)>, (StatusCode, String)> {
+    let Some(selection) = projects else {
+        return Ok(None);
+    };
+    let pm = state.project_manager.clone().ok_or((
+        StatusCode::BAD_REQUEST,
+        "Federated queries require per-project storage".to_string(),
+    ))?;
+    Ok(Some((pm, selection)))
+}
+
+/// Combine per-project time buckets into one series
+///
+/// Bucket values are averages, so they are re-weighted by sample count;
+/// buckets line up because every project used the same range and interval.
+fn merge_data_points(parts: impl IntoIterator<Item = Vec<DataPoint>>) -> Vec<DataPoint> {
+    let mut buckets: BTreeMap<u64, (f64, usize)> = BTreeMap::new();
+    for points in parts {
+        for point in points {
+            let bucket = buckets.entry(point.timestamp).or_insert((0.0, 0));
+            bucket.0 += point.value * point.count as f64;
+            bucket.1 += point.count;
+        }
+    }
+
+    buckets
+        .into_iter()
+        .map(|(timestamp, (weighted, count))| DataPoint {
+            timestamp,
+            value: weighted / count.max(1) as f64,
+            count,
+        })
+        .collect()
+}
+
+/// Combine per-project grouped metrics
+///
+/// Totals and counts add up across projects; every other metric is an
+/// average or rate and is re-weighted by each group's edge count.
+fn merge_grouped_metrics(
+    metric: &str,
+    parts: impl IntoIterator<Item = HashMap<String, (f64, usize)>>,
+) -> HashMap<String, (f64, usize)> {
+    let additive = matches!(metric, "total_tokens" | "count" | "trace_count");
+    let mut merged: HashMap<String, (f64, usize)> = HashMap::new();
+
+    for groups in parts {
+        for (key, (value, count)) in groups {
+            let entry = merged.entry(key).or_insert((0.0, 0));
+            let weight = if additive { 1.0 } else { count as f64 };
+            entry.0 += value * weight;
+            entry.1 += count;
+        }
+    }
+
+    if !additive {
+        for (value, count) in merged.values_mut() {
+            if *count > 0 {
+                *value /= *count as f64;
+            }
+        }
+    }
+    merged
+}
+
 fn calculate_correlation(x: &[f64], y: &[f64]) -> f64 {
     if x.len() != y.len() || x.is_empty() {
         return 0.0;
@@ -258,6 +343,9 @@ fn describe_correlation(coefficient: f64) -> String {
 
 /// GET /api/v1/analytics/timeseries
 /// Get time-series data for a metric
+///
+/// With `projects`, the series is computed in each selected project
+/// database and the buckets are merged into one org-wide series.
 pub async fn get_timeseries(
     State(state): State<AppState>,
     Query(params): Query<TimeSeriesQuery>,
@@ -265,18 +353,49 @@ pub async fn get_timeseries(
     let interval = calculate_granularity_interval(&params.granularity);
 
     // Get data points
-    let data_points = state
-        .db
-        .get_timeseries_data(
-            &params.metric,
-            params.start_time,
-            params.end_time,
-            interval,
-            params.project_id,
-            params.agent_id,
-            params.model.as_deref(),
-        )
-        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+    let (data_points, federation) = match federation_target(&state, params.projects.clone())? {
+        Some((pm, selection)) => {
+            let metric = params.metric.clone();
+            let model = params.model.clone();
+            let (start_time, end_time) = (params.start_time, params.end_time);
+            let (project_id, agent_id) = (params.project_id, params.agent_id);
+
+            let federated = tokio::task::spawn_blocking(move || {
+                pm.federate(&selection, |db| {
+                    db.get_timeseries_data(
+                        &metric,
+                        start_time,
+                        end_time,
+                        interval,
+                        project_id,
+                        agent_id,
+                        model.as_deref(),
+                    )
+                })
+            })
+            .await
+            .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?
+            .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+
+            let points = merge_data_points(federated.results.into_iter().map(|(_, p)| p));
+            (points, Some(federated.summary))
+        }
+        None => {
+            let points = state
+                .db
+                .get_timeseries_data(
+                    &params.metric,
+                    params.start_time,
+                    params.end_time,
+                    interval,
+                    params.project_id,
+                    params.agent_id,
+                    params.model.as_deref(),
+                )
+                .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+            (points, None)
+        }
+    };
 
     let summary = calculate_summary(&data_points);
 
@@ -285,6 +404,7 @@ pub async fn get_timeseries(
         granularity: params.granularity,
         data_points,
         summary,
+        federation,
     }))
 }
 
@@ -357,19 +477,47 @@ pub async fn get_trend_analysis(
 
 /// GET /api/v1/analytics/comparative
 /// Get comparative analysis across groups
+///
+/// With `projects`, groups from every selected project are combined, so
+/// `group_by=project` compares projects side by side.
 pub async fn get_comparative_analysis(
     State(state): State<AppState>,
     Query(params): Query<ComparativeAnalysisQuery>,
 ) -> Result<Json<ComparativeAnalysisResponse>, (StatusCode, String)> {
-    let group_data = state
-        .db
-        .get_grouped_metrics(
-            &params.metric,
-            params.start_time,
-            params.end_time,
-            &params.group_by,
-        )
-        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+    let (group_data, federation) = match federation_target(&state, params.projects.clone())? {
+        Some((pm, selection)) => {
+            let metric = params.metric.clone();
+            let group_by = params.group_by.clone();
+            let (start_time, end_time) = (params.start_time, params.end_time);
+
+            let federated = tokio::task::spawn_blocking(move || {
+                pm.federate(&selection, |db| {
+                    db.get_grouped_metrics(&metric, start_time, end_time, &group_by)
+                })
+            })
+            .await
+            .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?
+            .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+
+            let groups = merge_grouped_metrics(
+                &params.metric,
+                federated.results.into_iter().map(|(_, groups)| groups),
+            );
+            (groups, Some(federated.summary))
+        }
+        None => {
+            let groups = state
+                .db
+                .get_grouped_metrics(
+                    &params.metric,
+                    params.start_time,
+                    params.end_time,
+                    &params.group_by,
+                )
+                .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+            (groups, None)
+        }
+    };
 
     let total: f64 = group_data.values().map(|(v, _)| v).sum();
 
@@ -396,6 +544,7 @@ pub async fn get_comparative_analysis(
         metric: params.metric,
         groups,
         total,
+        federation,
     }))
 }
 
@@ -458,6 +607,53 @@ mod tests {
         assert_eq!(calculate_granularity_interval("hour"), 3_600_000_000);
         assert_eq!(calculate_granularity_interval("day"), 86_400_000_000);
     }
+
+    #[test]
+    fn test_merge_data_points_weights_by_count() {
+        let point = |timestamp, value, count| DataPoint {
+            timestamp,
+            value,
+            count,
+        };
+        let merged = merge_data_points(vec![
+            vec![point(0, 100.0, 1), point(10, 0.0, 0)],
+            vec![point(0, 40.0, 3), point(10, 20.0, 2)],
+        ]);
+
+        assert_eq!(merged.len(), 2);
+        assert_eq!(merged[0].timestamp, 0);
+        assert_eq!(merged[0].count, 4);
+        assert!((merged[0].value - 55.0).abs() < 1e-9);
+        assert_eq!(merged[1].count, 2);
+        assert!((merged[1].value - 20.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_merge_grouped_metrics() {
+        let groups = |entries: &[(&str, f64, usize)]| -> HashMap<String, (f64, usize)> {
+            entries
+                .iter()
+                .map(|(k, v, c)| (k.to_string(), (*v, *c)))
+                .collect()
+        };
+
+        let totals = merge_grouped_metrics(
+            "total_tokens",
+            vec![
+                groups(&[("a", 100.0, 2)]),
+                groups(&[("a", 50.0, 1), ("b", 5.0, 1)]),
+            ],
+        );
+        assert_eq!(totals["a"], (150.0, 3));
+        assert_eq!(totals["b"], (5.0, 1));
+
+        let latency = merge_grouped_metrics(
+            "avg_latency",
+            vec![groups(&[("a", 10.0, 1)]), groups(&[("a", 30.0, 3)])],
+        );
+        assert_eq!(latency["a"].1, 4);
+        assert!((latency["a"].0 - 25.0).abs() < 1e-9);
+    }
 }
 
 // ============================================================================
@@ -494,6 +690,7 @@ pub struct LatencyBreakdown {
 /// "Why is it slow? Which components dominate latency?"
 pub async fn get_latency_breakdown(
     State(state): State<AppState>,
+    Extension(auth): Extension<AuthContext>,
     Query(params): Query<LatencyBreakdownQuery>,
 ) -> Result<Json<LatencyBreakdown>, (StatusCode, String)> {
     debug!(
@@ -501,12 +698,11 @@ pub async fn get_latency_breakdown(
         params.session_id
     );
 
-    // Query all spans - use query_temporal_range (correct API method)
-    let start_time = 0u64;
-    let end_time = u64::MAX;
+    // Query all of the tenant's spans
     let edges = state
         .db
-        .query_temporal_range(start_time, end_time)
+        .scoped(TenantScope::Tenant(auth.tenant_id))
+        .range(0, u64::MAX)
         .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
 
     // Filter by session
@@ -581,15 +777,21 @@ pub async fn get_latency_breakdown(
 }
 
 /// Cost breakdown response
+///
+/// `total_cost` and `cost` are in `currency`, including project markup and
+/// tax; `*_usd` fields hold the raw cost.
 #[derive(Debug, Serialize)]
 pub struct CostBreakdown {
+    pub total_cost: f64,
     pub total_cost_usd: f64,
+    pub currency: String,
     pub by_model: HashMap<String, ModelCost>,
     pub token_usage: TokenUsageSummary,
 }
 
 #[derive(Debug, Serialize)]
 pub struct ModelCost {
+    pub cost: f64,
     pub cost_usd: f64,
     pub call_count: u32,
     pub input_tokens: u32,
@@ -607,6 +809,9 @@ pub struct TokenUsageSummary {
 #[derive(Debug, Deserialize)]
 pub struct CostBreakdownQuery {
     pub session_id: u64,
+    /// Report in this currency instead of the tenant's display currency
+    #[serde(default)]
+    pub currency: Option<String>,
 }
 
 /// GET /api/v1/analytics/cost-breakdown
@@ -615,13 +820,19 @@ pub struct CostBreakdownQuery {
 /// "How much did it cost? Which models are expensive?"
 pub async fn get_cost_breakdown(
     State(state): State<AppState>,
+    Extension(auth): Extension<AuthContext>,
     Query(params): Query<CostBreakdownQuery>,
 ) -> Result<Json<CostBreakdown>, (StatusCode, String)> {
     debug!("Getting cost breakdown for session {}", params.session_id);
+    let converter = state
+        .billing
+        .converter(auth.tenant_id, params.currency.as_deref())
+        .map_err(|e| (StatusCode::BAD_REQUEST, e))?;
 
     let edges = state
         .db
-        .query_temporal_range(0, u64::MAX)
+        .scoped(TenantScope::Tenant(auth.tenant_id))
+        .range(0, u64::MAX)
         .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
 
     let session_spans: Vec<AgentFlowEdge> = edges
@@ -630,6 +841,7 @@ pub async fn get_cost_breakdown(
         .collect();
 
     let mut total_cost = 0.0;
+    let mut total_cost_usd = 0.0;
     let mut by_model: HashMap<String, ModelCost> = HashMap::new();
     let mut token_summary = TokenUsageSummary {
         total_input_tokens: 0,
@@ -652,21 +864,27 @@ pub async fn get_cost_breakdown(
                     .clone()
                     .unwrap_or_else(|| "unknown".to_string());
 
-                // Calculate cost
+                // Calculate cost, preferring a stored one
                 let pricing = ModelPricing::for_model(&system, &model);
-                let cost = genai.calculate_cost(&pricing);
+                let cost_usd = genai
+                    .stored_cost()
+                    .unwrap_or_else(|| genai.calculate_cost(&pricing));
+                let cost = converter.convert(cost_usd, span.project_id, span.timestamp_us);
 
                 total_cost += cost;
+                total_cost_usd += cost_usd;
 
                 // Track by model
                 let model_cost = by_model.entry(model.clone()).or_insert(ModelCost {
+                    cost: 0.0,
                     cost_usd: 0.0,
                     call_count: 0,
                     input_tokens: 0,
                     output_tokens: 0,
                 });
 
-                model_cost.cost_usd += cost;
+                model_cost.cost += cost;
+                model_cost.cost_usd += cost_usd;
                 model_cost.call_count += 1;
                 model_cost.input_tokens += genai.input_tokens.unwrap_or(0);
                 model_cost.output_tokens += genai.output_tokens.unwrap_or(0);
@@ -680,7 +898,9 @@ pub async fn get_cost_breakdown(
     }
 
     Ok(Json(CostBreakdown {
-        total_cost_usd: total_cost,
+        total_cost,
+        total_cost_usd,
+        currency: converter.currency().to_string(),
         by_model,
         token_usage: token_summary,
     }))
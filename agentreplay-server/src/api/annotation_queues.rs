@@ -0,0 +1,1058 @@
+// Copyright 2025 AgentReplay (https://github.com/agentreplay)
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program. If not, see <https://www.gnu.org/licenses/>.
+
+//! Annotation queues for human review
+//!
+//! A queue is populated once, at creation, from a trace filter - typically
+//! "root traces whose `faithfulness` score is below 0.5 this week". Each
+//! item is assigned round-robin to `reviews_per_item` of the queue's
+//! reviewers, who pull items one at a time from
+//! `GET /api/v1/annotation-queues/:id/next` and answer the queue's rubric.
+//! When items get more than one review, `GET .../agreement` reports
+//! inter-annotator agreement per rubric dimension using
+//! [`InterRaterReliability`].
+
+use std::collections::{BTreeMap, HashMap, HashSet};
+
+use agentreplay_core::clock::now_us;
+use agentreplay_core::AgentFlowEdge;
+use agentreplay_evals::{InterRaterReliability, KappaResult, WeightedKappaResult};
+use agentreplay_storage::{
+    AnnotationLabel, AnnotationQueueItem, AnnotationQueueRecord, LabelValue, RubricDimension,
+    RubricScale,
+};
+use axum::{
+    extract::{Path, Query, State},
+    http::StatusCode,
+    Extension, Json,
+};
+use serde::{Deserialize, Serialize};
+use tracing::info;
+
+use super::evals::EvalMetricOutput;
+use super::feedback::{find_trace, parse_trace_id};
+use crate::api::{ApiError, AppState};
+use crate::auth::AuthContext;
+use crate::scaling::run_query;
+
+const DEFAULT_LOOKBACK_US: u64 = 7 * 24 * 3_600_000_000; // 7 days
+const DEFAULT_QUEUE_SIZE: usize = 100;
+const MAX_QUEUE_SIZE: usize = 1000;
+const MAX_REVIEWERS: usize = 100;
+const MAX_RUBRIC_DIMENSIONS: usize = 20;
+const MAX_NAME_LEN: usize = 128;
+const MAX_COMMENT_LEN: usize = 4000;
+
+/// Which traces a queue is populated with
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct QueueFilter {
+    pub start_ts: Option<u64>,
+    pub end_ts: Option<u64>,
+    /// Restrict to one project (per-project storage only)
+    pub project_id: Option<u16>,
+    /// Only traces scored by this eval metric; lowest scores are queued first
+    pub metric: Option<String>,
+    pub min_score: Option<f64>,
+    pub max_score: Option<f64>,
+    /// Maximum number of queued traces (default 100, at most 1000)
+    pub limit: Option<usize>,
+}
+
+/// Request body for `POST /api/v1/annotation-queues`
+#[derive(Debug, Deserialize)]
+pub struct CreateQueueRequest {
+    pub name: String,
+    #[serde(default)]
+    pub description: Option<String>,
+    pub rubric: Vec<RubricDimension>,
+    pub reviewers: Vec<String>,
+    /// Reviewers labeling each item (default 1)
+    #[serde(default)]
+    pub reviews_per_item: Option<u32>,
+    #[serde(default)]
+    pub filter: QueueFilter,
+}
+
+/// Request body for `PUT /api/v1/annotation-queues/:queue_id/reviewers`
+#[derive(Debug, Deserialize)]
+pub struct UpdateReviewersRequest {
+    pub reviewers: Vec<String>,
+    /// Defaults to the queue's current setting
+    #[serde(default)]
+    pub reviews_per_item: Option<u32>,
+}
+
+/// Request body for `POST .../items/:edge_id/labels`
+#[derive(Debug, Deserialize)]
+pub struct SubmitLabelRequest {
+    /// Defaults to the authenticated user
+    #[serde(default)]
+    pub reviewer: Option<String>,
+    /// Rubric dimension → answer; every dimension must be answered
+    pub labels: BTreeMap<String, LabelValue>,
+    #[serde(default)]
+    pub comment: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct NextItemParams {
+    /// Defaults to the authenticated user
+    pub reviewer: Option<String>,
+}
+
+/// Labeling progress of one reviewer
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct ReviewerProgress {
+    pub reviewer: String,
+    pub assigned: usize,
+    pub completed: usize,
+}
+
+/// Labeling progress of a queue
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct QueueProgress {
+    pub total_items: usize,
+    /// Items labeled by all of their assignees
+    pub completed_items: usize,
+    pub labels: usize,
+    pub reviewers: Vec<ReviewerProgress>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct QueueResponse {
+    #[serde(flatten)]
+    pub queue: AnnotationQueueRecord,
+    pub progress: QueueProgress,
+}
+
+#[derive(Debug, Serialize)]
+pub struct QueueListResponse {
+    pub queues: Vec<QueueResponse>,
+}
+
+/// An item presented for review, with the trace it points at
+#[derive(Debug, Serialize)]
+pub struct ReviewItem {
+    pub edge_id: String,
+    pub project_id: u16,
+    pub edge: AgentFlowEdge,
+    pub payload: Option<serde_json::Value>,
+    pub eval_metrics: Vec<EvalMetricOutput>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct NextItemResponse {
+    pub queue_id: String,
+    pub reviewer: String,
+    /// Unlabeled items assigned to the reviewer, including this one
+    pub remaining: usize,
+    pub rubric: Vec<RubricDimension>,
+    /// `None` once the reviewer has labeled everything assigned to them
+    pub item: Option<ReviewItem>,
+}
+
+/// Inter-annotator agreement on one rubric dimension
+///
+/// Kappas are computed over every pair of reviewers who labeled the same
+/// item, pooled across reviewer pairs.
+#[derive(Debug, Serialize)]
+pub struct DimensionAgreement {
+    pub dimension: String,
+    /// Items labeled by at least two reviewers
+    pub items_compared: usize,
+    pub pairs: usize,
+    /// Fraction of pairs giving the same answer
+    pub percent_agreement: f64,
+    /// Categorical dimensions
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub cohens_kappa: Option<KappaResult>,
+    /// Categorical dimensions with at least three reviews per item, over
+    /// items labeled by exactly `reviews_per_item` reviewers
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub fleiss_kappa: Option<f64>,
+    /// Ordinal dimensions
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub weighted_kappa: Option<WeightedKappaResult>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct AgreementResponse {
+    pub queue_id: String,
+    pub dimensions: Vec<DimensionAgreement>,
+}
+
+fn validate_name(what: &str, name: &str) -> Result<(), ApiError> {
+    if name.trim().is_empty() || name.len() > MAX_NAME_LEN {
+        return Err(ApiError::BadRequest(format!(
+            "{} must be 1-{} characters",
+            what, MAX_NAME_LEN
+        )));
+    }
+    Ok(())
+}
+
+fn validate_rubric(rubric: &[RubricDimension]) -> Result<(), ApiError> {
+    if rubric.is_empty() || rubric.len() > MAX_RUBRIC_DIMENSIONS {
+        return Err(ApiError::BadRequest(format!(
+            "rubric must have 1-{} dimensions",
+            MAX_RUBRIC_DIMENSIONS
+        )));
+    }
+    let mut names = HashSet::new();
+    for dimension in rubric {
+        validate_name("rubric dimension name", &dimension.name)?;
+        if !names.insert(dimension.name.as_str()) {
+            return Err(ApiError::BadRequest(format!(
+                "Duplicate rubric dimension '{}'",
+                dimension.name
+            )));
+        }
+        match &dimension.scale {
+            RubricScale::Categorical { labels } => {
+                let unique: HashSet<&String> = labels.iter().collect();
+                if labels.len() < 2 || unique.len() != labels.len() {
+                    return Err(ApiError::BadRequest(format!(
+                        "Dimension '{}' needs at least two distinct labels",
+                        dimension.name
+                    )));
+                }
+            }
+            RubricScale::Ordinal { min, max } => {
+                if min >= max {
+                    return Err(ApiError::BadRequest(format!(
+                        "Dimension '{}' needs min < max",
+                        dimension.name
+                    )));
+                }
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Validates a reviewer list, returning the effective reviews per item
+fn validate_reviewers(reviewers: &[String], reviews_per_item: u32) -> Result<u32, ApiError> {
+    if reviewers.is_empty() || reviewers.len() > MAX_REVIEWERS {
+        return Err(ApiError::BadRequest(format!(
+            "reviewers must list 1-{} reviewers",
+            MAX_REVIEWERS
+        )));
+    }
+    let mut seen = HashSet::new();
+    for reviewer in reviewers {
+        validate_name("reviewer", reviewer)?;
+        if !seen.insert(reviewer.as_str()) {
+            return Err(ApiError::BadRequest(format!(
+                "Duplicate reviewer '{}'",
+                reviewer
+            )));
+        }
+    }
+    if reviews_per_item == 0 || reviews_per_item as usize > reviewers.len() {
+        return Err(ApiError::BadRequest(format!(
+            "reviews_per_item must be between 1 and the number of reviewers ({})",
+            reviewers.len()
+        )));
+    }
+    Ok(reviews_per_item)
+}
+
+/// Checks that labels answer every rubric dimension on its scale
+fn validate_labels(
+    rubric: &[RubricDimension],
+    labels: &BTreeMap<String, LabelValue>,
+) -> Result<(), ApiError> {
+    for name in labels.keys() {
+        if !rubric.iter().any(|d| &d.name == name) {
+            return Err(ApiError::BadRequest(format!(
+                "Unknown rubric dimension '{}'",
+                name
+            )));
+        }
+    }
+    for dimension in rubric {
+        let value = labels.get(&dimension.name).ok_or_else(|| {
+            ApiError::BadRequest(format!("Missing label for '{}'", dimension.name))
+        })?;
+        let valid = match (&dimension.scale, value) {
+            (RubricScale::Categorical { labels }, LabelValue::Label(label)) => {
+                labels.contains(label)
+            }
+            (RubricScale::Ordinal { min, max }, LabelValue::Score(score)) => {
+                (*min..=*max).contains(score)
+            }
+            _ => false,
+        };
+        if !valid {
+            let expected = match &dimension.scale {
+                RubricScale::Categorical { labels } => format!("one of {:?}", labels),
+                RubricScale::Ordinal { min, max } => format!("an integer in [{}, {}]", min, max),
+            };
+            return Err(ApiError::BadRequest(format!(
+                "Label for '{}' must be {}",
+                dimension.name, expected
+            )));
+        }
+    }
+    Ok(())
+}
+
+/// Reviewers of the `index`th item, rotating through the reviewer list
+fn assign(index: usize, reviewers: &[String], reviews_per_item: u32) -> Vec<String> {
+    let per_item = reviews_per_item as usize;
+    (0..per_item.min(reviewers.len()))
+        .map(|k| reviewers[(index * per_item + k) % reviewers.len()].clone())
+        .collect()
+}
+
+/// Reassigns items to a new reviewer list
+///
+/// Reviewers who already labeled an item stay assigned to it; remaining
+/// slots are filled from the rotation.
+fn reassign(
+    items: &mut [AnnotationQueueItem],
+    labels: &[AnnotationLabel],
+    reviewers: &[String],
+    reviews_per_item: u32,
+) {
+    let labeled = labeled_by(labels);
+    for (index, item) in items.iter_mut().enumerate() {
+        let mut assignees: Vec<String> = item
+            .assignees
+            .iter()
+            .filter(|r| labeled.contains(&(item.edge_id, r.as_str())))
+            .cloned()
+            .collect();
+        for reviewer in assign(index, reviewers, reviews_per_item) {
+            if assignees.len() >= reviews_per_item as usize {
+                break;
+            }
+            if !assignees.contains(&reviewer) {
+                assignees.push(reviewer);
+            }
+        }
+        // Rotation collisions with already-labeled reviewers leave gaps
+        for reviewer in reviewers {
+            if assignees.len() >= reviews_per_item as usize {
+                break;
+            }
+            if !assignees.contains(reviewer) {
+                assignees.push(reviewer.clone());
+            }
+        }
+        item.assignees = assignees;
+    }
+}
+
+fn labeled_by(labels: &[AnnotationLabel]) -> HashSet<(u128, &str)> {
+    labels
+        .iter()
+        .map(|l| (l.edge_id, l.reviewer.as_str()))
+        .collect()
+}
+
+fn progress(
+    queue: &AnnotationQueueRecord,
+    items: &[AnnotationQueueItem],
+    labels: &[AnnotationLabel],
+) -> QueueProgress {
+    let labeled = labeled_by(labels);
+    let mut reviewers: Vec<ReviewerProgress> = queue
+        .reviewers
+        .iter()
+        .map(|reviewer| ReviewerProgress {
+            reviewer: reviewer.clone(),
+            assigned: 0,
+            completed: 0,
+        })
+        .collect();
+    let mut completed_items = 0;
+    for item in items {
+        let mut complete = true;
+        for assignee in &item.assignees {
+            let done = labeled.contains(&(item.edge_id, assignee.as_str()));
+            complete &= done;
+            if let Some(p) = reviewers.iter_mut().find(|p| &p.reviewer == assignee) {
+                p.assigned += 1;
+                p.completed += done as usize;
+            }
+        }
+        completed_items += complete as usize;
+    }
+    QueueProgress {
+        total_items: items.len(),
+        completed_items,
+        labels: labels.len(),
+        reviewers,
+    }
+}
+
+/// Root traces matching a queue filter, in review order
+fn select_traces(
+    shards: Vec<std::sync::Arc<agentreplay_query::Agentreplay>>,
+    tenant_id: u64,
+    filter: &QueueFilter,
+    start_ts: u64,
+    end_ts: u64,
+) -> Result<Vec<AgentFlowEdge>, ApiError> {
+    let limit = filter
+        .limit
+        .unwrap_or(DEFAULT_QUEUE_SIZE)
+        .min(MAX_QUEUE_SIZE);
+    // (sort key, edge): score ascending with a metric, newest first without
+    let mut candidates: Vec<(f64, AgentFlowEdge)> = Vec::new();
+    for db in shards {
+        let roots: Vec<AgentFlowEdge> = db
+            .query_temporal_range_for_tenant(start_ts, end_ts, tenant_id)
+            .map_err(|e| ApiError::Internal(e.to_string()))?
+            .into_iter()
+            .filter(|edge| edge.causal_parent == 0)
+            .collect();
+        let Some(metric) = filter.metric.as_deref() else {
+            candidates.extend(
+                roots
+                    .into_iter()
+                    .map(|edge| (-(edge.timestamp_us as f64), edge)),
+            );
+            continue;
+        };
+        let ids: Vec<u128> = roots.iter().map(|edge| edge.edge_id).collect();
+        let metrics = db
+            .get_eval_metrics_batch(&ids)
+            .map_err(|e| ApiError::Internal(e.to_string()))?;
+        for edge in roots {
+            // The most recent score of the metric counts
+            let score = metrics
+                .get(&edge.edge_id)
+                .into_iter()
+                .flatten()
+                .filter(|m| m.get_metric_name() == metric)
+                .max_by_key(|m| m.timestamp_us)
+                .map(|m| m.metric_value);
+            let Some(score) = score else {
+                continue;
+            };
+            if filter.min_score.is_some_and(|min| score < min)
+                || filter.max_score.is_some_and(|max| score > max)
+            {
+                continue;
+            }
+            candidates.push((score, edge));
+        }
+    }
+    candidates.sort_by(|a, b| a.0.total_cmp(&b.0));
+    candidates.truncate(limit);
+    Ok(candidates.into_iter().map(|(_, edge)| edge).collect())
+}
+
+fn load_queue(
+    state: &AppState,
+    tenant_id: u64,
+    queue_id: &str,
+) -> Result<AnnotationQueueRecord, ApiError> {
+    state
+        .db
+        .get_annotation_queue(tenant_id, queue_id)
+        .map_err(|e| ApiError::Internal(e.to_string()))?
+        .ok_or_else(|| ApiError::NotFound(format!("Annotation queue {} not found", queue_id)))
+}
+
+fn load_items_and_labels(
+    state: &AppState,
+    queue_id: &str,
+) -> Result<(Vec<AnnotationQueueItem>, Vec<AnnotationLabel>), ApiError> {
+    let items = state
+        .db
+        .list_annotation_items(queue_id)
+        .map_err(|e| ApiError::Internal(e.to_string()))?;
+    let labels = state
+        .db
+        .list_annotation_labels(queue_id)
+        .map_err(|e| ApiError::Internal(e.to_string()))?;
+    Ok((items, labels))
+}
+
+fn queue_response(
+    state: &AppState,
+    queue: AnnotationQueueRecord,
+) -> Result<QueueResponse, ApiError> {
+    let (items, labels) = load_items_and_labels(state, &queue.id)?;
+    let progress = progress(&queue, &items, &labels);
+    Ok(QueueResponse { queue, progress })
+}
+
+fn resolve_reviewer(requested: Option<String>, auth: &AuthContext) -> Result<String, ApiError> {
+    requested.or_else(|| auth.user_id.clone()).ok_or_else(|| {
+        ApiError::BadRequest("reviewer is required without an authenticated user".into())
+    })
+}
+
+/// POST /api/v1/annotation-queues - Create a queue from a trace filter
+pub async fn create_annotation_queue(
+    State(state): State<AppState>,
+    Extension(auth): Extension<AuthContext>,
+    Json(req): Json<CreateQueueRequest>,
+) -> Result<(StatusCode, Json<QueueResponse>), ApiError> {
+    validate_name("name", &req.name)?;
+    validate_rubric(&req.rubric)?;
+    let reviews_per_item = validate_reviewers(&req.reviewers, req.reviews_per_item.unwrap_or(1))?;
+    if req.filter.limit == Some(0) {
+        return Err(ApiError::BadRequest("filter.limit must be positive".into()));
+    }
+
+    let created_at = now_us();
+    let end_ts = req.filter.end_ts.unwrap_or(created_at);
+    let start_ts = req
+        .filter
+        .start_ts
+        .unwrap_or(end_ts.saturating_sub(DEFAULT_LOOKBACK_US));
+    if end_ts <= start_ts {
+        return Err(ApiError::BadRequest(
+            "filter.end_ts must be greater than filter.start_ts".into(),
+        ));
+    }
+
+    let shards = super::metrics::project_shards(&state, req.filter.project_id)?;
+    let tenant_id = auth.tenant_id;
+    let filter = req.filter.clone();
+    let traces = run_query(&state, move || {
+        select_traces(shards, tenant_id, &filter, start_ts, end_ts)
+    })
+    .await
+    .map_err(|e| ApiError::Internal(format!("Queue population task panicked: {}", e)))??;
+
+    let queue = AnnotationQueueRecord {
+        id: uuid::Uuid::new_v4().simple().to_string(),
+        tenant_id,
+        name: req.name,
+        description: req.description,
+        rubric: req.rubric,
+        reviewers: req.reviewers,
+        reviews_per_item,
+        filter: serde_json::to_value(&req.filter).unwrap_or_default(),
+        created_at,
+    };
+    for (index, edge) in traces.iter().enumerate() {
+        let item = AnnotationQueueItem {
+            queue_id: queue.id.clone(),
+            edge_id: edge.edge_id,
+            project_id: edge.project_id,
+            assignees: assign(index, &queue.reviewers, reviews_per_item),
+            added_at: created_at,
+        };
+        state
+            .db
+            .put_annotation_item(&item)
+            .map_err(|e| ApiError::Internal(e.to_string()))?;
+    }
+    // The queue becomes visible only once all of its items are stored
+    state
+        .db
+        .put_annotation_queue(&queue)
+        .map_err(|e| ApiError::Internal(e.to_string()))?;
+
+    info!(
+        "Created annotation queue {} ({}) with {} items",
+        queue.id,
+        queue.name,
+        traces.len()
+    );
+    Ok((StatusCode::CREATED, Json(queue_response(&state, queue)?)))
+}
+
+/// GET /api/v1/annotation-queues - List queues with their progress
+pub async fn list_annotation_queues(
+    State(state): State<AppState>,
+    Extension(auth): Extension<AuthContext>,
+) -> Result<Json<QueueListResponse>, ApiError> {
+    let mut queues = state
+        .db
+        .list_annotation_queues(auth.tenant_id)
+        .map_err(|e| ApiError::Internal(e.to_string()))?;
+    queues.sort_by(|a, b| b.created_at.cmp(&a.created_at));
+    let queues = queues
+        .into_iter()
+        .map(|queue| queue_response(&state, queue))
+        .collect::<Result<_, _>>()?;
+    Ok(Json(QueueListResponse { queues }))
+}
+
+/// GET /api/v1/annotation-queues/:queue_id - Queue definition and progress
+pub async fn get_annotation_queue(
+    State(state): State<AppState>,
+    Extension(auth): Extension<AuthContext>,
+    Path(queue_id): Path<String>,
+) -> Result<Json<QueueResponse>, ApiError> {
+    let queue = load_queue(&state, auth.tenant_id, &queue_id)?;
+    Ok(Json(queue_response(&state, queue)?))
+}
+
+/// PUT /api/v1/annotation-queues/:queue_id/reviewers - Change reviewers
+///
+/// Items are reassigned across the new reviewer list; labels already
+/// recorded are kept.
+pub async fn update_queue_reviewers(
+    State(state): State<AppState>,
+    Extension(auth): Extension<AuthContext>,
+    Path(queue_id): Path<String>,
+    Json(req): Json<UpdateReviewersRequest>,
+) -> Result<Json<QueueResponse>, ApiError> {
+    let mut queue = load_queue(&state, auth.tenant_id, &queue_id)?;
+    let reviews_per_item = validate_reviewers(
+        &req.reviewers,
+        req.reviews_per_item.unwrap_or(queue.reviews_per_item),
+    )?;
+
+    let (mut items, labels) = load_items_and_labels(&state, &queue_id)?;
+    reassign(&mut items, &labels, &req.reviewers, reviews_per_item);
+    for item in &items {
+        state
+            .db
+            .put_annotation_item(item)
+            .map_err(|e| ApiError::Internal(e.to_string()))?;
+    }
+    queue.reviewers = req.reviewers;
+    queue.reviews_per_item = reviews_per_item;
+    state
+        .db
+        .put_annotation_queue(&queue)
+        .map_err(|e| ApiError::Internal(e.to_string()))?;
+
+    let progress = progress(&queue, &items, &labels);
+    Ok(Json(QueueResponse { queue, progress }))
+}
+
+/// GET /api/v1/annotation-queues/:queue_id/next - Next item for a reviewer
+///
+/// Returns the reviewer's first unlabeled item with its trace, payload and
+/// eval scores. Items whose trace no longer exists are skipped.
+pub async fn next_annotation_item(
+    State(state): State<AppState>,
+    Extension(auth): Extension<AuthContext>,
+    Path(queue_id): Path<String>,
+    Query(params): Query<NextItemParams>,
+) -> Result<Json<NextItemResponse>, ApiError> {
+    let queue = load_queue(&state, auth.tenant_id, &queue_id)?;
+    let reviewer = resolve_reviewer(params.reviewer, &auth)?;
+    let (items, labels) = load_items_and_labels(&state, &queue_id)?;
+    let labeled = labeled_by(&labels);
+    let pending: Vec<&AnnotationQueueItem> = items
+        .iter()
+        .filter(|item| {
+            item.assignees.contains(&reviewer)
+                && !labeled.contains(&(item.edge_id, reviewer.as_str()))
+        })
+        .collect();
+
+    let mut remaining = pending.len();
+    let mut next = None;
+    for item in pending {
+        let Some((db, edge)) = find_trace(&state, item.edge_id, auth.tenant_id)? else {
+            remaining -= 1;
+            continue;
+        };
+        let payload = db
+            .get_payload(item.edge_id)
+            .ok()
+            .flatten()
+            .and_then(|bytes| serde_json::from_slice(&bytes).ok());
+        let eval_metrics = db
+            .get_eval_metrics(item.edge_id)
+            .map_err(|e| ApiError::Internal(e.to_string()))?
+            .iter()
+            .map(EvalMetricOutput::from)
+            .collect();
+        next = Some(ReviewItem {
+            edge_id: format!("{:#x}", item.edge_id),
+            project_id: item.project_id,
+            edge,
+            payload,
+            eval_metrics,
+        });
+        break;
+    }
+
+    Ok(Json(NextItemResponse {
+        queue_id,
+        reviewer,
+        remaining,
+        rubric: queue.rubric,
+        item: next,
+    }))
+}
+
+/// POST /api/v1/annotation-queues/:queue_id/items/:edge_id/labels
+///
+/// Records a reviewer's rubric answers for an item, replacing any earlier
+/// answers by the same reviewer.
+pub async fn submit_annotation_label(
+    State(state): State<AppState>,
+    Extension(auth): Extension<AuthContext>,
+    Path((queue_id, edge_id)): Path<(String, String)>,
+    Json(req): Json<SubmitLabelRequest>,
+) -> Result<(StatusCode, Json<AnnotationLabel>), ApiError> {
+    let queue = load_queue(&state, auth.tenant_id, &queue_id)?;
+    let edge_id = parse_trace_id(&edge_id)?;
+    let reviewer = resolve_reviewer(req.reviewer, &auth)?;
+    validate_labels(&queue.rubric, &req.labels)?;
+    if req
+        .comment
+        .as_ref()
+        .is_some_and(|c| c.len() > MAX_COMMENT_LEN)
+    {
+        return Err(ApiError::BadRequest(format!(
+            "comment exceeds {} characters",
+            MAX_COMMENT_LEN
+        )));
+    }
+
+    let items = state
+        .db
+        .list_annotation_items(&queue_id)
+        .map_err(|e| ApiError::Internal(e.to_string()))?;
+    let item = items
+        .iter()
+        .find(|item| item.edge_id == edge_id)
+        .ok_or_else(|| {
+            ApiError::NotFound(format!("Trace {:#x} is not in queue {}", edge_id, queue_id))
+        })?;
+    if !item.assignees.contains(&reviewer) {
+        return Err(ApiError::BadRequest(format!(
+            "Reviewer '{}' is not assigned to trace {:#x}",
+            reviewer, edge_id
+        )));
+    }
+
+    let label = AnnotationLabel {
+        queue_id,
+        edge_id,
+        reviewer,
+        labels: req.labels,
+        comment: req.comment,
+        created_at: now_us(),
+    };
+    state
+        .db
+        .put_annotation_label(&label)
+        .map_err(|e| ApiError::Internal(e.to_string()))?;
+    Ok((StatusCode::CREATED, Json(label)))
+}
+
+/// Agreement on one dimension from the answers given to each item
+fn dimension_agreement(
+    dimension: &RubricDimension,
+    answers: &[Vec<&LabelValue>],
+    reviews_per_item: u32,
+) -> DimensionAgreement {
+    let mut agreement = DimensionAgreement {
+        dimension: dimension.name.clone(),
+        items_compared: 0,
+        pairs: 0,
+        percent_agreement: 0.0,
+        cohens_kappa: None,
+        fleiss_kappa: None,
+        weighted_kappa: None,
+    };
+    let mut pairs: Vec<(&LabelValue, &LabelValue)> = Vec::new();
+    for item in answers.iter().filter(|item| item.len() >= 2) {
+        agreement.items_compared += 1;
+        for (i, a) in item.iter().enumerate() {
+            for b in &item[i + 1..] {
+                pairs.push((a, b));
+            }
+        }
+    }
+    if pairs.is_empty() {
+        return agreement;
+    }
+    agreement.pairs = pairs.len();
+    agreement.percent_agreement =
+        pairs.iter().filter(|(a, b)| a == b).count() as f64 / pairs.len() as f64;
+
+    match &dimension.scale {
+        RubricScale::Categorical { labels } => {
+            let category = |value: &LabelValue| match value {
+                LabelValue::Label(label) => labels.iter().position(|l| l == label),
+                LabelValue::Score(_) => None,
+            };
+            let categorical: Vec<(usize, usize)> = pairs
+                .iter()
+                .filter_map(|(a, b)| Some((category(a)?, category(b)?)))
+                .collect();
+            agreement.cohens_kappa = Some(InterRaterReliability::cohens_kappa(&categorical));
+
+            if reviews_per_item >= 3 {
+                let subjects: Vec<Vec<usize>> = answers
+                    .iter()
+                    .filter(|item| item.len() == reviews_per_item as usize)
+                    .filter_map(|item| item.iter().map(|v| category(v)).collect())
+                    .collect();
+                if !subjects.is_empty() {
+                    agreement.fleiss_kappa =
+                        Some(InterRaterReliability::fleiss_kappa(&subjects, labels.len()));
+                }
+            }
+        }
+        RubricScale::Ordinal { .. } => {
+            let scores: Vec<(i32, i32)> = pairs
+                .iter()
+                .filter_map(|pair| match pair {
+                    (LabelValue::Score(a), LabelValue::Score(b)) => Some((*a, *b)),
+                    _ => None,
+                })
+                .collect();
+            agreement.weighted_kappa = Some(InterRaterReliability::weighted_kappa(&scores));
+        }
+    }
+    agreement
+}
+
+fn compute_agreement(
+    queue: &AnnotationQueueRecord,
+    labels: &[AnnotationLabel],
+) -> Vec<DimensionAgreement> {
+    let mut by_item: HashMap<u128, Vec<&AnnotationLabel>> = HashMap::new();
+    for label in labels {
+        by_item.entry(label.edge_id).or_default().push(label);
+    }
+    queue
+        .rubric
+        .iter()
+        .map(|dimension| {
+            let answers: Vec<Vec<&LabelValue>> = by_item
+                .values()
+                .map(|item| {
+                    item.iter()
+                        .filter_map(|label| label.labels.get(&dimension.name))
+                        .collect()
+                })
+                .collect();
+            dimension_agreement(dimension, &answers, queue.reviews_per_item)
+        })
+        .collect()
+}
+
+/// GET /api/v1/annotation-queues/:queue_id/agreement - Inter-annotator agreement
+pub async fn get_queue_agreement(
+    State(state): State<AppState>,
+    Extension(auth): Extension<AuthContext>,
+    Path(queue_id): Path<String>,
+) -> Result<Json<AgreementResponse>, ApiError> {
+    let queue = load_queue(&state, auth.tenant_id, &queue_id)?;
+    let labels = state
+        .db
+        .list_annotation_labels(&queue_id)
+        .map_err(|e| ApiError::Internal(e.to_string()))?;
+    let dimensions = compute_agreement(&queue, &labels);
+    Ok(Json(AgreementResponse {
+        queue_id,
+        dimensions,
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn reviewers(names: &[&str]) -> Vec<String> {
+        names.iter().map(|n| n.to_string()).collect()
+    }
+
+    fn queue(reviews_per_item: u32) -> AnnotationQueueRecord {
+        AnnotationQueueRecord {
+            id: "q".to_string(),
+            tenant_id: 1,
+            name: "review".to_string(),
+            description: None,
+            rubric: vec![
+                RubricDimension {
+                    name: "correct".to_string(),
+                    description: None,
+                    scale: RubricScale::Categorical {
+                        labels: reviewers(&["yes", "no"]),
+                    },
+                },
+                RubricDimension {
+                    name: "helpfulness".to_string(),
+                    description: None,
+                    scale: RubricScale::Ordinal { min: 1, max: 5 },
+                },
+            ],
+            reviewers: reviewers(&["a", "b", "c"]),
+            reviews_per_item,
+            filter: serde_json::Value::Null,
+            created_at: 0,
+        }
+    }
+
+    fn label(edge_id: u128, reviewer: &str, correct: &str, helpfulness: i32) -> AnnotationLabel {
+        AnnotationLabel {
+            queue_id: "q".to_string(),
+            edge_id,
+            reviewer: reviewer.to_string(),
+            labels: [
+                (
+                    "correct".to_string(),
+                    LabelValue::Label(correct.to_string()),
+                ),
+                ("helpfulness".to_string(), LabelValue::Score(helpfulness)),
+            ]
+            .into_iter()
+            .collect(),
+            comment: None,
+            created_at: 0,
+        }
+    }
+
+    fn item(edge_id: u128, assignees: &[&str]) -> AnnotationQueueItem {
+        AnnotationQueueItem {
+            queue_id: "q".to_string(),
+            edge_id,
+            project_id: 0,
+            assignees: reviewers(assignees),
+            added_at: 0,
+        }
+    }
+
+    #[test]
+    fn test_assignment_rotates_reviewers() {
+        let all = reviewers(&["a", "b", "c"]);
+        assert_eq!(assign(0, &all, 2), reviewers(&["a", "b"]));
+        assert_eq!(assign(1, &all, 2), reviewers(&["c", "a"]));
+        assert_eq!(assign(2, &all, 2), reviewers(&["b", "c"]));
+        assert_eq!(assign(4, &all, 1), reviewers(&["b"]));
+
+        // Every reviewer gets an equal share over a full rotation
+        let mut counts: HashMap<String, usize> = HashMap::new();
+        for index in 0..3 {
+            for reviewer in assign(index, &all, 2) {
+                *counts.entry(reviewer).or_default() += 1;
+            }
+        }
+        assert!(counts.values().all(|&c| c == 2));
+    }
+
+    #[test]
+    fn test_reassign_keeps_completed_reviews() {
+        let mut items = vec![item(1, &["a", "b"]), item(2, &["c", "a"])];
+        let labels = vec![label(1, "b", "yes", 3)];
+        reassign(&mut items, &labels, &reviewers(&["c", "d"]), 2);
+
+        // b already labeled item 1 and keeps it; a is dropped
+        assert_eq!(items[0].assignees, reviewers(&["b", "c"]));
+        assert_eq!(items[1].assignees, reviewers(&["c", "d"]));
+    }
+
+    #[test]
+    fn test_progress() {
+        let queue = queue(2);
+        let items = vec![item(1, &["a", "b"]), item(2, &["c", "a"])];
+        let labels = vec![
+            label(1, "a", "yes", 3),
+            label(1, "b", "yes", 4),
+            label(2, "a", "no", 1),
+        ];
+        let progress = progress(&queue, &items, &labels);
+        assert_eq!(progress.total_items, 2);
+        assert_eq!(progress.completed_items, 1);
+        assert_eq!(progress.labels, 3);
+        assert_eq!(
+            progress.reviewers[0],
+            ReviewerProgress {
+                reviewer: "a".to_string(),
+                assigned: 2,
+                completed: 2,
+            }
+        );
+        assert_eq!(progress.reviewers[2].completed, 0);
+    }
+
+    #[test]
+    fn test_validation() {
+        let rubric = queue(1).rubric;
+        assert!(validate_rubric(&rubric).is_ok());
+        assert!(validate_rubric(&[]).is_err());
+        let mut duplicate = rubric.clone();
+        duplicate.push(rubric[0].clone());
+        assert!(validate_rubric(&duplicate).is_err());
+        let inverted = vec![RubricDimension {
+            name: "score".to_string(),
+            description: None,
+            scale: RubricScale::Ordinal { min: 5, max: 1 },
+        }];
+        assert!(validate_rubric(&inverted).is_err());
+
+        assert!(validate_reviewers(&reviewers(&["a", "b"]), 2).is_ok());
+        assert!(validate_reviewers(&reviewers(&["a", "b"]), 3).is_err());
+        assert!(validate_reviewers(&reviewers(&["a", "a"]), 1).is_err());
+
+        assert!(validate_labels(&rubric, &label(1, "a", "yes", 5).labels).is_ok());
+        assert!(validate_labels(&rubric, &label(1, "a", "maybe", 5).labels).is_err());
+        assert!(validate_labels(&rubric, &label(1, "a", "yes", 6).labels).is_err());
+        let mut missing = label(1, "a", "yes", 5).labels;
+        missing.remove("helpfulness");
+        assert!(validate_labels(&rubric, &missing).is_err());
+        let mut unknown = label(1, "a", "yes", 5).labels;
+        unknown.insert("tone".to_string(), LabelValue::Score(1));
+        assert!(validate_labels(&rubric, &unknown).is_err());
+    }
+
+    #[test]
+    fn test_agreement() {
+        let queue = queue(2);
+        let labels = vec![
+            label(1, "a", "yes", 5),
+            label(1, "b", "yes", 5),
+            label(2, "a", "no", 1),
+            label(2, "b", "no", 2),
+            label(3, "a", "yes", 4),
+            label(3, "b", "no", 4),
+            // Single review: not compared
+            label(4, "c", "yes", 3),
+        ];
+        let agreement = compute_agreement(&queue, &labels);
+
+        let correct = &agreement[0];
+        assert_eq!(correct.items_compared, 3);
+        assert_eq!(correct.pairs, 3);
+        assert!((correct.percent_agreement - 2.0 / 3.0).abs() < 1e-9);
+        let kappa = correct.cohens_kappa.as_ref().unwrap();
+        assert!(kappa.kappa > 0.0 && kappa.kappa < 1.0);
+        assert!(correct.weighted_kappa.is_none());
+        assert!(correct.fleiss_kappa.is_none());
+
+        let helpfulness = &agreement[1];
+        assert!((helpfulness.percent_agreement - 2.0 / 3.0).abs() < 1e-9);
+        assert!(helpfulness.cohens_kappa.is_none());
+        assert!(helpfulness.weighted_kappa.as_ref().unwrap().kappa_quadratic > 0.5);
+    }
+
+    #[test]
+    fn test_fleiss_agreement() {
+        let queue = queue(3);
+        let mut labels = Vec::new();
+        for edge_id in 0..4u128 {
+            let answer = if edge_id % 2 == 0 { "yes" } else { "no" };
+            for reviewer in ["a", "b", "c"] {
+                labels.push(label(edge_id, reviewer, answer, 3));
+            }
+        }
+        let agreement = compute_agreement(&queue, &labels);
+        assert_eq!(agreement[0].pairs, 12);
+        assert!((agreement[0].fleiss_kappa.unwrap() - 1.0).abs() < 1e-9);
+        assert!((agreement[0].cohens_kappa.as_ref().unwrap().kappa - 1.0).abs() < 1e-9);
+    }
+}
@@ -14,33 +14,70 @@
 // along with this program. If not, see <https://www.gnu.org/licenses/>.
 
 use super::query::AppState;
+use crate::auth::AuthContext;
+use crate::jobs::BackgroundQuery;
+use agentreplay_core::AgentreplayError;
+use agentreplay_query::Agentreplay;
+use agentreplay_storage::{
+    BackupChain, BackupManager, BackupMetadata, BackupOptions, BackupRetention, BackupVerification,
+    DEFAULT_MAX_CHAIN_LENGTH,
+};
 use axum::{
     extract::{Query, State},
     http::StatusCode,
-    Json,
+    response::{IntoResponse, Response},
+    Extension, Json,
 };
-use agentreplay_storage::{BackupManager, BackupMetadata};
 use serde::{Deserialize, Serialize};
 use std::path::PathBuf;
+use std::sync::Arc;
 
 // ============================================================================
 // Request/Response Types
 // ============================================================================
 
+fn default_verify() -> bool {
+    true
+}
+
 #[derive(Debug, Deserialize)]
 pub struct CreateBackupRequest {
-    /// Destination path for the backup
+    /// Directory to write the backup archive to
     pub destination: String,
 
     /// Optional backup name (defaults to timestamp-based name)
     #[serde(default)]
     pub name: Option<String>,
+
+    /// Back up a project database instead of the default one
+    #[serde(default)]
+    pub project_id: Option<u16>,
+
+    /// Extract, open and integrity-check the archive after writing it
+    #[serde(default = "default_verify")]
+    pub verify: bool,
+
+    /// Only store files changed since the newest backup in `destination`
+    #[serde(default)]
+    pub incremental: bool,
+
+    /// Backups per chain before an incremental backup becomes a full one
+    #[serde(default)]
+    pub max_chain_length: Option<u32>,
+
+    /// After a successful backup, delete all but this many newest chains
+    #[serde(default)]
+    pub keep_chains: Option<usize>,
 }
 
 #[derive(Debug, Deserialize)]
 pub struct RestoreBackupRequest {
-    /// Path to the backup to restore from
+    /// Path to the backup archive to restore from
     pub backup_path: String,
+
+    /// Restore a project database instead of the default one
+    #[serde(default)]
+    pub project_id: Option<u16>,
 }
 
 #[derive(Debug, Deserialize)]
@@ -49,12 +86,29 @@ pub struct ListBackupsQuery {
     pub location: String,
 }
 
+#[derive(Debug, Deserialize)]
+pub struct VerifyBackupQuery {
+    /// Path to the backup archive
+    pub backup_path: String,
+
+    /// Also extract the archive and open it, as a restore would
+    #[serde(default)]
+    pub deep: bool,
+}
+
 #[derive(Debug, Serialize)]
 pub struct BackupResponse {
+    pub id: String,
     pub timestamp_us: u64,
     pub created_at: String,
     pub size_bytes: u64,
     pub file_count: usize,
+    pub edge_count: u64,
+    pub verified: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub parent: Option<String>,
+    pub chain_depth: u32,
+    pub inherited_files: usize,
     pub database_version: String,
     pub backup_path: String,
 }
@@ -65,12 +119,20 @@ pub struct BackupListResponse {
     pub total: usize,
 }
 
+#[derive(Debug, Serialize)]
+pub struct BackupChainListResponse {
+    pub chains: Vec<BackupChain>,
+    pub total: usize,
+}
+
 #[derive(Debug, Serialize)]
 pub struct BackupOperationResponse {
     pub success: bool,
     pub message: String,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub metadata: Option<BackupMetadata>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub verification: Option<BackupVerification>,
 }
 
 // ============================================================================
@@ -79,83 +141,161 @@ pub struct BackupOperationResponse {
 
 fn metadata_to_response(metadata: &BackupMetadata, backup_path: String) -> BackupResponse {
     BackupResponse {
+        id: metadata.id.clone(),
         timestamp_us: metadata.timestamp_us,
         created_at: metadata.created_at.clone(),
         size_bytes: metadata.size_bytes,
         file_count: metadata.file_count,
+        edge_count: metadata.edge_count,
+        verified: metadata.verified,
+        parent: metadata.parent.clone(),
+        chain_depth: metadata.chain_depth,
+        inherited_files: metadata.inherited.len(),
         database_version: metadata.database_version.clone(),
         backup_path,
     }
 }
 
+/// Database and data directory for the default database or a project
+fn resolve_database(
+    state: &AppState,
+    project_id: Option<u16>,
+) -> Result<(Arc<Agentreplay>, PathBuf), (StatusCode, String)> {
+    let Some(project_id) = project_id else {
+        return Ok((state.db.clone(), PathBuf::from(&state.db_path)));
+    };
+    let pm = state.project_manager.as_ref().ok_or((
+        StatusCode::BAD_REQUEST,
+        "project_id requires per-project storage".to_string(),
+    ))?;
+    let db = pm.get_or_open_project(project_id).map_err(|e| {
+        (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            format!("Failed to open project {}: {}", project_id, e),
+        )
+    })?;
+    Ok((db, pm.project_dir(project_id)))
+}
+
+fn backup_error(action: &str, e: AgentreplayError) -> (StatusCode, String) {
+    let status = match e {
+        AgentreplayError::InvalidArgument(_) => StatusCode::CONFLICT,
+        AgentreplayError::NotFound(_) => StatusCode::NOT_FOUND,
+        _ => StatusCode::INTERNAL_SERVER_ERROR,
+    };
+    (status, format!("Failed to {}: {}", action, e))
+}
+
 // ============================================================================
 // API Handlers
 // ============================================================================
 
 /// POST /api/v1/backup
-/// Create a backup of the database
+/// Create an online backup of the database
+///
+/// Ingestion continues while the backup is archived; see
+/// `agentreplay_storage::backup` for how the snapshot stays consistent and
+/// how incremental backups form chains. With `?background=true` the backup
+/// runs as a job and the response is the job, to follow at
+/// `/api/v1/jobs/:id`.
 pub async fn create_backup(
     State(state): State<AppState>,
+    Extension(auth): Extension<AuthContext>,
+    Query(params): Query<BackgroundQuery>,
     Json(req): Json<CreateBackupRequest>,
-) -> Result<(StatusCode, Json<BackupOperationResponse>), (StatusCode, String)> {
-    // Get database path from config
-    let db_path = state.db_path.clone();
-
-    let backup_manager = BackupManager::new(&db_path);
-
+) -> Result<Response, (StatusCode, String)> {
+    let (db, _) = resolve_database(&state, req.project_id)?;
     let destination = PathBuf::from(&req.destination);
-
-    // If name provided, append it to destination
-    let final_destination = if let Some(name) = req.name {
-        destination.join(name)
-    } else {
-        destination
+    let options = BackupOptions {
+        verify: req.verify,
+        incremental: req.incremental,
+        max_chain_length: req.max_chain_length.unwrap_or(DEFAULT_MAX_CHAIN_LENGTH),
+    };
+    let retention = req
+        .keep_chains
+        .map(|keep_chains| BackupRetention { keep_chains });
+    let name = req.name;
+    let description = format!("Backup to {}", destination.display());
+    let run = move || -> agentreplay_core::Result<BackupMetadata> {
+        let metadata = db.create_backup(&destination, name.as_deref(), &options)?;
+        if let Some(retention) = &retention {
+            // The backup itself succeeded; pruning is retried on the next run
+            if let Err(e) = BackupManager::new(&destination).apply_retention(retention) {
+                tracing::warn!("Backup retention failed in {:?}: {}", destination, e);
+            }
+        }
+        Ok(metadata)
     };
 
-    match backup_manager.create_backup(&final_destination) {
-        Ok(metadata) => Ok((
-            StatusCode::CREATED,
-            Json(BackupOperationResponse {
-                success: true,
-                message: format!(
-                    "Backup created successfully at {}",
-                    final_destination.display()
-                ),
-                metadata: Some(metadata),
-            }),
-        )),
-        Err(e) => Err((
-            StatusCode::INTERNAL_SERVER_ERROR,
-            format!("Failed to create backup: {}", e),
-        )),
+    if params.background {
+        let job = state
+            .jobs
+            .submit("backup", auth.tenant_id, description, move |_| async move {
+                let metadata = tokio::task::spawn_blocking(run)
+                    .await
+                    .map_err(|e| format!("Backup task panicked: {}", e))?
+                    .map_err(|e| format!("Failed to create backup: {}", e))?;
+                serde_json::to_value(metadata)
+                    .map(Some)
+                    .map_err(|e| e.to_string())
+            });
+        return Ok((StatusCode::ACCEPTED, Json(job)).into_response());
     }
+
+    let metadata = tokio::task::spawn_blocking(run)
+        .await
+        .map_err(|e| {
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                format!("Backup task panicked: {}", e),
+            )
+        })?
+        .map_err(|e| backup_error("create backup", e))?;
+
+    Ok((
+        StatusCode::CREATED,
+        Json(BackupOperationResponse {
+            success: true,
+            message: format!("Backup '{}' created in {}", metadata.id, req.destination),
+            metadata: Some(metadata),
+            verification: None,
+        }),
+    )
+        .into_response())
 }
 
 /// POST /api/v1/backup/restore
-/// Restore database from a backup
+/// Verify a backup and stage it for restore
 ///
-/// **WARNING**: This operation requires the server to be restarted after restore.
+/// The live database is not touched: the verified copy is swapped in when the
+/// server restarts (or, for a project, when its database is next opened).
 pub async fn restore_backup(
     State(state): State<AppState>,
     Json(req): Json<RestoreBackupRequest>,
 ) -> Result<Json<BackupOperationResponse>, (StatusCode, String)> {
-    let db_path = state.db_path.clone();
+    let (_, data_dir) = resolve_database(&state, req.project_id)?;
+    let backup_path = req.backup_path;
 
-    let backup_manager = BackupManager::new(&db_path);
+    let outcome =
+        tokio::task::spawn_blocking(move || BackupManager::stage_restore(&backup_path, &data_dir))
+            .await
+            .map_err(|e| {
+                (
+                    StatusCode::INTERNAL_SERVER_ERROR,
+                    format!("Restore task panicked: {}", e),
+                )
+            })?
+            .map_err(|e| backup_error("restore backup", e))?;
 
-    match backup_manager.restore_backup(&req.backup_path) {
-        Ok(()) => Ok(Json(BackupOperationResponse {
-            success: true,
-            message:
-                "Backup restored successfully. Please restart the server to load the restored data."
-                    .to_string(),
-            metadata: None,
-        })),
-        Err(e) => Err((
-            StatusCode::INTERNAL_SERVER_ERROR,
-            format!("Failed to restore backup: {}", e),
-        )),
-    }
+    Ok(Json(BackupOperationResponse {
+        success: true,
+        message: format!(
+            "Backup '{}' verified and staged. Restart the server to load the restored data.",
+            outcome.backup_id
+        ),
+        metadata: None,
+        verification: Some(outcome.verification),
+    }))
 }
 
 /// GET /api/v1/backup
@@ -163,57 +303,64 @@ pub async fn restore_backup(
 pub async fn list_backups(
     Query(params): Query<ListBackupsQuery>,
 ) -> Result<Json<BackupListResponse>, (StatusCode, String)> {
-    match BackupManager::list_backups(&params.location) {
-        Ok(backups) => {
-            let total = backups.len();
-            let backup_responses: Vec<BackupResponse> = backups
-                .iter()
-                .enumerate()
-                .map(|(i, metadata)| {
-                    let backup_path = PathBuf::from(&params.location)
-                        .join(format!("backup-{}", i))
-                        .display()
-                        .to_string();
-                    metadata_to_response(metadata, backup_path)
-                })
-                .collect();
-
-            Ok(Json(BackupListResponse {
-                backups: backup_responses,
-                total,
-            }))
-        }
-        Err(e) => Err((
-            StatusCode::INTERNAL_SERVER_ERROR,
-            format!("Failed to list backups: {}", e),
-        )),
-    }
+    let manager = BackupManager::new(&params.location);
+    let backups = BackupManager::list_backups(&params.location)
+        .map_err(|e| backup_error("list backups", e))?;
+
+    let backups: Vec<BackupResponse> = backups
+        .iter()
+        .map(|metadata| {
+            let backup_path = manager.archive_path(&metadata.id).display().to_string();
+            metadata_to_response(metadata, backup_path)
+        })
+        .collect();
+
+    Ok(Json(BackupListResponse {
+        total: backups.len(),
+        backups,
+    }))
+}
+
+/// GET /api/v1/backup/chains
+/// List backup chains (a full backup and its incrementals) in a directory
+pub async fn list_backup_chains(
+    Query(params): Query<ListBackupsQuery>,
+) -> Result<Json<BackupChainListResponse>, (StatusCode, String)> {
+    let chains = BackupManager::new(&params.location)
+        .list_chains()
+        .map_err(|e| backup_error("list backup chains", e))?;
+    Ok(Json(BackupChainListResponse {
+        total: chains.len(),
+        chains,
+    }))
 }
 
 /// GET /api/v1/backup/verify
-/// Verify the integrity of a backup
+/// Verify the integrity of a backup (and, if incremental, its whole chain)
 pub async fn verify_backup(
-    Query(params): Query<RestoreBackupRequest>,
+    Query(params): Query<VerifyBackupQuery>,
 ) -> Result<Json<BackupOperationResponse>, (StatusCode, String)> {
-    match BackupManager::verify_backup(&params.backup_path) {
-        Ok(valid) => {
-            if valid {
-                Ok(Json(BackupOperationResponse {
-                    success: true,
-                    message: "Backup is valid and intact".to_string(),
-                    metadata: None,
-                }))
-            } else {
-                Ok(Json(BackupOperationResponse {
-                    success: false,
-                    message: "Backup verification failed: missing or corrupted files".to_string(),
-                    metadata: None,
-                }))
-            }
-        }
-        Err(e) => Err((
+    let verification = tokio::task::spawn_blocking(move || {
+        BackupManager::verify_backup(&params.backup_path, params.deep)
+    })
+    .await
+    .map_err(|e| {
+        (
             StatusCode::INTERNAL_SERVER_ERROR,
-            format!("Failed to verify backup: {}", e),
-        )),
-    }
+            format!("Verify task panicked: {}", e),
+        )
+    })?
+    .map_err(|e| backup_error("verify backup", e))?;
+
+    let message = if verification.valid {
+        "Backup is valid and intact".to_string()
+    } else {
+        "Backup verification failed: missing, corrupted or unexpected files".to_string()
+    };
+    Ok(Json(BackupOperationResponse {
+        success: verification.valid,
+        message,
+        metadata: None,
+        verification: Some(verification),
+    }))
 }
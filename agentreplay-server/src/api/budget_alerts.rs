@@ -29,6 +29,7 @@ use serde::{Deserialize, Serialize};
 // Data Models - Use core types with API enums
 // ============================================================================
 
+use agentreplay_core::clock::now_us;
 use agentreplay_core::enterprise::{
     AlertAction as CoreAlertAction, AlertFilters as CoreAlertFilters,
     BudgetAlert as CoreBudgetAlert,
@@ -371,7 +372,7 @@ pub struct DeleteResponse {
 // Helper Functions
 // ============================================================================
 
-fn generate_id() -> u128 {
+pub(super) fn generate_id() -> u128 {
     use std::time::{SystemTime, UNIX_EPOCH};
     let timestamp = SystemTime::now()
         .duration_since(UNIX_EPOCH)
@@ -382,13 +383,6 @@ fn generate_id() -> u128 {
     timestamp ^ random
 }
 
-fn current_timestamp_us() -> u64 {
-    std::time::SystemTime::now()
-        .duration_since(std::time::UNIX_EPOCH)
-        .unwrap()
-        .as_micros() as u64
-}
-
 fn parse_id(id_str: &str) -> Result<u128, String> {
     let id_str = id_str.trim_start_matches("0x");
     u128::from_str_radix(id_str, 16).map_err(|e| format!("Invalid ID: {}", e))
@@ -423,7 +417,7 @@ pub async fn create_alert(
     Json(req): Json<CreateAlertRequest>,
 ) -> Result<(StatusCode, Json<AlertResponse>), (StatusCode, String)> {
     let alert_id = generate_id();
-    let timestamp = current_timestamp_us();
+    let timestamp = now_us();
 
     let alert = BudgetAlert {
         id: alert_id,
@@ -506,7 +500,7 @@ pub async fn update_alert(
 ) -> Result<Json<AlertResponse>, (StatusCode, String)> {
     let alert_id = parse_id(&id).map_err(|e| (StatusCode::BAD_REQUEST, e))?;
 
-    let timestamp = current_timestamp_us();
+    let timestamp = now_us();
 
     state
         .db
@@ -593,7 +587,7 @@ pub async fn get_budget_status(
         _ => Period::Day,
     };
 
-    let current_time = current_timestamp_us();
+    let current_time = now_us();
     let _start_time = if let Some(period_us) = period.to_microseconds() {
         current_time.saturating_sub(period_us)
     } else {
@@ -15,7 +15,12 @@
 
 use crate::api::query::{ApiError, AppState};
 use crate::auth::AuthContext;
-use crate::llm::ChatMessage;
+use crate::guardrails::{GuardrailReport, GuardrailVerdict};
+use crate::llm::{ChatMessage, ChatResponse, LLMProviderManager};
+use crate::quotas::{QuotaMetric, QuotaScope};
+use crate::response_cache::CacheHit;
+use crate::shadow::PrimaryCall;
+use crate::vault::ResolvedKey;
 use axum::{
     extract::{Extension, State},
     response::sse::{Event, KeepAlive, Sse},
@@ -23,15 +28,109 @@ use axum::{
 };
 use futures::stream::Stream;
 use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
 use std::convert::Infallible;
 use tokio_stream::wrappers::ReceiverStream;
 use tokio_stream::StreamExt;
 
 #[derive(Deserialize)]
 pub struct ChatRequest {
+    /// Provider to call; may be omitted when `key_alias` is given
+    #[serde(default)]
     pub provider: String,
     pub model: Option<String>,
     pub messages: Vec<ChatMessage>,
+    /// Vault key to authenticate with instead of the server's provider keys
+    #[serde(default)]
+    pub key_alias: Option<String>,
+    /// Project whose response cache settings apply
+    #[serde(default)]
+    pub project_id: u16,
+}
+
+/// Resolve the request's vault key, checking it belongs to the requested provider
+fn resolve_key(
+    state: &AppState,
+    tenant_id: u64,
+    req: &ChatRequest,
+) -> Result<Option<ResolvedKey>, ApiError> {
+    let Some(alias) = &req.key_alias else {
+        return Ok(None);
+    };
+    let key = state.vault.resolve(&state.db, tenant_id, alias)?;
+    if !req.provider.is_empty() && req.provider != key.provider {
+        return Err(ApiError::BadRequest(format!(
+            "Key '{}' is a {} key, not {}",
+            alias, key.provider, req.provider
+        )));
+    }
+    Ok(Some(key))
+}
+
+/// Run input guardrails on the request's messages, recording a rejected
+/// span and failing when a rule blocks it
+async fn check_input(
+    state: &AppState,
+    tenant_id: u64,
+    session_id: u64,
+    req: &mut ChatRequest,
+    key: Option<&ResolvedKey>,
+) -> Result<GuardrailReport, ApiError> {
+    let report = state.guardrails.check_input(&mut req.messages);
+    if let Some(verdict) = report.blocked_by() {
+        if let Some(llm_manager) = state.llm_manager.as_ref() {
+            let provider = key.map_or(req.provider.as_str(), |key| key.provider.as_str());
+            if let Err(e) = llm_manager
+                .record_rejected_chat(provider, tenant_id, session_id, report.attributes())
+                .await
+            {
+                tracing::warn!("Failed to record blocked chat request: {}", e);
+            }
+        }
+        return Err(blocked(verdict));
+    }
+    Ok(report)
+}
+
+fn blocked(verdict: &GuardrailVerdict) -> ApiError {
+    ApiError::BadRequest(format!(
+        "Blocked by guardrail '{}': {}",
+        verdict.rule,
+        verdict.findings.join(", ")
+    ))
+}
+
+/// Records estimated streaming usage against the vault key and token
+/// quotas once the stream ends
+struct StreamUsage {
+    state: AppState,
+    tenant_id: u64,
+    key: Option<ResolvedKey>,
+    quota: QuotaScope,
+    model: String,
+    input_tokens: u32,
+    output_tokens: u32,
+}
+
+impl Drop for StreamUsage {
+    fn drop(&mut self) {
+        if let Some(key) = &self.key {
+            self.state.vault.record_usage(
+                &self.state.db,
+                self.tenant_id,
+                key,
+                "gateway",
+                &self.model,
+                self.input_tokens as u64,
+                self.output_tokens as u64,
+            );
+        }
+        self.state.quotas.record(
+            &self.quota,
+            QuotaMetric::Tokens,
+            self.input_tokens as u64 + self.output_tokens as u64,
+        );
+    }
 }
 
 #[derive(Serialize)]
@@ -41,6 +140,52 @@ pub struct ChatResponseWrapper {
     pub model: String,
     pub tokens_used: Option<u32>,
     pub duration_ms: u32,
+    /// Verdicts of the guardrail rules that ran
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub guardrails: Vec<GuardrailVerdict>,
+    /// Set when the answer came from the response cache
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub cache: Option<CacheHit>,
+}
+
+/// Answer a request from the response cache, recording the hit as a span
+#[allow(clippy::too_many_arguments)]
+async fn answer_from_cache(
+    state: &AppState,
+    llm_manager: &LLMProviderManager,
+    tenant_id: u64,
+    session_id: u64,
+    provider: &str,
+    mut guardrails: GuardrailReport,
+    hit: CacheHit,
+    mut cached: ChatResponse,
+) -> Result<Json<ChatResponseWrapper>, ApiError> {
+    let mut content = std::mem::take(&mut cached.content);
+    guardrails.merge(state.guardrails.check_output(&mut content));
+    let mut attributes = hit.attributes();
+    if !guardrails.is_empty() {
+        attributes.extend(guardrails.attributes());
+    }
+    if let Err(e) = llm_manager
+        .record_cached_chat(provider, &cached, tenant_id, session_id, attributes)
+        .await
+    {
+        tracing::warn!("Failed to record cached chat response: {}", e);
+    }
+    if let Some(verdict) = guardrails.blocked_by() {
+        return Err(blocked(verdict));
+    }
+
+    // No provider was called, so no tokens were used
+    Ok(Json(ChatResponseWrapper {
+        content,
+        provider: cached.provider,
+        model: cached.model,
+        tokens_used: None,
+        duration_ms: 0,
+        guardrails: guardrails.verdicts,
+        cache: Some(hit),
+    }))
 }
 
 #[derive(Serialize)]
@@ -51,7 +196,7 @@ pub struct ModelsResponse {
 pub async fn chat_completion(
     State(state): State<AppState>,
     Extension(auth): Extension<AuthContext>,
-    Json(req): Json<ChatRequest>,
+    Json(mut req): Json<ChatRequest>,
 ) -> Result<Json<ChatResponseWrapper>, ApiError> {
     let llm_manager = state
         .llm_manager
@@ -64,30 +209,152 @@ pub async fn chat_completion(
         .unwrap()
         .as_secs();
 
-    let response = llm_manager
-        .chat(
-            &req.provider,
-            req.model,
-            req.messages,
+    let key = resolve_key(&state, auth.tenant_id, &req)?;
+    let mut guardrails =
+        check_input(&state, auth.tenant_id, session_id, &mut req, key.as_ref()).await?;
+
+    let provider = key
+        .as_ref()
+        .map_or_else(|| req.provider.clone(), |key| key.provider.clone());
+    let cache_key = state.response_cache.key(
+        auth.tenant_id,
+        req.project_id,
+        &provider,
+        req.model.as_deref(),
+        &req.messages,
+    );
+    if let Some((hit, cached)) = cache_key
+        .as_ref()
+        .and_then(|cache_key| state.response_cache.lookup(cache_key))
+    {
+        return answer_from_cache(
+            &state,
+            llm_manager,
             auth.tenant_id,
             session_id,
+            &provider,
+            guardrails,
+            hit,
+            cached,
         )
-        .await
-        .map_err(|e| ApiError::Internal(format!("LLM request failed: {}", e)))?;
+        .await;
+    }
+    // Cache hits use no tokens, so only calls to the provider are checked
+    let quota = QuotaScope::new(&auth);
+    state.quotas.check(&quota, QuotaMetric::Tokens)?;
+    let attributes = if cache_key.is_some() {
+        HashMap::from([("agentreplay.cache.hit".to_string(), "false".to_string())])
+    } else {
+        HashMap::new()
+    };
+
+    // Sampled before the call, which consumes the messages
+    let shadow = state
+        .shadow
+        .sample(&provider, req.model.as_deref())
+        .map(|rule| (rule, req.messages.clone()));
+
+    let (mut response, edge_id) = match &key {
+        Some(key) => {
+            llm_manager
+                .chat_with_key_attributes(
+                    key,
+                    req.model,
+                    req.messages,
+                    auth.tenant_id,
+                    session_id,
+                    attributes,
+                )
+                .await
+        }
+        None => {
+            llm_manager
+                .chat_with_attributes(
+                    &req.provider,
+                    req.model,
+                    req.messages,
+                    auth.tenant_id,
+                    session_id,
+                    attributes,
+                )
+                .await
+        }
+    }
+    .map_err(|e| ApiError::Internal(format!("LLM request failed: {}", e)))?;
+
+    let mut content = std::mem::take(&mut response.content);
+    guardrails.merge(state.guardrails.check_output(&mut content));
+    if !guardrails.is_empty() {
+        if let Err(e) = llm_manager.annotate_span(edge_id, guardrails.attributes()) {
+            tracing::warn!("Failed to record guardrail verdicts: {}", e);
+        }
+    }
+
+    if let Some(key) = &key {
+        state.vault.record_usage(
+            &state.db,
+            auth.tenant_id,
+            key,
+            "gateway",
+            response
+                .response_model
+                .as_deref()
+                .unwrap_or(&response.model),
+            response.input_tokens.unwrap_or(0) as u64,
+            response.output_tokens.unwrap_or(0) as u64,
+        );
+    }
+    let tokens = match (response.input_tokens, response.output_tokens) {
+        (None, None) => response.tokens_used.unwrap_or(0),
+        (input, output) => input.unwrap_or(0) + output.unwrap_or(0),
+    };
+    state
+        .quotas
+        .record(&quota, QuotaMetric::Tokens, tokens as u64);
+
+    // The provider was paid either way, so usage is recorded before blocking
+    if let Some(verdict) = guardrails.blocked_by() {
+        return Err(blocked(verdict));
+    }
+
+    if let Some(cache_key) = cache_key {
+        let mut answered = response.clone();
+        answered.content = content.clone();
+        state.response_cache.store(cache_key, answered, edge_id);
+    }
+
+    if let Some((rule, messages)) = shadow {
+        let mut answered = response.clone();
+        answered.content = content.clone();
+        state.shadow.spawn(
+            state.clone(),
+            auth.tenant_id,
+            session_id,
+            rule,
+            messages,
+            PrimaryCall {
+                provider,
+                edge_id,
+                response: answered,
+            },
+        );
+    }
 
     Ok(Json(ChatResponseWrapper {
-        content: response.content,
+        content,
         provider: response.provider,
         model: response.model,
         tokens_used: response.tokens_used,
         duration_ms: response.duration_ms,
+        guardrails: guardrails.verdicts,
+        cache: None,
     }))
 }
 
 pub async fn stream_completion(
     State(state): State<AppState>,
     Extension(auth): Extension<AuthContext>,
-    Json(req): Json<ChatRequest>,
+    Json(mut req): Json<ChatRequest>,
 ) -> Result<Sse<impl Stream<Item = Result<Event, Infallible>>>, ApiError> {
     let llm_manager = state
         .llm_manager
@@ -99,23 +366,56 @@ pub async fn stream_completion(
         .unwrap()
         .as_secs();
 
+    let key = resolve_key(&state, auth.tenant_id, &req)?;
+    // Output checks need the whole completion, so streams only get input checks
+    let guardrails =
+        check_input(&state, auth.tenant_id, session_id, &mut req, key.as_ref()).await?;
+    let quota = QuotaScope::new(&auth);
+    state.quotas.check(&quota, QuotaMetric::Tokens)?;
+    let attributes = if guardrails.is_empty() {
+        HashMap::new()
+    } else {
+        guardrails.attributes()
+    };
+
     // Clone for cost calculation
-    let model_name = req.model.clone().unwrap_or_else(|| req.provider.clone());
+    let provider_id = key
+        .as_ref()
+        .map(|key| key.provider.clone())
+        .unwrap_or_else(|| req.provider.clone());
+    let model_name = req.model.clone().unwrap_or_else(|| provider_id.clone());
     let messages_for_counting = req.messages.clone();
 
-    let rx = llm_manager
-        .stream_chat(
-            &req.provider,
-            req.model,
-            req.messages,
-            auth.tenant_id,
-            session_id,
-        )
-        .await
-        .map_err(|e| ApiError::Internal(format!("LLM stream failed: {}", e)))?;
+    let rx = match &key {
+        Some(key) => {
+            llm_manager
+                .stream_chat_with_key(
+                    key,
+                    req.model,
+                    req.messages,
+                    auth.tenant_id,
+                    session_id,
+                    attributes,
+                )
+                .await
+        }
+        None => {
+            llm_manager
+                .stream_chat(
+                    &req.provider,
+                    req.model,
+                    req.messages,
+                    auth.tenant_id,
+                    session_id,
+                    attributes,
+                )
+                .await
+        }
+    }
+    .map_err(|e| ApiError::Internal(format!("LLM stream failed: {}", e)))?;
 
     // Get cost per 1K tokens based on model (simplified pricing)
-    let (input_cost_per_1k, output_cost_per_1k) = get_model_pricing(&model_name);
+    let (input_cost_per_1k, output_cost_per_1k) = get_model_pricing(&provider_id, &model_name);
 
     // Estimate input tokens (rough approximation: 4 chars ≈ 1 token)
     let input_tokens: u32 = messages_for_counting
@@ -123,6 +423,17 @@ pub async fn stream_completion(
         .map(|msg| (msg.content.len() as u32) / 4)
         .sum();
 
+    // Vault key and quota usage are recorded when the stream is dropped
+    let mut usage = StreamUsage {
+        state: state.clone(),
+        tenant_id: auth.tenant_id,
+        key,
+        quota,
+        model: model_name.clone(),
+        input_tokens,
+        output_tokens: 0,
+    };
+
     // Use scan() on the stream to track cumulative tokens and cost
     use std::sync::{Arc, Mutex};
     let state = Arc::new(Mutex::new((0u32, 0f64))); // (output_tokens, total_cost)
@@ -139,6 +450,7 @@ pub async fn stream_completion(
         let input_cost = (input_tokens as f64 / 1000.0) * input_cost_per_1k;
         let output_cost = (*output_tokens as f64 / 1000.0) * output_cost_per_1k;
         *total_cost = input_cost + output_cost;
+        usage.output_tokens = *output_tokens;
 
         // Create SSE event with cost metadata in comment
         let event = Event::default().data(chunk).comment(format!(
@@ -154,7 +466,10 @@ pub async fn stream_completion(
 
 /// Get model pricing per 1K tokens (input, output)
 /// Based on 2024-2025 pricing - should be kept up-to-date
-fn get_model_pricing(model: &str) -> (f64, f64) {
+pub(crate) fn get_model_pricing(provider: &str, model: &str) -> (f64, f64) {
+    if crate::otel_genai::is_local_system(provider) {
+        return (0.0, 0.0);
+    }
     let model_lower = model.to_lowercase();
 
     match () {
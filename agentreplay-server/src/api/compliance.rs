@@ -18,8 +18,10 @@
 // Compliance reports and audit API endpoints
 
 use super::query::AppState;
+use crate::auth::AuthContext;
+use agentreplay_query::TenantScope;
 use axum::{
-    extract::{Path, Query, State},
+    extract::{Extension, Path, Query, State},
     http::StatusCode,
     Json,
 };
@@ -390,6 +392,7 @@ fn report_to_detail_response(report: &ComplianceReport) -> ReportDetailResponse
 /// Generate a new compliance report
 pub async fn generate_report(
     State(state): State<AppState>,
+    Extension(auth): Extension<AuthContext>,
     Json(req): Json<GenerateReportRequest>,
 ) -> Result<(StatusCode, Json<ReportResponse>), (StatusCode, String)> {
     let report_id = generate_id();
@@ -421,7 +424,8 @@ pub async fn generate_report(
     // Generate report based on type
     let traces = state
         .db
-        .list_traces_in_range(req.period_start, req.period_end)
+        .scoped(TenantScope::Tenant(auth.tenant_id))
+        .range(req.period_start, req.period_end)
         .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
 
     report.summary.total_traces = traces.len();
@@ -0,0 +1,309 @@
+// Copyright 2025 AgentReplay (https://github.com/agentreplay)
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program. If not, see <https://www.gnu.org/licenses/>.
+
+//! Confidence analytics over captured token logprobs
+//!
+//! Spans ingested with the `logprobs` pipeline stage carry a
+//! [`LogprobSummary`]. `GET /api/v1/analytics/confidence` aggregates them over a
+//! time window: average sequence confidence (geometric mean token
+//! probability) and perplexity, a confidence histogram, a per-model split and
+//! the least confident spans for review.
+
+use std::collections::BTreeMap;
+
+use agentreplay_core::clock::now_us;
+use agentreplay_core::AgentFlowEdge;
+use agentreplay_storage::LogprobSummary;
+use axum::{
+    extract::{Query, State},
+    Json,
+};
+use serde::{Deserialize, Serialize};
+
+use crate::scaling::run_query;
+use crate::{api::query::ApiError, api::AppState, auth::AuthContext};
+
+const DEFAULT_LOOKBACK_US: u64 = 24 * 3_600_000_000; // 1 day
+const DEFAULT_THRESHOLD: f64 = 0.7;
+const DEFAULT_LIMIT: usize = 20;
+const MAX_LIMIT: usize = 500;
+const HISTOGRAM_BINS: usize = 10;
+
+#[derive(Debug, Deserialize)]
+pub struct ConfidenceParams {
+    pub start_ts: Option<u64>,
+    pub end_ts: Option<u64>,
+    /// Restrict to one project (per-project storage only)
+    pub project_id: Option<u16>,
+    /// Sequence confidence below which a span counts as low-confidence
+    pub threshold: Option<f64>,
+    /// Number of least confident spans to return
+    pub limit: Option<usize>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ConfidenceBin {
+    pub lower: f64,
+    pub upper: f64,
+    pub count: u64,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ModelConfidence {
+    pub model: String,
+    pub span_count: u64,
+    pub avg_sequence_confidence: f64,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct LowConfidenceSpan {
+    pub edge_id: String,
+    pub session_id: u64,
+    pub timestamp_us: u64,
+    pub model: Option<String>,
+    pub token_count: u32,
+    pub sequence_confidence: f64,
+    pub perplexity: f64,
+    /// Probability of the least likely token
+    pub min_token_prob: f64,
+    pub low_confidence_token_ratio: f64,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ConfidenceAnalytics {
+    pub start_ts: u64,
+    pub end_ts: u64,
+    /// Spans with captured logprobs
+    pub span_count: u64,
+    pub token_count: u64,
+    pub avg_sequence_confidence: f64,
+    pub avg_perplexity: f64,
+    pub threshold: f64,
+    pub low_confidence_spans: u64,
+    pub histogram: Vec<ConfidenceBin>,
+    pub by_model: Vec<ModelConfidence>,
+    /// Least confident spans first
+    pub lowest: Vec<LowConfidenceSpan>,
+}
+
+/// Running aggregate over span summaries
+#[derive(Debug)]
+struct ConfidenceAccum {
+    threshold: f64,
+    limit: usize,
+    span_count: u64,
+    token_count: u64,
+    confidence_sum: f64,
+    perplexity_sum: f64,
+    low_confidence_spans: u64,
+    histogram: [u64; HISTOGRAM_BINS],
+    /// model → (spans, confidence sum)
+    by_model: BTreeMap<String, (u64, f64)>,
+    lowest: Vec<LowConfidenceSpan>,
+}
+
+impl ConfidenceAccum {
+    fn new(threshold: f64, limit: usize) -> Self {
+        Self {
+            threshold,
+            limit,
+            span_count: 0,
+            token_count: 0,
+            confidence_sum: 0.0,
+            perplexity_sum: 0.0,
+            low_confidence_spans: 0,
+            histogram: [0; HISTOGRAM_BINS],
+            by_model: BTreeMap::new(),
+            lowest: Vec::new(),
+        }
+    }
+
+    fn record(&mut self, edge: &AgentFlowEdge, model: Option<String>, summary: &LogprobSummary) {
+        let confidence = summary.sequence_confidence();
+        self.span_count += 1;
+        self.token_count += summary.token_count as u64;
+        self.confidence_sum += confidence;
+        self.perplexity_sum += summary.perplexity();
+        let bin = ((confidence * HISTOGRAM_BINS as f64) as usize).min(HISTOGRAM_BINS - 1);
+        self.histogram[bin] += 1;
+        if let Some(ref model) = model {
+            let entry = self.by_model.entry(model.clone()).or_default();
+            entry.0 += 1;
+            entry.1 += confidence;
+        }
+        if confidence >= self.threshold {
+            return;
+        }
+
+        self.low_confidence_spans += 1;
+        self.lowest.push(LowConfidenceSpan {
+            edge_id: format!("{:#x}", edge.edge_id),
+            session_id: edge.session_id,
+            timestamp_us: edge.timestamp_us,
+            model,
+            token_count: summary.token_count,
+            sequence_confidence: confidence,
+            perplexity: summary.perplexity(),
+            min_token_prob: (summary.min_logprob as f64).exp(),
+            low_confidence_token_ratio: summary.low_confidence_ratio(),
+        });
+        // Keep the list bounded without sorting on every span
+        if self.lowest.len() >= self.limit.saturating_mul(2).max(1) {
+            self.truncate_lowest();
+        }
+    }
+
+    fn truncate_lowest(&mut self) {
+        self.lowest
+            .sort_by(|a, b| a.sequence_confidence.total_cmp(&b.sequence_confidence));
+        self.lowest.truncate(self.limit);
+    }
+
+    fn finish(mut self, start_ts: u64, end_ts: u64) -> ConfidenceAnalytics {
+        self.truncate_lowest();
+        let mean = |sum: f64, count: u64| if count == 0 { 0.0 } else { sum / count as f64 };
+        ConfidenceAnalytics {
+            start_ts,
+            end_ts,
+            span_count: self.span_count,
+            token_count: self.token_count,
+            avg_sequence_confidence: mean(self.confidence_sum, self.span_count),
+            avg_perplexity: mean(self.perplexity_sum, self.span_count),
+            threshold: self.threshold,
+            low_confidence_spans: self.low_confidence_spans,
+            histogram: self
+                .histogram
+                .iter()
+                .enumerate()
+                .map(|(i, count)| ConfidenceBin {
+                    lower: i as f64 / HISTOGRAM_BINS as f64,
+                    upper: (i + 1) as f64 / HISTOGRAM_BINS as f64,
+                    count: *count,
+                })
+                .collect(),
+            by_model: self
+                .by_model
+                .into_iter()
+                .map(|(model, (span_count, sum))| ModelConfidence {
+                    model,
+                    span_count,
+                    avg_sequence_confidence: mean(sum, span_count),
+                })
+                .collect(),
+            lowest: self.lowest,
+        }
+    }
+}
+
+/// GET /api/v1/analytics/confidence
+///
+/// Sequence confidence of the spans in a window that have captured logprobs.
+pub async fn get_confidence_analytics(
+    State(state): State<AppState>,
+    Query(params): Query<ConfidenceParams>,
+    axum::Extension(auth): axum::Extension<AuthContext>,
+) -> Result<Json<ConfidenceAnalytics>, ApiError> {
+    let end_ts = params.end_ts.unwrap_or_else(now_us);
+    let start_ts = params
+        .start_ts
+        .unwrap_or(end_ts.saturating_sub(DEFAULT_LOOKBACK_US));
+    if end_ts <= start_ts {
+        return Err(ApiError::BadRequest(
+            "end_ts must be greater than start_ts".into(),
+        ));
+    }
+    let threshold = params.threshold.unwrap_or(DEFAULT_THRESHOLD);
+    if !(0.0..=1.0).contains(&threshold) {
+        return Err(ApiError::BadRequest(
+            "threshold must be between 0 and 1".into(),
+        ));
+    }
+    let limit = params.limit.unwrap_or(DEFAULT_LIMIT).min(MAX_LIMIT);
+
+    let shards = super::metrics::project_shards(&state, params.project_id)?;
+    let tenant_id = auth.tenant_id;
+    let accum = run_query(&state, move || -> Result<ConfidenceAccum, ApiError> {
+        let mut accum = ConfidenceAccum::new(threshold, limit);
+        for db in shards {
+            let edges = db
+                .query_temporal_range_for_tenant(start_ts, end_ts, tenant_id)
+                .map_err(|e| ApiError::Internal(e.to_string()))?;
+            for edge in &edges {
+                let summary = match db.get_logprob_summary(edge.edge_id) {
+                    Ok(Some(summary)) => summary,
+                    Ok(None) => continue,
+                    Err(e) => {
+                        tracing::warn!(
+                            "Failed to read logprobs of edge {:#x}: {}",
+                            edge.edge_id,
+                            e
+                        );
+                        continue;
+                    }
+                };
+                let model = db
+                    .get_edge_attrs(edge.edge_id)
+                    .ok()
+                    .map(|(_, model, _)| model)
+                    .filter(|model| !model.is_empty());
+                accum.record(edge, model, &summary);
+            }
+        }
+        Ok(accum)
+    })
+    .await
+    .map_err(|e| ApiError::Internal(format!("Confidence task panicked: {}", e)))??;
+
+    Ok(Json(accum.finish(start_ts, end_ts)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn summary(mean_logprob: f32, min_logprob: f32) -> LogprobSummary {
+        LogprobSummary {
+            token_count: 10,
+            mean_logprob,
+            min_logprob,
+            low_confidence_tokens: 1,
+        }
+    }
+
+    #[test]
+    fn test_confidence_accum() {
+        let mut accum = ConfidenceAccum::new(0.7, 2);
+        let mut edge = AgentFlowEdge::default();
+        for (i, mean) in [-0.05f32, -0.9, -2.0, -0.4, -1.2].iter().enumerate() {
+            edge.edge_id = i as u128;
+            let model = (i % 2 == 0).then(|| "gpt-4o".to_string());
+            accum.record(&edge, model, &summary(*mean, mean * 3.0));
+        }
+        let result = accum.finish(0, 1);
+
+        assert_eq!(result.span_count, 5);
+        assert_eq!(result.token_count, 50);
+        // exp(-0.9), exp(-2.0), exp(-1.2) are below 0.7; exp(-0.4) ≈ 0.67 too
+        assert_eq!(result.low_confidence_spans, 4);
+        assert_eq!(result.lowest.len(), 2);
+        assert_eq!(result.lowest[0].edge_id, "0x2");
+        assert_eq!(result.lowest[1].edge_id, "0x4");
+        assert_eq!(result.histogram.iter().map(|b| b.count).sum::<u64>(), 5);
+        assert_eq!(result.histogram[9].count, 1);
+        assert_eq!(result.by_model.len(), 1);
+        assert_eq!(result.by_model[0].span_count, 3);
+        assert!(result.avg_sequence_confidence > 0.0 && result.avg_sequence_confidence < 1.0);
+    }
+}
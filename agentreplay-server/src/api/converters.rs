@@ -13,7 +13,7 @@
 // You should have received a copy of the GNU Affero General Public License
 // along with this program. If not, see <https://www.gnu.org/licenses/>.
 
-use agentreplay_core::{AgentFlowEdge, SpanEvent, SpanType};
+use agentreplay_core::{AgentFlowEdge, SpanEvent, SpanLink, SpanType, FLAG_HAS_LINKS};
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 use thiserror::Error;
@@ -72,10 +72,7 @@ pub fn convert_otel_span_to_edge(
     project_id: u16,
 ) -> Result<AgentFlowEdge, ConversionError> {
     // Parse span_id as hex → u128
-    let span_id_str = span.span_id.trim_start_matches("0x");
-    let edge_id = u128::from_str_radix(span_id_str, 16)
-        .or_else(|_| span_id_str.parse::<u128>())
-        .map_err(|_| ConversionError::InvalidSpanId)?;
+    let edge_id = parse_otel_span_id(&span.span_id).ok_or(ConversionError::InvalidSpanId)?;
 
     // Extract agent_id from attributes
     let agent_id = extract_u64_attr(&span.attributes, "agent_id")
@@ -97,12 +94,7 @@ pub fn convert_otel_span_to_edge(
 
     // Parse parent span_id
     let causal_parent = match &span.parent_span_id {
-        Some(p) if !p.is_empty() && p != "0" => {
-            let parent_str = p.trim_start_matches("0x");
-            u128::from_str_radix(parent_str, 16)
-                .or_else(|_| parent_str.parse::<u128>())
-                .unwrap_or(0)
-        }
+        Some(p) if !p.is_empty() && p != "0" => parse_otel_span_id(p).unwrap_or(0),
         _ => 0,
     };
 
@@ -171,6 +163,9 @@ pub fn convert_otel_span_to_edge(
     edge.duration_us = duration_us;
     edge.token_count = token_count;
     edge.flags = flags;
+    if !otel_span_links(span, edge_id).is_empty() {
+        edge.flags |= FLAG_HAS_LINKS;
+    }
     edge.has_payload = 1; // We store attributes as payload
 
     // Recompute checksum after modifications
@@ -179,6 +174,22 @@ pub fn convert_otel_span_to_edge(
     Ok(edge)
 }
 
+/// Parse an OTel span ID (hex, falling back to decimal) to an edge ID
+fn parse_otel_span_id(id: &str) -> Option<u128> {
+    let id = id.trim_start_matches("0x");
+    u128::from_str_radix(id, 16)
+        .or_else(|_| id.parse::<u128>())
+        .ok()
+}
+
+/// Span links recorded on an OTel span (see [`crate::ingestion::ATTR_SPAN_LINKS`])
+pub fn otel_span_links(span: &OtelSpan, edge_id: u128) -> Vec<SpanLink> {
+    span.attributes
+        .get(crate::ingestion::ATTR_SPAN_LINKS)
+        .map(|value| crate::ingestion::parse_span_links(edge_id, value, parse_otel_span_id))
+        .unwrap_or_default()
+}
+
 /// Extract u64 attribute from span attributes
 fn extract_u64_attr(attrs: &HashMap<String, serde_json::Value>, key: &str) -> Option<u64> {
     attrs.get(key).and_then(|v| match v {
@@ -249,4 +260,35 @@ mod tests {
         assert_eq!(edge.flags, 1); // Error flag set
         assert_eq!(edge.causal_parent, 50); // 0x32 = 50
     }
+
+    #[test]
+    fn test_convert_span_with_links() {
+        // Synthesis step merging two sub-agent results
+        let mut attrs = HashMap::new();
+        attrs.insert(
+            crate::ingestion::ATTR_SPAN_LINKS.to_string(),
+            serde_json::json!(["0x65", "0x66"]),
+        );
+        let span = OtelSpan {
+            span_id: "0x64".to_string(),
+            trace_id: "0xc8".to_string(),
+            parent_span_id: Some("0x32".to_string()),
+            name: "synthesize".to_string(),
+            start_time: 1000000,
+            end_time: Some(2000000),
+            attributes: attrs,
+            events: Vec::new(),
+            status: None,
+        };
+
+        let edge = convert_otel_span_to_edge(&span, 1, 1).unwrap();
+        assert!(edge.has_links());
+        assert_eq!(edge.causal_parent, 0x32);
+
+        let linked: Vec<u128> = otel_span_links(&span, edge.edge_id)
+            .iter()
+            .map(|link| link.span_id)
+            .collect();
+        assert_eq!(linked, vec![0x65, 0x66]);
+    }
 }
@@ -14,15 +14,18 @@
 // along with this program. If not, see <https://www.gnu.org/licenses/>.
 
 use axum::{
-    extract::{Query, State},
+    extract::{Extension, Query, State},
     Json,
 };
 use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap};
 use tracing::debug;
 
 use crate::api::query::{ApiError, AppState};
+use crate::auth::{AuthContext, Role};
+use crate::billing::{BillingSettings, CostConverter, ProjectBilling, RateSnapshot};
 use crate::otel_genai::{GenAIPayload, ModelPricing};
+use agentreplay_query::TenantScope;
 
 // ============================================================================
 // Request/Response Types
@@ -34,12 +37,27 @@ pub struct CostBreakdownQuery {
     pub end_ts: u64,
     #[serde(default)]
     pub group_by: Vec<String>, // ["provider", "model", "route", "user", "project"]
+    /// Report in this currency instead of the tenant's display currency
+    #[serde(default)]
+    pub currency: Option<String>,
+}
+
+/// Currency override accepted by the cost endpoints
+#[derive(Debug, Default, Deserialize)]
+pub struct CurrencyQuery {
+    #[serde(default)]
+    pub currency: Option<String>,
 }
 
+/// Costs are in `currency`, including project markup and tax; `*_usd`
+/// fields hold the raw cost
 #[derive(Debug, Serialize)]
 pub struct CostBreakdownResponse {
     pub total_cost: f64,
+    pub total_cost_usd: f64,
     pub currency: String,
+    /// Latest units of `currency` per USD; spans convert at their own date
+    pub exchange_rate: f64,
     pub breakdown: Vec<CostGroup>,
     pub forecast_30d: Option<f64>,
 }
@@ -48,6 +66,7 @@ pub struct CostBreakdownResponse {
 pub struct CostGroup {
     pub group_key: String,
     pub cost: f64,
+    pub cost_usd: f64,
     pub percentage: f64,
     pub token_count: u64,
     pub request_count: u64,
@@ -56,6 +75,7 @@ pub struct CostGroup {
 
 #[derive(Debug, Serialize)]
 pub struct ProviderCostResponse {
+    pub currency: String,
     pub providers: Vec<ProviderCost>,
 }
 
@@ -63,6 +83,7 @@ pub struct ProviderCostResponse {
 pub struct ProviderCost {
     pub provider: String,
     pub total_cost: f64,
+    pub total_cost_usd: f64,
     pub total_tokens: u64,
     pub request_count: u64,
     pub models: Vec<ModelCost>,
@@ -78,29 +99,96 @@ pub struct ModelCost {
     pub requests: u64,
 }
 
+/// Body of `PUT /api/v1/billing`
+#[derive(Debug, Deserialize)]
+pub struct UpdateBillingRequest {
+    pub display_currency: String,
+    /// Markup and tax per project ID; projects left out have none
+    #[serde(default)]
+    pub projects: BTreeMap<u16, ProjectBilling>,
+}
+
 // ============================================================================
 // API Handlers
 // ============================================================================
 
+/// Converter for the caller's tenant, in `currency` when given
+pub(crate) fn cost_converter(
+    state: &AppState,
+    auth: &AuthContext,
+    currency: Option<&str>,
+) -> Result<CostConverter, ApiError> {
+    state
+        .billing
+        .converter(auth.tenant_id, currency)
+        .map_err(ApiError::BadRequest)
+}
+
+/// GET /api/v1/billing
+pub async fn get_billing_settings(
+    State(state): State<AppState>,
+    Extension(auth): Extension<AuthContext>,
+) -> Json<BillingSettings> {
+    Json(state.billing.settings(auth.tenant_id))
+}
+
+/// PUT /api/v1/billing
+///
+/// Sets the display currency and the markup and tax of each project; the
+/// currency needs a recorded exchange rate.
+pub async fn put_billing_settings(
+    State(state): State<AppState>,
+    Extension(auth): Extension<AuthContext>,
+    Json(req): Json<UpdateBillingRequest>,
+) -> Result<Json<BillingSettings>, ApiError> {
+    auth.require_role(Role::Admin)?;
+    state
+        .billing
+        .update(auth.tenant_id, &req.display_currency, req.projects)
+        .map(Json)
+        .map_err(ApiError::BadRequest)
+}
+
+/// POST /api/v1/billing/rates
+///
+/// Records an exchange-rate snapshot; spans from `effective_from` on
+/// convert at its rates.
+pub async fn add_exchange_rates(
+    State(state): State<AppState>,
+    Extension(auth): Extension<AuthContext>,
+    Json(snapshot): Json<RateSnapshot>,
+) -> Result<Json<BillingSettings>, ApiError> {
+    auth.require_role(Role::Admin)?;
+    state
+        .billing
+        .add_rate_snapshot(auth.tenant_id, snapshot)
+        .map(Json)
+        .map_err(ApiError::BadRequest)
+}
+
 /// GET /api/v1/analytics/cost/breakdown
 /// Get detailed cost breakdown
 pub async fn get_detailed_cost_breakdown(
     State(state): State<AppState>,
+    Extension(auth): Extension<AuthContext>,
     Query(params): Query<CostBreakdownQuery>,
 ) -> Result<Json<CostBreakdownResponse>, ApiError> {
     debug!(
         "Getting cost breakdown from {} to {}",
         params.start_ts, params.end_ts
     );
+    let converter = cost_converter(&state, &auth, params.currency.as_deref())?;
 
     // Query edges in range
     let edges = state
         .db
-        .query_temporal_range(params.start_ts, params.end_ts)
+        .scoped(TenantScope::Tenant(auth.tenant_id))
+        .range(params.start_ts, params.end_ts)
         .map_err(|e| ApiError::Internal(e.to_string()))?;
 
     let mut groups: HashMap<String, CostGroup> = HashMap::new();
     let mut total_cost = 0.0;
+    let mut total_cost_usd = 0.0;
 
     for edge in edges {
         // Calculate cost for edge
@@ -122,8 +210,10 @@ pub async fn get_detailed_cost_breakdown(
                     .or_else(|| genai.request_model.clone())
                     .unwrap_or_else(|| "unknown".to_string());
 
-                let pricing = ModelPricing::for_model(&system, &m);
-                cost = genai.calculate_cost(&pricing);
+                // A stored cost (from the SDK or a recompute job) wins
+                cost = genai
+                    .stored_cost()
+                    .unwrap_or_else(|| genai.calculate_cost(&ModelPricing::for_model(&system, &m)));
                 model = m;
                 provider = system;
                 tokens =
@@ -131,7 +221,10 @@ pub async fn get_detailed_cost_breakdown(
             }
         }
 
+        let cost_usd = cost;
+        let cost = converter.convert(cost_usd, edge.project_id, edge.timestamp_us);
         total_cost += cost;
+        total_cost_usd += cost_usd;
 
         // Determine group key
         let key = if params.group_by.contains(&"provider".to_string()) {
@@ -147,6 +240,7 @@ pub async fn get_detailed_cost_breakdown(
         let entry = groups.entry(key.clone()).or_insert(CostGroup {
             group_key: key,
             cost: 0.0,
+            cost_usd: 0.0,
             percentage: 0.0,
             token_count: 0,
             request_count: 0,
@@ -154,6 +248,7 @@ pub async fn get_detailed_cost_breakdown(
         });
 
         entry.cost += cost;
+        entry.cost_usd += cost_usd;
         entry.token_count += tokens;
         entry.request_count += 1;
     }
@@ -178,7 +273,9 @@ pub async fn get_detailed_cost_breakdown(
 
     Ok(Json(CostBreakdownResponse {
         total_cost,
-        currency: "USD".to_string(),
+        total_cost_usd,
+        currency: converter.currency().to_string(),
+        exchange_rate: converter.current_rate(),
         breakdown,
         forecast_30d: Some(total_cost * 30.0), // Naive forecast
     }))
@@ -188,7 +285,10 @@ pub async fn get_detailed_cost_breakdown(
 /// Get cost breakdown by provider
 pub async fn get_provider_costs(
     State(state): State<AppState>,
+    Extension(auth): Extension<AuthContext>,
+    Query(params): Query<CurrencyQuery>,
 ) -> Result<Json<ProviderCostResponse>, ApiError> {
+    let converter = cost_converter(&state, &auth, params.currency.as_deref())?;
     // Default to last 30 days
     let end_ts = std::time::SystemTime::now()
         .duration_since(std::time::UNIX_EPOCH)
@@ -198,7 +298,8 @@ pub async fn get_provider_costs(
 
     let edges = state
         .db
-        .query_temporal_range(start_ts, end_ts)
+        .scoped(TenantScope::Tenant(auth.tenant_id))
+        .range(start_ts, end_ts)
         .map_err(|e| ApiError::Internal(e.to_string()))?;
 
     let mut provider_map: HashMap<String, ProviderCost> = HashMap::new();
@@ -222,8 +323,10 @@ pub async fn get_provider_costs(
                     .or_else(|| genai.request_model.clone())
                     .unwrap_or_else(|| "unknown".to_string());
 
-                let pricing = ModelPricing::for_model(&system, &m);
-                cost = genai.calculate_cost(&pricing);
+                // A stored cost (from the SDK or a recompute job) wins
+                cost = genai
+                    .stored_cost()
+                    .unwrap_or_else(|| genai.calculate_cost(&ModelPricing::for_model(&system, &m)));
                 provider_name = system;
                 tokens =
                     (genai.input_tokens.unwrap_or(0) + genai.output_tokens.unwrap_or(0)) as u64;
@@ -239,6 +342,7 @@ pub async fn get_provider_costs(
             .or_insert(ProviderCost {
                 provider: provider_name.clone(),
                 total_cost: 0.0,
+                total_cost_usd: 0.0,
                 total_tokens: 0,
                 request_count: 0,
                 models: Vec::new(),
@@ -246,7 +350,8 @@ pub async fn get_provider_costs(
                 error_rate: 0.0,
             });
 
-        entry.total_cost += cost;
+        entry.total_cost += converter.convert(cost, edge.project_id, edge.timestamp_us);
+        entry.total_cost_usd += cost;
         entry.total_tokens += tokens;
         entry.request_count += 1;
         entry.avg_latency_ms += latency; // Accumulate for now
@@ -267,5 +372,8 @@ pub async fn get_provider_costs(
         }
     }
 
-    Ok(Json(ProviderCostResponse { providers }))
+    Ok(Json(ProviderCostResponse {
+        currency: converter.currency().to_string(),
+        providers,
+    }))
 }
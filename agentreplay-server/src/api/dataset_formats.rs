@@ -0,0 +1,575 @@
+// Copyright 2025 AgentReplay (https://github.com/agentreplay)
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program. If not, see <https://www.gnu.org/licenses/>.
+
+//! Fine-tuning JSONL formats for datasets and flywheel exports
+//!
+//! - `openai`: `{"messages": [{"role", "content", "tool_calls"?}, ...]}`
+//! - `anthropic`: `{"system"?: "...", "messages": [...]}` with strictly
+//!   alternating user/assistant turns and tool activity folded into text
+//! - `agentreplay`: `{"input", "expected_output", "metadata"}`
+//!
+//! Import accepts any of the three per line. Multi-turn conversations keep
+//! their last user turn as the test case input and the turns before it in
+//! the `messages` metadata key, so export reproduces the full conversation.
+
+use std::collections::HashMap;
+
+use agentreplay_core::{ContentPartV1, MessageV1, TestCase};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+
+use crate::api::flywheel::merge_message_text;
+
+/// Metadata key holding the system prompt of an imported conversation
+pub const SYSTEM_KEY: &str = "system";
+
+/// Metadata key holding the turns before the last user message (JSON)
+pub const MESSAGES_KEY: &str = "messages";
+
+/// Supported JSONL line formats
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DatasetFormat {
+    OpenAi,
+    Anthropic,
+    AgentReplay,
+}
+
+impl DatasetFormat {
+    pub fn parse(format: &str) -> Option<Self> {
+        match format {
+            "openai" => Some(Self::OpenAi),
+            "anthropic" => Some(Self::Anthropic),
+            "agentreplay" => Some(Self::AgentReplay),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::OpenAi => "openai",
+            Self::Anthropic => "anthropic",
+            Self::AgentReplay => "agentreplay",
+        }
+    }
+}
+
+/// A plain conversation turn, as stored in the `messages` metadata key
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Turn {
+    pub role: String,
+    pub content: String,
+}
+
+/// A test case parsed from one JSONL line
+#[derive(Debug, Clone, PartialEq)]
+pub struct ImportedExample {
+    pub input: String,
+    pub expected_output: Option<String>,
+    pub metadata: HashMap<String, String>,
+}
+
+/// Why a JSONL line was rejected (1-based line number)
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct LineError {
+    pub line: usize,
+    pub error: String,
+}
+
+fn text_message(role: &str, text: &str) -> MessageV1 {
+    MessageV1 {
+        role: role.to_string(),
+        content: vec![ContentPartV1::Text {
+            text: text.to_string(),
+        }],
+        name: None,
+        tool_call_id: None,
+        metadata: HashMap::new(),
+    }
+}
+
+fn text_parts(message: &MessageV1) -> String {
+    let mut text = String::new();
+    for part in &message.content {
+        let chunk = match part {
+            ContentPartV1::Text { text } => text.clone(),
+            ContentPartV1::Json { value } => value.to_string(),
+            _ => continue,
+        };
+        if !text.is_empty() {
+            text.push('\n');
+        }
+        text.push_str(&chunk);
+    }
+    text
+}
+
+/// OpenAI chat fine-tuning line: tool calls and results keep their own fields
+pub fn messages_to_openai(messages: &[MessageV1]) -> Value {
+    let mut formatted = Vec::new();
+    for message in messages {
+        let tool_results: Vec<Value> = message
+            .content
+            .iter()
+            .filter_map(|part| match part {
+                ContentPartV1::ToolResult {
+                    tool_call_id,
+                    content,
+                } => Some(json!({
+                    "role": "tool",
+                    "tool_call_id": tool_call_id,
+                    "content": content.clone().unwrap_or_default(),
+                })),
+                _ => None,
+            })
+            .collect();
+        let text = text_parts(message);
+
+        match message.role.as_str() {
+            "assistant" => {
+                let tool_calls: Vec<Value> = message
+                    .content
+                    .iter()
+                    .filter_map(|part| match part {
+                        ContentPartV1::ToolUse {
+                            id,
+                            name,
+                            arguments,
+                        } => Some(json!({
+                            "id": id,
+                            "type": "function",
+                            "function": {
+                                "name": name,
+                                "arguments": arguments.clone().unwrap_or_else(|| "{}".to_string()),
+                            },
+                        })),
+                        _ => None,
+                    })
+                    .collect();
+                let mut entry = json!({ "role": "assistant", "content": text });
+                if !tool_calls.is_empty() {
+                    if text.is_empty() {
+                        entry["content"] = Value::Null;
+                    }
+                    entry["tool_calls"] = Value::Array(tool_calls);
+                }
+                formatted.push(entry);
+            }
+            "tool" if tool_results.is_empty() => formatted.push(json!({
+                "role": "tool",
+                "tool_call_id": message.tool_call_id.clone().unwrap_or_default(),
+                "content": text,
+            })),
+            role => {
+                formatted.extend(tool_results);
+                if !text.is_empty() && role != "tool" {
+                    let role = if role == "system" { "system" } else { "user" };
+                    formatted.push(json!({ "role": role, "content": text }));
+                }
+            }
+        }
+    }
+
+    json!({ "messages": formatted })
+}
+
+/// Anthropic fine-tuning line: separate system prompt, alternating user/assistant text turns
+pub fn messages_to_anthropic(messages: &[MessageV1]) -> Value {
+    let mut system = Vec::new();
+    let mut turns: Vec<Turn> = Vec::new();
+    for message in messages {
+        let text = merge_message_text(message).trim().to_string();
+        if text.is_empty() {
+            continue;
+        }
+        let role = match message.role.as_str() {
+            "system" => {
+                system.push(text);
+                continue;
+            }
+            "assistant" => "assistant",
+            _ => "user",
+        };
+        match turns.last_mut() {
+            Some(last) if last.role == role => {
+                last.content.push('\n');
+                last.content.push_str(&text);
+            }
+            // The conversation has to open with a user turn
+            None if role == "assistant" => {}
+            _ => turns.push(Turn {
+                role: role.to_string(),
+                content: text,
+            }),
+        }
+    }
+
+    let mut line = json!({ "messages": turns });
+    if !system.is_empty() {
+        line["system"] = json!(system.join("\n"));
+    }
+    line
+}
+
+/// The conversation a test case stands for: history, last user turn, expected answer
+pub fn test_case_messages(test_case: &TestCase) -> Vec<MessageV1> {
+    let mut messages = Vec::new();
+    if let Some(system) = test_case.metadata.get(SYSTEM_KEY) {
+        messages.push(text_message("system", system));
+    }
+    if let Some(history) = test_case
+        .metadata
+        .get(MESSAGES_KEY)
+        .and_then(|raw| serde_json::from_str::<Vec<Turn>>(raw).ok())
+    {
+        messages.extend(history.iter().map(|t| text_message(&t.role, &t.content)));
+    }
+    messages.push(text_message("user", &test_case.input));
+    if let Some(expected) = &test_case.expected_output {
+        messages.push(text_message("assistant", expected));
+    }
+    messages
+}
+
+/// One export line for a test case
+///
+/// Returns `None` for fine-tuning formats when the test case has no expected
+/// output, since a line without an assistant turn has nothing to train on.
+pub fn test_case_line(format: DatasetFormat, test_case: &TestCase) -> Option<Value> {
+    match format {
+        DatasetFormat::AgentReplay => Some(json!({
+            "input": test_case.input,
+            "expected_output": test_case.expected_output,
+            "metadata": test_case.metadata,
+        })),
+        _ if test_case.expected_output.is_none() => None,
+        DatasetFormat::OpenAi => Some(messages_to_openai(&test_case_messages(test_case))),
+        DatasetFormat::Anthropic => Some(messages_to_anthropic(&test_case_messages(test_case))),
+    }
+}
+
+/// Text of a message's `content`: a string, null, or an array of content blocks
+fn content_text(content: Option<&Value>) -> Result<String, String> {
+    let blocks = match content {
+        None | Some(Value::Null) => return Ok(String::new()),
+        Some(Value::String(text)) => return Ok(text.clone()),
+        Some(Value::Array(blocks)) => blocks,
+        Some(_) => return Err("content must be a string or an array".to_string()),
+    };
+
+    let mut text = String::new();
+    for (idx, block) in blocks.iter().enumerate() {
+        let kind = block.get("type").and_then(Value::as_str).unwrap_or("");
+        let chunk = match kind {
+            "text" => block
+                .get("text")
+                .and_then(Value::as_str)
+                .ok_or_else(|| format!("content[{}]: text block without \"text\"", idx))?
+                .to_string(),
+            "tool_use" => format!(
+                "[tool_use:{} {}]",
+                block.get("name").and_then(Value::as_str).unwrap_or(""),
+                block.get("input").cloned().unwrap_or(Value::Null)
+            ),
+            "tool_result" => format!(
+                "[tool_result:{} {}]",
+                block
+                    .get("tool_use_id")
+                    .and_then(Value::as_str)
+                    .unwrap_or(""),
+                content_text(block.get("content"))?
+            ),
+            other => {
+                return Err(format!(
+                    "content[{}]: unsupported block type '{}'",
+                    idx, other
+                ))
+            }
+        };
+        if !text.is_empty() {
+            text.push('\n');
+        }
+        text.push_str(&chunk);
+    }
+    Ok(text)
+}
+
+fn parse_messages(line: &serde_json::Map<String, Value>) -> Result<ImportedExample, String> {
+    let messages = line
+        .get("messages")
+        .and_then(Value::as_array)
+        .ok_or("\"messages\" must be an array")?;
+
+    let mut system: Vec<String> = line
+        .get("system")
+        .map(|s| content_text(Some(s)))
+        .transpose()
+        .map_err(|e| format!("system: {}", e))?
+        .into_iter()
+        .collect();
+    let mut turns = Vec::new();
+    for (idx, message) in messages.iter().enumerate() {
+        let role = message
+            .get("role")
+            .and_then(Value::as_str)
+            .ok_or_else(|| format!("messages[{}]: missing \"role\"", idx))?;
+        let mut text = content_text(message.get("content"))
+            .map_err(|e| format!("messages[{}]: {}", idx, e))?;
+        match role {
+            "system" => {
+                system.push(text);
+                continue;
+            }
+            "user" | "assistant" | "tool" => {}
+            other => return Err(format!("messages[{}]: unknown role '{}'", idx, other)),
+        }
+        if let Some(calls) = message.get("tool_calls").and_then(Value::as_array) {
+            for call in calls {
+                let function = call.get("function").unwrap_or(&Value::Null);
+                let name = function.get("name").and_then(Value::as_str).unwrap_or("");
+                let arguments = function
+                    .get("arguments")
+                    .and_then(Value::as_str)
+                    .unwrap_or("");
+                if !text.is_empty() {
+                    text.push('\n');
+                }
+                text.push_str(&format!("[tool_use:{} {}]", name, arguments));
+            }
+        }
+        turns.push(Turn {
+            role: role.to_string(),
+            content: text,
+        });
+    }
+
+    let expected_output = match turns.last() {
+        Some(last) if last.role == "assistant" => turns.pop().map(|t| t.content),
+        _ => None,
+    };
+    let last_user = turns
+        .iter()
+        .rposition(|t| t.role == "user")
+        .ok_or("conversation has no user message")?;
+    let input = turns.remove(last_user).content;
+
+    let mut metadata = HashMap::new();
+    if !system.is_empty() {
+        metadata.insert(SYSTEM_KEY.to_string(), system.join("\n"));
+    }
+    if !turns.is_empty() {
+        let history = serde_json::to_string(&turns).map_err(|e| e.to_string())?;
+        metadata.insert(MESSAGES_KEY.to_string(), history);
+    }
+
+    Ok(ImportedExample {
+        input,
+        expected_output,
+        metadata,
+    })
+}
+
+fn parse_native(line: &serde_json::Map<String, Value>) -> Result<ImportedExample, String> {
+    let input = line
+        .get("input")
+        .and_then(Value::as_str)
+        .ok_or("\"input\" must be a string")?
+        .to_string();
+    let expected_output = match line.get("expected_output") {
+        None | Some(Value::Null) => None,
+        Some(Value::String(expected)) => Some(expected.clone()),
+        Some(_) => return Err("\"expected_output\" must be a string".to_string()),
+    };
+    let metadata = match line.get("metadata") {
+        None | Some(Value::Null) => HashMap::new(),
+        Some(Value::Object(entries)) => entries
+            .iter()
+            .map(|(k, v)| {
+                let value = match v {
+                    Value::String(s) => s.clone(),
+                    other => other.to_string(),
+                };
+                (k.clone(), value)
+            })
+            .collect(),
+        Some(_) => return Err("\"metadata\" must be an object".to_string()),
+    };
+
+    Ok(ImportedExample {
+        input,
+        expected_output,
+        metadata,
+    })
+}
+
+/// Parse and validate one JSONL line in any supported format
+pub fn parse_line(line: &str) -> Result<ImportedExample, String> {
+    let value: Value = serde_json::from_str(line).map_err(|e| format!("invalid JSON: {}", e))?;
+    let object = value.as_object().ok_or("line must be a JSON object")?;
+    if object.contains_key("messages") {
+        parse_messages(object)
+    } else if object.contains_key("input") {
+        parse_native(object)
+    } else {
+        Err("expected a \"messages\" array or an \"input\" field".to_string())
+    }
+}
+
+/// Parse a JSONL document, skipping blank lines and collecting per-line errors
+pub fn parse_jsonl(data: &str) -> (Vec<ImportedExample>, Vec<LineError>) {
+    let mut examples = Vec::new();
+    let mut errors = Vec::new();
+    for (idx, line) in data.lines().enumerate() {
+        if line.trim().is_empty() {
+            continue;
+        }
+        match parse_line(line) {
+            Ok(example) => examples.push(example),
+            Err(error) => errors.push(LineError {
+                line: idx + 1,
+                error,
+            }),
+        }
+    }
+    (examples, errors)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_openai_line_round_trips_through_test_case() {
+        let line = r#"{"messages": [
+            {"role": "system", "content": "Be terse."},
+            {"role": "user", "content": "Hi"},
+            {"role": "assistant", "content": "Hello."},
+            {"role": "user", "content": "Capital of France?"},
+            {"role": "assistant", "content": "Paris."}
+        ]}"#
+        .replace('\n', "");
+        let example = parse_line(&line).unwrap();
+        assert_eq!(example.input, "Capital of France?");
+        assert_eq!(example.expected_output.as_deref(), Some("Paris."));
+        assert_eq!(example.metadata[SYSTEM_KEY], "Be terse.");
+
+        let mut test_case = TestCase::new(1, example.input);
+        test_case.expected_output = example.expected_output;
+        test_case.metadata = example.metadata;
+
+        let exported = test_case_line(DatasetFormat::OpenAi, &test_case).unwrap();
+        let roles: Vec<&str> = exported["messages"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|m| m["role"].as_str().unwrap())
+            .collect();
+        assert_eq!(roles, ["system", "user", "assistant", "user", "assistant"]);
+        assert_eq!(exported["messages"][4]["content"], "Paris.");
+
+        let anthropic = test_case_line(DatasetFormat::Anthropic, &test_case).unwrap();
+        assert_eq!(anthropic["system"], "Be terse.");
+        assert_eq!(anthropic["messages"].as_array().unwrap().len(), 4);
+    }
+
+    #[test]
+    fn test_anthropic_and_native_lines() {
+        let anthropic = parse_line(
+            r#"{"system": "You are helpful.", "messages": [{"role": "user", "content": [{"type": "text", "text": "2+2?"}]}, {"role": "assistant", "content": "4"}]}"#,
+        )
+        .unwrap();
+        assert_eq!(anthropic.input, "2+2?");
+        assert_eq!(anthropic.expected_output.as_deref(), Some("4"));
+        assert_eq!(anthropic.metadata[SYSTEM_KEY], "You are helpful.");
+
+        let native =
+            parse_line(r#"{"input": "q", "metadata": {"tier": 2, "tag": "math"}}"#).unwrap();
+        assert_eq!(native.expected_output, None);
+        assert_eq!(native.metadata["tier"], "2");
+        assert_eq!(native.metadata["tag"], "math");
+    }
+
+    #[test]
+    fn test_parse_jsonl_reports_errors_per_line() {
+        let data = [
+            r#"{"input": "ok"}"#,
+            "",
+            "{not json",
+            r#"{"messages": [{"role": "assistant", "content": "no question"}]}"#,
+            r#"{"messages": [{"role": "bot", "content": "x"}]}"#,
+            r#"{"messages": [{"role": "user", "content": [{"type": "image"}]}]}"#,
+            r#"{"prompt": "legacy"}"#,
+        ]
+        .join("\n");
+
+        let (examples, errors) = parse_jsonl(&data);
+        assert_eq!(examples.len(), 1);
+        let lines: Vec<usize> = errors.iter().map(|e| e.line).collect();
+        assert_eq!(lines, [3, 4, 5, 6, 7]);
+        assert!(errors[0].error.starts_with("invalid JSON"));
+        assert_eq!(errors[1].error, "conversation has no user message");
+        assert!(errors[2].error.contains("unknown role 'bot'"));
+        assert!(errors[3].error.contains("unsupported block type 'image'"));
+    }
+
+    #[test]
+    fn test_openai_export_keeps_tool_calls() {
+        let messages = vec![
+            text_message("user", "Weather in Paris?"),
+            MessageV1 {
+                role: "assistant".to_string(),
+                content: vec![ContentPartV1::ToolUse {
+                    id: "call_1".to_string(),
+                    name: "get_weather".to_string(),
+                    arguments: Some(r#"{"city":"Paris"}"#.to_string()),
+                }],
+                name: None,
+                tool_call_id: None,
+                metadata: HashMap::new(),
+            },
+            MessageV1 {
+                role: "tool".to_string(),
+                content: vec![ContentPartV1::ToolResult {
+                    tool_call_id: "call_1".to_string(),
+                    content: Some("18C".to_string()),
+                }],
+                name: None,
+                tool_call_id: Some("call_1".to_string()),
+                metadata: HashMap::new(),
+            },
+            text_message("assistant", "18C and sunny."),
+        ];
+
+        let line = messages_to_openai(&messages);
+        let formatted = line["messages"].as_array().unwrap();
+        assert_eq!(formatted.len(), 4);
+        assert_eq!(formatted[1]["content"], Value::Null);
+        assert_eq!(
+            formatted[1]["tool_calls"][0]["function"]["name"],
+            "get_weather"
+        );
+        assert_eq!(formatted[2]["role"], "tool");
+        assert_eq!(formatted[2]["tool_call_id"], "call_1");
+
+        // Anthropic folds tool activity into alternating text turns
+        let line = messages_to_anthropic(&messages);
+        let roles: Vec<&str> = line["messages"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|m| m["role"].as_str().unwrap())
+            .collect();
+        assert_eq!(roles, ["user", "assistant", "user", "assistant"]);
+    }
+}
@@ -38,8 +38,9 @@
 //! Remote Code Execution (RCE) for tools is intentionally NOT supported.
 //! This is a security feature, not a limitation.
 
+use agentreplay_query::Agentreplay;
 use axum::{
-    extract::{Json, State},
+    extract::{Extension, Json, State},
     http::StatusCode,
 };
 use serde::{Deserialize, Serialize};
@@ -47,7 +48,9 @@ use std::collections::HashMap;
 use std::sync::Arc;
 use tracing::info;
 
+use crate::auth::AuthContext;
 use crate::llm::LLMProviderManager;
+use crate::vault::KeyVault;
 
 /// The type of span being debugged
 #[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
@@ -147,6 +150,9 @@ pub struct ReplayLLMRequest {
     pub max_tokens: Option<u32>,
     /// Additional context to prepend
     pub additional_context: Option<String>,
+    /// Vault key to replay with (default: the server's provider keys)
+    #[serde(default)]
+    pub key_alias: Option<String>,
 }
 
 /// Response from LLM replay
@@ -254,6 +260,8 @@ pub struct ForkedSpanInfo {
 #[derive(Clone)]
 pub struct DebugState {
     pub llm_provider: Arc<LLMProviderManager>,
+    pub vault: Arc<KeyVault>,
+    pub db: Arc<Agentreplay>,
     // In real implementation: add trace storage, etc.
 }
 
@@ -297,6 +305,7 @@ pub async fn check_replayable(
 /// Replay an LLM span with optional modifications
 pub async fn replay_llm(
     State(state): State<DebugState>,
+    Extension(auth): Extension<AuthContext>,
     Json(req): Json<ReplayLLMRequest>,
 ) -> Result<Json<ReplayLLMResponse>, (StatusCode, String)> {
     info!(
@@ -338,22 +347,57 @@ pub async fn replay_llm(
     // Execute LLM call using provider manager
     let start = std::time::Instant::now();
 
-    let result = state
-        .llm_provider
-        .chat(
-            provider_id,
-            Some(model_name.to_string()),
-            messages,
-            1, // tenant_id - would come from auth in real impl
-            1, // session_id
+    let key = req
+        .key_alias
+        .as_deref()
+        .map(|alias| state.vault.resolve(&state.db, auth.tenant_id, alias))
+        .transpose()
+        .map_err(|e| (StatusCode::BAD_REQUEST, e.to_string()))?;
+
+    let result = match &key {
+        Some(key) => {
+            state
+                .llm_provider
+                .chat_with_key(
+                    key,
+                    Some(model_name.to_string()),
+                    messages,
+                    auth.tenant_id,
+                    1, // session_id
+                )
+                .await
+        }
+        None => {
+            state
+                .llm_provider
+                .chat(
+                    provider_id,
+                    Some(model_name.to_string()),
+                    messages,
+                    auth.tenant_id,
+                    1, // session_id
+                )
+                .await
+        }
+    }
+    .map_err(|e| {
+        (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            format!("LLM execution failed: {}", e),
         )
-        .await
-        .map_err(|e| {
-            (
-                StatusCode::INTERNAL_SERVER_ERROR,
-                format!("LLM execution failed: {}", e),
-            )
-        })?;
+    })?;
+
+    if let Some(key) = &key {
+        state.vault.record_usage(
+            &state.db,
+            auth.tenant_id,
+            key,
+            "replay",
+            &result.model,
+            result.input_tokens.unwrap_or(0) as u64,
+            result.output_tokens.unwrap_or(0) as u64,
+        );
+    }
 
     let latency_ms = start.elapsed().as_millis() as u64;
 
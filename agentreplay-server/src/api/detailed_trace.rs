@@ -19,8 +19,10 @@ use axum::{
 };
 use serde::Serialize;
 
+use crate::access_policy::AccessFilter;
 use crate::api::payload_extractors::*;
 use crate::api::build_eval_trace_v1;
+use crate::api::hydration::{self, HydrationLevel};
 use crate::api::query::{find_edge_by_id_or_session, ApiError, AppState};
 use crate::auth::AuthContext;
 use crate::otel_genai::ModelPricing;
@@ -72,6 +74,22 @@ pub struct DetailedTraceResponse {
     // Canonical eval trace (EvalTraceV1)
     #[serde(skip_serializing_if = "Option::is_none")]
     pub eval_trace: Option<agentreplay_core::EvalTraceV1>,
+
+    // Character ranges evaluators flagged in this span
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub highlights: Vec<HighlightOutput>,
+}
+
+/// An evaluator's highlight, positioned in characters within one part of the span
+#[derive(Debug, Serialize)]
+pub struct HighlightOutput {
+    pub evaluator_id: String,
+    pub span_id: String,
+    pub trace_part: String,
+    pub start_offset: usize,
+    pub end_offset: usize,
+    pub severity: agentreplay_core::actionable_feedback::Severity,
+    pub message: String,
 }
 
 /// GET /api/v1/traces/:trace_id/detailed
@@ -80,33 +98,36 @@ pub async fn get_detailed_trace(
     State(state): State<AppState>,
     Path(trace_id): Path<String>,
     auth: axum::Extension<AuthContext>,
+    axum::Extension(access): axum::Extension<AccessFilter>,
 ) -> Result<Json<DetailedTraceResponse>, ApiError> {
     // Parse trace ID
     let trace_id_u128 = u128::from_str_radix(trace_id.trim_start_matches("0x"), 16)
         .map_err(|_| ApiError::BadRequest("Invalid trace ID format".into()))?;
 
     // Find edge (handling edge_id vs session_id mismatch)
-    let edge = find_edge_by_id_or_session(&state, trace_id_u128, auth.tenant_id)
+    let edge = find_edge_by_id_or_session(&state, trace_id_u128, auth.tenant_id, &access)
         .await?
         .ok_or_else(|| ApiError::NotFound("Trace not found".into()))?;
 
-    // Fetch payload
-    let payload = if edge.has_payload == 0 {
+    // Fetch payload, as far as the caller's role allows
+    let hydration = hydration::detail_hydration(&state.hydration, auth.role, &edge);
+    let payload = if edge.has_payload == 0 || hydration.level == HydrationLevel::Metadata {
         None
     } else {
         let payload_bytes = state.db.get_payload(edge.edge_id).ok().flatten();
         payload_bytes.and_then(|bytes| serde_json::from_slice(&bytes).ok())
     };
+    // Prompts, completions, tool calls and raw attributes need full hydration
+    let full_payload = payload
+        .as_ref()
+        .filter(|_| hydration.level == HydrationLevel::Full);
 
     // Extract structured data
-    let prompts = payload.as_ref().map(extract_prompts).unwrap_or_default();
+    let prompts = full_payload.map(extract_prompts).unwrap_or_default();
 
-    let completions = payload
-        .as_ref()
-        .map(extract_completions)
-        .unwrap_or_default();
+    let completions = full_payload.map(extract_completions).unwrap_or_default();
 
-    let tool_calls = payload.as_ref().map(extract_tool_calls).unwrap_or_default();
+    let tool_calls = full_payload.map(extract_tool_calls).unwrap_or_default();
 
     let hyperparameters = payload
         .as_ref()
@@ -128,6 +149,7 @@ pub async fn get_detailed_trace(
             total_tokens: 0,
             reasoning_tokens: None,
             cache_read_tokens: None,
+            cache_creation_tokens: None,
         });
 
     // Calculate cost
@@ -162,6 +184,28 @@ pub async fn get_detailed_trace(
     // Get agent name
     let agent_name = state.agent_registry.get_display_name(edge.agent_id);
 
+    let highlights = state
+        .db
+        .list_span_highlights(edge.edge_id)
+        .unwrap_or_else(|e| {
+            tracing::warn!("Failed to load span highlights: {}", e);
+            Vec::new()
+        })
+        .into_iter()
+        .flat_map(|record| {
+            let evaluator_id = record.evaluator_id;
+            record.highlights.into_iter().map(move |h| HighlightOutput {
+                evaluator_id: evaluator_id.clone(),
+                span_id: format!("{:#x}", h.span_id),
+                trace_part: h.trace_part,
+                start_offset: h.start_offset,
+                end_offset: h.end_offset,
+                severity: h.severity,
+                message: h.message,
+            })
+        })
+        .collect();
+
     let response = DetailedTraceResponse {
         // Basic trace info
         trace_id: format!("{:#x}", edge.session_id),
@@ -209,14 +253,23 @@ pub async fn get_detailed_trace(
         token_breakdown,
 
         // Previews
-        input_preview: payload.as_ref().and_then(get_input_preview),
-        output_preview: payload.as_ref().and_then(get_output_preview),
+        input_preview: hydration::redact_preview(
+            payload.as_ref().and_then(get_input_preview),
+            hydration,
+        ),
+        output_preview: hydration::redact_preview(
+            payload.as_ref().and_then(get_output_preview),
+            hydration,
+        ),
 
         // Raw attributes
-        attributes: payload.and_then(|p| serde_json::to_value(p).ok()),
+        attributes: full_payload.and_then(|p| serde_json::to_value(p).ok()),
+
+        // Canonical eval trace, which carries the span's inputs and outputs
+        eval_trace: (hydration.level == HydrationLevel::Full)
+            .then(|| build_eval_trace_v1(&state, &edge)),
 
-        // Canonical eval trace
-        eval_trace: Some(build_eval_trace_v1(&state, &edge)),
+        highlights,
     };
 
     Ok(Json(response))
@@ -0,0 +1,395 @@
+// Copyright 2025 AgentReplay (https://github.com/agentreplay)
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program. If not, see <https://www.gnu.org/licenses/>.
+
+//! API error responses
+//!
+//! Errors are returned as RFC 7807 problem details
+//! (`application/problem+json`) carrying a stable [`ErrorCode`] that SDKs
+//! branch on, and the request's correlation ID:
+//!
+//! ```json
+//! {
+//!   "type": "urn:agentreplay:error:not_found",
+//!   "title": "Not Found",
+//!   "status": 404,
+//!   "code": "not_found",
+//!   "detail": "Trace 0x2a not found",
+//!   "correlation_id": "5f0c3e9a1b7d4c22",
+//!   "error": "Trace 0x2a not found"
+//! }
+//! ```
+//!
+//! `error` repeats `detail` for clients written against the old
+//! `{"error": "..."}` body. The correlation ID is taken from an incoming
+//! `X-Request-Id` (or `X-Correlation-Id`) header or generated, echoed in
+//! the `X-Request-Id` response header and logged with internal errors.
+
+use std::future::Future;
+
+use axum::{
+    body::Body,
+    extract::Request,
+    http::{header, HeaderName, HeaderValue, StatusCode},
+    middleware::Next,
+    response::{IntoResponse, Response},
+};
+use rand::Rng;
+use serde::Serialize;
+
+/// Response header carrying the correlation ID
+pub const REQUEST_ID_HEADER: &str = "x-request-id";
+
+const CORRELATION_ID_HEADER: &str = "x-correlation-id";
+
+/// Longest client-supplied correlation ID that is kept
+const MAX_CORRELATION_ID_LEN: usize = 128;
+
+tokio::task_local! {
+    static CORRELATION_ID: String;
+}
+
+/// Stable, machine-readable error kinds
+///
+/// The string forms are part of the API; add new codes rather than
+/// renaming existing ones.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ErrorCode {
+    ValidationFailed,
+    Unauthenticated,
+    Forbidden,
+    NotFound,
+    RequestTimeout,
+    Conflict,
+    RateLimited,
+    QuotaExceeded,
+    QueueFull,
+    Unavailable,
+    Internal,
+    IdempotencyKeyReused,
+    PayloadTooLarge,
+}
+
+impl ErrorCode {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ErrorCode::ValidationFailed => "validation_failed",
+            ErrorCode::Unauthenticated => "unauthenticated",
+            ErrorCode::Forbidden => "forbidden",
+            ErrorCode::NotFound => "not_found",
+            ErrorCode::RequestTimeout => "request_timeout",
+            ErrorCode::Conflict => "conflict",
+            ErrorCode::RateLimited => "rate_limited",
+            ErrorCode::QuotaExceeded => "quota_exceeded",
+            ErrorCode::QueueFull => "queue_full",
+            ErrorCode::Unavailable => "unavailable",
+            ErrorCode::Internal => "internal",
+            ErrorCode::IdempotencyKeyReused => "idempotency_key_reused",
+            ErrorCode::PayloadTooLarge => "payload_too_large",
+        }
+    }
+
+    /// Status code used when the code is returned on its own
+    pub fn status(&self) -> StatusCode {
+        match self {
+            ErrorCode::ValidationFailed => StatusCode::BAD_REQUEST,
+            ErrorCode::Unauthenticated => StatusCode::UNAUTHORIZED,
+            ErrorCode::Forbidden => StatusCode::FORBIDDEN,
+            ErrorCode::NotFound => StatusCode::NOT_FOUND,
+            ErrorCode::RequestTimeout => StatusCode::REQUEST_TIMEOUT,
+            ErrorCode::Conflict => StatusCode::CONFLICT,
+            ErrorCode::RateLimited | ErrorCode::QuotaExceeded | ErrorCode::QueueFull => {
+                StatusCode::TOO_MANY_REQUESTS
+            }
+            ErrorCode::Unavailable => StatusCode::SERVICE_UNAVAILABLE,
+            ErrorCode::Internal => StatusCode::INTERNAL_SERVER_ERROR,
+            ErrorCode::IdempotencyKeyReused => StatusCode::UNPROCESSABLE_ENTITY,
+            ErrorCode::PayloadTooLarge => StatusCode::PAYLOAD_TOO_LARGE,
+        }
+    }
+}
+
+/// Error returned by API handlers
+#[derive(Debug, thiserror::Error)]
+pub enum ApiError {
+    #[error("Not found: {0}")]
+    NotFound(String),
+
+    #[error("Bad request: {0}")]
+    BadRequest(String),
+
+    #[error("Internal server error: {0}")]
+    Internal(String),
+
+    #[error("Unauthorized")]
+    Unauthorized,
+
+    #[error("Forbidden: {0}")]
+    Forbidden(String),
+
+    #[error("Request timeout: {0}")]
+    RequestTimeout(String),
+
+    #[error("Conflict: {0}")]
+    Conflict(String),
+
+    #[error("Quota exceeded: {0}")]
+    QuotaExceeded(crate::quotas::QuotaBreach),
+
+    #[error("Overloaded: {0:?}")]
+    Overloaded(crate::admission::RejectionReason),
+
+    /// An Idempotency-Key was sent again with a different request body
+    #[error("Idempotency-Key reused: {0}")]
+    IdempotencyKeyReused(String),
+
+    #[error("Payload too large: {0}")]
+    PayloadTooLarge(String),
+}
+
+impl ApiError {
+    pub fn code(&self) -> ErrorCode {
+        match self {
+            ApiError::NotFound(_) => ErrorCode::NotFound,
+            ApiError::BadRequest(_) => ErrorCode::ValidationFailed,
+            ApiError::Internal(_) => ErrorCode::Internal,
+            ApiError::Unauthorized => ErrorCode::Unauthenticated,
+            ApiError::Forbidden(_) => ErrorCode::Forbidden,
+            ApiError::RequestTimeout(_) => ErrorCode::RequestTimeout,
+            ApiError::Conflict(_) => ErrorCode::Conflict,
+            ApiError::QuotaExceeded(_) => ErrorCode::QuotaExceeded,
+            ApiError::Overloaded(reason) => reason.code(),
+            ApiError::IdempotencyKeyReused(_) => ErrorCode::IdempotencyKeyReused,
+            ApiError::PayloadTooLarge(_) => ErrorCode::PayloadTooLarge,
+        }
+    }
+}
+
+impl IntoResponse for ApiError {
+    fn into_response(self) -> Response {
+        let code = self.code();
+        let detail = match self {
+            ApiError::NotFound(msg)
+            | ApiError::BadRequest(msg)
+            | ApiError::Internal(msg)
+            | ApiError::Forbidden(msg)
+            | ApiError::RequestTimeout(msg)
+            | ApiError::Conflict(msg)
+            | ApiError::IdempotencyKeyReused(msg)
+            | ApiError::PayloadTooLarge(msg) => msg,
+            ApiError::Unauthorized => "Unauthorized".to_string(),
+            // Carry their own status code and Retry-After header
+            ApiError::QuotaExceeded(breach) => return breach.into_response(),
+            ApiError::Overloaded(reason) => return reason.into_response(),
+        };
+
+        if code == ErrorCode::Internal {
+            tracing::error!(
+                correlation_id = correlation_id().as_deref().unwrap_or("-"),
+                "Internal error: {}",
+                detail
+            );
+        }
+        ProblemDetails::new(code.status(), code, detail).into_response()
+    }
+}
+
+/// RFC 7807 problem details body
+#[derive(Debug, Clone, Serialize)]
+pub struct ProblemDetails {
+    #[serde(rename = "type")]
+    pub kind: String,
+    pub title: String,
+    pub status: u16,
+    pub code: ErrorCode,
+    pub detail: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub correlation_id: Option<String>,
+    /// Copy of `detail` for clients of the old error body
+    pub error: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub retry_after_ms: Option<u64>,
+    /// Unix seconds when an exhausted quota resets
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub reset_at: Option<u64>,
+}
+
+impl ProblemDetails {
+    /// Problem for the current request, picking up its correlation ID
+    pub fn new(status: StatusCode, code: ErrorCode, detail: impl Into<String>) -> Self {
+        let detail = detail.into();
+        Self {
+            kind: format!("urn:agentreplay:error:{}", code.as_str()),
+            title: status.canonical_reason().unwrap_or("Error").to_string(),
+            status: status.as_u16(),
+            code,
+            error: detail.clone(),
+            detail,
+            correlation_id: correlation_id(),
+            retry_after_ms: None,
+            reset_at: None,
+        }
+    }
+
+    /// Add a retry hint, also sent as `Retry-After` in whole seconds
+    pub fn with_retry_after_ms(mut self, retry_after_ms: u64) -> Self {
+        self.retry_after_ms = Some(retry_after_ms);
+        self
+    }
+
+    pub fn with_reset_at(mut self, reset_at: u64) -> Self {
+        self.reset_at = Some(reset_at);
+        self
+    }
+}
+
+impl IntoResponse for ProblemDetails {
+    fn into_response(self) -> Response {
+        let status = StatusCode::from_u16(self.status).unwrap_or(StatusCode::INTERNAL_SERVER_ERROR);
+        let mut builder = Response::builder().status(status).header(
+            header::CONTENT_TYPE,
+            HeaderValue::from_static("application/problem+json"),
+        );
+        if let Some(retry_after_ms) = self.retry_after_ms {
+            builder = builder.header(header::RETRY_AFTER, retry_after_ms.div_ceil(1000).max(1));
+        }
+        builder
+            .body(Body::from(serde_json::to_vec(&self).unwrap_or_default()))
+            .unwrap_or_else(|_| status.into_response())
+    }
+}
+
+/// Correlation ID of the request being handled, if any
+pub fn correlation_id() -> Option<String> {
+    CORRELATION_ID.try_with(|id| id.clone()).ok()
+}
+
+/// Run `f` with `id` as the correlation ID, for work outside a request
+pub async fn with_correlation_id<F: Future>(id: String, f: F) -> F::Output {
+    CORRELATION_ID.scope(id, f).await
+}
+
+fn incoming_correlation_id(request: &Request) -> Option<String> {
+    [REQUEST_ID_HEADER, CORRELATION_ID_HEADER]
+        .iter()
+        .filter_map(|name| request.headers().get(*name)?.to_str().ok())
+        .map(str::trim)
+        .find(|id| {
+            !id.is_empty()
+                && id.len() <= MAX_CORRELATION_ID_LEN
+                && id.bytes().all(|b| b.is_ascii_graphic())
+        })
+        .map(str::to_string)
+}
+
+fn new_correlation_id() -> String {
+    hex::encode(rand::thread_rng().gen::<[u8; 8]>())
+}
+
+/// Assign every request a correlation ID and echo it in `X-Request-Id`
+pub async fn correlation_middleware(request: Request, next: Next) -> Response {
+    let id = incoming_correlation_id(&request).unwrap_or_else(new_correlation_id);
+    let mut response = CORRELATION_ID.scope(id.clone(), next.run(request)).await;
+    if let Ok(value) = HeaderValue::from_str(&id) {
+        response
+            .headers_mut()
+            .insert(HeaderName::from_static(REQUEST_ID_HEADER), value);
+    }
+    response
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::admission::RejectionReason;
+
+    async fn body_json(response: Response) -> serde_json::Value {
+        let bytes = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        serde_json::from_slice(&bytes).unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_problem_details_body() {
+        let response = with_correlation_id("req-1".to_string(), async {
+            ApiError::NotFound("Trace 0x2a not found".to_string()).into_response()
+        })
+        .await;
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+        assert_eq!(
+            response.headers()[header::CONTENT_TYPE],
+            "application/problem+json"
+        );
+
+        let body = body_json(response).await;
+        assert_eq!(body["code"], "not_found");
+        assert_eq!(body["type"], "urn:agentreplay:error:not_found");
+        assert_eq!(body["status"], 404);
+        assert_eq!(body["detail"], "Trace 0x2a not found");
+        assert_eq!(body["error"], "Trace 0x2a not found");
+        assert_eq!(body["correlation_id"], "req-1");
+        assert!(body.get("retry_after_ms").is_none());
+
+        let body = body_json(ApiError::BadRequest("bad limit".to_string()).into_response()).await;
+        assert_eq!(body["code"], "validation_failed");
+        assert!(body.get("correlation_id").is_none());
+    }
+
+    #[tokio::test]
+    async fn test_overloaded_keeps_retry_after() {
+        let response = ApiError::Overloaded(RejectionReason::QueueFull {
+            retry_after_ms: 2_500,
+        })
+        .into_response();
+        assert_eq!(response.status(), StatusCode::TOO_MANY_REQUESTS);
+        assert_eq!(response.headers()[header::RETRY_AFTER], "3");
+
+        let body = body_json(response).await;
+        assert_eq!(body["code"], "queue_full");
+        assert_eq!(body["retry_after_ms"], 2_500);
+    }
+
+    #[test]
+    fn test_incoming_correlation_id() {
+        let request = |headers: &[(&str, &str)]| {
+            let mut builder = Request::builder();
+            for (name, value) in headers {
+                builder = builder.header(*name, *value);
+            }
+            builder.body(Body::empty()).unwrap()
+        };
+
+        assert_eq!(incoming_correlation_id(&request(&[])), None);
+        assert_eq!(
+            incoming_correlation_id(&request(&[("x-correlation-id", "abc-123")])),
+            Some("abc-123".to_string())
+        );
+        // Unusable request IDs fall through to the correlation header
+        let too_long = "a".repeat(MAX_CORRELATION_ID_LEN + 1);
+        assert_eq!(
+            incoming_correlation_id(&request(&[
+                ("x-request-id", too_long.as_str()),
+                ("x-correlation-id", "abc-123"),
+            ])),
+            Some("abc-123".to_string())
+        );
+        assert_eq!(
+            incoming_correlation_id(&request(&[("x-request-id", "has space")])),
+            None
+        );
+        assert_eq!(new_correlation_id().len(), 16);
+    }
+}
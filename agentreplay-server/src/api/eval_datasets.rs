@@ -13,13 +13,14 @@
 // You should have received a copy of the GNU Affero General Public License
 // along with this program. If not, see <https://www.gnu.org/licenses/>.
 
+use super::dataset_formats::{parse_jsonl, test_case_line, DatasetFormat, LineError};
 use super::query::AppState;
 use axum::{
-    extract::{Path, State},
+    extract::{Path, Query, State},
     http::StatusCode,
     Json,
 };
-use agentreplay_core::{EvalDataset, TaskDefinitionV2, TestCase};
+use agentreplay_core::{EvalDataset, ReferenceTrajectory, TaskDefinitionV2, TestCase};
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 
@@ -45,6 +46,9 @@ pub struct TestCaseInput {
     pub metadata: HashMap<String, String>,
     #[serde(default)]
     pub task_definition_v2: Option<TaskDefinitionV2>,
+    /// Tool calls the agent should make, checked when run results are added
+    #[serde(default)]
+    pub reference_trajectory: Option<ReferenceTrajectory>,
 }
 
 #[derive(Debug, Serialize)]
@@ -76,6 +80,8 @@ pub struct TestCaseOutput {
     pub metadata: HashMap<String, String>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub task_definition_v2: Option<TaskDefinitionV2>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub reference_trajectory: Option<ReferenceTrajectory>,
 }
 
 #[derive(Debug, Serialize)]
@@ -150,6 +156,7 @@ fn dataset_to_detail_response(dataset: &EvalDataset) -> DatasetDetailResponse {
                 expected_output: tc.expected_output.clone(),
                 metadata: tc.metadata.clone(),
                 task_definition_v2: tc.task_definition_v2.clone(),
+                reference_trajectory: tc.reference_trajectory.clone(),
             })
             .collect(),
         created_at: dataset.created_at,
@@ -184,6 +191,7 @@ pub async fn create_dataset(
             task_definition.ensure_task_id();
             test_case.task_definition_v2 = Some(task_definition);
         }
+        test_case.reference_trajectory = tc_input.reference_trajectory;
         dataset.add_test_case(test_case);
     }
 
@@ -333,6 +341,127 @@ pub async fn add_examples(
     }))
 }
 
+#[derive(Debug, Deserialize)]
+pub struct ExportDatasetQuery {
+    /// openai, anthropic or agentreplay
+    #[serde(default = "default_export_format")]
+    pub format: String,
+}
+
+fn default_export_format() -> String {
+    DatasetFormat::OpenAi.as_str().to_string()
+}
+
+#[derive(Debug, Serialize)]
+pub struct ExportDatasetResponse {
+    pub format: String,
+    pub jsonl: String,
+    pub exported: usize,
+    /// Test cases without an expected output (nothing to fine-tune on)
+    pub skipped: usize,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ImportDatasetRequest {
+    /// JSONL in openai, anthropic or agentreplay format (detected per line)
+    pub data: String,
+    /// Import the valid lines even if others fail validation
+    #[serde(default)]
+    pub skip_invalid: bool,
+    /// Validate only, without storing anything
+    #[serde(default)]
+    pub dry_run: bool,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ImportDatasetResponse {
+    pub success: bool,
+    pub imported: usize,
+    pub errors: Vec<LineError>,
+}
+
+/// GET /api/v1/evals/datasets/:id/export?format=openai
+/// Export a dataset as fine-tuning JSONL
+pub async fn export_dataset(
+    State(state): State<AppState>,
+    Path(id): Path<String>,
+    Query(query): Query<ExportDatasetQuery>,
+) -> Result<Json<ExportDatasetResponse>, (StatusCode, String)> {
+    let dataset_id = parse_dataset_id(&id).map_err(|e| (StatusCode::BAD_REQUEST, e))?;
+    let format = DatasetFormat::parse(&query.format).ok_or_else(|| {
+        (
+            StatusCode::BAD_REQUEST,
+            format!("Unknown export format: {}", query.format),
+        )
+    })?;
+
+    let dataset = state
+        .db
+        .get_eval_dataset(dataset_id)
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?
+        .ok_or_else(|| (StatusCode::NOT_FOUND, "Dataset not found".to_string()))?;
+
+    let lines: Vec<String> = dataset
+        .test_cases
+        .iter()
+        .filter_map(|tc| test_case_line(format, tc))
+        .map(|line| line.to_string())
+        .collect();
+    let exported = lines.len();
+
+    Ok(Json(ExportDatasetResponse {
+        format: format.as_str().to_string(),
+        jsonl: lines.join("\n"),
+        exported,
+        skipped: dataset.test_cases.len() - exported,
+    }))
+}
+
+/// POST /api/v1/evals/datasets/:id/import
+/// Import JSONL examples into an existing dataset, reporting errors per line
+pub async fn import_dataset(
+    State(state): State<AppState>,
+    Path(id): Path<String>,
+    Json(req): Json<ImportDatasetRequest>,
+) -> Result<Json<ImportDatasetResponse>, (StatusCode, String)> {
+    let dataset_id = parse_dataset_id(&id).map_err(|e| (StatusCode::BAD_REQUEST, e))?;
+
+    let mut dataset = state
+        .db
+        .get_eval_dataset(dataset_id)
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?
+        .ok_or_else(|| (StatusCode::NOT_FOUND, "Dataset not found".to_string()))?;
+
+    let (examples, errors) = parse_jsonl(&req.data);
+    if req.dry_run || (!errors.is_empty() && !req.skip_invalid) {
+        return Ok(Json(ImportDatasetResponse {
+            success: errors.is_empty(),
+            imported: 0,
+            errors,
+        }));
+    }
+
+    let imported = examples.len();
+    for example in examples {
+        let mut test_case = TestCase::new(generate_id(), example.input);
+        test_case.expected_output = example.expected_output;
+        test_case.metadata = example.metadata;
+        dataset.add_test_case(test_case);
+    }
+    dataset.updated_at = current_timestamp_us();
+
+    state
+        .db
+        .store_eval_dataset(dataset)
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+
+    Ok(Json(ImportDatasetResponse {
+        success: errors.is_empty(),
+        imported,
+        errors,
+    }))
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -23,12 +23,15 @@
 //! 5. Iterate - Results analysis and recommendations
 
 use super::query::AppState;
+use crate::auth::AuthContext;
+use crate::scripting::ScriptHook;
 use axum::{
-    extract::{Query, State},
+    extract::{Extension, Query, State},
     http::StatusCode,
     Json,
 };
 use agentreplay_core::AgentFlowEdge;
+use agentreplay_query::TenantScope;
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 use std::sync::Arc;
@@ -234,6 +237,7 @@ pub struct CollectionSummary {
 /// POST /api/v1/evals/pipeline/collect
 pub async fn collect_traces(
     State(state): State<AppState>,
+    Extension(auth): Extension<AuthContext>,
     Json(req): Json<CollectTracesRequest>,
 ) -> Result<Json<CollectTracesResponse>, (StatusCode, String)> {
     let now = current_timestamp_us();
@@ -244,7 +248,8 @@ pub async fn collect_traces(
     // Fetch traces from database
     let edges = state
         .db
-        .list_traces_in_range(start_time, end_time)
+        .scoped(TenantScope::Tenant(auth.tenant_id))
+        .range(start_time, end_time)
         .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
 
     // Group edges by trace_id
@@ -454,6 +459,7 @@ pub struct ProcessingStats {
 /// POST /api/v1/evals/pipeline/process
 pub async fn process_traces(
     State(state): State<AppState>,
+    Extension(auth): Extension<AuthContext>,
     Json(req): Json<ProcessTracesRequest>,
 ) -> Result<Json<ProcessTracesResponse>, (StatusCode, String)> {
     let start = std::time::Instant::now();
@@ -466,7 +472,8 @@ pub async fn process_traces(
     let now = current_timestamp_us();
     let all_edges = state
         .db
-        .list_traces_in_range(0, now)
+        .scoped(TenantScope::Tenant(auth.tenant_id))
+        .range(0, now)
         .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
 
     // Parse and process trace data
@@ -852,6 +859,7 @@ pub async fn add_golden_test_cases(
             expected_output: Some(tc.expected_output),
             metadata: tc.metadata.unwrap_or_default(),
             task_definition_v2: None,
+            reference_trajectory: None,
         };
         dataset.add_test_case(test_case);
         added_count += 1;
@@ -925,6 +933,7 @@ pub struct BaselineComparison {
 /// POST /api/v1/evals/pipeline/evaluate
 pub async fn run_evaluation(
     State(state): State<AppState>,
+    Extension(auth): Extension<AuthContext>,
     Json(req): Json<RunEvaluationRequest>,
 ) -> Result<Json<EvaluationResults>, (StatusCode, String)> {
     let run_id = format!("0x{:x}", generate_id());
@@ -942,7 +951,8 @@ pub async fn run_evaluation(
 
         let edges = state
             .db
-            .list_traces_in_range(0, now)
+            .scoped(TenantScope::Tenant(auth.tenant_id))
+            .range(0, now)
             .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
 
         let trace_edges: Vec<_> = edges
@@ -1153,7 +1163,7 @@ pub async fn run_evaluation(
         HealthStatus::Healthy
     };
 
-    Ok(Json(EvaluationResults {
+    let results = EvaluationResults {
         run_id,
         timestamp: now,
         trace_count: req.trace_ids.len(),
@@ -1162,7 +1172,16 @@ pub async fn run_evaluation(
         overall_health,
         alerts,
         comparison: None, // TODO: Implement baseline comparison
-    }))
+    };
+
+    state
+        .scripts
+        .fire(auth.tenant_id, ScriptHook::OnEvalComplete, &results);
+    for alert in &results.alerts {
+        state.scripts.fire(auth.tenant_id, ScriptHook::OnAlert, alert);
+    }
+
+    Ok(Json(results))
 }
 
 // ============================================================================
@@ -14,15 +14,18 @@
 // along with this program. If not, see <https://www.gnu.org/licenses/>.
 
 use super::query::AppState;
+use crate::auth::AuthContext;
 use axum::{
     extract::{Path, Query, State},
     http::StatusCode,
-    Json,
+    Extension, Json,
 };
 use agentreplay_core::{
     EvalRun, EvalTraceV1, GraderResult, OverallResult, RunResult, TaskAggregate, TraceRefV1,
     TranscriptEventV1,
 };
+use agentreplay_evals::evaluators::TrajectoryMatchEvaluator;
+use agentreplay_evals::{Evaluator, TraceContext};
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 
@@ -569,6 +572,19 @@ pub async fn add_run_result(
     result.cost_usd = req.cost_usd;
     result.latency_ms = req.latency_ms;
 
+    if let Some(trace_id) = trace_id {
+        if let Some(grader) = grade_trajectory(&state, run_id, test_case_id, trace_id).await? {
+            result
+                .eval_metrics
+                .insert("trajectory_match".to_string(), grader.score.unwrap_or(0.0));
+            if !grader.passed && result.passed {
+                result.passed = false;
+                result.error = grader.rationale.clone();
+            }
+            result.grader_results.push(grader);
+        }
+    }
+
     state
         .db
         .update_eval_run(run_id, |run| {
@@ -586,10 +602,73 @@ pub async fn add_run_result(
     Ok(Json(run_to_detail_response(&state, &run)))
 }
 
+/// Grade a trace's tool calls against its test case's reference trajectory
+///
+/// `None` when the test case has no reference trajectory or the trace is
+/// not stored.
+async fn grade_trajectory(
+    state: &AppState,
+    run_id: u128,
+    test_case_id: u128,
+    trace_id: u128,
+) -> Result<Option<GraderResult>, (StatusCode, String)> {
+    let run = state
+        .db
+        .get_eval_run(run_id)
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?
+        .ok_or_else(|| (StatusCode::NOT_FOUND, "Run not found".to_string()))?;
+    let reference = state
+        .db
+        .get_eval_dataset(run.dataset_id)
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?
+        .and_then(|dataset| dataset.find_test_case(test_case_id).cloned())
+        .and_then(|test_case| test_case.reference_trajectory);
+    let Some(reference) = reference else {
+        return Ok(None);
+    };
+    let Some(edge) = state.db.get(trace_id).ok().flatten() else {
+        return Ok(None);
+    };
+
+    let trace = TraceContext {
+        trace_id,
+        eval_trace: Some(crate::api::build_eval_trace_v1(state, &edge)),
+        timestamp_us: edge.timestamp_us,
+        edges: vec![edge],
+        input: None,
+        output: None,
+        context: None,
+        metadata: HashMap::new(),
+    };
+    let evaluator = TrajectoryMatchEvaluator::new().with_reference(reference);
+    let eval_result = evaluator.evaluate(&trace).await.map_err(|e| {
+        (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            format!("Trajectory evaluation failed: {}", e),
+        )
+    })?;
+
+    Ok(Some(GraderResult {
+        grader_id: evaluator.id().to_string(),
+        grader_type: "trajectory".to_string(),
+        weight: None,
+        score: match eval_result.metrics.get("score") {
+            Some(agentreplay_evals::MetricValue::Float(score)) => Some(*score),
+            _ => None,
+        },
+        passed: eval_result.passed,
+        assertions: eval_result.assertions,
+        judge_votes: Vec::new(),
+        rationale: eval_result.explanation,
+        evidence_refs: eval_result.evidence_refs,
+    }))
+}
+
 /// POST /api/v1/evals/runs/:id/status
 /// Update the status of an evaluation run (complete, fail, stop)
 pub async fn update_run_status(
     State(state): State<AppState>,
+    Extension(auth): Extension<AuthContext>,
     Path(id): Path<String>,
     Json(req): Json<UpdateRunStatusRequest>,
 ) -> Result<Json<RunDetailResponse>, (StatusCode, String)> {
@@ -616,7 +695,16 @@ pub async fn update_run_status(
         .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?
         .ok_or_else(|| (StatusCode::NOT_FOUND, "Run not found".to_string()))?;
 
-    Ok(Json(run_to_detail_response(&state, &run)))
+    let response = run_to_detail_response(&state, &run);
+    if req.status.eq_ignore_ascii_case("completed") {
+        state.scripts.fire(
+            auth.tenant_id,
+            crate::scripting::ScriptHook::OnEvalComplete,
+            &response,
+        );
+    }
+
+    Ok(Json(response))
 }
 
 /// DELETE /api/v1/evals/runs/:id
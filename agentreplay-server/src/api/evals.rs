@@ -21,8 +21,11 @@ use axum::{
     extract::{Query, State},
     Json,
 };
-use agentreplay_core::{evaluators, EvalMetric};
+use agentreplay_core::actionable_feedback::Severity;
+use agentreplay_core::{evaluators, EvalMetric, SpanHighlight};
+use agentreplay_storage::SpanHighlightRecord;
 use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
 use std::time::{SystemTime, UNIX_EPOCH};
 
 use super::query::{ApiError, AppState};
@@ -35,6 +38,10 @@ pub struct StoreEvalMetricsRequest {
 
     /// List of metrics to store
     pub metrics: Vec<EvalMetricInput>,
+
+    /// Optional span-level highlights backing the metrics
+    #[serde(default)]
+    pub highlights: Vec<HighlightInput>,
 }
 
 /// Single evaluation metric input
@@ -55,6 +62,40 @@ fn default_evaluator() -> String {
     evaluators::CUSTOM.to_string()
 }
 
+/// Character range an evaluator flagged within a span
+#[derive(Debug, Deserialize)]
+pub struct HighlightInput {
+    /// Span the range belongs to (hex string, defaults to the request's edge_id)
+    #[serde(default)]
+    pub span_id: Option<String>,
+
+    /// Which text of the span: "input", "output" or "tool_arguments"
+    #[serde(default = "default_trace_part")]
+    pub trace_part: String,
+
+    /// Start of the range in characters (inclusive)
+    pub start_offset: usize,
+
+    /// End of the range in characters (exclusive)
+    pub end_offset: usize,
+
+    #[serde(default = "default_severity")]
+    pub severity: Severity,
+
+    pub message: String,
+
+    #[serde(default = "default_evaluator")]
+    pub evaluator: String,
+}
+
+fn default_trace_part() -> String {
+    SpanHighlight::OUTPUT.to_string()
+}
+
+fn default_severity() -> Severity {
+    Severity::Minor
+}
+
 /// Query parameters for retrieving evaluation metrics
 #[derive(Debug, Deserialize)]
 pub struct GetEvalMetricsParams {
@@ -121,6 +162,8 @@ pub async fn store_eval_metrics(
 
     // Count metrics before consuming the vector
     let metrics_count = req.metrics.len();
+    let highlights_count = req.highlights.len();
+    let highlight_records = group_highlights(edge_id, req.highlights, timestamp_us)?;
 
     // Convert input metrics to EvalMetric structs
     let mut eval_metrics = Vec::new();
@@ -145,16 +188,78 @@ pub async fn store_eval_metrics(
     // Store metrics
     state
         .db
-        .store_eval_metrics(edge_id, eval_metrics)
+        .store_eval_metrics(edge_id, eval_metrics.clone())
         .map_err(|e| ApiError::Internal(format!("Failed to store eval metrics: {}", e)))?;
+    crate::session_summary::record_eval_metrics(&state, edge_id, &eval_metrics);
+
+    for record in &highlight_records {
+        state
+            .db
+            .put_span_highlights(record)
+            .map_err(|e| ApiError::Internal(format!("Failed to store span highlights: {}", e)))?;
+    }
 
     Ok(Json(serde_json::json!({
         "success": true,
         "edge_id": format!("0x{:x}", edge_id),
         "metrics_stored": metrics_count,
+        "highlights_stored": highlights_count,
     })))
 }
 
+/// Validate highlight inputs and group them into one record per (span, evaluator)
+fn group_highlights(
+    edge_id: u128,
+    inputs: Vec<HighlightInput>,
+    created_at: u64,
+) -> Result<Vec<SpanHighlightRecord>, ApiError> {
+    let mut grouped: BTreeMap<(u128, String), Vec<SpanHighlight>> = BTreeMap::new();
+    for input in inputs {
+        if input.start_offset >= input.end_offset {
+            return Err(ApiError::BadRequest(format!(
+                "Highlight range {}..{} is empty",
+                input.start_offset, input.end_offset
+            )));
+        }
+        if ![
+            SpanHighlight::INPUT,
+            SpanHighlight::OUTPUT,
+            SpanHighlight::TOOL_ARGUMENTS,
+        ]
+        .contains(&input.trace_part.as_str())
+        {
+            return Err(ApiError::BadRequest(format!(
+                "Unknown highlight trace_part: {}",
+                input.trace_part
+            )));
+        }
+        let span_id = match &input.span_id {
+            Some(span_id) => parse_edge_id(span_id)?,
+            None => edge_id,
+        };
+        grouped
+            .entry((span_id, input.evaluator))
+            .or_default()
+            .push(SpanHighlight::new(
+                span_id,
+                &input.trace_part,
+                input.start_offset..input.end_offset,
+                input.severity,
+                input.message,
+            ));
+    }
+
+    Ok(grouped
+        .into_iter()
+        .map(|((span_id, evaluator), highlights)| SpanHighlightRecord {
+            span_id,
+            evaluator_id: evaluator,
+            highlights,
+            created_at,
+        })
+        .collect())
+}
+
 /// GET /api/v1/evals/metrics - Retrieve evaluation metrics
 ///
 /// # Example
@@ -273,4 +378,43 @@ mod tests {
         assert_eq!(output.evaluator, "ragas");
         assert_eq!(output.timestamp_us, 1234567890000000);
     }
+
+    fn input(span_id: Option<&str>, evaluator: &str, range: (usize, usize)) -> HighlightInput {
+        HighlightInput {
+            span_id: span_id.map(str::to_string),
+            trace_part: default_trace_part(),
+            start_offset: range.0,
+            end_offset: range.1,
+            severity: default_severity(),
+            message: "flagged".to_string(),
+            evaluator: evaluator.to_string(),
+        }
+    }
+
+    #[test]
+    fn test_group_highlights() {
+        let records = group_highlights(
+            0x10,
+            vec![
+                input(None, "ragas", (0, 4)),
+                input(Some("0x10"), "ragas", (8, 12)),
+                input(Some("0x11"), "ragas", (0, 2)),
+                input(None, "custom", (1, 3)),
+            ],
+            42,
+        )
+        .unwrap();
+        assert_eq!(records.len(), 3);
+        let ragas = records
+            .iter()
+            .find(|r| r.span_id == 0x10 && r.evaluator_id == "ragas")
+            .unwrap();
+        assert_eq!(ragas.highlights.len(), 2);
+        assert_eq!(ragas.created_at, 42);
+
+        assert!(group_highlights(0x10, vec![input(None, "ragas", (4, 4))], 0).is_err());
+        let mut unknown = input(None, "ragas", (0, 1));
+        unknown.trace_part = "metadata".to_string();
+        assert!(group_highlights(0x10, vec![unknown], 0).is_err());
+    }
 }
@@ -17,16 +17,27 @@
 //!
 //! Integrates G-Eval and RAGAS evaluators to assess LLM outputs
 
-use super::query::AppState;
+use super::query::{ApiError, AppState};
+use crate::auth::AuthContext;
+use crate::llm::local::DEFAULT_OLLAMA_BASE_URL;
+use crate::vault::ResolvedKey;
+use agentreplay_evals::evaluators::RagSuiteReport;
+use agentreplay_query::TenantScope;
 use axum::{
-    extract::{Path, State},
+    extract::{Extension, Path, State},
     http::StatusCode,
     Json,
 };
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
+use std::sync::atomic::{AtomicU64, Ordering};
 use std::time::Instant;
 
+const OPENAI_API_BASE: &str = "https://api.openai.com/v1";
+
+/// Judge models named `ollama/<model>` run on the local Ollama server
+const LOCAL_JUDGE_PREFIX: &str = "ollama/";
+
 /// Request to run G-Eval evaluation
 #[derive(Debug, Deserialize)]
 pub struct GEvalRequest {
@@ -42,17 +53,171 @@ pub struct GEvalRequest {
     pub output: Option<String>,
     #[serde(default)]
     pub context: Option<String>,
+    /// Vault key for the judge model (default: the server's OPENAI_API_KEY)
+    #[serde(default)]
+    pub key_alias: Option<String>,
 }
 
 /// Request to run RAGAS evaluation
 #[derive(Debug, Deserialize)]
 pub struct RagasRequest {
     pub trace_id: String,
-    pub question: String,
-    pub answer: String,
-    pub context: Vec<String>,
+    /// Question, answer and retrieved chunks left out are read from the trace
+    #[serde(default)]
+    pub question: Option<String>,
+    #[serde(default)]
+    pub answer: Option<String>,
+    #[serde(default)]
+    pub context: Option<Vec<String>>,
     pub ground_truth: Option<String>,
     pub model: Option<String>,
+    /// Vault key for the judge model (default: the server's OPENAI_API_KEY)
+    #[serde(default)]
+    pub key_alias: Option<String>,
+    /// Run the LLM-judged metrics; without them only the rule-based suite
+    /// runs and no key is needed
+    #[serde(default = "default_llm_judge")]
+    pub llm_judge: bool,
+    /// Share of an answer sentence's content words a chunk must contain to
+    /// support it
+    #[serde(default = "default_attribution_threshold")]
+    pub attribution_threshold: f64,
+}
+
+fn default_llm_judge() -> bool {
+    true
+}
+
+fn default_attribution_threshold() -> f64 {
+    0.5
+}
+
+/// OpenAI-compatible endpoint and credentials of the LLM judge, with the
+/// tokens its calls used
+pub(crate) struct LlmJudge {
+    model: String,
+    api_key: String,
+    base_url: String,
+    /// Vault key the judge's usage is attributed to
+    key: Option<ResolvedKey>,
+    input_tokens: AtomicU64,
+    output_tokens: AtomicU64,
+}
+
+impl LlmJudge {
+    /// Judge authenticated with a tenant's vault key, or the server's
+    /// `OPENAI_API_KEY`
+    ///
+    /// An `ollama/<model>` judge needs neither: it runs offline through
+    /// Ollama's OpenAI-compatible endpoint.
+    pub(crate) fn resolve(
+        state: &AppState,
+        tenant_id: u64,
+        key_alias: Option<&str>,
+        model: String,
+    ) -> Result<Self, (StatusCode, String)> {
+        if let Some(local_model) = model.strip_prefix(LOCAL_JUDGE_PREFIX) {
+            if key_alias.is_some() {
+                return Err((
+                    StatusCode::BAD_REQUEST,
+                    format!("Local judge {} does not take a key", model),
+                ));
+            }
+            let base_url = state
+                .llm_manager
+                .as_ref()
+                .and_then(|manager| manager.local_models())
+                .map(|local| local.openai_base_url())
+                .unwrap_or_else(|| format!("{}/v1", DEFAULT_OLLAMA_BASE_URL));
+            return Ok(Self {
+                model: local_model.to_string(),
+                // Ollama ignores the key, but OpenAI clients always send one
+                api_key: "ollama".to_string(),
+                base_url,
+                key: None,
+                input_tokens: AtomicU64::new(0),
+                output_tokens: AtomicU64::new(0),
+            });
+        }
+
+        let (api_key, base_url, key) = match key_alias {
+            Some(alias) => {
+                let key = state
+                    .vault
+                    .resolve(&state.db, tenant_id, alias)
+                    .map_err(|e| {
+                        let status = match e {
+                            ApiError::Internal(_) => StatusCode::INTERNAL_SERVER_ERROR,
+                            _ => StatusCode::BAD_REQUEST,
+                        };
+                        (status, e.to_string())
+                    })?;
+                if key.provider != "openai" {
+                    return Err((
+                        StatusCode::BAD_REQUEST,
+                        format!(
+                            "LLM-judge evaluators need an openai key, '{}' is {}",
+                            alias, key.provider
+                        ),
+                    ));
+                }
+                let base_url = key
+                    .base_url
+                    .clone()
+                    .unwrap_or_else(|| OPENAI_API_BASE.to_string());
+                (key.api_key.clone(), base_url, Some(key))
+            }
+            None => {
+                let api_key = std::env::var("OPENAI_API_KEY").map_err(|_| {
+                    (
+                        StatusCode::INTERNAL_SERVER_ERROR,
+                        "Evaluation failed: OPENAI_API_KEY not set".to_string(),
+                    )
+                })?;
+                (api_key, OPENAI_API_BASE.to_string(), None)
+            }
+        };
+        Ok(Self {
+            model,
+            api_key,
+            base_url,
+            key,
+            input_tokens: AtomicU64::new(0),
+            output_tokens: AtomicU64::new(0),
+        })
+    }
+
+    fn chat_completions_url(&self) -> String {
+        format!("{}/chat/completions", self.base_url.trim_end_matches('/'))
+    }
+
+    /// Add the token usage reported in a chat completion response
+    fn record_usage(&self, response: &serde_json::Value) {
+        let usage = &response["usage"];
+        self.input_tokens.fetch_add(
+            usage["prompt_tokens"].as_u64().unwrap_or(0),
+            Ordering::Relaxed,
+        );
+        self.output_tokens.fetch_add(
+            usage["completion_tokens"].as_u64().unwrap_or(0),
+            Ordering::Relaxed,
+        );
+    }
+
+    /// Attribute the judge's usage to its vault key, if any
+    pub(crate) fn finish(&self, state: &AppState, tenant_id: u64) {
+        if let Some(key) = &self.key {
+            state.vault.record_usage(
+                &state.db,
+                tenant_id,
+                key,
+                "evaluator",
+                &self.model,
+                self.input_tokens.load(Ordering::Relaxed),
+                self.output_tokens.load(Ordering::Relaxed),
+            );
+        }
+    }
 }
 
 /// Evaluation result response
@@ -72,6 +237,15 @@ pub struct EvaluationResponse {
     pub cost_usd: Option<f64>,
 }
 
+/// RAGAS scores with the sentence attributions and citation checks of the
+/// rule-based suite
+#[derive(Debug, Serialize)]
+pub struct RagasResponse {
+    #[serde(flatten)]
+    pub evaluation: EvaluationResponse,
+    pub suite: RagSuiteReport,
+}
+
 /// Extended evaluation response with per-criterion breakdown
 #[derive(Debug, Serialize)]
 pub struct GEvalDetailedResponse {
@@ -96,10 +270,35 @@ pub struct CriterionScore {
     pub reasoning: String,
 }
 
+/// Edges of a trace, matched by edge id or the trace's upper 64 bits
+fn load_trace_edges(
+    state: &AppState,
+    tenant_id: u64,
+    trace_id: u128,
+) -> Result<Vec<agentreplay_core::AgentFlowEdge>, (StatusCode, String)> {
+    let now = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .unwrap()
+        .as_micros() as u64;
+    let scoped = state.db.scoped(TenantScope::Tenant(tenant_id));
+    let edges = scoped.range(0, now).map_err(|e| {
+        (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            format!("Failed to fetch traces: {}", e),
+        )
+    })?;
+
+    Ok(edges
+        .into_iter()
+        .filter(|e| e.edge_id == trace_id || (e.edge_id >> 64) as u64 == (trace_id >> 64) as u64)
+        .collect())
+}
+
 /// POST /api/v1/evals/geval
 /// Run G-Eval on a trace with actual LLM-as-judge evaluation
 pub async fn run_geval(
     State(state): State<AppState>,
+    Extension(auth): Extension<AuthContext>,
     Json(req): Json<GEvalRequest>,
 ) -> Result<Json<EvaluationResponse>, (StatusCode, String)> {
     let start = Instant::now();
@@ -117,25 +316,7 @@ pub async fn run_geval(
             req.context.clone().unwrap_or_default(),
         )
     } else {
-        // Fetch trace from database using list_traces_in_range
-        let now = std::time::SystemTime::now()
-            .duration_since(std::time::UNIX_EPOCH)
-            .unwrap()
-            .as_micros() as u64;
-        let edges = state.db.list_traces_in_range(0, now).map_err(|e| {
-            (
-                StatusCode::INTERNAL_SERVER_ERROR,
-                format!("Failed to fetch traces: {}", e),
-            )
-        })?;
-
-        // Filter edges for this trace by matching edge_id pattern
-        let trace_edges: Vec<_> = edges
-            .iter()
-            .filter(|e| {
-                e.edge_id == trace_id || (e.edge_id >> 64) as u64 == (trace_id >> 64) as u64
-            })
-            .collect();
+        let trace_edges = load_trace_edges(&state, auth.tenant_id, trace_id)?;
 
         if trace_edges.is_empty() {
             // If no trace found, use placeholder values for evaluation
@@ -170,17 +351,25 @@ pub async fn run_geval(
         .clone()
         .unwrap_or_else(|| "gpt-4o-mini".to_string());
 
+    let judge = LlmJudge::resolve(
+        &state,
+        auth.tenant_id,
+        req.key_alias.as_deref(),
+        model.clone(),
+    )?;
+
     // Build evaluation prompt and run G-Eval
-    let (scores, detail_explanations, explanation, confidence) = run_geval_evaluation(
+    let result = run_geval_evaluation(
         &input,
         &output,
         &context,
         &req.criteria,
         &req.weights,
-        &model,
+        &judge,
     )
-    .await
-    .map_err(|e| {
+    .await;
+    judge.finish(&state, auth.tenant_id);
+    let (scores, detail_explanations, explanation, confidence) = result.map_err(|e| {
         (
             StatusCode::INTERNAL_SERVER_ERROR,
             format!("Evaluation failed: {}", e),
@@ -215,6 +404,7 @@ pub async fn run_geval(
     );
     if let Some(metric) = eval_metric {
         let _ = state.db.store_eval_metrics(trace_id, vec![metric]);
+        crate::session_summary::record_eval_metrics(&state, trace_id, &[metric]);
     }
 
     let duration = start.elapsed();
@@ -236,11 +426,13 @@ pub async fn run_geval(
 }
 
 /// POST /api/v1/evals/ragas
-/// Run RAGAS evaluation on a RAG trace
+/// Run RAGAS evaluation on a RAG trace, with sentence-level attribution of
+/// the answer to the retrieved chunks and verification of its quotes
 pub async fn run_ragas(
     State(state): State<AppState>,
-    Json(req): Json<RagasRequest>,
-) -> Result<Json<EvaluationResponse>, (StatusCode, String)> {
+    Extension(auth): Extension<AuthContext>,
+    Json(mut req): Json<RagasRequest>,
+) -> Result<Json<RagasResponse>, (StatusCode, String)> {
     let start = Instant::now();
 
     // Parse trace_id
@@ -248,26 +440,110 @@ pub async fn run_ragas(
     let trace_id = u128::from_str_radix(trace_id_str, 16)
         .map_err(|e| (StatusCode::BAD_REQUEST, format!("Invalid trace_id: {}", e)))?;
 
-    let model = req
-        .model
-        .clone()
-        .unwrap_or_else(|| "gpt-4o-mini".to_string());
+    // Fill what the request leaves out from the trace's stored spans
+    let (question, answer, context) =
+        if req.question.is_some() && req.answer.is_some() && req.context.is_some() {
+            (
+                req.question.take().unwrap_or_default(),
+                req.answer.take().unwrap_or_default(),
+                req.context.take().unwrap_or_default(),
+            )
+        } else {
+            let trace_edges = load_trace_edges(&state, auth.tenant_id, trace_id)?;
+            if trace_edges.is_empty() {
+                return Err((
+                    StatusCode::NOT_FOUND,
+                    format!("Trace {} not found", req.trace_id),
+                ));
+            }
+            let question = req.question.take().or_else(|| {
+                trace_edges
+                    .first()
+                    .and_then(|e| extract_input_from_edge_with_db(e, &state.db))
+            });
+            let answer = req.answer.take().or_else(|| {
+                trace_edges
+                    .last()
+                    .and_then(|e| extract_output_from_edge_with_db(e, &state.db))
+            });
+            let context = req.context.take().unwrap_or_else(|| {
+                trace_edges
+                    .iter()
+                    .flat_map(|e| extract_context_chunks_from_edge_with_db(e, &state.db))
+                    .collect()
+            });
+            (
+                question.unwrap_or_default(),
+                answer.unwrap_or_default(),
+                context,
+            )
+        };
+    if answer.trim().is_empty() {
+        return Err((
+            StatusCode::BAD_REQUEST,
+            "No answer in the request or on the trace".to_string(),
+        ));
+    }
+    if context.is_empty() {
+        return Err((
+            StatusCode::BAD_REQUEST,
+            "No retrieved context in the request or on the trace".to_string(),
+        ));
+    }
 
-    // Run RAGAS evaluation
-    let (scores, explanation, confidence) = run_ragas_evaluation(
-        &req.question,
-        &req.answer,
-        &req.context,
+    let suite = RagSuiteReport::analyze(
+        &answer,
+        &context,
         req.ground_truth.as_deref(),
-        &model,
-    )
-    .await
-    .map_err(|e| {
+        req.attribution_threshold,
+    );
+
+    let (scores, explanation, confidence, model, cost_usd) = if req.llm_judge {
+        let model = req
+            .model
+            .clone()
+            .unwrap_or_else(|| "gpt-4o-mini".to_string());
+
+        let judge = LlmJudge::resolve(
+            &state,
+            auth.tenant_id,
+            req.key_alias.as_deref(),
+            model.clone(),
+        )?;
+
+        // Run RAGAS evaluation
+        let result = run_ragas_evaluation(
+            &question,
+            &answer,
+            &context,
+            req.ground_truth.as_deref(),
+            &judge,
+        )
+        .await;
+        judge.finish(&state, auth.tenant_id);
+        let (mut scores, explanation, confidence) = result.map_err(|e| {
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                format!("RAGAS evaluation failed: {}", e),
+            )
+        })?;
+
+        // The judge's scores take precedence; the suite adds citation accuracy
+        for (metric_name, score) in suite.scores() {
+            scores.entry(metric_name).or_insert(score);
+        }
+        let explanation = format!("{}\nAttribution: {}", explanation, suite.summary());
+        let cost_usd = estimate_cost(&model, 2000);
+        (scores, explanation, confidence, model, cost_usd)
+    } else {
         (
-            StatusCode::INTERNAL_SERVER_ERROR,
-            format!("RAGAS evaluation failed: {}", e),
+            suite.scores(),
+            suite.summary(),
+            0.7, // Lexical overlap misses paraphrases
+            "rule-based".to_string(),
+            0.0,
         )
-    })?;
+    };
 
     // Calculate overall RAGAS score (harmonic mean of components)
     let component_scores: Vec<f64> = scores.values().copied().collect();
@@ -296,23 +572,30 @@ pub async fn run_ragas(
         }
     }
     if !metrics_to_store.is_empty() {
-        let _ = state.db.store_eval_metrics(trace_id, metrics_to_store);
+        let _ = state
+            .db
+            .store_eval_metrics(trace_id, metrics_to_store.clone());
+        crate::session_summary::record_eval_metrics(&state, trace_id, &metrics_to_store);
     }
 
     let duration = start.elapsed();
 
-    Ok(Json(EvaluationResponse {
-        trace_id: req.trace_id,
-        evaluator: "ragas".to_string(),
-        score: overall_score,
-        details: scores,
-        detail_explanations: None, // RAGAS doesn't provide per-metric explanations yet
-        explanation: Some(explanation),
-        evaluation_time_ms: duration.as_millis() as u64,
-        model_used: model,
-        confidence,
-        passed: overall_score >= 0.7,
-        cost_usd: Some(estimate_cost(&req.model.clone().unwrap_or_default(), 2000)),
+    Ok(Json(RagasResponse {
+        evaluation: EvaluationResponse {
+            trace_id: req.trace_id,
+            evaluator: "ragas".to_string(),
+            score: overall_score,
+            details: scores,
+            detail_explanations: None, // RAGAS doesn't provide per-metric explanations yet
+            explanation: Some(explanation),
+            evaluation_time_ms: duration.as_millis() as u64,
+            model_used: model,
+            confidence,
+            // A quote missing from the retrieved context fails the trace
+            passed: overall_score >= 0.7 && suite.fabricated_quotes() == 0,
+            cost_usd: Some(cost_usd),
+        },
+        suite,
     }))
 }
 
@@ -384,16 +667,16 @@ async fn run_geval_evaluation(
     context: &str,
     criteria: &[String],
     _weights: &HashMap<String, f64>,
-    model: &str,
+    judge: &LlmJudge,
 ) -> Result<(HashMap<String, f64>, HashMap<String, String>, String, f64), String> {
     // Step 1: Generate Chain-of-Thought evaluation steps (Task 2)
-    let cot_steps = generate_cot_steps(criteria, model).await?;
+    let cot_steps = generate_cot_steps(criteria, judge).await?;
 
     // Step 2: Build evaluation prompt with few-shot examples (Task 3)
     let prompt = build_geval_prompt_with_cot(input, output, context, criteria, &cot_steps);
 
     // Step 3: Call LLM with logprobs for probability normalization (Task 1)
-    let (response, logprobs) = call_llm_with_logprobs(&prompt, model).await?;
+    let (response, logprobs) = call_llm_with_logprobs(&prompt, judge).await?;
 
     // Step 4: Parse response with probability weighting
     parse_geval_response_with_probs(&response, criteria, &logprobs)
@@ -405,7 +688,7 @@ async fn run_geval_evaluation(
 /// Auto-generate detailed reasoning steps before scoring
 async fn generate_cot_steps(
     criteria: &[String],
-    model: &str,
+    judge: &LlmJudge,
 ) -> Result<HashMap<String, Vec<String>>, String> {
     let criteria_list = criteria.join(", ");
 
@@ -429,7 +712,7 @@ Respond in JSON format:
 }}"#
     );
 
-    let response = call_llm_for_evaluation(&prompt, model).await?;
+    let response = call_llm_for_evaluation(&prompt, judge).await?;
 
     let json: serde_json::Value = serde_json::from_str(&response)
         .map_err(|e| format!("Failed to parse CoT response: {}", e))?;
@@ -585,15 +868,13 @@ Respond in JSON format:
 /// Call LLM with logprobs enabled for probability normalization (Task 1)
 async fn call_llm_with_logprobs(
     prompt: &str,
-    model: &str,
+    judge: &LlmJudge,
 ) -> Result<(String, Vec<LogProbEntry>), String> {
-    let api_key = std::env::var("OPENAI_API_KEY").map_err(|_| "OPENAI_API_KEY not set")?;
-
     let client = reqwest::Client::new();
-    let response = client.post("https://api.openai.com/v1/chat/completions")
-        .header("Authorization", format!("Bearer {}", api_key))
+    let response = client.post(judge.chat_completions_url())
+        .header("Authorization", format!("Bearer {}", judge.api_key))
         .json(&serde_json::json!({
-            "model": model,
+            "model": judge.model,
             "messages": [
                 {"role": "system", "content": "You are an expert AI evaluation assistant. Always respond with valid JSON."},
                 {"role": "user", "content": prompt}
@@ -616,6 +897,7 @@ async fn call_llm_with_logprobs(
         .json()
         .await
         .map_err(|e| format!("Failed to parse response: {}", e))?;
+    judge.record_usage(&json);
 
     let content = json["choices"][0]["message"]["content"]
         .as_str()
@@ -805,14 +1087,14 @@ async fn run_ragas_evaluation(
     answer: &str,
     context: &[String],
     ground_truth: Option<&str>,
-    model: &str,
+    judge: &LlmJudge,
 ) -> Result<(HashMap<String, f64>, String, f64), String> {
     // Use tokio::join! to parallelize independent metrics (Task 6)
     // This reduces latency from 2-8s to 500ms-2s (4x improvement)
 
-    let precision_fut = evaluate_context_precision(question, context, answer, model);
-    let faithfulness_fut = evaluate_faithfulness_qag(context, answer, model); // QAG-based (Task 10)
-    let relevance_fut = evaluate_answer_relevance(question, answer, model);
+    let precision_fut = evaluate_context_precision(question, context, answer, judge);
+    let faithfulness_fut = evaluate_faithfulness_qag(context, answer, judge); // QAG-based (Task 10)
+    let relevance_fut = evaluate_answer_relevance(question, answer, judge);
 
     // Run parallel evaluations
     let (precision_result, faithfulness_result, relevance_result) =
@@ -866,7 +1148,7 @@ async fn run_ragas_evaluation(
 
     // Context recall requires ground truth, run separately if available
     if let Some(gt) = ground_truth {
-        match evaluate_context_recall(question, context, gt, model).await {
+        match evaluate_context_recall(question, context, gt, judge).await {
             Ok((score, exp)) => {
                 scores.insert("context_recall".to_string(), score);
                 explanations.push(format!("Context Recall: {}", exp));
@@ -939,15 +1221,16 @@ Respond in JSON format:
     )
 }
 
-async fn call_llm_for_evaluation(prompt: &str, model: &str) -> Result<String, String> {
+pub(crate) async fn call_llm_for_evaluation(
+    prompt: &str,
+    judge: &LlmJudge,
+) -> Result<String, String> {
     // Check for API key
-    let api_key = std::env::var("OPENAI_API_KEY").map_err(|_| "OPENAI_API_KEY not set")?;
-
     let client = reqwest::Client::new();
-    let response = client.post("https://api.openai.com/v1/chat/completions")
-        .header("Authorization", format!("Bearer {}", api_key))
+    let response = client.post(judge.chat_completions_url())
+        .header("Authorization", format!("Bearer {}", judge.api_key))
         .json(&serde_json::json!({
-            "model": model,
+            "model": judge.model,
             "messages": [
                 {"role": "system", "content": "You are an expert AI evaluation assistant. Always respond with valid JSON."},
                 {"role": "user", "content": prompt}
@@ -968,6 +1251,7 @@ async fn call_llm_for_evaluation(prompt: &str, model: &str) -> Result<String, St
         .json()
         .await
         .map_err(|e| format!("Failed to parse response: {}", e))?;
+    judge.record_usage(&json);
 
     json["choices"][0]["message"]["content"]
         .as_str()
@@ -1028,7 +1312,7 @@ async fn evaluate_context_precision(
     question: &str,
     context: &[String],
     answer: &str,
-    model: &str,
+    judge: &LlmJudge,
 ) -> Result<(f64, String), String> {
     let prompt = format!(
         r#"Evaluate the precision of the retrieved context for answering the question.
@@ -1050,7 +1334,7 @@ Respond in JSON:
             .join("\n\n")
     );
 
-    let response = call_llm_for_evaluation(&prompt, model).await?;
+    let response = call_llm_for_evaluation(&prompt, judge).await?;
     let json: serde_json::Value =
         serde_json::from_str(&response).map_err(|e| format!("Failed to parse: {}", e))?;
 
@@ -1064,7 +1348,7 @@ async fn evaluate_context_recall(
     question: &str,
     context: &[String],
     ground_truth: &str,
-    model: &str,
+    judge: &LlmJudge,
 ) -> Result<(f64, String), String> {
     let prompt = format!(
         r#"Evaluate if the retrieved context contains all information needed to answer the question.
@@ -1078,7 +1362,7 @@ Respond in JSON:
         context = context.join("\n\n")
     );
 
-    let response = call_llm_for_evaluation(&prompt, model).await?;
+    let response = call_llm_for_evaluation(&prompt, judge).await?;
     let json: serde_json::Value =
         serde_json::from_str(&response).map_err(|e| format!("Failed to parse: {}", e))?;
 
@@ -1093,7 +1377,7 @@ Respond in JSON:
 async fn evaluate_faithfulness(
     context: &[String],
     answer: &str,
-    model: &str,
+    judge: &LlmJudge,
 ) -> Result<(f64, String), String> {
     let prompt = format!(
         r#"Evaluate if the answer is faithful to the context (no hallucinations).
@@ -1110,7 +1394,7 @@ Respond in JSON:
         context = context.join("\n\n")
     );
 
-    let response = call_llm_for_evaluation(&prompt, model).await?;
+    let response = call_llm_for_evaluation(&prompt, judge).await?;
     let json: serde_json::Value =
         serde_json::from_str(&response).map_err(|e| format!("Failed to parse: {}", e))?;
 
@@ -1132,7 +1416,7 @@ Respond in JSON:
 async fn evaluate_faithfulness_qag(
     context: &[String],
     answer: &str,
-    model: &str,
+    judge: &LlmJudge,
 ) -> Result<(f64, String, usize, usize), String> {
     let context_str = context.join("\n\n");
 
@@ -1153,7 +1437,7 @@ Respond in JSON:
 }}"#
     );
 
-    let claims_response = call_llm_for_evaluation(&claims_prompt, model).await?;
+    let claims_response = call_llm_for_evaluation(&claims_prompt, judge).await?;
     let claims_json: serde_json::Value = serde_json::from_str(&claims_response)
         .map_err(|e| format!("Failed to parse claims: {}", e))?;
 
@@ -1202,7 +1486,7 @@ Respond in JSON:
 }}"#
     );
 
-    let verify_response = call_llm_for_evaluation(&verify_prompt, model).await?;
+    let verify_response = call_llm_for_evaluation(&verify_prompt, judge).await?;
     let verify_json: serde_json::Value = serde_json::from_str(&verify_response)
         .map_err(|e| format!("Failed to parse verification: {}", e))?;
 
@@ -1246,7 +1530,7 @@ Respond in JSON:
 async fn evaluate_answer_relevance(
     question: &str,
     answer: &str,
-    model: &str,
+    judge: &LlmJudge,
 ) -> Result<(f64, String), String> {
     let prompt = format!(
         r#"Evaluate how relevant the answer is to the question.
@@ -1258,7 +1542,7 @@ Respond in JSON:
 {{"relevance_score": <float 0-1>, "explanation": "<brief explanation>"}}"#
     );
 
-    let response = call_llm_for_evaluation(&prompt, model).await?;
+    let response = call_llm_for_evaluation(&prompt, judge).await?;
     let json: serde_json::Value =
         serde_json::from_str(&response).map_err(|e| format!("Failed to parse: {}", e))?;
 
@@ -1417,25 +1701,38 @@ fn extract_context_from_edge_with_db(
     edge: &agentreplay_core::AgentFlowEdge,
     db: &agentreplay_query::Agentreplay,
 ) -> Option<String> {
+    let chunks = extract_context_chunks_from_edge_with_db(edge, db);
+    if chunks.is_empty() {
+        None
+    } else {
+        Some(chunks.join("\n\n"))
+    }
+}
+
+/// Extract the retrieved chunks of a retrieval or tool span, one per document
+fn extract_context_chunks_from_edge_with_db(
+    edge: &agentreplay_core::AgentFlowEdge,
+    db: &agentreplay_query::Agentreplay,
+) -> Vec<String> {
     // Check if this edge contains retrieval context based on span_type
     // span_type: 2 = retrieval, 7 = tool call
     if edge.span_type != 2 && edge.span_type != 7 {
-        return None;
+        return Vec::new();
     }
 
     if edge.has_payload == 0 {
-        return None;
+        return Vec::new();
     }
 
     let payload_bytes = match db.get_payload(edge.edge_id) {
         Ok(Some(bytes)) => bytes,
-        _ => return None,
+        _ => return Vec::new(),
     };
 
     if let Ok(payload) = serde_json::from_slice::<serde_json::Value>(&payload_bytes) {
         // Strategy 1: Look for context field
         if let Some(context) = payload.get("context").and_then(|v| v.as_str()) {
-            return Some(context.to_string());
+            return vec![context.to_string()];
         }
 
         // Strategy 2: Look for documents array
@@ -1445,7 +1742,7 @@ fn extract_context_from_edge_with_db(
                 .filter_map(|d| d.as_str().map(|s| s.to_string()))
                 .collect();
             if !context.is_empty() {
-                return Some(context.join("\n\n"));
+                return context;
             }
         }
 
@@ -1464,19 +1761,19 @@ fn extract_context_from_edge_with_db(
                 })
                 .collect();
             if !context.is_empty() {
-                return Some(context.join("\n\n"));
+                return context;
             }
         }
 
         // Strategy 4: Tool call result for tool spans
         if edge.span_type == 7 {
             if let Some(result) = payload.get("gen_ai.tool.0.result").and_then(|v| v.as_str()) {
-                return Some(result.to_string());
+                return vec![result.to_string()];
             }
         }
     }
 
-    None
+    Vec::new()
 }
 
 // Legacy wrapper functions for backward compatibility (deprecated - use _with_db versions)
@@ -1503,6 +1800,7 @@ fn extract_context_from_edge(_edge: &agentreplay_core::AgentFlowEdge) -> Option<
 fn estimate_cost(model: &str, tokens: u64) -> f64 {
     // Approximate costs per 1K tokens
     let cost_per_1k = match model {
+        m if m.starts_with(LOCAL_JUDGE_PREFIX) => 0.0,
         "gpt-4" | "gpt-4-turbo" => 0.03,
         "gpt-4o" => 0.005,
         "gpt-4o-mini" => 0.00015,
@@ -1531,5 +1829,18 @@ mod tests {
         let json = r#"{"trace_id":"0x123","question":"What is AI?","answer":"AI is...","context":["doc1","doc2"]}"#;
         let req: Result<RagasRequest, _> = serde_json::from_str(json);
         assert!(req.is_ok());
+
+        // Everything but the trace id may come from the trace
+        let req: RagasRequest =
+            serde_json::from_str(r#"{"trace_id":"0x123","llm_judge":false}"#).unwrap();
+        assert!(req.answer.is_none() && req.context.is_none());
+        assert!(!req.llm_judge);
+        assert_eq!(req.attribution_threshold, 0.5);
+    }
+
+    #[test]
+    fn test_local_judges_cost_nothing() {
+        assert_eq!(estimate_cost("ollama/llama3.1:8b", 2000), 0.0);
+        assert!(estimate_cost("gpt-4o-mini", 2000) > 0.0);
     }
 }
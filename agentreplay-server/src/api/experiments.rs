@@ -31,8 +31,10 @@ use std::collections::HashMap;
 // ============================================================================
 
 use agentreplay_core::enterprise::{
-    Experiment as CoreExperiment, ExperimentResult as CoreResult, ExperimentVariant as CoreVariant,
+    Experiment as CoreExperiment, ExperimentAutoStop, ExperimentResult as CoreResult,
+    ExperimentVariant as CoreVariant,
 };
+use agentreplay_evals::{probability_of_superiority, Bootstrap, BootstrapCI};
 
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
 #[serde(rename_all = "lowercase")]
@@ -81,6 +83,8 @@ pub struct Experiment {
     pub end_time: Option<u64>,
     pub created_at: u64,
     pub updated_at: u64,
+    #[serde(default)]
+    pub auto_stop: Option<ExperimentAutoStop>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -115,6 +119,7 @@ impl From<Experiment> for CoreExperiment {
             end_time: exp.end_time,
             created_at: exp.created_at,
             updated_at: exp.updated_at,
+            auto_stop: exp.auto_stop,
         }
     }
 }
@@ -143,6 +148,7 @@ impl From<CoreExperiment> for Experiment {
             end_time: core.end_time,
             created_at: core.created_at,
             updated_at: core.updated_at,
+            auto_stop: core.auto_stop,
         }
     }
 }
@@ -158,6 +164,8 @@ pub struct CreateExperimentRequest {
     pub variants: Vec<VariantInput>,
     #[serde(default)]
     pub metrics: Vec<String>,
+    #[serde(default)]
+    pub auto_stop: Option<ExperimentAutoStop>,
 }
 
 #[derive(Debug, Deserialize)]
@@ -172,6 +180,7 @@ pub struct UpdateExperimentRequest {
     pub name: Option<String>,
     pub description: Option<String>,
     pub traffic_split: Option<HashMap<String, f64>>,
+    pub auto_stop: Option<ExperimentAutoStop>,
 }
 
 #[derive(Debug, Deserialize)]
@@ -186,6 +195,13 @@ pub struct RecordResultRequest {
     pub metrics: HashMap<String, f64>,
 }
 
+#[derive(Debug, Deserialize)]
+pub struct AnalysisQuery {
+    /// Skip the bootstrap intervals (probabilities only)
+    #[serde(default)]
+    pub fast: bool,
+}
+
 #[derive(Debug, Deserialize)]
 pub struct ListExperimentsQuery {
     pub status: Option<String>,
@@ -204,6 +220,7 @@ pub struct ExperimentResponse {
     pub end_time: Option<u64>,
     pub created_at: u64,
     pub updated_at: u64,
+    pub auto_stop: Option<ExperimentAutoStop>,
 }
 
 #[derive(Debug, Serialize)]
@@ -237,6 +254,41 @@ pub struct MetricStats {
     pub count: usize,
 }
 
+/// Bayesian comparison of one variant against control on one metric
+#[derive(Debug, Serialize)]
+pub struct MetricComparison {
+    pub metric: String,
+    pub variant_id: String,
+    pub control_mean: f64,
+    pub variant_mean: f64,
+    pub control_count: usize,
+    pub variant_count: usize,
+    /// Relative change of the variant mean versus control
+    pub lift: Option<f64>,
+    /// P(variant is better than control), direction-aware
+    pub probability_of_superiority: f64,
+    /// Bootstrap CI for `variant_mean - control_mean`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub difference_ci: Option<BootstrapCI>,
+    pub significant: bool,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ExperimentAnalysisResponse {
+    pub experiment_id: String,
+    pub status: String,
+    pub control_variant_id: Option<String>,
+    pub primary_metric: Option<String>,
+    pub threshold: f64,
+    pub min_samples_per_variant: usize,
+    pub comparisons: Vec<MetricComparison>,
+    /// Set once the primary metric has a decisive result
+    pub winner: Option<String>,
+    pub confidence: Option<f64>,
+    /// Whether there are enough samples to act on the result
+    pub ready: bool,
+}
+
 #[derive(Debug, Serialize)]
 pub struct DeleteResponse {
     pub success: bool,
@@ -287,6 +339,7 @@ fn experiment_to_response(exp: &Experiment) -> ExperimentResponse {
         end_time: exp.end_time,
         created_at: exp.created_at,
         updated_at: exp.updated_at,
+        auto_stop: exp.auto_stop.clone(),
     }
 }
 
@@ -316,6 +369,126 @@ fn calculate_metric_stats(values: &[f64]) -> MetricStats {
     }
 }
 
+/// Compare every variant against control on every experiment metric
+///
+/// Probabilities come from the normal posterior so this stays cheap enough to
+/// run on each recorded result; `bootstrap` adds distribution-free intervals
+/// for the analysis endpoint.
+pub(crate) fn analyze_experiment(
+    experiment: &Experiment,
+    results: &[CoreResult],
+    bootstrap: Option<&Bootstrap>,
+) -> ExperimentAnalysisResponse {
+    let config = experiment.auto_stop.clone().unwrap_or_default();
+    let control_id = config
+        .control_variant_id
+        .clone()
+        .or_else(|| experiment.variants.first().map(|v| v.id.clone()));
+    let primary_metric = config
+        .primary_metric
+        .clone()
+        .or_else(|| experiment.metrics.first().cloned());
+
+    let values = |variant_id: &str, metric: &str| -> Vec<f64> {
+        results
+            .iter()
+            .filter(|r| r.variant_id == variant_id)
+            .filter_map(|r| r.metrics.get(metric).copied())
+            .collect()
+    };
+
+    let mut comparisons = Vec::new();
+    if let Some(control_id) = &control_id {
+        for metric in &experiment.metrics {
+            let control = values(control_id, metric);
+            let lower_is_better = config.lower_is_better.contains(metric);
+
+            for variant in experiment.variants.iter().filter(|v| &v.id != control_id) {
+                let treatment = values(&variant.id, metric);
+                let (better, worse) = if lower_is_better {
+                    (&control, &treatment)
+                } else {
+                    (&treatment, &control)
+                };
+                let probability = probability_of_superiority(better, worse);
+                let control_mean = calculate_metric_stats(&control).mean;
+                let variant_mean = calculate_metric_stats(&treatment).mean;
+                let enough = control.len() >= config.min_samples_per_variant
+                    && treatment.len() >= config.min_samples_per_variant;
+
+                comparisons.push(MetricComparison {
+                    metric: metric.clone(),
+                    variant_id: variant.id.clone(),
+                    control_mean,
+                    variant_mean,
+                    control_count: control.len(),
+                    variant_count: treatment.len(),
+                    lift: (control_mean != 0.0)
+                        .then(|| (variant_mean - control_mean) / control_mean.abs()),
+                    probability_of_superiority: probability,
+                    difference_ci: bootstrap
+                        .map(|b| b.difference_ci(&treatment, &control, config.threshold)),
+                    significant: enough
+                        && (probability >= config.threshold
+                            || probability <= 1.0 - config.threshold),
+                });
+            }
+        }
+    }
+
+    let primary: Vec<&MetricComparison> = comparisons
+        .iter()
+        .filter(|c| Some(&c.metric) == primary_metric.as_ref())
+        .collect();
+    let ready = !primary.is_empty()
+        && primary.iter().all(|c| {
+            c.control_count >= config.min_samples_per_variant
+                && c.variant_count >= config.min_samples_per_variant
+        });
+
+    // A variant wins by clearing the threshold against control; control wins
+    // once every challenger is confidently worse.
+    let (winner, confidence) = if !ready {
+        (None, None)
+    } else if let Some(best) = primary
+        .iter()
+        .filter(|c| c.probability_of_superiority >= config.threshold)
+        .max_by(|a, b| {
+            a.probability_of_superiority
+                .total_cmp(&b.probability_of_superiority)
+        })
+    {
+        (
+            Some(best.variant_id.clone()),
+            Some(best.probability_of_superiority),
+        )
+    } else if primary
+        .iter()
+        .all(|c| c.probability_of_superiority <= 1.0 - config.threshold)
+    {
+        let confidence = primary
+            .iter()
+            .map(|c| 1.0 - c.probability_of_superiority)
+            .fold(f64::INFINITY, f64::min);
+        (control_id.clone(), Some(confidence))
+    } else {
+        (None, None)
+    };
+
+    ExperimentAnalysisResponse {
+        experiment_id: format!("0x{:x}", experiment.id),
+        status: experiment.status.as_str().to_string(),
+        control_variant_id: control_id,
+        primary_metric,
+        threshold: config.threshold,
+        min_samples_per_variant: config.min_samples_per_variant,
+        comparisons,
+        winner,
+        confidence,
+        ready,
+    }
+}
+
 // ============================================================================
 // API Handlers
 // ============================================================================
@@ -352,6 +525,7 @@ pub async fn create_experiment(
         end_time: None,
         created_at: timestamp,
         updated_at: timestamp,
+        auto_stop: req.auto_stop,
     };
 
     // Convert API type to Core type for storage
@@ -438,6 +612,9 @@ pub async fn update_experiment(
             if let Some(traffic_split) = req.traffic_split {
                 exp.traffic_split = traffic_split;
             }
+            if let Some(auto_stop) = req.auto_stop {
+                exp.auto_stop = Some(auto_stop);
+            }
             exp.updated_at = timestamp;
         })
         .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
@@ -550,12 +727,94 @@ pub async fn record_result(
         .store_experiment_result(result)
         .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
 
+    let (auto_stopped, winner) = auto_stop_if_decided(&state, experiment_id)?;
+
     Ok(Json(serde_json::json!({
         "success": true,
         "experiment_id": format!("0x{:x}", experiment_id),
+        "auto_stopped": auto_stopped,
+        "winner": winner,
     })))
 }
 
+/// Re-run the sequential analysis for a running experiment with auto-stop
+/// enabled and complete it once the primary metric is decided.
+fn auto_stop_if_decided(
+    state: &AppState,
+    experiment_id: u128,
+) -> Result<(bool, Option<String>), (StatusCode, String)> {
+    let Some(core_experiment) = state
+        .db
+        .get_experiment(experiment_id)
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?
+    else {
+        return Ok((false, None));
+    };
+
+    let experiment: Experiment = core_experiment.into();
+    let enabled = experiment.auto_stop.as_ref().is_some_and(|c| c.enabled);
+    if !enabled || experiment.status != ExperimentStatus::Running {
+        return Ok((false, None));
+    }
+
+    let results = state
+        .db
+        .get_experiment_results(experiment_id)
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+
+    let analysis = analyze_experiment(&experiment, &results, None);
+    let Some(winner) = analysis.winner else {
+        return Ok((false, None));
+    };
+
+    let timestamp = current_timestamp_us();
+    state
+        .db
+        .update_experiment(experiment_id, |exp| {
+            exp.status = ExperimentStatus::Completed.as_str().to_string();
+            exp.end_time = Some(timestamp);
+            exp.updated_at = timestamp;
+        })
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+
+    tracing::info!(
+        "Experiment 0x{:x} auto-stopped: {} wins with confidence {:.3}",
+        experiment_id,
+        winner,
+        analysis.confidence.unwrap_or_default()
+    );
+
+    Ok((true, Some(winner)))
+}
+
+/// GET /api/v1/experiments/:id/analysis
+/// Bayesian probability-of-superiority per metric with bootstrap intervals
+pub async fn get_experiment_analysis(
+    State(state): State<AppState>,
+    Path(id): Path<String>,
+    Query(params): Query<AnalysisQuery>,
+) -> Result<Json<ExperimentAnalysisResponse>, (StatusCode, String)> {
+    let experiment_id = parse_id(&id).map_err(|e| (StatusCode::BAD_REQUEST, e))?;
+
+    let core_experiment = state
+        .db
+        .get_experiment(experiment_id)
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?
+        .ok_or_else(|| (StatusCode::NOT_FOUND, "Experiment not found".to_string()))?;
+
+    let experiment: Experiment = core_experiment.into();
+
+    let results = state
+        .db
+        .get_experiment_results(experiment_id)
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+
+    let bootstrap = Bootstrap::new().with_resamples(2_000);
+    let bootstrap = (!params.fast).then_some(&bootstrap);
+
+    Ok(Json(analyze_experiment(&experiment, &results, bootstrap)))
+}
+
 /// GET /api/v1/experiments/:id/stats
 /// Get statistics for an experiment
 pub async fn get_experiment_stats(
@@ -615,11 +874,14 @@ pub async fn get_experiment_stats(
         );
     }
 
+    let all_results: Vec<CoreResult> = variant_data.into_values().flatten().collect();
+    let analysis = analyze_experiment(&experiment, &all_results, None);
+
     Ok(Json(ExperimentStatsResponse {
         experiment_id: format!("0x{:x}", experiment_id),
         variant_stats,
-        winner: None, // TODO: Implement statistical significance testing
-        confidence: None,
+        winner: analysis.winner,
+        confidence: analysis.confidence,
     }))
 }
 
@@ -661,6 +923,96 @@ mod tests {
         assert_eq!(stats.count, 5);
     }
 
+    fn sample_experiment(auto_stop: ExperimentAutoStop) -> Experiment {
+        let variant = |id: &str| Variant {
+            id: id.to_string(),
+            name: id.to_string(),
+            description: String::new(),
+            config: HashMap::new(),
+        };
+        Experiment {
+            id: 1,
+            name: "prompt-v2".to_string(),
+            description: String::new(),
+            variants: vec![variant("control"), variant("treatment")],
+            status: ExperimentStatus::Running,
+            traffic_split: HashMap::new(),
+            metrics: vec!["accuracy".to_string(), "latency_ms".to_string()],
+            start_time: None,
+            end_time: None,
+            created_at: 0,
+            updated_at: 0,
+            auto_stop: Some(auto_stop),
+        }
+    }
+
+    fn results(variant: &str, accuracy: &[f64], latency: f64) -> Vec<CoreResult> {
+        accuracy
+            .iter()
+            .enumerate()
+            .map(|(i, &a)| CoreResult {
+                experiment_id: 1,
+                variant_id: variant.to_string(),
+                trace_id: i as u128,
+                metrics: HashMap::from([
+                    ("accuracy".to_string(), a),
+                    ("latency_ms".to_string(), latency + i as f64),
+                ]),
+                timestamp_us: 0,
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_analysis_picks_winner_after_min_samples() {
+        let config = ExperimentAutoStop {
+            min_samples_per_variant: 5,
+            lower_is_better: vec!["latency_ms".to_string()],
+            ..Default::default()
+        };
+        let experiment = sample_experiment(config);
+
+        let mut all = results("control", &[0.60, 0.62, 0.58, 0.61, 0.59], 200.0);
+        all.extend(results("treatment", &[0.80, 0.82, 0.78, 0.81, 0.79], 100.0));
+
+        let analysis = analyze_experiment(&experiment, &all, None);
+        assert!(analysis.ready);
+        assert_eq!(analysis.winner.as_deref(), Some("treatment"));
+        assert!(analysis.confidence.unwrap() > 0.99);
+
+        // Lower latency counts as superiority for the treatment
+        let latency = analysis
+            .comparisons
+            .iter()
+            .find(|c| c.metric == "latency_ms")
+            .unwrap();
+        assert!(latency.probability_of_superiority > 0.99);
+        assert!(latency.significant);
+
+        // Too few samples: no decision yet
+        let analysis = analyze_experiment(&experiment, &all[..8], None);
+        assert!(!analysis.ready);
+        assert!(analysis.winner.is_none());
+    }
+
+    #[test]
+    fn test_analysis_declares_control_when_challenger_is_worse() {
+        let config = ExperimentAutoStop {
+            min_samples_per_variant: 5,
+            ..Default::default()
+        };
+        let experiment = sample_experiment(config);
+
+        let mut all = results("control", &[0.80, 0.82, 0.78, 0.81, 0.79], 100.0);
+        all.extend(results("treatment", &[0.60, 0.62, 0.58, 0.61, 0.59], 100.0));
+
+        let bootstrap = Bootstrap::new().with_seed(1).with_resamples(200);
+        let analysis = analyze_experiment(&experiment, &all, Some(&bootstrap));
+        assert_eq!(analysis.winner.as_deref(), Some("control"));
+        let ci = analysis.comparisons[0].difference_ci.as_ref().unwrap();
+        assert!(ci.upper < 0.0);
+    }
+
     #[test]
     fn test_empty_metric_stats() {
         let values: Vec<f64> = vec![];
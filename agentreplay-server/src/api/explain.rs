@@ -0,0 +1,576 @@
+// Copyright 2025 AgentReplay (https://github.com/agentreplay)
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program. If not, see <https://www.gnu.org/licenses/>.
+
+//! Root cause summarization for failing traces
+//!
+//! `POST /api/v1/traces/:trace_id/explain` compresses the trace with the
+//! hierarchical [`TraceSummarizer`] (weighted towards error content), lists
+//! the failed spans with their failure category (see
+//! [`crate::ingestion::classify_failure`]) and asks the LLM judge for a
+//! root-cause hypothesis: what went wrong, which spans are implicated and
+//! how, and what to change.
+//!
+//! Hypotheses are kept in the eval cache, keyed by trace, model and span
+//! count, so repeated views of a trace cost one judge call until new spans
+//! arrive. Pass `"refresh": true` to ask again.
+
+use std::collections::{HashMap, HashSet};
+use std::time::Instant;
+
+use agentreplay_core::clock::now_secs;
+use agentreplay_core::AgentFlowEdge;
+use agentreplay_evals::trace_summarizer::{SpanContent, TraceSummarizer};
+use axum::{
+    extract::{Extension, Path, State},
+    http::StatusCode,
+    Json,
+};
+use serde::{Deserialize, Serialize};
+use tracing::warn;
+
+use super::evaluate::{call_llm_for_evaluation, LlmJudge};
+use super::query::{find_edge_by_id_or_session, ApiError, AppState};
+use crate::access_policy::AccessFilter;
+use crate::auth::AuthContext;
+use crate::cache::{CachedEvalResult, EvalCacheKey};
+use crate::ingestion::{classify_failure, FailureCategory, SpanFailure};
+
+/// Evaluator name of cached hypotheses
+const EVALUATOR: &str = "root_cause";
+const DEFAULT_MODEL: &str = "gpt-4o-mini";
+
+const DEFAULT_TOKEN_BUDGET: usize = 3_000;
+const MAX_TOKEN_BUDGET: usize = 16_000;
+
+const MAX_SPANS: usize = 10_000;
+const MAX_DEPTH: usize = 1_000;
+
+/// Summarizer relevance keywords, so failing spans keep more of their budget
+const FAILURE_CRITERIA: [&str; 6] = [
+    "error",
+    "exception",
+    "failed",
+    "timeout",
+    "refused",
+    "limit",
+];
+
+/// Longest attribute value put into a span's summary input, in characters
+const MAX_VALUE_CHARS: usize = 2_000;
+
+/// Attributes describing what a span did and how it failed, in prompt order
+const CONTENT_KEYS: [&str; 14] = [
+    "gen_ai.operation.name",
+    "gen_ai.request.model",
+    "gen_ai.tool.name",
+    "gen_ai.tool.call.arguments",
+    "error.type",
+    "error.message",
+    "exception.type",
+    "exception.message",
+    "exception.stacktrace",
+    "otel.status_description",
+    "http.response.status_code",
+    "gen_ai.response.finish_reasons",
+    "input",
+    "output",
+];
+
+#[derive(Debug, Default, Deserialize)]
+pub struct ExplainRequest {
+    /// Judge model (default: gpt-4o-mini)
+    pub model: Option<String>,
+    /// Vault key for the judge model (default: the server's OPENAI_API_KEY)
+    pub key_alias: Option<String>,
+    /// Token budget of the trace summary sent to the judge
+    pub token_budget: Option<usize>,
+    /// Ignore a cached hypothesis
+    #[serde(default)]
+    pub refresh: bool,
+}
+
+/// How an implicated span relates to the failure
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SpanRole {
+    /// Where the failure started
+    Origin,
+    /// Passed a bad result on
+    Propagated,
+    /// Failed because of an earlier span
+    Symptom,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ImplicatedSpan {
+    pub span_id: String,
+    pub role: SpanRole,
+    pub reason: String,
+}
+
+/// Structured root-cause hypothesis from the judge
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RootCauseHypothesis {
+    pub root_cause: String,
+    pub category: FailureCategory,
+    /// 0.0 - 1.0
+    pub confidence: f64,
+    pub implicated_spans: Vec<ImplicatedSpan>,
+    #[serde(default)]
+    pub evidence: Vec<String>,
+    #[serde(default)]
+    pub suggested_fixes: Vec<String>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct FailedSpan {
+    pub span_id: String,
+    pub category: FailureCategory,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error_type: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub message: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ExplainResponse {
+    pub trace_id: String,
+    pub model: String,
+    /// Served from the eval cache
+    pub cached: bool,
+    pub span_count: usize,
+    pub failed_spans: Vec<FailedSpan>,
+    pub hypothesis: RootCauseHypothesis,
+    pub evaluation_time_ms: u64,
+}
+
+/// Stored span attributes as strings (the payload is a flat JSON object)
+fn payload_attributes(bytes: &[u8]) -> HashMap<String, String> {
+    let Ok(serde_json::Value::Object(fields)) = serde_json::from_slice(bytes) else {
+        return HashMap::new();
+    };
+    fields
+        .into_iter()
+        .filter_map(|(key, value)| match value {
+            serde_json::Value::Null => None,
+            serde_json::Value::String(s) => Some((key, s)),
+            other => Some((key, other.to_string())),
+        })
+        .collect()
+}
+
+fn truncate_chars(value: &str, max: usize) -> String {
+    match value.char_indices().nth(max) {
+        Some((end, _)) => format!("{}...", &value[..end]),
+        None => value.to_string(),
+    }
+}
+
+/// Summarizer input for one span: what it did, how it failed, its I/O
+fn span_content(
+    trace_id: u128,
+    edge: &AgentFlowEdge,
+    attributes: &HashMap<String, String>,
+    failure: Option<&SpanFailure>,
+) -> SpanContent {
+    let span_type = format!("{:?}", edge.get_span_type()).to_lowercase();
+    let mut lines = vec![format!(
+        "Span {:#x} ({}, {} ms).",
+        edge.edge_id,
+        span_type,
+        edge.duration_us / 1_000
+    )];
+    if let Some(failure) = failure {
+        lines.push(format!("Failed: {} error.", failure.category.as_str()));
+    }
+    let prompt = (0..10)
+        .rev()
+        .find_map(|i| attributes.get(&format!("gen_ai.prompt.{}.content", i)));
+    let completion = attributes.get("gen_ai.completion.0.content");
+    let fields = CONTENT_KEYS
+        .iter()
+        .filter_map(|key| attributes.get(*key).map(|value| (*key, value)))
+        .chain(prompt.map(|value| ("prompt", value)))
+        .chain(completion.map(|value| ("completion", value)));
+    for (key, value) in fields {
+        lines.push(format!(
+            "{}: {}.",
+            key,
+            truncate_chars(value.trim(), MAX_VALUE_CHARS)
+        ));
+    }
+
+    SpanContent::new(trace_id, edge.edge_id as u64, span_type, lines.join("\n"))
+}
+
+fn build_prompt(summary: &str, failed: &[FailedSpan], span_count: usize) -> String {
+    let failures = failed
+        .iter()
+        .map(|span| {
+            format!(
+                "- {} [{}] {}{}",
+                span.span_id,
+                span.category.as_str(),
+                span.error_type.as_deref().unwrap_or(""),
+                span.message
+                    .as_deref()
+                    .map(|m| format!(": {}", truncate_chars(m, 300)))
+                    .unwrap_or_default()
+            )
+        })
+        .collect::<Vec<_>>()
+        .join("\n");
+
+    format!(
+        r#"An AI agent trace with {span_count} spans failed. Find the root cause.
+
+Failed spans:
+{failures}
+
+Trace summary (span by span, compressed):
+{summary}
+
+A span that failed may only be a symptom of an earlier span that returned a
+bad result. Identify where the failure originated, how it propagated, and
+what to change. Cite only span IDs from the trace.
+
+Respond in JSON:
+{{
+  "root_cause": "<one or two sentences>",
+  "category": "rate_limit" | "context_overflow" | "tool_exception" | "timeout" | "refusal" | "other",
+  "confidence": <float 0-1>,
+  "implicated_spans": [
+    {{"span_id": "<0x...>", "role": "origin" | "propagated" | "symptom", "reason": "<why>"}}
+  ],
+  "evidence": ["<observation from the trace>"],
+  "suggested_fixes": ["<concrete change>"]
+}}"#
+    )
+}
+
+/// Parse the judge's answer, keeping only spans of this trace
+///
+/// An unknown category falls back to `fallback`, the most common category
+/// among the failed spans.
+fn parse_hypothesis(
+    response: &str,
+    span_ids: &HashSet<String>,
+    fallback: FailureCategory,
+) -> Result<RootCauseHypothesis, String> {
+    let json: serde_json::Value =
+        serde_json::from_str(response).map_err(|e| format!("Failed to parse JSON: {}", e))?;
+    let root_cause = json["root_cause"]
+        .as_str()
+        .filter(|s| !s.trim().is_empty())
+        .ok_or_else(|| "Judge response has no root_cause".to_string())?
+        .to_string();
+    let strings = |field: &str| -> Vec<String> {
+        json[field]
+            .as_array()
+            .map(|items| {
+                items
+                    .iter()
+                    .filter_map(|item| item.as_str().map(str::to_string))
+                    .collect()
+            })
+            .unwrap_or_default()
+    };
+    let implicated_spans = json["implicated_spans"]
+        .as_array()
+        .map(|items| {
+            items
+                .iter()
+                .filter_map(|item| serde_json::from_value::<ImplicatedSpan>(item.clone()).ok())
+                .filter_map(|mut span| {
+                    let id =
+                        u128::from_str_radix(span.span_id.trim_start_matches("0x"), 16).ok()?;
+                    span.span_id = format!("{:#x}", id);
+                    span_ids.contains(&span.span_id).then_some(span)
+                })
+                .collect()
+        })
+        .unwrap_or_default();
+
+    Ok(RootCauseHypothesis {
+        root_cause,
+        category: json["category"]
+            .as_str()
+            .and_then(FailureCategory::parse)
+            .unwrap_or(fallback),
+        confidence: json["confidence"].as_f64().unwrap_or(0.5).clamp(0.0, 1.0),
+        implicated_spans,
+        evidence: strings("evidence"),
+        suggested_fixes: strings("suggested_fixes"),
+    })
+}
+
+fn judge_error((status, message): (StatusCode, String)) -> ApiError {
+    if status == StatusCode::BAD_REQUEST {
+        ApiError::BadRequest(message)
+    } else {
+        ApiError::Internal(message)
+    }
+}
+
+/// POST /api/v1/traces/:trace_id/explain
+///
+/// The body is optional.
+pub async fn explain_trace(
+    State(state): State<AppState>,
+    Path(trace_id): Path<String>,
+    Extension(auth): Extension<AuthContext>,
+    Extension(access): Extension<AccessFilter>,
+    req: Option<Json<ExplainRequest>>,
+) -> Result<Json<ExplainResponse>, ApiError> {
+    let start = Instant::now();
+    let req = req.map(|Json(req)| req).unwrap_or_default();
+    let token_budget = req.token_budget.unwrap_or(DEFAULT_TOKEN_BUDGET);
+    if token_budget == 0 || token_budget > MAX_TOKEN_BUDGET {
+        return Err(ApiError::BadRequest(format!(
+            "token_budget must be between 1 and {}",
+            MAX_TOKEN_BUDGET
+        )));
+    }
+    let model = req.model.unwrap_or_else(|| DEFAULT_MODEL.to_string());
+
+    let id = u128::from_str_radix(trace_id.trim_start_matches("0x"), 16)
+        .map_err(|_| ApiError::BadRequest("Invalid trace ID format".into()))?;
+    let root = find_edge_by_id_or_session(&state, id, auth.tenant_id, &access)
+        .await?
+        .ok_or_else(|| ApiError::NotFound("Trace not found".into()))?;
+    let db = match state.project_manager {
+        Some(ref pm) => pm
+            .get_or_open_project(root.project_id)
+            .unwrap_or_else(|_| state.db.clone()),
+        None => state.db.clone(),
+    };
+    let spans = db
+        .get_descendants_with_depth_for_tenant(root.edge_id, auth.tenant_id, MAX_DEPTH, MAX_SPANS)
+        .map_err(|e| ApiError::Internal(e.to_string()))?;
+    if spans.len() >= MAX_SPANS {
+        return Err(ApiError::BadRequest("Trace too large".into()));
+    }
+    let mut spans: Vec<AgentFlowEdge> = spans.into_iter().map(|(span, _)| span).collect();
+    if !spans.iter().any(|span| span.edge_id == root.edge_id) {
+        spans.push(root);
+    }
+    spans.sort_by_key(|span| (span.timestamp_us, span.edge_id));
+
+    let mut contents = Vec::with_capacity(spans.len());
+    let mut failed_spans = Vec::new();
+    let mut categories: HashMap<FailureCategory, usize> = HashMap::new();
+    for span in &spans {
+        let attributes = if span.has_payload == 0 {
+            HashMap::new()
+        } else {
+            db.get_payload(span.edge_id)
+                .ok()
+                .flatten()
+                .map(|bytes| payload_attributes(&bytes))
+                .unwrap_or_default()
+        };
+        let failure = classify_failure(&attributes);
+        contents.push(span_content(
+            root.edge_id,
+            span,
+            &attributes,
+            failure.as_ref(),
+        ));
+        if let Some(failure) = failure {
+            *categories.entry(failure.category).or_default() += 1;
+            failed_spans.push(FailedSpan {
+                span_id: format!("{:#x}", span.edge_id),
+                category: failure.category,
+                error_type: failure.error_type,
+                message: failure.message,
+            });
+        }
+    }
+    let Some(fallback) = categories
+        .into_iter()
+        .max_by_key(|(category, count)| (*count, std::cmp::Reverse(category.as_str())))
+        .map(|(category, _)| category)
+    else {
+        return Err(ApiError::BadRequest(
+            "Trace has no failed spans to explain".into(),
+        ));
+    };
+
+    let span_count = spans.len();
+    let cache_key = EvalCacheKey::new(
+        root.edge_id,
+        EVALUATOR,
+        &[model.clone(), format!("spans:{}", span_count)],
+    );
+    let cached = if req.refresh {
+        None
+    } else {
+        state
+            .eval_cache
+            .as_ref()
+            .and_then(|cache| cache.get(&cache_key))
+            .and_then(|cached| serde_json::from_str(&cached.explanation).ok())
+    };
+    let from_cache = cached.is_some();
+
+    let hypothesis = match cached {
+        Some(hypothesis) => hypothesis,
+        None => {
+            let summary = TraceSummarizer::default()
+                .with_budget(token_budget)
+                .with_criteria(FAILURE_CRITERIA.map(String::from).to_vec())
+                .summarize(&contents);
+            let summary_text = summary
+                .span_summaries
+                .iter()
+                .map(|span| span.summary.as_str())
+                .collect::<Vec<_>>()
+                .join("\n\n");
+            let prompt = build_prompt(&summary_text, &failed_spans, span_count);
+
+            let judge = LlmJudge::resolve(
+                &state,
+                auth.tenant_id,
+                req.key_alias.as_deref(),
+                model.clone(),
+            )
+            .map_err(judge_error)?;
+            let response = call_llm_for_evaluation(&prompt, &judge).await;
+            judge.finish(&state, auth.tenant_id);
+            let response = response
+                .map_err(|e| ApiError::Internal(format!("Root cause analysis failed: {}", e)))?;
+
+            let span_ids: HashSet<String> = spans
+                .iter()
+                .map(|span| format!("{:#x}", span.edge_id))
+                .collect();
+            let hypothesis = parse_hypothesis(&response, &span_ids, fallback)
+                .map_err(|e| ApiError::Internal(format!("Root cause analysis failed: {}", e)))?;
+
+            if let Some(cache) = state.eval_cache.as_ref() {
+                match serde_json::to_string(&hypothesis) {
+                    Ok(explanation) => cache.insert(
+                        cache_key,
+                        CachedEvalResult {
+                            scores: HashMap::from([(
+                                "confidence".to_string(),
+                                hypothesis.confidence,
+                            )]),
+                            explanation,
+                            confidence: hypothesis.confidence,
+                            model: model.clone(),
+                            cached_at: now_secs(),
+                            eval_time_ms: start.elapsed().as_millis() as u64,
+                        },
+                    ),
+                    Err(e) => warn!("Failed to cache root cause hypothesis: {}", e),
+                }
+            }
+            hypothesis
+        }
+    };
+
+    Ok(Json(ExplainResponse {
+        trace_id: format!("{:#x}", root.edge_id),
+        model,
+        cached: from_cache,
+        span_count,
+        failed_spans,
+        hypothesis,
+        evaluation_time_ms: start.elapsed().as_millis() as u64,
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use agentreplay_core::SpanType;
+
+    #[test]
+    fn test_payload_attributes() {
+        let attributes = payload_attributes(
+            br#"{"error.type": "TimeoutError", "http.response.status_code": 504, "x": null}"#,
+        );
+        assert_eq!(attributes["error.type"], "TimeoutError");
+        assert_eq!(attributes["http.response.status_code"], "504");
+        assert!(!attributes.contains_key("x"));
+        assert!(payload_attributes(b"[1, 2]").is_empty());
+    }
+
+    #[test]
+    fn test_span_content() {
+        let mut edge = AgentFlowEdge::new(1, 1, 1, 1, SpanType::ToolCall, 0);
+        edge.edge_id = 0x2a;
+        let attributes = HashMap::from([
+            ("gen_ai.tool.name".to_string(), "web_search".to_string()),
+            ("exception.message".to_string(), "x".repeat(3_000)),
+            ("gen_ai.prompt.0.content".to_string(), "system".to_string()),
+            (
+                "gen_ai.prompt.1.content".to_string(),
+                "find flights".to_string(),
+            ),
+        ]);
+        let failure = classify_failure(&attributes);
+        let content = span_content(1, &edge, &attributes, failure.as_ref());
+        assert_eq!(content.span_id, 0x2a);
+        assert!(content.content.starts_with("Span 0x2a (toolcall"));
+        assert!(content.content.contains("Failed: tool_exception error."));
+        assert!(content.content.contains("gen_ai.tool.name: web_search."));
+        assert!(content.content.contains("prompt: find flights."));
+        assert!(content.content.len() < 2_500);
+    }
+
+    #[test]
+    fn test_parse_hypothesis() {
+        let span_ids = HashSet::from(["0x2a".to_string(), "0x2b".to_string()]);
+        let response = r#"{
+            "root_cause": "The search tool returned no results and the agent retried until the context overflowed.",
+            "category": "context_overflow",
+            "confidence": 1.4,
+            "implicated_spans": [
+                {"span_id": "0x002a", "role": "origin", "reason": "empty tool result"},
+                {"span_id": "0x2b", "role": "symptom", "reason": "overflow"},
+                {"span_id": "0x99", "role": "symptom", "reason": "not in trace"},
+                {"span_id": "0x2b", "role": "unknown", "reason": "bad role"}
+            ],
+            "evidence": ["7 identical tool calls"]
+        }"#;
+        let hypothesis = parse_hypothesis(response, &span_ids, FailureCategory::Other).unwrap();
+        assert_eq!(hypothesis.category, FailureCategory::ContextOverflow);
+        assert_eq!(hypothesis.confidence, 1.0);
+        assert_eq!(hypothesis.implicated_spans.len(), 2);
+        assert_eq!(hypothesis.implicated_spans[0].span_id, "0x2a");
+        assert_eq!(hypothesis.implicated_spans[0].role, SpanRole::Origin);
+        assert_eq!(hypothesis.evidence.len(), 1);
+        assert!(hypothesis.suggested_fixes.is_empty());
+
+        let hypothesis = parse_hypothesis(
+            r#"{"root_cause": "Provider throttling", "category": "overload"}"#,
+            &span_ids,
+            FailureCategory::RateLimit,
+        )
+        .unwrap();
+        assert_eq!(hypothesis.category, FailureCategory::RateLimit);
+        assert_eq!(hypothesis.confidence, 0.5);
+
+        assert!(parse_hypothesis(
+            r#"{"category": "timeout"}"#,
+            &span_ids,
+            FailureCategory::Other
+        )
+        .is_err());
+        assert!(parse_hypothesis("not json", &span_ids, FailureCategory::Other).is_err());
+    }
+}
@@ -0,0 +1,383 @@
+// Copyright 2025 AgentReplay (https://github.com/agentreplay)
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program. If not, see <https://www.gnu.org/licenses/>.
+
+//! Prometheus text exposition at `GET /metrics`
+//!
+//! Server-internal counters, gauges and per-route latency histograms for
+//! scraping; per-project trace metrics live under
+//! `/api/v1/projects/:project_id/metrics`.
+//!
+//! Scrapers accepting OpenMetrics get latency buckets with exemplars that
+//! name a trace stored by a request in the bucket.
+
+use std::fmt::Write;
+
+use axum::{
+    extract::State,
+    http::{header, HeaderMap},
+    response::IntoResponse,
+};
+
+use super::AppState;
+use crate::governor::ShardStats;
+use crate::middleware::request_metrics::{Exemplar, LATENCY_BUCKETS};
+
+/// Content type of the Prometheus text format
+const CONTENT_TYPE: &str = "text/plain; version=0.0.4; charset=utf-8";
+
+/// Content type of the OpenMetrics text format
+const OPENMETRICS_CONTENT_TYPE: &str = "application/openmetrics-text; version=1.0.0; charset=utf-8";
+
+/// Builder for the Prometheus and OpenMetrics text formats
+#[derive(Default)]
+pub struct MetricsText {
+    out: String,
+    openmetrics: bool,
+}
+
+impl MetricsText {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Builder for OpenMetrics, which adds exemplars
+    pub fn openmetrics() -> Self {
+        Self {
+            out: String::new(),
+            openmetrics: true,
+        }
+    }
+
+    /// Start a metric family
+    pub fn family(&mut self, name: &str, kind: &str, help: &str) -> &mut Self {
+        // OpenMetrics names a counter family without its `_total` suffix
+        let name = match name.strip_suffix("_total") {
+            Some(family) if self.openmetrics && kind == "counter" => family,
+            _ => name,
+        };
+        let _ = writeln!(self.out, "# HELP {} {}", name, help);
+        let _ = writeln!(self.out, "# TYPE {} {}", name, kind);
+        self
+    }
+
+    /// Add a sample to the current family
+    pub fn sample(&mut self, name: &str, labels: &[(&str, &str)], value: f64) -> &mut Self {
+        self.sample_with_exemplar(name, labels, value, None)
+    }
+
+    /// Add a sample, with an exemplar in OpenMetrics
+    pub fn sample_with_exemplar(
+        &mut self,
+        name: &str,
+        labels: &[(&str, &str)],
+        value: f64,
+        exemplar: Option<&Exemplar>,
+    ) -> &mut Self {
+        self.out.push_str(name);
+        if !labels.is_empty() {
+            self.out.push('{');
+            for (i, (key, val)) in labels.iter().enumerate() {
+                if i > 0 {
+                    self.out.push(',');
+                }
+                let val = val
+                    .replace('\\', "\\\\")
+                    .replace('"', "\\\"")
+                    .replace('\n', "\\n");
+                let _ = write!(self.out, "{}=\"{}\"", key, val);
+            }
+            self.out.push('}');
+        }
+        let _ = write!(self.out, " {}", value);
+        if let Some(exemplar) = exemplar.filter(|_| self.openmetrics) {
+            let _ = write!(
+                self.out,
+                " # {{trace_id=\"{}\"}} {} {:.3}",
+                exemplar.trace_id, exemplar.value, exemplar.timestamp
+            );
+        }
+        self.out.push('\n');
+        self
+    }
+
+    /// A family with a single unlabeled sample
+    pub fn single(&mut self, name: &str, kind: &str, help: &str, value: f64) -> &mut Self {
+        self.family(name, kind, help).sample(name, &[], value)
+    }
+
+    pub fn finish(mut self) -> String {
+        if self.openmetrics {
+            self.out.push_str("# EOF\n");
+        }
+        self.out
+    }
+}
+
+fn write_governor_metrics(metrics: &mut MetricsText, state: &AppState) {
+    let Some(governor) = state.semantic_governor.as_ref() else {
+        return;
+    };
+    let stats = governor.stats();
+
+    metrics
+        .single(
+            "agentreplay_governor_processed_total",
+            "counter",
+            "Traces checked for semantic duplicates",
+            stats.total_processed as f64,
+        )
+        .single(
+            "agentreplay_governor_stored_total",
+            "counter",
+            "Traces stored as novel",
+            stats.stored as f64,
+        )
+        .single(
+            "agentreplay_governor_dropped_total",
+            "counter",
+            "Traces dropped as semantic duplicates",
+            stats.dropped as f64,
+        )
+        .single(
+            "agentreplay_governor_exempted_total",
+            "counter",
+            "Traces from exempt agents or projects stored without a dedup check",
+            stats.exempted as f64,
+        )
+        .single(
+            "agentreplay_governor_epsilon",
+            "gauge",
+            "Cosine distance under which traces are deduplicated",
+            stats.epsilon as f64,
+        )
+        .single(
+            "agentreplay_governor_binary_quantization",
+            "gauge",
+            "Whether stored embeddings are binary quantized (1) or full precision (0)",
+            if stats.binary_quantization { 1.0 } else { 0.0 },
+        )
+        .single(
+            "agentreplay_governor_memory_bytes",
+            "gauge",
+            "Memory held by stored embeddings",
+            stats.memory_usage_bytes as f64,
+        );
+
+    let per_shard: [(&str, &str, &str, fn(&ShardStats) -> f64); 3] = [
+        (
+            "agentreplay_governor_shard_processed_total",
+            "counter",
+            "Traces checked per shard",
+            |s| s.processed as f64,
+        ),
+        (
+            "agentreplay_governor_shard_dropped_total",
+            "counter",
+            "Duplicates dropped per shard",
+            |s| s.dropped as f64,
+        ),
+        (
+            "agentreplay_governor_shard_vectors",
+            "gauge",
+            "Embeddings stored per shard",
+            |s| s.vectors as f64,
+        ),
+    ];
+    for (name, kind, help, value) in per_shard {
+        metrics.family(name, kind, help);
+        for shard in &stats.shards {
+            metrics.sample(name, &[("shard", &shard.shard.to_string())], value(shard));
+        }
+    }
+}
+
+fn write_ingestion_metrics(metrics: &mut MetricsText, state: &AppState) {
+    let Some(actor) = state.ingestion_actor.as_ref() else {
+        return;
+    };
+    let stats = actor.stats();
+
+    metrics
+        .single(
+            "agentreplay_ingestion_received_total",
+            "counter",
+            "Traces received by the ingestion actor",
+            stats.total_received as f64,
+        )
+        .single(
+            "agentreplay_ingestion_failed_total",
+            "counter",
+            "Traces that failed embedding or deduplication",
+            stats.total_failed as f64,
+        )
+        .single(
+            "agentreplay_ingestion_queue_depth",
+            "gauge",
+            "Traces waiting in the ingestion actor channel",
+            actor.queue_depth() as f64,
+        );
+}
+
+fn write_rate_limit_metrics(metrics: &mut MetricsText, state: &AppState) {
+    let limiter = &state.rate_limiter;
+    if !limiter.enabled() {
+        return;
+    }
+
+    let stats = limiter.stats();
+    metrics.family(
+        "agentreplay_rate_limit_requests_total",
+        "counter",
+        "Requests checked against route rate limits, by class and outcome",
+    );
+    for stat in &stats {
+        let class = stat.class.as_str();
+        metrics
+            .sample(
+                "agentreplay_rate_limit_requests_total",
+                &[("class", class), ("outcome", "allowed")],
+                stat.allowed as f64,
+            )
+            .sample(
+                "agentreplay_rate_limit_requests_total",
+                &[("class", class), ("outcome", "limited")],
+                stat.limited as f64,
+            );
+    }
+    metrics.single(
+        "agentreplay_rate_limit_buckets",
+        "gauge",
+        "Client and route class buckets currently tracked",
+        limiter.tracked_buckets() as f64,
+    );
+}
+
+fn write_request_metrics(metrics: &mut MetricsText, state: &AppState) {
+    let routes = state.request_metrics.snapshot();
+    if routes.is_empty() {
+        return;
+    }
+
+    let name = "agentreplay_http_request_duration_seconds";
+    let bounds: Vec<String> = LATENCY_BUCKETS
+        .iter()
+        .map(|bound| format!("{:?}", bound))
+        .chain(["+Inf".to_string()])
+        .collect();
+    metrics.family(
+        name,
+        "histogram",
+        "HTTP request latency by route, method and status class",
+    );
+    for route in &routes {
+        let labels = [
+            ("route", route.key.route.as_str()),
+            ("method", route.key.method.as_str()),
+            ("status", route.key.status),
+        ];
+        for ((le, count), exemplar) in bounds.iter().zip(route.buckets).zip(&route.exemplars) {
+            let mut bucket_labels = labels.to_vec();
+            bucket_labels.push(("le", le));
+            metrics.sample_with_exemplar(
+                &format!("{}_bucket", name),
+                &bucket_labels,
+                count as f64,
+                exemplar.as_ref(),
+            );
+        }
+        metrics
+            .sample(&format!("{}_sum", name), &labels, route.sum_seconds)
+            .sample(&format!("{}_count", name), &labels, route.count() as f64);
+    }
+}
+
+/// GET /metrics
+///
+/// Answers in OpenMetrics when the scraper accepts it, else in the
+/// Prometheus text format.
+pub async fn get_prometheus_metrics(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+) -> impl IntoResponse {
+    let openmetrics = headers
+        .get(header::ACCEPT)
+        .and_then(|accept| accept.to_str().ok())
+        .is_some_and(|accept| accept.contains("application/openmetrics-text"));
+    let (mut metrics, content_type) = if openmetrics {
+        (MetricsText::openmetrics(), OPENMETRICS_CONTENT_TYPE)
+    } else {
+        (MetricsText::new(), CONTENT_TYPE)
+    };
+    write_governor_metrics(&mut metrics, &state);
+    write_ingestion_metrics(&mut metrics, &state);
+    write_rate_limit_metrics(&mut metrics, &state);
+    write_request_metrics(&mut metrics, &state);
+
+    ([(header::CONTENT_TYPE, content_type)], metrics.finish())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_metrics_text_format() {
+        let mut metrics = MetricsText::new();
+        metrics
+            .single("requests_total", "counter", "Requests served", 3.0)
+            .family("shard_vectors", "gauge", "Vectors per shard")
+            .sample("shard_vectors", &[("shard", "0"), ("note", "a\"b")], 1.5);
+
+        assert_eq!(
+            metrics.finish(),
+            "# HELP requests_total Requests served\n\
+             # TYPE requests_total counter\n\
+             requests_total 3\n\
+             # HELP shard_vectors Vectors per shard\n\
+             # TYPE shard_vectors gauge\n\
+             shard_vectors{shard=\"0\",note=\"a\\\"b\"} 1.5\n"
+        );
+    }
+
+    #[test]
+    fn test_openmetrics_exemplars() {
+        let exemplar = Exemplar {
+            trace_id: "0xab".to_string(),
+            value: 0.07,
+            timestamp: 1700000000.5,
+        };
+        let render = |mut metrics: MetricsText| {
+            let le = [("le", "0.1")];
+            metrics
+                .single("requests_total", "counter", "Requests served", 3.0)
+                .family("latency_seconds", "histogram", "Latency")
+                .sample_with_exemplar("latency_seconds_bucket", &le, 2.0, Some(&exemplar));
+            metrics.finish()
+        };
+
+        assert_eq!(
+            render(MetricsText::openmetrics()),
+            "# HELP requests Requests served\n\
+             # TYPE requests counter\n\
+             requests_total 3\n\
+             # HELP latency_seconds Latency\n\
+             # TYPE latency_seconds histogram\n\
+             latency_seconds_bucket{le=\"0.1\"} 2 # {trace_id=\"0xab\"} 0.07 1700000000.500\n\
+             # EOF\n"
+        );
+        // The Prometheus text format has no exemplars
+        assert!(!render(MetricsText::new()).contains("trace_id"));
+    }
+}
@@ -13,22 +13,94 @@
 // You should have received a copy of the GNU Affero General Public License
 // along with this program. If not, see <https://www.gnu.org/licenses/>.
 
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use agentreplay_core::clock::now_us;
+use agentreplay_core::{eval::EvalMetric, AgentFlowEdge};
+use agentreplay_query::Agentreplay;
+use agentreplay_storage::FeedbackRecord;
 use axum::{
-    extract::{Path, State},
+    extract::{Path, Query, State},
     http::StatusCode,
     response::IntoResponse,
     Extension, Json,
 };
 use serde::{Deserialize, Serialize};
-use tracing::debug;
+use tracing::{debug, warn};
 
 use crate::api::{ApiError, AppState};
 use crate::auth::AuthContext;
+use crate::otel_genai::GenAIPayload;
+use crate::scaling::run_query;
+use crate::session_analysis::{attr_string, ATTR_PROMPT_NAME, ATTR_PROMPT_VERSION};
+
+/// Eval metric recording each piece of feedback as a score in [0, 1]
+pub const FEEDBACK_METRIC: &str = "user_feedback";
+/// Evaluator name of [`FEEDBACK_METRIC`]
+pub const FEEDBACK_EVALUATOR: &str = "human";
+
+const MAX_TAGS: usize = 20;
+const MAX_TAG_LEN: usize = 64;
+const MAX_COMMENT_LEN: usize = 4000;
+const MAX_TOP_TAGS: usize = 10;
+const DEFAULT_LOOKBACK_US: u64 = 30 * 24 * 3_600_000_000; // 30 days
 
 /// Request body for trace feedback
+///
+/// At least one of `feedback` and `rating` is required.
 #[derive(Debug, Deserialize)]
 pub struct FeedbackRequest {
-    pub feedback: i8, // 1 for thumbs up, -1 for thumbs down
+    /// 1 for thumbs up, -1 for thumbs down
+    #[serde(default)]
+    pub feedback: Option<i8>,
+    /// Star rating from 1 to 5
+    #[serde(default)]
+    pub rating: Option<u8>,
+    #[serde(default)]
+    pub tags: Vec<String>,
+    #[serde(default)]
+    pub comment: Option<String>,
+    /// End user giving the feedback
+    #[serde(default)]
+    pub user_id: Option<String>,
+}
+
+impl FeedbackRequest {
+    fn validate(&self) -> Result<(), ApiError> {
+        if self.feedback.is_none() && self.rating.is_none() {
+            return Err(ApiError::BadRequest(
+                "Feedback needs 'feedback' (1 or -1) or 'rating' (1-5)".to_string(),
+            ));
+        }
+        if matches!(self.feedback, Some(f) if f != 1 && f != -1) {
+            return Err(ApiError::BadRequest(
+                "Feedback must be 1 (positive) or -1 (negative)".to_string(),
+            ));
+        }
+        if matches!(self.rating, Some(r) if !(1..=5).contains(&r)) {
+            return Err(ApiError::BadRequest(
+                "Rating must be between 1 and 5".to_string(),
+            ));
+        }
+        if self.tags.len() > MAX_TAGS || self.tags.iter().any(|t| t.len() > MAX_TAG_LEN) {
+            return Err(ApiError::BadRequest(format!(
+                "At most {} tags of up to {} characters",
+                MAX_TAGS, MAX_TAG_LEN
+            )));
+        }
+        if self
+            .comment
+            .as_ref()
+            .is_some_and(|c| c.len() > MAX_COMMENT_LEN)
+        {
+            return Err(ApiError::BadRequest(format!(
+                "Comment exceeds {} characters",
+                MAX_COMMENT_LEN
+            )));
+        }
+        Ok(())
+    }
 }
 
 /// Request body for adding trace to dataset
@@ -54,53 +126,83 @@ pub struct DatasetResponse {
     pub dataset_name: String,
 }
 
-/// POST /api/v1/traces/:trace_id/feedback - Submit thumbs up/down feedback for a trace
+pub(crate) fn parse_trace_id(trace_id: &str) -> Result<u128, ApiError> {
+    u128::from_str_radix(trace_id.trim_start_matches("0x"), 16)
+        .map_err(|_| ApiError::BadRequest(format!("Invalid trace_id format: {}", trace_id)))
+}
+
+/// The shard holding a tenant's trace, and the trace's root edge
+pub(crate) fn find_trace(
+    state: &AppState,
+    edge_id: u128,
+    tenant_id: u64,
+) -> Result<Option<(Arc<Agentreplay>, AgentFlowEdge)>, ApiError> {
+    for db in super::metrics::project_shards(state, None)? {
+        let edge = db
+            .get(edge_id)
+            .map_err(|e| ApiError::Internal(e.to_string()))?;
+        if let Some(edge) = edge.filter(|edge| edge.tenant_id == tenant_id) {
+            return Ok(Some((db, edge)));
+        }
+    }
+    Ok(None)
+}
+
+/// POST /api/v1/traces/:trace_id/feedback - Submit feedback for a trace
+///
+/// Stores thumbs, a 1-5 rating, tags and a comment, and records the
+/// feedback as the `user_feedback` eval metric of the trace.
 pub async fn submit_trace_feedback(
     State(state): State<AppState>,
     Extension(auth): Extension<AuthContext>,
     Path(trace_id): Path<String>,
     Json(req): Json<FeedbackRequest>,
 ) -> Result<impl IntoResponse, ApiError> {
-    debug!(
-        "Submitting feedback for trace {}: {}",
-        trace_id, req.feedback
-    );
+    debug!("Submitting feedback for trace {}: {:?}", trace_id, req);
+    req.validate()?;
 
-    // Validate feedback value
-    if req.feedback != 1 && req.feedback != -1 {
-        return Err(ApiError::BadRequest(
-            "Feedback must be 1 (positive) or -1 (negative)".to_string(),
-        ));
-    }
-
-    // Parse trace_id as edge_id
-    let edge_id = u128::from_str_radix(&trace_id, 16)
-        .map_err(|_| ApiError::BadRequest(format!("Invalid trace_id format: {}", trace_id)))?;
-
-    // Store feedback as metadata on the edge
-    let mut feedback_data = serde_json::Map::new();
-    feedback_data.insert("feedback".to_string(), serde_json::json!(req.feedback));
-    feedback_data.insert("tenant_id".to_string(), serde_json::json!(auth.tenant_id));
-    feedback_data.insert(
-        "timestamp".to_string(),
-        serde_json::json!(std::time::SystemTime::now()
-            .duration_since(std::time::UNIX_EPOCH)
-            .unwrap()
-            .as_secs()),
-    );
-
-    let feedback_json = serde_json::to_vec(&serde_json::Value::Object(feedback_data))
-        .map_err(|e| ApiError::Internal(format!("Failed to serialize feedback: {}", e)))?;
-
-    // Store as payload with special prefix (feedback_)
-    let feedback_key = format!("feedback_{}", edge_id);
-    let feedback_edge_id = crate::api::ingest::hash_string_to_u64(&feedback_key) as u128;
+    let edge_id = parse_trace_id(&trace_id)?;
+    let (_, edge) = find_trace(&state, edge_id, auth.tenant_id)?
+        .ok_or_else(|| ApiError::NotFound(format!("Trace {} not found", trace_id)))?;
 
+    let created_at = now_us();
+    let record = FeedbackRecord {
+        edge_id,
+        tenant_id: auth.tenant_id,
+        project_id: edge.project_id,
+        thumbs: req.feedback,
+        rating: req.rating,
+        tags: req
+            .tags
+            .iter()
+            .map(|tag| tag.trim().to_lowercase())
+            .filter(|tag| !tag.is_empty())
+            .collect(),
+        comment: req.comment.filter(|c| !c.trim().is_empty()),
+        user_id: req.user_id,
+        created_at,
+    };
     state
         .db
-        .put_payload(feedback_edge_id, &feedback_json)
+        .put_feedback(&record)
         .map_err(|e| ApiError::Internal(format!("Failed to store feedback: {}", e)))?;
 
+    let metric = record.score().and_then(|score| {
+        EvalMetric::new(
+            edge_id,
+            FEEDBACK_METRIC,
+            score,
+            FEEDBACK_EVALUATOR,
+            created_at,
+        )
+    });
+    if let Some(metric) = metric {
+        if let Err(e) = state.db.store_eval_metrics(edge_id, vec![metric]) {
+            warn!("Failed to record feedback metric for {:#x}: {}", edge_id, e);
+        }
+        crate::session_summary::record_eval_metrics(&state, edge_id, &[metric]);
+    }
+
     Ok((
         StatusCode::OK,
         Json(FeedbackResponse {
@@ -110,6 +212,394 @@ pub async fn submit_trace_feedback(
     ))
 }
 
+/// GET /api/v1/traces/:trace_id/feedback - Feedback given on a trace
+pub async fn list_trace_feedback(
+    State(state): State<AppState>,
+    Extension(auth): Extension<AuthContext>,
+    Path(trace_id): Path<String>,
+) -> Result<Json<Vec<FeedbackRecord>>, ApiError> {
+    let edge_id = parse_trace_id(&trace_id)?;
+    let feedback = state
+        .db
+        .list_feedback(auth.tenant_id, Some(edge_id))
+        .map_err(|e| ApiError::Internal(e.to_string()))?;
+    Ok(Json(feedback))
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum FeedbackGroupBy {
+    #[default]
+    Model,
+    PromptVersion,
+    Experiment,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct FeedbackAnalyticsParams {
+    /// Feedback given at or after this time (microseconds)
+    pub start_ts: Option<u64>,
+    pub end_ts: Option<u64>,
+    #[serde(default)]
+    pub group_by: FeedbackGroupBy,
+    pub project_id: Option<u16>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct TagCount {
+    pub tag: String,
+    pub count: u64,
+}
+
+/// Aggregated feedback of a group of traces
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct FeedbackStats {
+    pub count: u64,
+    pub thumbs_up: u64,
+    pub thumbs_down: u64,
+    /// `thumbs_up / (thumbs_up + thumbs_down)`
+    pub positive_rate: Option<f64>,
+    pub ratings: u64,
+    pub avg_rating: Option<f64>,
+    /// Mean feedback score in [0, 1] (see [`FeedbackRecord::score`])
+    pub avg_score: Option<f64>,
+    pub top_tags: Vec<TagCount>,
+}
+
+#[derive(Debug, Default)]
+struct FeedbackAccum {
+    count: u64,
+    thumbs_up: u64,
+    thumbs_down: u64,
+    rating_sum: u64,
+    ratings: u64,
+    score_sum: f64,
+    scores: u64,
+    tags: HashMap<String, u64>,
+}
+
+impl FeedbackAccum {
+    fn record(&mut self, feedback: &FeedbackRecord) {
+        self.count += 1;
+        match feedback.thumbs {
+            Some(t) if t > 0 => self.thumbs_up += 1,
+            Some(_) => self.thumbs_down += 1,
+            None => {}
+        }
+        if let Some(rating) = feedback.rating {
+            self.rating_sum += rating as u64;
+            self.ratings += 1;
+        }
+        if let Some(score) = feedback.score() {
+            self.score_sum += score;
+            self.scores += 1;
+        }
+        for tag in &feedback.tags {
+            *self.tags.entry(tag.clone()).or_default() += 1;
+        }
+    }
+
+    fn finish(self) -> FeedbackStats {
+        let thumbs = self.thumbs_up + self.thumbs_down;
+        let mut top_tags: Vec<TagCount> = self
+            .tags
+            .into_iter()
+            .map(|(tag, count)| TagCount { tag, count })
+            .collect();
+        top_tags.sort_by(|a, b| b.count.cmp(&a.count).then_with(|| a.tag.cmp(&b.tag)));
+        top_tags.truncate(MAX_TOP_TAGS);
+        FeedbackStats {
+            count: self.count,
+            thumbs_up: self.thumbs_up,
+            thumbs_down: self.thumbs_down,
+            positive_rate: (thumbs > 0).then(|| self.thumbs_up as f64 / thumbs as f64),
+            ratings: self.ratings,
+            avg_rating: (self.ratings > 0).then(|| self.rating_sum as f64 / self.ratings as f64),
+            avg_score: (self.scores > 0).then(|| self.score_sum / self.scores as f64),
+            top_tags,
+        }
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub struct FeedbackGroup {
+    pub key: String,
+    #[serde(flatten)]
+    pub stats: FeedbackStats,
+}
+
+/// How well an automated eval metric agrees with user feedback
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct MetricCorrelation {
+    pub metric: String,
+    pub evaluator: String,
+    /// Feedback entries whose trace has this metric
+    pub samples: usize,
+    /// Pearson correlation of metric value and feedback score (None when
+    /// either side is constant)
+    pub pearson: Option<f64>,
+    /// Mean metric value on traces with positive (score >= 0.5) feedback
+    pub mean_when_positive: Option<f64>,
+    pub mean_when_negative: Option<f64>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct FeedbackAnalytics {
+    pub start_ts: u64,
+    pub end_ts: u64,
+    pub group_by: FeedbackGroupBy,
+    pub total: FeedbackStats,
+    /// Most feedback first
+    pub groups: Vec<FeedbackGroup>,
+    /// Strongest correlation first
+    pub correlations: Vec<MetricCorrelation>,
+}
+
+/// Pearson correlation coefficient of paired samples
+fn pearson(pairs: &[(f64, f64)]) -> Option<f64> {
+    if pairs.len() < 2 {
+        return None;
+    }
+    let n = pairs.len() as f64;
+    let mean_x = pairs.iter().map(|(x, _)| x).sum::<f64>() / n;
+    let mean_y = pairs.iter().map(|(_, y)| y).sum::<f64>() / n;
+    let (mut cov, mut var_x, mut var_y) = (0.0, 0.0, 0.0);
+    for (x, y) in pairs {
+        cov += (x - mean_x) * (y - mean_y);
+        var_x += (x - mean_x).powi(2);
+        var_y += (y - mean_y).powi(2);
+    }
+    if var_x <= f64::EPSILON || var_y <= f64::EPSILON {
+        return None;
+    }
+    Some(cov / (var_x * var_y).sqrt())
+}
+
+fn mean(values: impl Iterator<Item = f64>) -> Option<f64> {
+    let (sum, count) = values.fold((0.0, 0usize), |(sum, count), v| (sum + v, count + 1));
+    (count > 0).then(|| sum / count as f64)
+}
+
+/// Correlate feedback scores with the eval metrics of the same traces;
+/// `samples` pairs each feedback score with `(metric, evaluator, value)`
+fn correlate(samples: &[(f64, Vec<(String, String, f64)>)]) -> Vec<MetricCorrelation> {
+    let mut by_metric: HashMap<(String, String), Vec<(f64, f64)>> = HashMap::new();
+    for (score, metrics) in samples {
+        for (metric, evaluator, value) in metrics {
+            by_metric
+                .entry((metric.clone(), evaluator.clone()))
+                .or_default()
+                .push((*score, *value));
+        }
+    }
+    let mut correlations: Vec<MetricCorrelation> = by_metric
+        .into_iter()
+        .map(|((metric, evaluator), pairs)| MetricCorrelation {
+            metric,
+            evaluator,
+            samples: pairs.len(),
+            pearson: pearson(&pairs),
+            mean_when_positive: mean(pairs.iter().filter(|(s, _)| *s >= 0.5).map(|(_, v)| *v)),
+            mean_when_negative: mean(pairs.iter().filter(|(s, _)| *s < 0.5).map(|(_, v)| *v)),
+        })
+        .collect();
+    correlations.sort_by(|a, b| {
+        let strength = |c: &MetricCorrelation| c.pearson.map(f64::abs).unwrap_or(-1.0);
+        strength(b)
+            .total_cmp(&strength(a))
+            .then_with(|| a.metric.cmp(&b.metric))
+    });
+    correlations
+}
+
+/// Model and `name@version` prompt of a trace, from its root span or the
+/// first descendant reporting them
+fn trace_model_and_prompt(
+    db: &Agentreplay,
+    edge_id: u128,
+    tenant_id: u64,
+) -> (Option<String>, Option<String>) {
+    let payload = |edge_id: u128| {
+        db.get_payload(edge_id)
+            .ok()
+            .flatten()
+            .and_then(|bytes| serde_json::from_slice::<GenAIPayload>(&bytes).ok())
+    };
+    let mut ids = vec![edge_id];
+    ids.extend(
+        db.get_descendants_for_tenant(edge_id, tenant_id)
+            .unwrap_or_default()
+            .into_iter()
+            .map(|edge| edge.edge_id),
+    );
+
+    let (mut model, mut prompt) = (None, None);
+    for id in ids {
+        let Some(payload) = payload(id) else {
+            continue;
+        };
+        if model.is_none() {
+            model = payload
+                .response_model
+                .clone()
+                .or(payload.request_model.clone());
+        }
+        if prompt.is_none() {
+            prompt = attr_string(&payload, ATTR_PROMPT_NAME).map(|name| {
+                match attr_string(&payload, ATTR_PROMPT_VERSION) {
+                    Some(version) => format!("{}@{}", name, version),
+                    None => name,
+                }
+            });
+        }
+        if model.is_some() && prompt.is_some() {
+            break;
+        }
+    }
+    (model, prompt)
+}
+
+/// GET /api/v1/feedback/analytics?group_by=model|prompt_version|experiment
+///
+/// Feedback aggregated per model, prompt version or experiment variant,
+/// and its correlation with automated eval scores.
+pub async fn get_feedback_analytics(
+    State(state): State<AppState>,
+    Extension(auth): Extension<AuthContext>,
+    Query(params): Query<FeedbackAnalyticsParams>,
+) -> Result<Json<FeedbackAnalytics>, ApiError> {
+    let end_ts = params.end_ts.unwrap_or_else(now_us);
+    let start_ts = params
+        .start_ts
+        .unwrap_or(end_ts.saturating_sub(DEFAULT_LOOKBACK_US));
+    if end_ts <= start_ts {
+        return Err(ApiError::BadRequest(
+            "end_ts must be greater than start_ts".into(),
+        ));
+    }
+
+    let tenant_id = auth.tenant_id;
+    let group_by = params.group_by;
+    let project_id = params.project_id;
+    let queue = state.clone();
+    let analytics = run_query(&queue, move || -> Result<FeedbackAnalytics, ApiError> {
+        let mut feedback = state
+            .db
+            .list_feedback(tenant_id, None)
+            .map_err(|e| ApiError::Internal(e.to_string()))?;
+        feedback.retain(|f| {
+            f.created_at >= start_ts
+                && f.created_at < end_ts
+                && project_id.map_or(true, |p| f.project_id == p)
+        });
+
+        // Experiment variant of each trace that recorded a result
+        let mut variants: HashMap<u128, String> = HashMap::new();
+        if group_by == FeedbackGroupBy::Experiment {
+            let experiments = state
+                .db
+                .list_experiments()
+                .map_err(|e| ApiError::Internal(e.to_string()))?;
+            for experiment in experiments {
+                let results = state
+                    .db
+                    .get_experiment_results(experiment.id)
+                    .map_err(|e| ApiError::Internal(e.to_string()))?;
+                for result in results {
+                    variants.insert(
+                        result.trace_id,
+                        format!("{}/{}", experiment.name, result.variant_id),
+                    );
+                }
+            }
+        }
+
+        let mut total = FeedbackAccum::default();
+        let mut groups: HashMap<String, FeedbackAccum> = HashMap::new();
+        let mut trace_keys: HashMap<u128, String> = HashMap::new();
+        let mut samples = Vec::new();
+        for record in &feedback {
+            total.record(record);
+
+            let key = match trace_keys.get(&record.edge_id) {
+                Some(key) => key.clone(),
+                None => {
+                    let key = match group_by {
+                        FeedbackGroupBy::Experiment => variants
+                            .get(&record.edge_id)
+                            .cloned()
+                            .unwrap_or_else(|| "none".to_string()),
+                        _ => {
+                            let shard = match state.project_manager {
+                                Some(ref pm) => pm.get_or_open_project(record.project_id).ok(),
+                                None => Some(state.db.clone()),
+                            };
+                            let (model, prompt) = shard
+                                .map(|db| trace_model_and_prompt(&db, record.edge_id, tenant_id))
+                                .unwrap_or_default();
+                            if group_by == FeedbackGroupBy::Model {
+                                model.unwrap_or_else(|| "unknown".to_string())
+                            } else {
+                                prompt.unwrap_or_else(|| "unversioned".to_string())
+                            }
+                        }
+                    };
+                    trace_keys.insert(record.edge_id, key.clone());
+                    key
+                }
+            };
+            groups.entry(key).or_default().record(record);
+
+            if let Some(score) = record.score() {
+                let metrics = state
+                    .db
+                    .get_eval_metrics(record.edge_id)
+                    .unwrap_or_default()
+                    .into_iter()
+                    .filter(|m| m.get_evaluator() != FEEDBACK_EVALUATOR)
+                    .map(|m| {
+                        (
+                            m.get_metric_name().to_string(),
+                            m.get_evaluator().to_string(),
+                            m.metric_value,
+                        )
+                    })
+                    .collect::<Vec<_>>();
+                if !metrics.is_empty() {
+                    samples.push((score, metrics));
+                }
+            }
+        }
+
+        let mut groups: Vec<FeedbackGroup> = groups
+            .into_iter()
+            .map(|(key, accum)| FeedbackGroup {
+                key,
+                stats: accum.finish(),
+            })
+            .collect();
+        groups.sort_by(|a, b| {
+            b.stats
+                .count
+                .cmp(&a.stats.count)
+                .then_with(|| a.key.cmp(&b.key))
+        });
+
+        Ok(FeedbackAnalytics {
+            start_ts,
+            end_ts,
+            group_by,
+            total: total.finish(),
+            groups,
+            correlations: correlate(&samples),
+        })
+    })
+    .await
+    .map_err(|e| ApiError::Internal(format!("Feedback analytics task panicked: {}", e)))??;
+
+    Ok(Json(analytics))
+}
+
 /// POST /api/v1/datasets/:name/add - Add trace to evaluation dataset
 pub async fn add_trace_to_dataset(
     State(state): State<AppState>,
@@ -160,3 +650,87 @@ pub async fn add_trace_to_dataset(
         }),
     ))
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn feedback(thumbs: Option<i8>, rating: Option<u8>, tags: &[&str]) -> FeedbackRecord {
+        FeedbackRecord {
+            edge_id: 1,
+            tenant_id: 1,
+            project_id: 0,
+            thumbs,
+            rating,
+            tags: tags.iter().map(|t| t.to_string()).collect(),
+            comment: None,
+            user_id: None,
+            created_at: 0,
+        }
+    }
+
+    #[test]
+    fn test_feedback_accum() {
+        let mut accum = FeedbackAccum::default();
+        accum.record(&feedback(Some(1), None, &["helpful"]));
+        accum.record(&feedback(Some(1), Some(5), &["helpful", "fast"]));
+        accum.record(&feedback(Some(-1), Some(2), &["wrong"]));
+        accum.record(&feedback(None, Some(3), &[]));
+        let stats = accum.finish();
+
+        assert_eq!(stats.count, 4);
+        assert_eq!((stats.thumbs_up, stats.thumbs_down), (2, 1));
+        assert!((stats.positive_rate.unwrap() - 2.0 / 3.0).abs() < 1e-9);
+        assert_eq!(stats.avg_rating, Some(10.0 / 3.0));
+        // Scores: 1.0, 1.0 (rating 5), 0.25, 0.5
+        assert!((stats.avg_score.unwrap() - 0.6875).abs() < 1e-9);
+        assert_eq!(stats.top_tags[0].tag, "helpful");
+        assert_eq!(stats.top_tags[0].count, 2);
+    }
+
+    #[test]
+    fn test_correlate() {
+        let metric = |name: &str, value: f64| (name.to_string(), "ragas".to_string(), value);
+        let samples = vec![
+            (
+                1.0,
+                vec![metric("faithfulness", 0.9), metric("latency", 0.5)],
+            ),
+            (
+                1.0,
+                vec![metric("faithfulness", 0.8), metric("latency", 0.5)],
+            ),
+            (
+                0.0,
+                vec![metric("faithfulness", 0.2), metric("latency", 0.5)],
+            ),
+            (0.25, vec![metric("faithfulness", 0.3)]),
+        ];
+        let correlations = correlate(&samples);
+
+        assert_eq!(correlations[0].metric, "faithfulness");
+        assert_eq!(correlations[0].samples, 4);
+        assert!(correlations[0].pearson.unwrap() > 0.9);
+        assert!((correlations[0].mean_when_positive.unwrap() - 0.85).abs() < 1e-9);
+        assert!((correlations[0].mean_when_negative.unwrap() - 0.25).abs() < 1e-9);
+        // A constant metric has no correlation
+        assert_eq!(correlations[1].metric, "latency");
+        assert_eq!(correlations[1].pearson, None);
+    }
+
+    #[test]
+    fn test_feedback_request_validation() {
+        let request = |feedback: Option<i8>, rating: Option<u8>| FeedbackRequest {
+            feedback,
+            rating,
+            tags: Vec::new(),
+            comment: None,
+            user_id: None,
+        };
+        assert!(request(Some(1), None).validate().is_ok());
+        assert!(request(None, Some(4)).validate().is_ok());
+        assert!(request(None, None).validate().is_err());
+        assert!(request(Some(0), None).validate().is_err());
+        assert!(request(None, Some(6)).validate().is_err());
+    }
+}
@@ -18,16 +18,20 @@
 //! Implements the Dataset Flywheel pattern for auto-curating fine-tuning data:
 //! - τ_high (≥0.9): Traces with high eval scores → positive examples
 //! - τ_low (≤0.3): Traces with low eval scores → negative examples
-//! - Export to JSONL format for LLM fine-tuning
+//! - Export to JSONL format for LLM fine-tuning (`openai` and `anthropic`
+//!   formats emit only the fields those fine-tuning APIs accept)
 
 use axum::{
-    extract::{Query, State},
+    extract::{Extension, Query, State},
     Json,
 };
 use serde::{Deserialize, Serialize};
 
+use crate::api::dataset_formats::{messages_to_anthropic, messages_to_openai};
 use crate::api::{build_eval_trace_v1, AppState};
+use crate::auth::AuthContext;
 use agentreplay_core::{ContentPartV1, MessageV1};
+use agentreplay_query::TenantScope;
 
 /// Query parameters for getting flywheel candidates
 #[derive(Debug, Deserialize)]
@@ -108,7 +112,7 @@ pub struct ExportResponse {
     pub total_examples: usize,
 }
 
-fn merge_message_text(message: &MessageV1) -> String {
+pub(crate) fn merge_message_text(message: &MessageV1) -> String {
     let mut text = String::new();
     for part in &message.content {
         match part {
@@ -204,6 +208,8 @@ fn export_example(
     score: f64,
 ) -> serde_json::Value {
     match format {
+        "openai" => messages_to_openai(&eval_trace.outcome.messages),
+        "anthropic" => messages_to_anthropic(&eval_trace.outcome.messages),
         "chatml" => {
             let mut base = to_chatml(&eval_trace.outcome.messages);
             base["label"] = serde_json::json!(label);
@@ -233,6 +239,7 @@ fn export_example(
 /// Get flywheel candidates based on eval scores
 pub async fn get_candidates(
     State(state): State<AppState>,
+    Extension(auth): Extension<AuthContext>,
     Query(query): Query<CandidatesQuery>,
 ) -> Json<CandidatesResponse> {
     let mut positive_candidates = Vec::new();
@@ -250,7 +257,8 @@ pub async fn get_candidates(
     // Scan last 7 days of traces
     let week_ago = now_us.saturating_sub(7 * 24 * 60 * 60 * 1_000_000);
 
-    if let Ok(traces) = state.db.query_temporal_range(week_ago, now_us) {
+    let scoped = state.db.scoped(TenantScope::Tenant(auth.tenant_id));
+    if let Ok(traces) = scoped.range(week_ago, now_us) {
         for trace in traces.into_iter().take(query.limit * 10) {
             // Check if trace has eval metrics
             if let Ok(metrics) = state.db.get_eval_metrics(trace.edge_id) {
@@ -295,6 +303,7 @@ pub async fn get_candidates(
 /// Export fine-tuning dataset in JSONL format
 pub async fn export_dataset(
     State(state): State<AppState>,
+    Extension(auth): Extension<AuthContext>,
     Json(request): Json<ExportRequest>,
 ) -> Json<ExportResponse> {
     let mut jsonl_lines = Vec::new();
@@ -309,7 +318,8 @@ pub async fn export_dataset(
     // Scan last 30 days for more data
     let month_ago = now_us.saturating_sub(30 * 24 * 60 * 60 * 1_000_000);
 
-    if let Ok(traces) = state.db.query_temporal_range(month_ago, now_us) {
+    let scoped = state.db.scoped(TenantScope::Tenant(auth.tenant_id));
+    if let Ok(traces) = scoped.range(month_ago, now_us) {
         for trace in traces {
             if positive_count + negative_count >= request.max_examples {
                 break;
@@ -337,6 +347,13 @@ pub async fn export_dataset(
 
                 let eval_trace = build_eval_trace_v1(&state, &trace);
                 let mut entry = export_example(&request.format, &eval_trace, label, avg_score);
+                if matches!(request.format.as_str(), "openai" | "anthropic") {
+                    // Fine-tuning APIs reject lines with unknown keys
+                    if let Ok(line) = serde_json::to_string(&entry) {
+                        jsonl_lines.push(line);
+                    }
+                    continue;
+                }
                 entry["trace_id"] = serde_json::json!(format!("{:032x}", trace.edge_id));
                 entry["timestamp_us"] = serde_json::json!(trace.timestamp_us);
 
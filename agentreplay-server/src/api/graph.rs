@@ -18,10 +18,12 @@ use axum::{
     Json,
 };
 use agentreplay_core::AgentFlowEdge;
+use agentreplay_query::{build_merge_view, BranchSpan};
 use serde::Serialize;
 use std::collections::HashMap;
 use tracing::debug;
 
+use crate::access_policy::AccessFilter;
 use crate::api::query::{find_edge_by_id_or_session, ApiError, AppState};
 use crate::auth::AuthContext;
 
@@ -49,12 +51,16 @@ pub struct GraphNode {
     pub confidence: Option<f32>,
     pub status: String,
     pub position: Option<Position>,
+    /// Spans this span merges or follows from, including spans outside the trace
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub linked_span_ids: Vec<String>,
 }
 
 #[derive(Debug, Serialize)]
 pub struct GraphEdge {
     pub source: String,
     pub target: String,
+    /// `causal` for the parent, or the link kind (`fan_in`, `follows_from`)
     pub edge_type: String,
 }
 
@@ -81,6 +87,7 @@ pub async fn get_trace_graph(
     State(state): State<AppState>,
     Path(trace_id): Path<String>,
     auth: axum::Extension<AuthContext>,
+    axum::Extension(access): axum::Extension<AccessFilter>,
 ) -> Result<Json<GraphResponse>, ApiError> {
     // Parse trace ID
     let trace_id_u128 = u128::from_str_radix(trace_id.trim_start_matches("0x"), 16)
@@ -92,11 +99,12 @@ pub async fn get_trace_graph(
     // We reuse the logic from get_trace_observations to get the full tree
 
     // Get root span
-    let root = match find_edge_by_id_or_session(&state, trace_id_u128, auth.tenant_id).await {
-        Ok(Some(span)) => span,
-        Ok(None) => return Err(ApiError::NotFound("Trace not found".into())),
-        Err(e) => return Err(e),
-    };
+    let root =
+        match find_edge_by_id_or_session(&state, trace_id_u128, auth.tenant_id, &access).await {
+            Ok(Some(span)) => span,
+            Ok(None) => return Err(ApiError::NotFound("Trace not found".into())),
+            Err(e) => return Err(e),
+        };
 
     // Get the correct database (project-specific or fallback)
     let db = if let Some(ref pm) = state.project_manager {
@@ -208,6 +216,7 @@ pub async fn get_trace_graph(
             },
             status: status.to_string(),
             position: Some(Position { x, y }),
+            linked_span_ids: Vec::new(),
         };
 
         // Enrich with payload data
@@ -237,6 +246,25 @@ pub async fn get_trace_graph(
             // But the detail view could fetch /api/v1/traces/:id/attributes
         }
 
+        // Span links: fan-in from the spans this one merges
+        let links = if span.has_links() {
+            db.get_edge_links(span.edge_id)
+                .map_err(|e| ApiError::Internal(e.to_string()))?
+        } else {
+            Vec::new()
+        };
+        for link in &links {
+            let source = format!("{:#x}", link.span_id);
+            if node_depths.contains_key(&link.span_id) {
+                edges.push(GraphEdge {
+                    source: source.clone(),
+                    target: node_id.clone(),
+                    edge_type: link.kind.as_str().to_string(),
+                });
+            }
+            node.linked_span_ids.push(source);
+        }
+
         nodes.push(node);
 
         // Create Edge if parent exists and is in our dataset
@@ -262,3 +290,145 @@ pub async fn get_trace_graph(
         }),
     }))
 }
+
+// ============================================================================
+// Parallel Branch (Fan-out/Fan-in) View
+// ============================================================================
+
+#[derive(Debug, Serialize)]
+pub struct ParallelViewResponse {
+    pub trace_id: String,
+    pub groups: Vec<ParallelGroupView>,
+    /// Spans gating trace completion, in execution order
+    pub critical_path: Vec<String>,
+    pub critical_path_ms: f64,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ParallelGroupView {
+    pub group_id: String,
+    pub parent_span_id: String,
+    /// True when grouped by time overlap rather than an ingest-time group ID
+    pub inferred: bool,
+    pub fan_out_offset_ms: f64,
+    pub wall_time_ms: f64,
+    pub serial_time_ms: f64,
+    pub speedup: f64,
+    pub slowest_branch: String,
+    pub branches: Vec<ParallelBranchView>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ParallelBranchView {
+    pub span_id: String,
+    pub label: String,
+    pub start_offset_ms: f64,
+    pub latency_ms: f64,
+    pub slack_ms: f64,
+    pub span_count: usize,
+}
+
+/// GET /api/v1/traces/:trace_id/parallel
+/// Group parallel branches of a trace and find the slowest branch of each fan-out
+pub async fn get_trace_parallel_view(
+    State(state): State<AppState>,
+    Path(trace_id): Path<String>,
+    auth: axum::Extension<AuthContext>,
+    axum::Extension(access): axum::Extension<AccessFilter>,
+) -> Result<Json<ParallelViewResponse>, ApiError> {
+    let trace_id_u128 = u128::from_str_radix(trace_id.trim_start_matches("0x"), 16)
+        .map_err(|_| ApiError::BadRequest("Invalid trace ID format".into()))?;
+
+    let root = find_edge_by_id_or_session(&state, trace_id_u128, auth.tenant_id, &access)
+        .await?
+        .ok_or_else(|| ApiError::NotFound("Trace not found".into()))?;
+
+    let db = match state.project_manager {
+        Some(ref pm) => pm
+            .get_or_open_project(root.project_id)
+            .unwrap_or_else(|_| state.db.clone()),
+        None => state.db.clone(),
+    };
+
+    const MAX_SPANS: usize = 10_000;
+    const MAX_DEPTH: usize = 1000;
+    let all_spans_with_depth = db
+        .get_descendants_with_depth_for_tenant(root.edge_id, auth.tenant_id, MAX_DEPTH, MAX_SPANS)
+        .map_err(|e| ApiError::Internal(e.to_string()))?;
+    if all_spans_with_depth.len() >= MAX_SPANS {
+        return Err(ApiError::BadRequest("Trace too large".into()));
+    }
+
+    // Group IDs and operation names live in the payload
+    let branch_spans: Vec<BranchSpan> = all_spans_with_depth
+        .iter()
+        .map(|(span, _)| {
+            let payload: Option<crate::otel_genai::GenAIPayload> = (span.has_payload != 0)
+                .then(|| db.get_payload(span.edge_id).ok().flatten())
+                .flatten()
+                .and_then(|bytes| serde_json::from_slice(&bytes).ok());
+            let group_id = payload.as_ref().and_then(|p| {
+                p.additional
+                    .get(crate::ingestion::ATTR_PARALLEL_GROUP)
+                    .map(|v| v.as_str().map_or_else(|| v.to_string(), String::from))
+            });
+            let label = payload
+                .and_then(|p| p.operation_name.or(p.request_model))
+                .unwrap_or_else(|| format!("{:?}", span.span_type));
+            BranchSpan {
+                edge_id: span.edge_id,
+                parent_id: if span.edge_id == root.edge_id {
+                    0
+                } else {
+                    span.causal_parent
+                },
+                start_us: span.timestamp_us,
+                duration_us: span.duration_us as u64,
+                group_id,
+                label,
+            }
+        })
+        .collect();
+
+    let view = build_merge_view(&branch_spans);
+    let origin_us = root.timestamp_us;
+    let ms = |us: u64| us as f64 / 1000.0;
+
+    let groups = view
+        .groups
+        .iter()
+        .map(|group| ParallelGroupView {
+            group_id: group.group_id.clone(),
+            parent_span_id: format!("{:#x}", group.parent_id),
+            inferred: group.inferred,
+            fan_out_offset_ms: ms(group.fan_out_us.saturating_sub(origin_us)),
+            wall_time_ms: ms(group.wall_time_us),
+            serial_time_ms: ms(group.serial_time_us),
+            speedup: group.speedup(),
+            slowest_branch: format!("{:#x}", group.slowest_branch),
+            branches: group
+                .branches
+                .iter()
+                .map(|branch| ParallelBranchView {
+                    span_id: format!("{:#x}", branch.edge_id),
+                    label: branch.label.clone(),
+                    start_offset_ms: ms(branch.start_us.saturating_sub(origin_us)),
+                    latency_ms: ms(branch.latency_us),
+                    slack_ms: ms(branch.slack_us),
+                    span_count: branch.span_count,
+                })
+                .collect(),
+        })
+        .collect();
+
+    Ok(Json(ParallelViewResponse {
+        trace_id: format!("{:#x}", root.edge_id),
+        groups,
+        critical_path: view
+            .critical_path
+            .iter()
+            .map(|id| format!("{:#x}", id))
+            .collect(),
+        critical_path_ms: ms(view.critical_path_us),
+    }))
+}
@@ -13,11 +13,15 @@
 // You should have received a copy of the GNU Affero General Public License
 // along with this program. If not, see <https://www.gnu.org/licenses/>.
 
+use std::collections::BTreeMap;
+
+use agentreplay_storage::WriteTelemetryReport;
 use axum::{extract::State, http::StatusCode, response::IntoResponse, Json};
 use serde::Serialize;
 use tracing::debug;
 
 use crate::api::{ApiError, AppState};
+use crate::llm::failover::CircuitStatus;
 
 /// Health check response structure
 #[derive(Debug, Serialize)]
@@ -27,12 +31,57 @@ pub struct HealthResponse {
     pub uptime_seconds: u64,
     pub storage: StorageHealth,
     pub api: ApiHealth,
+    /// Circuit breakers of the LLM providers called since startup; an open
+    /// circuit degrades LLM features but not the server's status
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub llm_circuits: Vec<CircuitStatus>,
 }
 
 #[derive(Debug, Serialize)]
 pub struct StorageHealth {
     pub reachable: bool,
     pub total_edges: u64,
+    /// WAL vs logical bytes, fsync latency and group commit sizes since startup
+    pub write_path: WriteTelemetryReport,
+    /// Same, per open project database (multi-project mode only)
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    pub project_write_paths: BTreeMap<u16, WriteTelemetryReport>,
+    /// Hints for diagnosing slow ingestion from the write path telemetry
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub write_path_warnings: Vec<String>,
+}
+
+/// Fsync p99 above this usually means a slow or contended disk
+const SLOW_FSYNC_P99_MS: f64 = 100.0;
+/// Overall write amplification considered high
+const HIGH_WRITE_AMPLIFICATION: f64 = 10.0;
+/// Commits needed before batch sizes say anything about group commit
+const MIN_COMMITS_FOR_BATCH_WARNING: u64 = 100;
+
+/// Warnings for write path telemetry that explain slow ingestion
+fn write_path_warnings(label: &str, report: &WriteTelemetryReport) -> Vec<String> {
+    let mut warnings = Vec::new();
+    if report.fsync.p99_ms > SLOW_FSYNC_P99_MS {
+        warnings.push(format!(
+            "{}: fsync p99 is {:.0}ms; the data directory's disk is slow or contended",
+            label, report.fsync.p99_ms
+        ));
+    }
+    if report.group_commit.commits >= MIN_COMMITS_FOR_BATCH_WARNING
+        && report.group_commit.avg_batch_size < 2.0
+    {
+        warnings.push(format!(
+            "{}: commits average {:.1} writes; send spans in batches to amortize fsyncs",
+            label, report.group_commit.avg_batch_size
+        ));
+    }
+    if report.write_amplification.overall_wa > HIGH_WRITE_AMPLIFICATION {
+        warnings.push(format!(
+            "{}: write amplification is {:.1}x (WAL bytes vs logical bytes)",
+            label, report.write_amplification.overall_wa
+        ));
+    }
+    warnings
 }
 
 #[derive(Debug, Serialize)]
@@ -43,7 +92,7 @@ pub struct ApiHealth {
 
 /// GET /api/v1/health - Comprehensive health check endpoint
 pub async fn health_check_detailed(
-    State(_state): State<AppState>,
+    State(state): State<AppState>,
 ) -> Result<impl IntoResponse, ApiError> {
     debug!("Health check requested");
 
@@ -52,6 +101,20 @@ pub async fn health_check_detailed(
     let storage_reachable = true;
     let total_edges = 0u64;
 
+    let write_path = state.db.write_telemetry();
+    let mut warnings = write_path_warnings("default database", &write_path);
+    let mut project_write_paths = BTreeMap::new();
+    if let Some(pm) = &state.project_manager {
+        for (project_id, db) in pm.open_projects() {
+            let report = db.write_telemetry();
+            warnings.extend(write_path_warnings(
+                &format!("project {}", project_id),
+                &report,
+            ));
+            project_write_paths.insert(project_id, report);
+        }
+    }
+
     // Get uptime (simplified - using current timestamp)
     let uptime = std::time::SystemTime::now()
         .duration_since(std::time::UNIX_EPOCH)
@@ -69,11 +132,19 @@ pub async fn health_check_detailed(
         storage: StorageHealth {
             reachable: storage_reachable,
             total_edges,
+            write_path,
+            project_write_paths,
+            write_path_warnings: warnings,
         },
         api: ApiHealth {
             requests_total: 0, // TODO: Implement metrics tracking
             avg_latency_ms: 0.0,
         },
+        llm_circuits: state
+            .llm_manager
+            .as_ref()
+            .map(|llm_manager| llm_manager.circuits())
+            .unwrap_or_default(),
     };
 
     let status_code = if storage_reachable {
@@ -84,3 +155,37 @@ pub async fn health_check_detailed(
 
     Ok((status_code, Json(health)))
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use agentreplay_storage::{FsyncLatencyReport, GroupCommitReport};
+
+    fn report(p99_ms: f64, commits: u64, avg_batch_size: f64) -> WriteTelemetryReport {
+        let telemetry = agentreplay_storage::WriteTelemetry::default();
+        let mut report = telemetry.report();
+        report.fsync = FsyncLatencyReport {
+            count: commits,
+            p99_ms,
+            ..Default::default()
+        };
+        report.group_commit = GroupCommitReport {
+            commits,
+            avg_batch_size,
+            ..Default::default()
+        };
+        report
+    }
+
+    #[test]
+    fn test_write_path_warnings() {
+        assert!(write_path_warnings("db", &report(5.0, 1000, 50.0)).is_empty());
+        // Too few commits to judge batching
+        assert!(write_path_warnings("db", &report(5.0, 10, 1.0)).is_empty());
+
+        let warnings = write_path_warnings("project 3", &report(250.0, 1000, 1.0));
+        assert_eq!(warnings.len(), 2);
+        assert!(warnings[0].starts_with("project 3: fsync p99 is 250ms"));
+        assert!(warnings[1].contains("average 1.0 writes"));
+    }
+}
@@ -0,0 +1,232 @@
+// Copyright 2025 AgentReplay (https://github.com/agentreplay)
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program. If not, see <https://www.gnu.org/licenses/>.
+
+//! Payload hydration levels for trace listings
+//!
+//! `GET /api/v1/traces?hydration=metadata|preview|full` picks how much of
+//! each span's payload the listing carries:
+//!
+//! - `metadata`: edge fields, agent name and enrichment only; no payload reads
+//! - `preview`: adds provider, model, cost, display name and truncated
+//!   input/output previews
+//! - `full`: adds the structured prompts, completions, tool calls and
+//!   hyperparameters
+//!
+//! The caller's [`Role`] caps the level it may request
+//! ([`HydrationConfig::max_level`]). Spans flagged as containing PII or
+//! secrets are further capped at `preview`, with the previews redacted,
+//! unless the caller holds `sensitive_role`.
+//!
+//! Endpoints reading one trace in depth (`/traces/:id`, `/attributes`,
+//! `/observations`, `/detailed` and `/export`) hydrate each span at the
+//! highest level the caller's role allows ([`detail_hydration`]), under the
+//! same caps. Below `full` they leave out raw attributes, prompts,
+//! completions and tool calls.
+
+use serde::{Deserialize, Serialize};
+
+use agentreplay_core::AgentFlowEdge;
+
+use crate::api::query::ApiError;
+use crate::auth::Role;
+use crate::config::HydrationConfig;
+
+/// Replaces previews of sensitive spans the caller may not read
+pub const REDACTED_PREVIEW: &str = "[REDACTED]";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum HydrationLevel {
+    Metadata,
+    Preview,
+    Full,
+}
+
+impl HydrationLevel {
+    pub fn parse(s: &str) -> Option<Self> {
+        match s.to_ascii_lowercase().as_str() {
+            "metadata" => Some(HydrationLevel::Metadata),
+            "preview" => Some(HydrationLevel::Preview),
+            "full" => Some(HydrationLevel::Full),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            HydrationLevel::Metadata => "metadata",
+            HydrationLevel::Preview => "preview",
+            HydrationLevel::Full => "full",
+        }
+    }
+}
+
+/// How one span of a listing is hydrated
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SpanHydration {
+    pub level: HydrationLevel,
+    /// Replace input/output previews with [`REDACTED_PREVIEW`]
+    pub redact_previews: bool,
+}
+
+/// Level for a listing request
+///
+/// Without an explicit level the configured default applies, lowered to the
+/// role's limit. Explicitly asking for more than the limit is rejected.
+pub fn resolve_level(
+    config: &HydrationConfig,
+    role: Role,
+    requested: Option<HydrationLevel>,
+) -> Result<HydrationLevel, ApiError> {
+    let max = config.max_level(role);
+    match requested {
+        Some(level) if level > max => Err(ApiError::Forbidden(format!(
+            "role '{}' may hydrate trace listings up to '{}', requested '{}'",
+            role.as_str(),
+            max.as_str(),
+            level.as_str()
+        ))),
+        Some(level) => Ok(level),
+        None => Ok(config.default_level.min(max)),
+    }
+}
+
+/// Hydration of `edge` in a listing resolved at `level`
+pub fn span_hydration(
+    config: &HydrationConfig,
+    role: Role,
+    level: HydrationLevel,
+    edge: &AgentFlowEdge,
+) -> SpanHydration {
+    let sensitive = edge.has_pii() || edge.has_secrets();
+    if !sensitive || role >= config.sensitive_role {
+        return SpanHydration {
+            level,
+            redact_previews: false,
+        };
+    }
+    SpanHydration {
+        level: level.min(HydrationLevel::Preview),
+        redact_previews: level >= HydrationLevel::Preview,
+    }
+}
+
+/// Hydration of `edge` read on its own rather than in a listing
+pub fn detail_hydration(
+    config: &HydrationConfig,
+    role: Role,
+    edge: &AgentFlowEdge,
+) -> SpanHydration {
+    span_hydration(config, role, config.max_level(role), edge)
+}
+
+/// Replace a preview with [`REDACTED_PREVIEW`] if `hydration` asks for it
+pub fn redact_preview(preview: Option<String>, hydration: SpanHydration) -> Option<String> {
+    match preview {
+        Some(_) if hydration.redact_previews => Some(REDACTED_PREVIEW.to_string()),
+        preview => preview,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use agentreplay_core::SpanType;
+
+    #[test]
+    fn test_resolve_level_enforces_role_limits() {
+        let config = HydrationConfig::default();
+
+        assert_eq!(
+            resolve_level(&config, Role::Viewer, None).unwrap(),
+            HydrationLevel::Preview
+        );
+        assert_eq!(
+            resolve_level(&config, Role::Member, None).unwrap(),
+            HydrationLevel::Full
+        );
+        assert_eq!(
+            resolve_level(&config, Role::Viewer, Some(HydrationLevel::Metadata)).unwrap(),
+            HydrationLevel::Metadata
+        );
+        assert!(matches!(
+            resolve_level(&config, Role::Viewer, Some(HydrationLevel::Full)),
+            Err(ApiError::Forbidden(_))
+        ));
+    }
+
+    #[test]
+    fn test_sensitive_spans_need_elevated_role() {
+        let config = HydrationConfig::default();
+        let mut edge = AgentFlowEdge::new(1, 0, 1, 1, SpanType::Root, 0);
+        assert_eq!(
+            span_hydration(&config, Role::Member, HydrationLevel::Full, &edge),
+            SpanHydration {
+                level: HydrationLevel::Full,
+                redact_previews: false
+            }
+        );
+
+        edge.mark_pii();
+        assert_eq!(
+            span_hydration(&config, Role::Member, HydrationLevel::Full, &edge),
+            SpanHydration {
+                level: HydrationLevel::Preview,
+                redact_previews: true
+            }
+        );
+        assert_eq!(
+            span_hydration(&config, Role::Member, HydrationLevel::Metadata, &edge),
+            SpanHydration {
+                level: HydrationLevel::Metadata,
+                redact_previews: false
+            }
+        );
+        assert_eq!(
+            span_hydration(&config, Role::Admin, HydrationLevel::Full, &edge).level,
+            HydrationLevel::Full
+        );
+    }
+
+    #[test]
+    fn test_detail_hydration_uses_role_limit() {
+        let config = HydrationConfig::default();
+        let mut edge = AgentFlowEdge::new(1, 0, 1, 1, SpanType::Root, 0);
+        assert_eq!(
+            detail_hydration(&config, Role::Viewer, &edge).level,
+            HydrationLevel::Preview
+        );
+        assert_eq!(
+            detail_hydration(&config, Role::Member, &edge).level,
+            HydrationLevel::Full
+        );
+
+        edge.mark_secret();
+        let member = detail_hydration(&config, Role::Member, &edge);
+        assert_eq!(member.level, HydrationLevel::Preview);
+        assert_eq!(
+            redact_preview(Some("api_key=sk-123".to_string()), member).as_deref(),
+            Some(REDACTED_PREVIEW)
+        );
+        assert_eq!(redact_preview(None, member), None);
+
+        let admin = detail_hydration(&config, Role::Admin, &edge);
+        assert_eq!(admin.level, HydrationLevel::Full);
+        assert_eq!(
+            redact_preview(Some("hi".to_string()), admin).as_deref(),
+            Some("hi")
+        );
+    }
+}
@@ -0,0 +1,1015 @@
+// Copyright 2025 AgentReplay (https://github.com/agentreplay)
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program. If not, see <https://www.gnu.org/licenses/>.
+
+//! Bulk trace import
+//!
+//! `POST /api/v1/import` takes an NDJSON file, or a ZIP of NDJSON files, and
+//! ingests it in the background instead of in one request:
+//!
+//! - The upload is streamed to disk under `<data_dir>/imports` and the call
+//!   returns `202 Accepted`. The import runs as a background job
+//!   ([`crate::jobs`]): `GET /api/v1/jobs/:id` reports progress and
+//!   `DELETE /api/v1/jobs/:id` cancels it, while `GET /api/v1/import/:id`
+//!   lists the record errors.
+//! - Each line holds one span as accepted by `POST /api/v1/traces`, or a
+//!   whole `{"spans": [...]}` batch. Lines that fail to parse or validate are
+//!   reported per record and skipped; the rest goes through the normal
+//!   ingestion pipeline in batches.
+//! - Progress is checkpointed after every batch. Jobs interrupted by a
+//!   restart resume where they stopped, and failed jobs can be resumed with
+//!   `POST /api/v1/import/:id/resume`. Spans already stored are skipped by the
+//!   span_id dedup window, so replaying a partial batch is harmless.
+//! - Uploads are refused with 413 past [`MAX_IMPORT_BYTES`], when a line is
+//!   longer than [`MAX_LINE_BYTES`] or when a ZIP expands past
+//!   [`MAX_EXTRACTED_BYTES`]. ZIP entry sizes are only claims, so the job
+//!   also stops reading at these limits.
+
+use agentreplay_core::clock::now_us;
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::{BufRead, BufReader, Read};
+use std::path::{Path as FsPath, PathBuf};
+use std::sync::Arc;
+use std::time::Duration;
+
+use axum::{
+    body::Body,
+    extract::{Path, Query, State},
+    http::{header, HeaderMap, StatusCode},
+    Extension, Json,
+};
+use futures::StreamExt;
+use parking_lot::RwLock;
+use serde::{Deserialize, Serialize};
+use tokio::io::AsyncWriteExt;
+use tokio::sync::mpsc;
+use tracing::{info, warn};
+
+use super::ingest::{ingest_spans, validate_span, AgentreplaySpan};
+use super::{ApiError, AppState};
+use crate::auth::{AuthContext, Role};
+use crate::jobs::{JobContext, JobResult, JobState};
+
+/// Import uploads and job checkpoints, under the data directory
+pub const IMPORTS_DIR: &str = "imports";
+
+/// Job kind of imports in `GET /api/v1/jobs`
+pub const IMPORT_JOB_KIND: &str = "import";
+
+/// Largest accepted upload
+pub const MAX_IMPORT_BYTES: u64 = 4 << 30;
+
+/// Longest line of an import, holding one span or batch
+pub const MAX_LINE_BYTES: usize = 16 << 20;
+
+/// Most bytes an import may hold once decompressed
+pub const MAX_EXTRACTED_BYTES: u64 = 16 << 30;
+
+const BATCH_SPANS: usize = 500;
+/// Per-record errors kept on a job; later ones are only counted
+const MAX_RECORD_ERRORS: usize = 1000;
+const OVERLOAD_BACKOFF: Duration = Duration::from_secs(1);
+const MAX_OVERLOAD_RETRIES: u32 = 300;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ImportFormat {
+    Ndjson,
+    Zip,
+}
+
+/// A record that could not be imported
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ImportRecordError {
+    /// 1-based record (line) number across the whole upload
+    pub record: u64,
+    /// Archive entry, for ZIP uploads
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub file: Option<String>,
+    pub error: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ImportJob {
+    pub id: String,
+    pub tenant_id: u64,
+    pub format: ImportFormat,
+    #[serde(default)]
+    pub filename: Option<String>,
+    pub state: JobState,
+    pub created_at_us: u64,
+    #[serde(default)]
+    pub started_at_us: Option<u64>,
+    #[serde(default)]
+    pub finished_at_us: Option<u64>,
+    /// Uncompressed input size
+    pub total_bytes: u64,
+    /// Input consumed as of the last checkpoint
+    pub bytes_read: u64,
+    /// Fraction of the input processed, 0 to 1
+    pub progress: f64,
+    /// Records consumed as of the last checkpoint; a resumed job skips these
+    pub records_read: u64,
+    pub spans_accepted: u64,
+    pub spans_rejected: u64,
+    #[serde(default)]
+    pub spans_duplicate: u64,
+    #[serde(default)]
+    pub spans_deduplicated: u64,
+    /// All record errors, including those beyond `errors`
+    pub error_count: u64,
+    #[serde(default)]
+    pub errors: Vec<ImportRecordError>,
+    /// Why the job stopped, when it failed
+    #[serde(default)]
+    pub error: Option<String>,
+}
+
+impl ImportJob {
+    fn record_errors(&mut self, errors: Vec<ImportRecordError>) {
+        self.error_count += errors.len() as u64;
+        let room = MAX_RECORD_ERRORS.saturating_sub(self.errors.len());
+        self.errors.extend(errors.into_iter().take(room));
+    }
+
+    /// Counters shown with the job in `GET /api/v1/jobs`
+    fn summary(&self) -> serde_json::Value {
+        serde_json::json!({
+            "records_read": self.records_read,
+            "spans_accepted": self.spans_accepted,
+            "spans_rejected": self.spans_rejected,
+            "spans_duplicate": self.spans_duplicate,
+            "error_count": self.error_count,
+        })
+    }
+}
+
+fn load_job(path: &FsPath) -> Result<ImportJob, String> {
+    let bytes = std::fs::read(path).map_err(|e| e.to_string())?;
+    serde_json::from_slice(&bytes).map_err(|e| e.to_string())
+}
+
+/// Import checkpoints, one JSON file per import next to its upload
+///
+/// Imports run on the [`crate::jobs::JobManager`] under the same id, which
+/// handles queueing and cancellation; the checkpoint holds what is needed to
+/// resume and the per-record errors.
+pub struct ImportJobs {
+    dir: Option<PathBuf>,
+    jobs: RwLock<HashMap<String, ImportJob>>,
+}
+
+impl Default for ImportJobs {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl ImportJobs {
+    /// Without a directory; uploads are refused
+    pub fn new() -> Self {
+        Self {
+            dir: None,
+            jobs: RwLock::new(HashMap::new()),
+        }
+    }
+
+    /// Imports kept in `dir`, loading the checkpoints already there
+    pub fn with_storage(dir: impl AsRef<FsPath>) -> Self {
+        let dir = dir.as_ref().to_path_buf();
+        let mut jobs = HashMap::new();
+        match std::fs::read_dir(&dir) {
+            Ok(entries) => {
+                for path in entries.flatten().map(|entry| entry.path()) {
+                    if path.extension().and_then(|e| e.to_str()) != Some("json") {
+                        continue;
+                    }
+                    match load_job(&path) {
+                        Ok(job) => {
+                            jobs.insert(job.id.clone(), job);
+                        }
+                        Err(e) => warn!("Skipping import job {:?}: {}", path, e),
+                    }
+                }
+            }
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => {}
+            Err(e) => warn!("Failed to read {:?}: {}", dir, e),
+        }
+
+        Self {
+            dir: Some(dir),
+            jobs: RwLock::new(jobs),
+        }
+    }
+
+    pub fn get(&self, tenant_id: u64, id: &str) -> Option<ImportJob> {
+        self.jobs
+            .read()
+            .get(id)
+            .filter(|job| job.tenant_id == tenant_id)
+            .cloned()
+    }
+
+    /// Restart imports that were queued or running when the server stopped
+    pub fn resume_interrupted(self: &Arc<Self>, state: &AppState) {
+        let pending: Vec<ImportJob> = self
+            .jobs
+            .read()
+            .values()
+            .filter(|job| !job.state.is_finished())
+            .cloned()
+            .collect();
+        for job in pending {
+            info!("Resuming interrupted import job {}", job.id);
+            self.start(state, &job);
+        }
+    }
+
+    fn upload_path(&self, id: &str) -> Option<PathBuf> {
+        self.dir
+            .as_ref()
+            .map(|dir| dir.join(format!("{}.upload", id)))
+    }
+
+    fn insert(&self, job: ImportJob) {
+        let id = job.id.clone();
+        self.jobs.write().insert(id.clone(), job);
+        self.persist(&id);
+    }
+
+    fn update<T>(&self, id: &str, f: impl FnOnce(&mut ImportJob) -> T) -> Option<T> {
+        let result = self.jobs.write().get_mut(id).map(f);
+        self.persist(id);
+        result
+    }
+
+    fn persist(&self, id: &str) {
+        let Some(dir) = &self.dir else {
+            return;
+        };
+        let Some(job) = self.jobs.read().get(id).cloned() else {
+            return;
+        };
+        let path = dir.join(format!("{}.json", id));
+        let tmp = dir.join(format!("{}.json.tmp", id));
+        let result = serde_json::to_vec_pretty(&job)
+            .map_err(std::io::Error::from)
+            .and_then(|bytes| std::fs::write(&tmp, bytes))
+            .and_then(|_| std::fs::rename(&tmp, &path));
+        if let Err(e) = result {
+            warn!("Failed to checkpoint import job {}: {}", id, e);
+        }
+    }
+
+    fn start(self: &Arc<Self>, state: &AppState, job: &ImportJob) {
+        let imports = Arc::clone(self);
+        let task_state = state.clone();
+        let id = job.id.clone();
+        let description = format!("Import {}", job.filename.as_deref().unwrap_or("upload"));
+        state.jobs.submit_as(
+            job.id.clone(),
+            IMPORT_JOB_KIND,
+            job.tenant_id,
+            description,
+            move |ctx| imports.run(task_state, id, ctx),
+        );
+    }
+
+    async fn run(self: Arc<Self>, state: AppState, id: String, ctx: JobContext) -> JobResult {
+        let path = self
+            .upload_path(&id)
+            .ok_or_else(|| "imports need a data directory".to_string())?;
+        let (format, skip, tenant_id) = self
+            .update(&id, |job| {
+                job.state = JobState::Running;
+                job.started_at_us.get_or_insert_with(now_us);
+                job.error = None;
+                (job.format, job.records_read, job.tenant_id)
+            })
+            .ok_or_else(|| format!("import checkpoint {} is missing", id))?;
+
+        let (tx, mut rx) = mpsc::channel::<Batch>(2);
+        let reader = tokio::task::spawn_blocking(move || read_batches(&path, format, skip, tx));
+
+        let mut failure = None;
+        while let Some(batch) = rx.recv().await {
+            if ctx.is_cancelled() {
+                break;
+            }
+            let outcome = if batch.spans.is_empty() {
+                Ok(None)
+            } else {
+                ingest_batch(&state, tenant_id, &batch).await.map(Some)
+            };
+            let progress = self.update(&id, |job| {
+                job.record_errors(batch.errors);
+                job.spans_rejected += batch.invalid;
+                if let Ok(ref response) = outcome {
+                    if let Some(response) = response {
+                        job.spans_accepted += response.accepted as u64;
+                        job.spans_rejected += response.rejected as u64;
+                        job.spans_duplicate += response.duplicates.unwrap_or(0) as u64;
+                        job.spans_deduplicated += response.deduplicated.unwrap_or(0) as u64;
+                        if !response.errors.is_empty() {
+                            let first = batch.first_record;
+                            job.record_errors(
+                                response
+                                    .errors
+                                    .iter()
+                                    .map(|error| ImportRecordError {
+                                        record: first,
+                                        file: None,
+                                        error: format!("in batch from record {}: {}", first, error),
+                                    })
+                                    .collect(),
+                            );
+                        }
+                    }
+                    job.records_read = batch.records_through;
+                    job.bytes_read = batch.bytes_through;
+                    job.progress = if job.total_bytes == 0 {
+                        1.0
+                    } else {
+                        (job.bytes_read as f64 / job.total_bytes as f64).min(1.0)
+                    };
+                }
+                (job.progress, job.summary())
+            });
+            if let Some((progress, summary)) = progress {
+                ctx.progress(progress, None);
+                ctx.detail(summary);
+            }
+            if let Err(e) = outcome {
+                failure = Some(e.to_string());
+                break;
+            }
+        }
+        drop(rx);
+
+        let read_result = reader
+            .await
+            .map_err(|e| format!("import reader panicked: {}", e))
+            .and_then(|result| result);
+        let error = failure.or(read_result.err());
+        let outcome = match (ctx.is_cancelled(), &error) {
+            (true, _) => JobState::Cancelled,
+            (false, Some(_)) => JobState::Failed,
+            (false, None) => JobState::Completed,
+        };
+        let summary = self.update(&id, |job| {
+            job.state = outcome;
+            job.finished_at_us = Some(now_us());
+            job.error = error.clone();
+            if outcome == JobState::Completed {
+                job.progress = 1.0;
+            }
+            job.summary()
+        });
+        if matches!(outcome, JobState::Completed | JobState::Cancelled) {
+            if let Some(path) = self.upload_path(&id) {
+                let _ = std::fs::remove_file(path);
+            }
+        }
+        match (outcome, error) {
+            (JobState::Completed, _) => Ok(summary),
+            (_, Some(error)) => Err(error),
+            _ => Err("import cancelled".to_string()),
+        }
+    }
+}
+
+/// Ingest one batch, waiting out ingestion backpressure
+async fn ingest_batch(
+    state: &AppState,
+    tenant_id: u64,
+    batch: &Batch,
+) -> Result<super::ingest::IngestResponse, ApiError> {
+    let mut retries = 0;
+    loop {
+        match ingest_spans(state, tenant_id, batch.spans.clone(), None).await {
+            Err(ApiError::Overloaded(_)) if retries < MAX_OVERLOAD_RETRIES => {
+                retries += 1;
+                tokio::time::sleep(OVERLOAD_BACKOFF).await;
+            }
+            result => return result.map(|(_, response)| response),
+        }
+    }
+}
+
+/// Spans read from the upload, with the checkpoint reached after them
+struct Batch {
+    spans: Vec<AgentreplaySpan>,
+    first_record: u64,
+    records_through: u64,
+    bytes_through: u64,
+    /// Spans dropped before ingestion, also listed in `errors`
+    invalid: u64,
+    errors: Vec<ImportRecordError>,
+}
+
+/// Splits records into batches, skipping those before the checkpoint
+struct BatchBuilder {
+    tx: mpsc::Sender<Batch>,
+    skip: u64,
+    record: u64,
+    bytes: u64,
+    batch: Batch,
+}
+
+impl BatchBuilder {
+    fn new(tx: mpsc::Sender<Batch>, skip: u64) -> Self {
+        Self {
+            tx,
+            skip,
+            record: 0,
+            bytes: 0,
+            batch: empty_batch(skip + 1),
+        }
+    }
+
+    /// Add one line; false once the job stopped listening
+    fn line(&mut self, line: &str, file: Option<&str>) -> bool {
+        self.record += 1;
+        self.bytes += line.len() as u64;
+        if self.record <= self.skip {
+            return true;
+        }
+
+        let record = self.record;
+        let trimmed = line.trim();
+        if !trimmed.is_empty() {
+            match parse_line(trimmed) {
+                Ok(spans) => {
+                    for span in spans {
+                        match validate_span(0, &span) {
+                            Ok(()) => self.batch.spans.push(span),
+                            Err(e) => {
+                                self.batch.invalid += 1;
+                                self.error(record, file, e.trim_start_matches("Span 0: "));
+                            }
+                        }
+                    }
+                }
+                Err(e) => self.error(record, file, &e),
+            }
+        }
+        self.batch.records_through = record;
+        self.batch.bytes_through = self.bytes;
+
+        if self.batch.spans.len() >= BATCH_SPANS {
+            return self.flush();
+        }
+        true
+    }
+
+    fn error(&mut self, record: u64, file: Option<&str>, error: &str) {
+        self.batch.errors.push(ImportRecordError {
+            record,
+            file: file.map(String::from),
+            error: error.to_string(),
+        });
+    }
+
+    fn flush(&mut self) -> bool {
+        let batch = std::mem::replace(&mut self.batch, empty_batch(self.record + 1));
+        if batch.records_through == 0 {
+            return true;
+        }
+        self.tx.blocking_send(batch).is_ok()
+    }
+}
+
+fn empty_batch(first_record: u64) -> Batch {
+    Batch {
+        spans: Vec::new(),
+        first_record,
+        records_through: 0,
+        bytes_through: 0,
+        invalid: 0,
+        errors: Vec::new(),
+    }
+}
+
+/// One span, or a `{"spans": [...]}` batch as sent to `POST /api/v1/traces`
+fn parse_line(line: &str) -> Result<Vec<AgentreplaySpan>, String> {
+    let value: serde_json::Value =
+        serde_json::from_str(line).map_err(|e| format!("invalid JSON: {}", e))?;
+    match value.get("spans") {
+        Some(spans) => serde_json::from_value(spans.clone()),
+        None => serde_json::from_value(value).map(|span| vec![span]),
+    }
+    .map_err(|e| format!("invalid span: {}", e))
+}
+
+fn is_ndjson_entry(name: &str) -> bool {
+    let name = name.to_ascii_lowercase();
+    !name.starts_with("__macosx/")
+        && (name.ends_with(".ndjson") || name.ends_with(".jsonl") || name.ends_with(".json"))
+}
+
+/// NDJSON entries of an archive in name order, with their sizes
+fn zip_entries(archive: &mut zip::ZipArchive<File>) -> Result<Vec<(usize, String, u64)>, String> {
+    let mut entries = Vec::new();
+    for index in 0..archive.len() {
+        let entry = archive.by_index(index).map_err(|e| e.to_string())?;
+        if entry.is_file() && is_ndjson_entry(entry.name()) {
+            entries.push((index, entry.name().to_string(), entry.size()));
+        }
+    }
+    entries.sort_by(|a, b| a.1.cmp(&b.1));
+    Ok(entries)
+}
+
+/// Uncompressed size of an upload, checking that it can be read and stays
+/// within the import limits
+fn input_size(path: &FsPath, format: ImportFormat) -> Result<u64, ApiError> {
+    match format {
+        ImportFormat::Ndjson => {
+            let file = File::open(path).map_err(|e| ApiError::Internal(e.to_string()))?;
+            let mut reader = BufReader::new(file);
+            let mut line = Vec::new();
+            let mut size = 0;
+            let mut record = 0;
+            loop {
+                record += 1;
+                let read = read_line(&mut reader, &mut line).map_err(|e| match e {
+                    LineError::TooLong => too_long(record, None),
+                    LineError::Io(e) => ApiError::BadRequest(format!("unreadable upload: {}", e)),
+                })?;
+                if read == 0 {
+                    return Ok(size);
+                }
+                size += read as u64;
+            }
+        }
+        ImportFormat::Zip => {
+            let file = File::open(path).map_err(|e| ApiError::Internal(e.to_string()))?;
+            let mut archive = zip::ZipArchive::new(file)
+                .map_err(|e| ApiError::BadRequest(format!("invalid ZIP archive: {}", e)))?;
+            let entries = zip_entries(&mut archive).map_err(ApiError::BadRequest)?;
+            if entries.is_empty() {
+                return Err(ApiError::BadRequest(
+                    "ZIP archive contains no .ndjson, .jsonl or .json files".to_string(),
+                ));
+            }
+            let size = entries.iter().map(|(_, _, size)| size).sum();
+            if size > MAX_EXTRACTED_BYTES {
+                return Err(ApiError::PayloadTooLarge(format!(
+                    "ZIP archive expands to {} bytes, more than {}",
+                    size, MAX_EXTRACTED_BYTES
+                )));
+            }
+            Ok(size)
+        }
+    }
+}
+
+fn too_long(record: u64, file: Option<&str>) -> ApiError {
+    let place = match file {
+        Some(file) => format!("record {} of {}", record, file),
+        None => format!("record {}", record),
+    };
+    ApiError::PayloadTooLarge(format!("{} is longer than {} bytes", place, MAX_LINE_BYTES))
+}
+
+enum LineError {
+    TooLong,
+    Io(std::io::Error),
+}
+
+/// Read one line into `line`, reading no more than [`MAX_LINE_BYTES`] of
+/// it; 0 at the end of the input
+fn read_line(reader: &mut impl BufRead, line: &mut Vec<u8>) -> Result<usize, LineError> {
+    line.clear();
+    let read = reader
+        .take(MAX_LINE_BYTES as u64 + 1)
+        .read_until(b'\n', line)
+        .map_err(LineError::Io)?;
+    if read > MAX_LINE_BYTES && line.last() != Some(&b'\n') {
+        return Err(LineError::TooLong);
+    }
+    Ok(read)
+}
+
+fn read_lines(
+    reader: impl Read,
+    file: Option<&str>,
+    builder: &mut BatchBuilder,
+) -> Result<bool, String> {
+    let read_error = |e: &dyn std::fmt::Display| match file {
+        Some(file) => format!("failed to read {}: {}", file, e),
+        None => format!("failed to read upload: {}", e),
+    };
+    let mut reader = BufReader::new(reader);
+    let mut line = Vec::new();
+    loop {
+        let read = read_line(&mut reader, &mut line).map_err(|e| match e {
+            LineError::TooLong => too_long(builder.record + 1, file).to_string(),
+            LineError::Io(e) => read_error(&e),
+        })?;
+        if read == 0 {
+            return Ok(true);
+        }
+        if builder.bytes + read as u64 > MAX_EXTRACTED_BYTES {
+            return Err(format!(
+                "import expands to more than {} bytes",
+                MAX_EXTRACTED_BYTES
+            ));
+        }
+        let line = std::str::from_utf8(&line).map_err(|e| read_error(&e))?;
+        if !builder.line(line, file) {
+            return Ok(false);
+        }
+    }
+}
+
+/// Read the upload from record `skip + 1` on, sending batches to `tx`
+fn read_batches(
+    path: &FsPath,
+    format: ImportFormat,
+    skip: u64,
+    tx: mpsc::Sender<Batch>,
+) -> Result<(), String> {
+    let file = File::open(path).map_err(|e| format!("upload is no longer available: {}", e))?;
+    let mut builder = BatchBuilder::new(tx, skip);
+    match format {
+        ImportFormat::Ndjson => {
+            if !read_lines(file, None, &mut builder)? {
+                return Ok(());
+            }
+        }
+        ImportFormat::Zip => {
+            let mut archive = zip::ZipArchive::new(file).map_err(|e| e.to_string())?;
+            for (index, name, _) in zip_entries(&mut archive)? {
+                let entry = archive.by_index(index).map_err(|e| e.to_string())?;
+                if !read_lines(entry, Some(&name), &mut builder)? {
+                    return Ok(());
+                }
+            }
+        }
+    }
+    builder.flush();
+    Ok(())
+}
+
+#[derive(Debug, Default, Deserialize)]
+pub struct ImportParams {
+    /// Detected from Content-Type or the file contents when omitted
+    #[serde(default)]
+    pub format: Option<ImportFormat>,
+    #[serde(default)]
+    pub filename: Option<String>,
+}
+
+pub(super) fn require_member(auth: &AuthContext) -> Result<(), ApiError> {
+    if auth.role >= Role::Member {
+        Ok(())
+    } else {
+        Err(ApiError::Forbidden(format!(
+            "role '{}' may not import traces",
+            auth.role.as_str()
+        )))
+    }
+}
+
+/// POST /api/v1/import
+///
+/// The body is the raw NDJSON or ZIP file.
+pub async fn start_import(
+    State(state): State<AppState>,
+    Extension(auth): Extension<AuthContext>,
+    Query(params): Query<ImportParams>,
+    headers: HeaderMap,
+    body: Body,
+) -> Result<(StatusCode, Json<ImportJob>), ApiError> {
+    require_member(&auth)?;
+    let jobs = &state.import_jobs;
+    let id = hex::encode(rand::random::<[u8; 8]>());
+    let (Some(dir), Some(path)) = (jobs.dir.clone(), jobs.upload_path(&id)) else {
+        return Err(ApiError::BadRequest(
+            "imports need a data directory".to_string(),
+        ));
+    };
+    tokio::fs::create_dir_all(&dir)
+        .await
+        .map_err(|e| ApiError::Internal(format!("Failed to create {:?}: {}", dir, e)))?;
+
+    let written = write_upload(&path, body).await;
+    let written = match written {
+        Ok(written) => written,
+        Err(e) => {
+            let _ = tokio::fs::remove_file(&path).await;
+            return Err(e);
+        }
+    };
+
+    let content_type = headers
+        .get(header::CONTENT_TYPE)
+        .and_then(|v| v.to_str().ok())
+        .unwrap_or_default();
+    let format = match params.format {
+        Some(format) => format,
+        None if content_type.contains("zip") || written.zip_magic => ImportFormat::Zip,
+        None => ImportFormat::Ndjson,
+    };
+    let scan_path = path.clone();
+    let total_bytes = tokio::task::spawn_blocking(move || input_size(&scan_path, format))
+        .await
+        .map_err(|e| ApiError::Internal(format!("Import scan panicked: {}", e)))?;
+    let total_bytes = match total_bytes {
+        Ok(total_bytes) => total_bytes,
+        Err(e) => {
+            let _ = tokio::fs::remove_file(&path).await;
+            return Err(e);
+        }
+    };
+
+    let job = ImportJob {
+        id,
+        tenant_id: auth.tenant_id,
+        format,
+        filename: params.filename,
+        state: JobState::Queued,
+        created_at_us: now_us(),
+        started_at_us: None,
+        finished_at_us: None,
+        total_bytes,
+        bytes_read: 0,
+        progress: 0.0,
+        records_read: 0,
+        spans_accepted: 0,
+        spans_rejected: 0,
+        spans_duplicate: 0,
+        spans_deduplicated: 0,
+        error_count: 0,
+        errors: Vec::new(),
+        error: None,
+    };
+    jobs.insert(job.clone());
+    jobs.start(&state, &job);
+    Ok((StatusCode::ACCEPTED, Json(job)))
+}
+
+pub(super) struct Upload {
+    pub(super) zip_magic: bool,
+}
+
+pub(super) async fn write_upload(path: &FsPath, body: Body) -> Result<Upload, ApiError> {
+    let mut file = tokio::fs::File::create(path)
+        .await
+        .map_err(|e| ApiError::Internal(format!("Failed to store upload: {}", e)))?;
+    let mut stream = body.into_data_stream();
+    let mut written: u64 = 0;
+    let mut head: Vec<u8> = Vec::with_capacity(4);
+    while let Some(chunk) = stream.next().await {
+        let chunk = chunk.map_err(|e| ApiError::BadRequest(format!("Upload failed: {}", e)))?;
+        written += chunk.len() as u64;
+        if written > MAX_IMPORT_BYTES {
+            return Err(ApiError::PayloadTooLarge(format!(
+                "upload exceeds {} bytes",
+                MAX_IMPORT_BYTES
+            )));
+        }
+        if head.len() < 4 {
+            head.extend(chunk.iter().take(4 - head.len()));
+        }
+        file.write_all(&chunk)
+            .await
+            .map_err(|e| ApiError::Internal(format!("Failed to store upload: {}", e)))?;
+    }
+    file.flush()
+        .await
+        .map_err(|e| ApiError::Internal(format!("Failed to store upload: {}", e)))?;
+    if written == 0 {
+        return Err(ApiError::BadRequest("upload is empty".to_string()));
+    }
+    Ok(Upload {
+        zip_magic: head == b"PK\x03\x04",
+    })
+}
+
+/// GET /api/v1/import/:id
+///
+/// The import checkpoint with its record errors; queue state and
+/// cancellation are under `/api/v1/jobs/:id`.
+pub async fn get_import(
+    State(state): State<AppState>,
+    Extension(auth): Extension<AuthContext>,
+    Path(id): Path<String>,
+) -> Result<Json<ImportJob>, ApiError> {
+    state
+        .import_jobs
+        .get(auth.tenant_id, &id)
+        .map(Json)
+        .ok_or_else(|| ApiError::NotFound(format!("Import '{}' not found", id)))
+}
+
+/// POST /api/v1/import/:id/resume
+///
+/// Continues a failed import from its last checkpoint.
+pub async fn resume_import(
+    State(state): State<AppState>,
+    Extension(auth): Extension<AuthContext>,
+    Path(id): Path<String>,
+) -> Result<(StatusCode, Json<ImportJob>), ApiError> {
+    require_member(&auth)?;
+    let jobs = &state.import_jobs;
+    let job = jobs
+        .get(auth.tenant_id, &id)
+        .ok_or_else(|| ApiError::NotFound(format!("Import '{}' not found", id)))?;
+    if job.state != JobState::Failed {
+        return Err(ApiError::BadRequest(format!(
+            "only failed imports can be resumed, import '{}' is {:?}",
+            id, job.state
+        )));
+    }
+    if !jobs.upload_path(&id).is_some_and(|path| path.exists()) {
+        return Err(ApiError::BadRequest(format!(
+            "the upload of import '{}' is no longer available",
+            id
+        )));
+    }
+    let job = jobs
+        .update(&id, |job| {
+            job.state = JobState::Queued;
+            job.finished_at_us = None;
+            job.clone()
+        })
+        .ok_or_else(|| ApiError::NotFound(format!("Import '{}' not found", id)))?;
+    jobs.start(&state, &job);
+    Ok((StatusCode::ACCEPTED, Json(job)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn span_line(id: u32) -> String {
+        serde_json::json!({
+            "span_id": format!("0x{:x}", id),
+            "trace_id": "0x1",
+            "parent_span_id": null,
+            "name": "step",
+            "start_time": 1_700_000_000_000_000u64 + id as u64,
+            "end_time": 1_700_000_000_000_100u64 + id as u64,
+            "attributes": {},
+        })
+        .to_string()
+    }
+
+    fn collect(path: &FsPath, format: ImportFormat, skip: u64) -> Vec<Batch> {
+        let (tx, mut rx) = mpsc::channel(1024);
+        read_batches(path, format, skip, tx).unwrap();
+        let mut batches = Vec::new();
+        while let Ok(batch) = rx.try_recv() {
+            batches.push(batch);
+        }
+        batches
+    }
+
+    #[test]
+    fn test_ndjson_batches_and_record_errors() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("spans.ndjson");
+        let mut lines: Vec<String> = (1..=BATCH_SPANS as u32 + 10).map(span_line).collect();
+        lines.insert(2, "{not json".to_string());
+        lines.insert(5, String::new());
+        std::fs::write(&path, lines.join("\n")).unwrap();
+
+        let batches = collect(&path, ImportFormat::Ndjson, 0);
+        assert_eq!(batches.len(), 2);
+        assert_eq!(batches[0].spans.len(), BATCH_SPANS);
+        assert_eq!(batches[0].errors.len(), 1);
+        assert_eq!(batches[0].errors[0].record, 3);
+        assert_eq!(batches[1].spans.len(), 10);
+        assert_eq!(batches[1].first_record, batches[0].records_through + 1);
+        assert_eq!(batches[1].records_through, lines.len() as u64);
+        assert_eq!(
+            batches[1].bytes_through,
+            input_size(&path, ImportFormat::Ndjson).unwrap()
+        );
+
+        // Resuming skips the records already checkpointed
+        let resumed = collect(&path, ImportFormat::Ndjson, batches[0].records_through);
+        assert_eq!(resumed.len(), 1);
+        assert_eq!(resumed[0].spans.len(), 10);
+    }
+
+    #[test]
+    fn test_zip_and_batch_lines() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("export.zip");
+        let mut zip = zip::ZipWriter::new(File::create(&path).unwrap());
+        let options = zip::write::SimpleFileOptions::default();
+        zip.start_file("b.ndjson", options).unwrap();
+        std::io::Write::write_all(&mut zip, span_line(2).as_bytes()).unwrap();
+        zip.start_file("a.jsonl", options).unwrap();
+        let batch_line = serde_json::json!({
+            "spans": [
+                serde_json::from_str::<serde_json::Value>(&span_line(1)).unwrap(),
+                {"span_id": "zz", "trace_id": "0x1", "name": "bad", "start_time": 1, "attributes": {}},
+            ]
+        });
+        std::io::Write::write_all(&mut zip, batch_line.to_string().as_bytes()).unwrap();
+        zip.start_file("README.txt", options).unwrap();
+        std::io::Write::write_all(&mut zip, b"ignored").unwrap();
+        zip.finish().unwrap();
+
+        assert!(input_size(&path, ImportFormat::Zip).unwrap() > 0);
+        let batches = collect(&path, ImportFormat::Zip, 0);
+        assert_eq!(batches.len(), 1);
+        let ids: Vec<&str> = batches[0]
+            .spans
+            .iter()
+            .map(|s| s.span_id.as_str())
+            .collect();
+        assert_eq!(ids, vec!["0x1", "0x2"]);
+        assert_eq!(batches[0].invalid, 1);
+        assert_eq!(batches[0].errors[0].file.as_deref(), Some("a.jsonl"));
+    }
+
+    #[test]
+    fn test_overlong_lines_are_refused() {
+        let dir = tempfile::tempdir().unwrap();
+        let longest = "x".repeat(MAX_LINE_BYTES);
+        let mut reader = std::io::Cursor::new(format!("{}\n{}x", longest, longest));
+        let mut line = Vec::new();
+        assert_eq!(
+            read_line(&mut reader, &mut line).ok(),
+            Some(MAX_LINE_BYTES + 1)
+        );
+        assert!(matches!(
+            read_line(&mut reader, &mut line),
+            Err(LineError::TooLong)
+        ));
+
+        // NDJSON uploads are checked before the job starts
+        let path = dir.path().join("spans.ndjson");
+        std::fs::write(&path, format!("{}\n{}x", span_line(1), longest)).unwrap();
+        assert!(matches!(
+            input_size(&path, ImportFormat::Ndjson),
+            Err(ApiError::PayloadTooLarge(_))
+        ));
+
+        // ZIP entries only when they are read
+        let path = dir.path().join("export.zip");
+        let mut zip = zip::ZipWriter::new(File::create(&path).unwrap());
+        let options = zip::write::SimpleFileOptions::default();
+        zip.start_file("a.ndjson", options).unwrap();
+        std::io::Write::write_all(&mut zip, longest.as_bytes()).unwrap();
+        std::io::Write::write_all(&mut zip, b"x").unwrap();
+        zip.finish().unwrap();
+        let (tx, _rx) = mpsc::channel(1024);
+        let error = read_batches(&path, ImportFormat::Zip, 0, tx).unwrap_err();
+        assert!(
+            error.contains("record 1 of a.ndjson is longer than"),
+            "{}",
+            error
+        );
+    }
+
+    #[test]
+    fn test_jobs_reload_from_checkpoints() {
+        let dir = tempfile::tempdir().unwrap();
+        let jobs = ImportJobs::with_storage(dir.path());
+        jobs.insert(ImportJob {
+            id: "abc".to_string(),
+            tenant_id: 3,
+            format: ImportFormat::Ndjson,
+            filename: None,
+            state: JobState::Running,
+            created_at_us: 1,
+            started_at_us: Some(1),
+            finished_at_us: None,
+            total_bytes: 100,
+            bytes_read: 40,
+            progress: 0.4,
+            records_read: 12,
+            spans_accepted: 12,
+            spans_rejected: 0,
+            spans_duplicate: 0,
+            spans_deduplicated: 0,
+            error_count: 0,
+            errors: Vec::new(),
+            error: None,
+        });
+
+        let reloaded = ImportJobs::with_storage(dir.path());
+        let job = reloaded.get(3, "abc").unwrap();
+        assert_eq!(job.records_read, 12);
+        assert!(!job.state.is_finished());
+        assert!(reloaded.get(4, "abc").is_none());
+    }
+}
@@ -20,27 +20,54 @@
 //!
 //! ## Architecture
 //!
-//! When the IngestionActor is available, traces flow through:
+//! Spans pass through the stages configured in `ingestion.pipeline` (see
+//! [`crate::ingestion::pipeline`]). With the default pipeline and the
+//! IngestionActor available, traces flow through:
 //! ```text
-//! HTTP Request → Validation → Actor Channel → Batch → Governor → Storage
+//! HTTP Request → Sanitize → Enrich → Scripts → Sample → Governor → Storage
 //! ```
 //!
 //! This provides:
 //! - **Batching**: 64 traces per batch, amortizes embedding cost
 //! - **Deduplication**: Semantic Governor drops similar traces (32x storage savings)
-//! - **Backpressure**: Channel capacity limits memory under load
-
-use axum::{extract::State, http::StatusCode, response::IntoResponse, Extension, Json};
-use agentreplay_core::{AgentFlowEdge, Environment, SpanType};
+//! - **Backpressure**: Above the queue's high watermark batches are rejected
+//!   with 429 + Retry-After, or sampled when `ingestion.overload_policy = "sample"`
+//! - **Retry safety**: spans whose span_id was already stored within the dedup
+//!   window are skipped, and requests carrying an `Idempotency-Key` header
+//!   replay the original response (see [`crate::ingestion::IdempotencyGuard`])
+//! - **Clock skew**: timestamps from hosts with skewed clocks are corrected
+//!   before conversion (see [`crate::ingestion::ClockSkewCorrector`])
+//! - **Session stitching**: spans tagged with `session.external_key` are mapped
+//!   to one stable session_id across SDK restarts (see
+//!   [`crate::session_registry::SessionRegistry`])
+//! - **Enrichment**: model family, provider, client SDK and country are derived
+//!   from raw attributes and stored as indexed fields for list filtering
+
+use axum::{
+    extract::State,
+    http::{header, HeaderMap, HeaderValue, StatusCode},
+    response::{IntoResponse, Response},
+    Extension, Json,
+};
+use agentreplay_core::clock::now_us;
+use agentreplay_core::{
+    AgentFlowEdge, Environment, SpanLink, SpanType, FLAG_ABANDONED, FLAG_IN_PROGRESS,
+};
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 use tracing::{debug, error, info, warn};
 
+use crate::admission::QueueDecision;
 use crate::api::query::{ApiError, AppState};
 use crate::auth::AuthContext;
-use crate::ingestion::{IngestionResult, TracePayload};
+use crate::ingestion::{
+    CachedResponse, IdempotencyKey, IngestionResult, PipelineStage, Replay, TracePayload,
+};
 use crate::otel_genai::GenAIPayload;
+use crate::quotas::QuotaScope;
 use crate::sanitization;
+use crate::scripting::ScriptHook;
+use crate::session_registry::EXTERNAL_SESSION_KEY_ATTR;
 use crate::validation;
 
 /// Simplified span structure for ingestion (matches agentreplay-observability)
@@ -63,6 +90,13 @@ pub struct IngestRequest {
     pub spans: Vec<AgentreplaySpan>,
 }
 
+/// Header carrying a client-chosen key that makes a POST safe to retry
+pub const IDEMPOTENCY_KEY_HEADER: &str = "idempotency-key";
+
+/// Response header naming the trace a request stored, for client correlation
+/// and `/metrics` exemplars
+pub const TRACE_HEADER: &str = "x-agentreplay-trace";
+
 /// Response for POST /api/v1/traces
 #[derive(Debug, Serialize)]
 pub struct IngestResponse {
@@ -71,6 +105,22 @@ pub struct IngestResponse {
     /// Number of traces deduplicated (similar to existing)
     #[serde(skip_serializing_if = "Option::is_none")]
     pub deduplicated: Option<usize>,
+    /// Number of spans dropped by load-shedding sampling (queue near capacity)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub sampled_out: Option<usize>,
+    /// Number of spans skipped because their span_id was already ingested (client retries)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub duplicates: Option<usize>,
+    /// Number of spans dropped by `on_ingest` scripts
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub dropped_by_scripts: Option<usize>,
+    /// Number of spans dropped by transform plugins
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub dropped_by_transforms: Option<usize>,
+    /// Stored trace, as accepted by `/api/v1/traces/:trace_id`: the first
+    /// root span stored, else the first span
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub trace_id: Option<String>,
     pub errors: Vec<String>,
 }
 
@@ -104,74 +154,657 @@ pub struct IngestResponse {
 /// - Attributes total size must be ≤ 1MB
 /// - Span name max length: 256 characters
 ///
+/// # Sessions
+/// Set the `session.external_key` attribute (e.g. a chat thread ID) to keep a
+/// conversation in one session even when the agent process restarts and its
+/// SDK generates a new session_id.
+///
+/// # Clock Skew
+/// Spans more than `ingestion.clock_skew_tolerance_ms` ahead of server time, or
+/// starting before their parent, are shifted into causal order. Corrected spans
+/// carry `agentreplay.clock_skew.*` attributes with the original timestamps.
+///
+/// # Parallel Calls
+/// Overlapping siblings in a batch are tagged with a shared
+/// `agentreplay.parallel.group_id` (set it yourself to group calls across
+/// batches); `/api/v1/traces/:trace_id/parallel` shows the resulting fan-outs.
+///
+/// # In-Progress Spans
+/// Send a span without `end_time` and with `agentreplay.span.in_progress` set
+/// to `true` when it starts; it is stored and streamed as in progress. Send it
+/// again with the same span_id and start_time plus an `end_time` to finalize
+/// it. Spans not finalized within `ingestion.open_span_timeout_secs` are
+/// closed and flagged `agentreplay.span.abandoned`.
+///
+/// # Span Links
+/// A step that merges the results of other spans (fan-in) lists their span
+/// IDs in `agentreplay.links`, e.g. `["0x1a", "0x1b"]`; the trace graph draws
+/// them as `fan_in` edges next to the causal parent.
+///
+/// # Scripts
+/// Enabled `on_ingest` scripts (see `/api/v1/scripts`) run on each span before
+/// storage and may modify attributes or drop it (counted in `dropped_by_scripts`).
+///
+/// # Pipeline
+/// Stage order comes from `ingestion.pipeline` (see
+/// `/api/v1/ingestion/pipeline`). `transform:<name>` stages run WASM transform
+/// plugins that may rewrite or drop spans (counted in `dropped_by_transforms`).
+///
+/// # Retries
+/// Resending a span_id already stored within `ingestion.dedup_window_secs` is a
+/// no-op (counted in `duplicates`). An optional `Idempotency-Key` header makes
+/// the whole request idempotent: a retry with the same key gets the original
+/// response back without re-ingesting anything. Keys are scoped to the
+/// caller's tenant and project.
+///
 /// # Response Codes
-/// - 201: Success - Returns accepted/rejected counts
+/// - 201: Success - Returns accepted/rejected counts; the stored trace is
+///   named in `trace_id` and the `X-Agentreplay-Trace` header
 /// - 400: Validation error - Check error field for details
 /// - 401: Unauthorized - Check authentication
+/// - 422: `Idempotency-Key` already used with a different request body
+/// - 429: Ingestion queue full or span quota used up - Retry after the
+///   `Retry-After` header
 /// - 500: Server error - Check server logs
-#[tracing::instrument(skip(state, _auth, request), fields(span_count = request.spans.len()))]
+#[tracing::instrument(skip(state, auth, headers, request), fields(span_count = request.spans.len()))]
 pub async fn ingest_traces(
     State(state): State<AppState>,
-    Extension(_auth): Extension<AuthContext>, // TODO: Use auth.tenant_id for multi-tenancy
+    Extension(auth): Extension<AuthContext>, // TODO: Use auth.tenant_id for multi-tenancy
+    headers: HeaderMap,
     Json(request): Json<IngestRequest>,
-) -> Result<(StatusCode, Json<IngestResponse>), ApiError> {
+) -> Result<Response, ApiError> {
     debug!("Ingesting {} spans", request.spans.len());
 
     // VALIDATION: Batch size (Task 5)
     validation::validate_batch_size(request.spans.len())?;
 
+    // Retried request: replay the response recorded for this Idempotency-Key
+    let idempotency_key = headers
+        .get(IDEMPOTENCY_KEY_HEADER)
+        .and_then(|v| v.to_str().ok())
+        .filter(|k| !k.is_empty())
+        .map(|key| {
+            let key = IdempotencyKey {
+                tenant_id: auth.tenant_id,
+                project_id: auth.project_id,
+                key: key.to_string(),
+            };
+            (key, request_digest(&request.spans))
+        });
+    if let Some((ref key, digest)) = idempotency_key {
+        match state.ingestion_idempotency.replay(key, digest) {
+            Replay::Miss => {}
+            Replay::Hit(cached) => {
+                debug!("Replaying response for Idempotency-Key {}", key.key);
+                let status = StatusCode::from_u16(cached.status).unwrap_or(StatusCode::CREATED);
+                return Ok(with_trace_header(status, cached.body));
+            }
+            Replay::Mismatch => {
+                return Err(ApiError::IdempotencyKeyReused(format!(
+                    "Idempotency-Key '{}' was already used with a different request body",
+                    key.key
+                )));
+            }
+        }
+    }
+
+    state.quotas.consume_spans(
+        &QuotaScope::new(&auth),
+        request.spans.iter().map(span_project_id),
+    )?;
+
+    let user_agent = headers
+        .get(header::USER_AGENT)
+        .and_then(|v| v.to_str().ok());
+    let (status, response) =
+        ingest_spans(&state, auth.tenant_id, request.spans, user_agent).await?;
+
+    let body = serde_json::to_value(&response).unwrap_or(serde_json::json!({}));
+    if let Some((key, digest)) = idempotency_key {
+        let cached = CachedResponse {
+            status: status.as_u16(),
+            body: body.clone(),
+        };
+        state
+            .ingestion_idempotency
+            .store_response(key, digest, cached);
+    }
+
+    Ok(with_trace_header(status, body))
+}
+
+/// Digest of a batch, independent of attribute order, to tell a retry from
+/// a different request reusing its Idempotency-Key
+fn request_digest(spans: &[AgentreplaySpan]) -> u64 {
+    use std::hash::{Hash, Hasher};
+
+    let mut hasher = std::collections::hash_map::DefaultHasher::new();
+    for span in spans {
+        span.span_id.hash(&mut hasher);
+        span.trace_id.hash(&mut hasher);
+        span.parent_span_id.hash(&mut hasher);
+        span.name.hash(&mut hasher);
+        span.start_time.hash(&mut hasher);
+        span.end_time.hash(&mut hasher);
+        let mut attributes: Vec<_> = span.attributes.iter().collect();
+        attributes.sort_unstable();
+        attributes.hash(&mut hasher);
+    }
+    hasher.finish()
+}
+
+/// Ingest response naming its stored trace in [`TRACE_HEADER`]
+fn with_trace_header(status: StatusCode, body: serde_json::Value) -> Response {
+    let trace_id = body
+        .get("trace_id")
+        .and_then(|id| id.as_str())
+        .and_then(|id| HeaderValue::from_str(id).ok());
+    let mut response = (status, Json(body)).into_response();
+    if let Some(trace_id) = trace_id {
+        response.headers_mut().insert(TRACE_HEADER, trace_id);
+    }
+    response
+}
+
+/// Keep the first stored root span as a request's trace, else its first span
+fn note_stored_trace(trace: &mut Option<AgentFlowEdge>, edge: &AgentFlowEdge) {
+    match trace {
+        Some(current) if current.causal_parent == 0 || edge.causal_parent != 0 => {}
+        _ => *trace = Some(*edge),
+    }
+}
+
+fn trace_id_of(trace: Option<AgentFlowEdge>) -> Option<String> {
+    trace.map(|edge| format!("{:#x}", edge.edge_id))
+}
+
+/// Run a batch through the configured pipeline and store what survives
+///
+/// Shared by `POST /api/v1/traces` and bulk imports; the caller checks the
+/// batch size. `tenant_id` is the caller's tenant, whose scripts run.
+pub(crate) async fn ingest_spans(
+    state: &AppState,
+    tenant_id: u64,
+    mut spans: Vec<AgentreplaySpan>,
+    user_agent: Option<&str>,
+) -> Result<(StatusCode, IngestResponse), ApiError> {
+    // Sanitize, enrich, script and transform spans in the configured order
+    let stages = run_span_stages(state, tenant_id, &mut spans, user_agent);
+
     // Try the high-performance path first (IngestionActor with deduplication)
-    if let Some(ref actor) = state.ingestion_actor {
-        return ingest_via_actor(&state, actor, request).await;
+    let use_governor = state.ingest_pipeline.has(&PipelineStage::Governor);
+    let (status, Json(mut response)) = match state.ingestion_actor {
+        Some(ref actor) if use_governor => {
+            ingest_via_actor(state, actor, spans, stages.errors).await?
+        }
+        _ => {
+            // Fallback: Direct ingestion (no deduplication)
+            debug!("Using direct ingestion path (no actor available or governor stage disabled)");
+            ingest_direct(state, spans, stages.errors).await?
+        }
+    };
+
+    if stages.dropped_by_scripts > 0 {
+        response.dropped_by_scripts = Some(stages.dropped_by_scripts);
+    }
+    if stages.dropped_by_transforms > 0 {
+        response.dropped_by_transforms = Some(stages.dropped_by_transforms);
+    }
+    Ok((status, response))
+}
+
+/// Spans rejected or dropped by the span stages of the ingestion pipeline
+#[derive(Debug, Default)]
+struct SpanStageOutcome {
+    /// Validation errors from sanitization
+    errors: Vec<String>,
+    dropped_by_scripts: usize,
+    dropped_by_transforms: usize,
+}
+
+/// Run the span stages of the configured pipeline in order
+///
+/// Spans are sanitized once more at the end if a script or transform ran
+/// after the `sanitize` stage, or if the pipeline does not list it.
+///
+/// `user_agent` is the request's User-Agent header, used by the `enrich` stage.
+fn run_span_stages(
+    state: &AppState,
+    tenant_id: u64,
+    spans: &mut Vec<AgentreplaySpan>,
+    user_agent: Option<&str>,
+) -> SpanStageOutcome {
+    let mut outcome = SpanStageOutcome::default();
+    let mut sanitized = false;
+
+    for stage in state.ingest_pipeline.stages() {
+        match stage {
+            PipelineStage::Sanitize => {
+                sanitize_spans(spans, &mut outcome.errors);
+                sanitized = true;
+            }
+            PipelineStage::Enrich => enrich_spans(state, spans, user_agent),
+            PipelineStage::Logprobs => {
+                let captured = spans
+                    .iter_mut()
+                    .map(crate::ingestion::capture_logprobs)
+                    .filter(|&captured| captured)
+                    .count();
+                if captured > 0 {
+                    debug!("Captured token logprobs on {} spans", captured);
+                }
+            }
+            PipelineStage::Scripts => {
+                // on_ingest scripts may re-tag spans, drop them or route notifications
+                if state.scripts.has_hook(tenant_id, ScriptHook::OnIngest) {
+                    outcome.dropped_by_scripts += apply_ingest_scripts(state, tenant_id, spans);
+                    sanitized = false;
+                }
+            }
+            PipelineStage::Transform(name) => {
+                let transforms = state.ingest_pipeline.transforms();
+                match transforms.apply(tenant_id, name, spans) {
+                    Some(dropped) => {
+                        outcome.dropped_by_transforms += dropped;
+                        sanitized = false;
+                    }
+                    None => debug!(
+                        "Tenant {} has no transform registered as '{}', skipping stage",
+                        tenant_id, name
+                    ),
+                }
+            }
+            PipelineStage::Sample | PipelineStage::Governor | PipelineStage::Store => {}
+        }
+    }
+
+    if !sanitized {
+        sanitize_spans(spans, &mut outcome.errors);
+    }
+    outcome
+}
+
+/// Validate and clean spans in place, moving invalid ones into `errors`
+fn sanitize_spans(spans: &mut Vec<AgentreplaySpan>, errors: &mut Vec<String>) {
+    let mut idx = 0;
+    spans.retain_mut(|span| {
+        let result = validate_span(idx, span)
+            .and_then(|_| sanitize_span(span).map_err(|e| format!("Span {}: {}", idx, e)));
+        idx += 1;
+        match result {
+            Ok(clean) => {
+                *span = clean;
+                true
+            }
+            Err(e) => {
+                debug!("Rejected {}", e);
+                errors.push(e);
+                false
+            }
+        }
+    });
+}
+
+/// Enrich stage: clock skew correction, parallel call grouping, attribute
+/// enrichment and failure classification
+fn enrich_spans(state: &AppState, spans: &mut [AgentreplaySpan], user_agent: Option<&str>) {
+    // Correct clock skew between hosts before timestamps are stored
+    if let Some(ref corrector) = state.clock_skew {
+        let corrected = corrector.correct_batch(spans);
+        if corrected > 0 {
+            debug!("Corrected clock skew on {} spans", corrected);
+        }
+    }
+
+    // Tag concurrent siblings (parallel tool calls) with batch group IDs
+    let grouped = crate::ingestion::annotate_parallel_groups(spans);
+    if grouped > 0 {
+        debug!("Tagged {} spans as parallel branches", grouped);
+    }
+
+    // Derive model family, provider, SDK and country from raw attributes
+    for span in spans.iter_mut() {
+        crate::ingestion::enrich_span(span, user_agent);
+    }
+
+    // Classify and fingerprint failed spans
+    let failed = spans
+        .iter_mut()
+        .map(crate::ingestion::tag_failure)
+        .filter(|&failed| failed)
+        .count();
+    if failed > 0 {
+        debug!("Tagged {} failed spans", failed);
+    }
+}
+
+/// Store the provider/model/operation filter attributes of an edge (best effort)
+///
+/// Written before the edge so list filters skip payload reads and the
+/// dashboard rollups attribute the edge to its model.
+fn store_filter_attrs(state: &AppState, edge: &AgentFlowEdge, payload: &GenAIPayload) {
+    let model = payload
+        .request_model
+        .as_deref()
+        .or(payload.response_model.as_deref());
+    let provider = payload.system.as_deref();
+    let operation = payload.operation_name.as_deref();
+    if provider.is_none() && model.is_none() && operation.is_none() {
+        return;
+    }
+
+    let result = if let Some(ref pm) = state.project_manager {
+        pm.get_or_open_project(edge.project_id)
+            .and_then(|db| db.put_edge_attrs(edge.edge_id, provider, model, operation))
+    } else {
+        state
+            .db
+            .put_edge_attrs(edge.edge_id, provider, model, operation)
+    };
+    if let Err(e) = result {
+        warn!(
+            "Failed to store filter attributes for edge {:#x}: {}",
+            edge.edge_id, e
+        );
+    }
+}
+
+/// Store the enrichment record of an edge for list filtering (best effort)
+fn store_edge_enrichment(state: &AppState, edge: &AgentFlowEdge, attrs: &HashMap<String, String>) {
+    let enrichment = crate::ingestion::enrichment_from_attributes(attrs);
+    if enrichment.is_empty() {
+        return;
+    }
+
+    let result = if let Some(ref pm) = state.project_manager {
+        pm.get_or_open_project(edge.project_id)
+            .and_then(|db| db.put_edge_enrichment(edge.edge_id, &enrichment))
+    } else {
+        state.db.put_edge_enrichment(edge.edge_id, &enrichment)
+    };
+    if let Err(e) = result {
+        warn!(
+            "Failed to store enrichment for edge {:#x}: {}",
+            edge.edge_id, e
+        );
+    }
+}
+
+/// Store the token logprobs captured by the `logprobs` stage (best effort)
+fn store_edge_logprobs(state: &AppState, edge: &AgentFlowEdge, attrs: &HashMap<String, String>) {
+    let Some(tokens) = crate::ingestion::logprobs_from_attributes(attrs) else {
+        return;
+    };
+
+    let result = if let Some(ref pm) = state.project_manager {
+        pm.get_or_open_project(edge.project_id)
+            .and_then(|db| db.put_edge_logprobs(edge.edge_id, &tokens))
+    } else {
+        state.db.put_edge_logprobs(edge.edge_id, &tokens)
+    };
+    if let Err(e) = result {
+        warn!(
+            "Failed to store token logprobs for edge {:#x}: {}",
+            edge.edge_id, e
+        );
+    }
+}
+
+/// Fold a stored span into its session summary (best effort)
+fn store_session_summary(state: &AppState, edge: &AgentFlowEdge, attrs: &HashMap<String, String>) {
+    let result = if let Some(ref pm) = state.project_manager {
+        pm.get_or_open_project(edge.project_id)
+            .and_then(|db| state.session_summarizer.record_span(&db, edge, attrs))
+    } else {
+        state.session_summarizer.record_span(&state.db, edge, attrs)
+    };
+    if let Err(e) = result {
+        warn!(
+            "Failed to update summary of session {} for edge {:#x}: {}",
+            edge.session_id, edge.edge_id, e
+        );
+    }
+}
+
+/// Link a stored span to its conversation (best effort)
+fn store_conversation_link(
+    state: &AppState,
+    edge: &AgentFlowEdge,
+    attrs: &HashMap<String, String>,
+) {
+    let result = if let Some(ref pm) = state.project_manager {
+        pm.get_or_open_project(edge.project_id)
+            .and_then(|db| state.conversations.record(&db, edge, attrs))
+    } else {
+        state.conversations.record(&state.db, edge, attrs)
+    };
+    if let Err(e) = result {
+        warn!(
+            "Failed to link edge {:#x} to its conversation: {}",
+            edge.edge_id, e
+        );
+    }
+}
+
+/// Span links recorded on a native span, with IDs mapped like `span_id`
+fn span_links(edge: &AgentFlowEdge, attrs: &HashMap<String, String>) -> Vec<SpanLink> {
+    let Some(value) = attrs.get(crate::ingestion::ATTR_SPAN_LINKS) else {
+        return Vec::new();
+    };
+    crate::ingestion::parse_span_links(
+        edge.edge_id,
+        &serde_json::Value::String(value.clone()),
+        |id| parse_id_to_u64(id).map(u128::from),
+    )
+}
+
+/// Store the span links of an edge (best effort)
+fn store_edge_links(state: &AppState, edge: &AgentFlowEdge, attrs: &HashMap<String, String>) {
+    if !edge.has_links() {
+        return;
+    }
+    let links = span_links(edge, attrs);
+
+    let result = if let Some(ref pm) = state.project_manager {
+        pm.get_or_open_project(edge.project_id)
+            .and_then(|db| db.put_edge_links(edge.edge_id, &links))
+    } else {
+        state.db.put_edge_links(edge.edge_id, &links)
+    };
+    if let Err(e) = result {
+        warn!(
+            "Failed to store span links for edge {:#x}: {}",
+            edge.edge_id, e
+        );
+    }
+}
+
+/// Run a tenant's `on_ingest` scripts over each span, returning how many
+/// were dropped
+fn apply_ingest_scripts(
+    state: &AppState,
+    tenant_id: u64,
+    spans: &mut Vec<AgentreplaySpan>,
+) -> usize {
+    let before = spans.len();
+    let mut notifications = Vec::new();
+    spans.retain_mut(|span| {
+        let Ok(record) = serde_json::to_value(&*span) else {
+            return true;
+        };
+        let outcome = state.scripts.run(tenant_id, ScriptHook::OnIngest, record);
+        notifications.extend(outcome.notifications);
+        if outcome.dropped {
+            return false;
+        }
+        if let Some(updated) = span_from_script_record(outcome.record) {
+            *span = updated;
+        }
+        true
+    });
+    state.scripts.dispatch(ScriptHook::OnIngest, notifications);
+    before - spans.len()
+}
+
+/// Convert a script-modified record back into a span
+///
+/// Scripts may assign non-string attribute values; those are stringified.
+/// Returns None (keeping the original span) if the record lost required fields.
+fn span_from_script_record(mut record: serde_json::Value) -> Option<AgentreplaySpan> {
+    if let Some(attrs) = record
+        .get_mut("attributes")
+        .and_then(|a| a.as_object_mut())
+    {
+        for value in attrs.values_mut() {
+            if !value.is_string() {
+                *value = serde_json::Value::String(value.to_string());
+            }
+        }
+    }
+    serde_json::from_value(record).ok()
+}
+
+/// Whether `edge` is a client retry of a span already stored within the dedup window
+///
+/// The bloom filter answers the common case (never seen) without touching
+/// storage; only possible hits are confirmed with a point lookup. A complete
+/// span replacing a stored in-progress or abandoned one is a finalize
+/// update, not a retry.
+fn is_retried_span(state: &AppState, edge: &AgentFlowEdge) -> bool {
+    if !state.ingestion_idempotency.possibly_seen(edge.edge_id) {
+        return false;
+    }
+
+    let existing = if let Some(ref pm) = state.project_manager {
+        pm.get_or_open_project(edge.project_id)
+            .and_then(|db| db.get_for_tenant(edge.edge_id, edge.tenant_id))
+    } else {
+        state.db.get_for_tenant(edge.edge_id, edge.tenant_id)
+    };
+
+    match existing {
+        Ok(Some(stored)) => {
+            edge.is_in_progress() || !(stored.is_in_progress() || stored.is_abandoned())
+        }
+        _ => false,
+    }
+}
+
+/// Track a span start event, or merge a finalize update with its start
+///
+/// Start attributes the finalize update does not repeat are kept.
+fn track_open_span(state: &AppState, edge: &AgentFlowEdge, attrs: &mut HashMap<String, String>) {
+    if edge.is_in_progress() {
+        if !state.open_spans.open(edge, attrs) {
+            warn!(
+                "Too many open spans, {:#x} will not be closed if abandoned",
+                edge.edge_id
+            );
+        }
+        return;
     }
 
-    // Fallback: Direct ingestion (no deduplication)
-    debug!("Using direct ingestion path (no actor available)");
-    ingest_direct(&state, request).await
+    if let Some(start) = state.open_spans.finalize(edge.edge_id) {
+        for (key, value) in start.attributes {
+            attrs.entry(key).or_insert(value);
+        }
+        attrs.remove(crate::ingestion::ATTR_SPAN_IN_PROGRESS);
+    }
+}
+
+/// Close open spans whose finalize update never arrived
+///
+/// Each span is stored complete with the time it was open as its duration
+/// and flagged abandoned. Returns how many were closed.
+pub(crate) async fn close_abandoned_spans(
+    state: &AppState,
+    spans: Vec<crate::ingestion::OpenSpan>,
+) -> usize {
+    let now_us = now_us();
+
+    let mut closed = 0;
+    for open in spans {
+        let mut edge = open.edge;
+        edge.flags = (edge.flags & !FLAG_IN_PROGRESS) | FLAG_ABANDONED;
+        edge.duration_us = now_us
+            .saturating_sub(edge.timestamp_us)
+            .min(u32::MAX as u64) as u32;
+        edge.checksum = edge.compute_checksum();
+
+        let mut attrs = open.attributes;
+        attrs.remove(crate::ingestion::ATTR_SPAN_IN_PROGRESS);
+        attrs.insert(
+            crate::ingestion::ATTR_SPAN_ABANDONED.to_string(),
+            "true".to_string(),
+        );
+
+        match store_edge_and_payload(state, &edge, &attrs, None).await {
+            Ok(()) => {
+                state.cost_tracker.track_edge(&edge, None).await;
+                let _ = state.trace_broadcaster.send(edge);
+                closed += 1;
+            }
+            Err(e) => warn!("Failed to close abandoned span {:#x}: {}", edge.edge_id, e),
+        }
+    }
+    closed
+}
+
+/// Rewrite the edge's session_id to the stable session bound to its external session key
+fn stitch_session(state: &AppState, edge: &mut AgentFlowEdge, attrs: &HashMap<String, String>) {
+    let Some(external_key) = attrs.get(EXTERNAL_SESSION_KEY_ATTR) else {
+        return;
+    };
+
+    let session_id = state.session_registry.resolve(
+        edge.tenant_id,
+        edge.project_id,
+        external_key,
+        edge.session_id,
+    );
+    if session_id != edge.session_id {
+        edge.session_id = session_id;
+        edge.checksum = edge.compute_checksum();
+    }
 }
 
 /// High-performance ingestion via the IngestionActor
 ///
-/// Routes traces through: Validation → Batching → Deduplication → Storage
+/// Routes sanitized spans through: Batching → Deduplication → Storage.
+/// `errors` carries the rejections from earlier pipeline stages.
 async fn ingest_via_actor(
     state: &AppState,
     actor: &crate::ingestion::IngestionActorHandle,
-    request: IngestRequest,
+    spans: Vec<AgentreplaySpan>,
+    mut errors: Vec<String>,
 ) -> Result<(StatusCode, Json<IngestResponse>), ApiError> {
-    let mut payloads = Vec::with_capacity(request.spans.len());
-    let mut errors = Vec::new();
+    let mut payloads = Vec::with_capacity(spans.len());
     let mut edges_for_storage = Vec::new();
-
-    // Phase 1: Validate and convert spans
-    for (idx, span) in request.spans.iter().enumerate() {
-        // Run all validations
-        if let Err(e) = validate_span(idx, span) {
-            errors.push(e);
-            continue;
-        }
-
-        // Convert to edge (for storage after deduplication)
-        let validated_span = match sanitize_span(span) {
-            Ok(s) => s,
-            Err(e) => {
-                errors.push(format!("Span {}: {}", idx, e));
-                continue;
+    let mut duplicates = 0;
+
+    // Phase 1: Convert spans (for storage after deduplication)
+    for (idx, mut span) in spans.into_iter().enumerate() {
+        match convert_span_to_edge(&span) {
+            Ok(edge) if is_retried_span(state, &edge) => {
+                debug!("Span {:#x} already ingested, skipping retry", edge.edge_id);
+                duplicates += 1;
             }
-        };
+            Ok(mut edge) => {
+                stitch_session(state, &mut edge, &span.attributes);
+                track_open_span(state, &edge, &mut span.attributes);
 
-        match convert_span_to_edge(&validated_span) {
-            Ok(edge) => {
                 // Extract text for embedding (prompt + completion if available)
-                let text = extract_embedding_text(&validated_span.attributes);
-                let payload_json =
-                    serde_json::to_value(&validated_span).unwrap_or(serde_json::json!({}));
+                let text = extract_embedding_text(&span.attributes);
+                let payload_json = serde_json::to_value(&span).unwrap_or(serde_json::json!({}));
 
                 payloads.push(TracePayload {
                     trace_id: edge.edge_id,
+                    project_id: edge.project_id,
+                    agent_id: edge.agent_id,
                     text,
                     payload: payload_json,
                 });
-                edges_for_storage.push((edge, validated_span.attributes.clone()));
+                edges_for_storage.push((edge, span.attributes));
             }
             Err(e) => {
                 errors.push(format!("Span {}: {}", idx, e));
@@ -186,16 +819,61 @@ async fn ingest_via_actor(
                 accepted: 0,
                 rejected: errors.len(),
                 deduplicated: Some(0),
+                sampled_out: None,
+                duplicates: Some(duplicates),
+                dropped_by_scripts: None,
+                dropped_by_transforms: None,
+                trace_id: None,
                 errors,
             }),
         ));
     }
 
+    // Admission: apply backpressure before the actor channel fills up
+    let decision = state
+        .ingestion_admission
+        .admit(
+            payloads.len(),
+            actor.queue_depth(),
+            actor.queue_capacity(),
+            actor.stats().throughput,
+        )
+        .map_err(|reason| {
+            warn!(
+                "Ingestion queue above high watermark ({}/{}), rejecting {} spans",
+                actor.queue_depth(),
+                actor.queue_capacity(),
+                payloads.len()
+            );
+            ApiError::Overloaded(reason)
+        })?;
+
+    let sampled_out = if let QueueDecision::Sample { keep, total } = decision {
+        warn!(
+            "Ingestion queue near capacity, sampling {} of {} spans",
+            keep, total
+        );
+        payloads = payloads
+            .into_iter()
+            .enumerate()
+            .filter_map(|(idx, p)| decision.keeps(idx).then_some(p))
+            .collect();
+        edges_for_storage = edges_for_storage
+            .into_iter()
+            .enumerate()
+            .filter_map(|(idx, e)| decision.keeps(idx).then_some(e))
+            .collect();
+        Some(total - keep)
+    } else {
+        None
+    };
+
     // Phase 2: Send to actor for batching and deduplication
     let results = actor.ingest_many(payloads).await;
 
     // Phase 3: Process results and store non-deduplicated traces
     let mut stored = 0;
+    let mut stored_trace = None;
     let mut deduplicated = 0;
     let mut failed = 0;
 
@@ -214,7 +892,27 @@ async fn ingest_via_actor(
                 }
 
                 // Track cost and broadcast
+                if !edge.is_in_progress() {
+                    state.cost_tracker.track_edge(&edge, None).await;
+                }
+                note_stored_trace(&mut stored_trace, &edge);
+                let _ = state.trace_broadcaster.send(edge);
+                stored += 1;
+            }
+            // A span cannot duplicate itself: this is a finalize update
+            // matching its own start event
+            Ok(IngestionResult::Deduplicated {
+                trace_id,
+                similar_to,
+                ..
+            }) if similar_to == trace_id => {
+                if let Err(e) = store_edge_and_payload(state, &edge, &attrs, None).await {
+                    warn!("Failed to store edge {:#x}: {}", trace_id, e);
+                    failed += 1;
+                    continue;
+                }
                 state.cost_tracker.track_edge(&edge, None).await;
+                note_stored_trace(&mut stored_trace, &edge);
                 let _ = state.trace_broadcaster.send(edge);
                 stored += 1;
             }
@@ -254,11 +952,42 @@ async fn ingest_via_actor(
             accepted: stored,
             rejected: errors.len(),
             deduplicated: Some(deduplicated),
+            sampled_out,
+            duplicates: Some(duplicates),
+            dropped_by_scripts: None,
+            dropped_by_transforms: None,
+            trace_id: trace_id_of(stored_trace),
             errors,
         }),
     ))
 }
 
+/// GET /api/v1/ingestion/queue
+///
+/// Ingestion queue depth and admission counters (rejected / sampled spans).
+pub async fn get_ingestion_queue_metrics(
+    State(state): State<AppState>,
+    Extension(_auth): Extension<AuthContext>,
+) -> Json<serde_json::Value> {
+    let Some(ref actor) = state.ingestion_actor else {
+        return Json(serde_json::json!({ "enabled": false }));
+    };
+
+    let metrics = state
+        .ingestion_admission
+        .metrics(actor.queue_depth(), actor.queue_capacity());
+    let stats = actor.stats();
+
+    Json(serde_json::json!({
+        "enabled": true,
+        "admission": metrics,
+        "throughput": stats.throughput,
+        "avg_batch_latency_ms": stats.avg_batch_latency_ms,
+        "clock_skew": state.clock_skew.as_ref().map(|c| c.stats()),
+        "open_spans": state.open_spans.len(),
+    }))
+}
+
 /// Extract text for embedding from span attributes
 fn extract_embedding_text(attrs: &HashMap<String, String>) -> String {
     // Prioritize GenAI semantic convention fields
@@ -294,7 +1023,7 @@ fn extract_embedding_text(attrs: &HashMap<String, String>) -> String {
 }
 
 /// Validate a span and return error message if invalid
-fn validate_span(idx: usize, span: &AgentreplaySpan) -> Result<(), String> {
+pub(crate) fn validate_span(idx: usize, span: &AgentreplaySpan) -> Result<(), String> {
     if let Err(e) = validation::validate_span_id(&span.span_id) {
         return Err(format!("Span {}: {}", idx, e));
     }
@@ -332,7 +1061,7 @@ fn sanitize_span(span: &AgentreplaySpan) -> Result<AgentreplaySpan, String> {
 ///
 /// When an embedding is provided, it's stored in the vector index to enable
 /// semantic search across all ingested traces.
-async fn store_edge_and_payload(
+pub(crate) async fn store_edge_and_payload(
     state: &AppState,
     edge: &AgentFlowEdge,
     attrs: &HashMap<String, String>,
@@ -342,6 +1071,8 @@ async fn store_edge_and_payload(
     if !attrs.is_empty() {
         let mut genai_payload = GenAIPayload::from_attributes(attrs);
         genai_payload.calculate_total_tokens();
+        // Logprobs are stored compactly on their own
+        genai_payload.additional.remove(crate::ingestion::ATTR_LOGPROBS);
 
         let json_bytes = serde_json::to_vec(&genai_payload)
             .map_err(|e| format!("Failed to serialize payload: {}", e))?;
@@ -354,6 +1085,7 @@ async fn store_edge_and_payload(
         };
 
         store_result.map_err(|e| format!("Failed to store payload: {}", e))?;
+        store_filter_attrs(state, edge, &genai_payload);
     }
 
     // Insert edge (with embedding if available for semantic search)
@@ -387,85 +1119,59 @@ async fn store_edge_and_payload(
         }
     }
 
+    store_edge_enrichment(state, edge, attrs);
+    store_edge_logprobs(state, edge, attrs);
+    store_edge_links(state, edge, attrs);
+    record_completed_span(state, edge, attrs);
+
     Ok(())
 }
 
-/// Direct ingestion path (fallback when actor is not available)
+/// Feed a span to the aggregates that should count it once
+///
+/// Start events are skipped; the span is counted when it is finalized or
+/// closed as abandoned.
+fn record_completed_span(state: &AppState, edge: &AgentFlowEdge, attrs: &HashMap<String, String>) {
+    if edge.is_in_progress() {
+        return;
+    }
+    state.heavy_hitters.record(edge, attrs);
+    state.failures.record(edge, attrs);
+    state.tool_contracts.record(edge, attrs);
+    state.knowledge_graph.record_span(edge, attrs);
+    state.volume_monitor.record(edge);
+    store_session_summary(state, edge, attrs);
+    store_conversation_link(state, edge, attrs);
+}
+
+/// Direct ingestion path (fallback when the actor or governor stage is not available)
+///
+/// `spans` are already sanitized; `errors` carries the rejections from earlier
+/// pipeline stages.
 async fn ingest_direct(
     state: &AppState,
-    request: IngestRequest,
+    spans: Vec<AgentreplaySpan>,
+    mut errors: Vec<String>,
 ) -> Result<(StatusCode, Json<IngestResponse>), ApiError> {
     let mut edges = Vec::new();
-    let mut errors = Vec::new();
+    let mut duplicates = 0;
 
     // Build list of (edge, attributes) pairs
     let mut edge_attributes: Vec<(AgentFlowEdge, std::collections::HashMap<String, String>)> =
         Vec::new();
 
-    for (idx, span) in request.spans.iter().enumerate() {
-        // VALIDATION: Span ID format (Task 5)
-        if let Err(e) = validation::validate_span_id(&span.span_id) {
-            warn!("Invalid span ID at index {}: {}", idx, e);
-            errors.push(format!("Span {}: {}", idx, e));
-            continue;
-        }
-
-        // VALIDATION: Timestamp range (Task 5)
-        if let Err(e) = validation::validate_timestamp_range(span.start_time, span.end_time) {
-            warn!("Invalid timestamp at index {}: {}", idx, e);
-            errors.push(format!("Span {}: {}", idx, e));
-            continue;
-        }
-
-        // VALIDATION: Span name (Task 5)
-        if let Err(e) = validation::validate_span_name(&span.name) {
-            warn!("Invalid span name at index {}: {}", idx, e);
-            errors.push(format!("Span {}: {}", idx, e));
-            continue;
-        }
-
-        // VALIDATION: Attributes size (Task 5)
-        if let Err(e) = validation::validate_attributes_size(&span.attributes) {
-            warn!("Invalid attributes at index {}: {}", idx, e);
-            errors.push(format!("Span {}: {}", idx, e));
-            continue;
-        }
-
-        // SECURITY: Validate and sanitize span name
-        let validated_name = match sanitization::validate_name(&span.name) {
-            Ok(name) => name,
-            Err(e) => {
-                warn!("Invalid span name at index {}: {}", idx, e);
-                errors.push(format!("Span {}: invalid name - {}", idx, e));
-                continue;
-            }
-        };
-
-        // SECURITY: Validate and sanitize attributes
-        let validated_attrs = match sanitization::validate_attributes(&span.attributes) {
-            Ok(attrs) => attrs,
-            Err(e) => {
-                warn!("Invalid attributes at index {}: {}", idx, e);
-                errors.push(format!("Span {}: invalid attributes - {}", idx, e));
-                continue;
+    for (idx, mut span) in spans.into_iter().enumerate() {
+        match convert_span_to_edge(&span) {
+            Ok(edge) if is_retried_span(state, &edge) => {
+                debug!("Span {:#x} already ingested, skipping retry", edge.edge_id);
+                duplicates += 1;
             }
-        };
+            Ok(mut edge) => {
+                stitch_session(state, &mut edge, &span.attributes);
+                track_open_span(state, &edge, &mut span.attributes);
 
-        // Create a validated span with cleaned data
-        let validated_span = AgentreplaySpan {
-            span_id: span.span_id.clone(),
-            trace_id: span.trace_id.clone(),
-            parent_span_id: span.parent_span_id.clone(),
-            name: validated_name,
-            start_time: span.start_time,
-            end_time: span.end_time,
-            attributes: validated_attrs.clone(),
-        };
-
-        match convert_span_to_edge(&validated_span) {
-            Ok(edge) => {
                 // Store edge and its validated attributes together
-                edge_attributes.push((edge, validated_attrs));
+                edge_attributes.push((edge, span.attributes));
                 edges.push(edge);
             }
             Err(e) => {
@@ -477,6 +1183,10 @@ async fn ingest_direct(
 
     let accepted = edges.len();
     let rejected = errors.len();
+    let mut stored_trace = None;
+    for edge in &edges {
+        note_stored_trace(&mut stored_trace, edge);
+    }
 
     // CRITICAL FIX: Reorder operations to prevent read-your-writes race condition
     // 1. Store payloads FIRST
@@ -498,6 +1208,8 @@ async fn ingest_direct(
                 // Convert to GenAI-compliant payload
                 let mut genai_payload = GenAIPayload::from_attributes(attributes);
                 genai_payload.calculate_total_tokens();
+                // Logprobs are stored compactly on their own
+                genai_payload.additional.remove(crate::ingestion::ATTR_LOGPROBS);
 
                 match serde_json::to_vec(&genai_payload) {
                     Ok(json_bytes) => {
@@ -525,6 +1237,7 @@ async fn ingest_direct(
                                     "[INGEST] ✓ Payload stored successfully for {:#x}",
                                     edge.edge_id
                                 );
+                                store_filter_attrs(state, edge, &genai_payload);
                             }
                             Err(e) => {
                                 eprintln!("[INGEST] ✗ Failed to store payload: {}", e);
@@ -569,10 +1282,19 @@ async fn ingest_direct(
             }
         }
 
+        for (edge, attributes) in &edge_attributes {
+            store_edge_enrichment(state, edge, attributes);
+            store_edge_logprobs(state, edge, attributes);
+            store_edge_links(state, edge, attributes);
+            record_completed_span(state, edge, attributes);
+        }
+
         // Step 3: Broadcast to UI (Only after data is fully consistent)
         for edge in &edges {
             // Track cost after successful write
-            state.cost_tracker.track_edge(edge, None).await;
+            if !edge.is_in_progress() {
+                state.cost_tracker.track_edge(edge, None).await;
+            }
 
             // Broadcast to UI
             if let Err(e) = state.trace_broadcaster.send(*edge) {
@@ -596,13 +1318,33 @@ async fn ingest_direct(
             accepted,
             rejected,
             deduplicated: None, // Direct path doesn't deduplicate
+            sampled_out: None,
+            duplicates: Some(duplicates),
+            dropped_by_scripts: None,
+            dropped_by_transforms: None,
+            trace_id: trace_id_of(stored_trace),
             errors,
         }),
     ))
 }
 
+/// Project a span is stored in: its `project_id` attribute, else a hash of
+/// its project or service namespace
+fn span_project_id(span: &AgentreplaySpan) -> u16 {
+    span.attributes
+        .get("project_id")
+        .and_then(|s| s.parse::<u16>().ok())
+        .or_else(|| {
+            span.attributes
+                .get("project")
+                .or_else(|| span.attributes.get("service.namespace"))
+                .map(|s| hash_string_to_u16(s))
+        })
+        .unwrap_or(0)
+}
+
 /// Convert AgentreplaySpan to AgentFlowEdge
-fn convert_span_to_edge(span: &AgentreplaySpan) -> Result<AgentFlowEdge, String> {
+pub(crate) fn convert_span_to_edge(span: &AgentreplaySpan) -> Result<AgentFlowEdge, String> {
     // Parse span_id as u64 (will be cast to u128 for edge_id)
     let edge_id = parse_id_to_u64(&span.span_id)
         .ok_or_else(|| format!("Invalid span_id format: {}", span.span_id))?
@@ -695,18 +1437,7 @@ fn convert_span_to_edge(span: &AgentreplaySpan) -> Result<AgentFlowEdge, String>
         })
         .unwrap_or(0); // Default tenant
 
-    // Extract project (check for direct project_id attribute first, then hash)
-    let project_id = span
-        .attributes
-        .get("project_id")
-        .and_then(|s| s.parse::<u16>().ok())
-        .or_else(|| {
-            span.attributes
-                .get("project")
-                .or_else(|| span.attributes.get("service.namespace"))
-                .map(|s| hash_string_to_u16(s))
-        })
-        .unwrap_or(0);
+    let project_id = span_project_id(span);
 
     // Extract agent_id (parse or hash)
     let agent_id = span
@@ -732,6 +1463,12 @@ fn convert_span_to_edge(span: &AgentreplaySpan) -> Result<AgentFlowEdge, String>
     edge.token_count = token_count;
     edge.environment = environment;
     edge.edge_id = edge_id; // Use provided span_id instead of generated
+    if !span_links(&edge, &span.attributes).is_empty() {
+        edge.flags |= agentreplay_core::FLAG_HAS_LINKS;
+    }
+    if crate::ingestion::is_span_start(span) {
+        edge.flags |= FLAG_IN_PROGRESS;
+    }
 
     // Recompute checksum after modifications
     edge.checksum = edge.compute_checksum();
@@ -838,6 +1575,7 @@ pub async fn ingest_otel_spans(
     let mut edges = Vec::new();
     let mut errors = Vec::new();
     let mut edge_payloads: Vec<(u128, serde_json::Value)> = Vec::new();
+    let mut edge_links: Vec<(u128, Vec<SpanLink>)> = Vec::new();
 
     // Extract project_id from first span's attributes if not in auth
     let mut project_id = auth.project_id.unwrap_or(0);
@@ -870,6 +1608,11 @@ pub async fn ingest_otel_spans(
         project_id
     );
 
+    state.quotas.consume_spans(
+        &QuotaScope::new(&auth),
+        std::iter::repeat(project_id).take(batch.spans.len()),
+    )?;
+
     for (idx, span) in batch.spans.iter().enumerate() {
         match crate::api::convert_otel_span_to_edge(span, auth.tenant_id, project_id) {
             Ok(edge) => {
@@ -898,6 +1641,12 @@ pub async fn ingest_otel_spans(
 
                     edge_payloads.push((edge.edge_id, serde_json::Value::Object(payload)));
                 }
+                if edge.has_links() {
+                    edge_links.push((
+                        edge.edge_id,
+                        crate::api::otel_span_links(span, edge.edge_id),
+                    ));
+                }
 
                 edges.push(edge);
             }
@@ -982,6 +1731,18 @@ pub async fn ingest_otel_spans(
                 }
             }
         }
+
+        for (edge_id, links) in edge_links {
+            let store_result = match state.project_manager {
+                Some(ref pm) => pm
+                    .get_or_open_project(project_id)
+                    .and_then(|db| db.put_edge_links(edge_id, &links)),
+                None => state.db.put_edge_links(edge_id, &links),
+            };
+            if let Err(e) = store_result {
+                warn!("Failed to store span links for edge {:#x}: {}", edge_id, e);
+            }
+        }
     }
 
     tracing::info!(
@@ -1078,6 +1839,35 @@ mod tests {
         assert!(edge.verify_checksum());
     }
 
+    #[test]
+    fn test_convert_span_with_links() {
+        let mut attributes = HashMap::new();
+        attributes.insert(
+            crate::ingestion::ATTR_SPAN_LINKS.to_string(),
+            r#"["0x201", {"span_id": "0x202", "kind": "follows_from"}]"#.to_string(),
+        );
+
+        let span = AgentreplaySpan {
+            span_id: "0x200".to_string(),
+            trace_id: "trace-001".to_string(),
+            parent_span_id: Some("0x100".to_string()),
+            name: "synthesize".to_string(),
+            start_time: 1_700_000_000_000_000,
+            end_time: Some(1_700_000_001_000_000),
+            attributes,
+        };
+
+        let edge = convert_span_to_edge(&span).unwrap();
+        assert!(edge.has_links());
+        assert_eq!(edge.causal_parent, 0x100);
+        assert!(edge.verify_checksum());
+
+        let links = span_links(&edge, &span.attributes);
+        assert_eq!(links.len(), 2);
+        assert_eq!(links[0].span_id, 0x201);
+        assert_eq!(links[1].kind, agentreplay_core::LinkKind::FollowsFrom);
+    }
+
     #[test]
     fn test_convert_span_invalid_timestamp() {
         let span = AgentreplaySpan {
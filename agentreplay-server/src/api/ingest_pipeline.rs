@@ -0,0 +1,117 @@
+// Copyright 2025 AgentReplay (https://github.com/agentreplay)
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program. If not, see <https://www.gnu.org/licenses/>.
+
+//! Ingestion pipeline API: configured stages and transform plugins
+//!
+//! Transforms are per tenant. Any caller may list its tenant's transforms;
+//! registering and removing them requires the admin role. The transform
+//! routes are only served by builds with the `wasm` feature.
+
+use axum::{
+    body::Bytes,
+    extract::{Path, State},
+    http::StatusCode,
+    Extension, Json,
+};
+use serde::Serialize;
+
+use super::query::{ApiError, AppState};
+use crate::auth::{AuthContext, Role};
+use crate::ingestion::{PipelineStage, TransformInfo};
+
+#[derive(Debug, Serialize)]
+pub struct PipelineResponse {
+    pub stages: Vec<PipelineStage>,
+    pub transforms: Vec<TransformInfo>,
+}
+
+/// GET /api/v1/ingestion/pipeline
+///
+/// Stage order (from `ingestion.pipeline`) and the caller's tenant's
+/// registered transforms with their counters.
+pub async fn get_pipeline(
+    State(state): State<AppState>,
+    Extension(auth): Extension<AuthContext>,
+) -> Json<PipelineResponse> {
+    let pipeline = &state.ingest_pipeline;
+    Json(PipelineResponse {
+        stages: pipeline.stages().to_vec(),
+        transforms: pipeline
+            .transforms()
+            .list(auth.tenant_id, pipeline.stages()),
+    })
+}
+
+/// PUT /api/v1/ingestion/transforms/:name
+///
+/// Body is the raw WASM module. It is compiled and checked before being saved;
+/// invalid modules are returned as 400. The transform only runs on the
+/// caller's tenant's spans, once `transform:<name>` is listed in
+/// `ingestion.pipeline`.
+pub async fn put_transform(
+    State(state): State<AppState>,
+    Extension(auth): Extension<AuthContext>,
+    Path(name): Path<String>,
+    module: Bytes,
+) -> Result<(StatusCode, Json<TransformInfo>), ApiError> {
+    auth.require_role(Role::Admin)?;
+    let pipeline = state.ingest_pipeline.clone();
+    let registered_name = name.clone();
+    let tenant_id = auth.tenant_id;
+    let replaced = tokio::task::spawn_blocking(move || {
+        pipeline
+            .transforms()
+            .register_wasm(tenant_id, &registered_name, &module)
+    })
+    .await
+    .map_err(|e| ApiError::Internal(format!("Transform registration panicked: {}", e)))?
+    .map_err(ApiError::BadRequest)?;
+
+    let pipeline = &state.ingest_pipeline;
+    let info = pipeline
+        .transforms()
+        .list(tenant_id, pipeline.stages())
+        .into_iter()
+        .find(|t| t.name == name)
+        .ok_or_else(|| ApiError::Internal(format!("Transform '{}' vanished", name)))?;
+
+    let status = if replaced {
+        StatusCode::OK
+    } else {
+        StatusCode::CREATED
+    };
+    Ok((status, Json(info)))
+}
+
+/// DELETE /api/v1/ingestion/transforms/:name
+pub async fn delete_transform(
+    State(state): State<AppState>,
+    Extension(auth): Extension<AuthContext>,
+    Path(name): Path<String>,
+) -> Result<StatusCode, ApiError> {
+    auth.require_role(Role::Admin)?;
+    match state
+        .ingest_pipeline
+        .transforms()
+        .unregister(auth.tenant_id, &name)
+    {
+        Ok(true) => Ok(StatusCode::NO_CONTENT),
+        Ok(false) => Err(ApiError::NotFound(format!(
+            "Transform '{}' not found",
+            name
+        ))),
+        Err(e) => Err(ApiError::Internal(e)),
+    }
+}
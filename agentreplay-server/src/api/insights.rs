@@ -15,15 +15,21 @@
 
 // agentreplay-server/src/api/insights.rs
 //
-// Insights API endpoints for anomaly detection and pattern recognition
+// Insights API endpoints for anomaly detection, pattern recognition and
+// instrumentation data quality
 
 use super::query::{ApiError, AppState};
+use crate::auth::AuthContext;
+use crate::data_quality::{self, ProjectDataQuality};
+use crate::otel_genai::GenAIPayload;
 use axum::{
-    extract::{Query, State},
+    extract::{Extension, Query, State},
     Json,
 };
 use agentreplay_core::insights::{Insight, InsightConfig, InsightEngine, InsightType, Severity};
+use agentreplay_query::TenantScope;
 use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
 
 // ============================================================================
 // Request/Response Types
@@ -56,6 +62,33 @@ fn default_limit() -> usize {
     50
 }
 
+#[derive(Debug, Deserialize)]
+pub struct DataQualityQuery {
+    /// Time window in seconds (default: 3600 = 1 hour)
+    #[serde(default = "default_window_seconds")]
+    pub window_seconds: u64,
+    /// Project ID filter
+    #[serde(default)]
+    pub project_id: Option<u16>,
+    /// Maximum number of (most recent) spans to analyze
+    #[serde(default = "default_max_spans")]
+    pub max_spans: usize,
+}
+
+fn default_max_spans() -> usize {
+    10_000
+}
+
+#[derive(Debug, Serialize)]
+pub struct DataQualityResponse {
+    /// Overall data quality score 0-100 (100 = no instrumentation gaps)
+    pub score: u8,
+    pub spans_analyzed: usize,
+    pub window_seconds: u64,
+    pub projects: Vec<ProjectDataQuality>,
+    pub generated_at: u64,
+}
+
 #[derive(Debug, Serialize)]
 pub struct InsightsResponse {
     pub insights: Vec<InsightView>,
@@ -64,7 +97,7 @@ pub struct InsightsResponse {
     pub generated_at: u64,
 }
 
-#[derive(Debug, Serialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct InsightView {
     pub id: String,
     pub insight_type: String,
@@ -191,6 +224,41 @@ impl From<Insight> for InsightView {
                     "Consider prompt compression or summarization".to_string(),
                 ],
             ),
+            InsightType::ToolContractViolation {
+                tool_name,
+                violation_count,
+                sample_error,
+            } => (
+                "tool_contract_violation".to_string(),
+                vec![
+                    format!(
+                        "{} call(s) to '{}' sent arguments that break its schema",
+                        violation_count, tool_name
+                    ),
+                    format!("Latest error: {}", sample_error),
+                    "Check the tool description in the prompt or update the registered schema"
+                        .to_string(),
+                ],
+            ),
+            InsightType::DatasetDrift {
+                datasets,
+                mmd,
+                centroid_shift,
+                coverage,
+            } => (
+                "dataset_drift".to_string(),
+                vec![
+                    format!(
+                        "Only {:.0}% of recent inputs resemble a test case in {} (MMD {:.3}, centroid shift {:.3})",
+                        coverage * 100.0,
+                        datasets.join(", "),
+                        mmd,
+                        centroid_shift
+                    ),
+                    "Add the uncovered production inputs to the golden dataset".to_string(),
+                    "Re-run evals once the dataset reflects current traffic".to_string(),
+                ],
+            ),
         };
 
         InsightView {
@@ -221,6 +289,7 @@ impl From<Insight> for InsightView {
 /// Generate and return insights for the specified time window
 pub async fn get_insights(
     State(state): State<AppState>,
+    Extension(auth): Extension<AuthContext>,
     Query(query): Query<InsightsQuery>,
 ) -> Result<Json<InsightsResponse>, ApiError> {
     let config = InsightConfig::default();
@@ -241,17 +310,25 @@ pub async fn get_insights(
     // Query recent edges
     let recent_edges = state
         .db
-        .query_temporal_range(recent_start_us, now_us)
+        .scoped(TenantScope::Tenant(auth.tenant_id))
+        .range(recent_start_us, now_us)
         .map_err(|e| ApiError::Internal(e.to_string()))?;
 
     // Query baseline edges
     let baseline_edges = state
         .db
-        .query_temporal_range(baseline_start_us, recent_start_us)
+        .scoped(TenantScope::Tenant(auth.tenant_id))
+        .range(baseline_start_us, recent_start_us)
         .map_err(|e| ApiError::Internal(e.to_string()))?;
 
     // Generate insights by comparing recent vs baseline
-    let insights = engine.generate_insights_from_edges(&recent_edges, &baseline_edges);
+    let mut insights = engine.generate_insights_from_edges(&recent_edges, &baseline_edges);
+    insights.extend(state.tool_contracts.insights(recent_start_us, now_us));
+    insights.extend(
+        state
+            .drift_detector
+            .insights(auth.tenant_id, recent_start_us, now_us),
+    );
 
     // Apply filters
     let min_severity = query.min_severity.as_ref().and_then(|s| parse_severity(s));
@@ -291,6 +368,7 @@ pub async fn get_insights(
 /// Get a summary of current insights by severity
 pub async fn get_insights_summary(
     State(state): State<AppState>,
+    Extension(auth): Extension<AuthContext>,
 ) -> Result<Json<InsightsSummary>, ApiError> {
     let config = InsightConfig::default();
     let engine = InsightEngine::new(config);
@@ -308,15 +386,23 @@ pub async fn get_insights_summary(
 
     let recent_edges = state
         .db
-        .query_temporal_range(recent_start_us, now_us)
+        .scoped(TenantScope::Tenant(auth.tenant_id))
+        .range(recent_start_us, now_us)
         .map_err(|e| ApiError::Internal(e.to_string()))?;
 
     let baseline_edges = state
         .db
-        .query_temporal_range(baseline_start_us, recent_start_us)
+        .scoped(TenantScope::Tenant(auth.tenant_id))
+        .range(baseline_start_us, recent_start_us)
         .map_err(|e| ApiError::Internal(e.to_string()))?;
 
-    let insights = engine.generate_insights_from_edges(&recent_edges, &baseline_edges);
+    let mut insights = engine.generate_insights_from_edges(&recent_edges, &baseline_edges);
+    insights.extend(state.tool_contracts.insights(recent_start_us, now_us));
+    insights.extend(
+        state
+            .drift_detector
+            .insights(auth.tenant_id, recent_start_us, now_us),
+    );
 
     let mut by_severity = std::collections::HashMap::new();
     let mut by_type = std::collections::HashMap::new();
@@ -355,6 +441,82 @@ pub async fn get_insights_summary(
     }))
 }
 
+/// GET /api/v1/insights/data-quality
+///
+/// Detect instrumentation gaps per project (missing usage, orphan spans, clock
+/// skew, unmapped models, unknown attributes) with a data quality score and
+/// fix suggestions.
+pub async fn get_data_quality(
+    State(state): State<AppState>,
+    Extension(auth): Extension<AuthContext>,
+    Query(query): Query<DataQualityQuery>,
+) -> Result<Json<DataQualityResponse>, ApiError> {
+    let now_us = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|d| d.as_micros() as u64)
+        .unwrap_or(0);
+    let start_us = now_us.saturating_sub(query.window_seconds * 1_000_000);
+
+    let mut edges = state
+        .db
+        .scoped(TenantScope::Tenant(auth.tenant_id))
+        .range(start_us, now_us)
+        .map_err(|e| ApiError::Internal(e.to_string()))?;
+    if let Some(project_id) = query.project_id {
+        edges.retain(|e| e.project_id == project_id);
+    }
+    // Most recent spans first when sampling
+    edges.sort_by(|a, b| b.timestamp_us.cmp(&a.timestamp_us));
+    edges.truncate(query.max_spans);
+
+    let edge_ids: Vec<u128> = edges.iter().map(|e| e.edge_id).collect();
+    let mut payloads: HashMap<u128, GenAIPayload> = state
+        .db
+        .get_payloads_batch(&edge_ids)
+        .map_err(|e| ApiError::Internal(e.to_string()))?
+        .into_iter()
+        .filter_map(|(id, bytes)| {
+            let payload = serde_json::from_slice::<GenAIPayload>(&bytes?).ok()?;
+            Some((id, payload))
+        })
+        .collect();
+
+    let spans: Vec<_> = edges
+        .into_iter()
+        .map(|edge| {
+            let payload = payloads.remove(&edge.edge_id);
+            (edge, payload)
+        })
+        .collect();
+
+    // Parents outside the window may still exist in storage
+    let found_parents: HashSet<u128> = data_quality::missing_parents(&spans)
+        .into_iter()
+        .filter(|id| matches!(state.db.get(*id), Ok(Some(_))))
+        .collect();
+
+    let projects = data_quality::analyze(&spans, |id| found_parents.contains(&id));
+    let spans_analyzed = spans.len();
+    let score = if spans_analyzed == 0 {
+        100
+    } else {
+        // Span-weighted average across projects
+        (projects
+            .iter()
+            .map(|p| p.score as usize * p.spans_analyzed)
+            .sum::<usize>()
+            / spans_analyzed) as u8
+    };
+
+    Ok(Json(DataQualityResponse {
+        score,
+        spans_analyzed,
+        window_seconds: query.window_seconds,
+        projects,
+        generated_at: now_us,
+    }))
+}
+
 #[derive(Debug, Serialize)]
 pub struct InsightsSummary {
     pub total_insights: usize,
@@ -398,6 +560,8 @@ fn insight_type_name(insight: &InsightType) -> String {
         InsightType::PerformanceRegression { .. } => "performance_regression",
         InsightType::TrafficAnomaly { .. } => "traffic_anomaly",
         InsightType::TokenUsageSpike { .. } => "token_usage_spike",
+        InsightType::ToolContractViolation { .. } => "tool_contract_violation",
+        InsightType::DatasetDrift { .. } => "dataset_drift",
     }
     .to_string()
 }
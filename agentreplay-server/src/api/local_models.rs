@@ -0,0 +1,136 @@
+// Copyright 2025 AgentReplay (https://github.com/agentreplay)
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program. If not, see <https://www.gnu.org/licenses/>.
+
+//! Local models served by Ollama: listing, details and pulls
+
+use super::query::{ApiError, AppState};
+use crate::auth::AuthContext;
+use crate::jobs::Job;
+use crate::llm::local::{LocalModel, LocalModels, ModelInfo, SystemResources};
+use axum::{
+    extract::{Extension, Path, State},
+    http::StatusCode,
+    Json,
+};
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+
+/// Installed models and the machine they run on
+#[derive(Debug, Serialize)]
+pub struct LocalModelsResponse {
+    pub base_url: String,
+    /// Whether Ollama answered; the system is reported either way
+    pub reachable: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+    pub models: Vec<LocalModel>,
+    pub system: SystemResources,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct PullModelRequest {
+    /// e.g. "llama3.1:8b"
+    pub model: String,
+}
+
+fn local_models(state: &AppState) -> Result<Arc<LocalModels>, ApiError> {
+    state
+        .llm_manager
+        .as_ref()
+        .and_then(|manager| manager.local_models())
+        .ok_or_else(|| {
+            ApiError::BadRequest(
+                "No local model server configured; set OLLAMA_BASE_URL".to_string(),
+            )
+        })
+}
+
+/// GET /api/v1/local-models
+///
+/// Each model says whether it fits in GPU memory, or in system memory when
+/// no GPU is detected.
+pub async fn list_local_models(
+    State(state): State<AppState>,
+) -> Result<Json<LocalModelsResponse>, ApiError> {
+    let local = local_models(&state)?;
+    let system = tokio::task::spawn_blocking(SystemResources::detect)
+        .await
+        .map_err(|e| ApiError::Internal(e.to_string()))?;
+
+    let (models, error) = match local.list().await {
+        Ok(mut models) => {
+            for model in &mut models {
+                model.fits_in_memory = Some(system.fits(model.size_bytes));
+            }
+            (models, None)
+        }
+        Err(e) => (Vec::new(), Some(e.to_string())),
+    };
+    Ok(Json(LocalModelsResponse {
+        base_url: local.base_url().to_string(),
+        reachable: error.is_none(),
+        error,
+        models,
+        system,
+    }))
+}
+
+/// GET /api/v1/local-models/:name - details and context length
+pub async fn get_local_model(
+    State(state): State<AppState>,
+    Path(name): Path<String>,
+) -> Result<Json<ModelInfo>, ApiError> {
+    let local = local_models(&state)?;
+    local
+        .show(&name)
+        .await
+        .map(Json)
+        .map_err(|e| ApiError::NotFound(format!("Local model {}: {}", name, e)))
+}
+
+/// POST /api/v1/local-models/pull
+///
+/// Downloads the model as a job, to follow at `/api/v1/jobs/:id`; its
+/// progress is the fraction of bytes pulled and its message Ollama's status.
+pub async fn pull_local_model(
+    State(state): State<AppState>,
+    Extension(auth): Extension<AuthContext>,
+    Json(req): Json<PullModelRequest>,
+) -> Result<(StatusCode, Json<Job>), ApiError> {
+    let local = local_models(&state)?;
+    let model = req.model.trim().to_string();
+    if model.is_empty() {
+        return Err(ApiError::BadRequest("model is required".to_string()));
+    }
+
+    let job = state.jobs.submit(
+        "local_model_pull",
+        auth.tenant_id,
+        format!("Pull local model {}", model),
+        move |ctx| async move {
+            let pull = local.pull(&model, |progress| {
+                ctx.progress(progress.fraction(), Some(progress.status.clone()));
+            });
+            let progress = tokio::select! {
+                result = pull => result.map_err(|e| e.to_string())?,
+                _ = ctx.cancelled() => return Err(format!("Pull of {} cancelled", model)),
+            };
+            serde_json::to_value(progress)
+                .map(Some)
+                .map_err(|e| e.to_string())
+        },
+    );
+    Ok((StatusCode::ACCEPTED, Json(job)))
+}
@@ -13,6 +13,8 @@
 // You should have received a copy of the GNU Affero General Public License
 // along with this program. If not, see <https://www.gnu.org/licenses/>.
 
+use std::collections::BTreeMap;
+use std::sync::Arc;
 use std::time::{SystemTime, UNIX_EPOCH};
 
 use axum::{
@@ -20,8 +22,11 @@ use axum::{
     Json,
 };
 use agentreplay_core::{AgentFlowEdge, SpanType};
+use agentreplay_query::Agentreplay;
+use agentreplay_storage::DDSketch;
 use serde::{Deserialize, Serialize};
 
+use crate::scaling::run_query;
 use crate::{api::query::ApiError, api::AppState, auth::AuthContext};
 
 const DEFAULT_LOOKBACK_US: u64 = 3_600_000_000; // 1 hour
@@ -37,6 +42,8 @@ pub struct TimeseriesParams {
     pub environment: Option<String>,
     /// Filter by agent ID
     pub agent_id: Option<u64>,
+    /// Restrict to one project (per-project storage only)
+    pub project_id: Option<u16>,
     /// Group metrics by: "agent", "model", or none for aggregate
     pub group_by: Option<String>,
 }
@@ -180,6 +187,11 @@ fn percentile_val(values: &[u32], percentile: f64) -> f64 {
     values[idx] as f64
 }
 
+/// One timeseries bucket
+///
+/// Latencies go into a DDSketch (1% relative error) rather than a list of raw
+/// durations, so buckets built from different shards merge exactly as if
+/// all edges had been recorded into one.
 #[derive(Clone, Default)]
 struct BucketAccum {
     request_count: u64,
@@ -187,7 +199,96 @@ struct BucketAccum {
     total_cost: f64,
     total_duration_us: u64,
     error_count: u64,
-    durations_us: Vec<u32>,
+    latency_us: DDSketch,
+}
+
+impl BucketAccum {
+    fn record(&mut self, edge: &AgentFlowEdge) {
+        self.request_count += 1;
+        self.total_tokens += edge.token_count as u64;
+        self.total_cost += estimate_edge_cost(edge);
+        self.total_duration_us += edge.duration_us as u64;
+        self.latency_us.add(edge.duration_us as f64);
+
+        if edge.is_deleted() || matches!(edge.get_span_type(), SpanType::Error) {
+            self.error_count += 1;
+        }
+    }
+
+    fn merge(&mut self, other: &BucketAccum) {
+        self.request_count += other.request_count;
+        self.total_tokens += other.total_tokens;
+        self.total_cost += other.total_cost;
+        self.total_duration_us += other.total_duration_us;
+        self.error_count += other.error_count;
+        self.latency_us.merge(&other.latency_us);
+    }
+
+    fn latency_ms(&self, quantile: f64) -> f64 {
+        self.latency_us.quantile(quantile) / 1_000.0
+    }
+}
+
+/// Bucketed metrics of one shard (project database), optionally per agent
+struct TimeseriesAccum {
+    start_ts: u64,
+    bucket_duration_us: u64,
+    aggregate: Vec<BucketAccum>,
+    /// Present when grouping by agent
+    by_agent: Option<BTreeMap<u64, Vec<BucketAccum>>>,
+}
+
+impl TimeseriesAccum {
+    fn new(
+        start_ts: u64,
+        bucket_duration_us: u64,
+        bucket_count: usize,
+        group_by_agent: bool,
+    ) -> Self {
+        Self {
+            start_ts,
+            bucket_duration_us,
+            aggregate: vec![BucketAccum::default(); bucket_count],
+            by_agent: group_by_agent.then(BTreeMap::new),
+        }
+    }
+
+    fn record(&mut self, edge: &AgentFlowEdge) {
+        if edge.timestamp_us < self.start_ts {
+            return;
+        }
+        let bucket_count = self.aggregate.len();
+        let bucket_idx = (((edge.timestamp_us - self.start_ts) / self.bucket_duration_us) as usize)
+            .min(bucket_count - 1);
+
+        self.aggregate[bucket_idx].record(edge);
+        if let Some(by_agent) = &mut self.by_agent {
+            by_agent
+                .entry(edge.agent_id)
+                .or_insert_with(|| vec![BucketAccum::default(); bucket_count])[bucket_idx]
+                .record(edge);
+        }
+    }
+
+    fn merge(&mut self, other: &TimeseriesAccum) {
+        merge_buckets(&mut self.aggregate, &other.aggregate);
+        if let (Some(by_agent), Some(other_by_agent)) = (&mut self.by_agent, &other.by_agent) {
+            for (agent_id, buckets) in other_by_agent {
+                match by_agent.get_mut(agent_id) {
+                    Some(existing) => merge_buckets(existing, buckets),
+                    None => {
+                        by_agent.insert(*agent_id, buckets.clone());
+                    }
+                }
+            }
+        }
+    }
+}
+
+fn merge_buckets(into: &mut [BucketAccum], from: &[BucketAccum]) {
+    for (bucket, other) in into.iter_mut().zip(from) {
+        bucket.merge(other);
+    }
 }
 
 /// GET /api/v1/metrics/timeseries
@@ -228,73 +329,92 @@ pub async fn get_timeseries_metrics(
         );
     }
 
-    let mut edges = state
-        .db
-        .query_temporal_range_for_tenant(start_ts, end_ts, auth.tenant_id)
-        .map_err(|e| ApiError::Internal(e.to_string()))?;
-
-    // Apply environment filter if requested
-    if let Some(env_str) = &params.environment {
-        use agentreplay_core::Environment;
-        let target_env = Environment::parse(env_str);
-        let target_env_u8 = target_env as u8;
-        edges.retain(|e| e.environment == target_env_u8);
-    }
-
-    // Apply agent_id filter if requested
-    if let Some(agent_id) = params.agent_id {
-        edges.retain(|e| e.agent_id == agent_id);
-    }
-
-    // Check if grouping is requested
-    let group_by = params.group_by.as_deref();
-
-    match group_by {
-        Some("agent") => {
-            // Group by agent_id
-            let (data, groups) = compute_grouped_metrics_by_agent(
-                &edges,
-                start_ts,
-                bucket_duration_us,
-                bucket_count,
-                &state.agent_registry,
-            );
-
-            Ok(Json(TimeseriesResponse {
-                data,
-                metadata: TimeseriesMetadata {
-                    start_ts,
-                    end_ts,
-                    interval_seconds,
-                    bucket_count,
-                },
-                groups: Some(groups),
-            }))
-        }
+    let group_by_agent = match params.group_by.as_deref() {
+        Some("agent") => true,
         Some("model") => {
             // TODO: Group by model (requires reading payload attributes)
-            // For now, return error
-            Err(ApiError::BadRequest(
+            return Err(ApiError::BadRequest(
                 "group_by=model not yet implemented. Use group_by=agent instead.".into(),
-            ))
+            ));
         }
-        _ => {
-            // No grouping - compute aggregate metrics
-            let data =
-                compute_aggregate_metrics(&edges, start_ts, bucket_duration_us, bucket_count);
-
-            Ok(Json(TimeseriesResponse {
-                data,
-                metadata: TimeseriesMetadata {
-                    start_ts,
-                    end_ts,
-                    interval_seconds,
-                    bucket_count,
-                },
-                groups: None,
-            }))
+        _ => false,
+    };
+
+    // Each shard is bucketed on its own and the sketches merged afterwards
+    let shards = project_shards(&state, params.project_id)?;
+    let environment = params
+        .environment
+        .as_deref()
+        .map(|env| agentreplay_core::Environment::parse(env) as u8);
+    let agent_id = params.agent_id;
+    let tenant_id = auth.tenant_id;
+    let accum = run_query(&state, move || -> Result<TimeseriesAccum, ApiError> {
+        let mut total =
+            TimeseriesAccum::new(start_ts, bucket_duration_us, bucket_count, group_by_agent);
+        for db in shards {
+            let edges = db
+                .query_temporal_range_for_tenant(start_ts, end_ts, tenant_id)
+                .map_err(|e| ApiError::Internal(e.to_string()))?;
+            let mut shard =
+                TimeseriesAccum::new(start_ts, bucket_duration_us, bucket_count, group_by_agent);
+            for edge in edges.iter().filter(|e| {
+                environment.is_none_or(|env| e.environment == env)
+                    && agent_id.is_none_or(|agent_id| e.agent_id == agent_id)
+            }) {
+                shard.record(edge);
+            }
+            total.merge(&shard);
         }
-    }
+        Ok(total)
+    })
+    .await
+    .map_err(|e| ApiError::Internal(format!("Timeseries task panicked: {}", e)))??;
+
+    let groups = accum.by_agent.map(|by_agent| {
+        by_agent
+            .into_iter()
+            .map(|(agent_id, buckets)| GroupedMetrics {
+                group_key: agent_id.to_string(),
+                group_name: state.agent_registry.get_display_name(agent_id),
+                data: buckets_to_timeseries_data(buckets, start_ts, bucket_duration_us),
+            })
+            .collect()
+    });
+
+    Ok(Json(TimeseriesResponse {
+        data: buckets_to_timeseries_data(accum.aggregate, start_ts, bucket_duration_us),
+        metadata: TimeseriesMetadata {
+            start_ts,
+            end_ts,
+            interval_seconds,
+            bucket_count,
+        },
+        groups,
+    }))
+}
+
+/// Databases holding the tenant's edges: every project database (or only
+/// `project_id`'s) with per-project storage, the single database otherwise
+pub(crate) fn project_shards(
+    state: &AppState,
+    project_id: Option<u16>,
+) -> Result<Vec<Arc<Agentreplay>>, ApiError> {
+    let Some(ref pm) = state.project_manager else {
+        return Ok(vec![state.db.clone()]);
+    };
+    let project_ids = match project_id {
+        Some(project_id) => vec![project_id],
+        None => pm
+            .discover_projects()
+            .map_err(|e| ApiError::Internal(e.to_string()))?,
+    };
+    project_ids
+        .into_iter()
+        .map(|project_id| {
+            pm.get_or_open_project(project_id)
+                .map_err(|e| ApiError::Internal(e.to_string()))
+        })
+        .collect()
 }
 
 fn percentile_ms(values: &[u32], percentile: f64) -> f64 {
@@ -319,86 +439,6 @@ fn current_timestamp_us() -> u64 {
         .unwrap_or(0)
 }
 
-/// Compute aggregate metrics (no grouping)
-fn compute_aggregate_metrics(
-    edges: &[AgentFlowEdge],
-    start_ts: u64,
-    bucket_duration_us: u64,
-    bucket_count: usize,
-) -> Vec<TimeseriesData> {
-    let mut buckets = vec![BucketAccum::default(); bucket_count];
-
-    for edge in edges {
-        if edge.timestamp_us < start_ts {
-            continue;
-        }
-        let mut bucket_idx = ((edge.timestamp_us - start_ts) / bucket_duration_us) as usize;
-        if bucket_idx >= bucket_count {
-            bucket_idx = bucket_count - 1;
-        }
-
-        let bucket = &mut buckets[bucket_idx];
-        bucket.request_count += 1;
-        bucket.total_tokens += edge.token_count as u64;
-        bucket.total_cost += estimate_edge_cost(edge);
-        bucket.total_duration_us += edge.duration_us as u64;
-        bucket.durations_us.push(edge.duration_us);
-
-        if edge.is_deleted() || matches!(edge.get_span_type(), SpanType::Error) {
-            bucket.error_count += 1;
-        }
-    }
-
-    buckets_to_timeseries_data(buckets, start_ts, bucket_duration_us)
-}
-
-/// Compute grouped metrics by agent_id
-fn compute_grouped_metrics_by_agent(
-    edges: &[AgentFlowEdge],
-    start_ts: u64,
-    bucket_duration_us: u64,
-    bucket_count: usize,
-    agent_registry: &crate::agent_registry::AgentRegistry,
-) -> (Vec<TimeseriesData>, Vec<GroupedMetrics>) {
-    use std::collections::HashMap;
-
-    // Group edges by agent_id
-    let mut agent_edges: HashMap<u64, Vec<&AgentFlowEdge>> = HashMap::new();
-    for edge in edges {
-        agent_edges.entry(edge.agent_id).or_default().push(edge);
-    }
-
-    // Compute aggregate (total across all agents)
-    let aggregate_data =
-        compute_aggregate_metrics(edges, start_ts, bucket_duration_us, bucket_count);
-
-    // Compute per-agent metrics
-    let mut groups = Vec::new();
-    let mut agent_ids: Vec<u64> = agent_edges.keys().copied().collect();
-    agent_ids.sort_unstable();
-
-    for agent_id in agent_ids {
-        let agent_edges = agent_edges.get(&agent_id).unwrap();
-
-        // Copy edges for processing (needed since compute_aggregate_metrics takes &[])
-        let edges_vec: Vec<AgentFlowEdge> = agent_edges.iter().map(|&e| *e).collect();
-
-        let data =
-            compute_aggregate_metrics(&edges_vec, start_ts, bucket_duration_us, bucket_count);
-
-        let group_key = agent_id.to_string();
-        let group_name = agent_registry.get_display_name(agent_id);
-
-        groups.push(GroupedMetrics {
-            group_key,
-            group_name,
-            data,
-        });
-    }
-
-    (aggregate_data, groups)
-}
-
 /// Convert bucket accumulators to TimeseriesData
 fn buckets_to_timeseries_data(
     buckets: Vec<BucketAccum>,
@@ -414,9 +454,6 @@ fn buckets_to_timeseries_data(
             0.0
         };
 
-        let mut durations = bucket.durations_us;
-        durations.sort_unstable();
-
         data.push(TimeseriesData {
             timestamp,
             request_count: bucket.request_count,
@@ -424,11 +461,65 @@ fn buckets_to_timeseries_data(
             total_cost: bucket.total_cost,
             avg_duration,
             error_count: bucket.error_count,
-            p50_duration: percentile_ms(&durations, 50.0),
-            p90_duration: percentile_ms(&durations, 90.0),
-            p95_duration: percentile_ms(&durations, 95.0),
-            p99_duration: percentile_ms(&durations, 99.0),
+            p50_duration: bucket.latency_ms(0.50),
+            p90_duration: bucket.latency_ms(0.90),
+            p95_duration: bucket.latency_ms(0.95),
+            p99_duration: bucket.latency_ms(0.99),
         });
     }
     data
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn edge(agent_id: u64, timestamp_us: u64, duration_us: u32) -> AgentFlowEdge {
+        let mut edge = AgentFlowEdge::new(1, 0, agent_id, 1, SpanType::Root, 0);
+        edge.timestamp_us = timestamp_us;
+        edge.duration_us = duration_us;
+        edge
+    }
+
+    #[test]
+    fn test_shard_sketches_merge() {
+        // Two shards with disjoint latency ranges: averaging per-shard
+        // percentiles would put p50 at ~50ms, the merged sketch at ~10ms
+        let mut fast = TimeseriesAccum::new(0, 1_000_000, 2, true);
+        let mut slow = TimeseriesAccum::new(0, 1_000_000, 2, true);
+        let mut single = TimeseriesAccum::new(0, 1_000_000, 2, true);
+        for i in 0..90u32 {
+            let e = edge(1, 10, 10_000 + i);
+            fast.record(&e);
+            single.record(&e);
+        }
+        for i in 0..10u32 {
+            let e = edge(2, 20, 100_000 + i);
+            slow.record(&e);
+            single.record(&e);
+        }
+        // Past the window end lands in the last bucket
+        let late = edge(2, 5_000_000, 1_000);
+        slow.record(&late);
+        single.record(&late);
+
+        let mut merged = TimeseriesAccum::new(0, 1_000_000, 2, true);
+        merged.merge(&fast);
+        merged.merge(&slow);
+
+        let bucket = &merged.aggregate[0];
+        assert_eq!(bucket.request_count, 100);
+        assert!((bucket.latency_ms(0.50) - 10.0).abs() < 0.2);
+        assert!((bucket.latency_ms(0.99) - 100.0).abs() < 2.0);
+        assert_eq!(
+            bucket.latency_ms(0.90),
+            single.aggregate[0].latency_ms(0.90)
+        );
+        assert_eq!(merged.aggregate[1].request_count, 1);
+
+        let by_agent = merged.by_agent.unwrap();
+        assert_eq!(by_agent[&1][0].request_count, 90);
+        assert_eq!(by_agent[&2][0].request_count, 10);
+        assert_eq!(by_agent[&2][1].request_count, 1);
+    }
+}
@@ -15,15 +15,20 @@
 
 pub mod admin;
 pub mod agents;
+pub mod aggregate;
 pub mod analytics;
+pub mod annotation_queues;
 pub mod backup;
 pub mod budget_alerts;
 pub mod chat;
 pub mod compliance;
+pub mod confidence;
 pub mod converters;
 pub mod cost;
+pub mod dataset_formats;
 pub mod debug;
 pub mod detailed_trace;
+pub mod error;
 pub mod eval_datasets;
 pub mod eval_trace;
 pub mod eval_pipeline;
@@ -31,24 +36,37 @@ pub mod eval_runs;
 pub mod evals;
 pub mod evaluate;
 pub mod experiments;
+pub mod explain;
+pub mod exposition;
 pub mod feedback;
 pub mod flywheel;
 pub mod git_versioning;
 pub mod graph;
 pub mod health;
+pub mod hydration;
+pub mod import;
 pub mod ingest;
+pub mod ingest_pipeline;
 pub mod insights;
+pub mod local_models;
 pub mod memory;
 pub mod metrics;
+pub mod nl_query;
 pub mod payload_extractors;
+pub mod project_bundle;
+pub mod project_templates;
 pub mod projects;
+pub mod prompt_cache;
 pub mod prompts;
 pub mod query;
 pub mod realtime;
 pub mod retention;
+pub mod scripts;
 pub mod search;
 pub mod sessions;
 pub mod storage_debug;
+pub mod trace_export;
+pub mod trace_listing;
 pub mod views;
 
 pub use agents::*;
@@ -119,7 +137,8 @@ pub use compliance::{
     // Note: DeleteResponse also exists here but we use the one from prompts module
 };
 pub use converters::{
-    convert_otel_span_to_edge, ConversionError, IngestResponse, OtelSpan, OtelSpanBatch,
+    convert_otel_span_to_edge, otel_span_links, ConversionError, IngestResponse, OtelSpan,
+    OtelSpanBatch,
 };
 pub use cost::*;
 pub use detailed_trace::*;
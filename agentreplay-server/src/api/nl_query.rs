@@ -0,0 +1,669 @@
+// Copyright 2025 AgentReplay (https://github.com/agentreplay)
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program. If not, see <https://www.gnu.org/licenses/>.
+
+//! Natural language trace queries
+//!
+//! `POST /api/v1/query/nl` translates a question such as "show me expensive
+//! gpt-4 failures yesterday" into [`QueryFilters`] with [`NLQueryParser`],
+//! optionally lets a configured LLM provider refine them, runs them over the
+//! caller's spans and returns the parsed query, the filters and the matches.
+//!
+//! `POST /api/v1/query/nl/feedback` records whether a translation was right,
+//! with corrected filters when it was not. Feedback is appended to
+//! [`NL_QUERY_FEEDBACK_FILE`] in the data directory and listed by
+//! `GET /api/v1/query/nl/feedback` so parser patterns can be improved from
+//! real questions.
+
+use std::collections::VecDeque;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+use agentreplay_core::clock::now_us;
+use agentreplay_core::AgentFlowEdge;
+use agentreplay_query::{NLQueryParser, ParsedQuery, QueryFilters};
+use axum::{
+    extract::{Query, State},
+    Extension, Json,
+};
+use parking_lot::Mutex;
+use serde::{Deserialize, Serialize};
+use tracing::warn;
+
+use crate::access_policy::AccessFilter;
+use crate::api::aggregate::{resolve_span, Needs};
+use crate::api::query::TraceView;
+use crate::api::{ApiError, AppState};
+use crate::auth::AuthContext;
+use crate::llm::ChatMessage;
+use crate::scaling::run_query;
+
+/// Feedback log file in the data directory
+pub const NL_QUERY_FEEDBACK_FILE: &str = "nl_query_feedback.jsonl";
+
+const DEFAULT_LOOKBACK_US: u64 = 24 * 3_600_000_000; // 24 hours
+const MAX_RANGE_US: u64 = 365 * 24 * 3_600_000_000;
+const MAX_QUERY_LEN: usize = 2000;
+const MAX_COMMENT_LEN: usize = 4000;
+const MAX_LIMIT: usize = 1000;
+/// Feedback entries kept in memory; older ones remain in the log file
+const MAX_FEEDBACK_IN_MEMORY: usize = 10_000;
+
+#[derive(Debug, Deserialize)]
+pub struct NlQueryRequest {
+    pub query: String,
+    /// Ask an LLM provider to refine the parser's filters
+    #[serde(default)]
+    pub refine: bool,
+    /// Provider for refinement; defaults to the first configured one
+    #[serde(default)]
+    pub provider: Option<String>,
+    #[serde(default)]
+    pub model: Option<String>,
+    #[serde(default = "default_limit")]
+    pub limit: usize,
+    /// Restrict the query to a project regardless of the translation
+    #[serde(default)]
+    pub project_id: Option<u16>,
+}
+
+fn default_limit() -> usize {
+    50
+}
+
+/// Outcome of LLM refinement
+#[derive(Debug, Serialize)]
+pub struct Refinement {
+    pub provider: String,
+    /// Whether the refined filters replaced the parser's
+    pub applied: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct NlQueryResponse {
+    pub parsed: ParsedQuery,
+    /// Filters the results were selected with
+    pub filters: QueryFilters,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub refinement: Option<Refinement>,
+    pub start_ts: u64,
+    pub end_ts: u64,
+    pub total_matched: usize,
+    /// Newest matches first, up to `limit`
+    pub traces: Vec<TraceView>,
+}
+
+/// POST /api/v1/query/nl
+pub async fn nl_query(
+    State(state): State<AppState>,
+    Extension(auth): Extension<AuthContext>,
+    Extension(access): Extension<AccessFilter>,
+    Json(request): Json<NlQueryRequest>,
+) -> Result<Json<NlQueryResponse>, ApiError> {
+    let query = request.query.trim();
+    if query.is_empty() || query.len() > MAX_QUERY_LEN {
+        return Err(ApiError::BadRequest(format!(
+            "query must be between 1 and {} characters",
+            MAX_QUERY_LEN
+        )));
+    }
+    let limit = request.limit.clamp(1, MAX_LIMIT);
+
+    let parser = NLQueryParser::new();
+    let parsed = parser.parse(query);
+    let mut filters = parser.to_semantic_query(&parsed).filters;
+
+    let refinement = if request.refine {
+        let (refinement, refined) = refine_filters(&state, &request, &filters).await?;
+        if let Some(refined) = refined {
+            filters = refined;
+        }
+        Some(refinement)
+    } else {
+        None
+    };
+    if request.project_id.is_some() {
+        filters.project_id = request.project_id;
+    }
+
+    let (start_ts, end_ts) = match filters.time_range {
+        Some(ref range) => (range.start_us, range.end_us),
+        None => {
+            let end_ts = now_us();
+            (end_ts.saturating_sub(DEFAULT_LOOKBACK_US), end_ts)
+        }
+    };
+
+    let shards = super::metrics::project_shards(&state, filters.project_id)?;
+    let agent_registry = state.agent_registry.clone();
+    let tenant_id = auth.tenant_id;
+    let scan_filters = filters.clone();
+    let (total_matched, traces) = run_query(
+        &state,
+        move || -> Result<(usize, Vec<TraceView>), ApiError> {
+            let needs = Needs {
+                model: scan_filters.models.is_some(),
+                prompt_version: false,
+                cost: scan_filters.min_cost_usd.is_some() || scan_filters.max_cost_usd.is_some(),
+            };
+            let mut matched: Vec<(AgentFlowEdge, Option<String>, Option<f64>)> = Vec::new();
+            for db in shards {
+                let edges = db
+                    .query_temporal_range_for_tenant(start_ts, end_ts, tenant_id)
+                    .map_err(|e| ApiError::Internal(e.to_string()))?;
+                let edges = readable_matches(edges, &scan_filters, &access, |edge| {
+                    db.get_payload(edge.edge_id).ok().flatten()
+                });
+                for edge in edges {
+                    if !scan_filters.needs_payload() {
+                        matched.push((edge, None, None));
+                        continue;
+                    }
+                    let span = resolve_span(&db, &edge, needs);
+                    if scan_filters.matches_payload(span.model.as_deref(), span.cost) {
+                        let cost = needs.cost.then_some(span.cost);
+                        matched.push((edge, span.model, cost));
+                    }
+                }
+            }
+
+            let total = matched.len();
+            matched.sort_by(|a, b| {
+                (b.0.timestamp_us, b.0.edge_id).cmp(&(a.0.timestamp_us, a.0.edge_id))
+            });
+            let traces = matched
+                .into_iter()
+                .take(limit)
+                .map(|(edge, model, cost)| {
+                    let agent_name = agent_registry.get_display_name(edge.agent_id);
+                    let mut view = TraceView::from(edge);
+                    view.agent_name = agent_name;
+                    view.model = model;
+                    view.cost = cost;
+                    view
+                })
+                .collect();
+            Ok((total, traces))
+        },
+    )
+    .await
+    .map_err(|e| ApiError::Internal(format!("NL query task panicked: {}", e)))??;
+
+    Ok(Json(NlQueryResponse {
+        parsed,
+        filters,
+        refinement,
+        start_ts,
+        end_ts,
+        total_matched,
+        traces,
+    }))
+}
+
+/// Spans the filters' metadata conditions match and the caller may read
+///
+/// Access policies are checked after the cheaper filters so payloads are
+/// only loaded for candidate matches.
+fn readable_matches(
+    edges: Vec<AgentFlowEdge>,
+    filters: &QueryFilters,
+    access: &AccessFilter,
+    load: impl Fn(&AgentFlowEdge) -> Option<Vec<u8>>,
+) -> Vec<AgentFlowEdge> {
+    let mut edges: Vec<AgentFlowEdge> = edges.into_iter().filter(|e| filters.matches(e)).collect();
+    access.retain_with(&mut edges, load);
+    edges
+}
+
+/// Ask an LLM provider to correct the parser's filters
+///
+/// Provider failures and unusable answers are reported in the
+/// [`Refinement`] and leave the parser's filters in place.
+async fn refine_filters(
+    state: &AppState,
+    request: &NlQueryRequest,
+    filters: &QueryFilters,
+) -> Result<(Refinement, Option<QueryFilters>), ApiError> {
+    let llm = state.llm_manager.as_ref().ok_or_else(|| {
+        ApiError::BadRequest("refine requires an LLM provider to be configured".to_string())
+    })?;
+    let provider = match request.provider.clone() {
+        Some(provider) => provider,
+        None => {
+            let mut providers: Vec<String> =
+                llm.list_providers().into_iter().map(|p| p.id).collect();
+            providers.sort();
+            providers.into_iter().next().ok_or_else(|| {
+                ApiError::BadRequest("refine requires an LLM provider to be configured".to_string())
+            })?
+        }
+    };
+
+    let messages = vec![
+        ChatMessage {
+            role: "system".to_string(),
+            content: "You translate questions about LLM agent traces into JSON trace filters. Output only valid JSON.".to_string(),
+        },
+        ChatMessage {
+            role: "user".to_string(),
+            content: build_refinement_prompt(&request.query, filters, now_us()),
+        },
+    ];
+    let outcome = match llm
+        .chat(&provider, request.model.clone(), messages, 0, 0)
+        .await
+    {
+        Ok(response) => parse_refined_filters(&response.content),
+        Err(e) => Err(format!("LLM call failed: {}", e)),
+    };
+
+    Ok(match outcome {
+        Ok(refined) => (
+            Refinement {
+                provider,
+                applied: true,
+                error: None,
+            },
+            Some(refined),
+        ),
+        Err(error) => {
+            warn!(
+                "NL query refinement failed, keeping parsed filters: {}",
+                error
+            );
+            (
+                Refinement {
+                    provider,
+                    applied: false,
+                    error: Some(error),
+                },
+                None,
+            )
+        }
+    })
+}
+
+fn build_refinement_prompt(query: &str, filters: &QueryFilters, now_us: u64) -> String {
+    format!(
+        r#"Translate the question into trace filters.
+
+## FIELDS (omit any that do not apply)
+- time_range: {{"start_us": <u64>, "end_us": <u64>}} in Unix microseconds
+- agent_ids, session_ids: arrays of u64
+- has_error: true for failures or errors
+- project_id: u16
+- models: array of model name substrings, e.g. ["gpt-4"]
+- min_cost_usd, max_cost_usd: span cost in USD
+- min_duration_ms, max_duration_ms: span duration in milliseconds
+- min_tokens, max_tokens: token counts
+
+## CURRENT TIME
+{now_us}
+
+## DRAFT FROM THE RULE-BASED PARSER
+{draft}
+
+## QUESTION
+{query}
+
+Return only the corrected filters as one JSON object."#,
+        now_us = now_us,
+        draft = serde_json::to_string(filters).unwrap_or_default(),
+        query = query
+    )
+}
+
+/// Filters from an LLM answer, which may wrap the JSON in prose or fences
+fn parse_refined_filters(content: &str) -> Result<QueryFilters, String> {
+    let start = content.find('{');
+    let end = content.rfind('}');
+    let json = match (start, end) {
+        (Some(start), Some(end)) if start < end => &content[start..=end],
+        _ => return Err("LLM answer contains no JSON object".to_string()),
+    };
+    let filters: QueryFilters = serde_json::from_str(json)
+        .map_err(|e| format!("LLM answer is not a valid filter object: {}", e))?;
+    validate_filters(&filters)?;
+    Ok(filters)
+}
+
+fn validate_filters(filters: &QueryFilters) -> Result<(), String> {
+    if let Some(ref range) = filters.time_range {
+        if range.end_us <= range.start_us {
+            return Err("time_range.end_us must be greater than start_us".to_string());
+        }
+        if range.end_us - range.start_us > MAX_RANGE_US {
+            return Err("time_range cannot exceed 365 days".to_string());
+        }
+    }
+    let bounds = [
+        ("cost_usd", filters.min_cost_usd, filters.max_cost_usd),
+        (
+            "duration_ms",
+            filters.min_duration_ms,
+            filters.max_duration_ms,
+        ),
+        (
+            "tokens",
+            filters.min_tokens.map(|t| t as f64),
+            filters.max_tokens.map(|t| t as f64),
+        ),
+    ];
+    for (field, min, max) in bounds {
+        if min.is_some_and(|min| min < 0.0) || max.is_some_and(|max| max < 0.0) {
+            return Err(format!("{} bounds must not be negative", field));
+        }
+        if let (Some(min), Some(max)) = (min, max) {
+            if min > max {
+                return Err(format!("min_{} exceeds max_{}", field, field));
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Request body for translation feedback
+#[derive(Debug, Deserialize)]
+pub struct NlFeedbackRequest {
+    pub query: String,
+    /// Filters returned for `query`
+    #[serde(default)]
+    pub filters: QueryFilters,
+    /// Whether the translation matched the question
+    pub correct: bool,
+    /// What the filters should have been
+    #[serde(default)]
+    pub corrected_filters: Option<QueryFilters>,
+    #[serde(default)]
+    pub comment: Option<String>,
+}
+
+/// One recorded piece of translation feedback
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct NlFeedbackEntry {
+    pub timestamp_us: u64,
+    pub tenant_id: u64,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub user_id: Option<String>,
+    pub query: String,
+    pub filters: QueryFilters,
+    pub correct: bool,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub corrected_filters: Option<QueryFilters>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub comment: Option<String>,
+}
+
+/// Translation feedback, appended to a JSON lines file when persistent
+pub struct NlQueryFeedbackLog {
+    storage_path: Option<PathBuf>,
+    entries: Mutex<VecDeque<NlFeedbackEntry>>,
+}
+
+impl Default for NlQueryFeedbackLog {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl NlQueryFeedbackLog {
+    /// In-memory log; feedback is lost on restart
+    pub fn new() -> Self {
+        Self {
+            storage_path: None,
+            entries: Mutex::new(VecDeque::new()),
+        }
+    }
+
+    /// Log appending to `path`, loading the most recent entries already saved
+    pub fn with_storage(path: impl AsRef<Path>) -> Self {
+        let path = path.as_ref().to_path_buf();
+        let mut entries = VecDeque::new();
+
+        match std::fs::read_to_string(&path) {
+            Ok(contents) => {
+                for line in contents.lines().filter(|l| !l.trim().is_empty()) {
+                    match serde_json::from_str::<NlFeedbackEntry>(line) {
+                        Ok(entry) => {
+                            if entries.len() == MAX_FEEDBACK_IN_MEMORY {
+                                entries.pop_front();
+                            }
+                            entries.push_back(entry);
+                        }
+                        Err(e) => warn!("Skipping malformed NL query feedback: {}", e),
+                    }
+                }
+            }
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => {}
+            Err(e) => warn!("Failed to read {:?}: {}", path, e),
+        }
+
+        Self {
+            storage_path: Some(path),
+            entries: Mutex::new(entries),
+        }
+    }
+
+    pub fn record(&self, entry: NlFeedbackEntry) -> std::io::Result<()> {
+        if let Some(ref path) = self.storage_path {
+            let mut line = serde_json::to_vec(&entry)?;
+            line.push(b'\n');
+            std::fs::OpenOptions::new()
+                .create(true)
+                .append(true)
+                .open(path)?
+                .write_all(&line)?;
+        }
+        let mut entries = self.entries.lock();
+        if entries.len() == MAX_FEEDBACK_IN_MEMORY {
+            entries.pop_front();
+        }
+        entries.push_back(entry);
+        Ok(())
+    }
+
+    /// A tenant's feedback, newest first
+    pub fn list(&self, tenant_id: u64) -> Vec<NlFeedbackEntry> {
+        self.entries
+            .lock()
+            .iter()
+            .rev()
+            .filter(|entry| entry.tenant_id == tenant_id)
+            .cloned()
+            .collect()
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub struct NlFeedbackResponse {
+    pub success: bool,
+}
+
+/// POST /api/v1/query/nl/feedback
+pub async fn submit_nl_feedback(
+    State(state): State<AppState>,
+    Extension(auth): Extension<AuthContext>,
+    Json(request): Json<NlFeedbackRequest>,
+) -> Result<Json<NlFeedbackResponse>, ApiError> {
+    let query = request.query.trim();
+    if query.is_empty() || query.len() > MAX_QUERY_LEN {
+        return Err(ApiError::BadRequest(format!(
+            "query must be between 1 and {} characters",
+            MAX_QUERY_LEN
+        )));
+    }
+    if request
+        .comment
+        .as_ref()
+        .is_some_and(|c| c.len() > MAX_COMMENT_LEN)
+    {
+        return Err(ApiError::BadRequest(format!(
+            "Comment exceeds {} characters",
+            MAX_COMMENT_LEN
+        )));
+    }
+    if request.correct && request.corrected_filters.is_some() {
+        return Err(ApiError::BadRequest(
+            "corrected_filters only apply when correct is false".to_string(),
+        ));
+    }
+    if let Some(ref corrected) = request.corrected_filters {
+        validate_filters(corrected)
+            .map_err(|e| ApiError::BadRequest(format!("corrected_filters: {}", e)))?;
+    }
+
+    state
+        .nl_query_feedback
+        .record(NlFeedbackEntry {
+            timestamp_us: now_us(),
+            tenant_id: auth.tenant_id,
+            user_id: auth.user_id.clone(),
+            query: query.to_string(),
+            filters: request.filters,
+            correct: request.correct,
+            corrected_filters: request.corrected_filters,
+            comment: request.comment,
+        })
+        .map_err(|e| ApiError::Internal(format!("Failed to record NL query feedback: {}", e)))?;
+
+    Ok(Json(NlFeedbackResponse { success: true }))
+}
+
+#[derive(Debug, Deserialize)]
+pub struct NlFeedbackListQuery {
+    #[serde(default = "default_limit")]
+    pub limit: usize,
+    /// Only incorrect translations
+    #[serde(default)]
+    pub incorrect_only: bool,
+}
+
+#[derive(Debug, Serialize)]
+pub struct NlFeedbackListResponse {
+    pub total: usize,
+    pub correct: usize,
+    pub entries: Vec<NlFeedbackEntry>,
+}
+
+/// GET /api/v1/query/nl/feedback
+pub async fn list_nl_feedback(
+    State(state): State<AppState>,
+    Extension(auth): Extension<AuthContext>,
+    Query(params): Query<NlFeedbackListQuery>,
+) -> Result<Json<NlFeedbackListResponse>, ApiError> {
+    let entries = state.nl_query_feedback.list(auth.tenant_id);
+    let total = entries.len();
+    let correct = entries.iter().filter(|entry| entry.correct).count();
+    let entries = entries
+        .into_iter()
+        .filter(|entry| !params.incorrect_only || !entry.correct)
+        .take(params.limit.clamp(1, MAX_LIMIT))
+        .collect();
+
+    Ok(Json(NlFeedbackListResponse {
+        total,
+        correct,
+        entries,
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_refined_filters() {
+        let filters = parse_refined_filters(
+            "Here you go:\n```json\n{\"models\": [\"gpt-4\"], \"has_error\": true, \"min_cost_usd\": 0.1}\n```",
+        )
+        .unwrap();
+        assert_eq!(filters.models, Some(vec!["gpt-4".to_string()]));
+        assert_eq!(filters.has_error, Some(true));
+        assert_eq!(filters.min_cost_usd, Some(0.1));
+
+        assert!(parse_refined_filters("no filters apply").is_err());
+        assert!(parse_refined_filters("{\"has_error\": \"yes\"}").is_err());
+        assert!(
+            parse_refined_filters("{\"min_duration_ms\": 500, \"max_duration_ms\": 100}").is_err()
+        );
+        assert!(
+            parse_refined_filters("{\"time_range\": {\"start_us\": 10, \"end_us\": 5}}").is_err()
+        );
+    }
+
+    #[test]
+    fn test_matches_respect_access_policies() {
+        use agentreplay_core::SpanType;
+
+        let span = |project_id: u16, timestamp_us: u64| {
+            let mut edge = AgentFlowEdge::new(1, project_id, 1, 9, SpanType::Root, 0);
+            edge.timestamp_us = timestamp_us;
+            edge.has_payload = 1;
+            edge
+        };
+        let edges = vec![span(3, 10), span(3, 20), span(4, 30)];
+        let team_of = |edge: &AgentFlowEdge| {
+            let team = if edge.timestamp_us == 20 {
+                "search"
+            } else {
+                "ranking"
+            };
+            Some(format!(r#"{{"team":"{}"}}"#, team).into_bytes())
+        };
+        let filters = QueryFilters {
+            project_id: Some(3),
+            ..Default::default()
+        };
+
+        let all = readable_matches(edges.clone(), &filters, &AccessFilter::default(), team_of);
+        assert_eq!(all.len(), 2);
+
+        let search = AccessFilter::for_teams(&["search"]);
+        let visible = readable_matches(edges.clone(), &filters, &search, team_of);
+        assert_eq!(visible.len(), 1);
+        assert_eq!(visible[0].timestamp_us, 20);
+
+        let infra = AccessFilter::for_teams(&["infra"]);
+        assert!(readable_matches(edges, &filters, &infra, team_of).is_empty());
+    }
+
+    #[test]
+    fn test_feedback_log_persists() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join(NL_QUERY_FEEDBACK_FILE);
+        let entry = |tenant_id, correct| NlFeedbackEntry {
+            timestamp_us: 1,
+            tenant_id,
+            user_id: None,
+            query: "expensive gpt-4 failures".to_string(),
+            filters: QueryFilters::default(),
+            correct,
+            corrected_filters: None,
+            comment: None,
+        };
+
+        let log = NlQueryFeedbackLog::with_storage(&path);
+        log.record(entry(1, true)).unwrap();
+        log.record(entry(1, false)).unwrap();
+        log.record(entry(2, true)).unwrap();
+
+        let reloaded = NlQueryFeedbackLog::with_storage(&path);
+        let entries = reloaded.list(1);
+        assert_eq!(entries.len(), 2);
+        assert!(!entries[0].correct);
+        assert_eq!(reloaded.list(2).len(), 1);
+    }
+}
@@ -62,6 +62,8 @@ pub struct TokenBreakdown {
     pub reasoning_tokens: Option<u32>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub cache_read_tokens: Option<u32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub cache_creation_tokens: Option<u32>,
 }
 
 /// Extract prompts from GenAI payload
@@ -181,6 +183,7 @@ pub fn extract_token_breakdown(payload: &GenAIPayload) -> TokenBreakdown {
         total_tokens: payload.total_tokens.unwrap_or(0),
         reasoning_tokens: payload.reasoning_tokens,
         cache_read_tokens: payload.cache_read_tokens,
+        cache_creation_tokens: payload.cache_creation_tokens,
     }
 }
 
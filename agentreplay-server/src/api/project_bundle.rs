@@ -0,0 +1,842 @@
+// Copyright 2025 AgentReplay (https://github.com/agentreplay)
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program. If not, see <https://www.gnu.org/licenses/>.
+
+//! Portable project bundles
+//!
+//! `POST /api/v1/projects/:project_id/export` packs a project into one ZIP
+//! file that `POST /api/v1/projects/import` loads on another install, e.g. to
+//! move a project from a laptop to a team server:
+//!
+//! | Entry            | Contents                                              |
+//! |------------------|-------------------------------------------------------|
+//! | `bundle.json`    | [`BundleManifest`]: format version, project, counts   |
+//! | `traces.ndjson`  | one [`BundleSpan`] per line: stored span and payload   |
+//! | `datasets.json`  | eval datasets                                         |
+//! | `eval_runs.json` | eval runs                                             |
+//! | `prompts.json`   | prompt templates                                      |
+//! | `views.json`     | saved views                                           |
+//!
+//! Spans belong to a project, but datasets, eval runs, prompts and saved
+//! views are shared by the whole install. A bundle takes the eval runs with a
+//! result on one of the exported traces, the datasets those runs used (plus
+//! any listed in `dataset_ids`), all prompt templates and the saved views not
+//! filtered to a different project.
+//!
+//! Imports rewrite every span's project and tenant to the target project and
+//! the caller's tenant but keep span ids, so importing the same bundle twice
+//! does not duplicate traces. Datasets, runs and prompts are shared by every
+//! tenant, so an import never replaces a stored one: one identical to the
+//! stored object is skipped, and one that differs is imported under a new id
+//! (runs follow their dataset's new id). Views whose id already exists are
+//! skipped unless an admin passes `overwrite=true`.
+
+use std::collections::{BTreeSet, HashMap, HashSet};
+use std::io::{BufRead, BufReader, Cursor, Read, Seek, Write};
+use std::sync::Arc;
+
+use agentreplay_core::clock::now_us;
+use agentreplay_core::enterprise::PromptTemplate;
+use agentreplay_core::{AgentFlowEdge, EvalDataset, EvalRun, SavedView};
+use agentreplay_query::Agentreplay;
+use axum::{
+    body::Body,
+    extract::{Path, Query, State},
+    http::{header, StatusCode},
+    response::{IntoResponse, Response},
+    Extension, Json,
+};
+use base64::{engine::general_purpose::STANDARD, Engine};
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use tracing::{info, warn};
+
+use super::import::{require_member, write_upload, IMPORTS_DIR};
+use super::{ApiError, AppState};
+use crate::access_policy::AccessFilter;
+use crate::auth::{AuthContext, Role};
+
+/// Bundle layout version written to [`BundleManifest::format_version`]
+pub const BUNDLE_FORMAT_VERSION: u32 = 1;
+
+const MANIFEST_ENTRY: &str = "bundle.json";
+const TRACES_ENTRY: &str = "traces.ndjson";
+const DATASETS_ENTRY: &str = "datasets.json";
+const RUNS_ENTRY: &str = "eval_runs.json";
+const PROMPTS_ENTRY: &str = "prompts.json";
+const VIEWS_ENTRY: &str = "views.json";
+
+/// Spans written per storage batch on import
+const IMPORT_BATCH_SPANS: usize = 500;
+
+fn default_true() -> bool {
+    true
+}
+
+/// Project the bundle was exported from
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct BundleProject {
+    pub project_id: u16,
+    pub name: String,
+    #[serde(default)]
+    pub description: Option<String>,
+}
+
+/// `bundle.json`
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct BundleManifest {
+    pub format_version: u32,
+    pub exported_at_us: u64,
+    pub server_version: String,
+    pub project: BundleProject,
+    pub spans: usize,
+    pub payloads: usize,
+    pub datasets: usize,
+    pub eval_runs: usize,
+    pub prompts: usize,
+    pub views: usize,
+}
+
+/// One line of `traces.ndjson`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BundleSpan {
+    pub edge: AgentFlowEdge,
+    /// Base64 of the stored payload
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub payload: Option<String>,
+}
+
+/// Contents of a bundle
+#[derive(Debug, Default)]
+pub struct ProjectBundle {
+    pub manifest: BundleManifest,
+    pub spans: Vec<BundleSpan>,
+    pub datasets: Vec<EvalDataset>,
+    pub eval_runs: Vec<EvalRun>,
+    pub prompts: Vec<PromptTemplate>,
+    pub views: Vec<SavedView>,
+}
+
+fn json_entry<T: DeserializeOwned>(
+    archive: &mut zip::ZipArchive<impl Read + Seek>,
+    name: &str,
+) -> Result<Option<T>, String> {
+    let entry = match archive.by_name(name) {
+        Ok(entry) => entry,
+        Err(zip::result::ZipError::FileNotFound) => return Ok(None),
+        Err(e) => return Err(format!("{}: {}", name, e)),
+    };
+    serde_json::from_reader(entry)
+        .map(Some)
+        .map_err(|e| format!("{}: {}", name, e))
+}
+
+fn write_json<W: Write + Seek>(
+    zip: &mut zip::ZipWriter<W>,
+    name: &str,
+    value: &impl Serialize,
+) -> Result<(), String> {
+    zip.start_file(name, zip::write::SimpleFileOptions::default())
+        .map_err(|e| e.to_string())?;
+    serde_json::to_writer(zip, value).map_err(|e| format!("{}: {}", name, e))
+}
+
+impl ProjectBundle {
+    /// Write the bundle as a ZIP file, filling in the manifest counts
+    pub fn write<W: Write + Seek>(&mut self, writer: W) -> Result<W, String> {
+        let manifest = &mut self.manifest;
+        manifest.format_version = BUNDLE_FORMAT_VERSION;
+        manifest.spans = self.spans.len();
+        manifest.payloads = self.spans.iter().filter(|s| s.payload.is_some()).count();
+        manifest.datasets = self.datasets.len();
+        manifest.eval_runs = self.eval_runs.len();
+        manifest.prompts = self.prompts.len();
+        manifest.views = self.views.len();
+
+        let mut zip = zip::ZipWriter::new(writer);
+        write_json(&mut zip, MANIFEST_ENTRY, &self.manifest)?;
+        zip.start_file(TRACES_ENTRY, zip::write::SimpleFileOptions::default())
+            .map_err(|e| e.to_string())?;
+        for span in &self.spans {
+            serde_json::to_writer(&mut zip, span).map_err(|e| e.to_string())?;
+            zip.write_all(b"\n").map_err(|e| e.to_string())?;
+        }
+        write_json(&mut zip, DATASETS_ENTRY, &self.datasets)?;
+        write_json(&mut zip, RUNS_ENTRY, &self.eval_runs)?;
+        write_json(&mut zip, PROMPTS_ENTRY, &self.prompts)?;
+        write_json(&mut zip, VIEWS_ENTRY, &self.views)?;
+        zip.finish().map_err(|e| e.to_string())
+    }
+
+    /// Read a bundle written by [`Self::write`]
+    pub fn read<R: Read + Seek>(reader: R) -> Result<Self, String> {
+        let mut archive =
+            zip::ZipArchive::new(reader).map_err(|e| format!("invalid ZIP archive: {}", e))?;
+        let manifest: BundleManifest = json_entry(&mut archive, MANIFEST_ENTRY)?
+            .ok_or_else(|| format!("not a project bundle: {} is missing", MANIFEST_ENTRY))?;
+        if manifest.format_version > BUNDLE_FORMAT_VERSION {
+            return Err(format!(
+                "bundle format {} is newer than this server supports ({})",
+                manifest.format_version, BUNDLE_FORMAT_VERSION
+            ));
+        }
+
+        let mut spans = Vec::with_capacity(manifest.spans);
+        match archive.by_name(TRACES_ENTRY) {
+            Ok(entry) => {
+                for (n, line) in BufReader::new(entry).lines().enumerate() {
+                    let line = line.map_err(|e| format!("{}: {}", TRACES_ENTRY, e))?;
+                    if line.trim().is_empty() {
+                        continue;
+                    }
+                    let span = serde_json::from_str(&line)
+                        .map_err(|e| format!("{} line {}: {}", TRACES_ENTRY, n + 1, e))?;
+                    spans.push(span);
+                }
+            }
+            Err(zip::result::ZipError::FileNotFound) => {}
+            Err(e) => return Err(format!("{}: {}", TRACES_ENTRY, e)),
+        }
+
+        Ok(Self {
+            spans,
+            datasets: json_entry(&mut archive, DATASETS_ENTRY)?.unwrap_or_default(),
+            eval_runs: json_entry(&mut archive, RUNS_ENTRY)?.unwrap_or_default(),
+            prompts: json_entry(&mut archive, PROMPTS_ENTRY)?.unwrap_or_default(),
+            views: json_entry(&mut archive, VIEWS_ENTRY)?.unwrap_or_default(),
+            manifest,
+        })
+    }
+}
+
+/// Whether a saved view belongs in a bundle for `project_id`: views filtered
+/// to another project are left out
+fn view_in_project(view: &SavedView, project_id: u16) -> bool {
+    let filter = view
+        .filters
+        .get("project_id")
+        .or_else(|| view.filters.get("projectId"));
+    match filter {
+        None | Some(serde_json::Value::Null) => true,
+        Some(serde_json::Value::Number(n)) => n.as_u64() == Some(project_id as u64),
+        Some(serde_json::Value::String(s)) => s.parse::<u16>().ok() == Some(project_id),
+        Some(_) => true,
+    }
+}
+
+/// Database holding a project's spans
+fn project_db(state: &AppState, project_id: u16) -> Result<Arc<Agentreplay>, ApiError> {
+    match &state.project_manager {
+        Some(pm) => pm.get_or_open_project(project_id).map_err(|e| {
+            ApiError::Internal(format!("Failed to open project {}: {}", project_id, e))
+        }),
+        None => Ok(state.db.clone()),
+    }
+}
+
+fn project_metadata(state: &AppState, project_id: u16) -> BundleProject {
+    let registered = state
+        .project_registry
+        .as_ref()
+        .and_then(|registry| registry.get_metadata(project_id));
+    match registered {
+        Some(metadata) => BundleProject {
+            project_id,
+            name: metadata.name,
+            description: metadata.description,
+        },
+        None => BundleProject {
+            project_id,
+            name: format!("Project {}", project_id),
+            description: None,
+        },
+    }
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ExportProjectRequest {
+    /// Earliest span timestamp (microseconds); defaults to all history
+    #[serde(default)]
+    pub start_ts: Option<u64>,
+    #[serde(default)]
+    pub end_ts: Option<u64>,
+    /// Datasets to include besides those used by the exported runs
+    #[serde(default)]
+    pub dataset_ids: Vec<String>,
+    #[serde(default = "default_true")]
+    pub include_payloads: bool,
+    #[serde(default = "default_true")]
+    pub include_prompts: bool,
+    #[serde(default = "default_true")]
+    pub include_views: bool,
+}
+
+impl Default for ExportProjectRequest {
+    fn default() -> Self {
+        Self {
+            start_ts: None,
+            end_ts: None,
+            dataset_ids: Vec::new(),
+            include_payloads: true,
+            include_prompts: true,
+            include_views: true,
+        }
+    }
+}
+
+fn parse_hex_id(id: &str) -> Result<u128, ApiError> {
+    u128::from_str_radix(id.trim_start_matches("0x"), 16)
+        .map_err(|e| ApiError::BadRequest(format!("Invalid dataset ID '{}': {}", id, e)))
+}
+
+/// Spans of `project_id` the caller may read
+fn exported_edges(
+    edges: Vec<AgentFlowEdge>,
+    project_id: u16,
+    access: &AccessFilter,
+    load: impl Fn(&AgentFlowEdge) -> Option<Vec<u8>>,
+) -> Vec<AgentFlowEdge> {
+    let mut edges: Vec<AgentFlowEdge> = edges
+        .into_iter()
+        .filter(|edge| edge.project_id == project_id)
+        .collect();
+    access.retain_with(&mut edges, load);
+    edges
+}
+
+/// Collect everything a bundle for `project_id` holds
+fn build_bundle(
+    state: &AppState,
+    db: &Agentreplay,
+    project: BundleProject,
+    tenant_id: u64,
+    access: &AccessFilter,
+    req: &ExportProjectRequest,
+    views: Vec<SavedView>,
+) -> Result<ProjectBundle, ApiError> {
+    let internal = |e: agentreplay_core::AgentreplayError| ApiError::Internal(e.to_string());
+    let project_id = project.project_id;
+
+    let edges = db
+        .query_temporal_range_for_tenant(
+            req.start_ts.unwrap_or(0),
+            req.end_ts.unwrap_or(u64::MAX),
+            tenant_id,
+        )
+        .map_err(internal)?;
+    let edges = exported_edges(edges, project_id, access, |edge| {
+        db.get_payload(edge.edge_id).ok().flatten()
+    });
+    let mut spans = Vec::with_capacity(edges.len());
+    for edge in edges {
+        let payload = if req.include_payloads {
+            db.get_payload(edge.edge_id)
+                .map_err(internal)?
+                .map(|bytes| STANDARD.encode(bytes))
+        } else {
+            None
+        };
+        spans.push(BundleSpan { edge, payload });
+    }
+
+    let trace_ids: HashSet<u128> = spans.iter().map(|s| s.edge.edge_id).collect();
+    let eval_runs: Vec<EvalRun> = state
+        .db
+        .list_eval_runs(None)
+        .map_err(internal)?
+        .into_iter()
+        .filter(|run| {
+            run.results
+                .iter()
+                .any(|r| r.trace_id.is_some_and(|id| trace_ids.contains(&id)))
+        })
+        .collect();
+
+    let mut dataset_ids: BTreeSet<u128> = eval_runs.iter().map(|run| run.dataset_id).collect();
+    for id in &req.dataset_ids {
+        dataset_ids.insert(parse_hex_id(id)?);
+    }
+    let mut datasets = Vec::with_capacity(dataset_ids.len());
+    for id in dataset_ids {
+        match state.db.get_eval_dataset(id).map_err(internal)? {
+            Some(dataset) => datasets.push(dataset),
+            None => warn!("Skipping missing dataset 0x{:x} in project export", id),
+        }
+    }
+
+    let prompts = if req.include_prompts {
+        state.db.list_prompt_templates().map_err(internal)?
+    } else {
+        Vec::new()
+    };
+
+    Ok(ProjectBundle {
+        manifest: BundleManifest {
+            exported_at_us: now_us(),
+            server_version: env!("CARGO_PKG_VERSION").to_string(),
+            project,
+            ..Default::default()
+        },
+        spans,
+        datasets,
+        eval_runs,
+        prompts,
+        views,
+    })
+}
+
+/// POST /api/v1/projects/:project_id/export
+///
+/// Returns the bundle as `application/zip`. The body is optional.
+pub async fn export_project(
+    State(state): State<AppState>,
+    Path(project_id): Path<u16>,
+    Extension(auth): Extension<AuthContext>,
+    Extension(access): Extension<AccessFilter>,
+    req: Option<Json<ExportProjectRequest>>,
+) -> Result<Response, ApiError> {
+    let req = req.map(|Json(req)| req).unwrap_or_default();
+    let db = project_db(&state, project_id)?;
+    let project = project_metadata(&state, project_id);
+    let views: Vec<SavedView> = if req.include_views {
+        let registry = state.saved_view_registry.read().await;
+        registry
+            .list_views()
+            .into_iter()
+            .filter(|view| view_in_project(view, project_id))
+            .cloned()
+            .collect()
+    } else {
+        Vec::new()
+    };
+
+    let bundle = tokio::task::spawn_blocking(move || {
+        let mut bundle = build_bundle(&state, &db, project, auth.tenant_id, &access, &req, views)?;
+        let bytes = bundle
+            .write(Cursor::new(Vec::new()))
+            .map_err(|e| ApiError::Internal(format!("Failed to write bundle: {}", e)))?
+            .into_inner();
+        Ok::<_, ApiError>((bundle.manifest, bytes))
+    })
+    .await
+    .map_err(|e| ApiError::Internal(format!("Export task panicked: {}", e)))?;
+    let (manifest, bytes) = bundle?;
+
+    info!(
+        project_id,
+        spans = manifest.spans,
+        datasets = manifest.datasets,
+        eval_runs = manifest.eval_runs,
+        bytes = bytes.len(),
+        "Exported project bundle"
+    );
+    Ok((
+        [
+            (header::CONTENT_TYPE, "application/zip".to_string()),
+            (
+                header::CONTENT_DISPOSITION,
+                format!(
+                    "attachment; filename=\"project-{}.agentreplay.zip\"",
+                    project_id
+                ),
+            ),
+        ],
+        bytes,
+    )
+        .into_response())
+}
+
+#[derive(Debug, Default, Deserialize)]
+pub struct ImportProjectParams {
+    /// Project to import into; defaults to the bundle's project id
+    #[serde(default)]
+    pub project_id: Option<u16>,
+    /// Name for a newly registered project; defaults to the bundle's
+    #[serde(default)]
+    pub name: Option<String>,
+    /// Replace saved views that already exist; admins only
+    #[serde(default)]
+    pub overwrite: bool,
+}
+
+#[derive(Debug, Default, Serialize)]
+pub struct ImportProjectResponse {
+    pub project_id: u16,
+    pub name: String,
+    pub spans: usize,
+    pub payloads: usize,
+    pub datasets: usize,
+    pub eval_runs: usize,
+    pub prompts: usize,
+    pub views: usize,
+    /// Datasets, runs and prompts already stored unchanged
+    pub skipped: usize,
+    /// Datasets, runs and prompts imported under a new id because theirs
+    /// was taken by a different object
+    pub renamed: usize,
+}
+
+/// How to store a bundle object whose id may already be taken
+enum Placement {
+    New,
+    Duplicate,
+    Renamed(u128),
+}
+
+fn placement<T: Serialize>(bundled: &T, stored: Option<T>) -> Placement {
+    let Some(stored) = stored else {
+        return Placement::New;
+    };
+    if serde_json::to_value(&stored).ok() == serde_json::to_value(bundled).ok() {
+        Placement::Duplicate
+    } else {
+        Placement::Renamed(rand::random())
+    }
+}
+
+/// Write a bundle's spans, datasets, runs and prompts; views are imported by
+/// the caller, which holds the registry lock
+fn import_bundle(
+    state: &AppState,
+    db: &Agentreplay,
+    bundle: &ProjectBundle,
+    project_id: u16,
+    tenant_id: u64,
+    response: &mut ImportProjectResponse,
+) -> Result<(), ApiError> {
+    let internal = |e: agentreplay_core::AgentreplayError| ApiError::Internal(e.to_string());
+
+    for chunk in bundle.spans.chunks(IMPORT_BATCH_SPANS) {
+        let mut edges = Vec::with_capacity(chunk.len());
+        let mut payloads = Vec::new();
+        for span in chunk {
+            let mut edge = span.edge;
+            edge.project_id = project_id;
+            edge.tenant_id = tenant_id;
+            edge.checksum = edge.compute_checksum();
+            edges.push(edge);
+            if let Some(payload) = &span.payload {
+                let bytes = STANDARD.decode(payload).map_err(|e| {
+                    ApiError::BadRequest(format!(
+                        "payload of span 0x{:x} is not base64: {}",
+                        edge.edge_id, e
+                    ))
+                })?;
+                payloads.push((edge.edge_id, bytes));
+            }
+        }
+        let payload_refs: Vec<(u128, &[u8])> = payloads
+            .iter()
+            .map(|(id, bytes)| (*id, bytes.as_slice()))
+            .collect();
+        db.insert_batch_with_payloads(&edges, &payload_refs)
+            .map_err(internal)?;
+        response.spans += edges.len();
+        response.payloads += payloads.len();
+    }
+
+    // Datasets renamed on import, for the runs that used them
+    let mut dataset_ids: HashMap<u128, u128> = HashMap::new();
+    for dataset in &bundle.datasets {
+        let stored = state.db.get_eval_dataset(dataset.id).map_err(internal)?;
+        let mut dataset = dataset.clone();
+        match placement(&dataset, stored) {
+            Placement::Duplicate => {
+                response.skipped += 1;
+                continue;
+            }
+            Placement::Renamed(id) => {
+                dataset_ids.insert(dataset.id, id);
+                dataset.id = id;
+                response.renamed += 1;
+            }
+            Placement::New => {}
+        }
+        state.db.store_eval_dataset(dataset).map_err(internal)?;
+        response.datasets += 1;
+    }
+    for run in &bundle.eval_runs {
+        let mut run = run.clone();
+        if let Some(id) = dataset_ids.get(&run.dataset_id) {
+            run.dataset_id = *id;
+        }
+        let stored = state.db.get_eval_run(run.id).map_err(internal)?;
+        match placement(&run, stored) {
+            Placement::Duplicate => {
+                response.skipped += 1;
+                continue;
+            }
+            Placement::Renamed(id) => {
+                run.id = id;
+                response.renamed += 1;
+            }
+            Placement::New => {}
+        }
+        state.db.store_eval_run(run).map_err(internal)?;
+        response.eval_runs += 1;
+    }
+    for prompt in &bundle.prompts {
+        let stored = state.db.get_prompt_template(prompt.id).map_err(internal)?;
+        let mut prompt = prompt.clone();
+        match placement(&prompt, stored) {
+            Placement::Duplicate => {
+                response.skipped += 1;
+                continue;
+            }
+            Placement::Renamed(id) => {
+                prompt.id = id;
+                response.renamed += 1;
+            }
+            Placement::New => {}
+        }
+        state.db.store_prompt_template(prompt).map_err(internal)?;
+        response.prompts += 1;
+    }
+    Ok(())
+}
+
+/// POST /api/v1/projects/import
+///
+/// The body is a bundle produced by the export endpoint.
+pub async fn import_project(
+    State(state): State<AppState>,
+    Extension(auth): Extension<AuthContext>,
+    Query(params): Query<ImportProjectParams>,
+    body: Body,
+) -> Result<(StatusCode, Json<ImportProjectResponse>), ApiError> {
+    require_member(&auth)?;
+    if params.overwrite {
+        auth.require_role(Role::Admin)?;
+    }
+    let dir = std::path::Path::new(&state.db_path).join(IMPORTS_DIR);
+    tokio::fs::create_dir_all(&dir)
+        .await
+        .map_err(|e| ApiError::Internal(format!("Failed to create {:?}: {}", dir, e)))?;
+    let path = dir.join(format!(
+        "bundle-{}.zip",
+        hex::encode(rand::random::<[u8; 8]>())
+    ));
+
+    let upload = write_upload(&path, body).await;
+    let read_path = path.clone();
+    let bundle = match upload {
+        Ok(_) => tokio::task::spawn_blocking(move || {
+            let file = std::fs::File::open(&read_path).map_err(|e| e.to_string())?;
+            ProjectBundle::read(BufReader::new(file))
+        })
+        .await
+        .map_err(|e| ApiError::Internal(format!("Import task panicked: {}", e))),
+        Err(e) => Err(e),
+    };
+    let _ = tokio::fs::remove_file(&path).await;
+    let bundle = bundle?.map_err(ApiError::BadRequest)?;
+
+    let project_id = params
+        .project_id
+        .unwrap_or(bundle.manifest.project.project_id);
+    let existing = state
+        .project_registry
+        .as_ref()
+        .and_then(|registry| registry.get_metadata(project_id));
+    let name = match (&existing, params.name) {
+        (Some(metadata), _) => metadata.name.clone(),
+        (None, Some(name)) => name,
+        (None, None) => bundle.manifest.project.name.clone(),
+    };
+    if let (Some(registry), None) = (&state.project_registry, &existing) {
+        registry
+            .register_project(
+                project_id,
+                name.clone(),
+                bundle.manifest.project.description.clone(),
+            )
+            .map_err(|e| ApiError::Internal(format!("Failed to register project: {}", e)))?;
+    }
+    let db = project_db(&state, project_id)?;
+
+    let overwrite = params.overwrite;
+    let tenant_id = auth.tenant_id;
+    let worker_state = state.clone();
+    let (bundle, mut response) = tokio::task::spawn_blocking(move || {
+        let mut response = ImportProjectResponse {
+            project_id,
+            name,
+            ..Default::default()
+        };
+        import_bundle(
+            &worker_state,
+            &db,
+            &bundle,
+            project_id,
+            tenant_id,
+            &mut response,
+        )?;
+        Ok::<_, ApiError>((bundle, response))
+    })
+    .await
+    .map_err(|e| ApiError::Internal(format!("Import task panicked: {}", e)))??;
+
+    if !bundle.views.is_empty() {
+        let views_json =
+            serde_json::to_string(&bundle.views).map_err(|e| ApiError::Internal(e.to_string()))?;
+        response.views = state
+            .saved_view_registry
+            .write()
+            .await
+            .import_views(&views_json, overwrite)
+            .map_err(ApiError::Internal)?;
+    }
+    if let Some(registry) = &state.project_registry {
+        registry.invalidate_stats(project_id);
+    }
+
+    info!(
+        project_id,
+        from_project = bundle.manifest.project.project_id,
+        spans = response.spans,
+        datasets = response.datasets,
+        eval_runs = response.eval_runs,
+        skipped = response.skipped,
+        renamed = response.renamed,
+        "Imported project bundle"
+    );
+    Ok((StatusCode::CREATED, Json(response)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use agentreplay_core::SpanType;
+
+    fn span(id: u128, payload: Option<&[u8]>) -> BundleSpan {
+        let mut edge = AgentFlowEdge::new(7, 42, 1, 1, SpanType::Root, 0);
+        edge.edge_id = id;
+        BundleSpan {
+            edge,
+            payload: payload.map(|p| STANDARD.encode(p)),
+        }
+    }
+
+    #[test]
+    fn test_bundle_roundtrip() {
+        let mut bundle = ProjectBundle {
+            manifest: BundleManifest {
+                project: BundleProject {
+                    project_id: 42,
+                    name: "support-bot".to_string(),
+                    description: None,
+                },
+                ..Default::default()
+            },
+            spans: vec![span(1, Some(b"{\"model\":\"gpt-4o\"}")), span(2, None)],
+            datasets: vec![EvalDataset::new(9, "golden".to_string(), String::new(), 1)],
+            views: vec![SavedView::new(
+                "errors".to_string(),
+                serde_json::json!({"status": "error"}),
+                vec!["trace_id".to_string()],
+            )],
+            ..Default::default()
+        };
+        let bytes = bundle.write(Cursor::new(Vec::new())).unwrap().into_inner();
+
+        let read = ProjectBundle::read(Cursor::new(bytes)).unwrap();
+        assert_eq!(read.manifest.format_version, BUNDLE_FORMAT_VERSION);
+        assert_eq!(read.manifest.project.name, "support-bot");
+        assert_eq!(read.manifest.spans, 2);
+        assert_eq!(read.manifest.payloads, 1);
+        assert_eq!(read.spans[0].edge.edge_id, 1);
+        assert_eq!(
+            STANDARD
+                .decode(read.spans[0].payload.as_ref().unwrap())
+                .unwrap(),
+            b"{\"model\":\"gpt-4o\"}"
+        );
+        assert_eq!(read.datasets[0].name, "golden");
+        assert_eq!(read.views.len(), 1);
+        assert!(read.eval_runs.is_empty());
+    }
+
+    #[test]
+    fn test_export_respects_access_policies() {
+        let span = |project_id: u16, edge_id: u128| {
+            let mut edge = AgentFlowEdge::new(7, project_id, 1, 1, SpanType::Root, 0);
+            edge.edge_id = edge_id;
+            edge.has_payload = 1;
+            edge
+        };
+        let edges = vec![span(42, 1), span(42, 2), span(43, 3)];
+        let team_of = |edge: &AgentFlowEdge| {
+            let team = if edge.edge_id == 2 {
+                "search"
+            } else {
+                "ranking"
+            };
+            Some(format!(r#"{{"team":"{}"}}"#, team).into_bytes())
+        };
+
+        let all = exported_edges(edges.clone(), 42, &AccessFilter::default(), team_of);
+        assert_eq!(all.len(), 2);
+
+        let search = AccessFilter::for_teams(&["search"]);
+        let visible = exported_edges(edges.clone(), 42, &search, team_of);
+        assert_eq!(visible.len(), 1);
+        assert_eq!(visible[0].edge_id, 2);
+
+        let infra = AccessFilter::for_teams(&["infra"]);
+        assert!(exported_edges(edges, 42, &infra, team_of).is_empty());
+    }
+
+    #[test]
+    fn test_read_rejects_other_archives() {
+        let mut zip = zip::ZipWriter::new(Cursor::new(Vec::new()));
+        zip.start_file("spans.ndjson", zip::write::SimpleFileOptions::default())
+            .unwrap();
+        let bytes = zip.finish().unwrap().into_inner();
+        assert!(ProjectBundle::read(Cursor::new(bytes))
+            .unwrap_err()
+            .contains(MANIFEST_ENTRY));
+        assert!(ProjectBundle::read(Cursor::new(b"not a zip".to_vec())).is_err());
+    }
+
+    #[test]
+    fn test_view_in_project() {
+        let view = |filters| SavedView::new("v".to_string(), filters, Vec::new());
+        assert!(view_in_project(&view(serde_json::json!({})), 42));
+        assert!(view_in_project(
+            &view(serde_json::json!({"project_id": 42})),
+            42
+        ));
+        assert!(view_in_project(
+            &view(serde_json::json!({"project_id": "42"})),
+            42
+        ));
+        assert!(!view_in_project(
+            &view(serde_json::json!({"project_id": 7})),
+            42
+        ));
+    }
+
+    #[test]
+    fn test_taken_ids_are_never_replaced() {
+        let dataset = |name: &str| EvalDataset::new(5, name.to_string(), String::new(), 1);
+        assert!(matches!(placement(&dataset("a"), None), Placement::New));
+        assert!(matches!(
+            placement(&dataset("a"), Some(dataset("a"))),
+            Placement::Duplicate
+        ));
+        assert!(matches!(
+            placement(&dataset("a"), Some(dataset("other tenant's"))),
+            Placement::Renamed(id) if id != 5
+        ));
+    }
+}
@@ -0,0 +1,274 @@
+// Copyright 2025 AgentReplay (https://github.com/agentreplay)
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program. If not, see <https://www.gnu.org/licenses/>.
+
+//! Project template management and provisioning
+
+use agentreplay_core::clock::{now_secs, now_us};
+use agentreplay_core::enterprise::BudgetAlert as CoreBudgetAlert;
+use agentreplay_core::SavedView;
+use agentreplay_query::RetentionConfig;
+use axum::{
+    extract::{Path, State},
+    http::StatusCode,
+    Extension, Json,
+};
+use serde::Serialize;
+use std::path::PathBuf;
+
+use super::budget_alerts::{generate_id, AlertFilters, AlertStatus, BudgetAlert};
+use super::query::{ApiError, AppState};
+use crate::agent_registry::AgentMetadata;
+use crate::auth::{AuthContext, Role};
+use crate::project_templates::{project_retention_path, ProjectSettings, ProjectTemplate};
+
+#[derive(Debug, Serialize)]
+pub struct TemplateListResponse {
+    pub templates: Vec<ProjectTemplate>,
+    pub total: usize,
+}
+
+/// What was created when a project was provisioned from a template
+#[derive(Debug, Default, Serialize)]
+pub struct ProvisionReport {
+    pub template: String,
+    pub retention_configured: bool,
+    pub eval_configured: bool,
+    pub budget_alert_ids: Vec<String>,
+    pub view_ids: Vec<String>,
+    pub agent_ids: Vec<u64>,
+    /// Parts of the template that could not be applied
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub errors: Vec<String>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ProjectSettingsResponse {
+    pub project_id: u16,
+    #[serde(flatten)]
+    pub settings: ProjectSettings,
+    /// Project-specific retention, if one was configured
+    pub retention: Option<RetentionConfig>,
+}
+
+/// GET /api/v1/project-templates
+pub async fn list_templates(
+    State(state): State<AppState>,
+    Extension(auth): Extension<AuthContext>,
+) -> Json<TemplateListResponse> {
+    let templates = state.project_templates.list(auth.tenant_id);
+    Json(TemplateListResponse {
+        total: templates.len(),
+        templates,
+    })
+}
+
+/// GET /api/v1/project-templates/:name
+pub async fn get_template(
+    State(state): State<AppState>,
+    Extension(auth): Extension<AuthContext>,
+    Path(name): Path<String>,
+) -> Result<Json<ProjectTemplate>, ApiError> {
+    state
+        .project_templates
+        .get(auth.tenant_id, &name)
+        .map(Json)
+        .ok_or_else(|| ApiError::NotFound(format!("Project template '{}' not found", name)))
+}
+
+/// PUT /api/v1/project-templates/:name
+///
+/// The body is the template; its `name` is taken from the path and it
+/// belongs to the caller's tenant.
+pub async fn put_template(
+    State(state): State<AppState>,
+    Extension(auth): Extension<AuthContext>,
+    Path(name): Path<String>,
+    Json(mut template): Json<ProjectTemplate>,
+) -> Result<(StatusCode, Json<ProjectTemplate>), ApiError> {
+    auth.require_role(Role::Admin)?;
+    let status = if state.project_templates.get(auth.tenant_id, &name).is_some() {
+        StatusCode::OK
+    } else {
+        StatusCode::CREATED
+    };
+
+    template.name = name;
+    template.tenant_id = auth.tenant_id;
+    let saved = state
+        .project_templates
+        .upsert(template)
+        .map_err(ApiError::BadRequest)?;
+    Ok((status, Json(saved)))
+}
+
+/// DELETE /api/v1/project-templates/:name
+///
+/// Projects already provisioned from the template are not affected.
+pub async fn delete_template(
+    State(state): State<AppState>,
+    Extension(auth): Extension<AuthContext>,
+    Path(name): Path<String>,
+) -> Result<StatusCode, ApiError> {
+    auth.require_role(Role::Admin)?;
+    match state.project_templates.delete(auth.tenant_id, &name) {
+        Ok(true) => Ok(StatusCode::NO_CONTENT),
+        Ok(false) => Err(ApiError::NotFound(format!(
+            "Project template '{}' not found",
+            name
+        ))),
+        Err(e) => Err(ApiError::Internal(e)),
+    }
+}
+
+/// GET /api/v1/projects/:project_id/settings
+///
+/// Template, eval and retention settings recorded for the project.
+pub async fn get_project_settings(
+    State(state): State<AppState>,
+    Path(project_id): Path<u16>,
+) -> Result<Json<ProjectSettingsResponse>, ApiError> {
+    let dir = project_settings_dir(&state, project_id);
+    let settings = ProjectSettings::load(&dir).map_err(ApiError::Internal)?;
+    let retention_path = project_retention_path(&dir);
+    let retention = retention_path
+        .exists()
+        .then(|| RetentionConfig::load(&retention_path));
+
+    Ok(Json(ProjectSettingsResponse {
+        project_id,
+        settings,
+        retention,
+    }))
+}
+
+/// Directory holding a project's settings files
+///
+/// This is the project's storage directory under per-project storage; in
+/// single database mode the same layout is used under the data directory.
+pub(crate) fn project_settings_dir(state: &AppState, project_id: u16) -> PathBuf {
+    match &state.project_manager {
+        Some(pm) => pm.project_dir(project_id),
+        None => PathBuf::from(&state.db_path)
+            .join("projects")
+            .join(format!("project_{}", project_id)),
+    }
+}
+
+/// Apply a template to a newly created project
+///
+/// Each part is applied independently; failures are collected in the
+/// report so one bad part does not leave the rest unprovisioned.
+pub(super) async fn provision_project(
+    state: &AppState,
+    project_id: u16,
+    template: &ProjectTemplate,
+) -> ProvisionReport {
+    let mut report = ProvisionReport {
+        template: template.name.clone(),
+        ..Default::default()
+    };
+    let dir = project_settings_dir(state, project_id);
+
+    if let Some(retention) = &template.retention {
+        match retention.to_config().save(&project_retention_path(&dir)) {
+            Ok(()) => report.retention_configured = true,
+            Err(e) => report.errors.push(format!("retention: {}", e)),
+        }
+    }
+
+    let settings = ProjectSettings {
+        template: Some(template.name.clone()),
+        eval: template.eval.clone(),
+        provisioned_at: now_us(),
+    };
+    match settings.save(&dir) {
+        Ok(()) => report.eval_configured = template.eval.is_some(),
+        Err(e) => report.errors.push(format!("settings: {}", e)),
+    }
+
+    for budget in &template.budgets {
+        let now = now_us();
+        let alert = BudgetAlert {
+            id: generate_id(),
+            name: budget.name.clone(),
+            description: budget.description.clone(),
+            threshold_type: budget.threshold_type.clone(),
+            threshold_value: budget.threshold_value,
+            period: budget.period.clone(),
+            filters: AlertFilters {
+                project_ids: vec![project_id],
+                ..Default::default()
+            },
+            actions: budget.actions.clone(),
+            status: AlertStatus::Active,
+            triggered_count: 0,
+            last_triggered: None,
+            created_at: now,
+            updated_at: now,
+        };
+        let id = alert.id;
+        let core_alert: CoreBudgetAlert = alert.into();
+        match state.db.store_budget_alert(core_alert) {
+            Ok(()) => report.budget_alert_ids.push(format!("0x{:x}", id)),
+            Err(e) => report
+                .errors
+                .push(format!("budget alert '{}': {}", budget.name, e)),
+        }
+    }
+
+    if !template.views.is_empty() {
+        let mut registry = state.saved_view_registry.write().await;
+        for spec in &template.views {
+            let mut view = SavedView::new(
+                spec.name.clone(),
+                spec.scoped_filters(project_id),
+                spec.columns.clone(),
+            );
+            view.description = spec.description.clone();
+            view.tags = spec.tags.clone();
+            view.is_shared = spec.is_shared;
+
+            match registry.add_view(view) {
+                Ok(saved) => report.view_ids.push(saved.id),
+                Err(e) => report
+                    .errors
+                    .push(format!("saved view '{}': {}", spec.name, e)),
+            }
+        }
+    }
+
+    for spec in &template.agents {
+        let now = now_secs();
+        let mut metadata = spec.metadata.clone();
+        metadata.insert("project_id".to_string(), project_id.to_string());
+        let agent = AgentMetadata {
+            agent_id: spec.agent_id_for(project_id),
+            name: spec.name.clone(),
+            namespace: spec.namespace.clone(),
+            version: spec.version.clone(),
+            description: spec.description.clone(),
+            created_at: now,
+            updated_at: now,
+            metadata,
+        };
+
+        match state.agent_registry.register(agent) {
+            Ok(registered) => report.agent_ids.push(registered.agent_id),
+            Err(e) => report.errors.push(format!("agent '{}': {}", spec.name, e)),
+        }
+    }
+
+    report
+}
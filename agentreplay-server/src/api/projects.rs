@@ -14,13 +14,14 @@
 // along with this program. If not, see <https://www.gnu.org/licenses/>.
 
 use axum::{
-    extract::{Path, State},
+    extract::{Path, Query, State},
     http::StatusCode,
     Json,
 };
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 
+use crate::api::project_templates::{provision_project, ProvisionReport};
 use crate::api::{ApiError, AppState};
 use crate::auth::AuthContext;
 
@@ -43,6 +44,13 @@ pub struct CreateProjectRequest {
     pub id: Option<u16>,
 }
 
+/// Query parameters for project creation
+#[derive(Debug, Default, Deserialize)]
+pub struct CreateProjectQuery {
+    /// Name of a project template to provision the project from
+    pub template: Option<String>,
+}
+
 /// Response after creating a project
 #[derive(Debug, Serialize)]
 pub struct CreateProjectResponse {
@@ -50,6 +58,9 @@ pub struct CreateProjectResponse {
     pub name: String,
     pub description: Option<String>,
     pub env_vars: EnvVariables,
+    /// What the template provisioned, when created with `?template=`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub provisioned: Option<ProvisionReport>,
 }
 
 /// Environment variables for SDK setup
@@ -81,6 +92,10 @@ fn get_known_project_name(project_id: u16) -> Option<(&'static str, &'static str
     match project_id {
         // Claude Code plugin uses deterministic hash of "Claude Code" = 49455
         49455 => Some(("Claude Code", "Claude Code coding sessions")),
+        crate::self_trace::SELF_TRACE_PROJECT_ID => Some((
+            crate::self_trace::SELF_TRACE_PROJECT_NAME,
+            "Traces of the Agentreplay server itself",
+        )),
         _ => None,
     }
 }
@@ -265,14 +280,30 @@ pub async fn get_project(
 
 /// POST /api/v1/projects
 /// Create a new project with environment variables for SDK setup
+///
+/// With `?template=<name>`, the project is provisioned from that template:
+/// retention, budget alerts, eval config, saved views and agents.
 pub async fn create_project(
     State(state): State<AppState>,
     auth: axum::Extension<AuthContext>,
+    Query(params): Query<CreateProjectQuery>,
     body: String,
 ) -> Result<Json<CreateProjectResponse>, ApiError> {
     let payload: CreateProjectRequest = serde_json::from_str(&body)
         .map_err(|e| ApiError::BadRequest(format!("Invalid JSON: {}", e)))?;
 
+    // Resolve the template first so an unknown name creates nothing
+    let template = params
+        .template
+        .as_deref()
+        .map(|name| {
+            state
+                .project_templates
+                .get(auth.tenant_id, name)
+                .ok_or_else(|| ApiError::NotFound(format!("Project template '{}' not found", name)))
+        })
+        .transpose()?;
+
     // Check if ID is provided in payload, otherwise generate
     // Check if ID is provided in payload
     // SPECIAL CASE: If name is "Claude Code", always use reserved ID 49455
@@ -288,6 +319,12 @@ pub async fn create_project(
                 % 65535) as u16
         })
     };
+    if project_id == crate::self_trace::SELF_TRACE_PROJECT_ID {
+        return Err(ApiError::BadRequest(format!(
+            "Project ID {} is reserved for the server's own traces",
+            project_id
+        )));
+    }
 
     // Register project in registry if available
     if let Some(ref registry) = state.project_registry {
@@ -325,11 +362,28 @@ pub async fn create_project(
         project_id: project_id.to_string(),
     };
 
+    let provisioned = match &template {
+        Some(template) => {
+            let report = provision_project(&state, project_id, template).await;
+            if !report.errors.is_empty() {
+                tracing::warn!(
+                    "Project {} provisioned from template '{}' with errors: {:?}",
+                    project_id,
+                    template.name,
+                    report.errors
+                );
+            }
+            Some(report)
+        }
+        None => None,
+    };
+
     Ok(Json(CreateProjectResponse {
         project_id,
         name: payload.name,
         description: payload.description,
         env_vars,
+        provisioned,
     }))
 }
 
@@ -0,0 +1,277 @@
+// Copyright 2025 AgentReplay (https://github.com/agentreplay)
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program. If not, see <https://www.gnu.org/licenses/>.
+
+//! Prompt-caching analytics
+//!
+//! Providers report how many input tokens were read from (or written to)
+//! their prompt cache. `GET /api/v1/analytics/cache` aggregates those counts
+//! over a window, per project and per model: cache hit rates, the cost
+//! actually paid, what the same calls would have cost uncached, and the
+//! realized savings (cache-read discounts minus cache-write premiums).
+
+use std::collections::BTreeMap;
+
+use agentreplay_core::clock::now_us;
+use axum::{
+    extract::{Query, State},
+    Json,
+};
+use serde::{Deserialize, Serialize};
+
+use crate::otel_genai::{GenAIPayload, ModelPricing};
+use crate::scaling::run_query;
+use crate::{api::query::ApiError, api::AppState, auth::AuthContext};
+
+const DEFAULT_LOOKBACK_US: u64 = 7 * 24 * 3_600_000_000; // 7 days
+
+#[derive(Debug, Deserialize)]
+pub struct CacheAnalyticsParams {
+    pub start_ts: Option<u64>,
+    pub end_ts: Option<u64>,
+    /// Restrict to one project (per-project storage only)
+    pub project_id: Option<u16>,
+}
+
+/// Prompt-cache usage of a group of LLM calls
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct CacheStats {
+    pub requests: u64,
+    /// Calls that read at least one token from the cache
+    pub cached_requests: u64,
+    pub input_tokens: u64,
+    pub cache_read_tokens: u64,
+    pub cache_creation_tokens: u64,
+    /// `cache_read_tokens / input_tokens`
+    pub token_hit_rate: f64,
+    /// `cached_requests / requests`
+    pub request_hit_rate: f64,
+    pub cost_usd: f64,
+    /// Cost had no input token been cached
+    pub uncached_cost_usd: f64,
+    pub savings_usd: f64,
+}
+
+impl CacheStats {
+    fn record(&mut self, payload: &GenAIPayload, pricing: &ModelPricing) {
+        let cache_read = payload.cache_read_tokens.unwrap_or(0);
+        let cost = payload.calculate_cost(pricing);
+        let savings = payload.calculate_cache_savings(pricing);
+
+        self.requests += 1;
+        if cache_read > 0 {
+            self.cached_requests += 1;
+        }
+        self.input_tokens += payload.input_tokens.unwrap_or(0) as u64;
+        self.cache_read_tokens += cache_read as u64;
+        self.cache_creation_tokens += payload.cache_creation_tokens.unwrap_or(0) as u64;
+        self.cost_usd += cost;
+        self.uncached_cost_usd += cost + savings;
+        self.savings_usd += savings;
+    }
+
+    fn finish(mut self) -> Self {
+        if self.input_tokens > 0 {
+            self.token_hit_rate =
+                (self.cache_read_tokens as f64 / self.input_tokens as f64).min(1.0);
+        }
+        if self.requests > 0 {
+            self.request_hit_rate = self.cached_requests as f64 / self.requests as f64;
+        }
+        self
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub struct ProjectCacheStats {
+    pub project_id: u16,
+    #[serde(flatten)]
+    pub stats: CacheStats,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ModelCacheStats {
+    pub provider: String,
+    pub model: String,
+    #[serde(flatten)]
+    pub stats: CacheStats,
+}
+
+#[derive(Debug, Serialize)]
+pub struct CacheAnalytics {
+    pub start_ts: u64,
+    pub end_ts: u64,
+    #[serde(flatten)]
+    pub total: CacheStats,
+    pub by_project: Vec<ProjectCacheStats>,
+    /// Largest savings first
+    pub by_model: Vec<ModelCacheStats>,
+}
+
+#[derive(Debug, Default)]
+struct CacheAccum {
+    total: CacheStats,
+    by_project: BTreeMap<u16, CacheStats>,
+    by_model: BTreeMap<(String, String), CacheStats>,
+}
+
+impl CacheAccum {
+    /// Add one LLM call; spans without token usage or a model are skipped
+    fn record(&mut self, project_id: u16, payload: &GenAIPayload) {
+        if payload.input_tokens.is_none() {
+            return;
+        }
+        let Some(model) = payload
+            .response_model
+            .as_deref()
+            .or(payload.request_model.as_deref())
+        else {
+            return;
+        };
+        let provider = payload
+            .system
+            .as_deref()
+            .or(payload.provider_name.as_deref())
+            .unwrap_or("unknown");
+        let pricing = ModelPricing::for_model(provider, model);
+
+        self.total.record(payload, &pricing);
+        self.by_project
+            .entry(project_id)
+            .or_default()
+            .record(payload, &pricing);
+        self.by_model
+            .entry((provider.to_string(), model.to_string()))
+            .or_default()
+            .record(payload, &pricing);
+    }
+
+    fn finish(self, start_ts: u64, end_ts: u64) -> CacheAnalytics {
+        let mut by_model: Vec<ModelCacheStats> = self
+            .by_model
+            .into_iter()
+            .map(|((provider, model), stats)| ModelCacheStats {
+                provider,
+                model,
+                stats: stats.finish(),
+            })
+            .collect();
+        by_model.sort_by(|a, b| b.stats.savings_usd.total_cmp(&a.stats.savings_usd));
+        CacheAnalytics {
+            start_ts,
+            end_ts,
+            total: self.total.finish(),
+            by_project: self
+                .by_project
+                .into_iter()
+                .map(|(project_id, stats)| ProjectCacheStats {
+                    project_id,
+                    stats: stats.finish(),
+                })
+                .collect(),
+            by_model,
+        }
+    }
+}
+
+/// GET /api/v1/analytics/cache
+///
+/// Prompt-cache hit rates and realized savings per project and model.
+pub async fn get_cache_analytics(
+    State(state): State<AppState>,
+    Query(params): Query<CacheAnalyticsParams>,
+    axum::Extension(auth): axum::Extension<AuthContext>,
+) -> Result<Json<CacheAnalytics>, ApiError> {
+    let end_ts = params.end_ts.unwrap_or_else(now_us);
+    let start_ts = params
+        .start_ts
+        .unwrap_or(end_ts.saturating_sub(DEFAULT_LOOKBACK_US));
+    if end_ts <= start_ts {
+        return Err(ApiError::BadRequest(
+            "end_ts must be greater than start_ts".into(),
+        ));
+    }
+
+    let shards = super::metrics::project_shards(&state, params.project_id)?;
+    let tenant_id = auth.tenant_id;
+    let accum = run_query(&state, move || -> Result<CacheAccum, ApiError> {
+        let mut accum = CacheAccum::default();
+        for db in shards {
+            let edges = db
+                .query_temporal_range_for_tenant(start_ts, end_ts, tenant_id)
+                .map_err(|e| ApiError::Internal(e.to_string()))?;
+            for edge in &edges {
+                let Ok(Some(bytes)) = db.get_payload(edge.edge_id) else {
+                    continue;
+                };
+                if let Ok(payload) = serde_json::from_slice::<GenAIPayload>(&bytes) {
+                    accum.record(edge.project_id, &payload);
+                }
+            }
+        }
+        Ok(accum)
+    })
+    .await
+    .map_err(|e| ApiError::Internal(format!("Cache analytics task panicked: {}", e)))??;
+
+    Ok(Json(accum.finish(start_ts, end_ts)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn call(model: &str, input: u32, cache_read: u32, cache_creation: u32) -> GenAIPayload {
+        GenAIPayload {
+            system: Some("anthropic".to_string()),
+            request_model: Some(model.to_string()),
+            input_tokens: Some(input),
+            output_tokens: Some(0),
+            cache_read_tokens: (cache_read > 0).then_some(cache_read),
+            cache_creation_tokens: (cache_creation > 0).then_some(cache_creation),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_cache_accum() {
+        let mut accum = CacheAccum::default();
+        // First call writes a 100K-token prefix, the next two read it
+        accum.record(1, &call("claude-3-5-sonnet", 101_000, 0, 100_000));
+        accum.record(1, &call("claude-3-5-sonnet", 101_000, 100_000, 0));
+        accum.record(2, &call("claude-3-5-sonnet", 101_000, 100_000, 0));
+        accum.record(2, &call("claude-3-haiku", 1_000, 0, 0));
+        // No usage or model: ignored
+        accum.record(2, &GenAIPayload::default());
+        let result = accum.finish(0, 1);
+
+        assert_eq!(result.total.requests, 4);
+        assert_eq!(result.total.cached_requests, 2);
+        assert_eq!(result.total.cache_read_tokens, 200_000);
+        assert!((result.total.request_hit_rate - 0.5).abs() < 1e-9);
+        // Reads save 2 * 100K * $2.70/1M, the write costs 100K * $0.75/1M extra
+        assert!((result.total.savings_usd - (0.54 - 0.075)).abs() < 1e-9);
+        assert!(
+            (result.total.uncached_cost_usd - result.total.cost_usd - result.total.savings_usd)
+                .abs()
+                < 1e-9
+        );
+
+        assert_eq!(result.by_project.len(), 2);
+        assert_eq!(result.by_project[0].stats.requests, 2);
+        assert!((result.by_project[0].stats.savings_usd - (0.27 - 0.075)).abs() < 1e-9);
+        assert_eq!(result.by_model[0].model, "claude-3-5-sonnet");
+        assert_eq!(result.by_model[1].stats.savings_usd, 0.0);
+    }
+}
@@ -18,8 +18,11 @@
 // Prompt templates directory API endpoints
 
 use super::query::AppState;
+use crate::auth::AuthContext;
+use crate::llm::{ChatMessage, ChatResponse};
+use agentreplay_prompts::{TemplateEngine, TemplateValidation, TemplateVariables, VariableSchema};
 use axum::{
-    extract::{Path, Query, State},
+    extract::{Extension, Path, Query, State},
     http::StatusCode,
     Json,
 };
@@ -140,7 +143,35 @@ pub struct UpdatePromptRequest {
 
 #[derive(Debug, Deserialize)]
 pub struct RenderPromptRequest {
-    pub variables: HashMap<String, String>,
+    pub variables: TemplateVariables,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ValidatePromptRequest {
+    /// Draft template to check instead of the stored one
+    pub template: Option<String>,
+    /// Variable schema; defaults to the prompt's `variable_schema` metadata
+    pub schema: Option<HashMap<String, VariableSchema>>,
+    /// Sample values to type-check and test-render with
+    pub variables: Option<TemplateVariables>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct PlaygroundRequest {
+    #[serde(default)]
+    pub variables: TemplateVariables,
+    /// Optional system message sent ahead of the rendered prompt
+    pub system: Option<String>,
+    /// Version the caller expects to run; rejected if it is no longer current
+    pub version: Option<u32>,
+    /// Models to run side by side
+    pub targets: Vec<PlaygroundTarget>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct PlaygroundTarget {
+    pub provider: String,
+    pub model: Option<String>,
 }
 
 #[derive(Debug, Deserialize)]
@@ -175,6 +206,37 @@ pub struct RenderPromptResponse {
     pub template_id: String,
 }
 
+#[derive(Debug, Serialize)]
+pub struct ValidatePromptResponse {
+    pub template_id: String,
+    #[serde(flatten)]
+    pub validation: TemplateValidation,
+}
+
+#[derive(Debug, Serialize)]
+pub struct PlaygroundRunResult {
+    pub provider: String,
+    pub model: Option<String>,
+    pub output: Option<String>,
+    pub error: Option<String>,
+    /// Edge ID of the recorded response span
+    pub trace_id: Option<String>,
+    pub latency_ms: u64,
+    pub input_tokens: u32,
+    pub output_tokens: u32,
+    pub cost_usd: f64,
+}
+
+#[derive(Debug, Serialize)]
+pub struct PlaygroundResponse {
+    pub prompt_id: String,
+    pub version: u32,
+    pub rendered: String,
+    /// Template variables with no supplied value, left unrendered
+    pub missing_variables: Vec<String>,
+    pub results: Vec<PlaygroundRunResult>,
+}
+
 #[derive(Debug, Serialize)]
 pub struct DeleteResponse {
     pub success: bool,
@@ -256,7 +318,15 @@ fn parse_id(id_str: &str) -> Result<u128, String> {
     u128::from_str_radix(id_str, 16).map_err(|e| format!("Invalid ID: {}", e))
 }
 
+/// Variables a template reads, falling back to a plain `{{name}}` scan for
+/// templates that are not valid Jinja syntax
 fn extract_variables(template: &str) -> Vec<String> {
+    TemplateEngine::new()
+        .variables(template)
+        .unwrap_or_else(|_| scan_placeholders(template))
+}
+
+fn scan_placeholders(template: &str) -> Vec<String> {
     let mut variables = Vec::new();
     let mut chars = template.chars().peekable();
 
@@ -325,15 +395,108 @@ fn compute_diff(template1: &str, template2: &str) -> Vec<DiffLine> {
     diff
 }
 
-fn render_template(template: &str, variables: &HashMap<String, String>) -> String {
-    let mut result = template.to_string();
+/// Template engine with every stored prompt registered as an includable
+/// partial under its name
+fn template_engine(state: &AppState) -> Result<TemplateEngine, (StatusCode, String)> {
+    let prompts = state
+        .db
+        .list_prompt_templates()
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
 
-    for (key, value) in variables {
-        let placeholder = format!("{{{{{}}}}}", key);
-        result = result.replace(&placeholder, value);
-    }
+    Ok(TemplateEngine::new().with_partials(prompts.into_iter().map(|p| (p.name, p.template))))
+}
 
-    result
+fn render_template(
+    engine: &TemplateEngine,
+    template: &str,
+    variables: &TemplateVariables,
+) -> Result<String, (StatusCode, String)> {
+    engine
+        .render(template, variables)
+        .map_err(|e| (StatusCode::BAD_REQUEST, e.to_string()))
+}
+
+/// Schema from the prompt's `variable_schema` metadata, or one treating every
+/// declared variable as a required string
+fn variable_schema(prompt: &CorePromptTemplate) -> HashMap<String, VariableSchema> {
+    let from_metadata = prompt
+        .metadata
+        .as_ref()
+        .and_then(|m| m.get("variable_schema"))
+        .and_then(|v| serde_json::from_value(v.clone()).ok());
+
+    from_metadata.unwrap_or_else(|| {
+        prompt
+            .variables
+            .iter()
+            .map(|name| {
+                (
+                    name.clone(),
+                    VariableSchema {
+                        name: name.clone(),
+                        var_type: agentreplay_prompts::VariableType::String,
+                        required: true,
+                        default_value: None,
+                        validation_regex: None,
+                        allowed_values: None,
+                    },
+                )
+            })
+            .collect()
+    })
+}
+
+/// Summarize one playground call, estimating tokens when the provider
+/// does not report usage
+fn playground_result(
+    target: &PlaygroundTarget,
+    messages: &[ChatMessage],
+    outcome: anyhow::Result<(ChatResponse, u128)>,
+    latency_ms: u64,
+) -> PlaygroundRunResult {
+    match outcome {
+        Ok((response, trace_id)) => {
+            let model = response
+                .response_model
+                .clone()
+                .unwrap_or_else(|| response.model.clone());
+            let input_tokens = response.input_tokens.unwrap_or_else(|| {
+                messages
+                    .iter()
+                    .map(|m| m.content.len() as u32 / 4)
+                    .sum::<u32>()
+            });
+            let output_tokens = response
+                .output_tokens
+                .unwrap_or(response.content.len() as u32 / 4);
+            let (input_per_1k, output_per_1k) =
+                super::chat::get_model_pricing(&target.provider, &model);
+
+            PlaygroundRunResult {
+                provider: target.provider.clone(),
+                model: Some(model),
+                output: Some(response.content),
+                error: None,
+                trace_id: Some(format!("0x{:x}", trace_id)),
+                latency_ms,
+                input_tokens,
+                output_tokens,
+                cost_usd: input_tokens as f64 / 1000.0 * input_per_1k
+                    + output_tokens as f64 / 1000.0 * output_per_1k,
+            }
+        }
+        Err(e) => PlaygroundRunResult {
+            provider: target.provider.clone(),
+            model: target.model.clone(),
+            output: None,
+            error: Some(e.to_string()),
+            trace_id: None,
+            latency_ms,
+            input_tokens: 0,
+            output_tokens: 0,
+            cost_usd: 0.0,
+        },
+    }
 }
 
 fn prompt_to_response(prompt: &PromptTemplate) -> PromptResponse {
@@ -516,7 +679,8 @@ pub async fn render_prompt(
             )
         })?;
 
-    let rendered = render_template(&prompt.template, &req.variables);
+    let engine = template_engine(&state)?;
+    let rendered = render_template(&engine, &prompt.template, &req.variables)?;
 
     Ok(Json(RenderPromptResponse {
         rendered,
@@ -524,6 +688,151 @@ pub async fn render_prompt(
     }))
 }
 
+/// POST /api/v1/prompts/:id/validate
+/// Check a prompt template's syntax and variables against its schema
+pub async fn validate_prompt(
+    State(state): State<AppState>,
+    Path(id): Path<String>,
+    Json(req): Json<ValidatePromptRequest>,
+) -> Result<Json<ValidatePromptResponse>, (StatusCode, String)> {
+    let prompt_id = parse_id(&id).map_err(|e| (StatusCode::BAD_REQUEST, e))?;
+
+    let prompt = state
+        .db
+        .get_prompt_template(prompt_id)
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?
+        .ok_or_else(|| {
+            (
+                StatusCode::NOT_FOUND,
+                "Prompt template not found".to_string(),
+            )
+        })?;
+
+    let schema = req.schema.unwrap_or_else(|| variable_schema(&prompt));
+    let template = req.template.as_deref().unwrap_or(&prompt.template);
+    let engine = template_engine(&state)?;
+    let validation = engine.validate(template, &schema, req.variables.as_ref());
+
+    Ok(Json(ValidatePromptResponse {
+        template_id: format!("0x{:x}", prompt_id),
+        validation,
+    }))
+}
+
+/// POST /api/v1/prompts/:id/playground
+/// Render a prompt and run it against several models side by side
+///
+/// Each run is recorded as a traced LLM call tagged with the prompt ID and
+/// version, so playground iterations show up alongside production traffic.
+pub async fn run_prompt_playground(
+    State(state): State<AppState>,
+    Extension(auth): Extension<AuthContext>,
+    Path(id): Path<String>,
+    Json(req): Json<PlaygroundRequest>,
+) -> Result<Json<PlaygroundResponse>, (StatusCode, String)> {
+    let prompt_id = parse_id(&id).map_err(|e| (StatusCode::BAD_REQUEST, e))?;
+
+    if req.targets.is_empty() {
+        return Err((
+            StatusCode::BAD_REQUEST,
+            "At least one target model is required".to_string(),
+        ));
+    }
+
+    let llm_manager = state.llm_manager.clone().ok_or_else(|| {
+        (
+            StatusCode::SERVICE_UNAVAILABLE,
+            "LLM features are not enabled".to_string(),
+        )
+    })?;
+
+    let prompt = state
+        .db
+        .get_prompt_template(prompt_id)
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?
+        .ok_or_else(|| {
+            (
+                StatusCode::NOT_FOUND,
+                "Prompt template not found".to_string(),
+            )
+        })?;
+
+    if let Some(version) = req.version {
+        if version != prompt.version {
+            return Err((
+                StatusCode::CONFLICT,
+                format!(
+                    "Version {} is not the current version ({})",
+                    version, prompt.version
+                ),
+            ));
+        }
+    }
+
+    let engine = template_engine(&state)?;
+    let rendered = render_template(&engine, &prompt.template, &req.variables)?;
+    let missing_variables: Vec<String> = extract_variables(&prompt.template)
+        .into_iter()
+        .filter(|v| !req.variables.contains_key(v))
+        .collect();
+
+    let mut messages = Vec::new();
+    if let Some(system) = &req.system {
+        messages.push(ChatMessage {
+            role: "system".to_string(),
+            content: system.clone(),
+        });
+    }
+    messages.push(ChatMessage {
+        role: "user".to_string(),
+        content: rendered.clone(),
+    });
+
+    let attributes = HashMap::from([
+        (
+            "agentreplay.prompt.id".to_string(),
+            format!("0x{:x}", prompt_id),
+        ),
+        (
+            "agentreplay.prompt.version".to_string(),
+            prompt.version.to_string(),
+        ),
+        ("agentreplay.playground".to_string(), "true".to_string()),
+    ]);
+    let tenant_id = auth.tenant_id;
+    let session_id = current_timestamp_us() / 1_000_000;
+
+    let runs = req.targets.iter().map(|target| {
+        let llm_manager = llm_manager.clone();
+        let messages = messages.clone();
+        let attributes = attributes.clone();
+        async move {
+            let start = std::time::Instant::now();
+            let outcome = llm_manager
+                .chat_with_attributes(
+                    &target.provider,
+                    target.model.clone(),
+                    messages.clone(),
+                    tenant_id,
+                    session_id,
+                    attributes,
+                )
+                .await;
+            let latency_ms = start.elapsed().as_millis() as u64;
+            playground_result(target, &messages, outcome, latency_ms)
+        }
+    });
+    let results = futures::future::join_all(runs).await;
+
+    Ok(Json(PlaygroundResponse {
+        prompt_id: format!("0x{:x}", prompt_id),
+        version: prompt.version,
+        rendered,
+        missing_variables,
+        results,
+    }))
+}
+
 /// GET /api/v1/prompts/:id/versions
 /// Get version history for a prompt template
 pub async fn get_prompt_versions(
@@ -727,11 +1036,55 @@ mod tests {
     fn test_render_template() {
         let template = "Hello {{name}}, your age is {{age}}!";
         let mut vars = HashMap::new();
-        vars.insert("name".to_string(), "Alice".to_string());
-        vars.insert("age".to_string(), "30".to_string());
+        vars.insert("name".to_string(), serde_json::json!("Alice"));
+        vars.insert("age".to_string(), serde_json::json!(30));
 
-        let rendered = render_template(template, &vars);
+        let rendered = render_template(&TemplateEngine::new(), template, &vars).unwrap();
         assert_eq!(rendered, "Hello Alice, your age is 30!");
+
+        let template = "{% for doc in docs %}- {{ doc | upper }}\n{% endfor %}";
+        vars.insert("docs".to_string(), serde_json::json!(["a", "b"]));
+        let rendered = render_template(&TemplateEngine::new(), template, &vars).unwrap();
+        assert_eq!(rendered, "- A\n- B\n");
+        assert_eq!(extract_variables(template), vec!["docs"]);
+
+        let err = render_template(&TemplateEngine::new(), "{% if %}", &vars).unwrap_err();
+        assert_eq!(err.0, StatusCode::BAD_REQUEST);
+    }
+
+    #[test]
+    fn test_playground_result() {
+        let target = PlaygroundTarget {
+            provider: "openai".to_string(),
+            model: Some("gpt-4o-mini".to_string()),
+        };
+        let messages = vec![ChatMessage {
+            role: "user".to_string(),
+            content: "Summarize this".to_string(),
+        }];
+        let response = ChatResponse {
+            content: "A summary".to_string(),
+            provider: "openai".to_string(),
+            model: "gpt-4o-mini".to_string(),
+            response_model: None,
+            response_id: None,
+            tokens_used: Some(1500),
+            input_tokens: Some(1000),
+            output_tokens: Some(500),
+            finish_reason: Some("stop".to_string()),
+            duration_ms: 120,
+        };
+
+        let result = playground_result(&target, &messages, Ok((response, 0xabc)), 120);
+        assert_eq!(result.output.as_deref(), Some("A summary"));
+        assert_eq!(result.trace_id.as_deref(), Some("0xabc"));
+        assert_eq!(result.input_tokens, 1000);
+        assert!(result.cost_usd > 0.0);
+
+        let failed = playground_result(&target, &messages, Err(anyhow::anyhow!("boom")), 5);
+        assert_eq!(failed.error.as_deref(), Some("boom"));
+        assert!(failed.trace_id.is_none());
+        assert_eq!(failed.cost_usd, 0.0);
     }
 
     #[test]
@@ -16,7 +16,7 @@
 use axum::{
     extract::{Path, Query, State},
     http::StatusCode,
-    response::{IntoResponse, Response},
+    response::IntoResponse,
     Json,
 };
 use agentreplay_core::{checked_timestamp_add, AgentFlowEdge};
@@ -26,46 +26,15 @@ use std::sync::Arc;
 use tokio::sync::broadcast;
 use tokio::sync::RwLock;
 
+use crate::access_policy::AccessFilter;
 use crate::agent_registry::AgentRegistry;
+use crate::api::hydration::{self, HydrationLevel};
+use crate::api::trace_listing::{self, TraceCursor, TraceProjection};
 use crate::auth::AuthContext;
+use crate::scaling::run_query;
 
 /// API error type
-#[derive(Debug, thiserror::Error)]
-pub enum ApiError {
-    #[error("Not found: {0}")]
-    NotFound(String),
-
-    #[error("Bad request: {0}")]
-    BadRequest(String),
-
-    #[error("Internal server error: {0}")]
-    Internal(String),
-
-    #[error("Unauthorized")]
-    Unauthorized,
-
-    #[error("Request timeout: {0}")]
-    RequestTimeout(String),
-}
-
-impl IntoResponse for ApiError {
-    fn into_response(self) -> Response {
-        let (status, message) = match self {
-            ApiError::NotFound(msg) => (StatusCode::NOT_FOUND, msg),
-            ApiError::BadRequest(msg) => (StatusCode::BAD_REQUEST, msg),
-            ApiError::Internal(msg) => (StatusCode::INTERNAL_SERVER_ERROR, msg),
-            ApiError::Unauthorized => (StatusCode::UNAUTHORIZED, "Unauthorized".to_string()),
-            ApiError::RequestTimeout(msg) => (StatusCode::REQUEST_TIMEOUT, msg),
-        };
-
-        (status, Json(ErrorResponse { error: message })).into_response()
-    }
-}
-
-#[derive(Serialize)]
-struct ErrorResponse {
-    error: String,
-}
+pub use crate::api::error::ApiError;
 
 /// Shared application state
 #[derive(Clone)]
@@ -90,6 +59,92 @@ pub struct AppState {
     /// High-performance ingestion actor for batched, deduplicated trace ingestion
     /// Routes traces through: Validation → Batching → Deduplication → Storage
     pub ingestion_actor: Option<crate::ingestion::IngestionActorHandle>,
+    /// Queue-depth admission policy applied before handing batches to the ingestion actor
+    pub ingestion_admission: Arc<crate::admission::QueueAdmission>,
+    /// Retry deduplication (client span IDs and Idempotency-Key) for trace ingestion
+    pub ingestion_idempotency: Arc<crate::ingestion::IdempotencyGuard>,
+    /// Started spans waiting for their finalize update
+    pub open_spans: Arc<crate::ingestion::OpenSpanTracker>,
+    /// External session key -> stable session_id mappings (session stitching)
+    pub session_registry: Arc<crate::session_registry::SessionRegistry>,
+    /// HLC-based clock-skew correction applied to ingested spans (None = disabled)
+    pub clock_skew: Option<Arc<crate::ingestion::ClockSkewCorrector>>,
+    /// Cluster membership (None for standalone nodes)
+    pub cluster: Option<Arc<crate::cluster::ClusterNode>>,
+    /// WAL shipping to / replay from a warm standby (None when disabled)
+    pub replication: Option<Arc<crate::standby::WalReplicator>>,
+    /// Archive tier for cold traces (restored on demand by trace queries)
+    pub archive: Option<Arc<crate::rehydration::ArchiveManager>>,
+    /// Embedded hook scripts run on ingest, eval completion and alerts
+    pub scripts: Arc<crate::scripting::ScriptManager>,
+    /// Configured ingestion stages and registered transform plugins
+    pub ingest_pipeline: Arc<crate::ingestion::IngestPipeline>,
+    /// Data directory lock (None when the embedding app manages it itself)
+    pub instance_lock: Option<Arc<crate::instance_lock::InstanceLock>>,
+    /// Topic and goal-completion analysis of finished sessions
+    pub session_analyzer: Arc<crate::session_analysis::SessionAnalyzer>,
+    /// Streaming top-K of the most expensive prompts, tools and sessions
+    pub heavy_hitters: Arc<crate::heavy_hitters::HeavyHitters>,
+    /// Failed spans grouped by failure fingerprint
+    pub failures: Arc<crate::failures::FailureTracker>,
+    /// Registered tool schemas, per-tool call stats and contract violations
+    pub tool_contracts: Arc<crate::tool_registry::ToolContractMonitor>,
+    /// Files, APIs, tickets and people mentioned by spans and memories
+    pub knowledge_graph: Arc<crate::knowledge_graph::KnowledgeGraphIndexer>,
+    /// Per-session summary records maintained at ingestion
+    pub session_summarizer: Arc<crate::session_summary::SessionSummarizer>,
+    /// Links sessions and traces to conversations via correlation attributes
+    pub conversations: Arc<crate::conversations::ConversationLinker>,
+    /// Per-project ingest volume counters behind spike/drop alerts
+    pub volume_monitor: Arc<crate::volume_alerts::VolumeMonitor>,
+    /// Latest comparison of production inputs with golden datasets
+    pub drift_detector: Arc<crate::drift::DriftDetector>,
+    /// Policy checks on chat proxy prompts and completions
+    pub guardrails: Arc<crate::guardrails::GuardrailEngine>,
+    /// Replays chat proxy requests on candidate models and compares answers
+    pub shadow: Arc<crate::shadow::ShadowTraffic>,
+    /// Cached chat proxy completions
+    pub response_cache: Arc<crate::response_cache::ResponseCache>,
+    /// Runs dataset tasks against live agents and grades the trials
+    pub simulator: Arc<crate::simulation::Simulator>,
+    /// Model comparison runs over a prompt set
+    pub benchmarks: Arc<crate::benchmarks::BenchmarkRunner>,
+    /// Encrypted provider API keys, referenced by alias
+    pub vault: Arc<crate::vault::KeyVault>,
+    /// Per-role payload hydration limits for trace listings
+    pub hydration: Arc<crate::config::HydrationConfig>,
+    /// Feedback on natural language query translations
+    pub nl_query_feedback: Arc<crate::api::nl_query::NlQueryFeedbackLog>,
+    /// Scheduled report definitions and delivery
+    pub reports: Arc<crate::reports::ReportScheduler>,
+    /// Issued API tokens, also consulted by the authenticator
+    pub api_tokens: Arc<crate::auth::TokenStore>,
+    /// Per-client request budgets by route class
+    pub rate_limiter: Arc<crate::middleware::RouteRateLimiter>,
+    /// Bounded workers for analytics queries, measuring their queue wait
+    pub query_queue: Arc<crate::scaling::QueryQueue>,
+    /// Background bulk imports and their checkpoints
+    pub import_jobs: Arc<crate::api::import::ImportJobs>,
+    /// Queue and status of long-running background jobs
+    pub jobs: Arc<crate::jobs::JobManager>,
+    /// Shutdown progress, reported by `/health`
+    pub shutdown: Arc<crate::shutdown::Shutdown>,
+    /// Re-reads the config file on SIGHUP or admin request
+    pub config_reloader: Arc<crate::config_reload::ConfigReloader>,
+    /// Templates for provisioning new projects
+    pub project_templates: Arc<crate::project_templates::ProjectTemplateStore>,
+    /// Sampling, redaction and feature settings polled by agent SDKs
+    pub agent_configs: Arc<crate::agent_config::AgentConfigStore>,
+    /// Versioned model prices, used to recompute stored costs
+    pub pricing: Arc<agentreplay_core::ModelPricingRegistry>,
+    /// Display currency, exchange rates and project markup and tax
+    pub billing: Arc<crate::billing::BillingStore>,
+    /// Daily and monthly usage quotas per API key and project
+    pub quotas: Arc<crate::quotas::QuotaManager>,
+    /// Attribute-based read policies for teams sharing a tenant
+    pub access_policies: Arc<crate::access_policy::AccessPolicies>,
+    /// Per-route latency histograms exposed at /metrics
+    pub request_metrics: Arc<crate::middleware::RequestMetrics>,
 }
 
 /// Query parameters for listing traces
@@ -146,6 +201,12 @@ pub struct TraceQueryParams {
     pub has_errors: Option<bool>,
     pub full_text_search: Option<String>, // Search in payloads
 
+    // Fields derived at ingest time (see crate::ingestion::enrich_span)
+    pub model_families: Option<Vec<String>>, // ["gpt", "claude", "gemini"]
+    pub sdk_names: Option<Vec<String>>,      // ["agentreplay-python"]
+    pub sdk_versions: Option<Vec<String>>,   // ["0.3.1"]
+    pub countries: Option<Vec<String>>,      // ["US", "DE"]
+
     // SORTING
     pub sort_by: Option<String>, // "timestamp", "duration", "cost", "tokens"
     pub sort_order: Option<String>, // "asc", "desc"
@@ -155,6 +216,13 @@ pub struct TraceQueryParams {
     /// When provided, `offset` is ignored and pagination uses the cursor
     /// for O(log N + page_size) per page instead of O(N).
     pub cursor: Option<String>,
+
+    /// Comma-separated trace fields to return (default: all)
+    pub fields: Option<String>,
+
+    /// How much payload each trace carries: "metadata", "preview" or "full".
+    /// Defaults to the configured level, capped by the caller's role.
+    pub hydration: Option<HydrationLevel>,
 }
 
 fn default_limit() -> usize {
@@ -312,6 +380,10 @@ pub struct TraceView {
     // Tags for filtering/categorization
     #[serde(skip_serializing_if = "Option::is_none")]
     pub tags: Option<Vec<String>>, // Tags from attributes
+
+    // Model family, provider, SDK and country derived at ingest time
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub enrichment: Option<agentreplay_storage::EdgeEnrichment>,
 }
 
 /// Rich observation with decoded attributes (for span tree visualization)
@@ -413,6 +485,7 @@ impl From<AgentFlowEdge> for TraceView {
             output_preview: None,
             display_name: None, // Will be populated from payload
             tags: None,         // Will be populated from payload
+            enrichment: None,
         }
     }
 }
@@ -428,15 +501,24 @@ impl TraceView {
 /// Response for trace listing
 #[derive(Debug, Serialize)]
 pub struct TracesResponse {
-    pub traces: Vec<TraceView>,
+    /// Traces, restricted to the requested `fields`
+    pub traces: Vec<serde_json::Value>,
+    /// Matching traces; with a cursor, only those read to fill this page
     pub total: usize,
     pub limit: usize,
     pub offset: usize,
     /// Cursor for next page (Task 3: cursor-based pagination).
-    /// None if no more pages. Pass this as `cursor` query parameter
+    /// Only set for timestamp order; None if no more pages. Pass this as `cursor` query parameter
     /// for the next page request for O(log N + page_size) pagination.
     #[serde(skip_serializing_if = "Option::is_none")]
     pub next_cursor: Option<String>,
+    /// Restores of archived ranges this query touches. When non-empty the
+    /// response only covers hot data and is sent as 202 Accepted; repeat the
+    /// query once the jobs complete.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub rehydration_jobs: Vec<crate::rehydration::RehydrationJob>,
+    /// Hydration level the traces were built at
+    pub hydration: HydrationLevel,
 }
 
 /// Database statistics
@@ -463,11 +545,34 @@ pub async fn list_traces(
     State(state): State<AppState>,
     Query(params): Query<TraceQueryParams>,
     auth: axum::Extension<AuthContext>,
-) -> Result<Json<TracesResponse>, ApiError> {
+    axum::Extension(access): axum::Extension<AccessFilter>,
+) -> Result<(StatusCode, Json<TracesResponse>), ApiError> {
     use tokio::time::{timeout, Duration};
 
     // Validate query parameters
     validate_query_params(&params)?;
+    let hydration_level = hydration::resolve_level(&state.hydration, auth.role, params.hydration)?;
+    let projection = TraceProjection::parse(params.fields.as_deref())?;
+    let hydration_level = if projection.needs_payload() {
+        hydration_level
+    } else {
+        HydrationLevel::Metadata
+    };
+
+    let descending = params.sort_order.as_deref().unwrap_or("desc") != "asc";
+    let timestamp_order = !matches!(params.sort_by.as_deref(), Some("duration" | "tokens"));
+    let cursor = params
+        .cursor
+        .as_deref()
+        .map(TraceCursor::decode)
+        .transpose()?;
+    if let Some(cursor) = cursor {
+        if !timestamp_order || cursor.descending != descending {
+            return Err(ApiError::BadRequest(
+                "cursor only continues the timestamp order it was issued for".to_string(),
+            ));
+        }
+    }
 
     // Wrap query execution with timeout to prevent DoS
     let query_future = async {
@@ -480,53 +585,18 @@ pub async fn list_traces(
         let start_ts = params.start_ts.unwrap_or(now - 86_400_000_000); // 24 hours ago
         let end_ts = params.end_ts.unwrap_or(now);
 
-        // Query traces - use ProjectManager if available and project_id specified
-        let mut cursor_from_storage: Option<String> = None;
-        let using_cursor = params.cursor.is_some() && state.project_manager.is_none();
-
-        let mut edges = if let Some(ref pm) = state.project_manager {
-            if let Some(project_id) = params.project_id {
-                // Query specific project
-                pm.query_project(project_id, auth.tenant_id, start_ts, end_ts)
-                    .map_err(|e| ApiError::Internal(e.to_string()))?
-            } else {
-                // Query all projects for this tenant
-                pm.query_all_projects(auth.tenant_id, start_ts, end_ts)
-                    .map_err(|e| ApiError::Internal(e.to_string()))?
-            }
-        } else if using_cursor {
-            // Task 3: Cursor-based pagination — O(log N + page_size) per page.
-            // Only used for the single-DB path (non-ProjectManager).
-            // We over-fetch by 4x the limit to account for post-filters,
-            // then paginate in-memory. The storage-level cursor ensures we
-            // skip efficiently to the right position.
-            let descending = params.sort_order.as_deref().unwrap_or("desc") != "asc";
-            let fetch_limit = params.limit * 4; // Over-fetch for filtering headroom
-            let (fetched, next) = state
-                .db
-                .query_temporal_range_for_tenant_paginated(
-                    start_ts,
-                    end_ts,
-                    auth.tenant_id,
-                    fetch_limit,
-                    params.cursor.as_deref(),
-                    descending,
-                )
-                .map_err(|e| ApiError::Internal(e.to_string()))?;
-            cursor_from_storage = next;
-            fetched
-        } else {
-            // Fallback to single database (offset-based)
-            let mut all_edges = state
-                .db
-                .query_temporal_range_for_tenant(start_ts, end_ts, auth.tenant_id)
-                .map_err(|e| ApiError::Internal(e.to_string()))?;
-
-            // Filter by project_id if specified
-            if let Some(project_id) = params.project_id {
-                all_edges.retain(|e| e.project_id == project_id);
+        // Restore archived parts of the range in the background; until then
+        // only hot data is returned. Read-only nodes leave restores to the writer.
+        let replica = state.cluster.as_ref().is_some_and(|c| !c.accepts_writes());
+        let standby = state
+            .replication
+            .as_ref()
+            .is_some_and(|r| !r.accepts_writes());
+        let rehydration_jobs = match (&state.archive, &state.project_manager) {
+            (Some(archive), None) if !replica && !standby => {
+                archive.request_rehydration(&state.db, auth.tenant_id, start_ts, end_ts)
             }
-            all_edges
+            _ => Vec::new(),
         };
 
         // OPTIMIZED: Combined single-pass filter instead of multiple retain() calls
@@ -552,26 +622,56 @@ pub async fn list_traces(
         });
 
         // Helper to fetch payload for a trace (handling project-specific DBs)
-        let fetch_payload = |edge: &AgentFlowEdge| -> Option<GenAIPayload> {
+        let fetch_payload_bytes = |edge: &AgentFlowEdge| -> Option<Vec<u8>> {
             if edge.has_payload == 0 {
                 return None;
             }
 
-            let payload_bytes = if let Some(ref pm) = state.project_manager {
+            if let Some(ref pm) = state.project_manager {
                 pm.get_or_open_project(edge.project_id)
                     .ok()
                     .and_then(|db| db.get_payload(edge.edge_id).ok())
                     .flatten()
             } else {
                 state.db.get_payload(edge.edge_id).ok().flatten()
-            };
+            }
+        };
+        let fetch_payload = |edge: &AgentFlowEdge| -> Option<GenAIPayload> {
+            fetch_payload_bytes(edge)
+                .and_then(|bytes| serde_json::from_slice::<GenAIPayload>(&bytes).ok())
+        };
 
-            payload_bytes.and_then(|bytes| serde_json::from_slice::<GenAIPayload>(&bytes).ok())
+        // Helper to fetch the ingest-time enrichment of an edge (no payload I/O)
+        let fetch_enrichment = |edge: &AgentFlowEdge| -> Option<agentreplay_storage::EdgeEnrichment> {
+            if let Some(ref pm) = state.project_manager {
+                pm.get_or_open_project(edge.project_id)
+                    .ok()
+                    .and_then(|db| db.get_edge_enrichment(edge.edge_id).ok())
+                    .flatten()
+            } else {
+                state.db.get_edge_enrichment(edge.edge_id).ok().flatten()
+            }
+        };
+        let filters_enrichment = params.model_families.is_some()
+            || params.sdk_names.is_some()
+            || params.sdk_versions.is_some()
+            || params.countries.is_some();
+        let matches_any = |values: &Option<Vec<String>>, field: &Option<String>| match values {
+            Some(values) => field
+                .as_deref()
+                .is_some_and(|f| values.iter().any(|v| v.eq_ignore_ascii_case(f))),
+            None => true,
         };
 
         // Single-pass filter with early exit optimization
-        edges.retain(|e| {
+        let matches = |e: &AgentFlowEdge| -> bool {
             // Most selective filters first for early exit
+            if let Some(project_id) = params.project_id {
+                if e.project_id != project_id {
+                    return false;
+                }
+            }
+
             // Session and agent filters are typically most selective (80-95% elimination)
             if let Some(session_id) = session_filter {
                 if e.session_id != session_id {
@@ -661,6 +761,26 @@ pub async fn list_traces(
                 }
             }
 
+            // Enrichment filters (model family, SDK, country) read the
+            // per-edge enrichment record only
+            let enrichment = if filters_enrichment || params.providers.is_some() {
+                fetch_enrichment(e)
+            } else {
+                None
+            };
+            if filters_enrichment {
+                let Some(ref enrichment) = enrichment else {
+                    return false;
+                };
+                if !matches_any(&params.model_families, &enrichment.model_family)
+                    || !matches_any(&params.sdk_names, &enrichment.sdk_name)
+                    || !matches_any(&params.sdk_versions, &enrichment.sdk_version)
+                    || !matches_any(&params.countries, &enrichment.country)
+                {
+                    return false;
+                }
+            }
+
             // Provider and Model filters
             // Task 4: Try denormalized attrs first (O(1) per edge) before payload I/O
             if params.providers.is_some() || params.models.is_some() {
@@ -691,8 +811,14 @@ pub async fn list_traces(
                     let payload_opt = fetch_payload(e);
                     if let Some(payload) = payload_opt {
                         if let Some(ref providers) = params.providers {
+                            // The normalized provider also matches aliases
+                            // (e.g. "google" for "vertex_ai")
                             let provider = payload.system.as_deref().unwrap_or("openai");
-                            if !providers.iter().any(|p| provider.contains(p)) {
+                            let normalized = enrichment.as_ref().and_then(|en| en.provider.as_deref());
+                            if !providers
+                                .iter()
+                                .any(|p| provider.contains(p) || normalized == Some(p.as_str()))
+                            {
                                 return false;
                             }
                         }
@@ -826,8 +952,67 @@ pub async fn list_traces(
                 }
             }
 
+            // Access policies last, as they may read the payload
+            if !access.permits_with(e, || fetch_payload_bytes(e)) {
+                return false;
+            }
+
             true // Passed all filters
-        });
+        };
+
+        // Query traces - use ProjectManager if available and project_id specified.
+        // A cursor on a single database is served by seeking its temporal
+        // index past the cursor, so deep pages cost the same as the first.
+        let keyset_db = match (&cursor, &state.project_manager, params.project_id) {
+            (Some(_), None, _) => Some(state.db.clone()),
+            (Some(_), Some(pm), Some(project_id)) => Some(
+                pm.get_or_open_project(project_id)
+                    .map_err(|e| ApiError::Internal(e.to_string()))?,
+            ),
+            _ => None,
+        };
+
+        let mut edges = if let (Some(db), Some(cursor)) = (keyset_db, cursor) {
+            trace_listing::scan_page(
+                &db,
+                auth.tenant_id,
+                start_ts,
+                end_ts,
+                &cursor,
+                params.limit,
+                &matches,
+            )?
+        } else {
+            let mut edges = if let Some(ref pm) = state.project_manager {
+                if let Some(project_id) = params.project_id {
+                    // Query specific project
+                    pm.query_project(project_id, auth.tenant_id, start_ts, end_ts)
+                        .map_err(|e| ApiError::Internal(e.to_string()))?
+                } else {
+                    // Query all projects for this tenant
+                    pm.query_all_projects(auth.tenant_id, start_ts, end_ts)
+                        .map_err(|e| ApiError::Internal(e.to_string()))?
+                }
+            } else {
+                // Fallback to single database (offset-based)
+                let mut all_edges = state
+                    .db
+                    .query_temporal_range_for_tenant(start_ts, end_ts, auth.tenant_id)
+                    .map_err(|e| ApiError::Internal(e.to_string()))?;
+
+                // Filter by project_id if specified
+                if let Some(project_id) = params.project_id {
+                    all_edges.retain(|e| e.project_id == project_id);
+                }
+                all_edges
+            };
+
+            edges.retain(&matches);
+            if let Some(cursor) = cursor {
+                edges.retain(|e| cursor.admits(e));
+            }
+            edges
+        };
 
         let total = edges.len();
 
@@ -851,17 +1036,11 @@ pub async fn list_traces(
                     }
                 }
                 // Default to timestamp for unknown sort keys
-                _ => {
-                    if asc {
-                        edges.sort_by(|a, b| a.timestamp_us.cmp(&b.timestamp_us));
-                    } else {
-                        edges.sort_by(|a, b| b.timestamp_us.cmp(&a.timestamp_us));
-                    }
-                }
+                _ => trace_listing::sort_by_key(&mut edges, !asc),
             }
         } else {
             // Default sort: Newest first
-            edges.sort_by(|a, b| b.timestamp_us.cmp(&a.timestamp_us));
+            trace_listing::sort_by_key(&mut edges, descending);
         }
 
         // Apply pagination and enrich with agent names AND payloads
@@ -870,7 +1049,10 @@ pub async fn list_traces(
         // When using cursor-based pagination, skip()/take() starts from 0
         // because the cursor already positions us at the right offset.
         // For offset-based, use the offset parameter.
-        let skip_count = if using_cursor { 0 } else { params.offset };
+        let skip_count = if cursor.is_some() { 0 } else { params.offset };
+        let page_end = edges.len().min(skip_count + params.limit);
+        let has_more = edges.len() > page_end;
+        let last_on_page = (page_end > skip_count).then(|| edges[page_end - 1]);
 
         // Helper to fetch payload for pagination (reuse the earlier helper)
         // Apply pagination and enrich with agent names AND payloads
@@ -881,9 +1063,15 @@ pub async fn list_traces(
             .map(|edge| {
                 let mut view = TraceView::from(edge);
                 view.agent_name = registry.get_display_name(view.agent_id);
+                let access = hydration::span_hydration(&state.hydration, auth.role, hydration_level, &edge);
 
                 // Fetch payload and populate fields using extractors
-                if let Some(payload) = fetch_payload(&edge) {
+                let payload = if access.level == HydrationLevel::Metadata {
+                    None
+                } else {
+                    fetch_payload(&edge)
+                };
+                if let Some(payload) = payload {
                     // Populate provider & model
                     view.provider = payload.system.clone();
                     view.model = payload.request_model.clone()
@@ -907,6 +1095,9 @@ pub async fn list_traces(
                     // Output preview (from completion)
                     view.output_preview = crate::api::payload_extractors::get_output_preview(&payload);
 
+                    view.input_preview = hydration::redact_preview(view.input_preview.take(), access);
+                    view.output_preview = hydration::redact_preview(view.output_preview.take(), access);
+
                     // DEVELOPER EXPERIENCE: Extract meaningful display name
                     // Priority: operation_name > span.name > model name > span type
                     view.display_name = if let Some(ref op_name) = payload.operation_name {
@@ -943,24 +1134,26 @@ pub async fn list_traces(
                     };
 
                     // Set metadata with structured data
-                    let metadata = serde_json::json!({
-                        "prompts": crate::api::payload_extractors::extract_prompts(&payload),
-                        "completions": crate::api::payload_extractors::extract_completions(&payload),
-                        "tool_calls": crate::api::payload_extractors::extract_tool_calls(&payload),
-                        "hyperparameters": crate::api::payload_extractors::extract_hyperparameters(&payload),
-                        "token_breakdown": crate::api::payload_extractors::extract_token_breakdown(&payload),
-                        "model": payload.request_model,
-                        "system": payload.system,
-                        "operation_name": payload.operation_name,
-                        "temperature": payload.temperature,
-                        "top_p": payload.top_p,
-                        "max_tokens": payload.max_tokens,
-                        "confidence": edge.confidence,
-                        "input_tokens": payload.input_tokens,
-                        "output_tokens": payload.output_tokens,
-                        "total_tokens": payload.total_tokens,
-                    });
-                    view.metadata = Some(metadata);
+                    if access.level == HydrationLevel::Full {
+                        let metadata = serde_json::json!({
+                            "prompts": crate::api::payload_extractors::extract_prompts(&payload),
+                            "completions": crate::api::payload_extractors::extract_completions(&payload),
+                            "tool_calls": crate::api::payload_extractors::extract_tool_calls(&payload),
+                            "hyperparameters": crate::api::payload_extractors::extract_hyperparameters(&payload),
+                            "token_breakdown": crate::api::payload_extractors::extract_token_breakdown(&payload),
+                            "model": payload.request_model,
+                            "system": payload.system,
+                            "operation_name": payload.operation_name,
+                            "temperature": payload.temperature,
+                            "top_p": payload.top_p,
+                            "max_tokens": payload.max_tokens,
+                            "confidence": edge.confidence,
+                            "input_tokens": payload.input_tokens,
+                            "output_tokens": payload.output_tokens,
+                            "total_tokens": payload.total_tokens,
+                        });
+                        view.metadata = Some(metadata);
+                    }
                 } else {
                     // No payload - generate basic display name from span type
                     let formatted_type = view.span_type.replace('_', " ");
@@ -978,51 +1171,46 @@ pub async fn list_traces(
                     );
                 }
 
+                if let Some(enrichment) = fetch_enrichment(&edge) {
+                    if view.provider.is_none() {
+                        view.provider = enrichment.provider.clone();
+                    }
+                    view.enrichment = Some(enrichment);
+                }
+
                 view
             })
             .collect();
 
-        // Compute next_cursor for cursor-based pagination (Task 3).
-        // When using cursor mode: if we got a full page AND storage says more exist,
-        // encode the last edge's timestamp/id as the cursor for the next page.
-        let next_cursor = if using_cursor {
-            if paginated.len() >= params.limit {
-                // If storage reported a cursor, use it. Otherwise derive from last edge.
-                cursor_from_storage.or_else(|| {
-                    paginated.last().map(|last| {
-                        // Parse edge_id back from hex span_id (removing 0x prefix)
-                        let eid_hex = last.span_id.trim_start_matches("0x");
-                        format!("{:020}/{}", last.timestamp_us, eid_hex)
-                    })
-                })
-            } else {
-                None // Last page
-            }
+        // Timestamp-ordered pages continue after their last trace, whether
+        // this page was reached by offset or by cursor
+        let next_cursor = if timestamp_order && has_more {
+            last_on_page.map(|edge| TraceCursor::after(&edge, descending).encode())
         } else {
-            // Offset-based mode: provide a cursor hint for clients that want to switch
-            // to cursor-based pagination. Only if there are more items.
-            if params.offset + paginated.len() < total {
-                paginated.last().map(|last| {
-                    let eid_hex = last.span_id.trim_start_matches("0x");
-                    format!("{:020}/{}", last.timestamp_us, eid_hex)
-                })
-            } else {
-                None
-            }
+            None
         };
 
         Ok::<_, ApiError>(TracesResponse {
-            traces: paginated,
+            traces: projection.apply(paginated),
             total,
             limit: params.limit,
             offset: params.offset,
             next_cursor,
+            rehydration_jobs,
+            hydration: hydration_level,
         })
     };
 
     // Execute with timeout
     match timeout(Duration::from_secs(MAX_QUERY_DURATION_SECS), query_future).await {
-        Ok(result) => result.map(Json),
+        Ok(result) => result.map(|response| {
+            let status = if response.rehydration_jobs.is_empty() {
+                StatusCode::OK
+            } else {
+                StatusCode::ACCEPTED
+            };
+            (status, Json(response))
+        }),
         Err(_) => Err(ApiError::RequestTimeout(format!(
             "Query exceeded maximum duration of {} seconds. \
              Try reducing the time range or using pagination.",
@@ -1032,11 +1220,22 @@ pub async fn list_traces(
 }
 
 // Helper function to find an edge by ID or Session ID
-// Returns the edge if found, handling the case where the provided ID is a session_id
+// Returns the edge if found and readable under the caller's access policies,
+// handling the case where the provided ID is a session_id
 pub async fn find_edge_by_id_or_session(
     state: &AppState,
     id: u128,
     tenant_id: u64,
+    access: &AccessFilter,
+) -> Result<Option<AgentFlowEdge>, ApiError> {
+    let edge = lookup_edge_by_id_or_session(state, id, tenant_id).await?;
+    Ok(edge.filter(|edge| access.permits_stored(state, edge)))
+}
+
+async fn lookup_edge_by_id_or_session(
+    state: &AppState,
+    id: u128,
+    tenant_id: u64,
 ) -> Result<Option<AgentFlowEdge>, ApiError> {
     // 1. Try direct lookup as edge_id (fastest)
     if let Some(ref pm) = state.project_manager {
@@ -1107,13 +1306,14 @@ pub async fn get_trace(
     State(state): State<AppState>,
     Path(trace_id): Path<String>,
     auth: axum::Extension<AuthContext>,
+    axum::Extension(access): axum::Extension<AccessFilter>,
 ) -> Result<Json<TraceView>, ApiError> {
     // Parse trace ID (hex format)
     let trace_id_u128 = u128::from_str_radix(trace_id.trim_start_matches("0x"), 16)
         .map_err(|_| ApiError::BadRequest("Invalid trace ID format".into()))?;
 
     // Find edge (handling edge_id vs session_id mismatch)
-    let edge = find_edge_by_id_or_session(&state, trace_id_u128, auth.tenant_id)
+    let edge = find_edge_by_id_or_session(&state, trace_id_u128, auth.tenant_id, &access)
         .await?
         .ok_or_else(|| ApiError::NotFound("Trace not found".into()))?;
 
@@ -1125,9 +1325,12 @@ pub async fn get_trace(
         trace_id_u128, edge.project_id, edge.tenant_id
     );
 
-    // Fetch and attach payload/attributes if available
+    // Fetch and attach payload/attributes if available and the caller's role allows
     // Try to get from the appropriate database (project-specific or main)
-    let payload_result = if let Some(ref pm) = state.project_manager {
+    let hydration = hydration::detail_hydration(&state.hydration, auth.role, &edge);
+    let payload_result = if hydration.level == HydrationLevel::Metadata {
+        None
+    } else if let Some(ref pm) = state.project_manager {
         // Get the project database for this trace
         match pm.get_or_open_project(edge.project_id) {
             Ok(db) => db.get_payload(edge.edge_id).ok().flatten(),
@@ -1159,30 +1362,36 @@ pub async fn get_trace(
                 }
 
                 // Previews
-                trace_view.input_preview =
-                    crate::api::payload_extractors::get_input_preview(&payload);
-                trace_view.output_preview =
-                    crate::api::payload_extractors::get_output_preview(&payload);
+                trace_view.input_preview = hydration::redact_preview(
+                    crate::api::payload_extractors::get_input_preview(&payload),
+                    hydration,
+                );
+                trace_view.output_preview = hydration::redact_preview(
+                    crate::api::payload_extractors::get_output_preview(&payload),
+                    hydration,
+                );
 
                 // Structured metadata
-                let metadata = serde_json::json!({
-                    "prompts": crate::api::payload_extractors::extract_prompts(&payload),
-                    "completions": crate::api::payload_extractors::extract_completions(&payload),
-                    "tool_calls": crate::api::payload_extractors::extract_tool_calls(&payload),
-                    "hyperparameters": crate::api::payload_extractors::extract_hyperparameters(&payload),
-                    "token_breakdown": crate::api::payload_extractors::extract_token_breakdown(&payload),
-                    "model": payload.request_model,
-                    "system": payload.system,
-                    "operation_name": payload.operation_name,
-                    "temperature": payload.temperature,
-                    "top_p": payload.top_p,
-                    "max_tokens": payload.max_tokens,
-                    "confidence": edge.confidence,
-                    "input_tokens": payload.input_tokens,
-                    "output_tokens": payload.output_tokens,
-                    "total_tokens": payload.total_tokens,
-                });
-                trace_view.metadata = Some(metadata);
+                if hydration.level == HydrationLevel::Full {
+                    let metadata = serde_json::json!({
+                        "prompts": crate::api::payload_extractors::extract_prompts(&payload),
+                        "completions": crate::api::payload_extractors::extract_completions(&payload),
+                        "tool_calls": crate::api::payload_extractors::extract_tool_calls(&payload),
+                        "hyperparameters": crate::api::payload_extractors::extract_hyperparameters(&payload),
+                        "token_breakdown": crate::api::payload_extractors::extract_token_breakdown(&payload),
+                        "model": payload.request_model,
+                        "system": payload.system,
+                        "operation_name": payload.operation_name,
+                        "temperature": payload.temperature,
+                        "top_p": payload.top_p,
+                        "max_tokens": payload.max_tokens,
+                        "confidence": edge.confidence,
+                        "input_tokens": payload.input_tokens,
+                        "output_tokens": payload.output_tokens,
+                        "total_tokens": payload.total_tokens,
+                    });
+                    trace_view.metadata = Some(metadata);
+                }
 
                 // DEVELOPER EXPERIENCE: Extract meaningful display name
                 // Priority: operation_name > span.name > model name > span type
@@ -1307,6 +1516,7 @@ pub async fn get_trace_attributes(
     State(state): State<AppState>,
     Path(trace_id): Path<String>,
     auth: axum::Extension<AuthContext>,
+    axum::Extension(access): axum::Extension<AccessFilter>,
 ) -> Result<Json<serde_json::Value>, ApiError> {
     // Parse trace ID (hex format)
     let trace_id = u128::from_str_radix(trace_id.trim_start_matches("0x"), 16)
@@ -1333,6 +1543,18 @@ pub async fn get_trace_attributes(
     };
 
     eprintln!("[ATTRIBUTES] Found edge in project {}", edge.project_id);
+    if !access.permits_stored(&state, &edge) {
+        return Err(ApiError::NotFound("Trace not found".into()));
+    }
+    // Raw attributes carry prompts and completions
+    let hydration = hydration::detail_hydration(&state.hydration, auth.role, &edge);
+    if hydration.level < HydrationLevel::Full {
+        return Err(ApiError::Forbidden(format!(
+            "role '{}' may not read the attributes of trace {:#x}",
+            auth.role.as_str(),
+            trace_id
+        )));
+    }
 
     // Get payload from the appropriate database
     let payload = if let Some(ref pm) = state.project_manager {
@@ -1378,28 +1600,59 @@ pub async fn get_trace_children(
     State(state): State<AppState>,
     Path(trace_id): Path<String>,
     auth: axum::Extension<AuthContext>,
+    axum::Extension(access): axum::Extension<AccessFilter>,
 ) -> Result<Json<Vec<TraceView>>, ApiError> {
     // Parse trace ID
     let trace_id = u128::from_str_radix(trace_id.trim_start_matches("0x"), 16)
         .map_err(|_| ApiError::BadRequest("Invalid trace ID format".into()))?;
 
-    // Verify parent exists and belongs to tenant
+    // Verify parent exists, belongs to tenant and is readable
     let _ = state
         .db
         .get_for_tenant(trace_id, auth.tenant_id)
         .map_err(|e| ApiError::Internal(e.to_string()))?
+        .filter(|parent| access.permits_stored(&state, parent))
         .ok_or_else(|| ApiError::NotFound("Parent trace not found".into()))?;
 
     // Get children
-    let children = state
+    let mut children = state
         .db
         .get_children_for_tenant(trace_id, auth.tenant_id)
         .map_err(|e| ApiError::Internal(e.to_string()))?;
+    access.retain(&state, &mut children);
 
     let views: Vec<TraceView> = children.into_iter().map(TraceView::from).collect();
     Ok(Json(views))
 }
 
+/// GET /api/v1/traces/:trace_id/ancestors
+/// Get the causal ancestors of a span, nearest parent first
+pub async fn get_trace_ancestors(
+    State(state): State<AppState>,
+    Path(trace_id): Path<String>,
+    auth: axum::Extension<AuthContext>,
+    axum::Extension(access): axum::Extension<AccessFilter>,
+) -> Result<Json<Vec<TraceView>>, ApiError> {
+    let trace_id = u128::from_str_radix(trace_id.trim_start_matches("0x"), 16)
+        .map_err(|_| ApiError::BadRequest("Invalid trace ID format".into()))?;
+
+    let _ = state
+        .db
+        .get_for_tenant(trace_id, auth.tenant_id)
+        .map_err(|e| ApiError::Internal(e.to_string()))?
+        .filter(|span| access.permits_stored(&state, span))
+        .ok_or_else(|| ApiError::NotFound("Trace not found".into()))?;
+
+    let mut ancestors = state
+        .db
+        .get_ancestors_for_tenant(trace_id, auth.tenant_id)
+        .map_err(|e| ApiError::Internal(e.to_string()))?;
+    access.retain(&state, &mut ancestors);
+
+    let views: Vec<TraceView> = ancestors.into_iter().map(TraceView::from).collect();
+    Ok(Json(views))
+}
+
 /// GET /api/v1/traces/:trace_id/observations
 /// Get enriched observations (span tree) with attributes
 ///
@@ -1412,6 +1665,7 @@ pub async fn get_trace_observations(
     State(state): State<AppState>,
     Path(trace_id): Path<String>,
     auth: axum::Extension<AuthContext>,
+    axum::Extension(access): axum::Extension<AccessFilter>,
 ) -> Result<Json<Vec<ObservationView>>, ApiError> {
     // Parse trace ID (handle both 0x prefixed and plain hex)
     let trace_id = u128::from_str_radix(trace_id.trim_start_matches("0x"), 16)
@@ -1425,7 +1679,7 @@ pub async fn get_trace_observations(
     // Get the root span - if it doesn't exist, try to find it by looking up children
     // Get the root span - if it doesn't exist, try to find it by looking up children
     // Use find_edge_by_id_or_session to handle both edge IDs and session IDs
-    let root = match find_edge_by_id_or_session(&state, trace_id, auth.tenant_id).await {
+    let root = match find_edge_by_id_or_session(&state, trace_id, auth.tenant_id, &access).await {
         Ok(Some(span)) => {
             eprintln!(
                 "[OBSERVATIONS] ✓ Found span: tenant_id={}, project_id={}",
@@ -1447,7 +1701,7 @@ pub async fn get_trace_observations(
     // This replaces the O(D) BFS loop with O(1) database round-trips
     const MAX_TREE_DEPTH: usize = 1000; // Reasonable depth limit
 
-    let all_spans_with_depth = state
+    let mut all_spans_with_depth = state
         .db
         .get_descendants_with_depth_for_tenant(
             root.edge_id,
@@ -1456,6 +1710,9 @@ pub async fn get_trace_observations(
             MAX_SPANS_PER_TRACE,
         )
         .map_err(|e| ApiError::Internal(e.to_string()))?;
+    if !access.is_unrestricted() {
+        all_spans_with_depth.retain(|(span, _)| access.permits_stored(&state, span));
+    }
 
     if all_spans_with_depth.is_empty() {
         return Err(ApiError::NotFound(format!(
@@ -1490,26 +1747,31 @@ pub async fn get_trace_observations(
         let span_id = span.edge_id;
         let span_id_str = format!("{:#x}", span_id);
 
-        // Fetch attributes from PayloadStore
-        let attributes = match state.db.get_payload(span_id) {
-            Ok(Some(payload_data)) => {
-                // Deserialize JSON attributes
-                match serde_json::from_slice::<serde_json::Value>(&payload_data) {
-                    Ok(attrs) => Some(attrs),
-                    Err(e) => {
-                        tracing::warn!(
-                            "Failed to deserialize attributes for span {}: {}",
-                            span_id_str,
-                            e
-                        );
-                        None
+        // Fetch attributes from PayloadStore if the caller's role allows
+        let hydration = hydration::detail_hydration(&state.hydration, auth.role, &span);
+        let attributes = if hydration.level < HydrationLevel::Full {
+            None
+        } else {
+            match state.db.get_payload(span_id) {
+                Ok(Some(payload_data)) => {
+                    // Deserialize JSON attributes
+                    match serde_json::from_slice::<serde_json::Value>(&payload_data) {
+                        Ok(attrs) => Some(attrs),
+                        Err(e) => {
+                            tracing::warn!(
+                                "Failed to deserialize attributes for span {}: {}",
+                                span_id_str,
+                                e
+                            );
+                            None
+                        }
                     }
                 }
-            }
-            Ok(None) => None,
-            Err(e) => {
-                tracing::warn!("Failed to fetch attributes for span {}: {}", span_id_str, e);
-                None
+                Ok(None) => None,
+                Err(e) => {
+                    tracing::warn!("Failed to fetch attributes for span {}: {}", span_id_str, e);
+                    None
+                }
             }
         };
 
@@ -1561,6 +1823,7 @@ pub async fn get_trace_tree(
     State(state): State<AppState>,
     Path(trace_id): Path<String>,
     auth: axum::Extension<AuthContext>,
+    axum::Extension(access): axum::Extension<AccessFilter>,
 ) -> Result<Json<TraceTreeResponse>, ApiError> {
     // Parse trace ID
     let trace_id = u128::from_str_radix(trace_id.trim_start_matches("0x"), 16)
@@ -1568,7 +1831,7 @@ pub async fn get_trace_tree(
 
     // Get all observations first
     let observations =
-        get_trace_observations_internal(state.clone(), trace_id, auth.tenant_id).await?;
+        get_trace_observations_internal(state.clone(), trace_id, auth.tenant_id, &access).await?;
 
     if observations.is_empty() {
         return Err(ApiError::NotFound("No spans found for trace".into()));
@@ -1591,9 +1854,10 @@ async fn get_trace_observations_internal(
     state: AppState,
     trace_id: u128,
     tenant_id: u64,
+    access: &AccessFilter,
 ) -> Result<Vec<ObservationView>, ApiError> {
     // Get the root span using fallback lookup
-    let root = find_edge_by_id_or_session(&state, trace_id, tenant_id)
+    let root = find_edge_by_id_or_session(&state, trace_id, tenant_id, access)
         .await?
         .ok_or_else(|| ApiError::NotFound(format!("Trace not found: {:#x}", trace_id)))?;
 
@@ -1604,7 +1868,7 @@ async fn get_trace_observations_internal(
     // This replaces the O(D) BFS loop with O(1) database round-trips
     const MAX_TREE_DEPTH: usize = 1000; // Reasonable depth limit
 
-    let all_spans_with_depth = state
+    let mut all_spans_with_depth = state
         .db
         .get_descendants_with_depth_for_tenant(
             root_id,
@@ -1613,6 +1877,9 @@ async fn get_trace_observations_internal(
             MAX_SPANS_PER_TRACE,
         )
         .map_err(|e| ApiError::Internal(e.to_string()))?;
+    if !access.is_unrestricted() {
+        all_spans_with_depth.retain(|(span, _)| access.permits_stored(&state, span));
+    }
 
     if all_spans_with_depth.is_empty() {
         return Err(ApiError::NotFound(format!(
@@ -1775,18 +2042,64 @@ pub async fn get_stats(
     }))
 }
 
+/// Window of the dashboard summary served from the rollup tables
+#[derive(Debug, Deserialize)]
+pub struct DashboardSummaryParams {
+    /// Window start (microseconds since epoch, default: 24 hours before `end_ts`)
+    pub start_ts: Option<u64>,
+    /// Window end, exclusive (microseconds since epoch, default: now)
+    pub end_ts: Option<u64>,
+    pub project_id: Option<u16>,
+    pub agent_id: Option<u64>,
+    pub model: Option<String>,
+}
+
 /// GET /api/v1/dashboard/summary
 ///
 /// Pre-computed dashboard summary — O(1) instead of full-scan aggregation.
 /// Returns total traces, tokens, unique models, unique sessions, and time range.
 /// Updated incrementally on every edge insertion (Task 9).
+///
+/// `window` covers `[start_ts, end_ts)` for the caller's tenant: counts, tokens,
+/// cost, errors and latency percentiles, broken down per project, agent and
+/// model. It is read from the minute/hour/day rollups, with raw edges merged in
+/// for the partial minutes at the window edges, so it stays exact.
 pub async fn get_dashboard_summary(
     State(state): State<AppState>,
-    _auth: axum::Extension<AuthContext>,
+    Query(params): Query<DashboardSummaryParams>,
+    auth: axum::Extension<AuthContext>,
 ) -> Result<Json<serde_json::Value>, ApiError> {
     let summary = state.db.get_dashboard_summary();
 
+    let now = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|d| d.as_micros() as u64)
+        .unwrap_or(0);
+    let end_ts = params.end_ts.unwrap_or(now);
+    let start_ts = params
+        .start_ts
+        .unwrap_or_else(|| end_ts.saturating_sub(ONE_DAY_MICROS));
+    if start_ts >= end_ts {
+        return Err(ApiError::BadRequest(
+            "start_ts must be before end_ts".to_string(),
+        ));
+    }
+
+    let filter = agentreplay_storage::RollupFilter {
+        tenant_id: auth.tenant_id,
+        project_id: params.project_id,
+        agent_id: params.agent_id,
+        model: params.model.clone(),
+    };
+    let window = run_query(&state, {
+        let state = state.clone();
+        move || summarize_rollups(&state, start_ts, end_ts, &filter)
+    })
+    .await
+    .map_err(|e| ApiError::Internal(format!("Rollup query panicked: {}", e)))??;
+
     Ok(Json(serde_json::json!({
+        "window": rollup_window_json(&state, start_ts, end_ts, &window),
         "total_traces": summary.total_traces,
         "total_sessions": summary.total_sessions,
         "total_tokens": summary.total_tokens,
@@ -1799,6 +2112,128 @@ pub async fn get_dashboard_summary(
     })))
 }
 
+/// Rollup summary across the main database or every project database
+fn summarize_rollups(
+    state: &AppState,
+    start_ts: u64,
+    end_ts: u64,
+    filter: &agentreplay_storage::RollupFilter,
+) -> Result<agentreplay_storage::RollupSummary, ApiError> {
+    let Some(ref pm) = state.project_manager else {
+        return state
+            .db
+            .summarize_rollups(start_ts, end_ts, filter)
+            .map_err(|e| ApiError::Internal(e.to_string()));
+    };
+
+    let project_ids = match filter.project_id {
+        Some(project_id) => vec![project_id],
+        None => pm
+            .discover_projects()
+            .map_err(|e| ApiError::Internal(e.to_string()))?,
+    };
+    let mut summary = agentreplay_storage::RollupSummary::default();
+    for project_id in project_ids {
+        let db = pm
+            .get_or_open_project(project_id)
+            .map_err(|e| ApiError::Internal(e.to_string()))?;
+        let project_summary = db
+            .summarize_rollups(start_ts, end_ts, filter)
+            .map_err(|e| ApiError::Internal(e.to_string()))?;
+        summary.merge(&project_summary);
+    }
+    Ok(summary)
+}
+
+fn rollup_bucket_json(bucket: &agentreplay_storage::RollupBucket) -> serde_json::Value {
+    let latency_ms = |q: f64| bucket.latency_quantile_us(q) / 1000.0;
+    let error_rate = if bucket.request_count > 0 {
+        bucket.error_count as f64 / bucket.request_count as f64
+    } else {
+        0.0
+    };
+    serde_json::json!({
+        "request_count": bucket.request_count,
+        "error_count": bucket.error_count,
+        "error_rate": error_rate,
+        "total_tokens": bucket.total_tokens,
+        "avg_latency_ms": if bucket.request_count > 0 {
+            bucket.total_duration_us as f64 / bucket.request_count as f64 / 1000.0
+        } else {
+            0.0
+        },
+        "p50_latency_ms": latency_ms(0.50),
+        "p90_latency_ms": latency_ms(0.90),
+        "p95_latency_ms": latency_ms(0.95),
+        "p99_latency_ms": latency_ms(0.99),
+    })
+}
+
+/// JSON for the `window` section of the dashboard summary
+///
+/// Cost is derived per model from the token totals, except for spans whose
+/// cost was recomputed, which count the stored cost. Only the total and the
+/// per-model rows carry it.
+fn rollup_window_json(
+    state: &AppState,
+    start_ts: u64,
+    end_ts: u64,
+    summary: &agentreplay_storage::RollupSummary,
+) -> serde_json::Value {
+    use rust_decimal::prelude::ToPrimitive;
+
+    let cost_micros = |model: &str, tokens: u64| {
+        let model = (!model.is_empty()).then_some(model);
+        (state.cost_tracker.estimate_cost(model, tokens) * rust_decimal::Decimal::from(1_000_000))
+            .round()
+            .to_u64()
+            .unwrap_or(0)
+    };
+
+    let mut total_cost_micros = 0u64;
+    let by_model: Vec<_> = summary
+        .by_model
+        .iter()
+        .map(|(model, bucket)| {
+            let cost = cost_micros(model, bucket.unpriced_tokens()) + bucket.repriced_cost_micros;
+            total_cost_micros += cost;
+            let mut row = rollup_bucket_json(bucket);
+            row["model"] = serde_json::json!(model);
+            row["total_cost_micros"] = serde_json::json!(cost);
+            row
+        })
+        .collect();
+    let by_agent: Vec<_> = summary
+        .by_agent
+        .iter()
+        .map(|(agent_id, bucket)| {
+            let mut row = rollup_bucket_json(bucket);
+            row["agent_id"] = serde_json::json!(agent_id);
+            row["agent_name"] = serde_json::json!(state.agent_registry.get_display_name(*agent_id));
+            row
+        })
+        .collect();
+    let by_project: Vec<_> = summary
+        .by_project
+        .iter()
+        .map(|(project_id, bucket)| {
+            let mut row = rollup_bucket_json(bucket);
+            row["project_id"] = serde_json::json!(project_id);
+            row
+        })
+        .collect();
+
+    let mut window = rollup_bucket_json(&summary.totals);
+    window["start_ts"] = serde_json::json!(start_ts);
+    window["end_ts"] = serde_json::json!(end_ts);
+    window["total_cost_micros"] = serde_json::json!(total_cost_micros);
+    window["raw_edges"] = serde_json::json!(summary.raw_edges);
+    window["by_model"] = serde_json::json!(by_model);
+    window["by_agent"] = serde_json::json!(by_agent);
+    window["by_project"] = serde_json::json!(by_project);
+    window
+}
+
 /// Batch fetch span details by IDs
 /// POST /api/v1/spans/batch
 ///
@@ -1846,6 +2281,7 @@ pub struct BatchSpanResponse {
 pub async fn get_spans_batch(
     State(state): State<AppState>,
     auth: axum::Extension<AuthContext>,
+    axum::Extension(access): axum::Extension<AccessFilter>,
     Json(request): Json<BatchSpanRequest>,
 ) -> Result<Json<BatchSpanResponse>, ApiError> {
     const MAX_BATCH_SIZE: usize = 10_000;
@@ -1893,6 +2329,10 @@ pub async fn get_spans_batch(
                         .and_then(|payload_data| {
                             serde_json::from_slice::<serde_json::Value>(&payload_data).ok()
                         });
+                if !access.permits(&edge, attributes.as_ref()) {
+                    not_found.push(format!("{:#x}", span_id));
+                    continue;
+                }
 
                 let name = attributes
                     .as_ref()
@@ -1939,12 +2379,22 @@ pub async fn get_spans_batch(
 
 /// Health check endpoint
 /// GET /health
-pub async fn health_check() -> impl IntoResponse {
-    Json(serde_json::json!({
-        "status": "healthy",
-        "service": "agentreplay-server",
-        "version": env!("CARGO_PKG_VERSION"),
-    }))
+///
+/// Returns 503 once shutdown has begun so load balancers stop routing here.
+pub async fn health_check(State(state): State<AppState>) -> impl IntoResponse {
+    let (status, health) = if state.shutdown.is_draining() {
+        (StatusCode::SERVICE_UNAVAILABLE, "draining")
+    } else {
+        (StatusCode::OK, "healthy")
+    };
+    (
+        status,
+        Json(serde_json::json!({
+            "status": health,
+            "service": "agentreplay-server",
+            "version": env!("CARGO_PKG_VERSION"),
+        })),
+    )
 }
 
 #[cfg(test)]
@@ -13,13 +13,51 @@
 // You should have received a copy of the GNU Affero General Public License
 // along with this program. If not, see <https://www.gnu.org/licenses/>.
 
+//! Real-time trace streaming over WebSocket and SSE
+//!
+//! Both endpoints accept filter query parameters so a client only receives
+//! the edges it cares about (comma-separated lists match any of the values):
+//!
+//! | Parameter | Example |
+//! |-----------|---------|
+//! | `project_id` | `?project_id=3,4` |
+//! | `agent_id` | `?agent_id=7` |
+//! | `session_id` | `?session_id=42` |
+//! | `span_type` | `?span_type=llm,tool` (case-insensitive) |
+//! | `min_latency_ms` | `?min_latency_ms=250` |
+//!
+//! Span types are named in snake_case (`tool_call`, `retrieval`, ...), or by
+//! the groups `llm` (planning, reasoning and synthesis spans) and `tool`
+//! (tool calls and responses). Unknown span types and IDs that don't parse
+//! are rejected with 400.
+//!
+//! `/api/v1/traces/stream` (SSE) is a one-way feed. `/ws/traces` is
+//! bidirectional: after the `Connected` message the client may send control
+//! commands as JSON text frames, tagged by `type`:
+//!
+//! | Client message | Effect |
+//! |----------------|--------|
+//! | `{"type":"Pause"}` | Stop forwarding trace events (events are dropped, not buffered) |
+//! | `{"type":"Resume"}` | Resume forwarding trace events |
+//! | `{"type":"Subscribe","session_ids":[1],"agent_ids":[7]}` | Replace the filter; also accepts `project_ids`, `span_types` and `min_latency_ms` (empty list = any) |
+//! | `{"type":"Unsubscribe"}` | Restore the filter given in the query string |
+//! | `{"type":"Hydrate","edge_id":"0x1a2b","request_id":"r1"}` | Fetch the stored payload for one edge |
+//!
+//! Every command is answered with an `Ack` (current pause state and filter),
+//! a `Payload` (for `Hydrate`) or an `Error` message. Trace events themselves
+//! are sent untagged, as before. Filtering happens server-side so paused or
+//! narrowly-subscribed clients don't pay for the full stream.
+
+use std::collections::HashSet;
 use std::convert::Infallible;
-use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+use std::time::{Duration, Instant};
 
+use agentreplay_core::clock::now_us;
+use agentreplay_core::{AgentFlowEdge, SpanType};
 use axum::{
     extract::{
         ws::{Message, WebSocket, WebSocketUpgrade},
-        State,
+        Query, State,
     },
     response::{
         sse::{Event, KeepAlive, Sse},
@@ -27,28 +65,42 @@ use axum::{
     },
     Extension,
 };
-use agentreplay_core::{AgentFlowEdge, SpanType};
 use futures::{stream::Stream, SinkExt, StreamExt as FuturesStreamExt};
-use serde::Serialize;
+use serde::{Deserialize, Serialize};
 use tokio::sync::broadcast;
 use tokio::time::interval;
 use tracing::{debug, error, info, warn};
 
-use crate::{api::AppState, auth::AuthContext};
+use crate::{
+    access_policy::{stored_payload, AccessFilter},
+    api::{ApiError, AppState},
+    auth::AuthContext,
+};
 
 /// WebSocket endpoint that streams newly ingested traces in real time.
 pub async fn ws_traces(
     ws: WebSocketUpgrade,
     State(state): State<AppState>,
     Extension(auth): Extension<AuthContext>,
-) -> impl IntoResponse {
+    Extension(access): Extension<AccessFilter>,
+    Query(params): Query<StreamFilterParams>,
+) -> Result<impl IntoResponse, ApiError> {
     info!("WebSocket upgrade requested for tenant {}", auth.tenant_id);
-    ws.on_upgrade(move |socket| handle_trace_stream(socket, state, auth))
+    let filter = StreamFilter::try_from(params).map_err(ApiError::BadRequest)?;
+    Ok(ws.on_upgrade(move |socket| handle_trace_stream(socket, state, auth, access, filter)))
 }
 
-async fn handle_trace_stream(socket: WebSocket, state: AppState, auth: AuthContext) {
+async fn handle_trace_stream(
+    socket: WebSocket,
+    state: AppState,
+    auth: AuthContext,
+    access: AccessFilter,
+    base_filter: StreamFilter,
+) {
     let (mut sender, mut receiver) = socket.split();
     let mut rx = state.trace_broadcaster.subscribe();
+    let mut paused = false;
+    let mut filter = base_filter.clone();
 
     // Heartbeat: ping every 30 seconds, timeout after 60 seconds
     let mut ping_interval = interval(Duration::from_secs(30));
@@ -57,7 +109,7 @@ async fn handle_trace_stream(socket: WebSocket, state: AppState, auth: AuthConte
     if sender
         .send(Message::Text(
             serde_json::to_string(&ServerMessage::Connected {
-                timestamp: current_timestamp_us(),
+                timestamp: now_us(),
             })
             .unwrap_or_else(|_| "{\"type\":\"Connected\"}".to_string()),
         ))
@@ -98,7 +150,22 @@ async fn handle_trace_stream(socket: WebSocket, state: AppState, auth: AuthConte
                         last_pong = Instant::now();
                     }
                     Some(Ok(Message::Text(text))) => {
-                        debug!("Ignoring client text message: {}", text);
+                        let reply = match serde_json::from_str::<ClientMessage>(&text) {
+                            Ok(command) => {
+                                debug!("WebSocket control command from tenant {}: {:?}", auth.tenant_id, command);
+                                handle_client_message(command, &state, &auth, &access, &base_filter, &mut paused, &mut filter)
+                            }
+                            Err(err) => ServerMessage::Error {
+                                message: format!("Invalid control message: {}", err),
+                            },
+                        };
+
+                        let json = serde_json::to_string(&reply)
+                            .unwrap_or_else(|_| "{\"type\":\"Error\"}".to_string());
+                        if sender.send(Message::Text(json)).await.is_err() {
+                            info!("WebSocket client disconnected (tenant {})", auth.tenant_id);
+                            break;
+                        }
                     }
                     Some(Ok(Message::Binary(_))) => {
                         debug!("Ignoring binary message from client");
@@ -112,11 +179,15 @@ async fn handle_trace_stream(socket: WebSocket, state: AppState, auth: AuthConte
             event = rx.recv() => {
                 match event {
                     Ok(edge) => {
-                        if edge.tenant_id != auth.tenant_id {
+                        if paused
+                            || !streamable(&edge, auth.tenant_id, &filter, &access, || {
+                                stored_payload(&state, &edge)
+                            })
+                        {
                             continue;
                         }
 
-                        let payload = match serde_json::to_string(&TraceEvent::from_edge(edge)) {
+                        let payload = match serde_json::to_string(&TraceEvent::from_edge(edge, &state)) {
                             Ok(json) => json,
                             Err(err) => {
                                 error!("Failed to serialise trace event: {}", err);
@@ -143,10 +214,287 @@ async fn handle_trace_stream(socket: WebSocket, state: AppState, auth: AuthConte
     }
 }
 
+/// Apply a control command to the connection state and build the reply
+fn handle_client_message(
+    command: ClientMessage,
+    state: &AppState,
+    auth: &AuthContext,
+    access: &AccessFilter,
+    base_filter: &StreamFilter,
+    paused: &mut bool,
+    filter: &mut StreamFilter,
+) -> ServerMessage {
+    match command {
+        ClientMessage::Pause => *paused = true,
+        ClientMessage::Resume => *paused = false,
+        ClientMessage::Subscribe {
+            project_ids,
+            session_ids,
+            agent_ids,
+            span_types,
+            min_latency_ms,
+        } => {
+            let span_types = match parse_span_types(span_types.iter().map(String::as_str)) {
+                Ok(span_types) => span_types,
+                Err(message) => return ServerMessage::Error { message },
+            };
+            *filter = StreamFilter {
+                project_ids: project_ids.into_iter().collect(),
+                session_ids: session_ids.into_iter().collect(),
+                agent_ids: agent_ids.into_iter().collect(),
+                span_types,
+                min_latency_ms,
+            };
+        }
+        ClientMessage::Unsubscribe => *filter = base_filter.clone(),
+        ClientMessage::Hydrate {
+            edge_id,
+            request_id,
+        } => return hydrate_payload(state, auth, access, &edge_id, request_id),
+    }
+
+    ServerMessage::Ack {
+        paused: *paused,
+        filter: filter.clone(),
+    }
+}
+
+/// Whether a broadcast edge is sent to a subscriber of `tenant_id`
+///
+/// `load` fetches the edge's payload if an access policy needs its
+/// attributes.
+fn streamable(
+    edge: &AgentFlowEdge,
+    tenant_id: u64,
+    filter: &StreamFilter,
+    access: &AccessFilter,
+    load: impl FnOnce() -> Option<Vec<u8>>,
+) -> bool {
+    edge.tenant_id == tenant_id && filter.matches(edge) && access.permits_with(edge, load)
+}
+
+/// Look up the stored payload of a single edge the caller may read
+fn hydrate_payload(
+    state: &AppState,
+    auth: &AuthContext,
+    access: &AccessFilter,
+    edge_id: &str,
+    request_id: Option<String>,
+) -> ServerMessage {
+    let id = match u128::from_str_radix(edge_id.trim_start_matches("0x"), 16) {
+        Ok(id) => id,
+        Err(_) => {
+            return ServerMessage::Error {
+                message: format!("Invalid edge_id: {}", edge_id),
+            }
+        }
+    };
+
+    let edge = if let Some(ref pm) = state.project_manager {
+        pm.get_edge_for_tenant(id, auth.tenant_id).ok().flatten()
+    } else {
+        state.db.get_for_tenant(id, auth.tenant_id).ok().flatten()
+    };
+
+    let Some(edge) = edge.filter(|edge| access.permits_with(edge, || stored_payload(state, edge)))
+    else {
+        return ServerMessage::Error {
+            message: format!("Edge {} not found", edge_id),
+        };
+    };
+
+    let payload_bytes = stored_payload(state, &edge);
+
+    let payload = payload_bytes.map(|bytes| {
+        serde_json::from_slice::<serde_json::Value>(&bytes).unwrap_or_else(|_| {
+            serde_json::Value::String(String::from_utf8_lossy(&bytes).into_owned())
+        })
+    });
+
+    ServerMessage::Payload {
+        edge_id: format!("{:#x}", edge.edge_id),
+        request_id,
+        payload,
+    }
+}
+
+/// Filter query parameters accepted by `/api/v1/traces/stream` and `/ws/traces`
+///
+/// List parameters are comma-separated (e.g. `span_type=llm,tool`).
+#[derive(Debug, Default, Deserialize)]
+pub struct StreamFilterParams {
+    pub project_id: Option<String>,
+    pub agent_id: Option<String>,
+    pub session_id: Option<String>,
+    pub span_type: Option<String>,
+    pub min_latency_ms: Option<f64>,
+}
+
+/// Server-side filter applied to a single stream subscription
+///
+/// Empty sets match everything.
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct StreamFilter {
+    pub project_ids: HashSet<u16>,
+    pub session_ids: HashSet<u64>,
+    pub agent_ids: HashSet<u64>,
+    #[serde(serialize_with = "serialize_span_types")]
+    pub span_types: HashSet<SpanType>,
+    pub min_latency_ms: Option<f64>,
+}
+
+impl StreamFilter {
+    pub fn matches(&self, edge: &AgentFlowEdge) -> bool {
+        (self.project_ids.is_empty() || self.project_ids.contains(&edge.project_id))
+            && (self.session_ids.is_empty() || self.session_ids.contains(&edge.session_id))
+            && (self.agent_ids.is_empty() || self.agent_ids.contains(&edge.agent_id))
+            && (self.span_types.is_empty() || self.span_types.contains(&edge.get_span_type()))
+            && self
+                .min_latency_ms
+                .is_none_or(|min_ms| edge.duration_us as f64 / 1_000.0 >= min_ms)
+    }
+}
+
+impl TryFrom<StreamFilterParams> for StreamFilter {
+    type Error = String;
+
+    fn try_from(params: StreamFilterParams) -> Result<Self, String> {
+        Ok(Self {
+            project_ids: parse_id_list("project_id", params.project_id.as_deref())?,
+            session_ids: parse_id_list("session_id", params.session_id.as_deref())?,
+            agent_ids: parse_id_list("agent_id", params.agent_id.as_deref())?,
+            span_types: parse_span_types(
+                params
+                    .span_type
+                    .as_deref()
+                    .unwrap_or_default()
+                    .split(',')
+                    .map(str::trim)
+                    .filter(|t| !t.is_empty()),
+            )?,
+            min_latency_ms: params.min_latency_ms,
+        })
+    }
+}
+
+/// Parse a comma-separated list of IDs given as query parameter `param`
+fn parse_id_list<T: std::str::FromStr + std::hash::Hash + Eq>(
+    param: &str,
+    list: Option<&str>,
+) -> Result<HashSet<T>, String> {
+    list.unwrap_or_default()
+        .split(',')
+        .map(str::trim)
+        .filter(|id| !id.is_empty())
+        .map(|id| {
+            id.parse()
+                .map_err(|_| format!("Invalid {} '{}'", param, id))
+        })
+        .collect()
+}
+
+/// Span types by their filter name
+const SPAN_TYPE_NAMES: &[(&str, SpanType)] = &[
+    ("root", SpanType::Root),
+    ("planning", SpanType::Planning),
+    ("reasoning", SpanType::Reasoning),
+    ("tool_call", SpanType::ToolCall),
+    ("tool_response", SpanType::ToolResponse),
+    ("synthesis", SpanType::Synthesis),
+    ("response", SpanType::Response),
+    ("error", SpanType::Error),
+    ("retrieval", SpanType::Retrieval),
+    ("embedding", SpanType::Embedding),
+    ("http_call", SpanType::HttpCall),
+    ("database", SpanType::Database),
+    ("function", SpanType::Function),
+    ("reranking", SpanType::Reranking),
+    ("parsing", SpanType::Parsing),
+    ("generation", SpanType::Generation),
+    ("custom", SpanType::Custom),
+];
+
+/// Resolve span type filter names (case-insensitive), expanding the `llm`
+/// and `tool` groups
+fn parse_span_types<'a>(
+    names: impl IntoIterator<Item = &'a str>,
+) -> Result<HashSet<SpanType>, String> {
+    let mut span_types = HashSet::new();
+    for name in names {
+        match name.to_ascii_lowercase().as_str() {
+            // Ingestion types LLM and chat calls as planning
+            "llm" => {
+                span_types.extend([SpanType::Planning, SpanType::Reasoning, SpanType::Synthesis])
+            }
+            "tool" => span_types.extend([SpanType::ToolCall, SpanType::ToolResponse]),
+            lower => {
+                let (_, span_type) = SPAN_TYPE_NAMES
+                    .iter()
+                    .find(|(known, _)| *known == lower)
+                    .ok_or_else(|| format!("Unknown span_type '{}'", name))?;
+                span_types.insert(*span_type);
+            }
+        }
+    }
+    Ok(span_types)
+}
+
+fn serialize_span_types<S: serde::Serializer>(
+    span_types: &HashSet<SpanType>,
+    serializer: S,
+) -> Result<S::Ok, S::Error> {
+    let names: Vec<&str> = SPAN_TYPE_NAMES
+        .iter()
+        .filter(|(_, span_type)| span_types.contains(span_type))
+        .map(|(name, _)| *name)
+        .collect();
+    names.serialize(serializer)
+}
+
+/// Control commands sent by WebSocket clients
+#[derive(Debug, Deserialize)]
+#[serde(tag = "type")]
+enum ClientMessage {
+    Pause,
+    Resume,
+    Subscribe {
+        #[serde(default)]
+        project_ids: Vec<u16>,
+        #[serde(default)]
+        session_ids: Vec<u64>,
+        #[serde(default)]
+        agent_ids: Vec<u64>,
+        #[serde(default)]
+        span_types: Vec<String>,
+        #[serde(default)]
+        min_latency_ms: Option<f64>,
+    },
+    Unsubscribe,
+    Hydrate {
+        edge_id: String,
+        #[serde(default)]
+        request_id: Option<String>,
+    },
+}
+
 #[derive(Serialize)]
 #[serde(tag = "type")]
 enum ServerMessage {
-    Connected { timestamp: u64 },
+    Connected {
+        timestamp: u64,
+    },
+    Ack {
+        paused: bool,
+        filter: StreamFilter,
+    },
+    Payload {
+        edge_id: String,
+        request_id: Option<String>,
+        payload: Option<serde_json::Value>,
+    },
+    Error {
+        message: String,
+    },
 }
 
 #[derive(Serialize)]
@@ -161,11 +509,24 @@ struct TraceEvent {
     status: String,
     agent_id: u64,
     session_id: u64,
+    project_id: u16,
+    /// Model family derived at ingest time, e.g. "gpt" or "claude"
+    #[serde(skip_serializing_if = "Option::is_none")]
+    model_family: Option<String>,
 }
 
 impl TraceEvent {
-    fn from_edge(edge: AgentFlowEdge) -> Self {
+    fn from_edge(edge: AgentFlowEdge, state: &AppState) -> Self {
         let span = edge.get_span_type();
+        // Enrichment is stored before the edge is broadcast (no payload I/O)
+        let enrichment = match &state.project_manager {
+            Some(pm) => pm
+                .get_or_open_project(edge.project_id)
+                .ok()
+                .and_then(|db| db.get_edge_enrichment(edge.edge_id).ok())
+                .flatten(),
+            None => state.db.get_edge_enrichment(edge.edge_id).ok().flatten(),
+        };
         Self {
             edge_id: format!("{:#x}", edge.edge_id),
             timestamp_us: edge.timestamp_us,
@@ -176,11 +537,17 @@ impl TraceEvent {
             cost: estimate_edge_cost(&edge),
             status: if edge.is_deleted() || matches!(span, SpanType::Error) {
                 "error".to_string()
+            } else if edge.is_in_progress() {
+                "in_progress".to_string()
+            } else if edge.is_abandoned() {
+                "abandoned".to_string()
             } else {
                 "success".to_string()
             },
             agent_id: edge.agent_id,
             session_id: edge.session_id,
+            project_id: edge.project_id,
+            model_family: enrichment.and_then(|e| e.model_family),
         }
     }
 }
@@ -190,35 +557,31 @@ fn estimate_edge_cost(edge: &AgentFlowEdge) -> f64 {
     (edge.token_count as f64 / 1_000.0) * PRICE_PER_1K_TOKENS_USD
 }
 
-fn current_timestamp_us() -> u64 {
-    SystemTime::now()
-        .duration_since(UNIX_EPOCH)
-        .map(|d| d.as_micros().min(u128::from(u64::MAX)) as u64)
-        .unwrap_or(0)
-}
-
 /// Server-Sent Events endpoint for streaming traces (better alternative to WebSocket for unidirectional streams)
 pub async fn sse_traces(
     State(state): State<AppState>,
     Extension(auth): Extension<AuthContext>,
-) -> Sse<impl Stream<Item = Result<Event, Infallible>>> {
+    Extension(access): Extension<AccessFilter>,
+    Query(params): Query<StreamFilterParams>,
+) -> Result<Sse<impl Stream<Item = Result<Event, Infallible>>>, ApiError> {
     info!("SSE trace stream requested for tenant {}", auth.tenant_id);
 
+    let filter = StreamFilter::try_from(params).map_err(ApiError::BadRequest)?;
     let mut rx = state.trace_broadcaster.subscribe();
     let tenant_id = auth.tenant_id;
 
     let stream = async_stream::stream! {
         loop {
             match rx.recv().await {
-                Ok(edge) if edge.tenant_id == tenant_id => {
-                    match serde_json::to_string(&TraceEvent::from_edge(edge)) {
+                Ok(edge) if streamable(&edge, tenant_id, &filter, &access, || stored_payload(&state, &edge)) => {
+                    match serde_json::to_string(&TraceEvent::from_edge(edge, &state)) {
                         Ok(json) => yield Ok(Event::default().data(json)),
                         Err(err) => {
                             error!("Failed to serialize trace event: {}", err);
                         }
                     }
                 }
-                Ok(_) => {}, // Different tenant
+                Ok(_) => {}, // Different tenant, filtered out or not readable
                 Err(broadcast::error::RecvError::Lagged(skipped)) => {
                     warn!("SSE stream lagged for tenant {} (skipped {} events)", tenant_id, skipped);
                 }
@@ -227,5 +590,148 @@ pub async fn sse_traces(
         }
     };
 
-    Sse::new(stream).keep_alive(KeepAlive::default())
+    Ok(Sse::new(stream).keep_alive(KeepAlive::default()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_client_messages() {
+        assert!(matches!(
+            serde_json::from_str::<ClientMessage>(r#"{"type":"Pause"}"#).unwrap(),
+            ClientMessage::Pause
+        ));
+
+        match serde_json::from_str::<ClientMessage>(r#"{"type":"Subscribe","session_ids":[42]}"#)
+            .unwrap()
+        {
+            ClientMessage::Subscribe {
+                session_ids,
+                agent_ids,
+                span_types,
+                ..
+            } => {
+                assert_eq!(session_ids, vec![42]);
+                assert!(agent_ids.is_empty());
+                assert!(span_types.is_empty());
+            }
+            other => panic!("unexpected message: {:?}", other),
+        }
+
+        assert!(serde_json::from_str::<ClientMessage>(r#"{"type":"Explode"}"#).is_err());
+    }
+
+    #[test]
+    fn test_stream_filter_matches() {
+        let edge = AgentFlowEdge::new(1, 0, 7, 42, SpanType::Root, 0);
+
+        assert!(StreamFilter::default().matches(&edge));
+
+        let mut filter = StreamFilter::default();
+        filter.session_ids.insert(42);
+        assert!(filter.matches(&edge));
+
+        filter.agent_ids.insert(8);
+        assert!(!filter.matches(&edge));
+    }
+
+    #[test]
+    fn test_stream_respects_access_policies() {
+        let mut edge = AgentFlowEdge::new(1, 0, 7, 42, SpanType::Root, 0);
+        edge.has_payload = 1;
+        let filter = StreamFilter::default();
+        let access = AccessFilter::for_teams(&["search"]);
+        let team = |team: &str| {
+            let payload = format!(r#"{{"team":"{}"}}"#, team).into_bytes();
+            move || Some(payload)
+        };
+
+        assert!(streamable(&edge, 1, &filter, &access, team("search")));
+        assert!(!streamable(&edge, 1, &filter, &access, team("ranking")));
+        assert!(!streamable(
+            &edge,
+            2,
+            &filter,
+            &AccessFilter::default(),
+            team("search")
+        ));
+    }
+
+    #[test]
+    fn test_stream_filter_from_query_params() {
+        let mut edge = AgentFlowEdge::new(1, 3, 7, 42, SpanType::Planning, 0);
+        edge.duration_us = 300_000;
+
+        let filter = StreamFilter::try_from(StreamFilterParams {
+            project_id: Some("3, 4".to_string()),
+            span_type: Some("Planning,tool".to_string()),
+            min_latency_ms: Some(250.0),
+            ..Default::default()
+        })
+        .unwrap();
+        assert!(filter.matches(&edge));
+
+        edge.duration_us = 100_000;
+        assert!(!filter.matches(&edge));
+
+        let other_project = StreamFilter::try_from(StreamFilterParams {
+            project_id: Some("5".to_string()),
+            ..Default::default()
+        })
+        .unwrap();
+        assert!(!other_project.matches(&AgentFlowEdge::new(1, 3, 7, 42, SpanType::Root, 0)));
+    }
+
+    #[test]
+    fn test_span_type_names() {
+        let filter = |span_type: &str| {
+            StreamFilter::try_from(StreamFilterParams {
+                span_type: Some(span_type.to_string()),
+                ..Default::default()
+            })
+        };
+        let edge = |span_type| AgentFlowEdge::new(1, 3, 7, 42, span_type, 0);
+
+        let llm = filter("LLM").unwrap();
+        assert!(llm.matches(&edge(SpanType::Planning)));
+        assert!(!llm.matches(&edge(SpanType::ToolCall)));
+
+        let tool = filter("tool").unwrap();
+        assert!(tool.matches(&edge(SpanType::ToolCall)));
+        assert!(tool.matches(&edge(SpanType::ToolResponse)));
+        assert!(!tool.matches(&edge(SpanType::Root)));
+
+        let retrieval = filter("http_call, retrieval").unwrap();
+        assert!(retrieval.matches(&edge(SpanType::Retrieval)));
+        assert_eq!(
+            serde_json::to_value(&retrieval).unwrap()["span_types"],
+            serde_json::json!(["retrieval", "http_call"])
+        );
+
+        assert!(filter("toolz").unwrap_err().contains("toolz"));
+    }
+
+    #[test]
+    fn test_invalid_ids_are_rejected() {
+        let err = StreamFilter::try_from(StreamFilterParams {
+            project_id: Some("3,abc".to_string()),
+            ..Default::default()
+        })
+        .unwrap_err();
+        assert!(err.contains("project_id 'abc'"));
+
+        // u16 project IDs
+        assert!(StreamFilter::try_from(StreamFilterParams {
+            project_id: Some("70000".to_string()),
+            ..Default::default()
+        })
+        .is_err());
+        assert!(StreamFilter::try_from(StreamFilterParams {
+            agent_id: Some("7, ".to_string()),
+            ..Default::default()
+        })
+        .is_ok());
+    }
 }
@@ -14,15 +14,18 @@
 // along with this program. If not, see <https://www.gnu.org/licenses/>.
 
 use axum::{
-    extract::State,
+    extract::{Query, State},
     http::StatusCode,
-    response::{IntoResponse, Json},
+    response::{IntoResponse, Json, Response},
+    Extension,
 };
 use agentreplay_query::retention::{RetentionConfig, RetentionPolicy, RetentionStats};
 use serde::{Deserialize, Serialize};
 use std::path::PathBuf;
 
 use super::{ApiError, AppState};
+use crate::auth::AuthContext;
+use crate::jobs::BackgroundQuery;
 
 /// Request to manually trigger retention cleanup
 #[derive(Debug, Deserialize)]
@@ -31,6 +34,10 @@ pub struct TriggerCleanupRequest {
     pub environment: Option<String>,
     /// Optional: Override retention days (for manual cleanup)
     pub retention_days: Option<u32>,
+    /// Optional: Clean up one project's database using its own retention
+    /// config (per-project storage only)
+    #[serde(default)]
+    pub project_id: Option<u16>,
 }
 
 /// Response with retention statistics
@@ -124,10 +131,15 @@ pub async fn update_retention_config(
 }
 
 /// POST /api/v1/retention/cleanup - Manually trigger retention cleanup
+///
+/// With `?background=true` the cleanup runs as a job and the response is the
+/// job, to follow at `/api/v1/jobs/:id`.
 pub async fn trigger_cleanup(
     State(state): State<AppState>,
+    Extension(auth): Extension<AuthContext>,
+    Query(params): Query<BackgroundQuery>,
     Json(req): Json<TriggerCleanupRequest>,
-) -> Result<Json<RetentionResponse>, (StatusCode, String)> {
+) -> Result<Response, (StatusCode, String)> {
     // Build config for cleanup
     let config = if let Some(ref env) = req.environment {
         RetentionConfig {
@@ -140,8 +152,15 @@ pub async fn trigger_cleanup(
             global_retention_days: req.retention_days,
         }
     } else {
-        // Load existing config
-        let config_path = get_retention_config_path();
+        // Load existing config; a project provisioned with its own
+        // retention uses that instead of the global one
+        let project_config_path = req.project_id.map(|project_id| {
+            let dir = super::project_templates::project_settings_dir(&state, project_id);
+            crate::project_templates::project_retention_path(&dir)
+        });
+        let config_path = project_config_path
+            .filter(|path| path.exists())
+            .unwrap_or_else(get_retention_config_path);
         let mut config = RetentionConfig::load(&config_path);
         // Override with request retention_days if provided
         if let Some(days) = req.retention_days {
@@ -150,7 +169,38 @@ pub async fn trigger_cleanup(
         config
     };
 
-    match state.db.apply_retention(&config).await {
+    let db = match (req.project_id, &state.project_manager) {
+        (Some(project_id), Some(pm)) => pm
+            .get_or_open_project(project_id)
+            .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?,
+        (Some(_), None) => {
+            return Err((
+                StatusCode::BAD_REQUEST,
+                "project_id requires per-project storage".to_string(),
+            ))
+        }
+        (None, _) => state.db.clone(),
+    };
+
+    if params.background {
+        let job = state.jobs.submit(
+            "retention_cleanup",
+            auth.tenant_id,
+            "Retention cleanup".to_string(),
+            move |_| async move {
+                let stats = db
+                    .apply_retention(&config)
+                    .await
+                    .map_err(|e| format!("Cleanup failed: {}", e))?;
+                serde_json::to_value(stats)
+                    .map(Some)
+                    .map_err(|e| e.to_string())
+            },
+        );
+        return Ok((StatusCode::ACCEPTED, Json(job)).into_response());
+    }
+
+    match db.apply_retention(&config).await {
         Ok(stats) => Ok(Json(RetentionResponse {
             success: true,
             message: format!(
@@ -158,7 +208,8 @@ pub async fn trigger_cleanup(
                 stats.traces_deleted, stats.disk_freed_bytes
             ),
             stats: Some(stats),
-        })),
+        })
+        .into_response()),
         Err(e) => Err((
             StatusCode::INTERNAL_SERVER_ERROR,
             format!("Cleanup failed: {}", e),
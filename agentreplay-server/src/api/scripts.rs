@@ -0,0 +1,165 @@
+// Copyright 2025 AgentReplay (https://github.com/agentreplay)
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program. If not, see <https://www.gnu.org/licenses/>.
+
+//! Script management API for embedded hook scripts
+//!
+//! Scripts are per tenant. Any caller may read its tenant's scripts;
+//! saving, deleting and test-running them requires the admin role.
+
+use axum::{
+    extract::{Path, State},
+    http::StatusCode,
+    Extension, Json,
+};
+use serde::{Deserialize, Serialize};
+
+use super::query::{ApiError, AppState};
+use crate::auth::{AuthContext, Role};
+use crate::scripting::{
+    ScriptDefinition, ScriptHook, ScriptInfo, ScriptOutcome, DEFAULT_MAX_OPERATIONS,
+    DEFAULT_TIMEOUT_MS,
+};
+
+/// Request to create or replace a script
+#[derive(Debug, Deserialize)]
+pub struct UpsertScriptRequest {
+    pub hook: ScriptHook,
+    pub source: String,
+    pub description: Option<String>,
+    pub enabled: Option<bool>,
+    pub timeout_ms: Option<u64>,
+    pub max_operations: Option<u64>,
+}
+
+/// Request to dry-run a script against a sample record
+#[derive(Debug, Deserialize)]
+pub struct TestScriptRequest {
+    pub record: serde_json::Value,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ScriptListResponse {
+    pub scripts: Vec<ScriptInfo>,
+    pub total: usize,
+}
+
+/// GET /api/v1/scripts
+pub async fn list_scripts(
+    State(state): State<AppState>,
+    Extension(auth): Extension<AuthContext>,
+) -> Json<ScriptListResponse> {
+    let scripts = state.scripts.list(auth.tenant_id);
+    Json(ScriptListResponse {
+        total: scripts.len(),
+        scripts,
+    })
+}
+
+/// GET /api/v1/scripts/:name
+pub async fn get_script(
+    State(state): State<AppState>,
+    Extension(auth): Extension<AuthContext>,
+    Path(name): Path<String>,
+) -> Result<Json<ScriptInfo>, ApiError> {
+    state
+        .scripts
+        .get(auth.tenant_id, &name)
+        .map(Json)
+        .ok_or_else(|| ApiError::NotFound(format!("Script '{}' not found", name)))
+}
+
+/// PUT /api/v1/scripts/:name
+///
+/// Compiles the script before saving; syntax errors are returned as 400.
+pub async fn put_script(
+    State(state): State<AppState>,
+    Extension(auth): Extension<AuthContext>,
+    Path(name): Path<String>,
+    Json(req): Json<UpsertScriptRequest>,
+) -> Result<(StatusCode, Json<ScriptInfo>), ApiError> {
+    auth.require_role(Role::Admin)?;
+    let existing = state.scripts.get(auth.tenant_id, &name);
+    let status = if existing.is_some() {
+        StatusCode::OK
+    } else {
+        StatusCode::CREATED
+    };
+    let defaults = existing.map(|info| info.definition);
+
+    let definition = ScriptDefinition {
+        tenant_id: auth.tenant_id,
+        name,
+        hook: req.hook,
+        source: req.source,
+        description: req
+            .description
+            .or_else(|| defaults.as_ref().and_then(|d| d.description.clone())),
+        enabled: req
+            .enabled
+            .or(defaults.as_ref().map(|d| d.enabled))
+            .unwrap_or(true),
+        timeout_ms: req
+            .timeout_ms
+            .or(defaults.as_ref().map(|d| d.timeout_ms))
+            .unwrap_or(DEFAULT_TIMEOUT_MS),
+        max_operations: req
+            .max_operations
+            .or(defaults.as_ref().map(|d| d.max_operations))
+            .unwrap_or(DEFAULT_MAX_OPERATIONS),
+        created_at: 0,
+        updated_at: 0,
+    };
+
+    let info = state
+        .scripts
+        .upsert(definition)
+        .map_err(ApiError::BadRequest)?;
+    Ok((status, Json(info)))
+}
+
+/// DELETE /api/v1/scripts/:name
+pub async fn delete_script(
+    State(state): State<AppState>,
+    Extension(auth): Extension<AuthContext>,
+    Path(name): Path<String>,
+) -> Result<StatusCode, ApiError> {
+    auth.require_role(Role::Admin)?;
+    match state.scripts.delete(auth.tenant_id, &name) {
+        Ok(true) => Ok(StatusCode::NO_CONTENT),
+        Ok(false) => Err(ApiError::NotFound(format!("Script '{}' not found", name))),
+        Err(e) => Err(ApiError::Internal(e)),
+    }
+}
+
+/// POST /api/v1/scripts/:name/test
+///
+/// Runs the script once against `record` without side effects: the outcome
+/// (modified record, drop decision, notifications) is returned, not applied.
+pub async fn test_script(
+    State(state): State<AppState>,
+    Extension(auth): Extension<AuthContext>,
+    Path(name): Path<String>,
+    Json(req): Json<TestScriptRequest>,
+) -> Result<Json<ScriptOutcome>, ApiError> {
+    auth.require_role(Role::Admin)?;
+    if state.scripts.get(auth.tenant_id, &name).is_none() {
+        return Err(ApiError::NotFound(format!("Script '{}' not found", name)));
+    }
+    state
+        .scripts
+        .test(auth.tenant_id, &name, req.record)
+        .map(Json)
+        .map_err(ApiError::BadRequest)
+}
@@ -13,25 +13,37 @@
 // You should have received a copy of the GNU Affero General Public License
 // along with this program. If not, see <https://www.gnu.org/licenses/>.
 
-use std::time::{SystemTime, UNIX_EPOCH};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
 
 use axum::{extract::State, Json};
 use agentreplay_core::{AgentFlowEdge, SpanType};
 use agentreplay_index::embedding::{EmbeddingProvider, LocalEmbeddingProvider};
-use agentreplay_index::Embedding;
+use agentreplay_index::{EffectiveSearchParams, Embedding};
 use serde::{Deserialize, Serialize};
 
+use crate::access_policy::AccessFilter;
+use crate::project_manager::{FederationSummary, ProjectSelection};
 use crate::{api::query::ApiError, api::AppState, auth::AuthContext};
 
 const DEFAULT_LOOKBACK_US: u64 = 86_400_000_000; // 24 hours
 const MAX_LIMIT: usize = 500;
 const MAX_QUERY_LENGTH: usize = 2000;
+const MAX_BUDGET_MS: u64 = 10_000;
 
 #[derive(Debug, Deserialize)]
 pub struct SearchRequest {
     pub query: String,
     #[serde(default = "default_limit")]
     pub limit: usize,
+    /// Latency budget for semantic search in milliseconds (e.g. 50).
+    /// When set, ef_search is chosen per query to fit the budget, and the
+    /// search stops at the budget with what it found.
+    #[serde(default)]
+    pub budget_ms: Option<u64>,
+    /// Search across project databases: `"all"`, `"1,2"` or `[1, 2]`.
+    /// Requires per-project storage.
+    #[serde(default)]
+    pub projects: Option<ProjectSelection>,
 }
 
 #[derive(Debug, Serialize)]
@@ -39,6 +51,12 @@ pub struct SearchResponse {
     pub results: Vec<TraceSearchView>,
     pub count: usize,
     pub query_interpretation: QueryInterpretation,
+    /// Effective vector search parameters (only for budgeted semantic searches)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub search_params: Option<EffectiveSearchParams>,
+    /// Projects covered when the search was federated
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub federation: Option<FederationSummary>,
 }
 
 #[derive(Debug, Serialize)]
@@ -76,9 +94,14 @@ struct ParsedQuery {
 ///
 /// Performs semantic search using vector embeddings when possible,
 /// falling back to content-based payload search for text queries.
+///
+/// With `projects`, the content search fans out across the selected
+/// project databases and merges matches; the embedding fallback only
+/// covers the default database.
 pub async fn semantic_search(
     State(state): State<AppState>,
     axum::Extension(auth): axum::Extension<AuthContext>,
+    axum::Extension(access): axum::Extension<AccessFilter>,
     Json(request): Json<SearchRequest>,
 ) -> Result<Json<SearchResponse>, ApiError> {
     // Input validation
@@ -93,18 +116,57 @@ pub async fn semantic_search(
         )));
     }
 
+    let started = Instant::now();
+    if request.budget_ms == Some(0) {
+        return Err(ApiError::BadRequest(
+            "budget_ms must be greater than 0".into(),
+        ));
+    }
+
     let limit = request.limit.clamp(1, MAX_LIMIT);
+    let budget = request
+        .budget_ms
+        .map(|ms| Duration::from_millis(ms.min(MAX_BUDGET_MS)));
     let parsed = parse_search_query(&request.query);
     let query_lower = request.query.to_lowercase();
 
-    // First, try content-based search through payloads
-    let edges = {
-        // Get edges from temporal range
-        let all_edges = state
-            .db
-            .query_temporal_range_for_tenant(parsed.start_ts, parsed.end_ts, auth.tenant_id)
+    // Get edges from temporal range, federated across projects if requested
+    let (all_edges, federation) = match request.projects.clone() {
+        Some(selection) => {
+            let pm = state.project_manager.clone().ok_or_else(|| {
+                ApiError::BadRequest("Federated search requires per-project storage".into())
+            })?;
+            let (start_ts, end_ts, tenant_id) = (parsed.start_ts, parsed.end_ts, auth.tenant_id);
+            let (edges, summary) = tokio::task::spawn_blocking(move || {
+                pm.query_projects(&selection, tenant_id, start_ts, end_ts)
+            })
+            .await
+            .map_err(|e| ApiError::Internal(e.to_string()))?
             .map_err(|e| ApiError::Internal(e.to_string()))?;
+            (edges, Some(summary))
+        }
+        None => {
+            let edges = state
+                .db
+                .query_temporal_range_for_tenant(parsed.start_ts, parsed.end_ts, auth.tenant_id)
+                .map_err(|e| ApiError::Internal(e.to_string()))?;
+            (edges, None)
+        }
+    };
 
+    // Federated edges keep their payloads in their own project database
+    let load_payload = |edge: &AgentFlowEdge| {
+        let payload = match (&federation, &state.project_manager) {
+            (Some(_), Some(pm)) => pm
+                .get_or_open_project(edge.project_id)
+                .and_then(|db| db.get_payload(edge.edge_id)),
+            _ => state.db.get_payload(edge.edge_id),
+        };
+        payload.ok().flatten()
+    };
+
+    // First, try content-based search through payloads
+    let edges = {
         // Filter by content if it's a text search
         let mut matched_edges: Vec<AgentFlowEdge> = Vec::new();
 
@@ -131,7 +193,7 @@ pub async fn semantic_search(
             }
 
             // Check payload content for the search query
-            if let Ok(Some(payload_bytes)) = state.db.get_payload(edge.edge_id) {
+            if let Some(payload_bytes) = load_payload(&edge) {
                 // Try to parse as JSON and search in string representation
                 if let Ok(payload_str) = String::from_utf8(payload_bytes.clone()) {
                     if payload_str.to_lowercase().contains(&query_lower) {
@@ -153,9 +215,17 @@ pub async fn semantic_search(
     };
 
     // If no results from content search and it looks like a semantic query, try embedding search
-    let final_edges = if edges.is_empty() && is_natural_language_query(&request.query) {
-        match perform_semantic_search(&state, &request.query, limit, auth.tenant_id).await {
-            Ok(semantic_edges) => semantic_edges,
+    let mut search_params = None;
+    let final_edges = if edges.is_empty()
+        && federation.is_none()
+        && is_natural_language_query(&request.query)
+    {
+        let budget = budget.map(|budget| (started, budget));
+        match perform_semantic_search(&state, &request.query, limit, auth.tenant_id, budget).await {
+            Ok((semantic_edges, params)) => {
+                search_params = params;
+                semantic_edges
+            }
             Err(_) => edges, // Return empty if semantic search also fails
         }
     } else {
@@ -163,6 +233,7 @@ pub async fn semantic_search(
     };
 
     let mut filtered_edges = final_edges;
+    filtered_edges.retain(|edge| access.permits_with(edge, || load_payload(edge)));
     filtered_edges.sort_by_key(|edge| std::cmp::Reverse(edge.timestamp_us));
     filtered_edges.truncate(limit);
 
@@ -181,6 +252,8 @@ pub async fn semantic_search(
             min_tokens: parsed.min_tokens,
             time_range: format!("{} - {}", parsed.start_ts, parsed.end_ts),
         },
+        search_params,
+        federation,
     }))
 }
 
@@ -229,12 +302,17 @@ fn is_natural_language_query(query: &str) -> bool {
 /// 
 /// **Tenant Safety:** Results are filtered to only include edges belonging to the specified tenant.
 /// This prevents cross-tenant data leakage in semantic search results.
+///
+/// With a latency `budget`, counted from the given start of the request,
+/// ef_search is tuned per query, the search stops at the deadline and the
+/// effective parameters are returned.
 async fn perform_semantic_search(
     state: &AppState,
     query: &str,
     limit: usize,
     tenant_id: u64,
-) -> Result<Vec<AgentFlowEdge>, ApiError> {
+    budget: Option<(Instant, Duration)>,
+) -> Result<(Vec<AgentFlowEdge>, Option<EffectiveSearchParams>), ApiError> {
     // Initialize embedding provider
     let provider = LocalEmbeddingProvider::default_provider().map_err(|e| {
         ApiError::Internal(format!("Failed to initialize embedding provider: {}", e))
@@ -246,6 +324,14 @@ async fn perform_semantic_search(
         .map_err(|e| ApiError::Internal(format!("Failed to generate query embedding: {}", e)))?;
     let query_embedding = Embedding::from_vec(query_vec);
 
+    if let Some((started, budget)) = budget {
+        let (edges, params) = state
+            .db
+            .semantic_search_with_budget(&query_embedding, limit, tenant_id, started, budget)
+            .map_err(|e| ApiError::Internal(format!("Semantic search failed: {}", e)))?;
+        return Ok((edges, Some(params)));
+    }
+
     // Perform tenant-scoped semantic search
     // We request more results than needed to account for tenant filtering
     let expanded_limit = limit * 3; // Request 3x to ensure enough results after filtering
@@ -262,7 +348,7 @@ async fn perform_semantic_search(
         .take(limit)
         .collect();
 
-    Ok(tenant_edges)
+    Ok((tenant_edges, None))
 }
 
 impl From<AgentFlowEdge> for TraceSearchView {
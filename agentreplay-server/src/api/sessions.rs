@@ -22,12 +22,18 @@ use axum::{
     Extension, Json,
 };
 use agentreplay_core::AgentFlowEdge;
+use agentreplay_query::Agentreplay;
+use agentreplay_storage::{SessionAnalysis, SessionRollup};
 use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap};
+use std::sync::Arc;
 use tracing::debug;
 
+use crate::access_policy::{stored_payload, AccessFilter};
 use crate::api::query::{ApiError, AppState};
 use crate::auth::AuthContext;
+use crate::scaling::run_query;
+use crate::session_analysis::{build_funnel, SessionFunnel};
 
 /// Query parameters for listing sessions
 #[derive(Debug, Deserialize)]
@@ -54,12 +60,63 @@ pub struct SessionQueryParams {
 
     /// Filter by status (active, ended)
     pub status: Option<String>,
+
+    /// Sort order, descending: recent (default), cost, duration, tokens
+    pub sort: Option<String>,
 }
 
 fn default_limit() -> usize {
     100
 }
 
+/// Sort orders accepted by `GET /api/v1/sessions`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum SessionSort {
+    Recent,
+    Cost,
+    Duration,
+    Tokens,
+}
+
+impl SessionSort {
+    fn parse(sort: Option<&str>) -> Result<Self, ApiError> {
+        match sort.unwrap_or("recent") {
+            "recent" => Ok(Self::Recent),
+            "cost" => Ok(Self::Cost),
+            "duration" => Ok(Self::Duration),
+            "tokens" => Ok(Self::Tokens),
+            other => Err(ApiError::BadRequest(format!(
+                "Unknown sort '{}' (expected recent, cost, duration or tokens)",
+                other
+            ))),
+        }
+    }
+
+    fn apply(self, sessions: &mut [SessionInfo]) {
+        match self {
+            Self::Recent => sessions.sort_by(|a, b| b.last_message_at.cmp(&a.last_message_at)),
+            Self::Cost => sessions.sort_by(|a, b| {
+                b.cost_usd
+                    .unwrap_or(0.0)
+                    .total_cmp(&a.cost_usd.unwrap_or(0.0))
+            }),
+            Self::Duration => sessions.sort_by_key(|s| {
+                std::cmp::Reverse(s.duration_ms.unwrap_or(s.total_duration_ms as u64))
+            }),
+            Self::Tokens => sessions.sort_by_key(|s| std::cmp::Reverse(s.total_tokens)),
+        }
+    }
+}
+
+fn session_status(last_message_at: u64, now: u64) -> String {
+    // Active if last message < 1 hour ago
+    if last_message_at > now.saturating_sub(3_600_000_000) {
+        "active".to_string()
+    } else {
+        "ended".to_string()
+    }
+}
+
 /// Session metadata
 #[derive(Debug, Serialize)]
 pub struct SessionInfo {
@@ -73,6 +130,41 @@ pub struct SessionInfo {
     pub total_duration_ms: u32,
     pub trace_ids: Vec<String>,
     pub status: String,
+    /// Wall-clock duration from the first span's start to the last span's end
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub duration_ms: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub cost_usd: Option<f64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error_count: Option<u32>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub models: Vec<String>,
+    /// Latest score of each eval metric recorded on the session's spans
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    pub eval_scores: BTreeMap<String, f64>,
+}
+
+impl SessionInfo {
+    fn from_summary(summary: SessionRollup, now: u64) -> Self {
+        Self {
+            session_id: summary.session_id,
+            project_id: summary.project_id,
+            agent_id: summary.agent_id,
+            started_at: summary.started_at,
+            last_message_at: summary.last_span_at,
+            message_count: summary.span_count as usize,
+            total_tokens: summary.total_tokens.min(u32::MAX as u64) as u32,
+            total_duration_ms: (summary.span_duration_us / 1000).min(u32::MAX as u64) as u32,
+            // Clients load a session's traces by session_id
+            trace_ids: Vec::new(),
+            status: session_status(summary.last_span_at, now),
+            duration_ms: Some(summary.duration_us() / 1000),
+            cost_usd: Some(summary.cost_usd),
+            error_count: Some(summary.error_count),
+            models: summary.models,
+            eval_scores: summary.eval_scores,
+        }
+    }
 }
 
 /// Response for GET /api/v1/sessions
@@ -118,20 +210,88 @@ pub async fn list_sessions(
 
     let start_ts = params.start_ts.unwrap_or(now - 7 * 86_400_000_000); // 7 days ago
     let end_ts = params.end_ts.unwrap_or(now);
+    let sort = SessionSort::parse(params.sort.as_deref())?;
+
+    let mut sessions = match summarized_sessions(&state, &params, auth.tenant_id)? {
+        Some(summaries) => summaries
+            .into_iter()
+            .filter(|s| s.ended_at >= start_ts && s.started_at <= end_ts)
+            .map(|s| SessionInfo::from_summary(s, now))
+            .collect(),
+        None => derive_sessions(&state, &params, auth.tenant_id, start_ts, end_ts, now)?,
+    };
+
+    // Filter by search query
+    if let Some(ref search) = params.search {
+        let search_lower = search.to_lowercase();
+        sessions.retain(|s| s.session_id.to_string().contains(&search_lower));
+    }
 
+    // Filter by status
+    if let Some(ref status_filter) = params.status {
+        sessions.retain(|s| s.status == *status_filter);
+    }
+
+    sort.apply(&mut sessions);
+
+    let total = sessions.len();
+
+    // Apply pagination
+    let sessions = sessions
+        .into_iter()
+        .skip(params.offset)
+        .take(params.limit)
+        .collect();
+
+    Ok(Json(SessionsResponse { sessions, total }))
+}
+
+/// Session summaries of the tenant, or None while some shard's summaries
+/// are still being backfilled
+fn summarized_sessions(
+    state: &AppState,
+    params: &SessionQueryParams,
+    tenant_id: u64,
+) -> Result<Option<Vec<SessionRollup>>, ApiError> {
+    let shards = super::metrics::project_shards(state, params.project_id)?;
+    let mut summaries = Vec::new();
+    for db in shards {
+        if !db.session_summaries_ready().unwrap_or(false) {
+            return Ok(None);
+        }
+        summaries.extend(
+            db.list_session_summaries(tenant_id)
+                .map_err(|e| ApiError::Internal(e.to_string()))?,
+        );
+    }
+    if let Some(project_id) = params.project_id {
+        summaries.retain(|s| s.project_id == project_id);
+    }
+    Ok(Some(summaries))
+}
+
+/// Group the spans of the time range into sessions
+fn derive_sessions(
+    state: &AppState,
+    params: &SessionQueryParams,
+    tenant_id: u64,
+    start_ts: u64,
+    end_ts: u64,
+    now: u64,
+) -> Result<Vec<SessionInfo>, ApiError> {
     // Query all traces in time range
     let edges = if let Some(ref pm) = state.project_manager {
         if let Some(project_id) = params.project_id {
-            pm.query_project(project_id, auth.tenant_id, start_ts, end_ts)
+            pm.query_project(project_id, tenant_id, start_ts, end_ts)
                 .map_err(|e| ApiError::Internal(e.to_string()))?
         } else {
-            pm.query_all_projects(auth.tenant_id, start_ts, end_ts)
+            pm.query_all_projects(tenant_id, start_ts, end_ts)
                 .map_err(|e| ApiError::Internal(e.to_string()))?
         }
     } else {
         let mut all_edges = state
             .db
-            .query_temporal_range_for_tenant(start_ts, end_ts, auth.tenant_id)
+            .query_temporal_range_for_tenant(start_ts, end_ts, tenant_id)
             .map_err(|e| ApiError::Internal(e.to_string()))?;
 
         if let Some(project_id) = params.project_id {
@@ -151,7 +311,7 @@ pub async fn list_sessions(
     debug!("Grouped into {} unique sessions", session_map.len());
 
     // Convert to SessionInfo
-    let mut sessions: Vec<SessionInfo> = session_map
+    let sessions: Vec<SessionInfo> = session_map
         .into_iter()
         .map(|(session_id, mut traces)| {
             // Sort by timestamp
@@ -166,13 +326,7 @@ pub async fn list_sessions(
             let trace_ids: Vec<String> =
                 traces.iter().map(|e| format!("{:#x}", e.edge_id)).collect();
 
-            // Determine status (active if last message < 1 hour ago)
-            let one_hour_ago = now - 3_600_000_000; // 1 hour in microseconds
-            let status = if last_message_at > one_hour_ago {
-                "active".to_string()
-            } else {
-                "ended".to_string()
-            };
+            let status = session_status(last_message_at, now);
 
             let project_id = traces.first().map(|e| e.project_id).unwrap_or(0);
             let agent_id = traces.first().map(|e| e.agent_id).unwrap_or(0);
@@ -188,34 +342,16 @@ pub async fn list_sessions(
                 total_duration_ms,
                 trace_ids,
                 status,
+                duration_ms: None,
+                cost_usd: None,
+                error_count: None,
+                models: Vec::new(),
+                eval_scores: BTreeMap::new(),
             }
         })
         .collect();
 
-    // Filter by search query
-    if let Some(ref search) = params.search {
-        let search_lower = search.to_lowercase();
-        sessions.retain(|s| s.session_id.to_string().contains(&search_lower));
-    }
-
-    // Filter by status
-    if let Some(ref status_filter) = params.status {
-        sessions.retain(|s| s.status == *status_filter);
-    }
-
-    // Sort by last_message_at descending (most recent first)
-    sessions.sort_by(|a, b| b.last_message_at.cmp(&a.last_message_at));
-
-    let total = sessions.len();
-
-    // Apply pagination
-    let sessions = sessions
-        .into_iter()
-        .skip(params.offset)
-        .take(params.limit)
-        .collect();
-
-    Ok(Json(SessionsResponse { sessions, total }))
+    Ok(sessions)
 }
 
 /// GET /api/v1/sessions/:session_id - Get session details with all traces
@@ -224,6 +360,7 @@ pub async fn get_session(
     State(state): State<AppState>,
     Path(session_id): Path<u64>,
     auth: Extension<AuthContext>,
+    Extension(access): Extension<AccessFilter>,
 ) -> Result<Json<SessionDetailResponse>, ApiError> {
     debug!("Getting session details for session_id: {}", session_id);
 
@@ -233,25 +370,13 @@ pub async fn get_session(
         .as_micros() as u64;
 
     // Use session index for O(log N + K_session) lookup instead of scanning 30 days
-    let mut session_traces: Vec<AgentFlowEdge> = if let Some(ref _pm) = state.project_manager {
-        // With project manager, use session index on the main DB
-        state
-            .db
-            .get_session_edges_full(session_id)
-            .map_err(|e| ApiError::Internal(e.to_string()))?
-            .into_iter()
-            .filter(|e| e.tenant_id == auth.tenant_id)
-            .collect()
-    } else {
-        // Direct session index lookup — O(log N + K) instead of O(N_30days)
-        state
-            .db
-            .get_session_edges_full(session_id)
-            .map_err(|e| ApiError::Internal(e.to_string()))?
-            .into_iter()
-            .filter(|e| e.tenant_id == auth.tenant_id)
-            .collect()
-    };
+    let edges = state
+        .db
+        .get_session_edges_full(session_id)
+        .map_err(|e| ApiError::Internal(e.to_string()))?;
+    let session_traces = readable_session_spans(edges, auth.tenant_id, &access, |edge| {
+        stored_payload(&state, edge)
+    });
 
     if session_traces.is_empty() {
         return Err(ApiError::NotFound(format!(
@@ -260,9 +385,6 @@ pub async fn get_session(
         )));
     }
 
-    // Sort by timestamp
-    session_traces.sort_by_key(|e| e.timestamp_us);
-
     // Build SessionInfo
     let started_at = session_traces.first().map(|e| e.timestamp_us).unwrap_or(0);
     let last_message_at = session_traces.last().map(|e| e.timestamp_us).unwrap_or(0);
@@ -296,6 +418,11 @@ pub async fn get_session(
         total_duration_ms,
         trace_ids,
         status,
+        duration_ms: None,
+        cost_usd: None,
+        error_count: None,
+        models: Vec::new(),
+        eval_scores: BTreeMap::new(),
     };
 
     // Convert edges to SessionTrace
@@ -319,6 +446,190 @@ pub async fn get_session(
     Ok(Json(SessionDetailResponse { session, traces }))
 }
 
+/// Spans of a session the caller may read, oldest first
+///
+/// A session whose spans are all hidden by access policies is not found,
+/// like one of another tenant.
+fn readable_session_spans(
+    mut edges: Vec<AgentFlowEdge>,
+    tenant_id: u64,
+    access: &AccessFilter,
+    load: impl Fn(&AgentFlowEdge) -> Option<Vec<u8>>,
+) -> Vec<AgentFlowEdge> {
+    edges.retain(|e| e.tenant_id == tenant_id);
+    access.retain_with(&mut edges, load);
+    edges.sort_by_key(|e| e.timestamp_us);
+    edges
+}
+
+/// Query parameters for resolving an external session key
+#[derive(Debug, Deserialize)]
+pub struct ExternalSessionParams {
+    /// Project the key was used in
+    #[serde(default)]
+    pub project_id: u16,
+}
+
+/// GET /api/v1/sessions/external/:external_key - Get the session stitched from an external key
+///
+/// Resolves a client-provided `session.external_key` to its stable session_id
+/// and returns the same payload as `GET /api/v1/sessions/:session_id`.
+#[tracing::instrument(skip(state, auth), fields(tenant_id = auth.tenant_id))]
+pub async fn get_session_by_external_key(
+    State(state): State<AppState>,
+    Path(external_key): Path<String>,
+    Query(params): Query<ExternalSessionParams>,
+    auth: Extension<AuthContext>,
+    access: Extension<AccessFilter>,
+) -> Result<Json<SessionDetailResponse>, ApiError> {
+    let mapping = state
+        .session_registry
+        .get(auth.tenant_id, params.project_id, &external_key)
+        .ok_or_else(|| {
+            ApiError::NotFound(format!("No session for external key '{}'", external_key))
+        })?;
+
+    get_session(State(state), Path(mapping.session_id), auth, access).await
+}
+
+/// Shard holding the tenant's spans of a session
+fn session_shard(
+    state: &AppState,
+    session_id: u64,
+    tenant_id: u64,
+) -> Result<Arc<Agentreplay>, ApiError> {
+    for db in super::metrics::project_shards(state, None)? {
+        let edges = db
+            .get_session_edges_full(session_id)
+            .map_err(|e| ApiError::Internal(e.to_string()))?;
+        if edges.iter().any(|e| e.tenant_id == tenant_id) {
+            return Ok(db);
+        }
+    }
+    Err(ApiError::NotFound(format!(
+        "Session {} not found",
+        session_id
+    )))
+}
+
+/// POST /api/v1/sessions/:session_id/analyze - Analyze a session now
+///
+/// Tags the session with topics, topic drift and a goal-completion verdict
+/// and stores the result, whether or not the session has gone idle.
+#[tracing::instrument(skip(state, auth), fields(tenant_id = auth.tenant_id))]
+pub async fn analyze_session(
+    State(state): State<AppState>,
+    Path(session_id): Path<u64>,
+    auth: Extension<AuthContext>,
+) -> Result<Json<SessionAnalysis>, ApiError> {
+    let db = session_shard(&state, session_id, auth.tenant_id)?;
+    state
+        .session_analyzer
+        .analyze_session(&db, session_id)
+        .await
+        .map_err(|e| ApiError::Internal(e.to_string()))?
+        .map(Json)
+        .ok_or_else(|| ApiError::NotFound(format!("Session {} not found", session_id)))
+}
+
+/// GET /api/v1/sessions/:session_id/analysis - Stored analysis of a session
+#[tracing::instrument(skip(state, auth), fields(tenant_id = auth.tenant_id))]
+pub async fn get_session_analysis(
+    State(state): State<AppState>,
+    Path(session_id): Path<u64>,
+    auth: Extension<AuthContext>,
+) -> Result<Json<SessionAnalysis>, ApiError> {
+    let db = session_shard(&state, session_id, auth.tenant_id)?;
+    db.get_session_analysis(session_id)
+        .map_err(|e| ApiError::Internal(e.to_string()))?
+        .filter(|analysis| analysis.tenant_id == auth.tenant_id)
+        .map(Json)
+        .ok_or_else(|| ApiError::NotFound(format!("Session {} has not been analyzed", session_id)))
+}
+
+/// Query parameters for the goal-completion funnel
+#[derive(Debug, Deserialize)]
+pub struct SessionFunnelParams {
+    /// Start timestamp (microseconds since epoch, default: 7 days ago)
+    pub start_ts: Option<u64>,
+
+    /// End timestamp (microseconds since epoch, default: now)
+    pub end_ts: Option<u64>,
+
+    /// Restrict to one project (per-project storage only)
+    pub project_id: Option<u16>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct SessionFunnelResponse {
+    pub start_ts: u64,
+    pub end_ts: u64,
+    #[serde(flatten)]
+    pub funnel: SessionFunnel,
+}
+
+const FUNNEL_DEFAULT_LOOKBACK_US: u64 = 7 * 24 * 3_600_000_000;
+const FUNNEL_TOP_TOPICS: usize = 10;
+
+/// GET /api/v1/sessions/funnel - Goal-completion funnel
+///
+/// Sessions with spans in the window → analyzed → goal completed, in total,
+/// per project and per prompt version, with the most common topics.
+#[tracing::instrument(skip(state, auth), fields(tenant_id = auth.tenant_id))]
+pub async fn get_session_funnel(
+    State(state): State<AppState>,
+    Query(params): Query<SessionFunnelParams>,
+    auth: Extension<AuthContext>,
+) -> Result<Json<SessionFunnelResponse>, ApiError> {
+    let end_ts = params.end_ts.unwrap_or_else(|| {
+        std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .map(|d| d.as_micros() as u64)
+            .unwrap_or(0)
+    });
+    let start_ts = params
+        .start_ts
+        .unwrap_or(end_ts.saturating_sub(FUNNEL_DEFAULT_LOOKBACK_US));
+    if end_ts <= start_ts {
+        return Err(ApiError::BadRequest(
+            "end_ts must be greater than start_ts".into(),
+        ));
+    }
+
+    let shards = super::metrics::project_shards(&state, params.project_id)?;
+    let tenant_id = auth.tenant_id;
+    let funnel = run_query(&state, move || -> Result<SessionFunnel, ApiError> {
+        let mut sessions: BTreeMap<u64, u16> = BTreeMap::new();
+        let mut analyses: HashMap<u64, SessionAnalysis> = HashMap::new();
+        for db in shards {
+            let edges = db
+                .query_temporal_range_for_tenant(start_ts, end_ts, tenant_id)
+                .map_err(|e| ApiError::Internal(e.to_string()))?;
+            for edge in edges.iter().filter(|e| e.session_id != 0) {
+                if sessions.insert(edge.session_id, edge.project_id).is_some() {
+                    continue;
+                }
+                if let Some(analysis) = db
+                    .get_session_analysis(edge.session_id)
+                    .map_err(|e| ApiError::Internal(e.to_string()))?
+                {
+                    analyses.insert(edge.session_id, analysis);
+                }
+            }
+        }
+        let sessions: Vec<(u64, u16)> = sessions.into_iter().collect();
+        Ok(build_funnel(&sessions, &analyses, FUNNEL_TOP_TOPICS))
+    })
+    .await
+    .map_err(|e| ApiError::Internal(format!("Funnel task panicked: {}", e)))??;
+
+    Ok(Json(SessionFunnelResponse {
+        start_ts,
+        end_ts,
+        funnel,
+    }))
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -327,4 +638,39 @@ mod tests {
     fn test_default_limit() {
         assert_eq!(default_limit(), 100);
     }
+
+    #[test]
+    fn test_session_spans_respect_access_policies() {
+        use agentreplay_core::SpanType;
+
+        let span = |tenant_id: u64, timestamp_us: u64| {
+            let mut edge = AgentFlowEdge::new(tenant_id, 0, 1, 9, SpanType::Root, 0);
+            edge.timestamp_us = timestamp_us;
+            edge.has_payload = 1;
+            edge
+        };
+        let edges = vec![span(1, 30), span(1, 10), span(2, 20)];
+        let team_of = |edge: &AgentFlowEdge| {
+            let team = if edge.timestamp_us == 30 {
+                "search"
+            } else {
+                "ranking"
+            };
+            Some(format!(r#"{{"team":"{}"}}"#, team).into_bytes())
+        };
+
+        let all = readable_session_spans(edges.clone(), 1, &AccessFilter::default(), team_of);
+        assert_eq!(
+            all.iter().map(|e| e.timestamp_us).collect::<Vec<_>>(),
+            vec![10, 30]
+        );
+
+        let search = AccessFilter::for_teams(&["search"]);
+        let visible = readable_session_spans(edges.clone(), 1, &search, team_of);
+        assert_eq!(visible.len(), 1);
+        assert_eq!(visible[0].timestamp_us, 30);
+
+        let infra = AccessFilter::for_teams(&["infra"]);
+        assert!(readable_session_spans(edges, 1, &infra, team_of).is_empty());
+    }
 }
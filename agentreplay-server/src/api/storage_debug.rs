@@ -13,14 +13,21 @@
 // You should have received a copy of the GNU Affero General Public License
 // along with this program. If not, see <https://www.gnu.org/licenses/>.
 
+use std::sync::Arc;
+
+use agentreplay_core::AgentreplayError;
+use agentreplay_query::Agentreplay;
+use agentreplay_storage::{CompactionRun, CompactionStats};
 use axum::{
     extract::{Query, State},
-    Json,
+    http::StatusCode,
+    response::{IntoResponse, Response},
+    Extension, Json,
 };
 use serde::{Deserialize, Serialize};
 
 use crate::api::query::{ApiError, AppState};
-use crate::auth::AuthContext;
+use crate::auth::{AuthContext, Role};
 
 #[derive(Debug, Deserialize)]
 pub struct StorageDumpParams {
@@ -268,3 +275,82 @@ pub async fn get_storage_stats(
         projects: project_stats,
     }))
 }
+
+#[derive(Debug, Deserialize)]
+pub struct CompactionParams {
+    /// Project database to use in multi-project mode (main database otherwise)
+    pub project_id: Option<u16>,
+    #[serde(default)]
+    pub background: bool,
+}
+
+fn compaction_db(state: &AppState, project_id: Option<u16>) -> Result<Arc<Agentreplay>, ApiError> {
+    match (&state.project_manager, project_id) {
+        (Some(pm), Some(project_id)) => pm
+            .get_or_open_project(project_id)
+            .map_err(|e| ApiError::Internal(format!("Failed to open project DB: {}", e))),
+        _ => Ok(state.db.clone()),
+    }
+}
+
+/// GET /api/v1/storage/compaction
+/// Compaction progress, space amplification and tombstone counts
+pub async fn get_compaction_stats(
+    State(state): State<AppState>,
+    Query(params): Query<CompactionParams>,
+) -> Result<Json<CompactionStats>, ApiError> {
+    let db = compaction_db(&state, params.project_id)?;
+    let stats = tokio::task::spawn_blocking(move || db.compaction_stats())
+        .await
+        .map_err(|e| ApiError::Internal(e.to_string()))?
+        .map_err(|e| ApiError::Internal(e.to_string()))?;
+    Ok(Json(stats))
+}
+
+fn compaction_error(e: AgentreplayError) -> ApiError {
+    match e {
+        AgentreplayError::InvalidArgument(msg) => ApiError::Conflict(msg),
+        e => ApiError::Internal(format!("Compaction failed: {}", e)),
+    }
+}
+
+/// POST /api/v1/storage/compaction
+/// Trigger a manual compaction
+///
+/// With `?background=true` the compaction runs as a job and the response is
+/// the job, to follow at `/api/v1/jobs/:id`; progress also shows up in
+/// `GET /api/v1/storage/compaction` either way. Returns 409 if one is
+/// already running. Compaction rewrites the whole store, so only admins
+/// may start it.
+pub async fn trigger_compaction(
+    State(state): State<AppState>,
+    Extension(auth): Extension<AuthContext>,
+    Query(params): Query<CompactionParams>,
+) -> Result<Response, ApiError> {
+    auth.require_role(Role::Admin)?;
+    let db = compaction_db(&state, params.project_id)?;
+
+    if params.background {
+        let job = state.jobs.submit(
+            "storage_compaction",
+            auth.tenant_id,
+            "Manual storage compaction".to_string(),
+            move |_| async move {
+                let run = tokio::task::spawn_blocking(move || db.compact())
+                    .await
+                    .map_err(|e| e.to_string())?
+                    .map_err(|e| format!("Compaction failed: {}", e))?;
+                serde_json::to_value(run)
+                    .map(Some)
+                    .map_err(|e| e.to_string())
+            },
+        );
+        return Ok((StatusCode::ACCEPTED, Json(job)).into_response());
+    }
+
+    let run: CompactionRun = tokio::task::spawn_blocking(move || db.compact())
+        .await
+        .map_err(|e| ApiError::Internal(e.to_string()))?
+        .map_err(compaction_error)?;
+    Ok(Json(run).into_response())
+}
@@ -0,0 +1,529 @@
+// Copyright 2025 AgentReplay (https://github.com/agentreplay)
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program. If not, see <https://www.gnu.org/licenses/>.
+
+//! Self-contained trace exports
+//!
+//! `GET /api/v1/traces/:trace_id/export?format=html` renders the trace's
+//! session into one HTML file that can be attached to a bug report: a
+//! waterfall of its spans, then each span's messages, tool calls and eval
+//! results. Styles are inline and the page has no scripts or external
+//! requests, so it opens anywhere without access to the server.
+//! `format=json` returns the same data.
+//!
+//! Spans are hydrated as far as the caller's role allows
+//! ([`hydration::detail_hydration`]): messages and tool calls are only
+//! exported at `full`.
+
+use std::collections::{BTreeMap, HashMap};
+use std::fmt::Write;
+
+use axum::{
+    extract::{Path, Query, State},
+    http::header,
+    response::{IntoResponse, Response},
+    Json,
+};
+use serde::{Deserialize, Serialize};
+
+use crate::api::payload_extractors::{
+    extract_completions, extract_prompts, extract_tool_calls, CompletionMessage, PromptMessage,
+    ToolCall,
+};
+use crate::access_policy::AccessFilter;
+use crate::api::hydration::{self, HydrationLevel};
+use crate::api::query::{find_edge_by_id_or_session, ApiError, AppState};
+use crate::auth::{AuthContext, Role};
+use crate::otel_genai::{GenAIPayload, ModelPricing};
+use crate::sanitization::sanitize_string as escape;
+
+#[derive(Debug, Deserialize)]
+pub struct ExportQuery {
+    #[serde(default = "default_format")]
+    pub format: String,
+}
+
+fn default_format() -> String {
+    "html".to_string()
+}
+
+/// A trace with everything needed to render it offline
+#[derive(Debug, Serialize)]
+pub struct TraceExport {
+    pub trace_id: String,
+    pub session_id: u64,
+    pub start_us: u64,
+    pub duration_us: u64,
+    pub total_tokens: u64,
+    pub total_cost: Option<f64>,
+    pub spans: Vec<ExportedSpan>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ExportedSpan {
+    pub span_id: String,
+    pub parent_span_id: Option<String>,
+    /// Nesting level in the waterfall
+    pub depth: usize,
+    pub name: String,
+    pub span_type: String,
+    pub status: String,
+    pub timestamp_us: u64,
+    pub duration_us: u32,
+    pub model: Option<String>,
+    pub tokens: u32,
+    pub cost: Option<f64>,
+    pub prompts: Vec<PromptMessage>,
+    pub completions: Vec<CompletionMessage>,
+    pub tool_calls: Vec<ToolCall>,
+    pub evals: Vec<ExportedEval>,
+}
+
+/// Metrics one evaluator recorded for a span in one evaluation
+#[derive(Debug, Serialize)]
+pub struct ExportedEval {
+    pub evaluator: String,
+    pub timestamp_us: u64,
+    pub metrics: BTreeMap<String, f64>,
+}
+
+/// GET /api/v1/traces/:trace_id/export?format=html|json
+pub async fn export_trace(
+    State(state): State<AppState>,
+    Path(trace_id): Path<String>,
+    Query(query): Query<ExportQuery>,
+    axum::Extension(auth): axum::Extension<AuthContext>,
+    axum::Extension(access): axum::Extension<AccessFilter>,
+) -> Result<Response, ApiError> {
+    let trace_id_u128 = u128::from_str_radix(trace_id.trim_start_matches("0x"), 16)
+        .map_err(|_| ApiError::BadRequest("Invalid trace ID format".into()))?;
+    let root = find_edge_by_id_or_session(&state, trace_id_u128, auth.tenant_id, &access)
+        .await?
+        .ok_or_else(|| ApiError::NotFound("Trace not found".into()))?;
+
+    let export = build_export(&state, &root, auth.tenant_id, auth.role, &access);
+    match query.format.as_str() {
+        "html" => {
+            let filename = format!("trace-{}.html", export.trace_id);
+            Ok((
+                [
+                    (header::CONTENT_TYPE, "text/html; charset=utf-8".to_string()),
+                    (
+                        header::CONTENT_DISPOSITION,
+                        format!("attachment; filename=\"{}\"", filename),
+                    ),
+                ],
+                render_html(&export),
+            )
+                .into_response())
+        }
+        "json" => Ok(Json(export).into_response()),
+        other => Err(ApiError::BadRequest(format!(
+            "Unsupported export format '{}', expected html or json",
+            other
+        ))),
+    }
+}
+
+/// Collect the spans of the root's session the caller may read, with their
+/// payloads and evals
+fn build_export(
+    state: &AppState,
+    root: &agentreplay_core::AgentFlowEdge,
+    tenant_id: u64,
+    role: Role,
+    access: &AccessFilter,
+) -> TraceExport {
+    // Spans and payloads live in the project database when projects are enabled
+    let db = state
+        .project_manager
+        .as_ref()
+        .and_then(|pm| pm.get_or_open_project(root.project_id).ok())
+        .unwrap_or_else(|| state.db.clone());
+    let mut edges: Vec<agentreplay_core::AgentFlowEdge> = db
+        .get_session_edges(root.session_id)
+        .into_iter()
+        .filter_map(|edge_id| db.get(edge_id).ok().flatten())
+        .filter(|edge| edge.tenant_id == tenant_id)
+        .collect();
+    access.retain_with(&mut edges, |edge| {
+        db.get_payload(edge.edge_id).ok().flatten()
+    });
+    if edges.is_empty() {
+        edges.push(*root);
+    }
+    edges.sort_by(|a, b| {
+        a.timestamp_us
+            .cmp(&b.timestamp_us)
+            .then(a.logical_clock.cmp(&b.logical_clock))
+            .then(a.edge_id.cmp(&b.edge_id))
+    });
+
+    let mut depths: HashMap<u128, usize> = HashMap::new();
+    let mut spans = Vec::with_capacity(edges.len());
+    for edge in &edges {
+        let depth = depths
+            .get(&edge.causal_parent)
+            .map_or(0, |parent_depth| parent_depth + 1);
+        depths.insert(edge.edge_id, depth);
+
+        let hydration = hydration::detail_hydration(&state.hydration, role, edge);
+        let payload: Option<GenAIPayload> =
+            if edge.has_payload == 0 || hydration.level == HydrationLevel::Metadata {
+                None
+            } else {
+                db.get_payload(edge.edge_id)
+                    .ok()
+                    .flatten()
+                    .and_then(|bytes| serde_json::from_slice(&bytes).ok())
+            };
+        let full_payload = payload
+            .as_ref()
+            .filter(|_| hydration.level == HydrationLevel::Full);
+        let model = payload
+            .as_ref()
+            .and_then(|p| p.request_model.clone().or_else(|| p.response_model.clone()));
+        let cost = payload.as_ref().zip(model.as_ref()).map(|(p, model)| {
+            p.calculate_cost(&ModelPricing::for_model(
+                p.system.as_deref().unwrap_or("openai"),
+                model,
+            ))
+        });
+        let is_error = edge.get_span_type() == agentreplay_core::SpanType::Error
+            || payload.as_ref().is_some_and(|p| p.error_type.is_some());
+        let name = payload
+            .as_ref()
+            .and_then(|p| {
+                p.additional
+                    .get("name")
+                    .and_then(|v| v.as_str())
+                    .map(str::to_string)
+                    .or_else(|| p.operation_name.clone())
+            })
+            .unwrap_or_else(|| format!("{:?}", edge.get_span_type()));
+
+        let mut evals: BTreeMap<(String, u64), BTreeMap<String, f64>> = BTreeMap::new();
+        for metric in state.db.get_eval_metrics(edge.edge_id).unwrap_or_default() {
+            evals
+                .entry((metric.get_evaluator().to_string(), metric.timestamp_us))
+                .or_default()
+                .insert(metric.get_metric_name().to_string(), metric.metric_value);
+        }
+
+        spans.push(ExportedSpan {
+            span_id: format!("{:#x}", edge.edge_id),
+            parent_span_id: (edge.causal_parent != 0).then(|| format!("{:#x}", edge.causal_parent)),
+            depth,
+            name,
+            span_type: format!("{:?}", edge.get_span_type()),
+            status: if is_error { "error" } else { "completed" }.to_string(),
+            timestamp_us: edge.timestamp_us,
+            duration_us: edge.duration_us,
+            model,
+            tokens: edge.token_count,
+            cost,
+            prompts: full_payload.map(extract_prompts).unwrap_or_default(),
+            completions: full_payload.map(extract_completions).unwrap_or_default(),
+            tool_calls: full_payload.map(extract_tool_calls).unwrap_or_default(),
+            evals: evals
+                .into_iter()
+                .map(|((evaluator, timestamp_us), metrics)| ExportedEval {
+                    evaluator,
+                    timestamp_us,
+                    metrics,
+                })
+                .collect(),
+        });
+    }
+
+    let start_us = edges.first().map_or(root.timestamp_us, |e| e.timestamp_us);
+    let end_us = edges
+        .iter()
+        .map(|e| e.timestamp_us + e.duration_us as u64)
+        .max()
+        .unwrap_or(start_us);
+    let costs: Vec<f64> = spans.iter().filter_map(|s| s.cost).collect();
+    TraceExport {
+        trace_id: format!("{:#x}", root.edge_id),
+        session_id: root.session_id,
+        start_us,
+        duration_us: end_us.saturating_sub(start_us),
+        total_tokens: spans.iter().map(|s| s.tokens as u64).sum(),
+        total_cost: (!costs.is_empty()).then(|| costs.iter().sum()),
+        spans,
+    }
+}
+
+const STYLE: &str = r#"
+body { font: 14px/1.5 -apple-system, "Segoe UI", Helvetica, Arial, sans-serif; margin: 0; padding: 24px; color: #1f2328; background: #f6f8fa; }
+h1 { font-size: 20px; margin: 0 0 4px; }
+h2 { font-size: 16px; margin: 24px 0 8px; }
+.meta { color: #59636e; margin-bottom: 16px; }
+.card { background: #fff; border: 1px solid #d1d9e0; border-radius: 6px; padding: 12px 16px; margin-bottom: 12px; }
+.row { display: flex; align-items: center; height: 24px; }
+.label { width: 32%; overflow: hidden; white-space: nowrap; text-overflow: ellipsis; padding-right: 8px; }
+.label a { color: inherit; text-decoration: none; }
+.track { position: relative; flex: 1; height: 14px; background: #eef1f4; border-radius: 3px; }
+.bar { position: absolute; top: 0; height: 14px; min-width: 2px; border-radius: 3px; background: #2f81f7; }
+.bar.error { background: #cf222e; }
+.dur { width: 90px; text-align: right; color: #59636e; font-variant-numeric: tabular-nums; }
+.badge { display: inline-block; padding: 0 6px; border-radius: 10px; font-size: 12px; background: #eef1f4; margin-left: 6px; }
+.badge.error { background: #ffebe9; color: #cf222e; }
+.msg { border-left: 3px solid #d1d9e0; padding-left: 10px; margin: 8px 0; }
+.msg .role { font-size: 12px; font-weight: 600; text-transform: uppercase; color: #59636e; }
+.msg.assistant { border-color: #2f81f7; }
+.msg.tool { border-color: #8250df; }
+pre { white-space: pre-wrap; word-break: break-word; margin: 4px 0; font: 13px/1.45 ui-monospace, SFMono-Regular, Menlo, monospace; }
+table { border-collapse: collapse; margin: 8px 0; }
+td, th { border: 1px solid #d1d9e0; padding: 2px 8px; text-align: left; font-size: 13px; }
+footer { color: #59636e; font-size: 12px; margin-top: 24px; }
+"#;
+
+/// Render an export as a standalone HTML page
+///
+/// Every value from the trace is escaped.
+pub fn render_html(export: &TraceExport) -> String {
+    let mut html = String::new();
+    let total = export.duration_us.max(1) as f64;
+
+    let _ = write!(
+        html,
+        "<!DOCTYPE html>\n<html lang=\"en\"><head><meta charset=\"utf-8\">\
+         <meta name=\"viewport\" content=\"width=device-width, initial-scale=1\">\
+         <title>Trace {id}</title><style>{style}</style></head><body>\
+         <h1>Trace {id}</h1><div class=\"meta\">Started {start} · {duration} · {spans} spans · {tokens} tokens{cost}</div>",
+        id = escape(&export.trace_id),
+        style = STYLE,
+        start = format_utc(export.start_us),
+        duration = format_duration(export.duration_us),
+        spans = export.spans.len(),
+        tokens = export.total_tokens,
+        cost = export
+            .total_cost
+            .map(|c| format!(" · ${:.4}", c))
+            .unwrap_or_default(),
+    );
+
+    html.push_str("<h2>Waterfall</h2><div class=\"card\">");
+    for span in &export.spans {
+        let offset = span.timestamp_us.saturating_sub(export.start_us) as f64;
+        let _ = write!(
+            html,
+            "<div class=\"row\"><div class=\"label\" style=\"padding-left:{indent}px\">\
+             <a href=\"#span-{id}\">{name}</a></div><div class=\"track\">\
+             <div class=\"bar{class}\" style=\"left:{left:.3}%;width:{width:.3}%\"></div></div>\
+             <div class=\"dur\">{duration}</div></div>",
+            indent = span.depth * 16,
+            id = escape(&span.span_id),
+            name = escape(&span.name),
+            class = if span.status == "error" { " error" } else { "" },
+            left = offset / total * 100.0,
+            width = (span.duration_us as f64 / total * 100.0).min(100.0 - offset / total * 100.0),
+            duration = format_duration(span.duration_us as u64),
+        );
+    }
+    html.push_str("</div><h2>Spans</h2>");
+
+    for span in &export.spans {
+        let _ = write!(
+            html,
+            "<div class=\"card\" id=\"span-{id}\"><strong>{name}</strong>\
+             <span class=\"badge\">{span_type}</span><span class=\"badge{class}\">{status}</span>\
+             <div class=\"meta\">{id} · +{offset} · {duration}{model} · {tokens} tokens{cost}</div>",
+            id = escape(&span.span_id),
+            name = escape(&span.name),
+            span_type = escape(&span.span_type),
+            class = if span.status == "error" { " error" } else { "" },
+            status = escape(&span.status),
+            offset = format_duration(span.timestamp_us.saturating_sub(export.start_us)),
+            duration = format_duration(span.duration_us as u64),
+            model = span
+                .model
+                .as_ref()
+                .map(|m| format!(" · {}", escape(m)))
+                .unwrap_or_default(),
+            tokens = span.tokens,
+            cost = span
+                .cost
+                .map(|c| format!(" · ${:.4}", c))
+                .unwrap_or_default(),
+        );
+
+        for message in &span.prompts {
+            push_message(&mut html, &message.role, &message.content);
+        }
+        for message in &span.completions {
+            push_message(&mut html, &message.role, &message.content);
+        }
+        for call in &span.tool_calls {
+            let _ = write!(
+                html,
+                "<div class=\"msg tool\"><div class=\"role\">tool · {name}</div><pre>{arguments}</pre>",
+                name = escape(&call.name),
+                arguments = escape(&call.arguments),
+            );
+            if let Some(result) = &call.result {
+                let _ = write!(
+                    html,
+                    "<div class=\"role\">result</div><pre>{}</pre>",
+                    escape(result)
+                );
+            }
+            html.push_str("</div>");
+        }
+
+        if !span.evals.is_empty() {
+            html.push_str("<table><tr><th>Evaluator</th><th>Metric</th><th>Value</th></tr>");
+            for eval in &span.evals {
+                for (metric, value) in &eval.metrics {
+                    let _ = write!(
+                        html,
+                        "<tr><td>{}</td><td>{}</td><td>{:.3}</td></tr>",
+                        escape(&eval.evaluator),
+                        escape(metric),
+                        value
+                    );
+                }
+            }
+            html.push_str("</table>");
+        }
+        html.push_str("</div>");
+    }
+
+    let _ = write!(
+        html,
+        "<footer>Exported from AgentReplay · session {}</footer></body></html>\n",
+        export.session_id
+    );
+    html
+}
+
+fn push_message(html: &mut String, role: &str, content: &str) {
+    let class = match role {
+        "assistant" => " assistant",
+        "tool" => " tool",
+        _ => "",
+    };
+    let _ = write!(
+        html,
+        "<div class=\"msg{}\"><div class=\"role\">{}</div><pre>{}</pre></div>",
+        class,
+        escape(role),
+        escape(content)
+    );
+}
+
+fn format_duration(us: u64) -> String {
+    if us >= 1_000_000 {
+        format!("{:.2} s", us as f64 / 1_000_000.0)
+    } else {
+        format!("{:.1} ms", us as f64 / 1000.0)
+    }
+}
+
+/// `YYYY-MM-DD HH:MM:SS UTC` for microseconds since the Unix epoch
+pub(crate) fn format_utc(us: u64) -> String {
+    let secs = us / 1_000_000;
+    let (days, rem) = ((secs / 86_400) as i64, secs % 86_400);
+    // Civil date from days since 1970-01-01 (Howard Hinnant's algorithm)
+    let z = days + 719_468;
+    let era = z.div_euclid(146_097);
+    let doe = z - era * 146_097;
+    let yoe = (doe - doe / 1_460 + doe / 36_524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + i64::from(month <= 2);
+    format!(
+        "{:04}-{:02}-{:02} {:02}:{:02}:{:02} UTC",
+        year,
+        month,
+        day,
+        rem / 3600,
+        rem % 3600 / 60,
+        rem % 60
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn span(id: &str, depth: usize, offset_us: u64, duration_us: u32) -> ExportedSpan {
+        ExportedSpan {
+            span_id: id.to_string(),
+            parent_span_id: None,
+            depth,
+            name: format!("span {}", id),
+            span_type: "Root".to_string(),
+            status: "completed".to_string(),
+            timestamp_us: 1_700_000_000_000_000 + offset_us,
+            duration_us,
+            model: None,
+            tokens: 0,
+            cost: None,
+            prompts: Vec::new(),
+            completions: Vec::new(),
+            tool_calls: Vec::new(),
+            evals: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn test_format_utc() {
+        assert_eq!(format_utc(0), "1970-01-01 00:00:00 UTC");
+        assert_eq!(format_utc(1_700_000_000_000_000), "2023-11-14 22:13:20 UTC");
+        assert_eq!(format_utc(951_782_400_000_000), "2000-02-29 00:00:00 UTC");
+    }
+
+    #[test]
+    fn test_render_html_escapes_and_lays_out_waterfall() {
+        let mut root = span("0x1", 0, 0, 1_000_000);
+        root.prompts.push(PromptMessage {
+            role: "user".to_string(),
+            content: "<script>alert('x')</script>".to_string(),
+        });
+        let mut tool = span("0x2", 1, 500_000, 250_000);
+        tool.status = "error".to_string();
+        tool.tool_calls.push(ToolCall {
+            name: "search".to_string(),
+            arguments: "{\"q\": \"a & b\"}".to_string(),
+            result: None,
+        });
+        tool.evals.push(ExportedEval {
+            evaluator: "latency".to_string(),
+            timestamp_us: 0,
+            metrics: BTreeMap::from([("p95_ms".to_string(), 250.0)]),
+        });
+        let export = TraceExport {
+            trace_id: "0x1".to_string(),
+            session_id: 7,
+            start_us: 1_700_000_000_000_000,
+            duration_us: 1_000_000,
+            total_tokens: 0,
+            total_cost: None,
+            spans: vec![root, tool],
+        };
+
+        let html = render_html(&export);
+        assert!(!html.contains("<script>"));
+        assert!(html.contains("&lt;script&gt;alert(&#x27;x&#x27;)&lt;/script&gt;"));
+        assert!(html.contains("a &amp; b"));
+        assert!(html.contains("class=\"bar error\" style=\"left:50.000%;width:25.000%\""));
+        assert!(html.contains("padding-left:16px"));
+        assert!(html.contains("<td>latency</td><td>p95_ms</td><td>250.000</td>"));
+        assert!(html.contains("href=\"#span-0x2\""));
+    }
+}
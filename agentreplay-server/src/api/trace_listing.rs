@@ -0,0 +1,332 @@
+// Copyright 2025 AgentReplay (https://github.com/agentreplay)
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program. If not, see <https://www.gnu.org/licenses/>.
+
+//! Keyset cursors and field projection for `GET /api/v1/traces`
+//!
+//! Timestamp-ordered listings are totally ordered by `(timestamp_us,
+//! edge_id)`. A [`TraceCursor`] records that key for the last trace of a page
+//! and the next page starts strictly after it. Traces ingested meanwhile
+//! therefore never shift or repeat entries the way offsets do, and on the
+//! storage path the page is read by seeking the tenant's temporal index
+//! instead of scanning past an offset.
+//!
+//! `fields=trace_id,timestamp_us,model` limits each returned trace to the
+//! named [`TraceView`](crate::api::query::TraceView) fields. When none of
+//! them come from the payload, payloads are not read at all.
+
+use agentreplay_core::AgentFlowEdge;
+use agentreplay_query::Agentreplay;
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine};
+
+use crate::api::query::{ApiError, TraceView};
+
+/// Cursor token format version
+const CURSOR_VERSION: &str = "v1";
+
+/// Edges read per storage page, as a multiple of the requested limit, to
+/// leave headroom for filters
+const SCAN_OVERFETCH: usize = 4;
+
+/// Position just after the last trace of a page
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TraceCursor {
+    pub descending: bool,
+    pub timestamp_us: u64,
+    pub edge_id: u128,
+}
+
+impl TraceCursor {
+    pub fn after(edge: &AgentFlowEdge, descending: bool) -> Self {
+        Self {
+            descending,
+            timestamp_us: edge.timestamp_us,
+            edge_id: edge.edge_id,
+        }
+    }
+
+    /// Opaque token handed to clients as `next_cursor`
+    pub fn encode(&self) -> String {
+        let raw = format!(
+            "{}/{}/{:020}/{:032x}",
+            CURSOR_VERSION,
+            if self.descending { "d" } else { "a" },
+            self.timestamp_us,
+            self.edge_id
+        );
+        URL_SAFE_NO_PAD.encode(raw)
+    }
+
+    pub fn decode(token: &str) -> Result<Self, ApiError> {
+        let invalid = || ApiError::BadRequest("Invalid pagination cursor".to_string());
+        let raw = URL_SAFE_NO_PAD
+            .decode(token.trim())
+            .ok()
+            .and_then(|bytes| String::from_utf8(bytes).ok())
+            .ok_or_else(invalid)?;
+        let parts: Vec<&str> = raw.split('/').collect();
+        let [version, order, timestamp_us, edge_id] = parts[..] else {
+            return Err(invalid());
+        };
+        if version != CURSOR_VERSION {
+            return Err(invalid());
+        }
+        let descending = match order {
+            "d" => true,
+            "a" => false,
+            _ => return Err(invalid()),
+        };
+        Ok(Self {
+            descending,
+            timestamp_us: timestamp_us.parse().map_err(|_| invalid())?,
+            edge_id: u128::from_str_radix(edge_id, 16).map_err(|_| invalid())?,
+        })
+    }
+
+    /// Suffix of the temporal index key, as taken by
+    /// `query_temporal_range_for_tenant_paginated`
+    fn storage_key(&self) -> String {
+        format!("{:020}/{:032x}", self.timestamp_us, self.edge_id)
+    }
+
+    /// Whether `edge` lies past the cursor in its order
+    pub fn admits(&self, edge: &AgentFlowEdge) -> bool {
+        let key = (edge.timestamp_us, edge.edge_id);
+        let cursor = (self.timestamp_us, self.edge_id);
+        if self.descending {
+            key < cursor
+        } else {
+            key > cursor
+        }
+    }
+}
+
+/// Sort into the listing's total order, `(timestamp_us, edge_id)`
+pub fn sort_by_key(edges: &mut [AgentFlowEdge], descending: bool) {
+    if descending {
+        edges.sort_by(|a, b| (b.timestamp_us, b.edge_id).cmp(&(a.timestamp_us, a.edge_id)));
+    } else {
+        edges.sort_by(|a, b| (a.timestamp_us, a.edge_id).cmp(&(b.timestamp_us, b.edge_id)));
+    }
+}
+
+/// Up to `limit + 1` matching edges past `cursor`, read from the tenant's
+/// temporal index in order. The extra edge tells the caller another page
+/// exists.
+pub fn scan_page(
+    db: &Agentreplay,
+    tenant_id: u64,
+    start_ts: u64,
+    end_ts: u64,
+    cursor: &TraceCursor,
+    limit: usize,
+    matches: impl Fn(&AgentFlowEdge) -> bool,
+) -> Result<Vec<AgentFlowEdge>, ApiError> {
+    let mut storage_cursor = Some(cursor.storage_key());
+    let mut page = Vec::with_capacity(limit + 1);
+    loop {
+        let (batch, next) = db
+            .query_temporal_range_for_tenant_paginated(
+                start_ts,
+                end_ts,
+                tenant_id,
+                limit * SCAN_OVERFETCH,
+                storage_cursor.as_deref(),
+                cursor.descending,
+            )
+            .map_err(|e| ApiError::Internal(e.to_string()))?;
+        for edge in batch {
+            if matches(&edge) {
+                page.push(edge);
+                if page.len() > limit {
+                    return Ok(page);
+                }
+            }
+        }
+        match next {
+            Some(next) => storage_cursor = Some(next),
+            None => return Ok(page),
+        }
+    }
+}
+
+/// Every field a projection may name, in `TraceView` order
+pub const TRACE_FIELDS: &[&str] = &[
+    "trace_id",
+    "span_id",
+    "parent_span_id",
+    "tenant_id",
+    "project_id",
+    "agent_id",
+    "agent_name",
+    "session_id",
+    "span_type",
+    "environment",
+    "timestamp_us",
+    "duration_us",
+    "token_count",
+    "sensitivity_flags",
+    "started_at",
+    "ended_at",
+    "duration_ms",
+    "status",
+    "tokens",
+    "metadata",
+    "provider",
+    "model",
+    "cost",
+    "confidence",
+    "route",
+    "input_preview",
+    "output_preview",
+    "display_name",
+    "tags",
+    "enrichment",
+];
+
+/// Fields filled from the span payload
+const PAYLOAD_FIELDS: &[&str] = &[
+    "metadata",
+    "provider",
+    "model",
+    "cost",
+    "route",
+    "input_preview",
+    "output_preview",
+    "display_name",
+];
+
+/// Fields requested with `fields=`; `None` keeps them all
+#[derive(Debug, Clone, Default)]
+pub struct TraceProjection {
+    fields: Option<Vec<String>>,
+}
+
+impl TraceProjection {
+    pub fn parse(fields: Option<&str>) -> Result<Self, ApiError> {
+        let Some(fields) = fields else {
+            return Ok(Self::default());
+        };
+        let mut selected: Vec<String> = Vec::new();
+        for field in fields.split(',').map(str::trim).filter(|f| !f.is_empty()) {
+            if !TRACE_FIELDS.contains(&field) {
+                return Err(ApiError::BadRequest(format!(
+                    "Unknown trace field '{}' in fields (expected any of: {})",
+                    field,
+                    TRACE_FIELDS.join(", ")
+                )));
+            }
+            if !selected.iter().any(|f| f == field) {
+                selected.push(field.to_string());
+            }
+        }
+        if selected.is_empty() {
+            return Err(ApiError::BadRequest(
+                "fields must name at least one trace field".to_string(),
+            ));
+        }
+        Ok(Self {
+            fields: Some(selected),
+        })
+    }
+
+    /// Whether any requested field needs the span payload
+    pub fn needs_payload(&self) -> bool {
+        match &self.fields {
+            Some(fields) => fields.iter().any(|f| PAYLOAD_FIELDS.contains(&f.as_str())),
+            None => true,
+        }
+    }
+
+    pub fn apply(&self, traces: Vec<TraceView>) -> Vec<serde_json::Value> {
+        traces
+            .into_iter()
+            .map(|trace| {
+                let value = serde_json::to_value(trace).unwrap_or_default();
+                match (&self.fields, value) {
+                    (Some(fields), serde_json::Value::Object(mut object)) => {
+                        object.retain(|key, _| fields.iter().any(|f| f == key));
+                        serde_json::Value::Object(object)
+                    }
+                    (_, value) => value,
+                }
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use agentreplay_core::SpanType;
+
+    fn edge(timestamp_us: u64, edge_id: u128) -> AgentFlowEdge {
+        let mut edge = AgentFlowEdge::new(1, 0, 1, 1, SpanType::Root, 0);
+        edge.timestamp_us = timestamp_us;
+        edge.edge_id = edge_id;
+        edge
+    }
+
+    #[test]
+    fn test_cursor_round_trip() {
+        let cursor = TraceCursor::after(&edge(1_700_000_000_000_000, 0xabc), true);
+        let token = cursor.encode();
+        assert!(!token.contains('/'));
+        assert_eq!(TraceCursor::decode(&token).unwrap(), cursor);
+
+        assert!(TraceCursor::decode("not a cursor").is_err());
+        assert!(TraceCursor::decode(&URL_SAFE_NO_PAD.encode("v0/d/1/2")).is_err());
+    }
+
+    #[test]
+    fn test_cursor_pages_are_stable_under_ingestion() {
+        let mut edges = vec![edge(10, 1), edge(20, 2), edge(20, 3), edge(30, 4)];
+        sort_by_key(&mut edges, true);
+        let ids: Vec<u128> = edges.iter().map(|e| e.edge_id).collect();
+        assert_eq!(ids, vec![4, 3, 2, 1]);
+
+        // Page one ends at (20, 3); a newer trace arrives before page two
+        let cursor = TraceCursor::after(&edges[1], true);
+        edges.push(edge(40, 5));
+        sort_by_key(&mut edges, true);
+        let next: Vec<u128> = edges
+            .iter()
+            .filter(|e| cursor.admits(e))
+            .map(|e| e.edge_id)
+            .collect();
+        assert_eq!(next, vec![2, 1]);
+
+        let ascending = TraceCursor::after(&edge(20, 2), false);
+        assert!(ascending.admits(&edge(20, 3)));
+        assert!(!ascending.admits(&edge(20, 2)));
+    }
+
+    #[test]
+    fn test_projection() {
+        assert!(TraceProjection::parse(Some("trace_id,nope")).is_err());
+        assert!(TraceProjection::parse(Some(" , ")).is_err());
+        assert!(TraceProjection::default().needs_payload());
+
+        let projection = TraceProjection::parse(Some("trace_id, timestamp_us")).unwrap();
+        assert!(!projection.needs_payload());
+        assert!(TraceProjection::parse(Some("trace_id,model"))
+            .unwrap()
+            .needs_payload());
+
+        let traces = projection.apply(vec![TraceView::from(edge(10, 0x1))]);
+        let object = traces[0].as_object().unwrap();
+        assert_eq!(object.len(), 2);
+        assert_eq!(object["timestamp_us"], 10);
+    }
+}
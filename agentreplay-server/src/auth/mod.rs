@@ -20,11 +20,19 @@ use axum::{
     response::{IntoResponse, Response},
 };
 use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
 use std::sync::Arc;
 use url::form_urlencoded;
 
+use crate::api::error::{ErrorCode, ProblemDetails};
+use crate::api::ApiError;
+
+pub mod oidc;
 pub mod rate_limit;
+pub mod tokens;
+pub use oidc::OidcAuth;
 pub use rate_limit::{extract_client_ip, RateLimitConfig, RateLimitResult, RateLimiter};
+pub use tokens::{TokenStore, API_TOKENS_FILE};
 
 // Type alias for the request type we use
 type Request = AxumRequest;
@@ -35,6 +43,54 @@ pub struct AuthContext {
     pub tenant_id: u64,
     pub project_id: Option<u16>,
     pub user_id: Option<String>,
+    pub role: Role,
+}
+
+impl AuthContext {
+    /// Refuse callers whose role is below `role`
+    pub fn require_role(&self, role: Role) -> Result<(), ApiError> {
+        if self.role >= role {
+            Ok(())
+        } else {
+            Err(ApiError::Forbidden(format!(
+                "this requires the {} role, not '{}'",
+                role.as_str(),
+                self.role.as_str()
+            )))
+        }
+    }
+}
+
+/// Access role of an authenticated caller, ordered by privilege
+#[derive(
+    Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize,
+)]
+#[serde(rename_all = "lowercase")]
+pub enum Role {
+    /// Read-only access to dashboards and trace listings
+    Viewer,
+    #[default]
+    Member,
+    Admin,
+}
+
+impl Role {
+    pub fn parse(s: &str) -> Option<Self> {
+        match s.to_ascii_lowercase().as_str() {
+            "viewer" => Some(Role::Viewer),
+            "member" => Some(Role::Member),
+            "admin" => Some(Role::Admin),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Role::Viewer => "viewer",
+            Role::Member => "member",
+            Role::Admin => "admin",
+        }
+    }
 }
 
 /// Authentication error
@@ -55,14 +111,14 @@ pub enum AuthError {
 
 impl IntoResponse for AuthError {
     fn into_response(self) -> Response {
-        let (status, message) = match self {
-            AuthError::MissingCredentials => (StatusCode::UNAUTHORIZED, self.to_string()),
-            AuthError::InvalidCredentials => (StatusCode::UNAUTHORIZED, self.to_string()),
-            AuthError::JwtValidation(_) => (StatusCode::UNAUTHORIZED, self.to_string()),
-            AuthError::Unauthorized(_) => (StatusCode::FORBIDDEN, self.to_string()),
+        let code = match self {
+            AuthError::MissingCredentials
+            | AuthError::InvalidCredentials
+            | AuthError::JwtValidation(_) => ErrorCode::Unauthenticated,
+            AuthError::Unauthorized(_) => ErrorCode::Forbidden,
         };
 
-        (status, message).into_response()
+        ProblemDetails::new(code.status(), code, self.to_string()).into_response()
     }
 }
 
@@ -72,6 +128,8 @@ pub struct Claims {
     pub sub: String,    // User ID
     pub tenant_id: u64, // Tenant ID
     pub project_id: Option<u16>,
+    #[serde(default)]
+    pub role: Role,
     pub exp: usize, // Expiration time
 }
 
@@ -83,8 +141,8 @@ pub trait Authenticator: Send + Sync {
 
 /// API Key authenticator
 pub struct ApiKeyAuth {
-    /// Map of API key -> (tenant_id, project_id)
-    keys: std::collections::HashMap<String, (u64, Option<u16>)>,
+    /// Map of API key -> (tenant_id, project_id, role)
+    keys: std::collections::HashMap<String, (u64, Option<u16>, Role)>,
 }
 
 impl ApiKeyAuth {
@@ -92,12 +150,23 @@ impl ApiKeyAuth {
         let mut keys = std::collections::HashMap::new();
 
         for key_config in api_keys {
-            // Format: "api_key:tenant_id" or "api_key:tenant_id:project_id"
+            // Format: "api_key:tenant_id", "api_key:tenant_id:project_id" or
+            // "api_key:tenant_id:project_id:role" (project_id may be empty)
             let parts: Vec<&str> = key_config.split(':').collect();
             if parts.len() >= 2 {
                 if let Ok(tenant_id) = parts[1].parse::<u64>() {
                     let project_id = parts.get(2).and_then(|p| p.parse::<u16>().ok());
-                    keys.insert(parts[0].to_string(), (tenant_id, project_id));
+                    let role = match parts.get(3) {
+                        Some(role) => match Role::parse(role) {
+                            Some(role) => role,
+                            None => {
+                                tracing::warn!("Ignoring API key with unknown role '{}'", role);
+                                continue;
+                            }
+                        },
+                        None => Role::default(),
+                    };
+                    keys.insert(parts[0].to_string(), (tenant_id, project_id, role));
                 }
             }
         }
@@ -116,7 +185,7 @@ impl Authenticator for ApiKeyAuth {
             .ok_or(AuthError::MissingCredentials)?;
 
         // Validate API key
-        let (tenant_id, project_id) = self
+        let (tenant_id, project_id, role) = self
             .keys
             .get(api_key)
             .ok_or(AuthError::InvalidCredentials)?;
@@ -124,7 +193,12 @@ impl Authenticator for ApiKeyAuth {
         Ok(AuthContext {
             tenant_id: *tenant_id,
             project_id: *project_id,
-            user_id: None,
+            // Callers are told apart by a digest of their key, never the key
+            user_id: Some(format!(
+                "key:{}",
+                &hex::encode(Sha256::digest(api_key.as_bytes()))[..16]
+            )),
+            role: *role,
         })
     }
 }
@@ -168,6 +242,7 @@ impl Authenticator for BearerTokenAuth {
             tenant_id: token_data.claims.tenant_id,
             project_id: token_data.claims.project_id,
             user_id: Some(token_data.claims.sub),
+            role: token_data.claims.role,
         })
     }
 }
@@ -211,6 +286,8 @@ impl Authenticator for NoAuth {
             tenant_id: self.default_tenant_id,
             project_id: Some(0),
             user_id: None,
+            // Auth is disabled, so the local user has full access
+            role: Role::Admin,
         })
     }
 }
@@ -298,23 +375,20 @@ pub async fn auth_with_rate_limit_middleware(
         }
         RateLimitResult::RateLimited { retry_after } => {
             // Rate limited - return 429 Too Many Requests
-            let mut response = Response::new(
+            let mut response = ProblemDetails::new(
+                StatusCode::TOO_MANY_REQUESTS,
+                ErrorCode::RateLimited,
                 format!(
                     "Rate limit exceeded. Try again in {} seconds.",
                     retry_after.as_secs()
-                )
-                .into(),
-            );
-            *response.status_mut() = StatusCode::TOO_MANY_REQUESTS;
+                ),
+            )
+            .with_retry_after_ms(retry_after.as_millis() as u64)
+            .into_response();
 
             // Add rate limit headers
             let headers = response.headers_mut();
             headers.insert("X-RateLimit-Remaining", HeaderValue::from_static("0"));
-            headers.insert(
-                "Retry-After",
-                HeaderValue::from_str(&retry_after.as_secs().to_string())
-                    .unwrap_or_else(|_| HeaderValue::from_static("60")),
-            );
 
             tracing::warn!(
                 "Rate limit exceeded for client: {} (retry after {}s)",
@@ -356,6 +430,33 @@ mod tests {
         let ctx = auth.authenticate(&headers).unwrap();
         assert_eq!(ctx.tenant_id, 123);
         assert_eq!(ctx.project_id, None);
+        assert_eq!(ctx.role, Role::Member);
+        let user_id = ctx.user_id.unwrap();
+        assert!(user_id.starts_with("key:") && !user_id.contains("test_key"));
+    }
+
+    #[test]
+    fn test_api_key_roles() {
+        let auth = ApiKeyAuth::new(vec![
+            "viewer_key:1::viewer".to_string(),
+            "admin_key:1:7:Admin".to_string(),
+            "bad_key:1:7:owner".to_string(),
+        ]);
+
+        let mut headers = HeaderMap::new();
+        headers.insert("X-API-Key", "viewer_key".parse().unwrap());
+        let ctx = auth.authenticate(&headers).unwrap();
+        assert_eq!(ctx.role, Role::Viewer);
+        assert_eq!(ctx.project_id, None);
+
+        headers.insert("X-API-Key", "admin_key".parse().unwrap());
+        let ctx = auth.authenticate(&headers).unwrap();
+        assert_eq!(ctx.role, Role::Admin);
+        assert_eq!(ctx.project_id, Some(7));
+
+        headers.insert("X-API-Key", "bad_key".parse().unwrap());
+        assert!(auth.authenticate(&headers).is_err());
+        assert!(Role::Viewer < Role::Member && Role::Member < Role::Admin);
     }
 
     #[test]
@@ -0,0 +1,448 @@
+// Copyright 2025 AgentReplay (https://github.com/agentreplay)
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program. If not, see <https://www.gnu.org/licenses/>.
+
+//! OpenID Connect authentication
+//!
+//! Validates ID tokens issued by an enterprise identity provider (Okta,
+//! Azure AD, Keycloak, ...) sent as `Authorization: Bearer <token>`. Signing
+//! keys come from the provider's JWKS, cached and refreshed in the
+//! background; a token signed with an unknown key id triggers an early
+//! refresh, so provider key rotation is picked up within seconds.
+
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use axum::http::{header, HeaderMap};
+use jsonwebtoken::jwk::{AlgorithmParameters, EllipticCurve, Jwk, JwkSet, KeyAlgorithm};
+use jsonwebtoken::{Algorithm, DecodingKey, Validation};
+use parking_lot::RwLock;
+use serde_json::{Map, Value};
+use tokio::sync::Notify;
+use tracing::{info, warn};
+
+use super::{AuthContext, AuthError, Authenticator, Role};
+use crate::config::OidcConfig;
+
+const HTTP_TIMEOUT: Duration = Duration::from_secs(10);
+/// Minimum time between refreshes triggered by unknown key ids
+const MIN_FORCED_REFRESH: Duration = Duration::from_secs(30);
+
+struct SigningKey {
+    key: DecodingKey,
+    algorithm: Algorithm,
+}
+
+#[derive(Default)]
+struct JwksCache {
+    keys: HashMap<String, SigningKey>,
+    refreshed_at: Option<Instant>,
+}
+
+/// OIDC ID token authenticator
+pub struct OidcAuth {
+    config: OidcConfig,
+    client: reqwest::Client,
+    jwks: RwLock<JwksCache>,
+    refresh_requested: Notify,
+}
+
+impl OidcAuth {
+    pub fn new(config: OidcConfig) -> Arc<Self> {
+        Arc::new(Self {
+            config,
+            client: reqwest::Client::builder()
+                .timeout(HTTP_TIMEOUT)
+                .build()
+                .unwrap_or_default(),
+            jwks: RwLock::new(JwksCache::default()),
+            refresh_requested: Notify::new(),
+        })
+    }
+
+    /// Fetch the provider's signing keys, returning how many are usable
+    pub async fn refresh(&self) -> Result<usize, String> {
+        let jwks_uri = match &self.config.jwks_uri {
+            Some(uri) => uri.clone(),
+            None => self.discover_jwks_uri().await?,
+        };
+        let set: JwkSet = self
+            .client
+            .get(&jwks_uri)
+            .send()
+            .await
+            .and_then(|r| r.error_for_status())
+            .map_err(|e| format!("failed to fetch JWKS from {}: {}", jwks_uri, e))?
+            .json()
+            .await
+            .map_err(|e| format!("invalid JWKS from {}: {}", jwks_uri, e))?;
+
+        let keys: HashMap<String, SigningKey> = set
+            .keys
+            .iter()
+            .filter_map(|jwk| {
+                let kid = jwk.common.key_id.clone().unwrap_or_default();
+                signing_key(jwk).map(|key| (kid, key))
+            })
+            .collect();
+        let count = keys.len();
+        *self.jwks.write() = JwksCache {
+            keys,
+            refreshed_at: Some(Instant::now()),
+        };
+        Ok(count)
+    }
+
+    async fn discover_jwks_uri(&self) -> Result<String, String> {
+        let url = format!(
+            "{}/.well-known/openid-configuration",
+            self.config.issuer.trim_end_matches('/')
+        );
+        let document: Value = self
+            .client
+            .get(&url)
+            .send()
+            .await
+            .and_then(|r| r.error_for_status())
+            .map_err(|e| format!("OIDC discovery at {} failed: {}", url, e))?
+            .json()
+            .await
+            .map_err(|e| format!("invalid OIDC discovery document at {}: {}", url, e))?;
+        document
+            .get("jwks_uri")
+            .and_then(Value::as_str)
+            .map(String::from)
+            .ok_or_else(|| format!("OIDC discovery document at {} has no jwks_uri", url))
+    }
+
+    /// Keep the key cache fresh until the server stops
+    pub fn spawn_refresh(self: &Arc<Self>) {
+        let auth = Arc::clone(self);
+        tokio::spawn(async move {
+            let period = Duration::from_secs(auth.config.jwks_refresh_minutes * 60);
+            loop {
+                match auth.refresh().await {
+                    Ok(count) => info!("Loaded {} OIDC signing keys", count),
+                    Err(e) => warn!("OIDC key refresh failed: {}", e),
+                }
+                tokio::select! {
+                    _ = tokio::time::sleep(period) => {}
+                    _ = auth.refresh_requested.notified() => {}
+                }
+            }
+        });
+    }
+
+    /// Ask the refresh task for new keys, at most every [`MIN_FORCED_REFRESH`]
+    fn request_refresh(&self) {
+        let stale = match self.jwks.read().refreshed_at {
+            Some(at) => at.elapsed() >= MIN_FORCED_REFRESH,
+            None => true,
+        };
+        if stale {
+            self.refresh_requested.notify_one();
+        }
+    }
+
+    fn validate(&self, token: &str) -> Result<Map<String, Value>, AuthError> {
+        let header = jsonwebtoken::decode_header(token)
+            .map_err(|e| AuthError::JwtValidation(e.to_string()))?;
+
+        let jwks = self.jwks.read();
+        let key = match &header.kid {
+            Some(kid) => jwks.keys.get(kid),
+            // Providers with a single key may leave out the key id
+            None if jwks.keys.len() == 1 => jwks.keys.values().next(),
+            None => None,
+        };
+        let Some(key) = key else {
+            drop(jwks);
+            self.request_refresh();
+            return Err(AuthError::JwtValidation(
+                "token signed with an unknown key".to_string(),
+            ));
+        };
+        if header.alg != key.algorithm {
+            return Err(AuthError::JwtValidation(format!(
+                "token algorithm {:?} does not match its key",
+                header.alg
+            )));
+        }
+
+        let mut validation = Validation::new(key.algorithm);
+        validation.set_issuer(&[&self.config.issuer]);
+        validation.set_audience(&[&self.config.audience]);
+        jsonwebtoken::decode::<Map<String, Value>>(token, &key.key, &validation)
+            .map(|data| data.claims)
+            .map_err(|e| AuthError::JwtValidation(e.to_string()))
+    }
+
+    #[cfg(test)]
+    fn insert_key(&self, kid: &str, key: DecodingKey, algorithm: Algorithm) {
+        let mut jwks = self.jwks.write();
+        jwks.keys
+            .insert(kid.to_string(), SigningKey { key, algorithm });
+        jwks.refreshed_at = Some(Instant::now());
+    }
+}
+
+impl Authenticator for OidcAuth {
+    fn authenticate(&self, headers: &HeaderMap) -> Result<AuthContext, AuthError> {
+        let token = headers
+            .get(header::AUTHORIZATION)
+            .and_then(|h| h.to_str().ok())
+            .and_then(|h| h.strip_prefix("Bearer "))
+            .ok_or(AuthError::MissingCredentials)?;
+        let claims = self.validate(token)?;
+        map_claims(&self.config, &claims)
+    }
+}
+
+/// Key and algorithm of an asymmetric signing key; other keys are skipped
+fn signing_key(jwk: &Jwk) -> Option<SigningKey> {
+    let algorithm = match (&jwk.common.key_algorithm, &jwk.algorithm) {
+        (Some(KeyAlgorithm::RS256), _) => Algorithm::RS256,
+        (Some(KeyAlgorithm::RS384), _) => Algorithm::RS384,
+        (Some(KeyAlgorithm::RS512), _) => Algorithm::RS512,
+        (Some(KeyAlgorithm::PS256), _) => Algorithm::PS256,
+        (Some(KeyAlgorithm::PS384), _) => Algorithm::PS384,
+        (Some(KeyAlgorithm::PS512), _) => Algorithm::PS512,
+        (Some(KeyAlgorithm::ES256), _) => Algorithm::ES256,
+        (Some(KeyAlgorithm::ES384), _) => Algorithm::ES384,
+        (Some(KeyAlgorithm::EdDSA), _) => Algorithm::EdDSA,
+        (None, AlgorithmParameters::RSA(_)) => Algorithm::RS256,
+        (None, AlgorithmParameters::EllipticCurve(ec)) => match ec.curve {
+            EllipticCurve::P256 => Algorithm::ES256,
+            EllipticCurve::P384 => Algorithm::ES384,
+            _ => return None,
+        },
+        (None, AlgorithmParameters::OctetKeyPair(_)) => Algorithm::EdDSA,
+        _ => return None,
+    };
+    match DecodingKey::from_jwk(jwk) {
+        Ok(key) => Some(SigningKey { key, algorithm }),
+        Err(e) => {
+            warn!("Skipping unusable OIDC key {:?}: {}", jwk.common.key_id, e);
+            None
+        }
+    }
+}
+
+/// Claim at a dotted path, e.g. "realm_access.roles"
+fn claim<'a>(claims: &'a Map<String, Value>, path: &str) -> Option<&'a Value> {
+    // Exact names first, since some providers use URLs as claim names
+    if let Some(value) = claims.get(path) {
+        return Some(value);
+    }
+    let mut parts = path.split('.');
+    let mut value = claims.get(parts.next()?)?;
+    for part in parts {
+        value = value.get(part)?;
+    }
+    Some(value)
+}
+
+fn claim_string(value: &Value) -> Option<String> {
+    match value {
+        Value::String(s) => Some(s.clone()),
+        Value::Number(n) => Some(n.to_string()),
+        _ => None,
+    }
+}
+
+/// Map validated claims to the caller's tenant, project and role
+pub(crate) fn map_claims(
+    config: &OidcConfig,
+    claims: &Map<String, Value>,
+) -> Result<AuthContext, AuthError> {
+    let tenant_id = match claim(claims, &config.tenant_claim).and_then(claim_string) {
+        Some(value) => match config.tenant_map.get(&value) {
+            Some(tenant_id) => *tenant_id,
+            // With a map, only listed tenants get in; numeric ids are not a
+            // way around it
+            None if !config.tenant_map.is_empty() => {
+                return Err(AuthError::Unauthorized(format!(
+                    "unknown tenant '{}'",
+                    value
+                )));
+            }
+            None => value
+                .parse()
+                .map_err(|_| AuthError::Unauthorized(format!("unknown tenant '{}'", value)))?,
+        },
+        None => config.default_tenant_id.ok_or_else(|| {
+            AuthError::Unauthorized(format!("token has no '{}' claim", config.tenant_claim))
+        })?,
+    };
+
+    let project_id = config
+        .project_claim
+        .as_deref()
+        .and_then(|name| claim(claims, name))
+        .and_then(claim_string)
+        .map(|value| {
+            value
+                .parse::<u16>()
+                .map_err(|_| AuthError::Unauthorized(format!("invalid project '{}'", value)))
+        })
+        .transpose()?;
+
+    let granted = match claim(claims, &config.roles_claim) {
+        Some(Value::Array(values)) => values.iter().filter_map(claim_string).collect(),
+        Some(value) => claim_string(value)
+            .map(|s| s.split_whitespace().map(String::from).collect())
+            .unwrap_or_default(),
+        None => Vec::new(),
+    };
+    let role = granted
+        .iter()
+        .filter_map(|name| config.role_map.get(name))
+        .copied()
+        .chain(std::iter::once(config.default_role))
+        .max()
+        .unwrap_or(Role::Viewer);
+
+    let user_id = ["email", "preferred_username", "sub"]
+        .iter()
+        .find_map(|name| claims.get(*name).and_then(Value::as_str))
+        .map(String::from);
+
+    Ok(AuthContext {
+        tenant_id,
+        project_id,
+        user_id,
+        role,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use jsonwebtoken::{EncodingKey, Header};
+
+    fn config() -> OidcConfig {
+        toml::from_str(
+            r#"
+            issuer = "https://login.example.com"
+            audience = "agentreplay"
+            tenant_claim = "org.id"
+            project_claim = "project"
+            roles_claim = "groups"
+            default_tenant_id = 1
+
+            [tenant_map]
+            acme = 42
+
+            [role_map]
+            engineers = "member"
+            platform-admins = "admin"
+            "#,
+        )
+        .unwrap()
+    }
+
+    fn claims(value: Value) -> Map<String, Value> {
+        value.as_object().unwrap().clone()
+    }
+
+    #[test]
+    fn test_map_claims() {
+        let config = config();
+        let ctx = map_claims(
+            &config,
+            &claims(serde_json::json!({
+                "sub": "u1",
+                "email": "dev@acme.com",
+                "org": {"id": "acme"},
+                "project": "7",
+                "groups": ["engineers", "platform-admins", "other"],
+            })),
+        )
+        .unwrap();
+        assert_eq!(ctx.tenant_id, 42);
+        assert_eq!(ctx.project_id, Some(7));
+        assert_eq!(ctx.role, Role::Admin);
+        assert_eq!(ctx.user_id.as_deref(), Some("dev@acme.com"));
+
+        // Missing tenant falls back to the default; no groups gives the default role
+        let ctx = map_claims(&config, &claims(serde_json::json!({"sub": "u2"}))).unwrap();
+        assert_eq!(ctx.tenant_id, 1);
+        assert_eq!(ctx.role, Role::Viewer);
+        assert_eq!(ctx.user_id.as_deref(), Some("u2"));
+
+        // With a tenant map, unlisted names and numeric ids are rejected
+        for tenant in [serde_json::json!("globex"), serde_json::json!(9)] {
+            let claims = claims(serde_json::json!({"org": {"id": tenant}}));
+            assert!(matches!(
+                map_claims(&config, &claims),
+                Err(AuthError::Unauthorized(_))
+            ));
+        }
+
+        // Without one, numeric tenants pass through
+        let config = OidcConfig {
+            tenant_map: HashMap::new(),
+            ..config
+        };
+        let ctx = map_claims(&config, &claims(serde_json::json!({"org": {"id": 9}}))).unwrap();
+        assert_eq!(ctx.tenant_id, 9);
+        assert!(map_claims(
+            &config,
+            &claims(serde_json::json!({"org": {"id": "globex"}}))
+        )
+        .is_err());
+    }
+
+    #[test]
+    fn test_validate_token() {
+        let auth = OidcAuth::new(config());
+        auth.insert_key("k1", DecodingKey::from_secret(b"secret"), Algorithm::HS256);
+
+        let token = |kid: &str, iss: &str, aud: &str| {
+            let mut jwt_header = Header::new(Algorithm::HS256);
+            jwt_header.kid = Some(kid.to_string());
+            let claims = serde_json::json!({
+                "iss": iss,
+                "aud": aud,
+                "sub": "u1",
+                "exp": 4_000_000_000u64,
+                "groups": ["engineers"],
+            });
+            let token =
+                jsonwebtoken::encode(&jwt_header, &claims, &EncodingKey::from_secret(b"secret"))
+                    .unwrap();
+            let mut headers = HeaderMap::new();
+            headers.insert(
+                header::AUTHORIZATION,
+                format!("Bearer {}", token).parse().unwrap(),
+            );
+            headers
+        };
+
+        let ctx = auth
+            .authenticate(&token("k1", "https://login.example.com", "agentreplay"))
+            .unwrap();
+        assert_eq!(ctx.role, Role::Member);
+        assert!(auth
+            .authenticate(&token("k1", "https://evil.example.com", "agentreplay"))
+            .is_err());
+        assert!(auth
+            .authenticate(&token("k1", "https://login.example.com", "other-app"))
+            .is_err());
+        assert!(auth
+            .authenticate(&token("k2", "https://login.example.com", "agentreplay"))
+            .is_err());
+    }
+}
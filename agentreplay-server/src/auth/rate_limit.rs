@@ -46,7 +46,7 @@ impl Default for RateLimitConfig {
 ///
 /// CRITICAL FIX: Now uses AtomicU64 for thread-safe updates without Mutex
 #[derive(Debug)]
-struct TokenBucket {
+pub(crate) struct TokenBucket {
     /// Current number of tokens (scaled by 1000 for precision)
     tokens: AtomicU64,
     /// Maximum tokens (capacity)
@@ -60,7 +60,7 @@ struct TokenBucket {
 }
 
 impl TokenBucket {
-    fn new(capacity: u32, window: Duration) -> Self {
+    pub(crate) fn new(capacity: u32, window: Duration) -> Self {
         let refill_rate = capacity as f64 / window.as_secs_f64();
         Self {
             tokens: AtomicU64::new((capacity as u64) * 1000), // Scale by 1000
@@ -95,7 +95,7 @@ impl TokenBucket {
     }
 
     /// Try to consume one token
-    fn try_consume(&self) -> bool {
+    pub(crate) fn try_consume(&self) -> bool {
         self.refill();
 
         let current = self.get_tokens();
@@ -108,13 +108,13 @@ impl TokenBucket {
     }
 
     /// Get remaining tokens
-    fn remaining(&self) -> u32 {
+    pub(crate) fn remaining(&self) -> u32 {
         self.refill();
         self.get_tokens().floor() as u32
     }
 
     /// Get time until next token is available
-    fn retry_after(&self) -> Duration {
+    pub(crate) fn retry_after(&self) -> Duration {
         self.refill();
 
         let current = self.get_tokens();
@@ -126,6 +126,14 @@ impl TokenBucket {
             Duration::from_secs_f64(seconds)
         }
     }
+
+    /// Get time until the bucket is full again
+    pub(crate) fn reset_after(&self) -> Duration {
+        self.refill();
+
+        let missing = (self.capacity - self.get_tokens()).max(0.0);
+        Duration::from_secs_f64(missing / self.refill_rate)
+    }
 }
 
 /// Rate limiter using token bucket algorithm
@@ -0,0 +1,713 @@
+// Copyright 2025 AgentReplay (https://github.com/agentreplay)
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program. If not, see <https://www.gnu.org/licenses/>.
+
+//! Managed API tokens
+//!
+//! Tenant admins issue tokens through `/api/v1/admin/tokens` instead of
+//! listing keys in `auth.api_keys`. Each token is scoped to a tenant, an
+//! optional project and a [`Role`], may expire, and can be revoked or
+//! rotated. Only the SHA-256 of a token is stored; the token itself is
+//! returned once, when it is issued.
+//!
+//! Tokens are sent like static API keys (`X-API-Key`, or `?api_key=` for
+//! WebSocket clients) or as `Authorization: Bearer art_...`.
+
+use agentreplay_core::clock::now_us;
+use std::collections::HashMap;
+use std::path::{Path as FsPath, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+
+use axum::{
+    extract::{Path, State},
+    http::{header, HeaderMap, StatusCode},
+    Extension, Json,
+};
+use parking_lot::Mutex;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use tracing::warn;
+
+use super::{AuthContext, AuthError, Authenticator, Role};
+use crate::api::query::ApiError;
+use crate::api::AppState;
+
+/// Tokens file under the data directory
+pub const API_TOKENS_FILE: &str = "api_tokens.json";
+
+/// Prefix of every issued token, so they are recognisable in logs and scanners
+pub const TOKEN_PREFIX: &str = "art_";
+
+const DAY_US: u64 = 24 * 3_600_000_000;
+const MAX_TTL_DAYS: u32 = 730;
+const MAX_ROTATION_GRACE_SECS: u64 = 7 * 24 * 3600;
+const MAX_NAME_LEN: usize = 100;
+/// Last-used times are written to disk at most this often
+const LAST_USED_FLUSH_INTERVAL: Duration = Duration::from_secs(60);
+/// Characters of the token kept for display
+const DISPLAY_PREFIX_LEN: usize = TOKEN_PREFIX.len() + 8;
+
+fn hash_token(token: &str) -> String {
+    hex::encode(Sha256::digest(token.as_bytes()))
+}
+
+/// A stored token; never contains the token itself
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ApiToken {
+    pub id: String,
+    pub name: String,
+    pub tenant_id: u64,
+    #[serde(default)]
+    pub project_id: Option<u16>,
+    pub role: Role,
+    /// First characters of the token, to tell tokens apart
+    pub display_prefix: String,
+    #[serde(skip)]
+    pub token_hash: String,
+    pub created_at_us: u64,
+    #[serde(default)]
+    pub created_by: Option<String>,
+    #[serde(default)]
+    pub expires_at_us: Option<u64>,
+    #[serde(default)]
+    pub last_used_at_us: Option<u64>,
+    #[serde(default)]
+    pub revoked_at_us: Option<u64>,
+    /// Token this one replaced, when issued by rotation
+    #[serde(default)]
+    pub rotated_from: Option<String>,
+}
+
+impl ApiToken {
+    pub fn is_active(&self, now_us: u64) -> bool {
+        self.revoked_at_us.is_none() && !matches!(self.expires_at_us, Some(at) if at <= now_us)
+    }
+
+    /// Whether a caller scoped to `tenant_id` and, if set, `project_id`
+    /// manages this token
+    fn in_scope(&self, tenant_id: u64, project_id: Option<u16>) -> bool {
+        self.tenant_id == tenant_id && project_id.is_none_or(|p| self.project_id == Some(p))
+    }
+}
+
+/// On-disk form, which unlike the API form keeps the hash
+#[derive(Serialize, Deserialize)]
+struct StoredToken {
+    #[serde(flatten)]
+    token: ApiToken,
+    token_hash: String,
+}
+
+/// Scope of a new token
+#[derive(Debug, Clone)]
+pub struct TokenGrant {
+    pub name: String,
+    pub tenant_id: u64,
+    pub project_id: Option<u16>,
+    pub role: Role,
+    pub expires_at_us: Option<u64>,
+    pub created_by: Option<String>,
+}
+
+#[derive(Default)]
+struct Tokens {
+    by_id: HashMap<String, ApiToken>,
+    /// token hash -> id
+    by_hash: HashMap<String, String>,
+}
+
+/// Issued tokens, persisted as JSON
+pub struct TokenStore {
+    storage_path: Option<PathBuf>,
+    tokens: Mutex<Tokens>,
+    /// Held from snapshot to rename, so an older snapshot never replaces a
+    /// newer file
+    persist_lock: Mutex<()>,
+    /// Last-used times changed since the last save
+    last_used_dirty: AtomicBool,
+}
+
+impl Default for TokenStore {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl TokenStore {
+    /// In-memory store; tokens are lost on restart
+    pub fn new() -> Self {
+        Self {
+            storage_path: None,
+            tokens: Mutex::new(Tokens::default()),
+            persist_lock: Mutex::new(()),
+            last_used_dirty: AtomicBool::new(false),
+        }
+    }
+
+    /// Store persisting tokens to `path`, loading any already saved
+    pub fn with_storage(path: impl AsRef<FsPath>) -> Self {
+        let store = Self::new();
+        let path = path.as_ref().to_path_buf();
+
+        match std::fs::read(&path) {
+            Ok(bytes) => match serde_json::from_slice::<Vec<StoredToken>>(&bytes) {
+                Ok(stored) => {
+                    let mut tokens = store.tokens.lock();
+                    for StoredToken {
+                        mut token,
+                        token_hash,
+                    } in stored
+                    {
+                        token.token_hash = token_hash.clone();
+                        tokens.by_hash.insert(token_hash, token.id.clone());
+                        tokens.by_id.insert(token.id.clone(), token);
+                    }
+                }
+                Err(e) => warn!("Failed to parse {:?}: {}", path, e),
+            },
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => {}
+            Err(e) => warn!("Failed to read {:?}: {}", path, e),
+        }
+
+        Self {
+            storage_path: Some(path),
+            ..store
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.tokens.lock().by_id.is_empty()
+    }
+
+    /// Issue a token, returning its record and the token itself
+    pub fn issue(&self, grant: TokenGrant) -> (ApiToken, String) {
+        let (token, secret) = self.insert(grant, None);
+        self.persist_logged();
+        (token, secret)
+    }
+
+    fn insert(&self, grant: TokenGrant, rotated_from: Option<String>) -> (ApiToken, String) {
+        let secret = format!(
+            "{}{}",
+            TOKEN_PREFIX,
+            hex::encode(rand::random::<[u8; 32]>())
+        );
+        let token = ApiToken {
+            id: hex::encode(rand::random::<[u8; 8]>()),
+            name: grant.name,
+            tenant_id: grant.tenant_id,
+            project_id: grant.project_id,
+            role: grant.role,
+            display_prefix: secret[..DISPLAY_PREFIX_LEN].to_string(),
+            token_hash: hash_token(&secret),
+            created_at_us: now_us(),
+            created_by: grant.created_by,
+            expires_at_us: grant.expires_at_us,
+            last_used_at_us: None,
+            revoked_at_us: None,
+            rotated_from,
+        };
+        let mut tokens = self.tokens.lock();
+        tokens
+            .by_hash
+            .insert(token.token_hash.clone(), token.id.clone());
+        tokens.by_id.insert(token.id.clone(), token.clone());
+        (token, secret)
+    }
+
+    /// Tokens of a tenant, or of one of its projects, newest first
+    pub fn list(&self, tenant_id: u64, project_id: Option<u16>) -> Vec<ApiToken> {
+        let mut tokens: Vec<ApiToken> = self
+            .tokens
+            .lock()
+            .by_id
+            .values()
+            .filter(|t| t.in_scope(tenant_id, project_id))
+            .cloned()
+            .collect();
+        tokens.sort_by(|a, b| b.created_at_us.cmp(&a.created_at_us));
+        tokens
+    }
+
+    /// Revoke a token; revoking twice keeps the first revocation time
+    pub fn revoke(&self, tenant_id: u64, project_id: Option<u16>, id: &str) -> Option<ApiToken> {
+        let revoked = {
+            let mut tokens = self.tokens.lock();
+            let token = tokens
+                .by_id
+                .get_mut(id)
+                .filter(|t| t.in_scope(tenant_id, project_id))?;
+            token.revoked_at_us.get_or_insert_with(now_us);
+            token.clone()
+        };
+        self.persist_logged();
+        Some(revoked)
+    }
+
+    /// Replace an active token with a new one of the same scope
+    ///
+    /// The old token keeps working for `grace_us`, so clients can be
+    /// switched over without downtime, and is revoked immediately when
+    /// `grace_us` is zero.
+    pub fn rotate(
+        &self,
+        tenant_id: u64,
+        project_id: Option<u16>,
+        id: &str,
+        expires_at_us: Option<u64>,
+        grace_us: u64,
+        rotated_by: Option<String>,
+    ) -> Result<(ApiToken, String), ApiError> {
+        let now = now_us();
+        let grant = {
+            let mut tokens = self.tokens.lock();
+            let old = tokens
+                .by_id
+                .get_mut(id)
+                .filter(|t| t.in_scope(tenant_id, project_id))
+                .ok_or_else(|| ApiError::NotFound(format!("Token {} not found", id)))?;
+            if !old.is_active(now) {
+                return Err(ApiError::BadRequest(format!(
+                    "token {} is revoked or expired",
+                    id
+                )));
+            }
+            if grace_us == 0 {
+                old.revoked_at_us = Some(now);
+            } else {
+                let ends = now + grace_us;
+                old.expires_at_us = Some(old.expires_at_us.map_or(ends, |at| at.min(ends)));
+            }
+            TokenGrant {
+                name: old.name.clone(),
+                tenant_id,
+                project_id: old.project_id,
+                role: old.role,
+                expires_at_us,
+                created_by: rotated_by,
+            }
+        };
+        let rotated = self.insert(grant, Some(id.to_string()));
+        self.persist_logged();
+        Ok(rotated)
+    }
+
+    /// Resolve a presented token, recording its use
+    ///
+    /// The use is only recorded in memory; [`Self::spawn_flush`] saves it.
+    pub fn verify(&self, presented: &str) -> Result<AuthContext, AuthError> {
+        let now = now_us();
+        let hash = hash_token(presented);
+        let mut tokens = self.tokens.lock();
+        let id = tokens
+            .by_hash
+            .get(&hash)
+            .cloned()
+            .ok_or(AuthError::InvalidCredentials)?;
+        let token = tokens
+            .by_id
+            .get_mut(&id)
+            .ok_or(AuthError::InvalidCredentials)?;
+        if !token.is_active(now) {
+            return Err(AuthError::InvalidCredentials);
+        }
+        token.last_used_at_us = Some(now);
+        self.last_used_dirty.store(true, Ordering::Relaxed);
+        Ok(AuthContext {
+            tenant_id: token.tenant_id,
+            project_id: token.project_id,
+            user_id: Some(format!("token:{}", token.id)),
+            role: token.role,
+        })
+    }
+
+    /// Save recorded last-used times periodically in the background
+    pub fn spawn_flush(self: &Arc<Self>) {
+        if self.storage_path.is_none() {
+            return;
+        }
+        let store = Arc::clone(self);
+        tokio::spawn(async move {
+            let mut interval = tokio::time::interval(LAST_USED_FLUSH_INTERVAL);
+            interval.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+            loop {
+                interval.tick().await;
+                if !store.last_used_dirty.load(Ordering::Relaxed) {
+                    continue;
+                }
+                let store = Arc::clone(&store);
+                if let Err(e) = tokio::task::spawn_blocking(move || store.persist_logged()).await {
+                    warn!("API token flush failed: {}", e);
+                }
+            }
+        });
+    }
+
+    fn persist_logged(&self) {
+        if let Err(e) = self.persist() {
+            warn!("Failed to persist API tokens: {}", e);
+        }
+    }
+
+    fn persist(&self) -> std::io::Result<()> {
+        let Some(path) = &self.storage_path else {
+            return Ok(());
+        };
+        let _persisting = self.persist_lock.lock();
+        // Cleared before the snapshot: uses recorded after it are saved by
+        // the next flush
+        self.last_used_dirty.store(false, Ordering::Relaxed);
+        let stored: Vec<StoredToken> = self
+            .tokens
+            .lock()
+            .by_id
+            .values()
+            .map(|token| StoredToken {
+                token_hash: token.token_hash.clone(),
+                token: token.clone(),
+            })
+            .collect();
+
+        let tmp = path.with_extension("json.tmp");
+        let written = serde_json::to_vec_pretty(&stored)
+            .map_err(std::io::Error::from)
+            .and_then(|bytes| std::fs::write(&tmp, bytes))
+            .and_then(|_| std::fs::rename(tmp, path));
+        if written.is_err() {
+            self.last_used_dirty.store(true, Ordering::Relaxed);
+        }
+        written
+    }
+}
+
+impl Authenticator for TokenStore {
+    fn authenticate(&self, headers: &HeaderMap) -> Result<AuthContext, AuthError> {
+        let presented = headers
+            .get("X-API-Key")
+            .or_else(|| headers.get("X-Agentreplay-API-Key"))
+            .and_then(|h| h.to_str().ok())
+            .or_else(|| {
+                headers
+                    .get(header::AUTHORIZATION)
+                    .and_then(|h| h.to_str().ok())
+                    .and_then(|h| h.strip_prefix("Bearer "))
+            })
+            .filter(|token| token.starts_with(TOKEN_PREFIX))
+            .ok_or(AuthError::MissingCredentials)?;
+        self.verify(presented)
+    }
+}
+
+fn expiry(expires_in_days: Option<u32>, now_us: u64) -> Result<Option<u64>, ApiError> {
+    match expires_in_days {
+        Some(days) if days == 0 || days > MAX_TTL_DAYS => Err(ApiError::BadRequest(format!(
+            "expires_in_days must be between 1 and {}",
+            MAX_TTL_DAYS
+        ))),
+        Some(days) => Ok(Some(now_us + days as u64 * DAY_US)),
+        None => Ok(None),
+    }
+}
+
+/// Project of a token the caller asked for; callers scoped to a project
+/// may only issue tokens for that project
+fn granted_project(auth: &AuthContext, requested: Option<u16>) -> Result<Option<u16>, ApiError> {
+    match (auth.project_id, requested) {
+        (None, requested) => Ok(requested),
+        (Some(own), None) => Ok(Some(own)),
+        (Some(own), Some(requested)) if requested == own => Ok(Some(own)),
+        (Some(own), Some(requested)) => Err(ApiError::Forbidden(format!(
+            "callers scoped to project {} may not issue tokens for project {}",
+            own, requested
+        ))),
+    }
+}
+
+#[derive(Debug, Deserialize)]
+pub struct IssueTokenRequest {
+    pub name: String,
+    /// Defaults to the caller's project when the caller is scoped to one
+    #[serde(default)]
+    pub project_id: Option<u16>,
+    #[serde(default)]
+    pub role: Role,
+    /// Omit for a token that does not expire
+    #[serde(default)]
+    pub expires_in_days: Option<u32>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct IssuedTokenResponse {
+    #[serde(flatten)]
+    pub record: ApiToken,
+    /// Shown only in this response
+    pub token: String,
+}
+
+/// POST /api/v1/admin/tokens
+pub async fn issue_token(
+    State(state): State<AppState>,
+    Extension(auth): Extension<AuthContext>,
+    Json(request): Json<IssueTokenRequest>,
+) -> Result<(StatusCode, Json<IssuedTokenResponse>), ApiError> {
+    auth.require_role(Role::Admin)?;
+    let name = request.name.trim();
+    if name.is_empty() || name.chars().count() > MAX_NAME_LEN {
+        return Err(ApiError::BadRequest(format!(
+            "name must be between 1 and {} characters",
+            MAX_NAME_LEN
+        )));
+    }
+    if request.role > auth.role {
+        return Err(ApiError::Forbidden(format!(
+            "role '{}' may not issue '{}' tokens",
+            auth.role.as_str(),
+            request.role.as_str()
+        )));
+    }
+    let project_id = granted_project(&auth, request.project_id)?;
+    let (record, token) = state.api_tokens.issue(TokenGrant {
+        name: name.to_string(),
+        tenant_id: auth.tenant_id,
+        project_id,
+        role: request.role,
+        expires_at_us: expiry(request.expires_in_days, now_us())?,
+        created_by: auth.user_id,
+    });
+    Ok((
+        StatusCode::CREATED,
+        Json(IssuedTokenResponse { record, token }),
+    ))
+}
+
+/// GET /api/v1/admin/tokens
+pub async fn list_tokens(
+    State(state): State<AppState>,
+    Extension(auth): Extension<AuthContext>,
+) -> Result<Json<Vec<ApiToken>>, ApiError> {
+    auth.require_role(Role::Admin)?;
+    Ok(Json(state.api_tokens.list(auth.tenant_id, auth.project_id)))
+}
+
+/// DELETE /api/v1/admin/tokens/:id
+pub async fn revoke_token(
+    State(state): State<AppState>,
+    Extension(auth): Extension<AuthContext>,
+    Path(id): Path<String>,
+) -> Result<Json<ApiToken>, ApiError> {
+    auth.require_role(Role::Admin)?;
+    state
+        .api_tokens
+        .revoke(auth.tenant_id, auth.project_id, &id)
+        .map(Json)
+        .ok_or_else(|| ApiError::NotFound(format!("Token {} not found", id)))
+}
+
+#[derive(Debug, Default, Deserialize)]
+pub struct RotateTokenRequest {
+    /// Omit for a token that does not expire
+    #[serde(default)]
+    pub expires_in_days: Option<u32>,
+    /// How long the old token keeps working; 0 revokes it immediately
+    #[serde(default)]
+    pub grace_seconds: u64,
+}
+
+/// POST /api/v1/admin/tokens/:id/rotate
+pub async fn rotate_token(
+    State(state): State<AppState>,
+    Extension(auth): Extension<AuthContext>,
+    Path(id): Path<String>,
+    request: Option<Json<RotateTokenRequest>>,
+) -> Result<Json<IssuedTokenResponse>, ApiError> {
+    auth.require_role(Role::Admin)?;
+    let request = request.map(|Json(r)| r).unwrap_or_default();
+    if request.grace_seconds > MAX_ROTATION_GRACE_SECS {
+        return Err(ApiError::BadRequest(format!(
+            "grace_seconds may not exceed {}",
+            MAX_ROTATION_GRACE_SECS
+        )));
+    }
+    let (record, token) = state.api_tokens.rotate(
+        auth.tenant_id,
+        auth.project_id,
+        &id,
+        expiry(request.expires_in_days, now_us())?,
+        request.grace_seconds * 1_000_000,
+        auth.user_id,
+    )?;
+    Ok(Json(IssuedTokenResponse { record, token }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn grant(tenant_id: u64) -> TokenGrant {
+        TokenGrant {
+            name: "ci".to_string(),
+            tenant_id,
+            project_id: Some(3),
+            role: Role::Viewer,
+            expires_at_us: None,
+            created_by: None,
+        }
+    }
+
+    fn headers(token: &str) -> HeaderMap {
+        let mut headers = HeaderMap::new();
+        headers.insert("X-API-Key", token.parse().unwrap());
+        headers
+    }
+
+    #[test]
+    fn test_issue_verify_revoke() {
+        let store = TokenStore::new();
+        let (record, token) = store.issue(grant(7));
+        assert!(token.starts_with(TOKEN_PREFIX));
+        assert!(token.starts_with(&record.display_prefix));
+        assert_ne!(record.token_hash, token);
+
+        let ctx = store.authenticate(&headers(&token)).unwrap();
+        assert_eq!(ctx.tenant_id, 7);
+        assert_eq!(ctx.project_id, Some(3));
+        assert_eq!(ctx.role, Role::Viewer);
+        assert!(store.list(7, None)[0].last_used_at_us.is_some());
+
+        let mut bearer = HeaderMap::new();
+        bearer.insert(
+            header::AUTHORIZATION,
+            format!("Bearer {}", token).parse().unwrap(),
+        );
+        assert!(store.authenticate(&bearer).is_ok());
+
+        // Other tenants cannot revoke it
+        assert!(store.revoke(8, None, &record.id).is_none());
+        assert!(store.revoke(7, None, &record.id).is_some());
+        assert!(store.authenticate(&headers(&token)).is_err());
+    }
+
+    #[test]
+    fn test_project_scoped_callers() {
+        let store = TokenStore::new();
+        let (own, _) = store.issue(grant(1));
+        let (other, _) = store.issue(TokenGrant {
+            project_id: None,
+            ..grant(1)
+        });
+        let listed = store.list(1, Some(3));
+        assert_eq!(listed.len(), 1);
+        assert_eq!(listed[0].id, own.id);
+        assert!(store.revoke(1, Some(3), &other.id).is_none());
+        assert!(store.rotate(1, Some(4), &own.id, None, 0, None).is_err());
+        assert!(store.revoke(1, Some(3), &own.id).is_some());
+
+        let auth = AuthContext {
+            tenant_id: 1,
+            project_id: Some(3),
+            user_id: None,
+            role: Role::Admin,
+        };
+        assert_eq!(granted_project(&auth, None).unwrap(), Some(3));
+        assert_eq!(granted_project(&auth, Some(3)).unwrap(), Some(3));
+        assert!(granted_project(&auth, Some(4)).is_err());
+    }
+
+    #[test]
+    fn test_expiry_and_rotation() {
+        let store = TokenStore::new();
+        let (expired, expired_token) = store.issue(TokenGrant {
+            expires_at_us: Some(1),
+            ..grant(1)
+        });
+        assert!(store.authenticate(&headers(&expired_token)).is_err());
+        assert!(store.rotate(1, None, &expired.id, None, 0, None).is_err());
+
+        let (old, old_token) = store.issue(grant(1));
+        let (new, new_token) = store.rotate(1, None, &old.id, None, 0, None).unwrap();
+        assert_eq!(new.rotated_from.as_deref(), Some(old.id.as_str()));
+        assert_eq!(new.project_id, old.project_id);
+        assert!(store.authenticate(&headers(&old_token)).is_err());
+        assert!(store.authenticate(&headers(&new_token)).is_ok());
+
+        // With a grace period the old token keeps working for now
+        let (_, newer_token) = store
+            .rotate(1, None, &new.id, None, 60_000_000, None)
+            .unwrap();
+        assert!(store.authenticate(&headers(&new_token)).is_ok());
+        assert!(store.authenticate(&headers(&newer_token)).is_ok());
+    }
+
+    #[test]
+    fn test_tokens_persist_hashed() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join(API_TOKENS_FILE);
+
+        let store = TokenStore::with_storage(&path);
+        let (record, token) = store.issue(grant(1));
+        let saved = std::fs::read_to_string(&path).unwrap();
+        assert!(!saved.contains(&token));
+        assert!(saved.contains(&record.token_hash));
+
+        let reloaded = TokenStore::with_storage(&path);
+        assert_eq!(
+            reloaded.authenticate(&headers(&token)).unwrap().tenant_id,
+            1
+        );
+        // The API form never exposes the hash
+        let listed = serde_json::to_string(&reloaded.list(1, None)).unwrap();
+        assert!(!listed.contains(&record.token_hash));
+    }
+
+    #[test]
+    fn test_last_used_saved_by_flush() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join(API_TOKENS_FILE);
+
+        let store = TokenStore::with_storage(&path);
+        let (_, token) = store.issue(grant(1));
+        store.authenticate(&headers(&token)).unwrap();
+        // Verifying does not write the file
+        let saved = TokenStore::with_storage(&path);
+        assert!(saved.list(1, None)[0].last_used_at_us.is_none());
+
+        store.persist().unwrap();
+        let saved = TokenStore::with_storage(&path);
+        assert!(saved.list(1, None)[0].last_used_at_us.is_some());
+    }
+
+    #[test]
+    fn test_concurrent_persists_keep_every_token() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join(API_TOKENS_FILE);
+        let store = Arc::new(TokenStore::with_storage(&path));
+
+        let handles: Vec<_> = (0..8)
+            .map(|_| {
+                let store = store.clone();
+                std::thread::spawn(move || {
+                    for _ in 0..5 {
+                        store.issue(grant(1));
+                    }
+                })
+            })
+            .collect();
+        for handle in handles {
+            handle.join().unwrap();
+        }
+        assert_eq!(TokenStore::with_storage(&path).list(1, None).len(), 40);
+    }
+}
@@ -0,0 +1,1138 @@
+// Copyright 2025 AgentReplay (https://github.com/agentreplay)
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program. If not, see <https://www.gnu.org/licenses/>.
+
+//! Model benchmarks: the same prompts against several models, side by side
+//!
+//! A benchmark sends every prompt to every model through the
+//! [`LLMProviderManager`](crate::llm::LLMProviderManager), either with a
+//! configured provider or with a vault key, so each call is recorded as a
+//! trace like any chat proxy request. Every answer is then scored with the
+//! requested evaluators (reference metrics against the expected output by
+//! default) and the calls are aggregated per model into a comparison matrix
+//! of quality, cost and latency.
+//!
+//! `POST /api/v1/benchmarks` starts the benchmark as a background job whose
+//! ID is the benchmark ID, so it can be followed and cancelled through
+//! `/api/v1/jobs/:id`. Finished benchmarks are kept, to compare runs of the
+//! same name across model upgrades.
+
+use agentreplay_core::clock::now_us;
+use std::collections::{BTreeMap, HashMap, VecDeque};
+use std::path::{Path as FsPath, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+
+use agentreplay_evals::evaluators::{
+    LowConfidenceDetector, ReferenceEvaluator, StructuredOutputEvaluator,
+};
+use agentreplay_evals::{
+    EvalConfig, EvalResult, Evaluator, EvaluatorRegistry, MetricValue, TraceContext,
+};
+use axum::{
+    extract::{Path, Query, State},
+    http::StatusCode,
+    Extension, Json,
+};
+use futures::StreamExt;
+use parking_lot::Mutex;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use tracing::warn;
+
+use crate::api::query::ApiError;
+use crate::api::AppState;
+use crate::auth::AuthContext;
+use crate::jobs::{Job, JobContext};
+use crate::llm::ChatMessage;
+use crate::otel_genai::ModelPricing;
+use crate::simulation::task_messages;
+use crate::vault::ResolvedKey;
+
+/// File under the data directory that benchmarks are persisted to
+pub const BENCHMARKS_FILE: &str = "benchmarks.json";
+
+/// Evaluator scoring answers against the prompt's expected output
+pub const REFERENCE_EVALUATOR: &str = "reference_metrics_v1";
+
+const MAX_PROMPTS: usize = 500;
+const MAX_MODELS: usize = 10;
+/// Benchmarks kept per tenant, oldest dropped first
+const MAX_BENCHMARKS_PER_TENANT: usize = 100;
+/// Model calls in flight at once
+const CONCURRENCY: usize = 4;
+const DEFAULT_LIST_LIMIT: usize = 20;
+
+/// A model to benchmark: a configured provider or a vault key, and the
+/// model name to request
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BenchmarkModel {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub provider: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub key_alias: Option<String>,
+    pub model: String,
+    /// Column name in the matrix, `{provider or key alias}/{model}` by default
+    #[serde(default)]
+    pub label: String,
+}
+
+impl BenchmarkModel {
+    fn validate(&mut self) -> Result<(), String> {
+        let source = match (&self.provider, &self.key_alias) {
+            (Some(provider), None) => provider.clone(),
+            (None, Some(alias)) => alias.clone(),
+            _ => {
+                return Err(format!(
+                    "Model {} needs exactly one of provider or key_alias",
+                    self.model
+                ))
+            }
+        };
+        if self.model.trim().is_empty() {
+            return Err("Model names cannot be empty".to_string());
+        }
+        if self.label.is_empty() {
+            self.label = format!("{}/{}", source, self.model);
+        }
+        Ok(())
+    }
+}
+
+/// One prompt of a benchmark
+#[derive(Debug, Clone, Deserialize)]
+pub struct BenchmarkPrompt {
+    #[serde(default)]
+    pub id: Option<String>,
+    /// A string, `{"messages": [...]}` or an object with a prompt field, as
+    /// for simulation tasks
+    pub input: Value,
+    #[serde(default)]
+    pub expected_output: Option<String>,
+}
+
+/// Body of `POST /api/v1/benchmarks`
+#[derive(Debug, Deserialize)]
+pub struct BenchmarkRequest {
+    pub name: String,
+    /// Prompts to send; alternatively `dataset_id`
+    #[serde(default)]
+    pub prompts: Vec<BenchmarkPrompt>,
+    /// Eval dataset whose test cases are used as prompts
+    #[serde(default)]
+    pub dataset_id: Option<String>,
+    pub models: Vec<BenchmarkModel>,
+    /// Evaluator IDs, [`REFERENCE_EVALUATOR`] when empty
+    #[serde(default)]
+    pub evaluators: Vec<String>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum BenchmarkStatus {
+    Running,
+    Completed,
+    Cancelled,
+    Failed,
+}
+
+/// One prompt sent to one model
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct BenchmarkCell {
+    pub prompt_id: String,
+    pub model: String,
+    /// Edge ID of the recorded call
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub trace_id: Option<String>,
+    pub latency_ms: u64,
+    #[serde(default)]
+    pub input_tokens: Option<u32>,
+    #[serde(default)]
+    pub output_tokens: Option<u32>,
+    #[serde(default)]
+    pub cost_usd: Option<f64>,
+    /// Mean of the evaluator scores
+    #[serde(default)]
+    pub quality: Option<f64>,
+    /// Whether every evaluator passed; None when none ran
+    #[serde(default)]
+    pub passed: Option<bool>,
+    /// Score by evaluator ID
+    #[serde(default)]
+    pub scores: BTreeMap<String, f64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+/// A model's row of the comparison matrix
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct ModelSummary {
+    pub model: String,
+    pub calls: usize,
+    pub errors: usize,
+    /// Mean quality of the scored calls
+    pub quality: Option<f64>,
+    pub pass_rate: Option<f64>,
+    pub mean_latency_ms: f64,
+    pub p95_latency_ms: f64,
+    pub total_cost_usd: f64,
+    pub mean_cost_usd: f64,
+    /// Mean score by evaluator ID
+    pub scores: BTreeMap<String, f64>,
+}
+
+/// A benchmark and its results
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Benchmark {
+    pub id: String,
+    pub tenant_id: u64,
+    pub name: String,
+    pub status: BenchmarkStatus,
+    pub created_at_us: u64,
+    #[serde(default)]
+    pub finished_at_us: Option<u64>,
+    /// Session the calls are recorded under
+    pub session_id: u64,
+    pub prompts: usize,
+    pub models: Vec<BenchmarkModel>,
+    pub evaluators: Vec<String>,
+    /// One row per model, in request order
+    #[serde(default)]
+    pub matrix: Vec<ModelSummary>,
+    #[serde(default)]
+    pub cells: Vec<BenchmarkCell>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+/// A benchmark without its cells, as listed
+#[derive(Debug, Clone, Serialize)]
+pub struct BenchmarkSummary {
+    pub id: String,
+    pub name: String,
+    pub status: BenchmarkStatus,
+    pub created_at_us: u64,
+    pub finished_at_us: Option<u64>,
+    pub prompts: usize,
+    pub matrix: Vec<ModelSummary>,
+}
+
+impl From<&Benchmark> for BenchmarkSummary {
+    fn from(benchmark: &Benchmark) -> Self {
+        Self {
+            id: benchmark.id.clone(),
+            name: benchmark.name.clone(),
+            status: benchmark.status,
+            created_at_us: benchmark.created_at_us,
+            finished_at_us: benchmark.finished_at_us,
+            prompts: benchmark.prompts,
+            matrix: benchmark.matrix.clone(),
+        }
+    }
+}
+
+/// Score of an evaluator result: its `score` or `primary_score` metric,
+/// else 1 when it passed and 0 when it did not
+pub fn result_score(result: &EvalResult) -> f64 {
+    ["score", "primary_score"]
+        .iter()
+        .find_map(|name| match result.metrics.get(*name) {
+            Some(MetricValue::Float(score)) => Some(*score),
+            _ => None,
+        })
+        .unwrap_or(if result.passed { 1.0 } else { 0.0 })
+}
+
+/// Nearest-rank percentile of sorted values (0 when empty)
+fn percentile(sorted: &[f64], q: f64) -> f64 {
+    if sorted.is_empty() {
+        return 0.0;
+    }
+    let rank = (q * sorted.len() as f64).ceil() as usize;
+    sorted[rank.clamp(1, sorted.len()) - 1]
+}
+
+fn mean(values: &[f64]) -> Option<f64> {
+    (!values.is_empty()).then(|| values.iter().sum::<f64>() / values.len() as f64)
+}
+
+/// Aggregate the cells of each model into its matrix row
+///
+/// Failed calls count as errors and are left out of every other column.
+pub fn build_matrix(models: &[BenchmarkModel], cells: &[BenchmarkCell]) -> Vec<ModelSummary> {
+    models
+        .iter()
+        .map(|model| {
+            let calls: Vec<&BenchmarkCell> =
+                cells.iter().filter(|c| c.model == model.label).collect();
+            let ok: Vec<&BenchmarkCell> = calls
+                .iter()
+                .copied()
+                .filter(|c| c.error.is_none())
+                .collect();
+
+            let mut latencies: Vec<f64> = ok.iter().map(|c| c.latency_ms as f64).collect();
+            latencies.sort_by(|a, b| a.total_cmp(b));
+            let qualities: Vec<f64> = ok.iter().filter_map(|c| c.quality).collect();
+            let verdicts: Vec<bool> = ok.iter().filter_map(|c| c.passed).collect();
+            let total_cost_usd: f64 = ok.iter().filter_map(|c| c.cost_usd).sum();
+
+            let mut by_evaluator: BTreeMap<String, Vec<f64>> = BTreeMap::new();
+            for cell in &ok {
+                for (evaluator, score) in &cell.scores {
+                    by_evaluator
+                        .entry(evaluator.clone())
+                        .or_default()
+                        .push(*score);
+                }
+            }
+
+            ModelSummary {
+                model: model.label.clone(),
+                calls: calls.len(),
+                errors: calls.len() - ok.len(),
+                quality: mean(&qualities),
+                pass_rate: (!verdicts.is_empty()).then(|| {
+                    verdicts.iter().filter(|p| **p).count() as f64 / verdicts.len() as f64
+                }),
+                mean_latency_ms: mean(&latencies).unwrap_or(0.0),
+                p95_latency_ms: percentile(&latencies, 0.95),
+                total_cost_usd,
+                mean_cost_usd: if ok.is_empty() {
+                    0.0
+                } else {
+                    total_cost_usd / ok.len() as f64
+                },
+                scores: by_evaluator
+                    .into_iter()
+                    .filter_map(|(evaluator, scores)| Some((evaluator, mean(&scores)?)))
+                    .collect(),
+            }
+        })
+        .collect()
+}
+
+/// A model with its vault key resolved
+struct ModelTarget {
+    model: BenchmarkModel,
+    key: Option<ResolvedKey>,
+}
+
+impl ModelTarget {
+    /// Provider the model is priced with
+    fn provider(&self) -> &str {
+        match &self.key {
+            Some(key) => &key.provider,
+            None => self.model.provider.as_deref().unwrap_or_default(),
+        }
+    }
+}
+
+/// Prompt with its ID and chat messages
+struct PreparedPrompt {
+    id: String,
+    messages: Vec<ChatMessage>,
+    expected_output: Option<String>,
+}
+
+fn chat_messages(input: &Value) -> Vec<ChatMessage> {
+    task_messages(input)
+        .iter()
+        .map(|message| ChatMessage {
+            role: message["role"].as_str().unwrap_or("user").to_string(),
+            content: match &message["content"] {
+                Value::String(s) => s.clone(),
+                Value::Null => String::new(),
+                other => other.to_string(),
+            },
+        })
+        .collect()
+}
+
+/// Runs benchmarks and keeps their results
+pub struct BenchmarkRunner {
+    storage_path: Option<PathBuf>,
+    benchmarks: Mutex<VecDeque<Benchmark>>,
+    evaluators: EvaluatorRegistry,
+}
+
+impl Default for BenchmarkRunner {
+    fn default() -> Self {
+        Self::new(None, VecDeque::new())
+    }
+}
+
+impl BenchmarkRunner {
+    fn new(storage_path: Option<PathBuf>, benchmarks: VecDeque<Benchmark>) -> Self {
+        // Every call is a new trace, caching results would only cost memory.
+        // Latency and cost have their own columns, so only quality
+        // evaluators are offered.
+        let evaluators = EvaluatorRegistry::with_config(EvalConfig {
+            enable_cache: false,
+            ..Default::default()
+        });
+        let quality: Vec<Arc<dyn Evaluator>> = vec![
+            Arc::new(ReferenceEvaluator::new()),
+            Arc::new(StructuredOutputEvaluator::new()),
+            Arc::new(LowConfidenceDetector::new()),
+        ];
+        for evaluator in quality {
+            if let Err(e) = evaluators.register(evaluator) {
+                warn!("Failed to register benchmark evaluator: {}", e);
+            }
+        }
+
+        Self {
+            storage_path,
+            benchmarks: Mutex::new(benchmarks),
+            evaluators,
+        }
+    }
+
+    /// Runner persisting benchmarks to `path`, loading any already saved
+    ///
+    /// Benchmarks still running when they were saved are marked failed.
+    pub fn with_storage(path: impl AsRef<FsPath>) -> Self {
+        let path = path.as_ref().to_path_buf();
+        let mut benchmarks = match std::fs::read(&path) {
+            Ok(bytes) => {
+                serde_json::from_slice::<VecDeque<Benchmark>>(&bytes).unwrap_or_else(|e| {
+                    warn!("Failed to parse {:?}: {}", path, e);
+                    VecDeque::new()
+                })
+            }
+            Err(e) => {
+                if e.kind() != std::io::ErrorKind::NotFound {
+                    warn!("Failed to read {:?}: {}", path, e);
+                }
+                VecDeque::new()
+            }
+        };
+        for benchmark in benchmarks
+            .iter_mut()
+            .filter(|b| b.status == BenchmarkStatus::Running)
+        {
+            benchmark.status = BenchmarkStatus::Failed;
+            benchmark.error = Some("Interrupted by a server restart".to_string());
+        }
+        Self::new(Some(path), benchmarks)
+    }
+
+    /// Evaluator IDs a benchmark may request
+    pub fn evaluator_ids(&self) -> Vec<String> {
+        let mut ids = self.evaluators.list_evaluators();
+        ids.sort_unstable();
+        ids
+    }
+
+    /// Benchmarks of a tenant, optionally of one name, newest first
+    pub fn list(&self, tenant_id: u64, name: Option<&str>, limit: usize) -> Vec<BenchmarkSummary> {
+        self.benchmarks
+            .lock()
+            .iter()
+            .filter(|b| b.tenant_id == tenant_id)
+            .filter(|b| name.is_none_or(|name| b.name == name))
+            .take(limit)
+            .map(BenchmarkSummary::from)
+            .collect()
+    }
+
+    pub fn get(&self, tenant_id: u64, id: &str) -> Option<Benchmark> {
+        self.benchmarks
+            .lock()
+            .iter()
+            .find(|b| b.id == id && b.tenant_id == tenant_id)
+            .cloned()
+    }
+
+    /// Remove a finished benchmark; Err if it is still running
+    pub fn remove(&self, tenant_id: u64, id: &str) -> Result<bool, ApiError> {
+        let removed = {
+            let mut benchmarks = self.benchmarks.lock();
+            let Some(index) = benchmarks
+                .iter()
+                .position(|b| b.id == id && b.tenant_id == tenant_id)
+            else {
+                return Ok(false);
+            };
+            if benchmarks[index].status == BenchmarkStatus::Running {
+                return Err(ApiError::Conflict(format!(
+                    "Benchmark {} is running, cancel its job first",
+                    id
+                )));
+            }
+            benchmarks.remove(index).is_some()
+        };
+        self.persist_or_warn();
+        Ok(removed)
+    }
+
+    /// Keep `benchmark`, dropping the oldest of its tenant beyond
+    /// [`MAX_BENCHMARKS_PER_TENANT`]
+    fn insert(&self, benchmark: Benchmark) {
+        {
+            let mut benchmarks = self.benchmarks.lock();
+            let tenant_id = benchmark.tenant_id;
+            benchmarks.push_front(benchmark);
+            let mut kept = 0;
+            benchmarks.retain(|b| {
+                if b.tenant_id != tenant_id {
+                    return true;
+                }
+                kept += 1;
+                kept <= MAX_BENCHMARKS_PER_TENANT
+            });
+        }
+        self.persist_or_warn();
+    }
+
+    fn update(&self, id: &str, f: impl FnOnce(&mut Benchmark)) {
+        {
+            let mut benchmarks = self.benchmarks.lock();
+            let Some(benchmark) = benchmarks.iter_mut().find(|b| b.id == id) else {
+                return;
+            };
+            f(benchmark);
+        }
+        self.persist_or_warn();
+    }
+
+    fn persist_or_warn(&self) {
+        if let Err(e) = self.persist() {
+            warn!("Failed to persist benchmarks: {}", e);
+        }
+    }
+
+    fn persist(&self) -> std::io::Result<()> {
+        let Some(path) = &self.storage_path else {
+            return Ok(());
+        };
+        let bytes = serde_json::to_vec(&*self.benchmarks.lock())?;
+
+        let tmp = path.with_extension("json.tmp");
+        std::fs::write(&tmp, bytes)?;
+        std::fs::rename(tmp, path)
+    }
+
+    /// Send every prompt to every model, then store the matrix
+    async fn run(
+        self: Arc<Self>,
+        state: AppState,
+        ctx: JobContext,
+        benchmark: Benchmark,
+        prompts: Vec<PreparedPrompt>,
+        targets: Vec<ModelTarget>,
+    ) -> Result<Value, String> {
+        // Prompt and model indices, prompt by prompt
+        let calls: Vec<(usize, usize)> = (0..prompts.len())
+            .flat_map(|prompt| (0..targets.len()).map(move |target| (prompt, target)))
+            .collect();
+        let total = calls.len();
+        let done = AtomicUsize::new(0);
+        let (this, state_ref, benchmark_ref, ctx_ref, done_ref) =
+            (&self, &state, &benchmark, &ctx, &done);
+        let (prompts_ref, targets_ref) = (&prompts, &targets);
+
+        let mut cells: Vec<(usize, BenchmarkCell)> =
+            futures::stream::iter(calls.into_iter().enumerate())
+                .map(|(index, (prompt, target))| async move {
+                    if ctx_ref.is_cancelled() {
+                        return None;
+                    }
+                    let cell = this
+                        .call(
+                            state_ref,
+                            benchmark_ref,
+                            &prompts_ref[prompt],
+                            &targets_ref[target],
+                        )
+                        .await;
+                    let done = done_ref.fetch_add(1, Ordering::Relaxed) + 1;
+                    ctx_ref.progress(
+                        done as f64 / total.max(1) as f64,
+                        Some(format!("{}/{} calls", done, total)),
+                    );
+                    Some((index, cell))
+                })
+                .buffer_unordered(CONCURRENCY)
+                .filter_map(|cell| async move { cell })
+                .collect()
+                .await;
+        cells.sort_by_key(|(index, _)| *index);
+        let cells: Vec<BenchmarkCell> = cells.into_iter().map(|(_, cell)| cell).collect();
+
+        let matrix = build_matrix(&benchmark.models, &cells);
+        let errors = cells.iter().filter(|c| c.error.is_some()).count();
+        let status = if ctx.is_cancelled() {
+            BenchmarkStatus::Cancelled
+        } else if total > 0 && errors == total {
+            BenchmarkStatus::Failed
+        } else {
+            BenchmarkStatus::Completed
+        };
+        let result = json!({ "status": status, "matrix": matrix });
+        self.update(&benchmark.id, |stored| {
+            stored.status = status;
+            stored.finished_at_us = Some(now_us());
+            stored.matrix = matrix;
+            stored.cells = cells;
+            if status == BenchmarkStatus::Failed {
+                stored.error = Some("Every model call failed".to_string());
+            }
+        });
+
+        match status {
+            BenchmarkStatus::Failed => Err("Every model call failed".to_string()),
+            _ => Ok(result),
+        }
+    }
+
+    /// Send one prompt to one model and score the answer
+    async fn call(
+        &self,
+        state: &AppState,
+        benchmark: &Benchmark,
+        prompt: &PreparedPrompt,
+        target: &ModelTarget,
+    ) -> BenchmarkCell {
+        let mut cell = BenchmarkCell {
+            prompt_id: prompt.id.clone(),
+            model: target.model.label.clone(),
+            ..Default::default()
+        };
+        let Some(llm_manager) = &state.llm_manager else {
+            cell.error = Some("LLM features are not enabled".to_string());
+            return cell;
+        };
+
+        let attributes = HashMap::from([
+            ("benchmark.id".to_string(), benchmark.id.clone()),
+            ("benchmark.name".to_string(), benchmark.name.clone()),
+            ("benchmark.prompt_id".to_string(), prompt.id.clone()),
+            ("benchmark.model".to_string(), target.model.label.clone()),
+        ]);
+        let model = Some(target.model.model.clone());
+        let messages = prompt.messages.clone();
+        let response = match &target.key {
+            Some(key) => {
+                llm_manager
+                    .chat_with_key_attributes(
+                        key,
+                        model,
+                        messages,
+                        benchmark.tenant_id,
+                        benchmark.session_id,
+                        attributes,
+                    )
+                    .await
+            }
+            None => {
+                llm_manager
+                    .chat_with_attributes(
+                        target.provider(),
+                        model,
+                        messages,
+                        benchmark.tenant_id,
+                        benchmark.session_id,
+                        attributes,
+                    )
+                    .await
+            }
+        };
+        let (response, edge_id) = match response {
+            Ok(response) => response,
+            Err(e) => {
+                cell.error = Some(format!("LLM request failed: {}", e));
+                return cell;
+            }
+        };
+
+        let model_name = response
+            .response_model
+            .as_deref()
+            .unwrap_or(&response.model);
+        if let Some(key) = &target.key {
+            state.vault.record_usage(
+                &state.db,
+                benchmark.tenant_id,
+                key,
+                "benchmark",
+                model_name,
+                response.input_tokens.unwrap_or(0) as u64,
+                response.output_tokens.unwrap_or(0) as u64,
+            );
+        }
+        cell.trace_id = Some(format!("0x{:x}", edge_id));
+        cell.latency_ms = response.duration_ms as u64;
+        cell.input_tokens = response.input_tokens;
+        cell.output_tokens = response.output_tokens;
+        if response.input_tokens.is_some() || response.output_tokens.is_some() {
+            cell.cost_usd = Some(
+                ModelPricing::for_model(target.provider(), model_name).token_cost(
+                    response.input_tokens.unwrap_or(0),
+                    response.output_tokens.unwrap_or(0),
+                ),
+            );
+        }
+
+        let results = self
+            .evaluate(
+                state,
+                edge_id,
+                prompt,
+                response.content,
+                &benchmark.evaluators,
+            )
+            .await;
+        if !results.is_empty() {
+            cell.scores = results
+                .iter()
+                .map(|(id, result)| (id.clone(), result_score(result)))
+                .collect();
+            cell.quality = mean(&cell.scores.values().copied().collect::<Vec<_>>());
+            cell.passed = Some(results.values().all(|r| r.passed));
+        }
+        cell
+    }
+
+    /// Results of the evaluators that could score the answer
+    async fn evaluate(
+        &self,
+        state: &AppState,
+        edge_id: u128,
+        prompt: &PreparedPrompt,
+        output: String,
+        evaluators: &[String],
+    ) -> HashMap<String, EvalResult> {
+        let mut evaluators = evaluators.to_vec();
+        // Reference metrics need something to compare with
+        if prompt.expected_output.is_none() {
+            evaluators.retain(|id| id != REFERENCE_EVALUATOR);
+        }
+        if evaluators.is_empty() {
+            return HashMap::new();
+        }
+
+        let edge = state.db.get(edge_id).ok().flatten();
+        let mut metadata = HashMap::new();
+        if let Some(expected) = &prompt.expected_output {
+            metadata.insert("expected_output".to_string(), json!(expected));
+        }
+        let trace = TraceContext {
+            trace_id: edge_id,
+            eval_trace: edge
+                .as_ref()
+                .map(|edge| crate::api::build_eval_trace_v1(state, edge)),
+            timestamp_us: edge.as_ref().map_or_else(now_us, |edge| edge.timestamp_us),
+            edges: edge.into_iter().collect(),
+            input: prompt
+                .messages
+                .iter()
+                .rev()
+                .find(|m| m.role == "user")
+                .map(|m| m.content.clone()),
+            output: Some(output),
+            context: None,
+            metadata,
+        };
+        self.evaluators
+            .evaluate_trace(&trace, evaluators)
+            .await
+            .unwrap_or_else(|e| {
+                warn!("Benchmark evaluation failed: {}", e);
+                HashMap::new()
+            })
+    }
+}
+
+/// Prompts of a request, from its body or its dataset
+fn prepare_prompts(
+    state: &AppState,
+    req: &BenchmarkRequest,
+) -> Result<Vec<PreparedPrompt>, ApiError> {
+    let prompts: Vec<PreparedPrompt> = match &req.dataset_id {
+        Some(_) if !req.prompts.is_empty() => {
+            return Err(ApiError::BadRequest(
+                "Give either prompts or dataset_id, not both".to_string(),
+            ))
+        }
+        Some(dataset_id) => {
+            let dataset_id = u128::from_str_radix(dataset_id.trim_start_matches("0x"), 16)
+                .map_err(|e| ApiError::BadRequest(format!("Invalid dataset ID: {}", e)))?;
+            let dataset = state
+                .db
+                .get_eval_dataset(dataset_id)
+                .map_err(|e| ApiError::Internal(e.to_string()))?
+                .ok_or_else(|| ApiError::NotFound("Dataset not found".to_string()))?;
+            dataset
+                .test_cases
+                .iter()
+                .map(|case| {
+                    let input = serde_json::from_str(&case.input)
+                        .unwrap_or_else(|_| Value::String(case.input.clone()));
+                    PreparedPrompt {
+                        id: format!("0x{:x}", case.id),
+                        messages: chat_messages(&input),
+                        expected_output: case.expected_output.clone(),
+                    }
+                })
+                .collect()
+        }
+        None => req
+            .prompts
+            .iter()
+            .enumerate()
+            .map(|(i, prompt)| PreparedPrompt {
+                id: prompt.id.clone().unwrap_or_else(|| (i + 1).to_string()),
+                messages: chat_messages(&prompt.input),
+                expected_output: prompt.expected_output.clone(),
+            })
+            .collect(),
+    };
+
+    if prompts.is_empty() {
+        return Err(ApiError::BadRequest(
+            "A benchmark needs prompts".to_string(),
+        ));
+    }
+    if prompts.len() > MAX_PROMPTS {
+        return Err(ApiError::BadRequest(format!(
+            "At most {} prompts per benchmark",
+            MAX_PROMPTS
+        )));
+    }
+    Ok(prompts)
+}
+
+/// POST /api/v1/benchmarks
+///
+/// Answers 202 with the benchmark's job; its ID is the benchmark ID.
+pub async fn start_benchmark(
+    State(state): State<AppState>,
+    Extension(auth): Extension<AuthContext>,
+    Json(mut req): Json<BenchmarkRequest>,
+) -> Result<(StatusCode, Json<Job>), ApiError> {
+    let llm_manager = state
+        .llm_manager
+        .as_ref()
+        .ok_or_else(|| ApiError::Internal("LLM features are not enabled".to_string()))?;
+    if req.name.trim().is_empty() {
+        return Err(ApiError::BadRequest("name is required".to_string()));
+    }
+    if req.models.is_empty() || req.models.len() > MAX_MODELS {
+        return Err(ApiError::BadRequest(format!(
+            "Benchmark between 1 and {} models",
+            MAX_MODELS
+        )));
+    }
+    for model in &mut req.models {
+        model.validate().map_err(ApiError::BadRequest)?;
+    }
+    let mut labels: Vec<&str> = req.models.iter().map(|m| m.label.as_str()).collect();
+    labels.sort_unstable();
+    if labels.windows(2).any(|pair| pair[0] == pair[1]) {
+        return Err(ApiError::BadRequest(
+            "Models need distinct labels".to_string(),
+        ));
+    }
+
+    let runner = Arc::clone(&state.benchmarks);
+    if req.evaluators.is_empty() {
+        req.evaluators.push(REFERENCE_EVALUATOR.to_string());
+    }
+    let available = runner.evaluator_ids();
+    let unknown: Vec<&str> = req
+        .evaluators
+        .iter()
+        .map(String::as_str)
+        .filter(|id| !available.iter().any(|a| a == id))
+        .collect();
+    if !unknown.is_empty() {
+        return Err(ApiError::BadRequest(format!(
+            "Unknown evaluators: {} (available: {})",
+            unknown.join(", "),
+            available.join(", ")
+        )));
+    }
+
+    let prompts = prepare_prompts(&state, &req)?;
+    let providers: Vec<String> = llm_manager
+        .list_providers()
+        .into_iter()
+        .map(|p| p.id)
+        .collect();
+    let targets = req
+        .models
+        .iter()
+        .map(|model| {
+            let key = match (&model.key_alias, &model.provider) {
+                (Some(alias), _) => Some(state.vault.resolve(&state.db, auth.tenant_id, alias)?),
+                (None, Some(provider)) if !providers.contains(provider) => {
+                    return Err(ApiError::BadRequest(format!(
+                        "Provider {} is not configured",
+                        provider
+                    )))
+                }
+                (None, _) => None,
+            };
+            Ok(ModelTarget {
+                model: model.clone(),
+                key,
+            })
+        })
+        .collect::<Result<Vec<_>, ApiError>>()?;
+
+    let id = hex::encode(rand::random::<[u8; 8]>());
+    let benchmark = Benchmark {
+        id: id.clone(),
+        tenant_id: auth.tenant_id,
+        name: req.name.clone(),
+        status: BenchmarkStatus::Running,
+        created_at_us: now_us(),
+        finished_at_us: None,
+        session_id: rand::random::<u64>() >> 1,
+        prompts: prompts.len(),
+        models: req.models,
+        evaluators: req.evaluators,
+        matrix: Vec::new(),
+        cells: Vec::new(),
+        error: None,
+    };
+    runner.insert(benchmark.clone());
+
+    let description = format!(
+        "Benchmark {}: {} prompts x {} models",
+        benchmark.name,
+        benchmark.prompts,
+        benchmark.models.len()
+    );
+    let jobs = Arc::clone(&state.jobs);
+    let job = jobs.submit_as(
+        id,
+        "benchmark",
+        auth.tenant_id,
+        description,
+        move |ctx| async move {
+            runner
+                .run(state, ctx, benchmark, prompts, targets)
+                .await
+                .map(Some)
+        },
+    );
+    Ok((StatusCode::ACCEPTED, Json(job)))
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ListBenchmarksParams {
+    #[serde(default)]
+    pub name: Option<String>,
+    #[serde(default)]
+    pub limit: Option<usize>,
+}
+
+/// GET /api/v1/benchmarks?name=nightly&limit=20
+///
+/// Listing by name gives the history of a benchmark across model upgrades.
+pub async fn list_benchmarks(
+    State(state): State<AppState>,
+    Extension(auth): Extension<AuthContext>,
+    Query(params): Query<ListBenchmarksParams>,
+) -> Json<Vec<BenchmarkSummary>> {
+    let limit = params.limit.unwrap_or(DEFAULT_LIST_LIMIT);
+    Json(
+        state
+            .benchmarks
+            .list(auth.tenant_id, params.name.as_deref(), limit),
+    )
+}
+
+/// GET /api/v1/benchmarks/:id
+pub async fn get_benchmark(
+    State(state): State<AppState>,
+    Extension(auth): Extension<AuthContext>,
+    Path(id): Path<String>,
+) -> Result<Json<Benchmark>, ApiError> {
+    state
+        .benchmarks
+        .get(auth.tenant_id, &id)
+        .map(Json)
+        .ok_or_else(|| ApiError::NotFound(format!("Benchmark {} not found", id)))
+}
+
+/// DELETE /api/v1/benchmarks/:id
+pub async fn delete_benchmark(
+    State(state): State<AppState>,
+    Extension(auth): Extension<AuthContext>,
+    Path(id): Path<String>,
+) -> Result<StatusCode, ApiError> {
+    if state.benchmarks.remove(auth.tenant_id, &id)? {
+        Ok(StatusCode::NO_CONTENT)
+    } else {
+        Err(ApiError::NotFound(format!("Benchmark {} not found", id)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn model(provider: &str, name: &str) -> BenchmarkModel {
+        let mut model: BenchmarkModel =
+            serde_json::from_value(json!({ "provider": provider, "model": name })).unwrap();
+        model.validate().unwrap();
+        model
+    }
+
+    fn cell(
+        model: &str,
+        latency_ms: u64,
+        quality: Option<f64>,
+        passed: Option<bool>,
+    ) -> BenchmarkCell {
+        BenchmarkCell {
+            prompt_id: "1".to_string(),
+            model: model.to_string(),
+            latency_ms,
+            cost_usd: Some(0.01),
+            quality,
+            passed,
+            scores: quality
+                .map(|q| BTreeMap::from([(REFERENCE_EVALUATOR.to_string(), q)]))
+                .unwrap_or_default(),
+            ..Default::default()
+        }
+    }
+
+    fn benchmark(tenant_id: u64, id: &str, status: BenchmarkStatus) -> Benchmark {
+        Benchmark {
+            id: id.to_string(),
+            tenant_id,
+            name: "nightly".to_string(),
+            status,
+            created_at_us: 1,
+            finished_at_us: None,
+            session_id: 7,
+            prompts: 1,
+            models: vec![model("openai", "gpt-4o-mini")],
+            evaluators: vec![REFERENCE_EVALUATOR.to_string()],
+            matrix: Vec::new(),
+            cells: Vec::new(),
+            error: None,
+        }
+    }
+
+    #[test]
+    fn test_model_validation() {
+        assert_eq!(model("openai", "gpt-4o").label, "openai/gpt-4o");
+
+        let mut keyed: BenchmarkModel = serde_json::from_value(json!({
+            "key_alias": "prod",
+            "model": "claude-3-5-sonnet",
+            "label": "candidate"
+        }))
+        .unwrap();
+        keyed.validate().unwrap();
+        assert_eq!(keyed.label, "candidate");
+
+        let mut both: BenchmarkModel = serde_json::from_value(json!({
+            "provider": "openai",
+            "key_alias": "prod",
+            "model": "gpt-4o"
+        }))
+        .unwrap();
+        assert!(both.validate().is_err());
+    }
+
+    #[test]
+    fn test_build_matrix() {
+        let models = vec![model("openai", "gpt-4o"), model("openai", "gpt-4o-mini")];
+        let mut failed = cell("openai/gpt-4o-mini", 0, None, None);
+        failed.error = Some("timeout".to_string());
+        let cells = vec![
+            cell("openai/gpt-4o", 100, Some(0.9), Some(true)),
+            cell("openai/gpt-4o", 300, Some(0.5), Some(false)),
+            cell("openai/gpt-4o-mini", 50, Some(0.6), Some(true)),
+            failed,
+        ];
+
+        let matrix = build_matrix(&models, &cells);
+        assert_eq!(matrix.len(), 2);
+
+        let large = &matrix[0];
+        assert_eq!(large.calls, 2);
+        assert_eq!(large.errors, 0);
+        assert!((large.quality.unwrap() - 0.7).abs() < 1e-9);
+        assert_eq!(large.pass_rate, Some(0.5));
+        assert_eq!(large.mean_latency_ms, 200.0);
+        assert_eq!(large.p95_latency_ms, 300.0);
+        assert!((large.total_cost_usd - 0.02).abs() < 1e-9);
+        assert!((large.scores[REFERENCE_EVALUATOR] - 0.7).abs() < 1e-9);
+
+        let small = &matrix[1];
+        assert_eq!((small.calls, small.errors), (2, 1));
+        assert_eq!(small.mean_latency_ms, 50.0);
+        assert!((small.mean_cost_usd - 0.01).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_result_score() {
+        let mut result: EvalResult = serde_json::from_value(json!({
+            "evaluator_id": REFERENCE_EVALUATOR,
+            "metrics": {},
+            "passed": true,
+            "confidence": 1.0
+        }))
+        .unwrap();
+        assert_eq!(result_score(&result), 1.0);
+
+        result
+            .metrics
+            .insert("primary_score".to_string(), MetricValue::Float(0.42));
+        assert_eq!(result_score(&result), 0.42);
+    }
+
+    #[test]
+    fn test_chat_messages() {
+        let messages = chat_messages(&json!({"prompt": "Summarize the ticket"}));
+        assert_eq!(messages.len(), 1);
+        assert_eq!(messages[0].role, "user");
+        assert_eq!(messages[0].content, "Summarize the ticket");
+    }
+
+    #[test]
+    fn test_store_persistence() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join(BENCHMARKS_FILE);
+
+        let runner = BenchmarkRunner::with_storage(&path);
+        runner.insert(benchmark(1, "a", BenchmarkStatus::Completed));
+        runner.insert(benchmark(1, "b", BenchmarkStatus::Running));
+        runner.insert(benchmark(2, "c", BenchmarkStatus::Completed));
+        assert!(runner.remove(1, "b").is_err());
+
+        let reloaded = BenchmarkRunner::with_storage(&path);
+        let listed = reloaded.list(1, Some("nightly"), 10);
+        assert_eq!(
+            listed.iter().map(|b| b.id.as_str()).collect::<Vec<_>>(),
+            vec!["b", "a"]
+        );
+        assert_eq!(listed[0].status, BenchmarkStatus::Failed);
+        assert!(reloaded.get(1, "c").is_none());
+        assert!(reloaded.remove(1, "b").unwrap());
+        assert!(!reloaded.remove(1, "b").unwrap());
+        assert!(reloaded
+            .evaluator_ids()
+            .contains(&REFERENCE_EVALUATOR.to_string()));
+    }
+}
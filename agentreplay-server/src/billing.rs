@@ -0,0 +1,417 @@
+// Copyright 2025 AgentReplay (https://github.com/agentreplay)
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program. If not, see <https://www.gnu.org/licenses/>.
+
+//! Display currency, exchange rates and chargeback adjustments for costs
+//!
+//! Costs are computed in USD. Each tenant can pick a display currency and
+//! record exchange-rate snapshots (units of each currency per USD, from a
+//! given date on); a span's cost is converted at the rate in effect at its
+//! timestamp, so past reports don't move when rates are updated. Projects
+//! can carry a markup and a tax rate, applied in that order, so internal
+//! chargeback numbers match what finance bills.
+//!
+//! The analytics cost endpoints convert through a [`CostConverter`] built
+//! by [`BillingStore::converter`].
+
+use parking_lot::RwLock;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs::{self, File};
+use std::io::{BufReader, BufWriter};
+use std::path::{Path, PathBuf};
+use tracing::{info, warn};
+
+/// Billing settings file name inside the data directory
+pub const BILLING_FILE: &str = "billing.json";
+/// Currency costs are computed in
+pub const BASE_CURRENCY: &str = "USD";
+
+/// Exchange rates from a date on
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RateSnapshot {
+    /// Unix seconds the rates apply from
+    pub effective_from: u64,
+    /// Units of each currency per USD, e.g. `{"EUR": 0.92}`
+    pub rates: BTreeMap<String, f64>,
+    /// Where the rates came from, e.g. "ECB reference rates"
+    #[serde(default)]
+    pub source: Option<String>,
+}
+
+/// Chargeback adjustments of one project
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+pub struct ProjectBilling {
+    /// Fraction added on top of cost, e.g. 0.15 for 15%
+    #[serde(default)]
+    pub markup: f64,
+    /// Tax rate applied after the markup, e.g. 0.2 for 20% VAT
+    #[serde(default)]
+    pub tax_rate: f64,
+}
+
+impl ProjectBilling {
+    /// Factor turning a raw cost into the charged cost
+    pub fn multiplier(&self) -> f64 {
+        (1.0 + self.markup) * (1.0 + self.tax_rate)
+    }
+}
+
+/// A tenant's billing settings
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct BillingSettings {
+    #[serde(default = "default_currency")]
+    pub display_currency: String,
+    /// Ordered by `effective_from`
+    #[serde(default)]
+    pub rate_snapshots: Vec<RateSnapshot>,
+    #[serde(default)]
+    pub projects: BTreeMap<u16, ProjectBilling>,
+}
+
+impl Default for BillingSettings {
+    fn default() -> Self {
+        Self {
+            display_currency: default_currency(),
+            rate_snapshots: Vec::new(),
+            projects: BTreeMap::new(),
+        }
+    }
+}
+
+fn default_currency() -> String {
+    BASE_CURRENCY.to_string()
+}
+
+/// Converts USD costs into what a tenant reports
+#[derive(Debug, Clone)]
+pub struct CostConverter {
+    currency: String,
+    /// (effective_from, units per USD), ordered
+    rates: Vec<(u64, f64)>,
+    projects: BTreeMap<u16, ProjectBilling>,
+}
+
+impl CostConverter {
+    pub fn currency(&self) -> &str {
+        &self.currency
+    }
+
+    /// Units of the currency per USD at `at` (Unix seconds); the oldest
+    /// snapshot applies before the first one
+    pub fn rate_at(&self, at: u64) -> f64 {
+        self.rates
+            .iter()
+            .rev()
+            .find(|(from, _)| *from <= at)
+            .or(self.rates.first())
+            .map_or(1.0, |(_, rate)| *rate)
+    }
+
+    /// The latest rate, for display alongside converted totals
+    pub fn current_rate(&self) -> f64 {
+        self.rates.last().map_or(1.0, |(_, rate)| *rate)
+    }
+
+    /// Markup and tax factor of a project
+    pub fn multiplier(&self, project_id: u16) -> f64 {
+        self.projects
+            .get(&project_id)
+            .map_or(1.0, ProjectBilling::multiplier)
+    }
+
+    /// A USD cost of a span of `project_id` at `timestamp_us`, in the
+    /// display currency with the project's markup and tax
+    pub fn convert(&self, cost_usd: f64, project_id: u16, timestamp_us: u64) -> f64 {
+        cost_usd * self.rate_at(timestamp_us / 1_000_000) * self.multiplier(project_id)
+    }
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct StoredBilling {
+    #[serde(default)]
+    tenants: BTreeMap<u64, BillingSettings>,
+}
+
+/// Stores every tenant's billing settings
+pub struct BillingStore {
+    settings: RwLock<StoredBilling>,
+    storage_path: PathBuf,
+}
+
+impl BillingStore {
+    /// Create a store, loading settings from `storage_path`
+    pub fn new(storage_path: impl AsRef<Path>) -> Self {
+        let store = Self {
+            settings: RwLock::new(StoredBilling::default()),
+            storage_path: storage_path.as_ref().to_path_buf(),
+        };
+
+        if let Err(e) = store.load_from_disk() {
+            warn!(
+                "Failed to load billing settings from disk: {}. Using USD.",
+                e
+            );
+        }
+
+        store
+    }
+
+    pub fn settings(&self, tenant_id: u64) -> BillingSettings {
+        self.settings
+            .read()
+            .tenants
+            .get(&tenant_id)
+            .cloned()
+            .unwrap_or_default()
+    }
+
+    /// Replace the display currency and project adjustments, keeping the
+    /// recorded rate snapshots
+    pub fn update(
+        &self,
+        tenant_id: u64,
+        display_currency: &str,
+        projects: BTreeMap<u16, ProjectBilling>,
+    ) -> Result<BillingSettings, String> {
+        let display_currency = normalize_currency(display_currency)?;
+        if display_currency != BASE_CURRENCY
+            && !self
+                .settings(tenant_id)
+                .rate_snapshots
+                .iter()
+                .any(|s| s.rates.contains_key(&display_currency))
+        {
+            return Err(format!(
+                "Record an exchange rate for {} first",
+                display_currency
+            ));
+        }
+        for (project_id, billing) in &projects {
+            for (name, value) in [("markup", billing.markup), ("tax_rate", billing.tax_rate)] {
+                if !value.is_finite() || !(0.0..=10.0).contains(&value) {
+                    return Err(format!(
+                        "{} of project {} must be between 0 and 10",
+                        name, project_id
+                    ));
+                }
+            }
+        }
+
+        let settings = {
+            let mut stored = self.settings.write();
+            let settings = stored.tenants.entry(tenant_id).or_default();
+            settings.display_currency = display_currency;
+            settings.projects = projects;
+            settings.clone()
+        };
+        self.save_to_disk()?;
+
+        info!("Updated billing settings of tenant {}", tenant_id);
+        Ok(settings)
+    }
+
+    /// Record exchange rates from `snapshot.effective_from` on, replacing a
+    /// snapshot starting at the same time
+    pub fn add_rate_snapshot(
+        &self,
+        tenant_id: u64,
+        mut snapshot: RateSnapshot,
+    ) -> Result<BillingSettings, String> {
+        if snapshot.rates.is_empty() {
+            return Err("rates cannot be empty".to_string());
+        }
+        let mut rates = BTreeMap::new();
+        for (currency, rate) in snapshot.rates {
+            if !rate.is_finite() || rate <= 0.0 {
+                return Err(format!("rate of {} must be a positive number", currency));
+            }
+            rates.insert(normalize_currency(&currency)?, rate);
+        }
+        snapshot.rates = rates;
+
+        let settings = {
+            let mut stored = self.settings.write();
+            let settings = stored.tenants.entry(tenant_id).or_default();
+            settings
+                .rate_snapshots
+                .retain(|s| s.effective_from != snapshot.effective_from);
+            settings.rate_snapshots.push(snapshot);
+            settings.rate_snapshots.sort_by_key(|s| s.effective_from);
+            settings.clone()
+        };
+        self.save_to_disk()?;
+        Ok(settings)
+    }
+
+    /// Converter into `currency`, or the tenant's display currency
+    ///
+    /// Fails when no snapshot has a rate for the currency.
+    pub fn converter(
+        &self,
+        tenant_id: u64,
+        currency: Option<&str>,
+    ) -> Result<CostConverter, String> {
+        let settings = self.settings(tenant_id);
+        let currency = match currency {
+            Some(currency) => normalize_currency(currency)?,
+            None => settings.display_currency,
+        };
+        let rates: Vec<(u64, f64)> = if currency == BASE_CURRENCY {
+            Vec::new()
+        } else {
+            settings
+                .rate_snapshots
+                .iter()
+                .filter_map(|s| s.rates.get(&currency).map(|rate| (s.effective_from, *rate)))
+                .collect()
+        };
+        if currency != BASE_CURRENCY && rates.is_empty() {
+            return Err(format!("No exchange rate recorded for {}", currency));
+        }
+
+        Ok(CostConverter {
+            currency,
+            rates,
+            projects: settings.projects,
+        })
+    }
+
+    fn load_from_disk(&self) -> Result<(), String> {
+        if !self.storage_path.exists() {
+            return Ok(());
+        }
+
+        let file = File::open(&self.storage_path)
+            .map_err(|e| format!("Failed to open billing file: {}", e))?;
+        let stored: StoredBilling = serde_json::from_reader(BufReader::new(file))
+            .map_err(|e| format!("Failed to parse billing JSON: {}", e))?;
+
+        info!(
+            "Loaded billing settings of {} tenants",
+            stored.tenants.len()
+        );
+        *self.settings.write() = stored;
+        Ok(())
+    }
+
+    /// Save settings to disk (write to temp file then rename)
+    fn save_to_disk(&self) -> Result<(), String> {
+        if let Some(parent) = self.storage_path.parent() {
+            fs::create_dir_all(parent)
+                .map_err(|e| format!("Failed to create billing directory: {}", e))?;
+        }
+
+        let temp_path = self.storage_path.with_extension("json.tmp");
+        {
+            let file = File::create(&temp_path)
+                .map_err(|e| format!("Failed to create temp billing file: {}", e))?;
+            serde_json::to_writer_pretty(BufWriter::new(file), &*self.settings.read())
+                .map_err(|e| format!("Failed to serialize billing settings: {}", e))?;
+        }
+
+        fs::rename(&temp_path, &self.storage_path)
+            .map_err(|e| format!("Failed to rename temp file: {}", e))?;
+
+        Ok(())
+    }
+}
+
+/// ISO 4217 style code: three ASCII letters, upper-cased
+fn normalize_currency(currency: &str) -> Result<String, String> {
+    let code = currency.trim().to_ascii_uppercase();
+    if code.len() == 3 && code.chars().all(|c| c.is_ascii_alphabetic()) {
+        Ok(code)
+    } else {
+        Err(format!(
+            "'{}' is not a three-letter currency code",
+            currency
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn snapshot(effective_from: u64, eur: f64) -> RateSnapshot {
+        RateSnapshot {
+            effective_from,
+            rates: BTreeMap::from([("eur".to_string(), eur)]),
+            source: None,
+        }
+    }
+
+    #[test]
+    fn test_converts_at_rate_in_effect() {
+        let dir = tempfile::tempdir().unwrap();
+        let store = BillingStore::new(dir.path().join(BILLING_FILE));
+        store.add_rate_snapshot(1, snapshot(1_000, 0.9)).unwrap();
+        store.add_rate_snapshot(1, snapshot(2_000, 0.8)).unwrap();
+        store
+            .update(
+                1,
+                "eur",
+                BTreeMap::from([(
+                    7,
+                    ProjectBilling {
+                        markup: 0.25,
+                        tax_rate: 0.2,
+                    },
+                )]),
+            )
+            .unwrap();
+
+        let converter = store.converter(1, None).unwrap();
+        assert_eq!(converter.currency(), "EUR");
+        // Before the first snapshot its rate applies
+        assert_eq!(converter.rate_at(500), 0.9);
+        assert_eq!(converter.rate_at(1_500), 0.9);
+        assert_eq!(converter.rate_at(2_000), 0.8);
+        assert_eq!(converter.current_rate(), 0.8);
+        assert!((converter.convert(10.0, 0, 1_500_000_000) - 9.0).abs() < 1e-9);
+        // 10 USD * 0.8 * 1.25 markup * 1.2 tax
+        assert!((converter.convert(10.0, 7, 2_500_000_000) - 12.0).abs() < 1e-9);
+
+        // USD needs no rate; other tenants and unknown currencies do
+        assert_eq!(store.converter(1, Some("usd")).unwrap().rate_at(1_500), 1.0);
+        assert!(store.converter(1, Some("GBP")).is_err());
+        assert_eq!(store.converter(2, None).unwrap().currency(), "USD");
+
+        // Persisted
+        let reloaded = BillingStore::new(dir.path().join(BILLING_FILE));
+        assert_eq!(reloaded.settings(1), store.settings(1));
+    }
+
+    #[test]
+    fn test_rejects_invalid_settings() {
+        let dir = tempfile::tempdir().unwrap();
+        let store = BillingStore::new(dir.path().join(BILLING_FILE));
+        assert!(store.update(1, "euro", BTreeMap::new()).is_err());
+        // No rate to convert with
+        assert!(store.update(1, "EUR", BTreeMap::new()).is_err());
+        store.add_rate_snapshot(1, snapshot(0, 0.9)).unwrap();
+        let negative = ProjectBilling {
+            markup: -0.1,
+            tax_rate: 0.0,
+        };
+        assert!(store
+            .update(1, "EUR", BTreeMap::from([(1, negative)]))
+            .is_err());
+        assert!(store.add_rate_snapshot(1, snapshot(0, 0.0)).is_err());
+        let settings = store.settings(1);
+        assert_eq!(settings.display_currency, "USD");
+        assert_eq!(settings.rate_snapshots.len(), 1);
+    }
+}
@@ -0,0 +1,493 @@
+// Copyright 2025 AgentReplay (https://github.com/agentreplay)
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program. If not, see <https://www.gnu.org/licenses/>.
+
+//! Multi-node deployment over shared storage
+//!
+//! A cluster has exactly one **writer** and any number of **replicas**, all
+//! pointed at the same shared directory:
+//!
+//! - The writer holds a lease in the shared directory, renews it every third
+//!   of its TTL, and periodically checkpoints the database and publishes a
+//!   snapshot. A second writer refuses to start while the lease is live, and a
+//!   writer that loses its lease stops accepting writes (no split-brain).
+//! - Replicas materialize the latest snapshot into a local generation
+//!   directory at startup, open the database from it, and keep syncing in the
+//!   background. A newly synced snapshot is opened and swapped in without a
+//!   restart; `/api/v1/cluster/status` reports the lag and the snapshot served.
+//!
+//! Standalone nodes (the default) skip all of this.
+
+use crate::api::AppState;
+use crate::config::ClusterConfig;
+use agentreplay_query::Agentreplay;
+use agentreplay_storage::{
+    LeaseManager, LeaseOutcome, LocalFsBackend, SnapshotPublisher, SnapshotSyncer, StorageBackend,
+    WriterLease,
+};
+use axum::{
+    body::Body,
+    extract::State,
+    http::{Method, Request, StatusCode},
+    middleware::Next,
+    response::{IntoResponse, Response},
+    Json,
+};
+use parking_lot::RwLock;
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+/// Subdirectory of the local data dir holding replica generations
+const REPLICA_DIR: &str = "replica";
+
+/// POST endpoints that only read data and stay available on replicas
+const READ_ONLY_POST_PATHS: &[&str] = &["/api/v1/search"];
+
+/// Endpoints that change this node's role and must work while it rejects writes
+const CONTROL_PATHS: &[&str] = &["/api/v1/replication/promote"];
+
+/// Role of this node in a multi-node deployment
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum NodeRole {
+    /// Single node, no shared storage
+    #[default]
+    Standalone,
+    /// Ingests data and publishes snapshots; at most one per cluster
+    Writer,
+    /// Serves queries from synced snapshots
+    Replica,
+}
+
+/// Point-in-time view of this node's replication state
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct ClusterStatus {
+    pub role: NodeRole,
+    pub node_id: String,
+    /// Whether this node currently accepts ingestion and other writes
+    pub accepts_writes: bool,
+    /// Lease as last observed in shared storage
+    pub lease: Option<WriterLease>,
+    /// Writer: sequence number of the last published snapshot
+    pub last_published_seq: Option<u64>,
+    /// Replica: snapshot the database was opened from
+    pub serving_seq: Option<u64>,
+    /// Replica: newest snapshot materialized locally
+    pub synced_seq: Option<u64>,
+    /// Replica: age of the newest published snapshot in seconds
+    pub replication_lag_secs: Option<u64>,
+    /// Replica: a newer snapshot is on disk but could not be served yet
+    pub restart_required: bool,
+    pub last_error: Option<String>,
+}
+
+/// Cluster membership and background replication for one node
+pub struct ClusterNode {
+    config: ClusterConfig,
+    backend: Arc<dyn StorageBackend>,
+    leases: LeaseManager,
+    held_lease: RwLock<Option<WriterLease>>,
+    writable: AtomicBool,
+    status: RwLock<ClusterStatus>,
+}
+
+impl ClusterNode {
+    /// Connect to the shared directory; returns None for standalone nodes
+    pub fn new(config: &ClusterConfig) -> anyhow::Result<Option<Arc<Self>>> {
+        if config.role == NodeRole::Standalone {
+            return Ok(None);
+        }
+        let shared_dir = config
+            .shared_dir
+            .as_ref()
+            .ok_or_else(|| anyhow::anyhow!("cluster.shared_dir is required"))?;
+
+        let backend: Arc<dyn StorageBackend> = Arc::new(LocalFsBackend::new(shared_dir)?);
+        let leases = LeaseManager::new(backend.clone(), config.node_id.clone(), config.lease_ttl());
+        Ok(Some(Arc::new(Self {
+            config: config.clone(),
+            backend,
+            leases,
+            held_lease: RwLock::new(None),
+            writable: AtomicBool::new(false),
+            status: RwLock::new(ClusterStatus {
+                role: config.role,
+                node_id: config.node_id.clone(),
+                ..Default::default()
+            }),
+        })))
+    }
+
+    pub fn role(&self) -> NodeRole {
+        self.config.role
+    }
+
+    /// Whether writes may be served right now
+    pub fn accepts_writes(&self) -> bool {
+        self.writable.load(Ordering::Acquire)
+    }
+
+    pub fn status(&self) -> ClusterStatus {
+        let mut status = self.status.read().clone();
+        status.accepts_writes = self.accepts_writes();
+        status
+    }
+
+    /// Prepare local storage before the database is opened
+    ///
+    /// Writers take the lease (failing if another node holds it) and keep
+    /// using `data_dir`. Replicas sync the latest snapshot and return the
+    /// generation directory the database should be opened from.
+    pub fn prepare(&self, data_dir: &Path) -> anyhow::Result<PathBuf> {
+        match self.config.role {
+            NodeRole::Standalone => Ok(data_dir.to_path_buf()),
+            NodeRole::Writer => {
+                match self.leases.try_acquire()? {
+                    LeaseOutcome::Acquired(lease) => {
+                        tracing::info!(
+                            "Acquired writer lease (node '{}', epoch {})",
+                            lease.node_id,
+                            lease.epoch
+                        );
+                        self.hold(lease);
+                    }
+                    LeaseOutcome::HeldBy(lease) => anyhow::bail!(
+                        "writer lease is held by node '{}' until {} (epoch {}); \
+                        refusing to start a second writer",
+                        lease.node_id,
+                        lease.expires_at,
+                        lease.epoch
+                    ),
+                }
+                Ok(data_dir.to_path_buf())
+            }
+            NodeRole::Replica => {
+                let syncer = self.syncer(data_dir);
+                let outcome = syncer.sync()?;
+                if let Err(e) = syncer.prune(0) {
+                    tracing::warn!("Failed to prune old replica generations: {}", e);
+                }
+
+                let dir = match syncer.current_generation() {
+                    Some((seq, dir)) => {
+                        tracing::info!(
+                            "Replica serving snapshot {} (lag {:?}s)",
+                            seq,
+                            outcome.lag_secs
+                        );
+                        dir
+                    }
+                    None => {
+                        tracing::warn!("No snapshot published yet; replica starts empty");
+                        let dir = data_dir.join(REPLICA_DIR).join("empty");
+                        std::fs::create_dir_all(&dir)?;
+                        dir
+                    }
+                };
+
+                let mut status = self.status.write();
+                status.serving_seq = outcome.seq;
+                status.synced_seq = outcome.seq;
+                status.replication_lag_secs = outcome.lag_secs;
+                status.lease = self.leases.current().ok().flatten();
+                Ok(dir)
+            }
+        }
+    }
+
+    /// Start background lease renewal + publishing (writer) or syncing (replica)
+    pub fn spawn(self: &Arc<Self>, db: Arc<Agentreplay>, data_dir: PathBuf) {
+        let node = self.clone();
+        match self.config.role {
+            NodeRole::Standalone => {}
+            NodeRole::Writer => {
+                tokio::spawn(async move { node.run_writer(db, data_dir).await });
+            }
+            NodeRole::Replica => {
+                tokio::spawn(async move { node.run_replica(db, data_dir).await });
+            }
+        }
+    }
+
+    fn hold(&self, lease: WriterLease) {
+        self.status.write().lease = Some(lease.clone());
+        *self.held_lease.write() = Some(lease);
+        self.writable.store(true, Ordering::Release);
+    }
+
+    fn fence(&self, reason: String) {
+        if self.writable.swap(false, Ordering::AcqRel) {
+            tracing::error!("Writer lease lost, refusing further writes: {}", reason);
+        }
+        *self.held_lease.write() = None;
+        let mut status = self.status.write();
+        status.lease = self.leases.current().ok().flatten();
+        status.last_error = Some(reason);
+    }
+
+    fn syncer(&self, data_dir: &Path) -> SnapshotSyncer {
+        SnapshotSyncer::new(self.backend.clone(), data_dir.join(REPLICA_DIR))
+    }
+
+    async fn run_writer(self: Arc<Self>, db: Arc<Agentreplay>, data_dir: PathBuf) {
+        let publisher = SnapshotPublisher::new(self.backend.clone(), &data_dir);
+        let renew_every = (self.config.lease_ttl() / 3).max(Duration::from_secs(1));
+        let mut ticker = tokio::time::interval(renew_every.min(self.config.sync_interval()));
+        let mut last_publish = Instant::now();
+
+        loop {
+            ticker.tick().await;
+
+            // Only renew a lease we still hold; a fenced writer must restart
+            if self.held_lease.read().is_none() {
+                continue;
+            }
+            match self.leases.try_acquire() {
+                Ok(LeaseOutcome::Acquired(lease)) => self.hold(lease),
+                Ok(LeaseOutcome::HeldBy(lease)) => {
+                    self.fence(format!(
+                        "lease taken over by node '{}' (epoch {})",
+                        lease.node_id, lease.epoch
+                    ));
+                    continue;
+                }
+                Err(e) => {
+                    tracing::warn!("Failed to renew writer lease: {}", e);
+                    self.status.write().last_error = Some(e.to_string());
+                    continue;
+                }
+            }
+
+            if last_publish.elapsed() < self.config.sync_interval() {
+                continue;
+            }
+            last_publish = Instant::now();
+
+            let Some(lease) = self.held_lease.read().clone() else {
+                continue;
+            };
+            if let Err(e) = db.checkpoint() {
+                tracing::warn!("Checkpoint before snapshot publish failed: {}", e);
+            }
+            match publisher.publish(&self.leases, &lease) {
+                Ok(stats) => {
+                    tracing::info!(
+                        "Published snapshot {} ({} files, {} new, {} bytes uploaded)",
+                        stats.seq,
+                        stats.files,
+                        stats.uploaded_files,
+                        stats.uploaded_bytes
+                    );
+                    let mut status = self.status.write();
+                    status.last_published_seq = Some(stats.seq);
+                    status.last_error = None;
+                }
+                Err(e) => {
+                    if self.leases.verify(&lease).is_err() {
+                        self.fence(format!("snapshot publish fenced: {}", e));
+                    } else {
+                        tracing::warn!("Snapshot publish failed: {}", e);
+                        self.status.write().last_error = Some(e.to_string());
+                    }
+                }
+            }
+        }
+    }
+
+    async fn run_replica(self: Arc<Self>, db: Arc<Agentreplay>, data_dir: PathBuf) {
+        let syncer = Arc::new(self.syncer(&data_dir));
+        let mut ticker = tokio::time::interval(self.config.sync_interval());
+        ticker.tick().await; // Startup sync already ran in prepare()
+
+        loop {
+            ticker.tick().await;
+
+            let task_syncer = syncer.clone();
+            let result = tokio::task::spawn_blocking(move || task_syncer.sync()).await;
+            {
+                let mut status = self.status.write();
+                status.lease = self.leases.current().ok().flatten();
+                match &result {
+                    Ok(Ok(outcome)) => {
+                        status.synced_seq = outcome.seq;
+                        status.replication_lag_secs = outcome.lag_secs;
+                        status.restart_required = status.synced_seq != status.serving_seq;
+                        status.last_error = None;
+                    }
+                    Ok(Err(e)) => {
+                        tracing::warn!("Replica sync failed: {}", e);
+                        status.last_error = Some(e.to_string());
+                    }
+                    Err(e) => {
+                        status.last_error = Some(format!("sync task panicked: {}", e));
+                    }
+                }
+            }
+
+            if self.status.read().restart_required {
+                self.reload(&db, &syncer).await;
+            }
+        }
+    }
+
+    /// Serve the newest synced generation without a restart
+    ///
+    /// The database is opened from the generation directory off the async
+    /// runtime and swapped into `db`. The generation served until now is kept
+    /// for queries still running against it and pruned on the next reload.
+    async fn reload(&self, db: &Arc<Agentreplay>, syncer: &Arc<SnapshotSyncer>) {
+        let Some((seq, dir)) = syncer.current_generation() else {
+            return;
+        };
+        let opened = tokio::task::spawn_blocking(move || Agentreplay::open(&dir)).await;
+        let mut status = self.status.write();
+        match opened {
+            Ok(Ok(fresh)) => {
+                db.replace_data(fresh);
+                tracing::info!("Replica now serving snapshot {}", seq);
+                status.serving_seq = Some(seq);
+                status.restart_required = status.synced_seq != status.serving_seq;
+                if let Err(e) = syncer.prune(1) {
+                    tracing::warn!("Failed to prune old replica generations: {}", e);
+                }
+            }
+            Ok(Err(e)) => {
+                tracing::warn!("Failed to open synced snapshot {}: {}", seq, e);
+                status.last_error = Some(format!("reload of snapshot {} failed: {}", seq, e));
+            }
+            Err(e) => {
+                status.last_error = Some(format!("reload task panicked: {}", e));
+            }
+        }
+    }
+}
+
+/// Why this node refuses writes right now, if it does
+///
+/// Replicas, warm standbys (and a writer that lost its lease or was fenced
+/// by a promoted standby) refuse writes, as does a process that opened its
+/// data directory read-only because another one holds the lock. Every
+/// ingest path, HTTP and gRPC, checks this before storing anything.
+pub fn write_refusal(state: &AppState) -> Option<serde_json::Value> {
+    if let Some(lock) = state.instance_lock.as_ref().filter(|l| !l.accepts_writes()) {
+        let status = lock.status();
+        return Some(serde_json::json!({
+            "error": "The data directory is opened read-only by this process",
+            "data_dir": status.data_dir,
+            "lock_owner": status.owner,
+        }));
+    }
+    if let Some(cluster) = state.cluster.as_ref().filter(|c| !c.accepts_writes()) {
+        let status = cluster.status();
+        return Some(serde_json::json!({
+            "error": "This node does not accept writes",
+            "role": status.role,
+            "writer": status.lease.map(|lease| lease.node_id),
+        }));
+    }
+    if let Some(replication) = state.replication.as_ref().filter(|r| !r.accepts_writes()) {
+        let status = replication.status();
+        return Some(serde_json::json!({
+            "error": "This node does not accept writes",
+            "role": status.role,
+            "last_error": status.last_error,
+        }));
+    }
+    None
+}
+
+/// Reject writes on nodes that do not hold the writer lease
+///
+/// Mutating requests refused by [`write_refusal`] are answered with 503 so
+/// load balancers and SDKs retry against the writer.
+pub async fn write_guard_middleware(
+    State(state): State<AppState>,
+    request: Request<Body>,
+    next: Next,
+) -> Response {
+    let path = request.uri().path();
+    let is_read = matches!(
+        *request.method(),
+        Method::GET | Method::HEAD | Method::OPTIONS
+    ) || (request.method() == Method::POST && READ_ONLY_POST_PATHS.contains(&path));
+    if is_read || CONTROL_PATHS.contains(&path) {
+        return next.run(request).await;
+    }
+
+    match write_refusal(&state) {
+        Some(body) => (StatusCode::SERVICE_UNAVAILABLE, Json(body)).into_response(),
+        None => next.run(request).await,
+    }
+}
+
+/// GET /api/v1/cluster/status
+pub async fn get_cluster_status(State(state): State<AppState>) -> Json<ClusterStatus> {
+    Json(match state.cluster.as_ref() {
+        Some(cluster) => cluster.status(),
+        None => ClusterStatus {
+            accepts_writes: true,
+            ..Default::default()
+        },
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    fn cluster_config(shared: &Path, role: NodeRole, node_id: &str) -> ClusterConfig {
+        ClusterConfig {
+            role,
+            node_id: node_id.to_string(),
+            shared_dir: Some(shared.to_path_buf()),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_second_writer_refused() {
+        let shared = TempDir::new().unwrap();
+        let data = TempDir::new().unwrap();
+
+        let first = ClusterNode::new(&cluster_config(shared.path(), NodeRole::Writer, "a"))
+            .unwrap()
+            .unwrap();
+        first.prepare(data.path()).unwrap();
+        assert!(first.accepts_writes());
+
+        let second = ClusterNode::new(&cluster_config(shared.path(), NodeRole::Writer, "b"))
+            .unwrap()
+            .unwrap();
+        assert!(second.prepare(data.path()).is_err());
+        assert!(!second.accepts_writes());
+    }
+
+    #[test]
+    fn test_replica_is_read_only() {
+        let shared = TempDir::new().unwrap();
+        let data = TempDir::new().unwrap();
+
+        let replica = ClusterNode::new(&cluster_config(shared.path(), NodeRole::Replica, "r"))
+            .unwrap()
+            .unwrap();
+        let dir = replica.prepare(data.path()).unwrap();
+        assert!(dir.starts_with(data.path()));
+        assert!(!replica.accepts_writes());
+        assert!(replica.status().serving_seq.is_none());
+    }
+}
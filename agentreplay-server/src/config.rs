@@ -13,11 +13,23 @@
 // You should have received a copy of the GNU Affero General Public License
 // along with this program. If not, see <https://www.gnu.org/licenses/>.
 
-use anyhow::Result;
+use anyhow::{Context, Result};
 use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
 use std::net::SocketAddr;
 use std::path::{Path, PathBuf};
 
+use crate::admission::{OverloadPolicy, QueueAdmissionConfig};
+use crate::api::hydration::HydrationLevel;
+use crate::auth::Role;
+use crate::cluster::NodeRole;
+use crate::guardrails::{GuardrailEngine, GuardrailRule};
+use crate::instance_lock::LockConflictPolicy;
+use crate::llm::failover::{Failover, RoutingRule};
+use crate::ingestion::pipeline::{self, PipelineStage};
+use crate::response_cache::{ProjectCacheConfig, ResponseCache};
+use crate::shadow::{ShadowRule, ShadowTraffic};
+
 /// Agentreplay Server Configuration
 #[derive(Debug, Clone, Deserialize, Serialize)]
 pub struct ServerConfig {
@@ -26,6 +38,60 @@ pub struct ServerConfig {
     pub auth: AuthConfig,
     #[serde(default)]
     pub llm: LLMConfig,
+    #[serde(default)]
+    pub ingestion: IngestionAdmissionConfig,
+    #[serde(default)]
+    pub cluster: ClusterConfig,
+    #[serde(default)]
+    pub replication: ReplicationConfig,
+    #[serde(default)]
+    pub archive: ArchiveConfig,
+    #[serde(default)]
+    pub session_analysis: SessionAnalysisConfig,
+    #[serde(default)]
+    pub conversations: ConversationConfig,
+    #[serde(default)]
+    pub volume_alerts: VolumeAlertConfig,
+    #[serde(default)]
+    pub vault: VaultConfig,
+    #[serde(default)]
+    pub scaling: ScalingConfig,
+    #[serde(default)]
+    pub governor: SemanticGovernorConfig,
+    #[serde(default)]
+    pub drift: DriftConfig,
+    #[serde(default)]
+    pub guardrails: GuardrailsConfig,
+    #[serde(default)]
+    pub simulation: SimulationConfig,
+    #[serde(default)]
+    pub shadow: ShadowConfig,
+    #[serde(default)]
+    pub response_cache: ResponseCacheConfig,
+    #[serde(default)]
+    pub hydration: HydrationConfig,
+    #[serde(default)]
+    pub reports: ReportsConfig,
+    #[serde(default)]
+    pub rate_limits: RouteRateLimitConfig,
+    #[serde(default)]
+    pub jobs: JobsConfig,
+    #[serde(default)]
+    pub pricing: PricingConfig,
+    #[serde(default)]
+    pub quotas: QuotaConfig,
+    #[serde(default)]
+    pub access_policies: AccessPolicyConfig,
+    #[serde(default)]
+    pub self_tracing: SelfTracingConfig,
+    #[serde(default)]
+    pub scripting: ScriptingConfig,
+    #[serde(default)]
+    pub mcp: McpConfig,
+
+    /// File this config was loaded from, re-read on reload
+    #[serde(skip)]
+    pub config_file: Option<PathBuf>,
 }
 
 #[derive(Debug, Clone, Deserialize, Serialize)]
@@ -49,6 +115,21 @@ pub struct HttpServerConfig {
     /// Allowed CORS origins (empty = allow all, use specific origins in production)
     #[serde(default)]
     pub cors_origins: Vec<String>,
+
+    /// Seconds `/health` reports draining before listeners close on shutdown,
+    /// giving load balancers time to stop routing here
+    #[serde(default)]
+    pub shutdown_drain_delay_secs: u64,
+
+    /// Seconds allowed for in-flight requests and queued ingestion to finish
+    /// on shutdown
+    #[serde(default = "default_shutdown_grace")]
+    pub shutdown_grace_secs: u64,
+
+    /// Log filter directives, e.g. "info" or "agentreplay_server=debug"
+    /// (default: agentreplay_server=info,tower_http=info; RUST_LOG wins at startup)
+    #[serde(default)]
+    pub log_level: Option<String>,
 }
 
 #[derive(Debug, Clone, Deserialize, Serialize)]
@@ -72,6 +153,11 @@ pub struct StorageConfig {
     /// Default: true for maximum throughput
     #[serde(default = "default_high_performance")]
     pub high_performance: bool,
+
+    /// What to do when another process (server or desktop app) holds the
+    /// data directory lock: "fail" (default) or "read_only"
+    #[serde(default)]
+    pub on_lock_conflict: LockConflictPolicy,
 }
 
 fn default_high_performance() -> bool {
@@ -91,74 +177,1532 @@ pub struct LLMConfig {
 
     /// Ollama base URL (e.g., "http://localhost:11434")
     pub ollama_base_url: Option<String>,
+
+    /// Retries, timeouts, circuit breakers and fallback providers
+    #[serde(default)]
+    pub failover: FailoverConfig,
 }
 
+/// Failover of LLM provider calls (see [`crate::llm::failover`])
+///
+/// Retries back off exponentially from `initial_backoff_ms` up to
+/// `max_backoff_ms`. Each call adds `budget_ratio` retries to a budget
+/// holding at most `budget_burst`. A provider's circuit opens for
+/// `open_secs` after `failure_threshold` failures in a row.
 #[derive(Debug, Clone, Deserialize, Serialize)]
-pub struct AuthConfig {
-    /// Enable authentication (default: false for development)
+pub struct FailoverConfig {
+    #[serde(default = "default_failover_enabled")]
+    pub enabled: bool,
+
+    #[serde(default = "default_failover_timeout_secs")]
+    pub timeout_secs: u64,
+
+    #[serde(default = "default_failover_max_retries")]
+    pub max_retries: u32,
+
+    #[serde(default = "default_failover_initial_backoff_ms")]
+    pub initial_backoff_ms: u64,
+
+    #[serde(default = "default_failover_max_backoff_ms")]
+    pub max_backoff_ms: u64,
+
+    #[serde(default = "default_failover_budget_ratio")]
+    pub budget_ratio: f64,
+
+    #[serde(default = "default_failover_budget_burst")]
+    pub budget_burst: f64,
+
+    #[serde(default = "default_failover_failure_threshold")]
+    pub failure_threshold: u32,
+
+    #[serde(default = "default_failover_open_secs")]
+    pub open_secs: u64,
+
+    #[serde(default)]
+    pub routes: Vec<RoutingRule>,
+}
+
+impl Default for FailoverConfig {
+    fn default() -> Self {
+        Self {
+            enabled: default_failover_enabled(),
+            timeout_secs: default_failover_timeout_secs(),
+            max_retries: default_failover_max_retries(),
+            initial_backoff_ms: default_failover_initial_backoff_ms(),
+            max_backoff_ms: default_failover_max_backoff_ms(),
+            budget_ratio: default_failover_budget_ratio(),
+            budget_burst: default_failover_budget_burst(),
+            failure_threshold: default_failover_failure_threshold(),
+            open_secs: default_failover_open_secs(),
+            routes: Vec::new(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct IngestionAdmissionConfig {
+    /// What to do when the ingestion queue is above its high watermark:
+    /// "reject" (429 + Retry-After) or "sample" (keep a subset of the batch)
+    #[serde(default)]
+    pub overload_policy: OverloadPolicy,
+
+    /// Fraction of queue capacity at which backpressure kicks in (0.0 - 1.0)
+    #[serde(default = "default_queue_high_watermark")]
+    pub queue_high_watermark: f64,
+
+    /// How long retried spans and Idempotency-Key responses are remembered,
+    /// in seconds (0 disables deduplication)
+    #[serde(default = "default_dedup_window_secs")]
+    pub dedup_window_secs: u64,
+
+    /// Clock skew tolerated between client hosts and the server before span
+    /// timestamps are corrected, in milliseconds (0 disables correction)
+    #[serde(default = "default_clock_skew_tolerance_ms")]
+    pub clock_skew_tolerance_ms: u64,
+
+    /// How long a started span may wait for its finalize update before it is
+    /// closed as abandoned, in seconds (0 keeps open spans open)
+    #[serde(default = "default_open_span_timeout_secs")]
+    pub open_span_timeout_secs: u64,
+
+    /// Ordered ingestion stages, e.g. `["sanitize", "transform:normalize",
+    /// "enrich", "scripts", "sample", "governor", "store"]`
+    /// (see [`crate::ingestion::pipeline`])
+    #[serde(default = "pipeline::default_pipeline")]
+    pub pipeline: Vec<PipelineStage>,
+
+    /// Directory of WASM transform modules (default: `<data_dir>/transforms`);
+    /// they are only loaded by builds with the `wasm` feature
+    #[serde(default)]
+    pub transforms_dir: Option<PathBuf>,
+}
+
+impl Default for IngestionAdmissionConfig {
+    fn default() -> Self {
+        Self {
+            overload_policy: OverloadPolicy::default(),
+            queue_high_watermark: default_queue_high_watermark(),
+            dedup_window_secs: default_dedup_window_secs(),
+            clock_skew_tolerance_ms: default_clock_skew_tolerance_ms(),
+            open_span_timeout_secs: default_open_span_timeout_secs(),
+            pipeline: pipeline::default_pipeline(),
+            transforms_dir: None,
+        }
+    }
+}
+
+impl IngestionAdmissionConfig {
+    pub fn queue_admission_config(&self) -> QueueAdmissionConfig {
+        QueueAdmissionConfig {
+            high_watermark: self.queue_high_watermark,
+            policy: self.overload_policy,
+            ..Default::default()
+        }
+    }
+
+    pub fn dedup_window(&self) -> std::time::Duration {
+        std::time::Duration::from_secs(self.dedup_window_secs)
+    }
+
+    /// Directory holding `<name>.wasm` transform modules
+    pub fn transforms_dir(&self, data_dir: &Path) -> PathBuf {
+        self.transforms_dir
+            .clone()
+            .unwrap_or_else(|| data_dir.join("transforms"))
+    }
+
+    /// Skew tolerance, or None when correction is disabled
+    pub fn clock_skew_tolerance(&self) -> Option<std::time::Duration> {
+        (self.clock_skew_tolerance_ms > 0)
+            .then(|| std::time::Duration::from_millis(self.clock_skew_tolerance_ms))
+    }
+
+    /// Open span timeout, or None when abandoned spans are never closed
+    pub fn open_span_timeout(&self) -> Option<std::time::Duration> {
+        (self.open_span_timeout_secs > 0)
+            .then(|| std::time::Duration::from_secs(self.open_span_timeout_secs))
+    }
+}
+
+/// Multi-node deployment over a shared storage directory
+///
+/// One writer node ingests and publishes snapshots; replicas serve reads from
+/// the latest synced snapshot. The writer holds a lease in the shared
+/// directory so two writers never publish at once.
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct ClusterConfig {
+    /// "standalone" (default), "writer" or "replica"
+    #[serde(default)]
+    pub role: NodeRole,
+
+    /// Identifier recorded in the writer lease and published snapshots
+    #[serde(default = "default_node_id")]
+    pub node_id: String,
+
+    /// Shared directory (NFS, mounted object store, ...) holding snapshots
+    /// and the writer lease; required for writer and replica roles
+    #[serde(default)]
+    pub shared_dir: Option<PathBuf>,
+
+    /// Writer lease lifetime in seconds; renewed every third of this
+    #[serde(default = "default_lease_ttl_secs")]
+    pub lease_ttl_secs: u64,
+
+    /// How often the writer publishes and replicas sync, in seconds
+    #[serde(default = "default_sync_interval_secs")]
+    pub sync_interval_secs: u64,
+}
+
+impl Default for ClusterConfig {
+    fn default() -> Self {
+        Self {
+            role: NodeRole::default(),
+            node_id: default_node_id(),
+            shared_dir: None,
+            lease_ttl_secs: default_lease_ttl_secs(),
+            sync_interval_secs: default_sync_interval_secs(),
+        }
+    }
+}
+
+impl ClusterConfig {
+    pub fn lease_ttl(&self) -> std::time::Duration {
+        std::time::Duration::from_secs(self.lease_ttl_secs)
+    }
+
+    pub fn sync_interval(&self) -> std::time::Duration {
+        std::time::Duration::from_secs(self.sync_interval_secs)
+    }
+}
+
+/// Write-ahead log shipping to a warm standby
+///
+/// A primary with `wal_dir` set ships a logical log of its writes there; a
+/// standby (`--standby`) pointed at the same directory replays it
+/// continuously, rejects writes, and takes over shipping once promoted.
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct ReplicationConfig {
+    /// Shared directory (NFS, mounted object store, ...) WAL segments are
+    /// shipped through; shipping is disabled when unset
+    #[serde(default)]
+    pub wal_dir: Option<PathBuf>,
+
+    /// Start as a warm standby applying the primary's WAL
+    #[serde(default)]
+    pub standby: bool,
+
+    /// How often the primary seals buffered writes into a segment, in ms
+    #[serde(default = "default_wal_ship_interval_ms")]
+    pub ship_interval_ms: u64,
+
+    /// How often the standby polls for new segments, in ms
+    #[serde(default = "default_wal_apply_interval_ms")]
+    pub apply_interval_ms: u64,
+
+    /// Seal a segment early once this many bytes are buffered
+    #[serde(default = "default_wal_max_segment_bytes")]
+    pub max_segment_bytes: usize,
+
+    /// Segments kept in `wal_dir`; the primary prunes older ones
+    #[serde(default = "default_wal_retain_segments")]
+    pub retain_segments: usize,
+}
+
+impl Default for ReplicationConfig {
+    fn default() -> Self {
+        Self {
+            wal_dir: None,
+            standby: false,
+            ship_interval_ms: default_wal_ship_interval_ms(),
+            apply_interval_ms: default_wal_apply_interval_ms(),
+            max_segment_bytes: default_wal_max_segment_bytes(),
+            retain_segments: default_wal_retain_segments(),
+        }
+    }
+}
+
+impl ReplicationConfig {
+    pub fn ship_interval(&self) -> std::time::Duration {
+        std::time::Duration::from_millis(self.ship_interval_ms)
+    }
+
+    pub fn apply_interval(&self) -> std::time::Duration {
+        std::time::Duration::from_millis(self.apply_interval_ms)
+    }
+}
+
+/// Cold archive tier for old traces
+///
+/// With `dir` set, traces older than `archive_after_days` are moved there
+/// periodically, and trace queries touching archived ranges restore them on
+/// demand.
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct ArchiveConfig {
+    /// Archive location (local directory or mounted object store); archiving
+    /// is disabled when unset
+    #[serde(default)]
+    pub dir: Option<PathBuf>,
+
+    /// Archive traces older than this many days; 0 only archives on request
+    #[serde(default)]
+    pub archive_after_days: u32,
+
+    /// How often the archive pass runs, in hours
+    #[serde(default = "default_archive_interval_hours")]
+    pub interval_hours: u64,
+
+    /// Archived ranges restored at the same time
+    #[serde(default = "default_max_concurrent_rehydrations")]
+    pub max_concurrent_rehydrations: usize,
+}
+
+impl Default for ArchiveConfig {
+    fn default() -> Self {
+        Self {
+            dir: None,
+            archive_after_days: 0,
+            interval_hours: default_archive_interval_hours(),
+            max_concurrent_rehydrations: default_max_concurrent_rehydrations(),
+        }
+    }
+}
+
+/// Session topic and goal-completion analysis
+///
+/// When enabled, sessions idle for `idle_minutes` are analyzed periodically:
+/// tagged with topics, topic drift and a goal-completed verdict, which feed
+/// the goal-completion funnel. With `llm_provider` set the verdict and topics
+/// come from that provider (falling back to heuristics on failure).
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct SessionAnalysisConfig {
+    /// Run the background analysis pass
     #[serde(default)]
     pub enabled: bool,
 
-    /// JWT secret for token validation (required if auth enabled)
-    pub jwt_secret: Option<String>,
+    /// A session counts as finished after this many minutes without spans
+    #[serde(default = "default_session_idle_minutes")]
+    pub idle_minutes: u64,
+
+    /// How often the analysis pass runs, in minutes
+    #[serde(default = "default_session_analysis_interval_minutes")]
+    pub interval_minutes: u64,
+
+    /// Only sessions active within this many hours are picked up
+    #[serde(default = "default_session_analysis_lookback_hours")]
+    pub lookback_hours: u64,
 
-    /// Static API keys (format: "key:tenant_id")
+    /// LLM provider for analysis (e.g. "openai", "ollama"); heuristics when unset
     #[serde(default)]
-    pub api_keys: Vec<String>,
+    pub llm_provider: Option<String>,
 
-    /// Rate limiting configuration
+    /// Model to use with `llm_provider` (provider default when unset)
     #[serde(default)]
-    pub rate_limit: RateLimitConfig,
+    pub llm_model: Option<String>,
+}
+
+impl Default for SessionAnalysisConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            idle_minutes: default_session_idle_minutes(),
+            interval_minutes: default_session_analysis_interval_minutes(),
+            lookback_hours: default_session_analysis_lookback_hours(),
+            llm_provider: None,
+            llm_model: None,
+        }
+    }
 }
 
+/// Conversation threading across traces
+///
+/// Spans carrying one of `correlation_attributes` link their session (or,
+/// without a session, their trace) to the conversation named by the
+/// attribute's value, so multi-trace chat apps can be viewed as one thread.
 #[derive(Debug, Clone, Deserialize, Serialize)]
-pub struct RateLimitConfig {
-    /// Enable rate limiting on authentication endpoints
-    #[serde(default = "default_rate_limit_enabled")]
+pub struct ConversationConfig {
+    /// Span attributes holding the conversation ID, checked in order
+    #[serde(default = "default_correlation_attributes")]
+    pub correlation_attributes: Vec<String>,
+}
+
+impl Default for ConversationConfig {
+    fn default() -> Self {
+        Self {
+            correlation_attributes: default_correlation_attributes(),
+        }
+    }
+}
+
+/// Alerts on sudden changes in per-project ingest volume
+///
+/// Every `check_interval_minutes` the span count of each project over the
+/// last `window_minutes` is compared with its average over the preceding
+/// `baseline_hours`. A spike (`spike_factor` times the baseline) or a drop
+/// (at most `drop_factor` times the baseline - zero spans by default) of a
+/// project averaging at least `min_baseline` spans per window raises an
+/// alert, delivered to `channels` and `on_alert` scripts.
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct VolumeAlertConfig {
+    #[serde(default = "default_volume_alerts_enabled")]
     pub enabled: bool,
 
-    /// Maximum requests per window
-    #[serde(default = "default_rate_limit_max_requests")]
-    pub max_requests: u32,
+    #[serde(default = "default_volume_window_minutes")]
+    pub window_minutes: u64,
 
-    /// Time window in seconds
-    #[serde(default = "default_rate_limit_window_secs")]
-    pub window_secs: u64,
+    #[serde(default = "default_volume_baseline_hours")]
+    pub baseline_hours: u64,
+
+    #[serde(default = "default_volume_check_interval_minutes")]
+    pub check_interval_minutes: u64,
+
+    #[serde(default = "default_volume_spike_factor")]
+    pub spike_factor: f64,
+
+    #[serde(default)]
+    pub drop_factor: f64,
+
+    /// Projects with a lower average are too quiet to alert on
+    #[serde(default = "default_volume_min_baseline")]
+    pub min_baseline: f64,
+
+    /// Notification channels: "log" or http(s) webhook URLs
+    #[serde(default = "default_volume_channels")]
+    pub channels: Vec<String>,
 }
 
-impl Default for RateLimitConfig {
+impl Default for VolumeAlertConfig {
     fn default() -> Self {
         Self {
             enabled: true,
-            max_requests: 100,
-            window_secs: 60,
+            window_minutes: default_volume_window_minutes(),
+            baseline_hours: default_volume_baseline_hours(),
+            check_interval_minutes: default_volume_check_interval_minutes(),
+            spike_factor: default_volume_spike_factor(),
+            drop_factor: 0.0,
+            min_baseline: default_volume_min_baseline(),
+            channels: default_volume_channels(),
+        }
+    }
+}
+
+/// Provider API key vault (see [`crate::vault`])
+///
+/// Stored keys are encrypted with a 32-byte master key: the base64
+/// `master_key` when set, else the key held in `key_file` (default
+/// `<data_dir>/vault.key`), which is generated on first start. Losing the
+/// master key makes stored keys unrecoverable.
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+pub struct VaultConfig {
+    #[serde(default)]
+    pub master_key: Option<String>,
+
+    #[serde(default)]
+    pub key_file: Option<PathBuf>,
+}
+
+impl VaultConfig {
+    pub fn key_file(&self, data_dir: &Path) -> PathBuf {
+        self.key_file
+            .clone()
+            .unwrap_or_else(|| data_dir.join("vault.key"))
+    }
+}
+
+/// Autoscaling signals (see [`crate::scaling`])
+///
+/// Analytics queries run on at most `max_concurrent_queries` blocking
+/// workers; the time they wait for one is the query pressure signal. Each
+/// signal at `/api/v1/admin/scaling` is reported against the target below,
+/// the per-replica value external scalers (KEDA, HPA) should aim for.
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct ScalingConfig {
+    #[serde(default = "default_max_concurrent_queries")]
+    pub max_concurrent_queries: usize,
+
+    /// Window over which query wait percentiles are computed
+    #[serde(default = "default_query_wait_window_secs")]
+    pub query_wait_window_secs: u64,
+
+    /// Target ingestion queue utilization (fraction of capacity)
+    #[serde(default = "default_target_ingestion_utilization")]
+    pub target_ingestion_utilization: f64,
+
+    /// Target WAL bytes not yet checkpointed
+    #[serde(default = "default_target_wal_backlog_bytes")]
+    pub target_wal_backlog_bytes: u64,
+
+    /// Target test cases pending in running eval runs
+    #[serde(default = "default_target_eval_backlog")]
+    pub target_eval_backlog: u64,
+
+    /// Target p95 query queue wait
+    #[serde(default = "default_target_query_wait_ms")]
+    pub target_query_wait_ms: f64,
+}
+
+impl Default for ScalingConfig {
+    fn default() -> Self {
+        Self {
+            max_concurrent_queries: default_max_concurrent_queries(),
+            query_wait_window_secs: default_query_wait_window_secs(),
+            target_ingestion_utilization: default_target_ingestion_utilization(),
+            target_wal_backlog_bytes: default_target_wal_backlog_bytes(),
+            target_eval_backlog: default_target_eval_backlog(),
+            target_query_wait_ms: default_target_query_wait_ms(),
+        }
+    }
+}
+
+/// Semantic deduplication at ingestion (see [`crate::governor`])
+///
+/// A span whose embedding lies within `epsilon` cosine distance of an
+/// already stored one is dropped as a duplicate, unless its agent or project
+/// is exempt. Everything here can be changed at runtime through
+/// `/api/v1/admin/governor`; such changes last until restart.
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct SemanticGovernorConfig {
+    #[serde(default = "default_governor_epsilon")]
+    pub epsilon: f32,
+
+    /// Keep 1-bit embeddings instead of f32 (32x less memory)
+    #[serde(default = "default_governor_binary_quantization")]
+    pub binary_quantization: bool,
+
+    #[serde(default)]
+    pub exempt_agents: Vec<u64>,
+
+    #[serde(default)]
+    pub exempt_projects: Vec<u16>,
+}
+
+impl Default for SemanticGovernorConfig {
+    fn default() -> Self {
+        Self {
+            epsilon: default_governor_epsilon(),
+            binary_quantization: default_governor_binary_quantization(),
+            exempt_agents: Vec::new(),
+            exempt_projects: Vec::new(),
+        }
+    }
+}
+
+/// Drift between production inputs and golden datasets (see [`crate::drift`])
+///
+/// Every `interval_minutes` up to `sample_size` user inputs from the last
+/// `window_hours` are embedded and compared with the test case inputs of
+/// `datasets` (names or IDs; all datasets when empty). Drift is reported
+/// when the kernel MMD exceeds `mmd_threshold` or the centroids are more
+/// than `centroid_threshold` cosine distance apart. An input counts as
+/// covered when some test case is at least `coverage_similarity` similar.
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct DriftConfig {
+    #[serde(default = "default_drift_enabled")]
+    pub enabled: bool,
+
+    #[serde(default = "default_drift_interval_minutes")]
+    pub interval_minutes: u64,
+
+    #[serde(default = "default_drift_window_hours")]
+    pub window_hours: u64,
+
+    #[serde(default = "default_drift_sample_size")]
+    pub sample_size: usize,
+
+    /// Fewer production inputs than this are not compared
+    #[serde(default = "default_drift_min_samples")]
+    pub min_samples: usize,
+
+    #[serde(default)]
+    pub datasets: Vec<String>,
+
+    #[serde(default = "default_drift_mmd_threshold")]
+    pub mmd_threshold: f64,
+
+    #[serde(default = "default_drift_centroid_threshold")]
+    pub centroid_threshold: f64,
+
+    #[serde(default = "default_drift_coverage_similarity")]
+    pub coverage_similarity: f64,
+}
+
+impl Default for DriftConfig {
+    fn default() -> Self {
+        Self {
+            enabled: default_drift_enabled(),
+            interval_minutes: default_drift_interval_minutes(),
+            window_hours: default_drift_window_hours(),
+            sample_size: default_drift_sample_size(),
+            min_samples: default_drift_min_samples(),
+            datasets: Vec::new(),
+            mmd_threshold: default_drift_mmd_threshold(),
+            centroid_threshold: default_drift_centroid_threshold(),
+            coverage_similarity: default_drift_coverage_similarity(),
+        }
+    }
+}
+
+/// Policy checks on the chat completion proxy (see [`crate::guardrails`])
+///
+/// `rules` run in order; input rules see the prompt messages, output rules
+/// the completion.
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct GuardrailsConfig {
+    #[serde(default = "default_guardrails_enabled")]
+    pub enabled: bool,
+
+    #[serde(default)]
+    pub rules: Vec<GuardrailRule>,
+}
+
+impl Default for GuardrailsConfig {
+    fn default() -> Self {
+        Self {
+            enabled: default_guardrails_enabled(),
+            rules: Vec::new(),
+        }
+    }
+}
+
+/// Simulation runs against live agents (see [`crate::simulation`])
+///
+/// Trial traces are recorded in project `project_id`. Each agent call times
+/// out after `timeout_secs`; at most `concurrency` trials of a run are in
+/// flight at once.
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct SimulationConfig {
+    #[serde(default = "default_simulation_project_id")]
+    pub project_id: u16,
+
+    /// Upper bound on `trials` per task in a simulation request
+    #[serde(default = "default_simulation_max_trials")]
+    pub max_trials: u32,
+
+    #[serde(default = "default_simulation_concurrency")]
+    pub concurrency: usize,
+
+    #[serde(default = "default_simulation_timeout_secs")]
+    pub timeout_secs: u64,
+}
+
+impl Default for SimulationConfig {
+    fn default() -> Self {
+        Self {
+            project_id: default_simulation_project_id(),
+            max_trials: default_simulation_max_trials(),
+            concurrency: default_simulation_concurrency(),
+            timeout_secs: default_simulation_timeout_secs(),
         }
     }
 }
 
-// Default values
-fn default_http_addr() -> String {
-    "127.0.0.1:47100".to_string()
+/// Shadow traffic on the chat proxy (see [`crate::shadow`])
+///
+/// At most `max_in_flight` candidate calls run at once; sampled requests
+/// beyond that are not shadowed.
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct ShadowConfig {
+    #[serde(default = "default_shadow_enabled")]
+    pub enabled: bool,
+
+    #[serde(default = "default_shadow_max_in_flight")]
+    pub max_in_flight: usize,
+
+    #[serde(default)]
+    pub rules: Vec<ShadowRule>,
+}
+
+impl Default for ShadowConfig {
+    fn default() -> Self {
+        Self {
+            enabled: default_shadow_enabled(),
+            max_in_flight: default_shadow_max_in_flight(),
+            rules: Vec::new(),
+        }
+    }
+}
+
+/// Response cache of the chat proxy (see [`crate::response_cache`])
+///
+/// Off by default; `projects` turn caching on or off and change the TTL or
+/// similarity threshold for single projects. Past `max_entries`, the oldest
+/// answers are evicted first.
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct ResponseCacheConfig {
+    #[serde(default)]
+    pub enabled: bool,
+
+    #[serde(default = "default_response_cache_ttl_secs")]
+    pub ttl_secs: u64,
+
+    #[serde(default = "default_response_cache_max_entries")]
+    pub max_entries: usize,
+
+    /// Cosine similarity at which prompts count as the same; exact matches
+    /// only when unset
+    #[serde(default)]
+    pub similarity_threshold: Option<f32>,
+
+    #[serde(default)]
+    pub projects: Vec<ProjectCacheConfig>,
+}
+
+impl Default for ResponseCacheConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            ttl_secs: default_response_cache_ttl_secs(),
+            max_entries: default_response_cache_max_entries(),
+            similarity_threshold: None,
+            projects: Vec::new(),
+        }
+    }
+}
+
+/// Payload hydration of trace listings (see [`crate::api::hydration`])
+///
+/// `default_level` applies when a request names no level. Each role may
+/// request up to its `*_max` level. Spans flagged as containing PII or
+/// secrets get full payloads and unredacted previews only for callers with
+/// at least `sensitive_role`.
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct HydrationConfig {
+    #[serde(default = "default_hydration_default_level")]
+    pub default_level: HydrationLevel,
+
+    #[serde(default = "default_hydration_viewer_max")]
+    pub viewer_max: HydrationLevel,
+
+    #[serde(default = "default_hydration_member_max")]
+    pub member_max: HydrationLevel,
+
+    #[serde(default = "default_hydration_admin_max")]
+    pub admin_max: HydrationLevel,
+
+    #[serde(default = "default_hydration_sensitive_role")]
+    pub sensitive_role: Role,
+}
+
+impl HydrationConfig {
+    /// Highest level `role` may request
+    pub fn max_level(&self, role: Role) -> HydrationLevel {
+        match role {
+            Role::Viewer => self.viewer_max,
+            Role::Member => self.member_max,
+            Role::Admin => self.admin_max,
+        }
+    }
+}
+
+impl Default for HydrationConfig {
+    fn default() -> Self {
+        Self {
+            default_level: default_hydration_default_level(),
+            viewer_max: default_hydration_viewer_max(),
+            member_max: default_hydration_member_max(),
+            admin_max: default_hydration_admin_max(),
+            sensitive_role: default_hydration_sensitive_role(),
+        }
+    }
+}
+
+/// Scheduled reports (see [`crate::reports`])
+///
+/// Schedules are checked for due runs every `check_interval_minutes`.
+/// Webhook delivery works out of the box; email delivery needs `smtp`.
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct ReportsConfig {
+    #[serde(default = "default_reports_enabled")]
+    pub enabled: bool,
+
+    #[serde(default = "default_reports_check_interval_minutes")]
+    pub check_interval_minutes: u64,
+
+    #[serde(default)]
+    pub smtp: Option<SmtpConfig>,
+}
+
+impl Default for ReportsConfig {
+    fn default() -> Self {
+        Self {
+            enabled: default_reports_enabled(),
+            check_interval_minutes: default_reports_check_interval_minutes(),
+            smtp: None,
+        }
+    }
+}
+
+/// Outgoing mail server for report emails
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct SmtpConfig {
+    pub host: String,
+
+    #[serde(default = "default_smtp_port")]
+    pub port: u16,
+
+    #[serde(default)]
+    pub username: Option<String>,
+
+    #[serde(default)]
+    pub password: Option<String>,
+
+    /// Sender address, e.g. "AgentReplay <reports@example.com>"
+    pub from: String,
+
+    /// Upgrade the connection with STARTTLS; disable only for local relays
+    #[serde(default = "default_smtp_starttls")]
+    pub starttls: bool,
+}
+
+/// Per-client request budgets by route class
+///
+/// Each authenticated client (API token, user or static key, within its
+/// tenant) gets a token bucket per class that holds up to `burst` requests
+/// and refills at `requests_per_minute`.
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct RouteRateLimitConfig {
+    #[serde(default)]
+    pub enabled: bool,
+
+    /// Trace and span ingestion, including bulk imports
+    #[serde(default = "default_ingestion_budget")]
+    pub ingestion: RouteBudget,
+
+    /// Everything that is neither ingestion nor an LLM call
+    #[serde(default = "default_query_budget")]
+    pub query: RouteBudget,
+
+    /// LLM gateway and playground calls, which cost provider tokens
+    #[serde(default = "default_llm_budget")]
+    pub llm: RouteBudget,
+
+    /// Clients tracked at once; idle clients are evicted first
+    #[serde(default = "default_rate_limit_max_clients")]
+    pub max_clients: u64,
+}
+
+impl Default for RouteRateLimitConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            ingestion: default_ingestion_budget(),
+            query: default_query_budget(),
+            llm: default_llm_budget(),
+            max_clients: default_rate_limit_max_clients(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
+pub struct RouteBudget {
+    pub requests_per_minute: u32,
+    pub burst: u32,
+}
+
+/// Background jobs (see [`crate::jobs`])
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct JobsConfig {
+    /// Jobs running at once; the rest wait in the queue
+    #[serde(default = "default_job_workers")]
+    pub workers: usize,
+
+    /// Finished jobs kept for the status API, oldest dropped first
+    #[serde(default = "default_jobs_keep_finished")]
+    pub keep_finished: usize,
+}
+
+impl Default for JobsConfig {
+    fn default() -> Self {
+        Self {
+            workers: default_job_workers(),
+            keep_finished: default_jobs_keep_finished(),
+        }
+    }
+}
+
+/// Model pricing registry, kept under `<data_dir>/pricing`
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct PricingConfig {
+    /// Pull prices from LiteLLM periodically (default: true)
+    #[serde(default = "default_pricing_auto_sync")]
+    pub auto_sync: bool,
+
+    /// Hours between syncs
+    #[serde(default = "default_pricing_sync_interval_hours")]
+    pub sync_interval_hours: u64,
+}
+
+impl Default for PricingConfig {
+    fn default() -> Self {
+        Self {
+            auto_sync: default_pricing_auto_sync(),
+            sync_interval_hours: default_pricing_sync_interval_hours(),
+        }
+    }
+}
+
+/// Daily and monthly usage quotas (see [`crate::quotas`])
+///
+/// ```toml
+/// [quotas]
+/// enabled = true
+///
+/// [quotas.per_key]
+/// requests_per_day = 50000
+/// tokens_per_month = 20000000
+///
+/// [[quotas.projects]]
+/// project_id = 7
+/// spans_per_month = 10000000
+/// ```
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+pub struct QuotaConfig {
+    #[serde(default)]
+    pub enabled: bool,
+
+    /// Limits of every API key, token or user
+    #[serde(default)]
+    pub per_key: QuotaLimits,
+
+    /// Limits of a project, across all of its keys
+    #[serde(default)]
+    pub projects: Vec<ProjectQuota>,
+}
+
+impl QuotaConfig {
+    /// Limits of a project, if it has any
+    pub fn project(&self, project_id: u16) -> Option<&QuotaLimits> {
+        self.projects
+            .iter()
+            .find(|p| p.project_id == project_id)
+            .map(|p| &p.limits)
+    }
+}
+
+/// Usage caps over a UTC calendar day or month; unset means unlimited
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize, Serialize)]
+pub struct QuotaLimits {
+    #[serde(default)]
+    pub requests_per_day: Option<u64>,
+    #[serde(default)]
+    pub requests_per_month: Option<u64>,
+    /// Spans accepted by the ingestion endpoints
+    #[serde(default)]
+    pub spans_per_day: Option<u64>,
+    #[serde(default)]
+    pub spans_per_month: Option<u64>,
+    /// Prompt and completion tokens of LLM calls through the chat proxy
+    #[serde(default)]
+    pub tokens_per_day: Option<u64>,
+    #[serde(default)]
+    pub tokens_per_month: Option<u64>,
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct ProjectQuota {
+    pub project_id: u16,
+    #[serde(flatten)]
+    pub limits: QuotaLimits,
+}
+
+/// Row-level read policies by span attribute (see [`crate::access_policy`])
+///
+/// ```toml
+/// [access_policies]
+/// enabled = true
+///
+/// [[access_policies.policies]]
+/// name = "search-team"
+/// users = ["ana@acme.com", "li@acme.com"]
+/// attributes = { team = ["search"] }
+/// environments = ["staging", "production"]
+/// deny_secrets = true
+/// ```
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct AccessPolicyConfig {
+    #[serde(default)]
+    pub enabled: bool,
+
+    /// Callers with at least this role are never restricted
+    #[serde(default = "default_access_exempt_role")]
+    pub exempt_role: Role,
+
+    /// Whether callers no policy applies to read every span; when false
+    /// they read none
+    #[serde(default = "default_access_allow_unmatched")]
+    pub allow_unmatched: bool,
+
+    #[serde(default)]
+    pub policies: Vec<AccessPolicy>,
+}
+
+impl Default for AccessPolicyConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            exempt_role: default_access_exempt_role(),
+            allow_unmatched: default_access_allow_unmatched(),
+            policies: Vec::new(),
+        }
+    }
+}
+
+/// Which callers a policy applies to and which spans it lets them read
+///
+/// A policy applies to a caller matching every non-empty principal list;
+/// with all of them empty it applies to everyone. A span is readable if
+/// it passes every rule of at least one applicable policy.
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+pub struct AccessPolicy {
+    pub name: String,
+
+    /// User ids, as set by tokens, JWTs and OIDC
+    #[serde(default)]
+    pub users: Vec<String>,
+    #[serde(default)]
+    pub roles: Vec<Role>,
+    #[serde(default)]
+    pub tenants: Vec<u64>,
+    /// Projects of project-scoped keys
+    #[serde(default)]
+    pub projects: Vec<u16>,
+
+    /// Span attributes and their allowed values; spans without the
+    /// attribute are not readable
+    #[serde(default)]
+    pub attributes: HashMap<String, Vec<String>>,
+    /// Allowed environments; empty allows all
+    #[serde(default)]
+    pub environments: Vec<String>,
+    /// Hide spans flagged as containing PII
+    #[serde(default)]
+    pub deny_pii: bool,
+    /// Hide spans flagged as containing secrets
+    #[serde(default)]
+    pub deny_secrets: bool,
+}
+
+/// The server's own spans stored in a reserved project (see
+/// [`crate::self_trace`])
+///
+/// ```toml
+/// [self_tracing]
+/// enabled = true
+/// sample_rate = 0.05
+/// ```
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct SelfTracingConfig {
+    #[serde(default)]
+    pub enabled: bool,
+
+    /// Fraction of traces kept (0.0 - 1.0)
+    #[serde(default = "default_self_trace_sample_rate")]
+    pub sample_rate: f64,
+
+    /// Most spans kept per second; the rest are dropped
+    #[serde(default = "default_self_trace_max_spans_per_second")]
+    pub max_spans_per_second: u32,
+
+    /// Tenant owning the internal project
+    #[serde(default = "default_self_trace_tenant_id")]
+    pub tenant_id: u64,
+
+    /// Module path prefixes of the spans kept; spans must also pass the
+    /// log filter
+    #[serde(default = "default_self_trace_targets")]
+    pub targets: Vec<String>,
+}
+
+impl Default for SelfTracingConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            sample_rate: default_self_trace_sample_rate(),
+            max_spans_per_second: default_self_trace_max_spans_per_second(),
+            tenant_id: default_self_trace_tenant_id(),
+            targets: default_self_trace_targets(),
+        }
+    }
+}
+
+/// Embedded hook scripts (see [`crate::scripting`])
+///
+/// Scripts may POST notifications only to hosts listed here, exact
+/// (`hooks.slack.com`) or by domain (`*.example.com`), and only when they
+/// resolve to public addresses. With the list empty, scripts can notify the
+/// log only.
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+pub struct ScriptingConfig {
+    #[serde(default)]
+    pub webhook_allowlist: Vec<String>,
+}
+
+/// MCP server on 127.0.0.1:47101 (see [`crate::mcp`])
+///
+/// MCP clients do not authenticate, so the server reads the data of one
+/// tenant only. With `auth.strict_tenant_isolation`, it is not started
+/// unless `tenant_id` names that tenant explicitly.
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+pub struct McpConfig {
+    /// Tenant whose traces MCP tools read; tenant 1 when unset
+    #[serde(default)]
+    pub tenant_id: Option<u64>,
+}
+
+impl McpConfig {
+    pub fn tenant(&self) -> u64 {
+        self.tenant_id.unwrap_or(1)
+    }
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct AuthConfig {
+    /// Enable authentication (default: false for development)
+    #[serde(default)]
+    pub enabled: bool,
+
+    /// JWT secret for token validation (required if auth enabled)
+    pub jwt_secret: Option<String>,
+
+    /// Static API keys (format: "key:tenant_id[:project_id[:role]]")
+    ///
+    /// Deprecated in favour of tokens issued through `/api/v1/admin/tokens`;
+    /// keep one admin key or a JWT secret to bootstrap the first token.
+    #[serde(default)]
+    pub api_keys: Vec<String>,
+
+    /// OpenID Connect identity provider for SSO logins
+    #[serde(default)]
+    pub oidc: Option<OidcConfig>,
+
+    /// Rate limiting configuration
+    #[serde(default)]
+    pub rate_limit: RateLimitConfig,
+
+    /// Refuse storage scans that are not scoped to the caller's tenant
+    ///
+    /// Cross-tenant reads by background jobs stay allowed because they ask
+    /// for all tenants explicitly. Requires `enabled`.
+    #[serde(default)]
+    pub strict_tenant_isolation: bool,
+}
+
+/// OpenID Connect ID token validation
+///
+/// Tokens are checked against the provider's JWKS, which is discovered from
+/// `issuer` unless `jwks_uri` is set. Tenants and roles come from claims:
+/// `tenant_claim` is a value listed in `tenant_map`, or a numeric tenant id
+/// when no map is configured, and each value of `roles_claim` found in `role_map` grants that role,
+/// the highest one winning.
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct OidcConfig {
+    /// Issuer URL, matched exactly against the `iss` claim
+    pub issuer: String,
+
+    /// Expected `aud` claim, usually the client id
+    pub audience: String,
+
+    #[serde(default)]
+    pub jwks_uri: Option<String>,
+
+    #[serde(default = "default_oidc_jwks_refresh_minutes")]
+    pub jwks_refresh_minutes: u64,
+
+    /// Claim holding the tenant, e.g. "tid" on Azure AD; dotted paths reach
+    /// into nested claims
+    #[serde(default = "default_oidc_tenant_claim")]
+    pub tenant_claim: String,
+
+    #[serde(default)]
+    pub tenant_map: HashMap<String, u64>,
+
+    /// Tenant for users whose token lacks `tenant_claim`; such users are
+    /// rejected when unset
+    #[serde(default)]
+    pub default_tenant_id: Option<u64>,
+
+    #[serde(default)]
+    pub project_claim: Option<String>,
+
+    /// Claim listing the user's groups or roles, e.g. "realm_access.roles"
+    /// on Keycloak
+    #[serde(default = "default_oidc_roles_claim")]
+    pub roles_claim: String,
+
+    #[serde(default)]
+    pub role_map: HashMap<String, Role>,
+
+    #[serde(default = "default_oidc_role")]
+    pub default_role: Role,
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct RateLimitConfig {
+    /// Enable rate limiting on authentication endpoints
+    #[serde(default = "default_rate_limit_enabled")]
+    pub enabled: bool,
+
+    /// Maximum requests per window
+    #[serde(default = "default_rate_limit_max_requests")]
+    pub max_requests: u32,
+
+    /// Time window in seconds
+    #[serde(default = "default_rate_limit_window_secs")]
+    pub window_secs: u64,
+}
+
+impl Default for RateLimitConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            max_requests: 100,
+            window_secs: 60,
+        }
+    }
+}
+
+// Default values
+fn default_http_addr() -> String {
+    "127.0.0.1:47100".to_string()
+}
+
+fn default_max_connections() -> usize {
+    1000
+}
+
+fn default_request_timeout() -> u64 {
+    30
+}
+
+fn default_shutdown_grace() -> u64 {
+    30
+}
+
+fn default_enable_cors() -> bool {
+    true
+}
+
+fn default_data_dir() -> PathBuf {
+    PathBuf::from("./agentreplay-data")
+}
+
+fn default_enable_compression() -> bool {
+    true
+}
+
+fn default_queue_high_watermark() -> f64 {
+    0.9
+}
+
+fn default_dedup_window_secs() -> u64 {
+    600
+}
+
+fn default_clock_skew_tolerance_ms() -> u64 {
+    1000
+}
+
+fn default_open_span_timeout_secs() -> u64 {
+    900
+}
+
+fn default_node_id() -> String {
+    std::env::var("HOSTNAME")
+        .ok()
+        .filter(|h| !h.is_empty())
+        .unwrap_or_else(|| format!("node-{}", std::process::id()))
+}
+
+fn default_lease_ttl_secs() -> u64 {
+    30
+}
+
+fn default_sync_interval_secs() -> u64 {
+    60
+}
+
+fn default_wal_ship_interval_ms() -> u64 {
+    1000
+}
+
+fn default_wal_apply_interval_ms() -> u64 {
+    1000
+}
+
+fn default_wal_max_segment_bytes() -> usize {
+    4 * 1024 * 1024
+}
+
+fn default_wal_retain_segments() -> usize {
+    10_000
+}
+
+fn default_archive_interval_hours() -> u64 {
+    24
+}
+
+fn default_max_concurrent_rehydrations() -> usize {
+    2
+}
+
+fn default_session_idle_minutes() -> u64 {
+    30
+}
+
+fn default_session_analysis_interval_minutes() -> u64 {
+    10
+}
+
+fn default_session_analysis_lookback_hours() -> u64 {
+    24
+}
+
+fn default_volume_alerts_enabled() -> bool {
+    true
+}
+
+fn default_volume_window_minutes() -> u64 {
+    60
+}
+
+fn default_volume_baseline_hours() -> u64 {
+    24
+}
+
+fn default_volume_check_interval_minutes() -> u64 {
+    5
+}
+
+fn default_volume_spike_factor() -> f64 {
+    5.0
+}
+
+fn default_volume_min_baseline() -> f64 {
+    10.0
+}
+
+fn default_volume_channels() -> Vec<String> {
+    vec!["log".to_string()]
+}
+
+fn default_governor_epsilon() -> f32 {
+    0.1
+}
+
+fn default_governor_binary_quantization() -> bool {
+    true
+}
+
+fn default_drift_enabled() -> bool {
+    true
+}
+
+fn default_drift_interval_minutes() -> u64 {
+    60
+}
+
+fn default_drift_window_hours() -> u64 {
+    24
+}
+
+fn default_drift_sample_size() -> usize {
+    200
+}
+
+fn default_drift_min_samples() -> usize {
+    20
+}
+
+fn default_drift_mmd_threshold() -> f64 {
+    0.05
+}
+
+fn default_drift_centroid_threshold() -> f64 {
+    0.15
+}
+
+fn default_drift_coverage_similarity() -> f64 {
+    0.75
+}
+
+fn default_guardrails_enabled() -> bool {
+    true
+}
+
+fn default_simulation_project_id() -> u16 {
+    65_000
+}
+
+fn default_simulation_max_trials() -> u32 {
+    20
+}
+
+fn default_simulation_concurrency() -> usize {
+    4
+}
+
+fn default_simulation_timeout_secs() -> u64 {
+    120
+}
+
+fn default_failover_enabled() -> bool {
+    true
+}
+
+fn default_failover_timeout_secs() -> u64 {
+    120
+}
+
+fn default_failover_max_retries() -> u32 {
+    1
+}
+
+fn default_failover_initial_backoff_ms() -> u64 {
+    250
+}
+
+fn default_failover_max_backoff_ms() -> u64 {
+    4_000
+}
+
+fn default_failover_budget_ratio() -> f64 {
+    0.2
+}
+
+fn default_failover_budget_burst() -> f64 {
+    10.0
+}
+
+fn default_failover_failure_threshold() -> u32 {
+    5
+}
+
+fn default_failover_open_secs() -> u64 {
+    30
+}
+
+fn default_shadow_enabled() -> bool {
+    true
+}
+
+fn default_shadow_max_in_flight() -> usize {
+    16
+}
+
+fn default_response_cache_ttl_secs() -> u64 {
+    3600
+}
+
+fn default_response_cache_max_entries() -> usize {
+    10_000
+}
+
+fn default_hydration_default_level() -> HydrationLevel {
+    HydrationLevel::Full
+}
+
+fn default_hydration_viewer_max() -> HydrationLevel {
+    HydrationLevel::Preview
+}
+
+fn default_hydration_member_max() -> HydrationLevel {
+    HydrationLevel::Full
+}
+
+fn default_hydration_admin_max() -> HydrationLevel {
+    HydrationLevel::Full
+}
+
+fn default_hydration_sensitive_role() -> Role {
+    Role::Admin
+}
+
+fn default_access_exempt_role() -> Role {
+    Role::Admin
+}
+
+fn default_access_allow_unmatched() -> bool {
+    true
+}
+
+fn default_self_trace_sample_rate() -> f64 {
+    0.1
+}
+
+fn default_self_trace_max_spans_per_second() -> u32 {
+    100
+}
+
+fn default_self_trace_tenant_id() -> u64 {
+    1
+}
+
+fn default_self_trace_targets() -> Vec<String> {
+    vec!["agentreplay_server".to_string()]
+}
+
+fn default_reports_enabled() -> bool {
+    true
+}
+
+fn default_reports_check_interval_minutes() -> u64 {
+    15
+}
+
+fn default_smtp_port() -> u16 {
+    587
+}
+
+fn default_smtp_starttls() -> bool {
+    true
+}
+
+fn default_oidc_jwks_refresh_minutes() -> u64 {
+    60
+}
+
+fn default_oidc_tenant_claim() -> String {
+    "tenant_id".to_string()
+}
+
+fn default_oidc_roles_claim() -> String {
+    "groups".to_string()
+}
+
+fn default_oidc_role() -> Role {
+    Role::Viewer
 }
 
-fn default_max_connections() -> usize {
-    1000
+fn default_max_concurrent_queries() -> usize {
+    std::thread::available_parallelism()
+        .map(|n| n.get() * 2)
+        .unwrap_or(8)
 }
 
-fn default_request_timeout() -> u64 {
-    30
+fn default_query_wait_window_secs() -> u64 {
+    60
 }
 
-fn default_enable_cors() -> bool {
-    true
+fn default_target_ingestion_utilization() -> f64 {
+    0.5
 }
 
-fn default_data_dir() -> PathBuf {
-    PathBuf::from("./agentreplay-data")
+fn default_target_wal_backlog_bytes() -> u64 {
+    256 * 1024 * 1024
 }
 
-fn default_enable_compression() -> bool {
-    true
+fn default_target_eval_backlog() -> u64 {
+    100
+}
+
+fn default_target_query_wait_ms() -> f64 {
+    250.0
+}
+
+fn default_correlation_attributes() -> Vec<String> {
+    vec![
+        "gen_ai.conversation.id".to_string(),
+        "conversation.id".to_string(),
+    ]
 }
 
 fn default_rate_limit_enabled() -> bool {
@@ -173,6 +1717,47 @@ fn default_rate_limit_window_secs() -> u64 {
     60
 }
 
+fn default_ingestion_budget() -> RouteBudget {
+    RouteBudget {
+        requests_per_minute: 6000,
+        burst: 1000,
+    }
+}
+
+fn default_query_budget() -> RouteBudget {
+    RouteBudget {
+        requests_per_minute: 1200,
+        burst: 200,
+    }
+}
+
+fn default_llm_budget() -> RouteBudget {
+    RouteBudget {
+        requests_per_minute: 60,
+        burst: 10,
+    }
+}
+
+fn default_rate_limit_max_clients() -> u64 {
+    100_000
+}
+
+fn default_job_workers() -> usize {
+    4
+}
+
+fn default_jobs_keep_finished() -> usize {
+    500
+}
+
+fn default_pricing_auto_sync() -> bool {
+    true
+}
+
+fn default_pricing_sync_interval_hours() -> u64 {
+    24
+}
+
 impl Default for ServerConfig {
     fn default() -> Self {
         Self {
@@ -182,20 +1767,52 @@ impl Default for ServerConfig {
                 request_timeout_secs: default_request_timeout(),
                 enable_cors: default_enable_cors(),
                 cors_origins: vec![], // Empty = allow all (development mode)
+                shutdown_drain_delay_secs: 0,
+                shutdown_grace_secs: default_shutdown_grace(),
+                log_level: None,
             },
             storage: StorageConfig {
                 data_dir: default_data_dir(),
                 enable_compression: default_enable_compression(),
                 use_project_storage: false,
                 high_performance: default_high_performance(),
+                on_lock_conflict: LockConflictPolicy::default(),
             },
             auth: AuthConfig {
                 enabled: false,
                 jwt_secret: None,
                 api_keys: vec![],
+                oidc: None,
                 rate_limit: RateLimitConfig::default(),
+                strict_tenant_isolation: false,
             },
             llm: LLMConfig::default(),
+            ingestion: IngestionAdmissionConfig::default(),
+            cluster: ClusterConfig::default(),
+            replication: ReplicationConfig::default(),
+            archive: ArchiveConfig::default(),
+            session_analysis: SessionAnalysisConfig::default(),
+            conversations: ConversationConfig::default(),
+            volume_alerts: VolumeAlertConfig::default(),
+            vault: VaultConfig::default(),
+            scaling: ScalingConfig::default(),
+            governor: SemanticGovernorConfig::default(),
+            drift: DriftConfig::default(),
+            guardrails: GuardrailsConfig::default(),
+            simulation: SimulationConfig::default(),
+            shadow: ShadowConfig::default(),
+            response_cache: ResponseCacheConfig::default(),
+            hydration: HydrationConfig::default(),
+            reports: ReportsConfig::default(),
+            rate_limits: RouteRateLimitConfig::default(),
+            jobs: JobsConfig::default(),
+            pricing: PricingConfig::default(),
+            quotas: QuotaConfig::default(),
+            access_policies: AccessPolicyConfig::default(),
+            self_tracing: SelfTracingConfig::default(),
+            scripting: ScriptingConfig::default(),
+            mcp: McpConfig::default(),
+            config_file: None,
         }
     }
 }
@@ -213,12 +1830,29 @@ impl ServerConfig {
     /// Supported environment variables:
     /// - AGENTREPLAY_HTTP_ADDR: HTTP listen address (default: 127.0.0.1:47100)
     /// - AGENTREPLAY_DATA_DIR: Data directory path (default: ./agentreplay-data)
+    /// - AGENTREPLAY_LOCK_CONFLICT: "fail" or "read_only" when another process holds the data directory (default: fail)
     /// - AGENTREPLAY_AUTH_ENABLED: Enable authentication (default: false)
     /// - AGENTREPLAY_JWT_SECRET: JWT secret for token validation
     /// - AGENTREPLAY_API_KEYS: Comma-separated API keys (format: key:tenant_id)
+    /// - AGENTREPLAY_OIDC_ISSUER / AGENTREPLAY_OIDC_AUDIENCE: OIDC provider for SSO
     /// - AGENTREPLAY_MAX_CONNECTIONS: Max concurrent connections (default: 1000)
     /// - AGENTREPLAY_REQUEST_TIMEOUT: Request timeout in seconds (default: 30)
+    /// - AGENTREPLAY_SHUTDOWN_DRAIN_DELAY: Seconds /health reports draining before shutdown (default: 0)
+    /// - AGENTREPLAY_SHUTDOWN_GRACE: Seconds to finish requests and queued ingestion on shutdown (default: 30)
     /// - AGENTREPLAY_ENABLE_CORS: Enable CORS (default: true)
+    /// - AGENTREPLAY_OVERLOAD_POLICY: "reject" or "sample" when the ingestion queue is full (default: reject)
+    /// - AGENTREPLAY_DEDUP_WINDOW_SECS: Retry dedup window in seconds, 0 disables (default: 600)
+    /// - AGENTREPLAY_INGEST_PIPELINE: Comma-separated ingestion stages (default: sanitize,enrich,scripts,sample,governor,store; add `logprobs` to capture token logprobs)
+    /// - AGENTREPLAY_NODE_ROLE: "standalone", "writer" or "replica" (default: standalone)
+    /// - AGENTREPLAY_NODE_ID: Node identifier for the writer lease (default: hostname)
+    /// - AGENTREPLAY_SHARED_DIR: Shared snapshot directory for writer/replica roles
+    /// - AGENTREPLAY_WAL_DIR: Shared directory for WAL shipping (default: disabled)
+    /// - AGENTREPLAY_STANDBY: Start as a warm standby (default: false)
+    /// - AGENTREPLAY_SESSION_ANALYSIS: Analyze finished sessions in the background (default: false)
+    /// - AGENTREPLAY_SESSION_ANALYSIS_PROVIDER: LLM provider for session analysis (default: heuristics)
+    /// - AGENTREPLAY_GOVERNOR_EPSILON: Cosine distance under which spans are deduplicated (default: 0.1)
+    /// - AGENTREPLAY_DRIFT_DETECTION: Compare production inputs with golden datasets (default: true)
+    /// - AGENTREPLAY_GUARDRAILS: Apply configured guardrail rules on the chat proxy (default: true)
     pub fn from_env() -> Self {
         let mut config = Self::default();
 
@@ -239,6 +1873,18 @@ impl ServerConfig {
             }
         }
 
+        if let Ok(delay) = std::env::var("AGENTREPLAY_SHUTDOWN_DRAIN_DELAY") {
+            if let Ok(val) = delay.parse() {
+                config.server.shutdown_drain_delay_secs = val;
+            }
+        }
+
+        if let Ok(grace) = std::env::var("AGENTREPLAY_SHUTDOWN_GRACE") {
+            if let Ok(val) = grace.parse() {
+                config.server.shutdown_grace_secs = val;
+            }
+        }
+
         if let Ok(cors) = std::env::var("AGENTREPLAY_ENABLE_CORS") {
             config.server.enable_cors = cors.parse().unwrap_or(true);
         }
@@ -256,6 +1902,16 @@ impl ServerConfig {
             config.storage.use_project_storage = use_projects.parse().unwrap_or(false);
         }
 
+        if let Ok(policy) = std::env::var("AGENTREPLAY_LOCK_CONFLICT") {
+            match policy.to_lowercase().as_str() {
+                "fail" => config.storage.on_lock_conflict = LockConflictPolicy::Fail,
+                "read_only" | "readonly" => {
+                    config.storage.on_lock_conflict = LockConflictPolicy::ReadOnly
+                }
+                other => tracing::warn!("Unknown AGENTREPLAY_LOCK_CONFLICT '{}', ignoring", other),
+            }
+        }
+
         // Auth configuration
         if let Ok(enabled) = std::env::var("AGENTREPLAY_AUTH_ENABLED") {
             config.auth.enabled = enabled.parse().unwrap_or(false);
@@ -269,6 +1925,177 @@ impl ServerConfig {
             config.auth.api_keys = keys.split(',').map(String::from).collect();
         }
 
+        if let Ok(strict) = std::env::var("AGENTREPLAY_STRICT_TENANT_ISOLATION") {
+            config.auth.strict_tenant_isolation = strict.parse().unwrap_or(false);
+        }
+
+        if let (Ok(issuer), Ok(audience)) = (
+            std::env::var("AGENTREPLAY_OIDC_ISSUER"),
+            std::env::var("AGENTREPLAY_OIDC_AUDIENCE"),
+        ) {
+            config.auth.oidc = Some(OidcConfig {
+                issuer,
+                audience,
+                jwks_uri: None,
+                jwks_refresh_minutes: default_oidc_jwks_refresh_minutes(),
+                tenant_claim: default_oidc_tenant_claim(),
+                tenant_map: HashMap::new(),
+                default_tenant_id: None,
+                project_claim: None,
+                roles_claim: default_oidc_roles_claim(),
+                role_map: HashMap::new(),
+                default_role: default_oidc_role(),
+            });
+        }
+
+        // Ingestion admission
+        if let Ok(policy) = std::env::var("AGENTREPLAY_OVERLOAD_POLICY") {
+            match policy.to_lowercase().as_str() {
+                "reject" => config.ingestion.overload_policy = OverloadPolicy::Reject,
+                "sample" => config.ingestion.overload_policy = OverloadPolicy::Sample,
+                other => tracing::warn!("Unknown AGENTREPLAY_OVERLOAD_POLICY '{}', ignoring", other),
+            }
+        }
+
+        if let Ok(secs) = std::env::var("AGENTREPLAY_DEDUP_WINDOW_SECS") {
+            if let Ok(secs) = secs.parse() {
+                config.ingestion.dedup_window_secs = secs;
+            }
+        }
+
+        if let Ok(spec) = std::env::var("AGENTREPLAY_INGEST_PIPELINE") {
+            match pipeline::parse_pipeline(&spec) {
+                Ok(stages) => config.ingestion.pipeline = stages,
+                Err(e) => tracing::warn!("Invalid AGENTREPLAY_INGEST_PIPELINE, ignoring: {}", e),
+            }
+        }
+
+        // Cluster configuration
+        if let Ok(role) = std::env::var("AGENTREPLAY_NODE_ROLE") {
+            match role.to_lowercase().as_str() {
+                "standalone" => config.cluster.role = NodeRole::Standalone,
+                "writer" => config.cluster.role = NodeRole::Writer,
+                "replica" => config.cluster.role = NodeRole::Replica,
+                other => tracing::warn!("Unknown AGENTREPLAY_NODE_ROLE '{}', ignoring", other),
+            }
+        }
+
+        if let Ok(node_id) = std::env::var("AGENTREPLAY_NODE_ID") {
+            config.cluster.node_id = node_id;
+        }
+
+        if let Ok(shared_dir) = std::env::var("AGENTREPLAY_SHARED_DIR") {
+            config.cluster.shared_dir = Some(PathBuf::from(shared_dir));
+        }
+
+        // WAL shipping configuration
+        if let Ok(wal_dir) = std::env::var("AGENTREPLAY_WAL_DIR") {
+            config.replication.wal_dir = Some(PathBuf::from(wal_dir));
+        }
+
+        if let Ok(standby) = std::env::var("AGENTREPLAY_STANDBY") {
+            config.replication.standby = standby.parse().unwrap_or(false);
+        }
+
+        // Archive tier configuration
+        if let Ok(archive_dir) = std::env::var("AGENTREPLAY_ARCHIVE_DIR") {
+            config.archive.dir = Some(PathBuf::from(archive_dir));
+        }
+
+        if let Ok(days) = std::env::var("AGENTREPLAY_ARCHIVE_AFTER_DAYS") {
+            if let Ok(days) = days.parse() {
+                config.archive.archive_after_days = days;
+            }
+        }
+
+        // Session analysis configuration
+        if let Ok(enabled) = std::env::var("AGENTREPLAY_SESSION_ANALYSIS") {
+            config.session_analysis.enabled = enabled.parse().unwrap_or(false);
+        }
+
+        if let Ok(provider) = std::env::var("AGENTREPLAY_SESSION_ANALYSIS_PROVIDER") {
+            config.session_analysis.llm_provider = Some(provider);
+        }
+
+        if let Ok(epsilon) = std::env::var("AGENTREPLAY_GOVERNOR_EPSILON") {
+            if let Ok(epsilon) = epsilon.parse() {
+                config.governor.epsilon = epsilon;
+            }
+        }
+
+        if let Ok(enabled) = std::env::var("AGENTREPLAY_DRIFT_DETECTION") {
+            config.drift.enabled = enabled.parse().unwrap_or(true);
+        }
+
+        if let Ok(enabled) = std::env::var("AGENTREPLAY_GUARDRAILS") {
+            config.guardrails.enabled = enabled.parse().unwrap_or(true);
+        }
+
+        if let Ok(project_id) = std::env::var("AGENTREPLAY_SIMULATION_PROJECT_ID") {
+            if let Ok(project_id) = project_id.parse() {
+                config.simulation.project_id = project_id;
+            }
+        }
+
+        if let Ok(level) = std::env::var("AGENTREPLAY_HYDRATION_DEFAULT_LEVEL") {
+            if let Some(level) = HydrationLevel::parse(&level) {
+                config.hydration.default_level = level;
+            }
+        }
+
+        // Volume alerts
+        if let Ok(enabled) = std::env::var("AGENTREPLAY_VOLUME_ALERTS") {
+            config.volume_alerts.enabled = enabled.parse().unwrap_or(true);
+        }
+
+        // Per-client route rate limits
+        if let Ok(enabled) = std::env::var("AGENTREPLAY_RATE_LIMITS") {
+            config.rate_limits.enabled = enabled.parse().unwrap_or(false);
+        }
+
+        // Background job workers
+        if let Ok(workers) = std::env::var("AGENTREPLAY_JOB_WORKERS") {
+            if let Ok(workers) = workers.parse() {
+                config.jobs.workers = workers;
+            }
+        }
+
+        // Scheduled reports
+        if let Ok(enabled) = std::env::var("AGENTREPLAY_REPORTS") {
+            config.reports.enabled = enabled.parse().unwrap_or(true);
+        }
+
+        if let (Ok(host), Ok(from)) = (
+            std::env::var("AGENTREPLAY_SMTP_HOST"),
+            std::env::var("AGENTREPLAY_SMTP_FROM"),
+        ) {
+            config.reports.smtp = Some(SmtpConfig {
+                host,
+                port: std::env::var("AGENTREPLAY_SMTP_PORT")
+                    .ok()
+                    .and_then(|port| port.parse().ok())
+                    .unwrap_or_else(default_smtp_port),
+                username: std::env::var("AGENTREPLAY_SMTP_USERNAME").ok(),
+                password: std::env::var("AGENTREPLAY_SMTP_PASSWORD").ok(),
+                from,
+                starttls: default_smtp_starttls(),
+            });
+        }
+
+        // Conversation correlation attributes (comma-separated)
+        if let Ok(attributes) = std::env::var("AGENTREPLAY_CONVERSATION_ATTRIBUTES") {
+            config.conversations.correlation_attributes = attributes
+                .split(',')
+                .map(str::trim)
+                .filter(|attr| !attr.is_empty())
+                .map(String::from)
+                .collect();
+        }
+
+        if let Ok(master_key) = std::env::var("AGENTREPLAY_VAULT_KEY") {
+            config.vault.master_key = Some(master_key);
+        }
+
         // LLM configuration
         if let Ok(key) = std::env::var("OPENAI_API_KEY") {
             config.llm.openai_api_key = Some(key);
@@ -294,7 +2121,9 @@ impl ServerConfig {
         let mut config = if let Some(path) = config_file {
             if path.exists() {
                 tracing::info!("Loading configuration from file: {:?}", path);
-                Self::from_file(&path)?
+                let mut config = Self::from_file(&path)?;
+                config.config_file = Some(path);
+                config
             } else {
                 tracing::warn!("Config file not found: {:?}, using defaults", path);
                 Self::default()
@@ -323,6 +2152,9 @@ impl ServerConfig {
         if std::env::var("AGENTREPLAY_USE_PROJECT_STORAGE").is_ok() {
             config.storage.use_project_storage = env_config.storage.use_project_storage;
         }
+        if std::env::var("AGENTREPLAY_LOCK_CONFLICT").is_ok() {
+            config.storage.on_lock_conflict = env_config.storage.on_lock_conflict;
+        }
         if std::env::var("AGENTREPLAY_AUTH_ENABLED").is_ok() {
             config.auth.enabled = env_config.auth.enabled;
         }
@@ -332,6 +2164,51 @@ impl ServerConfig {
         if std::env::var("AGENTREPLAY_API_KEYS").is_ok() {
             config.auth.api_keys = env_config.auth.api_keys;
         }
+        if std::env::var("AGENTREPLAY_STRICT_TENANT_ISOLATION").is_ok() {
+            config.auth.strict_tenant_isolation = env_config.auth.strict_tenant_isolation;
+        }
+        if env_config.auth.oidc.is_some() {
+            config.auth.oidc = env_config.auth.oidc;
+        }
+        if std::env::var("AGENTREPLAY_OVERLOAD_POLICY").is_ok() {
+            config.ingestion.overload_policy = env_config.ingestion.overload_policy;
+        }
+        if std::env::var("AGENTREPLAY_DEDUP_WINDOW_SECS").is_ok() {
+            config.ingestion.dedup_window_secs = env_config.ingestion.dedup_window_secs;
+        }
+        if std::env::var("AGENTREPLAY_INGEST_PIPELINE").is_ok() {
+            config.ingestion.pipeline = env_config.ingestion.pipeline;
+        }
+        if std::env::var("AGENTREPLAY_NODE_ROLE").is_ok() {
+            config.cluster.role = env_config.cluster.role;
+        }
+        if std::env::var("AGENTREPLAY_NODE_ID").is_ok() {
+            config.cluster.node_id = env_config.cluster.node_id;
+        }
+        if std::env::var("AGENTREPLAY_SHARED_DIR").is_ok() {
+            config.cluster.shared_dir = env_config.cluster.shared_dir;
+        }
+        if std::env::var("AGENTREPLAY_WAL_DIR").is_ok() {
+            config.replication.wal_dir = env_config.replication.wal_dir;
+        }
+        if std::env::var("AGENTREPLAY_STANDBY").is_ok() {
+            config.replication.standby = env_config.replication.standby;
+        }
+        if std::env::var("AGENTREPLAY_ARCHIVE_DIR").is_ok() {
+            config.archive.dir = env_config.archive.dir;
+        }
+        if std::env::var("AGENTREPLAY_ARCHIVE_AFTER_DAYS").is_ok() {
+            config.archive.archive_after_days = env_config.archive.archive_after_days;
+        }
+        if std::env::var("AGENTREPLAY_SESSION_ANALYSIS").is_ok() {
+            config.session_analysis.enabled = env_config.session_analysis.enabled;
+        }
+        if std::env::var("AGENTREPLAY_SESSION_ANALYSIS_PROVIDER").is_ok() {
+            config.session_analysis.llm_provider = env_config.session_analysis.llm_provider;
+        }
+        if std::env::var("AGENTREPLAY_VAULT_KEY").is_ok() {
+            config.vault.master_key = env_config.vault.master_key;
+        }
 
         config
     }
@@ -341,14 +2218,299 @@ impl ServerConfig {
         Ok(self.server.listen_addr.parse()?)
     }
 
+    /// Configuration summary for diagnostic bundles, with secrets redacted
+    pub fn diagnostic_summary(&self) -> serde_json::Value {
+        let mut summary = serde_json::to_value(self).unwrap_or(serde_json::Value::Null);
+
+        if let Some(auth) = summary.get_mut("auth") {
+            auth["jwt_secret"] = serde_json::json!(self.auth.jwt_secret.as_ref().map(|_| "<redacted>"));
+            auth["api_keys"] = serde_json::json!(format!("<{} redacted>", self.auth.api_keys.len()));
+        }
+        if let Some(vault) = summary.get_mut("vault") {
+            vault["master_key"] =
+                serde_json::json!(self.vault.master_key.as_ref().map(|_| "<redacted>"));
+        }
+        if let Some(llm) = summary.get_mut("llm") {
+            for key in ["openai_api_key", "anthropic_api_key", "deepseek_api_key"] {
+                if !llm[key].is_null() {
+                    llm[key] = serde_json::json!("<redacted>");
+                }
+            }
+        }
+
+        summary
+    }
+
     /// Validate configuration
     pub fn validate(&self) -> Result<()> {
         // Validate socket address
         self.socket_addr()?;
 
+        if let Some(level) = &self.server.log_level {
+            tracing_subscriber::EnvFilter::try_new(level)
+                .map_err(|e| anyhow::anyhow!("server.log_level {:?}: {}", level, e))?;
+        }
+
+        // Validate ingestion admission
+        if !(0.0..=1.0).contains(&self.ingestion.queue_high_watermark) {
+            anyhow::bail!(
+                "ingestion.queue_high_watermark must be between 0.0 and 1.0, got {}",
+                self.ingestion.queue_high_watermark
+            );
+        }
+        pipeline::validate_pipeline(&self.ingestion.pipeline)
+            .map_err(|e| anyhow::anyhow!("ingestion.pipeline: {}", e))?;
+        if self.ingestion.overload_policy == OverloadPolicy::Sample
+            && !self.ingestion.pipeline.contains(&PipelineStage::Sample)
+        {
+            anyhow::bail!(
+                "ingestion.overload_policy = \"sample\" requires the 'sample' stage in ingestion.pipeline"
+            );
+        }
+
+        // Validate cluster configuration
+        if self.cluster.role != NodeRole::Standalone && self.cluster.shared_dir.is_none() {
+            anyhow::bail!(
+                "cluster.role = {:?} requires cluster.shared_dir (or AGENTREPLAY_SHARED_DIR)",
+                self.cluster.role
+            );
+        }
+        if self.cluster.lease_ttl_secs == 0 || self.cluster.sync_interval_secs == 0 {
+            anyhow::bail!("cluster.lease_ttl_secs and cluster.sync_interval_secs must be positive");
+        }
+
+        // Validate WAL shipping configuration
+        if self.replication.standby && self.replication.wal_dir.is_none() {
+            anyhow::bail!(
+                "replication.standby requires replication.wal_dir (or AGENTREPLAY_WAL_DIR)"
+            );
+        }
+        if self.replication.standby && self.cluster.role == NodeRole::Writer {
+            anyhow::bail!("A standby cannot also be the cluster writer");
+        }
+        // Only the main database ships and applies WAL; per-project
+        // databases would silently never reach the standby
+        if self.replication.wal_dir.is_some() && self.storage.use_project_storage {
+            anyhow::bail!("replication.wal_dir is not supported with per-project storage");
+        }
+        if self.replication.ship_interval_ms == 0 || self.replication.apply_interval_ms == 0 {
+            anyhow::bail!(
+                "replication.ship_interval_ms and replication.apply_interval_ms must be positive"
+            );
+        }
+
+        // Validate archive configuration
+        if self.archive.dir.is_some() && self.storage.use_project_storage {
+            anyhow::bail!("archive.dir is not supported with per-project storage");
+        }
+        if self.archive.interval_hours == 0 || self.archive.max_concurrent_rehydrations == 0 {
+            anyhow::bail!(
+                "archive.interval_hours and archive.max_concurrent_rehydrations must be positive"
+            );
+        }
+
+        // Validate session analysis configuration
+        if self.session_analysis.idle_minutes == 0 || self.session_analysis.interval_minutes == 0 {
+            anyhow::bail!(
+                "session_analysis.idle_minutes and session_analysis.interval_minutes must be positive"
+            );
+        }
+
+        // Validate semantic governor configuration
+        if !(self.governor.epsilon > 0.0 && self.governor.epsilon < 1.0) {
+            anyhow::bail!("governor.epsilon must be in (0, 1)");
+        }
+
+        // Validate drift detection configuration
+        let drift = &self.drift;
+        if drift.interval_minutes == 0 || drift.window_hours == 0 || drift.sample_size == 0 {
+            anyhow::bail!(
+                "drift.interval_minutes, drift.window_hours and drift.sample_size must be positive"
+            );
+        }
+        if !(0.0..=1.0).contains(&drift.coverage_similarity) {
+            anyhow::bail!("drift.coverage_similarity must be in [0, 1]");
+        }
+
+        // Validate guardrail rules
+        GuardrailEngine::new(&self.guardrails).map_err(|e| anyhow::anyhow!("guardrails: {}", e))?;
+
+        // Validate shadow rules
+        ShadowTraffic::new(&self.shadow).map_err(|e| anyhow::anyhow!("shadow: {}", e))?;
+
+        // Validate LLM failover settings
+        Failover::new(&self.llm.failover).map_err(|e| anyhow::anyhow!("llm.failover: {}", e))?;
+
+        // Validate response cache settings
+        ResponseCache::new(&self.response_cache)
+            .map_err(|e| anyhow::anyhow!("response_cache: {}", e))?;
+
+        // Validate simulation configuration
+        let simulation = &self.simulation;
+        if simulation.max_trials == 0 || simulation.concurrency == 0 || simulation.timeout_secs == 0
+        {
+            anyhow::bail!(
+                "simulation.max_trials, simulation.concurrency and simulation.timeout_secs must be positive"
+            );
+        }
+
+        // Validate hydration limits: more privileged roles may not get less
+        let hydration = &self.hydration;
+        if hydration.viewer_max > hydration.member_max || hydration.member_max > hydration.admin_max
+        {
+            anyhow::bail!(
+                "hydration limits must not decrease with privilege (viewer_max <= member_max <= admin_max)"
+            );
+        }
+
+        if let Some(oidc) = &self.auth.oidc {
+            let local = oidc.issuer.starts_with("http://localhost")
+                || oidc.issuer.starts_with("http://127.0.0.1");
+            if !oidc.issuer.starts_with("https://") && !local {
+                anyhow::bail!("auth.oidc.issuer must be an https URL");
+            }
+            if oidc.audience.trim().is_empty() {
+                anyhow::bail!("auth.oidc.audience must not be empty");
+            }
+            if oidc.jwks_refresh_minutes == 0 {
+                anyhow::bail!("auth.oidc.jwks_refresh_minutes must be positive");
+            }
+        }
+
+        // Validate route rate limit budgets
+        let limits = &self.rate_limits;
+        for (class, budget) in [
+            ("ingestion", limits.ingestion),
+            ("query", limits.query),
+            ("llm", limits.llm),
+        ] {
+            if budget.requests_per_minute == 0 || budget.burst == 0 {
+                anyhow::bail!(
+                    "rate_limits.{} requests_per_minute and burst must be positive",
+                    class
+                );
+            }
+        }
+        if limits.max_clients == 0 {
+            anyhow::bail!("rate_limits.max_clients must be positive");
+        }
+
+        if self.jobs.workers == 0 {
+            anyhow::bail!("jobs.workers must be positive");
+        }
+
+        let mut quota_projects: Vec<u16> =
+            self.quotas.projects.iter().map(|p| p.project_id).collect();
+        quota_projects.sort_unstable();
+        if let Some(pair) = quota_projects.windows(2).find(|pair| pair[0] == pair[1]) {
+            anyhow::bail!("quotas.projects lists project {} twice", pair[0]);
+        }
+
+        // Validate report scheduling and mail settings
+        if self.reports.check_interval_minutes == 0 {
+            anyhow::bail!("reports.check_interval_minutes must be positive");
+        }
+        if let Some(smtp) = &self.reports.smtp {
+            if smtp.host.trim().is_empty() {
+                anyhow::bail!("reports.smtp.host must not be empty");
+            }
+            if smtp.from.parse::<lettre::message::Mailbox>().is_err() {
+                anyhow::bail!("reports.smtp.from is not a valid email address");
+            }
+            if smtp.username.is_some() != smtp.password.is_some() {
+                anyhow::bail!(
+                    "reports.smtp.username and reports.smtp.password must be set together"
+                );
+            }
+        }
+
+        // Validate volume alert configuration
+        let alerts = &self.volume_alerts;
+        if alerts.window_minutes == 0 || alerts.check_interval_minutes == 0 {
+            anyhow::bail!(
+                "volume_alerts.window_minutes and volume_alerts.check_interval_minutes must be positive"
+            );
+        }
+        if alerts.baseline_hours * 60 <= alerts.window_minutes {
+            anyhow::bail!("volume_alerts.baseline_hours must span more than window_minutes");
+        }
+        if alerts.spike_factor <= 1.0 || !(0.0..1.0).contains(&alerts.drop_factor) {
+            anyhow::bail!(
+                "volume_alerts.spike_factor must exceed 1 and volume_alerts.drop_factor be in [0, 1)"
+            );
+        }
+
+        // Validate vault configuration
+        if let Some(master_key) = &self.vault.master_key {
+            crate::vault::decode_master_key(master_key)
+                .context("vault.master_key (or AGENTREPLAY_VAULT_KEY) is invalid")?;
+        }
+
+        // Validate autoscaling signal configuration
+        let scaling = &self.scaling;
+        if scaling.max_concurrent_queries == 0 || scaling.query_wait_window_secs == 0 {
+            anyhow::bail!(
+                "scaling.max_concurrent_queries and scaling.query_wait_window_secs must be positive"
+            );
+        }
+        let utilization = scaling.target_ingestion_utilization;
+        if utilization <= 0.0 || utilization > 1.0 {
+            anyhow::bail!("scaling.target_ingestion_utilization must be in (0, 1]");
+        }
+        if scaling.target_wal_backlog_bytes == 0
+            || scaling.target_eval_backlog == 0
+            || scaling.target_query_wait_ms <= 0.0
+        {
+            anyhow::bail!("scaling targets must be positive");
+        }
+
         // Validate auth configuration
-        if self.auth.enabled && self.auth.jwt_secret.is_none() && self.auth.api_keys.is_empty() {
-            anyhow::bail!("Authentication enabled but no JWT secret or API keys configured");
+        if self.auth.enabled
+            && self.auth.jwt_secret.is_none()
+            && self.auth.api_keys.is_empty()
+            && self.auth.oidc.is_none()
+            && !self
+                .storage
+                .data_dir
+                .join(crate::auth::API_TOKENS_FILE)
+                .exists()
+        {
+            anyhow::bail!(
+                "Authentication enabled but no JWT secret, API keys, OIDC provider or issued tokens configured"
+            );
+        }
+        if self.auth.strict_tenant_isolation && !self.auth.enabled {
+            anyhow::bail!(
+                "auth.strict_tenant_isolation requires auth.enabled, which supplies the tenant"
+            );
+        }
+        if self.access_policies.enabled {
+            if !self.auth.enabled {
+                anyhow::bail!(
+                    "access_policies requires auth.enabled; without it every caller is an admin"
+                );
+            }
+            let mut names = std::collections::HashSet::new();
+            for policy in &self.access_policies.policies {
+                if policy.name.is_empty() || !names.insert(policy.name.as_str()) {
+                    anyhow::bail!("access policy names must be unique and non-empty");
+                }
+                if policy.attributes.values().any(Vec::is_empty) {
+                    anyhow::bail!(
+                        "access policy '{}' has an attribute without allowed values",
+                        policy.name
+                    );
+                }
+            }
+        }
+
+        if self.self_tracing.enabled {
+            if !(0.0..=1.0).contains(&self.self_tracing.sample_rate) {
+                anyhow::bail!("self_tracing.sample_rate must be between 0.0 and 1.0");
+            }
+            if self.self_tracing.max_spans_per_second == 0 {
+                anyhow::bail!("self_tracing.max_spans_per_second must be greater than 0");
+            }
         }
 
         // Validate data directory is writable
@@ -371,6 +2533,314 @@ mod tests {
         assert!(!config.auth.enabled);
     }
 
+    #[test]
+    fn test_diagnostic_summary_redacts_secrets() {
+        let mut config = ServerConfig::default();
+        config.auth.jwt_secret = Some("super-secret".to_string());
+        config.auth.api_keys = vec!["key:1".to_string()];
+        config.llm.openai_api_key = Some("sk-test".to_string());
+        config.vault.master_key = Some("bWFzdGVyLWtleQ==".to_string());
+
+        let summary = config.diagnostic_summary().to_string();
+        assert!(!summary.contains("super-secret"));
+        assert!(!summary.contains("key:1"));
+        assert!(!summary.contains("sk-test"));
+        assert!(!summary.contains("bWFzdGVyLWtleQ=="));
+        assert!(summary.contains("127.0.0.1:47100"));
+    }
+
+    #[test]
+    fn test_cluster_role_requires_shared_dir() {
+        let mut config = ServerConfig::default();
+        config.storage.data_dir = std::env::temp_dir();
+        config.cluster.role = NodeRole::Replica;
+        assert!(config.validate().is_err());
+
+        config.cluster.shared_dir = Some(std::env::temp_dir());
+        assert!(config.validate().is_ok());
+    }
+
+    #[test]
+    fn test_standby_requires_wal_dir() {
+        let mut config = ServerConfig::default();
+        config.storage.data_dir = std::env::temp_dir();
+        config.replication.standby = true;
+        assert!(config.validate().is_err());
+
+        config.replication.wal_dir = Some(std::env::temp_dir());
+        assert!(config.validate().is_ok());
+
+        config.cluster.role = NodeRole::Writer;
+        config.cluster.shared_dir = Some(std::env::temp_dir());
+        assert!(config.validate().is_err());
+    }
+
+    #[test]
+    fn test_wal_shipping_requires_single_database() {
+        let mut config = ServerConfig::default();
+        config.storage.data_dir = std::env::temp_dir();
+        config.replication.wal_dir = Some(std::env::temp_dir());
+        assert!(config.validate().is_ok());
+
+        config.storage.use_project_storage = true;
+        assert!(config.validate().is_err());
+    }
+
+    #[test]
+    fn test_archive_requires_single_database() {
+        let mut config = ServerConfig::default();
+        config.storage.data_dir = std::env::temp_dir();
+        config.archive.dir = Some(std::env::temp_dir());
+        assert!(config.validate().is_ok());
+
+        config.storage.use_project_storage = true;
+        assert!(config.validate().is_err());
+    }
+
+    #[test]
+    fn test_scaling_validation() {
+        let mut config = ServerConfig::default();
+        config.storage.data_dir = std::env::temp_dir();
+        assert!(config.validate().is_ok());
+
+        config.scaling.target_ingestion_utilization = 1.5;
+        assert!(config.validate().is_err());
+        config.scaling.target_ingestion_utilization = 0.8;
+        config.scaling.max_concurrent_queries = 0;
+        assert!(config.validate().is_err());
+    }
+
+    #[test]
+    fn test_governor_validation() {
+        let mut config = ServerConfig::default();
+        config.storage.data_dir = std::env::temp_dir();
+        config.governor.epsilon = 0.0;
+        assert!(config.validate().is_err());
+        config.governor.epsilon = 0.25;
+        assert!(config.validate().is_ok());
+    }
+
+    #[test]
+    fn test_guardrails_validation() {
+        let mut config = ServerConfig::default();
+        config.storage.data_dir = std::env::temp_dir();
+        config.guardrails = toml::from_str(
+            r#"
+            [[rules]]
+            name = "banned"
+            stage = "output"
+            type = "regex"
+            pattern = "(unclosed"
+            action = "block"
+            "#,
+        )
+        .unwrap();
+        assert!(config.validate().is_err());
+
+        config.guardrails.enabled = false;
+        assert!(config.validate().is_ok());
+    }
+
+    #[test]
+    fn test_quota_config() {
+        let mut config = ServerConfig::default();
+        config.storage.data_dir = std::env::temp_dir();
+        config.quotas = toml::from_str(
+            r#"
+            enabled = true
+
+            [per_key]
+            requests_per_day = 100
+
+            [[projects]]
+            project_id = 7
+            tokens_per_month = 5000
+            "#,
+        )
+        .unwrap();
+        assert_eq!(config.quotas.per_key.requests_per_day, Some(100));
+        assert_eq!(config.quotas.per_key.tokens_per_month, None);
+        assert_eq!(
+            config.quotas.project(7).and_then(|l| l.tokens_per_month),
+            Some(5000)
+        );
+        assert!(config.quotas.project(8).is_none());
+        assert!(config.validate().is_ok());
+
+        let duplicate = config.quotas.projects[0].clone();
+        config.quotas.projects.push(duplicate);
+        assert!(config.validate().is_err());
+    }
+
+    #[test]
+    fn test_simulation_validation() {
+        let mut config = ServerConfig::default();
+        config.storage.data_dir = std::env::temp_dir();
+        config.simulation = toml::from_str("project_id = 4242").unwrap();
+        assert_eq!(config.simulation.project_id, 4242);
+        assert_eq!(config.simulation.max_trials, 20);
+        assert!(config.validate().is_ok());
+
+        config.simulation.concurrency = 0;
+        assert!(config.validate().is_err());
+    }
+
+    #[test]
+    fn test_hydration_validation() {
+        let mut config = ServerConfig::default();
+        config.storage.data_dir = std::env::temp_dir();
+        config.hydration = toml::from_str("viewer_max = \"metadata\"").unwrap();
+        assert_eq!(
+            config.hydration.max_level(Role::Viewer),
+            HydrationLevel::Metadata
+        );
+        assert_eq!(
+            config.hydration.max_level(Role::Admin),
+            HydrationLevel::Full
+        );
+        assert_eq!(config.hydration.sensitive_role, Role::Admin);
+        assert!(config.validate().is_ok());
+
+        config.hydration.member_max = HydrationLevel::Full;
+        config.hydration.admin_max = HydrationLevel::Preview;
+        assert!(config.validate().is_err());
+    }
+
+    #[test]
+    fn test_reports_validation() {
+        let mut config = ServerConfig::default();
+        config.storage.data_dir = std::env::temp_dir();
+        assert!(config.reports.enabled);
+        assert!(config.validate().is_ok());
+
+        config.reports = toml::from_str(
+            "[smtp]\nhost = \"smtp.example.com\"\nfrom = \"AgentReplay <reports@example.com>\"",
+        )
+        .unwrap();
+        let smtp = config.reports.smtp.clone().unwrap();
+        assert_eq!(smtp.port, 587);
+        assert!(smtp.starttls);
+        assert!(config.validate().is_ok());
+
+        config.reports.smtp.as_mut().unwrap().username = Some("reports".to_string());
+        assert!(config.validate().is_err());
+
+        config.reports.smtp.as_mut().unwrap().password = Some("secret".to_string());
+        config.reports.smtp.as_mut().unwrap().from = "not an address".to_string();
+        assert!(config.validate().is_err());
+
+        config.reports.smtp = None;
+        config.reports.check_interval_minutes = 0;
+        assert!(config.validate().is_err());
+    }
+
+    #[test]
+    fn test_oidc_validation() {
+        let mut config = ServerConfig::default();
+        config.storage.data_dir = std::env::temp_dir();
+        config.auth.enabled = true;
+        config.auth.oidc = Some(
+            toml::from_str(
+                "issuer = \"https://login.example.com\"\naudience = \"agentreplay\"\n\
+                 [role_map]\nagentreplay-admins = \"admin\"",
+            )
+            .unwrap(),
+        );
+        let oidc = config.auth.oidc.clone().unwrap();
+        assert_eq!(oidc.tenant_claim, "tenant_id");
+        assert_eq!(oidc.default_role, Role::Viewer);
+        assert_eq!(oidc.role_map["agentreplay-admins"], Role::Admin);
+        assert!(config.validate().is_ok());
+
+        config.auth.oidc.as_mut().unwrap().issuer = "http://login.example.com".to_string();
+        assert!(config.validate().is_err());
+
+        config.auth.oidc.as_mut().unwrap().issuer = "https://login.example.com".to_string();
+        config.auth.oidc.as_mut().unwrap().audience = " ".to_string();
+        assert!(config.validate().is_err());
+    }
+
+    #[test]
+    fn test_strict_tenant_isolation_requires_auth() {
+        let mut config = ServerConfig::default();
+        config.auth.strict_tenant_isolation = true;
+        assert!(config.validate().is_err());
+
+        config.storage.data_dir = std::env::temp_dir();
+        config.auth.enabled = true;
+        config.auth.api_keys = vec!["secret:1".to_string()];
+        assert!(config.validate().is_ok());
+    }
+
+    #[test]
+    fn test_mcp_tenant() {
+        assert_eq!(ServerConfig::default().mcp.tenant(), 1);
+        let mcp: McpConfig = toml::from_str("tenant_id = 4").unwrap();
+        assert_eq!(mcp.tenant(), 4);
+    }
+
+    #[test]
+    fn test_access_policy_validation() {
+        let mut config = ServerConfig::default();
+        config.storage.data_dir = std::env::temp_dir();
+        config.auth.enabled = true;
+        config.auth.api_keys = vec!["secret:1".to_string()];
+        config.access_policies = toml::from_str(
+            r#"
+            enabled = true
+
+            [[policies]]
+            name = "search"
+            roles = ["viewer"]
+            attributes = { team = ["search", "ranking"] }
+            deny_pii = true
+            "#,
+        )
+        .unwrap();
+        assert_eq!(config.access_policies.exempt_role, Role::Admin);
+        assert_eq!(config.access_policies.policies[0].roles, vec![Role::Viewer]);
+        assert!(config.validate().is_ok());
+
+        let duplicate = config.access_policies.policies[0].clone();
+        config.access_policies.policies.push(duplicate);
+        assert!(config.validate().is_err());
+
+        config.access_policies.policies.pop();
+        config.auth.enabled = false;
+        assert!(config.validate().is_err());
+    }
+
+    #[test]
+    fn test_self_tracing_config() {
+        let mut config = ServerConfig::default();
+        config.storage.data_dir = std::env::temp_dir();
+        config.self_tracing = toml::from_str("enabled = true\nsample_rate = 0.5").unwrap();
+        assert_eq!(config.self_tracing.tenant_id, 1);
+        assert_eq!(config.self_tracing.targets, vec!["agentreplay_server"]);
+        assert!(config.validate().is_ok());
+
+        config.self_tracing.sample_rate = 1.5;
+        assert!(config.validate().is_err());
+        config.self_tracing.sample_rate = 1.0;
+        config.self_tracing.max_spans_per_second = 0;
+        assert!(config.validate().is_err());
+    }
+
+    #[test]
+    fn test_ingest_pipeline_validation() {
+        let mut config = ServerConfig::default();
+        config.storage.data_dir = std::env::temp_dir();
+        assert!(config.validate().is_ok());
+
+        config.ingestion.pipeline = pipeline::parse_pipeline("sanitize, store, governor").unwrap();
+        assert!(config.validate().is_err());
+
+        config.ingestion.pipeline = pipeline::parse_pipeline("sanitize, governor, store").unwrap();
+        assert!(config.validate().is_ok());
+        config.ingestion.overload_policy = OverloadPolicy::Sample;
+        assert!(config.validate().is_err());
+    }
+
     #[test]
     fn test_from_env() {
         std::env::set_var("AGENTREPLAY_HTTP_ADDR", "0.0.0.0:8080");
@@ -0,0 +1,459 @@
+// Copyright 2025 AgentReplay (https://github.com/agentreplay)
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program. If not, see <https://www.gnu.org/licenses/>.
+
+//! Configuration hot-reload
+//!
+//! On SIGHUP or `POST /api/v1/admin/config/reload` the server re-reads the
+//! config file it was started with (plus `AGENTREPLAY_*` environment
+//! overrides), validates it and compares it with the settings last loaded.
+//! Changes to [`RELOADABLE`] settings take effect at once; every other
+//! change is reported and logged as needing a restart. An invalid file is
+//! rejected as a whole, with the diff of what it would have changed, and
+//! the running settings stay as they were.
+//!
+//! CLI flags are applied on top of the file at startup only, so they are
+//! neither re-applied nor reported as changes.
+
+use std::path::PathBuf;
+
+use axum::{
+    extract::{Query, State},
+    http::StatusCode,
+    response::{IntoResponse, Response},
+    Extension, Json,
+};
+use parking_lot::{Mutex, RwLock};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use tracing::{info, warn};
+
+use crate::api::{ApiError, AppState};
+use crate::auth::{AuthContext, Role};
+use crate::config::ServerConfig;
+
+/// Log filter used when neither `RUST_LOG` nor `server.log_level` is set
+pub const DEFAULT_LOG_FILTER: &str = "agentreplay_server=info,tower_http=info";
+
+/// Settings applied without a restart
+pub const RELOADABLE: &[&str] = &[
+    "server.cors_origins",
+    "server.log_level",
+    "ingestion.overload_policy",
+    "ingestion.queue_high_watermark",
+    "archive.archive_after_days",
+];
+
+/// Field names whose values are never echoed in diffs or logs
+const SECRET_MARKERS: &[&str] = &["secret", "password", "key", "token"];
+
+type LogFilterSetter = Box<dyn Fn(&str) -> Result<(), String> + Send + Sync>;
+
+/// One setting that differs between the running and the reloaded config
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct ConfigChange {
+    /// Dotted path, e.g. `ingestion.overload_policy`
+    pub path: String,
+    pub old: Value,
+    pub new: Value,
+    /// Whether the change takes effect without a restart
+    pub reloadable: bool,
+}
+
+/// Outcome of a reload
+#[derive(Debug, Clone, Serialize)]
+pub struct ReloadReport {
+    pub file: Option<PathBuf>,
+    pub dry_run: bool,
+    /// Reloadable changes took effect
+    pub applied: bool,
+    /// Why the file was rejected
+    pub error: Option<String>,
+    pub changes: Vec<ConfigChange>,
+}
+
+/// Re-reads the config file and applies the reloadable settings
+pub struct ConfigReloader {
+    path: Option<PathBuf>,
+    /// File and environment settings as last loaded, with reloadable
+    /// changes folded in; held for the whole reload so reloads don't race
+    baseline: Mutex<ServerConfig>,
+    cors_origins: RwLock<Vec<String>>,
+    set_log_filter: Option<LogFilterSetter>,
+}
+
+impl Default for ConfigReloader {
+    fn default() -> Self {
+        Self::new(&ServerConfig::default())
+    }
+}
+
+impl ConfigReloader {
+    /// Reloader for a server started with `config`
+    ///
+    /// The baseline is read from the file again so CLI overrides in
+    /// `config` don't show up as changes on the first reload.
+    pub fn new(config: &ServerConfig) -> Self {
+        let baseline = config
+            .config_file
+            .as_ref()
+            .and_then(|path| ServerConfig::load(Some(path.clone())).ok())
+            .unwrap_or_else(|| config.clone());
+        Self {
+            path: config.config_file.clone(),
+            baseline: Mutex::new(baseline),
+            cors_origins: RwLock::new(config.server.cors_origins.clone()),
+            set_log_filter: None,
+        }
+    }
+
+    /// Apply `server.log_level` changes through `set`, which receives
+    /// `EnvFilter` directives
+    pub fn with_log_filter(
+        mut self,
+        set: impl Fn(&str) -> Result<(), String> + Send + Sync + 'static,
+    ) -> Self {
+        self.set_log_filter = Some(Box::new(set));
+        self
+    }
+
+    /// Whether CORS requests from `origin` are allowed (any origin while
+    /// `server.cors_origins` is empty)
+    pub fn allows_origin(&self, origin: &str) -> bool {
+        let origins = self.cors_origins.read();
+        origins.is_empty() || origins.iter().any(|o| o == origin)
+    }
+
+    /// Re-read the config file and, unless `dry_run`, apply reloadable changes
+    pub fn reload(&self, state: &AppState, dry_run: bool) -> ReloadReport {
+        let mut report = ReloadReport {
+            file: self.path.clone(),
+            dry_run,
+            applied: false,
+            error: None,
+            changes: Vec::new(),
+        };
+
+        let mut baseline = self.baseline.lock();
+        let next = match self.read() {
+            Ok(next) => next,
+            Err(e) => {
+                warn!("Config reload failed: {}", e);
+                report.error = Some(e);
+                return report;
+            }
+        };
+        report.changes = diff(&baseline, &next);
+
+        if let Err(e) = next.validate() {
+            warn!("Config reload rejected: {:#}", e);
+            log_changes(&report.changes, "would change");
+            report.error = Some(format!("{:#}", e));
+            return report;
+        }
+        if dry_run {
+            return report;
+        }
+
+        if let Err(e) = self.apply(state, &baseline, &next) {
+            warn!("Config reload failed: {}", e);
+            report.error = Some(e);
+            return report;
+        }
+        fold_reloadable(&mut baseline, &next);
+        report.applied = true;
+
+        if report.changes.is_empty() {
+            info!("Config reloaded from {:?}: no changes", self.path);
+        } else {
+            info!("Config reloaded from {:?}", self.path);
+            log_changes(&report.changes, "changed");
+        }
+        report
+    }
+
+    fn read(&self) -> Result<ServerConfig, String> {
+        let Some(path) = &self.path else {
+            return Err("the server was not started with a config file".to_string());
+        };
+        if !path.exists() {
+            return Err(format!("config file {:?} not found", path));
+        }
+        ServerConfig::load(Some(path.clone()))
+            .map_err(|e| format!("failed to read {:?}: {:#}", path, e))
+    }
+
+    /// Push reloadable settings that differ from `old` to the live components
+    fn apply(
+        &self,
+        state: &AppState,
+        old: &ServerConfig,
+        new: &ServerConfig,
+    ) -> Result<(), String> {
+        // First, as the only step that can fail
+        if old.server.log_level != new.server.log_level {
+            if let Some(set) = &self.set_log_filter {
+                let directives = new.server.log_level.as_deref();
+                set(directives.unwrap_or(DEFAULT_LOG_FILTER))
+                    .map_err(|e| format!("server.log_level: {}", e))?;
+            }
+        }
+        if old.server.cors_origins != new.server.cors_origins {
+            *self.cors_origins.write() = new.server.cors_origins.clone();
+        }
+        if old.ingestion.overload_policy != new.ingestion.overload_policy
+            || old.ingestion.queue_high_watermark != new.ingestion.queue_high_watermark
+        {
+            state
+                .ingestion_admission
+                .reconfigure(new.ingestion.queue_admission_config());
+        }
+        if old.archive.archive_after_days != new.archive.archive_after_days {
+            if let Some(archive) = &state.archive {
+                archive.set_archive_after_days(new.archive.archive_after_days);
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Copy the reloadable settings of `next` into `baseline`, leaving the
+/// restart-only ones to be reported again on the next reload
+fn fold_reloadable(baseline: &mut ServerConfig, next: &ServerConfig) {
+    baseline.server.cors_origins = next.server.cors_origins.clone();
+    baseline.server.log_level = next.server.log_level.clone();
+    baseline.ingestion.overload_policy = next.ingestion.overload_policy;
+    baseline.ingestion.queue_high_watermark = next.ingestion.queue_high_watermark;
+    baseline.archive.archive_after_days = next.archive.archive_after_days;
+}
+
+fn log_changes(changes: &[ConfigChange], verb: &str) {
+    for change in changes {
+        if change.reloadable {
+            info!(
+                "  {} {}: {} -> {}",
+                verb, change.path, change.old, change.new
+            );
+        } else {
+            warn!(
+                "  {} {}: {} -> {} (restart required)",
+                verb, change.path, change.old, change.new
+            );
+        }
+    }
+}
+
+/// Settings that differ between `old` and `new`, secrets redacted
+pub fn diff(old: &ServerConfig, new: &ServerConfig) -> Vec<ConfigChange> {
+    let old = serde_json::to_value(old).unwrap_or(Value::Null);
+    let new = serde_json::to_value(new).unwrap_or(Value::Null);
+    let mut changes = Vec::new();
+    diff_values("", &old, &new, &mut changes);
+    changes
+}
+
+fn diff_values(path: &str, old: &Value, new: &Value, changes: &mut Vec<ConfigChange>) {
+    if let (Value::Object(old_map), Value::Object(new_map)) = (old, new) {
+        let mut keys: Vec<&String> = old_map.keys().chain(new_map.keys()).collect();
+        keys.sort();
+        keys.dedup();
+        for key in keys {
+            let child = if path.is_empty() {
+                key.clone()
+            } else {
+                format!("{}.{}", path, key)
+            };
+            diff_values(
+                &child,
+                old_map.get(key).unwrap_or(&Value::Null),
+                new_map.get(key).unwrap_or(&Value::Null),
+                changes,
+            );
+        }
+        return;
+    }
+
+    if old == new {
+        return;
+    }
+    let secret = path
+        .rsplit('.')
+        .next()
+        .is_some_and(|field| SECRET_MARKERS.iter().any(|m| field.contains(m)));
+    let shown = |value: &Value| {
+        if secret && !value.is_null() {
+            Value::String("<redacted>".to_string())
+        } else {
+            value.clone()
+        }
+    };
+    changes.push(ConfigChange {
+        path: path.to_string(),
+        old: shown(old),
+        new: shown(new),
+        reloadable: RELOADABLE.contains(&path),
+    });
+}
+
+/// Start reloading the config on SIGHUP
+#[cfg(unix)]
+pub fn spawn_sighup_handler(state: AppState) {
+    use tokio::signal::unix::{signal, SignalKind};
+
+    let mut hangup = match signal(SignalKind::hangup()) {
+        Ok(hangup) => hangup,
+        Err(e) => {
+            warn!(
+                "Failed to listen for SIGHUP, config reload only via API: {}",
+                e
+            );
+            return;
+        }
+    };
+    tokio::spawn(async move {
+        while hangup.recv().await.is_some() {
+            info!("Received SIGHUP, reloading configuration");
+            let reloader = state.config_reloader.clone();
+            reloader.reload(&state, false);
+        }
+    });
+}
+
+#[cfg(not(unix))]
+pub fn spawn_sighup_handler(_state: AppState) {}
+
+#[derive(Debug, Default, Deserialize)]
+pub struct ReloadQuery {
+    /// Validate and diff without applying
+    #[serde(default)]
+    pub dry_run: bool,
+}
+
+/// POST /api/v1/admin/config/reload
+///
+/// Returns the diff; 422 when the file is unreadable or invalid.
+pub async fn reload_config(
+    State(state): State<AppState>,
+    Extension(auth): Extension<AuthContext>,
+    Query(query): Query<ReloadQuery>,
+) -> Result<Response, ApiError> {
+    auth.require_role(Role::Admin)?;
+
+    let reloader = state.config_reloader.clone();
+    let report = reloader.reload(&state, query.dry_run);
+    let status = if report.error.is_some() {
+        StatusCode::UNPROCESSABLE_ENTITY
+    } else {
+        StatusCode::OK
+    };
+    Ok((status, Json(report)).into_response())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::admission::OverloadPolicy;
+
+    const BASE_TOML: &str = "[server]\n[storage]\n[auth]\n";
+
+    fn reloader_for(toml: &str) -> (tempfile::TempDir, ConfigReloader) {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("agentreplay.toml");
+        std::fs::write(&path, toml).unwrap();
+        let config = ServerConfig::load(Some(path)).unwrap();
+        (dir, ConfigReloader::new(&config))
+    }
+
+    #[test]
+    fn test_diff_classifies_and_redacts() {
+        let old = ServerConfig::default();
+        let mut new = old.clone();
+        new.server.cors_origins = vec!["https://app.example.com".to_string()];
+        new.ingestion.overload_policy = OverloadPolicy::Sample;
+        new.server.listen_addr = "0.0.0.0:47100".to_string();
+        new.auth.jwt_secret = Some("hunter2".to_string());
+
+        let changes = diff(&old, &new);
+        let paths: Vec<&str> = changes.iter().map(|c| c.path.as_str()).collect();
+        assert_eq!(
+            paths,
+            [
+                "auth.jwt_secret",
+                "ingestion.overload_policy",
+                "server.cors_origins",
+                "server.listen_addr"
+            ]
+        );
+
+        let reloadable: Vec<bool> = changes.iter().map(|c| c.reloadable).collect();
+        assert_eq!(reloadable, [false, true, true, false]);
+        assert_eq!(changes[0].old, Value::Null);
+        assert_eq!(changes[0].new, Value::String("<redacted>".to_string()));
+        assert_eq!(changes[1].new, Value::String("sample".to_string()));
+
+        assert!(diff(&old, &old.clone()).is_empty());
+    }
+
+    #[test]
+    fn test_read_and_fold() {
+        let (dir, reloader) = reloader_for(BASE_TOML);
+        assert!(reloader.allows_origin("https://anywhere.example.com"));
+
+        std::fs::write(
+            dir.path().join("agentreplay.toml"),
+            "[server]\ncors_origins = [\"https://app.example.com\"]\nmax_connections = 10\n\
+             [storage]\n[auth]\n[archive]\narchive_after_days = 30\n",
+        )
+        .unwrap();
+        let next = reloader.read().unwrap();
+        next.validate().unwrap();
+
+        let mut baseline = reloader.baseline.lock();
+        let paths: Vec<String> = diff(&baseline, &next).into_iter().map(|c| c.path).collect();
+        assert_eq!(
+            paths,
+            [
+                "archive.archive_after_days",
+                "server.cors_origins",
+                "server.max_connections"
+            ]
+        );
+
+        // The restart-only change is still pending after applying the rest
+        fold_reloadable(&mut baseline, &next);
+        let pending = diff(&baseline, &next);
+        assert_eq!(pending.len(), 1);
+        assert_eq!(pending[0].path, "server.max_connections");
+    }
+
+    #[test]
+    fn test_invalid_config_is_rejected() {
+        let (dir, reloader) = reloader_for(BASE_TOML);
+        std::fs::write(
+            dir.path().join("agentreplay.toml"),
+            "[server]\n[storage]\n[auth]\n[ingestion]\nqueue_high_watermark = 1.5\n",
+        )
+        .unwrap();
+        let next = reloader.read().unwrap();
+        assert!(next.validate().is_err());
+        let changes = diff(&reloader.baseline.lock(), &next);
+        assert_eq!(changes.len(), 1);
+        assert!(changes[0].reloadable);
+
+        std::fs::write(dir.path().join("agentreplay.toml"), "[server\n").unwrap();
+        assert!(reloader.read().is_err());
+
+        assert!(ConfigReloader::default().read().is_err());
+    }
+}
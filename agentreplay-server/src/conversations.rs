@@ -0,0 +1,475 @@
+// Copyright 2025 AgentReplay (https://github.com/agentreplay)
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program. If not, see <https://www.gnu.org/licenses/>.
+
+//! Conversation threading across traces
+//!
+//! Chat apps often emit one trace per request, sometimes from processes that
+//! do not share a session_id. Spans carrying a correlation attribute (see
+//! [`ConversationConfig`]) link their session - or, without a session, their
+//! trace - to the conversation named by the attribute's value.
+//! `GET /api/v1/conversations/:id` merges everything linked to a
+//! conversation into one timeline. An ID with no links that parses as a
+//! number is looked up as a session_id, so every session is also a
+//! conversation of its own.
+
+use std::collections::{HashMap, HashSet};
+
+use agentreplay_core::clock::now_us;
+use agentreplay_core::{AgentFlowEdge, Result, SpanType};
+use agentreplay_query::Agentreplay;
+use agentreplay_storage::ConversationLink;
+use axum::{
+    extract::{Path, Query, State},
+    Json,
+};
+use parking_lot::Mutex;
+use serde::{Deserialize, Serialize};
+
+use crate::access_policy::AccessFilter;
+use crate::api::query::ApiError;
+use crate::api::AppState;
+use crate::auth::AuthContext;
+use crate::config::ConversationConfig;
+use crate::scaling::run_query;
+use crate::session_analysis::{collect_turns, SessionTurn};
+
+/// Span attributes naming the end user, checked in order
+const USER_ID_ATTRIBUTES: &[&str] = &["user.id", "enduser.id"];
+
+/// Maximum accepted length of a conversation ID
+const MAX_CONVERSATION_ID_LEN: usize = 256;
+
+/// Session links remembered to skip rewriting them for every span
+const MAX_SEEN_LINKS: usize = 100_000;
+
+/// Parent hops followed to find the root of a linked trace
+const MAX_TRACE_DEPTH: usize = 64;
+
+/// Links sessions and traces to conversations as spans are ingested
+pub struct ConversationLinker {
+    attributes: Vec<String>,
+    seen: Mutex<HashSet<(u64, u16, u64, String)>>,
+}
+
+impl ConversationLinker {
+    pub fn new(config: ConversationConfig) -> Self {
+        Self {
+            attributes: config.correlation_attributes,
+            seen: Mutex::new(HashSet::new()),
+        }
+    }
+
+    /// The conversation a span belongs to, from the first correlation
+    /// attribute it carries
+    pub fn conversation_id<'a>(&self, attributes: &'a HashMap<String, String>) -> Option<&'a str> {
+        self.attributes
+            .iter()
+            .filter_map(|attr| attributes.get(attr))
+            .map(|value| value.trim())
+            .find(|value| !value.is_empty() && value.len() <= MAX_CONVERSATION_ID_LEN)
+    }
+
+    /// Link a stored span's session or trace to its conversation, if any
+    pub fn record(
+        &self,
+        db: &Agentreplay,
+        edge: &AgentFlowEdge,
+        attributes: &HashMap<String, String>,
+    ) -> Result<()> {
+        let Some(conversation_id) = self.conversation_id(attributes) else {
+            return Ok(());
+        };
+        let seen_key = (
+            edge.tenant_id,
+            edge.project_id,
+            edge.session_id,
+            conversation_id.to_string(),
+        );
+        if edge.session_id != 0 && self.seen.lock().contains(&seen_key) {
+            return Ok(());
+        }
+
+        db.put_conversation_link(&ConversationLink {
+            conversation_id: conversation_id.to_string(),
+            tenant_id: edge.tenant_id,
+            project_id: edge.project_id,
+            session_id: edge.session_id,
+            edge_id: edge.edge_id,
+            user_id: USER_ID_ATTRIBUTES
+                .iter()
+                .find_map(|attr| attributes.get(*attr))
+                .cloned(),
+            linked_at: now_us(),
+        })?;
+
+        if edge.session_id != 0 {
+            let mut seen = self.seen.lock();
+            if seen.len() >= MAX_SEEN_LINKS {
+                seen.clear();
+            }
+            seen.insert(seen_key);
+        }
+        Ok(())
+    }
+}
+
+/// One span of a conversation timeline
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct ConversationSpan {
+    pub span_id: String,
+    pub trace_id: String,
+    pub session_id: u64,
+    pub project_id: u16,
+    pub timestamp_us: u64,
+    pub duration_us: u32,
+    pub span_type: u32,
+    pub token_count: u32,
+}
+
+/// One trace (request) of a conversation
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct ConversationTrace {
+    pub trace_id: String,
+    pub session_id: u64,
+    pub started_at: u64,
+    pub ended_at: u64,
+    pub span_count: usize,
+    pub total_tokens: u64,
+    pub has_error: bool,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ConversationResponse {
+    pub conversation_id: String,
+    pub sessions: Vec<u64>,
+    pub user_ids: Vec<String>,
+    pub started_at: u64,
+    pub last_activity_at: u64,
+    pub span_count: usize,
+    pub total_tokens: u64,
+    /// Traces in start order
+    pub traces: Vec<ConversationTrace>,
+    /// User/assistant exchanges across all traces, in order
+    pub turns: Vec<SessionTurn>,
+    /// Every span, in start order
+    pub timeline: Vec<ConversationSpan>,
+}
+
+/// Root of each edge's trace, following parents present in `edges`
+fn trace_roots(edges: &[AgentFlowEdge]) -> HashMap<u128, u128> {
+    let parents: HashMap<u128, u128> = edges
+        .iter()
+        .map(|edge| (edge.edge_id, edge.causal_parent))
+        .collect();
+    parents
+        .keys()
+        .map(|&edge_id| {
+            let mut root = edge_id;
+            for _ in 0..MAX_TRACE_DEPTH {
+                match parents.get(&root) {
+                    Some(&parent) if parent != 0 && parents.contains_key(&parent) => root = parent,
+                    _ => break,
+                }
+            }
+            (edge_id, root)
+        })
+        .collect()
+}
+
+/// Merge the spans of a conversation into traces and a timeline, both in
+/// start order. `edges` must not contain duplicates.
+pub fn merge_timeline(
+    mut edges: Vec<AgentFlowEdge>,
+) -> (Vec<ConversationTrace>, Vec<ConversationSpan>) {
+    edges.sort_by_key(|edge| (edge.timestamp_us, edge.edge_id));
+    let roots = trace_roots(&edges);
+
+    let mut traces: Vec<ConversationTrace> = Vec::new();
+    let mut trace_index: HashMap<u128, usize> = HashMap::new();
+    let mut timeline = Vec::with_capacity(edges.len());
+    for edge in &edges {
+        let root = roots.get(&edge.edge_id).copied().unwrap_or(edge.edge_id);
+        let index = *trace_index.entry(root).or_insert_with(|| {
+            traces.push(ConversationTrace {
+                trace_id: format!("{:#x}", root),
+                session_id: edge.session_id,
+                started_at: edge.timestamp_us,
+                ended_at: edge.timestamp_us,
+                span_count: 0,
+                total_tokens: 0,
+                has_error: false,
+            });
+            traces.len() - 1
+        });
+        let trace = &mut traces[index];
+        trace.ended_at = trace
+            .ended_at
+            .max(edge.timestamp_us + edge.duration_us as u64);
+        trace.span_count += 1;
+        trace.total_tokens += edge.token_count as u64;
+        trace.has_error |= edge.get_span_type() == SpanType::Error;
+
+        timeline.push(ConversationSpan {
+            span_id: format!("{:#x}", edge.edge_id),
+            trace_id: format!("{:#x}", root),
+            session_id: edge.session_id,
+            project_id: edge.project_id,
+            timestamp_us: edge.timestamp_us,
+            duration_us: edge.duration_us,
+            span_type: edge.span_type,
+            token_count: edge.token_count,
+        });
+    }
+    (traces, timeline)
+}
+
+/// All spans of the trace containing `edge_id`
+fn trace_edges(db: &Agentreplay, edge_id: u128, tenant_id: u64) -> Result<Vec<AgentFlowEdge>> {
+    let Some(mut root) = db.get(edge_id)? else {
+        return Ok(Vec::new());
+    };
+    for _ in 0..MAX_TRACE_DEPTH {
+        if root.causal_parent == 0 {
+            break;
+        }
+        match db.get(root.causal_parent)? {
+            Some(parent) => root = parent,
+            None => break,
+        }
+    }
+    if root.tenant_id != tenant_id {
+        return Ok(Vec::new());
+    }
+    let mut edges = db.get_descendants_for_tenant(root.edge_id, tenant_id)?;
+    edges.push(root);
+    Ok(edges)
+}
+
+fn session_edges(db: &Agentreplay, session_id: u64, tenant_id: u64) -> Result<Vec<AgentFlowEdge>> {
+    let mut edges = db.get_session_edges_full(session_id)?;
+    edges.retain(|edge| edge.tenant_id == tenant_id);
+    Ok(edges)
+}
+
+/// Spans of one link the caller may read, or `None` if the link has spans
+/// but access policies hide all of them
+fn readable_link_spans(
+    mut found: Vec<AgentFlowEdge>,
+    access: &AccessFilter,
+    load: impl Fn(&AgentFlowEdge) -> Option<Vec<u8>>,
+) -> Option<Vec<AgentFlowEdge>> {
+    let linked = found.len();
+    access.retain_with(&mut found, load);
+    (linked == 0 || !found.is_empty()).then_some(found)
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ConversationParams {
+    /// Restrict to one project (per-project storage only)
+    pub project_id: Option<u16>,
+}
+
+/// GET /api/v1/conversations/:conversation_id
+///
+/// Merged timeline of every session and trace linked to a conversation.
+pub async fn get_conversation(
+    State(state): State<AppState>,
+    Path(conversation_id): Path<String>,
+    Query(params): Query<ConversationParams>,
+    axum::Extension(auth): axum::Extension<AuthContext>,
+    axum::Extension(access): axum::Extension<AccessFilter>,
+) -> std::result::Result<Json<ConversationResponse>, ApiError> {
+    let shards = crate::api::metrics::project_shards(&state, params.project_id)?;
+    let tenant_id = auth.tenant_id;
+    let id = conversation_id.clone();
+    let (edges, mut turns, sessions, user_ids) = run_query(&state, move || {
+        let internal = |e: agentreplay_core::AgentreplayError| ApiError::Internal(e.to_string());
+        let mut linked = Vec::new();
+        for db in &shards {
+            let links = db
+                .list_conversation_links(tenant_id, &id)
+                .map_err(internal)?;
+            linked.push(links);
+        }
+        if linked.iter().all(Vec::is_empty) {
+            // Not a correlated conversation: fall back to a single session
+            if let Ok(session_id) = id.parse::<u64>() {
+                for links in &mut linked {
+                    links.push(ConversationLink {
+                        conversation_id: id.clone(),
+                        tenant_id,
+                        project_id: 0,
+                        session_id,
+                        edge_id: 0,
+                        user_id: None,
+                        linked_at: 0,
+                    });
+                }
+            }
+        }
+
+        let mut edges = Vec::new();
+        let mut turns = Vec::new();
+        let mut seen = HashSet::new();
+        let mut sessions = HashSet::new();
+        let mut user_ids = HashSet::new();
+        for (db, links) in shards.iter().zip(linked) {
+            let mut shard_edges = Vec::new();
+            for link in links {
+                let found = if link.session_id != 0 {
+                    session_edges(db, link.session_id, tenant_id)
+                } else {
+                    trace_edges(db, link.edge_id, tenant_id)
+                }
+                .map_err(internal)?;
+                let Some(found) = readable_link_spans(found, &access, |edge| {
+                    db.get_payload(edge.edge_id).ok().flatten()
+                }) else {
+                    continue;
+                };
+                if !found.is_empty() && link.session_id != 0 {
+                    sessions.insert(link.session_id);
+                }
+                user_ids.extend(link.user_id);
+                shard_edges.extend(found.into_iter().filter(|e| seen.insert(e.edge_id)));
+            }
+            turns.extend(collect_turns(db, &shard_edges));
+            edges.extend(shard_edges);
+        }
+        Ok::<_, ApiError>((edges, turns, sessions, user_ids))
+    })
+    .await
+    .map_err(|e| ApiError::Internal(format!("Conversation task panicked: {}", e)))??;
+
+    if edges.is_empty() {
+        return Err(ApiError::NotFound(format!(
+            "Conversation {} not found",
+            conversation_id
+        )));
+    }
+
+    let (traces, timeline) = merge_timeline(edges);
+    turns.sort_by_key(|turn| turn.timestamp_us);
+    let mut sessions: Vec<u64> = sessions.into_iter().collect();
+    sessions.sort_unstable();
+    let mut user_ids: Vec<String> = user_ids.into_iter().collect();
+    user_ids.sort();
+
+    Ok(Json(ConversationResponse {
+        conversation_id,
+        sessions,
+        user_ids,
+        started_at: timeline.first().map(|s| s.timestamp_us).unwrap_or(0),
+        last_activity_at: traces.iter().map(|t| t.ended_at).max().unwrap_or(0),
+        span_count: timeline.len(),
+        total_tokens: traces.iter().map(|t| t.total_tokens).sum(),
+        traces,
+        turns,
+        timeline,
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn edge(edge_id: u128, parent: u128, session_id: u64, timestamp_us: u64) -> AgentFlowEdge {
+        AgentFlowEdge {
+            edge_id,
+            causal_parent: parent,
+            session_id,
+            timestamp_us,
+            duration_us: 100,
+            token_count: 10,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_conversation_id() {
+        let linker = ConversationLinker::new(ConversationConfig::default());
+        let mut attrs = HashMap::from([("conversation.id".to_string(), " chat-1 ".to_string())]);
+        assert_eq!(linker.conversation_id(&attrs), Some("chat-1"));
+
+        // Earlier attributes take precedence; blank values are skipped
+        attrs.insert("gen_ai.conversation.id".to_string(), "chat-2".to_string());
+        assert_eq!(linker.conversation_id(&attrs), Some("chat-2"));
+        attrs.insert("gen_ai.conversation.id".to_string(), "  ".to_string());
+        assert_eq!(linker.conversation_id(&attrs), Some("chat-1"));
+
+        let custom = ConversationLinker::new(ConversationConfig {
+            correlation_attributes: vec!["thread_id".to_string()],
+        });
+        assert_eq!(custom.conversation_id(&attrs), None);
+    }
+
+    #[test]
+    fn test_merge_timeline() {
+        // Two traces of session 1, one session-less trace in between
+        let edges = vec![
+            edge(0x11, 0x10, 1, 1_050),
+            edge(0x10, 0, 1, 1_000),
+            edge(0x20, 0, 0, 2_000),
+            edge(0x30, 0, 1, 3_000),
+            edge(0x31, 0x30, 1, 3_010),
+            edge(0x32, 0x31, 1, 3_020),
+        ];
+        let (traces, timeline) = merge_timeline(edges);
+
+        assert_eq!(timeline.len(), 6);
+        assert_eq!(timeline[0].span_id, "0x10");
+        assert_eq!(timeline[1].trace_id, "0x10");
+        assert_eq!(timeline[5].trace_id, "0x30");
+
+        assert_eq!(traces.len(), 3);
+        assert_eq!(traces[0].span_count, 2);
+        assert_eq!(traces[0].ended_at, 1_150);
+        assert_eq!(traces[1].trace_id, "0x20");
+        assert_eq!(traces[1].session_id, 0);
+        assert_eq!(traces[2].span_count, 3);
+        assert_eq!(traces[2].total_tokens, 30);
+    }
+
+    #[test]
+    fn test_link_spans_respect_access_policies() {
+        let mut spans = vec![edge(0x10, 0, 1, 1_000), edge(0x11, 0x10, 1, 1_050)];
+        for span in &mut spans {
+            span.has_payload = 1;
+        }
+        let team_of = |edge: &AgentFlowEdge| {
+            let team = if edge.edge_id == 0x11 {
+                "search"
+            } else {
+                "ranking"
+            };
+            Some(format!(r#"{{"team":"{}"}}"#, team).into_bytes())
+        };
+
+        let all = readable_link_spans(spans.clone(), &AccessFilter::default(), team_of);
+        assert_eq!(all.unwrap().len(), 2);
+
+        let search = AccessFilter::for_teams(&["search"]);
+        let visible = readable_link_spans(spans.clone(), &search, team_of).unwrap();
+        assert_eq!(visible.len(), 1);
+        assert_eq!(visible[0].edge_id, 0x11);
+
+        // A link whose spans are all hidden is dropped, with its user ID
+        let infra = AccessFilter::for_teams(&["infra"]);
+        assert!(readable_link_spans(spans, &infra, team_of).is_none());
+        assert!(readable_link_spans(Vec::new(), &infra, team_of)
+            .unwrap()
+            .is_empty());
+    }
+}
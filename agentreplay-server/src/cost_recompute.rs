@@ -0,0 +1,456 @@
+// Copyright 2025 AgentReplay (https://github.com/agentreplay)
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program. If not, see <https://www.gnu.org/licenses/>.
+
+//! Recomputing stored span costs after a pricing correction
+//!
+//! Once a price is fixed (a custom override, a LiteLLM update), the costs
+//! already derived for past spans are wrong. A recompute walks the spans of
+//! a time range, prices their token usage with the registry price that was
+//! in effect at each span's timestamp (see
+//! [`ModelPricingRegistry::get_pricing_at`]) and stores the result as the
+//! span's `cost_usd`, which the cost endpoints prefer over pricing tokens
+//! themselves. The cost is also recorded with the metric rollups (see
+//! [`Agentreplay::put_edge_cost`]), so the dashboard counts it instead of
+//! pricing the span's tokens, and the in-memory rollups of the
+//! [`CostTracker`](crate::cost_tracker::CostTracker) are shifted by each
+//! span's difference. Spans are read from the project databases when the
+//! server keeps one per project.
+//!
+//! `POST /api/v1/analytics/cost/recompute` runs as a background job whose
+//! result is a [`RecomputeReport`] of before/after totals per model; a dry
+//! run only reports them.
+
+use std::collections::BTreeMap;
+
+use agentreplay_core::clock::now_secs;
+use agentreplay_core::{AgentFlowEdge, ModelPricingRegistry};
+use agentreplay_query::Agentreplay;
+use axum::{extract::State, http::StatusCode, Extension, Json};
+use rust_decimal::Decimal;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+
+use crate::api::metrics::project_shards;
+use crate::api::query::ApiError;
+use crate::api::AppState;
+use crate::auth::{AuthContext, Role};
+use crate::jobs::{Job, JobContext};
+use crate::otel_genai::{is_local_system, GenAIPayload, ModelPricing, COST_USD_KEY};
+
+/// Payload key recording when (Unix seconds) `cost_usd` was last recomputed
+pub const COST_RECOMPUTED_AT_KEY: &str = "cost_recomputed_at";
+
+/// Spans between progress updates
+const PROGRESS_EVERY: usize = 500;
+
+/// Spans to reprice
+#[derive(Debug, Clone, Deserialize)]
+pub struct RecomputeRequest {
+    /// Start of the range (microseconds, inclusive)
+    pub start_ts: u64,
+    /// End of the range (microseconds, exclusive)
+    pub end_ts: u64,
+    #[serde(default)]
+    pub project_id: Option<u16>,
+    /// Only spans of this model (the response model, else the requested one)
+    #[serde(default)]
+    pub model: Option<String>,
+    /// Report the deltas without storing anything
+    #[serde(default)]
+    pub dry_run: bool,
+}
+
+/// Cost change of one model's spans
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct ModelCostDelta {
+    pub model: String,
+    pub spans: u64,
+    pub before_usd: f64,
+    pub after_usd: f64,
+    pub delta_usd: f64,
+}
+
+/// Outcome of a recompute
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct RecomputeReport {
+    pub dry_run: bool,
+    pub spans_scanned: u64,
+    /// Spans priced with the registry
+    pub spans_repriced: u64,
+    /// Repriced spans whose cost changed
+    pub spans_changed: u64,
+    /// Spans without token usage or whose model has no price
+    pub spans_skipped: u64,
+    /// Spans whose change was applied to the in-memory cost rollups
+    pub rollups_adjusted: u64,
+    pub before_usd: f64,
+    pub after_usd: f64,
+    pub delta_usd: f64,
+    /// Ordered by model name
+    pub by_model: Vec<ModelCostDelta>,
+}
+
+impl RecomputeReport {
+    fn record(
+        by_model: &mut BTreeMap<String, ModelCostDelta>,
+        model: &str,
+        before: f64,
+        after: f64,
+    ) {
+        let entry = by_model
+            .entry(model.to_string())
+            .or_insert_with(|| ModelCostDelta {
+                model: model.to_string(),
+                ..Default::default()
+            });
+        entry.spans += 1;
+        entry.before_usd += before;
+        entry.after_usd += after;
+        entry.delta_usd = entry.after_usd - entry.before_usd;
+    }
+
+    fn finish(mut self, by_model: BTreeMap<String, ModelCostDelta>) -> Self {
+        self.by_model = by_model.into_values().collect();
+        self.before_usd = self.by_model.iter().map(|m| m.before_usd).sum();
+        self.after_usd = self.by_model.iter().map(|m| m.after_usd).sum();
+        self.delta_usd = self.after_usd - self.before_usd;
+        self
+    }
+}
+
+/// Model a span is priced as: the response model, else the requested one
+fn payload_model(payload: &GenAIPayload) -> Option<&str> {
+    payload
+        .response_model
+        .as_deref()
+        .or(payload.request_model.as_deref())
+}
+
+/// Cost the analytics endpoints currently report for a span
+fn current_cost(payload: &GenAIPayload, model: &str) -> f64 {
+    payload.stored_cost().unwrap_or_else(|| {
+        let system = payload.system.as_deref().unwrap_or("unknown");
+        payload.calculate_cost(&ModelPricing::for_model(system, model))
+    })
+}
+
+/// Cost of a span's token usage at the registry price in effect at `at`
+/// (Unix seconds)
+///
+/// `None` when the span reports no usage or the registry has no price for
+/// its model; local models are free.
+pub async fn reprice(
+    pricing: &ModelPricingRegistry,
+    payload: &GenAIPayload,
+    model: &str,
+    at: u64,
+) -> Option<f64> {
+    if payload.input_tokens.is_none() && payload.output_tokens.is_none() {
+        return None;
+    }
+    if payload.system.as_deref().is_some_and(is_local_system) {
+        return Some(0.0);
+    }
+    let price = pricing.get_pricing_at(model, at).await?;
+    Some(payload.calculate_cost(&ModelPricing::from(&price)))
+}
+
+/// Reprice the tenant's spans of `req`, storing the new costs unless it is
+/// a dry run
+pub async fn recompute_costs(
+    state: &AppState,
+    tenant_id: u64,
+    req: &RecomputeRequest,
+    ctx: &JobContext,
+) -> Result<RecomputeReport, String> {
+    let mut scans = Vec::new();
+    let shards = project_shards(state, req.project_id).map_err(|e| e.to_string())?;
+    for db in shards {
+        let edges = db
+            .query_filtered(req.start_ts, req.end_ts, Some(tenant_id), req.project_id)
+            .map_err(|e| format!("Failed to scan spans: {}", e))?;
+        scans.push((db, edges));
+    }
+    let count: usize = scans.iter().map(|(_, edges)| edges.len()).sum();
+    let total = count.max(1);
+
+    let mut report = RecomputeReport {
+        dry_run: req.dry_run,
+        ..Default::default()
+    };
+    let mut by_model = BTreeMap::new();
+    let recomputed_at = now_secs();
+
+    let spans: Vec<_> = scans
+        .iter()
+        .flat_map(|(db, edges)| edges.iter().map(move |edge| (db, edge)))
+        .collect();
+    for (i, (db, edge)) in spans.into_iter().enumerate() {
+        if ctx.is_cancelled() {
+            return Err("Cost recompute cancelled".to_string());
+        }
+        if i % PROGRESS_EVERY == 0 {
+            ctx.progress(
+                i as f64 / total as f64,
+                Some(format!("{} of {} spans", i, count)),
+            );
+        }
+        report.spans_scanned += 1;
+
+        match recompute_edge(state, db, edge, req, recomputed_at).await? {
+            EdgeOutcome::Skipped => report.spans_skipped += 1,
+            EdgeOutcome::Filtered => {}
+            EdgeOutcome::Repriced {
+                model,
+                before,
+                after,
+                rollup_adjusted,
+            } => {
+                report.spans_repriced += 1;
+                if (after - before).abs() > f64::EPSILON {
+                    report.spans_changed += 1;
+                }
+                if rollup_adjusted {
+                    report.rollups_adjusted += 1;
+                }
+                RecomputeReport::record(&mut by_model, &model, before, after);
+            }
+        }
+    }
+
+    Ok(report.finish(by_model))
+}
+
+enum EdgeOutcome {
+    Skipped,
+    /// Not the requested model
+    Filtered,
+    Repriced {
+        model: String,
+        before: f64,
+        after: f64,
+        rollup_adjusted: bool,
+    },
+}
+
+async fn recompute_edge(
+    state: &AppState,
+    db: &Agentreplay,
+    edge: &AgentFlowEdge,
+    req: &RecomputeRequest,
+    recomputed_at: u64,
+) -> Result<EdgeOutcome, String> {
+    let Some(bytes) = db.get_payload(edge.edge_id).ok().flatten() else {
+        return Ok(EdgeOutcome::Skipped);
+    };
+    // The raw object is rewritten so attributes GenAIPayload doesn't model
+    // keep their shape
+    let Ok(Value::Object(mut raw)) = serde_json::from_slice::<Value>(&bytes) else {
+        return Ok(EdgeOutcome::Skipped);
+    };
+    let Ok(payload) = serde_json::from_value::<GenAIPayload>(Value::Object(raw.clone())) else {
+        return Ok(EdgeOutcome::Skipped);
+    };
+    let Some(model) = payload_model(&payload) else {
+        return Ok(EdgeOutcome::Skipped);
+    };
+    if req.model.as_deref().is_some_and(|wanted| wanted != model) {
+        return Ok(EdgeOutcome::Filtered);
+    }
+
+    let at = edge.timestamp_us / 1_000_000;
+    let Some(after) = reprice(&state.pricing, &payload, model, at).await else {
+        return Ok(EdgeOutcome::Skipped);
+    };
+    let before = current_cost(&payload, model);
+    let model = model.to_string();
+    if req.dry_run {
+        return Ok(EdgeOutcome::Repriced {
+            model,
+            before,
+            after,
+            rollup_adjusted: false,
+        });
+    }
+
+    raw.insert(COST_USD_KEY.to_string(), json!(after));
+    raw.insert(COST_RECOMPUTED_AT_KEY.to_string(), json!(recomputed_at));
+    let bytes = serde_json::to_vec(&raw).map_err(|e| e.to_string())?;
+    db.put_payload(edge.edge_id, &bytes)
+        .map_err(|e| format!("Failed to store cost of span {:#x}: {}", edge.edge_id, e))?;
+    db.put_edge_cost(edge.edge_id, (after * 1_000_000.0).round() as u64)
+        .map_err(|e| format!("Failed to roll up cost of span {:#x}: {}", edge.edge_id, e))?;
+
+    // Rollups hold what ingest estimated, or the last recomputed cost
+    let tracked = if payload.additional.contains_key(COST_RECOMPUTED_AT_KEY) {
+        payload
+            .stored_cost()
+            .and_then(|cost| Decimal::try_from(cost).ok())
+            .unwrap_or_default()
+    } else {
+        state.cost_tracker.tracked_cost(edge)
+    };
+    let delta = Decimal::try_from(after).unwrap_or_default() - tracked;
+    let rollup_adjusted = state.cost_tracker.adjust_edge(edge, delta).await;
+
+    Ok(EdgeOutcome::Repriced {
+        model,
+        before,
+        after,
+        rollup_adjusted,
+    })
+}
+
+/// POST /api/v1/analytics/cost/recompute
+///
+/// Answers 202 with the job; its result is the [`RecomputeReport`].
+pub async fn start_recompute(
+    State(state): State<AppState>,
+    Extension(auth): Extension<AuthContext>,
+    Json(mut req): Json<RecomputeRequest>,
+) -> Result<(StatusCode, Json<Job>), ApiError> {
+    if req.start_ts >= req.end_ts {
+        return Err(ApiError::BadRequest(
+            "start_ts must be before end_ts".to_string(),
+        ));
+    }
+    if !req.dry_run && auth.role < Role::Member {
+        return Err(ApiError::Forbidden(format!(
+            "role '{}' may not recompute costs",
+            auth.role.as_str()
+        )));
+    }
+    if let Some(scope) = auth.project_id {
+        if req.project_id.is_some_and(|project| project != scope) {
+            return Err(ApiError::Forbidden(format!(
+                "token is scoped to project {}",
+                scope
+            )));
+        }
+        req.project_id = Some(scope);
+    }
+
+    let description = format!(
+        "{} costs from {} to {}",
+        if req.dry_run { "Preview" } else { "Recompute" },
+        req.start_ts,
+        req.end_ts
+    );
+    let tenant_id = auth.tenant_id;
+    let task_state = state.clone();
+    let job = state.jobs.submit(
+        "cost_recompute",
+        tenant_id,
+        description,
+        move |ctx| async move {
+            let report = recompute_costs(&task_state, tenant_id, &req, &ctx).await?;
+            serde_json::to_value(report)
+                .map(Some)
+                .map_err(|e| e.to_string())
+        },
+    );
+    Ok((StatusCode::ACCEPTED, Json(job)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use agentreplay_core::CustomPricingOverride;
+    use std::collections::HashMap;
+
+    fn usage(model: &str, input: &str, output: &str) -> GenAIPayload {
+        let attributes: HashMap<String, String> = [
+            ("gen_ai.system", "openai"),
+            ("gen_ai.request.model", model),
+            ("gen_ai.usage.input_tokens", input),
+            ("gen_ai.usage.output_tokens", output),
+        ]
+        .into_iter()
+        .map(|(k, v)| (k.to_string(), v.to_string()))
+        .collect();
+        GenAIPayload::from_attributes(&attributes)
+    }
+
+    fn price(model: &str, input: f64, output: f64, from: u64) -> CustomPricingOverride {
+        CustomPricingOverride {
+            model_id: model.to_string(),
+            input_cost_per_token: input,
+            output_cost_per_token: output,
+            max_tokens: None,
+            litellm_provider: None,
+            source: None,
+            effective_from: Some(from),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_reprice_uses_price_in_effect() {
+        let dir = tempfile::tempdir().unwrap();
+        let pricing = ModelPricingRegistry::new(dir.path());
+        pricing
+            .add_custom_override(price("acme-large", 0.000001, 0.000002, 1_000))
+            .await
+            .unwrap();
+        // Corrected price from t=2000 on
+        pricing
+            .add_custom_override(price("acme-large", 0.000002, 0.000004, 2_000))
+            .await
+            .unwrap();
+
+        let payload = usage("acme-large", "1000", "500");
+        let before = reprice(&pricing, &payload, "acme-large", 1_500).await;
+        let after = reprice(&pricing, &payload, "acme-large", 2_500).await;
+        assert!((before.unwrap() - 0.002).abs() < 1e-12);
+        assert!((after.unwrap() - 0.004).abs() < 1e-12);
+
+        // Unknown models and spans without usage are not repriced
+        assert_eq!(
+            reprice(&pricing, &payload, "unknown-model", 2_500).await,
+            None
+        );
+        let no_usage = GenAIPayload::from_attributes(&HashMap::new());
+        assert_eq!(
+            reprice(&pricing, &no_usage, "acme-large", 2_500).await,
+            None
+        );
+    }
+
+    #[test]
+    fn test_current_cost_prefers_stored_cost() {
+        let mut payload = usage("gpt-4o", "1000000", "0");
+        assert!((current_cost(&payload, "gpt-4o") - 2.5).abs() < 1e-9);
+        payload
+            .additional
+            .insert(COST_USD_KEY.to_string(), json!(1.75));
+        assert_eq!(current_cost(&payload, "gpt-4o"), 1.75);
+    }
+
+    #[test]
+    fn test_report_totals() {
+        let mut by_model = BTreeMap::new();
+        RecomputeReport::record(&mut by_model, "b", 1.0, 1.5);
+        RecomputeReport::record(&mut by_model, "a", 2.0, 1.0);
+        RecomputeReport::record(&mut by_model, "b", 0.5, 0.5);
+        let report = RecomputeReport::default().finish(by_model);
+
+        assert_eq!(report.by_model.len(), 2);
+        assert_eq!(report.by_model[0].model, "a");
+        assert_eq!(report.by_model[1].spans, 2);
+        assert!((report.by_model[1].delta_usd - 0.5).abs() < 1e-12);
+        assert!((report.before_usd - 3.5).abs() < 1e-12);
+        assert!((report.after_usd - 3.0).abs() < 1e-12);
+        assert!((report.delta_usd + 0.5).abs() < 1e-12);
+    }
+}
@@ -207,8 +207,73 @@ impl CostTracker {
         session_cost.last_activity = edge.timestamp_us;
     }
 
+    /// Shift the cost an edge contributed to every rollup by `delta`, e.g.
+    /// after the edge was repriced
+    ///
+    /// Rollups only hold edges ingested since this process started, so
+    /// edges from an hour the tenant has no costs for are left alone.
+    /// Returns whether the edge was adjusted.
+    pub async fn adjust_edge(&self, edge: &AgentFlowEdge, delta: Decimal) -> bool {
+        if edge.token_count == 0 || delta.is_zero() {
+            return false;
+        }
+
+        let mut state = self.state.write().await;
+
+        let hour_timestamp = (edge.timestamp_us / 3_600_000_000) * 3_600_000_000;
+        let Some(tenant_cost) = state.tenant_costs.get_mut(&edge.tenant_id) else {
+            return false;
+        };
+        let Some(hour) = tenant_cost
+            .hourly_costs
+            .iter_mut()
+            .find(|(ts, _)| *ts == hour_timestamp)
+        else {
+            return false;
+        };
+        hour.1 += delta;
+        tenant_cost.total_cost += delta;
+
+        if let Some(project_cost) = state
+            .project_costs
+            .get_mut(&(edge.tenant_id, edge.project_id))
+        {
+            project_cost.total_cost += delta;
+            *project_cost
+                .agent_breakdown
+                .entry(edge.agent_id)
+                .or_default() += delta;
+        }
+
+        if let Some(agent_cost) = state.agent_costs.get_mut(&edge.agent_id) {
+            agent_cost.total_cost += delta;
+            if agent_cost.trace_count > 0 {
+                agent_cost.avg_cost_per_trace =
+                    agent_cost.total_cost / Decimal::from(agent_cost.trace_count);
+            }
+            let span_type = format!("{:?}", edge.get_span_type());
+            *agent_cost.span_type_breakdown.entry(span_type).or_default() += delta;
+        }
+
+        if let Some(session_cost) = state.session_costs.get_mut(&edge.session_id) {
+            session_cost.total_cost += delta;
+        }
+
+        true
+    }
+
+    /// Cost `track_edge` records for an edge ingested without a model name
+    pub fn tracked_cost(&self, edge: &AgentFlowEdge) -> Decimal {
+        self.calculate_edge_cost(edge, None)
+    }
+
     /// Calculate cost for an edge using exact Decimal arithmetic
     fn calculate_edge_cost(&self, edge: &AgentFlowEdge, model_name: Option<&str>) -> Decimal {
+        self.estimate_cost(model_name, edge.token_count as u64)
+    }
+
+    /// Cost of `tokens` tokens of `model_name` under the configured pricing
+    pub fn estimate_cost(&self, model_name: Option<&str>, tokens: u64) -> Decimal {
         let (input_price, output_price) = if let Some(model) = model_name {
             self.pricing.model_overrides.get(model).copied().unwrap_or((
                 self.pricing.input_price_per_1k,
@@ -223,7 +288,7 @@ impl CostTracker {
 
         // For now, assume equal split between input/output
         // In production, track input_tokens and output_tokens separately
-        let tokens = Decimal::from(edge.token_count);
+        let tokens = Decimal::from(tokens);
         let two = dec!(2);
         let thousand = dec!(1000);
         ((tokens / two) * input_price / thousand) + ((tokens / two) * output_price / thousand)
@@ -358,4 +423,29 @@ mod tests {
         assert!(forecast.is_some());
         assert!(forecast.unwrap() > Decimal::ZERO);
     }
+
+    #[tokio::test]
+    async fn test_adjust_edge() {
+        let tracker = CostTracker::new();
+
+        let mut edge = AgentFlowEdge::new(1, 1, 100, 200, SpanType::Planning, 0);
+        edge.token_count = 1000;
+        edge.timestamp_us = 1_700_000_000_000_000;
+        tracker.track_edge(&edge, None).await;
+        let tracked = tracker.tracked_cost(&edge);
+        assert_eq!(tracker.get_summary().await.total_cost, tracked);
+
+        assert!(tracker.adjust_edge(&edge, dec!(0.5)).await);
+        let tenant_costs = tracker.get_tenant_costs(1).await.unwrap();
+        assert_eq!(tenant_costs.total_cost, tracked + dec!(0.5));
+        assert_eq!(tenant_costs.hourly_costs[0].1, tracked + dec!(0.5));
+        assert_eq!(tenant_costs.trace_count, 1);
+        let agent_costs = tracker.get_agent_costs(edge.agent_id).await.unwrap();
+        assert_eq!(agent_costs.avg_cost_per_trace, tracked + dec!(0.5));
+
+        // An edge from an hour this tracker never saw is not in its rollups
+        edge.timestamp_us -= 24 * 3_600_000_000;
+        assert!(!tracker.adjust_edge(&edge, dec!(0.5)).await);
+        assert_eq!(tracker.get_summary().await.total_cost, tracked + dec!(0.5));
+    }
 }
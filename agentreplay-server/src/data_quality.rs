@@ -0,0 +1,419 @@
+// Copyright 2025 AgentReplay (https://github.com/agentreplay)
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program. If not, see <https://www.gnu.org/licenses/>.
+
+//! Data quality monitor for instrumentation gaps
+//!
+//! Scans a window of spans (edges plus their GenAI payloads) and reports
+//! instrumentation problems per project, each with a rate, example span IDs
+//! and a concrete fix suggestion:
+//!
+//! - **missing_usage**: LLM spans without token usage (cost and token charts undercount)
+//! - **orphan_spans**: spans whose parent was never ingested (broken trace trees)
+//! - **clock_skew**: spans corrected at ingestion or starting before their parent
+//! - **unmapped_models**: models with no pricing entry (cost falls back to defaults)
+//! - **unknown_attributes**: attribute keys outside known conventions
+//!
+//! The rates are combined into a 0-100 data quality score.
+
+use crate::ingestion::ATTR_SKEW_CORRECTED;
+use crate::otel_genai::{GenAIPayload, ModelPricing};
+use agentreplay_core::AgentFlowEdge;
+use serde::Serialize;
+use std::collections::{BTreeMap, HashMap, HashSet};
+
+/// Example span IDs reported per issue
+const MAX_EXAMPLES: usize = 5;
+
+/// Unknown attribute keys reported per project
+const MAX_UNKNOWN_KEYS: usize = 10;
+
+/// Attribute prefixes from OpenTelemetry / OpenInference / AgentReplay conventions
+const KNOWN_ATTRIBUTE_PREFIXES: &[&str] = &[
+    "gen_ai.",
+    "llm.",
+    "agentreplay.",
+    "session.",
+    "user.",
+    "metadata.",
+    "service.",
+    "host.",
+    "deployment.",
+    "telemetry.",
+    "otel.",
+    "http.",
+    "url.",
+    "server.",
+    "client.",
+    "db.",
+    "rpc.",
+    "messaging.",
+    "exception.",
+    "error.",
+    "code.",
+    "process.",
+    "tool.",
+    "input.",
+    "output.",
+    "retrieval.",
+    "embedding.",
+    "conversation.",
+];
+
+/// Bare attribute keys understood by the ingestion API
+const KNOWN_ATTRIBUTE_KEYS: &[&str] = &[
+    "input",
+    "output",
+    "tenant_id",
+    "project_id",
+    "project",
+    "agent_id",
+    "agent",
+    "session_id",
+    "environment",
+    "service",
+    "host",
+    "model",
+    "tokens",
+    "token_count",
+    "model_name",
+];
+
+/// One category of instrumentation problem
+#[derive(Debug, Clone, Serialize)]
+pub struct DataQualityIssue {
+    pub issue: String,
+    /// Spans affected by this issue
+    pub affected: usize,
+    /// Spans the check applies to
+    pub checked: usize,
+    /// affected / checked (0.0 - 1.0)
+    pub rate: f64,
+    /// Example span IDs (hex)
+    pub examples: Vec<String>,
+    /// Issue-specific details (e.g. unmapped model names)
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub details: Vec<String>,
+    pub suggestion: String,
+}
+
+/// Data quality report for one project
+#[derive(Debug, Clone, Serialize)]
+pub struct ProjectDataQuality {
+    pub project_id: u16,
+    pub spans_analyzed: usize,
+    /// 0-100 (100 = no instrumentation gaps found)
+    pub score: u8,
+    /// Only issues with at least one affected span
+    pub issues: Vec<DataQualityIssue>,
+}
+
+/// Accumulator for a single check
+#[derive(Default)]
+struct Check {
+    affected: usize,
+    checked: usize,
+    examples: Vec<String>,
+}
+
+impl Check {
+    fn observe(&mut self, edge: &AgentFlowEdge, affected: bool) {
+        self.checked += 1;
+        if affected {
+            self.affected += 1;
+            if self.examples.len() < MAX_EXAMPLES {
+                self.examples.push(format!("{:#x}", edge.edge_id));
+            }
+        }
+    }
+
+    fn rate(&self) -> f64 {
+        if self.checked == 0 {
+            0.0
+        } else {
+            self.affected as f64 / self.checked as f64
+        }
+    }
+
+    fn into_issue(self, issue: &str, details: Vec<String>, suggestion: String) -> DataQualityIssue {
+        DataQualityIssue {
+            issue: issue.to_string(),
+            affected: self.affected,
+            checked: self.checked,
+            rate: self.rate(),
+            examples: self.examples,
+            details,
+            suggestion,
+        }
+    }
+}
+
+#[derive(Default)]
+struct ProjectChecks {
+    spans: usize,
+    missing_usage: Check,
+    orphans: Check,
+    clock_skew: Check,
+    unmapped_models: Check,
+    unknown_attributes: Check,
+    unmapped_model_names: BTreeMap<String, usize>,
+    unknown_keys: HashMap<String, usize>,
+}
+
+/// Analyze spans and build a report per project
+///
+/// `spans` are the edges in the analysis window with their decoded payloads.
+/// Parents outside the window (see [`missing_parents`]) are checked with
+/// `parent_exists` before a span is reported as an orphan.
+pub fn analyze(
+    spans: &[(AgentFlowEdge, Option<GenAIPayload>)],
+    parent_exists: impl Fn(u128) -> bool,
+) -> Vec<ProjectDataQuality> {
+    let by_id: HashMap<u128, &AgentFlowEdge> =
+        spans.iter().map(|(edge, _)| (edge.edge_id, edge)).collect();
+    let mut projects: BTreeMap<u16, ProjectChecks> = BTreeMap::new();
+
+    for (edge, payload) in spans {
+        let checks = projects.entry(edge.project_id).or_default();
+        checks.spans += 1;
+
+        // Parent linkage: orphans and parent/child clock skew
+        let parent = (edge.causal_parent != 0).then(|| by_id.get(&edge.causal_parent));
+        if let Some(parent) = parent {
+            let orphan = parent.is_none() && !parent_exists(edge.causal_parent);
+            checks.orphans.observe(edge, orphan);
+        }
+
+        let corrected = payload
+            .as_ref()
+            .is_some_and(|p| p.additional.contains_key(ATTR_SKEW_CORRECTED));
+        let starts_before_parent = parent
+            .flatten()
+            .is_some_and(|parent| edge.timestamp_us < parent.timestamp_us);
+        checks
+            .clock_skew
+            .observe(edge, corrected || starts_before_parent);
+
+        let Some(payload) = payload else {
+            continue;
+        };
+
+        // LLM spans: usage and pricing
+        let model = payload
+            .response_model
+            .as_deref()
+            .or(payload.request_model.as_deref());
+        if let Some(model) = model {
+            let missing_usage = edge.token_count == 0
+                && payload.input_tokens.is_none()
+                && payload.output_tokens.is_none()
+                && payload.total_tokens.is_none();
+            checks.missing_usage.observe(edge, missing_usage);
+
+            let system = payload
+                .system
+                .as_deref()
+                .or(payload.provider_name.as_deref())
+                .unwrap_or("unknown");
+            let unmapped = ModelPricing::lookup(system, model).is_none();
+            checks.unmapped_models.observe(edge, unmapped);
+            if unmapped {
+                *checks
+                    .unmapped_model_names
+                    .entry(format!("{}/{}", system, model))
+                    .or_default() += 1;
+            }
+        }
+
+        // Attribute conventions
+        let unknown: Vec<&String> = payload
+            .additional
+            .keys()
+            .filter(|key| !is_known_attribute(key))
+            .collect();
+        checks.unknown_attributes.observe(edge, !unknown.is_empty());
+        for key in unknown {
+            *checks.unknown_keys.entry(key.clone()).or_default() += 1;
+        }
+    }
+
+    projects
+        .into_iter()
+        .map(|(project_id, checks)| build_report(project_id, checks))
+        .collect()
+}
+
+fn build_report(project_id: u16, checks: ProjectChecks) -> ProjectDataQuality {
+    // Weights reflect how much each gap distorts dashboards (sum = 100)
+    let score_penalty = 30.0 * checks.missing_usage.rate()
+        + 25.0 * checks.orphans.rate()
+        + 15.0 * checks.clock_skew.rate()
+        + 20.0 * checks.unmapped_models.rate()
+        + 10.0 * checks.unknown_attributes.rate();
+    let score = (100.0 - score_penalty).round().clamp(0.0, 100.0) as u8;
+
+    let unmapped_models: Vec<String> = checks
+        .unmapped_model_names
+        .into_iter()
+        .map(|(model, count)| format!("{} ({} spans)", model, count))
+        .collect();
+    let mut unknown_keys: Vec<(String, usize)> = checks.unknown_keys.into_iter().collect();
+    unknown_keys.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+    let unknown_keys: Vec<String> = unknown_keys
+        .into_iter()
+        .take(MAX_UNKNOWN_KEYS)
+        .map(|(key, count)| format!("{} ({} spans)", key, count))
+        .collect();
+
+    let issues = vec![
+        checks.missing_usage.into_issue(
+            "missing_usage",
+            Vec::new(),
+            "Record gen_ai.usage.input_tokens and gen_ai.usage.output_tokens on LLM spans \
+             (enable usage reporting in the provider SDK, e.g. stream_options.include_usage \
+             for streaming OpenAI calls)"
+                .to_string(),
+        ),
+        checks.orphans.into_issue(
+            "orphan_spans",
+            Vec::new(),
+            "Parent spans were never ingested: make sure parent spans are ended and flushed \
+             before the process exits, and that context is propagated across threads and \
+             async tasks"
+                .to_string(),
+        ),
+        checks.clock_skew.into_issue(
+            "clock_skew",
+            Vec::new(),
+            "Host clocks disagree: enable NTP/chrony on instrumented hosts and set host.name \
+             so skew can be attributed per host"
+                .to_string(),
+        ),
+        checks.unmapped_models.into_issue(
+            "unmapped_models",
+            unmapped_models,
+            "Add pricing for these models (or report gen_ai.system with a supported provider \
+             name) so costs are not estimated with default pricing"
+                .to_string(),
+        ),
+        checks.unknown_attributes.into_issue(
+            "unknown_attributes",
+            unknown_keys,
+            "Rename custom attributes to OpenTelemetry GenAI conventions (gen_ai.*) or \
+             namespace them under metadata.* so they are indexed consistently"
+                .to_string(),
+        ),
+    ]
+    .into_iter()
+    .filter(|issue| issue.affected > 0)
+    .collect();
+
+    ProjectDataQuality {
+        project_id,
+        spans_analyzed: checks.spans,
+        score,
+        issues,
+    }
+}
+
+fn is_known_attribute(key: &str) -> bool {
+    KNOWN_ATTRIBUTE_KEYS.contains(&key)
+        || KNOWN_ATTRIBUTE_PREFIXES
+            .iter()
+            .any(|prefix| key.starts_with(prefix))
+}
+
+/// Distinct parent IDs referenced by `spans` but not present among them
+pub fn missing_parents(spans: &[(AgentFlowEdge, Option<GenAIPayload>)]) -> HashSet<u128> {
+    let ids: HashSet<u128> = spans.iter().map(|(edge, _)| edge.edge_id).collect();
+    spans
+        .iter()
+        .map(|(edge, _)| edge.causal_parent)
+        .filter(|parent| *parent != 0 && !ids.contains(parent))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use agentreplay_core::SpanType;
+
+    fn edge(id: u128, parent: u128, ts: u64, tokens: u32) -> AgentFlowEdge {
+        let mut edge = AgentFlowEdge::new(1, 7, 1, 1, SpanType::Planning, parent);
+        edge.edge_id = id;
+        edge.timestamp_us = ts;
+        edge.token_count = tokens;
+        edge
+    }
+
+    fn llm_payload(system: &str, model: &str, with_usage: bool) -> GenAIPayload {
+        let mut attrs = HashMap::new();
+        attrs.insert("gen_ai.system".to_string(), system.to_string());
+        attrs.insert("gen_ai.request.model".to_string(), model.to_string());
+        if with_usage {
+            attrs.insert("gen_ai.usage.input_tokens".to_string(), "10".to_string());
+        }
+        GenAIPayload::from_attributes(&attrs)
+    }
+
+    #[test]
+    fn test_clean_project_scores_100() {
+        let spans = vec![
+            (
+                edge(1, 0, 1_000, 10),
+                Some(llm_payload("openai", "gpt-4o", true)),
+            ),
+            (
+                edge(2, 1, 2_000, 10),
+                Some(llm_payload("openai", "gpt-4o", true)),
+            ),
+        ];
+
+        let reports = analyze(&spans, |_| false);
+        assert_eq!(reports.len(), 1);
+        assert_eq!(reports[0].project_id, 7);
+        assert_eq!(reports[0].score, 100);
+        assert!(reports[0].issues.is_empty());
+    }
+
+    #[test]
+    fn test_detects_gaps() {
+        let mut custom = llm_payload("acme", "acme-large", false);
+        custom
+            .additional
+            .insert("myFramework.step".to_string(), serde_json::json!("plan"));
+
+        let spans = vec![
+            (edge(1, 0, 5_000, 0), Some(custom)),
+            // Parent 99 was never ingested
+            (edge(2, 99, 6_000, 10), None),
+            // Starts before its parent
+            (edge(3, 1, 4_000, 10), None),
+        ];
+
+        let report = &analyze(&spans, |_| false)[0];
+        let issue = |name: &str| report.issues.iter().find(|i| i.issue == name).unwrap();
+
+        assert_eq!(issue("missing_usage").affected, 1);
+        assert_eq!(issue("orphan_spans").examples, vec!["0x2".to_string()]);
+        assert_eq!(issue("clock_skew").affected, 1);
+        assert_eq!(
+            issue("unmapped_models").details,
+            vec!["acme/acme-large (1 spans)".to_string()]
+        );
+        assert_eq!(issue("unknown_attributes").affected, 1);
+        assert!(report.score < 100);
+        assert_eq!(missing_parents(&spans), HashSet::from([99]));
+    }
+}
@@ -0,0 +1,610 @@
+// Copyright 2025 AgentReplay (https://github.com/agentreplay)
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program. If not, see <https://www.gnu.org/licenses/>.
+
+//! Drift between production traffic and golden datasets
+//!
+//! Evals only say something about production if the test cases look like
+//! what users actually send. A periodic check embeds the latest user inputs
+//! and the test case inputs of the golden datasets, then compares the two
+//! distributions:
+//!
+//! - **MMD**: squared maximum mean discrepancy with a Gaussian kernel whose
+//!   bandwidth is the median pairwise distance
+//! - **Centroid shift**: cosine distance between the mean embeddings
+//! - **Coverage**: fraction of inputs with a test case at least
+//!   `coverage_similarity` similar
+//!
+//! Crossing either threshold (see [`DriftConfig`]) raises a `dataset_drift`
+//! insight listing the least covered traces. Production inputs are compared
+//! per tenant, and each tenant only sees its own report: the latest one is
+//! served at `GET /api/v1/insights/drift`; `POST /api/v1/insights/drift/run`
+//! checks the caller's tenant now.
+
+use std::collections::{BTreeMap, HashMap};
+use std::sync::Arc;
+use std::time::Duration;
+
+use agentreplay_core::clock::now_us;
+use agentreplay_core::insights::{Insight, InsightType, Severity};
+use agentreplay_core::{AgentFlowEdge, EvalDataset};
+use agentreplay_index::embedding::{EmbeddingProvider, LocalEmbeddingProvider};
+use agentreplay_query::TenantScope;
+use axum::{extract::State, Json};
+use parking_lot::Mutex;
+use serde::Serialize;
+use tracing::{info, warn};
+
+use crate::api::payload_extractors::extract_prompts;
+use crate::api::query::ApiError;
+use crate::api::AppState;
+use crate::auth::AuthContext;
+use crate::config::DriftConfig;
+use crate::otel_genai::GenAIPayload;
+
+const HOUR_US: u64 = 3_600_000_000;
+
+/// Uncovered traces listed in a report
+const MAX_UNCOVERED: usize = 20;
+
+/// Result of one drift check of a tenant's traffic
+#[derive(Debug, Clone, Serialize)]
+pub struct DriftReport {
+    pub tenant_id: u64,
+    pub generated_at: u64,
+    pub window_start: u64,
+    /// Golden datasets compared against
+    pub datasets: Vec<String>,
+    pub production_samples: usize,
+    pub golden_samples: usize,
+    pub mmd: f64,
+    pub centroid_shift: f64,
+    pub coverage: f64,
+    pub drifted: bool,
+    /// Least covered production traces with their best test case similarity
+    pub uncovered: Vec<UncoveredInput>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct UncoveredInput {
+    #[serde(serialize_with = "serialize_edge_id")]
+    pub edge_id: u128,
+    pub similarity: f64,
+}
+
+fn serialize_edge_id<S: serde::Serializer>(edge_id: &u128, s: S) -> Result<S::Ok, S::Error> {
+    s.serialize_str(&format!("{:#x}", edge_id))
+}
+
+/// Golden embeddings, reused while the datasets are unchanged
+struct GoldenCache {
+    /// `(id, updated_at)` of each dataset
+    key: Vec<(u128, u64)>,
+    embeddings: Vec<Vec<f32>>,
+}
+
+/// Periodic comparison of production inputs with golden datasets
+pub struct DriftDetector {
+    config: DriftConfig,
+    /// Latest report per tenant
+    latest: Mutex<HashMap<u64, DriftReport>>,
+    golden: Mutex<Option<GoldenCache>>,
+}
+
+impl DriftDetector {
+    pub fn new(config: DriftConfig) -> Self {
+        Self {
+            config,
+            latest: Mutex::new(HashMap::new()),
+            golden: Mutex::new(None),
+        }
+    }
+
+    pub fn config(&self) -> &DriftConfig {
+        &self.config
+    }
+
+    pub fn latest(&self, tenant_id: u64) -> Option<DriftReport> {
+        self.latest.lock().get(&tenant_id).cloned()
+    }
+
+    /// Check a tenant's traffic, returning `None` when there is too little
+    /// data
+    ///
+    /// Blocking: reads payloads and computes embeddings.
+    pub fn check(
+        &self,
+        state: &AppState,
+        tenant_id: u64,
+        now_us: u64,
+    ) -> Result<Option<DriftReport>, String> {
+        let datasets = self.golden_datasets(state)?;
+        if datasets.is_empty() {
+            return Ok(None);
+        }
+        let window_start = now_us.saturating_sub(self.config.window_hours * HOUR_US);
+        let edges = state
+            .db
+            .scoped(TenantScope::Tenant(tenant_id))
+            .range(window_start, now_us)
+            .map_err(|e| format!("Failed to query traces: {}", e))?;
+        self.compare(state, tenant_id, &datasets, edges, window_start, now_us)
+    }
+
+    /// Check every tenant with traffic in the window, one report each
+    ///
+    /// For the background task: scans across tenants, but never mixes
+    /// their inputs.
+    pub fn check_all(&self, state: &AppState, now_us: u64) -> Result<Vec<DriftReport>, String> {
+        let datasets = self.golden_datasets(state)?;
+        if datasets.is_empty() {
+            return Ok(Vec::new());
+        }
+        let window_start = now_us.saturating_sub(self.config.window_hours * HOUR_US);
+        let edges = state
+            .db
+            .scoped(TenantScope::AllTenants)
+            .range(window_start, now_us)
+            .map_err(|e| format!("Failed to query traces: {}", e))?;
+        let mut by_tenant: BTreeMap<u64, Vec<AgentFlowEdge>> = BTreeMap::new();
+        for edge in edges {
+            by_tenant.entry(edge.tenant_id).or_default().push(edge);
+        }
+
+        let mut reports = Vec::new();
+        for (tenant_id, edges) in by_tenant {
+            match self.compare(state, tenant_id, &datasets, edges, window_start, now_us) {
+                Ok(Some(report)) => reports.push(report),
+                Ok(None) => {}
+                Err(e) => warn!("Drift check of tenant {} failed: {}", tenant_id, e),
+            }
+        }
+        Ok(reports)
+    }
+
+    /// Compare a tenant's edges in the window with the golden datasets
+    fn compare(
+        &self,
+        state: &AppState,
+        tenant_id: u64,
+        datasets: &[EvalDataset],
+        edges: Vec<AgentFlowEdge>,
+        window_start: u64,
+        now_us: u64,
+    ) -> Result<Option<DriftReport>, String> {
+        let production = self.production_inputs(state, edges);
+        if production.len() < self.config.min_samples.max(2) {
+            return Ok(None);
+        }
+
+        let provider = LocalEmbeddingProvider::default_provider()
+            .map_err(|e| format!("Failed to initialize embedding provider: {}", e))?;
+        let golden = self.golden_embeddings(&provider, datasets)?;
+        if golden.len() < 2 {
+            return Ok(None);
+        }
+        let texts: Vec<&str> = production.iter().map(|(_, text)| text.as_str()).collect();
+        let live = provider
+            .embed_batch(&texts)
+            .map_err(|e| format!("Embedding failed: {}", e))?;
+
+        let mmd = mmd(&live, &golden);
+        let centroid_shift = centroid_shift(&live, &golden);
+        let similarities = best_similarities(&live, &golden);
+        let covered = similarities
+            .iter()
+            .filter(|&&s| s >= self.config.coverage_similarity)
+            .count();
+
+        let mut uncovered: Vec<UncoveredInput> = production
+            .iter()
+            .zip(&similarities)
+            .filter(|(_, &s)| s < self.config.coverage_similarity)
+            .map(|((edge_id, _), &similarity)| UncoveredInput {
+                edge_id: *edge_id,
+                similarity,
+            })
+            .collect();
+        uncovered.sort_by(|a, b| a.similarity.total_cmp(&b.similarity));
+        uncovered.truncate(MAX_UNCOVERED);
+
+        let report = DriftReport {
+            tenant_id,
+            generated_at: now_us,
+            window_start,
+            datasets: datasets.iter().map(|d| d.name.clone()).collect(),
+            production_samples: live.len(),
+            golden_samples: golden.len(),
+            mmd,
+            centroid_shift,
+            coverage: covered as f64 / live.len() as f64,
+            drifted: mmd > self.config.mmd_threshold
+                || centroid_shift > self.config.centroid_threshold,
+            uncovered,
+        };
+        self.latest.lock().insert(tenant_id, report.clone());
+        Ok(Some(report))
+    }
+
+    fn golden_datasets(&self, state: &AppState) -> Result<Vec<EvalDataset>, String> {
+        let datasets = state
+            .db
+            .list_eval_datasets()
+            .map_err(|e| format!("Failed to list datasets: {}", e))?;
+        let wanted = &self.config.datasets;
+        Ok(datasets
+            .into_iter()
+            .filter(|d| !d.test_cases.is_empty())
+            .filter(|d| {
+                wanted.is_empty()
+                    || wanted.iter().any(|w| {
+                        w == &d.name || w == &d.id.to_string() || w == &format!("{:#x}", d.id)
+                    })
+            })
+            .collect())
+    }
+
+    /// Most recent user inputs among `edges`, one per span
+    fn production_inputs(
+        &self,
+        state: &AppState,
+        mut edges: Vec<AgentFlowEdge>,
+    ) -> Vec<(u128, String)> {
+        edges.sort_by(|a, b| b.timestamp_us.cmp(&a.timestamp_us));
+
+        let mut inputs = Vec::new();
+        for edge in edges {
+            if inputs.len() >= self.config.sample_size {
+                break;
+            }
+            let Ok(Some(bytes)) = state.db.get_payload(edge.edge_id) else {
+                continue;
+            };
+            let Ok(payload) = serde_json::from_slice::<GenAIPayload>(&bytes) else {
+                continue;
+            };
+            if let Some(message) = extract_prompts(&payload)
+                .into_iter()
+                .rev()
+                .find(|m| m.role == "user" && !m.content.trim().is_empty())
+            {
+                inputs.push((edge.edge_id, message.content));
+            }
+        }
+        inputs
+    }
+
+    fn golden_embeddings(
+        &self,
+        provider: &LocalEmbeddingProvider,
+        datasets: &[EvalDataset],
+    ) -> Result<Vec<Vec<f32>>, String> {
+        let key: Vec<(u128, u64)> = datasets.iter().map(|d| (d.id, d.updated_at)).collect();
+        let mut cache = self.golden.lock();
+        if let Some(cached) = cache.as_ref().filter(|c| c.key == key) {
+            return Ok(cached.embeddings.clone());
+        }
+
+        let texts: Vec<String> = datasets
+            .iter()
+            .flat_map(|d| &d.test_cases)
+            .map(|case| input_text(&case.input))
+            .filter(|text| !text.trim().is_empty())
+            .collect();
+        let texts: Vec<&str> = texts.iter().map(String::as_str).collect();
+        let embeddings = provider
+            .embed_batch(&texts)
+            .map_err(|e| format!("Embedding failed: {}", e))?;
+
+        *cache = Some(GoldenCache {
+            key,
+            embeddings: embeddings.clone(),
+        });
+        Ok(embeddings)
+    }
+
+    /// Insight for the tenant's latest report if it found drift and its
+    /// window overlaps the requested one
+    pub fn insights(&self, tenant_id: u64, window_start: u64, window_end: u64) -> Vec<Insight> {
+        let Some(report) = self.latest(tenant_id).filter(|r| {
+            r.drifted && r.window_start <= window_end && r.generated_at >= window_start
+        }) else {
+            return Vec::new();
+        };
+        let severity = match report.coverage {
+            c if c < 0.25 => Severity::High,
+            c if c < 0.6 => Severity::Medium,
+            _ => Severity::Low,
+        };
+
+        vec![Insight {
+            id: format!("dataset-drift-{}", report.generated_at),
+            summary: format!(
+                "Production inputs drifted from golden datasets ({:.0}% covered)",
+                report.coverage * 100.0
+            ),
+            description: format!(
+                "{} recent inputs were compared with {} test cases from {}: MMD {:.3}, \
+                 centroid shift {:.3}. Eval results may not reflect current traffic.",
+                report.production_samples,
+                report.golden_samples,
+                report.datasets.join(", "),
+                report.mmd,
+                report.centroid_shift
+            ),
+            insight_type: InsightType::DatasetDrift {
+                datasets: report.datasets.clone(),
+                mmd: report.mmd,
+                centroid_shift: report.centroid_shift,
+                coverage: report.coverage,
+            },
+            severity,
+            confidence: (report.production_samples as f32 / self.config.sample_size as f32)
+                .min(1.0),
+            related_ids: report.uncovered.iter().map(|u| u.edge_id).collect(),
+            metadata: Default::default(),
+            generated_at: report.generated_at,
+            window_start: report.window_start,
+            window_end: report.generated_at,
+        }]
+    }
+
+    /// Check periodically until the server stops
+    pub fn spawn(self: &Arc<Self>, state: AppState) {
+        let detector = Arc::clone(self);
+        tokio::spawn(async move {
+            let period = Duration::from_secs(detector.config.interval_minutes * 60);
+            let mut interval = tokio::time::interval(period);
+            interval.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+            loop {
+                interval.tick().await;
+                let (detector, state) = (Arc::clone(&detector), state.clone());
+                match tokio::task::spawn_blocking(move || detector.check_all(&state, now_us()))
+                    .await
+                {
+                    Ok(Ok(reports)) => {
+                        for report in reports.iter().filter(|r| r.drifted) {
+                            warn!(
+                                "Production inputs of tenant {} drifted from golden datasets: MMD {:.3}, centroid shift {:.3}, coverage {:.0}%",
+                                report.tenant_id,
+                                report.mmd,
+                                report.centroid_shift,
+                                report.coverage * 100.0
+                            );
+                        }
+                    }
+                    Ok(Err(e)) => warn!("Drift check failed: {}", e),
+                    Err(e) => warn!("Drift check panicked: {}", e),
+                }
+            }
+        });
+        info!(
+            "Drift detection every {} min over the last {} h",
+            self.config.interval_minutes, self.config.window_hours
+        );
+    }
+}
+
+/// Text of a test case input, which is JSON serialized
+fn input_text(input: &str) -> String {
+    fn collect(value: &serde_json::Value, out: &mut Vec<String>) {
+        match value {
+            serde_json::Value::String(s) => out.push(s.clone()),
+            serde_json::Value::Array(items) => items.iter().for_each(|v| collect(v, out)),
+            serde_json::Value::Object(map) => map.values().for_each(|v| collect(v, out)),
+            _ => {}
+        }
+    }
+
+    match serde_json::from_str::<serde_json::Value>(input) {
+        Ok(value) => {
+            let mut parts = Vec::new();
+            collect(&value, &mut parts);
+            parts.join(" ")
+        }
+        Err(_) => input.to_string(),
+    }
+}
+
+fn dot(a: &[f32], b: &[f32]) -> f64 {
+    a.iter()
+        .zip(b)
+        .map(|(x, y)| (*x as f64) * (*y as f64))
+        .sum()
+}
+
+fn squared_distance(a: &[f32], b: &[f32]) -> f64 {
+    a.iter()
+        .zip(b)
+        .map(|(x, y)| {
+            let d = (*x - *y) as f64;
+            d * d
+        })
+        .sum()
+}
+
+fn cosine_similarity(a: &[f32], b: &[f32]) -> f64 {
+    let norm = (dot(a, a) * dot(b, b)).sqrt();
+    if norm == 0.0 {
+        0.0
+    } else {
+        dot(a, b) / norm
+    }
+}
+
+fn centroid(vectors: &[Vec<f32>]) -> Vec<f32> {
+    let dim = vectors.first().map_or(0, Vec::len);
+    let mut sum = vec![0.0f32; dim];
+    for v in vectors {
+        for (s, x) in sum.iter_mut().zip(v) {
+            *s += x;
+        }
+    }
+    let n = vectors.len().max(1) as f32;
+    sum.iter_mut().for_each(|s| *s /= n);
+    sum
+}
+
+/// Cosine distance between the mean embeddings
+pub fn centroid_shift(a: &[Vec<f32>], b: &[Vec<f32>]) -> f64 {
+    1.0 - cosine_similarity(&centroid(a), &centroid(b))
+}
+
+/// Squared MMD with a Gaussian kernel (median heuristic bandwidth)
+pub fn mmd(a: &[Vec<f32>], b: &[Vec<f32>]) -> f64 {
+    if a.is_empty() || b.is_empty() {
+        return 0.0;
+    }
+
+    let mut distances: Vec<f64> = Vec::new();
+    let all: Vec<&Vec<f32>> = a.iter().chain(b).collect();
+    for (i, x) in all.iter().enumerate() {
+        for y in &all[i + 1..] {
+            distances.push(squared_distance(x, y));
+        }
+    }
+    distances.sort_by(|x, y| x.total_cmp(y));
+    let median = distances.get(distances.len() / 2).copied().unwrap_or(1.0);
+    let gamma = 1.0 / (2.0 * median.max(f64::EPSILON));
+
+    let mean_kernel = |xs: &[Vec<f32>], ys: &[Vec<f32>]| {
+        let total: f64 = xs
+            .iter()
+            .flat_map(|x| {
+                ys.iter()
+                    .map(move |y| (-gamma * squared_distance(x, y)).exp())
+            })
+            .sum();
+        total / (xs.len() * ys.len()) as f64
+    };
+
+    (mean_kernel(a, a) + mean_kernel(b, b) - 2.0 * mean_kernel(a, b)).max(0.0)
+}
+
+/// Similarity of each vector in `live` to its nearest neighbor in `golden`
+fn best_similarities(live: &[Vec<f32>], golden: &[Vec<f32>]) -> Vec<f64> {
+    live.iter()
+        .map(|x| {
+            golden
+                .iter()
+                .map(|y| cosine_similarity(x, y))
+                .fold(f64::MIN, f64::max)
+        })
+        .collect()
+}
+
+/// GET /api/v1/insights/drift
+pub async fn get_drift_report(
+    State(state): State<AppState>,
+    axum::Extension(auth): axum::Extension<AuthContext>,
+) -> Result<Json<DriftReport>, ApiError> {
+    state
+        .drift_detector
+        .latest(auth.tenant_id)
+        .map(Json)
+        .ok_or_else(|| ApiError::NotFound("No drift check has completed yet".to_string()))
+}
+
+/// POST /api/v1/insights/drift/run
+pub async fn run_drift_check(
+    State(state): State<AppState>,
+    axum::Extension(auth): axum::Extension<AuthContext>,
+) -> Result<Json<DriftReport>, ApiError> {
+    let detector = Arc::clone(&state.drift_detector);
+    let report =
+        tokio::task::spawn_blocking(move || detector.check(&state, auth.tenant_id, now_us()))
+            .await
+            .map_err(|e| ApiError::Internal(format!("Drift check failed: {}", e)))?
+            .map_err(ApiError::Internal)?;
+
+    report.map(Json).ok_or_else(|| {
+        ApiError::BadRequest(
+            "Not enough production inputs or golden test cases to compare".to_string(),
+        )
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Unit vectors around `axis`, spread by `jitter`
+    fn cluster(axis: usize, n: usize, jitter: f32) -> Vec<Vec<f32>> {
+        (0..n)
+            .map(|i| {
+                let mut v = vec![0.0f32; 8];
+                v[axis] = 1.0;
+                v[(axis + 1 + i % 3) % 8] = jitter * (i as f32 % 5.0) / 5.0;
+                v
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_same_distribution_has_no_drift() {
+        let golden = cluster(0, 40, 0.3);
+        let live = cluster(0, 30, 0.3);
+        assert!(mmd(&live, &golden) < 0.05, "{}", mmd(&live, &golden));
+        assert!(centroid_shift(&live, &golden) < 0.05);
+        assert!(best_similarities(&live, &golden).iter().all(|&s| s > 0.99));
+    }
+
+    #[test]
+    fn test_shifted_distribution_drifts() {
+        let golden = cluster(0, 40, 0.3);
+        let mut live = cluster(0, 10, 0.3);
+        live.extend(cluster(4, 30, 0.3));
+
+        assert!(mmd(&live, &golden) > 0.05, "{}", mmd(&live, &golden));
+        assert!(centroid_shift(&live, &golden) > 0.15);
+        let covered = best_similarities(&live, &golden)
+            .iter()
+            .filter(|&&s| s >= 0.75)
+            .count();
+        assert_eq!(covered, 10);
+    }
+
+    #[test]
+    fn test_reports_are_per_tenant() {
+        let detector = DriftDetector::new(DriftConfig::default());
+        let report = DriftReport {
+            tenant_id: 1,
+            generated_at: 2 * HOUR_US,
+            window_start: HOUR_US,
+            datasets: vec!["golden".to_string()],
+            production_samples: 50,
+            golden_samples: 40,
+            mmd: 0.3,
+            centroid_shift: 0.4,
+            coverage: 0.2,
+            drifted: true,
+            uncovered: Vec::new(),
+        };
+        detector.latest.lock().insert(1, report);
+
+        assert!(detector.latest(2).is_none());
+        assert!(detector.insights(2, 0, 3 * HOUR_US).is_empty());
+        assert_eq!(detector.latest(1).unwrap().tenant_id, 1);
+        assert_eq!(detector.insights(1, 0, 3 * HOUR_US).len(), 1);
+    }
+
+    #[test]
+    fn test_input_text() {
+        assert_eq!(
+            input_text(r#"{"question": "reset password"}"#),
+            "reset password"
+        );
+        assert_eq!(input_text("plain text"), "plain text");
+    }
+}
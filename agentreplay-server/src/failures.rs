@@ -0,0 +1,531 @@
+// Copyright 2025 AgentReplay (https://github.com/agentreplay)
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program. If not, see <https://www.gnu.org/licenses/>.
+
+//! Failure groups
+//!
+//! Every stored span that failed is classified and fingerprinted (see
+//! [`crate::ingestion::classify_failure`]) and counted in its group: hourly
+//! counts for trends and the most recent spans as examples. Groups are kept
+//! per tenant and project; when a tenant has [`MAX_GROUPS_PER_TENANT`] groups,
+//! the one seen least recently makes room. Counts cover the spans ingested
+//! since the server started.
+//!
+//! Served at `GET /api/v1/failures?category=timeout&hours=24`.
+
+use std::collections::{BTreeMap, HashMap, VecDeque};
+
+use agentreplay_core::clock::now_us;
+use agentreplay_core::AgentFlowEdge;
+use axum::{
+    extract::{Query, State},
+    Json,
+};
+use parking_lot::Mutex;
+use serde::{Deserialize, Serialize};
+
+use crate::api::query::ApiError;
+use crate::api::AppState;
+use crate::auth::AuthContext;
+use crate::ingestion::{classify_failure, FailureCategory, SpanFailure};
+
+const HOUR_US: u64 = 3_600_000_000;
+
+/// Longest trend window served, in hours
+pub const MAX_TREND_HOURS: u64 = 168;
+const DEFAULT_TREND_HOURS: u64 = 24;
+
+/// Hourly counts kept per group: the longest window and the one before it
+const HISTORY_HOURS: u64 = 2 * MAX_TREND_HOURS;
+
+pub const MAX_GROUPS_PER_TENANT: usize = 1_000;
+const MAX_EXAMPLES: usize = 5;
+
+const DEFAULT_LIMIT: usize = 50;
+const MAX_LIMIT: usize = 500;
+
+/// A failed span kept as an example of its group
+#[derive(Debug, Clone, Serialize)]
+pub struct FailureExample {
+    /// Span ID, also accepted as trace ID by the trace endpoints
+    pub span_id: String,
+    pub session_id: u64,
+    pub timestamp_us: u64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub message: Option<String>,
+}
+
+struct FailureGroup {
+    category: FailureCategory,
+    error_type: Option<String>,
+    tool: Option<String>,
+    pattern: String,
+    count: u64,
+    first_seen_us: u64,
+    last_seen_us: u64,
+    /// Hour (timestamp / HOUR_US) -> failures
+    hourly: BTreeMap<u64, u64>,
+    /// Most recent last
+    examples: VecDeque<FailureExample>,
+}
+
+impl FailureGroup {
+    fn new(failure: &SpanFailure, timestamp_us: u64) -> Self {
+        Self {
+            category: failure.category,
+            error_type: failure.error_type.clone(),
+            tool: failure.tool.clone(),
+            pattern: failure.pattern.clone(),
+            count: 0,
+            first_seen_us: timestamp_us,
+            last_seen_us: timestamp_us,
+            hourly: BTreeMap::new(),
+            examples: VecDeque::with_capacity(MAX_EXAMPLES),
+        }
+    }
+
+    fn add(&mut self, edge: &AgentFlowEdge, failure: SpanFailure, now_hour: u64) {
+        self.count += 1;
+        self.first_seen_us = self.first_seen_us.min(edge.timestamp_us);
+        self.last_seen_us = self.last_seen_us.max(edge.timestamp_us);
+
+        *self.hourly.entry(edge.timestamp_us / HOUR_US).or_insert(0) += 1;
+        let oldest = now_hour.saturating_sub(HISTORY_HOURS);
+        self.hourly = self.hourly.split_off(&oldest);
+
+        if self.examples.len() == MAX_EXAMPLES {
+            self.examples.pop_front();
+        }
+        self.examples.push_back(FailureExample {
+            span_id: format!("{:#x}", edge.edge_id),
+            session_id: edge.session_id,
+            timestamp_us: edge.timestamp_us,
+            message: failure.message,
+        });
+    }
+
+    /// Failures from `start_hour` through `end_hour`
+    fn count_between(&self, start_hour: u64, end_hour: u64) -> u64 {
+        self.hourly
+            .range(start_hour..=end_hour)
+            .map(|(_, n)| n)
+            .sum()
+    }
+}
+
+/// (project_id, fingerprint)
+type GroupKey = (u16, String);
+
+/// Per-tenant failure groups
+pub struct FailureTracker {
+    since_us: u64,
+    groups: Mutex<HashMap<u64, HashMap<GroupKey, FailureGroup>>>,
+}
+
+impl Default for FailureTracker {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl FailureTracker {
+    pub fn new() -> Self {
+        Self {
+            since_us: now_us(),
+            groups: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Count a stored span in its failure group; returns false if it did not fail
+    pub fn record(&self, edge: &AgentFlowEdge, attributes: &HashMap<String, String>) -> bool {
+        let Some(failure) = classify_failure(attributes) else {
+            return false;
+        };
+        self.record_failure(edge, failure, now_us() / HOUR_US);
+        true
+    }
+
+    fn record_failure(&self, edge: &AgentFlowEdge, failure: SpanFailure, now_hour: u64) {
+        let mut groups = self.groups.lock();
+        let tenant = groups.entry(edge.tenant_id).or_default();
+        let key = (edge.project_id, failure.fingerprint.clone());
+
+        if !tenant.contains_key(&key) && tenant.len() >= MAX_GROUPS_PER_TENANT {
+            let stalest = tenant
+                .iter()
+                .min_by_key(|(_, group)| group.last_seen_us)
+                .map(|(key, _)| key.clone());
+            if let Some(stalest) = stalest {
+                tenant.remove(&stalest);
+            }
+        }
+
+        tenant
+            .entry(key)
+            .or_insert_with(|| FailureGroup::new(&failure, edge.timestamp_us))
+            .add(edge, failure, now_hour);
+    }
+
+    /// Groups of a tenant matching the filter, most failures in the window first
+    fn summarize(
+        &self,
+        tenant_id: u64,
+        params: &FailuresParams,
+        hours: u64,
+        now_hour: u64,
+    ) -> Vec<FailureGroupSummary> {
+        let start_hour = now_hour + 1 - hours.min(now_hour + 1);
+        let previous_start = start_hour.saturating_sub(hours);
+
+        let groups = self.groups.lock();
+        let Some(tenant) = groups.get(&tenant_id) else {
+            return Vec::new();
+        };
+        let mut summaries: Vec<FailureGroupSummary> = tenant
+            .iter()
+            .filter(|((project_id, _), group)| {
+                params.project_id.is_none_or(|id| id == *project_id)
+                    && params.category.is_none_or(|c| c == group.category)
+            })
+            .map(|((project_id, fingerprint), group)| FailureGroupSummary {
+                fingerprint: fingerprint.clone(),
+                project_id: *project_id,
+                category: group.category,
+                error_type: group.error_type.clone(),
+                tool: group.tool.clone(),
+                pattern: group.pattern.clone(),
+                count: group.count,
+                window_count: group.count_between(start_hour, now_hour),
+                previous_window_count: if start_hour == 0 {
+                    0
+                } else {
+                    group.count_between(previous_start, start_hour - 1)
+                },
+                first_seen_us: group.first_seen_us,
+                last_seen_us: group.last_seen_us,
+                trend: (start_hour..=now_hour)
+                    .map(|hour| group.hourly.get(&hour).copied().unwrap_or(0))
+                    .collect(),
+                examples: group.examples.iter().rev().cloned().collect(),
+            })
+            .collect();
+        summaries.sort_by(|a, b| {
+            b.window_count
+                .cmp(&a.window_count)
+                .then(b.count.cmp(&a.count))
+                .then_with(|| a.fingerprint.cmp(&b.fingerprint))
+        });
+        summaries
+    }
+
+    /// Groups of a tenant first seen in `[start_us, end_us)`, most failures
+    /// in that period first
+    ///
+    /// Only the last [`MAX_TREND_HOURS`] of the period are counted.
+    pub fn first_seen_between(
+        &self,
+        tenant_id: u64,
+        project_id: Option<u16>,
+        start_us: u64,
+        end_us: u64,
+    ) -> Vec<FailureGroupSummary> {
+        let hours = end_us
+            .saturating_sub(start_us)
+            .div_ceil(HOUR_US)
+            .clamp(1, MAX_TREND_HOURS);
+        let params = FailuresParams {
+            project_id,
+            ..Default::default()
+        };
+        let mut groups = self.summarize(
+            tenant_id,
+            &params,
+            hours,
+            end_us.saturating_sub(1) / HOUR_US,
+        );
+        groups.retain(|group| (start_us..end_us).contains(&group.first_seen_us));
+        groups
+    }
+}
+
+#[derive(Debug, Default, Deserialize)]
+pub struct FailuresParams {
+    pub category: Option<FailureCategory>,
+    pub project_id: Option<u16>,
+    /// Trend window in hours (default 24)
+    pub hours: Option<u64>,
+    pub limit: Option<usize>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct FailureGroupSummary {
+    pub fingerprint: String,
+    pub project_id: u16,
+    pub category: FailureCategory,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error_type: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub tool: Option<String>,
+    /// Error message with variable parts masked
+    pub pattern: String,
+    /// Failures since the server started
+    pub count: u64,
+    /// Failures in the trend window
+    pub window_count: u64,
+    /// Failures in the window of the same length before it
+    pub previous_window_count: u64,
+    pub first_seen_us: u64,
+    pub last_seen_us: u64,
+    /// Hourly failures over the trend window, oldest first
+    pub trend: Vec<u64>,
+    /// Most recent first
+    pub examples: Vec<FailureExample>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct CategoryCount {
+    pub category: FailureCategory,
+    pub count: u64,
+    pub window_count: u64,
+}
+
+#[derive(Debug, Serialize)]
+pub struct FailuresResponse {
+    /// Start of the tracked stream (server start, microseconds)
+    pub since_us: u64,
+    pub hours: u64,
+    /// Start of the first trend bucket (microseconds)
+    pub trend_start_us: u64,
+    pub categories: Vec<CategoryCount>,
+    /// Groups matching the filter before `limit` was applied
+    pub total_groups: usize,
+    pub groups: Vec<FailureGroupSummary>,
+}
+
+/// GET /api/v1/failures?category=timeout&hours=24
+pub async fn get_failures(
+    State(state): State<AppState>,
+    Query(params): Query<FailuresParams>,
+    axum::Extension(auth): axum::Extension<AuthContext>,
+) -> Result<Json<FailuresResponse>, ApiError> {
+    let hours = params.hours.unwrap_or(DEFAULT_TREND_HOURS);
+    if hours == 0 || hours > MAX_TREND_HOURS {
+        return Err(ApiError::BadRequest(format!(
+            "hours must be between 1 and {}",
+            MAX_TREND_HOURS
+        )));
+    }
+    let limit = params.limit.unwrap_or(DEFAULT_LIMIT);
+    if limit == 0 || limit > MAX_LIMIT {
+        return Err(ApiError::BadRequest(format!(
+            "limit must be between 1 and {}",
+            MAX_LIMIT
+        )));
+    }
+
+    let now_hour = now_us() / HOUR_US;
+    let mut groups = state
+        .failures
+        .summarize(auth.tenant_id, &params, hours, now_hour);
+
+    let mut categories: Vec<CategoryCount> = Vec::new();
+    for group in &groups {
+        match categories.iter_mut().find(|c| c.category == group.category) {
+            Some(category) => {
+                category.count += group.count;
+                category.window_count += group.window_count;
+            }
+            None => categories.push(CategoryCount {
+                category: group.category,
+                count: group.count,
+                window_count: group.window_count,
+            }),
+        }
+    }
+    categories.sort_by(|a, b| {
+        b.window_count
+            .cmp(&a.window_count)
+            .then(b.count.cmp(&a.count))
+    });
+
+    let total_groups = groups.len();
+    groups.truncate(limit);
+
+    Ok(Json(FailuresResponse {
+        since_us: state.failures.since_us,
+        hours,
+        trend_start_us: (now_hour + 1).saturating_sub(hours) * HOUR_US,
+        categories,
+        total_groups,
+        groups,
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const NOW_HOUR: u64 = 480_000;
+
+    fn span_attrs(pairs: &[(&str, &str)]) -> HashMap<String, String> {
+        pairs
+            .iter()
+            .map(|(k, v)| (k.to_string(), v.to_string()))
+            .collect()
+    }
+
+    fn failed_edge(edge_id: u128, project_id: u16, hour: u64) -> AgentFlowEdge {
+        AgentFlowEdge {
+            edge_id,
+            tenant_id: 7,
+            project_id,
+            session_id: 42,
+            timestamp_us: hour * HOUR_US + 1,
+            ..Default::default()
+        }
+    }
+
+    fn record(tracker: &FailureTracker, edge: &AgentFlowEdge, pairs: &[(&str, &str)]) {
+        let failure = classify_failure(&span_attrs(pairs)).unwrap();
+        tracker.record_failure(edge, failure, NOW_HOUR);
+    }
+
+    fn summarize(tracker: &FailureTracker, params: &FailuresParams) -> Vec<FailureGroupSummary> {
+        tracker.summarize(7, params, 24, NOW_HOUR)
+    }
+
+    #[test]
+    fn test_groups_and_trend() {
+        let tracker = FailureTracker::new();
+        let timeout = |n: u32| format!("Request {} timed out after {}s", n, n * 10);
+        for (id, hour) in [
+            (1, NOW_HOUR),
+            (2, NOW_HOUR),
+            (3, NOW_HOUR - 3),
+            (4, NOW_HOUR - 30),
+        ] {
+            let message = timeout(id as u32);
+            record(
+                &tracker,
+                &failed_edge(id, 1, hour),
+                &[("error.message", message.as_str())],
+            );
+        }
+        record(
+            &tracker,
+            &failed_edge(5, 1, NOW_HOUR - 40),
+            &[("http.response.status_code", "429")],
+        );
+
+        let groups = summarize(&tracker, &FailuresParams::default());
+        assert_eq!(groups.len(), 2);
+        let timeouts = &groups[0];
+        assert_eq!(timeouts.category, FailureCategory::Timeout);
+        assert_eq!(timeouts.pattern, "request # timed out after #");
+        assert_eq!(timeouts.count, 4);
+        assert_eq!(timeouts.window_count, 3);
+        assert_eq!(timeouts.previous_window_count, 1);
+        assert_eq!(timeouts.trend.len(), 24);
+        assert_eq!(timeouts.trend[23], 2);
+        assert_eq!(timeouts.trend[20], 1);
+        assert_eq!(timeouts.examples[0].span_id, "0x4");
+        assert_eq!(groups[1].category, FailureCategory::RateLimit);
+        assert_eq!(groups[1].window_count, 0);
+
+        let params = FailuresParams {
+            category: Some(FailureCategory::RateLimit),
+            ..Default::default()
+        };
+        assert_eq!(summarize(&tracker, &params).len(), 1);
+
+        // Other tenants and projects see nothing
+        assert!(tracker
+            .summarize(8, &FailuresParams::default(), 24, NOW_HOUR)
+            .is_empty());
+        let params = FailuresParams {
+            project_id: Some(2),
+            ..Default::default()
+        };
+        assert!(summarize(&tracker, &params).is_empty());
+    }
+
+    #[test]
+    fn test_examples_and_eviction() {
+        let tracker = FailureTracker::new();
+        for id in 0..(MAX_EXAMPLES as u128 + 3) {
+            record(
+                &tracker,
+                &failed_edge(id, 1, NOW_HOUR - 1),
+                &[("exception.type", "KeyError")],
+            );
+        }
+        let groups = summarize(&tracker, &FailuresParams::default());
+        assert_eq!(groups[0].examples.len(), MAX_EXAMPLES);
+        assert_eq!(groups[0].examples[0].span_id, "0x7");
+
+        for id in 0..MAX_GROUPS_PER_TENANT as u128 {
+            let fingerprint = format!("group-{}", id);
+            record(
+                &tracker,
+                &failed_edge(id, 2, NOW_HOUR),
+                &[
+                    ("error", "true"),
+                    ("agentreplay.failure.fingerprint", fingerprint.as_str()),
+                ],
+            );
+        }
+        let groups = summarize(&tracker, &FailuresParams::default());
+        assert_eq!(groups.len(), MAX_GROUPS_PER_TENANT);
+        // The KeyError group was seen least recently
+        assert!(groups.iter().all(|group| group.project_id == 2));
+    }
+
+    #[test]
+    fn test_first_seen_between() {
+        let tracker = FailureTracker::new();
+        record(
+            &tracker,
+            &failed_edge(1, 1, NOW_HOUR - 200),
+            &[("exception.type", "KeyError")],
+        );
+        record(
+            &tracker,
+            &failed_edge(2, 1, NOW_HOUR - 2),
+            &[("exception.type", "KeyError")],
+        );
+        record(
+            &tracker,
+            &failed_edge(3, 1, NOW_HOUR - 2),
+            &[("http.response.status_code", "429")],
+        );
+
+        let week_start = (NOW_HOUR - 167) * HOUR_US;
+        let end = (NOW_HOUR + 1) * HOUR_US;
+        let groups = tracker.first_seen_between(7, None, week_start, end);
+        assert_eq!(groups.len(), 1);
+        assert_eq!(groups[0].category, FailureCategory::RateLimit);
+        assert_eq!(groups[0].window_count, 1);
+        assert!(tracker
+            .first_seen_between(7, Some(2), week_start, end)
+            .is_empty());
+    }
+
+    #[test]
+    fn test_record_skips_successful_spans() {
+        let tracker = FailureTracker::new();
+        let edge = failed_edge(1, 1, NOW_HOUR);
+        assert!(!tracker.record(&edge, &span_attrs(&[("gen_ai.request.model", "gpt-4o")])));
+        assert!(tracker.record(&edge, &span_attrs(&[("error", "true")])));
+    }
+}
@@ -19,6 +19,9 @@
 
 mod sharded;
 mod sketch;
+pub mod tuning;
 
-pub use sharded::{GovernorConfig, GovernorDecision, GovernorStats, ShardedGovernor};
+pub use sharded::{
+    DedupExemptions, GovernorConfig, GovernorDecision, GovernorStats, ShardStats, ShardedGovernor,
+};
 pub use sketch::CountMinSketch;
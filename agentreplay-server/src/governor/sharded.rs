@@ -24,6 +24,7 @@
 //! - Optimistic read pattern: read lock first, upgrade only if needed
 //! - Count-Min Sketch for fixed-memory duplicate counting
 //! - Binary Quantization for 32x memory reduction (6KB → 192 bytes per vector)
+//! - Epsilon, quantization and agent/project exemptions adjustable at runtime
 //!
 //! Memory Comparison (10M traces):
 //! - Full f32 embeddings: 60 GB RAM
@@ -31,7 +32,9 @@
 
 use sochdb_index::hnsw::{DistanceMetric, HnswConfig, HnswIndex};
 use parking_lot::RwLock;
-use std::sync::atomic::{AtomicU64, Ordering};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeSet;
+use std::sync::atomic::{AtomicBool, AtomicU32, AtomicU64, Ordering};
 use std::sync::Arc;
 
 use super::sketch::CountMinSketch;
@@ -69,6 +72,25 @@ impl Default for GovernorConfig {
     }
 }
 
+/// Agents and projects whose traces are never deduplicated
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DedupExemptions {
+    #[serde(default)]
+    pub agents: BTreeSet<u64>,
+    #[serde(default)]
+    pub projects: BTreeSet<u16>,
+}
+
+impl DedupExemptions {
+    pub fn is_empty(&self) -> bool {
+        self.agents.is_empty() && self.projects.is_empty()
+    }
+
+    pub fn contains(&self, project_id: u16, agent_id: u64) -> bool {
+        self.projects.contains(&project_id) || self.agents.contains(&agent_id)
+    }
+}
+
 /// Result of semantic deduplication decision.
 #[derive(Debug, Clone)]
 pub enum GovernorDecision {
@@ -204,33 +226,82 @@ impl Shard {
             .min_by(|a, b| a.1.partial_cmp(&b.1).unwrap())
     }
 
-    /// Get memory usage for this shard
-    fn memory_usage(&self) -> usize {
-        if self.use_bq {
-            self.binary_embeddings
-                .iter()
-                .map(|(_, e)| 16 + e.memory_size()) // 16 bytes for u128 id
-                .sum()
-        } else if let Some(ref full) = self.full_embeddings {
-            full.iter()
-                .map(|(_, e)| 16 + e.len() * 4) // 16 bytes for u128 id + f32 per dim
-                .sum()
+    /// Switch how new embeddings are stored
+    ///
+    /// Enabling quantizes the full embeddings kept so far. Disabling can't
+    /// restore full precision, so embeddings quantized earlier stay binary.
+    fn set_binary_quantization(&mut self, enabled: bool) {
+        if enabled == self.use_bq {
+            return;
+        }
+        if enabled {
+            if let Some(full) = self.full_embeddings.take() {
+                self.binary_embeddings.extend(
+                    full.into_iter()
+                        .map(|(id, e)| (id, BinaryEmbedding::from_f32(&e))),
+                );
+            }
         } else {
-            0
+            self.full_embeddings = Some(Vec::new());
         }
+        self.use_bq = enabled;
+    }
+
+    /// Number of stored embeddings
+    fn len(&self) -> usize {
+        self.binary_embeddings.len() + self.full_embeddings.as_ref().map_or(0, Vec::len)
+    }
+
+    /// Get memory usage for this shard
+    fn memory_usage(&self) -> usize {
+        let binary: usize = self
+            .binary_embeddings
+            .iter()
+            .map(|(_, e)| 16 + e.memory_size()) // 16 bytes for u128 id
+            .sum();
+        let full: usize = self
+            .full_embeddings
+            .iter()
+            .flatten()
+            .map(|(_, e)| 16 + e.len() * 4) // 16 bytes for u128 id + f32 per dim
+            .sum();
+        binary + full
     }
 }
 
+/// Per-shard deduplication counters
+#[derive(Default)]
+struct ShardCounters {
+    processed: AtomicU64,
+    dropped: AtomicU64,
+}
+
 /// Statistics for the Sharded Governor.
-#[derive(Debug, Clone, Default)]
+#[derive(Debug, Clone, Default, Serialize)]
 pub struct GovernorStats {
     pub total_processed: u64,
     pub stored: u64,
     pub dropped: u64,
+    /// Traces from exempt agents/projects, stored without a dedup check
+    pub exempted: u64,
     pub total_vectors: usize,
     pub vectors_per_shard: Vec<usize>,
     /// Memory usage in bytes (with binary quantization this is 32x smaller)
     pub memory_usage_bytes: usize,
+    pub epsilon: f32,
+    pub binary_quantization: bool,
+    pub shards: Vec<ShardStats>,
+}
+
+/// Deduplication statistics of one shard
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct ShardStats {
+    pub shard: usize,
+    pub vectors: usize,
+    pub processed: u64,
+    pub dropped: u64,
+    /// Fraction of processed traces dropped as duplicates
+    pub hit_rate: f64,
 }
 
 /// High-performance sharded semantic governor.
@@ -245,12 +316,17 @@ pub struct ShardedGovernor {
     shards: Vec<RwLock<Shard>>,
     /// Fixed-memory duplicate counter
     duplicate_counts: CountMinSketch,
-    /// Configuration
-    config: GovernorConfig,
+    /// Current epsilon (f32 bits)
+    epsilon: AtomicU32,
+    /// Whether new embeddings are binary quantized
+    binary_quantization: AtomicBool,
+    exemptions: RwLock<DedupExemptions>,
     /// Statistics
     stats_processed: AtomicU64,
     stats_stored: AtomicU64,
     stats_dropped: AtomicU64,
+    stats_exempted: AtomicU64,
+    shard_counters: Vec<ShardCounters>,
 }
 
 impl ShardedGovernor {
@@ -269,10 +345,14 @@ impl ShardedGovernor {
         Self {
             shards,
             duplicate_counts: CountMinSketch::new(),
-            config,
+            epsilon: AtomicU32::new(config.epsilon.to_bits()),
+            binary_quantization: AtomicBool::new(config.use_binary_quantization),
+            exemptions: RwLock::new(DedupExemptions::default()),
             stats_processed: AtomicU64::new(0),
             stats_stored: AtomicU64::new(0),
             stats_dropped: AtomicU64::new(0),
+            stats_exempted: AtomicU64::new(0),
+            shard_counters: (0..NUM_SHARDS).map(|_| ShardCounters::default()).collect(),
         }
     }
 
@@ -281,17 +361,71 @@ impl ShardedGovernor {
         Arc::new(Self::new(config))
     }
 
+    /// Current distance threshold
+    pub fn epsilon(&self) -> f32 {
+        f32::from_bits(self.epsilon.load(Ordering::Relaxed))
+    }
+
+    /// Change the distance threshold; applies to the next processed trace
+    pub fn set_epsilon(&self, epsilon: f32) -> Result<(), String> {
+        if !(epsilon > 0.0 && epsilon < 1.0) {
+            return Err(format!("epsilon must be in (0, 1), got {}", epsilon));
+        }
+        self.epsilon.store(epsilon.to_bits(), Ordering::Relaxed);
+        tracing::info!("Semantic governor epsilon set to {}", epsilon);
+        Ok(())
+    }
+
+    pub fn binary_quantization(&self) -> bool {
+        self.binary_quantization.load(Ordering::Relaxed)
+    }
+
+    /// Toggle binary quantization of stored embeddings
+    ///
+    /// Enabling quantizes the embeddings already held; disabling only
+    /// affects embeddings stored from now on.
+    pub fn set_binary_quantization(&self, enabled: bool) {
+        self.binary_quantization.store(enabled, Ordering::Relaxed);
+        for shard in &self.shards {
+            shard.write().set_binary_quantization(enabled);
+        }
+        tracing::info!("Semantic governor binary quantization set to {}", enabled);
+    }
+
+    pub fn exemptions(&self) -> DedupExemptions {
+        self.exemptions.read().clone()
+    }
+
+    /// Replace the agents and projects exempt from deduplication
+    pub fn set_exemptions(&self, exemptions: DedupExemptions) {
+        *self.exemptions.write() = exemptions;
+    }
+
+    /// Whether traces of this project or agent bypass deduplication
+    pub fn is_exempt(&self, project_id: u16, agent_id: u64) -> bool {
+        let exemptions = self.exemptions.read();
+        !exemptions.is_empty() && exemptions.contains(project_id, agent_id)
+    }
+
+    /// Count a trace stored without a dedup check because it is exempt
+    pub fn record_exempt(&self) {
+        self.stats_exempted.fetch_add(1, Ordering::Relaxed);
+    }
+
     /// Process a trace embedding and decide whether to store or drop it.
     ///
     /// This is the hot path - optimized for minimal lock contention.
     pub fn process(&self, trace_id: u128, embedding: &[f32]) -> GovernorDecision {
         self.stats_processed.fetch_add(1, Ordering::Relaxed);
+        let epsilon = self.epsilon();
 
         // Determine which shard to use based on embedding hash
         let shard_idx = self.select_shard(embedding);
+        let counters = &self.shard_counters[shard_idx];
+        counters.processed.fetch_add(1, Ordering::Relaxed);
 
         // Pre-compute binary embedding once
-        let binary_query = if self.config.use_binary_quantization {
+        let binary_query = if self.binary_quantization() {
             Some(BinaryEmbedding::from_f32(embedding))
         } else {
             None
@@ -302,12 +436,13 @@ impl ShardedGovernor {
             let shard = self.shards[shard_idx].read();
 
             if let Some((similar_id, distance)) =
-                self.find_nearest(&shard, embedding, binary_query.as_ref())
+                self.find_nearest(&shard, embedding, binary_query.as_ref(), epsilon)
             {
-                if distance < self.config.epsilon {
+                if distance < epsilon {
                     // Duplicate found - increment count and drop
                     let count = self.duplicate_counts.increment(similar_id);
                     self.stats_dropped.fetch_add(1, Ordering::Relaxed);
+                    counters.dropped.fetch_add(1, Ordering::Relaxed);
 
                     return GovernorDecision::Drop {
                         similar_to: similar_id,
@@ -324,11 +459,12 @@ impl ShardedGovernor {
 
             // Double-check (another thread may have inserted)
             if let Some((similar_id, distance)) =
-                self.find_nearest(&shard, embedding, binary_query.as_ref())
+                self.find_nearest(&shard, embedding, binary_query.as_ref(), epsilon)
             {
-                if distance < self.config.epsilon {
+                if distance < epsilon {
                     let count = self.duplicate_counts.increment(similar_id);
                     self.stats_dropped.fetch_add(1, Ordering::Relaxed);
+                    counters.dropped.fetch_add(1, Ordering::Relaxed);
 
                     return GovernorDecision::Drop {
                         similar_to: similar_id,
@@ -355,6 +491,11 @@ impl ShardedGovernor {
     /// Process multiple embeddings in parallel across shards.
     ///
     /// This is the bulk processing path - maximizes parallelism.
+    #[tracing::instrument(
+        name = "governor.process_batch",
+        skip_all,
+        fields(batch = items.len(), stored = tracing::field::Empty, dropped = tracing::field::Empty)
+    )]
     pub async fn process_batch(
         self: &Arc<Self>,
         items: Vec<(u128, Vec<f32>)>,
@@ -402,21 +543,46 @@ impl ShardedGovernor {
         }
 
         // Reconstruct original order
-        original_order
+        let decisions: Vec<GovernorDecision> = original_order
             .into_iter()
             .map(|(shard_idx, idx)| shard_results[shard_idx][idx].clone())
-            .collect()
+            .collect();
+
+        let dropped = decisions
+            .iter()
+            .filter(|d| matches!(d, GovernorDecision::Drop { .. }))
+            .count();
+        let span = tracing::Span::current();
+        span.record("stored", decisions.len() - dropped);
+        span.record("dropped", dropped);
+        decisions
     }
 
     /// Get statistics about the governor.
     pub fn stats(&self) -> GovernorStats {
         let mut vectors_per_shard = Vec::with_capacity(NUM_SHARDS);
+        let mut shards = Vec::with_capacity(NUM_SHARDS);
         let mut total_memory = 0usize;
 
-        for shard_lock in &self.shards {
+        for (idx, shard_lock) in self.shards.iter().enumerate() {
             let shard = shard_lock.read();
-            vectors_per_shard.push(shard.binary_embeddings.len());
+            let vectors = shard.len();
+            vectors_per_shard.push(vectors);
             total_memory += shard.memory_usage();
+
+            let processed = self.shard_counters[idx].processed.load(Ordering::Relaxed);
+            let dropped = self.shard_counters[idx].dropped.load(Ordering::Relaxed);
+            shards.push(ShardStats {
+                shard: idx,
+                vectors,
+                processed,
+                dropped,
+                hit_rate: if processed > 0 {
+                    dropped as f64 / processed as f64
+                } else {
+                    0.0
+                },
+            });
         }
 
         let total_vectors = vectors_per_shard.iter().sum();
@@ -425,9 +591,13 @@ impl ShardedGovernor {
             total_processed: self.stats_processed.load(Ordering::Relaxed),
             stored: self.stats_stored.load(Ordering::Relaxed),
             dropped: self.stats_dropped.load(Ordering::Relaxed),
+            exempted: self.stats_exempted.load(Ordering::Relaxed),
             total_vectors,
             vectors_per_shard,
             memory_usage_bytes: total_memory,
+            epsilon: self.epsilon(),
+            binary_quantization: self.binary_quantization(),
+            shards,
         }
     }
 
@@ -447,6 +617,7 @@ impl ShardedGovernor {
         shard: &Shard,
         embedding: &[f32],
         binary_query: Option<&BinaryEmbedding>,
+        epsilon: f32,
     ) -> Option<(u128, f32)> {
         // First try HNSW index for fast approximate nearest neighbor
         match shard.index.search(embedding, 1) {
@@ -459,7 +630,7 @@ impl ShardedGovernor {
 
         // Fallback: use binary embeddings for fast approximate search
         if let Some(query) = binary_query {
-            if let Some(result) = shard.find_nearest_binary(query, self.config.epsilon * 2.0) {
+            if let Some(result) = shard.find_nearest_binary(query, epsilon * 2.0) {
                 return Some(result);
             }
         }
@@ -631,6 +802,46 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_runtime_tuning() {
+        let config = GovernorConfig {
+            epsilon: 0.1,
+            dimension: 128,
+            use_binary_quantization: false,
+            ..Default::default()
+        };
+        let governor = ShardedGovernor::new(config);
+        let emb = random_embedding(128, 7);
+        governor.process(1, &emb);
+        governor.process(2, &emb);
+
+        let stats = governor.stats();
+        assert_eq!(stats.total_vectors, 1);
+        let shard = stats.shards.iter().find(|s| s.processed > 0).unwrap();
+        assert_eq!((shard.processed, shard.dropped), (2, 1));
+        assert!((shard.hit_rate - 0.5).abs() < f64::EPSILON);
+
+        assert!(governor.set_epsilon(0.0).is_err());
+        assert!(governor.set_epsilon(0.3).is_ok());
+        assert!((governor.stats().epsilon - 0.3).abs() < f32::EPSILON);
+
+        // Enabling quantization converts the stored embedding
+        let full_memory = governor.stats().memory_usage_bytes;
+        governor.set_binary_quantization(true);
+        let stats = governor.stats();
+        assert!(stats.binary_quantization);
+        assert_eq!(stats.total_vectors, 1);
+        assert!(stats.memory_usage_bytes < full_memory);
+
+        assert!(!governor.is_exempt(3, 42));
+        governor.set_exemptions(DedupExemptions {
+            agents: BTreeSet::from([42]),
+            projects: BTreeSet::new(),
+        });
+        assert!(governor.is_exempt(3, 42));
+        assert!(!governor.is_exempt(3, 43));
+    }
+
     #[tokio::test]
     async fn test_batch_processing() {
         let config = GovernorConfig {
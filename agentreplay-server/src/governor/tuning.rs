@@ -0,0 +1,98 @@
+// Copyright 2025 AgentReplay (https://github.com/agentreplay)
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program. If not, see <https://www.gnu.org/licenses/>.
+
+//! Runtime tuning of the semantic governor
+//!
+//! - `GET /api/v1/admin/governor`: settings, totals and per-shard hit rates
+//! - `PATCH /api/v1/admin/governor`: change epsilon, binary quantization or
+//!   the agents/projects exempt from deduplication
+//!
+//! Changes apply to the next processed trace and last until restart; the
+//! `[governor]` config section sets the startup values.
+
+use std::sync::Arc;
+
+use axum::{extract::State, Json};
+use serde::{Deserialize, Serialize};
+
+use super::{DedupExemptions, GovernorStats, ShardedGovernor};
+use crate::api::query::ApiError;
+use crate::api::AppState;
+use crate::auth::{AuthContext, Role};
+
+#[derive(Debug, Serialize)]
+pub struct GovernorResponse {
+    pub stats: GovernorStats,
+    pub exemptions: DedupExemptions,
+}
+
+/// Fields left out are unchanged; `exemptions` replaces the whole set
+#[derive(Debug, Deserialize)]
+pub struct GovernorUpdate {
+    pub epsilon: Option<f32>,
+    pub binary_quantization: Option<bool>,
+    pub exemptions: Option<DedupExemptions>,
+}
+
+fn governor(state: &AppState) -> Result<&Arc<ShardedGovernor>, ApiError> {
+    state
+        .semantic_governor
+        .as_ref()
+        .ok_or_else(|| ApiError::NotFound("Semantic governor is not enabled".to_string()))
+}
+
+fn response(governor: &ShardedGovernor) -> GovernorResponse {
+    GovernorResponse {
+        stats: governor.stats(),
+        exemptions: governor.exemptions(),
+    }
+}
+
+/// GET /api/v1/admin/governor
+pub async fn get_governor(
+    State(state): State<AppState>,
+    axum::Extension(_auth): axum::Extension<AuthContext>,
+) -> Result<Json<GovernorResponse>, ApiError> {
+    Ok(Json(response(governor(&state)?)))
+}
+
+/// PATCH /api/v1/admin/governor
+pub async fn update_governor(
+    State(state): State<AppState>,
+    axum::Extension(auth): axum::Extension<AuthContext>,
+    Json(update): Json<GovernorUpdate>,
+) -> Result<Json<GovernorResponse>, ApiError> {
+    // The governor is shared by every tenant
+    auth.require_role(Role::Admin)?;
+    let governor = governor(&state)?;
+
+    if let Some(epsilon) = update.epsilon {
+        governor
+            .set_epsilon(epsilon)
+            .map_err(ApiError::BadRequest)?;
+    }
+    if let Some(enabled) = update.binary_quantization {
+        let governor = Arc::clone(governor);
+        // Quantizing every stored embedding takes each shard's write lock
+        tokio::task::spawn_blocking(move || governor.set_binary_quantization(enabled))
+            .await
+            .map_err(|e| ApiError::Internal(format!("Failed to toggle quantization: {}", e)))?;
+    }
+    if let Some(exemptions) = update.exemptions {
+        governor.set_exemptions(exemptions);
+    }
+
+    Ok(Json(response(governor)))
+}
@@ -0,0 +1,376 @@
+// Copyright 2025 AgentReplay (https://github.com/agentreplay)
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program. If not, see <https://www.gnu.org/licenses/>.
+
+//! Streaming top-K heavy hitters (most expensive prompts, tools, sessions)
+//!
+//! Every stored span adds its tokens and cost to a [`CountMinSketch`] per
+//! tenant, dimension and metric. Next to each sketch a bounded candidate set
+//! keeps the keys with the highest estimates, so the top-K is answered in
+//! O(K) memory no matter how many distinct prompts or sessions are seen.
+//! Estimates are upper bounds (the sketch only overcounts) and cover the
+//! spans ingested since the server started.
+//!
+//! Served at `GET /api/v1/analytics/top?dimension=tool&metric=cost`.
+
+use std::collections::HashMap;
+
+use agentreplay_core::clock::now_us;
+use agentreplay_core::AgentFlowEdge;
+use agentreplay_storage::CountMinSketch;
+use axum::{
+    extract::{Query, State},
+    Json,
+};
+use parking_lot::Mutex;
+use serde::{Deserialize, Serialize};
+
+use crate::api::query::ApiError;
+use crate::api::AppState;
+use crate::auth::AuthContext;
+use crate::otel_genai::{attrs, GenAIPayload, ModelPricing};
+use crate::session_analysis::{ATTR_PROMPT_NAME, ATTR_PROMPT_VERSION};
+
+/// Sketch dimensions (64 KiB per tenant, dimension and metric)
+const SKETCH_WIDTH: usize = 2048;
+const SKETCH_DEPTH: usize = 4;
+
+/// Candidate keys kept per sketch; also the largest `k` served
+pub const MAX_TOP_K: usize = 100;
+const DEFAULT_TOP_K: usize = 10;
+
+/// Cost is accumulated in micro-dollars
+const COST_SCALE: f64 = 1_000_000.0;
+
+/// What a heavy hitter is ranked by
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum HitterDimension {
+    /// Prompt template (`agentreplay.prompt.name@version`)
+    Prompt,
+    /// Tool (`gen_ai.tool.name`)
+    Tool,
+    Session,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum HitterMetric {
+    Tokens,
+    /// USD
+    Cost,
+}
+
+impl HitterMetric {
+    fn from_units(self, units: u64) -> f64 {
+        match self {
+            HitterMetric::Tokens => units as f64,
+            HitterMetric::Cost => units as f64 / COST_SCALE,
+        }
+    }
+}
+
+/// Count-Min Sketch plus the candidates with the highest estimates
+struct StreamingTopK {
+    sketch: CountMinSketch,
+    candidates: HashMap<String, u64>,
+}
+
+impl StreamingTopK {
+    fn new() -> Self {
+        Self {
+            sketch: CountMinSketch::with_dimensions(SKETCH_WIDTH, SKETCH_DEPTH),
+            candidates: HashMap::with_capacity(MAX_TOP_K),
+        }
+    }
+
+    fn add(&mut self, key: &str, weight: u64) {
+        self.sketch.add_count(&key, weight);
+        let estimate = self.sketch.estimate(&key);
+        if let Some(current) = self.candidates.get_mut(key) {
+            *current = estimate;
+            return;
+        }
+        if self.candidates.len() < MAX_TOP_K {
+            self.candidates.insert(key.to_string(), estimate);
+            return;
+        }
+        let Some((min_key, min_estimate)) = self
+            .candidates
+            .iter()
+            .min_by_key(|(_, estimate)| **estimate)
+            .map(|(k, v)| (k.clone(), *v))
+        else {
+            return;
+        };
+        if estimate > min_estimate {
+            self.candidates.remove(&min_key);
+            self.candidates.insert(key.to_string(), estimate);
+        }
+    }
+
+    fn top(&self, k: usize) -> Vec<(String, u64)> {
+        let mut top: Vec<(String, u64)> = self
+            .candidates
+            .iter()
+            .map(|(key, estimate)| (key.clone(), *estimate))
+            .collect();
+        top.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+        top.truncate(k);
+        top
+    }
+}
+
+/// Per-tenant streaming top-K of prompts, tools and sessions
+pub struct HeavyHitters {
+    since_us: u64,
+    trackers: Mutex<HashMap<(u64, HitterDimension, HitterMetric), StreamingTopK>>,
+}
+
+impl Default for HeavyHitters {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl HeavyHitters {
+    pub fn new() -> Self {
+        Self {
+            since_us: now_us(),
+            trackers: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Add a stored span's tokens and cost to its prompt, tool and session
+    pub fn record(&self, edge: &AgentFlowEdge, attributes: &HashMap<String, String>) {
+        let mut keys = Vec::with_capacity(3);
+        if let Some(name) = attributes.get(ATTR_PROMPT_NAME) {
+            let key = match attributes.get(ATTR_PROMPT_VERSION) {
+                Some(version) => format!("{}@{}", name, version),
+                None => name.clone(),
+            };
+            keys.push((HitterDimension::Prompt, key));
+        }
+        if let Some(tool) = attributes.get(attrs::GEN_AI_TOOL_NAME) {
+            keys.push((HitterDimension::Tool, tool.clone()));
+        }
+        if edge.session_id != 0 {
+            keys.push((HitterDimension::Session, edge.session_id.to_string()));
+        }
+        if keys.is_empty() {
+            return;
+        }
+
+        let payload = GenAIPayload::from_attributes(attributes);
+        let (input, output, _) = payload.get_token_counts();
+        let tokens = payload
+            .total_tokens
+            .unwrap_or(input + output)
+            .max(edge.token_count) as u64;
+        let model = payload
+            .response_model
+            .as_deref()
+            .or(payload.request_model.as_deref());
+        let cost = model
+            .map(|model| {
+                let system = payload.system.as_deref().unwrap_or("unknown");
+                payload.calculate_cost(&ModelPricing::for_model(system, model))
+            })
+            .unwrap_or(0.0);
+        let cost_units = (cost * COST_SCALE).round() as u64;
+
+        let mut trackers = self.trackers.lock();
+        for (dimension, key) in keys {
+            for (metric, weight) in [
+                (HitterMetric::Tokens, tokens),
+                (HitterMetric::Cost, cost_units),
+            ] {
+                if weight == 0 {
+                    continue;
+                }
+                trackers
+                    .entry((edge.tenant_id, dimension, metric))
+                    .or_insert_with(StreamingTopK::new)
+                    .add(&key, weight);
+            }
+        }
+    }
+
+    /// Top `k` keys of a tenant with their estimated totals and the grand total
+    pub fn top(
+        &self,
+        tenant_id: u64,
+        dimension: HitterDimension,
+        metric: HitterMetric,
+        k: usize,
+    ) -> (Vec<(String, f64)>, f64) {
+        let trackers = self.trackers.lock();
+        let Some(tracker) = trackers.get(&(tenant_id, dimension, metric)) else {
+            return (Vec::new(), 0.0);
+        };
+        let top = tracker
+            .top(k)
+            .into_iter()
+            .map(|(key, units)| (key, metric.from_units(units)))
+            .collect();
+        (top, metric.from_units(tracker.sketch.total()))
+    }
+}
+
+#[derive(Debug, Deserialize)]
+pub struct TopParams {
+    pub dimension: HitterDimension,
+    #[serde(default = "default_metric")]
+    pub metric: HitterMetric,
+    pub k: Option<usize>,
+}
+
+fn default_metric() -> HitterMetric {
+    HitterMetric::Cost
+}
+
+#[derive(Debug, Serialize)]
+pub struct HeavyHitter {
+    pub key: String,
+    /// Estimated total (upper bound): tokens, or USD for `cost`
+    pub value: f64,
+    /// Share of the tenant's total
+    pub share: f64,
+}
+
+#[derive(Debug, Serialize)]
+pub struct TopResponse {
+    pub dimension: HitterDimension,
+    pub metric: HitterMetric,
+    /// Start of the tracked stream (server start, microseconds)
+    pub since_us: u64,
+    pub total: f64,
+    pub items: Vec<HeavyHitter>,
+}
+
+/// GET /api/v1/analytics/top?dimension=tool&metric=cost
+pub async fn get_top_heavy_hitters(
+    State(state): State<AppState>,
+    Query(params): Query<TopParams>,
+    axum::Extension(auth): axum::Extension<AuthContext>,
+) -> Result<Json<TopResponse>, ApiError> {
+    let k = params.k.unwrap_or(DEFAULT_TOP_K);
+    if k == 0 || k > MAX_TOP_K {
+        return Err(ApiError::BadRequest(format!(
+            "k must be between 1 and {}",
+            MAX_TOP_K
+        )));
+    }
+
+    let (top, total) = state
+        .heavy_hitters
+        .top(auth.tenant_id, params.dimension, params.metric, k);
+    let items = top
+        .into_iter()
+        .map(|(key, value)| HeavyHitter {
+            key,
+            value,
+            share: if total > 0.0 {
+                (value / total).min(1.0)
+            } else {
+                0.0
+            },
+        })
+        .collect();
+
+    Ok(Json(TopResponse {
+        dimension: params.dimension,
+        metric: params.metric,
+        since_us: state.heavy_hitters.since_us,
+        total,
+        items,
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn span_attrs(pairs: &[(&str, &str)]) -> HashMap<String, String> {
+        pairs
+            .iter()
+            .map(|(k, v)| (k.to_string(), v.to_string()))
+            .collect()
+    }
+
+    #[test]
+    fn test_streaming_top_k_skewed() {
+        let mut tracker = StreamingTopK::new();
+        // Long tail of cheap keys around a few heavy ones
+        for i in 0..5_000u64 {
+            tracker.add(&format!("tail-{}", i), 1);
+            if i % 10 == 0 {
+                tracker.add("heavy-a", 30);
+                tracker.add("heavy-b", 20);
+            }
+        }
+        let top = tracker.top(2);
+        assert_eq!(top[0].0, "heavy-a");
+        assert_eq!(top[1].0, "heavy-b");
+        assert!(top[0].1 >= 15_000);
+        assert!(tracker.candidates.len() <= MAX_TOP_K);
+    }
+
+    #[test]
+    fn test_record_dimensions() {
+        let hitters = HeavyHitters::new();
+        let mut edge = AgentFlowEdge {
+            tenant_id: 7,
+            session_id: 42,
+            ..Default::default()
+        };
+        let expensive = span_attrs(&[
+            (ATTR_PROMPT_NAME, "support"),
+            (ATTR_PROMPT_VERSION, "3"),
+            (attrs::GEN_AI_TOOL_NAME, "web_search"),
+            (attrs::GEN_AI_SYSTEM, "openai"),
+            (attrs::GEN_AI_REQUEST_MODEL, "gpt-4o"),
+            (attrs::GEN_AI_USAGE_INPUT_TOKENS, "10000"),
+            (attrs::GEN_AI_USAGE_OUTPUT_TOKENS, "2000"),
+        ]);
+        hitters.record(&edge, &expensive);
+        edge.session_id = 43;
+        let cheap = span_attrs(&[
+            (attrs::GEN_AI_TOOL_NAME, "calculator"),
+            (attrs::GEN_AI_USAGE_TOTAL_TOKENS, "50"),
+        ]);
+        hitters.record(&edge, &cheap);
+
+        let (tools, total) = hitters.top(7, HitterDimension::Tool, HitterMetric::Tokens, 10);
+        assert_eq!(tools[0], ("web_search".to_string(), 12_000.0));
+        assert_eq!(tools[1], ("calculator".to_string(), 50.0));
+        assert_eq!(total, 12_050.0);
+
+        // Only the span with a model has a cost
+        let (tools, _) = hitters.top(7, HitterDimension::Tool, HitterMetric::Cost, 10);
+        assert_eq!(tools.len(), 1);
+        assert!(tools[0].1 > 0.0);
+
+        let (prompts, _) = hitters.top(7, HitterDimension::Prompt, HitterMetric::Tokens, 10);
+        assert_eq!(prompts[0].0, "support@3");
+        let (sessions, _) = hitters.top(7, HitterDimension::Session, HitterMetric::Tokens, 1);
+        assert_eq!(sessions, vec![("42".to_string(), 12_000.0)]);
+
+        // Other tenants see nothing
+        assert!(hitters
+            .top(8, HitterDimension::Tool, HitterMetric::Tokens, 10)
+            .0
+            .is_empty());
+    }
+}
@@ -38,7 +38,7 @@
 use std::sync::atomic::{AtomicU64, Ordering};
 use std::sync::Arc;
 use std::time::{Duration, Instant};
-use tokio::sync::{mpsc, oneshot};
+use tokio::sync::{mpsc, oneshot, watch};
 use tracing::{debug, info};
 
 use crate::governor::{GovernorDecision, ShardedGovernor};
@@ -72,6 +72,9 @@ impl Default for IngestionConfig {
 pub struct TracePayload {
     /// Unique trace ID
     pub trace_id: u128,
+    /// Project and agent, checked against the governor's dedup exemptions
+    pub project_id: u16,
+    pub agent_id: u64,
     /// Text content for embedding (prompt + completion)
     pub text: String,
     /// Full payload for storage
@@ -120,6 +123,8 @@ pub struct IngestionStats {
 pub struct IngestionActorHandle {
     sender: mpsc::Sender<IngestMessage>,
     stats: Arc<IngestionStatsInternal>,
+    shutdown: Arc<watch::Sender<bool>>,
+    stopped: watch::Receiver<bool>,
 }
 
 impl Clone for IngestionActorHandle {
@@ -127,6 +132,8 @@ impl Clone for IngestionActorHandle {
         Self {
             sender: self.sender.clone(),
             stats: self.stats.clone(),
+            shutdown: self.shutdown.clone(),
+            stopped: self.stopped.clone(),
         }
     }
 }
@@ -219,6 +226,26 @@ impl IngestionActorHandle {
     pub fn stats(&self) -> IngestionStats {
         self.stats.snapshot()
     }
+
+    /// Number of traces waiting in the actor channel.
+    pub fn queue_depth(&self) -> usize {
+        self.sender.max_capacity() - self.sender.capacity()
+    }
+
+    /// Capacity of the actor channel.
+    pub fn queue_capacity(&self) -> usize {
+        self.sender.max_capacity()
+    }
+
+    /// Stop accepting traces and wait for the queued ones to be processed.
+    ///
+    /// Returns false if the actor is still draining after `timeout`.
+    pub async fn shutdown(&self, timeout: Duration) -> bool {
+        self.shutdown.send_replace(true);
+        let mut stopped = self.stopped.clone();
+        let drained = tokio::time::timeout(timeout, stopped.wait_for(|stopped| *stopped)).await;
+        drained.is_ok()
+    }
 }
 
 /// The Ingestion Actor - runs as a background task.
@@ -256,20 +283,29 @@ impl IngestionActor {
     pub fn spawn(self) -> IngestionActorHandle {
         let (sender, receiver) = mpsc::channel(self.config.channel_capacity);
         let stats = Arc::new(IngestionStatsInternal::new());
+        let (shutdown_tx, shutdown_rx) = watch::channel(false);
+        let (stopped_tx, stopped_rx) = watch::channel(false);
 
         // Spawn the actor task
         let actor_stats = stats.clone();
         tokio::spawn(async move {
-            self.run(receiver, actor_stats).await;
+            self.run(receiver, shutdown_rx, actor_stats).await;
+            stopped_tx.send_replace(true);
         });
 
-        IngestionActorHandle { sender, stats }
+        IngestionActorHandle {
+            sender,
+            stats,
+            shutdown: Arc::new(shutdown_tx),
+            stopped: stopped_rx,
+        }
     }
 
     /// Main actor loop.
     async fn run(
         self,
         mut receiver: mpsc::Receiver<IngestMessage>,
+        mut shutdown: watch::Receiver<bool>,
         stats: Arc<IngestionStatsInternal>,
     ) {
         info!(
@@ -280,15 +316,29 @@ impl IngestionActor {
 
         let mut batch: Vec<IngestMessage> = Vec::with_capacity(self.config.max_batch_size);
         let mut batch_start = Instant::now();
+        let mut draining = false;
 
         loop {
+            if !draining && *shutdown.borrow() {
+                // Refuse new traces; those already queued are still received
+                receiver.close();
+                draining = true;
+                info!(
+                    "Ingestion actor draining {} queued traces",
+                    receiver.len() + batch.len()
+                );
+            }
+
             // Calculate remaining wait time for this batch
             let elapsed = batch_start.elapsed();
             let remaining = self.config.max_wait_time.saturating_sub(elapsed);
 
             let msg = if batch.is_empty() {
-                // No batch in progress, wait indefinitely for first message
-                receiver.recv().await
+                // No batch in progress, wait for the first message or shutdown
+                tokio::select! {
+                    msg = receiver.recv() => msg,
+                    Ok(()) = shutdown.changed(), if !draining => continue,
+                }
             } else if remaining.is_zero() {
                 // Timeout expired, process current batch
                 None
@@ -343,11 +393,22 @@ impl IngestionActor {
         // Step 1: Generate embeddings for all traces
         let embeddings = self.generate_embeddings(batch).await;
 
-        // Step 2: Process through governor in parallel
+        // Step 2: Process through governor in parallel (exempt traces skip it)
+        let exempt: Vec<bool> = batch
+            .iter()
+            .map(|msg| {
+                self.governor
+                    .is_exempt(msg.payload.project_id, msg.payload.agent_id)
+            })
+            .collect();
         let items: Vec<(u128, Vec<f32>)> = batch
             .iter()
             .zip(embeddings.iter())
-            .filter_map(|(msg, emb)| emb.as_ref().ok().map(|e| (msg.payload.trace_id, e.clone())))
+            .zip(exempt.iter())
+            .filter(|(_, exempt)| !**exempt)
+            .filter_map(|((msg, emb), _)| {
+                emb.as_ref().ok().map(|e| (msg.payload.trace_id, e.clone()))
+            })
             .collect();
 
         let decisions = self.governor.process_batch(items).await;
@@ -355,7 +416,7 @@ impl IngestionActor {
         // Step 3: Send results back to callers
         let mut decision_iter = decisions.into_iter();
 
-        for (msg, emb_result) in batch.drain(..).zip(embeddings.into_iter()) {
+        for ((msg, emb_result), exempt) in batch.drain(..).zip(embeddings.into_iter()).zip(exempt) {
             let result = match emb_result {
                 Err(e) => {
                     stats.failed.fetch_add(1, Ordering::Relaxed);
@@ -364,6 +425,14 @@ impl IngestionActor {
                         error: e,
                     }
                 }
+                Ok(embedding) if exempt => {
+                    self.governor.record_exempt();
+                    stats.stored.fetch_add(1, Ordering::Relaxed);
+                    IngestionResult::Stored {
+                        trace_id: msg.payload.trace_id,
+                        embedding,
+                    }
+                }
                 Ok(embedding) => {
                     match decision_iter.next() {
                         Some(GovernorDecision::Store { trace_id }) => {
@@ -493,6 +562,8 @@ mod tests {
         let payloads: Vec<_> = (0..10)
             .map(|i| TracePayload {
                 trace_id: i as u128,
+                project_id: 0,
+                agent_id: 0,
                 text: format!("Test trace {}", i),
                 payload: serde_json::json!({"test": i}),
             })
@@ -534,11 +605,15 @@ mod tests {
         // Submit the same text twice
         let payload1 = TracePayload {
             trace_id: 1,
+            project_id: 0,
+            agent_id: 0,
             text: "Same content here".to_string(),
             payload: serde_json::json!({}),
         };
         let payload2 = TracePayload {
             trace_id: 2,
+            project_id: 0,
+            agent_id: 0,
             text: "Same content here".to_string(),
             payload: serde_json::json!({}),
         };
@@ -551,6 +626,42 @@ mod tests {
         assert!(matches!(result2, IngestionResult::Deduplicated { .. }));
     }
 
+    #[tokio::test]
+    async fn test_exempt_agent_skips_dedup() {
+        let governor = ShardedGovernor::new_shared(GovernorConfig {
+            epsilon: 0.1,
+            dimension: 128,
+            ..Default::default()
+        });
+        governor.set_exemptions(crate::governor::DedupExemptions {
+            agents: [7].into(),
+            projects: Default::default(),
+        });
+
+        let actor_config = IngestionConfig {
+            max_batch_size: 2,
+            max_wait_time: Duration::from_millis(10),
+            channel_capacity: 100,
+            embedding_dimension: 128,
+        };
+        let handle = IngestionActor::new(actor_config, governor.clone(), None).spawn();
+
+        for trace_id in 1..=2 {
+            let result = handle
+                .ingest(TracePayload {
+                    trace_id,
+                    project_id: 0,
+                    agent_id: 7,
+                    text: "Same content here".to_string(),
+                    payload: serde_json::json!({}),
+                })
+                .await
+                .unwrap();
+            assert!(matches!(result, IngestionResult::Stored { .. }));
+        }
+        assert_eq!(governor.stats().exempted, 2);
+    }
+
     #[tokio::test]
     async fn test_batch_timeout() {
         let governor_config = GovernorConfig {
@@ -573,6 +684,8 @@ mod tests {
         // Submit just one trace (won't fill batch)
         let payload = TracePayload {
             trace_id: 1,
+            project_id: 0,
+            agent_id: 0,
             text: "Single trace".to_string(),
             payload: serde_json::json!({}),
         };
@@ -589,4 +702,57 @@ mod tests {
         let stats = handle.stats();
         assert_eq!(stats.total_batches, 1);
     }
+
+    #[tokio::test]
+    async fn test_shutdown_drains_queued_traces() {
+        let governor = ShardedGovernor::new_shared(GovernorConfig {
+            epsilon: 0.1,
+            dimension: 128,
+            ..Default::default()
+        });
+        let actor_config = IngestionConfig {
+            max_batch_size: 100,
+            max_wait_time: Duration::from_millis(200),
+            channel_capacity: 100,
+            embedding_dimension: 128,
+        };
+        let handle = IngestionActor::new(actor_config, governor, None).spawn();
+
+        // Queued behind a batch that is still waiting to fill
+        let pending: Vec<_> = (0..5)
+            .map(|i| {
+                let handle = handle.clone();
+                tokio::spawn(async move {
+                    handle
+                        .ingest(TracePayload {
+                            trace_id: i,
+                            project_id: 0,
+                            agent_id: 0,
+                            text: format!("Queued trace {}", i),
+                            payload: serde_json::json!({}),
+                        })
+                        .await
+                })
+            })
+            .collect();
+        tokio::time::sleep(Duration::from_millis(20)).await;
+
+        assert!(handle.shutdown(Duration::from_secs(5)).await);
+        for task in pending {
+            assert!(task.await.unwrap().is_ok());
+        }
+        assert_eq!(handle.stats().total_received, 5);
+
+        // New traces are refused once the actor has stopped
+        let refused = handle
+            .ingest(TracePayload {
+                trace_id: 99,
+                project_id: 0,
+                agent_id: 0,
+                text: "Late trace".to_string(),
+                payload: serde_json::json!({}),
+            })
+            .await;
+        assert!(refused.is_err());
+    }
 }
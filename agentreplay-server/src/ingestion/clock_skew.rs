@@ -0,0 +1,373 @@
+// Copyright 2025 AgentReplay (https://github.com/agentreplay)
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program. If not, see <https://www.gnu.org/licenses/>.
+
+//! Clock-skew detection and correction
+//!
+//! Spans from different hosts carry timestamps from different clocks, so a
+//! child recorded on a host whose clock lags can appear to start before its
+//! parent, and a host whose clock runs ahead produces spans "from the future".
+//! Both break causal ordering in timelines and replays.
+//!
+//! Each batch is checked in two passes:
+//!
+//! 1. **Future skew**: the span's start is fed to the server's
+//!    [`HybridLogicalClock`]. When the HLC refuses to adopt it (beyond the drift
+//!    tolerance) the span is shifted back so it ends at server time, and the
+//!    offset is remembered for that host so its other spans line up too.
+//! 2. **Causal skew**: a child starting before its parent (by more than the
+//!    tolerance) is shifted forward to the parent's start.
+//!
+//! Corrected spans keep their original timestamps in `agentreplay.clock_skew.*`
+//! attributes, which are stored with the payload.
+
+use crate::api::ingest::AgentreplaySpan;
+use agentreplay_core::{HlcTimestamp, HybridLogicalClock};
+use parking_lot::Mutex;
+use serde::Serialize;
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::{Duration, Instant};
+
+/// Set to "true" on spans whose timestamps were corrected
+pub const ATTR_SKEW_CORRECTED: &str = "agentreplay.clock_skew.corrected";
+/// Signed shift applied to start/end, in microseconds
+pub const ATTR_SKEW_OFFSET_US: &str = "agentreplay.clock_skew.offset_us";
+/// Why the span was corrected: "future" or "causal"
+pub const ATTR_SKEW_REASON: &str = "agentreplay.clock_skew.reason";
+/// Start time as reported by the client
+pub const ATTR_ORIGINAL_START: &str = "agentreplay.clock_skew.original_start_time";
+/// End time as reported by the client (absent for open spans)
+pub const ATTR_ORIGINAL_END: &str = "agentreplay.clock_skew.original_end_time";
+
+/// How long a detected host offset keeps being applied without being re-observed
+const HOST_OFFSET_TTL: Duration = Duration::from_secs(600);
+
+/// Attributes identifying the host that produced a span, in order of preference
+const HOST_ATTRS: &[&str] = &["host.name", "service.instance.id", "host"];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum SkewReason {
+    Future,
+    Causal,
+}
+
+impl SkewReason {
+    fn as_str(self) -> &'static str {
+        match self {
+            SkewReason::Future => "future",
+            SkewReason::Causal => "causal",
+        }
+    }
+}
+
+/// Counters exposed alongside ingestion metrics
+#[derive(Debug, Clone, Serialize)]
+pub struct ClockSkewStats {
+    pub tolerance_ms: u64,
+    pub future_corrections: u64,
+    pub causal_corrections: u64,
+    /// Hosts with an active clock offset, and that offset in microseconds
+    pub host_offsets_us: HashMap<String, i64>,
+}
+
+/// Detects and corrects clock skew in ingested span batches
+pub struct ClockSkewCorrector {
+    hlc: HybridLogicalClock,
+    tolerance_us: i64,
+    host_offsets: Mutex<HashMap<String, (i64, Instant)>>,
+    future_corrections: AtomicU64,
+    causal_corrections: AtomicU64,
+}
+
+impl ClockSkewCorrector {
+    /// Create a corrector that tolerates `tolerance` of skew before correcting
+    pub fn new(tolerance: Duration) -> Self {
+        Self {
+            hlc: HybridLogicalClock::with_max_drift(tolerance.as_millis() as u64),
+            tolerance_us: tolerance.as_micros() as i64,
+            host_offsets: Mutex::new(HashMap::new()),
+            future_corrections: AtomicU64::new(0),
+            causal_corrections: AtomicU64::new(0),
+        }
+    }
+
+    /// Correct skewed timestamps in place, returning the number of corrected spans
+    pub fn correct_batch(&self, spans: &mut [AgentreplaySpan]) -> usize {
+        let mut offsets: Vec<Option<(i64, SkewReason)>> = vec![None; spans.len()];
+
+        // Pass 1: spans ahead of server time, and hosts known to run ahead
+        for (idx, span) in spans.iter().enumerate() {
+            offsets[idx] = self.future_offset(span).map(|o| (o, SkewReason::Future));
+        }
+
+        // Pass 2: children that start before their (corrected) parent
+        self.resolve_causal(spans, &mut offsets);
+
+        let mut corrected = 0;
+        for (span, offset) in spans.iter_mut().zip(offsets) {
+            let Some((offset_us, reason)) = offset else {
+                continue;
+            };
+            if offset_us == 0 {
+                continue;
+            }
+            annotate(span, offset_us, reason);
+            let counter = match reason {
+                SkewReason::Future => &self.future_corrections,
+                SkewReason::Causal => &self.causal_corrections,
+            };
+            counter.fetch_add(1, Ordering::Relaxed);
+            corrected += 1;
+        }
+        corrected
+    }
+
+    pub fn stats(&self) -> ClockSkewStats {
+        let host_offsets = self.host_offsets.lock();
+        ClockSkewStats {
+            tolerance_ms: (self.tolerance_us / 1000) as u64,
+            future_corrections: self.future_corrections.load(Ordering::Relaxed),
+            causal_corrections: self.causal_corrections.load(Ordering::Relaxed),
+            host_offsets_us: host_offsets
+                .iter()
+                .filter(|(_, (_, seen))| seen.elapsed() < HOST_OFFSET_TTL)
+                .map(|(host, (offset, _))| (host.clone(), *offset))
+                .collect(),
+        }
+    }
+
+    /// Offset (negative) needed to pull a future-dated span back to server time
+    fn future_offset(&self, span: &AgentreplaySpan) -> Option<i64> {
+        let host = host_of(span);
+        let remote = HlcTimestamp::from_parts(span.start_time / 1000, 0);
+        let merged = self.hlc.receive(remote);
+
+        if merged.wall_time_ms() < remote.wall_time_ms() {
+            // HLC refused the timestamp: the host clock is beyond the drift bound
+            let latest = span
+                .end_time
+                .unwrap_or(span.start_time)
+                .max(span.start_time);
+            let offset = merged.wall_time_us() as i64 - latest as i64;
+            if let Some(host) = host {
+                self.host_offsets
+                    .lock()
+                    .insert(host.to_string(), (offset, Instant::now()));
+            }
+            return Some(offset);
+        }
+
+        // Within bounds on its own, but the host has been seen running ahead
+        let host = host?;
+        let mut host_offsets = self.host_offsets.lock();
+        match host_offsets.get(host) {
+            Some((_, seen)) if seen.elapsed() >= HOST_OFFSET_TTL => {
+                host_offsets.remove(host);
+                None
+            }
+            Some((offset, _)) => Some(*offset),
+            None => None,
+        }
+    }
+
+    /// Shift spans starting before their parent, parents first
+    ///
+    /// Each span is resolved once: the walk up from a span stops at the
+    /// first ancestor already resolved, and a parent chain that loops back
+    /// on itself is treated as starting at the span that closes the loop.
+    fn resolve_causal(&self, spans: &[AgentreplaySpan], offsets: &mut [Option<(i64, SkewReason)>]) {
+        let index: HashMap<&str, usize> = spans
+            .iter()
+            .enumerate()
+            .map(|(idx, span)| (span.span_id.as_str(), idx))
+            .collect();
+        let parent_of = |idx: usize| {
+            spans[idx]
+                .parent_span_id
+                .as_deref()
+                .and_then(|parent| index.get(parent).copied())
+        };
+
+        // Corrected start of every resolved span
+        let mut starts: Vec<Option<i64>> = vec![None; spans.len()];
+        let mut on_path = vec![false; spans.len()];
+        let mut path = Vec::new();
+        for first in 0..spans.len() {
+            // Unresolved ancestors of `first`, nearest first
+            let mut idx = first;
+            while starts[idx].is_none() && !on_path[idx] {
+                on_path[idx] = true;
+                path.push(idx);
+                match parent_of(idx) {
+                    Some(parent) => idx = parent,
+                    None => break,
+                }
+            }
+            let mut parent_start = match path.last() {
+                Some(&top) if top != idx => starts[idx],
+                _ => None,
+            };
+
+            for &idx in path.iter().rev() {
+                let own_offset = offsets[idx].map(|(o, _)| o).unwrap_or(0);
+                let mut start = spans[idx].start_time as i64 + own_offset;
+                if let Some(parent_start) = parent_start {
+                    if parent_start - start > self.tolerance_us {
+                        let offset = own_offset + (parent_start - start);
+                        offsets[idx] = Some((offset, SkewReason::Causal));
+                        start = parent_start;
+                    }
+                }
+                starts[idx] = Some(start);
+                on_path[idx] = false;
+                parent_start = Some(start);
+            }
+            path.clear();
+        }
+    }
+}
+
+impl Default for ClockSkewCorrector {
+    fn default() -> Self {
+        Self::new(Duration::from_secs(1))
+    }
+}
+
+fn host_of(span: &AgentreplaySpan) -> Option<&str> {
+    HOST_ATTRS
+        .iter()
+        .find_map(|attr| span.attributes.get(*attr))
+        .map(String::as_str)
+}
+
+fn shift(ts: u64, offset_us: i64) -> u64 {
+    (ts as i64).saturating_add(offset_us).max(0) as u64
+}
+
+fn annotate(span: &mut AgentreplaySpan, offset_us: i64, reason: SkewReason) {
+    span.attributes
+        .insert(ATTR_SKEW_CORRECTED.to_string(), "true".to_string());
+    span.attributes
+        .insert(ATTR_SKEW_OFFSET_US.to_string(), offset_us.to_string());
+    span.attributes
+        .insert(ATTR_SKEW_REASON.to_string(), reason.as_str().to_string());
+    span.attributes
+        .insert(ATTR_ORIGINAL_START.to_string(), span.start_time.to_string());
+    if let Some(end) = span.end_time {
+        span.attributes
+            .insert(ATTR_ORIGINAL_END.to_string(), end.to_string());
+    }
+
+    span.start_time = shift(span.start_time, offset_us);
+    span.end_time = span.end_time.map(|end| shift(end, offset_us));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use agentreplay_core::clock::now_us;
+
+    fn span(id: &str, parent: Option<&str>, start: u64, host: &str) -> AgentreplaySpan {
+        AgentreplaySpan {
+            span_id: id.to_string(),
+            trace_id: "0x1".to_string(),
+            parent_span_id: parent.map(String::from),
+            name: "step".to_string(),
+            start_time: start,
+            end_time: Some(start + 1_000),
+            attributes: HashMap::from([("host.name".to_string(), host.to_string())]),
+        }
+    }
+
+    #[test]
+    fn test_child_before_parent_is_shifted() {
+        let corrector = ClockSkewCorrector::default();
+        let now = now_us();
+        let mut spans = vec![
+            // Child from a host lagging 30s behind, sent before its parent
+            span("0x2", Some("0x1"), now - 30_000_000, "worker"),
+            span("0x1", None, now - 1_000, "api"),
+        ];
+
+        assert_eq!(corrector.correct_batch(&mut spans), 1);
+        assert_eq!(spans[0].start_time, now - 1_000);
+        assert_eq!(spans[0].attributes[ATTR_SKEW_REASON], "causal");
+        assert_eq!(
+            spans[0].attributes[ATTR_ORIGINAL_START],
+            (now - 30_000_000).to_string()
+        );
+        assert!(!spans[1].attributes.contains_key(ATTR_SKEW_CORRECTED));
+    }
+
+    #[test]
+    fn test_future_span_is_pulled_back_and_host_remembered() {
+        let corrector = ClockSkewCorrector::default();
+        let now = now_us();
+        let mut spans = vec![span("0x1", None, now + 300_000_000, "fast-host")];
+
+        assert_eq!(corrector.correct_batch(&mut spans), 1);
+        assert!(spans[0].end_time.unwrap() <= now_us());
+        assert_eq!(spans[0].attributes[ATTR_SKEW_REASON], "future");
+        assert!(corrector.stats().host_offsets_us.contains_key("fast-host"));
+
+        // A later span from the same host is shifted by the remembered offset
+        let mut later = vec![span("0x2", None, now_us(), "fast-host")];
+        assert_eq!(corrector.correct_batch(&mut later), 1);
+        assert!(later[0].start_time < now);
+    }
+
+    #[test]
+    fn test_in_order_spans_untouched() {
+        let corrector = ClockSkewCorrector::default();
+        let now = now_us();
+        let mut spans = vec![
+            span("0x1", None, now - 5_000, "api"),
+            span("0x2", Some("0x1"), now - 4_000, "worker"),
+        ];
+
+        assert_eq!(corrector.correct_batch(&mut spans), 0);
+        assert_eq!(spans[1].start_time, now - 4_000);
+    }
+
+    #[test]
+    fn test_deep_and_cyclic_chains() {
+        let corrector = ClockSkewCorrector::default();
+        let now = now_us();
+        // Each span starts 2s before its parent, children sent first
+        let depth = 50_000u64;
+        let mut spans: Vec<AgentreplaySpan> = (1..=depth)
+            .rev()
+            .map(|n| {
+                let parent = (n > 1).then(|| format!("0x{:x}", n - 1));
+                let start = now - 3_600_000_000 - n * 2_000_000;
+                span(&format!("0x{:x}", n), parent.as_deref(), start, "worker")
+            })
+            .collect();
+
+        assert_eq!(corrector.correct_batch(&mut spans), depth as usize - 1);
+        let root_start = spans[depth as usize - 1].start_time;
+        assert!(spans.iter().all(|s| s.start_time == root_start));
+
+        // Parents that loop back on each other still get an order
+        let mut cycle = vec![
+            span("0xa", Some("0xb"), now - 10_000_000, "worker"),
+            span("0xb", Some("0xa"), now - 5_000_000, "worker"),
+            span("0xc", Some("0xc"), now - 1_000, "worker"),
+        ];
+        assert_eq!(corrector.correct_batch(&mut cycle), 1);
+        assert_eq!(cycle[0].start_time, now - 5_000_000);
+        assert_eq!(cycle[2].start_time, now - 1_000);
+    }
+}
@@ -0,0 +1,283 @@
+// Copyright 2025 AgentReplay (https://github.com/agentreplay)
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program. If not, see <https://www.gnu.org/licenses/>.
+
+//! Attribute-based enrichment
+//!
+//! Derives structured fields from the raw attributes SDKs send — model family,
+//! normalized provider, client SDK and version, caller country — and writes
+//! them back as `agentreplay.*` attributes. The same fields are stored per edge
+//! as an [`EdgeEnrichment`] record so `list_traces` can filter on them without
+//! reading payloads.
+//!
+//! Values a client already set are never overwritten.
+
+use crate::api::ingest::AgentreplaySpan;
+use agentreplay_storage::EdgeEnrichment;
+use std::collections::HashMap;
+
+/// Model family derived from the model name (e.g. "gpt", "claude", "gemini")
+pub const ATTR_MODEL_FAMILY: &str = "agentreplay.model_family";
+/// Normalized provider (e.g. "openai", "anthropic", "google")
+pub const ATTR_PROVIDER: &str = "agentreplay.provider";
+/// Client SDK name
+pub const ATTR_SDK_NAME: &str = "agentreplay.sdk.name";
+/// Client SDK version
+pub const ATTR_SDK_VERSION: &str = "agentreplay.sdk.version";
+/// ISO 3166-1 alpha-2 country of the caller
+pub const ATTR_GEO_COUNTRY: &str = "agentreplay.geo.country";
+
+const MODEL_KEYS: [&str; 4] = [
+    "gen_ai.request.model",
+    "gen_ai.response.model",
+    "llm.model",
+    "model",
+];
+const PROVIDER_KEYS: [&str; 3] = ["gen_ai.system", "gen_ai.provider.name", "llm.provider"];
+const SDK_NAME_KEYS: [&str; 2] = ["telemetry.sdk.name", "sdk.name"];
+const SDK_VERSION_KEYS: [&str; 2] = ["telemetry.sdk.version", "sdk.version"];
+const COUNTRY_KEYS: [&str; 3] = [
+    "client.geo.country_iso_code",
+    "geo.country.iso_code",
+    "geo.country",
+];
+const ENVIRONMENT_KEYS: [&str; 3] = ["deployment.environment.name", "env", "stage"];
+
+/// Model name prefixes and the family they belong to
+const MODEL_FAMILIES: [(&str, &str); 13] = [
+    ("gpt", "gpt"),
+    ("chatgpt", "gpt"),
+    ("o1", "gpt"),
+    ("o3", "gpt"),
+    ("o4", "gpt"),
+    ("claude", "claude"),
+    ("gemini", "gemini"),
+    ("llama", "llama"),
+    ("mistral", "mistral"),
+    ("mixtral", "mistral"),
+    ("command", "command"),
+    ("deepseek", "deepseek"),
+    ("qwen", "qwen"),
+];
+
+/// Model family of a model name, if recognized
+///
+/// Vendor prefixes such as `anthropic.` (Bedrock) or `models/` (Gemini API)
+/// are ignored, so `anthropic.claude-3-haiku` maps to "claude".
+pub fn model_family(model: &str) -> Option<&'static str> {
+    let name = model.to_ascii_lowercase();
+    let name = name.rsplit(['/', '.', ':']).find(|part| {
+        MODEL_FAMILIES
+            .iter()
+            .any(|(prefix, _)| part.starts_with(prefix))
+    })?;
+    MODEL_FAMILIES
+        .iter()
+        .find(|(prefix, _)| {
+            name.strip_prefix(prefix)
+                .is_some_and(|rest| rest.is_empty() || !rest.starts_with(char::is_alphabetic))
+        })
+        .map(|(_, family)| *family)
+}
+
+/// Normalize a provider name (`gen_ai.system` value) to its canonical form
+pub fn normalize_provider(provider: &str) -> String {
+    let provider = provider.trim().to_ascii_lowercase();
+    match provider.as_str() {
+        "openai" | "open_ai" | "azure.openai" | "az.ai.openai" | "azure_openai" => "openai",
+        "anthropic" | "claude" => "anthropic",
+        "google" | "gemini" | "vertex_ai" | "vertexai" | "gcp.gemini" | "gcp.vertex_ai"
+        | "google_genai" => "google",
+        "aws.bedrock" | "bedrock" | "aws_bedrock" => "bedrock",
+        "mistral_ai" | "mistralai" | "mistral" => "mistral",
+        "cohere" => "cohere",
+        "meta" | "llama" => "meta",
+        _ => return provider,
+    }
+    .to_string()
+}
+
+/// Provider that serves a model family when the span does not name one
+fn provider_for_family(family: &str) -> Option<&'static str> {
+    match family {
+        "gpt" => Some("openai"),
+        "claude" => Some("anthropic"),
+        "gemini" => Some("google"),
+        "llama" => Some("meta"),
+        "mistral" => Some("mistral"),
+        "command" => Some("cohere"),
+        "deepseek" => Some("deepseek"),
+        "qwen" => Some("alibaba"),
+        _ => None,
+    }
+}
+
+/// SDK name and version from a User-Agent header
+///
+/// Uses the first `product/version` token, e.g. `agentreplay-python/0.3.1`.
+pub fn parse_user_agent(user_agent: &str) -> Option<(String, String)> {
+    let token = user_agent.split_whitespace().next()?;
+    let (name, version) = token.split_once('/')?;
+    if name.is_empty() || version.is_empty() {
+        return None;
+    }
+    Some((name.to_ascii_lowercase(), version.to_string()))
+}
+
+fn first_attr<'a>(attrs: &'a HashMap<String, String>, keys: &[&str]) -> Option<&'a str> {
+    keys.iter()
+        .filter_map(|key| attrs.get(*key))
+        .map(|value| value.trim())
+        .find(|value| !value.is_empty())
+}
+
+fn set_if_absent(attrs: &mut HashMap<String, String>, key: &str, value: impl Into<String>) {
+    attrs.entry(key.to_string()).or_insert_with(|| value.into());
+}
+
+/// Derive the enrichment attributes of one span
+///
+/// `user_agent` is the request's User-Agent header, used for the SDK fields
+/// when the span carries no `telemetry.sdk.*` attributes.
+pub fn enrich_span(span: &mut AgentreplaySpan, user_agent: Option<&str>) {
+    let attrs = &mut span.attributes;
+
+    let family = first_attr(attrs, &MODEL_KEYS).and_then(model_family);
+    if let Some(family) = family {
+        set_if_absent(attrs, ATTR_MODEL_FAMILY, family);
+    }
+
+    let provider = first_attr(attrs, &PROVIDER_KEYS)
+        .map(normalize_provider)
+        .or_else(|| family.and_then(provider_for_family).map(String::from));
+    if let Some(provider) = provider {
+        set_if_absent(attrs, ATTR_PROVIDER, provider);
+    }
+
+    let sdk = match first_attr(attrs, &SDK_NAME_KEYS) {
+        Some(name) => Some((
+            name.to_ascii_lowercase(),
+            first_attr(attrs, &SDK_VERSION_KEYS).map(String::from),
+        )),
+        None => user_agent
+            .and_then(parse_user_agent)
+            .map(|(name, version)| (name, Some(version))),
+    };
+    if let Some((name, version)) = sdk {
+        set_if_absent(attrs, ATTR_SDK_NAME, name);
+        if let Some(version) = version {
+            set_if_absent(attrs, ATTR_SDK_VERSION, version);
+        }
+    }
+
+    let country = first_attr(attrs, &COUNTRY_KEYS)
+        .filter(|c| c.len() == 2 && c.chars().all(|ch| ch.is_ascii_alphabetic()))
+        .map(str::to_ascii_uppercase);
+    if let Some(country) = country {
+        set_if_absent(attrs, ATTR_GEO_COUNTRY, country);
+    }
+
+    // Environment aliases feed the edge's environment column
+    if !attrs.contains_key("environment") && !attrs.contains_key("deployment.environment") {
+        if let Some(env) = first_attr(attrs, &ENVIRONMENT_KEYS).map(str::to_ascii_lowercase) {
+            attrs.insert("deployment.environment".to_string(), env);
+        }
+    }
+}
+
+/// Enrichment record of a span, from the attributes set by [`enrich_span`]
+pub fn enrichment_from_attributes(attrs: &HashMap<String, String>) -> EdgeEnrichment {
+    let get = |key: &str| attrs.get(key).filter(|v| !v.is_empty()).cloned();
+    EdgeEnrichment {
+        model_family: get(ATTR_MODEL_FAMILY),
+        provider: get(ATTR_PROVIDER),
+        sdk_name: get(ATTR_SDK_NAME),
+        sdk_version: get(ATTR_SDK_VERSION),
+        country: get(ATTR_GEO_COUNTRY),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn span(attrs: &[(&str, &str)]) -> AgentreplaySpan {
+        AgentreplaySpan {
+            span_id: "0x1".to_string(),
+            trace_id: "0x2".to_string(),
+            parent_span_id: None,
+            name: "chat".to_string(),
+            start_time: 1_700_000_000_000_000,
+            end_time: None,
+            attributes: attrs
+                .iter()
+                .map(|(k, v)| (k.to_string(), v.to_string()))
+                .collect(),
+        }
+    }
+
+    #[test]
+    fn test_model_family() {
+        assert_eq!(model_family("gpt-4o-mini"), Some("gpt"));
+        assert_eq!(model_family("o3-mini"), Some("gpt"));
+        assert_eq!(model_family("claude-3-5-sonnet-20241022"), Some("claude"));
+        assert_eq!(model_family("anthropic.claude-3-haiku"), Some("claude"));
+        assert_eq!(model_family("models/gemini-1.5-pro"), Some("gemini"));
+        assert_eq!(model_family("meta-llama/Llama-3.1-8B"), Some("llama"));
+        assert_eq!(model_family("commander"), None);
+        assert_eq!(model_family("my-finetune"), None);
+    }
+
+    #[test]
+    fn test_enrich_span() {
+        let mut s = span(&[
+            ("gen_ai.request.model", "claude-3-opus"),
+            ("gen_ai.system", "Anthropic"),
+            ("client.geo.country_iso_code", "de"),
+            ("env", "Staging"),
+        ]);
+        enrich_span(&mut s, Some("agentreplay-python/0.3.1 httpx/0.27"));
+
+        let enrichment = enrichment_from_attributes(&s.attributes);
+        assert_eq!(enrichment.model_family.as_deref(), Some("claude"));
+        assert_eq!(enrichment.provider.as_deref(), Some("anthropic"));
+        assert_eq!(enrichment.sdk_name.as_deref(), Some("agentreplay-python"));
+        assert_eq!(enrichment.sdk_version.as_deref(), Some("0.3.1"));
+        assert_eq!(enrichment.country.as_deref(), Some("DE"));
+        assert_eq!(s.attributes["deployment.environment"], "staging");
+    }
+
+    #[test]
+    fn test_enrich_span_keeps_client_values() {
+        let mut s = span(&[
+            ("llm.model", "gpt-4o"),
+            (ATTR_PROVIDER, "azure"),
+            ("telemetry.sdk.name", "opentelemetry"),
+            ("telemetry.sdk.version", "1.27.0"),
+            ("environment", "production"),
+            ("env", "dev"),
+        ]);
+        enrich_span(&mut s, Some("curl/8.0"));
+
+        assert_eq!(s.attributes[ATTR_MODEL_FAMILY], "gpt");
+        assert_eq!(s.attributes[ATTR_PROVIDER], "azure");
+        assert_eq!(s.attributes[ATTR_SDK_NAME], "opentelemetry");
+        assert_eq!(s.attributes[ATTR_SDK_VERSION], "1.27.0");
+        assert!(!s.attributes.contains_key("deployment.environment"));
+
+        let mut bare = span(&[]);
+        enrich_span(&mut bare, None);
+        assert!(enrichment_from_attributes(&bare.attributes).is_empty());
+    }
+}
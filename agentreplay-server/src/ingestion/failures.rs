@@ -0,0 +1,451 @@
+// Copyright 2025 AgentReplay (https://github.com/agentreplay)
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program. If not, see <https://www.gnu.org/licenses/>.
+
+//! Failure taxonomy and fingerprinting
+//!
+//! Failed spans are classified into a [`FailureCategory`] from their error
+//! type, exception, status, HTTP status code and finish reasons. Similar
+//! failures share a fingerprint: a hash of the category, the error type and
+//! the error message with numbers, IDs and quoted values masked, so
+//! "Request 3f2a... timed out after 30s" and "Request 9c1b... timed out
+//! after 45s" land in the same group.
+//!
+//! The `enrich` stage writes both as [`ATTR_FAILURE_CATEGORY`] and
+//! [`ATTR_FAILURE_FINGERPRINT`]. SDKs may set either attribute themselves to
+//! override the classification or the grouping.
+
+use crate::api::ingest::AgentreplaySpan;
+use crate::otel_genai::attrs;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+/// Failure category of a failed span
+pub const ATTR_FAILURE_CATEGORY: &str = "agentreplay.failure.category";
+/// Fingerprint grouping similar failures
+pub const ATTR_FAILURE_FINGERPRINT: &str = "agentreplay.failure.fingerprint";
+
+const MESSAGE_KEYS: [&str; 4] = [
+    "error.message",
+    "exception.message",
+    "otel.status_description",
+    "error",
+];
+const ERROR_TYPE_KEYS: [&str; 2] = [attrs::ERROR_TYPE, "exception.type"];
+const STATUS_KEYS: [&str; 2] = ["otel.status_code", "status"];
+const HTTP_STATUS_KEYS: [&str; 2] = ["http.response.status_code", "http.status_code"];
+
+const RATE_LIMIT_MARKERS: [&str; 7] = [
+    "rate limit",
+    "rate_limit",
+    "ratelimit",
+    "too many requests",
+    "quota exceeded",
+    "resource_exhausted",
+    "resource exhausted",
+];
+const CONTEXT_OVERFLOW_MARKERS: [&str; 7] = [
+    "context_length",
+    "context length",
+    "context window",
+    "maximum context",
+    "prompt is too long",
+    "input is too long",
+    "too many tokens",
+];
+const TIMEOUT_MARKERS: [&str; 4] = [
+    "timeout",
+    "timed out",
+    "deadline exceeded",
+    "deadline_exceeded",
+];
+const REFUSAL_MARKERS: [&str; 4] = [
+    "content_filter",
+    "content filter",
+    "content policy",
+    "refus",
+];
+
+/// Longest message pattern kept for grouping, in characters
+const MAX_PATTERN_CHARS: usize = 256;
+
+/// What kind of failure a span ended in
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum FailureCategory {
+    /// Provider throttling (HTTP 429, quota exhausted)
+    RateLimit,
+    /// Prompt larger than the model's context window
+    ContextOverflow,
+    /// A tool call raised an error
+    ToolException,
+    Timeout,
+    /// The model declined or the provider's content filter blocked it
+    Refusal,
+    Other,
+}
+
+impl FailureCategory {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            FailureCategory::RateLimit => "rate_limit",
+            FailureCategory::ContextOverflow => "context_overflow",
+            FailureCategory::ToolException => "tool_exception",
+            FailureCategory::Timeout => "timeout",
+            FailureCategory::Refusal => "refusal",
+            FailureCategory::Other => "other",
+        }
+    }
+
+    pub fn parse(s: &str) -> Option<Self> {
+        serde_json::from_value(serde_json::Value::String(s.trim().to_ascii_lowercase())).ok()
+    }
+}
+
+/// Classification of one failed span
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SpanFailure {
+    pub category: FailureCategory,
+    /// 16 hex digits
+    pub fingerprint: String,
+    pub error_type: Option<String>,
+    /// Error message with numbers, IDs and quoted values masked
+    pub pattern: String,
+    /// Raw error message, if any
+    pub message: Option<String>,
+    /// Tool that raised the error (tool exceptions only)
+    pub tool: Option<String>,
+}
+
+fn first_value<'a>(attributes: &'a HashMap<String, String>, keys: &[&str]) -> Option<&'a str> {
+    keys.iter()
+        .filter_map(|key| attributes.get(*key))
+        .map(|value| value.trim())
+        .find(|value| !value.is_empty())
+}
+
+/// Classify a span from its attributes; None if it did not fail
+pub fn classify_failure(attributes: &HashMap<String, String>) -> Option<SpanFailure> {
+    let error_type = first_value(attributes, &ERROR_TYPE_KEYS);
+    // `error` doubles as a boolean flag
+    let message = first_value(attributes, &MESSAGE_KEYS).filter(|message| {
+        !message.eq_ignore_ascii_case("true") && !message.eq_ignore_ascii_case("false")
+    });
+    let flagged = attributes
+        .get("error")
+        .is_some_and(|value| value.eq_ignore_ascii_case("true"))
+        || first_value(attributes, &STATUS_KEYS)
+            .is_some_and(|status| status.eq_ignore_ascii_case("error"));
+    let http_status = first_value(attributes, &HTTP_STATUS_KEYS)
+        .and_then(|status| status.parse::<u16>().ok())
+        .filter(|status| *status >= 400);
+    let filtered = attributes
+        .get(attrs::GEN_AI_RESPONSE_FINISH_REASONS)
+        .is_some_and(|reasons| reasons.contains("content_filter") || reasons.contains("refusal"));
+    let overridden = attributes
+        .get(ATTR_FAILURE_CATEGORY)
+        .and_then(|category| FailureCategory::parse(category));
+
+    if error_type.is_none()
+        && message.is_none()
+        && !flagged
+        && http_status.is_none()
+        && !filtered
+        && overridden.is_none()
+    {
+        return None;
+    }
+
+    let text = [error_type, message]
+        .into_iter()
+        .flatten()
+        .collect::<Vec<_>>()
+        .join(" ")
+        .to_ascii_lowercase();
+    let mentions = |markers: &[&str]| markers.iter().any(|marker| text.contains(marker));
+    let tool = attributes
+        .get(attrs::GEN_AI_TOOL_NAME)
+        .cloned()
+        .or_else(|| {
+            attributes
+                .get(attrs::GEN_AI_OPERATION_NAME)
+                .filter(|op| *op == "execute_tool")
+                .map(|_| "unknown".to_string())
+        });
+
+    let category = overridden.unwrap_or_else(|| {
+        if http_status == Some(429) || mentions(&RATE_LIMIT_MARKERS) {
+            FailureCategory::RateLimit
+        } else if mentions(&CONTEXT_OVERFLOW_MARKERS) {
+            FailureCategory::ContextOverflow
+        } else if matches!(http_status, Some(408 | 504)) || mentions(&TIMEOUT_MARKERS) {
+            FailureCategory::Timeout
+        } else if filtered || mentions(&REFUSAL_MARKERS) {
+            FailureCategory::Refusal
+        } else if tool.is_some() {
+            FailureCategory::ToolException
+        } else {
+            FailureCategory::Other
+        }
+    });
+    let tool = tool.filter(|_| category == FailureCategory::ToolException);
+
+    let pattern = normalize_message(message.unwrap_or_default());
+    let fingerprint = attributes
+        .get(ATTR_FAILURE_FINGERPRINT)
+        .filter(|fingerprint| !fingerprint.trim().is_empty())
+        .cloned()
+        .unwrap_or_else(|| {
+            fingerprint(category, error_type, tool.as_deref(), http_status, &pattern)
+        });
+
+    Some(SpanFailure {
+        category,
+        fingerprint,
+        error_type: error_type.map(str::to_string),
+        pattern,
+        message: message.map(str::to_string),
+        tool,
+    })
+}
+
+/// Lowercase a message and mask tokens that vary between occurrences
+///
+/// Words containing a digit (counts, durations, UUIDs, hex IDs) become `#`
+/// and quoted values become `*`; whitespace runs collapse to one space.
+pub fn normalize_message(message: &str) -> String {
+    let mut pattern = String::with_capacity(message.len().min(MAX_PATTERN_CHARS));
+    let mut word = String::new();
+    let mut quote: Option<char> = None;
+
+    for c in message.chars() {
+        if let Some(open) = quote {
+            if c == open {
+                pattern.push('*');
+                pattern.push(c);
+                quote = None;
+            }
+            continue;
+        }
+        if c.is_alphanumeric() || c == '_' || c == '-' {
+            word.push(c);
+            continue;
+        }
+        // An apostrophe inside a word ("model's") does not open a quote
+        let opens_quote = word.is_empty() && (c == '\'' || c == '"' || c == '`');
+        push_word(&mut pattern, &mut word);
+        if c.is_whitespace() {
+            if !pattern.is_empty() && !pattern.ends_with(' ') {
+                pattern.push(' ');
+            }
+        } else {
+            pattern.push(c);
+            if opens_quote {
+                quote = Some(c);
+            }
+        }
+    }
+    // An unterminated quote masks the rest of the message
+    if quote.is_some() {
+        pattern.push('*');
+    }
+    push_word(&mut pattern, &mut word);
+
+    pattern.trim_end().chars().take(MAX_PATTERN_CHARS).collect()
+}
+
+fn push_word(pattern: &mut String, word: &mut String) {
+    if word.is_empty() {
+        return;
+    }
+    if word.chars().any(|c| c.is_ascii_digit()) {
+        pattern.push('#');
+    } else {
+        pattern.extend(word.chars().flat_map(char::to_lowercase));
+    }
+    word.clear();
+}
+
+fn fingerprint(
+    category: FailureCategory,
+    error_type: Option<&str>,
+    tool: Option<&str>,
+    http_status: Option<u16>,
+    pattern: &str,
+) -> String {
+    let mut hasher = blake3::Hasher::new();
+    for part in [
+        category.as_str(),
+        error_type.unwrap_or_default(),
+        tool.unwrap_or_default(),
+        &http_status
+            .map(|status| status.to_string())
+            .unwrap_or_default(),
+        pattern,
+    ] {
+        hasher.update(part.as_bytes());
+        hasher.update(&[0]);
+    }
+    hasher.finalize().to_hex()[..16].to_string()
+}
+
+/// Write the failure category and fingerprint onto a failed span
+///
+/// Returns whether the span failed.
+pub fn tag_failure(span: &mut AgentreplaySpan) -> bool {
+    let Some(failure) = classify_failure(&span.attributes) else {
+        return false;
+    };
+    span.attributes
+        .entry(ATTR_FAILURE_CATEGORY.to_string())
+        .or_insert_with(|| failure.category.as_str().to_string());
+    span.attributes
+        .entry(ATTR_FAILURE_FINGERPRINT.to_string())
+        .or_insert(failure.fingerprint);
+    true
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn span_attrs(pairs: &[(&str, &str)]) -> HashMap<String, String> {
+        pairs
+            .iter()
+            .map(|(k, v)| (k.to_string(), v.to_string()))
+            .collect()
+    }
+
+    fn category(pairs: &[(&str, &str)]) -> Option<FailureCategory> {
+        classify_failure(&span_attrs(pairs)).map(|failure| failure.category)
+    }
+
+    #[test]
+    fn test_classify_categories() {
+        assert_eq!(category(&[("gen_ai.request.model", "gpt-4o")]), None);
+        assert_eq!(category(&[("error", "false")]), None);
+
+        assert_eq!(
+            category(&[("http.response.status_code", "429")]),
+            Some(FailureCategory::RateLimit)
+        );
+        assert_eq!(
+            category(&[
+                ("error.type", "RateLimitError"),
+                ("error.message", "Rate limit reached")
+            ]),
+            Some(FailureCategory::RateLimit)
+        );
+        assert_eq!(
+            category(&[(
+                "exception.message",
+                "This model's maximum context length is 128000 tokens"
+            )]),
+            Some(FailureCategory::ContextOverflow)
+        );
+        assert_eq!(
+            category(&[("error.type", "TimeoutError")]),
+            Some(FailureCategory::Timeout)
+        );
+        assert_eq!(
+            category(&[("gen_ai.response.finish_reasons", r#"["content_filter"]"#)]),
+            Some(FailureCategory::Refusal)
+        );
+        assert_eq!(
+            category(&[
+                ("gen_ai.tool.name", "web_search"),
+                ("exception.type", "KeyError"),
+            ]),
+            Some(FailureCategory::ToolException)
+        );
+        assert_eq!(
+            category(&[("otel.status_code", "ERROR")]),
+            Some(FailureCategory::Other)
+        );
+        // SDK override
+        assert_eq!(
+            category(&[("error", "true"), (ATTR_FAILURE_CATEGORY, "refusal")]),
+            Some(FailureCategory::Refusal)
+        );
+    }
+
+    #[test]
+    fn test_normalize_message() {
+        assert_eq!(
+            normalize_message("Request 3f2a-91c0 timed out  after 30s"),
+            "request # timed out after #"
+        );
+        assert_eq!(
+            normalize_message("Unknown tool 'lookup_order' in call_7"),
+            "unknown tool '*' in #"
+        );
+        assert_eq!(normalize_message("bad \"unterminated"), "bad \"*");
+        assert_eq!(
+            normalize_message("This model's maximum context is 8192 tokens"),
+            "this model's maximum context is # tokens"
+        );
+    }
+
+    #[test]
+    fn test_fingerprint_groups_similar_failures() {
+        let first = classify_failure(&span_attrs(&[
+            ("error.type", "TimeoutError"),
+            ("error.message", "Request 3f2a timed out after 30s"),
+        ]))
+        .unwrap();
+        let second = classify_failure(&span_attrs(&[
+            ("error.type", "TimeoutError"),
+            ("error.message", "Request 9c1b timed out after 45s"),
+        ]))
+        .unwrap();
+        let other = classify_failure(&span_attrs(&[
+            ("error.type", "TimeoutError"),
+            ("error.message", "Connection pool timed out"),
+        ]))
+        .unwrap();
+        assert_eq!(first.fingerprint, second.fingerprint);
+        assert_eq!(first.fingerprint.len(), 16);
+        assert_ne!(first.fingerprint, other.fingerprint);
+
+        let custom = classify_failure(&span_attrs(&[
+            ("error", "true"),
+            (ATTR_FAILURE_FINGERPRINT, "checkout-flow"),
+        ]))
+        .unwrap();
+        assert_eq!(custom.fingerprint, "checkout-flow");
+    }
+
+    #[test]
+    fn test_tag_failure() {
+        let mut span = AgentreplaySpan {
+            span_id: "0x1".to_string(),
+            trace_id: "t".to_string(),
+            parent_span_id: None,
+            name: "tool.call".to_string(),
+            start_time: 1_700_000_000_000_000,
+            end_time: Some(1_700_000_001_000_000),
+            attributes: span_attrs(&[
+                ("gen_ai.tool.name", "web_search"),
+                ("exception.message", "boom"),
+            ]),
+        };
+        assert!(tag_failure(&mut span));
+        assert_eq!(span.attributes[ATTR_FAILURE_CATEGORY], "tool_exception");
+        assert_eq!(span.attributes[ATTR_FAILURE_FINGERPRINT].len(), 16);
+
+        span.attributes.clear();
+        assert!(!tag_failure(&mut span));
+        assert!(span.attributes.is_empty());
+    }
+}
@@ -0,0 +1,210 @@
+// Copyright 2025 AgentReplay (https://github.com/agentreplay)
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program. If not, see <https://www.gnu.org/licenses/>.
+
+//! Idempotent ingestion
+//!
+//! SDKs retry a POST when it times out, even though the server may already
+//! have stored the batch. Two mechanisms make those retries safe:
+//!
+//! - **Idempotency-Key header**: the response to a keyed request is cached for
+//!   the dedup window and replayed verbatim, status included, for retries
+//!   with the same key. Keys are scoped to the caller's tenant and project;
+//!   reusing one for a different request body is refused.
+//! - **Client span IDs**: span IDs map deterministically to edge IDs, so each
+//!   incoming edge ID is checked against a [`DedupWindow`] bloom filter. A hit
+//!   is confirmed against storage before the span is dropped as a duplicate.
+
+use agentreplay_storage::DedupWindow;
+use parking_lot::Mutex;
+use std::collections::HashMap;
+use std::time::{Duration, Instant};
+
+/// Upper bound on cached Idempotency-Key responses
+const MAX_CACHED_RESPONSES: usize = 10_000;
+
+/// Expected distinct span IDs per dedup window (sizes the bloom filters)
+const EXPECTED_SPANS_PER_WINDOW: usize = 1_000_000;
+
+/// An Idempotency-Key in the scope of the caller that sent it
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct IdempotencyKey {
+    pub tenant_id: u64,
+    pub project_id: Option<u16>,
+    pub key: String,
+}
+
+/// Response recorded for an Idempotency-Key
+#[derive(Debug, Clone, PartialEq)]
+pub struct CachedResponse {
+    pub status: u16,
+    pub body: serde_json::Value,
+}
+
+/// What to do with a keyed request
+#[derive(Debug, Clone, PartialEq)]
+pub enum Replay {
+    /// Key not seen within the window: process the request
+    Miss,
+    /// Retry of a recorded request: answer with its response
+    Hit(CachedResponse),
+    /// Key already used for a request with a different body
+    Mismatch,
+}
+
+struct CachedEntry {
+    stored_at: Instant,
+    /// Digest of the request body the key was first used with
+    request_digest: u64,
+    response: CachedResponse,
+}
+
+/// Retry protection for the ingestion endpoint
+pub struct IdempotencyGuard {
+    spans: Option<DedupWindow>,
+    window: Duration,
+    responses: Mutex<HashMap<IdempotencyKey, CachedEntry>>,
+}
+
+impl IdempotencyGuard {
+    /// Create a guard with the given dedup window (zero disables deduplication)
+    pub fn new(window: Duration) -> Self {
+        Self {
+            spans: (!window.is_zero()).then(|| DedupWindow::new(window, EXPECTED_SPANS_PER_WINDOW)),
+            window,
+            responses: Mutex::new(HashMap::new()),
+        }
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        self.spans.is_some()
+    }
+
+    /// Record an edge ID and report whether it may be a retry
+    ///
+    /// `true` means "possibly seen before" and must be confirmed against
+    /// storage; `false` is definitive.
+    pub fn possibly_seen(&self, edge_id: u128) -> bool {
+        self.spans
+            .as_ref()
+            .is_some_and(|window| window.check_and_insert(&edge_id))
+    }
+
+    /// Look up a keyed request whose body hashes to `request_digest`
+    pub fn replay(&self, key: &IdempotencyKey, request_digest: u64) -> Replay {
+        let responses = self.responses.lock();
+        match responses
+            .get(key)
+            .filter(|entry| entry.stored_at.elapsed() < self.window)
+        {
+            None => Replay::Miss,
+            Some(entry) if entry.request_digest != request_digest => Replay::Mismatch,
+            Some(entry) => Replay::Hit(entry.response.clone()),
+        }
+    }
+
+    /// Remember the response to a keyed request
+    pub fn store_response(
+        &self,
+        key: IdempotencyKey,
+        request_digest: u64,
+        response: CachedResponse,
+    ) {
+        if !self.is_enabled() {
+            return;
+        }
+
+        let mut responses = self.responses.lock();
+        if responses.len() >= MAX_CACHED_RESPONSES {
+            let window = self.window;
+            responses.retain(|_, entry| entry.stored_at.elapsed() < window);
+        }
+        if responses.len() < MAX_CACHED_RESPONSES {
+            responses.insert(
+                key,
+                CachedEntry {
+                    stored_at: Instant::now(),
+                    request_digest,
+                    response,
+                },
+            );
+        }
+    }
+}
+
+impl Default for IdempotencyGuard {
+    fn default() -> Self {
+        Self::new(Duration::from_secs(600))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_span_dedup() {
+        let guard = IdempotencyGuard::default();
+        assert!(!guard.possibly_seen(0x1234));
+        assert!(guard.possibly_seen(0x1234));
+        assert!(!guard.possibly_seen(0x5678));
+    }
+
+    fn key(tenant_id: u64, key: &str) -> IdempotencyKey {
+        IdempotencyKey {
+            tenant_id,
+            project_id: Some(1),
+            key: key.to_string(),
+        }
+    }
+
+    fn response(status: u16) -> CachedResponse {
+        CachedResponse {
+            status,
+            body: serde_json::json!({ "accepted": 3 }),
+        }
+    }
+
+    #[test]
+    fn test_response_cache() {
+        let guard = IdempotencyGuard::default();
+        assert_eq!(guard.replay(&key(1, "retry-1"), 7), Replay::Miss);
+
+        guard.store_response(key(1, "retry-1"), 7, response(207));
+        assert_eq!(
+            guard.replay(&key(1, "retry-1"), 7),
+            Replay::Hit(response(207))
+        );
+
+        // A different body under the same key is refused
+        assert_eq!(guard.replay(&key(1, "retry-1"), 8), Replay::Mismatch);
+        // Other tenants' keys do not collide
+        assert_eq!(guard.replay(&key(2, "retry-1"), 7), Replay::Miss);
+        let other_project = IdempotencyKey {
+            project_id: Some(2),
+            ..key(1, "retry-1")
+        };
+        assert_eq!(guard.replay(&other_project, 7), Replay::Miss);
+    }
+
+    #[test]
+    fn test_disabled_guard() {
+        let guard = IdempotencyGuard::new(Duration::ZERO);
+        assert!(!guard.possibly_seen(0x1234));
+        assert!(!guard.possibly_seen(0x1234));
+
+        guard.store_response(key(1, "k"), 0, response(201));
+        assert_eq!(guard.replay(&key(1, "k"), 0), Replay::Miss);
+    }
+}
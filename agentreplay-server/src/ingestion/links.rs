@@ -0,0 +1,139 @@
+// Copyright 2025 AgentReplay (https://github.com/agentreplay)
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program. If not, see <https://www.gnu.org/licenses/>.
+
+//! Span links for fan-out/fan-in workflows
+//!
+//! A span's parent is the step that called it. When a step also merges the
+//! results of other spans (a map-reduce synthesis over N parallel
+//! sub-agents), SDKs list those spans under [`ATTR_SPAN_LINKS`]; OTLP span
+//! links are mapped to the same attribute. The links are stored per edge
+//! (see [`agentreplay_core::span_link`]) and drawn by the graph API.
+//!
+//! Accepted formats:
+//! - a JSON list of span IDs: `["0x1a", "0x1b"]`
+//! - a JSON list of objects: `[{"span_id": "0x1a", "kind": "follows_from"}]`
+//! - a comma-separated list of span IDs: `0x1a,0x1b`
+
+use agentreplay_core::{normalize_links, LinkKind, SpanLink};
+use serde_json::Value;
+
+/// Spans this span merges or follows from
+pub const ATTR_SPAN_LINKS: &str = "agentreplay.links";
+
+/// Parse the links recorded on a span with ID `edge_id`
+///
+/// `parse_id` maps a linked span ID to an edge ID the same way the span's
+/// own ID was mapped. Unparseable entries, self-links and duplicates are
+/// dropped.
+pub fn parse_span_links(
+    edge_id: u128,
+    value: &Value,
+    parse_id: impl Fn(&str) -> Option<u128>,
+) -> Vec<SpanLink> {
+    let entries = match value {
+        Value::String(s) if s.trim_start().starts_with('[') => {
+            match serde_json::from_str::<Value>(s) {
+                Ok(Value::Array(entries)) => entries,
+                _ => return Vec::new(),
+            }
+        }
+        Value::String(s) => s
+            .split(',')
+            .map(str::trim)
+            .filter(|id| !id.is_empty())
+            .map(|id| Value::String(id.to_string()))
+            .collect(),
+        Value::Array(entries) => entries.clone(),
+        _ => return Vec::new(),
+    };
+
+    let links = entries
+        .iter()
+        .filter_map(|entry| {
+            let (id, kind) = match entry {
+                Value::Object(fields) => {
+                    let kind = fields
+                        .get("kind")
+                        .and_then(|kind| serde_json::from_value(kind.clone()).ok())
+                        .unwrap_or_default();
+                    (fields.get("span_id")?, kind)
+                }
+                _ => (entry, LinkKind::default()),
+            };
+            let span_id = match id {
+                Value::String(s) => parse_id(s)?,
+                Value::Number(n) => parse_id(&n.to_string())?,
+                _ => return None,
+            };
+            Some(SpanLink::new(span_id, kind))
+        })
+        .collect();
+
+    normalize_links(edge_id, links)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn hex_id(id: &str) -> Option<u128> {
+        u128::from_str_radix(id.trim_start_matches("0x"), 16).ok()
+    }
+
+    #[test]
+    fn test_parse_id_lists() {
+        let expected = vec![
+            SpanLink::new(0x1a, LinkKind::FanIn),
+            SpanLink::new(0x1b, LinkKind::FanIn),
+        ];
+        assert_eq!(
+            parse_span_links(1, &json!(r#"["0x1a", "0x1b"]"#), hex_id),
+            expected
+        );
+        assert_eq!(parse_span_links(1, &json!("0x1a, 0x1b,"), hex_id), expected);
+        assert_eq!(
+            parse_span_links(1, &json!(["0x1a", "0x1b", "0x1a"]), hex_id),
+            expected
+        );
+    }
+
+    #[test]
+    fn test_parse_link_objects() {
+        let value = json!([
+            {"span_id": "0x1a"},
+            {"span_id": 27, "kind": "follows_from"},
+            {"span_id": "0x1c", "kind": "unknown"},
+            {"kind": "fan_in"},
+            "0x1"
+        ]);
+        let links = parse_span_links(1, &value, |id| id.parse().ok().or_else(|| hex_id(id)));
+        assert_eq!(
+            links,
+            vec![
+                SpanLink::new(0x1a, LinkKind::FanIn),
+                SpanLink::new(27, LinkKind::FollowsFrom),
+                SpanLink::new(0x1c, LinkKind::FanIn),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_parse_invalid() {
+        assert!(parse_span_links(1, &json!("[not json"), hex_id).is_empty());
+        assert!(parse_span_links(1, &json!(42), hex_id).is_empty());
+        assert!(parse_span_links(1, &json!(""), hex_id).is_empty());
+    }
+}
@@ -0,0 +1,288 @@
+// Copyright 2025 AgentReplay (https://github.com/agentreplay)
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program. If not, see <https://www.gnu.org/licenses/>.
+
+//! Token logprob capture
+//!
+//! The opt-in `logprobs` pipeline stage looks for the logprobs a provider
+//! returned (recorded by SDKs as a JSON span attribute) and normalizes them to
+//! a list of [`TokenLogprob`] under [`ATTR_LOGPROBS`]. The raw attributes are
+//! removed so they do not bloat the payload; at store time the tokens are
+//! written as a compact per-edge record (see [`agentreplay_storage::logprobs`]).
+//!
+//! Recognized formats:
+//! - OpenAI chat: `{"content": [{"token", "logprob", "top_logprobs": [...]}]}`
+//!   or the bare `content` array
+//! - OpenAI legacy completions / vLLM: `{"tokens", "token_logprobs",
+//!   "top_logprobs": [{token: logprob}]}`
+//! - Gemini: `{"chosenCandidates": [...], "topCandidates": [...]}`, optionally
+//!   wrapped in `logprobsResult`
+
+use crate::api::ingest::AgentreplaySpan;
+use agentreplay_storage::TokenLogprob;
+use serde_json::Value;
+use std::collections::HashMap;
+
+/// Normalized token logprobs (JSON list of [`TokenLogprob`])
+pub const ATTR_LOGPROBS: &str = "agentreplay.logprobs";
+
+/// Attributes SDKs record provider logprobs under, in order of preference
+const LOGPROB_KEYS: [&str; 4] = [
+    "gen_ai.response.logprobs",
+    "gen_ai.completion.logprobs",
+    "llm.output.logprobs",
+    "logprobs",
+];
+
+/// Alternatives kept per token position
+pub const MAX_TOP_LOGPROBS: usize = 5;
+
+fn number(entry: &Value, keys: &[&str]) -> Option<f32> {
+    keys.iter()
+        .find_map(|key| entry.get(*key))
+        .and_then(Value::as_f64)
+        .map(|v| v as f32)
+}
+
+fn parse_alternative(alt: &Value) -> Option<(String, f32)> {
+    match alt {
+        // Canonical form: [token, logprob]
+        Value::Array(pair) => Some((
+            pair.first()?.as_str()?.to_string(),
+            pair.get(1)?.as_f64()? as f32,
+        )),
+        _ => Some((
+            alt.get("token")?.as_str()?.to_string(),
+            number(alt, &["logprob", "logProbability"])?,
+        )),
+    }
+}
+
+fn parse_token(entry: &Value) -> Option<TokenLogprob> {
+    let top_logprobs = entry
+        .get("top_logprobs")
+        .and_then(Value::as_array)
+        .map(|alts| {
+            alts.iter()
+                .filter_map(parse_alternative)
+                .take(MAX_TOP_LOGPROBS)
+                .collect()
+        })
+        .unwrap_or_default();
+    Some(TokenLogprob {
+        token: entry.get("token")?.as_str()?.to_string(),
+        logprob: number(entry, &["logprob", "logProbability"])?,
+        top_logprobs,
+    })
+}
+
+/// OpenAI legacy completions: parallel `tokens` / `token_logprobs` arrays
+fn parse_legacy(value: &Value) -> Option<Vec<TokenLogprob>> {
+    let tokens = value.get("tokens")?.as_array()?;
+    let logprobs = value.get("token_logprobs")?.as_array()?;
+    let top = value.get("top_logprobs").and_then(Value::as_array);
+    let parsed = tokens
+        .iter()
+        .zip(logprobs)
+        .enumerate()
+        // Echoed prompt tokens have a null logprob
+        .filter_map(|(i, (token, logprob))| {
+            let mut top_logprobs: Vec<(String, f32)> = top
+                .and_then(|top| top.get(i))
+                .and_then(Value::as_object)
+                .map(|alts| {
+                    alts.iter()
+                        .filter_map(|(t, lp)| Some((t.clone(), lp.as_f64()? as f32)))
+                        .collect()
+                })
+                .unwrap_or_default();
+            top_logprobs.sort_by(|a, b| b.1.total_cmp(&a.1));
+            top_logprobs.truncate(MAX_TOP_LOGPROBS);
+            Some(TokenLogprob {
+                token: token.as_str()?.to_string(),
+                logprob: logprob.as_f64()? as f32,
+                top_logprobs,
+            })
+        })
+        .collect();
+    Some(parsed)
+}
+
+/// Gemini `logprobsResult`: chosen tokens plus aligned top candidates
+fn parse_gemini(value: &Value) -> Option<Vec<TokenLogprob>> {
+    let chosen = value.get("chosenCandidates")?.as_array()?;
+    let top = value.get("topCandidates").and_then(Value::as_array);
+    chosen
+        .iter()
+        .enumerate()
+        .map(|(i, candidate)| {
+            let mut token = parse_token(candidate)?;
+            token.top_logprobs = top
+                .and_then(|top| top.get(i))
+                .and_then(|position| position.get("candidates"))
+                .and_then(Value::as_array)
+                .map(|alts| {
+                    alts.iter()
+                        .filter_map(parse_alternative)
+                        .take(MAX_TOP_LOGPROBS)
+                        .collect()
+                })
+                .unwrap_or_default();
+            Some(token)
+        })
+        .collect()
+}
+
+/// Parse provider logprobs in any recognized format; None if unrecognized
+/// or empty
+pub fn parse_logprobs(value: &Value) -> Option<Vec<TokenLogprob>> {
+    let tokens = if let Some(result) = value.get("logprobsResult") {
+        parse_gemini(result)
+    } else if value.get("chosenCandidates").is_some() {
+        parse_gemini(value)
+    } else if value.get("token_logprobs").is_some() {
+        parse_legacy(value)
+    } else if let Some(content) = value.get("content").and_then(Value::as_array) {
+        content.iter().map(parse_token).collect()
+    } else {
+        value.as_array()?.iter().map(parse_token).collect()
+    };
+    tokens.filter(|tokens| !tokens.is_empty())
+}
+
+fn parse_attribute(raw: &str) -> Option<Vec<TokenLogprob>> {
+    serde_json::from_str::<Value>(raw)
+        .ok()
+        .and_then(|value| parse_logprobs(&value))
+}
+
+/// Logprobs stage: normalize provider logprobs into [`ATTR_LOGPROBS`]
+///
+/// Returns whether the span carried logprobs. Provider attributes that parse
+/// are removed; others (e.g. a `logprobs = true` request flag) are left alone.
+/// An existing [`ATTR_LOGPROBS`] takes precedence if it parses.
+pub fn capture_logprobs(span: &mut AgentreplaySpan) -> bool {
+    let attrs = &mut span.attributes;
+    let mut tokens = attrs
+        .get(ATTR_LOGPROBS)
+        .and_then(|raw| parse_attribute(raw));
+    for key in LOGPROB_KEYS {
+        if let Some(parsed) = attrs.get(key).and_then(|raw| parse_attribute(raw)) {
+            attrs.remove(key);
+            tokens.get_or_insert(parsed);
+        }
+    }
+
+    let Some(tokens) = tokens else {
+        attrs.remove(ATTR_LOGPROBS);
+        return false;
+    };
+    match serde_json::to_string(&tokens) {
+        Ok(json) => {
+            attrs.insert(ATTR_LOGPROBS.to_string(), json);
+            true
+        }
+        Err(_) => false,
+    }
+}
+
+/// Token logprobs captured by [`capture_logprobs`], if any
+pub fn logprobs_from_attributes(attrs: &HashMap<String, String>) -> Option<Vec<TokenLogprob>> {
+    attrs
+        .get(ATTR_LOGPROBS)
+        .and_then(|raw| serde_json::from_str::<Vec<TokenLogprob>>(raw).ok())
+        .filter(|tokens| !tokens.is_empty())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn span(attrs: &[(&str, String)]) -> AgentreplaySpan {
+        AgentreplaySpan {
+            span_id: "0x1".to_string(),
+            trace_id: "0x2".to_string(),
+            parent_span_id: None,
+            name: "chat".to_string(),
+            start_time: 1_700_000_000_000_000,
+            end_time: None,
+            attributes: attrs
+                .iter()
+                .map(|(k, v)| (k.to_string(), v.clone()))
+                .collect(),
+        }
+    }
+
+    #[test]
+    fn test_parse_provider_formats() {
+        let openai = json!({
+            "content": [
+                {"token": "Hi", "logprob": -0.1, "bytes": [72, 105],
+                 "top_logprobs": [{"token": "Hi", "logprob": -0.1}, {"token": "Hello", "logprob": -2.4}]},
+                {"token": "!", "logprob": -0.8, "top_logprobs": []}
+            ],
+            "refusal": null
+        });
+        let tokens = parse_logprobs(&openai).unwrap();
+        assert_eq!(tokens.len(), 2);
+        assert_eq!(tokens[0].top_logprobs[1], ("Hello".to_string(), -2.4));
+
+        let legacy = json!({
+            "tokens": ["The", " cat"],
+            "token_logprobs": [null, -1.5],
+            "top_logprobs": [null, {" dog": -1.2, " cat": -1.5, " cow": -4.0}]
+        });
+        let tokens = parse_logprobs(&legacy).unwrap();
+        assert_eq!(tokens.len(), 1);
+        assert_eq!(tokens[0].token, " cat");
+        assert_eq!(tokens[0].top_logprobs[0].0, " dog");
+
+        let gemini = json!({"logprobsResult": {
+            "chosenCandidates": [{"token": "Yes", "logProbability": -0.02}],
+            "topCandidates": [{"candidates": [
+                {"token": "Yes", "logProbability": -0.02},
+                {"token": "No", "logProbability": -4.1}
+            ]}]
+        }});
+        let tokens = parse_logprobs(&gemini).unwrap();
+        assert_eq!(tokens[0].logprob, -0.02);
+        assert_eq!(tokens[0].top_logprobs.len(), 2);
+
+        assert!(parse_logprobs(&json!({"content": []})).is_none());
+        assert!(parse_logprobs(&json!({"foo": 1})).is_none());
+    }
+
+    #[test]
+    fn test_capture_logprobs() {
+        let raw = json!([{"token": "ok", "logprob": -0.3}]).to_string();
+        let mut s = span(&[
+            ("gen_ai.response.logprobs", raw),
+            ("logprobs", "true".to_string()),
+        ]);
+        assert!(capture_logprobs(&mut s));
+        assert!(!s.attributes.contains_key("gen_ai.response.logprobs"));
+        assert_eq!(s.attributes["logprobs"], "true");
+        let tokens = logprobs_from_attributes(&s.attributes).unwrap();
+        assert_eq!(tokens[0].token, "ok");
+
+        // Canonical attribute survives a second pass
+        assert!(capture_logprobs(&mut s));
+        assert_eq!(logprobs_from_attributes(&s.attributes), Some(tokens));
+
+        let mut bare = span(&[(ATTR_LOGPROBS, "[]".to_string())]);
+        assert!(!capture_logprobs(&mut bare));
+        assert!(bare.attributes.is_empty());
+    }
+}
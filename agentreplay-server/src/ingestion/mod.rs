@@ -15,11 +15,51 @@
 
 //! Ingestion Actor Module
 //!
-//! High-performance trace ingestion with server-side batching.
+//! High-performance trace ingestion with server-side batching, run as a
+//! configurable pipeline of stages (see [`pipeline`]).
 
 mod actor;
+mod clock_skew;
+mod enrichment;
+mod failures;
+mod idempotency;
+mod links;
+mod logprobs;
+mod open_spans;
+mod parallel;
+pub mod pipeline;
+#[cfg(feature = "wasm")]
+mod wasm_transform;
 
 pub use actor::{
     IngestionActor, IngestionActorHandle, IngestionConfig, IngestionResult, IngestionStats,
     TracePayload,
 };
+pub use clock_skew::{
+    ClockSkewCorrector, ClockSkewStats, ATTR_ORIGINAL_END, ATTR_ORIGINAL_START,
+    ATTR_SKEW_CORRECTED, ATTR_SKEW_OFFSET_US, ATTR_SKEW_REASON,
+};
+pub use enrichment::{
+    enrich_span, enrichment_from_attributes, model_family, ATTR_GEO_COUNTRY, ATTR_MODEL_FAMILY,
+    ATTR_PROVIDER, ATTR_SDK_NAME, ATTR_SDK_VERSION,
+};
+pub use failures::{
+    classify_failure, normalize_message, tag_failure, FailureCategory, SpanFailure,
+    ATTR_FAILURE_CATEGORY, ATTR_FAILURE_FINGERPRINT,
+};
+pub use idempotency::{CachedResponse, IdempotencyGuard, IdempotencyKey, Replay};
+pub use links::{parse_span_links, ATTR_SPAN_LINKS};
+pub use logprobs::{capture_logprobs, logprobs_from_attributes, parse_logprobs, ATTR_LOGPROBS};
+pub use open_spans::{
+    is_span_start, OpenSpan, OpenSpanTracker, ATTR_SPAN_ABANDONED, ATTR_SPAN_IN_PROGRESS,
+};
+pub use parallel::{
+    annotate_parallel_groups, ATTR_PARALLEL_BRANCH_COUNT, ATTR_PARALLEL_BRANCH_INDEX,
+    ATTR_PARALLEL_GROUP,
+};
+pub use pipeline::{
+    default_pipeline, IngestPipeline, PipelineStage, SpanTransform, TransformInfo,
+    TransformRegistry,
+};
+#[cfg(feature = "wasm")]
+pub use wasm_transform::WasmTransform;
@@ -0,0 +1,349 @@
+// Copyright 2025 AgentReplay (https://github.com/agentreplay)
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program. If not, see <https://www.gnu.org/licenses/>.
+
+//! In-progress spans
+//!
+//! A long LLM call would otherwise only show up once it completes. SDKs can
+//! instead send the span as soon as it starts, without `end_time` and with
+//! [`ATTR_SPAN_IN_PROGRESS`] set, then send it again with the same span ID,
+//! the same `start_time` and an `end_time` to finalize it. The start is
+//! stored and streamed right away as in progress; the finalize update
+//! overwrites it and inherits the start attributes it does not repeat.
+//!
+//! Open spans whose finalize update never arrives (crashed process, lost
+//! request) are closed by a janitor after the configured timeout and
+//! flagged abandoned. The tracker lives in memory, so the janitor first
+//! picks up the spans a previous run left open from storage.
+
+use crate::api::ingest::AgentreplaySpan;
+use crate::api::AppState;
+use agentreplay_core::clock::now_us;
+use agentreplay_core::AgentFlowEdge;
+use agentreplay_query::TenantScope;
+use parking_lot::Mutex;
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tracing::{info, warn};
+
+/// Set to `true` on a span start event (a span without `end_time`)
+pub const ATTR_SPAN_IN_PROGRESS: &str = "agentreplay.span.in_progress";
+/// Set to `true` on spans the janitor closed
+pub const ATTR_SPAN_ABANDONED: &str = "agentreplay.span.abandoned";
+
+/// Upper bound on tracked open spans; starts beyond it are stored but never
+/// closed by the janitor
+const MAX_OPEN_SPANS: usize = 100_000;
+
+/// How far back startup looks for spans a previous run left open
+const RESTORE_LOOKBACK: Duration = Duration::from_secs(24 * 60 * 60);
+
+/// Whether a span is a start event to be finalized later
+pub fn is_span_start(span: &AgentreplaySpan) -> bool {
+    span.end_time.is_none()
+        && span
+            .attributes
+            .get(ATTR_SPAN_IN_PROGRESS)
+            .is_some_and(|v| v.eq_ignore_ascii_case("true"))
+}
+
+/// A started span waiting for its finalize update
+#[derive(Debug, Clone)]
+pub struct OpenSpan {
+    pub edge: AgentFlowEdge,
+    pub attributes: HashMap<String, String>,
+    opened_at: Instant,
+}
+
+/// Open spans, by edge ID
+pub struct OpenSpanTracker {
+    /// None disables the janitor
+    timeout: Option<Duration>,
+    spans: Mutex<HashMap<u128, OpenSpan>>,
+}
+
+impl OpenSpanTracker {
+    pub fn new(timeout: Option<Duration>) -> Self {
+        Self {
+            timeout,
+            spans: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Track a stored start event; returns false if the tracker is full
+    pub fn open(&self, edge: &AgentFlowEdge, attributes: &HashMap<String, String>) -> bool {
+        self.track(edge, attributes, Instant::now())
+    }
+
+    fn track(
+        &self,
+        edge: &AgentFlowEdge,
+        attributes: &HashMap<String, String>,
+        opened_at: Instant,
+    ) -> bool {
+        let mut spans = self.spans.lock();
+        if spans.len() >= MAX_OPEN_SPANS && !spans.contains_key(&edge.edge_id) {
+            return false;
+        }
+        spans.insert(
+            edge.edge_id,
+            OpenSpan {
+                edge: *edge,
+                attributes: attributes.clone(),
+                opened_at,
+            },
+        );
+        true
+    }
+
+    /// Track again the spans a previous run left open
+    ///
+    /// Spans started within [`RESTORE_LOOKBACK`] that are still stored in
+    /// progress count as open since their start, so those already past the
+    /// timeout are closed on the janitor's first pass. Their stored payload
+    /// stands in for the start attributes. Returns how many were restored.
+    pub fn restore(&self, state: &AppState) -> usize {
+        let Some(timeout) = self.timeout else {
+            return 0;
+        };
+        let shards = match crate::api::metrics::project_shards(state, None) {
+            Ok(shards) => shards,
+            Err(e) => {
+                warn!("Failed to restore open spans: {}", e);
+                return 0;
+            }
+        };
+
+        let now = now_us();
+        let since = now.saturating_sub(RESTORE_LOOKBACK.as_micros() as u64);
+        let mut restored = 0;
+        for db in shards {
+            let edges = match db.scoped(TenantScope::AllTenants).range(since, now) {
+                Ok(edges) => edges,
+                Err(e) => {
+                    warn!("Failed to restore open spans: {}", e);
+                    continue;
+                }
+            };
+            for edge in edges.iter().filter(|edge| edge.is_in_progress()) {
+                let attributes = db
+                    .get_payload(edge.edge_id)
+                    .ok()
+                    .flatten()
+                    .map(|payload| stored_attributes(&payload))
+                    .unwrap_or_default();
+                let open_for =
+                    Duration::from_micros(now.saturating_sub(edge.timestamp_us)).min(timeout);
+                let opened_at = Instant::now()
+                    .checked_sub(open_for)
+                    .unwrap_or_else(Instant::now);
+                if self.track(edge, &attributes, opened_at) {
+                    restored += 1;
+                }
+            }
+        }
+        restored
+    }
+
+    /// Stop tracking a span that was finalized, returning its start event
+    pub fn finalize(&self, edge_id: u128) -> Option<OpenSpan> {
+        self.spans.lock().remove(&edge_id)
+    }
+
+    pub fn len(&self) -> usize {
+        self.spans.lock().len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Remove and return the spans open for longer than the timeout
+    pub fn take_expired(&self) -> Vec<OpenSpan> {
+        self.take_expired_at(Instant::now())
+    }
+
+    fn take_expired_at(&self, now: Instant) -> Vec<OpenSpan> {
+        let Some(timeout) = self.timeout else {
+            return Vec::new();
+        };
+
+        let mut spans = self.spans.lock();
+        let expired: Vec<u128> = spans
+            .iter()
+            .filter(|(_, span)| now.saturating_duration_since(span.opened_at) >= timeout)
+            .map(|(id, _)| *id)
+            .collect();
+        expired
+            .into_iter()
+            .filter_map(|id| spans.remove(&id))
+            .collect()
+    }
+
+    /// Close abandoned spans periodically until the server stops
+    pub fn spawn_janitor(self: &Arc<Self>, state: AppState) {
+        let Some(timeout) = self.timeout else {
+            return;
+        };
+
+        let tracker = Arc::clone(self);
+        let period = (timeout / 4).clamp(Duration::from_secs(1), Duration::from_secs(60));
+        tokio::spawn(async move {
+            let restore = {
+                let (tracker, state) = (Arc::clone(&tracker), state.clone());
+                tokio::task::spawn_blocking(move || tracker.restore(&state))
+            };
+            match restore.await {
+                Ok(0) => {}
+                Ok(restored) => info!("Restored {} spans left open by a previous run", restored),
+                Err(e) => warn!("Failed to restore open spans: {}", e),
+            }
+
+            let mut interval = tokio::time::interval(period);
+            interval.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+            loop {
+                interval.tick().await;
+                let expired = tracker.take_expired();
+                if expired.is_empty() {
+                    continue;
+                }
+                let total = expired.len();
+                let closed = crate::api::ingest::close_abandoned_spans(&state, expired).await;
+                if closed < total {
+                    warn!(
+                        "Failed to close {} of {} abandoned spans",
+                        total - closed,
+                        total
+                    );
+                } else {
+                    info!("Closed {} abandoned spans", closed);
+                }
+            }
+        });
+        info!(
+            "Open spans are closed after {} s without a finalize update",
+            timeout.as_secs()
+        );
+    }
+}
+
+/// Span attributes as kept in a stored payload
+fn stored_attributes(payload: &[u8]) -> HashMap<String, String> {
+    let Ok(serde_json::Value::Object(fields)) = serde_json::from_slice(payload) else {
+        return HashMap::new();
+    };
+    fields
+        .into_iter()
+        .map(|(key, value)| match value {
+            serde_json::Value::String(value) => (key, value),
+            value => (key, value.to_string()),
+        })
+        .collect()
+}
+
+impl Default for OpenSpanTracker {
+    fn default() -> Self {
+        Self::new(Some(Duration::from_secs(900)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use agentreplay_core::SpanType;
+
+    fn edge(edge_id: u128) -> AgentFlowEdge {
+        let mut edge = AgentFlowEdge::new(1, 1, 1, 1, SpanType::Reasoning, 0);
+        edge.edge_id = edge_id;
+        edge
+    }
+
+    #[test]
+    fn test_open_and_finalize() {
+        let tracker = OpenSpanTracker::default();
+        let attrs = HashMap::from([("gen_ai.prompt".to_string(), "hi".to_string())]);
+        assert!(tracker.open(&edge(7), &attrs));
+        assert_eq!(tracker.len(), 1);
+
+        let start = tracker.finalize(7).unwrap();
+        assert_eq!(start.attributes["gen_ai.prompt"], "hi");
+        assert!(tracker.finalize(7).is_none());
+        assert!(tracker.is_empty());
+    }
+
+    #[test]
+    fn test_take_expired() {
+        let tracker = OpenSpanTracker::new(Some(Duration::from_secs(60)));
+        tracker.open(&edge(1), &HashMap::new());
+        tracker.open(&edge(2), &HashMap::new());
+
+        let now = Instant::now();
+        assert!(tracker.take_expired_at(now).is_empty());
+
+        let expired = tracker.take_expired_at(now + Duration::from_secs(61));
+        assert_eq!(expired.len(), 2);
+        assert!(tracker.is_empty());
+
+        let disabled = OpenSpanTracker::new(None);
+        disabled.open(&edge(3), &HashMap::new());
+        assert!(disabled
+            .take_expired_at(now + Duration::from_secs(86_400))
+            .is_empty());
+    }
+
+    #[test]
+    fn test_restored_spans_count_from_their_start() {
+        let tracker = OpenSpanTracker::new(Some(Duration::from_secs(60)));
+        let now = Instant::now();
+        tracker.track(&edge(1), &HashMap::new(), now - Duration::from_secs(50));
+        tracker.open(&edge(2), &HashMap::new());
+
+        let expired = tracker.take_expired_at(now + Duration::from_secs(15));
+        assert_eq!(expired.len(), 1);
+        assert_eq!(expired[0].edge.edge_id, 1);
+        assert_eq!(tracker.len(), 1);
+    }
+
+    #[test]
+    fn test_stored_attributes() {
+        let attrs = stored_attributes(
+            br#"{"gen_ai.request.model":"gpt-4o","gen_ai.usage.input_tokens":12}"#,
+        );
+        assert_eq!(attrs["gen_ai.request.model"], "gpt-4o");
+        assert_eq!(attrs["gen_ai.usage.input_tokens"], "12");
+        assert!(stored_attributes(b"not json").is_empty());
+    }
+
+    #[test]
+    fn test_is_span_start() {
+        let mut span = AgentreplaySpan {
+            span_id: "0x1".to_string(),
+            trace_id: "t".to_string(),
+            parent_span_id: None,
+            name: "llm.request".to_string(),
+            start_time: 1_700_000_000_000_000,
+            end_time: None,
+            attributes: HashMap::from([(ATTR_SPAN_IN_PROGRESS.to_string(), "true".to_string())]),
+        };
+        assert!(is_span_start(&span));
+
+        span.end_time = Some(1_700_000_001_000_000);
+        assert!(!is_span_start(&span));
+
+        span.end_time = None;
+        span.attributes.clear();
+        assert!(!is_span_start(&span));
+    }
+}
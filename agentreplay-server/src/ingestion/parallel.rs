@@ -0,0 +1,148 @@
+// Copyright 2025 AgentReplay (https://github.com/agentreplay)
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program. If not, see <https://www.gnu.org/licenses/>.
+
+//! Concurrency metadata for parallel tool calls
+//!
+//! Siblings in a batch that share a parent and overlap in time are tagged with
+//! a common batch group ID plus their branch position, so the trace merge view
+//! can show them as one fan-out/fan-in instead of unordered siblings.
+//!
+//! SDKs that know which calls were dispatched together (e.g. a single
+//! `asyncio.gather`) can set [`ATTR_PARALLEL_GROUP`] themselves; client-provided
+//! group IDs are kept as-is and also work across batches.
+
+use crate::api::ingest::AgentreplaySpan;
+use agentreplay_query::overlap_clusters;
+use std::collections::hash_map::DefaultHasher;
+use std::collections::HashMap;
+use std::hash::{Hash, Hasher};
+
+/// Batch group ID shared by the branches of one fan-out
+pub const ATTR_PARALLEL_GROUP: &str = "agentreplay.parallel.group_id";
+/// Zero-based position of the branch within its group, by start time
+pub const ATTR_PARALLEL_BRANCH_INDEX: &str = "agentreplay.parallel.branch_index";
+/// Number of branches in the group
+pub const ATTR_PARALLEL_BRANCH_COUNT: &str = "agentreplay.parallel.branch_count";
+
+/// Tag overlapping siblings with batch group IDs
+///
+/// Only untagged spans with a parent and an end time are considered.
+/// Returns the number of spans tagged.
+pub fn annotate_parallel_groups(spans: &mut [AgentreplaySpan]) -> usize {
+    let mut siblings: HashMap<(&str, &str), Vec<usize>> = HashMap::new();
+    for (idx, span) in spans.iter().enumerate() {
+        let Some(parent) = span.parent_span_id.as_deref() else {
+            continue;
+        };
+        if span.end_time.is_none() || span.attributes.contains_key(ATTR_PARALLEL_GROUP) {
+            continue;
+        }
+        siblings
+            .entry((span.trace_id.as_str(), parent))
+            .or_default()
+            .push(idx);
+    }
+
+    let mut assignments: Vec<(usize, String, usize, usize)> = Vec::new();
+    for ((trace_id, parent), members) in siblings {
+        if members.len() < 2 {
+            continue;
+        }
+        let intervals: Vec<(u64, u64)> = members
+            .iter()
+            .map(|&i| {
+                (
+                    spans[i].start_time,
+                    spans[i].end_time.unwrap_or(spans[i].start_time),
+                )
+            })
+            .collect();
+        for cluster in overlap_clusters(&intervals) {
+            let first = &spans[members[cluster[0]]];
+            let group_id = group_id(trace_id, parent, &first.span_id);
+            for (branch, &i) in cluster.iter().enumerate() {
+                assignments.push((members[i], group_id.clone(), branch, cluster.len()));
+            }
+        }
+    }
+
+    let tagged = assignments.len();
+    for (idx, group_id, branch, count) in assignments {
+        let attrs = &mut spans[idx].attributes;
+        attrs.insert(ATTR_PARALLEL_GROUP.to_string(), group_id);
+        attrs.insert(ATTR_PARALLEL_BRANCH_INDEX.to_string(), branch.to_string());
+        attrs.insert(ATTR_PARALLEL_BRANCH_COUNT.to_string(), count.to_string());
+    }
+    tagged
+}
+
+/// Deterministic, non-numeric group ID (so payload parsing keeps it a string)
+fn group_id(trace_id: &str, parent_span_id: &str, first_span_id: &str) -> String {
+    let mut hasher = DefaultHasher::new();
+    trace_id.hash(&mut hasher);
+    parent_span_id.hash(&mut hasher);
+    first_span_id.hash(&mut hasher);
+    format!("pg-{:016x}", hasher.finish())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn span(id: &str, parent: Option<&str>, start: u64, end: u64) -> AgentreplaySpan {
+        AgentreplaySpan {
+            span_id: id.to_string(),
+            trace_id: "0xabc".to_string(),
+            parent_span_id: parent.map(String::from),
+            name: "tool_call".to_string(),
+            start_time: start,
+            end_time: Some(end),
+            attributes: HashMap::new(),
+        }
+    }
+
+    #[test]
+    fn test_overlapping_siblings_grouped() {
+        let mut spans = vec![
+            span("0x1", None, 0, 1_000),
+            span("0x2", Some("0x1"), 100, 400),
+            span("0x3", Some("0x1"), 120, 300),
+            span("0x4", Some("0x1"), 500, 600),
+        ];
+
+        assert_eq!(annotate_parallel_groups(&mut spans), 2);
+        assert_eq!(
+            spans[1].attributes[ATTR_PARALLEL_GROUP],
+            spans[2].attributes[ATTR_PARALLEL_GROUP]
+        );
+        assert_eq!(spans[2].attributes[ATTR_PARALLEL_BRANCH_INDEX], "1");
+        assert_eq!(spans[2].attributes[ATTR_PARALLEL_BRANCH_COUNT], "2");
+        assert!(!spans[3].attributes.contains_key(ATTR_PARALLEL_GROUP));
+    }
+
+    #[test]
+    fn test_client_group_preserved() {
+        let mut spans = vec![
+            span("0x2", Some("0x1"), 100, 400),
+            span("0x3", Some("0x1"), 120, 300),
+        ];
+        spans[0]
+            .attributes
+            .insert(ATTR_PARALLEL_GROUP.to_string(), "gather-1".to_string());
+
+        assert_eq!(annotate_parallel_groups(&mut spans), 0);
+        assert_eq!(spans[0].attributes[ATTR_PARALLEL_GROUP], "gather-1");
+    }
+}
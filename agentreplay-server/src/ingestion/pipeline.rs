@@ -0,0 +1,678 @@
+// Copyright 2025 AgentReplay (https://github.com/agentreplay)
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program. If not, see <https://www.gnu.org/licenses/>.
+
+//! Configurable ingestion pipeline
+//!
+//! Spans posted to `/api/v1/traces` pass through the ordered stages listed in
+//! `ingestion.pipeline`:
+//!
+//! ```text
+//! sanitize → enrich → scripts → transform:<name>… → sample → governor → store
+//! ```
+//!
+//! - `sanitize`: validate ids, timestamps and sizes; clean names and attributes
+//! - `enrich`: clock skew correction, parallel call grouping and attribute
+//!   enrichment (model family, provider, SDK, country; see [`super::enrichment`])
+//! - `logprobs` (opt-in): capture provider token logprobs into a compact
+//!   per-span record for confidence analytics (see [`super::logprobs`])
+//! - `scripts`: `on_ingest` hook scripts
+//! - `transform:<name>`: a registered transform plugin, which may rewrite or
+//!   drop each span (e.g. to map custom attributes onto GenAI conventions)
+//! - `sample`: keep a subset of the batch when the ingestion queue is near
+//!   capacity; without it an overloaded queue rejects the batch
+//! - `governor`: semantic deduplication through the ingestion actor
+//! - `store`: convert spans to edges and write them with their payloads
+//!
+//! Span stages (everything before `sample`) may be reordered or left out.
+//! `sample`, `governor` and `store` follow them in that order, and only
+//! `store` is required. Sanitization cannot be skipped: spans are sanitized
+//! again before conversion if a script or transform ran after the `sanitize`
+//! stage, or if the stage is not listed.
+//!
+//! Transforms belong to a tenant: `transform:<name>` runs the transform the
+//! ingesting tenant registered under that name, and is skipped for tenants
+//! without one.
+
+use crate::api::ingest::AgentreplaySpan;
+use parking_lot::RwLock;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fmt;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use tracing::{info, warn};
+
+/// File extension of transform modules in the transforms directory
+pub const TRANSFORM_MODULE_EXTENSION: &str = "wasm";
+
+/// Largest transform module accepted by [`TransformRegistry::register_wasm`]
+pub const MAX_TRANSFORM_MODULE_BYTES: usize = 16 * 1024 * 1024;
+
+const MAX_TRANSFORM_NAME_LEN: usize = 64;
+const TRANSFORM_STAGE_PREFIX: &str = "transform:";
+
+/// One stage of the ingestion pipeline
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(try_from = "String", into = "String")]
+pub enum PipelineStage {
+    Sanitize,
+    Enrich,
+    /// Token logprob capture; not in the default pipeline
+    Logprobs,
+    Scripts,
+    /// A transform plugin, by registered name
+    Transform(String),
+    Sample,
+    Governor,
+    Store,
+}
+
+impl PipelineStage {
+    /// Position of the stage within the fixed tail (`sample → governor → store`)
+    fn tail_rank(&self) -> Option<u8> {
+        match self {
+            Self::Sample => Some(0),
+            Self::Governor => Some(1),
+            Self::Store => Some(2),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for PipelineStage {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Sanitize => f.write_str("sanitize"),
+            Self::Enrich => f.write_str("enrich"),
+            Self::Logprobs => f.write_str("logprobs"),
+            Self::Scripts => f.write_str("scripts"),
+            Self::Transform(name) => write!(f, "{}{}", TRANSFORM_STAGE_PREFIX, name),
+            Self::Sample => f.write_str("sample"),
+            Self::Governor => f.write_str("governor"),
+            Self::Store => f.write_str("store"),
+        }
+    }
+}
+
+impl FromStr for PipelineStage {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = s.trim();
+        if let Some(name) = s.strip_prefix(TRANSFORM_STAGE_PREFIX) {
+            validate_transform_name(name)?;
+            return Ok(Self::Transform(name.to_string()));
+        }
+        match s.to_lowercase().as_str() {
+            "sanitize" => Ok(Self::Sanitize),
+            "enrich" => Ok(Self::Enrich),
+            "logprobs" => Ok(Self::Logprobs),
+            "scripts" => Ok(Self::Scripts),
+            "sample" => Ok(Self::Sample),
+            "governor" => Ok(Self::Governor),
+            "store" => Ok(Self::Store),
+            other => Err(format!(
+                "Unknown pipeline stage '{}' (expected sanitize, enrich, logprobs, \
+                 scripts, transform:<name>, sample, governor or store)",
+                other
+            )),
+        }
+    }
+}
+
+impl TryFrom<String> for PipelineStage {
+    type Error = String;
+
+    fn try_from(s: String) -> Result<Self, Self::Error> {
+        s.parse()
+    }
+}
+
+impl From<PipelineStage> for String {
+    fn from(stage: PipelineStage) -> Self {
+        stage.to_string()
+    }
+}
+
+/// The built-in pipeline: every stage except `logprobs`, no transforms
+pub fn default_pipeline() -> Vec<PipelineStage> {
+    vec![
+        PipelineStage::Sanitize,
+        PipelineStage::Enrich,
+        PipelineStage::Scripts,
+        PipelineStage::Sample,
+        PipelineStage::Governor,
+        PipelineStage::Store,
+    ]
+}
+
+/// Parse a comma-separated stage list (as in `AGENTREPLAY_INGEST_PIPELINE`)
+pub fn parse_pipeline(spec: &str) -> Result<Vec<PipelineStage>, String> {
+    spec.split(',')
+        .filter(|s| !s.trim().is_empty())
+        .map(str::parse)
+        .collect()
+}
+
+/// Check stage ordering: span stages first, then `sample → governor → store`
+pub fn validate_pipeline(stages: &[PipelineStage]) -> Result<(), String> {
+    if stages.last() != Some(&PipelineStage::Store) {
+        return Err("The ingestion pipeline must end with the 'store' stage".to_string());
+    }
+
+    let mut last_tail_rank = None;
+    for (i, stage) in stages.iter().enumerate() {
+        if stages[..i].contains(stage) {
+            return Err(format!("Pipeline stage '{}' is listed twice", stage));
+        }
+        match stage.tail_rank() {
+            Some(rank) => {
+                if last_tail_rank.is_some_and(|last| last >= rank) {
+                    return Err(format!(
+                        "Pipeline stage '{}' is out of order (expected sample → governor → store)",
+                        stage
+                    ));
+                }
+                last_tail_rank = Some(rank);
+            }
+            None if last_tail_rank.is_some() => {
+                return Err(format!(
+                    "Span stage '{}' must come before sample, governor and store",
+                    stage
+                ));
+            }
+            None => {}
+        }
+    }
+
+    Ok(())
+}
+
+/// Names double as file names in the transforms directory
+pub fn validate_transform_name(name: &str) -> Result<(), String> {
+    if name.is_empty() || name.len() > MAX_TRANSFORM_NAME_LEN {
+        return Err(format!(
+            "Transform name must be 1-{} characters",
+            MAX_TRANSFORM_NAME_LEN
+        ));
+    }
+    if !name
+        .chars()
+        .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+    {
+        return Err(format!(
+            "Transform name '{}' may only contain letters, digits, '-' and '_'",
+            name
+        ));
+    }
+    Ok(())
+}
+
+/// A transform plugin: rewrites or drops spans before they are stored
+pub trait SpanTransform: Send + Sync {
+    /// Short description of the implementation (e.g. "wasm")
+    fn kind(&self) -> &'static str;
+
+    /// Return the (possibly modified) span, or None to drop it
+    fn transform(&self, span: &AgentreplaySpan) -> Result<Option<AgentreplaySpan>, String>;
+}
+
+/// Runtime counters for a transform
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct TransformStats {
+    pub runs: u64,
+    pub errors: u64,
+    pub dropped: u64,
+    pub last_error: Option<String>,
+}
+
+/// A registered transform plus its counters
+#[derive(Debug, Clone, Serialize)]
+pub struct TransformInfo {
+    pub name: String,
+    pub kind: &'static str,
+    /// Whether `ingestion.pipeline` references the transform
+    pub active: bool,
+    pub stats: TransformStats,
+}
+
+struct RegisteredTransform {
+    transform: Arc<dyn SpanTransform>,
+    runs: AtomicU64,
+    errors: AtomicU64,
+    dropped: AtomicU64,
+    last_error: RwLock<Option<String>>,
+}
+
+impl RegisteredTransform {
+    fn new(transform: Arc<dyn SpanTransform>) -> Self {
+        Self {
+            transform,
+            runs: AtomicU64::new(0),
+            errors: AtomicU64::new(0),
+            dropped: AtomicU64::new(0),
+            last_error: RwLock::new(None),
+        }
+    }
+
+    fn stats(&self) -> TransformStats {
+        TransformStats {
+            runs: self.runs.load(Ordering::Relaxed),
+            errors: self.errors.load(Ordering::Relaxed),
+            dropped: self.dropped.load(Ordering::Relaxed),
+            last_error: self.last_error.read().clone(),
+        }
+    }
+}
+
+/// Tenant owning transform modules saved before transforms were per tenant
+const LEGACY_TRANSFORM_TENANT: u64 = 1;
+
+/// Transform plugins by tenant and name, backed by a directory of WASM
+/// modules
+///
+/// A tenant's modules are saved as `<dir>/<tenant_id>/<name>.wasm`. Modules
+/// directly in `dir` predate per-tenant transforms and belong to the default
+/// tenant.
+pub struct TransformRegistry {
+    transforms: RwLock<BTreeMap<(u64, String), Arc<RegisteredTransform>>>,
+    dir: PathBuf,
+}
+
+impl TransformRegistry {
+    /// Create a registry, loading every transform module in `dir`
+    pub fn new(dir: impl AsRef<Path>) -> Self {
+        let registry = Self {
+            transforms: RwLock::new(BTreeMap::new()),
+            dir: dir.as_ref().to_path_buf(),
+        };
+
+        if let Err(e) = registry.load_dir() {
+            warn!(
+                "Failed to load transforms from {}: {}. Starting with no transforms.",
+                registry.dir.display(),
+                e
+            );
+        }
+
+        registry
+    }
+
+    /// Register an in-process transform for a tenant (not persisted)
+    pub fn register(
+        &self,
+        tenant_id: u64,
+        name: &str,
+        transform: Arc<dyn SpanTransform>,
+    ) -> Result<(), String> {
+        validate_transform_name(name)?;
+        self.transforms.write().insert(
+            (tenant_id, name.to_string()),
+            Arc::new(RegisteredTransform::new(transform)),
+        );
+        Ok(())
+    }
+
+    /// Compile a tenant's WASM transform module, save it and register it
+    ///
+    /// Returns true if an existing transform was replaced.
+    pub fn register_wasm(&self, tenant_id: u64, name: &str, module: &[u8]) -> Result<bool, String> {
+        validate_transform_name(name)?;
+        if module.len() > MAX_TRANSFORM_MODULE_BYTES {
+            return Err(format!(
+                "Transform module is {} bytes (limit {})",
+                module.len(),
+                MAX_TRANSFORM_MODULE_BYTES
+            ));
+        }
+        let transform = compile_wasm(module)?;
+
+        let path = self.module_path(tenant_id, name);
+        path.parent()
+            .map_or(Ok(()), std::fs::create_dir_all)
+            .and_then(|_| std::fs::write(&path, module))
+            .map_err(|e| format!("Failed to save transform module: {}", e))?;
+
+        let replaced = self
+            .transforms
+            .write()
+            .insert(
+                (tenant_id, name.to_string()),
+                Arc::new(RegisteredTransform::new(transform)),
+            )
+            .is_some();
+        info!("Registered transform '{}' of tenant {}", name, tenant_id);
+        Ok(replaced)
+    }
+
+    /// Remove a tenant's transform and its module file; returns false if it
+    /// did not exist
+    pub fn unregister(&self, tenant_id: u64, name: &str) -> Result<bool, String> {
+        let key = (tenant_id, name.to_string());
+        if self.transforms.write().remove(&key).is_none() {
+            return Ok(false);
+        }
+        let path = self.module_path(tenant_id, name);
+        if path.exists() {
+            std::fs::remove_file(&path)
+                .map_err(|e| format!("Failed to delete transform module: {}", e))?;
+        }
+        info!("Removed transform '{}' of tenant {}", name, tenant_id);
+        Ok(true)
+    }
+
+    /// Whether any tenant registered a transform under `name`
+    pub fn contains(&self, name: &str) -> bool {
+        self.transforms.read().keys().any(|(_, n)| n == name)
+    }
+
+    /// A tenant's registered transforms; `active` marks those used by `stages`
+    pub fn list(&self, tenant_id: u64, stages: &[PipelineStage]) -> Vec<TransformInfo> {
+        self.transforms
+            .read()
+            .range((tenant_id, String::new())..)
+            .take_while(|((tenant, _), _)| *tenant == tenant_id)
+            .map(|((_, name), registered)| TransformInfo {
+                name: name.clone(),
+                kind: registered.transform.kind(),
+                active: stages.contains(&PipelineStage::Transform(name.clone())),
+                stats: registered.stats(),
+            })
+            .collect()
+    }
+
+    /// Run a tenant's transform `name` over `spans`, returning how many it
+    /// dropped
+    ///
+    /// A failing transform is logged and the span passes through unchanged.
+    /// Returns None if the tenant has no transform registered under `name`.
+    pub fn apply(
+        &self,
+        tenant_id: u64,
+        name: &str,
+        spans: &mut Vec<AgentreplaySpan>,
+    ) -> Option<usize> {
+        let key = (tenant_id, name.to_string());
+        let registered = self.transforms.read().get(&key).cloned()?;
+        let before = spans.len();
+
+        spans.retain_mut(|span| {
+            registered.runs.fetch_add(1, Ordering::Relaxed);
+            match registered.transform.transform(span) {
+                Ok(Some(updated)) => {
+                    *span = updated;
+                    true
+                }
+                Ok(None) => {
+                    registered.dropped.fetch_add(1, Ordering::Relaxed);
+                    false
+                }
+                Err(e) => {
+                    registered.errors.fetch_add(1, Ordering::Relaxed);
+                    warn!(
+                        "Transform '{}' failed on span {}: {}",
+                        name, span.span_id, e
+                    );
+                    *registered.last_error.write() = Some(e);
+                    true
+                }
+            }
+        });
+
+        Some(before - spans.len())
+    }
+
+    fn module_path(&self, tenant_id: u64, name: &str) -> PathBuf {
+        self.dir
+            .join(tenant_id.to_string())
+            .join(format!("{}.{}", name, TRANSFORM_MODULE_EXTENSION))
+    }
+
+    fn load_dir(&self) -> std::io::Result<()> {
+        if !self.dir.exists() {
+            return Ok(());
+        }
+
+        let mut transforms = self.transforms.write();
+        for entry in std::fs::read_dir(&self.dir)? {
+            let path = entry?.path();
+            if !path.is_dir() {
+                load_module(&mut transforms, LEGACY_TRANSFORM_TENANT, &path);
+                continue;
+            }
+            let Some(tenant_id) = path
+                .file_name()
+                .and_then(|s| s.to_str())
+                .and_then(|s| s.parse::<u64>().ok())
+            else {
+                warn!("Skipping transform directory {}", path.display());
+                continue;
+            };
+            for entry in std::fs::read_dir(&path)? {
+                load_module(&mut transforms, tenant_id, &entry?.path());
+            }
+        }
+
+        info!("Loaded {} transforms", transforms.len());
+        Ok(())
+    }
+}
+
+/// Compile and register the module at `path` if it is a transform module
+fn load_module(
+    transforms: &mut BTreeMap<(u64, String), Arc<RegisteredTransform>>,
+    tenant_id: u64,
+    path: &Path,
+) {
+    if path.extension().and_then(|e| e.to_str()) != Some(TRANSFORM_MODULE_EXTENSION) {
+        return;
+    }
+    let Some(name) = path.file_stem().and_then(|s| s.to_str()) else {
+        return;
+    };
+    if let Err(e) = validate_transform_name(name) {
+        warn!("Skipping transform module {}: {}", path.display(), e);
+        return;
+    }
+
+    match std::fs::read(path)
+        .map_err(|e| e.to_string())
+        .and_then(|module| compile_wasm(&module))
+    {
+        Ok(transform) => {
+            transforms.insert(
+                (tenant_id, name.to_string()),
+                Arc::new(RegisteredTransform::new(transform)),
+            );
+        }
+        Err(e) => warn!("Failed to load transform {}: {}", path.display(), e),
+    }
+}
+
+#[cfg(feature = "wasm")]
+fn compile_wasm(module: &[u8]) -> Result<Arc<dyn SpanTransform>, String> {
+    Ok(Arc::new(super::wasm_transform::WasmTransform::new(module)?))
+}
+
+#[cfg(not(feature = "wasm"))]
+fn compile_wasm(_module: &[u8]) -> Result<Arc<dyn SpanTransform>, String> {
+    Err("This server was built without WASM support (feature \"wasm\")".to_string())
+}
+
+/// Ingestion stages plus the transforms they can reference
+pub struct IngestPipeline {
+    stages: Vec<PipelineStage>,
+    transforms: TransformRegistry,
+}
+
+impl IngestPipeline {
+    /// `stages` must already pass [`validate_pipeline`]
+    pub fn new(stages: Vec<PipelineStage>, transforms: TransformRegistry) -> Self {
+        for stage in &stages {
+            if let PipelineStage::Transform(name) = stage {
+                if !transforms.contains(name) {
+                    warn!(
+                        "Pipeline stage '{}' has no registered transform yet; it is skipped until one is",
+                        stage
+                    );
+                }
+            }
+        }
+        Self { stages, transforms }
+    }
+
+    pub fn stages(&self) -> &[PipelineStage] {
+        &self.stages
+    }
+
+    pub fn transforms(&self) -> &TransformRegistry {
+        &self.transforms
+    }
+
+    /// Whether the stage is part of the pipeline
+    pub fn has(&self, stage: &PipelineStage) -> bool {
+        self.stages.contains(stage)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+
+    struct DropHealthchecks;
+
+    impl SpanTransform for DropHealthchecks {
+        fn kind(&self) -> &'static str {
+            "test"
+        }
+
+        fn transform(&self, span: &AgentreplaySpan) -> Result<Option<AgentreplaySpan>, String> {
+            if span.name == "healthcheck" {
+                return Ok(None);
+            }
+            if span.name == "broken" {
+                return Err("boom".to_string());
+            }
+            let mut span = span.clone();
+            if let Some(model) = span.attributes.remove("llm.model") {
+                span.attributes
+                    .insert("gen_ai.request.model".to_string(), model);
+            }
+            Ok(Some(span))
+        }
+    }
+
+    fn span(name: &str) -> AgentreplaySpan {
+        AgentreplaySpan {
+            span_id: "0x1".to_string(),
+            trace_id: "0x2".to_string(),
+            parent_span_id: None,
+            name: name.to_string(),
+            start_time: 1_700_000_000_000_000,
+            end_time: None,
+            attributes: HashMap::from([("llm.model".to_string(), "gpt-4o".to_string())]),
+        }
+    }
+
+    #[test]
+    fn test_parse_and_validate_pipeline() {
+        let stages = parse_pipeline("transform:normalize, sanitize, sample, store").unwrap();
+        assert_eq!(stages[0], PipelineStage::Transform("normalize".to_string()));
+        assert!(validate_pipeline(&stages).is_ok());
+        assert!(validate_pipeline(&default_pipeline()).is_ok());
+        let with_logprobs = parse_pipeline("sanitize, enrich, logprobs, store").unwrap();
+        assert_eq!(with_logprobs[2], PipelineStage::Logprobs);
+        assert!(validate_pipeline(&with_logprobs).is_ok());
+
+        let roundtrip: Vec<PipelineStage> =
+            serde_json::from_value(serde_json::to_value(&stages).unwrap()).unwrap();
+        assert_eq!(roundtrip, stages);
+
+        for bad in [
+            "sanitize, governor",
+            "sanitize, store, governor",
+            "governor, sample, store",
+            "sample, enrich, store",
+            "enrich, enrich, store",
+        ] {
+            let stages = parse_pipeline(bad).unwrap();
+            assert!(
+                validate_pipeline(&stages).is_err(),
+                "{} should be invalid",
+                bad
+            );
+        }
+        assert!(parse_pipeline("sanitize, compress, store").is_err());
+        assert!(parse_pipeline("transform:../etc, store").is_err());
+    }
+
+    #[test]
+    fn test_transform_rewrites_and_drops_spans() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let registry = TransformRegistry::new(dir.path());
+        registry
+            .register(1, "normalize", Arc::new(DropHealthchecks))
+            .unwrap();
+
+        let mut spans = vec![span("chat"), span("healthcheck"), span("broken")];
+        assert_eq!(registry.apply(1, "normalize", &mut spans), Some(1));
+        assert_eq!(spans.len(), 2);
+        assert_eq!(
+            spans[0]
+                .attributes
+                .get("gen_ai.request.model")
+                .map(String::as_str),
+            Some("gpt-4o")
+        );
+        // Failed transform leaves the span untouched
+        assert!(spans[1].attributes.contains_key("llm.model"));
+        assert_eq!(registry.apply(1, "missing", &mut spans), None);
+
+        let stages = parse_pipeline("transform:normalize, store").unwrap();
+        let info = &registry.list(1, &stages)[0];
+        assert!(info.active);
+        assert_eq!(
+            (info.stats.runs, info.stats.dropped, info.stats.errors),
+            (3, 1, 1)
+        );
+
+        assert!(registry.unregister(1, "normalize").unwrap());
+        assert!(!registry.unregister(1, "normalize").unwrap());
+    }
+
+    #[test]
+    fn test_transforms_are_per_tenant() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let registry = TransformRegistry::new(dir.path());
+        registry
+            .register(1, "normalize", Arc::new(DropHealthchecks))
+            .unwrap();
+        assert!(registry.contains("normalize"));
+
+        // Another tenant's spans skip the stage and its listing is empty
+        let mut spans = vec![span("healthcheck")];
+        assert_eq!(registry.apply(2, "normalize", &mut spans), None);
+        assert_eq!(spans.len(), 1);
+        let stages = parse_pipeline("transform:normalize, store").unwrap();
+        assert!(registry.list(2, &stages).is_empty());
+        assert!(!registry.unregister(2, "normalize").unwrap());
+
+        assert_eq!(registry.apply(1, "normalize", &mut spans), Some(1));
+        assert_eq!(registry.list(1, &stages).len(), 1);
+    }
+}
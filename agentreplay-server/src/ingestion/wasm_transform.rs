@@ -0,0 +1,203 @@
+// Copyright 2025 AgentReplay (https://github.com/agentreplay)
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program. If not, see <https://www.gnu.org/licenses/>.
+
+//! WASM transform plugins
+//!
+//! A transform is a core WebAssembly module without imports that exports:
+//!
+//! ```text
+//! memory                                linear memory
+//! alloc(len: i32) -> i32                buffer for the input span
+//! transform(ptr: i32, len: i32) -> i64  rewrite the span JSON at [ptr, ptr + len)
+//! ```
+//!
+//! The input is the span as JSON (`span_id`, `trace_id`, `parent_span_id`,
+//! `name`, `start_time`, `end_time`, `attributes`). `transform` returns the
+//! output span JSON packed as `(ptr << 32) | len`, 0 to drop the span, or a
+//! negative value to report an error (the span is then kept unchanged).
+//!
+//! Every span runs in a fresh instance with an instruction (fuel) budget and
+//! a memory cap, so a module cannot keep state between spans or stall ingestion.
+
+use super::pipeline::SpanTransform;
+use crate::api::ingest::AgentreplaySpan;
+use anyhow::Context;
+use wasmtime::{
+    Config, Engine, InstancePre, Linker, Module, Store, StoreLimits, StoreLimitsBuilder,
+};
+
+/// Instruction budget per span
+const FUEL_PER_SPAN: u64 = 10_000_000;
+
+/// Linear memory cap per instance
+const MAX_MEMORY_BYTES: usize = 64 * 1024 * 1024;
+
+const REQUIRED_EXPORTS: [&str; 3] = ["memory", "alloc", "transform"];
+
+/// A compiled transform module
+pub struct WasmTransform {
+    engine: Engine,
+    instance_pre: InstancePre<StoreLimits>,
+}
+
+impl WasmTransform {
+    /// Compile `module` (binary or text format) and check its exports
+    pub fn new(module: &[u8]) -> Result<Self, String> {
+        let mut config = Config::new();
+        config.consume_fuel(true);
+        let engine =
+            Engine::new(&config).map_err(|e| format!("Failed to create WASM engine: {}", e))?;
+
+        let module =
+            Module::new(&engine, module).map_err(|e| format!("Invalid WASM module: {}", e))?;
+        if let Some(missing) = REQUIRED_EXPORTS
+            .iter()
+            .find(|name| module.get_export(name).is_none())
+        {
+            return Err(format!("Transform module does not export '{}'", missing));
+        }
+
+        let instance_pre = Linker::new(&engine)
+            .instantiate_pre(&module)
+            .map_err(|e| format!("Transform module cannot be instantiated: {}", e))?;
+
+        Ok(Self {
+            engine,
+            instance_pre,
+        })
+    }
+
+    /// Run the module on one input; None means the span was dropped
+    fn run(&self, input: &[u8]) -> anyhow::Result<Option<Vec<u8>>> {
+        let limits = StoreLimitsBuilder::new()
+            .memory_size(MAX_MEMORY_BYTES)
+            .build();
+        let mut store = Store::new(&self.engine, limits);
+        store.limiter(|limits| limits);
+        store.set_fuel(FUEL_PER_SPAN)?;
+
+        let instance = self.instance_pre.instantiate(&mut store)?;
+        let memory = instance
+            .get_memory(&mut store, "memory")
+            .context("'memory' export is not a memory")?;
+        let alloc = instance.get_typed_func::<i32, i32>(&mut store, "alloc")?;
+        let transform = instance.get_typed_func::<(i32, i32), i64>(&mut store, "transform")?;
+
+        let len = i32::try_from(input.len()).context("span too large")?;
+        let ptr = alloc.call(&mut store, len)?;
+        memory.write(&mut store, ptr as u32 as usize, input)?;
+
+        let packed = transform.call(&mut store, (ptr, len))?;
+        if packed == 0 {
+            return Ok(None);
+        }
+        if packed < 0 {
+            anyhow::bail!("transform returned error code {}", packed);
+        }
+
+        let out_ptr = (packed as u64 >> 32) as usize;
+        let out_len = (packed as u64 & 0xFFFF_FFFF) as usize;
+        if out_ptr.saturating_add(out_len) > memory.data_size(&store) {
+            anyhow::bail!("transform returned an out-of-bounds buffer");
+        }
+        let mut output = vec![0u8; out_len];
+        memory.read(&store, out_ptr, &mut output)?;
+        Ok(Some(output))
+    }
+}
+
+impl SpanTransform for WasmTransform {
+    fn kind(&self) -> &'static str {
+        "wasm"
+    }
+
+    fn transform(&self, span: &AgentreplaySpan) -> Result<Option<AgentreplaySpan>, String> {
+        let input = serde_json::to_vec(span).map_err(|e| e.to_string())?;
+        let Some(output) = self.run(&input).map_err(|e| e.to_string())? else {
+            return Ok(None);
+        };
+        serde_json::from_slice(&output)
+            .map(Some)
+            .map_err(|e| format!("Transform returned an invalid span: {}", e))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+
+    /// Bump allocator shared by the test modules
+    const PRELUDE: &str = r#"
+        (memory (export "memory") 1)
+        (global $next (mut i32) (i32.const 1024))
+        (func (export "alloc") (param $len i32) (result i32)
+            (local $ptr i32)
+            (local.set $ptr (global.get $next))
+            (global.set $next (i32.add (global.get $next) (local.get $len)))
+            (local.get $ptr))
+    "#;
+
+    fn module(transform_body: &str) -> Vec<u8> {
+        format!(
+            r#"(module {}
+                (func (export "transform") (param $ptr i32) (param $len i32) (result i64)
+                    {}))"#,
+            PRELUDE, transform_body
+        )
+        .into_bytes()
+    }
+
+    fn span() -> AgentreplaySpan {
+        AgentreplaySpan {
+            span_id: "0x1".to_string(),
+            trace_id: "0x2".to_string(),
+            parent_span_id: None,
+            name: "chat".to_string(),
+            start_time: 1_700_000_000_000_000,
+            end_time: Some(1_700_000_000_500_000),
+            attributes: HashMap::from([("llm.model".to_string(), "gpt-4o".to_string())]),
+        }
+    }
+
+    #[test]
+    fn test_identity_drop_and_fuel_limit() {
+        let identity = WasmTransform::new(&module(
+            "(i64.or
+                (i64.shl (i64.extend_i32_u (local.get $ptr)) (i64.const 32))
+                (i64.extend_i32_u (local.get $len)))",
+        ))
+        .unwrap();
+        let out = identity.transform(&span()).unwrap().unwrap();
+        assert_eq!(out.name, "chat");
+        assert_eq!(out.attributes, span().attributes);
+
+        let drop_all = WasmTransform::new(&module("(i64.const 0)")).unwrap();
+        assert!(drop_all.transform(&span()).unwrap().is_none());
+
+        let failing = WasmTransform::new(&module("(i64.const -1)")).unwrap();
+        assert!(failing.transform(&span()).is_err());
+
+        let spinning =
+            WasmTransform::new(&module("(loop $spin (br $spin)) (i64.const 0)")).unwrap();
+        assert!(spinning.transform(&span()).is_err());
+    }
+
+    #[test]
+    fn test_rejects_modules_without_exports() {
+        assert!(WasmTransform::new(b"(module)").is_err());
+        assert!(WasmTransform::new(b"not wasm").is_err());
+    }
+}
@@ -0,0 +1,163 @@
+// Copyright 2025 AgentReplay (https://github.com/agentreplay)
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program. If not, see <https://www.gnu.org/licenses/>.
+
+//! Single-instance coordination on the data directory
+//!
+//! The server and the desktop app take the cooperative [`DataDirLock`] on
+//! their data directory at startup. When another process already holds it,
+//! `storage.on_lock_conflict` decides whether to refuse to start (`fail`, the
+//! default) or to serve the data read-only (`read_only`): writes are then
+//! rejected by [`crate::cluster::write_guard_middleware`] and background
+//! passes that modify the database are not started.
+//!
+//! `GET /api/v1/storage/lock` reports who holds the lock.
+
+use crate::api::AppState;
+use agentreplay_storage::{read_lock_owner, DataDirLock, DirLockOutcome, LockOwner};
+use axum::{extract::State, Json};
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+
+/// What to do when another process holds the data directory lock
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum LockConflictPolicy {
+    /// Refuse to start
+    #[default]
+    Fail,
+    /// Open the data directory read-only
+    ReadOnly,
+}
+
+/// This process's claim on its data directory
+#[derive(Debug)]
+pub struct InstanceLock {
+    data_dir: PathBuf,
+    /// None when another process holds the lock and we run read-only
+    lock: Option<DataDirLock>,
+}
+
+/// Lock state reported by `/api/v1/storage/lock`
+#[derive(Debug, Clone, Serialize)]
+pub struct InstanceLockStatus {
+    pub data_dir: String,
+    /// Whether this process holds the lock (false = read-only)
+    pub held_by_self: bool,
+    pub read_only: bool,
+    /// Current holder, read from the lock file
+    pub owner: Option<LockOwner>,
+}
+
+impl InstanceLock {
+    /// Lock `data_dir` for `app`, applying `policy` if it is already held
+    pub fn acquire(
+        data_dir: impl AsRef<Path>,
+        app: &str,
+        policy: LockConflictPolicy,
+    ) -> anyhow::Result<Self> {
+        let data_dir = data_dir.as_ref();
+        match DataDirLock::acquire(data_dir, app, env!("CARGO_PKG_VERSION"))? {
+            DirLockOutcome::Acquired(lock) => Ok(Self::held(data_dir, lock)),
+            DirLockOutcome::HeldBy(owner) => match policy {
+                LockConflictPolicy::Fail => anyhow::bail!(
+                    "Data directory {:?} is in use by {} {} (pid {} on {}). \
+                     Stop it first, or set storage.on_lock_conflict = \"read_only\" \
+                     (AGENTREPLAY_LOCK_CONFLICT=read_only) to open it read-only",
+                    data_dir,
+                    owner.app,
+                    owner.version,
+                    owner.pid,
+                    owner.hostname,
+                ),
+                LockConflictPolicy::ReadOnly => {
+                    tracing::warn!(
+                        "Data directory {:?} is in use by {} (pid {} on {}); opening read-only",
+                        data_dir,
+                        owner.app,
+                        owner.pid,
+                        owner.hostname
+                    );
+                    Ok(Self::read_only(data_dir))
+                }
+            },
+        }
+    }
+
+    /// Wrap a lock this process already holds
+    pub fn held(data_dir: impl AsRef<Path>, lock: DataDirLock) -> Self {
+        Self {
+            data_dir: data_dir.as_ref().to_path_buf(),
+            lock: Some(lock),
+        }
+    }
+
+    /// Serve `data_dir` without holding its lock
+    pub fn read_only(data_dir: impl AsRef<Path>) -> Self {
+        Self {
+            data_dir: data_dir.as_ref().to_path_buf(),
+            lock: None,
+        }
+    }
+
+    pub fn accepts_writes(&self) -> bool {
+        self.lock.is_some()
+    }
+
+    pub fn status(&self) -> InstanceLockStatus {
+        let owner = match read_lock_owner(&self.data_dir) {
+            Ok(owner) => owner,
+            Err(e) => {
+                tracing::warn!("Failed to read data directory lock: {}", e);
+                None
+            }
+        };
+        InstanceLockStatus {
+            data_dir: self.data_dir.display().to_string(),
+            held_by_self: self.lock.is_some(),
+            read_only: self.lock.is_none(),
+            owner,
+        }
+    }
+}
+
+/// GET /api/v1/storage/lock
+pub async fn get_lock_status(State(state): State<AppState>) -> Json<Option<InstanceLockStatus>> {
+    Json(state.instance_lock.as_ref().map(|lock| lock.status()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_conflict_policy() {
+        let dir = TempDir::new().unwrap();
+        let writer = InstanceLock::acquire(dir.path(), "server", LockConflictPolicy::Fail).unwrap();
+        assert!(writer.accepts_writes());
+
+        let err = InstanceLock::acquire(dir.path(), "desktop", LockConflictPolicy::Fail)
+            .unwrap_err()
+            .to_string();
+        assert!(err.contains("in use by server"), "{}", err);
+
+        let reader =
+            InstanceLock::acquire(dir.path(), "desktop", LockConflictPolicy::ReadOnly).unwrap();
+        assert!(!reader.accepts_writes());
+        let status = reader.status();
+        assert!(status.read_only);
+        assert_eq!(status.owner.map(|o| o.app).as_deref(), Some("server"));
+    }
+}
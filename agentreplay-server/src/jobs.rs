@@ -0,0 +1,606 @@
+// Copyright 2025 AgentReplay (https://github.com/agentreplay)
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program. If not, see <https://www.gnu.org/licenses/>.
+
+//! Background jobs
+//!
+//! Long-running operations such as bulk imports, backups and retention
+//! cleanup run as jobs instead of holding a request open. The
+//! [`JobManager`] queues them on a bounded worker pool, keeps their state
+//! and progress in `jobs.json` under the data directory and exposes them at
+//! `GET /api/v1/jobs`.
+//!
+//! A job is an async task handed a [`JobContext`], through which it reports
+//! progress and learns about cancellation. Cancellation is cooperative: a
+//! task that never checks [`JobContext::is_cancelled`] runs to completion.
+//! Jobs still queued or running when the server stops are marked failed on
+//! the next start, unless their owner resubmits them (imports resume from
+//! their own checkpoints).
+
+use agentreplay_core::clock::now_us;
+use std::collections::HashMap;
+use std::future::Future;
+use std::panic::AssertUnwindSafe;
+use std::path::{Path as FsPath, PathBuf};
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::Arc;
+
+use axum::{
+    extract::{Path, Query, State},
+    http::StatusCode,
+    Extension, Json,
+};
+use futures::FutureExt;
+use parking_lot::{Mutex, RwLock};
+use serde::{Deserialize, Serialize};
+use tokio::sync::{Notify, Semaphore};
+use tracing::{info, warn};
+
+use crate::api::{ApiError, AppState};
+use crate::auth::{AuthContext, Role};
+use crate::config::JobsConfig;
+
+/// Job state file under the data directory
+pub const JOBS_FILE: &str = "jobs.json";
+
+/// Minimum spacing of checkpoints written for progress updates alone
+const PROGRESS_PERSIST_INTERVAL_US: u64 = 5_000_000;
+const DEFAULT_LIST_LIMIT: usize = 100;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum JobState {
+    Queued,
+    Running,
+    Completed,
+    Failed,
+    Cancelled,
+}
+
+impl JobState {
+    pub fn is_finished(&self) -> bool {
+        matches!(
+            self,
+            JobState::Completed | JobState::Failed | JobState::Cancelled
+        )
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Job {
+    pub id: String,
+    /// What the job does, e.g. "import" or "backup"
+    pub kind: String,
+    pub tenant_id: u64,
+    pub description: String,
+    pub state: JobState,
+    pub created_at_us: u64,
+    #[serde(default)]
+    pub started_at_us: Option<u64>,
+    #[serde(default)]
+    pub finished_at_us: Option<u64>,
+    /// Fraction done, 0 to 1, as reported by the job
+    #[serde(default)]
+    pub progress: f64,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub message: Option<String>,
+    /// Kind-specific status, such as import counters
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub detail: Option<serde_json::Value>,
+    /// Output of a completed job
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub result: Option<serde_json::Value>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+    #[serde(default)]
+    pub cancel_requested: bool,
+}
+
+/// What a job task returns: its result, or why it failed
+pub type JobResult = Result<Option<serde_json::Value>, String>;
+
+#[derive(Default)]
+struct CancelSignal {
+    cancelled: AtomicBool,
+    notify: Notify,
+}
+
+impl CancelSignal {
+    fn cancel(&self) {
+        self.cancelled.store(true, Ordering::SeqCst);
+        self.notify.notify_waiters();
+    }
+}
+
+/// Handle given to a running job
+#[derive(Clone)]
+pub struct JobContext {
+    id: String,
+    manager: Arc<JobManager>,
+    signal: Arc<CancelSignal>,
+}
+
+impl JobContext {
+    pub fn id(&self) -> &str {
+        &self.id
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.signal.cancelled.load(Ordering::SeqCst)
+    }
+
+    /// Resolves once cancellation is requested, for use in `select!`
+    pub async fn cancelled(&self) {
+        let notified = self.signal.notify.notified();
+        if self.is_cancelled() {
+            return;
+        }
+        notified.await;
+    }
+
+    /// Report progress (clamped to 0..=1) and an optional status line
+    pub fn progress(&self, progress: f64, message: Option<String>) {
+        self.manager.update(&self.id, false, |job| {
+            job.progress = progress.clamp(0.0, 1.0);
+            if message.is_some() {
+                job.message = message;
+            }
+        });
+    }
+
+    /// Replace the kind-specific status shown with the job
+    pub fn detail(&self, detail: serde_json::Value) {
+        self.manager.update(&self.id, false, |job| {
+            job.detail = Some(detail);
+        });
+    }
+}
+
+/// Queue, worker pool and state of background jobs
+pub struct JobManager {
+    config: JobsConfig,
+    storage_path: Option<PathBuf>,
+    jobs: RwLock<HashMap<String, Job>>,
+    signals: Mutex<HashMap<String, Arc<CancelSignal>>>,
+    workers: Arc<Semaphore>,
+    last_persist_us: AtomicU64,
+}
+
+impl Default for JobManager {
+    fn default() -> Self {
+        Self::new(JobsConfig::default())
+    }
+}
+
+impl JobManager {
+    pub fn new(config: JobsConfig) -> Self {
+        Self {
+            workers: Arc::new(Semaphore::new(config.workers.max(1))),
+            config,
+            storage_path: None,
+            jobs: RwLock::new(HashMap::new()),
+            signals: Mutex::new(HashMap::new()),
+            last_persist_us: AtomicU64::new(0),
+        }
+    }
+
+    /// Load jobs from `path`; those interrupted by a shutdown are marked failed
+    pub fn with_storage(config: JobsConfig, path: impl AsRef<FsPath>) -> Self {
+        let mut manager = Self::new(config);
+        let path = path.as_ref().to_path_buf();
+
+        match std::fs::read(&path) {
+            Ok(bytes) => match serde_json::from_slice::<Vec<Job>>(&bytes) {
+                Ok(jobs) => {
+                    let now = now_us();
+                    let jobs = jobs
+                        .into_iter()
+                        .map(|mut job| {
+                            if !job.state.is_finished() {
+                                job.state = JobState::Failed;
+                                job.finished_at_us = Some(now);
+                                job.error = Some("interrupted by a server restart".to_string());
+                            }
+                            (job.id.clone(), job)
+                        })
+                        .collect();
+                    manager.jobs = RwLock::new(jobs);
+                }
+                Err(e) => warn!("Failed to parse {:?}: {}", path, e),
+            },
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => {}
+            Err(e) => warn!("Failed to read {:?}: {}", path, e),
+        }
+
+        manager.storage_path = Some(path);
+        manager
+    }
+
+    pub fn get(&self, tenant_id: u64, id: &str) -> Option<Job> {
+        self.jobs
+            .read()
+            .get(id)
+            .filter(|job| job.tenant_id == tenant_id)
+            .cloned()
+    }
+
+    /// Jobs of a tenant, newest first
+    pub fn list(&self, tenant_id: u64, kind: Option<&str>, state: Option<JobState>) -> Vec<Job> {
+        let mut jobs: Vec<Job> = self
+            .jobs
+            .read()
+            .values()
+            .filter(|job| job.tenant_id == tenant_id)
+            .filter(|job| kind.is_none_or(|kind| job.kind == kind))
+            .filter(|job| state.is_none_or(|state| job.state == state))
+            .cloned()
+            .collect();
+        jobs.sort_by(|a, b| b.created_at_us.cmp(&a.created_at_us));
+        jobs
+    }
+
+    /// Queue `task` under a new id
+    pub fn submit<F, Fut>(
+        self: &Arc<Self>,
+        kind: &str,
+        tenant_id: u64,
+        description: String,
+        task: F,
+    ) -> Job
+    where
+        F: FnOnce(JobContext) -> Fut + Send + 'static,
+        Fut: Future<Output = JobResult> + Send + 'static,
+    {
+        let id = hex::encode(rand::random::<[u8; 8]>());
+        self.submit_as(id, kind, tenant_id, description, task)
+    }
+
+    /// Queue `task` under `id`, restarting the record of a finished job
+    /// with that id
+    pub fn submit_as<F, Fut>(
+        self: &Arc<Self>,
+        id: String,
+        kind: &str,
+        tenant_id: u64,
+        description: String,
+        task: F,
+    ) -> Job
+    where
+        F: FnOnce(JobContext) -> Fut + Send + 'static,
+        Fut: Future<Output = JobResult> + Send + 'static,
+    {
+        let job = {
+            let mut jobs = self.jobs.write();
+            let created_at_us = jobs.get(&id).map_or_else(now_us, |job| job.created_at_us);
+            let job = Job {
+                id: id.clone(),
+                kind: kind.to_string(),
+                tenant_id,
+                description,
+                state: JobState::Queued,
+                created_at_us,
+                started_at_us: None,
+                finished_at_us: None,
+                progress: 0.0,
+                message: None,
+                detail: None,
+                result: None,
+                error: None,
+                cancel_requested: false,
+            };
+            jobs.insert(id.clone(), job.clone());
+            job
+        };
+        self.persist_logged();
+
+        let signal = Arc::new(CancelSignal::default());
+        self.signals.lock().insert(id.clone(), Arc::clone(&signal));
+        let manager = Arc::clone(self);
+        tokio::spawn(async move {
+            let Ok(_permit) = Arc::clone(&manager.workers).acquire_owned().await else {
+                return;
+            };
+            if signal.cancelled.load(Ordering::SeqCst) {
+                manager.finish(&id, Err("cancelled before it started".to_string()), true);
+                return;
+            }
+            manager.update(&id, true, |job| {
+                job.state = JobState::Running;
+                job.started_at_us = Some(now_us());
+            });
+
+            let context = JobContext {
+                id: id.clone(),
+                manager: Arc::clone(&manager),
+                signal: Arc::clone(&signal),
+            };
+            let result = AssertUnwindSafe(task(context))
+                .catch_unwind()
+                .await
+                .unwrap_or_else(|_| Err("job panicked".to_string()));
+            manager.finish(&id, result, signal.cancelled.load(Ordering::SeqCst));
+        });
+        job
+    }
+
+    /// Ask a queued or running job to stop
+    pub fn cancel(&self, tenant_id: u64, id: &str) -> Result<Job, ApiError> {
+        let job = self
+            .get(tenant_id, id)
+            .ok_or_else(|| ApiError::NotFound(format!("Job '{}' not found", id)))?;
+        if job.state.is_finished() {
+            return Err(ApiError::BadRequest(format!(
+                "job '{}' already finished",
+                id
+            )));
+        }
+        if let Some(signal) = self.signals.lock().get(id) {
+            signal.cancel();
+        }
+        self.update(id, true, |job| job.cancel_requested = true)
+            .ok_or_else(|| ApiError::NotFound(format!("Job '{}' not found", id)))
+    }
+
+    fn finish(&self, id: &str, result: JobResult, cancelled: bool) {
+        self.signals.lock().remove(id);
+        let job = self.update(id, true, |job| {
+            job.finished_at_us = Some(now_us());
+            match result {
+                Ok(output) => {
+                    job.state = JobState::Completed;
+                    job.progress = 1.0;
+                    job.result = output;
+                }
+                Err(_) if cancelled => job.state = JobState::Cancelled,
+                Err(e) => {
+                    job.state = JobState::Failed;
+                    job.error = Some(e);
+                }
+            }
+        });
+        if let Some(job) = job {
+            match &job.error {
+                Some(e) => warn!("Job {} ({}) failed: {}", job.id, job.kind, e),
+                None => info!("Job {} ({}) {:?}", job.id, job.kind, job.state),
+            }
+        }
+        self.prune();
+    }
+
+    /// Drop the oldest finished jobs beyond `keep_finished`
+    fn prune(&self) {
+        let mut jobs = self.jobs.write();
+        let mut finished: Vec<(u64, String)> = jobs
+            .values()
+            .filter(|job| job.state.is_finished())
+            .map(|job| {
+                (
+                    job.finished_at_us.unwrap_or(job.created_at_us),
+                    job.id.clone(),
+                )
+            })
+            .collect();
+        if finished.len() <= self.config.keep_finished {
+            return;
+        }
+        finished.sort();
+        let excess = finished.len() - self.config.keep_finished;
+        for (_, id) in finished.into_iter().take(excess) {
+            jobs.remove(&id);
+        }
+    }
+
+    /// Apply `f` to a job; progress-only updates are persisted at most
+    /// every few seconds
+    fn update(&self, id: &str, persist: bool, f: impl FnOnce(&mut Job)) -> Option<Job> {
+        let job = {
+            let mut jobs = self.jobs.write();
+            let job = jobs.get_mut(id)?;
+            f(job);
+            job.clone()
+        };
+        let now = now_us();
+        let last = self.last_persist_us.load(Ordering::Relaxed);
+        if persist || now.saturating_sub(last) >= PROGRESS_PERSIST_INTERVAL_US {
+            self.persist_logged();
+        }
+        Some(job)
+    }
+
+    fn persist_logged(&self) {
+        if let Err(e) = self.persist() {
+            warn!("Failed to persist jobs: {}", e);
+        }
+    }
+
+    fn persist(&self) -> std::io::Result<()> {
+        let Some(path) = &self.storage_path else {
+            return Ok(());
+        };
+        self.last_persist_us.store(now_us(), Ordering::Relaxed);
+        let jobs: Vec<Job> = self.jobs.read().values().cloned().collect();
+
+        let tmp = path.with_extension("json.tmp");
+        std::fs::write(&tmp, serde_json::to_vec_pretty(&jobs)?)?;
+        std::fs::rename(tmp, path)
+    }
+}
+
+/// `?background=true` on endpoints that can run as a job instead of inline
+#[derive(Debug, Default, Deserialize)]
+pub struct BackgroundQuery {
+    #[serde(default)]
+    pub background: bool,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ListJobsQuery {
+    #[serde(default)]
+    pub kind: Option<String>,
+    #[serde(default)]
+    pub state: Option<JobState>,
+    #[serde(default)]
+    pub limit: Option<usize>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ListJobsResponse {
+    pub jobs: Vec<Job>,
+    pub total: usize,
+}
+
+/// GET /api/v1/jobs
+pub async fn list_jobs(
+    State(state): State<AppState>,
+    Extension(auth): Extension<AuthContext>,
+    Query(params): Query<ListJobsQuery>,
+) -> Json<ListJobsResponse> {
+    let mut jobs = state
+        .jobs
+        .list(auth.tenant_id, params.kind.as_deref(), params.state);
+    let total = jobs.len();
+    jobs.truncate(params.limit.unwrap_or(DEFAULT_LIST_LIMIT));
+    Json(ListJobsResponse { jobs, total })
+}
+
+/// GET /api/v1/jobs/:id
+pub async fn get_job(
+    State(state): State<AppState>,
+    Extension(auth): Extension<AuthContext>,
+    Path(id): Path<String>,
+) -> Result<Json<Job>, ApiError> {
+    state
+        .jobs
+        .get(auth.tenant_id, &id)
+        .map(Json)
+        .ok_or_else(|| ApiError::NotFound(format!("Job '{}' not found", id)))
+}
+
+/// DELETE /api/v1/jobs/:id
+///
+/// Requests cancellation; the job stops at its next check.
+pub async fn cancel_job(
+    State(state): State<AppState>,
+    Extension(auth): Extension<AuthContext>,
+    Path(id): Path<String>,
+) -> Result<(StatusCode, Json<Job>), ApiError> {
+    if auth.role < Role::Member {
+        return Err(ApiError::Forbidden(format!(
+            "role '{}' may not cancel jobs",
+            auth.role.as_str()
+        )));
+    }
+    let job = state.jobs.cancel(auth.tenant_id, &id)?;
+    Ok((StatusCode::ACCEPTED, Json(job)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    async fn wait_finished(manager: &JobManager, id: &str) -> Job {
+        for _ in 0..200 {
+            let job = manager.get(1, id).unwrap();
+            if job.state.is_finished() {
+                return job;
+            }
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+        panic!("job {} did not finish", id);
+    }
+
+    #[tokio::test]
+    async fn test_job_lifecycle_and_cancellation() {
+        let manager = Arc::new(JobManager::default());
+
+        let done = manager.submit("test", 1, "adds".to_string(), |ctx| async move {
+            ctx.progress(0.5, Some("halfway".to_string()));
+            Ok(Some(serde_json::json!({ "sum": 3 })))
+        });
+        let job = wait_finished(&manager, &done.id).await;
+        assert_eq!(job.state, JobState::Completed);
+        assert_eq!(job.progress, 1.0);
+        assert_eq!(job.message.as_deref(), Some("halfway"));
+        assert_eq!(job.result, Some(serde_json::json!({ "sum": 3 })));
+
+        let failed = manager.submit("test", 1, "fails".to_string(), |_| async move {
+            Err("boom".to_string())
+        });
+        let job = wait_finished(&manager, &failed.id).await;
+        assert_eq!(job.state, JobState::Failed);
+        assert_eq!(job.error.as_deref(), Some("boom"));
+
+        let slow = manager.submit("test", 1, "waits".to_string(), |ctx| async move {
+            ctx.cancelled().await;
+            Err("stopped".to_string())
+        });
+        assert!(manager.cancel(2, &slow.id).is_err());
+        manager.cancel(1, &slow.id).unwrap();
+        let job = wait_finished(&manager, &slow.id).await;
+        assert_eq!(job.state, JobState::Cancelled);
+        assert!(job.cancel_requested);
+        assert!(manager.cancel(1, &slow.id).is_err());
+
+        assert_eq!(manager.list(1, Some("test"), None).len(), 3);
+        assert_eq!(manager.list(1, None, Some(JobState::Failed)).len(), 1);
+        assert!(manager.list(2, None, None).is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_interrupted_jobs_fail_on_reload() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join(JOBS_FILE);
+        let manager = Arc::new(JobManager::with_storage(JobsConfig::default(), &path));
+        let job = manager.submit("test", 1, "never ends".to_string(), |ctx| async move {
+            ctx.cancelled().await;
+            Ok(None)
+        });
+        wait_running(&manager, &job.id).await;
+
+        let reloaded = JobManager::with_storage(JobsConfig::default(), &path);
+        let job = reloaded.get(1, &job.id).unwrap();
+        assert_eq!(job.state, JobState::Failed);
+        assert!(job.error.unwrap().contains("restart"));
+    }
+
+    async fn wait_running(manager: &JobManager, id: &str) {
+        for _ in 0..200 {
+            if manager.get(1, id).unwrap().state == JobState::Running {
+                return;
+            }
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+        panic!("job {} did not start", id);
+    }
+
+    #[tokio::test]
+    async fn test_finished_jobs_are_pruned() {
+        let manager = Arc::new(JobManager::new(JobsConfig {
+            workers: 1,
+            keep_finished: 2,
+        }));
+        let mut ids = Vec::new();
+        for i in 0..4 {
+            let job = manager.submit("test", 1, format!("job {}", i), |_| async move { Ok(None) });
+            wait_finished(&manager, &job.id).await;
+            // Distinct finish times keep the pruning order deterministic
+            tokio::time::sleep(Duration::from_millis(2)).await;
+            ids.push(job.id);
+        }
+        assert!(manager.get(1, &ids[0]).is_none());
+        assert!(manager.get(1, &ids[3]).is_some());
+        assert_eq!(manager.list(1, None, None).len(), 2);
+    }
+}
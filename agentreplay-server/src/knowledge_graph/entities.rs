@@ -60,6 +60,16 @@ pub enum EntityType {
     Model,
     /// A user or agent
     Agent,
+    /// An API endpoint or URL (e.g., "POST /v1/charges")
+    Api,
+    /// An issue tracker ticket (e.g., "PROJ-123", "org/repo#42")
+    Ticket,
+    /// A person (e.g., "@alice", "alice@example.com")
+    Person,
+    /// A trace span that mentioned other entities
+    Trace,
+    /// A memory observation
+    Observation,
     /// A generic concept
     Concept,
     /// Unknown type
@@ -76,6 +86,11 @@ impl EntityType {
             "error" | "exception" => EntityType::Error,
             "model" | "llm" => EntityType::Model,
             "agent" | "user" => EntityType::Agent,
+            "api" | "endpoint" | "url" => EntityType::Api,
+            "ticket" | "issue" => EntityType::Ticket,
+            "person" | "people" => EntityType::Person,
+            "trace" | "span" => EntityType::Trace,
+            "observation" | "memory" => EntityType::Observation,
             "concept" | "idea" => EntityType::Concept,
             _ => EntityType::Unknown,
         }
@@ -110,6 +125,8 @@ pub enum RelationType {
     RelatedTo,
     /// A is similar to B
     SimilarTo,
+    /// A mentions B (a trace or observation referencing an entity)
+    Mentions,
 }
 
 impl RelationType {
@@ -126,6 +143,7 @@ impl RelationType {
             "CONSUMES" | "INPUTS" | "TAKES" => RelationType::Consumes,
             "CAUSES" | "LEADS_TO" | "RESULTS_IN" => RelationType::Causes,
             "SIMILAR_TO" | "LIKE" => RelationType::SimilarTo,
+            "MENTIONS" | "REFERENCES" | "TOUCHES" => RelationType::Mentions,
             _ => RelationType::RelatedTo,
         }
     }
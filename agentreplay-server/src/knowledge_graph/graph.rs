@@ -51,6 +51,35 @@ pub struct GraphEdge {
     pub occurrence_count: u32,
 }
 
+/// Direction of a traversal relative to the starting entity
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum Direction {
+    /// Follow relationships from the entity (`entity -> neighbor`)
+    Outgoing,
+    /// Follow relationships into the entity (`neighbor -> entity`)
+    Incoming,
+    /// Follow both
+    #[default]
+    Both,
+}
+
+/// An entity reached by a traversal
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Neighbor {
+    pub entity: Entity,
+    /// Relationship of the edge that reached this entity
+    pub relation: RelationType,
+    /// Direction of that edge relative to the entity it was reached from
+    pub direction: Direction,
+    pub confidence: f64,
+    pub occurrence_count: u32,
+    /// Trace edge that produced the relationship
+    pub source_edge_id: Option<u128>,
+    /// Hops from the starting entity (1 = direct neighbor)
+    pub depth: usize,
+}
+
 /// Semantic Knowledge Graph
 pub struct SemanticGraph {
     /// Entity storage (ID -> Entity)
@@ -207,6 +236,84 @@ impl SemanticGraph {
             .unwrap_or_default()
     }
 
+    /// Set an attribute on an entity, returning false if it doesn't exist
+    pub fn set_entity_attribute(&self, entity_id: EntityId, key: &str, value: String) -> bool {
+        match self.entities.get_mut(&entity_id) {
+            Some(mut entity) => {
+                entity.attributes.insert(key.to_string(), value);
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Breadth-first traversal from an entity up to `max_depth` hops
+    ///
+    /// Each entity is reported once, at its shortest depth. `include`
+    /// filters which neighbors are reported; traversal continues through
+    /// filtered-out entities so e.g. files can be reached via traces.
+    pub fn neighbors(
+        &self,
+        entity_id: EntityId,
+        direction: Direction,
+        max_depth: usize,
+        include: impl Fn(&Neighbor) -> bool,
+    ) -> Vec<Neighbor> {
+        let mut visited: HashSet<EntityId> = HashSet::from([entity_id]);
+        let mut frontier = vec![entity_id];
+        let mut results = Vec::new();
+
+        for depth in 1..=max_depth.max(1) {
+            let mut next_frontier = Vec::new();
+            for id in frontier {
+                let mut edges: Vec<(EntityId, GraphEdge, Direction)> = Vec::new();
+                if direction != Direction::Incoming {
+                    edges.extend(
+                        self.get_outgoing(id)
+                            .into_iter()
+                            .map(|e| (e.to, e, Direction::Outgoing)),
+                    );
+                }
+                if direction != Direction::Outgoing {
+                    edges.extend(
+                        self.get_incoming(id)
+                            .into_iter()
+                            .map(|e| (e.from, e, Direction::Incoming)),
+                    );
+                }
+
+                for (other, edge, edge_direction) in edges {
+                    if !visited.insert(other) {
+                        continue;
+                    }
+                    let Some(entity) = self.get_entity(other) else {
+                        continue;
+                    };
+                    next_frontier.push(other);
+
+                    let neighbor = Neighbor {
+                        entity,
+                        relation: edge.relation,
+                        direction: edge_direction,
+                        confidence: edge.confidence,
+                        occurrence_count: edge.occurrence_count,
+                        source_edge_id: edge.source_edge_id,
+                        depth,
+                    };
+                    if include(&neighbor) {
+                        results.push(neighbor);
+                    }
+                }
+            }
+            if next_frontier.is_empty() {
+                break;
+            }
+            frontier = next_frontier;
+        }
+
+        results
+    }
+
     /// Query: "What depends on X?"
     pub fn what_depends_on(&self, entity_name: &str) -> Vec<(Entity, RelationType, f64)> {
         let normalized = normalize_entity_name(entity_name);
@@ -540,6 +647,46 @@ mod tests {
         assert!(names.contains(&"payment.rs"));
     }
 
+    #[test]
+    fn test_neighbors() {
+        let graph = SemanticGraph::new();
+
+        // span_1 -> auth.rs, span_2 -> auth.rs, span_2 -> PROJ-7
+        for (span, entity, entity_type) in [
+            ("span_1", "auth.rs", EntityType::File),
+            ("span_2", "auth.rs", EntityType::File),
+            ("span_2", "PROJ-7", EntityType::Ticket),
+        ] {
+            graph.add_triple(&Triple::with_types(
+                span,
+                EntityType::Trace,
+                RelationType::Mentions,
+                entity,
+                entity_type,
+            ));
+        }
+        let file = graph.get_entity_by_name("auth.rs").unwrap();
+
+        let traces = graph.neighbors(file.id, Direction::Incoming, 1, |n| {
+            n.entity.entity_type == EntityType::Trace
+        });
+        let mut names: Vec<&str> = traces.iter().map(|n| n.entity.name.as_str()).collect();
+        names.sort();
+        assert_eq!(names, vec!["span_1", "span_2"]);
+
+        // Two hops: auth.rs <- span_2 -> PROJ-7
+        let tickets = graph.neighbors(file.id, Direction::Both, 2, |n| {
+            n.entity.entity_type == EntityType::Ticket
+        });
+        assert_eq!(tickets.len(), 1);
+        assert_eq!(tickets[0].depth, 2);
+        assert_eq!(tickets[0].direction, Direction::Outgoing);
+
+        assert!(graph
+            .neighbors(file.id, Direction::Outgoing, 3, |_| true)
+            .is_empty());
+    }
+
     #[test]
     fn test_what_breaks() {
         let graph = SemanticGraph::new();
@@ -0,0 +1,490 @@
+// Copyright 2025 AgentReplay (https://github.com/agentreplay)
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program. If not, see <https://www.gnu.org/licenses/>.
+
+//! Automatic knowledge graph indexing
+//!
+//! Every stored span and saved memory is scanned for files, APIs, tickets
+//! and people (see [`extract_mentions`]). Each span becomes a `Trace`
+//! entity and each memory an `Observation` entity, linked to what they
+//! mention with `MENTIONS` edges. Tenants get separate graphs, persisted
+//! under `<data_dir>/knowledge_graph/tenant_<id>.json`.
+//!
+//! Served at:
+//! - `GET /api/v1/kg/entity/:id/neighbors?direction=incoming&entity_type=trace`
+//! - `GET /api/v1/kg/entities?q=auth&entity_type=file`
+
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::Duration;
+
+use agentreplay_core::AgentFlowEdge;
+use axum::{
+    extract::{Path as AxumPath, Query, State},
+    Json,
+};
+use dashmap::{DashMap, DashSet};
+use serde::{Deserialize, Serialize};
+
+use crate::api::query::ApiError;
+use crate::api::AppState;
+use crate::auth::AuthContext;
+use crate::knowledge_graph::entities::{Entity, EntityType, RelationType, Triple};
+use crate::knowledge_graph::graph::{Direction, Neighbor, SemanticGraph};
+use crate::knowledge_graph::mentions::{extract_mentions, Mention};
+
+/// Directory under the data dir holding one graph file per tenant
+pub const KNOWLEDGE_GRAPH_DIR: &str = "knowledge_graph";
+
+/// How often modified graphs are written to disk
+const FLUSH_INTERVAL: Duration = Duration::from_secs(30);
+
+/// Maximum traversal depth accepted by the neighbors endpoint
+const MAX_DEPTH: usize = 3;
+const DEFAULT_LIMIT: usize = 100;
+const MAX_LIMIT: usize = 1000;
+
+/// Span attributes naming a file, API or person directly
+const FILE_ATTRS: &[&str] = &["code.filepath", "code.file.path"];
+const URL_ATTRS: &[&str] = &["url.full", "http.url"];
+const PERSON_ATTRS: &[&str] = &["user.id", "enduser.id", "user.email"];
+
+/// Per-tenant knowledge graphs fed from ingestion and memory
+pub struct KnowledgeGraphIndexer {
+    graphs: DashMap<u64, Arc<SemanticGraph>>,
+    storage_dir: Option<PathBuf>,
+    /// Tenants with changes not yet flushed
+    dirty: DashSet<u64>,
+}
+
+impl Default for KnowledgeGraphIndexer {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl KnowledgeGraphIndexer {
+    /// In-memory indexer; graphs are lost on restart
+    pub fn new() -> Self {
+        Self {
+            graphs: DashMap::new(),
+            storage_dir: None,
+            dirty: DashSet::new(),
+        }
+    }
+
+    /// Indexer persisting graphs under `dir`, loading any already saved
+    pub fn with_storage(dir: impl AsRef<Path>) -> Self {
+        let mut indexer = Self::new();
+        let dir = dir.as_ref().to_path_buf();
+
+        match std::fs::read_dir(&dir) {
+            Ok(entries) => {
+                for entry in entries.flatten() {
+                    let path = entry.path();
+                    let Some(tenant_id) = path
+                        .file_name()
+                        .and_then(|n| n.to_str())
+                        .and_then(|n| n.strip_prefix("tenant_")?.strip_suffix(".json"))
+                        .and_then(|id| id.parse::<u64>().ok())
+                    else {
+                        continue;
+                    };
+                    match SemanticGraph::with_persistence(&path) {
+                        Ok(graph) => {
+                            indexer.graphs.insert(tenant_id, Arc::new(graph));
+                        }
+                        Err(e) => tracing::warn!("Failed to load {:?}: {}", path, e),
+                    }
+                }
+            }
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => {}
+            Err(e) => tracing::warn!("Failed to read {:?}: {}", dir, e),
+        }
+
+        indexer.storage_dir = Some(dir);
+        indexer
+    }
+
+    /// Graph of a tenant, if anything has been indexed for it
+    pub fn graph(&self, tenant_id: u64) -> Option<Arc<SemanticGraph>> {
+        self.graphs.get(&tenant_id).map(|g| Arc::clone(&g))
+    }
+
+    fn graph_mut(&self, tenant_id: u64) -> Arc<SemanticGraph> {
+        let graph = self
+            .graphs
+            .entry(tenant_id)
+            .or_insert_with(|| {
+                let path = self
+                    .storage_dir
+                    .as_ref()
+                    .map(|dir| dir.join(format!("tenant_{}.json", tenant_id)));
+                Arc::new(match path {
+                    Some(path) => SemanticGraph::with_persistence(&path).unwrap_or_else(|e| {
+                        tracing::warn!("Failed to open {:?}: {}", path, e);
+                        SemanticGraph::new()
+                    }),
+                    None => SemanticGraph::new(),
+                })
+            })
+            .clone();
+        self.dirty.insert(tenant_id);
+        graph
+    }
+
+    /// Index the entities a stored span mentions, returning how many were linked
+    pub fn record_span(&self, edge: &AgentFlowEdge, attrs: &HashMap<String, String>) -> usize {
+        let mut mentions = Vec::new();
+        for key in FILE_ATTRS {
+            if let Some(path) = attrs.get(*key) {
+                mentions.push(Mention {
+                    name: path.clone(),
+                    entity_type: EntityType::File,
+                });
+            }
+        }
+        if let Some(route) = attrs.get("http.route") {
+            let name = match attrs
+                .get("http.request.method")
+                .or_else(|| attrs.get("http.method"))
+            {
+                Some(method) => format!("{} {}", method.to_uppercase(), route),
+                None => route.clone(),
+            };
+            mentions.push(Mention {
+                name,
+                entity_type: EntityType::Api,
+            });
+        }
+        for key in URL_ATTRS {
+            if let Some(url) = attrs.get(*key) {
+                mentions.push(Mention {
+                    name: url.clone(),
+                    entity_type: EntityType::Api,
+                });
+            }
+        }
+        for key in PERSON_ATTRS {
+            if let Some(person) = attrs.get(*key) {
+                mentions.push(Mention {
+                    name: person.clone(),
+                    entity_type: EntityType::Person,
+                });
+            }
+        }
+        for value in attrs.values() {
+            mentions.extend(extract_mentions(value));
+        }
+        if mentions.is_empty() {
+            return 0;
+        }
+
+        let graph = self.graph_mut(edge.tenant_id);
+        let trace = format!("span:{:#x}", edge.edge_id);
+        let trace_id = graph.get_or_create_entity(&trace, EntityType::Trace);
+        graph.set_entity_attribute(trace_id, "edge_id", format!("{:#x}", edge.edge_id));
+        graph.set_entity_attribute(trace_id, "session_id", edge.session_id.to_string());
+        graph.set_entity_attribute(trace_id, "timestamp_us", edge.timestamp_us.to_string());
+
+        link_mentions(&graph, &trace, EntityType::Trace, edge.edge_id, mentions)
+    }
+
+    /// Index the entities a memory observation mentions
+    pub fn record_observation(
+        &self,
+        tenant_id: u64,
+        memory_id: u128,
+        content: &str,
+        tags: &[String],
+    ) -> usize {
+        let mut mentions = extract_mentions(content);
+        for tag in tags {
+            mentions.extend(extract_mentions(tag));
+        }
+        if mentions.is_empty() {
+            return 0;
+        }
+
+        let graph = self.graph_mut(tenant_id);
+        let observation = format!("memory:{:#x}", memory_id);
+        let observation_id = graph.get_or_create_entity(&observation, EntityType::Observation);
+        graph.set_entity_attribute(observation_id, "memory_id", format!("{:#x}", memory_id));
+
+        link_mentions(
+            &graph,
+            &observation,
+            EntityType::Observation,
+            memory_id,
+            mentions,
+        )
+    }
+
+    /// Write graphs changed since the last flush
+    pub fn flush(&self) {
+        let tenants: Vec<u64> = self.dirty.iter().map(|t| *t).collect();
+        for tenant_id in tenants {
+            self.dirty.remove(&tenant_id);
+            if let Some(graph) = self.graph(tenant_id) {
+                if let Err(e) = graph.save_to_disk() {
+                    tracing::warn!("Failed to persist knowledge graph {}: {}", tenant_id, e);
+                }
+            }
+        }
+    }
+
+    /// Flush periodically until the server stops
+    pub fn spawn_flush(self: &Arc<Self>) {
+        if self.storage_dir.is_none() {
+            return;
+        }
+        let indexer = Arc::clone(self);
+        tokio::spawn(async move {
+            let mut interval = tokio::time::interval(FLUSH_INTERVAL);
+            interval.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+            loop {
+                interval.tick().await;
+                let indexer = Arc::clone(&indexer);
+                if let Err(e) = tokio::task::spawn_blocking(move || indexer.flush()).await {
+                    tracing::warn!("Knowledge graph flush failed: {}", e);
+                }
+            }
+        });
+    }
+}
+
+/// Link `source` to each distinct mention, returning the number linked
+fn link_mentions(
+    graph: &SemanticGraph,
+    source: &str,
+    source_type: EntityType,
+    source_edge_id: u128,
+    mentions: Vec<Mention>,
+) -> usize {
+    let mut linked = std::collections::HashSet::new();
+    for mention in mentions {
+        if mention.name.trim().is_empty() || !linked.insert(mention.name.to_lowercase()) {
+            continue;
+        }
+        graph.add_triple(
+            &Triple::with_types(
+                source,
+                source_type.clone(),
+                RelationType::Mentions,
+                mention.name,
+                mention.entity_type,
+            )
+            .with_source(source_edge_id),
+        );
+    }
+    linked.len()
+}
+
+#[derive(Debug, Deserialize)]
+pub struct NeighborsParams {
+    #[serde(default)]
+    pub direction: Direction,
+    pub depth: Option<usize>,
+    /// Only report neighbors reached through this relation
+    pub relation: Option<String>,
+    /// Only report neighbors of this type
+    pub entity_type: Option<String>,
+    pub limit: Option<usize>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct NeighborsResponse {
+    pub entity: Entity,
+    pub neighbors: Vec<Neighbor>,
+    /// Neighbors matched before `limit` was applied
+    pub total: usize,
+}
+
+/// GET /api/v1/kg/entity/:id/neighbors
+///
+/// `:id` is a numeric entity ID or an entity name such as `src/auth.rs`.
+pub async fn get_entity_neighbors(
+    State(state): State<AppState>,
+    AxumPath(id): AxumPath<String>,
+    Query(params): Query<NeighborsParams>,
+    axum::Extension(auth): axum::Extension<AuthContext>,
+) -> Result<Json<NeighborsResponse>, ApiError> {
+    let depth = params.depth.unwrap_or(1);
+    if depth == 0 || depth > MAX_DEPTH {
+        return Err(ApiError::BadRequest(format!(
+            "depth must be between 1 and {}",
+            MAX_DEPTH
+        )));
+    }
+    let limit = params.limit.unwrap_or(DEFAULT_LIMIT).clamp(1, MAX_LIMIT);
+    let relation = params.relation.as_deref().map(RelationType::from_str);
+    let entity_type = params.entity_type.as_deref().map(EntityType::from_str);
+
+    let not_found = || ApiError::NotFound(format!("Entity not found: {}", id));
+    let graph = state
+        .knowledge_graph
+        .graph(auth.tenant_id)
+        .ok_or_else(not_found)?;
+    let entity = id
+        .parse::<u64>()
+        .ok()
+        .and_then(|entity_id| graph.get_entity(entity_id))
+        .or_else(|| graph.get_entity_by_name(&id))
+        .ok_or_else(not_found)?;
+
+    let mut neighbors = graph.neighbors(entity.id, params.direction, depth, |n| {
+        relation.as_ref().map_or(true, |r| &n.relation == r)
+            && entity_type
+                .as_ref()
+                .map_or(true, |t| &n.entity.entity_type == t)
+    });
+    let total = neighbors.len();
+    neighbors.truncate(limit);
+
+    Ok(Json(NeighborsResponse {
+        entity,
+        neighbors,
+        total,
+    }))
+}
+
+#[derive(Debug, Deserialize)]
+pub struct SearchEntitiesParams {
+    /// Substring of the entity name
+    pub q: Option<String>,
+    pub entity_type: Option<String>,
+    pub limit: Option<usize>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct SearchEntitiesResponse {
+    pub entities: Vec<Entity>,
+    pub total: usize,
+}
+
+/// GET /api/v1/kg/entities?q=auth&entity_type=file
+///
+/// Most frequently mentioned first.
+pub async fn search_entities(
+    State(state): State<AppState>,
+    Query(params): Query<SearchEntitiesParams>,
+    axum::Extension(auth): axum::Extension<AuthContext>,
+) -> Result<Json<SearchEntitiesResponse>, ApiError> {
+    let limit = params.limit.unwrap_or(DEFAULT_LIMIT).clamp(1, MAX_LIMIT);
+    let entity_type = params.entity_type.as_deref().map(EntityType::from_str);
+    let query = params.q.as_deref().map(str::to_lowercase);
+
+    let mut entities: Vec<Entity> = state
+        .knowledge_graph
+        .graph(auth.tenant_id)
+        .map(|graph| graph.all_entities())
+        .unwrap_or_default()
+        .into_iter()
+        .filter(|e| entity_type.as_ref().map_or(true, |t| &e.entity_type == t))
+        .filter(|e| {
+            query.as_ref().map_or(true, |q| {
+                e.name.contains(q.as_str())
+                    || e.aliases
+                        .iter()
+                        .any(|a| a.to_lowercase().contains(q.as_str()))
+            })
+        })
+        .collect();
+    entities.sort_by(|a, b| {
+        b.occurrence_count
+            .cmp(&a.occurrence_count)
+            .then_with(|| a.name.cmp(&b.name))
+    });
+    let total = entities.len();
+    entities.truncate(limit);
+
+    Ok(Json(SearchEntitiesResponse { entities, total }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn edge(tenant_id: u64) -> AgentFlowEdge {
+        AgentFlowEdge::new(tenant_id, 0, 1, 42, agentreplay_core::SpanType::ToolCall, 0)
+    }
+
+    #[test]
+    fn test_traces_touching_file() {
+        let indexer = KnowledgeGraphIndexer::new();
+        let first = edge(1);
+        let second = edge(1);
+
+        let attrs = HashMap::from([
+            ("code.filepath".to_string(), "src/auth.rs".to_string()),
+            (
+                "gen_ai.tool.call.arguments".to_string(),
+                r#"{"cmd": "edit src/auth.rs for PROJ-9"}"#.to_string(),
+            ),
+        ]);
+        assert_eq!(indexer.record_span(&first, &attrs), 2);
+        let attrs = HashMap::from([(
+            "gen_ai.prompt.0.content".to_string(),
+            "Why does src/auth.rs fail? cc @alice".to_string(),
+        )]);
+        assert_eq!(indexer.record_span(&second, &attrs), 2);
+        assert_eq!(indexer.record_span(&edge(1), &HashMap::new()), 0);
+
+        let graph = indexer.graph(1).unwrap();
+        let file = graph.get_entity_by_name("src/auth.rs").unwrap();
+        assert_eq!(file.entity_type, EntityType::File);
+
+        let traces = graph.neighbors(file.id, Direction::Incoming, 1, |n| {
+            n.entity.entity_type == EntityType::Trace
+        });
+        let mut edge_ids: Vec<Option<u128>> = traces.iter().map(|n| n.source_edge_id).collect();
+        edge_ids.sort();
+        let mut expected = vec![Some(first.edge_id), Some(second.edge_id)];
+        expected.sort();
+        assert_eq!(edge_ids, expected);
+        assert_eq!(
+            traces[0]
+                .entity
+                .attributes
+                .get("session_id")
+                .map(String::as_str),
+            Some("42")
+        );
+
+        // Other tenants don't see the graph
+        assert!(indexer.graph(2).is_none());
+    }
+
+    #[test]
+    fn test_observation_and_persistence() {
+        let dir = tempfile::tempdir().unwrap();
+        let indexer = KnowledgeGraphIndexer::with_storage(dir.path());
+        let linked = indexer.record_observation(
+            7,
+            0xabc,
+            "Decided to route POST /v1/charges through billing/api.py",
+            &["PAY-12".to_string()],
+        );
+        assert_eq!(linked, 3);
+        indexer.flush();
+
+        let reloaded = KnowledgeGraphIndexer::with_storage(dir.path());
+        let graph = reloaded.graph(7).unwrap();
+        let ticket = graph.get_entity_by_name("PAY-12").unwrap();
+        let observations = graph.neighbors(ticket.id, Direction::Incoming, 1, |_| true);
+        assert_eq!(observations.len(), 1);
+        assert_eq!(observations[0].entity.entity_type, EntityType::Observation);
+    }
+}
@@ -0,0 +1,213 @@
+// Copyright 2025 AgentReplay (https://github.com/agentreplay)
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program. If not, see <https://www.gnu.org/licenses/>.
+
+//! Entity Mention Extraction
+//!
+//! Pattern-based extraction of entities referenced in free text, cheap
+//! enough to run on every ingested span:
+//!
+//! | Type     | Examples                                          |
+//! |----------|---------------------------------------------------|
+//! | `File`   | `src/auth.rs`, `handlers/user.py`                 |
+//! | `Api`    | `POST /v1/charges`, `https://api.stripe.com/v1/x` |
+//! | `Ticket` | `PROJ-123`, `acme/web#42`                         |
+//! | `Person` | `@alice`, `alice@example.com`                     |
+//!
+//! Unlike [`TripleExtractor`](super::TripleExtractor), this does not
+//! produce relationships between the mentioned entities; the caller links
+//! them to the trace or observation they came from.
+
+use crate::knowledge_graph::entities::EntityType;
+use regex::Regex;
+use std::collections::HashSet;
+use std::sync::OnceLock;
+
+/// Maximum characters scanned per text
+pub const MAX_SCAN_CHARS: usize = 16 * 1024;
+
+/// Prefixes of `ABC-123` tokens that are standards or versions, not tickets
+const NON_TICKET_PREFIXES: &[&str] = &[
+    "AES", "CVE", "GPT", "HTTP", "ISO", "MD", "PEP", "RFC", "SHA", "TLS", "UTF",
+];
+
+/// An entity mentioned in text
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Mention {
+    pub name: String,
+    pub entity_type: EntityType,
+}
+
+impl Mention {
+    fn new(name: impl Into<String>, entity_type: EntityType) -> Self {
+        Self {
+            name: name.into(),
+            entity_type,
+        }
+    }
+}
+
+struct Patterns {
+    file: Regex,
+    http_route: Regex,
+    url: Regex,
+    jira: Regex,
+    github_issue: Regex,
+    email: Regex,
+    handle: Regex,
+}
+
+fn patterns() -> &'static Patterns {
+    static PATTERNS: OnceLock<Patterns> = OnceLock::new();
+    PATTERNS.get_or_init(|| Patterns {
+        file: Regex::new(
+            r"(?:^|[\s`'(\[])((?:[\w.-]+/)*[\w-][\w.-]*\.(?:rs|py|ts|tsx|js|jsx|go|java|kt|rb|php|cs|cpp|cc|c|h|hpp|swift|scala|sql|sh|yaml|yml|toml|json|md|proto|tf))\b",
+        )
+        .expect("valid file pattern"),
+        http_route: Regex::new(r"\b(GET|POST|PUT|PATCH|DELETE)\s+(/[\w./{}:-]*)")
+            .expect("valid route pattern"),
+        url: Regex::new(r#"\bhttps?://[\w.-]+(?::\d+)?(?:/[^\s"'<>)\]?#]*)?"#)
+            .expect("valid url pattern"),
+        jira: Regex::new(r"\b([A-Z][A-Z0-9]{1,9}-[1-9]\d{0,6})\b").expect("valid ticket pattern"),
+        github_issue: Regex::new(r"\b([\w.-]+/[\w.-]+#\d+)\b").expect("valid issue pattern"),
+        email: Regex::new(r"\b[\w.+-]+@[\w-]+(?:\.[\w-]+)*\.[a-zA-Z]{2,}\b")
+            .expect("valid email pattern"),
+        handle: Regex::new(r"(?:^|[\s(])@([A-Za-z][\w-]{1,38})\b").expect("valid handle pattern"),
+    })
+}
+
+/// Extract entity mentions from text, deduplicated in order of appearance
+pub fn extract_mentions(text: &str) -> Vec<Mention> {
+    let text = match text.char_indices().nth(MAX_SCAN_CHARS) {
+        Some((end, _)) => &text[..end],
+        None => text,
+    };
+    let p = patterns();
+    let mut found: Vec<(usize, Mention)> = Vec::new();
+
+    // URLs are matched first so their paths aren't also reported as files
+    let mut url_spans: Vec<(usize, usize)> = Vec::new();
+    for m in p.url.find_iter(text) {
+        url_spans.push((m.start(), m.end()));
+        let url = m.as_str().trim_end_matches(['.', ',', ';', ':']);
+        found.push((m.start(), Mention::new(url, EntityType::Api)));
+    }
+    let in_url = |pos: usize| url_spans.iter().any(|&(s, e)| pos >= s && pos < e);
+
+    for c in p.http_route.captures_iter(text) {
+        let start = c.get(0).map(|m| m.start()).unwrap_or(0);
+        found.push((
+            start,
+            Mention::new(format!("{} {}", &c[1], &c[2]), EntityType::Api),
+        ));
+    }
+
+    let mut emails: Vec<(usize, usize)> = Vec::new();
+    for m in p.email.find_iter(text) {
+        if in_url(m.start()) {
+            continue;
+        }
+        emails.push((m.start(), m.end()));
+        found.push((m.start(), Mention::new(m.as_str(), EntityType::Person)));
+    }
+    let in_email = |pos: usize| emails.iter().any(|&(s, e)| pos >= s && pos < e);
+
+    for c in p.handle.captures_iter(text) {
+        let m = c.get(1).expect("handle group");
+        found.push((
+            m.start(),
+            Mention::new(format!("@{}", m.as_str()), EntityType::Person),
+        ));
+    }
+
+    for c in p.file.captures_iter(text) {
+        let m = c.get(1).expect("file group");
+        if in_url(m.start()) || in_email(m.start()) {
+            continue;
+        }
+        found.push((m.start(), Mention::new(m.as_str(), EntityType::File)));
+    }
+
+    for c in p.github_issue.captures_iter(text) {
+        let m = c.get(1).expect("issue group");
+        if !in_url(m.start()) {
+            found.push((m.start(), Mention::new(m.as_str(), EntityType::Ticket)));
+        }
+    }
+    for c in p.jira.captures_iter(text) {
+        let m = c.get(1).expect("ticket group");
+        let prefix = m.as_str().split('-').next().unwrap_or_default();
+        if !in_url(m.start()) && !NON_TICKET_PREFIXES.contains(&prefix) {
+            found.push((m.start(), Mention::new(m.as_str(), EntityType::Ticket)));
+        }
+    }
+
+    found.sort_by_key(|(pos, _)| *pos);
+    let mut seen = HashSet::new();
+    found
+        .into_iter()
+        .map(|(_, mention)| mention)
+        .filter(|mention| seen.insert(mention.clone()))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn names(mentions: &[Mention], entity_type: EntityType) -> Vec<&str> {
+        mentions
+            .iter()
+            .filter(|m| m.entity_type == entity_type)
+            .map(|m| m.name.as_str())
+            .collect()
+    }
+
+    #[test]
+    fn test_extract_mentions() {
+        let text = "Fixing PROJ-123: src/auth/jwt.rs panics when POST /v1/login \
+                    returns 500 from https://api.example.com/v1/login.json. \
+                    See acme/web#42, cc @alice and bob@example.com. Also touched main.py.";
+        let mentions = extract_mentions(text);
+
+        assert_eq!(
+            names(&mentions, EntityType::File),
+            vec!["src/auth/jwt.rs", "main.py"]
+        );
+        assert_eq!(
+            names(&mentions, EntityType::Api),
+            vec!["POST /v1/login", "https://api.example.com/v1/login.json"]
+        );
+        assert_eq!(
+            names(&mentions, EntityType::Ticket),
+            vec!["PROJ-123", "acme/web#42"]
+        );
+        assert_eq!(
+            names(&mentions, EntityType::Person),
+            vec!["@alice", "bob@example.com"]
+        );
+    }
+
+    #[test]
+    fn test_no_false_positives_in_prose() {
+        let mentions = extract_mentions("The model said hello, e.g. version 1.2 is fine. UTF-8 ok");
+        assert!(mentions.is_empty(), "{:?}", mentions);
+    }
+
+    #[test]
+    fn test_dedup() {
+        let mentions = extract_mentions("lib.rs and lib.rs again, PROJ-1 PROJ-1");
+        assert_eq!(mentions.len(), 2);
+    }
+}
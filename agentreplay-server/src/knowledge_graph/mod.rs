@@ -17,6 +17,8 @@
 //!
 //! Implements GraphRAG-style semantic knowledge graph with:
 //! - Triple extraction from trace payloads using LLM
+//! - Automatic indexing of files, APIs, tickets and people mentioned by
+//!   ingested spans and memory observations
 //! - Entity resolution and normalization
 //! - Leiden community detection algorithm
 //! - Graph-based queries for dependency analysis
@@ -43,15 +45,20 @@
 //! - "What depends on auth.rs?"
 //! - "What breaks when I modify user_id?"
 //! - "Show me the authentication cluster"
+//! - "What traces touched src/auth.rs?"
 
 pub mod entities;
 pub mod extractor;
 pub mod graph;
+pub mod indexer;
 pub mod leiden;
+pub mod mentions;
 pub mod queries;
 
 pub use entities::*;
 pub use extractor::TripleExtractor;
-pub use graph::SemanticGraph;
+pub use graph::{Direction, Neighbor, SemanticGraph};
+pub use indexer::{KnowledgeGraphIndexer, KNOWLEDGE_GRAPH_DIR};
 pub use leiden::LeidenClustering;
+pub use mentions::{extract_mentions, Mention};
 pub use queries::GraphQueryEngine;
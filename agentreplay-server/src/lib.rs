@@ -13,16 +13,31 @@
 // You should have received a copy of the GNU Affero General Public License
 // along with this program. If not, see <https://www.gnu.org/licenses/>.
 
+pub mod access_policy;
 pub mod admission;
+pub mod agent_config;
 pub mod agent_registry;
 pub mod api;
 pub mod auth;
 pub mod batcher;
+pub mod benchmarks;
+pub mod billing;
 pub mod cache;
+pub mod cluster;
 pub mod config;
+pub mod config_reload;
+pub mod conversations;
+pub mod cost_recompute;
 pub mod cost_tracker;
+pub mod data_quality;
+pub mod drift;
+pub mod failures;
 pub mod governor;
+pub mod guardrails;
+pub mod heavy_hitters;
 pub mod ingestion;
+pub mod instance_lock;
+pub mod jobs;
 pub mod knowledge_graph;
 pub mod llm;
 pub mod mcp;
@@ -31,43 +46,85 @@ pub mod otel_genai;
 pub mod otlp_service;
 pub mod project_manager;
 pub mod project_registry;
+pub mod project_templates;
+pub mod quotas;
+pub mod rehydration;
+pub mod reports;
+pub mod response_cache;
 pub mod sanitization;
+pub mod scaling;
+pub mod scripting;
+pub mod self_trace;
+pub mod session_analysis;
+pub mod session_registry;
+pub mod session_summary;
+pub mod shadow;
+pub mod shutdown;
+pub mod simulation;
+pub mod standby;
 pub mod tool_registry;
 pub mod validation;
+pub mod vault;
+pub mod volume_alerts;
 
 use anyhow::Result;
 use axum::{
+    extract::DefaultBodyLimit,
     middleware as axum_middleware,
-    routing::{delete, get, post},
+    routing::{delete, get, post, put},
     Extension, Router,
 };
 use std::sync::Arc;
 use tower_http::{
-    cors::{Any, CorsLayer},
+    cors::{AllowOrigin, Any, CorsLayer},
     trace::TraceLayer,
 };
 use tracing_subscriber::{layer::SubscriberExt, util::SubscriberInitExt};
 
 use api::{
-    add_trace_to_dataset, get_dashboard_summary, get_detailed_trace, get_provider_costs, get_stats,
-    get_timeseries_metrics, get_trace, get_trace_attributes, get_trace_children, get_trace_graph,
-    get_trace_observations, health_check, health_check_detailed, ingest_otel_spans, ingest_traces,
-    list_traces, semantic_search, submit_trace_feedback, ws_traces, AppState,
+    add_trace_to_dataset, get_dashboard_summary, get_detailed_trace, get_ingestion_queue_metrics,
+    get_provider_costs, get_stats, get_timeseries_metrics, get_trace, get_trace_ancestors,
+    get_trace_attributes, get_trace_children, get_trace_graph, get_trace_observations, health_check,
+    health_check_detailed, ingest_otel_spans, ingest_traces, list_traces, semantic_search,
+    submit_trace_feedback, ws_traces, AppState,
+};
+use auth::{
+    auth_middleware, ApiKeyAuth, Authenticator, BearerTokenAuth, MultiAuth, NoAuth, OidcAuth,
 };
-use auth::{auth_middleware, ApiKeyAuth, Authenticator, BearerTokenAuth, MultiAuth, NoAuth};
 use config::ServerConfig;
+use agentreplay_core::{DiagnosticsCollector, LogSource, LogTail};
 use agentreplay_query::Agentreplay;
 use project_manager::ProjectManager;
 use tokio::sync::broadcast;
 
-pub async fn run_server(config: ServerConfig) -> Result<()> {
-    // Initialize tracing
+pub async fn run_server(mut config: ServerConfig) -> Result<()> {
+    // Initialize tracing, mirroring recent log lines into a ring buffer
+    // so crash bundles include the lead-up to a panic
+    let log_tail = LogTail::new(agentreplay_core::diagnostics::DEFAULT_LOG_TAIL_LINES);
+    let log_tail_writer = log_tail.clone();
+    // RUST_LOG wins over `server.log_level`; the filter is swapped on config reload
+    let log_filter = tracing_subscriber::EnvFilter::try_from_default_env()
+        .or_else(|_| {
+            tracing_subscriber::EnvFilter::try_new(
+                config
+                    .server
+                    .log_level
+                    .as_deref()
+                    .unwrap_or(config_reload::DEFAULT_LOG_FILTER),
+            )
+        })
+        .unwrap_or_else(|_| config_reload::DEFAULT_LOG_FILTER.into());
+    let (log_filter, log_filter_handle) = tracing_subscriber::reload::Layer::new(log_filter);
+    // The server's own spans, stored in the internal project by a writer
+    let (self_trace_layer, mut self_trace_receiver) = config
+        .self_tracing
+        .enabled
+        .then(|| self_trace::layer(&config.self_tracing))
+        .unzip();
     tracing_subscriber::registry()
-        .with(
-            tracing_subscriber::EnvFilter::try_from_default_env()
-                .unwrap_or_else(|_| "agentreplay_server=info,tower_http=info".into()),
-        )
-        .with(tracing_subscriber::fmt::layer())
+        .with(log_filter)
+        .with(tracing_subscriber::fmt::layer().with_writer(move || log_tail_writer.writer()))
+        .with(self_trace_layer)
         .init();
 
     tracing::info!("Starting Agentreplay Server");
@@ -76,13 +133,71 @@ pub async fn run_server(config: ServerConfig) -> Result<()> {
     // Validate configuration
     config.validate()?;
 
+    // Swap in a restore staged through the backup API before anything
+    // writes to the data directory
+    match agentreplay_storage::BackupManager::apply_pending_restore(&config.storage.data_dir) {
+        Ok(Some(previous)) => tracing::info!(
+            "Applied staged backup restore (previous data kept at {:?})",
+            previous
+        ),
+        Ok(None) => {}
+        Err(e) => tracing::error!("Failed to apply staged backup restore: {}", e),
+    }
+
+    // Install panic hook writing crash bundles to <data_dir>/diagnostics
+    let diagnostics = Arc::new(DiagnosticsCollector::new(
+        "server",
+        env!("CARGO_PKG_VERSION"),
+        config
+            .storage
+            .data_dir
+            .join(agentreplay_core::diagnostics::DIAGNOSTICS_DIR_NAME),
+        LogSource::Memory(log_tail),
+    ));
+    diagnostics.set_config_summary(config.diagnostic_summary());
+    diagnostics.clone().install_panic_hook();
+    tracing::info!(
+        "Crash diagnostics enabled (bundles in {:?})",
+        diagnostics.output_dir()
+    );
+
+    // Claim the data directory so a desktop app (or second server) pointed
+    // at it does not write concurrently
+    let node_data_dir = config.storage.data_dir.clone();
+    let instance_lock = Arc::new(crate::instance_lock::InstanceLock::acquire(
+        &node_data_dir,
+        "server",
+        config.storage.on_lock_conflict,
+    )?);
+    let read_only = !instance_lock.accepts_writes();
+
+    // Join the cluster before opening storage: writers take the lease,
+    // replicas open the database from the latest synced snapshot
+    let cluster = crate::cluster::ClusterNode::new(&config.cluster)?;
+    if let Some(cluster) = &cluster {
+        config.storage.data_dir = cluster.prepare(&node_data_dir)?;
+        tracing::info!(
+            "Cluster role {:?} (node '{}'), serving data from {:?}",
+            cluster.role(),
+            config.cluster.node_id,
+            config.storage.data_dir
+        );
+    }
+
     // Initialize Project Manager for per-project storage
     let use_project_storage = config.storage.use_project_storage;
 
+    let isolation = if config.auth.strict_tenant_isolation {
+        tracing::info!("Strict tenant isolation enabled");
+        agentreplay_query::IsolationMode::Strict
+    } else {
+        agentreplay_query::IsolationMode::Permissive
+    };
+
     let project_manager = if use_project_storage {
         tracing::info!("Initializing ProjectManager with per-project storage");
         let base_dir = config.storage.data_dir.join("projects");
-        match ProjectManager::new(&base_dir) {
+        match ProjectManager::new(&base_dir).map(|pm| pm.with_isolation(isolation)) {
             Ok(pm) => {
                 tracing::info!("ProjectManager initialized at: {:?}", base_dir);
                 let discovered = pm.discover_projects().unwrap_or_default();
@@ -150,6 +265,40 @@ pub async fn run_server(config: ServerConfig) -> Result<()> {
         tracing::info!("Using standard WAL mode (Segmented)");
         Arc::new(Agentreplay::open(&config.storage.data_dir)?)
     };
+    db.set_isolation_mode(isolation);
+
+    if let Some(cluster) = &cluster {
+        cluster.spawn(db.clone(), node_data_dir.clone());
+    }
+
+    // Ship writes to the warm standby, or replay the primary's WAL
+    let replication = crate::standby::WalReplicator::new(
+        &config.replication,
+        &config.cluster.node_id,
+        &config.storage.data_dir,
+    )?;
+    if let Some(replication) = &replication {
+        replication.spawn(db.clone())?;
+        if replication.is_standby() {
+            tracing::info!(
+                "Warm standby replaying WAL from {:?}; writes are rejected until promoted",
+                config.replication.wal_dir
+            );
+        }
+    }
+
+    // Archive tier for cold traces; read-only nodes query it but leave the
+    // periodic archive pass to the writer
+    let archive = crate::rehydration::ArchiveManager::new(&config.archive)?;
+    if let Some(archive) = &archive {
+        if !read_only
+            && !config.replication.standby
+            && config.cluster.role != cluster::NodeRole::Replica
+        {
+            archive.spawn(db.clone());
+        }
+        tracing::info!("Archive tier enabled at {:?}", config.archive.dir);
+    }
 
     // Create agent registry
     let agent_registry_path = config.storage.data_dir.join("agent_registry.json");
@@ -162,6 +311,25 @@ pub async fn run_server(config: ServerConfig) -> Result<()> {
         agent_registry.count()
     );
 
+    // Create external session key registry (session stitching across SDK restarts)
+    let session_registry = Arc::new(crate::session_registry::SessionRegistry::new(
+        config.storage.data_dir.join("session_registry.json"),
+    ));
+
+    // Load embedded hook scripts (on_ingest / on_eval_complete / on_alert)
+    let scripts = Arc::new(
+        crate::scripting::ScriptManager::new(config.storage.data_dir.join("scripts.json"))
+            .with_webhook_allowlist(config.scripting.webhook_allowlist.clone()),
+    );
+
+    // Ingestion stages plus WASM transform plugins from the transforms directory
+    let ingest_pipeline = Arc::new(crate::ingestion::IngestPipeline::new(
+        config.ingestion.pipeline.clone(),
+        crate::ingestion::TransformRegistry::new(
+            config.ingestion.transforms_dir(&config.storage.data_dir),
+        ),
+    ));
+
     // Create saved view registry
     let saved_view_registry = Arc::new(tokio::sync::RwLock::new(
         agentreplay_core::SavedViewRegistry::new(&config.storage.data_dir),
@@ -182,12 +350,72 @@ pub async fn run_server(config: ServerConfig) -> Result<()> {
         }
     };
 
+    // Provider API key vault
+    let vault = Arc::new(crate::vault::KeyVault::open(
+        &config.vault,
+        &config.storage.data_dir,
+    )?);
+
+    // Topic and goal-completion analysis of finished sessions
+    let session_analyzer = Arc::new(crate::session_analysis::SessionAnalyzer::new(
+        config.session_analysis.clone(),
+        llm_manager.clone(),
+    ));
+
+    // Per-project ingest volume spike/drop alerts
+    let volume_monitor = Arc::new(crate::volume_alerts::VolumeMonitor::new(
+        config.volume_alerts.clone(),
+    ));
+    // Production input drift against golden eval datasets
+    let drift_detector = Arc::new(crate::drift::DriftDetector::new(config.drift.clone()));
+    let api_tokens = Arc::new(crate::auth::TokenStore::with_storage(
+        config.storage.data_dir.join(crate::auth::API_TOKENS_FILE),
+    ));
+    let reports = Arc::new(
+        crate::reports::ReportScheduler::with_storage(
+            config.reports.clone(),
+            config
+                .storage
+                .data_dir
+                .join(crate::reports::REPORT_SCHEDULES_FILE),
+        )
+        .with_digests(crate::reports::DigestStore::with_storage(
+            config.storage.data_dir.join(crate::reports::DIGESTS_FILE),
+        )),
+    );
+    let guardrails = Arc::new(
+        crate::guardrails::GuardrailEngine::new(&config.guardrails)
+            .map_err(|e| anyhow::anyhow!("Invalid guardrails configuration: {}", e))?,
+    );
+    let shadow = Arc::new(
+        crate::shadow::ShadowTraffic::new(&config.shadow)
+            .map_err(|e| anyhow::anyhow!("Invalid shadow configuration: {}", e))?,
+    );
+    let response_cache = Arc::new(
+        crate::response_cache::ResponseCache::new(&config.response_cache)
+            .map_err(|e| anyhow::anyhow!("Invalid response cache configuration: {}", e))?,
+    );
+    let knowledge_graph = Arc::new(crate::knowledge_graph::KnowledgeGraphIndexer::with_storage(
+        config
+            .storage
+            .data_dir
+            .join(crate::knowledge_graph::KNOWLEDGE_GRAPH_DIR),
+    ));
+
     // Create application state with broadcast channel for real-time updates
     let (trace_tx, _) = broadcast::channel(1024);
 
     // Initialize cost tracker
     let cost_tracker = Arc::new(crate::cost_tracker::CostTracker::new());
 
+    // Model prices: builtins, cached LiteLLM data, price history and overrides
+    let pricing = Arc::new(agentreplay_core::ModelPricingRegistry::new(
+        config.storage.data_dir.join("pricing"),
+    ));
+    if let Err(e) = pricing.initialize().await {
+        tracing::warn!("Failed to load model pricing: {}", e);
+    }
+
     // Initialize HNSW vector index for semantic operations (Task 7)
     // Now uses sochdb-index HNSW which provides advanced features:
     // - Lock-free entry point with atomic CAS
@@ -216,15 +444,23 @@ pub async fn run_server(config: ServerConfig) -> Result<()> {
     // Binary quantization reduces memory by 32x (60GB → 1.9GB for 10M traces)
     let semantic_governor = {
         let governor_config = crate::governor::GovernorConfig {
-            epsilon: 0.1, // 10% cosine distance threshold
+            epsilon: config.governor.epsilon,
             dimension: 384,
             ef_search: 32,
             m: 16,
             ef_construction: 100,
-            use_binary_quantization: true, // 32x memory reduction
+            use_binary_quantization: config.governor.binary_quantization,
         };
         let governor = crate::governor::ShardedGovernor::new_shared(governor_config);
-        tracing::info!("Sharded Semantic Governor initialized with ε=0.1, 16 shards, binary quantization enabled");
+        governor.set_exemptions(crate::governor::DedupExemptions {
+            agents: config.governor.exempt_agents.iter().copied().collect(),
+            projects: config.governor.exempt_projects.iter().copied().collect(),
+        });
+        tracing::info!(
+            "Sharded Semantic Governor initialized with ε={}, 16 shards, binary quantization {}",
+            config.governor.epsilon,
+            if config.governor.binary_quantization { "enabled" } else { "disabled" }
+        );
         Some(governor)
     };
 
@@ -276,8 +512,157 @@ pub async fn run_server(config: ServerConfig) -> Result<()> {
         semantic_governor,
         eval_cache,
         ingestion_actor,
+        ingestion_admission: Arc::new(crate::admission::QueueAdmission::new(
+            config.ingestion.queue_admission_config(),
+        )),
+        ingestion_idempotency: Arc::new(crate::ingestion::IdempotencyGuard::new(
+            config.ingestion.dedup_window(),
+        )),
+        open_spans: Arc::new(crate::ingestion::OpenSpanTracker::new(
+            config.ingestion.open_span_timeout(),
+        )),
+        session_registry,
+        clock_skew: config
+            .ingestion
+            .clock_skew_tolerance()
+            .map(|tolerance| Arc::new(crate::ingestion::ClockSkewCorrector::new(tolerance))),
+        cluster,
+        replication,
+        archive,
+        scripts,
+        ingest_pipeline,
+        instance_lock: Some(instance_lock),
+        session_analyzer: session_analyzer.clone(),
+        heavy_hitters: Arc::new(crate::heavy_hitters::HeavyHitters::new()),
+        failures: Arc::new(crate::failures::FailureTracker::new()),
+        tool_contracts: Arc::new(crate::tool_registry::ToolContractMonitor::with_storage(
+            config
+                .storage
+                .data_dir
+                .join(crate::tool_registry::TOOL_REGISTRY_FILE),
+        )),
+        knowledge_graph: knowledge_graph.clone(),
+        session_summarizer: Arc::new(crate::session_summary::SessionSummarizer::new()),
+        conversations: Arc::new(crate::conversations::ConversationLinker::new(
+            config.conversations.clone(),
+        )),
+        volume_monitor: volume_monitor.clone(),
+        drift_detector: drift_detector.clone(),
+        guardrails,
+        shadow,
+        response_cache,
+        simulator: Arc::new(crate::simulation::Simulator::new(config.simulation.clone())),
+        benchmarks: Arc::new(crate::benchmarks::BenchmarkRunner::with_storage(
+            config
+                .storage
+                .data_dir
+                .join(crate::benchmarks::BENCHMARKS_FILE),
+        )),
+        vault,
+        hydration: Arc::new(config.hydration.clone()),
+        nl_query_feedback: Arc::new(crate::api::nl_query::NlQueryFeedbackLog::with_storage(
+            config
+                .storage
+                .data_dir
+                .join(crate::api::nl_query::NL_QUERY_FEEDBACK_FILE),
+        )),
+        reports: reports.clone(),
+        api_tokens: api_tokens.clone(),
+        rate_limiter: Arc::new(crate::middleware::RouteRateLimiter::new(
+            config.rate_limits.clone(),
+        )),
+        query_queue: Arc::new(crate::scaling::QueryQueue::new(config.scaling.clone())),
+        import_jobs: Arc::new(crate::api::import::ImportJobs::with_storage(
+            config.storage.data_dir.join(crate::api::import::IMPORTS_DIR),
+        )),
+        jobs: Arc::new(crate::jobs::JobManager::with_storage(
+            config.jobs.clone(),
+            config.storage.data_dir.join(crate::jobs::JOBS_FILE),
+        )),
+        shutdown: Arc::new(crate::shutdown::Shutdown::new()),
+        config_reloader: Arc::new(
+            crate::config_reload::ConfigReloader::new(&config).with_log_filter(
+                move |directives| {
+                    let filter = tracing_subscriber::EnvFilter::try_new(directives)
+                        .map_err(|e| e.to_string())?;
+                    log_filter_handle.reload(filter).map_err(|e| e.to_string())
+                },
+            ),
+        ),
+        project_templates: Arc::new(crate::project_templates::ProjectTemplateStore::new(
+            config
+                .storage
+                .data_dir
+                .join(crate::project_templates::TEMPLATES_FILE),
+        )),
+        agent_configs: Arc::new(crate::agent_config::AgentConfigStore::new(
+            config
+                .storage
+                .data_dir
+                .join(crate::agent_config::AGENT_CONFIGS_FILE),
+        )),
+        pricing: pricing.clone(),
+        billing: Arc::new(crate::billing::BillingStore::new(
+            config.storage.data_dir.join(crate::billing::BILLING_FILE),
+        )),
+        quotas: Arc::new(crate::quotas::QuotaManager::with_storage(
+            config.quotas.clone(),
+            config.storage.data_dir.join(crate::quotas::QUOTA_USAGE_FILE),
+        )),
+        access_policies: Arc::new(crate::access_policy::AccessPolicies::new(
+            config.access_policies.clone(),
+        )),
+        request_metrics: Arc::new(crate::middleware::RequestMetrics::new()),
     };
 
+    if !read_only
+        && !config.replication.standby
+        && config.cluster.role != cluster::NodeRole::Replica
+    {
+        crate::session_summary::SessionSummarizer::spawn_backfill(state.clone());
+        if config.volume_alerts.enabled {
+            volume_monitor.spawn(state.clone());
+        }
+        if config.drift.enabled {
+            drift_detector.spawn(state.clone());
+        }
+        if config.reports.enabled {
+            reports.spawn(state.clone());
+        }
+        state.import_jobs.resume_interrupted(&state);
+        knowledge_graph.spawn_flush();
+        state.open_spans.spawn_janitor(state.clone());
+        state.quotas.spawn_flush();
+        state.api_tokens.spawn_flush();
+        if let Some(receiver) = self_trace_receiver.take() {
+            tokio::spawn(self_trace::run_exporter(state.clone(), receiver));
+            tracing::info!(
+                "Self-tracing into project {} (sample rate {})",
+                self_trace::SELF_TRACE_PROJECT_ID,
+                config.self_tracing.sample_rate
+            );
+        }
+        if config.pricing.auto_sync {
+            let interval =
+                std::time::Duration::from_secs(config.pricing.sync_interval_hours.max(1) * 3600);
+            tokio::spawn(async move { pricing.run_auto_sync(interval).await });
+        }
+    }
+    // Replicas and standbys do not store self-traces
+    drop(self_trace_receiver);
+
+    if config.session_analysis.enabled
+        && !read_only
+        && !config.replication.standby
+        && config.cluster.role != cluster::NodeRole::Replica
+    {
+        session_analyzer.spawn(state.clone());
+        tracing::info!(
+            "Session analysis enabled (sessions idle for {} min)",
+            config.session_analysis.idle_minutes
+        );
+    }
+
     // Set up authenticator with secure-by-default approach (Task 4)
     let authenticator: Arc<dyn Authenticator> = if config.auth.enabled {
         tracing::info!("Authentication enabled");
@@ -290,18 +675,28 @@ pub async fn run_server(config: ServerConfig) -> Result<()> {
             strategies.push(Arc::new(BearerTokenAuth::new(jwt_secret)));
         }
 
+        // Add OIDC auth if an identity provider is configured
+        if let Some(oidc) = config.auth.oidc.clone() {
+            tracing::info!("OIDC authentication enabled (issuer {})", oidc.issuer);
+            let oidc = OidcAuth::new(oidc);
+            oidc.spawn_refresh();
+            strategies.push(oidc);
+        }
+
         // Add API key auth if keys are configured
         if !config.auth.api_keys.is_empty() {
-            tracing::info!(
-                "API key authentication enabled ({} keys)",
+            tracing::warn!(
+                "Static auth.api_keys are deprecated ({} keys); issue scoped tokens via /api/v1/admin/tokens",
                 config.auth.api_keys.len()
             );
             strategies.push(Arc::new(ApiKeyAuth::new(config.auth.api_keys.clone())));
         }
 
-        if strategies.is_empty() {
+        if strategies.is_empty() && api_tokens.is_empty() {
             anyhow::bail!("Authentication enabled but no strategies configured");
         }
+        // Issued tokens are always accepted, so tokens created later work
+        strategies.push(api_tokens.clone());
 
         Arc::new(MultiAuth::new(strategies))
     } else {
@@ -379,11 +774,38 @@ pub async fn run_server(config: ServerConfig) -> Result<()> {
         Arc::new(NoAuth::new(1)) // Default tenant_id = 1 (matches SDK default)
     };
 
+    // Transform plugins are WASM modules, registered only with the `wasm` feature
+    let transform_routes = Router::new();
+    #[cfg(feature = "wasm")]
+    let transform_routes = transform_routes.route(
+        "/api/v1/ingestion/transforms/:name",
+        put(api::ingest_pipeline::put_transform)
+            .delete(api::ingest_pipeline::delete_transform)
+            .layer(DefaultBodyLimit::max(
+                crate::ingestion::pipeline::MAX_TRANSFORM_MODULE_BYTES,
+            )),
+    );
+
     // Build authenticated routes (API + WebSocket)
     let authed_routes = Router::new()
         .route("/ws/traces", get(ws_traces))
         .route("/api/v1/traces/stream", get(api::sse_traces))
         .route("/api/v1/traces", get(list_traces).post(ingest_traces))
+        .route(
+            "/api/v1/query/aggregate",
+            post(api::aggregate::aggregate_query),
+        )
+        .route("/api/v1/query/nl", post(api::nl_query::nl_query))
+        .route(
+            "/api/v1/query/nl/feedback",
+            get(api::nl_query::list_nl_feedback).post(api::nl_query::submit_nl_feedback),
+        )
+        .route("/api/v1/ingestion/queue", get(get_ingestion_queue_metrics))
+        .route(
+            "/api/v1/ingestion/pipeline",
+            get(api::ingest_pipeline::get_pipeline),
+        )
+        .merge(transform_routes)
         .route("/api/v1/traces/otel", post(ingest_otel_spans))
         .route("/api/v1/traces/:trace_id", get(get_trace))
         .route(
@@ -391,15 +813,61 @@ pub async fn run_server(config: ServerConfig) -> Result<()> {
             get(get_trace_attributes),
         )
         .route("/api/v1/traces/:trace_id/children", get(get_trace_children))
+        .route(
+            "/api/v1/traces/:trace_id/ancestors",
+            get(get_trace_ancestors),
+        )
         .route(
             "/api/v1/traces/:trace_id/observations",
             get(get_trace_observations),
         )
         .route("/api/v1/traces/:trace_id/graph", get(get_trace_graph))
+        .route(
+            "/api/v1/traces/:trace_id/parallel",
+            get(api::get_trace_parallel_view),
+        )
         .route("/api/v1/traces/:trace_id/detailed", get(get_detailed_trace))
+        .route(
+            "/api/v1/traces/:trace_id/explain",
+            post(api::explain::explain_trace),
+        )
+        .route(
+            "/api/v1/traces/:trace_id/export",
+            get(api::trace_export::export_trace),
+        )
         .route(
             "/api/v1/traces/:trace_id/feedback",
-            post(submit_trace_feedback),
+            get(api::feedback::list_trace_feedback).post(submit_trace_feedback),
+        )
+        .route(
+            "/api/v1/feedback/analytics",
+            get(api::feedback::get_feedback_analytics),
+        )
+        // Annotation queues for human review
+        .route(
+            "/api/v1/annotation-queues",
+            get(api::annotation_queues::list_annotation_queues)
+                .post(api::annotation_queues::create_annotation_queue),
+        )
+        .route(
+            "/api/v1/annotation-queues/:queue_id",
+            get(api::annotation_queues::get_annotation_queue),
+        )
+        .route(
+            "/api/v1/annotation-queues/:queue_id/reviewers",
+            put(api::annotation_queues::update_queue_reviewers),
+        )
+        .route(
+            "/api/v1/annotation-queues/:queue_id/next",
+            get(api::annotation_queues::next_annotation_item),
+        )
+        .route(
+            "/api/v1/annotation-queues/:queue_id/items/:edge_id/labels",
+            post(api::annotation_queues::submit_annotation_label),
+        )
+        .route(
+            "/api/v1/annotation-queues/:queue_id/agreement",
+            get(api::annotation_queues::get_queue_agreement),
         )
         .route("/api/v1/datasets/:name/add", post(add_trace_to_dataset))
         // Projects/Collections routes
@@ -421,8 +889,58 @@ pub async fn run_server(config: ServerConfig) -> Result<()> {
             "/api/v1/projects/:project_id/favorite",
             post(api::toggle_favorite),
         )
+        .route(
+            "/api/v1/projects/:project_id/export",
+            post(api::project_bundle::export_project),
+        )
+        .route(
+            "/api/v1/projects/import",
+            post(api::project_bundle::import_project).layer(DefaultBodyLimit::disable()),
+        )
+        .route(
+            "/api/v1/projects/:project_id/settings",
+            get(api::project_templates::get_project_settings),
+        )
+        // Project templates for provisioning
+        .route(
+            "/api/v1/project-templates",
+            get(api::project_templates::list_templates),
+        )
+        .route(
+            "/api/v1/project-templates/:name",
+            get(api::project_templates::get_template)
+                .put(api::project_templates::put_template)
+                .delete(api::project_templates::delete_template),
+        )
         // Admin routes
         .route("/api/v1/admin/reset", delete(api::admin::reset_all_data))
+        .route(
+            "/api/v1/admin/tokens",
+            get(auth::tokens::list_tokens).post(auth::tokens::issue_token),
+        )
+        .route(
+            "/api/v1/admin/tokens/:id",
+            delete(auth::tokens::revoke_token),
+        )
+        .route(
+            "/api/v1/admin/tokens/:id/rotate",
+            post(auth::tokens::rotate_token),
+        )
+        .route("/api/v1/admin/scaling", get(scaling::get_scaling_signals))
+        .route(
+            "/api/v1/admin/config/reload",
+            post(config_reload::reload_config),
+        )
+        .route(
+            "/api/v1/admin/governor",
+            get(governor::tuning::get_governor).patch(governor::tuning::update_governor),
+        )
+        // Provider API key vault
+        .route("/api/v1/vault/keys", get(vault::list_provider_keys))
+        .route(
+            "/api/v1/vault/keys/:alias",
+            put(vault::put_provider_key).delete(vault::delete_provider_key),
+        )
         .route("/api/v1/health", get(health_check_detailed))
         .route("/api/v1/stats", get(get_stats))
         .route("/api/v1/dashboard/summary", get(get_dashboard_summary))
@@ -434,10 +952,48 @@ pub async fn run_server(config: ServerConfig) -> Result<()> {
             "/api/v1/sessions/:session_id",
             get(api::sessions::get_session),
         )
+        .route(
+            "/api/v1/sessions/external/:external_key",
+            get(api::sessions::get_session_by_external_key),
+        )
+        .route(
+            "/api/v1/sessions/funnel",
+            get(api::sessions::get_session_funnel),
+        )
+        .route(
+            "/api/v1/sessions/:session_id/analyze",
+            post(api::sessions::analyze_session),
+        )
+        .route(
+            "/api/v1/sessions/:session_id/analysis",
+            get(api::sessions::get_session_analysis),
+        )
+        .route(
+            "/api/v1/conversations/:conversation_id",
+            get(conversations::get_conversation),
+        )
         // Chat/LLM routes
         .route("/api/v1/chat/completions", post(api::chat_completion))
         .route("/api/v1/chat/stream", post(api::stream_completion))
         .route("/api/v1/chat/models", get(api::list_models))
+        .route(
+            "/api/v1/chat/cache",
+            get(response_cache::get_cache_stats).delete(response_cache::clear_cache),
+        )
+        .route("/api/v1/shadow", get(shadow::list_shadow_reports))
+        .route("/api/v1/shadow/:rule", get(shadow::get_shadow_report))
+        .route(
+            "/api/v1/local-models",
+            get(api::local_models::list_local_models),
+        )
+        .route(
+            "/api/v1/local-models/pull",
+            post(api::local_models::pull_local_model),
+        )
+        .route(
+            "/api/v1/local-models/:name",
+            get(api::local_models::get_local_model),
+        )
         // Agent registry routes
         .route("/api/v1/agents", get(api::list_agents))
         .route("/api/v1/agents/register", post(api::register_agent))
@@ -447,6 +1003,17 @@ pub async fn run_server(config: ServerConfig) -> Result<()> {
                 .put(api::update_agent)
                 .delete(api::delete_agent),
         )
+        // Remote config polled by agent SDKs
+        .route(
+            "/api/v1/agents/default/config",
+            get(api::get_default_agent_config).put(api::put_default_agent_config),
+        )
+        .route(
+            "/api/v1/agents/:agent_id/config",
+            get(api::get_agent_config)
+                .put(api::put_agent_config)
+                .delete(api::delete_agent_config),
+        )
         // Evaluation metrics routes (Task 3)
         .route(
             "/api/v1/evals/metrics",
@@ -465,6 +1032,14 @@ pub async fn run_server(config: ServerConfig) -> Result<()> {
             "/api/v1/evals/datasets/:id/examples",
             post(api::eval_datasets::add_examples),
         )
+        .route(
+            "/api/v1/evals/datasets/:id/export",
+            get(api::eval_datasets::export_dataset),
+        )
+        .route(
+            "/api/v1/evals/datasets/:id/import",
+            post(api::eval_datasets::import_dataset),
+        )
         // Evaluation runs routes (Task 4)
         .route(
             "/api/v1/evals/runs",
@@ -490,6 +1065,15 @@ pub async fn run_server(config: ServerConfig) -> Result<()> {
             "/api/v1/evals/runs/:id/status",
             post(api::eval_runs::update_run_status),
         )
+        .route("/api/v1/simulations", post(simulation::start_simulation))
+        .route(
+            "/api/v1/benchmarks",
+            get(benchmarks::list_benchmarks).post(benchmarks::start_benchmark),
+        )
+        .route(
+            "/api/v1/benchmarks/:id",
+            get(benchmarks::get_benchmark).delete(benchmarks::delete_benchmark),
+        )
         // Dataset Flywheel routes (auto-curate fine-tuning data)
         .route(
             "/api/v1/evals/flywheel/candidates",
@@ -514,6 +1098,14 @@ pub async fn run_server(config: ServerConfig) -> Result<()> {
             "/api/v1/prompts/:id/render",
             post(api::prompts::render_prompt),
         )
+        .route(
+            "/api/v1/prompts/:id/validate",
+            post(api::prompts::validate_prompt),
+        )
+        .route(
+            "/api/v1/prompts/:id/playground",
+            post(api::prompts::run_prompt_playground),
+        )
         // Prompt versioning routes (Task 9)
         .route(
             "/api/v1/prompts/:id/versions",
@@ -558,6 +1150,10 @@ pub async fn run_server(config: ServerConfig) -> Result<()> {
             "/api/v1/experiments/:id/stats",
             get(api::experiments::get_experiment_stats),
         )
+        .route(
+            "/api/v1/experiments/:id/analysis",
+            get(api::experiments::get_experiment_analysis),
+        )
         // Evaluation routes (Task 2)
         .route("/api/v1/evals/geval", post(api::run_geval))
         .route("/api/v1/evals/ragas", post(api::run_ragas))
@@ -634,6 +1230,23 @@ pub async fn run_server(config: ServerConfig) -> Result<()> {
             "/api/v1/compliance/security-metrics",
             get(api::compliance::get_security_metrics),
         )
+        // Scheduled reports
+        .route(
+            "/api/v1/reports/schedules",
+            get(crate::reports::list_schedules).post(crate::reports::create_schedule),
+        )
+        .route(
+            "/api/v1/reports/schedules/:id",
+            delete(crate::reports::delete_schedule),
+        )
+        .route(
+            "/api/v1/reports/schedules/:id/run",
+            post(crate::reports::run_schedule_now),
+        )
+        .route("/api/v1/reports/runs", get(crate::reports::list_runs))
+        .route("/api/v1/reports/preview", post(crate::reports::preview_report))
+        .route("/api/v1/digests", get(crate::reports::digest::list_digests))
+        .route("/api/v1/digests/:id", get(crate::reports::digest::get_digest))
         // Advanced analytics routes (Phase 3)
         .route(
             "/api/v1/analytics/timeseries",
@@ -665,12 +1278,60 @@ pub async fn run_server(config: ServerConfig) -> Result<()> {
             get(api::cost::get_detailed_cost_breakdown),
         )
         .route("/api/v1/analytics/cost/providers", get(get_provider_costs))
+        .route(
+            "/api/v1/analytics/cost/recompute",
+            post(cost_recompute::start_recompute),
+        )
+        .route("/api/v1/analytics/cost/keys", get(vault::get_key_costs))
+        .route(
+            "/api/v1/billing",
+            get(api::cost::get_billing_settings).put(api::cost::put_billing_settings),
+        )
+        .route("/api/v1/billing/rates", post(api::cost::add_exchange_rates))
+        .route(crate::quotas::USAGE_PATH, get(crate::quotas::get_usage))
+        .route(
+            "/api/v1/analytics/confidence",
+            get(api::confidence::get_confidence_analytics),
+        )
+        .route(
+            "/api/v1/analytics/top",
+            get(heavy_hitters::get_top_heavy_hitters),
+        )
+        .route("/api/v1/alerts/volume", get(volume_alerts::get_volume_alerts))
+        .route("/api/v1/failures", get(failures::get_failures))
+        .route(
+            "/api/v1/kg/entities",
+            get(knowledge_graph::indexer::search_entities),
+        )
+        .route(
+            "/api/v1/kg/entity/:id/neighbors",
+            get(knowledge_graph::indexer::get_entity_neighbors),
+        )
+        .route(
+            "/api/v1/tools",
+            get(tool_registry::list_tools).post(tool_registry::register_tool),
+        )
+        .route("/api/v1/tools/stats", get(tool_registry::get_tool_stats))
+        .route(
+            "/api/v1/tools/violations",
+            get(tool_registry::get_tool_violations),
+        )
+        .route(
+            "/api/v1/analytics/cache",
+            get(api::prompt_cache::get_cache_analytics),
+        )
         // Insights API (anomaly detection and pattern recognition)
         .route("/api/v1/insights", get(api::insights::get_insights))
         .route(
             "/api/v1/insights/summary",
             get(api::insights::get_insights_summary),
         )
+        .route(
+            "/api/v1/insights/data-quality",
+            get(api::insights::get_data_quality),
+        )
+        .route("/api/v1/insights/drift", get(drift::get_drift_report))
+        .route("/api/v1/insights/drift/run", post(drift::run_drift_check))
         // Storage Debug (NEW)
         .route(
             "/api/v1/storage/dump",
@@ -680,6 +1341,11 @@ pub async fn run_server(config: ServerConfig) -> Result<()> {
             "/api/v1/storage/stats",
             get(api::storage_debug::get_storage_stats),
         )
+        .route(
+            "/api/v1/storage/compaction",
+            get(api::storage_debug::get_compaction_stats)
+                .post(api::storage_debug::trigger_compaction),
+        )
         // Backup and restore routes (Task 7)
         .route(
             "/api/v1/backup",
@@ -687,6 +1353,7 @@ pub async fn run_server(config: ServerConfig) -> Result<()> {
         )
         .route("/api/v1/backup/restore", post(api::backup::restore_backup))
         .route("/api/v1/backup/verify", get(api::backup::verify_backup))
+        .route("/api/v1/backup/chains", get(api::backup::list_backup_chains))
         // Retention policy routes (Task 8)
         .route(
             "/api/v1/retention/config",
@@ -713,6 +1380,15 @@ pub async fn run_server(config: ServerConfig) -> Result<()> {
                 .put(api::views::update_view)
                 .delete(api::views::delete_view),
         )
+        // Embedded hook scripts
+        .route("/api/v1/scripts", get(api::scripts::list_scripts))
+        .route(
+            "/api/v1/scripts/:name",
+            get(api::scripts::get_script)
+                .put(api::scripts::put_script)
+                .delete(api::scripts::delete_script),
+        )
+        .route("/api/v1/scripts/:name/test", post(api::scripts::test_script))
         // Memory & RAG routes (MCP isolated project/tenant)
         .nest("/api/v1/memory", api::memory::memory_router())
         // Git-like Response Versioning routes
@@ -720,6 +1396,41 @@ pub async fn run_server(config: ServerConfig) -> Result<()> {
             let git_state = Arc::new(api::GitVersioningState::new("Agentreplay User"));
             api::git_versioning_router().with_state(git_state)
         })
+        .route("/api/v1/storage/lock", get(instance_lock::get_lock_status))
+        .route("/api/v1/cluster/status", get(cluster::get_cluster_status))
+        .route("/api/v1/replication/status", get(standby::get_replication_status))
+        .route("/api/v1/replication/promote", post(standby::promote_standby))
+        // Archive tier and on-demand rehydration
+        .route("/api/v1/archive", post(rehydration::run_archive))
+        .route("/api/v1/archive/ranges", get(rehydration::list_archived_ranges))
+        .route("/api/v1/rehydration/jobs", get(rehydration::list_rehydration_jobs))
+        .route("/api/v1/rehydration/jobs/:id", get(rehydration::get_rehydration_job))
+        // Background jobs
+        .route("/api/v1/jobs", get(jobs::list_jobs))
+        .route("/api/v1/jobs/:id", get(jobs::get_job).delete(jobs::cancel_job))
+        // Bulk NDJSON/ZIP imports, processed as background jobs
+        .route(
+            "/api/v1/import",
+            post(api::import::start_import).layer(DefaultBodyLimit::disable()),
+        )
+        .route("/api/v1/import/:id", get(api::import::get_import))
+        .route("/api/v1/import/:id/resume", post(api::import::resume_import))
+        .layer(axum_middleware::from_fn_with_state(
+            state.clone(),
+            cluster::write_guard_middleware,
+        ))
+        .layer(axum_middleware::from_fn_with_state(
+            state.clone(),
+            crate::quotas::quota_middleware,
+        ))
+        .layer(axum_middleware::from_fn_with_state(
+            state.clone(),
+            crate::access_policy::access_policy_middleware,
+        ))
+        .layer(axum_middleware::from_fn_with_state(
+            state.clone(),
+            crate::middleware::route_rate_limit_middleware,
+        ))
         .layer(axum_middleware::from_fn(auth_middleware))
         .layer(Extension(authenticator.clone()));
 
@@ -729,43 +1440,65 @@ pub async fn run_server(config: ServerConfig) -> Result<()> {
 
     // Clone project_manager before moving state
     let pm_for_otlp = state.project_manager.clone();
+    let state_for_otlp = state.clone();
 
     // Clone db for MCP server
     let db_for_mcp = db.clone();
     let state_for_mcp = state.clone();
+    let state_for_shutdown = state.clone();
+    let shutdown = state.shutdown.clone();
+    let cors_reloader = state.config_reloader.clone();
+    config_reload::spawn_sighup_handler(state.clone());
 
     // Build full application router
     let app = Router::new()
         .route("/health", get(health_check))
+        .route("/metrics", get(api::exposition::get_prometheus_metrics))
         .merge(authed_routes)
+        // Per-route latency, after routing so requests carry their route
+        .layer(axum_middleware::from_fn_with_state(
+            state.clone(),
+            crate::middleware::request_metrics_middleware,
+        ))
         .with_state(state)
         .layer(if config.server.enable_cors {
             // Secure CORS configuration
             let mut cors = CorsLayer::new()
                 .allow_methods(Any)
-                .allow_headers(Any);
+                .allow_headers(Any)
+                .expose_headers([
+                    axum::http::HeaderName::from_static(api::error::REQUEST_ID_HEADER),
+                    axum::http::HeaderName::from_static(api::ingest::TRACE_HEADER),
+                ]);
 
-            // If specific origins configured, use them; otherwise allow all (dev mode)
+            // If specific origins configured, use them; otherwise allow all (dev mode).
+            // Checked per request so a config reload can change the list
             if config.server.cors_origins.is_empty() {
                 tracing::warn!("CORS: Allowing all origins (development mode). Set cors_origins in production!");
-                cors = cors.allow_origin(Any);
             } else {
                 tracing::info!("CORS: Allowing origins: {:?}", config.server.cors_origins);
-                // In production, you'd parse these and set specific origins
-                // For now, keeping simple allow-all if list is provided
-                cors = cors.allow_origin(Any);
             }
+            cors = cors.allow_origin(AllowOrigin::predicate(move |origin, _| {
+                origin
+                    .to_str()
+                    .is_ok_and(|origin| cors_reloader.allows_origin(origin))
+            }));
             cors
         } else {
             CorsLayer::new()
         })
         // Add tracing
-        .layer(TraceLayer::new_for_http());
+        .layer(TraceLayer::new_for_http())
+        // Outermost, so every error response carries the correlation ID
+        .layer(axum_middleware::from_fn(api::error::correlation_middleware));
 
     // Start OTLP gRPC server on port 47117 in parallel (if project manager available)
-    let otlp_handle = if let Some(pm) = pm_for_otlp {
+    let mut otlp_handle = if let Some(pm) = pm_for_otlp {
+        let otlp_stopped = shutdown.stopped();
         Some(tokio::spawn(async move {
-            if let Err(e) = otlp_service::start_otlp_server(pm).await {
+            if let Err(e) =
+                otlp_service::start_otlp_server(state_for_otlp, pm, otlp_stopped).await
+            {
                 tracing::error!("OTLP gRPC server error: {}", e);
             }
         }))
@@ -774,54 +1507,88 @@ pub async fn run_server(config: ServerConfig) -> Result<()> {
         None
     };
 
-    // Start MCP server on port 47101 for Claude Desktop / Cursor integration
-    let mcp_handle = tokio::spawn(async move {
-        let causal_index = db_for_mcp.causal_index();
-        let mcp_router = mcp::mcp_router(state_for_mcp, causal_index);
-
-        let mcp_app = Router::new()
-            .merge(mcp_router)
-            .layer(
-                CorsLayer::new()
-                    .allow_origin(Any)
-                    .allow_methods(Any)
-                    .allow_headers(Any),
-            )
-            .layer(TraceLayer::new_for_http());
+    // Start MCP server on port 47101 for Claude Desktop / Cursor integration.
+    // It is unauthenticated, so it reads a single tenant, and only one named
+    // explicitly when tenants must be isolated
+    let mcp_tenant = config
+        .mcp
+        .tenant_id
+        .or_else(|| (!config.auth.strict_tenant_isolation).then(|| config.mcp.tenant()));
+    let mut mcp_handle = if let Some(mcp_tenant) = mcp_tenant {
+        let mcp_stopped = shutdown.stopped();
+        Some(tokio::spawn(async move {
+            let causal_index = db_for_mcp.causal_index();
+            let mcp_router = mcp::mcp_router(state_for_mcp, causal_index, mcp_tenant);
 
-        let mcp_addr = std::net::SocketAddr::from(([127, 0, 0, 1], 47101));
-        tracing::info!("🔌 MCP Server listening on http://{}", mcp_addr);
+            let mcp_app = Router::new()
+                .merge(mcp_router)
+                .layer(
+                    CorsLayer::new()
+                        .allow_origin(Any)
+                        .allow_methods(Any)
+                        .allow_headers(Any),
+                )
+                .layer(TraceLayer::new_for_http());
 
-        match tokio::net::TcpListener::bind(mcp_addr).await {
-            Ok(listener) => {
-                if let Err(e) = axum::serve(listener, mcp_app).await {
-                    tracing::error!("MCP server error: {}", e);
+            let mcp_addr = std::net::SocketAddr::from(([127, 0, 0, 1], 47101));
+            tracing::info!(
+                "🔌 MCP Server listening on http://{} (tenant {})",
+                mcp_addr,
+                mcp_tenant
+            );
+
+            match tokio::net::TcpListener::bind(mcp_addr).await {
+                Ok(listener) => {
+                    if let Err(e) = axum::serve(listener, mcp_app)
+                        .with_graceful_shutdown(mcp_stopped)
+                        .await
+                    {
+                        tracing::error!("MCP server error: {}", e);
+                    }
+                }
+                Err(e) => {
+                    tracing::error!("Failed to bind MCP server on port 47101: {}", e);
                 }
             }
-            Err(e) => {
-                tracing::error!("Failed to bind MCP server on port 47101: {}", e);
-            }
-        }
-    });
+        }))
+    } else {
+        tracing::warn!(
+            "MCP server not started: it is unauthenticated and \
+            auth.strict_tenant_isolation is on; set mcp.tenant_id to serve one tenant"
+        );
+        None
+    };
 
     // Run HTTP server
     let listener = tokio::net::TcpListener::bind(addr).await?;
-    let server_handle = tokio::spawn(async move {
-        if let Err(e) = axum::serve(listener, app).await {
+    let http_stopped = shutdown.stopped();
+    let mut server_handle = tokio::spawn(async move {
+        // Peer addresses key the rate limits of unauthenticated callers
+        let app = app.into_make_service_with_connect_info::<std::net::SocketAddr>();
+        if let Err(e) = axum::serve(listener, app)
+            .with_graceful_shutdown(http_stopped)
+            .await
+        {
             tracing::error!("HTTP server error: {}", e);
         }
     });
 
-    // Wait for any server to complete (all should run indefinitely)
+    // Run until a server stops on its own or a shutdown signal arrives
     tokio::select! {
-        _ = server_handle => {
+        _ = &mut server_handle => {
             tracing::info!("HTTP server stopped");
         }
-        _ = mcp_handle => {
+        _ = async {
+            if let Some(handle) = mcp_handle.as_mut() {
+                let _ = handle.await;
+            } else {
+                futures::future::pending::<()>().await
+            }
+        } => {
             tracing::info!("MCP server stopped");
         }
         _ = async {
-            if let Some(handle) = otlp_handle {
+            if let Some(handle) = otlp_handle.as_mut() {
                 let _ = handle.await;
             } else {
                 futures::future::pending::<()>().await
@@ -829,6 +1596,56 @@ pub async fn run_server(config: ServerConfig) -> Result<()> {
         } => {
             tracing::info!("OTLP gRPC server stopped");
         }
+        _ = crate::shutdown::signal() => {}
+    }
+
+    // Coordinated shutdown, see `crate::shutdown` for the stages
+    shutdown.begin_draining();
+    let drain_delay = std::time::Duration::from_secs(config.server.shutdown_drain_delay_secs);
+    if !drain_delay.is_zero() {
+        tracing::info!("Reporting draining on /health for {:?}", drain_delay);
+        tokio::time::sleep(drain_delay).await;
+    }
+    shutdown.stop_listeners();
+
+    let grace = std::time::Duration::from_secs(config.server.shutdown_grace_secs);
+    let deadline = tokio::time::Instant::now() + grace;
+    let listeners = async {
+        for handle in [Some(server_handle), mcp_handle, otlp_handle]
+            .into_iter()
+            .flatten()
+        {
+            if !handle.is_finished() {
+                let _ = handle.await;
+            }
+        }
+    };
+    match tokio::time::timeout_at(deadline, listeners).await {
+        Ok(()) => tracing::info!("Listeners closed, in-flight requests finished"),
+        Err(_) => tracing::warn!("In-flight requests still running after {:?}", grace),
+    }
+
+    if let Some(actor) = &state_for_shutdown.ingestion_actor {
+        let remaining = deadline.saturating_duration_since(tokio::time::Instant::now());
+        if actor.shutdown(remaining).await {
+            tracing::info!("Ingestion queue drained");
+        } else {
+            tracing::warn!("Ingestion queue not drained within {:?}, abandoning it", grace);
+        }
+    }
+
+    let project_manager = state_for_shutdown.project_manager.clone();
+    let closed = tokio::task::spawn_blocking(move || {
+        if let Some(pm) = project_manager {
+            pm.close_open_projects();
+        }
+        db.flush_metrics().and_then(|_| db.close())
+    })
+    .await;
+    match closed {
+        Ok(Ok(())) => tracing::info!("Database closed, shutdown complete"),
+        Ok(Err(e)) => tracing::error!("Failed to close database: {}", e),
+        Err(e) => tracing::error!("Database close task panicked: {}", e),
     }
 
     Ok(())
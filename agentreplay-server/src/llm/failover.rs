@@ -0,0 +1,576 @@
+// Copyright 2025 AgentReplay (https://github.com/agentreplay)
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program. If not, see <https://www.gnu.org/licenses/>.
+
+//! Retries, timeouts, circuit breakers and fallback providers
+//!
+//! Every chat call through [`super::LLMProviderManager`] gets a timeout and
+//! is retried with exponential backoff when it fails. Retries draw on a
+//! shared budget that each call refills by `budget_ratio`, so an outage
+//! cannot multiply the load on a provider.
+//!
+//! Each configured provider has a circuit breaker. After
+//! `failure_threshold` failures in a row the circuit opens and calls skip
+//! the provider for `open_secs`; then one trial call decides whether it
+//! closes again. Routing rules name, per model, the providers to fall back
+//! to, in order, when the requested one fails or its circuit is open.
+//! Calls with a vault key are retried but never fall back, since that
+//! would bill the server's credentials instead of the tenant's.
+//!
+//! Streams cannot be retried once started, so they only skip providers
+//! whose circuit is open.
+//!
+//! ```toml
+//! [llm.failover]
+//! timeout_secs = 60
+//! max_retries = 1
+//!
+//! [[llm.failover.routes]]
+//! model = "gpt-4o"
+//! fallbacks = [
+//!     { provider = "anthropic", model = "claude-3-5-sonnet-20241022" },
+//!     { provider = "deepseek", model = "deepseek-chat" },
+//! ]
+//! ```
+
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use parking_lot::Mutex;
+use serde::{Deserialize, Serialize};
+use tracing::warn;
+
+use super::{ChatMessage, ChatResponse, LLMProvider};
+use crate::config::FailoverConfig;
+
+/// Providers to try when a model's requested provider fails
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RoutingRule {
+    /// Model requested from the manager
+    pub model: String,
+    /// Requested provider the rule applies to; any when unset
+    #[serde(default)]
+    pub provider: Option<String>,
+    #[serde(default)]
+    pub fallbacks: Vec<FallbackTarget>,
+    /// Overrides the section's `timeout_secs` for this model
+    #[serde(default)]
+    pub timeout_secs: Option<u64>,
+    /// Overrides the section's `max_retries` for this model
+    #[serde(default)]
+    pub max_retries: Option<u32>,
+}
+
+impl RoutingRule {
+    fn matches(&self, provider: &str, model: Option<&str>) -> bool {
+        model == Some(self.model.as_str()) && self.provider.as_deref().is_none_or(|p| p == provider)
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FallbackTarget {
+    pub provider: String,
+    /// Model to ask the fallback provider for; the requested model when unset
+    #[serde(default)]
+    pub model: Option<String>,
+}
+
+/// A provider a call may be sent to
+pub struct Target {
+    pub provider_id: String,
+    pub provider: Arc<dyn LLMProvider>,
+    pub model: Option<String>,
+    /// Whether the provider's circuit breaker applies; vault keys have none,
+    /// so one tenant's bad key cannot cut off the server's provider
+    pub circuit: bool,
+}
+
+/// Response of a call and the failures it took to get it
+#[derive(Debug)]
+pub struct Served {
+    pub response: ChatResponse,
+    /// Provider that answered
+    pub provider_id: String,
+    /// Failed attempts, as "provider: error", oldest first
+    pub failures: Vec<String>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CircuitState {
+    Closed,
+    Open,
+    /// A trial call is deciding whether the circuit closes
+    HalfOpen,
+}
+
+/// Circuit breaker state of a provider, as shown by the health endpoint
+#[derive(Debug, Clone, Serialize)]
+pub struct CircuitStatus {
+    pub provider: String,
+    pub state: CircuitState,
+    pub consecutive_failures: u32,
+    pub failures_total: u64,
+    pub successes_total: u64,
+    /// Seconds until an open circuit lets a trial call through
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub retry_in_secs: Option<u64>,
+}
+
+#[derive(Debug, Default)]
+struct Breaker {
+    consecutive_failures: u32,
+    failures_total: u64,
+    successes_total: u64,
+    opened_at: Option<Instant>,
+    trial_in_flight: bool,
+}
+
+impl Breaker {
+    fn state(&self, now: Instant, open_for: Duration) -> CircuitState {
+        match self.opened_at {
+            None => CircuitState::Closed,
+            Some(opened_at) if now.duration_since(opened_at) < open_for => CircuitState::Open,
+            Some(_) => CircuitState::HalfOpen,
+        }
+    }
+
+    /// Whether a call may go through, claiming the trial of a half-open circuit
+    fn allow(&mut self, now: Instant, open_for: Duration) -> bool {
+        match self.state(now, open_for) {
+            CircuitState::Closed => true,
+            CircuitState::Open => false,
+            CircuitState::HalfOpen if self.trial_in_flight => false,
+            CircuitState::HalfOpen => {
+                self.trial_in_flight = true;
+                true
+            }
+        }
+    }
+
+    fn record_success(&mut self) {
+        self.successes_total += 1;
+        self.consecutive_failures = 0;
+        self.opened_at = None;
+        self.trial_in_flight = false;
+    }
+
+    fn record_failure(&mut self, now: Instant, threshold: u32) {
+        self.failures_total += 1;
+        self.consecutive_failures += 1;
+        // A failed trial reopens the circuit for another full period
+        if self.trial_in_flight || self.consecutive_failures >= threshold {
+            self.opened_at = Some(now);
+        }
+        self.trial_in_flight = false;
+    }
+}
+
+/// Retries left to spend; each call adds `ratio`, up to `burst`
+struct RetryBudget {
+    balance: f64,
+    ratio: f64,
+    burst: f64,
+}
+
+impl RetryBudget {
+    fn deposit(&mut self) {
+        self.balance = (self.balance + self.ratio).min(self.burst);
+    }
+
+    fn withdraw(&mut self) -> bool {
+        if self.balance < 1.0 {
+            return false;
+        }
+        self.balance -= 1.0;
+        true
+    }
+}
+
+fn backoff(config: &FailoverConfig, retry: u32) -> Duration {
+    let base = config
+        .initial_backoff_ms
+        .saturating_mul(1u64 << retry.min(16))
+        .min(config.max_backoff_ms);
+    // Jitter keeps retries of concurrent calls apart
+    Duration::from_millis(base / 2 + rand::random::<u64>() % (base / 2 + 1))
+}
+
+/// Failover policies and the circuit breakers of the manager's providers
+pub struct Failover {
+    config: FailoverConfig,
+    breakers: Mutex<HashMap<String, Breaker>>,
+    budget: Mutex<RetryBudget>,
+}
+
+impl Failover {
+    pub fn new(config: &FailoverConfig) -> Result<Self, String> {
+        if config.timeout_secs == 0 {
+            return Err("timeout_secs must be positive".to_string());
+        }
+        if config.failure_threshold == 0 {
+            return Err("failure_threshold must be positive".to_string());
+        }
+        if config.budget_ratio < 0.0 || config.budget_burst < 0.0 {
+            return Err("budget_ratio and budget_burst must not be negative".to_string());
+        }
+        for rule in &config.routes {
+            if rule.timeout_secs == Some(0) {
+                return Err(format!(
+                    "Route for {}: timeout_secs must be positive",
+                    rule.model
+                ));
+            }
+        }
+        Ok(Self {
+            budget: Mutex::new(RetryBudget {
+                balance: config.budget_burst,
+                ratio: config.budget_ratio,
+                burst: config.budget_burst,
+            }),
+            config: config.clone(),
+            breakers: Mutex::new(HashMap::new()),
+        })
+    }
+
+    fn rule(&self, provider: &str, model: Option<&str>) -> Option<&RoutingRule> {
+        self.config
+            .routes
+            .iter()
+            .find(|rule| rule.matches(provider, model))
+    }
+
+    /// Providers to fall back to for a model, in order
+    pub fn fallbacks(&self, provider: &str, model: Option<&str>) -> &[FallbackTarget] {
+        if !self.config.enabled {
+            return &[];
+        }
+        self.rule(provider, model)
+            .map_or(&[], |rule| rule.fallbacks.as_slice())
+    }
+
+    /// Whether calls may currently go to a provider
+    pub fn is_available(&self, provider: &str) -> bool {
+        let open_for = Duration::from_secs(self.config.open_secs);
+        self.breakers
+            .lock()
+            .get(provider)
+            .is_none_or(|breaker| breaker.state(Instant::now(), open_for) != CircuitState::Open)
+    }
+
+    fn allow(&self, provider: &str) -> bool {
+        let open_for = Duration::from_secs(self.config.open_secs);
+        self.breakers
+            .lock()
+            .entry(provider.to_string())
+            .or_default()
+            .allow(Instant::now(), open_for)
+    }
+
+    fn record(&self, provider: &str, ok: bool) {
+        let mut breakers = self.breakers.lock();
+        let breaker = breakers.entry(provider.to_string()).or_default();
+        if ok {
+            breaker.record_success();
+        } else {
+            breaker.record_failure(Instant::now(), self.config.failure_threshold);
+        }
+    }
+
+    /// Call the first target, retrying it and then falling back to the
+    /// others in order until one answers
+    pub async fn chat(
+        &self,
+        targets: Vec<Target>,
+        messages: Vec<ChatMessage>,
+    ) -> anyhow::Result<Served> {
+        let Some(first) = targets.first() else {
+            anyhow::bail!("No provider to call");
+        };
+        if !self.config.enabled {
+            let response = first.provider.chat(messages, first.model.clone()).await?;
+            return Ok(Served {
+                response,
+                provider_id: first.provider_id.clone(),
+                failures: Vec::new(),
+            });
+        }
+
+        let rule = self.rule(&first.provider_id, first.model.as_deref());
+        let timeout = Duration::from_secs(
+            rule.and_then(|r| r.timeout_secs)
+                .unwrap_or(self.config.timeout_secs),
+        );
+        let max_retries = rule
+            .and_then(|r| r.max_retries)
+            .unwrap_or(self.config.max_retries);
+        self.budget.lock().deposit();
+
+        let mut failures = Vec::new();
+        for target in &targets {
+            for attempt in 0..=max_retries {
+                if attempt > 0 {
+                    if !self.budget.lock().withdraw() {
+                        failures.push(format!("{}: retry budget exhausted", target.provider_id));
+                        break;
+                    }
+                    tokio::time::sleep(backoff(&self.config, attempt - 1)).await;
+                }
+                if target.circuit && !self.allow(&target.provider_id) {
+                    failures.push(format!("{}: circuit open", target.provider_id));
+                    break;
+                }
+                let call = target.provider.chat(messages.clone(), target.model.clone());
+                let error = match tokio::time::timeout(timeout, call).await {
+                    Ok(Ok(response)) => {
+                        if target.circuit {
+                            self.record(&target.provider_id, true);
+                        }
+                        return Ok(Served {
+                            response,
+                            provider_id: target.provider_id.clone(),
+                            failures,
+                        });
+                    }
+                    Ok(Err(e)) => e.to_string(),
+                    Err(_) => format!("timed out after {}s", timeout.as_secs()),
+                };
+                warn!("LLM call to {} failed: {}", target.provider_id, error);
+                if target.circuit {
+                    self.record(&target.provider_id, false);
+                }
+                failures.push(format!("{}: {}", target.provider_id, error));
+            }
+        }
+        anyhow::bail!("All providers failed: {}", failures.join("; "))
+    }
+
+    /// Index of the first target whose circuit is not open, for calls
+    /// that cannot be retried
+    pub fn first_available(&self, targets: &[Target]) -> Option<usize> {
+        if !self.config.enabled {
+            return (!targets.is_empty()).then_some(0);
+        }
+        targets
+            .iter()
+            .position(|target| !target.circuit || self.is_available(&target.provider_id))
+    }
+
+    pub fn circuits(&self) -> Vec<CircuitStatus> {
+        let now = Instant::now();
+        let open_for = Duration::from_secs(self.config.open_secs);
+        let mut circuits: Vec<CircuitStatus> = self
+            .breakers
+            .lock()
+            .iter()
+            .map(|(provider, breaker)| {
+                let state = breaker.state(now, open_for);
+                CircuitStatus {
+                    provider: provider.clone(),
+                    state,
+                    consecutive_failures: breaker.consecutive_failures,
+                    failures_total: breaker.failures_total,
+                    successes_total: breaker.successes_total,
+                    retry_in_secs: breaker
+                        .opened_at
+                        .filter(|_| state == CircuitState::Open)
+                        .map(|opened_at| (open_for - now.duration_since(opened_at)).as_secs()),
+                }
+            })
+            .collect();
+        circuits.sort_by(|a, b| a.provider.cmp(&b.provider));
+        circuits
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicU32, Ordering};
+
+    /// Provider failing its first `failures` calls
+    struct Flaky {
+        name: &'static str,
+        failures: u32,
+        calls: AtomicU32,
+    }
+
+    impl Flaky {
+        fn target(name: &'static str, failures: u32) -> (Target, Arc<Flaky>) {
+            let provider = Arc::new(Flaky {
+                name,
+                failures,
+                calls: AtomicU32::new(0),
+            });
+            let target = Target {
+                provider_id: name.to_string(),
+                provider: provider.clone(),
+                model: Some("m".to_string()),
+                circuit: true,
+            };
+            (target, provider)
+        }
+    }
+
+    #[async_trait::async_trait]
+    impl LLMProvider for Flaky {
+        async fn chat(
+            &self,
+            _messages: Vec<ChatMessage>,
+            model: Option<String>,
+        ) -> anyhow::Result<ChatResponse> {
+            if self.calls.fetch_add(1, Ordering::SeqCst) < self.failures {
+                anyhow::bail!("503 Service Unavailable");
+            }
+            Ok(ChatResponse {
+                content: "ok".to_string(),
+                provider: self.name.to_string(),
+                model: model.unwrap_or_default(),
+                response_model: None,
+                response_id: None,
+                tokens_used: None,
+                input_tokens: None,
+                output_tokens: None,
+                finish_reason: None,
+                duration_ms: 1,
+            })
+        }
+
+        async fn stream_chat(
+            &self,
+            _messages: Vec<ChatMessage>,
+            _model: Option<String>,
+        ) -> anyhow::Result<tokio::sync::mpsc::Receiver<String>> {
+            anyhow::bail!("not streaming")
+        }
+
+        fn list_models(&self) -> Vec<String> {
+            Vec::new()
+        }
+
+        fn name(&self) -> &str {
+            self.name
+        }
+    }
+
+    fn failover(value: serde_json::Value) -> Failover {
+        let mut config: FailoverConfig = serde_json::from_value(value).unwrap();
+        config.initial_backoff_ms = 1;
+        config.max_backoff_ms = 1;
+        Failover::new(&config).unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_retries_then_falls_back() {
+        let failover = failover(serde_json::json!({ "max_retries": 1 }));
+        let (primary, primary_calls) = Flaky::target("openai", 5);
+        let (fallback, _) = Flaky::target("anthropic", 0);
+
+        let served = failover
+            .chat(vec![primary, fallback], Vec::new())
+            .await
+            .unwrap();
+        assert_eq!(served.provider_id, "anthropic");
+        assert_eq!(served.failures.len(), 2);
+        assert_eq!(primary_calls.calls.load(Ordering::SeqCst), 2);
+
+        let (primary, _) = Flaky::target("openai", 1);
+        let served = failover.chat(vec![primary], Vec::new()).await.unwrap();
+        assert_eq!(served.provider_id, "openai");
+        assert_eq!(served.failures.len(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_circuit_opens_and_skips_provider() {
+        let failover = failover(serde_json::json!({
+            "max_retries": 0,
+            "failure_threshold": 2,
+            "open_secs": 60
+        }));
+        for _ in 0..2 {
+            let (primary, _) = Flaky::target("openai", 1);
+            assert!(failover.chat(vec![primary], Vec::new()).await.is_err());
+        }
+        assert!(!failover.is_available("openai"));
+        let circuits = failover.circuits();
+        assert_eq!(circuits[0].state, CircuitState::Open);
+        assert_eq!(circuits[0].failures_total, 2);
+        assert!(circuits[0].retry_in_secs.is_some());
+
+        // The open circuit is skipped without calling the provider
+        let (primary, primary_calls) = Flaky::target("openai", 0);
+        let (fallback, _) = Flaky::target("deepseek", 0);
+        let targets = vec![primary, fallback];
+        assert_eq!(failover.first_available(&targets), Some(1));
+        let served = failover.chat(targets, Vec::new()).await.unwrap();
+        assert_eq!(served.provider_id, "deepseek");
+        assert_eq!(served.failures, vec!["openai: circuit open"]);
+        assert_eq!(primary_calls.calls.load(Ordering::SeqCst), 0);
+    }
+
+    #[test]
+    fn test_half_open_trial() {
+        let open_for = Duration::from_secs(30);
+        let start = Instant::now();
+        let mut breaker = Breaker::default();
+        breaker.record_failure(start, 1);
+        assert!(!breaker.allow(start, open_for));
+
+        let later = start + open_for;
+        assert_eq!(breaker.state(later, open_for), CircuitState::HalfOpen);
+        assert!(breaker.allow(later, open_for));
+        // Only one trial at a time
+        assert!(!breaker.allow(later, open_for));
+        breaker.record_failure(later, 5);
+        assert_eq!(breaker.state(later, open_for), CircuitState::Open);
+
+        let much_later = later + open_for;
+        assert!(breaker.allow(much_later, open_for));
+        breaker.record_success();
+        assert_eq!(breaker.state(much_later, open_for), CircuitState::Closed);
+    }
+
+    #[tokio::test]
+    async fn test_retry_budget() {
+        let failover = failover(serde_json::json!({
+            "max_retries": 3,
+            "budget_ratio": 0.0,
+            "budget_burst": 1.0
+        }));
+        let (primary, calls) = Flaky::target("openai", 10);
+        let err = failover.chat(vec![primary], Vec::new()).await.unwrap_err();
+        assert!(err.to_string().contains("retry budget exhausted"));
+        // One call plus the single retry the budget held
+        assert_eq!(calls.calls.load(Ordering::SeqCst), 2);
+    }
+
+    #[test]
+    fn test_routing_rules() {
+        let failover = failover(serde_json::json!({
+            "routes": [{
+                "model": "gpt-4o",
+                "provider": "openai",
+                "fallbacks": [{ "provider": "anthropic", "model": "claude" }]
+            }]
+        }));
+        assert_eq!(failover.fallbacks("openai", Some("gpt-4o")).len(), 1);
+        assert!(failover.fallbacks("ollama", Some("gpt-4o")).is_empty());
+        assert!(failover.fallbacks("openai", None).is_empty());
+
+        let invalid: FailoverConfig =
+            serde_json::from_value(serde_json::json!({ "timeout_secs": 0 })).unwrap();
+        assert!(Failover::new(&invalid).is_err());
+    }
+}
@@ -0,0 +1,676 @@
+// Copyright 2025 AgentReplay (https://github.com/agentreplay)
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program. If not, see <https://www.gnu.org/licenses/>.
+
+//! Native integration with a local Ollama server
+//!
+//! Chat goes through [`OllamaProvider`](super::OllamaProvider). This module
+//! covers the rest of what running models offline takes: the installed
+//! models and their context length, pulling new models with progress, and
+//! whether a model fits in the machine's GPU or system memory.
+
+use dashmap::DashMap;
+use futures::StreamExt;
+use parking_lot::RwLock;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::Path;
+use std::time::Duration;
+use sysinfo::System;
+use tracing::debug;
+
+/// Where Ollama listens unless configured otherwise
+pub const DEFAULT_OLLAMA_BASE_URL: &str = "http://localhost:11434";
+
+/// Listing and model metadata requests; pulls run as long as the download
+const METADATA_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Tokens kept free for the reply when sizing a context window
+const REPLY_TOKENS: u32 = 1024;
+
+/// Smallest context window requested, Ollama's own default
+const MIN_CONTEXT_TOKENS: u32 = 2048;
+
+/// Model details reported by Ollama
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ModelDetails {
+    pub family: Option<String>,
+    /// e.g. "8.0B"
+    pub parameter_size: Option<String>,
+    /// e.g. "Q4_K_M"
+    #[serde(rename(deserialize = "quantization_level"))]
+    pub quantization: Option<String>,
+}
+
+/// An installed model
+#[derive(Debug, Clone, Serialize)]
+pub struct LocalModel {
+    pub name: String,
+    pub size_bytes: u64,
+    #[serde(flatten)]
+    pub details: ModelDetails,
+    pub modified_at: Option<String>,
+    /// Loaded in memory right now
+    pub loaded: bool,
+    /// Bytes of the loaded model held in GPU memory
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub vram_bytes: Option<u64>,
+    /// Set by callers that probed the machine, see [`SystemResources::fits`]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub fits_in_memory: Option<bool>,
+}
+
+/// What `/api/show` tells about a model
+#[derive(Debug, Clone, Serialize)]
+pub struct ModelInfo {
+    pub name: String,
+    #[serde(flatten)]
+    pub details: ModelDetails,
+    /// Longest context the model supports, in tokens
+    pub context_length: Option<u32>,
+}
+
+#[derive(Deserialize)]
+struct ModelList {
+    #[serde(default)]
+    models: Vec<ModelEntry>,
+}
+
+/// Entry of `/api/tags` (installed) or `/api/ps` (loaded)
+#[derive(Deserialize)]
+struct ModelEntry {
+    name: String,
+    #[serde(default)]
+    size: u64,
+    #[serde(default)]
+    modified_at: Option<String>,
+    #[serde(default)]
+    details: ModelDetails,
+    #[serde(default)]
+    size_vram: Option<u64>,
+}
+
+#[derive(Deserialize)]
+struct ShowResponse {
+    #[serde(default)]
+    details: ModelDetails,
+    #[serde(default)]
+    model_info: serde_json::Map<String, serde_json::Value>,
+}
+
+/// Line of the `/api/pull` stream
+#[derive(Deserialize)]
+struct PullEvent {
+    #[serde(default)]
+    status: String,
+    #[serde(default)]
+    digest: Option<String>,
+    #[serde(default)]
+    total: Option<u64>,
+    #[serde(default)]
+    completed: Option<u64>,
+    #[serde(default)]
+    error: Option<String>,
+}
+
+/// Progress of a pull across all the layers of the model
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct PullProgress {
+    /// Last status reported, e.g. "pulling manifest" or "success"
+    pub status: String,
+    pub completed_bytes: u64,
+    pub total_bytes: u64,
+    /// Completed and total bytes by layer digest
+    #[serde(skip)]
+    layers: HashMap<String, (u64, u64)>,
+}
+
+impl PullProgress {
+    fn update(&mut self, event: &PullEvent) {
+        self.status = event.status.clone();
+        if let (Some(digest), Some(total)) = (&event.digest, event.total) {
+            self.layers
+                .insert(digest.clone(), (event.completed.unwrap_or(0), total));
+            self.completed_bytes = self.layers.values().map(|(done, _)| done).sum();
+            self.total_bytes = self.layers.values().map(|(_, total)| total).sum();
+        }
+    }
+
+    /// Fraction of the bytes seen so far that are downloaded
+    pub fn fraction(&self) -> f64 {
+        if self.is_done() {
+            1.0
+        } else if self.total_bytes == 0 {
+            0.0
+        } else {
+            self.completed_bytes as f64 / self.total_bytes as f64
+        }
+    }
+
+    pub fn is_done(&self) -> bool {
+        self.status == "success"
+    }
+}
+
+/// Splits a byte stream of newline-delimited JSON into lines
+#[derive(Default)]
+pub(crate) struct NdjsonLines {
+    buffer: Vec<u8>,
+}
+
+impl NdjsonLines {
+    /// Complete lines in the chunk, keeping a trailing partial line for later
+    pub(crate) fn push(&mut self, chunk: &[u8]) -> Vec<String> {
+        self.buffer.extend_from_slice(chunk);
+        let mut lines = Vec::new();
+        while let Some(end) = self.buffer.iter().position(|b| *b == b'\n') {
+            let line: Vec<u8> = self.buffer.drain(..=end).collect();
+            let line = String::from_utf8_lossy(&line).trim().to_string();
+            if !line.is_empty() {
+                lines.push(line);
+            }
+        }
+        lines
+    }
+
+    /// A last line the stream did not terminate
+    pub(crate) fn finish(self) -> Option<String> {
+        let line = String::from_utf8_lossy(&self.buffer).trim().to_string();
+        (!line.is_empty()).then_some(line)
+    }
+}
+
+/// `num_ctx` to run a prompt with
+///
+/// Leaves room for a reply and rounds up to a power of two, since Ollama
+/// reloads a model whenever its context size changes, but never asks for more
+/// than the model supports. Fails when the prompt alone does not fit.
+pub fn context_window(prompt_tokens: u32, context_length: Option<u32>) -> Result<u32, String> {
+    let wanted = prompt_tokens
+        .saturating_add(REPLY_TOKENS)
+        .max(MIN_CONTEXT_TOKENS)
+        .checked_next_power_of_two()
+        .unwrap_or(u32::MAX);
+    match context_length {
+        Some(limit) if prompt_tokens >= limit => Err(format!(
+            "Prompt of about {} tokens does not fit the model's {} token context",
+            prompt_tokens, limit
+        )),
+        Some(limit) => Ok(wanted.min(limit)),
+        None => Ok(wanted),
+    }
+}
+
+/// Context length from the `model_info` of `/api/show`, stored under the
+/// architecture, e.g. `llama.context_length`
+fn context_length(model_info: &serde_json::Map<String, serde_json::Value>) -> Option<u32> {
+    let key = model_info
+        .get("general.architecture")
+        .and_then(|arch| arch.as_str())
+        .map(|arch| format!("{}.context_length", arch));
+    key.and_then(|key| model_info.get(&key))
+        .or_else(|| {
+            model_info
+                .iter()
+                .find(|(key, _)| key.ends_with(".context_length"))
+                .map(|(_, value)| value)
+        })
+        .and_then(|value| value.as_u64())
+        .map(|tokens| tokens.min(u32::MAX as u64) as u32)
+}
+
+/// The `error` Ollama puts in the body of failed requests
+async fn error_message(response: reqwest::Response) -> String {
+    let status = response.status();
+    let body = response.text().await.unwrap_or_default();
+    serde_json::from_str::<serde_json::Value>(&body)
+        .ok()
+        .and_then(|json| json["error"].as_str().map(|e| e.to_string()))
+        .unwrap_or_else(|| format!("Ollama returned {}: {}", status, body))
+}
+
+/// Client for the parts of Ollama's API beyond chat
+pub struct LocalModels {
+    base_url: String,
+    client: reqwest::Client,
+    /// Context lengths by model, which only change when a model is pulled
+    context_lengths: DashMap<String, Option<u32>>,
+    /// Names from the last successful listing
+    installed: RwLock<Vec<String>>,
+}
+
+impl LocalModels {
+    pub fn new(base_url: String) -> Self {
+        Self {
+            base_url: base_url.trim_end_matches('/').to_string(),
+            client: reqwest::Client::new(),
+            context_lengths: DashMap::new(),
+            installed: RwLock::new(Vec::new()),
+        }
+    }
+
+    pub fn base_url(&self) -> &str {
+        &self.base_url
+    }
+
+    /// Ollama's OpenAI-compatible endpoint, for clients that only speak that API
+    pub fn openai_base_url(&self) -> String {
+        format!("{}/v1", self.base_url)
+    }
+
+    /// Installed model names as of the last listing
+    pub fn installed_names(&self) -> Vec<String> {
+        self.installed.read().clone()
+    }
+
+    async fn get_models(&self, path: &str) -> anyhow::Result<Vec<ModelEntry>> {
+        let response = self
+            .client
+            .get(format!("{}{}", self.base_url, path))
+            .timeout(METADATA_TIMEOUT)
+            .send()
+            .await
+            .map_err(|e| anyhow::anyhow!("Ollama is not reachable at {}: {}", self.base_url, e))?;
+        if !response.status().is_success() {
+            anyhow::bail!(error_message(response).await);
+        }
+        Ok(response.json::<ModelList>().await?.models)
+    }
+
+    /// Installed models, marking those loaded in memory
+    pub async fn list(&self) -> anyhow::Result<Vec<LocalModel>> {
+        let installed = self.get_models("/api/tags").await?;
+        // Older Ollama versions have no /api/ps
+        let loaded: HashMap<String, u64> = self
+            .get_models("/api/ps")
+            .await
+            .map(|entries| {
+                entries
+                    .into_iter()
+                    .map(|entry| (entry.name, entry.size_vram.unwrap_or(0)))
+                    .collect()
+            })
+            .unwrap_or_default();
+
+        let models: Vec<LocalModel> = installed
+            .into_iter()
+            .map(|entry| {
+                let vram = loaded.get(&entry.name).copied();
+                LocalModel {
+                    loaded: vram.is_some(),
+                    vram_bytes: vram.filter(|bytes| *bytes > 0),
+                    name: entry.name,
+                    size_bytes: entry.size,
+                    details: entry.details,
+                    modified_at: entry.modified_at,
+                    fits_in_memory: None,
+                }
+            })
+            .collect();
+        *self.installed.write() = models.iter().map(|m| m.name.clone()).collect();
+        Ok(models)
+    }
+
+    /// Details and context length of an installed model
+    pub async fn show(&self, name: &str) -> anyhow::Result<ModelInfo> {
+        let response = self
+            .client
+            .post(format!("{}/api/show", self.base_url))
+            .timeout(METADATA_TIMEOUT)
+            .json(&serde_json::json!({ "model": name }))
+            .send()
+            .await?;
+        if !response.status().is_success() {
+            anyhow::bail!(error_message(response).await);
+        }
+        let show: ShowResponse = response.json().await?;
+        let info = ModelInfo {
+            name: name.to_string(),
+            details: show.details,
+            context_length: context_length(&show.model_info),
+        };
+        self.context_lengths
+            .insert(name.to_string(), info.context_length);
+        Ok(info)
+    }
+
+    /// Context length of a model, looked up once; `None` when Ollama does not
+    /// report it or cannot be reached
+    pub async fn context_length(&self, name: &str) -> Option<u32> {
+        if let Some(known) = self.context_lengths.get(name) {
+            return *known;
+        }
+        match self.show(name).await {
+            Ok(info) => info.context_length,
+            Err(e) => {
+                debug!("No context length for {}: {}", name, e);
+                None
+            }
+        }
+    }
+
+    /// Download a model, reporting progress after every line Ollama streams
+    pub async fn pull<F>(&self, name: &str, mut on_progress: F) -> anyhow::Result<PullProgress>
+    where
+        F: FnMut(&PullProgress),
+    {
+        let response = self
+            .client
+            .post(format!("{}/api/pull", self.base_url))
+            .json(&serde_json::json!({ "model": name, "stream": true }))
+            .send()
+            .await
+            .map_err(|e| anyhow::anyhow!("Ollama is not reachable at {}: {}", self.base_url, e))?;
+        if !response.status().is_success() {
+            anyhow::bail!(error_message(response).await);
+        }
+
+        let mut progress = PullProgress::default();
+        let mut lines = NdjsonLines::default();
+        let mut stream = response.bytes_stream();
+        let mut handle = |line: &str, progress: &mut PullProgress| -> anyhow::Result<()> {
+            let event: PullEvent = serde_json::from_str(line)?;
+            if let Some(error) = event.error {
+                anyhow::bail!("Pulling {} failed: {}", name, error);
+            }
+            progress.update(&event);
+            on_progress(progress);
+            Ok(())
+        };
+        while let Some(chunk) = stream.next().await {
+            for line in lines.push(&chunk?) {
+                handle(&line, &mut progress)?;
+            }
+        }
+        if let Some(line) = lines.finish() {
+            handle(&line, &mut progress)?;
+        }
+        if !progress.is_done() {
+            anyhow::bail!("Pull of {} ended at '{}'", name, progress.status);
+        }
+
+        self.context_lengths.remove(name);
+        if let Err(e) = self.list().await {
+            debug!(
+                "Could not refresh local models after pulling {}: {}",
+                name, e
+            );
+        }
+        Ok(progress)
+    }
+}
+
+/// A GPU models can be offloaded to
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Gpu {
+    /// "apple", "nvidia" or "amd"
+    pub vendor: String,
+    pub name: String,
+    /// Dedicated memory, when known
+    pub memory_bytes: Option<u64>,
+    /// Shares system memory with the CPU, as on Apple Silicon
+    pub unified_memory: bool,
+}
+
+/// Memory, CPU and GPUs of the machine running the models
+#[derive(Debug, Clone, Serialize)]
+pub struct SystemResources {
+    pub total_memory_bytes: u64,
+    pub available_memory_bytes: u64,
+    pub cpu_cores: usize,
+    pub cpu_brand: String,
+    pub gpus: Vec<Gpu>,
+}
+
+impl SystemResources {
+    /// Read memory and CPU through sysinfo, which does not see GPUs: those
+    /// come from the CPU brand on Apple Silicon, `nvidia-smi` for NVIDIA and
+    /// the amdgpu sysfs entries for AMD. Blocks for the `nvidia-smi` call.
+    pub fn detect() -> Self {
+        let mut sys = System::new();
+        sys.refresh_memory();
+        sys.refresh_cpu_usage();
+
+        let cpu_brand = sys
+            .cpus()
+            .first()
+            .map(|cpu| cpu.brand().trim().to_string())
+            .unwrap_or_default();
+        let total_memory_bytes = sys.total_memory();
+        // macOS may report no available memory
+        let available_memory_bytes = match sys.available_memory() {
+            0 => total_memory_bytes.saturating_sub(sys.used_memory()),
+            available => available,
+        };
+
+        let mut gpus = Vec::new();
+        if cfg!(target_os = "macos") && cpu_brand.starts_with("Apple") {
+            gpus.push(Gpu {
+                vendor: "apple".to_string(),
+                name: cpu_brand.clone(),
+                memory_bytes: None,
+                unified_memory: true,
+            });
+        }
+        gpus.extend(nvidia_gpus());
+        gpus.extend(amd_gpus(Path::new("/sys/class/drm")));
+
+        Self {
+            total_memory_bytes,
+            available_memory_bytes,
+            cpu_cores: sys.cpus().len(),
+            cpu_brand,
+            gpus,
+        }
+    }
+
+    /// Bytes a model can occupy: the largest GPU memory, or the available
+    /// system memory when running on the CPU. Metal lets the GPU use about
+    /// two thirds of unified memory.
+    pub fn model_memory_bytes(&self) -> u64 {
+        self.gpus
+            .iter()
+            .filter_map(|gpu| {
+                if gpu.unified_memory {
+                    Some(self.total_memory_bytes / 3 * 2)
+                } else {
+                    gpu.memory_bytes
+                }
+            })
+            .max()
+            .unwrap_or(self.available_memory_bytes)
+    }
+
+    /// Whether a model of this size fits, counting a fifth on top of the
+    /// weights for the KV cache and runtime buffers
+    pub fn fits(&self, model_bytes: u64) -> bool {
+        model_bytes.saturating_add(model_bytes / 5) <= self.model_memory_bytes()
+    }
+}
+
+fn nvidia_gpus() -> Vec<Gpu> {
+    std::process::Command::new("nvidia-smi")
+        .args([
+            "--query-gpu=name,memory.total",
+            "--format=csv,noheader,nounits",
+        ])
+        .output()
+        .ok()
+        .filter(|output| output.status.success())
+        .map(|output| parse_nvidia_smi(&String::from_utf8_lossy(&output.stdout)))
+        .unwrap_or_default()
+}
+
+/// Lines of `name, memory in MiB`
+fn parse_nvidia_smi(output: &str) -> Vec<Gpu> {
+    output
+        .lines()
+        .filter_map(|line| {
+            let (name, memory) = line.rsplit_once(',')?;
+            Some(Gpu {
+                vendor: "nvidia".to_string(),
+                name: name.trim().to_string(),
+                memory_bytes: memory
+                    .trim()
+                    .parse::<u64>()
+                    .ok()
+                    .map(|mib| mib * 1024 * 1024),
+                unified_memory: false,
+            })
+        })
+        .collect()
+}
+
+/// AMD cards under a DRM class directory, identified by the PCI vendor ID
+fn amd_gpus(drm: &Path) -> Vec<Gpu> {
+    let Ok(entries) = std::fs::read_dir(drm) else {
+        return Vec::new();
+    };
+    let mut cards: Vec<_> = entries
+        .filter_map(|entry| entry.ok())
+        .map(|entry| entry.file_name().to_string_lossy().to_string())
+        // card0 but not its connectors such as card0-DP-1
+        .filter(|name| name.starts_with("card") && !name.contains('-'))
+        .collect();
+    cards.sort();
+
+    cards
+        .into_iter()
+        .filter_map(|card| {
+            let device = drm.join(&card).join("device");
+            let vendor = std::fs::read_to_string(device.join("vendor")).ok()?;
+            if vendor.trim() != "0x1002" {
+                return None;
+            }
+            let memory_bytes = std::fs::read_to_string(device.join("mem_info_vram_total"))
+                .ok()
+                .and_then(|bytes| bytes.trim().parse().ok());
+            let name = std::fs::read_to_string(device.join("product_name"))
+                .ok()
+                .map(|name| name.trim().to_string())
+                .filter(|name| !name.is_empty())
+                .unwrap_or_else(|| format!("AMD GPU ({})", card));
+            Some(Gpu {
+                vendor: "amd".to_string(),
+                name,
+                memory_bytes,
+                unified_memory: false,
+            })
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_context_window_fits_prompt_and_reply() {
+        assert_eq!(context_window(100, Some(8192)), Ok(2048));
+        assert_eq!(context_window(3000, Some(8192)), Ok(4096));
+        // Capped at what the model supports
+        assert_eq!(context_window(7000, Some(8192)), Ok(8192));
+        assert_eq!(context_window(7000, None), Ok(8192));
+        assert!(context_window(9000, Some(8192)).is_err());
+    }
+
+    #[test]
+    fn test_context_length_from_model_info() {
+        let info = serde_json::json!({
+            "general.architecture": "llama",
+            "llama.context_length": 131072,
+            "llama.embedding_length": 4096
+        });
+        assert_eq!(context_length(info.as_object().unwrap()), Some(131072));
+
+        let info = serde_json::json!({ "qwen2.context_length": 32768 });
+        assert_eq!(context_length(info.as_object().unwrap()), Some(32768));
+        assert_eq!(context_length(&serde_json::Map::new()), None);
+    }
+
+    #[test]
+    fn test_pull_progress_across_layers() {
+        let mut lines = NdjsonLines::default();
+        let mut progress = PullProgress::default();
+        let stream = concat!(
+            "{\"status\":\"pulling manifest\"}\n",
+            "{\"status\":\"pulling a\",\"digest\":\"a\",\"total\":300,\"completed\":150}\n",
+            "{\"status\":\"pulling b\",\"digest\":\"b\",\"total\":100,\"comp",
+        );
+        for line in lines.push(stream.as_bytes()) {
+            progress.update(&serde_json::from_str(&line).unwrap());
+        }
+        assert_eq!(progress.fraction(), 0.5);
+
+        for line in lines.push(b"leted\":50}\n{\"status\":\"success\"}") {
+            progress.update(&serde_json::from_str(&line).unwrap());
+        }
+        assert_eq!((progress.completed_bytes, progress.total_bytes), (200, 400));
+        assert!(!progress.is_done());
+        progress.update(&serde_json::from_str(&lines.finish().unwrap()).unwrap());
+        assert!(progress.is_done());
+        assert_eq!(progress.fraction(), 1.0);
+    }
+
+    #[test]
+    fn test_parse_nvidia_smi() {
+        let gpus = parse_nvidia_smi("NVIDIA GeForce RTX 4090, 24564\nTesla T4, 15360\n");
+        assert_eq!(gpus.len(), 2);
+        assert_eq!(gpus[0].name, "NVIDIA GeForce RTX 4090");
+        assert_eq!(gpus[0].memory_bytes, Some(24564 * 1024 * 1024));
+        assert!(parse_nvidia_smi("").is_empty());
+    }
+
+    #[test]
+    fn test_amd_gpus_from_sysfs() {
+        let dir = tempfile::tempdir().unwrap();
+        for (card, vendor) in [("card0", "0x1002"), ("card1", "0x10de")] {
+            let device = dir.path().join(card).join("device");
+            std::fs::create_dir_all(&device).unwrap();
+            std::fs::write(device.join("vendor"), format!("{}\n", vendor)).unwrap();
+            std::fs::write(device.join("mem_info_vram_total"), "17163091968\n").unwrap();
+        }
+        std::fs::create_dir_all(dir.path().join("card0-DP-1")).unwrap();
+
+        let gpus = amd_gpus(dir.path());
+        assert_eq!(gpus.len(), 1);
+        assert_eq!(gpus[0].name, "AMD GPU (card0)");
+        assert_eq!(gpus[0].memory_bytes, Some(17163091968));
+    }
+
+    #[test]
+    fn test_model_fits_in_gpu_or_system_memory() {
+        let gib = 1024 * 1024 * 1024;
+        let mut resources = SystemResources {
+            total_memory_bytes: 32 * gib,
+            available_memory_bytes: 16 * gib,
+            cpu_cores: 8,
+            cpu_brand: String::new(),
+            gpus: Vec::new(),
+        };
+        assert!(resources.fits(10 * gib));
+        assert!(!resources.fits(15 * gib));
+
+        resources.gpus.push(Gpu {
+            vendor: "nvidia".to_string(),
+            name: "RTX".to_string(),
+            memory_bytes: Some(24 * gib),
+            unified_memory: false,
+        });
+        assert!(resources.fits(15 * gib));
+
+        resources.gpus[0].unified_memory = true;
+        assert_eq!(resources.model_memory_bytes(), 32 * gib / 3 * 2);
+    }
+}
@@ -14,14 +14,20 @@
 // along with this program. If not, see <https://www.gnu.org/licenses/>.
 
 use crate::config::LLMConfig;
+use crate::llm::failover::{CircuitStatus, Failover, Target};
+use crate::llm::local::LocalModels;
+use crate::vault::ResolvedKey;
 use dashmap::DashMap;
 use agentreplay_core::{AgentFlowEdge, SpanType};
 use agentreplay_query::Agentreplay;
 use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
 use std::sync::Arc;
 use std::time::Instant;
 use tracing::{info, warn};
 
+pub mod failover;
+pub mod local;
 mod providers;
 pub use providers::*;
 
@@ -55,6 +61,9 @@ pub struct ChatResponse {
 
 pub struct LLMProviderManager {
     providers: DashMap<String, Arc<dyn LLMProvider>>,
+    failover: Failover,
+    /// Model listing and pulls of the Ollama provider, when configured
+    local: Option<Arc<LocalModels>>,
     db: Arc<Agentreplay>,
 }
 
@@ -108,13 +117,84 @@ impl LLMProviderManager {
         }
 
         // Initialize Ollama (local, no key needed)
-        if let Some(base_url) = &llm_config.ollama_base_url {
-            let provider = Arc::new(OllamaProvider::new(base_url.clone())?);
+        let local = llm_config.ollama_base_url.as_ref().map(|base_url| {
+            let local = Arc::new(LocalModels::new(base_url.clone()));
+            let provider = Arc::new(OllamaProvider::with_local(Arc::clone(&local)));
             providers.insert("ollama".to_string(), provider as Arc<dyn LLMProvider>);
             info!("Initialized Ollama provider");
+
+            // Learn the installed models without holding up startup
+            let listing = Arc::clone(&local);
+            tokio::spawn(async move {
+                match listing.list().await {
+                    Ok(models) => info!("Found {} local Ollama models", models.len()),
+                    Err(e) => warn!("Could not list local Ollama models: {}", e),
+                }
+            });
+            local
+        });
+
+        let failover = Failover::new(&llm_config.failover)
+            .map_err(|e| anyhow::anyhow!("Invalid failover configuration: {}", e))?;
+        for route in &llm_config.failover.routes {
+            for fallback in &route.fallbacks {
+                if !providers.contains_key(&fallback.provider) {
+                    warn!(
+                        "Fallback provider {} of {} is not configured and will be skipped",
+                        fallback.provider, route.model
+                    );
+                }
+            }
         }
 
-        Ok(Self { providers, db })
+        Ok(Self {
+            providers,
+            failover,
+            local,
+            db,
+        })
+    }
+
+    /// The local Ollama server, if one is configured
+    pub fn local_models(&self) -> Option<Arc<LocalModels>> {
+        self.local.clone()
+    }
+
+    /// A configured provider followed by its configured fallbacks for the model
+    fn targets(&self, provider_id: &str, model: Option<String>) -> anyhow::Result<Vec<Target>> {
+        let provider = self
+            .providers
+            .get(provider_id)
+            .map(|entry| entry.value().clone())
+            .ok_or_else(|| anyhow::anyhow!("Provider not found: {}", provider_id))?;
+        let fallbacks = self
+            .failover
+            .fallbacks(provider_id, model.as_deref())
+            .iter()
+            .filter_map(|fallback| {
+                let provider = self.providers.get(&fallback.provider)?.value().clone();
+                Some(Target {
+                    provider_id: fallback.provider.clone(),
+                    provider,
+                    model: fallback.model.clone().or_else(|| model.clone()),
+                    circuit: true,
+                })
+            })
+            .collect::<Vec<_>>();
+
+        let mut targets = vec![Target {
+            provider_id: provider_id.to_string(),
+            provider,
+            model,
+            circuit: true,
+        }];
+        targets.extend(fallbacks);
+        Ok(targets)
+    }
+
+    /// Circuit breaker states of the providers called so far
+    pub fn circuits(&self) -> Vec<CircuitStatus> {
+        self.failover.circuits()
     }
 
     pub async fn chat(
@@ -125,12 +205,184 @@ impl LLMProviderManager {
         tenant_id: u64,
         session_id: u64,
     ) -> anyhow::Result<ChatResponse> {
-        // Get provider
-        let provider = self
-            .providers
-            .get(provider_id)
-            .ok_or_else(|| anyhow::anyhow!("Provider not found: {}", provider_id))?;
+        let targets = self.targets(provider_id, model)?;
+        self.traced_chat(
+            provider_id,
+            targets,
+            None,
+            messages,
+            tenant_id,
+            session_id,
+            HashMap::new(),
+        )
+        .await
+        .map(|(response, _)| response)
+    }
 
+    /// Chat through a configured provider, tagging the response span with
+    /// extra attributes and returning its edge ID alongside the response
+    pub async fn chat_with_attributes(
+        &self,
+        provider_id: &str,
+        model: Option<String>,
+        messages: Vec<ChatMessage>,
+        tenant_id: u64,
+        session_id: u64,
+        attributes: HashMap<String, String>,
+    ) -> anyhow::Result<(ChatResponse, u128)> {
+        let targets = self.targets(provider_id, model)?;
+        self.traced_chat(
+            provider_id,
+            targets,
+            None,
+            messages,
+            tenant_id,
+            session_id,
+            attributes,
+        )
+        .await
+    }
+
+    /// Chat with a vault key instead of the server-wide provider credentials
+    ///
+    /// The response span carries the key alias in `agentreplay.key_alias`.
+    pub async fn chat_with_key(
+        &self,
+        key: &ResolvedKey,
+        model: Option<String>,
+        messages: Vec<ChatMessage>,
+        tenant_id: u64,
+        session_id: u64,
+    ) -> anyhow::Result<ChatResponse> {
+        self.chat_with_key_attributes(key, model, messages, tenant_id, session_id, HashMap::new())
+            .await
+            .map(|(response, _)| response)
+    }
+
+    /// [`chat_with_key`](Self::chat_with_key), tagging the response span with
+    /// extra attributes and returning its edge ID alongside the response
+    pub async fn chat_with_key_attributes(
+        &self,
+        key: &ResolvedKey,
+        model: Option<String>,
+        messages: Vec<ChatMessage>,
+        tenant_id: u64,
+        session_id: u64,
+        attributes: HashMap<String, String>,
+    ) -> anyhow::Result<(ChatResponse, u128)> {
+        // Retried but never sent to a fallback, which would bill the server's keys
+        let target = Target {
+            provider_id: key.provider.clone(),
+            provider: provider_for_key(key)?,
+            model,
+            circuit: false,
+        };
+        self.traced_chat(
+            &key.provider,
+            vec![target],
+            Some(&key.alias),
+            messages,
+            tenant_id,
+            session_id,
+            attributes,
+        )
+        .await
+    }
+
+    /// Merge attributes into the payload of a span recorded by this manager
+    pub fn annotate_span(
+        &self,
+        edge_id: u128,
+        attributes: HashMap<String, String>,
+    ) -> anyhow::Result<()> {
+        let mut payload: HashMap<String, String> = self
+            .db
+            .get_payload(edge_id)?
+            .and_then(|bytes| serde_json::from_slice(&bytes).ok())
+            .unwrap_or_default();
+        payload.extend(attributes);
+        self.db.put_payload(edge_id, &serde_json::to_vec(&payload)?)?;
+        Ok(())
+    }
+
+    /// Record a chat request that was rejected before reaching the provider
+    pub async fn record_rejected_chat(
+        &self,
+        provider_id: &str,
+        tenant_id: u64,
+        session_id: u64,
+        mut attributes: HashMap<String, String>,
+    ) -> anyhow::Result<u128> {
+        let edge = AgentFlowEdge::new(
+            tenant_id,
+            0,
+            self.hash_provider(provider_id),
+            session_id,
+            SpanType::Error,
+            0,
+        );
+        attributes.insert("gen_ai.system".to_string(), provider_id.to_string());
+        attributes.insert("gen_ai.operation.name".to_string(), "chat".to_string());
+        self.db.put_payload(edge.edge_id, &serde_json::to_vec(&attributes)?)?;
+
+        let edge_id = edge.edge_id;
+        self.db.insert(edge).await?;
+        Ok(edge_id)
+    }
+
+    /// Record a chat request answered from the response cache. No provider
+    /// was called, so the span carries no token usage.
+    pub async fn record_cached_chat(
+        &self,
+        provider_id: &str,
+        response: &ChatResponse,
+        tenant_id: u64,
+        session_id: u64,
+        mut attributes: HashMap<String, String>,
+    ) -> anyhow::Result<u128> {
+        let mut edge = AgentFlowEdge::new(
+            tenant_id,
+            0,
+            self.hash_provider(provider_id),
+            session_id,
+            SpanType::ToolResponse,
+            0,
+        );
+        attributes.insert("gen_ai.system".to_string(), response.provider.clone());
+        attributes.insert("gen_ai.operation.name".to_string(), "chat".to_string());
+        attributes.insert("gen_ai.request.model".to_string(), response.model.clone());
+        if let Some(rm) = &response.response_model {
+            attributes.insert("gen_ai.response.model".to_string(), rm.clone());
+        }
+
+        let links = attribute_links(edge.edge_id, &attributes);
+        if !links.is_empty() {
+            edge.mark_has_links();
+        }
+        self.db
+            .put_payload(edge.edge_id, &serde_json::to_vec(&attributes)?)?;
+
+        let edge_id = edge.edge_id;
+        self.db.insert(edge).await?;
+        if !links.is_empty() {
+            if let Err(e) = self.db.put_edge_links(edge_id, &links) {
+                warn!("Failed to store span links of {:#x}: {}", edge_id, e);
+            }
+        }
+        Ok(edge_id)
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    async fn traced_chat(
+        &self,
+        provider_id: &str,
+        targets: Vec<Target>,
+        key_alias: Option<&str>,
+        messages: Vec<ChatMessage>,
+        tenant_id: u64,
+        session_id: u64,
+        extra_attributes: HashMap<String, String>,
+    ) -> anyhow::Result<(ChatResponse, u128)> {
         // Create request edge for tracing
         let request_edge = AgentFlowEdge::new(
             1, // TODO: Get from auth context
@@ -145,14 +397,15 @@ impl LLMProviderManager {
 
         // Call LLM
         let start = Instant::now();
-        let response = provider.chat(messages, model).await?;
+        let served = self.failover.chat(targets, messages).await?;
+        let response = served.response;
         let duration_ms = start.elapsed().as_millis() as u32;
 
         // Log response edge with OpenTelemetry GenAI attributes
         let mut response_edge = AgentFlowEdge::new(
             tenant_id,
             0,
-            self.hash_provider(provider_id),
+            self.hash_provider(&served.provider_id),
             session_id,
             SpanType::ToolResponse,
             request_id,
@@ -161,8 +414,7 @@ impl LLMProviderManager {
         response_edge.token_count = response.tokens_used.unwrap_or(0);
 
         // Store OpenTelemetry GenAI attributes in payload
-        use std::collections::HashMap;
-        let mut attributes = HashMap::new();
+        let mut attributes = extra_attributes;
 
         // Provider identification
         attributes.insert("gen_ai.system".to_string(), response.provider.clone());
@@ -201,16 +453,45 @@ impl LLMProviderManager {
             attributes.insert("token_count".to_string(), tc.to_string());
         }
 
+        if let Some(alias) = key_alias {
+            attributes.insert("agentreplay.key_alias".to_string(), alias.to_string());
+        }
+
+        // Retries and fallbacks it took to get the answer
+        if !served.failures.is_empty() {
+            attributes.insert(
+                "agentreplay.failover.requested_provider".to_string(),
+                provider_id.to_string(),
+            );
+            attributes.insert(
+                "agentreplay.failover.failures".to_string(),
+                serde_json::to_string(&served.failures).unwrap_or_default(),
+            );
+        }
+
+        let links = attribute_links(response_edge.edge_id, &attributes);
+        if !links.is_empty() {
+            response_edge.mark_has_links();
+        }
+
         // Store attributes as payload
         if let Ok(payload_bytes) = serde_json::to_vec(&attributes) {
             let _ = self.db.put_payload(response_edge.edge_id, &payload_bytes);
         }
 
+        let response_id = response_edge.edge_id;
         self.db.insert(response_edge).await?;
+        if !links.is_empty() {
+            if let Err(e) = self.db.put_edge_links(response_id, &links) {
+                warn!("Failed to store span links of {:#x}: {}", response_id, e);
+            }
+        }
 
-        Ok(response)
+        Ok((response, response_id))
     }
 
+    /// Stream through a configured provider; `attributes` are stored on the
+    /// request span
     pub async fn stream_chat(
         &self,
         provider_id: &str,
@@ -218,12 +499,65 @@ impl LLMProviderManager {
         messages: Vec<ChatMessage>,
         tenant_id: u64,
         session_id: u64,
+        mut attributes: HashMap<String, String>,
     ) -> anyhow::Result<tokio::sync::mpsc::Receiver<String>> {
-        let provider = self
-            .providers
-            .get(provider_id)
-            .ok_or_else(|| anyhow::anyhow!("Provider not found: {}", provider_id))?;
+        let mut targets = self.targets(provider_id, model)?;
+        let index = self.failover.first_available(&targets).ok_or_else(|| {
+            anyhow::anyhow!("Circuits of {} and its fallbacks are open", provider_id)
+        })?;
+        let target = targets.swap_remove(index);
+        if index > 0 {
+            attributes.insert(
+                "agentreplay.failover.requested_provider".to_string(),
+                provider_id.to_string(),
+            );
+        }
+        self.traced_stream_chat(
+            &target.provider_id,
+            target.provider,
+            target.model,
+            messages,
+            tenant_id,
+            session_id,
+            attributes,
+        )
+        .await
+    }
+
+    /// Stream with a vault key instead of the server-wide provider credentials
+    pub async fn stream_chat_with_key(
+        &self,
+        key: &ResolvedKey,
+        model: Option<String>,
+        messages: Vec<ChatMessage>,
+        tenant_id: u64,
+        session_id: u64,
+        attributes: HashMap<String, String>,
+    ) -> anyhow::Result<tokio::sync::mpsc::Receiver<String>> {
+        let provider = provider_for_key(key)?;
+        self.traced_stream_chat(
+            &key.provider,
+            provider,
+            model,
+            messages,
+            tenant_id,
+            session_id,
+            attributes,
+        )
+        .await
+    }
 
+    #[allow(clippy::too_many_arguments)]
+    async fn traced_stream_chat(
+        &self,
+        provider_id: &str,
+        provider: Arc<dyn LLMProvider>,
+        model: Option<String>,
+        messages: Vec<ChatMessage>,
+        tenant_id: u64,
+        session_id: u64,
+        attributes: HashMap<String, String>,
+    ) -> anyhow::Result<tokio::sync::mpsc::Receiver<String>> {
         // Log request edge
         let request_edge = AgentFlowEdge::new(
             tenant_id,
@@ -233,7 +567,11 @@ impl LLMProviderManager {
             SpanType::ToolCall,
             0,
         );
-        let _request_id = request_edge.edge_id;
+        if !attributes.is_empty() {
+            if let Ok(payload_bytes) = serde_json::to_vec(&attributes) {
+                let _ = self.db.put_payload(request_edge.edge_id, &payload_bytes);
+            }
+        }
         self.db.insert(request_edge).await?;
 
         provider.stream_chat(messages, model).await
@@ -247,7 +585,7 @@ impl LLMProviderManager {
                 ProviderInfo {
                     id: id.clone(),
                     name: provider.name().to_string(),
-                    available: true,
+                    available: self.failover.is_available(id),
                     models: provider.list_models(),
                 }
             })
@@ -262,3 +600,34 @@ impl LLMProviderManager {
         hasher.finish()
     }
 }
+
+/// Provider client authenticated with a vault key
+pub fn provider_for_key(key: &ResolvedKey) -> anyhow::Result<Arc<dyn LLMProvider>> {
+    let provider: Arc<dyn LLMProvider> = match key.provider.as_str() {
+        "openai" => Arc::new(OpenAIProvider::with_base_url(
+            key.api_key.clone(),
+            key.base_url.clone(),
+        )?),
+        "anthropic" => Arc::new(AnthropicProvider::new(key.api_key.clone())?),
+        "deepseek" => Arc::new(DeepSeekProvider::new(key.api_key.clone())?),
+        other => anyhow::bail!("Vault keys are not supported for provider {}", other),
+    };
+    Ok(provider)
+}
+
+/// Spans linked through the `agentreplay.links` attribute, as on ingested spans
+fn attribute_links(
+    edge_id: u128,
+    attributes: &HashMap<String, String>,
+) -> Vec<agentreplay_core::SpanLink> {
+    attributes
+        .get(crate::ingestion::ATTR_SPAN_LINKS)
+        .map(|value| {
+            crate::ingestion::parse_span_links(
+                edge_id,
+                &serde_json::Value::String(value.clone()),
+                |id| u128::from_str_radix(id.trim_start_matches("0x"), 16).ok(),
+            )
+        })
+        .unwrap_or_default()
+}
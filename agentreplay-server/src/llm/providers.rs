@@ -13,6 +13,7 @@
 // You should have received a copy of the GNU Affero General Public License
 // along with this program. If not, see <https://www.gnu.org/licenses/>.
 
+use super::local::{context_window, LocalModels, NdjsonLines};
 use super::{ChatMessage, ChatResponse, LLMProvider};
 use async_openai::{
     config::OpenAIConfig,
@@ -24,6 +25,7 @@ use async_openai::{
     Client as OpenAIClient,
 };
 use serde_json::json;
+use std::sync::Arc;
 use std::time::Instant;
 use tokio::sync::mpsc;
 
@@ -35,7 +37,15 @@ pub struct OpenAIProvider {
 
 impl OpenAIProvider {
     pub fn new(api_key: String) -> anyhow::Result<Self> {
-        let config = OpenAIConfig::new().with_api_key(api_key);
+        Self::with_base_url(api_key, None)
+    }
+
+    /// Client for an OpenAI-compatible endpoint (`None` for api.openai.com)
+    pub fn with_base_url(api_key: String, base_url: Option<String>) -> anyhow::Result<Self> {
+        let mut config = OpenAIConfig::new().with_api_key(api_key);
+        if let Some(base_url) = base_url {
+            config = config.with_api_base(base_url);
+        }
         let client = OpenAIClient::with_config(config);
 
         Ok(Self {
@@ -424,20 +434,55 @@ impl LLMProvider for DeepSeekProvider {
 
 // Ollama Provider (Local)
 pub struct OllamaProvider {
-    base_url: String,
-    models: Vec<String>,
+    local: Arc<LocalModels>,
 }
 
 impl OllamaProvider {
     pub fn new(base_url: String) -> anyhow::Result<Self> {
-        Ok(Self {
-            base_url,
-            models: vec![
-                "llama2".to_string(),
-                "mistral".to_string(),
-                "codellama".to_string(),
-            ],
-        })
+        Ok(Self::with_local(Arc::new(LocalModels::new(base_url))))
+    }
+
+    /// Provider sharing the model listing and context lengths of `local`
+    pub fn with_local(local: Arc<LocalModels>) -> Self {
+        Self { local }
+    }
+
+    /// Request body for `/api/chat`, with a context window sized to the
+    /// prompt instead of Ollama's default, which silently truncates
+    async fn request_body(
+        &self,
+        messages: &[ChatMessage],
+        model_name: &str,
+        stream: bool,
+    ) -> anyhow::Result<serde_json::Value> {
+        // Rough approximation: 4 chars ≈ 1 token
+        let prompt_tokens = messages
+            .iter()
+            .map(|m| m.content.len() as u32 / 4)
+            .sum::<u32>();
+        let context_length = self.local.context_length(model_name).await;
+        let num_ctx = context_window(prompt_tokens, context_length)
+            .map_err(|e| anyhow::anyhow!("{}: {}", model_name, e))?;
+
+        let formatted_messages: Vec<_> = messages
+            .iter()
+            .map(|m| json!({"role": m.role, "content": m.content}))
+            .collect();
+
+        Ok(json!({
+            "model": model_name,
+            "messages": formatted_messages,
+            "stream": stream,
+            "options": { "num_ctx": num_ctx },
+        }))
+    }
+
+    fn default_model(&self) -> String {
+        self.local
+            .installed_names()
+            .into_iter()
+            .next()
+            .unwrap_or_else(|| "llama2".to_string())
     }
 }
 
@@ -449,45 +494,42 @@ impl LLMProvider for OllamaProvider {
         model: Option<String>,
     ) -> anyhow::Result<ChatResponse> {
         let start = Instant::now();
-        let model_name = model.unwrap_or_else(|| "llama2".to_string());
+        let model_name = model.unwrap_or_else(|| self.default_model());
 
         let client = reqwest::Client::new();
-
-        let formatted_messages: Vec<_> = messages
-            .iter()
-            .map(|m| json!({"role": m.role, "content": m.content}))
-            .collect();
-
-        let body = json!({
-            "model": model_name,
-            "messages": formatted_messages,
-        });
+        let body = self.request_body(&messages, &model_name, false).await?;
 
         let response = client
-            .post(format!("{}/api/chat", self.base_url))
+            .post(format!("{}/api/chat", self.local.base_url()))
             .json(&body)
             .send()
             .await?;
 
         let json: serde_json::Value = response.json().await?;
+        if let Some(error) = json["error"].as_str() {
+            anyhow::bail!("Ollama error: {}", error);
+        }
 
         let content = json["message"]["content"]
             .as_str()
             .unwrap_or("")
             .to_string();
 
-        // Ollama doesn't always provide token counts, so these may be None
+        // Prompt tokens are left out when Ollama reuses a cached prompt
+        let input_tokens = json["prompt_eval_count"].as_u64().map(|t| t as u32);
+        let output_tokens = json["eval_count"].as_u64().map(|t| t as u32);
+        let tokens_used = output_tokens.map(|ot| ot + input_tokens.unwrap_or(0));
+
         let response_model = json["model"].as_str().map(|s| s.to_string());
-        let finish_reason =
-            json["done"].as_bool().and_then(
-                |done| {
-                    if done {
-                        Some("stop".to_string())
-                    } else {
-                        None
-                    }
-                },
-            );
+        let finish_reason = json["done_reason"]
+            .as_str()
+            .map(|s| s.to_string())
+            .or_else(|| {
+                json["done"]
+                    .as_bool()
+                    .filter(|done| *done)
+                    .map(|_| "stop".to_string())
+            });
 
         Ok(ChatResponse {
             content,
@@ -495,9 +537,9 @@ impl LLMProvider for OllamaProvider {
             model: model_name,
             response_model,
             response_id: None, // Ollama doesn't provide response IDs
-            tokens_used: None,
-            input_tokens: None,
-            output_tokens: None,
+            tokens_used,
+            input_tokens,
+            output_tokens,
             finish_reason,
             duration_ms: start.elapsed().as_millis() as u32,
         })
@@ -505,18 +547,59 @@ impl LLMProvider for OllamaProvider {
 
     async fn stream_chat(
         &self,
-        _messages: Vec<ChatMessage>,
-        _model: Option<String>,
+        messages: Vec<ChatMessage>,
+        model: Option<String>,
     ) -> anyhow::Result<mpsc::Receiver<String>> {
         let (tx, rx) = mpsc::channel(100);
-        // Ollama streaming implementation would go here
-        // For now, send a placeholder and close the channel
-        drop(tx);
+        let model_name = model.unwrap_or_else(|| self.default_model());
+        let body = self.request_body(&messages, &model_name, true).await?;
+
+        let response = reqwest::Client::new()
+            .post(format!("{}/api/chat", self.local.base_url()))
+            .json(&body)
+            .send()
+            .await?;
+        if !response.status().is_success() {
+            let error = response.text().await.unwrap_or_default();
+            anyhow::bail!("Ollama error: {}", error);
+        }
+
+        tokio::spawn(async move {
+            use futures::StreamExt;
+            let mut lines = NdjsonLines::default();
+            let mut stream = response.bytes_stream();
+            while let Some(Ok(bytes)) = stream.next().await {
+                for line in lines.push(&bytes) {
+                    let Ok(chunk) = serde_json::from_str::<serde_json::Value>(&line) else {
+                        continue;
+                    };
+                    if let Some(content) = chunk["message"]["content"].as_str() {
+                        if !content.is_empty() && tx.send(content.to_string()).await.is_err() {
+                            return;
+                        }
+                    }
+                    if chunk["done"].as_bool() == Some(true) || chunk.get("error").is_some() {
+                        return;
+                    }
+                }
+            }
+        });
+
         Ok(rx)
     }
 
     fn list_models(&self) -> Vec<String> {
-        self.models.clone()
+        let installed = self.local.installed_names();
+        if installed.is_empty() {
+            // Suggestions until Ollama has been reached
+            vec![
+                "llama2".to_string(),
+                "mistral".to_string(),
+                "codellama".to_string(),
+            ]
+        } else {
+            installed
+        }
     }
 
     fn name(&self) -> &str {
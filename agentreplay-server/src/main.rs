@@ -36,6 +36,10 @@ struct Args {
     /// Enable authentication
     #[arg(long, env = "AGENTREPLAY_AUTH_ENABLED")]
     auth_enabled: bool,
+
+    /// Run as a warm standby applying the primary's shipped WAL
+    #[arg(long, env = "AGENTREPLAY_STANDBY")]
+    standby: bool,
 }
 
 #[tokio::main]
@@ -55,6 +59,9 @@ async fn main() -> Result<()> {
     if args.auth_enabled {
         config.auth.enabled = true;
     }
+    if args.standby {
+        config.replication.standby = true;
+    }
 
     // Run server
     run_server(config).await
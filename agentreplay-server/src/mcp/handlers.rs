@@ -21,6 +21,7 @@ use crate::api::AppState;
 use crate::mcp::protocol::*;
 use crate::mcp::tools::*;
 use agentreplay_index::CausalIndex;
+use agentreplay_query::TenantScope;
 use serde_json::json;
 use std::sync::Arc;
 use tracing::{info, warn};
@@ -29,14 +30,17 @@ use tracing::{info, warn};
 pub struct MCPHandler {
     state: AppState,
     causal_index: Arc<CausalIndex>,
+    /// The only tenant whose traces are read; MCP clients do not authenticate
+    tenant_id: u64,
 }
 
 impl MCPHandler {
-    /// Create a new MCP handler
-    pub fn new(state: AppState, causal_index: Arc<CausalIndex>) -> Self {
+    /// Create a new MCP handler serving the traces of `tenant_id`
+    pub fn new(state: AppState, causal_index: Arc<CausalIndex>, tenant_id: u64) -> Self {
         Self {
             state,
             causal_index,
+            tenant_id,
         }
     }
 
@@ -197,7 +201,12 @@ impl MCPHandler {
                     .unwrap_or(0);
                 let start = now.saturating_sub(86_400_000_000); // 24 hours
 
-                match self.state.db.query_temporal_range(start, now) {
+                match self
+                    .state
+                    .db
+                    .scoped(TenantScope::Tenant(self.tenant_id))
+                    .range(start, now)
+                {
                     Ok(edges) => {
                         let traces: Vec<serde_json::Value> = edges
                             .iter()
@@ -225,7 +234,12 @@ impl MCPHandler {
                     .unwrap_or(0);
                 let start = now.saturating_sub(86_400_000_000 * 7); // Last week
 
-                match self.state.db.query_temporal_range(start, now) {
+                match self
+                    .state
+                    .db
+                    .scoped(TenantScope::Tenant(self.tenant_id))
+                    .range(start, now)
+                {
                     Ok(edges) => {
                         let errors: Vec<serde_json::Value> = edges
                             .iter()
@@ -334,7 +348,13 @@ impl MCPHandler {
                     }
                 };
 
-                execute_search_traces(&self.state, search_params, self.causal_index.clone()).await
+                execute_search_traces(
+                    &self.state,
+                    self.tenant_id,
+                    search_params,
+                    self.causal_index.clone(),
+                )
+                .await
             }
 
             "get_context" => {
@@ -355,7 +375,13 @@ impl MCPHandler {
                     }
                 };
 
-                execute_get_context(&self.state, context_params, self.causal_index.clone()).await
+                execute_get_context(
+                    &self.state,
+                    self.tenant_id,
+                    context_params,
+                    self.causal_index.clone(),
+                )
+                .await
             }
 
             "get_trace_details" => {
@@ -365,7 +391,7 @@ impl MCPHandler {
                     .and_then(|v| v.as_str())
                     .unwrap_or("");
 
-                execute_get_trace_details(&self.state, edge_id).await
+                execute_get_trace_details(&self.state, self.tenant_id, edge_id).await
             }
 
             "get_related_traces" => {
@@ -387,6 +413,7 @@ impl MCPHandler {
 
                 execute_get_related_traces(
                     &self.state,
+                    self.tenant_id,
                     self.causal_index.clone(),
                     edge_id,
                     direction,
@@ -408,15 +435,14 @@ impl MCPHandler {
                     .map(|d| d.as_micros() as u64)
                     .unwrap_or(0);
 
-                let start = match time_range {
-                    "last_hour" => now.saturating_sub(3_600_000_000),
-                    "last_day" => now.saturating_sub(86_400_000_000),
-                    "last_week" => now.saturating_sub(86_400_000_000 * 7),
-                    "last_month" => now.saturating_sub(86_400_000_000 * 30),
-                    _ => now.saturating_sub(86_400_000_000),
-                };
+                let start = time_range_start(now, time_range);
 
-                match self.state.db.query_temporal_range(start, now) {
+                match self
+                    .state
+                    .db
+                    .scoped(TenantScope::Tenant(self.tenant_id))
+                    .range(start, now)
+                {
                     Ok(edges) => {
                         let total = edges.len();
                         let errors = edges
@@ -485,6 +511,124 @@ impl MCPHandler {
                 execute_save_memory(&self.state, content, collection, tags).await
             }
 
+            "search_memory" => {
+                let query = match call_params.arguments.get("query").and_then(|v| v.as_str()) {
+                    Some(q) => q,
+                    None => {
+                        return JsonRpcResponse::error(
+                            id,
+                            JsonRpcError::invalid_params("Missing query parameter"),
+                        );
+                    }
+                };
+                let collection = call_params
+                    .arguments
+                    .get("collection")
+                    .and_then(|v| v.as_str());
+                let limit = call_params
+                    .arguments
+                    .get("limit")
+                    .and_then(|v| v.as_u64())
+                    .unwrap_or(10)
+                    .clamp(1, 100) as usize;
+
+                execute_search_memory(&self.state, query, collection, limit).await
+            }
+
+            "get_cost_summary" => {
+                let time_range = call_params
+                    .arguments
+                    .get("time_range")
+                    .and_then(|v| v.as_str())
+                    .unwrap_or("last_day");
+                let group_by = call_params
+                    .arguments
+                    .get("group_by")
+                    .and_then(|v| v.as_str())
+                    .unwrap_or("model");
+
+                execute_get_cost_summary(&self.state, self.tenant_id, time_range, group_by).await
+            }
+
+            "list_failing_evals" => {
+                let time_range = call_params
+                    .arguments
+                    .get("time_range")
+                    .and_then(|v| v.as_str())
+                    .unwrap_or("last_week");
+                let dataset_id = match call_params
+                    .arguments
+                    .get("dataset_id")
+                    .and_then(|v| v.as_str())
+                {
+                    Some(hex) => match u128::from_str_radix(hex.trim_start_matches("0x"), 16) {
+                        Ok(id) => Some(id),
+                        Err(_) => {
+                            return JsonRpcResponse::error(
+                                id,
+                                JsonRpcError::invalid_params(format!(
+                                    "Invalid dataset_id: {}",
+                                    hex
+                                )),
+                            );
+                        }
+                    },
+                    None => None,
+                };
+                let limit = call_params
+                    .arguments
+                    .get("limit")
+                    .and_then(|v| v.as_u64())
+                    .map(|l| l.clamp(1, 100) as usize)
+                    .unwrap_or(DEFAULT_FAILING_EVALS_LIMIT);
+
+                execute_list_failing_evals(&self.state, time_range, dataset_id, limit).await
+            }
+
+            "compare_prompt_versions" => {
+                let prompt_name = match call_params
+                    .arguments
+                    .get("prompt_name")
+                    .and_then(|v| v.as_str())
+                {
+                    Some(name) => name,
+                    None => {
+                        return JsonRpcResponse::error(
+                            id,
+                            JsonRpcError::invalid_params("Missing prompt_name parameter"),
+                        );
+                    }
+                };
+                let versions: Vec<String> = call_params
+                    .arguments
+                    .get("versions")
+                    .and_then(|v| v.as_array())
+                    .map(|arr| {
+                        arr.iter()
+                            .filter_map(|v| match v {
+                                serde_json::Value::String(s) => Some(s.clone()),
+                                serde_json::Value::Number(n) => Some(n.to_string()),
+                                _ => None,
+                            })
+                            .collect()
+                    })
+                    .unwrap_or_default();
+                let time_range = call_params
+                    .arguments
+                    .get("time_range")
+                    .and_then(|v| v.as_str())
+                    .unwrap_or("last_week");
+
+                execute_compare_prompt_versions(
+                    &self.state,
+                    self.tenant_id,
+                    prompt_name,
+                    &versions,
+                    time_range,
+                )
+                .await
+            }
+
             _ => {
                 return JsonRpcResponse::error(
                     id,
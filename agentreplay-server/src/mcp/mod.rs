@@ -53,10 +53,11 @@
 //! ## Usage
 //!
 //! The MCP server runs on a configurable port (default 47101) and accepts
-//! JSON-RPC 2.0 messages over HTTP or WebSocket.
+//! JSON-RPC 2.0 messages over HTTP or WebSocket. Clients do not
+//! authenticate, so a server reads the traces of a single tenant.
 //!
 //! ```rust,ignore
-//! let mcp_server = MCPServer::new(app_state.clone());
+//! let mcp_server = MCPServer::new(app_state.clone(), causal_index, tenant_id);
 //! mcp_server.run(47101).await?;
 //! ```
 
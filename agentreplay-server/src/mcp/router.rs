@@ -23,9 +23,9 @@ use axum::Router;
 use agentreplay_index::CausalIndex;
 use std::sync::Arc;
 
-/// Create the MCP router with all endpoints
-pub fn mcp_router(app_state: AppState, causal_index: Arc<CausalIndex>) -> Router {
-    let mcp_server = MCPServer::new(app_state, causal_index);
+/// Create the MCP router with all endpoints, reading the data of `tenant_id`
+pub fn mcp_router(app_state: AppState, causal_index: Arc<CausalIndex>, tenant_id: u64) -> Router {
+    let mcp_server = MCPServer::new(app_state, causal_index, tenant_id);
     mcp_server.router()
 }
 
@@ -45,8 +45,8 @@ pub struct MCPServer {
 
 impl MCPServer {
     /// Create a new MCP server
-    pub fn new(app_state: AppState, causal_index: Arc<CausalIndex>) -> Self {
-        let handler = Arc::new(MCPHandler::new(app_state, causal_index));
+    pub fn new(app_state: AppState, causal_index: Arc<CausalIndex>, tenant_id: u64) -> Self {
+        let handler = Arc::new(MCPHandler::new(app_state, causal_index, tenant_id));
 
         Self {
             state: MCPServerState {
@@ -21,6 +21,9 @@
 //! - `get_context`: Retrieve relevant context for error resolution
 //! - `get_trace_details`: Get full details for a specific trace
 //! - `get_related_traces`: Get causally related traces from the graph
+//! - `search_memory`: Semantic search over saved memory observations
+//! - `get_cost_summary`, `list_failing_evals`, `compare_prompt_versions`:
+//!   Analytics over costs, evals and prompts (see [`analytics`])
 //!
 //! ## Project Isolation
 //!
@@ -30,14 +33,21 @@
 //!
 //! This ensures MCP's operations don't conflict with LLM observability tracing.
 
+pub mod analytics;
 pub mod registry;
 
+pub use analytics::{
+    execute_compare_prompt_versions, execute_get_cost_summary, execute_list_failing_evals,
+    time_range_start, DEFAULT_FAILING_EVALS_LIMIT,
+};
+
 use crate::api::AppState;
 use crate::mcp::context::{MCP_DEFAULT_PROJECT_ID, MCP_TENANT_ID};
 use crate::mcp::protocol::*;
 use crate::mcp::relevance::{BatchRelevanceScorer, RelevanceConfig};
 use agentreplay_index::embedding::{EmbeddingProvider, LocalEmbeddingProvider};
 use agentreplay_index::{CausalIndex, Embedding};
+use agentreplay_query::Agentreplay;
 use serde_json::json;
 use std::sync::Arc;
 
@@ -217,6 +227,115 @@ pub fn get_tool_definitions() -> Vec<Tool> {
                 "required": ["content"]
             }),
         },
+        Tool {
+            name: "search_memory".to_string(),
+            description: Some(
+                "Search memory observations previously saved with save_memory, ranked by \
+                 semantic similarity to the query."
+                    .to_string(),
+            ),
+            input_schema: json!({
+                "type": "object",
+                "properties": {
+                    "query": {
+                        "type": "string",
+                        "description": "Natural language search query"
+                    },
+                    "collection": {
+                        "type": "string",
+                        "description": "Only return memories from this collection (optional)"
+                    },
+                    "limit": {
+                        "type": "integer",
+                        "description": "Maximum number of results (default: 10)",
+                        "default": 10
+                    }
+                },
+                "required": ["query"]
+            }),
+        },
+        Tool {
+            name: "get_cost_summary".to_string(),
+            description: Some(
+                "Get LLM spend over a time range with request and token counts, grouped by \
+                 model, provider or project."
+                    .to_string(),
+            ),
+            input_schema: json!({
+                "type": "object",
+                "properties": {
+                    "time_range": {
+                        "type": "string",
+                        "enum": ["last_hour", "last_day", "last_week", "last_month"],
+                        "description": "Time range for the summary",
+                        "default": "last_day"
+                    },
+                    "group_by": {
+                        "type": "string",
+                        "enum": ["model", "provider", "project"],
+                        "description": "How to group costs",
+                        "default": "model"
+                    }
+                }
+            }),
+        },
+        Tool {
+            name: "list_failing_evals".to_string(),
+            description: Some(
+                "List the most recent failed eval results with their run, model, error, \
+                 failed graders and linked trace."
+                    .to_string(),
+            ),
+            input_schema: json!({
+                "type": "object",
+                "properties": {
+                    "time_range": {
+                        "type": "string",
+                        "enum": ["last_hour", "last_day", "last_week", "last_month"],
+                        "description": "Only include failures recorded in this range",
+                        "default": "last_week"
+                    },
+                    "dataset_id": {
+                        "type": "string",
+                        "description": "Only include runs of this dataset, in hex format (optional)"
+                    },
+                    "limit": {
+                        "type": "integer",
+                        "description": "Maximum number of failures (default: 20)",
+                        "default": 20
+                    }
+                }
+            }),
+        },
+        Tool {
+            name: "compare_prompt_versions".to_string(),
+            description: Some(
+                "Compare versions of a prompt by traffic, error rate, latency, tokens and cost, \
+                 using spans tagged with agentreplay.prompt.name and agentreplay.prompt.version."
+                    .to_string(),
+            ),
+            input_schema: json!({
+                "type": "object",
+                "properties": {
+                    "prompt_name": {
+                        "type": "string",
+                        "description": "Name of the prompt template"
+                    },
+                    "versions": {
+                        "type": "array",
+                        "items": { "type": "string" },
+                        "description": "Versions to compare (optional, default: all seen)"
+                    },
+                    "time_range": {
+                        "type": "string",
+                        "enum": ["last_hour", "last_day", "last_week", "last_month"],
+                        "description": "Time range to compare over",
+                        "default": "last_week"
+                    }
+                },
+                "required": ["prompt_name"]
+            }),
+        },
     ]
 }
 
@@ -228,6 +347,7 @@ pub fn get_tool_definitions() -> Vec<Tool> {
 /// Then applies multi-signal relevance scoring (semantic + temporal + graph)
 pub async fn execute_search_traces(
     state: &AppState,
+    tenant_id: u64,
     params: TraceSearchParams,
     causal_index: Arc<CausalIndex>,
 ) -> Result<CallToolResult, String> {
@@ -247,10 +367,11 @@ pub async fn execute_search_traces(
     // - Phase 1: HNSW approximate search fetches 3x candidates (handled internally)
     // - Phase 2: Rerank with exact distances (handled by SemanticSearchEngine)
     // We fetch 2x limit for additional multi-signal filtering
-    let edges = state
+    let mut edges = state
         .db
         .semantic_search(&query_embedding, limit * 2)
         .map_err(|e| format!("Semantic search failed: {}", e))?;
+    edges.retain(|edge| edge.tenant_id == tenant_id);
 
     if edges.is_empty() {
         return Ok(CallToolResult {
@@ -361,6 +482,7 @@ pub async fn execute_search_traces(
 /// Execute the get_context tool
 pub async fn execute_get_context(
     state: &AppState,
+    tenant_id: u64,
     params: GetContextParams,
     causal_index: Arc<CausalIndex>,
 ) -> Result<CallToolResult, String> {
@@ -377,10 +499,11 @@ pub async fn execute_get_context(
     let query_embedding = Embedding::from_vec(query_vec);
 
     // Search for relevant traces
-    let edges = state
+    let mut edges = state
         .db
         .semantic_search(&query_embedding, limit * 3)
         .map_err(|e| format!("Search failed: {}", e))?;
+    edges.retain(|edge| edge.tenant_id == tenant_id);
 
     let mut items: Vec<ContextItem> = Vec::new();
 
@@ -457,6 +580,7 @@ pub async fn execute_get_context(
 /// Execute the get_trace_details tool
 pub async fn execute_get_trace_details(
     state: &AppState,
+    tenant_id: u64,
     edge_id_str: &str,
 ) -> Result<CallToolResult, String> {
     // Parse edge ID (remove 0x prefix if present)
@@ -469,6 +593,7 @@ pub async fn execute_get_trace_details(
         .db
         .get(edge_id)
         .map_err(|e| format!("Failed to get trace: {}", e))?
+        .filter(|edge| edge.tenant_id == tenant_id)
         .ok_or_else(|| format!("Trace not found: {}", edge_id_str))?;
 
     // Get payload
@@ -509,7 +634,8 @@ pub async fn execute_get_trace_details(
 
 /// Execute the get_related_traces tool
 pub async fn execute_get_related_traces(
-    _state: &AppState,
+    state: &AppState,
+    tenant_id: u64,
     causal_index: Arc<CausalIndex>,
     edge_id_str: &str,
     direction: &str,
@@ -520,6 +646,14 @@ pub async fn execute_get_related_traces(
     let edge_id = u128::from_str_radix(edge_id_clean, 16)
         .map_err(|_| format!("Invalid edge ID format: {}", edge_id_str))?;
 
+    // Spans of a trace share its tenant, so checking the start is enough
+    state
+        .db
+        .get(edge_id)
+        .map_err(|e| format!("Failed to get trace: {}", e))?
+        .filter(|edge| edge.tenant_id == tenant_id)
+        .ok_or_else(|| format!("Trace not found: {}", edge_id_str))?;
+
     let mut ancestors: Vec<String> = Vec::new();
     let mut descendants: Vec<String> = Vec::new();
 
@@ -585,14 +719,8 @@ fn estimate_cost(tokens: u32) -> f64 {
     (tokens as f64 / 1000.0) * PRICE_PER_1K_TOKENS
 }
 
-/// Execute the save_memory tool
-pub async fn execute_save_memory(
-    state: &AppState,
-    content: String,
-    collection: String,
-    tags: Option<Vec<String>>,
-) -> Result<CallToolResult, String> {
-    // Get the MCP-specific database (Project 1000)
+/// Open the MCP-specific memory database (Project 1000)
+fn memory_db(state: &AppState) -> Result<Arc<Agentreplay>, String> {
     // We need ProjectManager to get the correct DB handle
     let (project_manager, project_registry) = match (
         state.project_manager.as_ref(),
@@ -604,9 +732,19 @@ pub async fn execute_save_memory(
 
     let ctx = crate::mcp::MCPContext::new(project_manager, project_registry)
         .map_err(|e| format!("Failed to initialize MCP context: {}", e))?;
-        
-    let db = ctx.db()
-        .ok_or_else(|| "Failed to open MCP database".to_string())?;
+
+    ctx.db()
+        .ok_or_else(|| "Failed to open MCP database".to_string())
+}
+
+/// Execute the save_memory tool
+pub async fn execute_save_memory(
+    state: &AppState,
+    content: String,
+    collection: String,
+    tags: Option<Vec<String>>,
+) -> Result<CallToolResult, String> {
+    let db = memory_db(state)?;
 
     // 1. Generate embedding
     let provider = LocalEmbeddingProvider::default_provider()
@@ -632,7 +770,7 @@ pub async fn execute_save_memory(
     // 3. Prepare payload
     let mut metadata = serde_json::Map::new();
     metadata.insert("source".to_string(), json!("mcp_tool"));
-    if let Some(t) = tags {
+    if let Some(t) = &tags {
         metadata.insert("tags".to_string(), json!(t));
     }
     
@@ -655,6 +793,13 @@ pub async fn execute_save_memory(
     db
         .put_payload(edge.edge_id, &payload_bytes)
         .map_err(|e| format!("Payload storage failed: {}", e))?;
+
+    state.knowledge_graph.record_observation(
+        MCP_TENANT_ID,
+        edge.edge_id,
+        &content,
+        tags.as_deref().unwrap_or_default(),
+    );
         
     let result = json!({
         "success": true,
@@ -670,3 +815,66 @@ pub async fn execute_save_memory(
         is_error: None,
     })
 }
+
+/// Execute the search_memory tool
+pub async fn execute_search_memory(
+    state: &AppState,
+    query: &str,
+    collection: Option<&str>,
+    limit: usize,
+) -> Result<CallToolResult, String> {
+    let db = memory_db(state)?;
+
+    let provider = LocalEmbeddingProvider::default_provider()
+        .map_err(|e| format!("Failed to initialize embedding provider: {}", e))?;
+    let query_vec = provider
+        .embed(query)
+        .map_err(|e| format!("Embedding generation failed: {}", e))?;
+
+    // Over-fetch when filtering so a busy collection doesn't crowd out the target
+    let k = if collection.is_some() { limit * 4 } else { limit };
+    let edges = db
+        .semantic_search(&Embedding::from_vec(query_vec), k)
+        .map_err(|e| format!("Memory search failed: {}", e))?;
+
+    let mut memories = Vec::new();
+    for edge in edges {
+        let Ok(Some(bytes)) = db.get_payload(edge.edge_id) else {
+            continue;
+        };
+        let Ok(payload) = serde_json::from_slice::<serde_json::Value>(&bytes) else {
+            continue;
+        };
+        let memory_collection = payload
+            .get("collection")
+            .and_then(|c| c.as_str())
+            .unwrap_or("default");
+        if collection.is_some_and(|c| c != memory_collection) {
+            continue;
+        }
+
+        memories.push(json!({
+            "memory_id": format!("{:#x}", edge.edge_id),
+            "content": payload.get("content").and_then(|c| c.as_str()).unwrap_or(""),
+            "collection": memory_collection,
+            "tags": payload.pointer("/metadata/tags").cloned().unwrap_or(json!([])),
+            "timestamp_us": edge.timestamp_us,
+        }));
+        if memories.len() >= limit {
+            break;
+        }
+    }
+
+    let result = json!({
+        "query": query,
+        "count": memories.len(),
+        "memories": memories,
+    });
+
+    Ok(CallToolResult {
+        content: vec![ToolContent::Text {
+            text: result.to_string(),
+        }],
+        is_error: None,
+    })
+}
@@ -0,0 +1,400 @@
+// Copyright 2025 AgentReplay (https://github.com/agentreplay)
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program. If not, see <https://www.gnu.org/licenses/>.
+
+//! Analytics tools exposed via MCP
+//!
+//! Lets MCP clients ask questions about agent behaviour rather than
+//! individual traces:
+//!
+//! - `get_cost_summary`: Spend over a time range, grouped by model, provider or project
+//! - `list_failing_evals`: Most recent failed eval run results
+//! - `compare_prompt_versions`: Request, error, latency and cost stats per prompt version
+//!
+//! These tools read the observability data of the tenant the MCP server is
+//! bound to (`mcp.tenant_id`), not the isolated MCP memory project.
+
+use crate::api::cost::{get_detailed_cost_breakdown, CostBreakdownQuery};
+use crate::api::AppState;
+use crate::auth::{AuthContext, Role};
+use crate::mcp::protocol::{CallToolResult, ToolContent};
+use crate::otel_genai::{GenAIPayload, ModelPricing};
+use crate::session_analysis::{attr_string, ATTR_PROMPT_NAME, ATTR_PROMPT_VERSION};
+use agentreplay_core::clock::now_us;
+use agentreplay_core::eval_dataset::EvalRun;
+use agentreplay_core::SpanType;
+use agentreplay_query::TenantScope;
+use axum::extract::{Extension, Query, State};
+use serde::Serialize;
+use serde_json::json;
+use std::collections::BTreeMap;
+
+const HOUR_US: u64 = 3_600_000_000;
+const DAY_US: u64 = 24 * HOUR_US;
+
+/// Default number of failed eval results returned
+pub const DEFAULT_FAILING_EVALS_LIMIT: usize = 20;
+
+/// Start of a named time range (`last_hour`, `last_day`, `last_week`,
+/// `last_month`) ending at `now_us`. Unknown names fall back to `last_day`.
+pub fn time_range_start(now_us: u64, time_range: &str) -> u64 {
+    let span = match time_range {
+        "last_hour" => HOUR_US,
+        "last_week" => 7 * DAY_US,
+        "last_month" => 30 * DAY_US,
+        _ => DAY_US,
+    };
+    now_us.saturating_sub(span)
+}
+
+fn text_result(value: serde_json::Value) -> CallToolResult {
+    CallToolResult {
+        content: vec![ToolContent::Text {
+            text: value.to_string(),
+        }],
+        is_error: None,
+    }
+}
+
+/// Execute the get_cost_summary tool
+pub async fn execute_get_cost_summary(
+    state: &AppState,
+    tenant_id: u64,
+    time_range: &str,
+    group_by: &str,
+) -> Result<CallToolResult, String> {
+    if !matches!(group_by, "model" | "provider" | "project") {
+        return Err(format!(
+            "Invalid group_by '{}': expected model, provider or project",
+            group_by
+        ));
+    }
+
+    let end_ts = now_us();
+    let query = CostBreakdownQuery {
+        start_ts: time_range_start(end_ts, time_range),
+        end_ts,
+        group_by: vec![group_by.to_string()],
+        currency: None,
+    };
+    let auth = AuthContext {
+        tenant_id,
+        project_id: None,
+        user_id: None,
+        role: Role::Viewer,
+    };
+    let breakdown =
+        get_detailed_cost_breakdown(State(state.clone()), Extension(auth), Query(query))
+            .await
+            .map_err(|e| format!("Failed to compute cost summary: {}", e))?
+            .0;
+
+    let request_count: u64 = breakdown.breakdown.iter().map(|g| g.request_count).sum();
+    let token_count: u64 = breakdown.breakdown.iter().map(|g| g.token_count).sum();
+
+    Ok(text_result(json!({
+        "time_range": time_range,
+        "group_by": group_by,
+        "total_cost": breakdown.total_cost,
+        "currency": breakdown.currency,
+        "request_count": request_count,
+        "token_count": token_count,
+        "groups": breakdown.breakdown,
+    })))
+}
+
+/// A failed test case from an eval run
+#[derive(Debug, Clone, Serialize)]
+pub struct FailingEval {
+    pub run_id: String,
+    pub run_name: String,
+    pub dataset_id: String,
+    pub model: String,
+    pub agent_id: String,
+    pub test_case_id: String,
+    pub trace_id: Option<String>,
+    pub error: Option<String>,
+    pub failed_graders: Vec<String>,
+    pub composite_score: Option<f64>,
+    pub eval_metrics: BTreeMap<String, f64>,
+    pub timestamp_us: u64,
+}
+
+/// Failed results recorded at or after `since_us`, newest first
+pub fn collect_failing_evals(runs: &[EvalRun], since_us: u64, limit: usize) -> Vec<FailingEval> {
+    let mut failures: Vec<FailingEval> = runs
+        .iter()
+        .flat_map(|run| {
+            run.results
+                .iter()
+                .filter(move |r| !r.passed && r.timestamp_us >= since_us)
+                .map(move |r| FailingEval {
+                    run_id: format!("0x{:x}", run.id),
+                    run_name: run.name.clone(),
+                    dataset_id: format!("0x{:x}", run.dataset_id),
+                    model: run.model.clone(),
+                    agent_id: run.agent_id.clone(),
+                    test_case_id: format!("0x{:x}", r.test_case_id),
+                    trace_id: r.trace_id.map(|id| format!("0x{:x}", id)),
+                    error: r.error.clone(),
+                    failed_graders: r
+                        .grader_results
+                        .iter()
+                        .filter(|g| !g.passed)
+                        .map(|g| g.grader_id.clone())
+                        .collect(),
+                    composite_score: r.overall.as_ref().and_then(|o| o.composite_score),
+                    eval_metrics: r
+                        .eval_metrics
+                        .iter()
+                        .map(|(k, v)| (k.clone(), *v))
+                        .collect(),
+                    timestamp_us: r.timestamp_us,
+                })
+        })
+        .collect();
+
+    failures.sort_by(|a, b| b.timestamp_us.cmp(&a.timestamp_us));
+    failures.truncate(limit);
+    failures
+}
+
+/// Execute the list_failing_evals tool
+pub async fn execute_list_failing_evals(
+    state: &AppState,
+    time_range: &str,
+    dataset_id: Option<u128>,
+    limit: usize,
+) -> Result<CallToolResult, String> {
+    let runs = state
+        .db
+        .list_eval_runs(dataset_id)
+        .map_err(|e| format!("Failed to list eval runs: {}", e))?;
+
+    let since = time_range_start(now_us(), time_range);
+    let failures = collect_failing_evals(&runs, since, limit);
+    let mut failing_runs: Vec<&str> = failures.iter().map(|f| f.run_id.as_str()).collect();
+    failing_runs.sort_unstable();
+    failing_runs.dedup();
+
+    Ok(text_result(json!({
+        "time_range": time_range,
+        "runs_with_failures": failing_runs.len(),
+        "count": failures.len(),
+        "failures": failures,
+    })))
+}
+
+/// Aggregated traffic for one prompt version
+#[derive(Debug, Clone, Default, Serialize, PartialEq)]
+pub struct PromptVersionStats {
+    pub version: String,
+    pub requests: u64,
+    pub errors: u64,
+    pub error_rate: f64,
+    pub avg_latency_ms: f64,
+    pub total_tokens: u64,
+    pub total_cost: f64,
+    pub avg_cost: f64,
+    #[serde(skip)]
+    total_duration_us: u64,
+}
+
+impl PromptVersionStats {
+    fn record(&mut self, is_error: bool, duration_us: u64, tokens: u64, cost: f64) {
+        self.requests += 1;
+        self.errors += is_error as u64;
+        self.total_duration_us += duration_us;
+        self.total_tokens += tokens;
+        self.total_cost += cost;
+    }
+
+    fn finish(mut self) -> Self {
+        if self.requests > 0 {
+            let requests = self.requests as f64;
+            self.error_rate = self.errors as f64 / requests;
+            self.avg_latency_ms = self.total_duration_us as f64 / requests / 1000.0;
+            self.avg_cost = self.total_cost / requests;
+        }
+        self
+    }
+}
+
+/// Accumulates spans tagged with a prompt name into per-version stats
+#[derive(Debug, Default)]
+pub struct PromptVersionComparison {
+    versions: BTreeMap<String, PromptVersionStats>,
+}
+
+impl PromptVersionComparison {
+    pub fn record(
+        &mut self,
+        version: &str,
+        is_error: bool,
+        duration_us: u64,
+        tokens: u64,
+        cost: f64,
+    ) {
+        self.versions
+            .entry(version.to_string())
+            .or_insert_with(|| PromptVersionStats {
+                version: version.to_string(),
+                ..Default::default()
+            })
+            .record(is_error, duration_us, tokens, cost);
+    }
+
+    /// Stats ordered by version, restricted to `only` when non-empty
+    pub fn finish(self, only: &[String]) -> Vec<PromptVersionStats> {
+        self.versions
+            .into_values()
+            .filter(|stats| only.is_empty() || only.contains(&stats.version))
+            .map(PromptVersionStats::finish)
+            .collect()
+    }
+}
+
+/// Execute the compare_prompt_versions tool
+///
+/// Spans are matched on the `agentreplay.prompt.name` attribute and grouped
+/// by `agentreplay.prompt.version`.
+pub async fn execute_compare_prompt_versions(
+    state: &AppState,
+    tenant_id: u64,
+    prompt_name: &str,
+    versions: &[String],
+    time_range: &str,
+) -> Result<CallToolResult, String> {
+    let end = now_us();
+    let start = time_range_start(end, time_range);
+    let edges = state
+        .db
+        .scoped(TenantScope::Tenant(tenant_id))
+        .range(start, end)
+        .map_err(|e| format!("Failed to query traces: {}", e))?;
+
+    let mut comparison = PromptVersionComparison::default();
+    for edge in edges {
+        let Ok(Some(bytes)) = state.db.get_payload(edge.edge_id) else {
+            continue;
+        };
+        let Ok(payload) = serde_json::from_slice::<GenAIPayload>(&bytes) else {
+            continue;
+        };
+        if attr_string(&payload, ATTR_PROMPT_NAME).as_deref() != Some(prompt_name) {
+            continue;
+        }
+
+        let version =
+            attr_string(&payload, ATTR_PROMPT_VERSION).unwrap_or_else(|| "unknown".to_string());
+        let system = payload.system.clone().unwrap_or_default();
+        let model = payload
+            .response_model
+            .clone()
+            .or_else(|| payload.request_model.clone())
+            .unwrap_or_default();
+        let cost = payload.calculate_cost(&ModelPricing::for_model(&system, &model));
+        comparison.record(
+            &version,
+            matches!(edge.get_span_type(), SpanType::Error),
+            edge.duration_us as u64,
+            edge.token_count as u64,
+            cost,
+        );
+    }
+
+    let current_version = state
+        .db
+        .list_prompt_templates()
+        .map_err(|e| format!("Failed to list prompts: {}", e))?
+        .into_iter()
+        .find(|p| p.name == prompt_name)
+        .map(|p| p.version);
+
+    Ok(text_result(json!({
+        "prompt_name": prompt_name,
+        "time_range": time_range,
+        "current_version": current_version,
+        "versions": comparison.finish(versions),
+    })))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use agentreplay_core::eval_dataset::{GraderResult, RunResult};
+
+    #[test]
+    fn test_time_range_start() {
+        let now = 100 * DAY_US;
+        assert_eq!(time_range_start(now, "last_hour"), now - HOUR_US);
+        assert_eq!(time_range_start(now, "last_week"), now - 7 * DAY_US);
+        assert_eq!(time_range_start(now, "bogus"), now - DAY_US);
+        assert_eq!(time_range_start(HOUR_US, "last_month"), 0);
+    }
+
+    #[test]
+    fn test_collect_failing_evals() {
+        let mut run = EvalRun::new(1, 2, "nightly".into(), "agent".into(), "gpt-4o".into(), 0);
+        run.add_result(RunResult::success(10, 100, 50));
+        run.add_result(RunResult::failure(11, "timeout".into(), 5));
+        let mut graded = RunResult::success(12, 101, 60);
+        graded.passed = false;
+        graded.grader_results.push(GraderResult {
+            grader_id: "faithfulness".into(),
+            grader_type: "llm_judge".into(),
+            weight: None,
+            score: Some(0.2),
+            passed: false,
+            assertions: vec![],
+            judge_votes: vec![],
+            rationale: None,
+            evidence_refs: vec![],
+        });
+        run.add_result(graded);
+
+        let failures = collect_failing_evals(&[run.clone()], 0, 10);
+        assert_eq!(failures.len(), 2);
+        assert_eq!(failures[0].test_case_id, "0xc");
+        assert_eq!(failures[0].failed_graders, vec!["faithfulness".to_string()]);
+        assert_eq!(failures[1].error.as_deref(), Some("timeout"));
+
+        assert_eq!(collect_failing_evals(&[run.clone()], 10, 10).len(), 1);
+        assert_eq!(collect_failing_evals(&[run], 0, 1).len(), 1);
+    }
+
+    #[test]
+    fn test_prompt_version_comparison() {
+        let mut comparison = PromptVersionComparison::default();
+        comparison.record("1", false, 2_000, 100, 0.01);
+        comparison.record("1", true, 4_000, 300, 0.03);
+        comparison.record("2", false, 1_000, 50, 0.005);
+
+        let stats = comparison.finish(&[]);
+        assert_eq!(stats.len(), 2);
+        assert_eq!(stats[0].version, "1");
+        assert_eq!(stats[0].requests, 2);
+        assert!((stats[0].error_rate - 0.5).abs() < 1e-9);
+        assert!((stats[0].avg_latency_ms - 3.0).abs() < 1e-9);
+        assert!((stats[0].avg_cost - 0.02).abs() < 1e-9);
+        assert_eq!(stats[1].total_tokens, 50);
+
+        let mut comparison = PromptVersionComparison::default();
+        comparison.record("1", false, 0, 0, 0.0);
+        comparison.record("2", false, 0, 0, 0.0);
+        let stats = comparison.finish(&["2".to_string()]);
+        assert_eq!(stats.len(), 1);
+        assert_eq!(stats[0].version, "2");
+    }
+}
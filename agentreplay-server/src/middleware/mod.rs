@@ -15,6 +15,10 @@
 
 pub mod compression;
 pub mod rate_limit;
+pub mod request_metrics;
 
 pub use compression::{compression_layer, decompress_request_middleware};
-pub use rate_limit::{rate_limit_middleware, RateLimiter};
+pub use rate_limit::{
+    rate_limit_middleware, route_rate_limit_middleware, RateLimiter, RouteRateLimiter,
+};
+pub use request_metrics::{request_metrics_middleware, RequestMetrics};
@@ -15,17 +15,24 @@
 
 use axum::{
     body::Body,
-    extract::State,
-    http::{Request, StatusCode},
+    extract::{ConnectInfo, State},
+    http::{HeaderValue, Method, Request, StatusCode},
     middleware::Next,
     response::{IntoResponse, Response},
     Extension,
 };
 use dashmap::DashMap;
+use moka::sync::Cache;
+use std::net::{IpAddr, SocketAddr};
+use std::sync::atomic::{AtomicU64, Ordering};
 use std::sync::Arc;
 use std::time::{Duration, Instant};
 
+use crate::api::error::{ErrorCode, ProblemDetails};
+use crate::api::AppState;
+use crate::auth::rate_limit::TokenBucket;
 use crate::auth::AuthContext;
+use crate::config::{RouteBudget, RouteRateLimitConfig};
 
 /// Rate limiter state
 #[derive(Clone)]
@@ -132,22 +139,260 @@ pub async fn rate_limit_middleware(
         Err(retry_after) => {
             // Rate limited
             (
-                StatusCode::TOO_MANY_REQUESTS,
                 [
-                    ("Retry-After", retry_after.to_string()),
                     (
                         "X-RateLimit-Limit",
                         limiter.max_requests_per_minute.to_string(),
                     ),
                     ("X-RateLimit-Remaining", "0".to_string()),
                 ],
-                format!("Rate limit exceeded. Retry after {} seconds", retry_after),
+                ProblemDetails::new(
+                    StatusCode::TOO_MANY_REQUESTS,
+                    ErrorCode::RateLimited,
+                    format!("Rate limit exceeded. Retry after {} seconds", retry_after),
+                )
+                .with_retry_after_ms(retry_after * 1000),
             )
                 .into_response()
         }
     }
 }
 
+/// Budget class of a route
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum RouteClass {
+    Ingestion,
+    Query,
+    Llm,
+}
+
+impl RouteClass {
+    pub const ALL: [RouteClass; 3] = [RouteClass::Ingestion, RouteClass::Query, RouteClass::Llm];
+
+    pub fn classify(method: &Method, path: &str) -> Self {
+        if *method != Method::POST {
+            return RouteClass::Query;
+        }
+        if path == "/api/v1/traces" || path == "/api/v1/traces/otel" || path.ends_with("/import") {
+            RouteClass::Ingestion
+        } else if path.starts_with("/api/v1/chat/") || path.ends_with("/playground") {
+            RouteClass::Llm
+        } else {
+            RouteClass::Query
+        }
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            RouteClass::Ingestion => "ingestion",
+            RouteClass::Query => "query",
+            RouteClass::Llm => "llm",
+        }
+    }
+
+    fn index(&self) -> usize {
+        *self as usize
+    }
+}
+
+/// Outcome of a route rate limit check
+#[derive(Debug, Clone, Copy)]
+pub struct RouteLimitDecision {
+    pub allowed: bool,
+    pub limit: u32,
+    pub remaining: u32,
+    /// Until the bucket is full again, or until the next request is
+    /// allowed when limited
+    pub reset_after: Duration,
+}
+
+#[derive(Default)]
+struct ClassCounters {
+    allowed: AtomicU64,
+    limited: AtomicU64,
+}
+
+/// Request counters of one route class
+#[derive(Debug, Clone, Copy)]
+pub struct RouteLimitStats {
+    pub class: RouteClass,
+    pub allowed: u64,
+    pub limited: u64,
+}
+
+/// Token-bucket rate limiter with separate budgets per client and route class
+pub struct RouteRateLimiter {
+    config: RouteRateLimitConfig,
+    buckets: Cache<(RouteClass, String), Arc<TokenBucket>>,
+    counters: [ClassCounters; 3],
+}
+
+impl Default for RouteRateLimiter {
+    fn default() -> Self {
+        Self::new(RouteRateLimitConfig::default())
+    }
+}
+
+impl RouteRateLimiter {
+    pub fn new(config: RouteRateLimitConfig) -> Self {
+        let buckets = Cache::builder()
+            .max_capacity(config.max_clients)
+            // A bucket idle this long has refilled, so dropping it is lossless
+            .time_to_idle(
+                RouteClass::ALL
+                    .iter()
+                    .map(|class| refill_window(config.budget(*class)))
+                    .max()
+                    .unwrap_or_default()
+                    .max(Duration::from_secs(60)),
+            )
+            .build();
+        Self {
+            config,
+            buckets,
+            counters: Default::default(),
+        }
+    }
+
+    pub fn enabled(&self) -> bool {
+        self.config.enabled
+    }
+
+    /// Take one request from `client`'s budget for `class`
+    pub fn check(&self, class: RouteClass, client: &str) -> RouteLimitDecision {
+        let budget = self.config.budget(class);
+        let bucket = self.buckets.get_with((class, client.to_string()), || {
+            Arc::new(TokenBucket::new(budget.burst, refill_window(budget)))
+        });
+        let counters = &self.counters[class.index()];
+        if bucket.try_consume() {
+            counters.allowed.fetch_add(1, Ordering::Relaxed);
+            RouteLimitDecision {
+                allowed: true,
+                limit: budget.burst,
+                remaining: bucket.remaining(),
+                reset_after: bucket.reset_after(),
+            }
+        } else {
+            counters.limited.fetch_add(1, Ordering::Relaxed);
+            RouteLimitDecision {
+                allowed: false,
+                limit: budget.burst,
+                remaining: 0,
+                reset_after: bucket.retry_after(),
+            }
+        }
+    }
+
+    pub fn stats(&self) -> Vec<RouteLimitStats> {
+        RouteClass::ALL
+            .iter()
+            .map(|class| {
+                let counters = &self.counters[class.index()];
+                RouteLimitStats {
+                    class: *class,
+                    allowed: counters.allowed.load(Ordering::Relaxed),
+                    limited: counters.limited.load(Ordering::Relaxed),
+                }
+            })
+            .collect()
+    }
+
+    /// Clients with at least one live bucket
+    pub fn tracked_buckets(&self) -> u64 {
+        self.buckets.entry_count()
+    }
+}
+
+impl RouteRateLimitConfig {
+    pub fn budget(&self, class: RouteClass) -> RouteBudget {
+        match class {
+            RouteClass::Ingestion => self.ingestion,
+            RouteClass::Query => self.query,
+            RouteClass::Llm => self.llm,
+        }
+    }
+}
+
+/// Time for an empty bucket to refill to `burst`
+fn refill_window(budget: RouteBudget) -> Duration {
+    Duration::from_secs_f64(budget.burst as f64 * 60.0 / budget.requests_per_minute.max(1) as f64)
+}
+
+/// Budget key of the caller: its token, user or API key when
+/// authenticated, else the peer address it connected from, else its tenant
+///
+/// Unverified headers never pick the key, so without authentication a
+/// client cannot get a fresh budget by sending a made-up API key, nor
+/// exhaust everyone else's.
+pub(crate) fn client_key(auth: &AuthContext, peer: Option<IpAddr>) -> String {
+    match (&auth.user_id, peer) {
+        (Some(user_id), _) => format!("{}/{}", auth.tenant_id, user_id),
+        (None, Some(peer)) => format!("{}/ip:{}", auth.tenant_id, peer),
+        (None, None) => auth.tenant_id.to_string(),
+    }
+}
+
+fn header_value(value: impl ToString) -> HeaderValue {
+    HeaderValue::from_str(&value.to_string()).unwrap_or_else(|_| HeaderValue::from_static("0"))
+}
+
+/// Per-client, per-route-class rate limiting
+///
+/// Runs after authentication. Every response carries `X-RateLimit-Limit`,
+/// `X-RateLimit-Remaining` and `X-RateLimit-Reset` (seconds); limited
+/// requests get 429 with `Retry-After`.
+pub async fn route_rate_limit_middleware(
+    State(state): State<AppState>,
+    request: Request<Body>,
+    next: Next,
+) -> Response {
+    let limiter = &state.rate_limiter;
+    if !limiter.enabled() {
+        return next.run(request).await;
+    }
+    let peer = request
+        .extensions()
+        .get::<ConnectInfo<SocketAddr>>()
+        .map(|ConnectInfo(addr)| addr.ip());
+    let Some((tenant_id, client)) = request
+        .extensions()
+        .get::<AuthContext>()
+        .map(|auth| (auth.tenant_id, client_key(auth, peer)))
+    else {
+        return next.run(request).await;
+    };
+    let class = RouteClass::classify(request.method(), request.uri().path());
+    let decision = limiter.check(class, &client);
+    let reset_secs = decision.reset_after.as_secs_f64().ceil() as u64;
+
+    let mut response = if decision.allowed {
+        next.run(request).await
+    } else {
+        tracing::debug!(
+            "Rate limited {} request for tenant {}",
+            class.as_str(),
+            tenant_id
+        );
+        ProblemDetails::new(
+            StatusCode::TOO_MANY_REQUESTS,
+            ErrorCode::RateLimited,
+            format!(
+                "Rate limit exceeded for {} requests. Retry after {} seconds",
+                class.as_str(),
+                reset_secs
+            ),
+        )
+        .with_retry_after_ms(reset_secs * 1000)
+        .into_response()
+    };
+    let headers = response.headers_mut();
+    headers.insert("X-RateLimit-Limit", header_value(decision.limit));
+    headers.insert("X-RateLimit-Remaining", header_value(decision.remaining));
+    headers.insert("X-RateLimit-Reset", header_value(reset_secs));
+    response
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -201,4 +446,72 @@ mod tests {
         limiter.check_limit(1).unwrap();
         assert_eq!(limiter.get_remaining(1), 8);
     }
+
+    #[test]
+    fn test_route_classes() {
+        assert_eq!(
+            RouteClass::classify(&Method::POST, "/api/v1/traces"),
+            RouteClass::Ingestion
+        );
+        assert_eq!(
+            RouteClass::classify(&Method::GET, "/api/v1/traces"),
+            RouteClass::Query
+        );
+        assert_eq!(
+            RouteClass::classify(&Method::POST, "/api/v1/evals/runs/import"),
+            RouteClass::Ingestion
+        );
+        assert_eq!(
+            RouteClass::classify(&Method::POST, "/api/v1/chat/completions"),
+            RouteClass::Llm
+        );
+        assert_eq!(
+            RouteClass::classify(&Method::POST, "/api/v1/query/aggregate"),
+            RouteClass::Query
+        );
+    }
+
+    #[test]
+    fn test_route_budgets_are_separate() {
+        let limiter = RouteRateLimiter::new(RouteRateLimitConfig {
+            enabled: true,
+            llm: RouteBudget {
+                requests_per_minute: 1,
+                burst: 2,
+            },
+            ..Default::default()
+        });
+
+        assert!(limiter.check(RouteClass::Llm, "1/a").allowed);
+        let second = limiter.check(RouteClass::Llm, "1/a");
+        assert!(second.allowed);
+        assert_eq!(second.limit, 2);
+        assert_eq!(second.remaining, 0);
+        let limited = limiter.check(RouteClass::Llm, "1/a");
+        assert!(!limited.allowed);
+        assert!(limited.reset_after > Duration::from_secs(1));
+
+        // Other classes and other clients keep their own budgets
+        assert!(limiter.check(RouteClass::Query, "1/a").allowed);
+        assert!(limiter.check(RouteClass::Llm, "1/b").allowed);
+
+        let llm = limiter.stats()[RouteClass::Llm.index()];
+        assert_eq!((llm.allowed, llm.limited), (3, 1));
+    }
+
+    #[test]
+    fn test_client_key() {
+        let mut auth = AuthContext {
+            tenant_id: 5,
+            project_id: None,
+            user_id: None,
+            role: Default::default(),
+        };
+        let peer: IpAddr = "10.0.0.7".parse().unwrap();
+        assert_eq!(client_key(&auth, None), "5");
+        assert_eq!(client_key(&auth, Some(peer)), "5/ip:10.0.0.7");
+
+        auth.user_id = Some("token:abc".to_string());
+        assert_eq!(client_key(&auth, Some(peer)), "5/token:abc");
+    }
 }
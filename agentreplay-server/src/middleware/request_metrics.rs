@@ -0,0 +1,262 @@
+// Copyright 2025 AgentReplay (https://github.com/agentreplay)
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program. If not, see <https://www.gnu.org/licenses/>.
+
+//! Per-route request latency with exemplars
+//!
+//! [`request_metrics_middleware`] runs every request in an `http.request`
+//! span labelled with its route template and records its latency in a
+//! histogram per route, method and status class, exposed at `/metrics`.
+//!
+//! A handler that stores a trace names it in the
+//! [`TRACE_HEADER`](crate::api::ingest::TRACE_HEADER) response header. The
+//! request's latency bucket then keeps that trace as its exemplar, so a
+//! dashboard can jump from a slow bucket to a concrete stored trace.
+
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use agentreplay_core::clock::now_ms;
+use axum::{
+    body::Body,
+    extract::{MatchedPath, State},
+    http::{Method, Request, StatusCode},
+    middleware::Next,
+    response::Response,
+};
+use dashmap::DashMap;
+use parking_lot::Mutex;
+use tracing::Instrument;
+
+use crate::api::ingest::TRACE_HEADER;
+use crate::api::AppState;
+
+/// Upper bounds of the latency buckets, in seconds
+pub const LATENCY_BUCKETS: [f64; 12] = [
+    0.005, 0.01, 0.025, 0.05, 0.1, 0.25, 0.5, 1.0, 2.5, 5.0, 10.0, 30.0,
+];
+
+/// Route label of requests no route matched
+pub const UNMATCHED_ROUTE: &str = "unmatched";
+
+/// Buckets including the final +Inf one
+const BUCKETS: usize = LATENCY_BUCKETS.len() + 1;
+
+/// A request whose response named a stored trace
+#[derive(Debug, Clone, PartialEq)]
+pub struct Exemplar {
+    pub trace_id: String,
+    /// Latency in seconds
+    pub value: f64,
+    /// Unix time in seconds
+    pub timestamp: f64,
+}
+
+/// Labels of one histogram
+#[derive(Debug, Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct RouteKey {
+    pub route: String,
+    pub method: String,
+    /// Status class, e.g. `2xx`
+    pub status: &'static str,
+}
+
+#[derive(Default)]
+struct Histogram {
+    /// Requests per bucket, not cumulative
+    buckets: [AtomicU64; BUCKETS],
+    sum_us: AtomicU64,
+    /// Latest exemplar of each bucket
+    exemplars: Mutex<[Option<Exemplar>; BUCKETS]>,
+}
+
+/// Snapshot of one route's histogram
+#[derive(Debug, Clone)]
+pub struct RouteLatency {
+    pub key: RouteKey,
+    /// Cumulative counts per bucket, the last being +Inf
+    pub buckets: [u64; BUCKETS],
+    pub sum_seconds: f64,
+    pub exemplars: [Option<Exemplar>; BUCKETS],
+}
+
+impl RouteLatency {
+    pub fn count(&self) -> u64 {
+        self.buckets[BUCKETS - 1]
+    }
+}
+
+/// Latency histograms of every route
+#[derive(Default)]
+pub struct RequestMetrics {
+    routes: DashMap<RouteKey, Arc<Histogram>>,
+}
+
+impl RequestMetrics {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Record a request, with the stored trace it produced if any
+    pub fn observe(
+        &self,
+        route: &str,
+        method: &Method,
+        status: StatusCode,
+        latency: Duration,
+        trace_id: Option<&str>,
+    ) {
+        let key = RouteKey {
+            route: route.to_string(),
+            method: method.as_str().to_string(),
+            status: status_class(status),
+        };
+        let histogram = self.routes.entry(key).or_default().clone();
+
+        let seconds = latency.as_secs_f64();
+        let bucket = LATENCY_BUCKETS
+            .iter()
+            .position(|bound| seconds <= *bound)
+            .unwrap_or(BUCKETS - 1);
+        histogram.buckets[bucket].fetch_add(1, Ordering::Relaxed);
+        histogram
+            .sum_us
+            .fetch_add(latency.as_micros() as u64, Ordering::Relaxed);
+
+        if let Some(trace_id) = trace_id {
+            let timestamp = now_ms() as f64 / 1000.0;
+            histogram.exemplars.lock()[bucket] = Some(Exemplar {
+                trace_id: trace_id.to_string(),
+                value: seconds,
+                timestamp,
+            });
+        }
+    }
+
+    /// Histograms ordered by route, method and status
+    pub fn snapshot(&self) -> Vec<RouteLatency> {
+        let mut routes: Vec<RouteLatency> = self
+            .routes
+            .iter()
+            .map(|entry| {
+                let histogram = entry.value();
+                let mut buckets = [0; BUCKETS];
+                let mut total = 0;
+                for (cumulative, count) in buckets.iter_mut().zip(&histogram.buckets) {
+                    total += count.load(Ordering::Relaxed);
+                    *cumulative = total;
+                }
+                RouteLatency {
+                    key: entry.key().clone(),
+                    buckets,
+                    sum_seconds: histogram.sum_us.load(Ordering::Relaxed) as f64 / 1e6,
+                    exemplars: histogram.exemplars.lock().clone(),
+                }
+            })
+            .collect();
+        routes.sort_by(|a, b| a.key.cmp(&b.key));
+        routes
+    }
+}
+
+fn status_class(status: StatusCode) -> &'static str {
+    match status.as_u16() {
+        100..=199 => "1xx",
+        200..=299 => "2xx",
+        300..=399 => "3xx",
+        400..=499 => "4xx",
+        _ => "5xx",
+    }
+}
+
+/// Trace and time requests per route
+pub async fn request_metrics_middleware(
+    State(state): State<AppState>,
+    request: Request<Body>,
+    next: Next,
+) -> Response {
+    let method = request.method().clone();
+    let route = request
+        .extensions()
+        .get::<MatchedPath>()
+        .map(|path| path.as_str().to_string())
+        .unwrap_or_else(|| UNMATCHED_ROUTE.to_string());
+    let span = tracing::info_span!(
+        "http.request",
+        http.method = %method,
+        http.route = %route,
+        http.status_code = tracing::field::Empty,
+    );
+
+    let start = Instant::now();
+    let response = next.run(request).instrument(span.clone()).await;
+    let latency = start.elapsed();
+
+    span.record("http.status_code", response.status().as_u16());
+    let trace_id = response
+        .headers()
+        .get(TRACE_HEADER)
+        .and_then(|value| value.to_str().ok());
+    state
+        .request_metrics
+        .observe(&route, &method, response.status(), latency, trace_id);
+    response
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_histogram_buckets_and_exemplars() {
+        let metrics = RequestMetrics::new();
+        let observe = |method: Method, status, ms, trace_id| {
+            let latency = Duration::from_millis(ms);
+            metrics.observe("/api/v1/traces", &method, status, latency, trace_id);
+        };
+        observe(Method::POST, StatusCode::CREATED, 3, None);
+        observe(Method::POST, StatusCode::CREATED, 70, Some("0xab"));
+        observe(Method::POST, StatusCode::CREATED, 90, None);
+        observe(Method::GET, StatusCode::OK, 60_000, None);
+        metrics.observe(
+            UNMATCHED_ROUTE,
+            &Method::GET,
+            StatusCode::NOT_FOUND,
+            Duration::ZERO,
+            None,
+        );
+
+        let routes = metrics.snapshot();
+        assert_eq!(routes.len(), 3);
+        let ingest = &routes[1];
+        assert_eq!(ingest.key.method, "POST");
+        assert_eq!(ingest.key.status, "2xx");
+        assert_eq!(ingest.count(), 3);
+        // <= 5ms, <= 10ms, ..., <= 100ms
+        assert_eq!(&ingest.buckets[..5], &[1, 1, 1, 1, 3]);
+        assert!((ingest.sum_seconds - 0.163).abs() < 1e-9);
+        let exemplar = ingest.exemplars[4].as_ref().unwrap();
+        assert_eq!(exemplar.trace_id, "0xab");
+        assert!((exemplar.value - 0.07).abs() < 1e-9);
+        assert_eq!(ingest.exemplars.iter().flatten().count(), 1);
+
+        // Beyond the last bound only +Inf counts the request
+        let slow = &routes[0];
+        assert_eq!(slow.key.method, "GET");
+        assert_eq!(slow.buckets[BUCKETS - 2], 0);
+        assert_eq!(slow.count(), 1);
+        assert_eq!(routes[2].key.route, UNMATCHED_ROUTE);
+    }
+}
@@ -53,6 +53,12 @@ pub mod attrs {
     pub const GEN_AI_USAGE_CACHE_READ_TOKENS: &str = "gen_ai.usage.cache_read_tokens";
     /// Cached creation tokens (Anthropic)
     pub const GEN_AI_USAGE_CACHE_CREATION_TOKENS: &str = "gen_ai.usage.cache_creation_tokens";
+    /// Prompt-cache aliases (OpenLLMetry, OpenInference)
+    pub const GEN_AI_USAGE_CACHE_READ_INPUT_TOKENS: &str = "gen_ai.usage.cache_read_input_tokens";
+    pub const GEN_AI_USAGE_CACHE_CREATION_INPUT_TOKENS: &str =
+        "gen_ai.usage.cache_creation_input_tokens";
+    pub const LLM_TOKEN_COUNT_CACHE_READ: &str = "llm.token_count.prompt_details.cache_read";
+    pub const LLM_TOKEN_COUNT_CACHE_WRITE: &str = "llm.token_count.prompt_details.cache_write";
 
     // =========================================================================
     // FINISH REASONS
@@ -405,10 +411,24 @@ impl GenAIPayload {
             .and_then(|s| s.parse().ok());
         payload.cache_read_tokens = attributes
             .get(attrs::GEN_AI_USAGE_CACHE_READ_TOKENS)
+            .or_else(|| attributes.get(attrs::GEN_AI_USAGE_CACHE_READ_INPUT_TOKENS))
+            .or_else(|| attributes.get(attrs::LLM_TOKEN_COUNT_CACHE_READ))
             .and_then(|s| s.parse().ok());
         payload.cache_creation_tokens = attributes
             .get(attrs::GEN_AI_USAGE_CACHE_CREATION_TOKENS)
+            .or_else(|| attributes.get(attrs::GEN_AI_USAGE_CACHE_CREATION_INPUT_TOKENS))
+            .or_else(|| attributes.get(attrs::LLM_TOKEN_COUNT_CACHE_WRITE))
             .and_then(|s| s.parse().ok());
+        // input_tokens includes cached tokens (OpenAI semantics). Anthropic
+        // reports them separately; when the input is smaller than the cached
+        // part it cannot include it, so add it back.
+        if let Some(input) = payload.input_tokens {
+            let cached =
+                payload.cache_read_tokens.unwrap_or(0) + payload.cache_creation_tokens.unwrap_or(0);
+            if input < cached {
+                payload.input_tokens = Some(input + cached);
+            }
+        }
 
         // =========================================================================
         // FINISH REASONS
@@ -514,6 +534,10 @@ impl GenAIPayload {
             attrs::GEN_AI_USAGE_REASONING_TOKENS,
             attrs::GEN_AI_USAGE_CACHE_READ_TOKENS,
             attrs::GEN_AI_USAGE_CACHE_CREATION_TOKENS,
+            attrs::GEN_AI_USAGE_CACHE_READ_INPUT_TOKENS,
+            attrs::GEN_AI_USAGE_CACHE_CREATION_INPUT_TOKENS,
+            attrs::LLM_TOKEN_COUNT_CACHE_READ,
+            attrs::LLM_TOKEN_COUNT_CACHE_WRITE,
             attrs::GEN_AI_RESPONSE_FINISH_REASONS,
             attrs::GEN_AI_REQUEST_TEMPERATURE,
             attrs::GEN_AI_REQUEST_TOP_P,
@@ -612,18 +636,60 @@ impl GenAIPayload {
     pub fn calculate_cost(&self, model_pricing: &ModelPricing) -> f64 {
         let (input_tokens, output_tokens, reasoning_tokens) = self.get_token_counts();
         let cache_tokens = self.cache_read_tokens.unwrap_or(0);
+        let cache_write_tokens = self.cache_creation_tokens.unwrap_or(0);
 
-        // Regular input tokens (excluding cached)
-        let regular_input = input_tokens.saturating_sub(cache_tokens);
+        // Regular input tokens (excluding cache reads and writes)
+        let regular_input = input_tokens
+            .saturating_sub(cache_tokens)
+            .saturating_sub(cache_write_tokens);
 
         let input_cost = (regular_input as f64 / 1_000_000.0) * model_pricing.input_price_per_1m;
         let cache_cost = (cache_tokens as f64 / 1_000_000.0) * model_pricing.cache_price_per_1m;
+        let cache_write_cost =
+            (cache_write_tokens as f64 / 1_000_000.0) * model_pricing.cache_write_price_per_1m;
         let output_cost = (output_tokens as f64 / 1_000_000.0) * model_pricing.output_price_per_1m;
         let reasoning_cost =
             (reasoning_tokens as f64 / 1_000_000.0) * model_pricing.reasoning_price_per_1m;
 
-        input_cost + cache_cost + output_cost + reasoning_cost
+        input_cost + cache_cost + cache_write_cost + output_cost + reasoning_cost
     }
+
+    /// Realized prompt-caching savings versus sending every input token uncached
+    ///
+    /// Cache reads save `input - cache` per token; cache writes cost
+    /// `cache_write - input` extra, so the result is negative when a cache
+    /// is written but never read back.
+    pub fn calculate_cache_savings(&self, model_pricing: &ModelPricing) -> f64 {
+        let cache_tokens = self.cache_read_tokens.unwrap_or(0) as f64;
+        let cache_write_tokens = self.cache_creation_tokens.unwrap_or(0) as f64;
+        let read_savings = cache_tokens / 1_000_000.0
+            * (model_pricing.input_price_per_1m - model_pricing.cache_price_per_1m);
+        let write_premium = cache_write_tokens / 1_000_000.0
+            * (model_pricing.cache_write_price_per_1m - model_pricing.input_price_per_1m);
+        read_savings - write_premium
+    }
+
+    /// Cost stored with the span (`cost_usd`, else `gen_ai.usage.cost`),
+    /// which takes precedence over pricing its tokens again
+    pub fn stored_cost(&self) -> Option<f64> {
+        [COST_USD_KEY, "gen_ai.usage.cost"]
+            .iter()
+            .filter_map(|key| self.additional.get(*key))
+            .find_map(|value| {
+                value
+                    .as_f64()
+                    .or_else(|| value.as_str().and_then(|s| s.parse().ok()))
+            })
+    }
+}
+
+/// Payload key of the cost stored with a span, in USD
+pub const COST_USD_KEY: &str = "cost_usd";
+
+/// Whether a `gen_ai.system` runs models on the user's own hardware, where
+/// calls cost nothing per token
+pub fn is_local_system(system: &str) -> bool {
+    matches!(system, "ollama" | "llama.cpp" | "llamacpp")
 }
 
 /// Model pricing information
@@ -632,78 +698,128 @@ pub struct ModelPricing {
     pub input_price_per_1m: f64,
     pub output_price_per_1m: f64,
     pub cache_price_per_1m: f64, // Cache read tokens (90% discount for Anthropic)
+    pub cache_write_price_per_1m: f64, // Cache creation tokens (25% premium for Anthropic)
     pub reasoning_price_per_1m: f64, // For OpenAI o1 models
 }
 
 impl ModelPricing {
-    /// Get pricing for a model
+    /// Get pricing for a model, falling back to default pricing for unknown models
     pub fn for_model(system: &str, model: &str) -> Self {
+        Self::lookup(system, model).unwrap_or(Self {
+            input_price_per_1m: 10.0,
+            output_price_per_1m: 30.0,
+            cache_price_per_1m: 1.0,
+            cache_write_price_per_1m: 12.5,
+            reasoning_price_per_1m: 0.0,
+        })
+    }
+
+    /// Get pricing for a known model (None if the model has no pricing entry)
+    pub fn lookup(system: &str, model: &str) -> Option<Self> {
         match (system, model) {
-            // OpenAI models
-            ("openai", m) if m.contains("gpt-4o") => Self {
-                input_price_per_1m: 2.50,
-                output_price_per_1m: 10.0,
+            // Local models (Ollama, llama.cpp) are free to call
+            (system, _) if is_local_system(system) => Some(Self {
+                input_price_per_1m: 0.0,
+                output_price_per_1m: 0.0,
                 cache_price_per_1m: 0.0,
+                cache_write_price_per_1m: 0.0,
                 reasoning_price_per_1m: 0.0,
-            },
-            ("openai", m) if m.contains("gpt-4o-mini") => Self {
+            }),
+
+            // OpenAI models (automatic caching: discounted reads, no write premium)
+            ("openai", m) if m.contains("gpt-4o-mini") => Some(Self {
                 input_price_per_1m: 0.15,
                 output_price_per_1m: 0.60,
-                cache_price_per_1m: 0.0,
+                cache_price_per_1m: 0.075,
+                cache_write_price_per_1m: 0.15,
                 reasoning_price_per_1m: 0.0,
-            },
-            ("openai", m) if m.contains("gpt-4-turbo") => Self {
+            }),
+            ("openai", m) if m.contains("gpt-4o") => Some(Self {
+                input_price_per_1m: 2.50,
+                output_price_per_1m: 10.0,
+                cache_price_per_1m: 1.25,
+                cache_write_price_per_1m: 2.50,
+                reasoning_price_per_1m: 0.0,
+            }),
+            ("openai", m) if m.contains("gpt-4-turbo") => Some(Self {
                 input_price_per_1m: 10.0,
                 output_price_per_1m: 30.0,
-                cache_price_per_1m: 0.0,
+                cache_price_per_1m: 10.0, // No prompt caching
+                cache_write_price_per_1m: 10.0,
                 reasoning_price_per_1m: 0.0,
-            },
-            ("openai", m) if m.contains("o1-preview") => Self {
+            }),
+            ("openai", m) if m.contains("o1-preview") => Some(Self {
                 input_price_per_1m: 15.0,
                 output_price_per_1m: 60.0,
-                cache_price_per_1m: 0.0,
+                cache_price_per_1m: 7.50,
+                cache_write_price_per_1m: 15.0,
                 reasoning_price_per_1m: 15.0, // Reasoning tokens
-            },
-            ("openai", m) if m.contains("o1-mini") => Self {
+            }),
+            ("openai", m) if m.contains("o1-mini") => Some(Self {
                 input_price_per_1m: 3.0,
                 output_price_per_1m: 12.0,
-                cache_price_per_1m: 0.0,
+                cache_price_per_1m: 1.50,
+                cache_write_price_per_1m: 3.0,
                 reasoning_price_per_1m: 3.0,
-            },
+            }),
 
-            // Anthropic models
-            ("anthropic", m) if m.contains("claude-3-5-sonnet") => Self {
+            // Anthropic models (explicit caching: reads -90%, writes +25%)
+            ("anthropic", m) if m.contains("claude-3-5-sonnet") => Some(Self {
                 input_price_per_1m: 3.0,
                 output_price_per_1m: 15.0,
                 cache_price_per_1m: 0.30, // 90% discount
+                cache_write_price_per_1m: 3.75,
                 reasoning_price_per_1m: 0.0,
-            },
-            ("anthropic", m) if m.contains("claude-3-opus") => Self {
+            }),
+            ("anthropic", m) if m.contains("claude-3-opus") => Some(Self {
                 input_price_per_1m: 15.0,
                 output_price_per_1m: 75.0,
                 cache_price_per_1m: 1.50,
+                cache_write_price_per_1m: 18.75,
                 reasoning_price_per_1m: 0.0,
-            },
-            ("anthropic", m) if m.contains("claude-3-sonnet") => Self {
+            }),
+            ("anthropic", m) if m.contains("claude-3-sonnet") => Some(Self {
                 input_price_per_1m: 3.0,
                 output_price_per_1m: 15.0,
                 cache_price_per_1m: 0.30,
+                cache_write_price_per_1m: 3.75,
                 reasoning_price_per_1m: 0.0,
-            },
-            ("anthropic", m) if m.contains("claude-3-haiku") => Self {
+            }),
+            ("anthropic", m) if m.contains("claude-3-haiku") => Some(Self {
                 input_price_per_1m: 0.25,
                 output_price_per_1m: 1.25,
                 cache_price_per_1m: 0.03,
+                cache_write_price_per_1m: 0.30,
                 reasoning_price_per_1m: 0.0,
-            },
+            }),
 
-            // Default pricing
-            _ => Self {
-                input_price_per_1m: 10.0,
-                output_price_per_1m: 30.0,
-                cache_price_per_1m: 1.0,
-                reasoning_price_per_1m: 0.0,
-            },
+            _ => None,
+        }
+    }
+
+    /// Price of a call with no cached or reasoning tokens
+    pub fn token_cost(&self, input_tokens: u32, output_tokens: u32) -> f64 {
+        (input_tokens as f64 / 1_000_000.0) * self.input_price_per_1m
+            + (output_tokens as f64 / 1_000_000.0) * self.output_price_per_1m
+    }
+}
+
+/// Per-token prices of the pricing registry, as prices per million tokens
+///
+/// Cache reads and writes without a price of their own cost as much as
+/// regular input; reasoning tokens are billed at the output rate.
+impl From<&agentreplay_core::ModelPricing> for ModelPricing {
+    fn from(pricing: &agentreplay_core::ModelPricing) -> Self {
+        let per_1m = |cost_per_token: f64| cost_per_token * 1_000_000.0;
+        let input = pricing.input_cost_per_token;
+        Self {
+            input_price_per_1m: per_1m(input),
+            output_price_per_1m: per_1m(pricing.output_cost_per_token),
+            cache_price_per_1m: per_1m(pricing.cache_read_input_token_cost.unwrap_or(input)),
+            cache_write_price_per_1m: per_1m(
+                pricing.cache_creation_input_token_cost.unwrap_or(input),
+            ),
+            reasoning_price_per_1m: per_1m(pricing.output_cost_per_token),
         }
     }
 }
@@ -748,6 +864,21 @@ mod tests {
         assert!((cost - 0.025).abs() < 0.001);
     }
 
+    #[test]
+    fn test_local_models_are_free() {
+        let payload = GenAIPayload {
+            system: Some("ollama".to_string()),
+            request_model: Some("llama3.1:8b".to_string()),
+            input_tokens: Some(1000),
+            output_tokens: Some(500),
+            ..Default::default()
+        };
+
+        let pricing = ModelPricing::for_model("ollama", "llama3.1:8b");
+        assert_eq!(payload.calculate_cost(&pricing), 0.0);
+        assert!(ModelPricing::lookup("ollama", "anything").is_some());
+    }
+
     #[test]
     fn test_cache_tokens_discount() {
         let payload = GenAIPayload {
@@ -766,4 +897,66 @@ mod tests {
         //         = $0.0006 + $0.00024 + $0.0075 = $0.00834
         assert!((cost - 0.00834).abs() < 0.001);
     }
+
+    #[test]
+    fn test_cache_write_and_savings() {
+        let mut attributes = HashMap::new();
+        attributes.insert("gen_ai.system".to_string(), "anthropic".to_string());
+        // Anthropic-style usage: input_tokens excludes the cached prefix
+        attributes.insert("gen_ai.usage.input_tokens".to_string(), "100".to_string());
+        attributes.insert("gen_ai.usage.output_tokens".to_string(), "0".to_string());
+        attributes.insert(
+            "gen_ai.usage.cache_read_input_tokens".to_string(),
+            "1000000".to_string(),
+        );
+        attributes.insert(
+            "gen_ai.usage.cache_creation_input_tokens".to_string(),
+            "100000".to_string(),
+        );
+
+        let payload = GenAIPayload::from_attributes(&attributes);
+        assert_eq!(payload.input_tokens, Some(1_100_100));
+        assert_eq!(payload.cache_read_tokens, Some(1_000_000));
+        assert_eq!(payload.cache_creation_tokens, Some(100_000));
+        assert!(!payload
+            .additional
+            .contains_key("gen_ai.usage.cache_read_input_tokens"));
+
+        let pricing = ModelPricing::for_model("anthropic", "claude-3-5-sonnet");
+        // 100 * $3/1M + 1M * $0.30/1M + 100K * $3.75/1M
+        let cost = payload.calculate_cost(&pricing);
+        assert!((cost - (0.0003 + 0.30 + 0.375)).abs() < 1e-9);
+        // Reads save $2.70, writes cost $0.075 extra
+        let savings = payload.calculate_cache_savings(&pricing);
+        assert!((savings - 2.625).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_stored_cost_and_registry_pricing() {
+        let mut attributes = HashMap::new();
+        attributes.insert("gen_ai.usage.input_tokens".to_string(), "1000".to_string());
+        attributes.insert("gen_ai.usage.output_tokens".to_string(), "500".to_string());
+        let mut payload = GenAIPayload::from_attributes(&attributes);
+        assert_eq!(payload.stored_cost(), None);
+
+        payload
+            .additional
+            .insert("gen_ai.usage.cost".to_string(), serde_json::json!("0.25"));
+        assert_eq!(payload.stored_cost(), Some(0.25));
+        payload
+            .additional
+            .insert(COST_USD_KEY.to_string(), serde_json::json!(0.5));
+        assert_eq!(payload.stored_cost(), Some(0.5));
+
+        let registry_pricing = agentreplay_core::ModelPricing {
+            input_cost_per_token: 0.000002,
+            output_cost_per_token: 0.00001,
+            ..Default::default()
+        };
+        let pricing = ModelPricing::from(&registry_pricing);
+        assert!((pricing.input_price_per_1m - 2.0).abs() < 1e-9);
+        assert!((pricing.cache_price_per_1m - 2.0).abs() < 1e-9);
+        // 1000 * $2/1M + 500 * $10/1M
+        assert!((payload.calculate_cost(&pricing) - 0.007).abs() < 1e-9);
+    }
 }
@@ -28,16 +28,22 @@ use opentelemetry_proto::tonic::collector::trace::v1::{
 };
 use opentelemetry_proto::tonic::common::v1::any_value;
 
+use crate::api::AppState;
 use crate::project_manager::ProjectManager;
+use crate::quotas::QuotaScope;
 
 /// OTLP trace service implementation
 pub struct OtlpTraceService {
+    state: AppState,
     project_manager: Arc<ProjectManager>,
 }
 
 impl OtlpTraceService {
-    pub fn new(project_manager: Arc<ProjectManager>) -> Self {
-        Self { project_manager }
+    pub fn new(state: AppState, project_manager: Arc<ProjectManager>) -> Self {
+        Self {
+            state,
+            project_manager,
+        }
     }
 
     /// Extract tenant_id and project_id from resource attributes
@@ -148,6 +154,19 @@ impl OtlpTraceService {
                         }
                     }
 
+                    // OTLP links become the links attribute (IDs as hex, like span_id)
+                    if !span.links.is_empty() {
+                        let links: Vec<_> = span
+                            .links
+                            .iter()
+                            .map(|link| serde_json::json!(hex::encode(&link.span_id)))
+                            .collect();
+                        attributes.insert(
+                            crate::ingestion::ATTR_SPAN_LINKS.to_string(),
+                            serde_json::json!(links),
+                        );
+                    }
+
                     // Convert span to Agentreplay format
                     let agentreplay_span = serde_json::json!({
                         "span_id": hex::encode(&span.span_id),
@@ -193,6 +212,13 @@ impl TraceService for OtlpTraceService {
         &self,
         request: Request<ExportTraceServiceRequest>,
     ) -> Result<Response<ExportTraceServiceResponse>, Status> {
+        // Same fencing as the HTTP write guard: replicas, standbys and
+        // fenced writers must not store spans
+        if let Some(refusal) = crate::cluster::write_refusal(&self.state) {
+            return Err(Status::unavailable(refusal.to_string()));
+        }
+
+        let peer = request.remote_addr().map(|addr| addr.ip());
         let otlp_request = request.into_inner();
 
         // Count spans
@@ -231,6 +257,13 @@ impl TraceService for OtlpTraceService {
             span_count, tenant_id, project_id
         );
 
+        // gRPC callers are unauthenticated, so quotas key them by address
+        let quota = QuotaScope::peer(tenant_id, peer);
+        let projects = std::iter::repeat(project_id as u16).take(span_count);
+        if let Err(breach) = self.state.quotas.consume_spans(&quota, projects) {
+            return Err(Status::resource_exhausted(breach.to_string()));
+        }
+
         // Convert to Agentreplay format
         let spans = match self.convert_to_agentreplay(&otlp_request) {
             Ok(spans) => spans,
@@ -282,7 +315,12 @@ impl TraceService for OtlpTraceService {
                             }
 
                             let payload = serde_json::to_vec(&payload_map).unwrap_or_default();
-                            entries.push((edge, payload));
+                            let links = if edge.has_links() {
+                                crate::api::otel_span_links(&otel_span, edge.edge_id)
+                            } else {
+                                Vec::new()
+                            };
+                            entries.push((edge, payload, links));
                         }
                         Err(e) => {
                             warn!("Failed to convert span: {}", e);
@@ -297,7 +335,7 @@ impl TraceService for OtlpTraceService {
 
         // Store edges and payloads
         if !entries.is_empty() {
-            for (edge, payload) in entries {
+            for (edge, payload, links) in entries {
                 // Insert edge (metadata)
                 if let Err(e) = agentreplay.insert(edge).await {
                     warn!("Failed to insert edge: {}", e);
@@ -310,6 +348,15 @@ impl TraceService for OtlpTraceService {
                 if let Err(e) = agentreplay.put_payload(edge.edge_id, &payload) {
                     warn!("Failed to insert payload for edge {}: {}", edge.edge_id, e);
                 }
+
+                if !links.is_empty() {
+                    if let Err(e) = agentreplay.put_edge_links(edge.edge_id, &links) {
+                        warn!(
+                            "Failed to insert span links for edge {}: {}",
+                            edge.edge_id, e
+                        );
+                    }
+                }
             }
             info!("OTLP: Successfully stored {} spans", span_count);
         }
@@ -321,18 +368,22 @@ impl TraceService for OtlpTraceService {
     }
 }
 
-/// Start OTLP gRPC server on port 47117
-pub async fn start_otlp_server(project_manager: Arc<ProjectManager>) -> Result<()> {
+/// Start OTLP gRPC server on port 47117, serving until `shutdown` resolves
+pub async fn start_otlp_server(
+    state: AppState,
+    project_manager: Arc<ProjectManager>,
+    shutdown: impl std::future::Future<Output = ()>,
+) -> Result<()> {
     use opentelemetry_proto::tonic::collector::trace::v1::trace_service_server::TraceServiceServer;
 
     let addr = "0.0.0.0:47117".parse()?;
-    let service = OtlpTraceService::new(project_manager);
+    let service = OtlpTraceService::new(state, project_manager);
 
     info!("🚀 OTLP gRPC server starting on {}", addr);
 
     tonic::transport::Server::builder()
         .add_service(TraceServiceServer::new(service))
-        .serve(addr)
+        .serve_with_shutdown(addr, shutdown)
         .await?;
 
     Ok(())
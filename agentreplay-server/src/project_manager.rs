@@ -27,14 +27,19 @@
 //! - Easier to delete/archive projects
 //! - Better performance - smaller indexes per project
 
-use agentreplay_core::{AgentFlowEdge, Result};
-use agentreplay_query::Agentreplay;
+use agentreplay_core::{AgentFlowEdge, AgentreplayError, Result};
+use agentreplay_query::{Agentreplay, IsolationMode, KWayMerge, MergeKey};
+use agentreplay_storage::BackupManager;
 use moka::sync::Cache;
+use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 use std::path::{Path, PathBuf};
 use std::sync::Arc;
 use tracing::{info, warn};
 
+/// Maximum number of project databases a federated query scans concurrently
+pub const FEDERATION_PARALLELISM: usize = 8;
+
 /// Manages multiple Agentreplay database instances, one per project
 ///
 /// CRITICAL FIX: Now uses LRU cache with eviction to prevent file descriptor exhaustion.
@@ -45,6 +50,8 @@ pub struct ProjectManager {
     /// LRU cache of open Agentreplay instances: project_id -> Agentreplay
     /// Max 50 projects open at once to prevent FD exhaustion
     projects: Cache<u16, Arc<Agentreplay>>,
+    /// Applied to every project database as it opens
+    isolation: IsolationMode,
 }
 
 impl ProjectManager {
@@ -85,11 +92,21 @@ impl ProjectManager {
             })
             .build();
 
-        Ok(Self { base_dir, projects })
+        Ok(Self {
+            base_dir,
+            projects,
+            isolation: IsolationMode::default(),
+        })
+    }
+
+    /// Open project databases in the given tenant isolation mode
+    pub fn with_isolation(mut self, isolation: IsolationMode) -> Self {
+        self.isolation = isolation;
+        self
     }
 
     /// Get the storage directory for a specific project
-    fn project_dir(&self, project_id: u16) -> PathBuf {
+    pub fn project_dir(&self, project_id: u16) -> PathBuf {
         self.base_dir.join(format!("project_{}", project_id))
     }
 
@@ -116,14 +133,24 @@ impl ProjectManager {
                     project_id, project_dir
                 );
 
+                // A restore staged through the backup API applies on next open
+                if let Err(e) = BackupManager::apply_pending_restore(&project_dir) {
+                    warn!(
+                        "Failed to apply staged restore for project {}: {}",
+                        project_id, e
+                    );
+                }
+
                 // Always use high-performance mode for projects
-                Agentreplay::open_high_performance(&project_dir).map(Arc::new)
+                Agentreplay::open_high_performance(&project_dir).map(|db| {
+                    db.set_isolation_mode(self.isolation);
+                    Arc::new(db)
+                })
             })
             .map_err(|e| match Arc::try_unwrap(e) {
                 Ok(err) => err,
                 Err(arc_err) => {
                     // If we can't unwrap, create a new error with the same message
-                    use agentreplay_core::AgentreplayError;
                     AgentreplayError::Internal(format!("Failed to open project: {}", arc_err))
                 }
             })
@@ -172,21 +199,122 @@ impl ProjectManager {
         start_ts: u64,
         end_ts: u64,
     ) -> Result<Vec<AgentFlowEdge>> {
-        let mut all_edges = Vec::new();
+        let (edges, _) =
+            self.query_projects(&ProjectSelection::All, tenant_id, start_ts, end_ts)?;
+        Ok(edges)
+    }
 
-        // Get list of all project directories
-        let projects = self.discover_projects()?;
+    /// Resolve a selection to the IDs of projects that exist on disk
+    ///
+    /// Explicitly listed projects without a storage directory are dropped
+    /// rather than opened, so a federated query never creates empty projects.
+    pub fn resolve_selection(&self, selection: &ProjectSelection) -> Result<Vec<u16>> {
+        match selection {
+            ProjectSelection::All => self.discover_projects(),
+            ProjectSelection::Only(ids) => {
+                let mut ids: Vec<u16> = ids
+                    .iter()
+                    .copied()
+                    .filter(|id| self.project_dir(*id).is_dir())
+                    .collect();
+                ids.sort_unstable();
+                ids.dedup();
+                Ok(ids)
+            }
+        }
+    }
 
-        for project_id in projects {
-            match self.query_project(project_id, tenant_id, start_ts, end_ts) {
-                Ok(edges) => all_edges.extend(edges),
-                Err(e) => {
-                    warn!("Failed to query project {}: {}", project_id, e);
+    /// Run `f` against every selected project database
+    ///
+    /// Projects are queried in parallel, at most `FEDERATION_PARALLELISM` at
+    /// a time. A project that fails to open or query is reported in the
+    /// summary instead of failing the whole federated query.
+    pub fn federate<T, F>(&self, selection: &ProjectSelection, f: F) -> Result<Federated<T>>
+    where
+        T: Send,
+        F: Fn(&Agentreplay) -> Result<T> + Sync,
+    {
+        let project_ids = self.resolve_selection(selection)?;
+        let f = &f;
+
+        let mut federated = Federated {
+            results: Vec::with_capacity(project_ids.len()),
+            summary: FederationSummary::default(),
+        };
+
+        for batch in project_ids.chunks(FEDERATION_PARALLELISM) {
+            let outcomes: Vec<(u16, Result<T>)> = std::thread::scope(|scope| {
+                let handles: Vec<_> = batch
+                    .iter()
+                    .map(|&project_id| {
+                        let handle = scope.spawn(move || {
+                            let db = self.get_or_open_project(project_id)?;
+                            f(&db)
+                        });
+                        (project_id, handle)
+                    })
+                    .collect();
+
+                handles
+                    .into_iter()
+                    .map(|(project_id, handle)| {
+                        let outcome = handle.join().unwrap_or_else(|_| {
+                            Err(AgentreplayError::Internal(
+                                "Federated query panicked".to_string(),
+                            ))
+                        });
+                        (project_id, outcome)
+                    })
+                    .collect()
+            });
+
+            for (project_id, outcome) in outcomes {
+                match outcome {
+                    Ok(value) => {
+                        federated.summary.queried.push(project_id);
+                        federated.results.push((project_id, value));
+                    }
+                    Err(e) => {
+                        warn!("Failed to query project {}: {}", project_id, e);
+                        federated.summary.failed.push(FailedProject {
+                            project_id,
+                            error: e.to_string(),
+                        });
+                    }
                 }
             }
         }
 
-        Ok(all_edges)
+        Ok(federated)
+    }
+
+    /// Query edges across the selected projects for a tenant
+    ///
+    /// Each project's results are sorted and then combined with a k-way
+    /// merge, so the returned edges are in timestamp order across projects.
+    pub fn query_projects(
+        &self,
+        selection: &ProjectSelection,
+        tenant_id: u64,
+        start_ts: u64,
+        end_ts: u64,
+    ) -> Result<(Vec<AgentFlowEdge>, FederationSummary)> {
+        let federated = self.federate(selection, |db| {
+            let mut edges = db.query_temporal_range_for_tenant(start_ts, end_ts, tenant_id)?;
+            edges.sort_unstable_by_key(|e| e.merge_key());
+            Ok(edges)
+        })?;
+
+        let total = federated.results.iter().map(|(_, edges)| edges.len()).sum();
+        let streams = federated
+            .results
+            .into_iter()
+            .map(|(_, edges)| edges.into_iter())
+            .collect();
+        let mut merged = Vec::with_capacity(total);
+        merged.extend(KWayMerge::new(streams));
+
+        Ok((merged, federated.summary))
     }
 
     /// Get a specific edge by ID across all projects for a tenant
@@ -264,6 +392,31 @@ impl ProjectManager {
         Ok(())
     }
 
+    /// Flush metrics, sync and save the indexes of every open project
+    ///
+    /// Used at shutdown; returns the number of projects that failed to close.
+    pub fn close_open_projects(&self) -> usize {
+        let mut failed = 0;
+        for (project_id, db) in self.projects.iter() {
+            if let Err(e) = db.flush_metrics().and_then(|_| db.close()) {
+                warn!("Failed to close project {}: {}", project_id, e);
+                failed += 1;
+            }
+        }
+        failed
+    }
+
+    /// Currently open projects (not evicted from the LRU cache)
+    pub fn open_projects(&self) -> Vec<(u16, Arc<Agentreplay>)> {
+        let mut projects: Vec<_> = self
+            .projects
+            .iter()
+            .map(|(project_id, db)| (*project_id, db))
+            .collect();
+        projects.sort_by_key(|(project_id, _)| *project_id);
+        projects
+    }
+
     /// Delete a specific project and all its data
     pub fn delete_project(&self, project_id: u16) -> Result<()> {
         // First close the project to release all handles
@@ -372,3 +525,142 @@ pub struct ProjectStats {
     pub total_edges: usize,
     pub directory_size_bytes: u64,
 }
+
+/// Projects targeted by a federated query
+///
+/// Deserializes from `"all"`, a comma-separated string such as `"1,4,7"`
+/// (the form used in query strings), or a JSON array of project IDs.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+#[serde(try_from = "RawProjectSelection")]
+pub enum ProjectSelection {
+    /// Every project with storage on disk
+    All,
+    /// An explicit set of project IDs
+    Only(Vec<u16>),
+}
+
+impl ProjectSelection {
+    /// Parse `"all"` or a comma-separated list of project IDs
+    pub fn parse(value: &str) -> std::result::Result<Self, String> {
+        let value = value.trim();
+        if value.eq_ignore_ascii_case("all") || value == "*" {
+            return Ok(Self::All);
+        }
+
+        let ids = value
+            .split(',')
+            .map(str::trim)
+            .filter(|id| !id.is_empty())
+            .map(|id| {
+                id.parse::<u16>()
+                    .map_err(|_| format!("Invalid project ID in selection: {:?}", id))
+            })
+            .collect::<std::result::Result<Vec<u16>, String>>()?;
+
+        if ids.is_empty() {
+            return Err("Project selection must be \"all\" or at least one project ID".into());
+        }
+        Ok(Self::Only(ids))
+    }
+}
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum RawProjectSelection {
+    Text(String),
+    Ids(Vec<u16>),
+}
+
+impl TryFrom<RawProjectSelection> for ProjectSelection {
+    type Error = String;
+
+    fn try_from(raw: RawProjectSelection) -> std::result::Result<Self, Self::Error> {
+        match raw {
+            RawProjectSelection::Text(text) => Self::parse(&text),
+            RawProjectSelection::Ids(ids) if ids.is_empty() => {
+                Err("Project selection must list at least one project ID".into())
+            }
+            RawProjectSelection::Ids(ids) => Ok(Self::Only(ids)),
+        }
+    }
+}
+
+/// Per-project results of a federated query
+#[derive(Debug)]
+pub struct Federated<T> {
+    /// Results keyed by project ID, in ascending project order
+    pub results: Vec<(u16, T)>,
+    pub summary: FederationSummary,
+}
+
+/// Which projects a federated query covered, returned alongside results
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct FederationSummary {
+    /// Projects that answered successfully
+    pub queried: Vec<u16>,
+    /// Projects that could not be opened or queried
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub failed: Vec<FailedProject>,
+}
+
+/// A project that was skipped during a federated query
+#[derive(Debug, Clone, Serialize)]
+pub struct FailedProject {
+    pub project_id: u16,
+    pub error: String,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_project_selection() {
+        assert_eq!(
+            ProjectSelection::parse("all").unwrap(),
+            ProjectSelection::All
+        );
+        assert_eq!(
+            ProjectSelection::parse(" * ").unwrap(),
+            ProjectSelection::All
+        );
+        assert_eq!(
+            ProjectSelection::parse("3, 1,7,").unwrap(),
+            ProjectSelection::Only(vec![3, 1, 7])
+        );
+        assert!(ProjectSelection::parse("").is_err());
+        assert!(ProjectSelection::parse("1,abc").is_err());
+        assert!(ProjectSelection::parse("70000").is_err());
+    }
+
+    #[test]
+    fn test_deserialize_project_selection() {
+        let from_text: ProjectSelection = serde_json::from_str("\"1,2\"").unwrap();
+        assert_eq!(from_text, ProjectSelection::Only(vec![1, 2]));
+
+        let from_ids: ProjectSelection = serde_json::from_str("[4, 5]").unwrap();
+        assert_eq!(from_ids, ProjectSelection::Only(vec![4, 5]));
+
+        let all: ProjectSelection = serde_json::from_str("\"ALL\"").unwrap();
+        assert_eq!(all, ProjectSelection::All);
+
+        assert!(serde_json::from_str::<ProjectSelection>("[]").is_err());
+    }
+
+    #[test]
+    fn test_resolve_selection_skips_missing_projects() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::create_dir_all(dir.path().join("project_2")).unwrap();
+        std::fs::create_dir_all(dir.path().join("project_9")).unwrap();
+        let pm = ProjectManager::new(dir.path()).unwrap();
+
+        let ids = pm
+            .resolve_selection(&ProjectSelection::Only(vec![9, 5, 2, 9]))
+            .unwrap();
+        assert_eq!(ids, vec![2, 9]);
+        assert_eq!(
+            pm.resolve_selection(&ProjectSelection::All).unwrap(),
+            vec![2, 9]
+        );
+    }
+}
@@ -0,0 +1,526 @@
+// Copyright 2025 AgentReplay (https://github.com/agentreplay)
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program. If not, see <https://www.gnu.org/licenses/>.
+
+//! Project templates
+//!
+//! A template captures the defaults a platform team wants on every new
+//! project: retention, budget alerts, eval configuration, saved views and
+//! registered agents. `POST /api/v1/projects?template=<name>` creates the
+//! project and provisions each part from the template.
+//!
+//! Templates belong to the tenant that saved them and are stored in
+//! `project_templates.json` in the data directory.
+//! Retention and eval settings have no global home, so they are written to
+//! the project's storage directory (`retention-config.json` and
+//! `project_settings.json`).
+
+use agentreplay_core::clock::now_secs;
+use agentreplay_query::{RetentionConfig, RetentionPolicy};
+use parking_lot::RwLock;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+use std::fs::{self, File};
+use std::io::{BufReader, BufWriter};
+use std::path::{Path, PathBuf};
+use tracing::{info, warn};
+
+use crate::api::budget_alerts::{AlertAction, Period, ThresholdType};
+
+/// Template store file name inside the data directory
+pub const TEMPLATES_FILE: &str = "project_templates.json";
+/// Per-project settings file inside the project directory
+pub const PROJECT_SETTINGS_FILE: &str = "project_settings.json";
+/// Per-project retention config inside the project directory
+pub const PROJECT_RETENTION_FILE: &str = "retention-config.json";
+
+/// Defaults applied to projects created from a template
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ProjectTemplate {
+    /// Name, unique within the tenant; taken from the URL path when a
+    /// template is saved
+    #[serde(default)]
+    pub name: String,
+    /// Owning tenant; taken from the caller when a template is saved
+    #[serde(default)]
+    pub tenant_id: u64,
+    #[serde(default)]
+    pub description: Option<String>,
+    #[serde(default)]
+    pub retention: Option<TemplateRetention>,
+    #[serde(default)]
+    pub budgets: Vec<TemplateBudget>,
+    #[serde(default)]
+    pub eval: Option<ProjectEvalConfig>,
+    #[serde(default)]
+    pub views: Vec<TemplateView>,
+    #[serde(default)]
+    pub agents: Vec<TemplateAgent>,
+    #[serde(default)]
+    pub created_at: u64,
+    #[serde(default)]
+    pub updated_at: u64,
+}
+
+/// Retention defaults, in the same shape as the global retention config
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct TemplateRetention {
+    #[serde(default)]
+    pub policies: Vec<RetentionPolicy>,
+    #[serde(default)]
+    pub global_retention_days: Option<u32>,
+}
+
+impl TemplateRetention {
+    pub fn to_config(&self) -> RetentionConfig {
+        RetentionConfig {
+            version: 1,
+            policies: self.policies.clone(),
+            global_retention_days: self.global_retention_days,
+        }
+    }
+}
+
+/// A budget alert created for the new project
+///
+/// The alert's project filter is always set to the provisioned project.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TemplateBudget {
+    pub name: String,
+    #[serde(default)]
+    pub description: String,
+    pub threshold_type: ThresholdType,
+    pub threshold_value: f64,
+    pub period: Period,
+    #[serde(default)]
+    pub actions: Vec<AlertAction>,
+}
+
+/// Evaluation defaults for a project
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ProjectEvalConfig {
+    /// Metric IDs evaluated for the project's traces
+    #[serde(default)]
+    pub metrics: Vec<String>,
+    /// Fraction of traces evaluated online (0.0 to 1.0)
+    #[serde(default = "default_eval_sampling_rate")]
+    pub sampling_rate: f64,
+    #[serde(default)]
+    pub llm_judge_model: Option<String>,
+}
+
+fn default_eval_sampling_rate() -> f64 {
+    1.0
+}
+
+/// A saved view created for the new project
+///
+/// `filters` must be a JSON object (or omitted); `project_id` is added so
+/// the view is scoped to the provisioned project.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TemplateView {
+    pub name: String,
+    #[serde(default)]
+    pub description: Option<String>,
+    #[serde(default)]
+    pub filters: serde_json::Value,
+    #[serde(default)]
+    pub columns: Vec<String>,
+    #[serde(default)]
+    pub tags: Vec<String>,
+    #[serde(default)]
+    pub is_shared: bool,
+}
+
+impl TemplateView {
+    /// The view's filters with `project_id` pinned to `project_id`
+    pub fn scoped_filters(&self, project_id: u16) -> serde_json::Value {
+        let mut filters = match &self.filters {
+            serde_json::Value::Object(map) => map.clone(),
+            _ => serde_json::Map::new(),
+        };
+        filters.insert("project_id".to_string(), project_id.into());
+        serde_json::Value::Object(filters)
+    }
+}
+
+/// An agent registered for the new project
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TemplateAgent {
+    /// Fixed agent ID; derived from the project and agent name when omitted
+    #[serde(default)]
+    pub agent_id: Option<u64>,
+    pub name: String,
+    #[serde(default)]
+    pub namespace: Option<String>,
+    #[serde(default)]
+    pub version: Option<String>,
+    #[serde(default)]
+    pub description: Option<String>,
+    #[serde(default)]
+    pub metadata: HashMap<String, String>,
+}
+
+impl TemplateAgent {
+    /// Agent ID to register in `project_id`
+    ///
+    /// Derived IDs are stable, so re-provisioning the same template yields
+    /// the same agents and SDKs can compute the ID ahead of time.
+    pub fn agent_id_for(&self, project_id: u16) -> u64 {
+        self.agent_id.unwrap_or_else(|| {
+            let key = format!(
+                "{}/{}/{}",
+                project_id,
+                self.namespace.as_deref().unwrap_or_default(),
+                self.name
+            );
+            let hash = blake3::hash(key.as_bytes());
+            let mut bytes = [0u8; 8];
+            bytes.copy_from_slice(&hash.as_bytes()[..8]);
+            u64::from_le_bytes(bytes)
+        })
+    }
+}
+
+/// Settings recorded for a project at provisioning time
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ProjectSettings {
+    /// Template the project was created from
+    #[serde(default)]
+    pub template: Option<String>,
+    #[serde(default)]
+    pub eval: Option<ProjectEvalConfig>,
+    #[serde(default)]
+    pub provisioned_at: u64,
+}
+
+impl ProjectSettings {
+    /// Load settings from a project directory; missing file yields defaults
+    pub fn load(project_dir: &Path) -> Result<Self, String> {
+        let path = project_dir.join(PROJECT_SETTINGS_FILE);
+        if !path.exists() {
+            return Ok(Self::default());
+        }
+        let file =
+            File::open(&path).map_err(|e| format!("Failed to open project settings: {}", e))?;
+        serde_json::from_reader(BufReader::new(file))
+            .map_err(|e| format!("Failed to parse project settings: {}", e))
+    }
+
+    pub fn save(&self, project_dir: &Path) -> Result<(), String> {
+        fs::create_dir_all(project_dir)
+            .map_err(|e| format!("Failed to create project directory: {}", e))?;
+        let path = project_dir.join(PROJECT_SETTINGS_FILE);
+        let temp_path = path.with_extension("json.tmp");
+        {
+            let file = File::create(&temp_path)
+                .map_err(|e| format!("Failed to create temp settings file: {}", e))?;
+            serde_json::to_writer_pretty(BufWriter::new(file), self)
+                .map_err(|e| format!("Failed to serialize project settings: {}", e))?;
+        }
+        fs::rename(&temp_path, &path).map_err(|e| format!("Failed to rename temp file: {}", e))
+    }
+}
+
+/// Path of a project's own retention config
+pub fn project_retention_path(project_dir: &Path) -> PathBuf {
+    project_dir.join(PROJECT_RETENTION_FILE)
+}
+
+/// Stores project templates by tenant and name
+pub struct ProjectTemplateStore {
+    templates: RwLock<BTreeMap<(u64, String), ProjectTemplate>>,
+    storage_path: PathBuf,
+}
+
+impl ProjectTemplateStore {
+    /// Create a store, loading templates from `storage_path`
+    pub fn new(storage_path: impl AsRef<Path>) -> Self {
+        let store = Self {
+            templates: RwLock::new(BTreeMap::new()),
+            storage_path: storage_path.as_ref().to_path_buf(),
+        };
+
+        if let Err(e) = store.load_from_disk() {
+            warn!(
+                "Failed to load project templates from disk: {}. Starting with no templates.",
+                e
+            );
+        }
+
+        store
+    }
+
+    /// Templates of a tenant, by name
+    pub fn list(&self, tenant_id: u64) -> Vec<ProjectTemplate> {
+        self.templates
+            .read()
+            .values()
+            .filter(|t| t.tenant_id == tenant_id)
+            .cloned()
+            .collect()
+    }
+
+    pub fn get(&self, tenant_id: u64, name: &str) -> Option<ProjectTemplate> {
+        self.templates
+            .read()
+            .get(&(tenant_id, name.to_string()))
+            .cloned()
+    }
+
+    /// Create or replace a template; fails if it is invalid
+    pub fn upsert(&self, mut template: ProjectTemplate) -> Result<ProjectTemplate, String> {
+        validate(&template)?;
+
+        let now = now_secs();
+        template.created_at = self
+            .get(template.tenant_id, &template.name)
+            .map_or(now, |existing| existing.created_at);
+        template.updated_at = now;
+
+        self.templates.write().insert(
+            (template.tenant_id, template.name.clone()),
+            template.clone(),
+        );
+        self.save_to_disk()?;
+
+        info!(
+            "Saved project template '{}' of tenant {}",
+            template.name, template.tenant_id
+        );
+        Ok(template)
+    }
+
+    /// Delete a template; returns false if it did not exist
+    pub fn delete(&self, tenant_id: u64, name: &str) -> Result<bool, String> {
+        let removed = self
+            .templates
+            .write()
+            .remove(&(tenant_id, name.to_string()))
+            .is_some();
+        if removed {
+            self.save_to_disk()?;
+        }
+        Ok(removed)
+    }
+
+    fn load_from_disk(&self) -> Result<(), String> {
+        if !self.storage_path.exists() {
+            return Ok(());
+        }
+
+        let file = File::open(&self.storage_path)
+            .map_err(|e| format!("Failed to open templates file: {}", e))?;
+        let templates: Vec<ProjectTemplate> = serde_json::from_reader(BufReader::new(file))
+            .map_err(|e| format!("Failed to parse templates JSON: {}", e))?;
+
+        let mut map = self.templates.write();
+        for template in templates {
+            map.insert((template.tenant_id, template.name.clone()), template);
+        }
+
+        info!("Loaded {} project templates", map.len());
+        Ok(())
+    }
+
+    /// Save templates to disk (write to temp file then rename)
+    fn save_to_disk(&self) -> Result<(), String> {
+        if let Some(parent) = self.storage_path.parent() {
+            fs::create_dir_all(parent)
+                .map_err(|e| format!("Failed to create templates directory: {}", e))?;
+        }
+
+        let temp_path = self.storage_path.with_extension("json.tmp");
+        {
+            let file = File::create(&temp_path)
+                .map_err(|e| format!("Failed to create temp templates file: {}", e))?;
+            let templates: Vec<ProjectTemplate> = self.templates.read().values().cloned().collect();
+            serde_json::to_writer_pretty(BufWriter::new(file), &templates)
+                .map_err(|e| format!("Failed to serialize templates: {}", e))?;
+        }
+
+        fs::rename(&temp_path, &self.storage_path)
+            .map_err(|e| format!("Failed to rename temp file: {}", e))?;
+
+        Ok(())
+    }
+}
+
+fn validate(template: &ProjectTemplate) -> Result<(), String> {
+    let name_ok = !template.name.is_empty()
+        && template.name.len() <= 64
+        && template
+            .name
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_');
+    if !name_ok {
+        return Err("Template name must be 1-64 characters of [A-Za-z0-9_-]".to_string());
+    }
+
+    for budget in &template.budgets {
+        if budget.name.trim().is_empty() {
+            return Err("Budget alert name cannot be empty".to_string());
+        }
+        if !budget.threshold_value.is_finite() || budget.threshold_value <= 0.0 {
+            return Err(format!(
+                "Budget alert '{}' needs a positive threshold_value",
+                budget.name
+            ));
+        }
+    }
+
+    if let Some(eval) = &template.eval {
+        if !(0.0..=1.0).contains(&eval.sampling_rate) {
+            return Err("eval.sampling_rate must be between 0.0 and 1.0".to_string());
+        }
+    }
+
+    for view in &template.views {
+        if view.name.trim().is_empty() {
+            return Err("Saved view name cannot be empty".to_string());
+        }
+        if !(view.filters.is_object() || view.filters.is_null()) {
+            return Err(format!(
+                "Saved view '{}' filters must be a JSON object",
+                view.name
+            ));
+        }
+    }
+
+    for agent in &template.agents {
+        if agent.name.trim().is_empty() {
+            return Err("Agent name cannot be empty".to_string());
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    fn template(name: &str) -> ProjectTemplate {
+        serde_json::from_value(serde_json::json!({
+            "name": name,
+            "retention": { "global_retention_days": 14 },
+            "budgets": [{
+                "name": "daily spend",
+                "threshold_type": "daily",
+                "threshold_value": 25.0,
+                "period": "day"
+            }],
+            "eval": { "metrics": ["hallucination"] },
+            "views": [{ "name": "errors", "filters": { "has_errors": true } }],
+            "agents": [{ "name": "router", "namespace": "support" }]
+        }))
+        .unwrap()
+    }
+
+    #[test]
+    fn test_templates_persist_across_reload() {
+        let temp_dir = TempDir::new().unwrap();
+        let path = temp_dir.path().join(TEMPLATES_FILE);
+
+        let store = ProjectTemplateStore::new(&path);
+        let saved = store.upsert(template("support-bot")).unwrap();
+        assert!(saved.created_at > 0);
+        assert_eq!(saved.eval.as_ref().unwrap().sampling_rate, 1.0);
+
+        let reloaded = ProjectTemplateStore::new(&path);
+        let loaded = reloaded.get(0, "support-bot").unwrap();
+        assert_eq!(loaded.budgets.len(), 1);
+        assert_eq!(loaded.created_at, saved.created_at);
+
+        assert!(reloaded.delete(0, "support-bot").unwrap());
+        assert!(!reloaded.delete(0, "support-bot").unwrap());
+        assert!(ProjectTemplateStore::new(&path).list(0).is_empty());
+    }
+
+    #[test]
+    fn test_templates_are_per_tenant() {
+        let store = ProjectTemplateStore::new(TempDir::new().unwrap().path().join("t.json"));
+        store.upsert(template("shared-name")).unwrap();
+        let mut other = template("shared-name");
+        other.tenant_id = 2;
+        other.description = Some("tenant 2".to_string());
+        store.upsert(other).unwrap();
+
+        assert_eq!(store.list(0).len(), 1);
+        assert!(store.get(0, "shared-name").unwrap().description.is_none());
+        assert!(!store.delete(3, "shared-name").unwrap());
+        assert!(store.delete(2, "shared-name").unwrap());
+        assert!(store.get(0, "shared-name").is_some());
+    }
+
+    #[test]
+    fn test_rejects_invalid_templates() {
+        let store = ProjectTemplateStore::new(TempDir::new().unwrap().path().join("t.json"));
+
+        assert!(store.upsert(template("has space")).is_err());
+
+        let mut bad_budget = template("ok");
+        bad_budget.budgets[0].threshold_value = 0.0;
+        assert!(store.upsert(bad_budget).is_err());
+
+        let mut bad_view = template("ok");
+        bad_view.views[0].filters = serde_json::json!(["not", "an", "object"]);
+        assert!(store.upsert(bad_view).is_err());
+
+        let mut bad_eval = template("ok");
+        bad_eval.eval.as_mut().unwrap().sampling_rate = 1.5;
+        assert!(store.upsert(bad_eval).is_err());
+    }
+
+    #[test]
+    fn test_views_and_agents_are_scoped_to_project() {
+        let template = template("scoped");
+
+        let filters = template.views[0].scoped_filters(42);
+        assert_eq!(filters["project_id"], 42);
+        assert_eq!(filters["has_errors"], true);
+
+        let agent = &template.agents[0];
+        assert_eq!(agent.agent_id_for(42), agent.agent_id_for(42));
+        assert_ne!(agent.agent_id_for(42), agent.agent_id_for(43));
+
+        let fixed = TemplateAgent {
+            agent_id: Some(7),
+            ..agent.clone()
+        };
+        assert_eq!(fixed.agent_id_for(42), 7);
+    }
+
+    #[test]
+    fn test_project_settings_roundtrip() {
+        let temp_dir = TempDir::new().unwrap();
+        let project_dir = temp_dir.path().join("project_5");
+        assert!(ProjectSettings::load(&project_dir)
+            .unwrap()
+            .template
+            .is_none());
+
+        let settings = ProjectSettings {
+            template: Some("support-bot".to_string()),
+            eval: template("x").eval,
+            provisioned_at: 1,
+        };
+        settings.save(&project_dir).unwrap();
+
+        let loaded = ProjectSettings::load(&project_dir).unwrap();
+        assert_eq!(loaded.template.as_deref(), Some("support-bot"));
+        assert_eq!(loaded.eval, settings.eval);
+    }
+}
@@ -0,0 +1,882 @@
+// Copyright 2025 AgentReplay (https://github.com/agentreplay)
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program. If not, see <https://www.gnu.org/licenses/>.
+
+//! Daily and monthly usage quotas per API key and project
+//!
+//! Rate limits smooth bursts; quotas cap total usage over a UTC calendar
+//! day or month. Three things are counted:
+//!
+//! - requests, by [`quota_middleware`]
+//! - spans accepted by the HTTP and OTLP gRPC ingestion endpoints
+//! - prompt and completion tokens of LLM calls through the chat proxy
+//!
+//! Usage is charged to the caller's key (its token, user or API key, as
+//! for rate limits) and, for project-scoped keys, to the project. Spans
+//! are charged to the project each one is stored in instead; OTLP gRPC
+//! callers, which do not authenticate, are keyed by address. Limits come
+//! from `[quotas]` in the config. A request that would go over a quota
+//! gets 429 with the time the quota resets; `GET /api/v1/usage` shows the
+//! counters.
+//!
+//! Counters are flushed to [`QUOTA_USAGE_FILE`] every minute, so monthly
+//! quotas survive restarts.
+
+use agentreplay_core::clock::now_secs;
+use std::collections::{BTreeMap, HashMap};
+use std::fmt;
+use std::fs::{self, File};
+use std::io::{BufReader, BufWriter};
+use std::net::IpAddr;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+
+use axum::{
+    body::Body,
+    extract::State,
+    http::{HeaderValue, Request, StatusCode},
+    middleware::Next,
+    response::{IntoResponse, Response},
+    Extension, Json,
+};
+use parking_lot::Mutex;
+use serde::{Deserialize, Serialize};
+use tracing::{info, warn};
+
+use crate::api::error::{ApiError, ErrorCode, ProblemDetails};
+use crate::api::AppState;
+use crate::auth::AuthContext;
+use crate::config::{QuotaConfig, QuotaLimits};
+use crate::middleware::rate_limit::client_key;
+use crate::reports::schedule::{civil_from_days, days_from_civil};
+
+/// Usage counters file name inside the data directory
+pub const QUOTA_USAGE_FILE: &str = "quota_usage.json";
+
+/// Path of the usage endpoint, which never counts against quotas
+pub const USAGE_PATH: &str = "/api/v1/usage";
+
+const FLUSH_INTERVAL: Duration = Duration::from_secs(60);
+const DAY_SECS: u64 = 86_400;
+
+/// What a quota counts
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum QuotaMetric {
+    Requests,
+    Spans,
+    Tokens,
+}
+
+impl QuotaMetric {
+    pub const ALL: [QuotaMetric; 3] = [
+        QuotaMetric::Requests,
+        QuotaMetric::Spans,
+        QuotaMetric::Tokens,
+    ];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            QuotaMetric::Requests => "request",
+            QuotaMetric::Spans => "span",
+            QuotaMetric::Tokens => "token",
+        }
+    }
+
+    fn index(&self) -> usize {
+        *self as usize
+    }
+}
+
+/// UTC calendar period a quota covers
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum QuotaPeriod {
+    Day,
+    Month,
+}
+
+impl QuotaPeriod {
+    pub const ALL: [QuotaPeriod; 2] = [QuotaPeriod::Day, QuotaPeriod::Month];
+
+    /// Days since the epoch, or months since January 1970
+    fn number(&self, now: u64) -> u64 {
+        let day = now / DAY_SECS;
+        match self {
+            QuotaPeriod::Day => day,
+            QuotaPeriod::Month => {
+                let (year, month, _) = civil_from_days(day as i64);
+                ((year - 1970) * 12 + month as i64 - 1) as u64
+            }
+        }
+    }
+
+    /// Unix seconds the period containing `now` ends at
+    pub fn reset_at(&self, now: u64) -> u64 {
+        let day = now / DAY_SECS;
+        match self {
+            QuotaPeriod::Day => (day + 1) * DAY_SECS,
+            QuotaPeriod::Month => {
+                let (year, month, _) = civil_from_days(day as i64);
+                let (year, month) = if month == 12 {
+                    (year + 1, 1)
+                } else {
+                    (year, month + 1)
+                };
+                days_from_civil(year, month, 1) as u64 * DAY_SECS
+            }
+        }
+    }
+
+    fn adjective(&self) -> &'static str {
+        match self {
+            QuotaPeriod::Day => "Daily",
+            QuotaPeriod::Month => "Monthly",
+        }
+    }
+}
+
+impl QuotaLimits {
+    pub fn limit(&self, metric: QuotaMetric, period: QuotaPeriod) -> Option<u64> {
+        match (metric, period) {
+            (QuotaMetric::Requests, QuotaPeriod::Day) => self.requests_per_day,
+            (QuotaMetric::Requests, QuotaPeriod::Month) => self.requests_per_month,
+            (QuotaMetric::Spans, QuotaPeriod::Day) => self.spans_per_day,
+            (QuotaMetric::Spans, QuotaPeriod::Month) => self.spans_per_month,
+            (QuotaMetric::Tokens, QuotaPeriod::Day) => self.tokens_per_day,
+            (QuotaMetric::Tokens, QuotaPeriod::Month) => self.tokens_per_month,
+        }
+    }
+}
+
+/// Who usage is charged to
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "lowercase")]
+pub enum QuotaSubject {
+    /// A key, token or user, as `<tenant>/<client>`
+    Key {
+        key: String,
+    },
+    Project {
+        tenant_id: u64,
+        project_id: u16,
+    },
+}
+
+impl fmt::Display for QuotaSubject {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            QuotaSubject::Key { key } => write!(f, "key {}", key),
+            QuotaSubject::Project { project_id, .. } => write!(f, "project {}", project_id),
+        }
+    }
+}
+
+/// The caller of a request, as quotas see it
+#[derive(Debug, Clone)]
+pub struct QuotaScope {
+    key: String,
+    tenant_id: u64,
+    project_id: Option<u16>,
+}
+
+impl QuotaScope {
+    pub fn new(auth: &AuthContext) -> Self {
+        Self {
+            key: client_key(auth, None),
+            tenant_id: auth.tenant_id,
+            project_id: auth.project_id,
+        }
+    }
+
+    /// Unauthenticated caller of the OTLP gRPC endpoint, keyed by address
+    pub fn peer(tenant_id: u64, addr: Option<IpAddr>) -> Self {
+        let key = match addr {
+            Some(addr) => format!("{}/ip:{}", tenant_id, addr),
+            None => tenant_id.to_string(),
+        };
+        Self {
+            key,
+            tenant_id,
+            project_id: None,
+        }
+    }
+}
+
+/// A quota a request would go over
+#[derive(Debug, Clone, Serialize)]
+pub struct QuotaBreach {
+    pub subject: QuotaSubject,
+    pub metric: QuotaMetric,
+    pub period: QuotaPeriod,
+    pub limit: u64,
+    pub used: u64,
+    /// Unix seconds
+    pub reset_at: u64,
+}
+
+impl fmt::Display for QuotaBreach {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} {} quota of {} for {} exhausted ({} used); resets at {}",
+            self.period.adjective(),
+            self.metric.as_str(),
+            self.limit,
+            self.subject,
+            self.used,
+            self.reset_at
+        )
+    }
+}
+
+impl IntoResponse for QuotaBreach {
+    fn into_response(self) -> Response {
+        let retry_after_secs = self.reset_at.saturating_sub(now_secs()).max(1);
+        let mut response = ProblemDetails::new(
+            StatusCode::TOO_MANY_REQUESTS,
+            ErrorCode::QuotaExceeded,
+            self.to_string(),
+        )
+        .with_retry_after_ms(retry_after_secs * 1000)
+        .with_reset_at(self.reset_at)
+        .into_response();
+
+        let headers = response.headers_mut();
+        headers.insert("X-Quota-Limit", HeaderValue::from(self.limit));
+        headers.insert(
+            "X-Quota-Remaining",
+            HeaderValue::from(self.limit.saturating_sub(self.used)),
+        );
+        headers.insert("X-Quota-Reset", HeaderValue::from(self.reset_at));
+        response
+    }
+}
+
+impl From<QuotaBreach> for ApiError {
+    fn from(breach: QuotaBreach) -> Self {
+        ApiError::QuotaExceeded(breach)
+    }
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+struct Counters {
+    day: u64,
+    month: u64,
+    /// Indexed by [`QuotaMetric`]
+    daily: [u64; 3],
+    monthly: [u64; 3],
+}
+
+impl Counters {
+    /// Start over when a new day or month began
+    fn roll(&mut self, now: u64) {
+        let day = QuotaPeriod::Day.number(now);
+        if self.day != day {
+            self.day = day;
+            self.daily = [0; 3];
+        }
+        let month = QuotaPeriod::Month.number(now);
+        if self.month != month {
+            self.month = month;
+            self.monthly = [0; 3];
+        }
+    }
+
+    fn used(&self, metric: QuotaMetric, period: QuotaPeriod) -> u64 {
+        match period {
+            QuotaPeriod::Day => self.daily[metric.index()],
+            QuotaPeriod::Month => self.monthly[metric.index()],
+        }
+    }
+
+    fn add(&mut self, metric: QuotaMetric, amount: u64) {
+        self.daily[metric.index()] += amount;
+        self.monthly[metric.index()] += amount;
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+struct StoredCounters {
+    subject: QuotaSubject,
+    #[serde(flatten)]
+    counters: Counters,
+}
+
+/// One quota of a subject
+#[derive(Debug, Clone, Serialize)]
+pub struct QuotaUsage {
+    pub metric: QuotaMetric,
+    pub period: QuotaPeriod,
+    pub used: u64,
+    /// `None` when unlimited
+    pub limit: Option<u64>,
+    pub remaining: Option<u64>,
+    /// Unix seconds
+    pub reset_at: u64,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct SubjectUsage {
+    pub subject: QuotaSubject,
+    pub quotas: Vec<QuotaUsage>,
+}
+
+/// Body of `GET /api/v1/usage`
+#[derive(Debug, Clone, Serialize)]
+pub struct UsageResponse {
+    /// Whether quotas are enabled; nothing is counted otherwise
+    pub enabled: bool,
+    pub subjects: Vec<SubjectUsage>,
+}
+
+/// Counts usage and enforces the configured quotas
+pub struct QuotaManager {
+    config: QuotaConfig,
+    usage: Mutex<HashMap<QuotaSubject, Counters>>,
+    storage_path: Option<PathBuf>,
+    dirty: AtomicBool,
+}
+
+impl Default for QuotaManager {
+    fn default() -> Self {
+        Self::new(QuotaConfig::default())
+    }
+}
+
+impl QuotaManager {
+    /// Manager keeping counters in memory only
+    pub fn new(config: QuotaConfig) -> Self {
+        Self {
+            config,
+            usage: Mutex::new(HashMap::new()),
+            storage_path: None,
+            dirty: AtomicBool::new(false),
+        }
+    }
+
+    /// Manager persisting counters to `path`, loading the current ones
+    pub fn with_storage(config: QuotaConfig, path: impl AsRef<Path>) -> Self {
+        let mut manager = Self::new(config);
+        manager.storage_path = Some(path.as_ref().to_path_buf());
+        if let Err(e) = manager.load() {
+            warn!("Failed to load quota usage: {}. Starting from zero.", e);
+        }
+        manager
+    }
+
+    pub fn enabled(&self) -> bool {
+        self.config.enabled
+    }
+
+    /// Charge `amount` of `metric` to the scope, unless that would go over
+    /// one of its quotas
+    pub fn consume(
+        &self,
+        scope: &QuotaScope,
+        metric: QuotaMetric,
+        amount: u64,
+    ) -> Result<(), QuotaBreach> {
+        self.charge(scope, metric, amount, true, now_secs())
+    }
+
+    /// Charge spans to the scope's key and to the project each is stored
+    /// in, unless that would go over one of their quotas
+    pub fn consume_spans(
+        &self,
+        scope: &QuotaScope,
+        projects: impl IntoIterator<Item = u16>,
+    ) -> Result<(), QuotaBreach> {
+        self.charge_spans(scope, projects, now_secs())
+    }
+
+    /// Charge usage that already happened, such as the tokens of a
+    /// completed LLM call, even past a quota
+    pub fn record(&self, scope: &QuotaScope, metric: QuotaMetric, amount: u64) {
+        let _ = self.charge(scope, metric, amount, false, now_secs());
+    }
+
+    /// Whether the scope has any `metric` quota left
+    pub fn check(&self, scope: &QuotaScope, metric: QuotaMetric) -> Result<(), QuotaBreach> {
+        if !self.enabled() {
+            return Ok(());
+        }
+        let now = now_secs();
+        let charges: Vec<_> = self
+            .subjects(scope)
+            .into_iter()
+            .map(|(subject, limits)| (subject, limits, 1))
+            .collect();
+        let mut usage = self.usage.lock();
+        match find_breach(&mut usage, &charges, metric, now) {
+            Some(breach) => Err(breach),
+            None => Ok(()),
+        }
+    }
+
+    /// Counters and limits of the scope's subjects
+    pub fn usage(&self, scope: &QuotaScope) -> UsageResponse {
+        self.usage_at(scope, now_secs())
+    }
+
+    fn usage_at(&self, scope: &QuotaScope, now: u64) -> UsageResponse {
+        let usage = self.usage.lock();
+        let subjects = self
+            .subjects(scope)
+            .into_iter()
+            .map(|(subject, limits)| {
+                let mut counters = usage.get(&subject).cloned().unwrap_or_default();
+                counters.roll(now);
+                let quotas = QuotaMetric::ALL
+                    .iter()
+                    .flat_map(|metric| QuotaPeriod::ALL.map(|period| (*metric, period)))
+                    .map(|(metric, period)| {
+                        let used = counters.used(metric, period);
+                        let limit = limits.limit(metric, period);
+                        QuotaUsage {
+                            metric,
+                            period,
+                            used,
+                            limit,
+                            remaining: limit.map(|limit| limit.saturating_sub(used)),
+                            reset_at: period.reset_at(now),
+                        }
+                    })
+                    .collect();
+                SubjectUsage { subject, quotas }
+            })
+            .collect();
+        UsageResponse {
+            enabled: self.enabled(),
+            subjects,
+        }
+    }
+
+    fn subjects(&self, scope: &QuotaScope) -> Vec<(QuotaSubject, QuotaLimits)> {
+        let mut subjects = vec![(
+            QuotaSubject::Key {
+                key: scope.key.clone(),
+            },
+            self.config.per_key,
+        )];
+        if let Some(project_id) = scope.project_id {
+            subjects.push(self.project_subject(scope.tenant_id, project_id));
+        }
+        subjects
+    }
+
+    fn project_subject(&self, tenant_id: u64, project_id: u16) -> (QuotaSubject, QuotaLimits) {
+        (
+            QuotaSubject::Project {
+                tenant_id,
+                project_id,
+            },
+            self.config.project(project_id).copied().unwrap_or_default(),
+        )
+    }
+
+    fn charge(
+        &self,
+        scope: &QuotaScope,
+        metric: QuotaMetric,
+        amount: u64,
+        enforce: bool,
+        now: u64,
+    ) -> Result<(), QuotaBreach> {
+        let charges = self
+            .subjects(scope)
+            .into_iter()
+            .map(|(subject, limits)| (subject, limits, amount))
+            .collect();
+        self.apply(charges, metric, enforce, now)
+    }
+
+    fn charge_spans(
+        &self,
+        scope: &QuotaScope,
+        projects: impl IntoIterator<Item = u16>,
+        now: u64,
+    ) -> Result<(), QuotaBreach> {
+        let mut per_project = BTreeMap::new();
+        for project_id in projects {
+            *per_project.entry(project_id).or_insert(0u64) += 1;
+        }
+        let total: u64 = per_project.values().sum();
+        let key = QuotaSubject::Key {
+            key: scope.key.clone(),
+        };
+        let mut charges = vec![(key, self.config.per_key, total)];
+        for (project_id, amount) in per_project {
+            let (subject, limits) = self.project_subject(scope.tenant_id, project_id);
+            charges.push((subject, limits, amount));
+        }
+        self.apply(charges, QuotaMetric::Spans, true, now)
+    }
+
+    /// Add each amount to its subject, or nothing if `enforce` and one of
+    /// them would go over a quota
+    fn apply(
+        &self,
+        charges: Vec<(QuotaSubject, QuotaLimits, u64)>,
+        metric: QuotaMetric,
+        enforce: bool,
+        now: u64,
+    ) -> Result<(), QuotaBreach> {
+        if !self.enabled() || charges.iter().all(|(_, _, amount)| *amount == 0) {
+            return Ok(());
+        }
+        let mut usage = self.usage.lock();
+        if enforce {
+            if let Some(breach) = find_breach(&mut usage, &charges, metric, now) {
+                return Err(breach);
+            }
+        }
+        for (subject, _, amount) in charges {
+            let counters = usage.entry(subject).or_default();
+            counters.roll(now);
+            counters.add(metric, amount);
+        }
+        self.dirty.store(true, Ordering::Relaxed);
+        Ok(())
+    }
+
+    /// Write the counters to disk every minute while they change
+    pub fn spawn_flush(self: &Arc<Self>) {
+        if self.storage_path.is_none() || !self.enabled() {
+            return;
+        }
+        let manager = Arc::clone(self);
+        tokio::spawn(async move {
+            let mut interval = tokio::time::interval(FLUSH_INTERVAL);
+            interval.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+            loop {
+                interval.tick().await;
+                if !manager.dirty.swap(false, Ordering::Relaxed) {
+                    continue;
+                }
+                let manager = Arc::clone(&manager);
+                match tokio::task::spawn_blocking(move || manager.flush(now_secs())).await {
+                    Ok(Err(e)) => warn!("Failed to save quota usage: {}", e),
+                    Err(e) => warn!("Quota usage flush failed: {}", e),
+                    Ok(Ok(())) => {}
+                }
+            }
+        });
+    }
+
+    /// Save the counters of the current month, dropping older ones
+    fn flush(&self, now: u64) -> Result<(), String> {
+        let Some(path) = &self.storage_path else {
+            return Ok(());
+        };
+        let month = QuotaPeriod::Month.number(now);
+        let stored: Vec<StoredCounters> = {
+            let mut usage = self.usage.lock();
+            usage.retain(|_, counters| counters.month == month);
+            usage
+                .iter()
+                .map(|(subject, counters)| StoredCounters {
+                    subject: subject.clone(),
+                    counters: counters.clone(),
+                })
+                .collect()
+        };
+
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)
+                .map_err(|e| format!("Failed to create quota usage directory: {}", e))?;
+        }
+        let temp_path = path.with_extension("json.tmp");
+        {
+            let file = File::create(&temp_path)
+                .map_err(|e| format!("Failed to create temp quota usage file: {}", e))?;
+            serde_json::to_writer(BufWriter::new(file), &stored)
+                .map_err(|e| format!("Failed to serialize quota usage: {}", e))?;
+        }
+        fs::rename(&temp_path, path).map_err(|e| format!("Failed to rename temp file: {}", e))
+    }
+
+    fn load(&self) -> Result<(), String> {
+        let Some(path) = &self.storage_path else {
+            return Ok(());
+        };
+        if !path.exists() {
+            return Ok(());
+        }
+        let file =
+            File::open(path).map_err(|e| format!("Failed to open quota usage file: {}", e))?;
+        let stored: Vec<StoredCounters> = serde_json::from_reader(BufReader::new(file))
+            .map_err(|e| format!("Failed to parse quota usage JSON: {}", e))?;
+
+        info!("Loaded quota usage of {} keys and projects", stored.len());
+        *self.usage.lock() = stored
+            .into_iter()
+            .map(|stored| (stored.subject, stored.counters))
+            .collect();
+        Ok(())
+    }
+}
+
+/// First quota of `metric` that `amount` more would go over
+fn find_breach(
+    usage: &mut HashMap<QuotaSubject, Counters>,
+    charges: &[(QuotaSubject, QuotaLimits, u64)],
+    metric: QuotaMetric,
+    now: u64,
+) -> Option<QuotaBreach> {
+    for (subject, limits, amount) in charges {
+        let counters = usage.entry(subject.clone()).or_default();
+        counters.roll(now);
+        for period in QuotaPeriod::ALL {
+            let Some(limit) = limits.limit(metric, period) else {
+                continue;
+            };
+            let used = counters.used(metric, period);
+            if used.saturating_add(*amount) > limit {
+                return Some(QuotaBreach {
+                    subject: subject.clone(),
+                    metric,
+                    period,
+                    limit,
+                    used,
+                    reset_at: period.reset_at(now),
+                });
+            }
+        }
+    }
+    None
+}
+
+/// Count every authenticated request against the caller's request quotas
+///
+/// Runs after authentication and rate limiting; requests over a quota get
+/// 429 with `Retry-After` and `X-Quota-Reset` (Unix seconds).
+pub async fn quota_middleware(
+    State(state): State<AppState>,
+    request: Request<Body>,
+    next: Next,
+) -> Response {
+    if !state.quotas.enabled() || request.uri().path() == USAGE_PATH {
+        return next.run(request).await;
+    }
+    let Some(scope) = request
+        .extensions()
+        .get::<AuthContext>()
+        .map(QuotaScope::new)
+    else {
+        return next.run(request).await;
+    };
+    if let Err(breach) = state.quotas.consume(&scope, QuotaMetric::Requests, 1) {
+        tracing::debug!("Request over quota: {}", breach);
+        return breach.into_response();
+    }
+    next.run(request).await
+}
+
+/// GET /api/v1/usage
+///
+/// Usage and limits of the caller's key and project for the current day
+/// and month.
+pub async fn get_usage(
+    State(state): State<AppState>,
+    Extension(auth): Extension<AuthContext>,
+) -> Json<UsageResponse> {
+    Json(state.quotas.usage(&QuotaScope::new(&auth)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::ProjectQuota;
+
+    // 2024-02-29T12:00:00Z
+    const NOW: u64 = 1_709_208_000;
+
+    fn scope(project_id: Option<u16>) -> QuotaScope {
+        QuotaScope {
+            key: "1/token:abc".to_string(),
+            tenant_id: 1,
+            project_id,
+        }
+    }
+
+    fn config() -> QuotaConfig {
+        QuotaConfig {
+            enabled: true,
+            per_key: QuotaLimits {
+                requests_per_day: Some(2),
+                ..Default::default()
+            },
+            projects: vec![ProjectQuota {
+                project_id: 7,
+                limits: QuotaLimits {
+                    spans_per_month: Some(100),
+                    ..Default::default()
+                },
+            }],
+        }
+    }
+
+    #[test]
+    fn test_period_boundaries() {
+        assert_eq!(QuotaPeriod::Day.reset_at(NOW), 1_709_251_200); // Mar 1
+        assert_eq!(QuotaPeriod::Month.reset_at(NOW), 1_709_251_200);
+        // 2024-12-15 rolls over into January
+        assert_eq!(QuotaPeriod::Month.reset_at(1_734_264_000), 1_735_689_600);
+        assert_eq!(
+            QuotaPeriod::Month.number(NOW) + 1,
+            QuotaPeriod::Month.number(1_709_251_200)
+        );
+    }
+
+    #[test]
+    fn test_enforces_and_resets() {
+        let quotas = QuotaManager::new(config());
+        let key = scope(None);
+        let requests = QuotaMetric::Requests;
+        assert!(quotas.charge(&key, requests, 1, true, NOW).is_ok());
+        assert!(quotas.charge(&key, requests, 1, true, NOW).is_ok());
+        let breach = quotas.charge(&key, requests, 1, true, NOW).unwrap_err();
+        assert_eq!(breach.period, QuotaPeriod::Day);
+        assert_eq!((breach.limit, breach.used), (2, 2));
+        assert_eq!(breach.reset_at, 1_709_251_200);
+
+        // A new day starts from zero; the month keeps counting
+        let tomorrow = NOW + DAY_SECS;
+        assert!(quotas.charge(&key, requests, 1, true, tomorrow).is_ok());
+        let usage = quotas.usage_at(&key, tomorrow);
+        let requests_used: Vec<u64> = usage.subjects[0]
+            .quotas
+            .iter()
+            .filter(|q| q.metric == QuotaMetric::Requests)
+            .map(|q| q.used)
+            .collect();
+        assert_eq!(requests_used, vec![1, 1]);
+    }
+
+    #[test]
+    fn test_project_quota_spans_keys() {
+        let quotas = QuotaManager::new(config());
+        let spans = QuotaMetric::Spans;
+        assert!(quotas.charge(&scope(Some(7)), spans, 80, true, NOW).is_ok());
+
+        // Another key of the same project shares its quota
+        let mut other = scope(Some(7));
+        other.key = "1/token:def".to_string();
+        let breach = quotas.charge(&other, spans, 30, true, NOW).unwrap_err();
+        assert_eq!(
+            breach.subject,
+            QuotaSubject::Project {
+                tenant_id: 1,
+                project_id: 7
+            }
+        );
+        assert_eq!(breach.period, QuotaPeriod::Month);
+
+        // Usage that already happened is recorded past the limit
+        assert!(quotas.charge(&other, spans, 30, false, NOW).is_ok());
+        let project = &quotas.usage_at(&other, NOW).subjects[1];
+        let monthly = project
+            .quotas
+            .iter()
+            .find(|q| q.metric == spans && q.period == QuotaPeriod::Month)
+            .unwrap();
+        assert_eq!((monthly.used, monthly.remaining), (110, Some(0)));
+
+        // Keys without a project quota are unaffected
+        assert!(quotas.charge(&scope(None), spans, 1_000, true, NOW).is_ok());
+    }
+
+    #[test]
+    fn test_spans_charge_their_projects() {
+        let quotas = QuotaManager::new(config());
+        let project = |project_id| QuotaSubject::Project {
+            tenant_id: 1,
+            project_id,
+        };
+
+        // A key scoped to project 3 still pays for spans stored in project 7
+        let key = scope(Some(3));
+        assert!(quotas.charge_spans(&key, [7, 7, 3], NOW).is_ok());
+        let breach = quotas.charge_spans(&key, [3; 99].into_iter().chain([7; 99]), NOW);
+        assert_eq!(breach.unwrap_err().subject, project(7));
+
+        // A refused batch charges none of its subjects
+        let usage = quotas.usage.lock();
+        let spans =
+            |subject: &QuotaSubject| usage[subject].used(QuotaMetric::Spans, QuotaPeriod::Month);
+        assert_eq!(spans(&project(7)), 2);
+        assert_eq!(spans(&project(3)), 1);
+        let key = QuotaSubject::Key {
+            key: "1/token:abc".to_string(),
+        };
+        assert_eq!(spans(&key), 3);
+    }
+
+    #[test]
+    fn test_unverified_callers_share_a_key() {
+        let mut auth = AuthContext {
+            tenant_id: 1,
+            project_id: Some(0),
+            user_id: None,
+            role: Default::default(),
+        };
+        assert_eq!(QuotaScope::new(&auth).key, "1");
+        auth.user_id = Some("key:0123".to_string());
+        assert_eq!(QuotaScope::new(&auth).key, "1/key:0123");
+
+        let addr = "10.0.0.8".parse().ok();
+        assert_eq!(QuotaScope::peer(4, addr).key, "4/ip:10.0.0.8");
+        assert_eq!(QuotaScope::peer(4, None).key, "4");
+    }
+
+    #[test]
+    fn test_disabled_counts_nothing() {
+        let quotas = QuotaManager::new(QuotaConfig {
+            enabled: false,
+            ..config()
+        });
+        for _ in 0..5 {
+            assert!(quotas
+                .consume(&scope(None), QuotaMetric::Requests, 1)
+                .is_ok());
+        }
+        assert!(quotas.usage.lock().is_empty());
+    }
+
+    #[test]
+    fn test_persists_current_month() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join(QUOTA_USAGE_FILE);
+        let quotas = QuotaManager::with_storage(config(), &path);
+        quotas
+            .charge(&scope(Some(7)), QuotaMetric::Tokens, 500, true, NOW)
+            .unwrap();
+        quotas.flush(NOW).unwrap();
+
+        let reloaded = QuotaManager::with_storage(config(), &path);
+        let usage = reloaded.usage_at(&scope(Some(7)), NOW);
+        assert_eq!(usage.subjects.len(), 2);
+        assert!(usage.subjects.iter().all(|s| s
+            .quotas
+            .iter()
+            .any(|q| q.metric == QuotaMetric::Tokens && q.used == 500)));
+
+        // Last month's counters are dropped
+        reloaded.flush(NOW + 31 * DAY_SECS).unwrap();
+        assert!(reloaded.usage.lock().is_empty());
+    }
+}
@@ -0,0 +1,406 @@
+// Copyright 2025 AgentReplay (https://github.com/agentreplay)
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program. If not, see <https://www.gnu.org/licenses/>.
+
+//! Archive tier with on-demand rehydration
+//!
+//! With `archive.dir` set, traces older than `archive.archive_after_days` are
+//! moved into a [`TraceArchive`] (see [`agentreplay_query::archive`]).
+//! Instead of silently returning nothing for cold ranges:
+//!
+//! - `GET /api/v1/traces` over a range that overlaps archived data enqueues a
+//!   rehydration job per archived range and answers `202 Accepted` with the
+//!   hot part of the result plus the job handles.
+//! - `GET /api/v1/rehydration/jobs/:id` reports progress; once the job is
+//!   `completed`, repeating the query returns the full data with `200 OK`.
+//! - Concurrent queries over the same range share one job.
+
+use crate::api::{ApiError, AppState};
+use crate::auth::{AuthContext, Role};
+use crate::config::ArchiveConfig;
+use agentreplay_core::clock::now_secs;
+use agentreplay_query::Agentreplay;
+use agentreplay_storage::{ArchivedRange, LocalFsBackend, StorageBackend, TraceArchive};
+use axum::{
+    extract::{Path, State},
+    Extension, Json,
+};
+use parking_lot::RwLock;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicU32, Ordering};
+use std::sync::Arc;
+use tokio::sync::Semaphore;
+
+/// Finished jobs are kept this long so clients can poll their outcome
+const FINISHED_JOB_TTL_SECS: u64 = 3600;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RehydrationState {
+    Queued,
+    Running,
+    Completed,
+    Failed,
+}
+
+impl RehydrationState {
+    fn is_active(self) -> bool {
+        matches!(self, RehydrationState::Queued | RehydrationState::Running)
+    }
+}
+
+/// Restore of one archived range
+#[derive(Debug, Clone, Serialize)]
+pub struct RehydrationJob {
+    pub id: String,
+    pub range_id: String,
+    pub tenant_id: u64,
+    pub start_us: u64,
+    pub end_us: u64,
+    pub edge_count: usize,
+    pub state: RehydrationState,
+    pub restored_edges: usize,
+    pub error: Option<String>,
+    pub created_at: u64,
+    pub finished_at: Option<u64>,
+}
+
+/// Archive tier plus the rehydration jobs running against it
+pub struct ArchiveManager {
+    archive: Arc<TraceArchive>,
+    config: ArchiveConfig,
+    /// `config.archive_after_days`, changeable on config reload
+    archive_after_days: AtomicU32,
+    jobs: RwLock<HashMap<String, RehydrationJob>>,
+    permits: Arc<Semaphore>,
+}
+
+impl ArchiveManager {
+    /// Open the archive tier, or `None` when `archive.dir` is unset
+    pub fn new(config: &ArchiveConfig) -> anyhow::Result<Option<Arc<Self>>> {
+        let Some(dir) = config.dir.as_ref() else {
+            return Ok(None);
+        };
+
+        let backend: Arc<dyn StorageBackend> = Arc::new(LocalFsBackend::new(dir)?);
+        Ok(Some(Arc::new(Self {
+            archive: Arc::new(TraceArchive::open(backend)?),
+            config: config.clone(),
+            archive_after_days: AtomicU32::new(config.archive_after_days),
+            jobs: RwLock::new(HashMap::new()),
+            permits: Arc::new(Semaphore::new(config.max_concurrent_rehydrations)),
+        })))
+    }
+
+    pub fn archive(&self) -> &TraceArchive {
+        &self.archive
+    }
+
+    pub fn archive_after_days(&self) -> u32 {
+        self.archive_after_days.load(Ordering::Relaxed)
+    }
+
+    /// Change the archive age; 0 pauses the periodic pass
+    pub fn set_archive_after_days(&self, days: u32) {
+        self.archive_after_days.store(days, Ordering::Relaxed);
+    }
+
+    /// Start the periodic archive pass
+    ///
+    /// Passes are skipped while `archive_after_days` is 0.
+    pub fn spawn(self: &Arc<Self>, db: Arc<Agentreplay>) {
+        let manager = self.clone();
+        tokio::spawn(async move {
+            let mut interval = tokio::time::interval(std::time::Duration::from_secs(
+                manager.config.interval_hours * 3600,
+            ));
+            loop {
+                interval.tick().await;
+                let days = manager.archive_after_days();
+                if days == 0 {
+                    continue;
+                }
+                let age_us = days as u64 * 86_400 * 1_000_000;
+                let cutoff_us = (now_secs() * 1_000_000).saturating_sub(age_us);
+                if let Err(e) = db.archive_traces_before(&manager.archive, cutoff_us).await {
+                    tracing::warn!("Archive pass failed: {}", e);
+                }
+            }
+        });
+    }
+
+    /// Ensure every archived range of the tenant overlapping `[start_us, end_us]`
+    /// is being restored
+    ///
+    /// Returns the active jobs for those ranges; empty when nothing in the
+    /// range is archived.
+    pub fn request_rehydration(
+        self: &Arc<Self>,
+        db: &Arc<Agentreplay>,
+        tenant_id: u64,
+        start_us: u64,
+        end_us: u64,
+    ) -> Vec<RehydrationJob> {
+        let ranges = self.archive.overlapping(tenant_id, start_us, end_us);
+        if ranges.is_empty() {
+            return Vec::new();
+        }
+
+        let mut jobs = self.jobs.write();
+        let now = now_secs();
+        jobs.retain(|_, job| {
+            !matches!(job.finished_at, Some(at) if now.saturating_sub(at) >= FINISHED_JOB_TTL_SECS)
+        });
+
+        let mut active = Vec::with_capacity(ranges.len());
+        for range in ranges {
+            if let Some(job) = jobs
+                .values()
+                .find(|j| j.range_id == range.id && j.state.is_active())
+            {
+                active.push(job.clone());
+                continue;
+            }
+            // Restored by a job that finished since the overlap lookup
+            if self.archive.get_range(&range.id).is_none() {
+                continue;
+            }
+
+            let job = new_job(&range, now);
+            jobs.insert(job.id.clone(), job.clone());
+            tokio::spawn(self.clone().run_job(db.clone(), job.id.clone()));
+            active.push(job);
+        }
+        active
+    }
+
+    pub fn job(&self, id: &str) -> Option<RehydrationJob> {
+        self.jobs.read().get(id).cloned()
+    }
+
+    /// Jobs of a tenant, newest first
+    pub fn jobs(&self, tenant_id: u64) -> Vec<RehydrationJob> {
+        let mut jobs: Vec<_> = self
+            .jobs
+            .read()
+            .values()
+            .filter(|j| j.tenant_id == tenant_id)
+            .cloned()
+            .collect();
+        jobs.sort_by(|a, b| b.created_at.cmp(&a.created_at));
+        jobs
+    }
+
+    async fn run_job(self: Arc<Self>, db: Arc<Agentreplay>, job_id: String) {
+        let Ok(_permit) = self.permits.clone().acquire_owned().await else {
+            return;
+        };
+        let Some(range_id) = self.update(&job_id, |job| {
+            job.state = RehydrationState::Running;
+            job.range_id.clone()
+        }) else {
+            return;
+        };
+
+        let archive = self.archive.clone();
+        let result =
+            tokio::task::spawn_blocking(move || db.rehydrate_archived_range(&archive, &range_id))
+                .await
+                .map_err(|e| e.to_string())
+                .and_then(|r| r.map_err(|e| e.to_string()));
+
+        self.update(&job_id, |job| {
+            job.finished_at = Some(now_secs());
+            match result {
+                Ok(restored) => {
+                    job.state = RehydrationState::Completed;
+                    job.restored_edges = restored;
+                }
+                Err(e) => {
+                    tracing::warn!(
+                        "Rehydration of archived range {} failed: {}",
+                        job.range_id,
+                        e
+                    );
+                    job.state = RehydrationState::Failed;
+                    job.error = Some(e);
+                }
+            }
+        });
+    }
+
+    fn update<T>(&self, job_id: &str, f: impl FnOnce(&mut RehydrationJob) -> T) -> Option<T> {
+        self.jobs.write().get_mut(job_id).map(f)
+    }
+}
+
+fn new_job(range: &ArchivedRange, now: u64) -> RehydrationJob {
+    RehydrationJob {
+        id: uuid::Uuid::new_v4().to_string(),
+        range_id: range.id.clone(),
+        tenant_id: range.tenant_id,
+        start_us: range.start_us,
+        end_us: range.end_us,
+        edge_count: range.edge_count,
+        state: RehydrationState::Queued,
+        restored_edges: 0,
+        error: None,
+        created_at: now,
+        finished_at: None,
+    }
+}
+
+fn archive_manager(state: &AppState) -> Result<&Arc<ArchiveManager>, ApiError> {
+    state
+        .archive
+        .as_ref()
+        .ok_or_else(|| ApiError::BadRequest("Archive tier is not configured".to_string()))
+}
+
+#[derive(Debug, Serialize)]
+pub struct ArchivedRangesResponse {
+    pub ranges: Vec<ArchivedRange>,
+    pub total: usize,
+}
+
+/// GET /api/v1/archive/ranges
+pub async fn list_archived_ranges(
+    State(state): State<AppState>,
+    Extension(auth): Extension<AuthContext>,
+) -> Result<Json<ArchivedRangesResponse>, ApiError> {
+    let ranges: Vec<_> = archive_manager(&state)?
+        .archive()
+        .ranges()
+        .into_iter()
+        .filter(|r| r.tenant_id == auth.tenant_id)
+        .collect();
+    Ok(Json(ArchivedRangesResponse {
+        total: ranges.len(),
+        ranges,
+    }))
+}
+
+/// Request to archive old traces now
+#[derive(Debug, Deserialize)]
+pub struct RunArchiveRequest {
+    /// Archive traces older than this many days
+    pub older_than_days: u32,
+}
+
+/// POST /api/v1/archive
+///
+/// Archives the caller's tenant only; the periodic archive pass covers
+/// every tenant.
+pub async fn run_archive(
+    State(state): State<AppState>,
+    Extension(auth): Extension<AuthContext>,
+    Json(req): Json<RunArchiveRequest>,
+) -> Result<Json<ArchivedRangesResponse>, ApiError> {
+    auth.require_role(Role::Admin)?;
+    let manager = archive_manager(&state)?;
+    let age_us = req.older_than_days as u64 * 86_400 * 1_000_000;
+    let cutoff_us = (now_secs() * 1_000_000).saturating_sub(age_us);
+    let ranges = state
+        .db
+        .archive_tenant_traces_before(manager.archive(), auth.tenant_id, cutoff_us)
+        .await
+        .map_err(|e| ApiError::Internal(format!("Archive failed: {}", e)))?;
+    Ok(Json(ArchivedRangesResponse {
+        total: ranges.len(),
+        ranges,
+    }))
+}
+
+#[derive(Debug, Serialize)]
+pub struct RehydrationJobsResponse {
+    pub jobs: Vec<RehydrationJob>,
+}
+
+/// GET /api/v1/rehydration/jobs
+pub async fn list_rehydration_jobs(
+    State(state): State<AppState>,
+    Extension(auth): Extension<AuthContext>,
+) -> Result<Json<RehydrationJobsResponse>, ApiError> {
+    Ok(Json(RehydrationJobsResponse {
+        jobs: archive_manager(&state)?.jobs(auth.tenant_id),
+    }))
+}
+
+/// GET /api/v1/rehydration/jobs/:id
+pub async fn get_rehydration_job(
+    State(state): State<AppState>,
+    Extension(auth): Extension<AuthContext>,
+    Path(id): Path<String>,
+) -> Result<Json<RehydrationJob>, ApiError> {
+    archive_manager(&state)?
+        .job(&id)
+        .filter(|job| job.tenant_id == auth.tenant_id)
+        .map(Json)
+        .ok_or_else(|| ApiError::NotFound(format!("Rehydration job '{}' not found", id)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use agentreplay_core::{AgentFlowEdge, SpanType};
+
+    #[tokio::test]
+    async fn test_query_over_archived_range_rehydrates_once() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let db = Arc::new(Agentreplay::open(dir.path().join("db")).unwrap());
+        let manager = ArchiveManager::new(&ArchiveConfig {
+            dir: Some(dir.path().join("archive")),
+            ..Default::default()
+        })
+        .unwrap()
+        .unwrap();
+
+        for i in 1..=4u64 {
+            let mut edge = AgentFlowEdge::new(1, 0, 1, 1, SpanType::Root, 0);
+            edge.timestamp_us = i * 1000;
+            edge.checksum = edge.compute_checksum();
+            db.insert(edge).await.unwrap();
+        }
+        db.archive_traces_before(manager.archive(), 3000)
+            .await
+            .unwrap();
+
+        assert!(manager.request_rehydration(&db, 1, 3000, 5000).is_empty());
+        assert!(manager.request_rehydration(&db, 2, 0, 5000).is_empty());
+
+        let first = manager.request_rehydration(&db, 1, 0, 5000);
+        let second = manager.request_rehydration(&db, 1, 0, 5000);
+        assert_eq!(first.len(), 1);
+        assert_eq!(second.len(), 1);
+        assert_eq!(first[0].id, second[0].id);
+
+        for _ in 0..100 {
+            if manager.job(&first[0].id).unwrap().state == RehydrationState::Completed {
+                break;
+            }
+            tokio::time::sleep(std::time::Duration::from_millis(10)).await;
+        }
+        let job = manager.job(&first[0].id).unwrap();
+        assert_eq!(job.state, RehydrationState::Completed);
+        assert_eq!(job.restored_edges, 2);
+        assert_eq!(
+            db.query_temporal_range_for_tenant(0, 5000, 1)
+                .unwrap()
+                .len(),
+            4
+        );
+        assert!(manager.request_rehydration(&db, 1, 0, 5000).is_empty());
+    }
+}
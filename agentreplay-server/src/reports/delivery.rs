@@ -0,0 +1,267 @@
+// Copyright 2025 AgentReplay (https://github.com/agentreplay)
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program. If not, see <https://www.gnu.org/licenses/>.
+
+//! Report delivery by email and webhook
+//!
+//! Emails carry a short plain text body with the rendered report attached.
+//! Webhooks receive a JSON document with the report data and the rendered
+//! attachment in base64, so receivers can either forward the file or build
+//! their own view. Digest runs add the digest document.
+
+use std::fmt::Write as _;
+use std::time::Duration;
+
+use base64::{engine::general_purpose::STANDARD, Engine};
+use lettre::message::header::ContentType;
+use lettre::message::{Attachment, Mailbox, MultiPart, SinglePart};
+use lettre::transport::smtp::authentication::Credentials;
+use lettre::{AsyncSmtpTransport, AsyncTransport, Message, Tokio1Executor};
+use serde::{Deserialize, Serialize};
+
+use super::digest::Digest;
+use super::render::ReportFormat;
+use super::schedule::format_date;
+use super::templates::Report;
+use crate::config::SmtpConfig;
+
+const WEBHOOK_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// Where a scheduled report goes
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "lowercase")]
+pub enum Delivery {
+    Email { to: Vec<String> },
+    Webhook { url: String },
+}
+
+impl Delivery {
+    pub fn validate(&self) -> Result<(), String> {
+        match self {
+            Delivery::Email { to } => {
+                if to.is_empty() {
+                    return Err("email delivery needs at least one recipient".to_string());
+                }
+                for address in to {
+                    address
+                        .parse::<Mailbox>()
+                        .map_err(|e| format!("invalid recipient '{}': {}", address, e))?;
+                }
+                Ok(())
+            }
+            Delivery::Webhook { url } => {
+                if url.starts_with("http://") || url.starts_with("https://") {
+                    Ok(())
+                } else {
+                    Err(format!("webhook url '{}' must be http(s)", url))
+                }
+            }
+        }
+    }
+
+    /// Short description for run logs
+    pub fn target(&self) -> String {
+        match self {
+            Delivery::Email { to } => format!("email:{}", to.join(",")),
+            Delivery::Webhook { url } => format!("webhook:{}", url),
+        }
+    }
+}
+
+/// A rendered report ready to send
+pub struct Document<'a> {
+    pub report: &'a Report,
+    pub digest: Option<&'a Digest>,
+    pub format: ReportFormat,
+    pub content: &'a [u8],
+}
+
+impl Document<'_> {
+    pub fn filename(&self) -> String {
+        let slug: String = self
+            .report
+            .title
+            .chars()
+            .map(|c| {
+                if c.is_ascii_alphanumeric() {
+                    c.to_ascii_lowercase()
+                } else {
+                    '-'
+                }
+            })
+            .collect();
+        format!(
+            "{}-{}.{}",
+            slug.trim_matches('-'),
+            format_date(self.report.period_end_us),
+            self.format.extension()
+        )
+    }
+}
+
+pub async fn deliver(
+    delivery: &Delivery,
+    document: &Document<'_>,
+    smtp: Option<&SmtpConfig>,
+) -> Result<(), String> {
+    match delivery {
+        Delivery::Email { to } => {
+            let smtp = smtp
+                .ok_or_else(|| "email delivery needs reports.smtp to be configured".to_string())?;
+            send_email(smtp, to, document).await
+        }
+        Delivery::Webhook { url } => post_webhook(url, document).await,
+    }
+}
+
+async fn send_email(
+    smtp: &SmtpConfig,
+    to: &[String],
+    document: &Document<'_>,
+) -> Result<(), String> {
+    let report = document.report;
+    let mut builder = Message::builder()
+        .from(
+            smtp.from
+                .parse::<Mailbox>()
+                .map_err(|e| format!("invalid sender '{}': {}", smtp.from, e))?,
+        )
+        .subject(format!(
+            "{} ({} to {})",
+            report.title,
+            format_date(report.period_start_us),
+            format_date(report.period_end_us)
+        ));
+    for address in to {
+        builder = builder.to(address
+            .parse::<Mailbox>()
+            .map_err(|e| format!("invalid recipient '{}': {}", address, e))?);
+    }
+
+    let mut body = format!(
+        "{}\n{} to {}, {} spans\n",
+        report.title,
+        format_date(report.period_start_us),
+        format_date(report.period_end_us),
+        report.spans_scanned
+    );
+    for section in &report.sections {
+        let _ = writeln!(body, "\n{}", section.title);
+        for stat in &section.stats {
+            let _ = writeln!(body, "  {}: {}", stat.label, stat.value);
+        }
+    }
+    body.push_str("\nThe full report is attached.\n");
+
+    let content_type = ContentType::parse(document.format.content_type())
+        .map_err(|e| format!("invalid content type: {}", e))?;
+    let message = builder
+        .multipart(
+            MultiPart::mixed()
+                .singlepart(SinglePart::plain(body))
+                .singlepart(
+                    Attachment::new(document.filename())
+                        .body(document.content.to_vec(), content_type),
+                ),
+        )
+        .map_err(|e| format!("failed to build email: {}", e))?;
+
+    let mut transport = if smtp.starttls {
+        AsyncSmtpTransport::<Tokio1Executor>::starttls_relay(&smtp.host)
+            .map_err(|e| format!("invalid SMTP relay '{}': {}", smtp.host, e))?
+    } else {
+        AsyncSmtpTransport::<Tokio1Executor>::builder_dangerous(&smtp.host)
+    }
+    .port(smtp.port);
+    if let (Some(username), Some(password)) = (&smtp.username, &smtp.password) {
+        transport = transport.credentials(Credentials::new(username.clone(), password.clone()));
+    }
+    transport
+        .build()
+        .send(message)
+        .await
+        .map(|_| ())
+        .map_err(|e| format!("SMTP delivery failed: {}", e))
+}
+
+async fn post_webhook(url: &str, document: &Document<'_>) -> Result<(), String> {
+    let mut body = serde_json::json!({
+        "report": document.report,
+        "attachment": {
+            "filename": document.filename(),
+            "content_type": document.format.content_type(),
+            "content_base64": STANDARD.encode(document.content),
+        },
+    });
+    if let Some(digest) = document.digest {
+        body["digest"] = serde_json::json!(digest);
+    }
+    let response = reqwest::Client::new()
+        .post(url)
+        .timeout(WEBHOOK_TIMEOUT)
+        .json(&body)
+        .send()
+        .await
+        .map_err(|e| format!("webhook request failed: {}", e))?;
+    if response.status().is_success() {
+        Ok(())
+    } else {
+        Err(format!("webhook returned {}", response.status()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_delivery_validation() {
+        let email: Delivery = serde_json::from_value(serde_json::json!({
+            "type": "email",
+            "to": ["ops@example.com", "Finance <finance@example.com>"]
+        }))
+        .unwrap();
+        assert!(email.validate().is_ok());
+        assert!(Delivery::Email { to: vec![] }.validate().is_err());
+        assert!(Delivery::Email {
+            to: vec!["not an address".to_string()]
+        }
+        .validate()
+        .is_err());
+        assert!(Delivery::Webhook {
+            url: "ftp://example.com".to_string()
+        }
+        .validate()
+        .is_err());
+    }
+
+    #[test]
+    fn test_filename() {
+        let report = Report {
+            title: "Weekly cost: prod".to_string(),
+            period_start_us: 0,
+            period_end_us: 1_700_000_000_000_000,
+            generated_at_us: 0,
+            spans_scanned: 0,
+            sections: vec![],
+        };
+        let document = Document {
+            report: &report,
+            digest: None,
+            format: ReportFormat::Pdf,
+            content: b"",
+        };
+        assert_eq!(document.filename(), "weekly-cost--prod-2023-11-14.pdf");
+    }
+}
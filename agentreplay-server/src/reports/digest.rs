@@ -0,0 +1,775 @@
+// Copyright 2025 AgentReplay (https://github.com/agentreplay)
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program. If not, see <https://www.gnu.org/licenses/>.
+
+//! Insight digests
+//!
+//! A digest sums up a period of a project (or of the whole tenant): the
+//! regressions the insight engine finds against the period before, how
+//! spend moved per model, failure groups first seen in the period and
+//! experiments that started, finished or reached a decision. A schedule
+//! with the [`Digest`](super::ReportTemplate::Digest) template, typically
+//! weekly on Monday morning, generates one per run. Each digest is kept as a
+//! document, served at `GET /api/v1/digests`, and sent along with the
+//! rendered report to webhooks.
+//!
+//! New failure groups come from [`crate::failures::FailureTracker`], which
+//! only knows the spans ingested since the server started. Experiments are
+//! not scoped to tenants or projects, so every digest lists the same ones.
+
+use std::collections::{HashMap, VecDeque};
+use std::path::{Path as FsPath, PathBuf};
+use std::sync::Arc;
+
+use agentreplay_core::insights::{InsightConfig, InsightEngine, InsightType};
+use agentreplay_query::Agentreplay;
+use axum::{
+    extract::{Path, Query, State},
+    Extension, Json,
+};
+use parking_lot::Mutex;
+use serde::{Deserialize, Serialize};
+use tracing::warn;
+
+use super::templates::{format_cost, stat, table, ReportScope, ReportSection, MAX_TABLE_ROWS};
+use crate::api::aggregate::{resolve_span, Needs};
+use crate::api::experiments::{analyze_experiment, Experiment, ExperimentStatus};
+use crate::api::insights::InsightView;
+use crate::api::{ApiError, AppState};
+use crate::auth::AuthContext;
+use crate::failures::FailureGroupSummary;
+use crate::ingestion::FailureCategory;
+
+/// Digests file under the data directory
+pub const DIGESTS_FILE: &str = "digests.json";
+
+/// Digests kept per tenant and project, half a year of weekly ones
+const MAX_DIGESTS_PER_SCOPE: usize = 26;
+const DEFAULT_LIST_LIMIT: usize = 10;
+
+/// A generated digest
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Digest {
+    pub id: String,
+    pub tenant_id: u64,
+    /// None for a digest over all projects of the tenant
+    #[serde(default)]
+    pub project_id: Option<u16>,
+    pub title: String,
+    pub period_start_us: u64,
+    pub period_end_us: u64,
+    pub generated_at_us: u64,
+    pub spans: usize,
+    /// Spans in the period of the same length before this one
+    pub previous_spans: usize,
+    /// Most severe first
+    pub regressions: Vec<InsightView>,
+    pub cost: CostChange,
+    /// Most failures first
+    pub new_failures: Vec<NewFailureGroup>,
+    pub experiments: Vec<ExperimentHighlight>,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct CostChange {
+    pub cost: f64,
+    pub previous_cost: f64,
+    /// Relative change; None without spend in the previous period
+    pub change: Option<f64>,
+    /// Largest absolute change first
+    pub models: Vec<ModelCostChange>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ModelCostChange {
+    pub model: String,
+    pub spans: usize,
+    pub cost: f64,
+    pub previous_cost: f64,
+    pub change: Option<f64>,
+}
+
+/// A failure group first seen in the digest period
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct NewFailureGroup {
+    pub fingerprint: String,
+    pub project_id: u16,
+    pub category: FailureCategory,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error_type: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tool: Option<String>,
+    pub pattern: String,
+    /// Failures in the digest period
+    pub count: u64,
+    pub first_seen_us: u64,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub example_span_id: Option<String>,
+}
+
+impl From<FailureGroupSummary> for NewFailureGroup {
+    fn from(group: FailureGroupSummary) -> Self {
+        Self {
+            example_span_id: group.examples.first().map(|e| e.span_id.clone()),
+            fingerprint: group.fingerprint,
+            project_id: group.project_id,
+            category: group.category,
+            error_type: group.error_type,
+            tool: group.tool,
+            pattern: group.pattern,
+            count: group.window_count,
+            first_seen_us: group.first_seen_us,
+        }
+    }
+}
+
+/// Why an experiment made the digest
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ExperimentEvent {
+    Completed,
+    /// Still running, but its primary metric has a winner
+    Decided,
+    Started,
+}
+
+impl ExperimentEvent {
+    fn as_str(&self) -> &'static str {
+        match self {
+            ExperimentEvent::Completed => "completed",
+            ExperimentEvent::Decided => "decided",
+            ExperimentEvent::Started => "started",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ExperimentHighlight {
+    pub id: String,
+    pub name: String,
+    pub status: String,
+    pub event: ExperimentEvent,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub winner: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub confidence: Option<f64>,
+}
+
+#[derive(Debug, Default)]
+struct ModelSpend {
+    spans: usize,
+    cost: f64,
+    previous_cost: f64,
+}
+
+fn relative_change(current: f64, previous: f64) -> Option<f64> {
+    (previous > 0.0).then(|| (current - previous) / previous)
+}
+
+fn format_change(change: Option<f64>) -> String {
+    change
+        .map(|c| format!("{:+.1}%", c * 100.0))
+        .unwrap_or_else(|| "-".to_string())
+}
+
+fn cost_change(by_model: HashMap<String, ModelSpend>) -> CostChange {
+    let cost = by_model.values().map(|s| s.cost).sum();
+    let previous_cost = by_model.values().map(|s| s.previous_cost).sum();
+
+    let mut models: Vec<ModelCostChange> = by_model
+        .into_iter()
+        .filter(|(_, spend)| spend.cost > 0.0 || spend.previous_cost > 0.0)
+        .map(|(model, spend)| ModelCostChange {
+            model,
+            spans: spend.spans,
+            cost: spend.cost,
+            previous_cost: spend.previous_cost,
+            change: relative_change(spend.cost, spend.previous_cost),
+        })
+        .collect();
+    models.sort_by(|a, b| {
+        (b.cost - b.previous_cost)
+            .abs()
+            .total_cmp(&(a.cost - a.previous_cost).abs())
+            .then_with(|| a.model.cmp(&b.model))
+    });
+    models.truncate(MAX_TABLE_ROWS);
+
+    CostChange {
+        cost,
+        previous_cost,
+        change: relative_change(cost, previous_cost),
+        models,
+    }
+}
+
+/// Insight types describing something getting worse
+fn is_regression(insight_type: &InsightType) -> bool {
+    match insight_type {
+        InsightType::LatencyAnomaly { change_percent, .. }
+        | InsightType::ErrorRateAnomaly { change_percent, .. }
+        | InsightType::CostAnomaly { change_percent, .. }
+        | InsightType::TokenUsageSpike { change_percent, .. } => *change_percent > 0.0,
+        InsightType::PerformanceRegression { .. } | InsightType::FailurePattern { .. } => true,
+        _ => false,
+    }
+}
+
+/// What an experiment did in `scope`, if anything worth reporting
+fn experiment_event(
+    experiment: &Experiment,
+    decided: bool,
+    scope: &ReportScope,
+) -> Option<ExperimentEvent> {
+    let within =
+        |at: Option<u64>| at.is_some_and(|at| (scope.start_us..scope.end_us).contains(&at));
+    if within(experiment.end_time) {
+        Some(ExperimentEvent::Completed)
+    } else if experiment.status == ExperimentStatus::Running && decided {
+        Some(ExperimentEvent::Decided)
+    } else if within(experiment.start_time) {
+        Some(ExperimentEvent::Started)
+    } else {
+        None
+    }
+}
+
+fn notable_experiments(
+    db: &Agentreplay,
+    scope: &ReportScope,
+) -> Result<Vec<ExperimentHighlight>, ApiError> {
+    let experiments = db
+        .list_experiments()
+        .map_err(|e| ApiError::Internal(e.to_string()))?;
+
+    let mut highlights = Vec::new();
+    for experiment in experiments {
+        let experiment: Experiment = experiment.into();
+        // Only analyze experiments that could make the digest
+        if experiment_event(&experiment, true, scope).is_none() {
+            continue;
+        }
+        let results = db
+            .get_experiment_results(experiment.id)
+            .map_err(|e| ApiError::Internal(e.to_string()))?;
+        let analysis = analyze_experiment(&experiment, &results, None);
+        let Some(event) = experiment_event(&experiment, analysis.winner.is_some(), scope) else {
+            continue;
+        };
+        highlights.push(ExperimentHighlight {
+            id: format!("0x{:x}", experiment.id),
+            name: experiment.name,
+            status: experiment.status.as_str().to_string(),
+            event,
+            winner: analysis.winner,
+            confidence: analysis.confidence,
+        });
+    }
+    highlights.sort_by(|a, b| a.event.cmp(&b.event).then_with(|| a.name.cmp(&b.name)));
+    highlights.truncate(MAX_TABLE_ROWS);
+    Ok(highlights)
+}
+
+/// Build the digest of `scope`
+///
+/// Spans of `scope` and of the period of the same length before it are read
+/// from `shards`; experiments from `experiments_db`. `new_failures` are the
+/// failure groups first seen in the period.
+pub fn build_digest(
+    id: String,
+    title: &str,
+    scope: &ReportScope,
+    shards: Vec<Arc<Agentreplay>>,
+    experiments_db: &Agentreplay,
+    new_failures: Vec<NewFailureGroup>,
+    generated_at_us: u64,
+) -> Result<Digest, ApiError> {
+    let previous_start_us = scope.start_us.saturating_sub(scope.end_us - scope.start_us);
+    let needs = Needs {
+        model: true,
+        prompt_version: false,
+        cost: true,
+    };
+
+    let mut recent = Vec::new();
+    let mut baseline = Vec::new();
+    let mut by_model: HashMap<String, ModelSpend> = HashMap::new();
+    for db in shards {
+        let edges = db
+            .query_temporal_range_for_tenant(previous_start_us, scope.end_us, scope.tenant_id)
+            .map_err(|e| ApiError::Internal(e.to_string()))?;
+        for edge in edges {
+            if scope.project_id.is_some_and(|p| edge.project_id != p) {
+                continue;
+            }
+            let span = resolve_span(&db, &edge, needs);
+            let spend = by_model
+                .entry(span.model.unwrap_or_else(|| "unknown".to_string()))
+                .or_default();
+            if edge.timestamp_us >= scope.start_us {
+                spend.spans += 1;
+                spend.cost += span.cost;
+                recent.push(edge);
+            } else {
+                spend.previous_cost += span.cost;
+                baseline.push(edge);
+            }
+        }
+    }
+
+    let mut regressions: Vec<_> = InsightEngine::new(InsightConfig::default())
+        .generate_insights_from_edges(&recent, &baseline)
+        .into_iter()
+        .filter(|insight| is_regression(&insight.insight_type))
+        .collect();
+    regressions.sort_by(|a, b| {
+        b.severity
+            .cmp(&a.severity)
+            .then(b.confidence.total_cmp(&a.confidence))
+    });
+    regressions.truncate(MAX_TABLE_ROWS);
+
+    let mut new_failures = new_failures;
+    new_failures.truncate(MAX_TABLE_ROWS);
+
+    Ok(Digest {
+        id,
+        tenant_id: scope.tenant_id,
+        project_id: scope.project_id,
+        title: title.to_string(),
+        period_start_us: scope.start_us,
+        period_end_us: scope.end_us,
+        generated_at_us,
+        spans: recent.len(),
+        previous_spans: baseline.len(),
+        regressions: regressions.into_iter().map(InsightView::from).collect(),
+        cost: cost_change(by_model),
+        new_failures,
+        experiments: notable_experiments(experiments_db, scope)?,
+    })
+}
+
+impl Digest {
+    /// Report sections of the digest, in the order of the template
+    pub fn sections(&self) -> Vec<ReportSection> {
+        vec![
+            self.regressions_section(),
+            self.cost_section(),
+            self.failures_section(),
+            self.experiments_section(),
+        ]
+    }
+
+    fn regressions_section(&self) -> ReportSection {
+        let rows = self
+            .regressions
+            .iter()
+            .map(|insight| {
+                vec![
+                    insight.severity.clone(),
+                    insight.insight_type.clone(),
+                    insight.summary.clone(),
+                    format!("{:.2}", insight.confidence),
+                ]
+            })
+            .collect();
+        let spans_change = relative_change(self.spans as f64, self.previous_spans as f64);
+
+        ReportSection {
+            title: "Regressions".to_string(),
+            stats: vec![
+                stat("Regressions", self.regressions.len()),
+                stat("Spans", self.spans),
+                stat("Spans vs previous period", format_change(spans_change)),
+            ],
+            tables: table(&["Severity", "Type", "Summary", "Confidence"], rows)
+                .into_iter()
+                .collect(),
+            note: self
+                .regressions
+                .is_empty()
+                .then(|| "No regressions against the previous period.".to_string()),
+        }
+    }
+
+    fn cost_section(&self) -> ReportSection {
+        let rows = self
+            .cost
+            .models
+            .iter()
+            .map(|model| {
+                vec![
+                    model.model.clone(),
+                    model.spans.to_string(),
+                    format_cost(model.cost),
+                    format_cost(model.previous_cost),
+                    format_change(model.change),
+                ]
+            })
+            .collect();
+
+        ReportSection {
+            title: "Cost changes".to_string(),
+            stats: vec![
+                stat("Cost", format_cost(self.cost.cost)),
+                stat("Previous period", format_cost(self.cost.previous_cost)),
+                stat("Change", format_change(self.cost.change)),
+            ],
+            tables: table(&["Model", "Spans", "Cost", "Previous", "Change"], rows)
+                .into_iter()
+                .collect(),
+            note: self
+                .cost
+                .models
+                .is_empty()
+                .then(|| "No spend in either period.".to_string()),
+        }
+    }
+
+    fn failures_section(&self) -> ReportSection {
+        let rows = self
+            .new_failures
+            .iter()
+            .map(|group| {
+                vec![
+                    group.category.as_str().to_string(),
+                    group.error_type.clone().unwrap_or_else(|| "-".to_string()),
+                    group.pattern.clone(),
+                    group.count.to_string(),
+                    group
+                        .example_span_id
+                        .clone()
+                        .unwrap_or_else(|| "-".to_string()),
+                ]
+            })
+            .collect();
+
+        ReportSection {
+            title: "New failure groups".to_string(),
+            stats: vec![
+                stat("New groups", self.new_failures.len()),
+                stat(
+                    "Failures",
+                    self.new_failures.iter().map(|g| g.count).sum::<u64>(),
+                ),
+            ],
+            tables: table(
+                &["Category", "Error", "Pattern", "Failures", "Example span"],
+                rows,
+            )
+            .into_iter()
+            .collect(),
+            note: self
+                .new_failures
+                .is_empty()
+                .then(|| "No new failure groups in this period.".to_string()),
+        }
+    }
+
+    fn experiments_section(&self) -> ReportSection {
+        let rows = self
+            .experiments
+            .iter()
+            .map(|experiment| {
+                vec![
+                    experiment.name.clone(),
+                    experiment.event.as_str().to_string(),
+                    experiment.status.clone(),
+                    experiment.winner.clone().unwrap_or_else(|| "-".to_string()),
+                    experiment
+                        .confidence
+                        .map(|c| format!("{:.3}", c))
+                        .unwrap_or_else(|| "-".to_string()),
+                ]
+            })
+            .collect();
+
+        ReportSection {
+            title: "Experiments".to_string(),
+            stats: vec![stat("Notable experiments", self.experiments.len())],
+            tables: table(
+                &["Experiment", "Event", "Status", "Winner", "Confidence"],
+                rows,
+            )
+            .into_iter()
+            .collect(),
+            note: self
+                .experiments
+                .is_empty()
+                .then(|| "No experiments started, finished or decided.".to_string()),
+        }
+    }
+}
+
+/// Generated digests, newest first
+#[derive(Default)]
+pub struct DigestStore {
+    storage_path: Option<PathBuf>,
+    digests: Mutex<VecDeque<Digest>>,
+}
+
+impl DigestStore {
+    /// Store persisting digests to `path`, loading any already saved
+    pub fn with_storage(path: impl AsRef<FsPath>) -> Self {
+        let path = path.as_ref().to_path_buf();
+        let digests = match std::fs::read(&path) {
+            Ok(bytes) => serde_json::from_slice::<VecDeque<Digest>>(&bytes).unwrap_or_else(|e| {
+                warn!("Failed to parse {:?}: {}", path, e);
+                VecDeque::new()
+            }),
+            Err(e) => {
+                if e.kind() != std::io::ErrorKind::NotFound {
+                    warn!("Failed to read {:?}: {}", path, e);
+                }
+                VecDeque::new()
+            }
+        };
+        Self {
+            storage_path: Some(path),
+            digests: Mutex::new(digests),
+        }
+    }
+
+    /// Keep `digest`, dropping the oldest of its tenant and project beyond
+    /// [`MAX_DIGESTS_PER_SCOPE`]
+    pub fn insert(&self, digest: Digest) {
+        {
+            let mut digests = self.digests.lock();
+            let scope = (digest.tenant_id, digest.project_id);
+            digests.push_front(digest);
+            let mut kept = 0;
+            digests.retain(|d| {
+                if (d.tenant_id, d.project_id) != scope {
+                    return true;
+                }
+                kept += 1;
+                kept <= MAX_DIGESTS_PER_SCOPE
+            });
+        }
+        if let Err(e) = self.persist() {
+            warn!("Failed to persist digests: {}", e);
+        }
+    }
+
+    /// Digests of a tenant, optionally of one project, newest first
+    pub fn list(&self, tenant_id: u64, project_id: Option<u16>, limit: usize) -> Vec<Digest> {
+        self.digests
+            .lock()
+            .iter()
+            .filter(|d| d.tenant_id == tenant_id)
+            .filter(|d| project_id.is_none_or(|p| d.project_id == Some(p)))
+            .take(limit)
+            .cloned()
+            .collect()
+    }
+
+    pub fn get(&self, tenant_id: u64, id: &str) -> Option<Digest> {
+        self.digests
+            .lock()
+            .iter()
+            .find(|d| d.id == id && d.tenant_id == tenant_id)
+            .cloned()
+    }
+
+    fn persist(&self) -> std::io::Result<()> {
+        let Some(path) = &self.storage_path else {
+            return Ok(());
+        };
+        let bytes = serde_json::to_vec(&*self.digests.lock())?;
+
+        let tmp = path.with_extension("json.tmp");
+        std::fs::write(&tmp, bytes)?;
+        std::fs::rename(tmp, path)
+    }
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ListDigestsParams {
+    #[serde(default)]
+    pub project_id: Option<u16>,
+    #[serde(default)]
+    pub limit: Option<usize>,
+}
+
+/// GET /api/v1/digests?project_id=1&limit=10
+pub async fn list_digests(
+    State(state): State<AppState>,
+    Extension(auth): Extension<AuthContext>,
+    Query(params): Query<ListDigestsParams>,
+) -> Json<Vec<Digest>> {
+    let limit = params.limit.unwrap_or(DEFAULT_LIST_LIMIT);
+    Json(
+        state
+            .reports
+            .digests()
+            .list(auth.tenant_id, params.project_id, limit),
+    )
+}
+
+/// GET /api/v1/digests/:id
+pub async fn get_digest(
+    State(state): State<AppState>,
+    Extension(auth): Extension<AuthContext>,
+    Path(id): Path<String>,
+) -> Result<Json<Digest>, ApiError> {
+    state
+        .reports
+        .digests()
+        .get(auth.tenant_id, &id)
+        .map(Json)
+        .ok_or_else(|| ApiError::NotFound(format!("Digest {} not found", id)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn scope() -> ReportScope {
+        ReportScope {
+            tenant_id: 1,
+            project_id: Some(3),
+            start_us: 1_000,
+            end_us: 2_000,
+        }
+    }
+
+    fn digest(id: &str, tenant_id: u64, project_id: Option<u16>) -> Digest {
+        Digest {
+            id: id.to_string(),
+            tenant_id,
+            project_id,
+            title: "Weekly digest".to_string(),
+            period_start_us: 0,
+            period_end_us: 1,
+            generated_at_us: 1,
+            spans: 0,
+            previous_spans: 0,
+            regressions: vec![],
+            cost: CostChange::default(),
+            new_failures: vec![],
+            experiments: vec![],
+        }
+    }
+
+    fn experiment(status: ExperimentStatus, start: Option<u64>, end: Option<u64>) -> Experiment {
+        Experiment {
+            id: 0x2a,
+            name: "prompt v2".to_string(),
+            description: String::new(),
+            variants: vec![],
+            status,
+            traffic_split: HashMap::new(),
+            metrics: vec![],
+            start_time: start,
+            end_time: end,
+            created_at: 0,
+            updated_at: 0,
+            auto_stop: None,
+        }
+    }
+
+    #[test]
+    fn test_cost_change() {
+        let by_model = HashMap::from([
+            (
+                "gpt-4o".to_string(),
+                ModelSpend {
+                    spans: 10,
+                    cost: 3.0,
+                    previous_cost: 1.0,
+                },
+            ),
+            (
+                "claude-3-haiku".to_string(),
+                ModelSpend {
+                    spans: 5,
+                    cost: 0.5,
+                    previous_cost: 0.0,
+                },
+            ),
+            ("unknown".to_string(), ModelSpend::default()),
+        ]);
+        let cost = cost_change(by_model);
+        assert_eq!(cost.cost, 3.5);
+        assert_eq!(cost.change, Some(2.5));
+        assert_eq!(cost.models.len(), 2);
+        assert_eq!(cost.models[0].model, "gpt-4o");
+        assert_eq!(cost.models[0].change, Some(2.0));
+        assert_eq!(cost.models[1].change, None);
+
+        let section = digest("a", 1, None).cost_section();
+        assert_eq!(section.stats[2].value, "-");
+        assert!(section.note.is_some());
+    }
+
+    #[test]
+    fn test_experiment_events() {
+        let scope = scope();
+        let completed = experiment(ExperimentStatus::Completed, Some(10), Some(1_500));
+        assert_eq!(
+            experiment_event(&completed, false, &scope),
+            Some(ExperimentEvent::Completed)
+        );
+        let running = experiment(ExperimentStatus::Running, Some(10), None);
+        assert_eq!(
+            experiment_event(&running, true, &scope),
+            Some(ExperimentEvent::Decided)
+        );
+        assert_eq!(experiment_event(&running, false, &scope), None);
+        let started = experiment(ExperimentStatus::Running, Some(1_200), None);
+        assert_eq!(
+            experiment_event(&started, false, &scope),
+            Some(ExperimentEvent::Started)
+        );
+        let old = experiment(ExperimentStatus::Completed, Some(10), Some(500));
+        assert_eq!(experiment_event(&old, true, &scope), None);
+
+        assert!(is_regression(&InsightType::LatencyAnomaly {
+            baseline_ms: 100.0,
+            current_ms: 200.0,
+            change_percent: 100.0,
+        }));
+        assert!(!is_regression(&InsightType::CostAnomaly {
+            baseline_cost: 2.0,
+            current_cost: 1.0,
+            change_percent: -50.0,
+        }));
+    }
+
+    #[test]
+    fn test_store_keeps_recent_digests_per_scope() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join(DIGESTS_FILE);
+
+        let store = DigestStore::with_storage(&path);
+        for n in 0..MAX_DIGESTS_PER_SCOPE + 2 {
+            store.insert(digest(&format!("p3-{}", n), 1, Some(3)));
+        }
+        store.insert(digest("tenant-wide", 1, None));
+        store.insert(digest("other-tenant", 2, Some(3)));
+
+        let project = store.list(1, Some(3), usize::MAX);
+        assert_eq!(project.len(), MAX_DIGESTS_PER_SCOPE);
+        assert_eq!(project[0].id, format!("p3-{}", MAX_DIGESTS_PER_SCOPE + 1));
+        assert_eq!(
+            store.list(1, None, usize::MAX).len(),
+            MAX_DIGESTS_PER_SCOPE + 1
+        );
+        assert!(store.get(1, "other-tenant").is_none());
+
+        let reloaded = DigestStore::with_storage(&path);
+        assert_eq!(reloaded.list(1, None, 1)[0].id, "tenant-wide");
+        assert_eq!(reloaded.get(2, "other-tenant").unwrap().project_id, Some(3));
+    }
+}
@@ -0,0 +1,753 @@
+// Copyright 2025 AgentReplay (https://github.com/agentreplay)
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program. If not, see <https://www.gnu.org/licenses/>.
+
+//! Scheduled reports
+//!
+//! A schedule combines one or more [`ReportTemplate`]s, a weekly or monthly
+//! [`Cadence`], an output [`ReportFormat`] and where to deliver the result.
+//! A background task generates due reports and sends them by email or
+//! webhook; reports can also be run on demand or previewed. Schedules with
+//! the digest template also keep each run's [`Digest`] as a document.
+//!
+//! Audit-style compliance reports stay in [`crate::api::compliance`].
+
+pub mod delivery;
+pub mod digest;
+pub mod render;
+pub mod schedule;
+pub mod templates;
+
+pub use delivery::Delivery;
+pub use digest::{Digest, DigestStore, DIGESTS_FILE};
+pub use render::ReportFormat;
+pub use schedule::Cadence;
+pub use templates::{Report, ReportScope, ReportTemplate};
+
+use agentreplay_core::clock::now_us;
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::path::{Path as FsPath, PathBuf};
+use std::sync::Arc;
+use std::time::Duration;
+
+use axum::{
+    extract::{Path, State},
+    http::{header, StatusCode},
+    response::{IntoResponse, Response},
+    Extension, Json,
+};
+use parking_lot::Mutex;
+use serde::{Deserialize, Serialize};
+use tracing::{info, warn};
+
+use crate::api::{ApiError, AppState};
+use crate::auth::{AuthContext, Role};
+use crate::config::ReportsConfig;
+use crate::scaling::run_query;
+
+/// Schedules file under the data directory
+pub const REPORT_SCHEDULES_FILE: &str = "report_schedules.json";
+
+const MAX_RECENT_RUNS: usize = 200;
+const MAX_SCHEDULES_PER_TENANT: usize = 100;
+const MAX_DELIVERIES: usize = 10;
+const MAX_NAME_LEN: usize = 200;
+const DEFAULT_PREVIEW_US: u64 = 7 * 24 * 3_600_000_000;
+const MAX_PREVIEW_US: u64 = 366 * 24 * 3_600_000_000;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ReportSchedule {
+    pub id: String,
+    pub tenant_id: u64,
+    #[serde(default)]
+    pub project_id: Option<u16>,
+    pub name: String,
+    pub templates: Vec<ReportTemplate>,
+    pub cadence: Cadence,
+    pub format: ReportFormat,
+    pub delivery: Vec<Delivery>,
+    pub enabled: bool,
+    pub created_at_us: u64,
+    pub next_run_us: u64,
+    #[serde(default)]
+    pub last_run_us: Option<u64>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct DeliveryOutcome {
+    pub target: String,
+    pub delivered: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+/// One generation of a schedule, scheduled or manual
+#[derive(Debug, Clone, Serialize)]
+pub struct ReportRun {
+    pub schedule_id: String,
+    pub tenant_id: u64,
+    pub started_at_us: u64,
+    pub period_start_us: u64,
+    pub period_end_us: u64,
+    pub spans_scanned: usize,
+    pub deliveries: Vec<DeliveryOutcome>,
+    /// Digest kept by this run, see `GET /api/v1/digests/:id`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub digest_id: Option<String>,
+    /// Set when the report could not be generated at all
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+/// Report schedules and the log of recent runs
+pub struct ReportScheduler {
+    config: ReportsConfig,
+    storage_path: Option<PathBuf>,
+    schedules: Mutex<HashMap<String, ReportSchedule>>,
+    runs: Mutex<VecDeque<ReportRun>>,
+    digests: DigestStore,
+}
+
+impl Default for ReportScheduler {
+    fn default() -> Self {
+        Self::new(ReportsConfig::default())
+    }
+}
+
+impl ReportScheduler {
+    /// In-memory scheduler; schedules are lost on restart
+    pub fn new(config: ReportsConfig) -> Self {
+        Self {
+            config,
+            storage_path: None,
+            schedules: Mutex::new(HashMap::new()),
+            runs: Mutex::new(VecDeque::new()),
+            digests: DigestStore::default(),
+        }
+    }
+
+    /// Scheduler persisting schedules to `path`, loading any already saved
+    pub fn with_storage(config: ReportsConfig, path: impl AsRef<FsPath>) -> Self {
+        let mut scheduler = Self::new(config);
+        let path = path.as_ref().to_path_buf();
+
+        match std::fs::read(&path) {
+            Ok(bytes) => match serde_json::from_slice::<Vec<ReportSchedule>>(&bytes) {
+                Ok(schedules) => {
+                    scheduler.schedules = Mutex::new(
+                        schedules
+                            .into_iter()
+                            .map(|schedule| (schedule.id.clone(), schedule))
+                            .collect(),
+                    );
+                }
+                Err(e) => warn!("Failed to parse {:?}: {}", path, e),
+            },
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => {}
+            Err(e) => warn!("Failed to read {:?}: {}", path, e),
+        }
+
+        scheduler.storage_path = Some(path);
+        scheduler
+    }
+
+    /// Keep generated digests in `digests` instead of in memory
+    pub fn with_digests(mut self, digests: DigestStore) -> Self {
+        self.digests = digests;
+        self
+    }
+
+    pub fn config(&self) -> &ReportsConfig {
+        &self.config
+    }
+
+    pub fn digests(&self) -> &DigestStore {
+        &self.digests
+    }
+
+    pub fn create(&self, schedule: ReportSchedule) -> Result<(), ApiError> {
+        {
+            let mut schedules = self.schedules.lock();
+            let owned = schedules
+                .values()
+                .filter(|s| s.tenant_id == schedule.tenant_id)
+                .count();
+            if owned >= MAX_SCHEDULES_PER_TENANT {
+                return Err(ApiError::BadRequest(format!(
+                    "at most {} report schedules per tenant",
+                    MAX_SCHEDULES_PER_TENANT
+                )));
+            }
+            schedules.insert(schedule.id.clone(), schedule);
+        }
+        self.persist_logged();
+        Ok(())
+    }
+
+    /// Schedules of a tenant, oldest first
+    pub fn list(&self, tenant_id: u64) -> Vec<ReportSchedule> {
+        let mut schedules: Vec<ReportSchedule> = self
+            .schedules
+            .lock()
+            .values()
+            .filter(|s| s.tenant_id == tenant_id)
+            .cloned()
+            .collect();
+        schedules.sort_by(|a, b| (a.created_at_us, &a.id).cmp(&(b.created_at_us, &b.id)));
+        schedules
+    }
+
+    pub fn get(&self, tenant_id: u64, id: &str) -> Option<ReportSchedule> {
+        self.schedules
+            .lock()
+            .get(id)
+            .filter(|s| s.tenant_id == tenant_id)
+            .cloned()
+    }
+
+    pub fn remove(&self, tenant_id: u64, id: &str) -> bool {
+        let removed = {
+            let mut schedules = self.schedules.lock();
+            match schedules.get(id) {
+                Some(s) if s.tenant_id == tenant_id => schedules.remove(id).is_some(),
+                _ => false,
+            }
+        };
+        if removed {
+            self.persist_logged();
+        }
+        removed
+    }
+
+    /// Enabled schedules whose next run is at or before `now_us`
+    pub fn due(&self, now_us: u64) -> Vec<ReportSchedule> {
+        self.schedules
+            .lock()
+            .values()
+            .filter(|s| s.enabled && s.next_run_us <= now_us)
+            .cloned()
+            .collect()
+    }
+
+    /// Record a scheduled run and move the schedule to its next occurrence
+    ///
+    /// Occurrences missed while the server was down are not replayed; the
+    /// next run is the first one after `now_us`.
+    pub fn complete(&self, run: ReportRun, now_us: u64) {
+        let advanced = match self.schedules.lock().get_mut(&run.schedule_id) {
+            Some(schedule) => {
+                schedule.last_run_us = Some(run.started_at_us);
+                schedule.next_run_us = schedule.cadence.next_after(now_us);
+                true
+            }
+            None => false,
+        };
+        self.record_run(run);
+        if advanced {
+            self.persist_logged();
+        }
+    }
+
+    pub fn record_run(&self, run: ReportRun) {
+        let mut runs = self.runs.lock();
+        runs.push_front(run);
+        runs.truncate(MAX_RECENT_RUNS);
+    }
+
+    /// Recent runs of a tenant, newest first
+    pub fn recent_runs(&self, tenant_id: u64) -> Vec<ReportRun> {
+        self.runs
+            .lock()
+            .iter()
+            .filter(|run| run.tenant_id == tenant_id)
+            .cloned()
+            .collect()
+    }
+
+    fn persist_logged(&self) {
+        if let Err(e) = self.persist() {
+            warn!("Failed to persist report schedules: {}", e);
+        }
+    }
+
+    fn persist(&self) -> std::io::Result<()> {
+        let Some(path) = &self.storage_path else {
+            return Ok(());
+        };
+        let schedules: Vec<ReportSchedule> = self.schedules.lock().values().cloned().collect();
+
+        let tmp = path.with_extension("json.tmp");
+        std::fs::write(&tmp, serde_json::to_vec_pretty(&schedules)?)?;
+        std::fs::rename(tmp, path)
+    }
+
+    /// Run due schedules periodically until the server stops
+    pub fn spawn(self: &Arc<Self>, state: AppState) {
+        let scheduler = Arc::clone(self);
+        tokio::spawn(async move {
+            let period = Duration::from_secs(scheduler.config.check_interval_minutes * 60);
+            let mut interval = tokio::time::interval(period);
+            interval.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+            loop {
+                interval.tick().await;
+                for schedule in scheduler.due(now_us()) {
+                    let run = run_schedule(&state, &schedule, schedule.next_run_us).await;
+                    match run.error {
+                        Some(ref e) => warn!("Report schedule '{}' failed: {}", schedule.name, e),
+                        None => info!(
+                            "Report schedule '{}' generated from {} spans",
+                            schedule.name, run.spans_scanned
+                        ),
+                    }
+                    scheduler.complete(run, now_us());
+                }
+            }
+        });
+    }
+}
+
+fn generate_id(now: u64) -> String {
+    format!(
+        "{:032x}",
+        ((rand::random::<u64>() as u128) << 64) | now as u128
+    )
+}
+
+/// Generate a report, and the digest if `templates` include it
+async fn generate(
+    state: &AppState,
+    title: &str,
+    scope: ReportScope,
+    templates: &[ReportTemplate],
+) -> Result<(Report, Option<Digest>), ApiError> {
+    let digest_position = templates.iter().position(|t| *t == ReportTemplate::Digest);
+    let others: Vec<ReportTemplate> = templates
+        .iter()
+        .copied()
+        .filter(|t| *t != ReportTemplate::Digest)
+        .collect();
+
+    let mut report = if others.is_empty() {
+        Report {
+            title: title.to_string(),
+            period_start_us: scope.start_us,
+            period_end_us: scope.end_us,
+            generated_at_us: now_us(),
+            spans_scanned: 0,
+            sections: Vec::new(),
+        }
+    } else {
+        let shards = crate::api::metrics::project_shards(state, scope.project_id)?;
+        let eval_db = state.db.clone();
+        let agent_registry = state.agent_registry.clone();
+        let title = title.to_string();
+        run_query(state, move || {
+            templates::build_report(
+                &title,
+                &scope,
+                &others,
+                shards,
+                &eval_db,
+                &agent_registry,
+                now_us(),
+            )
+        })
+        .await
+        .map_err(|e| ApiError::Internal(format!("Report task panicked: {}", e)))??
+    };
+
+    let Some(position) = digest_position else {
+        return Ok((report, None));
+    };
+    let new_failures: Vec<digest::NewFailureGroup> = state
+        .failures
+        .first_seen_between(scope.tenant_id, scope.project_id, scope.start_us, scope.end_us)
+        .into_iter()
+        .map(Into::into)
+        .collect();
+    let shards = crate::api::metrics::project_shards(state, scope.project_id)?;
+    let experiments_db = state.db.clone();
+    let title = title.to_string();
+    let digest = run_query(state, move || {
+        let now = now_us();
+        digest::build_digest(
+            generate_id(now),
+            &title,
+            &scope,
+            shards,
+            &experiments_db,
+            new_failures,
+            now,
+        )
+    })
+    .await
+    .map_err(|e| ApiError::Internal(format!("Digest task panicked: {}", e)))??;
+
+    report.spans_scanned = report.spans_scanned.max(digest.spans);
+    report.sections.splice(position..position, digest.sections());
+    Ok((report, Some(digest)))
+}
+
+/// Generate the period of `schedule` ending at `period_end_us` and deliver it
+async fn run_schedule(
+    state: &AppState,
+    schedule: &ReportSchedule,
+    period_end_us: u64,
+) -> ReportRun {
+    let period_start_us = schedule.cadence.previous(period_end_us);
+    let mut run = ReportRun {
+        schedule_id: schedule.id.clone(),
+        tenant_id: schedule.tenant_id,
+        started_at_us: now_us(),
+        period_start_us,
+        period_end_us,
+        spans_scanned: 0,
+        deliveries: Vec::new(),
+        digest_id: None,
+        error: None,
+    };
+
+    let scope = ReportScope {
+        tenant_id: schedule.tenant_id,
+        project_id: schedule.project_id,
+        start_us: period_start_us,
+        end_us: period_end_us,
+    };
+    let (report, digest) = match generate(state, &schedule.name, scope, &schedule.templates).await
+    {
+        Ok(generated) => generated,
+        Err(e) => {
+            run.error = Some(e.to_string());
+            return run;
+        }
+    };
+    run.spans_scanned = report.spans_scanned;
+    if let Some(digest) = &digest {
+        run.digest_id = Some(digest.id.clone());
+        state.reports.digests().insert(digest.clone());
+    }
+
+    let content = render::render(&report, schedule.format);
+    let document = delivery::Document {
+        report: &report,
+        digest: digest.as_ref(),
+        format: schedule.format,
+        content: &content,
+    };
+    let smtp = state.reports.config().smtp.as_ref();
+    for target in &schedule.delivery {
+        let result = delivery::deliver(target, &document, smtp).await;
+        run.deliveries.push(DeliveryOutcome {
+            target: target.target(),
+            delivered: result.is_ok(),
+            error: result.err(),
+        });
+    }
+    run
+}
+
+fn require_member(auth: &AuthContext) -> Result<(), ApiError> {
+    if auth.role >= Role::Member {
+        Ok(())
+    } else {
+        Err(ApiError::Forbidden(format!(
+            "role '{}' may not manage report schedules",
+            auth.role.as_str()
+        )))
+    }
+}
+
+fn validate_templates(templates: &[ReportTemplate]) -> Result<(), ApiError> {
+    if templates.is_empty() {
+        return Err(ApiError::BadRequest(
+            "at least one template is required".to_string(),
+        ));
+    }
+    let mut seen = HashSet::new();
+    if let Some(duplicate) = templates.iter().find(|t| !seen.insert(**t)) {
+        return Err(ApiError::BadRequest(format!(
+            "template '{}' is listed more than once",
+            duplicate.title()
+        )));
+    }
+    Ok(())
+}
+
+#[derive(Debug, Deserialize)]
+pub struct CreateScheduleRequest {
+    pub name: String,
+    pub templates: Vec<ReportTemplate>,
+    pub cadence: Cadence,
+    #[serde(default = "default_format")]
+    pub format: ReportFormat,
+    pub delivery: Vec<Delivery>,
+    #[serde(default)]
+    pub project_id: Option<u16>,
+    #[serde(default = "default_enabled")]
+    pub enabled: bool,
+}
+
+fn default_format() -> ReportFormat {
+    ReportFormat::Html
+}
+
+fn default_enabled() -> bool {
+    true
+}
+
+/// GET /api/v1/reports/schedules
+pub async fn list_schedules(
+    State(state): State<AppState>,
+    Extension(auth): Extension<AuthContext>,
+) -> Json<Vec<ReportSchedule>> {
+    Json(state.reports.list(auth.tenant_id))
+}
+
+/// POST /api/v1/reports/schedules
+pub async fn create_schedule(
+    State(state): State<AppState>,
+    Extension(auth): Extension<AuthContext>,
+    Json(request): Json<CreateScheduleRequest>,
+) -> Result<(StatusCode, Json<ReportSchedule>), ApiError> {
+    require_member(&auth)?;
+    let name = request.name.trim();
+    if name.is_empty() || name.chars().count() > MAX_NAME_LEN {
+        return Err(ApiError::BadRequest(format!(
+            "name must be between 1 and {} characters",
+            MAX_NAME_LEN
+        )));
+    }
+    validate_templates(&request.templates)?;
+    request.cadence.validate().map_err(ApiError::BadRequest)?;
+    if request.delivery.is_empty() || request.delivery.len() > MAX_DELIVERIES {
+        return Err(ApiError::BadRequest(format!(
+            "between 1 and {} delivery targets are required",
+            MAX_DELIVERIES
+        )));
+    }
+    for target in &request.delivery {
+        target.validate().map_err(ApiError::BadRequest)?;
+    }
+
+    let now = now_us();
+    let schedule = ReportSchedule {
+        id: generate_id(now),
+        tenant_id: auth.tenant_id,
+        project_id: request.project_id,
+        name: name.to_string(),
+        templates: request.templates,
+        cadence: request.cadence,
+        format: request.format,
+        delivery: request.delivery,
+        enabled: request.enabled,
+        created_at_us: now,
+        next_run_us: request.cadence.next_after(now),
+        last_run_us: None,
+    };
+    state.reports.create(schedule.clone())?;
+    Ok((StatusCode::CREATED, Json(schedule)))
+}
+
+/// DELETE /api/v1/reports/schedules/:id
+pub async fn delete_schedule(
+    State(state): State<AppState>,
+    Extension(auth): Extension<AuthContext>,
+    Path(id): Path<String>,
+) -> Result<StatusCode, ApiError> {
+    require_member(&auth)?;
+    if state.reports.remove(auth.tenant_id, &id) {
+        Ok(StatusCode::NO_CONTENT)
+    } else {
+        Err(ApiError::NotFound(format!(
+            "Report schedule {} not found",
+            id
+        )))
+    }
+}
+
+/// POST /api/v1/reports/schedules/:id/run
+///
+/// Generates and delivers the period ending now without moving the
+/// schedule's next run.
+pub async fn run_schedule_now(
+    State(state): State<AppState>,
+    Extension(auth): Extension<AuthContext>,
+    Path(id): Path<String>,
+) -> Result<Json<ReportRun>, ApiError> {
+    require_member(&auth)?;
+    let schedule = state
+        .reports
+        .get(auth.tenant_id, &id)
+        .ok_or_else(|| ApiError::NotFound(format!("Report schedule {} not found", id)))?;
+    let run = run_schedule(&state, &schedule, now_us()).await;
+    state.reports.record_run(run.clone());
+    Ok(Json(run))
+}
+
+/// GET /api/v1/reports/runs
+pub async fn list_runs(
+    State(state): State<AppState>,
+    Extension(auth): Extension<AuthContext>,
+) -> Json<Vec<ReportRun>> {
+    Json(state.reports.recent_runs(auth.tenant_id))
+}
+
+#[derive(Debug, Deserialize)]
+pub struct PreviewRequest {
+    pub templates: Vec<ReportTemplate>,
+    /// Defaults to seven days before `end_ts`
+    #[serde(default)]
+    pub start_ts: Option<u64>,
+    /// Defaults to now
+    #[serde(default)]
+    pub end_ts: Option<u64>,
+    #[serde(default)]
+    pub project_id: Option<u16>,
+    #[serde(default = "default_format")]
+    pub format: ReportFormat,
+    #[serde(default)]
+    pub title: Option<String>,
+}
+
+/// POST /api/v1/reports/preview
+///
+/// Renders a report without saving or delivering it.
+pub async fn preview_report(
+    State(state): State<AppState>,
+    Extension(auth): Extension<AuthContext>,
+    Json(request): Json<PreviewRequest>,
+) -> Result<Response, ApiError> {
+    validate_templates(&request.templates)?;
+    let end_us = request.end_ts.unwrap_or_else(now_us);
+    let start_us = request
+        .start_ts
+        .unwrap_or(end_us.saturating_sub(DEFAULT_PREVIEW_US));
+    if start_us >= end_us {
+        return Err(ApiError::BadRequest(
+            "start_ts must be before end_ts".to_string(),
+        ));
+    }
+    if end_us - start_us > MAX_PREVIEW_US {
+        return Err(ApiError::BadRequest(
+            "report period may not exceed 366 days".to_string(),
+        ));
+    }
+
+    let scope = ReportScope {
+        tenant_id: auth.tenant_id,
+        project_id: request.project_id,
+        start_us,
+        end_us,
+    };
+    let title = request.title.as_deref().unwrap_or("Report preview");
+    let (report, _) = generate(&state, title, scope, &request.templates).await?;
+    Ok((
+        [(header::CONTENT_TYPE, request.format.content_type())],
+        render::render(&report, request.format),
+    )
+        .into_response())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn schedule(id: &str, tenant_id: u64, next_run_us: u64) -> ReportSchedule {
+        ReportSchedule {
+            id: id.to_string(),
+            tenant_id,
+            project_id: None,
+            name: format!("schedule {}", id),
+            templates: vec![ReportTemplate::CostSummary],
+            cadence: Cadence::Weekly {
+                weekday: 1,
+                hour: 8,
+            },
+            format: ReportFormat::Pdf,
+            delivery: vec![Delivery::Webhook {
+                url: "https://example.com/hook".to_string(),
+            }],
+            enabled: true,
+            created_at_us: 0,
+            next_run_us,
+            last_run_us: None,
+        }
+    }
+
+    fn run(schedule_id: &str, tenant_id: u64, started_at_us: u64) -> ReportRun {
+        ReportRun {
+            schedule_id: schedule_id.to_string(),
+            tenant_id,
+            started_at_us,
+            period_start_us: 0,
+            period_end_us: started_at_us,
+            spans_scanned: 0,
+            deliveries: vec![],
+            digest_id: None,
+            error: None,
+        }
+    }
+
+    #[test]
+    fn test_due_and_complete() {
+        let scheduler = ReportScheduler::default();
+        scheduler.create(schedule("a", 1, 100)).unwrap();
+        scheduler.create(schedule("b", 2, 500)).unwrap();
+
+        let due = scheduler.due(200);
+        assert_eq!(due.len(), 1);
+        assert_eq!(due[0].id, "a");
+
+        scheduler.complete(run("a", 1, 200), 200);
+        assert!(scheduler.due(200).is_empty());
+        let a = scheduler.get(1, "a").unwrap();
+        assert_eq!(a.last_run_us, Some(200));
+        assert_eq!(a.next_run_us, a.cadence.next_after(200));
+
+        // Other tenants can neither see nor delete the schedule
+        assert!(scheduler.get(2, "a").is_none());
+        assert!(!scheduler.remove(2, "a"));
+        assert_eq!(scheduler.recent_runs(1).len(), 1);
+        assert!(scheduler.recent_runs(2).is_empty());
+    }
+
+    #[test]
+    fn test_schedules_persist() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join(REPORT_SCHEDULES_FILE);
+
+        let scheduler = ReportScheduler::with_storage(ReportsConfig::default(), &path);
+        scheduler.create(schedule("a", 1, 100)).unwrap();
+        scheduler.create(schedule("b", 1, 100)).unwrap();
+        assert!(scheduler.remove(1, "b"));
+        scheduler.complete(run("a", 1, 150), 150);
+
+        let reloaded = ReportScheduler::with_storage(ReportsConfig::default(), &path);
+        let schedules = reloaded.list(1);
+        assert_eq!(schedules.len(), 1);
+        assert_eq!(schedules[0].last_run_us, Some(150));
+        assert_eq!(schedules[0].templates, vec![ReportTemplate::CostSummary]);
+    }
+
+    #[test]
+    fn test_validate_templates() {
+        assert!(validate_templates(&[]).is_err());
+        assert!(
+            validate_templates(&[ReportTemplate::EvalTrends, ReportTemplate::EvalTrends]).is_err()
+        );
+        assert!(
+            validate_templates(&[ReportTemplate::EvalTrends, ReportTemplate::TopFailures]).is_ok()
+        );
+    }
+}
@@ -0,0 +1,406 @@
+// Copyright 2025 AgentReplay (https://github.com/agentreplay)
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program. If not, see <https://www.gnu.org/licenses/>.
+
+//! HTML and PDF rendering of reports
+//!
+//! The PDF writer lays reports out as text on A4 pages using the standard
+//! Type 1 fonts every viewer ships, so no font or layout engine is needed.
+//! Tables are set in Courier to keep their columns aligned.
+
+use std::fmt::Write as _;
+
+use serde::{Deserialize, Serialize};
+
+use super::templates::{Report, ReportTable};
+use crate::api::trace_export::format_utc;
+use crate::sanitization::sanitize_string as escape;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ReportFormat {
+    Html,
+    Pdf,
+}
+
+impl ReportFormat {
+    pub fn content_type(&self) -> &'static str {
+        match self {
+            ReportFormat::Html => "text/html; charset=utf-8",
+            ReportFormat::Pdf => "application/pdf",
+        }
+    }
+
+    pub fn extension(&self) -> &'static str {
+        match self {
+            ReportFormat::Html => "html",
+            ReportFormat::Pdf => "pdf",
+        }
+    }
+}
+
+pub fn render(report: &Report, format: ReportFormat) -> Vec<u8> {
+    match format {
+        ReportFormat::Html => render_html(report).into_bytes(),
+        ReportFormat::Pdf => render_pdf(report),
+    }
+}
+
+const STYLE: &str = "body{font-family:-apple-system,BlinkMacSystemFont,'Segoe UI',sans-serif;\
+margin:0 auto;max-width:960px;padding:24px;color:#1f2933;background:#f7f9fb}\
+h1{font-size:22px;margin:0 0 4px}h2{font-size:17px;margin:28px 0 8px}\
+.meta{color:#616e7c;font-size:13px}\
+.stats{display:flex;flex-wrap:wrap;gap:12px;margin:8px 0}\
+.stat{background:#fff;border:1px solid #e4e7eb;border-radius:6px;padding:8px 12px}\
+.stat .value{font-size:18px;font-weight:600}.stat .label{color:#616e7c;font-size:12px}\
+table{border-collapse:collapse;width:100%;margin:8px 0;background:#fff;font-size:13px}\
+th,td{border:1px solid #e4e7eb;padding:4px 8px;text-align:left}th{background:#f0f4f8}\
+.note{color:#616e7c;font-style:italic}\
+footer{margin-top:32px;color:#9aa5b1;font-size:12px}";
+
+/// Standalone HTML document; every value from the report is escaped
+pub fn render_html(report: &Report) -> String {
+    let mut html = String::new();
+    let _ = write!(
+        html,
+        "<!DOCTYPE html>\n<html lang=\"en\"><head><meta charset=\"utf-8\">\
+         <title>{title}</title><style>{style}</style></head><body>\
+         <h1>{title}</h1><div class=\"meta\">{start} to {end} · {spans} spans</div>",
+        title = escape(&report.title),
+        style = STYLE,
+        start = format_utc(report.period_start_us),
+        end = format_utc(report.period_end_us),
+        spans = report.spans_scanned,
+    );
+
+    for section in &report.sections {
+        let _ = write!(
+            html,
+            "<h2>{}</h2><div class=\"stats\">",
+            escape(&section.title)
+        );
+        for stat in &section.stats {
+            let _ = write!(
+                html,
+                "<div class=\"stat\"><div class=\"value\">{}</div><div class=\"label\">{}</div></div>",
+                escape(&stat.value),
+                escape(&stat.label)
+            );
+        }
+        html.push_str("</div>");
+        for table in &section.tables {
+            html.push_str("<table><tr>");
+            for column in &table.columns {
+                let _ = write!(html, "<th>{}</th>", escape(column));
+            }
+            html.push_str("</tr>");
+            for row in &table.rows {
+                html.push_str("<tr>");
+                for cell in row {
+                    let _ = write!(html, "<td>{}</td>", escape(cell));
+                }
+                html.push_str("</tr>");
+            }
+            html.push_str("</table>");
+        }
+        if let Some(note) = &section.note {
+            let _ = write!(html, "<p class=\"note\">{}</p>", escape(note));
+        }
+    }
+
+    let _ = writeln!(
+        html,
+        "<footer>Generated by AgentReplay at {}</footer></body></html>",
+        format_utc(report.generated_at_us)
+    );
+    html
+}
+
+const PAGE_WIDTH: f32 = 595.0;
+const PAGE_HEIGHT: f32 = 842.0;
+const MARGIN: f32 = 50.0;
+/// Characters of Courier at table size that fit between the margins
+const TABLE_CHARS: usize = 98;
+const MAX_CELL_CHARS: usize = 40;
+
+#[derive(Debug, Clone, Copy)]
+enum Font {
+    Regular,
+    Bold,
+    Mono,
+}
+
+impl Font {
+    fn resource(&self) -> &'static str {
+        match self {
+            Font::Regular => "F1",
+            Font::Bold => "F2",
+            Font::Mono => "F3",
+        }
+    }
+}
+
+struct Line {
+    font: Font,
+    size: f32,
+    /// Extra space above the line
+    gap: f32,
+    text: String,
+}
+
+fn line(font: Font, size: f32, gap: f32, text: impl Into<String>) -> Line {
+    Line {
+        font,
+        size,
+        gap,
+        text: text.into(),
+    }
+}
+
+/// Minimal PDF 1.4 document of the report
+pub fn render_pdf(report: &Report) -> Vec<u8> {
+    let mut lines = vec![
+        line(Font::Bold, 18.0, 0.0, report.title.clone()),
+        line(
+            Font::Regular,
+            10.0,
+            2.0,
+            format!(
+                "{} to {} - {} spans",
+                format_utc(report.period_start_us),
+                format_utc(report.period_end_us),
+                report.spans_scanned
+            ),
+        ),
+    ];
+    for section in &report.sections {
+        lines.push(line(Font::Bold, 14.0, 16.0, section.title.clone()));
+        for stat in &section.stats {
+            lines.push(line(
+                Font::Regular,
+                10.0,
+                2.0,
+                format!("{}: {}", stat.label, stat.value),
+            ));
+        }
+        for table in &section.tables {
+            let mut rows = table_lines(table).into_iter();
+            if let Some(header) = rows.next() {
+                lines.push(line(Font::Mono, 8.0, 8.0, header));
+            }
+            lines.extend(rows.map(|row| line(Font::Mono, 8.0, 0.0, row)));
+        }
+        if let Some(note) = &section.note {
+            lines.push(line(Font::Regular, 10.0, 4.0, note.clone()));
+        }
+    }
+    lines.push(line(
+        Font::Regular,
+        8.0,
+        16.0,
+        format!(
+            "Generated by AgentReplay at {}",
+            format_utc(report.generated_at_us)
+        ),
+    ));
+
+    write_pdf(&paginate(&lines))
+}
+
+/// Table rows as fixed-width text, header first
+fn table_lines(table: &ReportTable) -> Vec<String> {
+    let mut widths: Vec<usize> = table.columns.iter().map(|c| c.chars().count()).collect();
+    for row in &table.rows {
+        for (width, cell) in widths.iter_mut().zip(row) {
+            *width = (*width).max(cell.chars().count()).min(MAX_CELL_CHARS);
+        }
+    }
+    let format_row = |cells: &[String]| {
+        let mut text = String::new();
+        for (cell, width) in cells.iter().zip(&widths) {
+            let cell: String = cell.chars().take(*width).collect();
+            let _ = write!(text, "{:<width$}  ", cell, width = width);
+        }
+        text.trim_end()
+            .chars()
+            .take(TABLE_CHARS)
+            .collect::<String>()
+    };
+
+    let mut lines = vec![format_row(&table.columns)];
+    lines.extend(table.rows.iter().map(|row| format_row(row)));
+    lines
+}
+
+/// Content streams of the pages `lines` fill
+fn paginate(lines: &[Line]) -> Vec<String> {
+    let mut pages = Vec::new();
+    let mut content = String::new();
+    let mut y = PAGE_HEIGHT - MARGIN;
+    for line in lines {
+        let advance = line.gap + line.size * 1.35;
+        if y - advance < MARGIN && !content.is_empty() {
+            pages.push(std::mem::take(&mut content));
+            y = PAGE_HEIGHT - MARGIN;
+        }
+        y -= advance;
+        let _ = writeln!(
+            content,
+            "BT /{} {} Tf {} {:.1} Td ({}) Tj ET",
+            line.font.resource(),
+            line.size,
+            MARGIN,
+            y,
+            pdf_string(&line.text)
+        );
+    }
+    pages.push(content);
+    pages
+}
+
+/// Text for a PDF literal string in the standard encoding
+fn pdf_string(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '(' | ')' | '\\' => {
+                escaped.push('\\');
+                escaped.push(c);
+            }
+            ' '..='~' => escaped.push(c),
+            '·' | '–' | '—' => escaped.push('-'),
+            _ => escaped.push('?'),
+        }
+    }
+    escaped
+}
+
+fn write_pdf(pages: &[String]) -> Vec<u8> {
+    // Objects: 1 catalog, 2 page tree, 3-5 fonts, then a page and its
+    // content stream per page
+    let page_ids: Vec<usize> = (0..pages.len()).map(|i| 6 + 2 * i).collect();
+    let mut objects = vec![
+        "<< /Type /Catalog /Pages 2 0 R >>".to_string(),
+        format!(
+            "<< /Type /Pages /Kids [{}] /Count {} >>",
+            page_ids
+                .iter()
+                .map(|id| format!("{} 0 R", id))
+                .collect::<Vec<_>>()
+                .join(" "),
+            pages.len()
+        ),
+    ];
+    for font in ["Helvetica", "Helvetica-Bold", "Courier"] {
+        objects.push(format!(
+            "<< /Type /Font /Subtype /Type1 /BaseFont /{} /Encoding /WinAnsiEncoding >>",
+            font
+        ));
+    }
+    for (page_id, content) in page_ids.iter().zip(pages) {
+        objects.push(format!(
+            "<< /Type /Page /Parent 2 0 R /MediaBox [0 0 {} {}] \
+             /Resources << /Font << /F1 3 0 R /F2 4 0 R /F3 5 0 R >> >> /Contents {} 0 R >>",
+            PAGE_WIDTH,
+            PAGE_HEIGHT,
+            page_id + 1
+        ));
+        objects.push(format!(
+            "<< /Length {} >>\nstream\n{}endstream",
+            content.len(),
+            content
+        ));
+    }
+
+    let mut pdf = b"%PDF-1.4\n".to_vec();
+    let mut offsets = Vec::with_capacity(objects.len());
+    for (i, object) in objects.iter().enumerate() {
+        offsets.push(pdf.len());
+        pdf.extend_from_slice(format!("{} 0 obj\n{}\nendobj\n", i + 1, object).as_bytes());
+    }
+    let xref = pdf.len();
+    let mut trailer = format!("xref\n0 {}\n0000000000 65535 f \n", objects.len() + 1);
+    for offset in offsets {
+        let _ = writeln!(trailer, "{:010} 00000 n ", offset);
+    }
+    let _ = writeln!(
+        trailer,
+        "trailer\n<< /Size {} /Root 1 0 R >>\nstartxref\n{}\n%%EOF",
+        objects.len() + 1,
+        xref
+    );
+    pdf.extend_from_slice(trailer.as_bytes());
+    pdf
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::reports::templates::{ReportSection, ReportStat};
+
+    fn report(rows: usize) -> Report {
+        Report {
+            title: "Weekly <ops> report".to_string(),
+            period_start_us: 1_700_000_000_000_000,
+            period_end_us: 1_700_604_800_000_000,
+            generated_at_us: 1_700_604_800_000_000,
+            spans_scanned: 42,
+            sections: vec![ReportSection {
+                title: "Cost summary".to_string(),
+                stats: vec![ReportStat {
+                    label: "Total cost".to_string(),
+                    value: "$1.23".to_string(),
+                }],
+                tables: vec![ReportTable {
+                    columns: vec!["Model".to_string(), "Cost".to_string()],
+                    rows: (0..rows)
+                        .map(|i| vec![format!("model-({})", i), "$0.01".to_string()])
+                        .collect(),
+                }],
+                note: None,
+            }],
+        }
+    }
+
+    #[test]
+    fn test_html_escapes_values() {
+        let html = render_html(&report(2));
+        assert!(html.contains("Weekly &lt;ops&gt; report"));
+        assert!(html.contains("<td>model-(1)</td>"));
+        assert!(html.ends_with("</html>\n"));
+    }
+
+    #[test]
+    fn test_pdf_structure() {
+        let pdf = render_pdf(&report(3));
+        let text = String::from_utf8(pdf.clone()).unwrap();
+        assert!(text.starts_with("%PDF-1.4\n"));
+        assert!(text.ends_with("%%EOF\n"));
+        assert!(text.contains("(model-\\(2\\)"));
+
+        // startxref points at the cross-reference table
+        let start: usize = text
+            .rsplit("startxref\n")
+            .next()
+            .and_then(|rest| rest.lines().next())
+            .and_then(|offset| offset.parse().ok())
+            .unwrap();
+        assert!(pdf[start..].starts_with(b"xref"));
+        assert!(text.contains("/Count 1 "));
+
+        // Long tables continue on further pages
+        let long = String::from_utf8(render_pdf(&report(200))).unwrap();
+        assert!(!long.contains("/Count 1 "));
+    }
+}
@@ -0,0 +1,178 @@
+// Copyright 2025 AgentReplay (https://github.com/agentreplay)
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program. If not, see <https://www.gnu.org/licenses/>.
+
+//! Weekly and monthly report cadences, in UTC
+
+use serde::{Deserialize, Serialize};
+
+const HOUR_US: u64 = 3_600_000_000;
+const DAY_US: u64 = 24 * HOUR_US;
+
+/// When a schedule runs
+///
+/// Each run covers the period since the previous occurrence, so a weekly
+/// report delivered Monday 08:00 covers the seven days before it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "every", rename_all = "lowercase")]
+pub enum Cadence {
+    /// `weekday` 1 (Monday) to 7 (Sunday)
+    Weekly { weekday: u8, hour: u8 },
+    /// `day` 1 to 28, so every month has it
+    Monthly { day: u8, hour: u8 },
+}
+
+impl Cadence {
+    pub fn validate(&self) -> Result<(), String> {
+        let (valid_day, hour) = match *self {
+            Cadence::Weekly { weekday, hour } => ((1..=7).contains(&weekday), hour),
+            Cadence::Monthly { day, hour } => ((1..=28).contains(&day), hour),
+        };
+        if !valid_day {
+            return Err(match self {
+                Cadence::Weekly { .. } => "weekday must be between 1 (Monday) and 7 (Sunday)",
+                Cadence::Monthly { .. } => "day must be between 1 and 28",
+            }
+            .to_string());
+        }
+        if hour > 23 {
+            return Err("hour must be between 0 and 23".to_string());
+        }
+        Ok(())
+    }
+
+    /// First occurrence strictly after `at_us`
+    pub fn next_after(&self, at_us: u64) -> u64 {
+        let today = (at_us / DAY_US) as i64;
+        match *self {
+            Cadence::Weekly { weekday, hour } => {
+                // 1970-01-01 was a Thursday
+                let current = (today + 3).rem_euclid(7) + 1;
+                let ahead = (weekday as i64 - current).rem_euclid(7);
+                let candidate = (today + ahead) as u64 * DAY_US + hour as u64 * HOUR_US;
+                if candidate > at_us {
+                    candidate
+                } else {
+                    candidate + 7 * DAY_US
+                }
+            }
+            Cadence::Monthly { day, hour } => {
+                let (year, month, _) = civil_from_days(today);
+                let candidate = month_occurrence(year, month, day, hour);
+                if candidate > at_us {
+                    candidate
+                } else {
+                    let (year, month) = add_months(year, month, 1);
+                    month_occurrence(year, month, day, hour)
+                }
+            }
+        }
+    }
+
+    /// Occurrence one period before `occurrence_us`
+    pub fn previous(&self, occurrence_us: u64) -> u64 {
+        match *self {
+            Cadence::Weekly { .. } => occurrence_us.saturating_sub(7 * DAY_US),
+            Cadence::Monthly { day, hour } => {
+                let (year, month, _) = civil_from_days((occurrence_us / DAY_US) as i64);
+                let (year, month) = add_months(year, month, -1);
+                month_occurrence(year, month, day, hour)
+            }
+        }
+    }
+}
+
+fn month_occurrence(year: i64, month: u32, day: u8, hour: u8) -> u64 {
+    days_from_civil(year, month, day as u32) as u64 * DAY_US + hour as u64 * HOUR_US
+}
+
+fn add_months(year: i64, month: u32, delta: i64) -> (i64, u32) {
+    let index = year * 12 + (month as i64 - 1) + delta;
+    (index.div_euclid(12), (index.rem_euclid(12) + 1) as u32)
+}
+
+/// Civil date of days since 1970-01-01 (Howard Hinnant's algorithm)
+pub(crate) fn civil_from_days(days: i64) -> (i64, u32, u32) {
+    let z = days + 719_468;
+    let era = z.div_euclid(146_097);
+    let doe = z - era * 146_097;
+    let yoe = (doe - doe / 1_460 + doe / 36_524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = (doy - (153 * mp + 2) / 5 + 1) as u32;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 } as u32;
+    (yoe + era * 400 + i64::from(month <= 2), month, day)
+}
+
+/// Days since 1970-01-01 of a civil date
+pub(crate) fn days_from_civil(year: i64, month: u32, day: u32) -> i64 {
+    let year = if month <= 2 { year - 1 } else { year };
+    let era = year.div_euclid(400);
+    let yoe = year - era * 400;
+    let mp = (month as i64 + 9) % 12;
+    let doy = (153 * mp + 2) / 5 + day as i64 - 1;
+    let doe = yoe * 365 + yoe / 4 - yoe / 100 + doy;
+    era * 146_097 + doe - 719_468
+}
+
+/// `YYYY-MM-DD` of a timestamp
+pub(crate) fn format_date(us: u64) -> String {
+    let (year, month, day) = civil_from_days((us / DAY_US) as i64);
+    format!("{:04}-{:02}-{:02}", year, month, day)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn at(year: i64, month: u32, day: u32, hour: u64) -> u64 {
+        days_from_civil(year, month, day) as u64 * DAY_US + hour * HOUR_US
+    }
+
+    #[test]
+    fn test_civil_round_trip() {
+        assert_eq!(days_from_civil(1970, 1, 1), 0);
+        assert_eq!(civil_from_days(days_from_civil(2024, 2, 29)), (2024, 2, 29));
+        assert_eq!(format_date(at(2025, 12, 31, 23)), "2025-12-31");
+    }
+
+    #[test]
+    fn test_weekly_cadence() {
+        let cadence = Cadence::Weekly {
+            weekday: 1,
+            hour: 8,
+        };
+        // Wednesday 2025-01-15 -> Monday 2025-01-20 08:00
+        assert_eq!(cadence.next_after(at(2025, 1, 15, 12)), at(2025, 1, 20, 8));
+        // Exactly at an occurrence moves to the next one
+        assert_eq!(cadence.next_after(at(2025, 1, 20, 8)), at(2025, 1, 27, 8));
+        assert_eq!(cadence.previous(at(2025, 1, 20, 8)), at(2025, 1, 13, 8));
+    }
+
+    #[test]
+    fn test_monthly_cadence() {
+        let cadence = Cadence::Monthly { day: 1, hour: 6 };
+        assert_eq!(cadence.next_after(at(2025, 1, 15, 0)), at(2025, 2, 1, 6));
+        assert_eq!(cadence.next_after(at(2025, 12, 1, 7)), at(2026, 1, 1, 6));
+        assert_eq!(cadence.previous(at(2025, 1, 1, 6)), at(2024, 12, 1, 6));
+
+        assert!(Cadence::Monthly { day: 31, hour: 0 }.validate().is_err());
+        assert!(Cadence::Weekly {
+            weekday: 0,
+            hour: 0
+        }
+        .validate()
+        .is_err());
+    }
+}
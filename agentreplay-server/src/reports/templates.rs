@@ -0,0 +1,539 @@
+// Copyright 2025 AgentReplay (https://github.com/agentreplay)
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program. If not, see <https://www.gnu.org/licenses/>.
+
+//! Report templates and the data behind them
+//!
+//! All templates of a report are filled from one scan of the tenant's spans
+//! in the report period. Each template becomes a [`ReportSection`] of
+//! headline stats and tables, which the renderers lay out without knowing
+//! which template produced it.
+
+use std::collections::{BTreeMap, HashMap};
+use std::sync::Arc;
+
+use agentreplay_core::{AgentFlowEdge, SpanType};
+use agentreplay_query::Agentreplay;
+use serde::{Deserialize, Serialize};
+
+use super::schedule::format_date;
+use crate::agent_registry::AgentRegistry;
+use crate::api::aggregate::{resolve_span, Needs};
+use crate::api::query::ApiError;
+use crate::otel_genai::GenAIPayload;
+
+const DAY_US: u64 = 86_400_000_000;
+/// Rows kept per report table
+pub(super) const MAX_TABLE_ROWS: usize = 20;
+/// Edges per eval metric lookup
+const EVAL_BATCH: usize = 1000;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ReportTemplate {
+    /// Spend, tokens and spans by model and day
+    CostSummary,
+    /// Eval metric averages and how they moved over the period
+    EvalTrends,
+    /// Most frequent failures by agent and error type
+    TopFailures,
+    /// Spans flagged as containing PII or secrets
+    Compliance,
+    /// Regressions, cost changes, new failure groups and experiments, kept
+    /// as a digest document (see [`super::digest`])
+    Digest,
+}
+
+impl ReportTemplate {
+    pub fn title(&self) -> &'static str {
+        match self {
+            ReportTemplate::CostSummary => "Cost summary",
+            ReportTemplate::EvalTrends => "Eval trends",
+            ReportTemplate::TopFailures => "Top failures",
+            ReportTemplate::Compliance => "Compliance",
+            ReportTemplate::Digest => "Weekly digest",
+        }
+    }
+}
+
+/// Spans a report covers
+#[derive(Debug, Clone, Copy)]
+pub struct ReportScope {
+    pub tenant_id: u64,
+    pub project_id: Option<u16>,
+    pub start_us: u64,
+    pub end_us: u64,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct Report {
+    pub title: String,
+    pub period_start_us: u64,
+    pub period_end_us: u64,
+    pub generated_at_us: u64,
+    pub spans_scanned: usize,
+    pub sections: Vec<ReportSection>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ReportSection {
+    pub title: String,
+    pub stats: Vec<ReportStat>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub tables: Vec<ReportTable>,
+    /// Shown when the section has nothing to report
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub note: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ReportStat {
+    pub label: String,
+    pub value: String,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ReportTable {
+    pub columns: Vec<String>,
+    pub rows: Vec<Vec<String>>,
+}
+
+pub(super) fn stat(label: &str, value: impl ToString) -> ReportStat {
+    ReportStat {
+        label: label.to_string(),
+        value: value.to_string(),
+    }
+}
+
+pub(super) fn table(columns: &[&str], rows: Vec<Vec<String>>) -> Option<ReportTable> {
+    if rows.is_empty() {
+        return None;
+    }
+    Some(ReportTable {
+        columns: columns.iter().map(|c| c.to_string()).collect(),
+        rows,
+    })
+}
+
+pub(super) fn format_cost(cost: f64) -> String {
+    if cost > 0.0 && cost < 0.01 {
+        format!("${:.4}", cost)
+    } else {
+        format!("${:.2}", cost)
+    }
+}
+
+fn format_percent(part: usize, whole: usize) -> String {
+    if whole == 0 {
+        "0.0%".to_string()
+    } else {
+        format!("{:.1}%", part as f64 / whole as f64 * 100.0)
+    }
+}
+
+#[derive(Debug, Default)]
+struct Usage {
+    spans: usize,
+    tokens: u64,
+    cost: f64,
+}
+
+#[derive(Debug, Default)]
+struct Failure {
+    count: usize,
+    last_seen_us: u64,
+    example_trace: String,
+}
+
+#[derive(Debug, Default)]
+struct MetricTrend {
+    count: usize,
+    sum: f64,
+    /// (count, sum) before and after the period midpoint
+    halves: [(usize, f64); 2],
+}
+
+/// Everything the selected templates need, gathered in one pass
+#[derive(Debug, Default)]
+struct Collector {
+    spans: usize,
+    errors: usize,
+    by_model: HashMap<String, Usage>,
+    by_day: BTreeMap<u64, Usage>,
+    failures: HashMap<(String, String), Failure>,
+    edge_ids: Vec<u128>,
+    pii: usize,
+    secrets: usize,
+    sensitive_projects: BTreeMap<u16, usize>,
+}
+
+/// Build a report of `templates` over `scope`
+///
+/// Spans are read from `shards`; eval metrics, which are kept by the
+/// default database, from `eval_db`.
+pub fn build_report(
+    title: &str,
+    scope: &ReportScope,
+    templates: &[ReportTemplate],
+    shards: Vec<Arc<Agentreplay>>,
+    eval_db: &Agentreplay,
+    agent_registry: &AgentRegistry,
+    generated_at_us: u64,
+) -> Result<Report, ApiError> {
+    let wants = |template| templates.contains(&template);
+    let needs = Needs {
+        model: wants(ReportTemplate::CostSummary),
+        prompt_version: false,
+        cost: wants(ReportTemplate::CostSummary),
+    };
+
+    let mut collector = Collector::default();
+    for db in shards {
+        let edges = db
+            .query_temporal_range_for_tenant(scope.start_us, scope.end_us, scope.tenant_id)
+            .map_err(|e| ApiError::Internal(e.to_string()))?;
+        for edge in &edges {
+            if scope.project_id.is_some_and(|p| edge.project_id != p) {
+                continue;
+            }
+            collector.spans += 1;
+            if wants(ReportTemplate::CostSummary) {
+                let span = resolve_span(&db, edge, needs);
+                let model = span.model.unwrap_or_else(|| "unknown".to_string());
+                for usage in [
+                    collector.by_model.entry(model).or_default(),
+                    collector
+                        .by_day
+                        .entry(edge.timestamp_us / DAY_US)
+                        .or_default(),
+                ] {
+                    usage.spans += 1;
+                    usage.tokens += edge.token_count as u64;
+                    usage.cost += span.cost;
+                }
+            }
+            if wants(ReportTemplate::TopFailures) && edge.get_span_type() == SpanType::Error {
+                collector.errors += 1;
+                let key = (
+                    agent_registry.get_display_name(edge.agent_id),
+                    error_type(&db, edge),
+                );
+                let failure = collector.failures.entry(key).or_default();
+                failure.count += 1;
+                if edge.timestamp_us >= failure.last_seen_us {
+                    failure.last_seen_us = edge.timestamp_us;
+                    failure.example_trace = format!("{:#x}", edge.session_id);
+                }
+            }
+            if wants(ReportTemplate::EvalTrends) {
+                collector.edge_ids.push(edge.edge_id);
+            }
+            if wants(ReportTemplate::Compliance) && (edge.has_pii() || edge.has_secrets()) {
+                if edge.has_pii() {
+                    collector.pii += 1;
+                }
+                if edge.has_secrets() {
+                    collector.secrets += 1;
+                }
+                *collector
+                    .sensitive_projects
+                    .entry(edge.project_id)
+                    .or_default() += 1;
+            }
+        }
+    }
+
+    let mut sections = Vec::with_capacity(templates.len());
+    for template in templates {
+        sections.push(match template {
+            ReportTemplate::CostSummary => cost_section(&collector),
+            ReportTemplate::EvalTrends => eval_section(&collector, eval_db, scope)?,
+            ReportTemplate::TopFailures => failures_section(&collector),
+            ReportTemplate::Compliance => compliance_section(&collector),
+            // Built by the caller, which needs more than the period's spans
+            ReportTemplate::Digest => continue,
+        });
+    }
+
+    Ok(Report {
+        title: title.to_string(),
+        period_start_us: scope.start_us,
+        period_end_us: scope.end_us,
+        generated_at_us,
+        spans_scanned: collector.spans,
+        sections,
+    })
+}
+
+fn error_type(db: &Agentreplay, edge: &AgentFlowEdge) -> String {
+    if edge.has_payload == 0 {
+        return "unknown".to_string();
+    }
+    db.get_payload(edge.edge_id)
+        .ok()
+        .flatten()
+        .and_then(|bytes| serde_json::from_slice::<GenAIPayload>(&bytes).ok())
+        .and_then(|payload| payload.error_type)
+        .unwrap_or_else(|| "unknown".to_string())
+}
+
+fn cost_section(collector: &Collector) -> ReportSection {
+    let total_cost: f64 = collector.by_model.values().map(|u| u.cost).sum();
+    let total_tokens: u64 = collector.by_model.values().map(|u| u.tokens).sum();
+
+    let mut models: Vec<(&String, &Usage)> = collector.by_model.iter().collect();
+    models.sort_by(|a, b| b.1.cost.total_cmp(&a.1.cost).then_with(|| a.0.cmp(b.0)));
+    let model_rows = models
+        .into_iter()
+        .take(MAX_TABLE_ROWS)
+        .map(|(model, usage)| {
+            vec![
+                model.clone(),
+                usage.spans.to_string(),
+                usage.tokens.to_string(),
+                format_cost(usage.cost),
+            ]
+        })
+        .collect();
+    let day_rows = collector
+        .by_day
+        .iter()
+        .map(|(day, usage)| {
+            vec![
+                format_date(day * DAY_US),
+                usage.spans.to_string(),
+                usage.tokens.to_string(),
+                format_cost(usage.cost),
+            ]
+        })
+        .collect();
+
+    ReportSection {
+        title: ReportTemplate::CostSummary.title().to_string(),
+        stats: vec![
+            stat("Total cost", format_cost(total_cost)),
+            stat("Tokens", total_tokens),
+            stat("Spans", collector.spans),
+            stat("Models", collector.by_model.len()),
+        ],
+        tables: [
+            table(&["Model", "Spans", "Tokens", "Cost"], model_rows),
+            table(&["Day", "Spans", "Tokens", "Cost"], day_rows),
+        ]
+        .into_iter()
+        .flatten()
+        .collect(),
+        note: (collector.spans == 0).then(|| "No spans in this period.".to_string()),
+    }
+}
+
+fn eval_section(
+    collector: &Collector,
+    eval_db: &Agentreplay,
+    scope: &ReportScope,
+) -> Result<ReportSection, ApiError> {
+    let midpoint = scope.start_us + (scope.end_us - scope.start_us) / 2;
+    let mut trends: BTreeMap<String, MetricTrend> = BTreeMap::new();
+    for chunk in collector.edge_ids.chunks(EVAL_BATCH) {
+        let metrics = eval_db
+            .get_eval_metrics_batch(chunk)
+            .map_err(|e| ApiError::Internal(e.to_string()))?;
+        for metric in metrics.into_values().flatten() {
+            let trend = trends
+                .entry(metric.get_metric_name().to_string())
+                .or_default();
+            trend.count += 1;
+            trend.sum += metric.metric_value;
+            let half = &mut trend.halves[usize::from(metric.timestamp_us >= midpoint)];
+            half.0 += 1;
+            half.1 += metric.metric_value;
+        }
+    }
+
+    let average = |(count, sum): (usize, f64)| (count > 0).then(|| sum / count as f64);
+    let rows: Vec<Vec<String>> = trends
+        .iter()
+        .take(MAX_TABLE_ROWS)
+        .map(|(name, trend)| {
+            let first = average(trend.halves[0]);
+            let second = average(trend.halves[1]);
+            let change = match (first, second) {
+                (Some(first), Some(second)) => format!("{:+.3}", second - first),
+                _ => "-".to_string(),
+            };
+            let show = |value: Option<f64>| {
+                value
+                    .map(|v| format!("{:.3}", v))
+                    .unwrap_or_else(|| "-".to_string())
+            };
+            vec![
+                name.clone(),
+                trend.count.to_string(),
+                show(average((trend.count, trend.sum))),
+                show(first),
+                show(second),
+                change,
+            ]
+        })
+        .collect();
+    let evaluations: usize = trends.values().map(|t| t.count).sum();
+
+    Ok(ReportSection {
+        title: ReportTemplate::EvalTrends.title().to_string(),
+        stats: vec![
+            stat("Evaluations", evaluations),
+            stat("Metrics", trends.len()),
+        ],
+        tables: table(
+            &[
+                "Metric",
+                "Evaluations",
+                "Average",
+                "First half",
+                "Second half",
+                "Change",
+            ],
+            rows,
+        )
+        .into_iter()
+        .collect(),
+        note: trends
+            .is_empty()
+            .then(|| "No eval metrics recorded in this period.".to_string()),
+    })
+}
+
+fn failures_section(collector: &Collector) -> ReportSection {
+    let mut failures: Vec<(&(String, String), &Failure)> = collector.failures.iter().collect();
+    failures.sort_by(|a, b| b.1.count.cmp(&a.1.count).then_with(|| a.0.cmp(b.0)));
+    let rows = failures
+        .into_iter()
+        .take(MAX_TABLE_ROWS)
+        .map(|((agent, error), failure)| {
+            vec![
+                agent.clone(),
+                error.clone(),
+                failure.count.to_string(),
+                format_percent(failure.count, collector.errors),
+                failure.example_trace.clone(),
+            ]
+        })
+        .collect();
+
+    ReportSection {
+        title: ReportTemplate::TopFailures.title().to_string(),
+        stats: vec![
+            stat("Failed spans", collector.errors),
+            stat(
+                "Error rate",
+                format_percent(collector.errors, collector.spans),
+            ),
+            stat("Distinct failures", collector.failures.len()),
+        ],
+        tables: table(&["Agent", "Error", "Count", "Share", "Latest trace"], rows)
+            .into_iter()
+            .collect(),
+        note: (collector.errors == 0).then(|| "No failures in this period.".to_string()),
+    }
+}
+
+fn compliance_section(collector: &Collector) -> ReportSection {
+    let rows = collector
+        .sensitive_projects
+        .iter()
+        .take(MAX_TABLE_ROWS)
+        .map(|(project_id, count)| vec![project_id.to_string(), count.to_string()])
+        .collect();
+
+    ReportSection {
+        title: ReportTemplate::Compliance.title().to_string(),
+        stats: vec![
+            stat("Spans with PII", collector.pii),
+            stat("Spans with secrets", collector.secrets),
+            stat(
+                "Sensitive share",
+                format_percent(collector.sensitive_projects.values().sum(), collector.spans),
+            ),
+        ],
+        tables: table(&["Project", "Sensitive spans"], rows)
+            .into_iter()
+            .collect(),
+        note: collector
+            .sensitive_projects
+            .is_empty()
+            .then(|| "No spans were flagged as sensitive.".to_string()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_sections_from_collector() {
+        let mut collector = Collector {
+            spans: 10,
+            errors: 3,
+            ..Default::default()
+        };
+        collector.by_model.insert(
+            "gpt-4o".to_string(),
+            Usage {
+                spans: 6,
+                tokens: 1200,
+                cost: 0.42,
+            },
+        );
+        collector.by_model.insert(
+            "claude-3-haiku".to_string(),
+            Usage {
+                spans: 4,
+                tokens: 800,
+                cost: 0.004,
+            },
+        );
+        collector.failures.insert(
+            ("planner".to_string(), "RateLimitError".to_string()),
+            Failure {
+                count: 2,
+                last_seen_us: 5,
+                example_trace: "0x2a".to_string(),
+            },
+        );
+        collector.failures.insert(
+            ("retriever".to_string(), "timeout".to_string()),
+            Failure {
+                count: 1,
+                last_seen_us: 3,
+                example_trace: "0x7".to_string(),
+            },
+        );
+
+        let cost = cost_section(&collector);
+        assert_eq!(cost.stats[0].value, "$0.42");
+        let rows = &cost.tables[0].rows;
+        assert_eq!(rows[0][0], "gpt-4o");
+        assert_eq!(rows[1][3], "$0.0040");
+
+        let failures = failures_section(&collector);
+        assert_eq!(failures.stats[1].value, "30.0%");
+        let rows = &failures.tables[0].rows;
+        assert_eq!(rows[0][1], "RateLimitError");
+        assert_eq!(rows[0][3], "66.7%");
+
+        let compliance = compliance_section(&collector);
+        assert!(compliance.tables.is_empty());
+        assert!(compliance.note.is_some());
+    }
+}
@@ -0,0 +1,649 @@
+// Copyright 2025 AgentReplay (https://github.com/agentreplay)
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program. If not, see <https://www.gnu.org/licenses/>.
+
+//! Response cache of the chat completion proxy
+//!
+//! With caching on for a project, `POST /api/v1/chat/completions` answers a
+//! request from an earlier completion when the same tenant and project sent
+//! the same messages to the same provider and model within the TTL. With a
+//! `similarity_threshold`, requests whose messages embed within that cosine
+//! similarity of a cached request's also hit, which catches agent loops
+//! re-sending a prompt with small changes.
+//!
+//! A hit is recorded as its own span carrying `agentreplay.cache.*`
+//! attributes and a `follows_from` link to the span that produced the
+//! answer. It has no token usage, so it adds no cost. Spans of misses get
+//! `agentreplay.cache.hit = "false"`. Output guardrails run on cached
+//! answers as on fresh ones, and answers they block are not cached.
+//! Streamed completions bypass the cache.
+//!
+//! The `[response_cache]` settings apply to every project unless a project
+//! overrides them; requests name their project with `project_id` (default 0).
+//!
+//! ```toml
+//! [response_cache]
+//! enabled = false
+//! ttl_secs = 3600
+//!
+//! [[response_cache.projects]]
+//! project_id = 7
+//! enabled = true
+//! ttl_secs = 600
+//! similarity_threshold = 0.97
+//! ```
+
+use std::collections::{BTreeSet, HashMap, VecDeque};
+use std::time::{Duration, Instant};
+
+use agentreplay_index::embedding::normalize::cosine_similarity_normalized;
+use agentreplay_index::embedding::{EmbeddingProvider, LocalEmbeddingProvider};
+use axum::{extract::State, Extension, Json};
+use parking_lot::Mutex;
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use sha2::{Digest, Sha256};
+
+use crate::api::AppState;
+use crate::auth::AuthContext;
+use crate::config::ResponseCacheConfig;
+use crate::ingestion::ATTR_SPAN_LINKS;
+use crate::llm::{ChatMessage, ChatResponse};
+use crate::otel_genai::ModelPricing;
+
+/// Cache settings of one project, overriding the section's defaults
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ProjectCacheConfig {
+    pub project_id: u16,
+    #[serde(default)]
+    pub enabled: Option<bool>,
+    #[serde(default)]
+    pub ttl_secs: Option<u64>,
+    #[serde(default)]
+    pub similarity_threshold: Option<f32>,
+}
+
+/// How the requests of a project are cached
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct CachePolicy {
+    pub ttl: Duration,
+    /// Cosine similarity at which two requests count as the same; exact
+    /// matches only when unset
+    pub similarity_threshold: Option<f32>,
+}
+
+/// Requests that may share answers
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+struct Scope {
+    tenant_id: u64,
+    project_id: u16,
+    provider: String,
+    model: Option<String>,
+}
+
+/// A request looked up in (and later stored into) the cache
+pub struct CacheKey {
+    scope: Scope,
+    hash: String,
+    embedding: Option<Vec<f32>>,
+    policy: CachePolicy,
+}
+
+struct CacheEntry {
+    scope: Scope,
+    embedding: Option<Vec<f32>>,
+    response: ChatResponse,
+    edge_id: u128,
+    stored_at: Instant,
+    expires_at: Instant,
+}
+
+/// How a cached answer matched the request
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CacheMatch {
+    Exact,
+    Semantic,
+}
+
+impl CacheMatch {
+    fn as_str(self) -> &'static str {
+        match self {
+            CacheMatch::Exact => "exact",
+            CacheMatch::Semantic => "semantic",
+        }
+    }
+}
+
+/// Where a cached answer came from
+#[derive(Debug, Clone, Serialize)]
+pub struct CacheHit {
+    #[serde(rename = "match")]
+    pub kind: CacheMatch,
+    pub similarity: f32,
+    /// Span of the call that produced the answer
+    pub source_edge_id: String,
+    pub age_secs: u64,
+    /// Tokens the original call used, which this request did not
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub saved_tokens: Option<u32>,
+}
+
+impl CacheHit {
+    /// Attributes of the span recording the hit
+    pub fn attributes(&self) -> HashMap<String, String> {
+        let link = json!([{
+            "span_id": self.source_edge_id,
+            "kind": "follows_from",
+        }]);
+        let mut attributes = HashMap::from([
+            ("agentreplay.cache.hit".to_string(), "true".to_string()),
+            (
+                "agentreplay.cache.match".to_string(),
+                self.kind.as_str().to_string(),
+            ),
+            (
+                "agentreplay.cache.similarity".to_string(),
+                format!("{:.4}", self.similarity),
+            ),
+            (
+                "agentreplay.cache.source_edge_id".to_string(),
+                self.source_edge_id.clone(),
+            ),
+            (
+                "agentreplay.cache.age_secs".to_string(),
+                self.age_secs.to_string(),
+            ),
+            (ATTR_SPAN_LINKS.to_string(), link.to_string()),
+        ]);
+        if let Some(tokens) = self.saved_tokens {
+            attributes.insert(
+                "agentreplay.cache.saved_tokens".to_string(),
+                tokens.to_string(),
+            );
+        }
+        attributes
+    }
+}
+
+/// Hit counts and savings of a tenant since the server started
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct CacheStats {
+    pub hits: u64,
+    pub semantic_hits: u64,
+    pub misses: u64,
+    pub saved_input_tokens: u64,
+    pub saved_output_tokens: u64,
+    pub saved_cost_usd: f64,
+}
+
+#[derive(Debug, Serialize)]
+pub struct CacheStatsResponse {
+    pub enabled: bool,
+    pub entries: usize,
+    #[serde(flatten)]
+    pub stats: CacheStats,
+}
+
+/// Text a request's messages are embedded from
+fn conversation_text(messages: &[ChatMessage]) -> String {
+    messages
+        .iter()
+        .map(|m| format!("{}: {}", m.role, m.content))
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+fn request_hash(scope: &Scope, messages: &[ChatMessage]) -> String {
+    let mut hasher = Sha256::new();
+    hasher.update(scope.tenant_id.to_le_bytes());
+    hasher.update(scope.project_id.to_le_bytes());
+    hasher.update(scope.provider.as_bytes());
+    hasher.update([0]);
+    hasher.update(scope.model.as_deref().unwrap_or_default().as_bytes());
+    for message in messages {
+        // Lengths keep ("ab", "c") apart from ("a", "bc")
+        hasher.update((message.role.len() as u64).to_le_bytes());
+        hasher.update(message.role.as_bytes());
+        hasher.update((message.content.len() as u64).to_le_bytes());
+        hasher.update(message.content.as_bytes());
+    }
+    hex::encode(hasher.finalize())
+}
+
+#[derive(Default)]
+struct Inner {
+    entries: HashMap<String, CacheEntry>,
+    /// Entry hashes, oldest first
+    order: VecDeque<String>,
+    stats: HashMap<u64, CacheStats>,
+}
+
+impl Inner {
+    fn remove_expired(&mut self, now: Instant) {
+        self.entries.retain(|_, entry| entry.expires_at > now);
+        let entries = &self.entries;
+        self.order.retain(|hash| entries.contains_key(hash));
+    }
+}
+
+/// Cached chat completions of every tenant
+pub struct ResponseCache {
+    enabled: bool,
+    default_policy: CachePolicy,
+    default_enabled: bool,
+    projects: HashMap<u16, ProjectCacheConfig>,
+    max_entries: usize,
+    embedder: Option<LocalEmbeddingProvider>,
+    inner: Mutex<Inner>,
+}
+
+impl Default for ResponseCache {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            default_policy: CachePolicy {
+                ttl: Duration::ZERO,
+                similarity_threshold: None,
+            },
+            default_enabled: false,
+            projects: HashMap::new(),
+            max_entries: 0,
+            embedder: None,
+            inner: Mutex::new(Inner::default()),
+        }
+    }
+}
+
+fn check_threshold(threshold: Option<f32>) -> Result<(), String> {
+    match threshold {
+        Some(t) if !(t > 0.0 && t <= 1.0) => {
+            Err(format!("similarity_threshold must be in (0, 1], got {}", t))
+        }
+        _ => Ok(()),
+    }
+}
+
+impl ResponseCache {
+    pub fn new(config: &ResponseCacheConfig) -> Result<Self, String> {
+        check_threshold(config.similarity_threshold)?;
+        let mut seen = BTreeSet::new();
+        for project in &config.projects {
+            if !seen.insert(project.project_id) {
+                return Err(format!(
+                    "Response cache settings of project {} are listed twice",
+                    project.project_id
+                ));
+            }
+            check_threshold(project.similarity_threshold)
+                .map_err(|e| format!("Project {}: {}", project.project_id, e))?;
+        }
+
+        let enabled = config.enabled || config.projects.iter().any(|p| p.enabled == Some(true));
+        let semantic = config.similarity_threshold.is_some()
+            || config
+                .projects
+                .iter()
+                .any(|p| p.similarity_threshold.is_some());
+        let embedder = if enabled && semantic {
+            Some(
+                LocalEmbeddingProvider::default_provider()
+                    .map_err(|e| format!("Failed to initialize embedding provider: {}", e))?,
+            )
+        } else {
+            None
+        };
+
+        Ok(Self {
+            enabled,
+            default_policy: CachePolicy {
+                ttl: Duration::from_secs(config.ttl_secs),
+                similarity_threshold: config.similarity_threshold,
+            },
+            default_enabled: config.enabled,
+            projects: config
+                .projects
+                .iter()
+                .map(|p| (p.project_id, p.clone()))
+                .collect(),
+            max_entries: config.max_entries,
+            embedder,
+            inner: Mutex::new(Inner::default()),
+        })
+    }
+
+    /// How a project's requests are cached, or `None` when they are not
+    pub fn policy(&self, project_id: u16) -> Option<CachePolicy> {
+        if !self.enabled {
+            return None;
+        }
+        let Some(project) = self.projects.get(&project_id) else {
+            return self.default_enabled.then_some(self.default_policy);
+        };
+        project
+            .enabled
+            .unwrap_or(self.default_enabled)
+            .then(|| CachePolicy {
+                ttl: project
+                    .ttl_secs
+                    .map_or(self.default_policy.ttl, Duration::from_secs),
+                similarity_threshold: project
+                    .similarity_threshold
+                    .or(self.default_policy.similarity_threshold),
+            })
+    }
+
+    /// Cache key of a request, or `None` when its project is not cached
+    pub fn key(
+        &self,
+        tenant_id: u64,
+        project_id: u16,
+        provider: &str,
+        model: Option<&str>,
+        messages: &[ChatMessage],
+    ) -> Option<CacheKey> {
+        let policy = self.policy(project_id)?;
+        let scope = Scope {
+            tenant_id,
+            project_id,
+            provider: provider.to_string(),
+            model: model.map(str::to_string),
+        };
+        let embedding = policy
+            .similarity_threshold
+            .and(self.embedder.as_ref())
+            .and_then(|embedder| embedder.embed(&conversation_text(messages)).ok());
+        Some(CacheKey {
+            hash: request_hash(&scope, messages),
+            scope,
+            embedding,
+            policy,
+        })
+    }
+
+    /// Cached answer to a request, counting the hit or miss
+    pub fn lookup(&self, key: &CacheKey) -> Option<(CacheHit, ChatResponse)> {
+        let now = Instant::now();
+        let mut inner = self.inner.lock();
+        let found = match inner.entries.get(&key.hash) {
+            Some(entry) if entry.expires_at > now => Some((entry, CacheMatch::Exact, 1.0)),
+            _ => key
+                .embedding
+                .as_ref()
+                .zip(key.policy.similarity_threshold)
+                .and_then(|(embedding, threshold)| {
+                    inner
+                        .entries
+                        .values()
+                        .filter(|entry| entry.scope == key.scope && entry.expires_at > now)
+                        .filter_map(|entry| {
+                            let similarity =
+                                cosine_similarity_normalized(embedding, entry.embedding.as_ref()?);
+                            Some((entry, similarity))
+                        })
+                        .filter(|(_, similarity)| *similarity >= threshold)
+                        .max_by(|a, b| a.1.total_cmp(&b.1))
+                })
+                .map(|(entry, similarity)| (entry, CacheMatch::Semantic, similarity)),
+        };
+        let hit = found.map(|(entry, kind, similarity)| {
+            let hit = CacheHit {
+                kind,
+                similarity,
+                source_edge_id: format!("{:#x}", entry.edge_id),
+                age_secs: now.duration_since(entry.stored_at).as_secs(),
+                saved_tokens: entry.response.tokens_used,
+            };
+            (hit, entry.response.clone())
+        });
+
+        let stats = inner.stats.entry(key.scope.tenant_id).or_default();
+        match &hit {
+            Some((hit, response)) => {
+                stats.hits += 1;
+                if hit.kind == CacheMatch::Semantic {
+                    stats.semantic_hits += 1;
+                }
+                let input_tokens = response.input_tokens.unwrap_or(0);
+                let output_tokens = response.output_tokens.unwrap_or(0);
+                stats.saved_input_tokens += input_tokens as u64;
+                stats.saved_output_tokens += output_tokens as u64;
+                let model = response
+                    .response_model
+                    .as_deref()
+                    .unwrap_or(&response.model);
+                stats.saved_cost_usd += ModelPricing::for_model(&response.provider, model)
+                    .token_cost(input_tokens, output_tokens);
+            }
+            None => stats.misses += 1,
+        }
+        hit
+    }
+
+    /// Cache the answer of a request, evicting the oldest entries when full
+    pub fn store(&self, key: CacheKey, response: ChatResponse, edge_id: u128) {
+        if self.max_entries == 0 {
+            return;
+        }
+        let now = Instant::now();
+        let mut inner = self.inner.lock();
+        if inner.entries.len() >= self.max_entries {
+            inner.remove_expired(now);
+        }
+        while inner.entries.len() >= self.max_entries {
+            let Some(oldest) = inner.order.pop_front() else {
+                break;
+            };
+            inner.entries.remove(&oldest);
+        }
+        let entry = CacheEntry {
+            scope: key.scope,
+            embedding: key.embedding,
+            response,
+            edge_id,
+            stored_at: now,
+            expires_at: now + key.policy.ttl,
+        };
+        if inner.entries.insert(key.hash.clone(), entry).is_some() {
+            inner.order.retain(|hash| *hash != key.hash);
+        }
+        inner.order.push_back(key.hash);
+    }
+
+    pub fn stats(&self, tenant_id: u64) -> CacheStatsResponse {
+        let now = Instant::now();
+        let inner = self.inner.lock();
+        CacheStatsResponse {
+            enabled: self.enabled,
+            entries: inner
+                .entries
+                .values()
+                .filter(|entry| entry.scope.tenant_id == tenant_id && entry.expires_at > now)
+                .count(),
+            stats: inner.stats.get(&tenant_id).cloned().unwrap_or_default(),
+        }
+    }
+
+    /// Drop a tenant's cached answers, returning how many there were
+    pub fn clear(&self, tenant_id: u64) -> usize {
+        let mut inner = self.inner.lock();
+        let before = inner.entries.len();
+        inner
+            .entries
+            .retain(|_, entry| entry.scope.tenant_id != tenant_id);
+        let Inner { entries, order, .. } = &mut *inner;
+        order.retain(|hash| entries.contains_key(hash));
+        before - entries.len()
+    }
+}
+
+/// GET /api/v1/chat/cache
+pub async fn get_cache_stats(
+    State(state): State<AppState>,
+    Extension(auth): Extension<AuthContext>,
+) -> Json<CacheStatsResponse> {
+    Json(state.response_cache.stats(auth.tenant_id))
+}
+
+/// DELETE /api/v1/chat/cache
+pub async fn clear_cache(
+    State(state): State<AppState>,
+    Extension(auth): Extension<AuthContext>,
+) -> Json<serde_json::Value> {
+    let removed = state.response_cache.clear(auth.tenant_id);
+    Json(json!({ "removed": removed }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn config(value: serde_json::Value) -> ResponseCacheConfig {
+        serde_json::from_value(value).unwrap()
+    }
+
+    fn message(content: &str) -> Vec<ChatMessage> {
+        vec![ChatMessage {
+            role: "user".to_string(),
+            content: content.to_string(),
+        }]
+    }
+
+    fn response(content: &str) -> ChatResponse {
+        ChatResponse {
+            content: content.to_string(),
+            provider: "openai".to_string(),
+            model: "gpt-4o".to_string(),
+            response_model: None,
+            response_id: None,
+            tokens_used: Some(30),
+            input_tokens: Some(10),
+            output_tokens: Some(20),
+            finish_reason: None,
+            duration_ms: 900,
+        }
+    }
+
+    fn key(cache: &ResponseCache, tenant_id: u64, content: &str) -> CacheKey {
+        cache
+            .key(tenant_id, 0, "openai", Some("gpt-4o"), &message(content))
+            .unwrap()
+    }
+
+    #[test]
+    fn test_project_policies() {
+        let cache = ResponseCache::new(&config(json!({
+            "enabled": false,
+            "ttl_secs": 60,
+            "projects": [
+                { "project_id": 7, "enabled": true, "similarity_threshold": 0.9 },
+                { "project_id": 8, "ttl_secs": 5 }
+            ]
+        })))
+        .unwrap();
+        assert!(cache.policy(0).is_none());
+        assert!(cache.policy(8).is_none());
+        let policy = cache.policy(7).unwrap();
+        assert_eq!(policy.ttl, Duration::from_secs(60));
+        assert_eq!(policy.similarity_threshold, Some(0.9));
+
+        let disabled = ResponseCache::new(&ResponseCacheConfig::default()).unwrap();
+        assert!(disabled.policy(0).is_none());
+    }
+
+    #[test]
+    fn test_invalid_config() {
+        assert!(ResponseCache::new(&config(json!({ "similarity_threshold": 1.5 }))).is_err());
+        assert!(ResponseCache::new(&config(json!({
+            "projects": [{ "project_id": 1 }, { "project_id": 1 }]
+        })))
+        .is_err());
+    }
+
+    #[test]
+    fn test_exact_hits_are_scoped() {
+        let cache = ResponseCache::new(&config(json!({ "enabled": true }))).unwrap();
+        assert!(cache.lookup(&key(&cache, 1, "hello")).is_none());
+        cache.store(key(&cache, 1, "hello"), response("hi"), 0x42);
+
+        let (hit, cached) = cache.lookup(&key(&cache, 1, "hello")).unwrap();
+        assert_eq!(hit.kind, CacheMatch::Exact);
+        assert_eq!(hit.source_edge_id, "0x42");
+        assert_eq!(cached.content, "hi");
+        assert!(hit.attributes()[ATTR_SPAN_LINKS].contains("follows_from"));
+
+        // Other tenants, models and prompts miss
+        assert!(cache.lookup(&key(&cache, 2, "hello")).is_none());
+        assert!(cache.lookup(&key(&cache, 1, "hello!")).is_none());
+        let other_model = cache
+            .key(1, 0, "openai", Some("gpt-4o-mini"), &message("hello"))
+            .unwrap();
+        assert!(cache.lookup(&other_model).is_none());
+
+        let stats = cache.stats(1);
+        assert_eq!(stats.entries, 1);
+        assert_eq!(stats.stats.hits, 1);
+        assert_eq!(stats.stats.misses, 3);
+        assert_eq!(stats.stats.saved_output_tokens, 20);
+        assert!(stats.stats.saved_cost_usd > 0.0);
+
+        assert_eq!(cache.clear(1), 1);
+        assert!(cache.lookup(&key(&cache, 1, "hello")).is_none());
+    }
+
+    #[test]
+    fn test_semantic_hits() {
+        let cache = ResponseCache::new(&config(json!({
+            "enabled": true,
+            "similarity_threshold": 0.9
+        })))
+        .unwrap();
+        let prompt = "Summarize the quarterly revenue report for the sales team";
+        cache.store(key(&cache, 1, prompt), response("summary"), 0x1);
+
+        let (hit, _) = cache
+            .lookup(&key(&cache, 1, &format!("{}.", prompt)))
+            .unwrap();
+        assert_eq!(hit.kind, CacheMatch::Semantic);
+        assert!(hit.similarity >= 0.9 && hit.similarity < 1.0);
+        assert!(cache
+            .lookup(&key(&cache, 1, "Translate this poem into French"))
+            .is_none());
+        assert_eq!(cache.stats(1).stats.semantic_hits, 1);
+    }
+
+    #[test]
+    fn test_expiry_and_eviction() {
+        let cache = ResponseCache::new(&config(json!({
+            "enabled": true,
+            "ttl_secs": 0,
+        })))
+        .unwrap();
+        cache.store(key(&cache, 1, "a"), response("a"), 0x1);
+        assert!(cache.lookup(&key(&cache, 1, "a")).is_none());
+
+        let cache = ResponseCache::new(&config(json!({
+            "enabled": true,
+            "max_entries": 2,
+        })))
+        .unwrap();
+        for (i, prompt) in ["a", "b", "c"].into_iter().enumerate() {
+            cache.store(key(&cache, 1, prompt), response(prompt), i as u128);
+        }
+        assert!(cache.lookup(&key(&cache, 1, "a")).is_none());
+        assert!(cache.lookup(&key(&cache, 1, "b")).is_some());
+        assert!(cache.lookup(&key(&cache, 1, "c")).is_some());
+        assert_eq!(cache.stats(1).entries, 2);
+    }
+}
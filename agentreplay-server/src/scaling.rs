@@ -0,0 +1,438 @@
+// Copyright 2025 AgentReplay (https://github.com/agentreplay)
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program. If not, see <https://www.gnu.org/licenses/>.
+
+//! Queue-depth autoscaling signals
+//!
+//! `GET /api/v1/admin/scaling` reports this node's pressure in a shape
+//! external scalers can consume directly - e.g. a KEDA `metrics-api`
+//! trigger with `valueLocation: signals.query_wait_ms.value` and the
+//! signal's `target` as `targetValue`:
+//!
+//! - `ingestion_queue_depth`: traces waiting in the ingestion actor
+//! - `wal_backlog_bytes`: WAL not yet checkpointed, across project shards
+//! - `eval_backlog`: test cases still pending in running eval runs
+//! - `query_wait_ms`: p95 time analytics queries waited for a worker
+//!
+//! Analytics queries run through [`QueryQueue`], which bounds their
+//! concurrency and records how long each waited before starting.
+
+use std::collections::VecDeque;
+use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use agentreplay_core::clock::now_us;
+use axum::{extract::State, Json};
+use parking_lot::Mutex;
+use serde::Serialize;
+use tokio::sync::Semaphore;
+
+use crate::admission::QueueMetrics;
+use crate::api::query::ApiError;
+use crate::api::AppState;
+use crate::auth::AuthContext;
+use crate::config::ScalingConfig;
+use crate::standby::WalRole;
+
+/// Wait samples kept for percentiles, regardless of the window
+const MAX_WAIT_SAMPLES: usize = 10_000;
+
+/// Bounded pool of blocking workers for analytics queries
+pub struct QueryQueue {
+    config: ScalingConfig,
+    permits: Arc<Semaphore>,
+    window: Duration,
+    queued: AtomicUsize,
+    running: AtomicUsize,
+    completed: AtomicU64,
+    /// (finished waiting, wait) of recently started queries
+    waits: Mutex<VecDeque<(Instant, Duration)>>,
+}
+
+/// Concurrency and recent wait times of the query queue
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct QueryQueueStats {
+    pub max_concurrent: usize,
+    pub queued: usize,
+    pub running: usize,
+    pub completed: u64,
+    /// Queries started within the window
+    pub samples: usize,
+    pub wait_ms_p50: f64,
+    pub wait_ms_p95: f64,
+    pub wait_ms_max: f64,
+}
+
+impl QueryQueue {
+    pub fn new(config: ScalingConfig) -> Self {
+        Self {
+            permits: Arc::new(Semaphore::new(config.max_concurrent_queries)),
+            window: Duration::from_secs(config.query_wait_window_secs),
+            config,
+            queued: AtomicUsize::new(0),
+            running: AtomicUsize::new(0),
+            completed: AtomicU64::new(0),
+            waits: Mutex::new(VecDeque::new()),
+        }
+    }
+
+    pub fn config(&self) -> &ScalingConfig {
+        &self.config
+    }
+
+    /// Run a blocking query once a worker is free
+    ///
+    /// Drop-in replacement for `tokio::task::spawn_blocking`; the wait
+    /// covers both the concurrency limit and blocking pool scheduling.
+    pub async fn run<F, T>(self: &Arc<Self>, f: F) -> Result<T, tokio::task::JoinError>
+    where
+        F: FnOnce() -> T + Send + 'static,
+        T: Send + 'static,
+    {
+        let enqueued = Instant::now();
+        self.queued.fetch_add(1, Ordering::Relaxed);
+        // Decrements `queued` even if the caller is cancelled while waiting
+        let waiting = Waiting(self.clone());
+        // The semaphore is never closed
+        let permit = self
+            .permits
+            .clone()
+            .acquire_owned()
+            .await
+            .expect("query semaphore closed");
+        let queue = self.clone();
+        tokio::task::spawn_blocking(move || {
+            let _permit = permit;
+            drop(waiting);
+            queue.record_wait(Instant::now(), enqueued.elapsed());
+            queue.running.fetch_add(1, Ordering::Relaxed);
+            let _running = Running(queue);
+            f()
+        })
+        .await
+    }
+
+    fn record_wait(&self, now: Instant, wait: Duration) {
+        let mut waits = self.waits.lock();
+        waits.push_back((now, wait));
+        while waits.len() > MAX_WAIT_SAMPLES
+            || waits
+                .front()
+                .is_some_and(|(at, _)| now.duration_since(*at) > self.window)
+        {
+            waits.pop_front();
+        }
+    }
+
+    pub fn stats(&self) -> QueryQueueStats {
+        let now = Instant::now();
+        let mut waits: Vec<f64> = self
+            .waits
+            .lock()
+            .iter()
+            .filter(|(at, _)| now.duration_since(*at) <= self.window)
+            .map(|(_, wait)| wait.as_secs_f64() * 1000.0)
+            .collect();
+        waits.sort_by(f64::total_cmp);
+        QueryQueueStats {
+            max_concurrent: self.config.max_concurrent_queries,
+            queued: self.queued.load(Ordering::Relaxed),
+            running: self.running.load(Ordering::Relaxed),
+            completed: self.completed.load(Ordering::Relaxed),
+            samples: waits.len(),
+            wait_ms_p50: percentile(&waits, 0.50),
+            wait_ms_p95: percentile(&waits, 0.95),
+            wait_ms_max: waits.last().copied().unwrap_or(0.0),
+        }
+    }
+}
+
+/// Run a blocking analytics query through the node's [`QueryQueue`]
+pub async fn run_query<F, T>(state: &AppState, f: F) -> Result<T, tokio::task::JoinError>
+where
+    F: FnOnce() -> T + Send + 'static,
+    T: Send + 'static,
+{
+    state.query_queue.run(f).await
+}
+
+struct Waiting(Arc<QueryQueue>);
+
+impl Drop for Waiting {
+    fn drop(&mut self) {
+        self.0.queued.fetch_sub(1, Ordering::Relaxed);
+    }
+}
+
+struct Running(Arc<QueryQueue>);
+
+impl Drop for Running {
+    fn drop(&mut self) {
+        self.0.running.fetch_sub(1, Ordering::Relaxed);
+        self.0.completed.fetch_add(1, Ordering::Relaxed);
+    }
+}
+
+/// Nearest-rank percentile of sorted values (0 when empty)
+fn percentile(sorted: &[f64], q: f64) -> f64 {
+    if sorted.is_empty() {
+        return 0.0;
+    }
+    let rank = (q * sorted.len() as f64).ceil() as usize;
+    sorted[rank.clamp(1, sorted.len()) - 1]
+}
+
+/// One scaling metric and the per-replica value to aim for
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+pub struct ScalingSignal {
+    pub value: f64,
+    pub target: f64,
+    /// `value / target`; above 1 means more replicas are wanted
+    pub pressure: f64,
+}
+
+impl ScalingSignal {
+    fn new(value: f64, target: f64) -> Self {
+        Self {
+            value,
+            target,
+            pressure: if target > 0.0 { value / target } else { 0.0 },
+        }
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub struct Signals {
+    pub ingestion_queue_depth: ScalingSignal,
+    pub wal_backlog_bytes: ScalingSignal,
+    pub eval_backlog: ScalingSignal,
+    pub query_wait_ms: ScalingSignal,
+}
+
+impl Signals {
+    fn max_pressure(&self) -> f64 {
+        [
+            self.ingestion_queue_depth.pressure,
+            self.wal_backlog_bytes.pressure,
+            self.eval_backlog.pressure,
+            self.query_wait_ms.pressure,
+        ]
+        .into_iter()
+        .fold(0.0, f64::max)
+    }
+}
+
+#[derive(Debug, Default, Serialize)]
+pub struct WalBacklog {
+    pub wal_bytes: u64,
+    pub shards: usize,
+    /// Replication role, when WAL shipping is enabled
+    pub replication_role: Option<WalRole>,
+    /// Primary: writes buffered but not yet shipped
+    pub unshipped_records: Option<usize>,
+    /// Standby: shipped records not yet applied
+    pub unapplied_records: Option<u64>,
+}
+
+#[derive(Debug, Default, PartialEq, Serialize)]
+pub struct EvalBacklog {
+    pub running_runs: usize,
+    pub pending_cases: u64,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ScalingResponse {
+    pub timestamp: u64,
+    /// Largest signal pressure
+    pub pressure: f64,
+    pub signals: Signals,
+    pub ingestion: Option<QueueMetrics>,
+    pub wal: WalBacklog,
+    pub eval: EvalBacklog,
+    pub query: QueryQueueStats,
+}
+
+/// Pending test cases of running eval runs
+fn eval_backlog(state: &AppState) -> Result<EvalBacklog, ApiError> {
+    let runs = state
+        .db
+        .list_eval_runs(None)
+        .map_err(|e| ApiError::Internal(e.to_string()))?;
+    let mut backlog = EvalBacklog::default();
+    for run in runs
+        .iter()
+        .filter(|run| run.status == agentreplay_core::eval_dataset::RunStatus::Running)
+    {
+        let cases = state
+            .db
+            .get_eval_dataset(run.dataset_id)
+            .map_err(|e| ApiError::Internal(e.to_string()))?
+            .map_or(0, |dataset| dataset.test_cases.len());
+        backlog.running_runs += 1;
+        backlog.pending_cases += cases.saturating_sub(run.results.len()) as u64;
+    }
+    Ok(backlog)
+}
+
+fn wal_backlog(state: &AppState) -> Result<WalBacklog, ApiError> {
+    let shards = crate::api::metrics::project_shards(state, None)?;
+    let mut backlog = WalBacklog {
+        wal_bytes: shards.iter().map(|db| db.wal_size_bytes()).sum(),
+        shards: shards.len(),
+        ..Default::default()
+    };
+    if let Some(replication) = &state.replication {
+        let status = replication.status();
+        backlog.replication_role = Some(status.role);
+        match status.role {
+            WalRole::Primary => backlog.unshipped_records = Some(status.pending_records),
+            WalRole::Standby => backlog.unapplied_records = status.lag_records,
+        }
+    }
+    Ok(backlog)
+}
+
+/// GET /api/v1/admin/scaling
+///
+/// Ingestion, WAL, eval and query pressure of this node, each against its
+/// configured per-replica target, for KEDA / HPA external scalers.
+pub async fn get_scaling_signals(
+    State(state): State<AppState>,
+    axum::Extension(_auth): axum::Extension<AuthContext>,
+) -> Result<Json<ScalingResponse>, ApiError> {
+    let config = state.query_queue.config();
+    let ingestion = state.ingestion_actor.as_ref().map(|actor| {
+        state
+            .ingestion_admission
+            .metrics(actor.queue_depth(), actor.queue_capacity())
+    });
+    let (ingestion_depth, ingestion_target) = ingestion.as_ref().map_or((0.0, 0.0), |m| {
+        (
+            m.queue_depth as f64,
+            m.queue_capacity as f64 * config.target_ingestion_utilization,
+        )
+    });
+
+    let wal = wal_backlog(&state)?;
+    let eval = eval_backlog(&state)?;
+    let query = state.query_queue.stats();
+
+    let signals = Signals {
+        ingestion_queue_depth: ScalingSignal::new(ingestion_depth, ingestion_target),
+        wal_backlog_bytes: ScalingSignal::new(
+            wal.wal_bytes as f64,
+            config.target_wal_backlog_bytes as f64,
+        ),
+        eval_backlog: ScalingSignal::new(
+            eval.pending_cases as f64,
+            config.target_eval_backlog as f64,
+        ),
+        query_wait_ms: ScalingSignal::new(query.wait_ms_p95, config.target_query_wait_ms),
+    };
+
+    Ok(Json(ScalingResponse {
+        timestamp: now_us(),
+        pressure: signals.max_pressure(),
+        signals,
+        ingestion,
+        wal,
+        eval,
+        query,
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn config(max_concurrent_queries: usize) -> ScalingConfig {
+        ScalingConfig {
+            max_concurrent_queries,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_percentile() {
+        assert_eq!(percentile(&[], 0.95), 0.0);
+        let values: Vec<f64> = (1..=100).map(f64::from).collect();
+        assert_eq!(percentile(&values, 0.50), 50.0);
+        assert_eq!(percentile(&values, 0.95), 95.0);
+        assert_eq!(percentile(&values, 1.0), 100.0);
+        assert_eq!(percentile(&[7.0], 0.5), 7.0);
+    }
+
+    #[test]
+    fn test_wait_window() {
+        let queue = QueryQueue::new(ScalingConfig {
+            query_wait_window_secs: 10,
+            ..Default::default()
+        });
+        let start = Instant::now();
+        queue.record_wait(start, Duration::from_millis(500));
+        queue.record_wait(start + Duration::from_secs(5), Duration::from_millis(10));
+        assert_eq!(queue.waits.lock().len(), 2);
+        // The first sample falls out of the window
+        queue.record_wait(start + Duration::from_secs(11), Duration::from_millis(20));
+        assert_eq!(queue.waits.lock().len(), 2);
+    }
+
+    #[test]
+    fn test_signal_pressure() {
+        let signals = Signals {
+            ingestion_queue_depth: ScalingSignal::new(50.0, 100.0),
+            wal_backlog_bytes: ScalingSignal::new(0.0, 1.0),
+            eval_backlog: ScalingSignal::new(300.0, 100.0),
+            query_wait_ms: ScalingSignal::new(10.0, 0.0),
+        };
+        assert_eq!(signals.ingestion_queue_depth.pressure, 0.5);
+        assert_eq!(signals.query_wait_ms.pressure, 0.0);
+        assert_eq!(signals.max_pressure(), 3.0);
+    }
+
+    #[tokio::test]
+    async fn test_query_queue_limits_concurrency() {
+        let queue = Arc::new(QueryQueue::new(config(1)));
+        let (tx, rx) = std::sync::mpsc::channel::<()>();
+
+        // Hold the only worker until signalled
+        let blocker = tokio::spawn({
+            let queue = queue.clone();
+            async move { queue.run(move || rx.recv().unwrap()).await }
+        });
+        while queue.stats().running == 0 {
+            tokio::time::sleep(Duration::from_millis(5)).await;
+        }
+        let waiter = tokio::spawn({
+            let queue = queue.clone();
+            async move { queue.run(|| 42).await }
+        });
+        while queue.stats().queued == 0 {
+            tokio::time::sleep(Duration::from_millis(5)).await;
+        }
+        tokio::time::sleep(Duration::from_millis(50)).await;
+
+        tx.send(()).unwrap();
+        blocker.await.unwrap().unwrap();
+        assert_eq!(waiter.await.unwrap().unwrap(), 42);
+
+        let stats = queue.stats();
+        assert_eq!(stats.queued, 0);
+        assert_eq!(stats.running, 0);
+        assert_eq!(stats.completed, 2);
+        assert_eq!(stats.samples, 2);
+        assert!(stats.wait_ms_max >= 50.0);
+    }
+}
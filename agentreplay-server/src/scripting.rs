@@ -0,0 +1,844 @@
+// Copyright 2025 AgentReplay (https://github.com/agentreplay)
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program. If not, see <https://www.gnu.org/licenses/>.
+
+//! Embedded scripting hooks
+//!
+//! Lightweight server-side automation without writing a WASM plugin. Scripts
+//! are written in [Rhai](https://rhai.rs) and attached to a hook point:
+//!
+//! - `on_ingest`: runs for every span posted to `/api/v1/traces`
+//! - `on_eval_complete`: runs when an evaluation run or pipeline finishes
+//! - `on_alert`: runs for each alert raised by an evaluation
+//!
+//! Each script sees three variables:
+//!
+//! ```rhai
+//! // `record`: the span / eval result / alert as a map (mutable)
+//! record.attributes["team"] = "search";
+//!
+//! // `keep`: set to false to drop the record (on_ingest only)
+//! if record.name == "healthcheck" { keep = false; }
+//!
+//! // `notifications`: push #{ channel, message } to route a notification;
+//! // channel is "log" or an http(s) webhook URL
+//! notifications.push(#{ channel: "log", message: "slow span" });
+//! ```
+//!
+//! Scripts belong to a tenant and only see that tenant's records.
+//!
+//! Scripts are sandboxed: no file, network or `eval` access, bounded
+//! string/array/map sizes (the memory limit), and a per-script operation
+//! budget and wall-clock timeout. A failing script is logged and skipped;
+//! the record passes through unchanged. Script webhooks are only sent to
+//! hosts on `scripting.webhook_allowlist` that resolve to public addresses.
+
+use agentreplay_core::clock::now_secs;
+use parking_lot::RwLock;
+use rhai::{Dynamic, Engine, EvalAltResult, Scope, AST};
+use serde::{Deserialize, Serialize};
+use std::cell::Cell;
+use std::fs::{self, File};
+use std::io::{BufReader, BufWriter};
+use std::net::{IpAddr, SocketAddr};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tracing::{debug, error, info, warn};
+
+/// Default wall-clock budget per script run
+pub const DEFAULT_TIMEOUT_MS: u64 = 10;
+/// Default operation budget per script run
+pub const DEFAULT_MAX_OPERATIONS: u64 = 50_000;
+/// Upper bounds accepted from the management API
+const MAX_TIMEOUT_MS: u64 = 1_000;
+const MAX_OPERATIONS: u64 = 10_000_000;
+
+/// Sandbox memory limits shared by all scripts
+const MAX_STRING_SIZE: usize = 64 * 1024;
+const MAX_ARRAY_SIZE: usize = 10_000;
+const MAX_MAP_SIZE: usize = 10_000;
+const MAX_CALL_LEVELS: usize = 32;
+const MAX_SOURCE_LEN: usize = 64 * 1024;
+
+const TERMINATED_TIMEOUT: &str = "timeout";
+const TERMINATED_OPERATIONS: &str = "operation limit";
+
+thread_local! {
+    /// (deadline, operation budget) of the script running on this thread
+    static RUN_BUDGET: Cell<Option<(Instant, u64)>> = const { Cell::new(None) };
+}
+
+/// Hook point a script is attached to
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ScriptHook {
+    OnIngest,
+    OnEvalComplete,
+    OnAlert,
+}
+
+/// A stored script
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ScriptDefinition {
+    /// Tenant whose records the script sees; scripts saved before scripts
+    /// were per tenant belong to the default tenant
+    #[serde(default = "default_script_tenant")]
+    pub tenant_id: u64,
+    pub name: String,
+    pub hook: ScriptHook,
+    pub source: String,
+    #[serde(default)]
+    pub description: Option<String>,
+    #[serde(default = "default_enabled")]
+    pub enabled: bool,
+    /// Wall-clock budget per run in milliseconds
+    #[serde(default = "default_timeout_ms")]
+    pub timeout_ms: u64,
+    /// Operation budget per run
+    #[serde(default = "default_max_operations")]
+    pub max_operations: u64,
+    pub created_at: u64,
+    pub updated_at: u64,
+}
+
+fn default_script_tenant() -> u64 {
+    1
+}
+
+fn default_enabled() -> bool {
+    true
+}
+
+fn default_timeout_ms() -> u64 {
+    DEFAULT_TIMEOUT_MS
+}
+
+fn default_max_operations() -> u64 {
+    DEFAULT_MAX_OPERATIONS
+}
+
+/// Runtime counters for a script
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct ScriptStats {
+    pub runs: u64,
+    pub errors: u64,
+    pub timeouts: u64,
+    pub dropped: u64,
+    pub last_error: Option<String>,
+}
+
+/// A notification requested by a script
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ScriptNotification {
+    pub channel: String,
+    pub message: String,
+}
+
+/// Result of running the scripts of one hook against a record
+#[derive(Debug, Clone, Serialize)]
+pub struct ScriptOutcome {
+    /// The record after all scripts ran
+    pub record: serde_json::Value,
+    /// Whether a script asked to drop the record
+    pub dropped: bool,
+    pub notifications: Vec<ScriptNotification>,
+}
+
+struct CompiledScript {
+    definition: ScriptDefinition,
+    ast: AST,
+    runs: AtomicU64,
+    errors: AtomicU64,
+    timeouts: AtomicU64,
+    dropped: AtomicU64,
+    last_error: RwLock<Option<String>>,
+}
+
+impl CompiledScript {
+    fn stats(&self) -> ScriptStats {
+        ScriptStats {
+            runs: self.runs.load(Ordering::Relaxed),
+            errors: self.errors.load(Ordering::Relaxed),
+            timeouts: self.timeouts.load(Ordering::Relaxed),
+            dropped: self.dropped.load(Ordering::Relaxed),
+            last_error: self.last_error.read().clone(),
+        }
+    }
+}
+
+/// Stored script plus its runtime counters
+#[derive(Debug, Clone, Serialize)]
+pub struct ScriptInfo {
+    #[serde(flatten)]
+    pub definition: ScriptDefinition,
+    pub stats: ScriptStats,
+}
+
+/// Compiles, stores and runs hook scripts
+pub struct ScriptManager {
+    engine: Engine,
+    /// Scripts sorted by tenant and name; name is also their execution order
+    scripts: RwLock<Vec<Arc<CompiledScript>>>,
+    storage_path: PathBuf,
+    /// Hosts script webhooks may be sent to
+    webhook_allowlist: Vec<String>,
+}
+
+impl ScriptManager {
+    /// Create a manager, loading and compiling scripts from `storage_path`
+    pub fn new(storage_path: impl AsRef<Path>) -> Self {
+        let manager = Self {
+            engine: sandboxed_engine(),
+            scripts: RwLock::new(Vec::new()),
+            storage_path: storage_path.as_ref().to_path_buf(),
+            webhook_allowlist: Vec::new(),
+        };
+
+        if let Err(e) = manager.load_from_disk() {
+            warn!(
+                "Failed to load scripts from disk: {}. Starting with no scripts.",
+                e
+            );
+        }
+
+        manager
+    }
+
+    /// Allow script webhooks to these hosts (`*.example.com` matches
+    /// subdomains); with none, scripts can only notify the log
+    pub fn with_webhook_allowlist(mut self, hosts: Vec<String>) -> Self {
+        self.webhook_allowlist = hosts;
+        self
+    }
+
+    /// Whether a tenant has an enabled script attached to `hook` (cheap fast path)
+    pub fn has_hook(&self, tenant_id: u64, hook: ScriptHook) -> bool {
+        self.scripts.read().iter().any(|s| {
+            s.definition.tenant_id == tenant_id && s.definition.enabled && s.definition.hook == hook
+        })
+    }
+
+    pub fn list(&self, tenant_id: u64) -> Vec<ScriptInfo> {
+        self.scripts
+            .read()
+            .iter()
+            .filter(|s| s.definition.tenant_id == tenant_id)
+            .map(|s| script_info(s))
+            .collect()
+    }
+
+    pub fn get(&self, tenant_id: u64, name: &str) -> Option<ScriptInfo> {
+        self.find(tenant_id, name).map(|s| script_info(&s))
+    }
+
+    /// Create or replace a script; fails if it does not compile
+    pub fn upsert(&self, mut definition: ScriptDefinition) -> Result<ScriptInfo, String> {
+        validate(&definition)?;
+        let ast = self
+            .engine
+            .compile(&definition.source)
+            .map_err(|e| format!("Script does not compile: {}", e))?;
+
+        let now = now_secs();
+        definition.created_at = self
+            .find(definition.tenant_id, &definition.name)
+            .map_or(now, |existing| existing.definition.created_at);
+        definition.updated_at = now;
+
+        let compiled = Arc::new(compile_result(definition, ast));
+        {
+            let mut scripts = self.scripts.write();
+            scripts.retain(|s| !same_script(&s.definition, &compiled.definition));
+            scripts.push(compiled.clone());
+            sort_scripts(&mut scripts);
+        }
+        self.save_to_disk()?;
+
+        info!(
+            "Saved script '{}' ({:?})",
+            compiled.definition.name, compiled.definition.hook
+        );
+        Ok(script_info(&compiled))
+    }
+
+    /// Delete a script; returns false if it did not exist
+    pub fn delete(&self, tenant_id: u64, name: &str) -> Result<bool, String> {
+        let removed = {
+            let mut scripts = self.scripts.write();
+            let before = scripts.len();
+            scripts.retain(|s| !(s.definition.tenant_id == tenant_id && s.definition.name == name));
+            scripts.len() != before
+        };
+        if removed {
+            self.save_to_disk()?;
+        }
+        Ok(removed)
+    }
+
+    /// Run a tenant's enabled scripts for `hook` in name order
+    ///
+    /// Each script sees the record as modified by the previous one. A script
+    /// that fails leaves the record unchanged; a drop stops the chain.
+    pub fn run(
+        &self,
+        tenant_id: u64,
+        hook: ScriptHook,
+        record: serde_json::Value,
+    ) -> ScriptOutcome {
+        let scripts: Vec<Arc<CompiledScript>> = self
+            .scripts
+            .read()
+            .iter()
+            .filter(|s| {
+                s.definition.tenant_id == tenant_id
+                    && s.definition.enabled
+                    && s.definition.hook == hook
+            })
+            .cloned()
+            .collect();
+
+        let mut outcome = ScriptOutcome {
+            record,
+            dropped: false,
+            notifications: Vec::new(),
+        };
+        for script in scripts {
+            match self.execute(&script, &outcome.record) {
+                Ok(result) => {
+                    outcome.record = result.record;
+                    outcome.notifications.extend(result.notifications);
+                    if result.dropped {
+                        outcome.dropped = true;
+                        break;
+                    }
+                }
+                Err(e) => debug!("Script '{}' skipped: {}", script.definition.name, e),
+            }
+        }
+        outcome
+    }
+
+    /// Run a tenant's `hook` scripts for their notifications only (record
+    /// changes are discarded)
+    pub fn fire(&self, tenant_id: u64, hook: ScriptHook, record: &impl Serialize) {
+        if !self.has_hook(tenant_id, hook) {
+            return;
+        }
+        let Ok(record) = serde_json::to_value(record) else {
+            return;
+        };
+        let outcome = self.run(tenant_id, hook, record);
+        self.dispatch(hook, outcome.notifications);
+    }
+
+    /// Deliver notifications raised by scripts
+    ///
+    /// Webhooks to hosts off the allowlist, or resolving to loopback,
+    /// private or link-local addresses, are logged and dropped.
+    pub fn dispatch(&self, script_hook: ScriptHook, notifications: Vec<ScriptNotification>) {
+        deliver(script_hook, notifications, Some(&self.webhook_allowlist));
+    }
+
+    /// Run a single script (enabled or not) and surface its errors
+    pub fn test(
+        &self,
+        tenant_id: u64,
+        name: &str,
+        record: serde_json::Value,
+    ) -> Result<ScriptOutcome, String> {
+        let script = self
+            .find(tenant_id, name)
+            .ok_or_else(|| format!("Script '{}' not found", name))?;
+        self.execute(&script, &record)
+    }
+
+    fn find(&self, tenant_id: u64, name: &str) -> Option<Arc<CompiledScript>> {
+        self.scripts
+            .read()
+            .iter()
+            .find(|s| s.definition.tenant_id == tenant_id && s.definition.name == name)
+            .cloned()
+    }
+
+    fn execute(
+        &self,
+        script: &CompiledScript,
+        record: &serde_json::Value,
+    ) -> Result<ScriptOutcome, String> {
+        script.runs.fetch_add(1, Ordering::Relaxed);
+        let result = self.evaluate(script, record);
+        match &result {
+            Ok(outcome) if outcome.dropped => {
+                script.dropped.fetch_add(1, Ordering::Relaxed);
+            }
+            Ok(_) => {}
+            Err(e) => {
+                script.errors.fetch_add(1, Ordering::Relaxed);
+                if e.contains(TERMINATED_TIMEOUT) {
+                    script.timeouts.fetch_add(1, Ordering::Relaxed);
+                }
+                *script.last_error.write() = Some(e.clone());
+            }
+        }
+        result
+    }
+
+    fn evaluate(
+        &self,
+        script: &CompiledScript,
+        record: &serde_json::Value,
+    ) -> Result<ScriptOutcome, String> {
+        let mut scope = Scope::new();
+        scope.push(
+            "record",
+            rhai::serde::to_dynamic(record).map_err(|e| e.to_string())?,
+        );
+        scope.push("keep", true);
+        scope.push("notifications", rhai::Array::new());
+
+        let deadline = Instant::now() + Duration::from_millis(script.definition.timeout_ms);
+        RUN_BUDGET.with(|b| b.set(Some((deadline, script.definition.max_operations))));
+        let run = self.engine.run_ast_with_scope(&mut scope, &script.ast);
+        RUN_BUDGET.with(|b| b.set(None));
+
+        if let Err(e) = run {
+            return Err(match *e {
+                EvalAltResult::ErrorTerminated(reason, _) => {
+                    format!("terminated: {} exceeded", reason)
+                }
+                other => other.to_string(),
+            });
+        }
+
+        let record = scope
+            .get_value::<Dynamic>("record")
+            .ok_or("script removed `record`")
+            .and_then(|d| {
+                rhai::serde::from_dynamic::<serde_json::Value>(&d)
+                    .map_err(|_| "`record` is not serializable")
+            })?;
+        let dropped = !scope.get_value::<bool>("keep").unwrap_or(true);
+        let notifications = scope
+            .get_value::<rhai::Array>("notifications")
+            .unwrap_or_default()
+            .iter()
+            .filter_map(|n| rhai::serde::from_dynamic::<ScriptNotification>(n).ok())
+            .collect();
+
+        Ok(ScriptOutcome {
+            record,
+            dropped,
+            notifications,
+        })
+    }
+
+    fn load_from_disk(&self) -> Result<(), String> {
+        if !self.storage_path.exists() {
+            return Ok(());
+        }
+
+        let file = File::open(&self.storage_path)
+            .map_err(|e| format!("Failed to open scripts file: {}", e))?;
+        let definitions: Vec<ScriptDefinition> = serde_json::from_reader(BufReader::new(file))
+            .map_err(|e| format!("Failed to parse scripts JSON: {}", e))?;
+
+        let mut scripts = self.scripts.write();
+        for definition in definitions {
+            match self.engine.compile(&definition.source) {
+                Ok(ast) => scripts.push(Arc::new(compile_result(definition, ast))),
+                Err(e) => error!("Script '{}' no longer compiles: {}", definition.name, e),
+            }
+        }
+        sort_scripts(&mut scripts);
+
+        info!("Loaded {} hook scripts", scripts.len());
+        Ok(())
+    }
+
+    /// Save scripts to disk (write to temp file then rename)
+    fn save_to_disk(&self) -> Result<(), String> {
+        if let Some(parent) = self.storage_path.parent() {
+            fs::create_dir_all(parent)
+                .map_err(|e| format!("Failed to create scripts directory: {}", e))?;
+        }
+
+        let temp_path = self.storage_path.with_extension("json.tmp");
+        {
+            let file = File::create(&temp_path)
+                .map_err(|e| format!("Failed to create temp scripts file: {}", e))?;
+            let definitions: Vec<ScriptDefinition> = self
+                .scripts
+                .read()
+                .iter()
+                .map(|s| s.definition.clone())
+                .collect();
+            serde_json::to_writer_pretty(BufWriter::new(file), &definitions)
+                .map_err(|e| format!("Failed to serialize scripts: {}", e))?;
+        }
+
+        fs::rename(&temp_path, &self.storage_path)
+            .map_err(|e| format!("Failed to rename temp file: {}", e))?;
+
+        Ok(())
+    }
+}
+
+/// Deliver notifications to operator-configured channels in the background
+///
+/// `log` notifications go to the server log; http(s) channels are POSTed as
+/// JSON webhooks. Unknown channels are logged and ignored. Notifications
+/// raised by scripts go through [`ScriptManager::dispatch`] instead.
+pub fn dispatch_notifications(script_hook: ScriptHook, notifications: Vec<ScriptNotification>) {
+    deliver(script_hook, notifications, None);
+}
+
+/// Deliver notifications, checking webhook targets against `allowlist` if given
+fn deliver(
+    script_hook: ScriptHook,
+    notifications: Vec<ScriptNotification>,
+    allowlist: Option<&[String]>,
+) {
+    for notification in notifications {
+        let channel = notification.channel.as_str();
+        if channel == "log" {
+            info!("[script {:?}] {}", script_hook, notification.message);
+        } else if channel.starts_with("http://") || channel.starts_with("https://") {
+            let url = notification.channel.clone();
+            let allowlist = allowlist.map(<[String]>::to_vec);
+            let body = serde_json::json!({
+                "hook": script_hook,
+                "message": notification.message,
+            });
+            tokio::spawn(async move {
+                let client = match allowlist {
+                    Some(allowlist) => match pinned_webhook_client(&url, &allowlist).await {
+                        Ok(client) => client,
+                        Err(e) => {
+                            warn!("Dropping script webhook to {}: {}", url, e);
+                            return;
+                        }
+                    },
+                    None => reqwest::Client::new(),
+                };
+                let result = client
+                    .post(&url)
+                    .timeout(Duration::from_secs(10))
+                    .json(&body)
+                    .send()
+                    .await;
+                if let Err(e) = result {
+                    warn!("Script webhook to {} failed: {}", url, e);
+                }
+            });
+        } else {
+            warn!(
+                "Ignoring script notification to unknown channel '{}'",
+                channel
+            );
+        }
+    }
+}
+
+/// HTTP client for a script webhook, pinned to the address that was checked
+///
+/// The host must be on the allowlist and resolve only to public addresses.
+/// Pinning the address keeps a second DNS answer from pointing the request
+/// elsewhere, and redirects are not followed for the same reason.
+async fn pinned_webhook_client(url: &str, allowlist: &[String]) -> Result<reqwest::Client, String> {
+    let parsed = url::Url::parse(url).map_err(|e| format!("invalid URL: {}", e))?;
+    let host = parsed
+        .host_str()
+        .ok_or("URL has no host")?
+        .to_ascii_lowercase();
+    if !webhook_host_allowed(&host, allowlist) {
+        return Err(format!(
+            "host '{}' is not in scripting.webhook_allowlist",
+            host
+        ));
+    }
+    let port = parsed.port_or_known_default().unwrap_or(443);
+    let addrs: Vec<SocketAddr> = tokio::net::lookup_host((host.as_str(), port))
+        .await
+        .map_err(|e| format!("cannot resolve '{}': {}", host, e))?
+        .collect();
+    let Some(addr) = addrs.first().copied() else {
+        return Err(format!("'{}' has no addresses", host));
+    };
+    if let Some(blocked) = addrs.iter().find(|a| !is_public_address(a.ip())) {
+        return Err(format!(
+            "'{}' resolves to non-public {}",
+            host,
+            blocked.ip()
+        ));
+    }
+    reqwest::Client::builder()
+        .redirect(reqwest::redirect::Policy::none())
+        .resolve(&host, addr)
+        .build()
+        .map_err(|e| e.to_string())
+}
+
+fn webhook_host_allowed(host: &str, allowlist: &[String]) -> bool {
+    allowlist.iter().any(|allowed| {
+        let allowed = allowed.to_ascii_lowercase();
+        match allowed.strip_prefix("*.") {
+            Some(domain) => host
+                .strip_suffix(domain)
+                .is_some_and(|sub| sub.ends_with('.') && sub.len() > 1),
+            None => host == allowed,
+        }
+    })
+}
+
+/// Whether `ip` is routable on the public internet
+fn is_public_address(ip: IpAddr) -> bool {
+    match ip {
+        IpAddr::V4(v4) => {
+            let [a, b, ..] = v4.octets();
+            !(v4.is_private()
+                || v4.is_loopback()
+                || v4.is_link_local()
+                || v4.is_unspecified()
+                || v4.is_broadcast()
+                || v4.is_multicast()
+                || v4.is_documentation()
+                || a == 0
+                // Carrier-grade NAT, 100.64.0.0/10
+                || (a == 100 && (b & 0xc0) == 64))
+        }
+        IpAddr::V6(v6) => match v6.to_ipv4_mapped() {
+            Some(v4) => is_public_address(IpAddr::V4(v4)),
+            None => {
+                let first = v6.segments()[0];
+                !(v6.is_loopback()
+                    || v6.is_unspecified()
+                    || v6.is_multicast()
+                    // Unique local fc00::/7 and link-local fe80::/10
+                    || (first & 0xfe00) == 0xfc00
+                    || (first & 0xffc0) == 0xfe80)
+            }
+        },
+    }
+}
+
+fn sandboxed_engine() -> Engine {
+    let mut engine = Engine::new();
+    engine.set_max_string_size(MAX_STRING_SIZE);
+    engine.set_max_array_size(MAX_ARRAY_SIZE);
+    engine.set_max_map_size(MAX_MAP_SIZE);
+    engine.set_max_call_levels(MAX_CALL_LEVELS);
+    engine.set_max_expr_depths(64, 32);
+    engine.disable_symbol("eval");
+    engine.on_print(|_| {});
+    engine.on_debug(|_, _, _| {});
+    engine.on_progress(|ops| {
+        let (deadline, max_operations) = RUN_BUDGET.with(Cell::get)?;
+        if ops > max_operations {
+            return Some(Dynamic::from(TERMINATED_OPERATIONS.to_string()));
+        }
+        if ops % 64 == 0 && Instant::now() >= deadline {
+            return Some(Dynamic::from(TERMINATED_TIMEOUT.to_string()));
+        }
+        None
+    });
+    engine
+}
+
+fn validate(definition: &ScriptDefinition) -> Result<(), String> {
+    let name_ok = !definition.name.is_empty()
+        && definition.name.len() <= 64
+        && definition
+            .name
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_');
+    if !name_ok {
+        return Err("Script name must be 1-64 characters of [A-Za-z0-9_-]".to_string());
+    }
+    if definition.source.len() > MAX_SOURCE_LEN {
+        return Err(format!("Script source exceeds {} bytes", MAX_SOURCE_LEN));
+    }
+    if definition.timeout_ms == 0 || definition.timeout_ms > MAX_TIMEOUT_MS {
+        return Err(format!(
+            "timeout_ms must be between 1 and {}",
+            MAX_TIMEOUT_MS
+        ));
+    }
+    if definition.max_operations == 0 || definition.max_operations > MAX_OPERATIONS {
+        return Err(format!(
+            "max_operations must be between 1 and {}",
+            MAX_OPERATIONS
+        ));
+    }
+    Ok(())
+}
+
+fn same_script(a: &ScriptDefinition, b: &ScriptDefinition) -> bool {
+    a.tenant_id == b.tenant_id && a.name == b.name
+}
+
+fn sort_scripts(scripts: &mut [Arc<CompiledScript>]) {
+    scripts.sort_by(|a, b| {
+        (a.definition.tenant_id, &a.definition.name)
+            .cmp(&(b.definition.tenant_id, &b.definition.name))
+    });
+}
+
+fn compile_result(definition: ScriptDefinition, ast: AST) -> CompiledScript {
+    CompiledScript {
+        definition,
+        ast,
+        runs: AtomicU64::new(0),
+        errors: AtomicU64::new(0),
+        timeouts: AtomicU64::new(0),
+        dropped: AtomicU64::new(0),
+        last_error: RwLock::new(None),
+    }
+}
+
+fn script_info(script: &CompiledScript) -> ScriptInfo {
+    ScriptInfo {
+        definition: script.definition.clone(),
+        stats: script.stats(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    fn script(name: &str, hook: ScriptHook, source: &str) -> ScriptDefinition {
+        ScriptDefinition {
+            tenant_id: 1,
+            name: name.to_string(),
+            hook,
+            source: source.to_string(),
+            description: None,
+            enabled: true,
+            timeout_ms: DEFAULT_TIMEOUT_MS,
+            max_operations: DEFAULT_MAX_OPERATIONS,
+            created_at: 0,
+            updated_at: 0,
+        }
+    }
+
+    #[test]
+    fn test_ingest_hook_tags_drops_and_notifies() {
+        let temp_dir = TempDir::new().unwrap();
+        let manager = ScriptManager::new(temp_dir.path().join("scripts.json"));
+        manager
+            .upsert(script(
+                "a-tag",
+                ScriptHook::OnIngest,
+                r#"record.attributes["team"] = "search";
+                   if record.name == "healthcheck" { keep = false; }
+                   notifications.push(#{ channel: "log", message: record.name });"#,
+            ))
+            .unwrap();
+
+        let outcome = manager.run(
+            1,
+            ScriptHook::OnIngest,
+            serde_json::json!({ "name": "llm_call", "attributes": {} }),
+        );
+        assert!(!outcome.dropped);
+        assert_eq!(outcome.record["attributes"]["team"], "search");
+        assert_eq!(outcome.notifications[0].message, "llm_call");
+
+        let outcome = manager.run(
+            1,
+            ScriptHook::OnIngest,
+            serde_json::json!({ "name": "healthcheck", "attributes": {} }),
+        );
+        assert!(outcome.dropped);
+
+        // Other hooks and other tenants' records are unaffected
+        assert!(!manager.has_hook(1, ScriptHook::OnAlert));
+        assert!(!manager.has_hook(2, ScriptHook::OnIngest));
+        let outcome = manager.run(
+            2,
+            ScriptHook::OnIngest,
+            serde_json::json!({ "name": "healthcheck", "attributes": {} }),
+        );
+        assert!(!outcome.dropped);
+        assert!(outcome.notifications.is_empty());
+        assert!(manager.list(2).is_empty());
+        assert!(manager.get(2, "a-tag").is_none());
+    }
+
+    #[test]
+    fn test_limits_and_failures_pass_record_through() {
+        let temp_dir = TempDir::new().unwrap();
+        let manager = ScriptManager::new(temp_dir.path().join("scripts.json"));
+        assert!(manager
+            .upsert(script("broken", ScriptHook::OnIngest, "let x = ;"))
+            .is_err());
+
+        manager
+            .upsert(script("spin", ScriptHook::OnIngest, "loop { }"))
+            .unwrap();
+        let record = serde_json::json!({ "name": "span" });
+        let outcome = manager.run(1, ScriptHook::OnIngest, record.clone());
+        assert_eq!(outcome.record, record);
+        assert!(!outcome.dropped);
+
+        let stats = manager.get(1, "spin").unwrap().stats;
+        assert_eq!(stats.errors, 1);
+        assert!(manager.test(1, "spin", record).is_err());
+    }
+
+    #[test]
+    fn test_scripts_persist() {
+        let temp_dir = TempDir::new().unwrap();
+        let path = temp_dir.path().join("scripts.json");
+        {
+            let manager = ScriptManager::new(&path);
+            manager
+                .upsert(script("alert", ScriptHook::OnAlert, "keep = true;"))
+                .unwrap();
+        }
+
+        let manager = ScriptManager::new(&path);
+        assert!(manager.has_hook(1, ScriptHook::OnAlert));
+        assert!(!manager.delete(2, "alert").unwrap());
+        assert!(manager.delete(1, "alert").unwrap());
+        assert!(!manager.delete(1, "alert").unwrap());
+    }
+
+    #[test]
+    fn test_webhook_targets() {
+        let allowlist = vec!["hooks.slack.com".to_string(), "*.example.com".to_string()];
+        assert!(webhook_host_allowed("hooks.slack.com", &allowlist));
+        assert!(webhook_host_allowed("alerts.example.com", &allowlist));
+        assert!(!webhook_host_allowed("example.com", &allowlist));
+        assert!(!webhook_host_allowed("evilexample.com", &allowlist));
+        assert!(!webhook_host_allowed("169.254.169.254", &allowlist));
+
+        for blocked in [
+            "127.0.0.1",
+            "10.0.0.8",
+            "169.254.169.254",
+            "100.64.1.1",
+            "::1",
+            "fd00::1",
+            "::ffff:192.168.1.1",
+        ] {
+            assert!(!is_public_address(blocked.parse().unwrap()), "{}", blocked);
+        }
+        assert!(is_public_address("93.184.216.34".parse().unwrap()));
+        assert!(is_public_address("2606:4700::1111".parse().unwrap()));
+    }
+}
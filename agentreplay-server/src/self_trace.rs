@@ -0,0 +1,150 @@
+// Copyright 2025 AgentReplay (https://github.com/agentreplay)
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program. If not, see <https://www.gnu.org/licenses/>.
+
+//! The server's own traces, stored in a reserved internal project
+//!
+//! With `[self_tracing]` enabled, a [`SelfTraceLayer`] on the server's
+//! subscriber turns sampled spans (ingestion, queries, governor batches)
+//! into Agentreplay spans of project [`SELF_TRACE_PROJECT_ID`], and
+//! [`run_exporter`] stores them like any client's spans. Storing them
+//! runs in an export span, so the ingestion it does is not traced in
+//! turn; sampling and a spans-per-second cap bound the rest.
+//!
+//! Only writers store self-traces. On replicas and standbys the spans are
+//! dropped.
+
+use std::collections::HashMap;
+
+use agentreplay_observability::batcher::AgentreplaySpan as ExportedSpan;
+use agentreplay_observability::self_trace::{export_span, SelfTraceLayer, SelfTraceOptions};
+use tokio::sync::mpsc;
+use tracing::Instrument;
+
+use crate::api::ingest::{self, AgentreplaySpan};
+use crate::api::AppState;
+use crate::config::SelfTracingConfig;
+
+/// Project of the server's own traces; generated project IDs never use it
+pub const SELF_TRACE_PROJECT_ID: u16 = u16::MAX;
+pub const SELF_TRACE_PROJECT_NAME: &str = "Agentreplay Internal";
+
+/// Spans buffered between the layer and the exporter
+const CHANNEL_CAPACITY: usize = 4096;
+/// Spans stored per wake-up of the exporter
+const EXPORT_BATCH: usize = 256;
+
+/// Layer exporting the server's spans, and the receiving end for
+/// [`run_exporter`]
+pub fn layer(
+    config: &SelfTracingConfig,
+) -> (
+    SelfTraceLayer<mpsc::Sender<ExportedSpan>>,
+    mpsc::Receiver<ExportedSpan>,
+) {
+    let (sender, receiver) = mpsc::channel(CHANNEL_CAPACITY);
+    let options = SelfTraceOptions {
+        sample_rate: config.sample_rate,
+        max_spans_per_second: config.max_spans_per_second,
+        targets: config.targets.clone(),
+        attributes: HashMap::from([
+            ("tenant_id".to_string(), config.tenant_id.to_string()),
+            ("project_id".to_string(), SELF_TRACE_PROJECT_ID.to_string()),
+            ("service.name".to_string(), "agentreplay-server".to_string()),
+        ]),
+    };
+    (SelfTraceLayer::new(sender, options), receiver)
+}
+
+/// Store self-traced spans until the layer is dropped
+pub async fn run_exporter(state: AppState, mut receiver: mpsc::Receiver<ExportedSpan>) {
+    async move {
+        register_project(&state);
+        let mut batch = Vec::with_capacity(EXPORT_BATCH);
+        while receiver.recv_many(&mut batch, EXPORT_BATCH).await > 0 {
+            for span in batch.drain(..) {
+                let name = span.name.clone();
+                if let Err(e) = store(&state, span).await {
+                    tracing::debug!("Dropping self-trace span {}: {}", name, e);
+                }
+            }
+        }
+    }
+    .instrument(export_span())
+    .await
+}
+
+/// Make the internal project visible in project listings
+fn register_project(state: &AppState) {
+    let Some(registry) = &state.project_registry else {
+        return;
+    };
+    if registry.get_metadata(SELF_TRACE_PROJECT_ID).is_some() {
+        return;
+    }
+    if let Err(e) = registry.register_project(
+        SELF_TRACE_PROJECT_ID,
+        SELF_TRACE_PROJECT_NAME.to_string(),
+        Some("Traces of the Agentreplay server itself".to_string()),
+    ) {
+        tracing::warn!("Failed to register the self-trace project: {}", e);
+    }
+}
+
+async fn store(state: &AppState, span: ExportedSpan) -> Result<(), String> {
+    let span = ingest_span(span);
+    let edge = ingest::convert_span_to_edge(&span)?;
+    ingest::store_edge_and_payload(state, &edge, &span.attributes, None).await
+}
+
+fn ingest_span(span: ExportedSpan) -> AgentreplaySpan {
+    AgentreplaySpan {
+        span_id: span.span_id,
+        trace_id: span.trace_id,
+        parent_span_id: span.parent_span_id,
+        name: span.name,
+        start_time: span.start_time,
+        end_time: span.end_time,
+        attributes: span.attributes,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tracing_subscriber::layer::SubscriberExt;
+
+    #[test]
+    fn test_spans_route_to_internal_project() {
+        let config = SelfTracingConfig {
+            enabled: true,
+            sample_rate: 1.0,
+            tenant_id: 9,
+            ..Default::default()
+        };
+        let (layer, mut receiver) = layer(&config);
+        let subscriber = tracing_subscriber::registry().with(layer);
+        tracing::subscriber::with_default(subscriber, || {
+            tracing::info_span!("query", project_id = 3).in_scope(|| {});
+            // Storing spans is not traced
+            export_span().in_scope(|| tracing::info_span!("ingest").in_scope(|| {}));
+        });
+
+        let span = receiver.try_recv().unwrap();
+        assert!(receiver.try_recv().is_err());
+        let edge = ingest::convert_span_to_edge(&ingest_span(span)).unwrap();
+        assert_eq!(edge.project_id, SELF_TRACE_PROJECT_ID);
+        assert_eq!(edge.tenant_id, 9);
+    }
+}
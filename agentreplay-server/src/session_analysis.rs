@@ -0,0 +1,754 @@
+// Copyright 2025 AgentReplay (https://github.com/agentreplay)
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program. If not, see <https://www.gnu.org/licenses/>.
+
+//! Session topic drift and goal-completion analysis
+//!
+//! A finished session is reduced to its turns (the user message and answer
+//! of each LLM call) and tagged with:
+//! - topics: the most frequent keywords of the user messages, or the topics
+//!   an LLM names when `session_analysis.llm_provider` is set
+//! - topic drift: how far the last turn's keywords moved from the first's
+//! - a goal verdict: an explicit `agentreplay.goal.completed` span attribute
+//!   wins; otherwise a failed last turn, or the tone of the user's last
+//!   message ("thanks, that worked" / "that's still wrong"), decides
+//!
+//! Results are stored per session as [`SessionAnalysis`] records and
+//! aggregated into goal-completion funnels per project and per prompt version
+//! (`agentreplay.prompt.name` / `agentreplay.prompt.version` attributes).
+
+use crate::api::payload_extractors::{extract_completions, extract_prompts};
+use crate::api::AppState;
+use crate::config::SessionAnalysisConfig;
+use crate::llm::{ChatMessage, LLMProviderManager};
+use crate::otel_genai::GenAIPayload;
+use agentreplay_core::clock::now_us;
+use agentreplay_core::{AgentFlowEdge, SpanType};
+use agentreplay_query::{Agentreplay, TenantScope};
+use agentreplay_storage::{GoalVerdict, SessionAnalysis};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::sync::Arc;
+use std::time::Duration;
+use tracing::{debug, info, warn};
+
+/// Explicit goal outcome set by the client ("true" / "false")
+pub const ATTR_GOAL_COMPLETED: &str = "agentreplay.goal.completed";
+/// Name of the prompt template used by a span
+pub const ATTR_PROMPT_NAME: &str = "agentreplay.prompt.name";
+/// Version of the prompt template used by a span
+pub const ATTR_PROMPT_VERSION: &str = "agentreplay.prompt.version";
+
+const MAX_TOPICS: usize = 3;
+const MIN_KEYWORD_LEN: usize = 4;
+
+/// Turns and characters per message sent to the LLM
+const LLM_MAX_TURNS: usize = 20;
+const LLM_MAX_MESSAGE_CHARS: usize = 500;
+
+const STOPWORDS: [&str; 49] = [
+    "about", "after", "again", "also", "been", "before", "being", "could", "does", "doing", "from",
+    "have", "having", "here", "into", "just", "like", "make", "more", "most", "much", "need",
+    "only", "other", "please", "should", "some", "such", "than", "that", "their", "them", "then",
+    "there", "these", "they", "this", "those", "very", "want", "what", "when", "where", "which",
+    "while", "will", "with", "would", "your",
+];
+
+/// Phrases in the user's last message that signal the goal was reached
+const COMPLETED_PHRASES: [&str; 9] = [
+    "thank",
+    "perfect",
+    "that works",
+    "that worked",
+    "it works",
+    "solved",
+    "got it",
+    "exactly what i",
+    "awesome",
+];
+
+/// Phrases that signal it was not (checked first: "thanks, but still wrong")
+const NOT_COMPLETED_PHRASES: [&str; 11] = [
+    "doesn't work",
+    "does not work",
+    "didn't work",
+    "not working",
+    "still wrong",
+    "still not",
+    "not what i",
+    "not helpful",
+    "useless",
+    "talk to a human",
+    "speak to a human",
+];
+
+/// One exchange of a session: the user message and the answer
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct SessionTurn {
+    pub timestamp_us: u64,
+    pub input: Option<String>,
+    pub output: Option<String>,
+    pub error: bool,
+    /// Explicit outcome from [`ATTR_GOAL_COMPLETED`]
+    pub goal_signal: Option<bool>,
+    /// `name@version` of the prompt template used
+    pub prompt_version: Option<String>,
+}
+
+pub(crate) fn attr_string(payload: &GenAIPayload, key: &str) -> Option<String> {
+    match payload.additional.get(key)? {
+        serde_json::Value::String(s) if !s.trim().is_empty() => Some(s.trim().to_string()),
+        serde_json::Value::Number(n) => Some(n.to_string()),
+        serde_json::Value::Bool(b) => Some(b.to_string()),
+        _ => None,
+    }
+}
+
+fn turn_from_payload(timestamp_us: u64, payload: &GenAIPayload, error: bool) -> SessionTurn {
+    let goal_signal = attr_string(payload, ATTR_GOAL_COMPLETED).and_then(|value| {
+        match value.to_ascii_lowercase().as_str() {
+            "true" | "1" | "yes" => Some(true),
+            "false" | "0" | "no" => Some(false),
+            _ => None,
+        }
+    });
+    let prompt_version = attr_string(payload, ATTR_PROMPT_NAME).map(|name| {
+        match attr_string(payload, ATTR_PROMPT_VERSION) {
+            Some(version) => format!("{}@{}", name, version),
+            None => name,
+        }
+    });
+    SessionTurn {
+        timestamp_us,
+        input: extract_prompts(payload)
+            .into_iter()
+            .rev()
+            .find(|message| message.role == "user")
+            .map(|message| message.content),
+        output: extract_completions(payload)
+            .into_iter()
+            .next()
+            .map(|message| message.content),
+        error: error || payload.error_type.is_some(),
+        goal_signal,
+        prompt_version,
+    }
+}
+
+/// Turns of a session from its spans' payloads, in time order
+///
+/// Consecutive LLM calls answering the same user message (agent steps) are
+/// merged into one turn.
+pub fn collect_turns(db: &Agentreplay, edges: &[AgentFlowEdge]) -> Vec<SessionTurn> {
+    let mut edges: Vec<&AgentFlowEdge> = edges.iter().collect();
+    edges.sort_by_key(|edge| edge.timestamp_us);
+
+    let mut turns: Vec<SessionTurn> = Vec::new();
+    for edge in edges {
+        let is_error = edge.get_span_type() == SpanType::Error;
+        let payload = db
+            .get_payload(edge.edge_id)
+            .ok()
+            .flatten()
+            .and_then(|bytes| serde_json::from_slice::<GenAIPayload>(&bytes).ok());
+        let turn = match payload {
+            Some(payload) => turn_from_payload(edge.timestamp_us, &payload, is_error),
+            None => SessionTurn {
+                timestamp_us: edge.timestamp_us,
+                error: is_error,
+                ..Default::default()
+            },
+        };
+        if turn.input.is_none()
+            && turn.output.is_none()
+            && !turn.error
+            && turn.goal_signal.is_none()
+        {
+            continue;
+        }
+
+        match turns.last_mut() {
+            Some(last) if turn.input.is_none() || turn.input == last.input => {
+                last.timestamp_us = turn.timestamp_us;
+                last.output = turn.output.or(last.output.take());
+                last.error = turn.error;
+                last.goal_signal = turn.goal_signal.or(last.goal_signal);
+                last.prompt_version = turn.prompt_version.or(last.prompt_version.take());
+            }
+            _ => turns.push(turn),
+        }
+    }
+    turns
+}
+
+fn keywords(text: &str) -> impl Iterator<Item = String> + '_ {
+    text.split(|c: char| !c.is_alphanumeric())
+        .filter(|word| word.chars().count() >= MIN_KEYWORD_LEN)
+        .map(str::to_lowercase)
+        .filter(|word| !STOPWORDS.contains(&word.as_str()))
+        .filter(|word| !word.chars().all(|c| c.is_ascii_digit()))
+}
+
+/// Text that represents a turn's subject: the user message, else the answer
+fn turn_text(turn: &SessionTurn) -> Option<&str> {
+    turn.input.as_deref().or(turn.output.as_deref())
+}
+
+/// Most frequent keywords of the session's user messages
+pub fn heuristic_topics(turns: &[SessionTurn]) -> Vec<String> {
+    let mut counts: HashMap<String, usize> = HashMap::new();
+    for text in turns.iter().filter_map(turn_text) {
+        for word in keywords(text) {
+            *counts.entry(word).or_default() += 1;
+        }
+    }
+    let mut ranked: Vec<(String, usize)> = counts.into_iter().collect();
+    ranked.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+    ranked
+        .into_iter()
+        .take(MAX_TOPICS)
+        .map(|(word, _)| word)
+        .collect()
+}
+
+/// Jaccard distance between the keywords of the first and last turns
+pub fn topic_drift(turns: &[SessionTurn]) -> f32 {
+    let texts: Vec<&str> = turns.iter().filter_map(turn_text).collect();
+    let (Some(first), Some(last)) = (texts.first(), texts.last()) else {
+        return 0.0;
+    };
+    if texts.len() < 2 {
+        return 0.0;
+    }
+    let first: HashSet<String> = keywords(first).collect();
+    let last: HashSet<String> = keywords(last).collect();
+    let union = first.union(&last).count();
+    if union == 0 {
+        return 0.0;
+    }
+    1.0 - first.intersection(&last).count() as f32 / union as f32
+}
+
+fn explicit_verdict(turns: &[SessionTurn]) -> Option<GoalVerdict> {
+    turns
+        .iter()
+        .rev()
+        .find_map(|turn| turn.goal_signal)
+        .map(|completed| {
+            if completed {
+                GoalVerdict::Completed
+            } else {
+                GoalVerdict::NotCompleted
+            }
+        })
+}
+
+/// Goal verdict from explicit signals, errors and the user's last message
+pub fn heuristic_verdict(turns: &[SessionTurn]) -> (GoalVerdict, String) {
+    if let Some(verdict) = explicit_verdict(turns) {
+        return (verdict, format!("{} attribute", ATTR_GOAL_COMPLETED));
+    }
+    let Some(last) = turns.last() else {
+        return (GoalVerdict::Unknown, "no turns".to_string());
+    };
+    if last.error {
+        return (GoalVerdict::NotCompleted, "last turn failed".to_string());
+    }
+    if let Some(input) = turns.iter().rev().find_map(|turn| turn.input.as_deref()) {
+        let input = input.to_lowercase();
+        if let Some(phrase) = NOT_COMPLETED_PHRASES.iter().find(|p| input.contains(*p)) {
+            return (
+                GoalVerdict::NotCompleted,
+                format!("last user message says \"{}\"", phrase),
+            );
+        }
+        if let Some(phrase) = COMPLETED_PHRASES.iter().find(|p| input.contains(*p)) {
+            return (
+                GoalVerdict::Completed,
+                format!("last user message says \"{}\"", phrase),
+            );
+        }
+    }
+    (GoalVerdict::Unknown, "no completion signal".to_string())
+}
+
+/// Answer expected from the LLM
+#[derive(Debug, Deserialize)]
+struct LlmVerdict {
+    #[serde(default)]
+    topics: Vec<String>,
+    goal_completed: Option<bool>,
+    #[serde(default)]
+    reason: String,
+}
+
+fn truncate_chars(text: &str, max: usize) -> String {
+    text.chars().take(max).collect()
+}
+
+fn build_llm_prompt(turns: &[SessionTurn]) -> String {
+    let start = turns.len().saturating_sub(LLM_MAX_TURNS);
+    let mut transcript = String::new();
+    for turn in &turns[start..] {
+        if let Some(ref input) = turn.input {
+            transcript.push_str(&format!(
+                "USER: {}\n",
+                truncate_chars(input, LLM_MAX_MESSAGE_CHARS)
+            ));
+        }
+        if let Some(ref output) = turn.output {
+            transcript.push_str(&format!(
+                "ASSISTANT: {}\n",
+                truncate_chars(output, LLM_MAX_MESSAGE_CHARS)
+            ));
+        }
+        if turn.error {
+            transcript.push_str("(the assistant failed with an error)\n");
+        }
+    }
+    format!(
+        "Read this conversation between a user and an AI assistant.\n\n{}\n\
+         Answer with JSON only: {{\"topics\": [up to {} short topic labels], \
+         \"goal_completed\": true if the user's goal was achieved, false if not, \
+         null if unclear, \"reason\": one short sentence}}",
+        transcript, MAX_TOPICS
+    )
+}
+
+fn parse_llm_verdict(content: &str) -> Option<LlmVerdict> {
+    let start = content.find('{')?;
+    let end = content.rfind('}')?;
+    serde_json::from_str(content.get(start..=end)?).ok()
+}
+
+/// Analyzes finished sessions and stores the results
+pub struct SessionAnalyzer {
+    config: SessionAnalysisConfig,
+    llm_manager: Option<Arc<LLMProviderManager>>,
+}
+
+impl SessionAnalyzer {
+    pub fn new(
+        config: SessionAnalysisConfig,
+        llm_manager: Option<Arc<LLMProviderManager>>,
+    ) -> Self {
+        Self {
+            config,
+            llm_manager,
+        }
+    }
+
+    pub fn config(&self) -> &SessionAnalysisConfig {
+        &self.config
+    }
+
+    async fn llm_verdict(&self, turns: &[SessionTurn]) -> Option<LlmVerdict> {
+        let provider = self.config.llm_provider.as_deref()?;
+        let llm = self.llm_manager.as_ref()?;
+        let messages = vec![
+            ChatMessage {
+                role: "system".to_string(),
+                content: "You classify conversations. Output only valid JSON.".to_string(),
+            },
+            ChatMessage {
+                role: "user".to_string(),
+                content: build_llm_prompt(turns),
+            },
+        ];
+        match llm
+            .chat(provider, self.config.llm_model.clone(), messages, 0, 0)
+            .await
+        {
+            Ok(response) => {
+                let verdict = parse_llm_verdict(&response.content);
+                if verdict.is_none() {
+                    warn!("Session analysis: unparseable LLM answer, using heuristics");
+                }
+                verdict
+            }
+            Err(e) => {
+                warn!("Session analysis LLM call failed, using heuristics: {}", e);
+                None
+            }
+        }
+    }
+
+    /// Analyze a session from its spans and turns; None without spans
+    pub async fn analyze(
+        &self,
+        edges: &[AgentFlowEdge],
+        turns: &[SessionTurn],
+    ) -> Option<SessionAnalysis> {
+        let first = edges.iter().min_by_key(|edge| edge.timestamp_us)?;
+        let ended_at = edges
+            .iter()
+            .map(|edge| edge.timestamp_us + edge.duration_us as u64)
+            .max()
+            .unwrap_or(first.timestamp_us);
+
+        let (mut goal, mut reason) = heuristic_verdict(turns);
+        let mut topics = heuristic_topics(turns);
+        let mut method = "heuristic";
+        if !turns.is_empty() {
+            if let Some(verdict) = self.llm_verdict(turns).await {
+                method = "llm";
+                if !verdict.topics.is_empty() {
+                    topics = verdict.topics;
+                    topics.truncate(MAX_TOPICS);
+                }
+                // Explicit client signals still win over the model's opinion
+                if explicit_verdict(turns).is_none() {
+                    goal = match verdict.goal_completed {
+                        Some(true) => GoalVerdict::Completed,
+                        Some(false) => GoalVerdict::NotCompleted,
+                        None => GoalVerdict::Unknown,
+                    };
+                    reason = verdict.reason;
+                }
+            }
+        }
+
+        let mut prompt_versions: Vec<String> = turns
+            .iter()
+            .filter_map(|turn| turn.prompt_version.clone())
+            .collect();
+        prompt_versions.sort();
+        prompt_versions.dedup();
+
+        Some(SessionAnalysis {
+            session_id: first.session_id,
+            tenant_id: first.tenant_id,
+            project_id: first.project_id,
+            started_at: first.timestamp_us,
+            ended_at,
+            turn_count: turns.len() as u32,
+            topics,
+            topic_drift: topic_drift(turns),
+            goal,
+            reason,
+            prompt_versions,
+            method: method.to_string(),
+            analyzed_at: now_us(),
+        })
+    }
+
+    /// Analyze one session of `db` and store the result
+    pub async fn analyze_session(
+        &self,
+        db: &Agentreplay,
+        session_id: u64,
+    ) -> agentreplay_core::Result<Option<SessionAnalysis>> {
+        let edges = db.get_session_edges_full(session_id)?;
+        let turns = collect_turns(db, &edges);
+        let Some(analysis) = self.analyze(&edges, &turns).await else {
+            return Ok(None);
+        };
+        db.put_session_analysis(&analysis)?;
+        Ok(Some(analysis))
+    }
+
+    /// Analyze the sessions that went idle since they were last analyzed
+    ///
+    /// Returns how many sessions were analyzed.
+    pub async fn run_pass(&self, state: &AppState) -> usize {
+        let shards = match crate::api::metrics::project_shards(state, None) {
+            Ok(shards) => shards,
+            Err(e) => {
+                warn!("Session analysis pass: {:?}", e);
+                return 0;
+            }
+        };
+        let now = now_us();
+        let idle_cutoff = now.saturating_sub(self.config.idle_minutes * 60_000_000);
+        let since = now.saturating_sub(self.config.lookback_hours * 3_600_000_000);
+
+        let mut analyzed = 0;
+        for db in shards {
+            let edges = match db.scoped(TenantScope::AllTenants).range(since, now) {
+                Ok(edges) => edges,
+                Err(e) => {
+                    warn!("Session analysis pass: failed to scan spans: {}", e);
+                    continue;
+                }
+            };
+            let mut last_seen: BTreeMap<u64, u64> = BTreeMap::new();
+            // Session 0 holds spans sent without a session
+            for edge in edges.iter().filter(|edge| edge.session_id != 0) {
+                let last = last_seen.entry(edge.session_id).or_default();
+                *last = (*last).max(edge.timestamp_us);
+            }
+
+            for (session_id, last_ts) in last_seen {
+                if last_ts > idle_cutoff {
+                    continue;
+                }
+                match db.get_session_analysis(session_id) {
+                    Ok(Some(existing)) if existing.ended_at >= last_ts => continue,
+                    Ok(_) => {}
+                    Err(e) => {
+                        warn!("Failed to read analysis of session {}: {}", session_id, e);
+                        continue;
+                    }
+                }
+                match self.analyze_session(&db, session_id).await {
+                    Ok(Some(_)) => analyzed += 1,
+                    Ok(None) => {}
+                    Err(e) => warn!("Failed to analyze session {}: {}", session_id, e),
+                }
+            }
+        }
+        analyzed
+    }
+
+    /// Start the periodic analysis pass
+    pub fn spawn(self: &Arc<Self>, state: AppState) {
+        let analyzer = self.clone();
+        tokio::spawn(async move {
+            let mut interval =
+                tokio::time::interval(Duration::from_secs(analyzer.config.interval_minutes * 60));
+            loop {
+                interval.tick().await;
+                let analyzed = analyzer.run_pass(&state).await;
+                if analyzed > 0 {
+                    info!("Session analysis pass analyzed {} sessions", analyzed);
+                } else {
+                    debug!("Session analysis pass found no finished sessions");
+                }
+            }
+        });
+    }
+}
+
+/// Sessions at each funnel stage
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct FunnelCounts {
+    /// Sessions with spans in the window
+    pub started: u64,
+    /// ... of which have been analyzed
+    pub analyzed: u64,
+    pub goal_completed: u64,
+    pub not_completed: u64,
+    pub unknown: u64,
+    /// `goal_completed / analyzed`
+    pub completion_rate: f64,
+}
+
+impl FunnelCounts {
+    fn record(&mut self, analysis: Option<&SessionAnalysis>) {
+        self.started += 1;
+        let Some(analysis) = analysis else {
+            return;
+        };
+        self.analyzed += 1;
+        match analysis.goal {
+            GoalVerdict::Completed => self.goal_completed += 1,
+            GoalVerdict::NotCompleted => self.not_completed += 1,
+            GoalVerdict::Unknown => self.unknown += 1,
+        }
+        self.completion_rate = self.goal_completed as f64 / self.analyzed as f64;
+    }
+}
+
+/// Funnel of one project or prompt version
+#[derive(Debug, Clone, Serialize)]
+pub struct FunnelGroup {
+    pub key: String,
+    #[serde(flatten)]
+    pub counts: FunnelCounts,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct TopicCount {
+    pub topic: String,
+    pub sessions: u64,
+}
+
+/// Goal-completion funnel over a set of sessions
+#[derive(Debug, Clone, Serialize)]
+pub struct SessionFunnel {
+    #[serde(flatten)]
+    pub total: FunnelCounts,
+    pub by_project: Vec<FunnelGroup>,
+    /// Analyzed sessions by prompt version; a session using several versions
+    /// counts in each
+    pub by_prompt_version: Vec<FunnelGroup>,
+    pub top_topics: Vec<TopicCount>,
+    pub avg_topic_drift: f64,
+}
+
+/// Build the funnel of `sessions` (session id, project id) from their analyses
+pub fn build_funnel(
+    sessions: &[(u64, u16)],
+    analyses: &HashMap<u64, SessionAnalysis>,
+    max_topics: usize,
+) -> SessionFunnel {
+    let mut total = FunnelCounts::default();
+    let mut by_project: BTreeMap<u16, FunnelCounts> = BTreeMap::new();
+    let mut by_prompt_version: BTreeMap<String, FunnelCounts> = BTreeMap::new();
+    let mut topics: HashMap<String, u64> = HashMap::new();
+    let mut drift_sum = 0.0;
+
+    for (session_id, project_id) in sessions {
+        let analysis = analyses.get(session_id);
+        total.record(analysis);
+        by_project.entry(*project_id).or_default().record(analysis);
+        let Some(analysis) = analysis else {
+            continue;
+        };
+        for version in &analysis.prompt_versions {
+            by_prompt_version
+                .entry(version.clone())
+                .or_default()
+                .record(Some(analysis));
+        }
+        for topic in &analysis.topics {
+            *topics.entry(topic.clone()).or_default() += 1;
+        }
+        drift_sum += analysis.topic_drift as f64;
+    }
+
+    let mut top_topics: Vec<TopicCount> = topics
+        .into_iter()
+        .map(|(topic, sessions)| TopicCount { topic, sessions })
+        .collect();
+    top_topics.sort_by(|a, b| {
+        b.sessions
+            .cmp(&a.sessions)
+            .then_with(|| a.topic.cmp(&b.topic))
+    });
+    top_topics.truncate(max_topics);
+
+    SessionFunnel {
+        avg_topic_drift: if total.analyzed == 0 {
+            0.0
+        } else {
+            drift_sum / total.analyzed as f64
+        },
+        total,
+        by_project: by_project
+            .into_iter()
+            .map(|(project_id, counts)| FunnelGroup {
+                key: project_id.to_string(),
+                counts,
+            })
+            .collect(),
+        by_prompt_version: by_prompt_version
+            .into_iter()
+            .map(|(key, counts)| FunnelGroup { key, counts })
+            .collect(),
+        top_topics,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn turn(input: &str, output: &str) -> SessionTurn {
+        SessionTurn {
+            input: Some(input.to_string()),
+            output: Some(output.to_string()),
+            ..Default::default()
+        }
+    }
+
+    fn analysis(session_id: u64, goal: GoalVerdict, versions: &[&str]) -> SessionAnalysis {
+        SessionAnalysis {
+            session_id,
+            tenant_id: 1,
+            project_id: 0,
+            started_at: 0,
+            ended_at: 0,
+            turn_count: 2,
+            topics: vec!["refund".to_string()],
+            topic_drift: 0.5,
+            goal,
+            reason: String::new(),
+            prompt_versions: versions.iter().map(|v| v.to_string()).collect(),
+            method: "heuristic".to_string(),
+            analyzed_at: 0,
+        }
+    }
+
+    #[test]
+    fn test_topics_and_drift() {
+        let turns = vec![
+            turn("I need a refund for my order 1234", "Sure, which order?"),
+            turn("The refund for order 1234 please", "Refund issued."),
+            turn(
+                "Also, what is your shipping policy for Canada?",
+                "We ship...",
+            ),
+        ];
+        assert_eq!(heuristic_topics(&turns), vec!["order", "refund", "canada"]);
+        assert!((topic_drift(&turns) - 1.0).abs() < 1e-6);
+        assert_eq!(topic_drift(&turns[..2]), 0.0);
+        assert_eq!(topic_drift(&turns[..1]), 0.0);
+    }
+
+    #[test]
+    fn test_heuristic_verdict() {
+        let mut turns = vec![
+            turn("How do I reset my password?", "Click 'Forgot password'."),
+            turn("Thanks, that worked!", "Glad to help."),
+        ];
+        assert_eq!(heuristic_verdict(&turns).0, GoalVerdict::Completed);
+
+        turns[1].input = Some("Thanks, but it still not sending the email".to_string());
+        assert_eq!(heuristic_verdict(&turns).0, GoalVerdict::NotCompleted);
+
+        turns[1].input = Some("And for the mobile app?".to_string());
+        assert_eq!(heuristic_verdict(&turns).0, GoalVerdict::Unknown);
+
+        turns[1].error = true;
+        assert_eq!(heuristic_verdict(&turns).0, GoalVerdict::NotCompleted);
+
+        turns[0].goal_signal = Some(true);
+        assert_eq!(heuristic_verdict(&turns).0, GoalVerdict::Completed);
+    }
+
+    #[test]
+    fn test_parse_llm_verdict() {
+        let verdict = parse_llm_verdict(
+            "Here you go:\n```json\n{\"topics\": [\"billing\"], \"goal_completed\": false, \"reason\": \"User gave up\"}\n```",
+        )
+        .unwrap();
+        assert_eq!(verdict.topics, vec!["billing"]);
+        assert_eq!(verdict.goal_completed, Some(false));
+        assert!(parse_llm_verdict("no idea").is_none());
+    }
+
+    #[test]
+    fn test_build_funnel() {
+        let sessions = [(1, 0), (2, 0), (3, 5), (4, 5)];
+        let analyses = HashMap::from([
+            (1, analysis(1, GoalVerdict::Completed, &["support@2"])),
+            (2, analysis(2, GoalVerdict::NotCompleted, &["support@3"])),
+            (3, analysis(3, GoalVerdict::Completed, &["support@3"])),
+        ]);
+        let funnel = build_funnel(&sessions, &analyses, 10);
+
+        assert_eq!(funnel.total.started, 4);
+        assert_eq!(funnel.total.analyzed, 3);
+        assert_eq!(funnel.total.goal_completed, 2);
+        assert!((funnel.total.completion_rate - 2.0 / 3.0).abs() < 1e-9);
+        assert_eq!(funnel.by_project[1].key, "5");
+        assert_eq!(funnel.by_project[1].counts.started, 2);
+        assert_eq!(funnel.by_project[1].counts.analyzed, 1);
+        assert_eq!(funnel.by_prompt_version[1].key, "support@3");
+        assert_eq!(funnel.by_prompt_version[1].counts.completion_rate, 0.5);
+        assert_eq!(funnel.top_topics[0].sessions, 3);
+        assert!((funnel.avg_topic_drift - 0.5).abs() < 1e-9);
+    }
+}
@@ -0,0 +1,387 @@
+// Copyright 2025 AgentReplay (https://github.com/agentreplay)
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program. If not, see <https://www.gnu.org/licenses/>.
+
+//! External session key registry
+//!
+//! SDKs mint a fresh session_id per process, so an agent that restarts
+//! mid-conversation ends up split across several sessions. Clients can instead
+//! tag spans with an external session key (e.g. a chat thread ID); the first
+//! time a key is seen it is bound to that span's session_id, and every later
+//! span carrying the same key - from any process - is stitched into it.
+//!
+//! Mappings are scoped per tenant and project and persisted to disk so they
+//! survive server restarts. A key unused for [`MAPPING_TTL_SECS`] expires,
+//! and at most [`MAX_MAPPINGS`] keys are kept: when full, the least recently
+//! used tenth is dropped to make room.
+
+use agentreplay_core::clock::now_secs;
+use parking_lot::Mutex;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fs::{self, File};
+use std::io::{BufReader, BufWriter};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, RwLock};
+use tracing::{error, info, warn};
+
+/// Span attribute carrying the client-provided external session key
+pub const EXTERNAL_SESSION_KEY_ATTR: &str = "session.external_key";
+
+/// Maximum accepted length of an external session key
+const MAX_EXTERNAL_KEY_LEN: usize = 256;
+
+/// Keys kept before the least recently used are dropped
+pub const MAX_MAPPINGS: usize = 100_000;
+
+/// Idle time after which a key expires (30 days)
+pub const MAPPING_TTL_SECS: u64 = 30 * 24 * 3600;
+
+/// How stale `last_seen` may get before a lookup refreshes it
+const TOUCH_INTERVAL_SECS: u64 = 3600;
+
+/// A persisted external key -> session_id binding
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SessionMapping {
+    pub external_key: String,
+    pub session_id: u64,
+    pub tenant_id: u64,
+    pub project_id: u16,
+    /// When the key was first seen (seconds since epoch)
+    pub created_at: u64,
+    /// When the key was last resolved (seconds since epoch, hourly precision)
+    #[serde(default)]
+    pub last_seen: u64,
+}
+
+impl SessionMapping {
+    fn expired(&self, now: u64) -> bool {
+        now.saturating_sub(self.last_seen.max(self.created_at)) > MAPPING_TTL_SECS
+    }
+}
+
+/// Thread-safe registry of external session keys
+#[derive(Clone)]
+pub struct SessionRegistry {
+    mappings: Arc<RwLock<HashMap<String, SessionMapping>>>,
+    storage_path: PathBuf,
+    max_mappings: usize,
+    /// Held while writing the registry file, so saves never interleave
+    persist_lock: Arc<Mutex<()>>,
+}
+
+impl SessionRegistry {
+    /// Create a registry, loading existing mappings from `storage_path`
+    pub fn new(storage_path: impl AsRef<Path>) -> Self {
+        Self::with_capacity(storage_path, MAX_MAPPINGS)
+    }
+
+    fn with_capacity(storage_path: impl AsRef<Path>, max_mappings: usize) -> Self {
+        let registry = Self {
+            mappings: Arc::new(RwLock::new(HashMap::new())),
+            storage_path: storage_path.as_ref().to_path_buf(),
+            max_mappings: max_mappings.max(1),
+            persist_lock: Arc::new(Mutex::new(())),
+        };
+
+        if let Err(e) = registry.load_from_disk(now_secs()) {
+            warn!(
+                "Failed to load session registry from disk: {}. Starting with empty registry.",
+                e
+            );
+        }
+
+        registry
+    }
+
+    /// Resolve an external key to its stable session_id
+    ///
+    /// Unknown or expired keys are bound to `candidate_session_id` (the
+    /// session_id the span would otherwise have been stored under) and
+    /// persisted.
+    ///
+    /// # Edge Cases:
+    /// - Empty or oversized key: Returns `candidate_session_id` unmapped
+    /// - Concurrent first use: The first writer wins, later callers get its session_id
+    /// - Registry full: The least recently used keys are dropped first
+    /// - Persistence failure: Logs error but keeps in-memory mapping
+    pub fn resolve(
+        &self,
+        tenant_id: u64,
+        project_id: u16,
+        external_key: &str,
+        candidate_session_id: u64,
+    ) -> u64 {
+        self.resolve_at(
+            tenant_id,
+            project_id,
+            external_key,
+            candidate_session_id,
+            now_secs(),
+        )
+    }
+
+    fn resolve_at(
+        &self,
+        tenant_id: u64,
+        project_id: u16,
+        external_key: &str,
+        candidate_session_id: u64,
+        now: u64,
+    ) -> u64 {
+        if external_key.is_empty() || external_key.len() > MAX_EXTERNAL_KEY_LEN {
+            return candidate_session_id;
+        }
+
+        let scoped = scoped_key(tenant_id, project_id, external_key);
+        if let Some((session_id, last_seen)) = self.lookup(&scoped, now) {
+            if now.saturating_sub(last_seen) < TOUCH_INTERVAL_SECS {
+                return session_id;
+            }
+        }
+
+        let Ok(mut mappings) = self.mappings.write() else {
+            return candidate_session_id;
+        };
+        if let Some(existing) = mappings.get_mut(&scoped) {
+            if !existing.expired(now) {
+                // Saved with the next new key; a restart at worst loses an
+                // hour of idle time
+                existing.last_seen = existing.last_seen.max(now);
+                return existing.session_id;
+            }
+        }
+
+        if mappings.len() >= self.max_mappings {
+            evict(&mut mappings, self.max_mappings, now);
+        }
+        mappings.insert(
+            scoped,
+            SessionMapping {
+                external_key: external_key.to_string(),
+                session_id: candidate_session_id,
+                tenant_id,
+                project_id,
+                created_at: now,
+                last_seen: now,
+            },
+        );
+        drop(mappings); // Release lock before disk I/O
+
+        if let Err(e) = self.save_to_disk() {
+            error!("Failed to persist session registry: {}", e);
+        }
+
+        info!(
+            "Mapped external session key '{}' to session_id={}",
+            external_key, candidate_session_id
+        );
+        candidate_session_id
+    }
+
+    /// Get the mapping for an external key, if one exists
+    pub fn get(
+        &self,
+        tenant_id: u64,
+        project_id: u16,
+        external_key: &str,
+    ) -> Option<SessionMapping> {
+        let now = now_secs();
+        let mappings = self.mappings.read().ok()?;
+        mappings
+            .get(&scoped_key(tenant_id, project_id, external_key))
+            .filter(|m| !m.expired(now))
+            .cloned()
+    }
+
+    /// Get total number of mapped keys
+    pub fn count(&self) -> usize {
+        self.mappings.read().map(|m| m.len()).unwrap_or(0)
+    }
+
+    /// Session and last use of a live mapping
+    fn lookup(&self, scoped: &str, now: u64) -> Option<(u64, u64)> {
+        let mappings = self.mappings.read().ok()?;
+        mappings
+            .get(scoped)
+            .filter(|m| !m.expired(now))
+            .map(|m| (m.session_id, m.last_seen))
+    }
+
+    fn load_from_disk(&self, now: u64) -> Result<(), String> {
+        if !self.storage_path.exists() {
+            return Ok(());
+        }
+
+        let file = File::open(&self.storage_path)
+            .map_err(|e| format!("Failed to open registry file: {}", e))?;
+        let entries: Vec<SessionMapping> = serde_json::from_reader(BufReader::new(file))
+            .map_err(|e| format!("Failed to parse registry JSON: {}", e))?;
+
+        let mut mappings = self
+            .mappings
+            .write()
+            .map_err(|e| format!("Lock poisoned: {}", e))?;
+        *mappings = entries
+            .into_iter()
+            .filter(|m| !m.expired(now))
+            .map(|m| (scoped_key(m.tenant_id, m.project_id, &m.external_key), m))
+            .collect();
+        if mappings.len() > self.max_mappings {
+            evict(&mut mappings, self.max_mappings, now);
+        }
+
+        info!("Loaded {} external session keys", mappings.len());
+        Ok(())
+    }
+
+    /// Save registry to disk (write to temp file then rename)
+    fn save_to_disk(&self) -> Result<(), String> {
+        if let Some(parent) = self.storage_path.parent() {
+            fs::create_dir_all(parent)
+                .map_err(|e| format!("Failed to create registry directory: {}", e))?;
+        }
+
+        // The snapshot is taken under the lock, so the last save to finish
+        // also wrote the newest state
+        let _persisting = self.persist_lock.lock();
+        let temp_path = self
+            .storage_path
+            .with_extension(format!("json.{}.tmp", std::process::id()));
+        {
+            let file = File::create(&temp_path)
+                .map_err(|e| format!("Failed to create temp registry file: {}", e))?;
+
+            let mappings = self
+                .mappings
+                .read()
+                .map_err(|e| format!("Lock poisoned: {}", e))?;
+            let entries: Vec<&SessionMapping> = mappings.values().collect();
+
+            serde_json::to_writer(BufWriter::new(file), &entries)
+                .map_err(|e| format!("Failed to serialize registry: {}", e))?;
+        }
+
+        fs::rename(&temp_path, &self.storage_path)
+            .map_err(|e| format!("Failed to rename temp file: {}", e))?;
+
+        Ok(())
+    }
+}
+
+/// Drop expired keys, then the least recently used until a tenth of
+/// `max_mappings` is free
+fn evict(mappings: &mut HashMap<String, SessionMapping>, max_mappings: usize, now: u64) {
+    mappings.retain(|_, m| !m.expired(now));
+    let target = max_mappings - max_mappings.div_ceil(10);
+    if mappings.len() <= target {
+        return;
+    }
+
+    let mut by_age: Vec<(u64, String)> = mappings
+        .iter()
+        .map(|(key, m)| (m.last_seen, key.clone()))
+        .collect();
+    let excess = by_age.len() - target;
+    by_age.select_nth_unstable(excess - 1);
+    for (_, key) in by_age.drain(..excess) {
+        mappings.remove(&key);
+    }
+    warn!(
+        "Session registry full; dropped {} least recently used external keys",
+        excess
+    );
+}
+
+fn scoped_key(tenant_id: u64, project_id: u16, external_key: &str) -> String {
+    format!("{}:{}:{}", tenant_id, project_id, external_key)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_first_session_wins() {
+        let temp_dir = TempDir::new().unwrap();
+        let registry = SessionRegistry::new(temp_dir.path().join("sessions.json"));
+
+        assert_eq!(registry.resolve(1, 0, "thread-42", 100), 100);
+        // Restarted process minted a new session_id, but the key stitches it back
+        assert_eq!(registry.resolve(1, 0, "thread-42", 200), 100);
+        // Keys are scoped per tenant/project
+        assert_eq!(registry.resolve(2, 0, "thread-42", 300), 300);
+        assert_eq!(registry.count(), 2);
+    }
+
+    #[test]
+    fn test_persistence() {
+        let temp_dir = TempDir::new().unwrap();
+        let path = temp_dir.path().join("sessions.json");
+
+        {
+            let registry = SessionRegistry::new(&path);
+            registry.resolve(1, 3, "thread-42", 100);
+        }
+
+        let registry = SessionRegistry::new(&path);
+        assert_eq!(registry.resolve(1, 3, "thread-42", 999), 100);
+        assert_eq!(registry.get(1, 3, "thread-42").unwrap().session_id, 100);
+    }
+
+    #[test]
+    fn test_idle_keys_expire() {
+        let temp_dir = TempDir::new().unwrap();
+        let registry = SessionRegistry::new(temp_dir.path().join("sessions.json"));
+        let start = 1_000_000;
+
+        assert_eq!(registry.resolve_at(1, 0, "thread-42", 100, start), 100);
+        // Use keeps the key alive past the TTL from its creation
+        let later = start + MAPPING_TTL_SECS - 1;
+        assert_eq!(registry.resolve_at(1, 0, "thread-42", 200, later), 100);
+        let still = start + MAPPING_TTL_SECS + 10;
+        assert_eq!(registry.resolve_at(1, 0, "thread-42", 300, still), 100);
+        // Left idle for longer than the TTL, the key is bound anew
+        let idle = still + MAPPING_TTL_SECS + 1;
+        assert_eq!(registry.resolve_at(1, 0, "thread-42", 400, idle), 400);
+        assert_eq!(registry.count(), 1);
+    }
+
+    #[test]
+    fn test_full_registry_drops_least_recently_used() {
+        let temp_dir = TempDir::new().unwrap();
+        let path = temp_dir.path().join("sessions.json");
+        let registry = SessionRegistry::with_capacity(&path, 10);
+        let hour = TOUCH_INTERVAL_SECS;
+        let at = |hours: u64| now_secs() - 30 * hour + hours * hour;
+
+        for i in 0..10u64 {
+            registry.resolve_at(1, 0, &format!("thread-{}", i), i, at(i));
+        }
+        // Touching thread-0 makes thread-1 the least recently used
+        registry.resolve_at(1, 0, "thread-0", 99, at(20));
+        registry.resolve_at(1, 0, "thread-10", 10, at(21));
+
+        assert_eq!(registry.count(), 10);
+        assert!(registry.get(1, 0, "thread-1").is_none());
+        assert_eq!(registry.resolve_at(1, 0, "thread-0", 99, at(22)), 0);
+        assert!(registry.get(1, 0, "thread-10").is_some());
+
+        // No temp files are left next to the registry
+        let files: Vec<_> = fs::read_dir(temp_dir.path()).unwrap().collect();
+        assert_eq!(files.len(), 1);
+        assert_eq!(SessionRegistry::with_capacity(&path, 10).count(), 10);
+    }
+}
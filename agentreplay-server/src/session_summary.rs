@@ -0,0 +1,385 @@
+// Copyright 2025 AgentReplay (https://github.com/agentreplay)
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program. If not, see <https://www.gnu.org/licenses/>.
+
+//! Session summaries maintained at ingestion
+//!
+//! Each stored span is folded into its session's [`SessionRollup`]
+//! (duration, turns, tokens, cost, errors, models) and recorded eval scores
+//! are added as they arrive, so `GET /api/v1/sessions` can list and sort
+//! sessions without grouping raw spans.
+//!
+//! A session without a summary is rebuilt from all of its spans the first
+//! time one of them is recorded, which covers sessions that began before
+//! summaries existed. A one-time backfill summarizes the remaining old
+//! sessions; until it completes, session lists are derived on read.
+
+use std::collections::{HashMap, HashSet};
+
+use agentreplay_core::clock::now_us;
+use agentreplay_core::{eval::EvalMetric, AgentFlowEdge, Result, SpanType};
+use agentreplay_query::{Agentreplay, TenantScope};
+use agentreplay_storage::SessionRollup;
+use parking_lot::{Mutex, MutexGuard};
+use tracing::{info, warn};
+
+use crate::api::AppState;
+use crate::otel_genai::{GenAIPayload, ModelPricing};
+
+/// Lock stripes serializing read-modify-write of the same session
+const LOCK_STRIPES: usize = 64;
+
+/// What one span adds to its session
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct SpanContribution {
+    pub model: Option<String>,
+    pub tokens: u64,
+    pub cost_usd: f64,
+    pub error: bool,
+}
+
+impl SpanContribution {
+    pub fn new(edge: &AgentFlowEdge, payload: Option<&GenAIPayload>) -> Self {
+        let mut span = Self {
+            tokens: edge.token_count as u64,
+            error: edge.get_span_type() == SpanType::Error,
+            ..Default::default()
+        };
+        let Some(payload) = payload else {
+            return span;
+        };
+        let (input, output, _) = payload.get_token_counts();
+        let total = payload
+            .total_tokens
+            .map(|t| t as u64)
+            .unwrap_or(input as u64 + output as u64);
+        span.tokens = span.tokens.max(total);
+        span.error |= payload.error_type.is_some();
+        span.model = payload
+            .response_model
+            .clone()
+            .or_else(|| payload.request_model.clone());
+        if let Some(ref model) = span.model {
+            let system = payload
+                .system
+                .as_deref()
+                .or(payload.provider_name.as_deref())
+                .unwrap_or("unknown");
+            span.cost_usd = payload.calculate_cost(&ModelPricing::for_model(system, model));
+        }
+        span
+    }
+}
+
+/// Fold one span into a session summary
+pub fn apply_span(summary: &mut SessionRollup, edge: &AgentFlowEdge, span: &SpanContribution) {
+    let ends_at = edge.timestamp_us + edge.duration_us as u64;
+    if summary.span_count == 0 {
+        summary.session_id = edge.session_id;
+        summary.tenant_id = edge.tenant_id;
+        summary.project_id = edge.project_id;
+        summary.agent_id = edge.agent_id;
+        summary.started_at = edge.timestamp_us;
+    } else if edge.timestamp_us < summary.started_at {
+        summary.started_at = edge.timestamp_us;
+        summary.agent_id = edge.agent_id;
+    }
+    summary.last_span_at = summary.last_span_at.max(edge.timestamp_us);
+    summary.ended_at = summary.ended_at.max(ends_at);
+    summary.span_count += 1;
+    summary.total_tokens += span.tokens;
+    summary.cost_usd += span.cost_usd;
+    summary.span_duration_us += edge.duration_us as u64;
+    if span.error {
+        summary.error_count += 1;
+    }
+    if let Some(ref model) = span.model {
+        summary.turn_count += 1;
+        if !summary.models.contains(model) {
+            summary.models.push(model.clone());
+        }
+    }
+}
+
+fn stored_payload(db: &Agentreplay, edge_id: u128) -> Option<GenAIPayload> {
+    db.get_payload(edge_id)
+        .ok()
+        .flatten()
+        .and_then(|bytes| serde_json::from_slice(&bytes).ok())
+}
+
+/// Maintains [`SessionRollup`] records as spans and eval scores arrive
+pub struct SessionSummarizer {
+    stripes: Vec<Mutex<()>>,
+}
+
+impl Default for SessionSummarizer {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl SessionSummarizer {
+    pub fn new() -> Self {
+        Self {
+            stripes: (0..LOCK_STRIPES).map(|_| Mutex::new(())).collect(),
+        }
+    }
+
+    fn lock(&self, session_id: u64) -> MutexGuard<'_, ()> {
+        self.stripes[(session_id % LOCK_STRIPES as u64) as usize].lock()
+    }
+
+    /// Summarize a session from all of its stored spans
+    fn rebuild(db: &Agentreplay, tenant_id: u64, session_id: u64) -> Result<Option<SessionRollup>> {
+        let mut edges = db.get_session_edges_full(session_id)?;
+        edges.retain(|edge| edge.tenant_id == tenant_id);
+        if edges.is_empty() {
+            return Ok(None);
+        }
+        edges.sort_by_key(|edge| edge.timestamp_us);
+
+        let mut summary = SessionRollup::default();
+        for edge in &edges {
+            let payload = stored_payload(db, edge.edge_id);
+            apply_span(
+                &mut summary,
+                edge,
+                &SpanContribution::new(edge, payload.as_ref()),
+            );
+        }
+        for edge in &edges {
+            for metric in db.get_eval_metrics(edge.edge_id)? {
+                summary
+                    .eval_scores
+                    .insert(metric.get_metric_name().to_string(), metric.metric_value);
+            }
+        }
+        Ok(Some(summary))
+    }
+
+    /// Add a stored span to its session's summary
+    ///
+    /// Must run after the edge is written: a session without a summary is
+    /// rebuilt from its stored spans, this one included.
+    pub fn record_span(
+        &self,
+        db: &Agentreplay,
+        edge: &AgentFlowEdge,
+        attributes: &HashMap<String, String>,
+    ) -> Result<()> {
+        if edge.session_id == 0 {
+            return Ok(());
+        }
+        let _guard = self.lock(edge.session_id);
+        let summary = match db.get_session_summary(edge.tenant_id, edge.session_id)? {
+            Some(mut summary) => {
+                let payload =
+                    (!attributes.is_empty()).then(|| GenAIPayload::from_attributes(attributes));
+                apply_span(
+                    &mut summary,
+                    edge,
+                    &SpanContribution::new(edge, payload.as_ref()),
+                );
+                summary
+            }
+            None => match Self::rebuild(db, edge.tenant_id, edge.session_id)? {
+                Some(summary) => summary,
+                None => return Ok(()),
+            },
+        };
+        db.put_session_summary(&SessionRollup {
+            updated_at: now_us(),
+            ..summary
+        })
+    }
+
+    /// Record eval scores of a span on its session's summary
+    pub fn record_eval_scores(
+        &self,
+        db: &Agentreplay,
+        edge: &AgentFlowEdge,
+        metrics: &[EvalMetric],
+    ) -> Result<()> {
+        if edge.session_id == 0 || metrics.is_empty() {
+            return Ok(());
+        }
+        let _guard = self.lock(edge.session_id);
+        let mut summary = match db.get_session_summary(edge.tenant_id, edge.session_id)? {
+            Some(summary) => summary,
+            None => match Self::rebuild(db, edge.tenant_id, edge.session_id)? {
+                Some(summary) => summary,
+                None => return Ok(()),
+            },
+        };
+        // Metrics may be stored outside the span's shard, so a rebuild
+        // does not necessarily see them
+        for metric in metrics {
+            summary
+                .eval_scores
+                .insert(metric.get_metric_name().to_string(), metric.metric_value);
+        }
+        db.put_session_summary(&SessionRollup {
+            updated_at: now_us(),
+            ..summary
+        })
+    }
+
+    /// Summarize every session of `db` that has no summary yet, then mark
+    /// summaries as complete. Returns how many sessions were summarized.
+    pub fn backfill(&self, db: &Agentreplay) -> Result<usize> {
+        if db.session_summaries_ready()? {
+            return Ok(0);
+        }
+        let sessions: HashSet<(u64, u64)> = db
+            .scoped(TenantScope::AllTenants)
+            .range(0, u64::MAX)?
+            .into_iter()
+            .filter(|edge| edge.session_id != 0)
+            .map(|edge| (edge.tenant_id, edge.session_id))
+            .collect();
+
+        let mut summarized = 0;
+        for (tenant_id, session_id) in sessions {
+            let _guard = self.lock(session_id);
+            if db.get_session_summary(tenant_id, session_id)?.is_some() {
+                continue;
+            }
+            if let Some(summary) = Self::rebuild(db, tenant_id, session_id)? {
+                db.put_session_summary(&SessionRollup {
+                    updated_at: now_us(),
+                    ..summary
+                })?;
+                summarized += 1;
+            }
+        }
+        db.mark_session_summaries_ready()?;
+        Ok(summarized)
+    }
+
+    /// Backfill all shards in the background
+    pub fn spawn_backfill(state: AppState) {
+        tokio::task::spawn_blocking(move || {
+            let shards = match crate::api::metrics::project_shards(&state, None) {
+                Ok(shards) => shards,
+                Err(e) => {
+                    warn!("Session summary backfill: {:?}", e);
+                    return;
+                }
+            };
+            for db in shards {
+                match state.session_summarizer.backfill(&db) {
+                    Ok(0) => {}
+                    Ok(count) => info!("Backfilled {} session summaries", count),
+                    Err(e) => warn!("Session summary backfill failed: {}", e),
+                }
+            }
+        });
+    }
+}
+
+/// Record eval scores on the summary of the session holding `edge_id` (best effort)
+pub fn record_eval_metrics(state: &AppState, edge_id: u128, metrics: &[EvalMetric]) {
+    let Ok(shards) = crate::api::metrics::project_shards(state, None) else {
+        return;
+    };
+    for db in shards {
+        let Ok(Some(edge)) = db.get(edge_id) else {
+            continue;
+        };
+        if let Err(e) = state
+            .session_summarizer
+            .record_eval_scores(&db, &edge, metrics)
+        {
+            warn!(
+                "Failed to update session summary with eval scores of {:#x}: {}",
+                edge_id, e
+            );
+        }
+        return;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn edge(timestamp_us: u64, duration_us: u32, span_type: SpanType) -> AgentFlowEdge {
+        AgentFlowEdge {
+            session_id: 9,
+            tenant_id: 1,
+            timestamp_us,
+            duration_us,
+            token_count: 10,
+            span_type: span_type.to_u64() as u32,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_span_contribution() {
+        let payload = GenAIPayload {
+            system: Some("openai".to_string()),
+            request_model: Some("gpt-4o".to_string()),
+            input_tokens: Some(1_000),
+            output_tokens: Some(500),
+            ..Default::default()
+        };
+        let e = edge(0, 0, SpanType::Root);
+        let span = SpanContribution::new(&e, Some(&payload));
+        assert_eq!(span.model.as_deref(), Some("gpt-4o"));
+        assert_eq!(span.tokens, 1_500);
+        assert!(span.cost_usd > 0.0);
+        assert!(!span.error);
+
+        let bare = SpanContribution::new(&edge(0, 0, SpanType::Error), None);
+        assert_eq!(bare.tokens, 10);
+        assert!(bare.error && bare.model.is_none());
+    }
+
+    #[test]
+    fn test_apply_span() {
+        let mut summary = SessionRollup::default();
+        let llm = SpanContribution {
+            model: Some("gpt-4o".to_string()),
+            tokens: 100,
+            cost_usd: 0.5,
+            error: false,
+        };
+        apply_span(&mut summary, &edge(2_000, 1_000, SpanType::Root), &llm);
+        apply_span(
+            &mut summary,
+            &edge(5_000, 500, SpanType::Error),
+            &SpanContribution {
+                tokens: 10,
+                error: true,
+                ..Default::default()
+            },
+        );
+        // Out-of-order span that started first
+        apply_span(&mut summary, &edge(1_000, 200, SpanType::Root), &llm);
+
+        assert_eq!(summary.session_id, 9);
+        assert_eq!(summary.span_count, 3);
+        assert_eq!(summary.turn_count, 2);
+        assert_eq!(summary.started_at, 1_000);
+        assert_eq!(summary.last_span_at, 5_000);
+        assert_eq!(summary.duration_us(), 4_500);
+        assert_eq!(summary.span_duration_us, 1_700);
+        assert_eq!(summary.total_tokens, 210);
+        assert!((summary.cost_usd - 1.0).abs() < 1e-9);
+        assert_eq!(summary.error_count, 1);
+        assert_eq!(summary.models, vec!["gpt-4o".to_string()]);
+    }
+}
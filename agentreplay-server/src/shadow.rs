@@ -0,0 +1,857 @@
+// Copyright 2025 AgentReplay (https://github.com/agentreplay)
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program. If not, see <https://www.gnu.org/licenses/>.
+
+//! Shadow traffic on the chat completion proxy
+//!
+//! Rules from the `[shadow]` config section duplicate chat proxy requests
+//! for a model to a candidate model. The caller is always answered by the
+//! requested (primary) model; the candidate is called in the background
+//! once the primary answer is out, so a migration can be tried on real
+//! traffic without anyone seeing the candidate's answers.
+//!
+//! Both calls are traced. The candidate's span links to the primary's span
+//! (`follows_from`), and both carry `shadow.rule` and `shadow.role`
+//! attributes plus the other span's ID. Each pair is compared on latency
+//! and cost, on how closely the candidate's answer matches the primary's
+//! (ROUGE-L F1), and, with a `judge_model`, on which answer an LLM judge
+//! prefers. `GET /api/v1/shadow` reports the candidate's win rates and a
+//! statistical comparison of the two models per rule.
+//!
+//! Pairs are kept in memory, so reports cover traffic since the server
+//! started. Streamed completions are not shadowed, since the comparison
+//! needs the whole answer.
+//!
+//! ```toml
+//! [[shadow.rules]]
+//! name = "gpt-4o-to-mini"
+//! model = "gpt-4o"
+//! candidate_model = "gpt-4o-mini"
+//! sample_rate = 0.1
+//! judge_model = "gpt-4o"
+//! ```
+
+use agentreplay_core::clock::now_us;
+use std::collections::{BTreeSet, HashMap, VecDeque};
+use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
+use std::sync::Arc;
+
+use agentreplay_evals::evaluators::ReferenceEvaluator;
+use agentreplay_evals::{Comparator, ComparisonResult};
+use axum::{
+    extract::{Path, Query, State},
+    Extension, Json,
+};
+use parking_lot::Mutex;
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use tracing::warn;
+
+use crate::api::evaluate::{call_llm_for_evaluation, LlmJudge};
+use crate::api::query::ApiError;
+use crate::api::AppState;
+use crate::auth::AuthContext;
+use crate::config::ShadowConfig;
+use crate::ingestion::ATTR_SPAN_LINKS;
+use crate::llm::{ChatMessage, ChatResponse};
+use crate::otel_genai::ModelPricing;
+
+/// Pairs kept per rule, oldest dropped first
+const MAX_PAIRS_PER_RULE: usize = 1_000;
+const DEFAULT_RECENT_PAIRS: usize = 50;
+/// Characters of each answer shown to the judge
+const MAX_JUDGED_CHARS: usize = 8_000;
+
+/// Requests to duplicate to a candidate model
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ShadowRule {
+    pub name: String,
+    /// Model requested from the proxy
+    pub model: String,
+    /// Provider of the requests to duplicate; any when unset
+    #[serde(default)]
+    pub provider: Option<String>,
+    pub candidate_model: String,
+    /// Provider to call the candidate through, else the request's provider
+    #[serde(default)]
+    pub candidate_provider: Option<String>,
+    /// Vault key to call the candidate with instead of a configured provider
+    #[serde(default)]
+    pub candidate_key_alias: Option<String>,
+    /// Fraction of matching requests duplicated
+    #[serde(default = "default_sample_rate")]
+    pub sample_rate: f64,
+    /// Model of the LLM judge picking the better answer of each pair
+    #[serde(default)]
+    pub judge_model: Option<String>,
+    /// Vault key of the judge, else the server's `OPENAI_API_KEY`
+    #[serde(default)]
+    pub judge_key_alias: Option<String>,
+}
+
+fn default_sample_rate() -> f64 {
+    1.0
+}
+
+impl ShadowRule {
+    fn matches(&self, provider: &str, model: Option<&str>) -> bool {
+        model == Some(self.model.as_str()) && self.provider.as_deref().is_none_or(|p| p == provider)
+    }
+}
+
+/// Answer the judge preferred
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ShadowVerdict {
+    Primary,
+    Candidate,
+    Tie,
+}
+
+/// Latency, cost and size of one side of a pair
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ShadowCall {
+    pub edge_id: String,
+    pub model: String,
+    pub latency_ms: u64,
+    #[serde(default)]
+    pub output_tokens: Option<u32>,
+    #[serde(default)]
+    pub cost_usd: Option<f64>,
+}
+
+impl ShadowCall {
+    fn new(provider: &str, response: &ChatResponse, edge_id: u128) -> Self {
+        let model = response
+            .response_model
+            .clone()
+            .unwrap_or_else(|| response.model.clone());
+        let cost_usd =
+            (response.input_tokens.is_some() || response.output_tokens.is_some()).then(|| {
+                ModelPricing::for_model(provider, &model).token_cost(
+                    response.input_tokens.unwrap_or(0),
+                    response.output_tokens.unwrap_or(0),
+                )
+            });
+        Self {
+            edge_id: format!("{:#x}", edge_id),
+            model,
+            latency_ms: response.duration_ms as u64,
+            output_tokens: response.output_tokens,
+            cost_usd,
+        }
+    }
+}
+
+/// A request answered by the primary model and replayed on the candidate
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ShadowPair {
+    pub tenant_id: u64,
+    pub timestamp_us: u64,
+    pub primary: ShadowCall,
+    #[serde(default)]
+    pub candidate: Option<ShadowCall>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub candidate_error: Option<String>,
+    /// ROUGE-L F1 of the candidate's answer against the primary's
+    #[serde(default)]
+    pub agreement: Option<f64>,
+    #[serde(default)]
+    pub verdict: Option<ShadowVerdict>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub judge_error: Option<String>,
+}
+
+/// The primary call of a shadowed request
+pub struct PrimaryCall {
+    pub provider: String,
+    pub edge_id: u128,
+    /// Response as answered to the caller, after output guardrails
+    pub response: ChatResponse,
+}
+
+/// Share of pairs the candidate won, counting ties as half
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct WinRates {
+    /// From the judge's verdicts
+    pub quality: Option<f64>,
+    /// Faster answers
+    pub latency: Option<f64>,
+    /// Cheaper answers
+    pub cost: Option<f64>,
+}
+
+/// Candidate against primary over the pairs of a rule
+#[derive(Debug, Clone, Serialize)]
+pub struct ShadowReport {
+    pub rule: String,
+    pub model: String,
+    pub candidate_model: String,
+    pub sample_rate: f64,
+    pub pairs: usize,
+    pub candidate_errors: usize,
+    pub judged: usize,
+    pub win_rates: WinRates,
+    /// Mean ROUGE-L F1 of the candidate's answers against the primary's
+    pub agreement: Option<f64>,
+    /// Primary (baseline) against candidate (treatment) on `latency_ms`,
+    /// `cost_usd` and judged `quality`
+    pub comparison: Option<ComparisonResult>,
+}
+
+/// Share of outcomes won, where `Less` is a win and `Equal` half of one
+fn win_rate(outcomes: impl Iterator<Item = std::cmp::Ordering>) -> Option<f64> {
+    let (mut n, mut won) = (0usize, 0.0);
+    for outcome in outcomes {
+        n += 1;
+        won += match outcome {
+            std::cmp::Ordering::Less => 1.0,
+            std::cmp::Ordering::Equal => 0.5,
+            std::cmp::Ordering::Greater => 0.0,
+        };
+    }
+    (n > 0).then(|| won / n as f64)
+}
+
+/// Win rates, agreement and comparison of a rule's pairs
+pub fn build_report(rule: &ShadowRule, pairs: &[&ShadowPair]) -> ShadowReport {
+    let completed: Vec<(&ShadowCall, &ShadowCall)> = pairs
+        .iter()
+        .filter_map(|pair| Some((&pair.primary, pair.candidate.as_ref()?)))
+        .collect();
+    let verdicts: Vec<ShadowVerdict> = pairs.iter().filter_map(|pair| pair.verdict).collect();
+    let agreements: Vec<f64> = pairs.iter().filter_map(|pair| pair.agreement).collect();
+
+    // Candidate-first ordering: Less means the candidate wins
+    let win_rates = WinRates {
+        quality: win_rate(verdicts.iter().map(|verdict| match verdict {
+            ShadowVerdict::Candidate => std::cmp::Ordering::Less,
+            ShadowVerdict::Tie => std::cmp::Ordering::Equal,
+            ShadowVerdict::Primary => std::cmp::Ordering::Greater,
+        })),
+        latency: win_rate(
+            completed
+                .iter()
+                .map(|(primary, candidate)| candidate.latency_ms.cmp(&primary.latency_ms)),
+        ),
+        cost: win_rate(completed.iter().filter_map(|(primary, candidate)| {
+            candidate.cost_usd?.partial_cmp(&primary.cost_usd?)
+        })),
+    };
+
+    let mut baseline: HashMap<String, Vec<f64>> = HashMap::new();
+    let mut treatment: HashMap<String, Vec<f64>> = HashMap::new();
+    for (primary, candidate) in &completed {
+        let mut push = |metric: &str, primary: f64, candidate: f64| {
+            baseline
+                .entry(metric.to_string())
+                .or_default()
+                .push(primary);
+            treatment
+                .entry(metric.to_string())
+                .or_default()
+                .push(candidate);
+        };
+        push(
+            "latency_ms",
+            primary.latency_ms as f64,
+            candidate.latency_ms as f64,
+        );
+        if let (Some(primary), Some(candidate)) = (primary.cost_usd, candidate.cost_usd) {
+            push("cost_usd", primary, candidate);
+        }
+    }
+    for verdict in &verdicts {
+        let (primary, candidate) = match verdict {
+            ShadowVerdict::Primary => (1.0, 0.0),
+            ShadowVerdict::Candidate => (0.0, 1.0),
+            ShadowVerdict::Tie => (0.5, 0.5),
+        };
+        baseline
+            .entry("quality".to_string())
+            .or_default()
+            .push(primary);
+        treatment
+            .entry("quality".to_string())
+            .or_default()
+            .push(candidate);
+    }
+    let direction = HashMap::from([
+        ("latency_ms".to_string(), false),
+        ("cost_usd".to_string(), false),
+        ("quality".to_string(), true),
+    ]);
+    // Welch's t-test needs two values per side
+    let comparison = (completed.len() >= 2).then(|| {
+        Comparator::compare_runs(
+            "primary",
+            &rule.model,
+            &baseline,
+            "candidate",
+            &rule.candidate_model,
+            &treatment,
+            &direction,
+        )
+    });
+
+    ShadowReport {
+        rule: rule.name.clone(),
+        model: rule.model.clone(),
+        candidate_model: rule.candidate_model.clone(),
+        sample_rate: rule.sample_rate,
+        pairs: pairs.len(),
+        candidate_errors: pairs.len() - completed.len(),
+        judged: verdicts.len(),
+        win_rates,
+        agreement: (!agreements.is_empty())
+            .then(|| agreements.iter().sum::<f64>() / agreements.len() as f64),
+        comparison,
+    }
+}
+
+/// Judge prompt with the two answers in the given order
+fn judge_prompt(messages: &[ChatMessage], first: &str, second: &str) -> String {
+    let truncate = |text: &str| text.chars().take(MAX_JUDGED_CHARS).collect::<String>();
+    let conversation = messages
+        .iter()
+        .map(|m| format!("{}: {}", m.role, truncate(&m.content)))
+        .collect::<Vec<_>>()
+        .join("\n");
+    format!(
+        "Two assistants answered the same conversation. Decide which answer is better: \
+         more correct, more helpful and better following the instructions. Ignore length \
+         unless it hurts the answer.\n\n\
+         Conversation:\n{}\n\nAnswer A:\n{}\n\nAnswer B:\n{}\n\n\
+         Respond with JSON: {{\"winner\": \"A\" | \"B\" | \"tie\", \"reason\": \"...\"}}",
+        conversation,
+        truncate(first),
+        truncate(second)
+    )
+}
+
+/// Verdict of a judge response; `candidate_first` says whether the
+/// candidate's answer was shown as A
+fn parse_verdict(response: &str, candidate_first: bool) -> Result<ShadowVerdict, String> {
+    let json: serde_json::Value =
+        serde_json::from_str(response).map_err(|e| format!("Failed to parse JSON: {}", e))?;
+    let winner = json["winner"]
+        .as_str()
+        .ok_or_else(|| "Judge response has no winner".to_string())?;
+    let (a, b) = if candidate_first {
+        (ShadowVerdict::Candidate, ShadowVerdict::Primary)
+    } else {
+        (ShadowVerdict::Primary, ShadowVerdict::Candidate)
+    };
+    match winner.trim().to_ascii_lowercase().as_str() {
+        "a" => Ok(a),
+        "b" => Ok(b),
+        "tie" => Ok(ShadowVerdict::Tie),
+        other => Err(format!("Unknown winner '{}'", other)),
+    }
+}
+
+/// Shadow rules and the pairs they produced
+pub struct ShadowTraffic {
+    rules: Vec<ShadowRule>,
+    max_in_flight: usize,
+    in_flight: AtomicUsize,
+    /// Sampled requests not shadowed because too many were in flight
+    dropped: AtomicU64,
+    pairs: Mutex<HashMap<String, VecDeque<ShadowPair>>>,
+}
+
+impl Default for ShadowTraffic {
+    fn default() -> Self {
+        Self {
+            rules: Vec::new(),
+            max_in_flight: 0,
+            in_flight: AtomicUsize::new(0),
+            dropped: AtomicU64::new(0),
+            pairs: Mutex::new(HashMap::new()),
+        }
+    }
+}
+
+impl ShadowTraffic {
+    pub fn new(config: &ShadowConfig) -> Result<Self, String> {
+        if !config.enabled {
+            return Ok(Self::default());
+        }
+        let mut names = BTreeSet::new();
+        for rule in &config.rules {
+            if !names.insert(rule.name.as_str()) {
+                return Err(format!("Shadow rule '{}' is listed twice", rule.name));
+            }
+            if !(0.0..=1.0).contains(&rule.sample_rate) {
+                return Err(format!(
+                    "Shadow rule '{}': sample_rate must be between 0 and 1",
+                    rule.name
+                ));
+            }
+            if rule.candidate_provider.is_some() && rule.candidate_key_alias.is_some() {
+                return Err(format!(
+                    "Shadow rule '{}': set candidate_provider or candidate_key_alias, not both",
+                    rule.name
+                ));
+            }
+        }
+        Ok(Self {
+            rules: config.rules.clone(),
+            max_in_flight: config.max_in_flight,
+            ..Default::default()
+        })
+    }
+
+    pub fn rules(&self) -> &[ShadowRule] {
+        &self.rules
+    }
+
+    /// First rule matching a request, if it is sampled
+    pub fn sample(&self, provider: &str, model: Option<&str>) -> Option<ShadowRule> {
+        let rule = self.rules.iter().find(|r| r.matches(provider, model))?;
+        (rand::random::<f64>() < rule.sample_rate).then(|| rule.clone())
+    }
+
+    /// Replay a request on the rule's candidate in the background
+    pub fn spawn(
+        self: &Arc<Self>,
+        state: AppState,
+        tenant_id: u64,
+        session_id: u64,
+        rule: ShadowRule,
+        messages: Vec<ChatMessage>,
+        primary: PrimaryCall,
+    ) {
+        if self.in_flight.fetch_add(1, Ordering::SeqCst) >= self.max_in_flight {
+            self.in_flight.fetch_sub(1, Ordering::SeqCst);
+            self.dropped.fetch_add(1, Ordering::Relaxed);
+            return;
+        }
+        let this = Arc::clone(self);
+        tokio::spawn(async move {
+            let pair = this
+                .replay(&state, tenant_id, session_id, &rule, &messages, primary)
+                .await;
+            this.in_flight.fetch_sub(1, Ordering::SeqCst);
+            this.record(&rule.name, pair);
+        });
+    }
+
+    async fn replay(
+        &self,
+        state: &AppState,
+        tenant_id: u64,
+        session_id: u64,
+        rule: &ShadowRule,
+        messages: &[ChatMessage],
+        primary: PrimaryCall,
+    ) -> ShadowPair {
+        let primary_call = ShadowCall::new(&primary.provider, &primary.response, primary.edge_id);
+        let mut pair = ShadowPair {
+            tenant_id,
+            timestamp_us: now_us(),
+            primary: primary_call,
+            candidate: None,
+            candidate_error: None,
+            agreement: None,
+            verdict: None,
+            judge_error: None,
+        };
+        let (candidate_provider, candidate, edge_id) = match self
+            .call_candidate(state, tenant_id, session_id, rule, messages, &primary)
+            .await
+        {
+            Ok(result) => result,
+            Err(e) => {
+                pair.candidate_error = Some(e);
+                return pair;
+            }
+        };
+
+        let candidate_call = ShadowCall::new(&candidate_provider, &candidate, edge_id);
+        if let Some(llm_manager) = &state.llm_manager {
+            let annotation = HashMap::from([
+                ("shadow.rule".to_string(), rule.name.clone()),
+                ("shadow.role".to_string(), "primary".to_string()),
+                (
+                    "shadow.candidate_edge_id".to_string(),
+                    candidate_call.edge_id.clone(),
+                ),
+            ]);
+            if let Err(e) = llm_manager.annotate_span(primary.edge_id, annotation) {
+                warn!("Failed to link shadowed span: {}", e);
+            }
+        }
+        pair.candidate = Some(candidate_call);
+        pair.agreement = Some(
+            ReferenceEvaluator::new()
+                .rouge_l(&primary.response.content, &candidate.content)
+                .f1,
+        );
+
+        if let Some(model) = &rule.judge_model {
+            match self
+                .judge(
+                    state,
+                    tenant_id,
+                    rule,
+                    model,
+                    messages,
+                    &primary.response.content,
+                    &candidate.content,
+                )
+                .await
+            {
+                Ok(verdict) => pair.verdict = Some(verdict),
+                Err(e) => pair.judge_error = Some(e),
+            }
+        }
+        pair
+    }
+
+    /// Call the candidate, returning its provider, response and edge ID
+    async fn call_candidate(
+        &self,
+        state: &AppState,
+        tenant_id: u64,
+        session_id: u64,
+        rule: &ShadowRule,
+        messages: &[ChatMessage],
+        primary: &PrimaryCall,
+    ) -> Result<(String, ChatResponse, u128), String> {
+        let llm_manager = state
+            .llm_manager
+            .as_ref()
+            .ok_or_else(|| "LLM features are not enabled".to_string())?;
+        let link = json!([{
+            "span_id": format!("{:#x}", primary.edge_id),
+            "kind": "follows_from",
+        }]);
+        let attributes = HashMap::from([
+            ("shadow.rule".to_string(), rule.name.clone()),
+            ("shadow.role".to_string(), "candidate".to_string()),
+            (
+                "shadow.primary_edge_id".to_string(),
+                format!("{:#x}", primary.edge_id),
+            ),
+            (ATTR_SPAN_LINKS.to_string(), link.to_string()),
+        ]);
+        let model = Some(rule.candidate_model.clone());
+
+        match &rule.candidate_key_alias {
+            Some(alias) => {
+                let key = state
+                    .vault
+                    .resolve(&state.db, tenant_id, alias)
+                    .map_err(|e| e.to_string())?;
+                let (response, edge_id) = llm_manager
+                    .chat_with_key_attributes(
+                        &key,
+                        model,
+                        messages.to_vec(),
+                        tenant_id,
+                        session_id,
+                        attributes,
+                    )
+                    .await
+                    .map_err(|e| format!("Candidate request failed: {}", e))?;
+                state.vault.record_usage(
+                    &state.db,
+                    tenant_id,
+                    &key,
+                    "shadow",
+                    response
+                        .response_model
+                        .as_deref()
+                        .unwrap_or(&response.model),
+                    response.input_tokens.unwrap_or(0) as u64,
+                    response.output_tokens.unwrap_or(0) as u64,
+                );
+                Ok((key.provider, response, edge_id))
+            }
+            None => {
+                let provider = rule
+                    .candidate_provider
+                    .clone()
+                    .unwrap_or_else(|| primary.provider.clone());
+                let (response, edge_id) = llm_manager
+                    .chat_with_attributes(
+                        &provider,
+                        model,
+                        messages.to_vec(),
+                        tenant_id,
+                        session_id,
+                        attributes,
+                    )
+                    .await
+                    .map_err(|e| format!("Candidate request failed: {}", e))?;
+                Ok((provider, response, edge_id))
+            }
+        }
+    }
+
+    /// Ask the judge which answer is better, in random order
+    #[allow(clippy::too_many_arguments)]
+    async fn judge(
+        &self,
+        state: &AppState,
+        tenant_id: u64,
+        rule: &ShadowRule,
+        model: &str,
+        messages: &[ChatMessage],
+        primary: &str,
+        candidate: &str,
+    ) -> Result<ShadowVerdict, String> {
+        let judge = LlmJudge::resolve(
+            state,
+            tenant_id,
+            rule.judge_key_alias.as_deref(),
+            model.to_string(),
+        )
+        .map_err(|(_, e)| e)?;
+        // Judges favour one position, so the candidate is not always first
+        let candidate_first = rand::random::<bool>();
+        let prompt = if candidate_first {
+            judge_prompt(messages, candidate, primary)
+        } else {
+            judge_prompt(messages, primary, candidate)
+        };
+        let response = call_llm_for_evaluation(&prompt, &judge).await;
+        judge.finish(state, tenant_id);
+        parse_verdict(&response?, candidate_first)
+    }
+
+    fn record(&self, rule: &str, pair: ShadowPair) {
+        let mut pairs = self.pairs.lock();
+        let rule_pairs = pairs.entry(rule.to_string()).or_default();
+        rule_pairs.push_front(pair);
+        rule_pairs.truncate(MAX_PAIRS_PER_RULE);
+    }
+
+    /// Sampled requests skipped because too many replays were in flight
+    pub fn dropped(&self) -> u64 {
+        self.dropped.load(Ordering::Relaxed)
+    }
+
+    /// Report of every rule over a tenant's pairs
+    pub fn reports(&self, tenant_id: u64) -> Vec<ShadowReport> {
+        let pairs = self.pairs.lock();
+        self.rules
+            .iter()
+            .map(|rule| {
+                let rule_pairs: Vec<&ShadowPair> = pairs
+                    .get(&rule.name)
+                    .into_iter()
+                    .flatten()
+                    .filter(|pair| pair.tenant_id == tenant_id)
+                    .collect();
+                build_report(rule, &rule_pairs)
+            })
+            .collect()
+    }
+
+    /// Report of a rule with its latest pairs, newest first
+    pub fn detail(&self, tenant_id: u64, rule: &str, limit: usize) -> Option<ShadowDetail> {
+        let rule = self.rules.iter().find(|r| r.name == rule)?;
+        let pairs = self.pairs.lock();
+        let rule_pairs: Vec<&ShadowPair> = pairs
+            .get(&rule.name)
+            .into_iter()
+            .flatten()
+            .filter(|pair| pair.tenant_id == tenant_id)
+            .collect();
+        Some(ShadowDetail {
+            report: build_report(rule, &rule_pairs),
+            recent_pairs: rule_pairs.into_iter().take(limit).cloned().collect(),
+        })
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub struct ShadowReports {
+    pub rules: Vec<ShadowReport>,
+    /// Sampled requests skipped because too many replays were in flight
+    pub dropped: u64,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ShadowDetail {
+    #[serde(flatten)]
+    pub report: ShadowReport,
+    pub recent_pairs: Vec<ShadowPair>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ShadowDetailParams {
+    #[serde(default)]
+    pub limit: Option<usize>,
+}
+
+/// GET /api/v1/shadow
+pub async fn list_shadow_reports(
+    State(state): State<AppState>,
+    Extension(auth): Extension<AuthContext>,
+) -> Json<ShadowReports> {
+    Json(ShadowReports {
+        rules: state.shadow.reports(auth.tenant_id),
+        dropped: state.shadow.dropped(),
+    })
+}
+
+/// GET /api/v1/shadow/:rule?limit=50
+pub async fn get_shadow_report(
+    State(state): State<AppState>,
+    Extension(auth): Extension<AuthContext>,
+    Path(rule): Path<String>,
+    Query(params): Query<ShadowDetailParams>,
+) -> Result<Json<ShadowDetail>, ApiError> {
+    let limit = params.limit.unwrap_or(DEFAULT_RECENT_PAIRS);
+    state
+        .shadow
+        .detail(auth.tenant_id, &rule, limit)
+        .map(Json)
+        .ok_or_else(|| ApiError::NotFound(format!("Shadow rule '{}' not found", rule)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rule(sample_rate: f64) -> ShadowRule {
+        serde_json::from_value(json!({
+            "name": "mini",
+            "model": "gpt-4o",
+            "provider": "openai",
+            "candidate_model": "gpt-4o-mini",
+            "sample_rate": sample_rate
+        }))
+        .unwrap()
+    }
+
+    fn call(latency_ms: u64, cost_usd: f64) -> ShadowCall {
+        ShadowCall {
+            edge_id: "0x1".to_string(),
+            model: "m".to_string(),
+            latency_ms,
+            output_tokens: Some(10),
+            cost_usd: Some(cost_usd),
+        }
+    }
+
+    fn pair(primary: ShadowCall, candidate: Option<ShadowCall>) -> ShadowPair {
+        ShadowPair {
+            tenant_id: 1,
+            timestamp_us: 0,
+            primary,
+            candidate,
+            candidate_error: None,
+            agreement: None,
+            verdict: None,
+            judge_error: None,
+        }
+    }
+
+    fn traffic(rules: Vec<ShadowRule>) -> Result<ShadowTraffic, String> {
+        ShadowTraffic::new(&ShadowConfig {
+            rules,
+            ..Default::default()
+        })
+    }
+
+    #[test]
+    fn test_rule_validation() {
+        assert!(traffic(vec![rule(0.5)]).is_ok());
+        assert!(traffic(vec![rule(0.5), rule(1.0)]).is_err());
+        assert!(traffic(vec![rule(1.5)]).is_err());
+    }
+
+    #[test]
+    fn test_sampling() {
+        let always = traffic(vec![rule(1.0)]).unwrap();
+        assert!(always.sample("openai", Some("gpt-4o")).is_some());
+        assert!(always.sample("anthropic", Some("gpt-4o")).is_none());
+        assert!(always.sample("openai", Some("gpt-4o-mini")).is_none());
+        assert!(always.sample("openai", None).is_none());
+
+        let never = traffic(vec![rule(0.0)]).unwrap();
+        assert!(never.sample("openai", Some("gpt-4o")).is_none());
+    }
+
+    #[test]
+    fn test_build_report() {
+        let mut judged = pair(call(1000, 0.01), Some(call(400, 0.001)));
+        judged.verdict = Some(ShadowVerdict::Candidate);
+        judged.agreement = Some(0.8);
+        let mut tie = pair(call(900, 0.01), Some(call(900, 0.002)));
+        tie.verdict = Some(ShadowVerdict::Tie);
+        tie.agreement = Some(0.6);
+        let slower = pair(call(500, 0.01), Some(call(700, 0.002)));
+        let mut failed = pair(call(500, 0.01), None);
+        failed.candidate_error = Some("timeout".to_string());
+        let pairs = [judged, tie, slower, failed];
+
+        let report = build_report(&rule(1.0), &pairs.iter().collect::<Vec<_>>());
+        assert_eq!(report.pairs, 4);
+        assert_eq!(report.candidate_errors, 1);
+        assert_eq!(report.judged, 2);
+        assert_eq!(report.win_rates.quality, Some(0.75));
+        assert_eq!(report.win_rates.latency, Some(0.5));
+        assert_eq!(report.win_rates.cost, Some(1.0));
+        assert!((report.agreement.unwrap() - 0.7).abs() < 1e-9);
+
+        let comparison = report.comparison.unwrap();
+        let cost = comparison
+            .metrics
+            .iter()
+            .find(|m| m.metric_name == "cost_usd")
+            .unwrap();
+        assert!(cost.difference < 0.0);
+    }
+
+    #[test]
+    fn test_parse_verdict() {
+        let response = r#"{"winner": "A", "reason": "more precise"}"#;
+        assert_eq!(
+            parse_verdict(response, true).unwrap(),
+            ShadowVerdict::Candidate
+        );
+        assert_eq!(
+            parse_verdict(response, false).unwrap(),
+            ShadowVerdict::Primary
+        );
+        assert_eq!(
+            parse_verdict(r#"{"winner": "tie"}"#, true).unwrap(),
+            ShadowVerdict::Tie
+        );
+        assert!(parse_verdict(r#"{"winner": "C"}"#, true).is_err());
+        assert!(parse_verdict("not json", true).is_err());
+    }
+
+    #[test]
+    fn test_record_keeps_latest_pairs() {
+        let shadow = traffic(vec![rule(1.0)]).unwrap();
+        for latency_ms in 0..(MAX_PAIRS_PER_RULE as u64 + 5) {
+            shadow.record("mini", pair(call(latency_ms, 0.0), None));
+        }
+        let detail = shadow.detail(1, "mini", 3).unwrap();
+        assert_eq!(detail.report.pairs, MAX_PAIRS_PER_RULE);
+        assert_eq!(
+            detail.recent_pairs[0].primary.latency_ms,
+            MAX_PAIRS_PER_RULE as u64 + 4
+        );
+        assert_eq!(detail.recent_pairs.len(), 3);
+        assert!(shadow.detail(2, "mini", 3).unwrap().recent_pairs.is_empty());
+        assert!(shadow.detail(1, "other", 3).is_none());
+    }
+}
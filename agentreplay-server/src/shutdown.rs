@@ -0,0 +1,143 @@
+// Copyright 2025 AgentReplay (https://github.com/agentreplay)
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program. If not, see <https://www.gnu.org/licenses/>.
+
+//! Coordinated shutdown
+//!
+//! On SIGTERM or SIGINT the server shuts down in stages, mirroring the
+//! desktop app's window-close handler:
+//!
+//! 1. `/health` reports `draining` with 503 for
+//!    `server.shutdown_drain_delay_secs`, so load balancers move traffic to
+//!    a replacement before connections are refused.
+//! 2. The HTTP, MCP and OTLP listeners stop accepting connections and
+//!    finish in-flight requests.
+//! 3. The ingestion actor stops taking new traces and processes those
+//!    already queued.
+//! 4. The databases sync their WAL, flush metric buckets and save their
+//!    indexes.
+//!
+//! Steps 2 and 3 share `server.shutdown_grace_secs`; whatever is still
+//! running afterwards is abandoned.
+
+use std::future::Future;
+use std::sync::atomic::{AtomicBool, Ordering};
+
+use tokio::sync::watch;
+
+/// Shutdown state shared by the servers and `/health`
+pub struct Shutdown {
+    draining: AtomicBool,
+    stop: watch::Sender<bool>,
+}
+
+impl Default for Shutdown {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Shutdown {
+    pub fn new() -> Self {
+        Self {
+            draining: AtomicBool::new(false),
+            stop: watch::Sender::new(false),
+        }
+    }
+
+    /// Whether shutdown has begun; `/health` fails from then on
+    pub fn is_draining(&self) -> bool {
+        self.draining.load(Ordering::SeqCst)
+    }
+
+    pub fn begin_draining(&self) {
+        self.draining.store(true, Ordering::SeqCst);
+    }
+
+    /// Tell the listeners to stop accepting connections
+    pub fn stop_listeners(&self) {
+        self.begin_draining();
+        self.stop.send_replace(true);
+    }
+
+    /// Resolves once [`Self::stop_listeners`] is called, for
+    /// `with_graceful_shutdown` and the like
+    pub fn stopped(&self) -> impl Future<Output = ()> + Send + 'static {
+        let mut stop = self.stop.subscribe();
+        async move {
+            let _ = stop.wait_for(|stopped| *stopped).await;
+        }
+    }
+}
+
+/// Wait for SIGINT (Ctrl-C) or, on Unix, SIGTERM
+pub async fn signal() {
+    let ctrl_c = async {
+        if let Err(e) = tokio::signal::ctrl_c().await {
+            tracing::error!("Failed to listen for Ctrl-C: {}", e);
+            std::future::pending::<()>().await;
+        }
+    };
+
+    #[cfg(unix)]
+    let terminate = async {
+        match tokio::signal::unix::signal(tokio::signal::unix::SignalKind::terminate()) {
+            Ok(mut sigterm) => {
+                sigterm.recv().await;
+            }
+            Err(e) => {
+                tracing::error!("Failed to listen for SIGTERM: {}", e);
+                std::future::pending::<()>().await;
+            }
+        }
+    };
+
+    #[cfg(not(unix))]
+    let terminate = std::future::pending::<()>();
+
+    tokio::select! {
+        _ = ctrl_c => tracing::info!("Received SIGINT, shutting down"),
+        _ = terminate => tracing::info!("Received SIGTERM, shutting down"),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    #[tokio::test]
+    async fn test_stopped_resolves_after_stop() {
+        let shutdown = Shutdown::new();
+        let early = shutdown.stopped();
+        assert!(!shutdown.is_draining());
+        assert!(
+            tokio::time::timeout(Duration::from_millis(20), shutdown.stopped())
+                .await
+                .is_err()
+        );
+
+        shutdown.begin_draining();
+        assert!(shutdown.is_draining());
+
+        shutdown.stop_listeners();
+        tokio::time::timeout(Duration::from_secs(1), early)
+            .await
+            .unwrap();
+        // Subscribers created afterwards resolve at once
+        tokio::time::timeout(Duration::from_secs(1), shutdown.stopped())
+            .await
+            .unwrap();
+    }
+}
@@ -0,0 +1,943 @@
+// Copyright 2025 AgentReplay (https://github.com/agentreplay)
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program. If not, see <https://www.gnu.org/licenses/>.
+
+//! Simulation runs: end-to-end agent CI against eval datasets
+//!
+//! A simulation sends every test case with a `TaskDefinitionV2` to a live
+//! agent, `trials` times each, and grades what comes back:
+//!
+//! - **Target**: an HTTP callback that receives a [`SimulationTask`] and
+//!   answers with an [`AgentReply`], or an OpenAI-compatible chat completion
+//!   endpoint whose key comes from the vault
+//! - **Traces**: each trial is recorded as a root span in the simulation
+//!   project (see [`SimulationConfig`]), so it can be inspected like
+//!   production traffic
+//! - **Grading**: the task's graders run on the recorded trace with the
+//!   rule-based evaluators; a test case's reference trajectory is always
+//!   checked
+//!
+//! Trials are stored as results of an eval run. `POST /api/v1/simulations`
+//! answers with the run ID right away, and the per-task aggregates are served
+//! by `GET /api/v1/evals/runs/:id`. Stopping the run through its status
+//! endpoint cancels the remaining trials.
+
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use agentreplay_core::clock::now_us;
+use agentreplay_core::{
+    AgentFlowEdge, EvalRun, GraderResult, GraderSpecV2, OverallResult, RunResult, SpanType,
+    TaskDefinitionV2, TestCase,
+};
+use agentreplay_evals::evaluators::trajectory_match::REFERENCE_TRAJECTORY_METADATA_KEY;
+use agentreplay_evals::evaluators::{
+    CostAnalyzer, LatencyBenchmark, LowConfidenceDetector, RagSuiteEvaluator,
+    StructuredOutputEvaluator, TrajectoryEfficiencyEvaluator, TrajectoryMatchEvaluator,
+};
+use agentreplay_evals::{EvalConfig, Evaluator, EvaluatorRegistry, TraceContext};
+use axum::{extract::State, http::StatusCode, Json};
+use futures::StreamExt;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use tracing::{info, warn};
+
+use crate::api::ingest::hash_string_to_u64;
+use crate::api::query::ApiError;
+use crate::api::AppState;
+use crate::auth::AuthContext;
+use crate::config::SimulationConfig;
+use crate::otel_genai::{GenAIPayload, ModelPricing};
+use crate::vault::ResolvedKey;
+
+const OPENAI_API_BASE: &str = "https://api.openai.com/v1";
+
+/// Name given to the simulation project when it is registered
+const SIMULATION_PROJECT_NAME: &str = "Simulations";
+
+/// Agent a simulation runs against
+#[derive(Debug, Clone, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum SimulationTarget {
+    /// POST each trial as a [`SimulationTask`], expecting an [`AgentReply`]
+    Http {
+        url: String,
+        #[serde(default)]
+        headers: HashMap<String, String>,
+    },
+    /// Send the task input as chat messages to `{base_url}/chat/completions`
+    #[serde(rename = "openai")]
+    OpenAi {
+        model: String,
+        #[serde(default)]
+        base_url: Option<String>,
+        /// Vault key to authenticate with, else the server's `OPENAI_API_KEY`
+        #[serde(default)]
+        key_alias: Option<String>,
+    },
+}
+
+/// Body of `POST /api/v1/simulations`
+#[derive(Debug, Deserialize)]
+pub struct SimulationRequest {
+    pub dataset_id: String,
+    pub target: SimulationTarget,
+    pub agent_id: String,
+    #[serde(default)]
+    pub name: Option<String>,
+    /// Model recorded on the run, defaults to the target's
+    #[serde(default)]
+    pub model: Option<String>,
+    #[serde(default = "default_trials")]
+    pub trials: u32,
+    /// Seed of the first trial; trial `n` gets `seed + n`
+    #[serde(default)]
+    pub seed: Option<u64>,
+}
+
+fn default_trials() -> u32 {
+    1
+}
+
+/// Response of `POST /api/v1/simulations`
+#[derive(Debug, Serialize)]
+pub struct SimulationStarted {
+    pub run_id: String,
+    pub project_id: u16,
+    pub tasks: usize,
+    pub trials: u32,
+}
+
+/// Request sent to an HTTP target for one trial
+#[derive(Debug, Serialize)]
+pub struct SimulationTask<'a> {
+    pub run_id: String,
+    pub test_case_id: String,
+    pub task_id: &'a str,
+    pub input: &'a Value,
+    pub trial_id: u32,
+    pub seed: Option<u64>,
+    /// Session the trial is recorded under, for agents that trace themselves
+    pub session_id: u64,
+    pub project_id: u16,
+}
+
+/// What the agent did in one trial
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct AgentReply {
+    #[serde(default)]
+    pub output: String,
+    #[serde(default)]
+    pub tool_calls: Vec<AgentToolCall>,
+    /// Retrieved chunks, for RAG graders
+    #[serde(default)]
+    pub context: Vec<String>,
+    #[serde(default)]
+    pub input_tokens: Option<u32>,
+    #[serde(default)]
+    pub output_tokens: Option<u32>,
+    #[serde(default)]
+    pub cost_usd: Option<f64>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct AgentToolCall {
+    pub name: String,
+    #[serde(default)]
+    pub arguments: Value,
+    #[serde(default)]
+    pub result: Option<Value>,
+}
+
+/// Target with its credentials resolved
+enum AgentEndpoint {
+    Http {
+        url: String,
+        headers: HashMap<String, String>,
+    },
+    OpenAi {
+        url: String,
+        model: String,
+        api_key: Option<String>,
+        /// Vault key the agent's usage is attributed to
+        key: Option<ResolvedKey>,
+    },
+}
+
+impl AgentEndpoint {
+    fn resolve(
+        state: &AppState,
+        tenant_id: u64,
+        target: SimulationTarget,
+    ) -> Result<Self, ApiError> {
+        match target {
+            SimulationTarget::Http { url, headers } => Ok(Self::Http { url, headers }),
+            SimulationTarget::OpenAi {
+                model,
+                base_url,
+                key_alias,
+            } => {
+                let key = key_alias
+                    .map(|alias| state.vault.resolve(&state.db, tenant_id, &alias))
+                    .transpose()?;
+                let base_url = base_url
+                    .or_else(|| key.as_ref().and_then(|k| k.base_url.clone()))
+                    .unwrap_or_else(|| OPENAI_API_BASE.to_string());
+                let api_key = match &key {
+                    Some(key) => Some(key.api_key.clone()),
+                    None => std::env::var("OPENAI_API_KEY").ok(),
+                };
+                Ok(Self::OpenAi {
+                    url: format!("{}/chat/completions", base_url.trim_end_matches('/')),
+                    model,
+                    api_key,
+                    key,
+                })
+            }
+        }
+    }
+
+    fn model(&self) -> Option<&str> {
+        match self {
+            Self::Http { .. } => None,
+            Self::OpenAi { model, .. } => Some(model),
+        }
+    }
+}
+
+/// Runs simulations and grades their trials
+pub struct Simulator {
+    config: SimulationConfig,
+    client: reqwest::Client,
+    graders: EvaluatorRegistry,
+}
+
+impl Simulator {
+    pub fn new(config: SimulationConfig) -> Self {
+        let client = reqwest::Client::builder()
+            .timeout(Duration::from_secs(config.timeout_secs))
+            .build()
+            .unwrap_or_default();
+
+        // Every trial has a new trace, caching results would only cost memory
+        let graders = EvaluatorRegistry::with_config(EvalConfig {
+            enable_cache: false,
+            ..Default::default()
+        });
+        let evaluators: Vec<Arc<dyn Evaluator>> = vec![
+            Arc::new(LatencyBenchmark::new()),
+            Arc::new(CostAnalyzer::new()),
+            Arc::new(TrajectoryEfficiencyEvaluator::new()),
+            Arc::new(LowConfidenceDetector::new()),
+            Arc::new(StructuredOutputEvaluator::new()),
+            Arc::new(RagSuiteEvaluator::new()),
+            Arc::new(TrajectoryMatchEvaluator::new()),
+        ];
+        for evaluator in evaluators {
+            if let Err(e) = graders.register(evaluator) {
+                warn!("Failed to register simulation grader: {}", e);
+            }
+        }
+
+        Self {
+            config,
+            client,
+            graders,
+        }
+    }
+
+    pub fn config(&self) -> &SimulationConfig {
+        &self.config
+    }
+
+    /// Grader IDs a task definition may reference
+    pub fn grader_ids(&self) -> Vec<String> {
+        self.graders.list_evaluators()
+    }
+
+    /// Run every trial of `run_id`, then mark the run finished
+    async fn run(
+        self: Arc<Self>,
+        state: AppState,
+        run_id: u128,
+        tenant_id: u64,
+        endpoint: AgentEndpoint,
+        jobs: Vec<(TestCase, TaskDefinitionV2, u32, Option<u64>)>,
+    ) {
+        let total = jobs.len();
+        let endpoint = &endpoint;
+        let state_ref = &state;
+        let this = &self;
+        let errors: usize = futures::stream::iter(jobs)
+            .map(|(test_case, task, trial_id, seed)| async move {
+                // Stopped through the status endpoint
+                let running = state_ref
+                    .db
+                    .get_eval_run(run_id)
+                    .ok()
+                    .flatten()
+                    .is_some_and(|run| run.is_running());
+                if !running {
+                    return 0;
+                }
+
+                let result = this
+                    .run_trial(
+                        state_ref, run_id, tenant_id, endpoint, &test_case, &task, trial_id, seed,
+                    )
+                    .await;
+                let failed = result.error.is_some() as usize;
+                let cost = result.cost_usd.unwrap_or(0.0);
+                if let Err(e) = state_ref.db.update_eval_run(run_id, |run| {
+                    run.total_cost += cost;
+                    run.add_result(result);
+                }) {
+                    warn!(run_id = %format!("{:#x}", run_id), error = %e, "Failed to store simulation trial");
+                }
+                failed
+            })
+            .buffer_unordered(self.config.concurrency.max(1))
+            .fold(0, |errors, failed| async move { errors + failed })
+            .await;
+
+        let finished_at = now_us();
+        if let Err(e) = state.db.update_eval_run(run_id, |run| {
+            if !run.is_running() {
+                return;
+            }
+            if total > 0 && errors == total {
+                run.fail(finished_at);
+            } else {
+                run.complete(finished_at);
+            }
+        }) {
+            warn!(run_id = %format!("{:#x}", run_id), error = %e, "Failed to finish simulation run");
+        }
+        info!(
+            "Simulation run {:#x} finished: {} trials, {} agent errors",
+            run_id, total, errors
+        );
+    }
+
+    /// Call the agent, record its trace and grade it
+    #[allow(clippy::too_many_arguments)]
+    async fn run_trial(
+        &self,
+        state: &AppState,
+        run_id: u128,
+        tenant_id: u64,
+        endpoint: &AgentEndpoint,
+        test_case: &TestCase,
+        task: &TaskDefinitionV2,
+        trial_id: u32,
+        seed: Option<u64>,
+    ) -> RunResult {
+        let session_id = rand::random::<u64>() >> 1;
+        let messages = task_messages(&task.input);
+        let started = Instant::now();
+        let reply = self
+            .invoke(
+                state,
+                tenant_id,
+                endpoint,
+                &SimulationTask {
+                    run_id: format!("{:#x}", run_id),
+                    test_case_id: format!("{:#x}", test_case.id),
+                    task_id: &task.task_id,
+                    input: &task.input,
+                    trial_id,
+                    seed,
+                    session_id,
+                    project_id: self.config.project_id,
+                },
+                &messages,
+            )
+            .await;
+        let latency = started.elapsed();
+        let failure = |error: String| {
+            RunResult::failure(test_case.id, error, now_us())
+                .with_trial(trial_id, seed)
+                .with_latency(latency.as_millis() as u64)
+        };
+        let reply = match reply {
+            Ok(reply) => reply,
+            Err(e) => return failure(format!("Agent call failed: {}", e)),
+        };
+
+        let payload = trial_payload(
+            &messages,
+            &reply,
+            endpoint.model(),
+            &[
+                ("simulation.run_id", format!("{:#x}", run_id)),
+                ("simulation.test_case_id", format!("{:#x}", test_case.id)),
+                ("simulation.task_id", task.task_id.clone()),
+                ("simulation.trial_id", trial_id.to_string()),
+            ],
+        );
+        let cost = reply.cost_usd.or_else(|| {
+            payload
+                .request_model
+                .as_ref()
+                .map(|model| payload.calculate_cost(&ModelPricing::for_model("openai", model)))
+        });
+        let edge = match self
+            .record_trace(state, tenant_id, session_id, latency, &payload)
+            .await
+        {
+            Ok(edge) => edge,
+            Err(e) => return failure(e),
+        };
+
+        let mut result = RunResult::success(test_case.id, edge.edge_id, now_us())
+            .with_trial(trial_id, seed)
+            .with_latency(latency.as_millis() as u64);
+        result.cost_usd = cost;
+        match self
+            .grade(state, &edge, test_case, task, &messages, &reply)
+            .await
+        {
+            Ok((grader_results, overall)) => {
+                for grader in &grader_results {
+                    if let Some(score) = grader.score {
+                        result.eval_metrics.insert(grader.grader_id.clone(), score);
+                    }
+                }
+                result.passed = overall.passed;
+                result.grader_results = grader_results;
+                result.overall = Some(overall);
+            }
+            Err(e) => {
+                result.passed = false;
+                result.error = Some(format!("Grading failed: {}", e));
+            }
+        }
+        result
+    }
+
+    async fn invoke(
+        &self,
+        state: &AppState,
+        tenant_id: u64,
+        endpoint: &AgentEndpoint,
+        task: &SimulationTask<'_>,
+        messages: &[Value],
+    ) -> Result<AgentReply, String> {
+        match endpoint {
+            AgentEndpoint::Http { url, headers } => {
+                let mut request = self.client.post(url).json(task);
+                for (name, value) in headers {
+                    request = request.header(name, value);
+                }
+                let response = request.send().await.map_err(|e| e.to_string())?;
+                let status = response.status();
+                if !status.is_success() {
+                    let body = response.text().await.unwrap_or_default();
+                    return Err(format!("{}: {}", status, body));
+                }
+                response
+                    .json::<AgentReply>()
+                    .await
+                    .map_err(|e| format!("Invalid agent reply: {}", e))
+            }
+            AgentEndpoint::OpenAi {
+                url,
+                model,
+                api_key,
+                key,
+            } => {
+                let mut body = json!({ "model": model, "messages": messages });
+                if let Some(seed) = task.seed {
+                    body["seed"] = json!(seed);
+                }
+                let mut request = self.client.post(url).json(&body);
+                if let Some(api_key) = api_key {
+                    request = request.bearer_auth(api_key);
+                }
+                let response = request.send().await.map_err(|e| e.to_string())?;
+                let status = response.status();
+                let json: Value = response
+                    .json()
+                    .await
+                    .map_err(|e| format!("Invalid chat completion: {}", e))?;
+                if !status.is_success() {
+                    return Err(format!("{}: {}", status, json["error"]["message"]));
+                }
+                let reply = parse_chat_completion(&json)?;
+                if let Some(key) = key {
+                    state.vault.record_usage(
+                        &state.db,
+                        tenant_id,
+                        key,
+                        "simulation",
+                        model,
+                        reply.input_tokens.unwrap_or(0) as u64,
+                        reply.output_tokens.unwrap_or(0) as u64,
+                    );
+                }
+                Ok(reply)
+            }
+        }
+    }
+
+    /// Store a trial as a root span of the simulation project
+    async fn record_trace(
+        &self,
+        state: &AppState,
+        tenant_id: u64,
+        session_id: u64,
+        latency: Duration,
+        payload: &GenAIPayload,
+    ) -> Result<AgentFlowEdge, String> {
+        let mut edge = AgentFlowEdge::new(
+            tenant_id,
+            self.config.project_id,
+            hash_string_to_u64(SIMULATION_PROJECT_NAME),
+            session_id,
+            SpanType::Root,
+            0,
+        );
+        edge.duration_us = latency.as_micros().min(u32::MAX as u128) as u32;
+        edge.token_count = payload.total_tokens.unwrap_or(0);
+        edge.has_payload = 1;
+        edge.checksum = edge.compute_checksum();
+
+        let bytes = serde_json::to_vec(payload)
+            .map_err(|e| format!("Failed to serialize payload: {}", e))?;
+        state
+            .db
+            .put_payload(edge.edge_id, &bytes)
+            .map_err(|e| format!("Failed to store payload: {}", e))?;
+        state
+            .db
+            .insert_batch(&[edge])
+            .await
+            .map_err(|e| format!("Failed to insert edge: {}", e))?;
+        let _ = state.trace_broadcaster.send(edge);
+        Ok(edge)
+    }
+
+    async fn grade(
+        &self,
+        state: &AppState,
+        edge: &AgentFlowEdge,
+        test_case: &TestCase,
+        task: &TaskDefinitionV2,
+        messages: &[Value],
+        reply: &AgentReply,
+    ) -> Result<(Vec<GraderResult>, OverallResult), String> {
+        let mut metadata = task.metadata.clone();
+        if let Some(expected) = &task.expected_output {
+            let expected = match expected {
+                Value::String(s) => s.clone(),
+                other => other.to_string(),
+            };
+            metadata.insert("expected_output".to_string(), json!(expected));
+        }
+        let mut task = task.clone();
+        if let Some(reference) = &test_case.reference_trajectory {
+            metadata.insert(
+                REFERENCE_TRAJECTORY_METADATA_KEY.to_string(),
+                serde_json::to_value(reference).map_err(|e| e.to_string())?,
+            );
+            let trajectory_id = TrajectoryMatchEvaluator::new().id().to_string();
+            if !task.graders.iter().any(|g| g.grader_id == trajectory_id) {
+                task.graders.push(GraderSpecV2 {
+                    grader_id: trajectory_id,
+                    grader_type: Some("trajectory".to_string()),
+                    config: HashMap::new(),
+                    weight: None,
+                    policy: None,
+                    thresholds: Vec::new(),
+                });
+            }
+        }
+
+        // Without graders, a trial passes when the agent answered
+        if task.graders.is_empty() {
+            return Ok((
+                Vec::new(),
+                OverallResult {
+                    policy: "agent_response".to_string(),
+                    passed: true,
+                    composite_score: None,
+                },
+            ));
+        }
+
+        let trace = TraceContext {
+            trace_id: edge.edge_id,
+            eval_trace: Some(crate::api::build_eval_trace_v1(state, edge)),
+            timestamp_us: edge.timestamp_us,
+            edges: vec![*edge],
+            input: messages
+                .iter()
+                .rev()
+                .find(|m| m["role"] == "user")
+                .map(message_text),
+            output: Some(reply.output.clone()),
+            context: (!reply.context.is_empty()).then(|| reply.context.clone()),
+            metadata,
+        };
+        let output = self
+            .graders
+            .evaluate_task_definition(&trace, &task)
+            .await
+            .map_err(|e| e.to_string())?;
+        Ok((output.grader_results, output.overall))
+    }
+}
+
+/// Chat messages for a task input
+///
+/// Accepts `{"messages": [...]}`, a plain string, or an object with a
+/// `prompt`, `query`, `question` or `input` field; anything else is sent as
+/// its JSON.
+pub fn task_messages(input: &Value) -> Vec<Value> {
+    if let Some(messages) = input.get("messages").and_then(Value::as_array) {
+        return messages.clone();
+    }
+    let text = match input {
+        Value::String(s) => s.clone(),
+        _ => ["prompt", "query", "question", "input"]
+            .iter()
+            .find_map(|field| input.get(field).and_then(Value::as_str))
+            .map(str::to_string)
+            .unwrap_or_else(|| input.to_string()),
+    };
+    vec![json!({ "role": "user", "content": text })]
+}
+
+fn message_text(message: &Value) -> String {
+    match &message["content"] {
+        Value::String(s) => s.clone(),
+        Value::Null => String::new(),
+        other => other.to_string(),
+    }
+}
+
+fn json_text(value: &Value) -> String {
+    match value {
+        Value::String(s) => s.clone(),
+        other => other.to_string(),
+    }
+}
+
+/// Reply of an OpenAI-compatible chat completion
+pub fn parse_chat_completion(response: &Value) -> Result<AgentReply, String> {
+    let message = response["choices"]
+        .get(0)
+        .map(|choice| &choice["message"])
+        .ok_or_else(|| "Chat completion has no choices".to_string())?;
+    let tool_calls = message["tool_calls"]
+        .as_array()
+        .map(|calls| {
+            calls
+                .iter()
+                .filter_map(|call| {
+                    let function = &call["function"];
+                    let name = function["name"].as_str()?;
+                    let arguments = match &function["arguments"] {
+                        Value::String(s) => serde_json::from_str(s).unwrap_or_else(|_| json!(s)),
+                        other => other.clone(),
+                    };
+                    Some(AgentToolCall {
+                        name: name.to_string(),
+                        arguments,
+                        result: None,
+                    })
+                })
+                .collect()
+        })
+        .unwrap_or_default();
+
+    Ok(AgentReply {
+        output: message_text(message),
+        tool_calls,
+        context: Vec::new(),
+        input_tokens: response["usage"]["prompt_tokens"]
+            .as_u64()
+            .map(|n| n as u32),
+        output_tokens: response["usage"]["completion_tokens"]
+            .as_u64()
+            .map(|n| n as u32),
+        cost_usd: None,
+    })
+}
+
+/// Payload of a trial's span, in the flat `gen_ai.*` layout of ingested spans
+///
+/// Message and tool texts are inserted as strings: going through
+/// [`GenAIPayload::from_attributes`] would turn numeric or JSON-looking
+/// content into other JSON types.
+pub fn trial_payload(
+    messages: &[Value],
+    reply: &AgentReply,
+    model: Option<&str>,
+    attributes: &[(&str, String)],
+) -> GenAIPayload {
+    let mut attrs: HashMap<String, String> = attributes
+        .iter()
+        .map(|(key, value)| (key.to_string(), value.clone()))
+        .collect();
+    attrs.insert("gen_ai.operation.name".to_string(), "chat".to_string());
+    if let Some(model) = model {
+        attrs.insert("gen_ai.request.model".to_string(), model.to_string());
+    }
+    if let Some(tokens) = reply.input_tokens {
+        attrs.insert("gen_ai.usage.input_tokens".to_string(), tokens.to_string());
+    }
+    if let Some(tokens) = reply.output_tokens {
+        attrs.insert("gen_ai.usage.output_tokens".to_string(), tokens.to_string());
+    }
+    let mut payload = GenAIPayload::from_attributes(&attrs);
+    payload.calculate_total_tokens();
+
+    let mut text = |key: String, value: String| {
+        payload.additional.insert(key, Value::String(value));
+    };
+    for (i, message) in messages.iter().enumerate() {
+        let role = message["role"].as_str().unwrap_or("user").to_string();
+        text(format!("gen_ai.prompt.{}.role", i), role);
+        text(
+            format!("gen_ai.prompt.{}.content", i),
+            message_text(message),
+        );
+    }
+    text(
+        "gen_ai.completion.0.role".to_string(),
+        "assistant".to_string(),
+    );
+    text(
+        "gen_ai.completion.0.content".to_string(),
+        reply.output.clone(),
+    );
+    for (i, call) in reply.tool_calls.iter().enumerate() {
+        text(format!("gen_ai.tool.{}.name", i), call.name.clone());
+        text(
+            format!("gen_ai.tool.{}.arguments", i),
+            json_text(&call.arguments),
+        );
+        if let Some(result) = &call.result {
+            text(format!("gen_ai.tool.{}.result", i), json_text(result));
+        }
+    }
+    payload
+}
+
+/// POST /api/v1/simulations
+pub async fn start_simulation(
+    State(state): State<AppState>,
+    axum::Extension(auth): axum::Extension<AuthContext>,
+    Json(req): Json<SimulationRequest>,
+) -> Result<(StatusCode, Json<SimulationStarted>), ApiError> {
+    let simulator = Arc::clone(&state.simulator);
+    let config = simulator.config();
+    if req.trials == 0 || req.trials > config.max_trials {
+        return Err(ApiError::BadRequest(format!(
+            "trials must be between 1 and {}",
+            config.max_trials
+        )));
+    }
+
+    let dataset_id = u128::from_str_radix(req.dataset_id.trim_start_matches("0x"), 16)
+        .map_err(|e| ApiError::BadRequest(format!("Invalid dataset ID: {}", e)))?;
+    let dataset = state
+        .db
+        .get_eval_dataset(dataset_id)
+        .map_err(|e| ApiError::Internal(e.to_string()))?
+        .ok_or_else(|| ApiError::NotFound("Dataset not found".to_string()))?;
+
+    let tasks: Vec<(TestCase, TaskDefinitionV2)> = dataset
+        .test_cases
+        .iter()
+        .filter_map(|case| {
+            let mut task = case.task_definition_v2.clone()?;
+            task.ensure_task_id();
+            Some((case.clone(), task))
+        })
+        .collect();
+    if tasks.is_empty() {
+        return Err(ApiError::BadRequest(
+            "Dataset has no test cases with a task definition".to_string(),
+        ));
+    }
+
+    let available = simulator.grader_ids();
+    let mut unknown: Vec<&str> = tasks
+        .iter()
+        .flat_map(|(_, task)| &task.graders)
+        .map(|g| g.grader_id.as_str())
+        .filter(|id| !available.iter().any(|a| a == id))
+        .collect();
+    unknown.sort_unstable();
+    unknown.dedup();
+    if !unknown.is_empty() {
+        return Err(ApiError::BadRequest(format!(
+            "Unknown graders: {} (available: {})",
+            unknown.join(", "),
+            available.join(", ")
+        )));
+    }
+
+    let endpoint = AgentEndpoint::resolve(&state, auth.tenant_id, req.target)?;
+    let project_id = config.project_id;
+    if let Some(registry) = &state.project_registry {
+        if registry.get_metadata(project_id).is_none() {
+            if let Err(e) = registry.register_project(
+                project_id,
+                SIMULATION_PROJECT_NAME.to_string(),
+                Some("Traces recorded by simulation runs".to_string()),
+            ) {
+                warn!("Failed to register simulation project: {}", e);
+            }
+        }
+    }
+
+    let run_id = (now_us() as u128) ^ ((rand::random::<u64>() as u128) << 64);
+    let model = req
+        .model
+        .or_else(|| endpoint.model().map(str::to_string))
+        .unwrap_or_else(|| "unknown".to_string());
+    let name = req
+        .name
+        .unwrap_or_else(|| format!("Simulation: {}", dataset.name));
+    let mut run = EvalRun::new(run_id, dataset_id, name, req.agent_id, model, now_us());
+    run.config = HashMap::from([
+        ("simulation".to_string(), "true".to_string()),
+        ("trials".to_string(), req.trials.to_string()),
+        ("project_id".to_string(), project_id.to_string()),
+    ]);
+    if let Some(seed) = req.seed {
+        run.config.insert("seed".to_string(), seed.to_string());
+    }
+    state
+        .db
+        .store_eval_run(run)
+        .map_err(|e| ApiError::Internal(e.to_string()))?;
+
+    let task_count = tasks.len();
+    let jobs: Vec<_> = tasks
+        .into_iter()
+        .flat_map(|(case, task)| {
+            (0..req.trials).map(move |trial_id| {
+                let seed = req.seed.map(|s| s.wrapping_add(trial_id as u64));
+                (case.clone(), task.clone(), trial_id, seed)
+            })
+        })
+        .collect();
+    tokio::spawn(simulator.run(state.clone(), run_id, auth.tenant_id, endpoint, jobs));
+
+    Ok((
+        StatusCode::ACCEPTED,
+        Json(SimulationStarted {
+            run_id: format!("0x{:x}", run_id),
+            project_id,
+            tasks: task_count,
+            trials: req.trials,
+        }),
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::api::payload_extractors::{
+        extract_completions, extract_prompts, extract_tool_calls,
+    };
+
+    #[test]
+    fn test_target_parsing() {
+        let target: SimulationTarget = serde_json::from_value(json!({
+            "type": "openai",
+            "model": "gpt-4o-mini",
+            "key_alias": "ci"
+        }))
+        .unwrap();
+        assert!(matches!(
+            target,
+            SimulationTarget::OpenAi { ref model, key_alias: Some(_), base_url: None }
+                if model == "gpt-4o-mini"
+        ));
+
+        let request: SimulationRequest = serde_json::from_value(json!({
+            "dataset_id": "0x1f",
+            "agent_id": "support-bot",
+            "target": {"type": "http", "url": "http://localhost:8000/run"}
+        }))
+        .unwrap();
+        assert_eq!(request.trials, 1);
+        assert!(
+            matches!(request.target, SimulationTarget::Http { ref headers, .. } if headers.is_empty())
+        );
+    }
+
+    #[test]
+    fn test_task_messages() {
+        assert_eq!(
+            task_messages(&json!("Book a flight")),
+            vec![json!({"role": "user", "content": "Book a flight"})]
+        );
+        assert_eq!(
+            task_messages(&json!({"query": "Refund order 42", "user_id": 7})),
+            vec![json!({"role": "user", "content": "Refund order 42"})]
+        );
+        let messages = json!([
+            {"role": "system", "content": "Be brief"},
+            {"role": "user", "content": "Hi"}
+        ]);
+        assert_eq!(
+            task_messages(&json!({ "messages": messages })),
+            messages.as_array().unwrap().clone()
+        );
+    }
+
+    #[test]
+    fn test_trial_payload_round_trips_through_extractors() {
+        let completion = json!({
+            "choices": [{"message": {
+                "role": "assistant",
+                "content": "42",
+                "tool_calls": [{"type": "function", "function": {
+                    "name": "lookup_order",
+                    "arguments": "{\"order_id\": 42}"
+                }}]
+            }}],
+            "usage": {"prompt_tokens": 12, "completion_tokens": 3}
+        });
+        let reply = parse_chat_completion(&completion).unwrap();
+        assert_eq!(reply.tool_calls[0].arguments, json!({"order_id": 42}));
+
+        let messages = task_messages(&json!("How many items are in order 42?"));
+        let payload = trial_payload(
+            &messages,
+            &reply,
+            Some("gpt-4o-mini"),
+            &[("simulation.trial_id", "0".to_string())],
+        );
+        assert_eq!(payload.total_tokens, Some(15));
+        assert_eq!(payload.request_model.as_deref(), Some("gpt-4o-mini"));
+
+        let prompts = extract_prompts(&payload);
+        assert_eq!(prompts.len(), 1);
+        assert_eq!(prompts[0].content, "How many items are in order 42?");
+        let completions = extract_completions(&payload);
+        assert_eq!(completions[0].content, "42");
+        let tools = extract_tool_calls(&payload);
+        assert_eq!(tools.len(), 1);
+        assert_eq!(tools[0].name, "lookup_order");
+        assert_eq!(tools[0].arguments, "{\"order_id\":42}");
+    }
+}
@@ -0,0 +1,428 @@
+// Copyright 2025 AgentReplay (https://github.com/agentreplay)
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program. If not, see <https://www.gnu.org/licenses/>.
+
+//! Warm standby through write-ahead log shipping
+//!
+//! With `replication.wal_dir` set, a primary ships every write to the shared
+//! directory (see [`agentreplay_storage::wal_shipping`]) and a node started
+//! with `--standby` replays it:
+//!
+//! - The **primary** seals buffered writes into a segment every
+//!   `ship_interval_ms` (or earlier once `max_segment_bytes` are buffered).
+//! - The **standby** polls every `apply_interval_ms`, replays new segments
+//!   into its own database, and records the applied LSN in its data
+//!   directory so a restart resumes where it left off. It serves reads but
+//!   rejects writes.
+//! - `POST /api/v1/replication/promote` drains the remaining segments and
+//!   turns the standby into the primary. Promotion starts a new epoch, which
+//!   fences the old primary: its later segments are ignored and it stops
+//!   accepting writes on its next ship attempt.
+//!
+//! `GET /api/v1/replication/status` reports LSNs and lag on both sides.
+//!
+//! Only the main database is shipped, so WAL shipping requires
+//! `use_project_storage = false`. Fencing covers the OTLP gRPC receiver as
+//! well as HTTP (see [`crate::cluster::write_refusal`]).
+
+use crate::api::{ApiError, AppState};
+use crate::auth::{AuthContext, Role};
+use crate::config::ReplicationConfig;
+use agentreplay_core::clock::now_secs;
+use agentreplay_query::Agentreplay;
+use agentreplay_storage::{read_wal_head, LocalFsBackend, StorageBackend, WalReceiver, WalShipper};
+use axum::{extract::State, Extension, Json};
+use parking_lot::RwLock;
+use serde::Serialize;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+
+/// File in the standby's data dir recording the last applied LSN
+const POSITION_FILE: &str = "wal_standby.position";
+
+/// Segments fetched per apply round
+const APPLY_BATCH_SEGMENTS: usize = 64;
+
+/// Ship rounds between segment pruning passes
+const PRUNE_EVERY_ROUNDS: u64 = 60;
+
+/// Which side of the log this node is on
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum WalRole {
+    #[default]
+    Primary,
+    Standby,
+}
+
+/// Point-in-time view of WAL shipping on this node
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct WalReplicationStatus {
+    pub role: WalRole,
+    pub node_id: String,
+    pub accepts_writes: bool,
+    /// Current epoch on the shared timeline
+    pub epoch: Option<u64>,
+    /// Primary: last LSN assigned to a write
+    pub last_lsn: Option<u64>,
+    /// Primary: last LSN sealed into a shipped segment
+    pub shipped_lsn: Option<u64>,
+    /// Primary: writes buffered but not yet shipped
+    pub pending_records: usize,
+    pub segments_shipped: u64,
+    /// Standby: last LSN replayed into the local database
+    pub applied_lsn: Option<u64>,
+    /// Standby: last LSN shipped by the primary
+    pub primary_lsn: Option<u64>,
+    pub segments_applied: u64,
+    /// Standby: shipped records not yet applied
+    pub lag_records: Option<u64>,
+    /// Standby: age of the oldest unapplied segment in seconds
+    pub lag_secs: Option<u64>,
+    pub promoted_at: Option<u64>,
+    pub last_error: Option<String>,
+}
+
+/// WAL shipping (primary) or replay (standby) for one node
+pub struct WalReplicator {
+    config: ReplicationConfig,
+    node_id: String,
+    backend: Arc<dyn StorageBackend>,
+    receiver: WalReceiver,
+    standby: AtomicBool,
+    /// Set when a newer epoch took over shipping from this primary
+    fenced: AtomicBool,
+    shipper: RwLock<Option<Arc<WalShipper>>>,
+    status: RwLock<WalReplicationStatus>,
+    position_path: PathBuf,
+    /// Serializes segment replay with promotion
+    apply_lock: tokio::sync::Mutex<()>,
+}
+
+impl WalReplicator {
+    /// Connect to the WAL directory; returns None when shipping is disabled
+    pub fn new(
+        config: &ReplicationConfig,
+        node_id: &str,
+        data_dir: &Path,
+    ) -> anyhow::Result<Option<Arc<Self>>> {
+        let Some(wal_dir) = config.wal_dir.as_ref() else {
+            return Ok(None);
+        };
+
+        let backend: Arc<dyn StorageBackend> = Arc::new(LocalFsBackend::new(wal_dir)?);
+        let role = if config.standby {
+            WalRole::Standby
+        } else {
+            WalRole::Primary
+        };
+        let replicator = Self {
+            config: config.clone(),
+            node_id: node_id.to_string(),
+            receiver: WalReceiver::new(backend.clone()),
+            backend,
+            standby: AtomicBool::new(config.standby),
+            fenced: AtomicBool::new(false),
+            shipper: RwLock::new(None),
+            status: RwLock::new(WalReplicationStatus {
+                role,
+                node_id: node_id.to_string(),
+                ..Default::default()
+            }),
+            position_path: data_dir.join(POSITION_FILE),
+            apply_lock: tokio::sync::Mutex::new(()),
+        };
+        if config.standby {
+            replicator.status.write().applied_lsn = Some(replicator.applied_lsn()?);
+        }
+        Ok(Some(Arc::new(replicator)))
+    }
+
+    pub fn is_standby(&self) -> bool {
+        self.standby.load(Ordering::Acquire)
+    }
+
+    /// Whether writes may be served right now
+    pub fn accepts_writes(&self) -> bool {
+        !self.is_standby() && !self.fenced.load(Ordering::Acquire)
+    }
+
+    pub fn status(&self) -> WalReplicationStatus {
+        let mut status = self.status.read().clone();
+        status.accepts_writes = self.accepts_writes();
+        if let Some(shipper) = self.shipper.read().as_ref() {
+            status.epoch = Some(shipper.epoch());
+            status.last_lsn = Some(shipper.last_lsn());
+            status.shipped_lsn = Some(shipper.shipped_lsn());
+            status.pending_records = shipper.pending_records();
+        }
+        status
+    }
+
+    /// Start shipping (primary) or the replay loop (standby)
+    pub fn spawn(self: &Arc<Self>, db: Arc<Agentreplay>) -> anyhow::Result<()> {
+        if self.is_standby() {
+            let node = self.clone();
+            tokio::spawn(async move { node.run_standby(db).await });
+            return Ok(());
+        }
+        self.start_shipping(&db)
+    }
+
+    /// Promote a standby to primary
+    ///
+    /// Replays everything the old primary shipped, then starts a new epoch
+    /// and begins shipping this node's writes.
+    pub async fn promote(self: &Arc<Self>, db: &Arc<Agentreplay>) -> anyhow::Result<()> {
+        let _guard = self.apply_lock.lock().await;
+        if !self.is_standby() {
+            anyhow::bail!("node is not a standby");
+        }
+
+        while self.apply_pending(db).await? > 0 {}
+        self.start_shipping(db)?;
+        self.standby.store(false, Ordering::Release);
+
+        let mut status = self.status.write();
+        status.role = WalRole::Primary;
+        status.promoted_at = Some(now_secs());
+        status.lag_records = None;
+        status.lag_secs = None;
+        tracing::info!(
+            "Promoted to primary at LSN {}",
+            status.applied_lsn.unwrap_or(0)
+        );
+        Ok(())
+    }
+
+    fn start_shipping(self: &Arc<Self>, db: &Arc<Agentreplay>) -> anyhow::Result<()> {
+        let shipper = Arc::new(WalShipper::start(
+            self.backend.clone(),
+            self.node_id.clone(),
+            self.config.max_segment_bytes,
+        )?);
+        tracing::info!(
+            "Shipping WAL to {:?} (epoch {}, next LSN {})",
+            self.config.wal_dir,
+            shipper.epoch(),
+            shipper.last_lsn() + 1
+        );
+        db.set_wal_shipper(Some(shipper.clone()));
+        *self.shipper.write() = Some(shipper.clone());
+
+        let node = self.clone();
+        let db = db.clone();
+        tokio::spawn(async move { node.run_shipper(db, shipper).await });
+        Ok(())
+    }
+
+    async fn run_shipper(self: Arc<Self>, db: Arc<Agentreplay>, shipper: Arc<WalShipper>) {
+        let mut ticker = tokio::time::interval(self.config.ship_interval());
+        let mut rounds: u64 = 0;
+
+        loop {
+            ticker.tick().await;
+            rounds += 1;
+
+            let task_shipper = shipper.clone();
+            let result = tokio::task::spawn_blocking(move || {
+                let mut sealed = Vec::new();
+                loop {
+                    match task_shipper.seal()? {
+                        Some(segment) => sealed.push(segment),
+                        None => break,
+                    }
+                    if !task_shipper.should_seal() {
+                        break;
+                    }
+                }
+                Ok::<_, agentreplay_core::AgentreplayError>(sealed)
+            })
+            .await;
+
+            match result {
+                Ok(Ok(sealed)) => {
+                    let mut status = self.status.write();
+                    status.segments_shipped += sealed.len() as u64;
+                    if !sealed.is_empty() {
+                        status.last_error = None;
+                    }
+                }
+                Ok(Err(e)) => {
+                    let superseded = read_wal_head(self.backend.as_ref())
+                        .ok()
+                        .flatten()
+                        .is_some_and(|head| head.epoch() != shipper.epoch());
+                    self.status.write().last_error = Some(e.to_string());
+                    if superseded {
+                        tracing::error!("WAL shipping fenced, refusing further writes: {}", e);
+                        self.fenced.store(true, Ordering::Release);
+                        db.set_wal_shipper(None);
+                        return;
+                    }
+                    tracing::warn!("WAL ship failed: {}", e);
+                }
+                Err(e) => {
+                    self.status.write().last_error = Some(format!("ship task panicked: {}", e));
+                }
+            }
+
+            if rounds % PRUNE_EVERY_ROUNDS == 0 {
+                if let Err(e) = shipper.prune(self.config.retain_segments) {
+                    tracing::warn!("Failed to prune WAL segments: {}", e);
+                }
+            }
+        }
+    }
+
+    async fn run_standby(self: Arc<Self>, db: Arc<Agentreplay>) {
+        let mut ticker = tokio::time::interval(self.config.apply_interval());
+
+        loop {
+            ticker.tick().await;
+
+            let _guard = self.apply_lock.lock().await;
+            if !self.is_standby() {
+                return;
+            }
+            if let Err(e) = self.apply_pending(&db).await {
+                tracing::warn!("WAL replay failed: {}", e);
+                self.status.write().last_error = Some(e.to_string());
+            }
+        }
+    }
+
+    /// Replay one batch of pending segments; returns the records applied
+    ///
+    /// Callers must hold `apply_lock`.
+    async fn apply_pending(&self, db: &Arc<Agentreplay>) -> anyhow::Result<usize> {
+        let mut applied_lsn = self.applied_lsn()?;
+        let head = read_wal_head(self.backend.as_ref())?;
+        let segments = self.receiver.pending(applied_lsn, APPLY_BATCH_SEGMENTS)?;
+
+        let mut applied = 0;
+        let lag_secs = segments
+            .first()
+            .map(|s| now_secs().saturating_sub(s.created_at));
+        for segment in &segments {
+            db.apply_wal(&segment.records).await?;
+            applied_lsn = segment.last_lsn();
+            self.store_applied_lsn(applied_lsn)?;
+            applied += segment.records.len();
+            self.status.write().segments_applied += 1;
+        }
+
+        let primary_lsn = head.as_ref().map(|h| h.last_lsn);
+        let lag_records = primary_lsn.map(|lsn| lsn.saturating_sub(applied_lsn));
+        let mut status = self.status.write();
+        status.epoch = head.as_ref().map(|h| h.epoch());
+        status.applied_lsn = Some(applied_lsn);
+        status.primary_lsn = primary_lsn;
+        status.lag_records = lag_records;
+        status.lag_secs = match lag_records {
+            Some(0) | None => Some(0),
+            Some(_) => lag_secs.or(status.lag_secs),
+        };
+        status.last_error = None;
+        Ok(applied)
+    }
+
+    fn applied_lsn(&self) -> anyhow::Result<u64> {
+        match std::fs::read_to_string(&self.position_path) {
+            Ok(text) => Ok(text.trim().parse()?),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(0),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    fn store_applied_lsn(&self, lsn: u64) -> anyhow::Result<()> {
+        let tmp = self.position_path.with_extension("tmp");
+        std::fs::write(&tmp, lsn.to_string())?;
+        std::fs::rename(&tmp, &self.position_path)?;
+        Ok(())
+    }
+}
+
+/// GET /api/v1/replication/status
+pub async fn get_replication_status(State(state): State<AppState>) -> Json<WalReplicationStatus> {
+    Json(match state.replication.as_ref() {
+        Some(replication) => replication.status(),
+        None => WalReplicationStatus {
+            accepts_writes: true,
+            ..Default::default()
+        },
+    })
+}
+
+/// POST /api/v1/replication/promote
+///
+/// Promoting fences the primary for every tenant, so only admins may.
+pub async fn promote_standby(
+    State(state): State<AppState>,
+    Extension(auth): Extension<AuthContext>,
+) -> Result<Json<WalReplicationStatus>, ApiError> {
+    auth.require_role(Role::Admin)?;
+    let replication = state
+        .replication
+        .as_ref()
+        .ok_or_else(|| ApiError::BadRequest("WAL shipping is not configured".to_string()))?;
+    replication.promote(&state.db).await.map_err(|e| {
+        if replication.is_standby() {
+            ApiError::Internal(format!("Promotion failed: {}", e))
+        } else {
+            ApiError::BadRequest(e.to_string())
+        }
+    })?;
+    Ok(Json(replication.status()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_disabled_without_wal_dir() {
+        let data_dir = TempDir::new().unwrap();
+        let config = ReplicationConfig::default();
+        assert!(WalReplicator::new(&config, "node", data_dir.path())
+            .unwrap()
+            .is_none());
+    }
+
+    #[test]
+    fn test_standby_rejects_writes_and_resumes_position() {
+        let data_dir = TempDir::new().unwrap();
+        let wal_dir = TempDir::new().unwrap();
+        let config = ReplicationConfig {
+            wal_dir: Some(wal_dir.path().to_path_buf()),
+            standby: true,
+            ..Default::default()
+        };
+
+        let replicator = WalReplicator::new(&config, "standby", data_dir.path())
+            .unwrap()
+            .unwrap();
+        assert!(!replicator.accepts_writes());
+        assert_eq!(replicator.status().applied_lsn, Some(0));
+
+        replicator.store_applied_lsn(42).unwrap();
+        let restarted = WalReplicator::new(&config, "standby", data_dir.path())
+            .unwrap()
+            .unwrap();
+        assert_eq!(restarted.status().applied_lsn, Some(42));
+        assert_eq!(restarted.status().role, WalRole::Standby);
+    }
+}
@@ -0,0 +1,579 @@
+// Copyright 2025 AgentReplay (https://github.com/agentreplay)
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program. If not, see <https://www.gnu.org/licenses/>.
+
+//! Tool contracts - schema validation of ingested tool calls
+//!
+//! Every stored span carrying `gen_ai.tool.name` is counted per tool (calls,
+//! errors, latency). When the tool is registered, its
+//! `gen_ai.tool.call.arguments` are validated against the definition's
+//! `input_schema`; mismatches are kept as contract violations and surfaced
+//! as `tool_contract_violation` insights.
+//!
+//! Served at:
+//! - `GET/POST /api/v1/tools` - list / register tool definitions
+//! - `GET /api/v1/tools/stats` - per-tool call, error and latency stats
+//! - `GET /api/v1/tools/violations` - recent contract violations
+
+use std::collections::{HashMap, VecDeque};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+use agentreplay_core::clock::now_us;
+use agentreplay_core::insights::{Insight, InsightType, Severity};
+use agentreplay_core::{AgentFlowEdge, SpanType, ToolRegistration, UnifiedToolDefinition};
+use axum::{
+    extract::{Query, State},
+    Json,
+};
+use dashmap::DashMap;
+use jsonschema::JSONSchema;
+use parking_lot::Mutex;
+use serde::{Deserialize, Serialize};
+
+use super::{ToolRegistry, ToolRegistryError};
+use crate::api::query::ApiError;
+use crate::api::AppState;
+use crate::otel_genai::attrs;
+
+/// Registered definitions, persisted in the data directory
+pub const TOOL_REGISTRY_FILE: &str = "tool_registry.json";
+
+/// Violations kept in memory (oldest dropped first)
+const MAX_VIOLATIONS: usize = 1_000;
+
+/// Latency samples kept per tool for percentiles
+const LATENCY_SAMPLES: usize = 512;
+
+/// A tool call whose arguments did not match the registered schema
+#[derive(Debug, Clone, Serialize)]
+pub struct ToolContractViolation {
+    #[serde(skip)]
+    pub edge_id: u128,
+    pub span_id: String,
+    pub tool_id: String,
+    pub tool_name: String,
+    pub errors: Vec<String>,
+    pub timestamp_us: u64,
+}
+
+/// Per-tool call statistics since server start
+#[derive(Debug, Clone, Serialize)]
+pub struct ToolStats {
+    pub tool_name: String,
+    pub registered: bool,
+    pub calls: u64,
+    pub errors: u64,
+    pub error_rate: f64,
+    pub contract_violations: u64,
+    pub avg_latency_ms: f64,
+    pub p95_latency_ms: f64,
+    pub max_latency_ms: f64,
+}
+
+#[derive(Debug, Default)]
+struct ToolCounters {
+    calls: u64,
+    errors: u64,
+    violations: u64,
+    total_latency_us: u64,
+    max_latency_us: u64,
+    recent_latencies_us: VecDeque<u64>,
+}
+
+/// Validates tool calls against registered definitions and tracks per-tool stats
+pub struct ToolContractMonitor {
+    registry: ToolRegistry,
+    storage_path: Option<PathBuf>,
+    /// Compiled input schemas by tool_id
+    validators: DashMap<String, Arc<JSONSchema>>,
+    counters: Mutex<HashMap<String, ToolCounters>>,
+    violations: Mutex<VecDeque<ToolContractViolation>>,
+}
+
+impl Default for ToolContractMonitor {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl ToolContractMonitor {
+    /// In-memory monitor; definitions are lost on restart
+    pub fn new() -> Self {
+        Self {
+            registry: ToolRegistry::default(),
+            storage_path: None,
+            validators: DashMap::new(),
+            counters: Mutex::new(HashMap::new()),
+            violations: Mutex::new(VecDeque::new()),
+        }
+    }
+
+    /// Monitor persisting definitions to `path`, loading any already saved
+    pub fn with_storage(path: impl AsRef<Path>) -> Self {
+        let mut monitor = Self::new();
+        let path = path.as_ref().to_path_buf();
+
+        match std::fs::read(&path) {
+            Ok(bytes) => match serde_json::from_slice::<Vec<UnifiedToolDefinition>>(&bytes) {
+                Ok(tools) => {
+                    for tool in tools {
+                        if let Err(e) = monitor.registry.register(tool) {
+                            tracing::warn!("Skipping stored tool definition: {}", e);
+                        }
+                    }
+                }
+                Err(e) => tracing::warn!("Failed to parse {:?}: {}", path, e),
+            },
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => {}
+            Err(e) => tracing::warn!("Failed to read {:?}: {}", path, e),
+        }
+
+        monitor.storage_path = Some(path);
+        monitor
+    }
+
+    pub fn registry(&self) -> &ToolRegistry {
+        &self.registry
+    }
+
+    /// Register a definition after checking its input schema compiles
+    pub fn register(
+        &self,
+        tool: UnifiedToolDefinition,
+    ) -> Result<ToolRegistration, ToolRegistryError> {
+        let validator = JSONSchema::compile(&tool.input_schema).map_err(|e| {
+            ToolRegistryError::InvalidDefinition {
+                reason: format!("input_schema: {}", e),
+            }
+        })?;
+        let tool_id = tool.tool_id.clone();
+        let registration = self.registry.register(tool)?;
+        self.validators.insert(tool_id, Arc::new(validator));
+
+        if let Err(e) = self.persist() {
+            tracing::warn!("Failed to persist tool registry: {}", e);
+        }
+        Ok(registration)
+    }
+
+    fn persist(&self) -> std::io::Result<()> {
+        let Some(path) = &self.storage_path else {
+            return Ok(());
+        };
+        let tools: Vec<UnifiedToolDefinition> = self
+            .registry
+            .namespaces()
+            .iter()
+            .flat_map(|namespace| self.registry.list_namespace(namespace))
+            .collect();
+
+        let tmp = path.with_extension("json.tmp");
+        std::fs::write(&tmp, serde_json::to_vec_pretty(&tools)?)?;
+        std::fs::rename(tmp, path)
+    }
+
+    /// Latest registered definition with this name, in any namespace
+    fn find_tool(&self, name: &str) -> Option<UnifiedToolDefinition> {
+        self.registry
+            .lookup(None, name, None)
+            .map(|found| found.tool)
+            .ok()
+            .or_else(|| {
+                self.registry
+                    .list_latest()
+                    .into_iter()
+                    .find(|tool| tool.name == name)
+            })
+    }
+
+    fn validator(&self, tool: &UnifiedToolDefinition) -> Option<Arc<JSONSchema>> {
+        if let Some(validator) = self.validators.get(&tool.tool_id) {
+            return Some(validator.clone());
+        }
+        let validator = Arc::new(JSONSchema::compile(&tool.input_schema).ok()?);
+        self.validators
+            .insert(tool.tool_id.clone(), validator.clone());
+        Some(validator)
+    }
+
+    /// Problems with `arguments` for `tool`; empty when they satisfy the schema
+    pub fn check_arguments(&self, tool: &UnifiedToolDefinition, arguments: &str) -> Vec<String> {
+        let value: serde_json::Value = match serde_json::from_str(arguments) {
+            Ok(value) => value,
+            Err(e) => return vec![format!("arguments are not valid JSON: {}", e)],
+        };
+        let Some(validator) = self.validator(tool) else {
+            return Vec::new();
+        };
+
+        let result = validator.validate(&value);
+        match result {
+            Ok(()) => Vec::new(),
+            Err(errors) => errors
+                .map(|e| {
+                    let path = e.instance_path.to_string();
+                    if path.is_empty() {
+                        e.to_string()
+                    } else {
+                        format!("{}: {}", path, e)
+                    }
+                })
+                .collect(),
+        }
+    }
+
+    /// Count a stored span against its tool and check its arguments
+    pub fn record(
+        &self,
+        edge: &AgentFlowEdge,
+        attributes: &HashMap<String, String>,
+    ) -> Option<ToolContractViolation> {
+        let name = attributes.get(attrs::GEN_AI_TOOL_NAME)?;
+        let is_error =
+            edge.get_span_type() == SpanType::Error || attributes.contains_key("error.type");
+
+        let violation = match (
+            self.find_tool(name),
+            attributes.get(attrs::GEN_AI_TOOL_CALL_ARGUMENTS),
+        ) {
+            (Some(tool), Some(arguments)) if tool.enabled => {
+                let errors = self.check_arguments(&tool, arguments);
+                (!errors.is_empty()).then(|| ToolContractViolation {
+                    edge_id: edge.edge_id,
+                    span_id: format!("{:#x}", edge.edge_id),
+                    tool_id: tool.tool_id,
+                    tool_name: name.clone(),
+                    errors,
+                    timestamp_us: edge.timestamp_us,
+                })
+            }
+            _ => None,
+        };
+
+        {
+            let mut counters = self.counters.lock();
+            let counter = counters.entry(name.clone()).or_default();
+            counter.calls += 1;
+            counter.errors += is_error as u64;
+            counter.violations += violation.is_some() as u64;
+            let latency_us = edge.duration_us as u64;
+            counter.total_latency_us += latency_us;
+            counter.max_latency_us = counter.max_latency_us.max(latency_us);
+            if counter.recent_latencies_us.len() == LATENCY_SAMPLES {
+                counter.recent_latencies_us.pop_front();
+            }
+            counter.recent_latencies_us.push_back(latency_us);
+        }
+
+        if let Some(violation) = &violation {
+            let mut violations = self.violations.lock();
+            if violations.len() == MAX_VIOLATIONS {
+                violations.pop_front();
+            }
+            violations.push_back(violation.clone());
+        }
+        violation
+    }
+
+    /// Per-tool stats, most called first
+    pub fn stats(&self) -> Vec<ToolStats> {
+        let counters = self.counters.lock();
+        let mut stats: Vec<ToolStats> = counters
+            .iter()
+            .map(|(name, c)| {
+                let mut latencies: Vec<u64> = c.recent_latencies_us.iter().copied().collect();
+                latencies.sort_unstable();
+                let p95 = latencies
+                    .get((latencies.len() * 95 / 100).min(latencies.len().saturating_sub(1)))
+                    .copied()
+                    .unwrap_or(0);
+
+                ToolStats {
+                    tool_name: name.clone(),
+                    registered: self.find_tool(name).is_some(),
+                    calls: c.calls,
+                    errors: c.errors,
+                    error_rate: c.errors as f64 / c.calls.max(1) as f64,
+                    contract_violations: c.violations,
+                    avg_latency_ms: c.total_latency_us as f64 / c.calls.max(1) as f64 / 1000.0,
+                    p95_latency_ms: p95 as f64 / 1000.0,
+                    max_latency_ms: c.max_latency_us as f64 / 1000.0,
+                }
+            })
+            .collect();
+        stats.sort_by(|a, b| {
+            b.calls
+                .cmp(&a.calls)
+                .then_with(|| a.tool_name.cmp(&b.tool_name))
+        });
+        stats
+    }
+
+    /// Violations at or after `since_us`, newest first
+    pub fn violations(
+        &self,
+        since_us: u64,
+        tool: Option<&str>,
+        limit: usize,
+    ) -> Vec<ToolContractViolation> {
+        self.violations
+            .lock()
+            .iter()
+            .rev()
+            .filter(|v| v.timestamp_us >= since_us)
+            .filter(|v| tool.map_or(true, |t| v.tool_name == t))
+            .take(limit)
+            .cloned()
+            .collect()
+    }
+
+    /// One `tool_contract_violation` insight per tool with violations in the window
+    pub fn insights(&self, window_start: u64, window_end: u64) -> Vec<Insight> {
+        let mut by_tool: HashMap<String, Vec<ToolContractViolation>> = HashMap::new();
+        for violation in self.violations.lock().iter() {
+            if (window_start..=window_end).contains(&violation.timestamp_us) {
+                by_tool
+                    .entry(violation.tool_name.clone())
+                    .or_default()
+                    .push(violation.clone());
+            }
+        }
+
+        let mut insights: Vec<Insight> = by_tool
+            .into_iter()
+            .map(|(tool_name, violations)| {
+                let count = violations.len();
+                let sample_error = violations
+                    .last()
+                    .and_then(|v| v.errors.first().cloned())
+                    .unwrap_or_default();
+                let severity = match count {
+                    c if c >= 10 => Severity::High,
+                    c if c >= 3 => Severity::Medium,
+                    _ => Severity::Low,
+                };
+
+                Insight {
+                    id: format!("tool-contract-{}-{}", tool_name, window_end),
+                    summary: format!(
+                        "{} call(s) to '{}' violated its argument schema",
+                        count, tool_name
+                    ),
+                    description: format!(
+                        "Arguments sent to tool '{}' did not match its registered input \
+                         schema {} time(s). Latest error: {}",
+                        tool_name, count, sample_error
+                    ),
+                    insight_type: InsightType::ToolContractViolation {
+                        tool_name,
+                        violation_count: count,
+                        sample_error,
+                    },
+                    severity,
+                    confidence: 1.0,
+                    related_ids: violations.iter().map(|v| v.edge_id).collect(),
+                    metadata: HashMap::new(),
+                    generated_at: window_end,
+                    window_start,
+                    window_end,
+                }
+            })
+            .collect();
+        insights.sort_by(|a, b| b.severity.cmp(&a.severity).then_with(|| a.id.cmp(&b.id)));
+        insights
+    }
+}
+
+// ============================================================================
+// API Handlers
+// ============================================================================
+
+#[derive(Debug, Deserialize)]
+pub struct ViolationsQuery {
+    /// Look-back window in seconds (default: 24 hours)
+    #[serde(default = "default_since_seconds")]
+    pub since_seconds: u64,
+    pub tool: Option<String>,
+    #[serde(default = "default_limit")]
+    pub limit: usize,
+}
+
+fn default_since_seconds() -> u64 {
+    86_400
+}
+
+fn default_limit() -> usize {
+    100
+}
+
+#[derive(Debug, Serialize)]
+pub struct ToolListResponse {
+    pub tools: Vec<UnifiedToolDefinition>,
+    pub total: usize,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ToolStatsResponse {
+    pub tools: Vec<ToolStats>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ViolationsResponse {
+    pub violations: Vec<ToolContractViolation>,
+    pub total: usize,
+}
+
+/// GET /api/v1/tools
+pub async fn list_tools(State(state): State<AppState>) -> Json<ToolListResponse> {
+    let mut tools = state.tool_contracts.registry().list_latest();
+    tools.sort_by(|a, b| a.tool_id.cmp(&b.tool_id));
+    let total = tools.len();
+    Json(ToolListResponse { tools, total })
+}
+
+/// POST /api/v1/tools
+pub async fn register_tool(
+    State(state): State<AppState>,
+    Json(tool): Json<UnifiedToolDefinition>,
+) -> Result<Json<ToolRegistration>, ApiError> {
+    state
+        .tool_contracts
+        .register(tool)
+        .map(Json)
+        .map_err(|e| ApiError::BadRequest(e.to_string()))
+}
+
+/// GET /api/v1/tools/stats
+pub async fn get_tool_stats(State(state): State<AppState>) -> Json<ToolStatsResponse> {
+    Json(ToolStatsResponse {
+        tools: state.tool_contracts.stats(),
+    })
+}
+
+/// GET /api/v1/tools/violations
+pub async fn get_tool_violations(
+    State(state): State<AppState>,
+    Query(query): Query<ViolationsQuery>,
+) -> Json<ViolationsResponse> {
+    let now_us = now_us();
+    let since_us = now_us.saturating_sub(query.since_seconds * 1_000_000);
+
+    let violations = state
+        .tool_contracts
+        .violations(since_us, query.tool.as_deref(), query.limit);
+    let total = violations.len();
+    Json(ViolationsResponse { violations, total })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use agentreplay_core::{ToolKind, ToolVersion};
+
+    fn weather_tool() -> UnifiedToolDefinition {
+        UnifiedToolDefinition::new(
+            "default",
+            "get_weather",
+            ToolVersion::new(1, 0, 0),
+            ToolKind::Native {
+                handler_id: "weather".to_string(),
+            },
+            serde_json::json!({
+                "type": "object",
+                "properties": { "city": { "type": "string" } },
+                "required": ["city"]
+            }),
+        )
+    }
+
+    fn tool_span(edge_id: u128, arguments: &str) -> (AgentFlowEdge, HashMap<String, String>) {
+        let mut edge = AgentFlowEdge::new(1, 0, 1, 1, SpanType::ToolCall, 0);
+        edge.edge_id = edge_id;
+        edge.duration_us = 2_000;
+        let attributes = HashMap::from([
+            (
+                attrs::GEN_AI_TOOL_NAME.to_string(),
+                "get_weather".to_string(),
+            ),
+            (
+                attrs::GEN_AI_TOOL_CALL_ARGUMENTS.to_string(),
+                arguments.to_string(),
+            ),
+        ]);
+        (edge, attributes)
+    }
+
+    #[test]
+    fn test_arguments_validated_against_schema() {
+        let monitor = ToolContractMonitor::new();
+        monitor.register(weather_tool()).unwrap();
+
+        let (edge, attributes) = tool_span(1, r#"{"city":"Paris"}"#);
+        assert!(monitor.record(&edge, &attributes).is_none());
+
+        let (edge, attributes) = tool_span(2, r#"{"city":42}"#);
+        let violation = monitor.record(&edge, &attributes).unwrap();
+        assert_eq!(violation.span_id, "0x2");
+        assert!(violation.errors[0].starts_with("/city"));
+
+        let (edge, attributes) = tool_span(3, "{not json");
+        let violation = monitor.record(&edge, &attributes).unwrap();
+        assert!(violation.errors[0].contains("not valid JSON"));
+
+        let stats = monitor.stats();
+        assert_eq!(stats.len(), 1);
+        assert!(stats[0].registered);
+        assert_eq!(stats[0].calls, 3);
+        assert_eq!(stats[0].contract_violations, 2);
+        assert_eq!(stats[0].avg_latency_ms, 2.0);
+
+        let insights = monitor.insights(0, u64::MAX);
+        assert_eq!(insights.len(), 1);
+        assert!(matches!(
+            insights[0].insight_type,
+            InsightType::ToolContractViolation {
+                violation_count: 2,
+                ..
+            }
+        ));
+        assert_eq!(insights[0].related_ids, vec![2, 3]);
+    }
+
+    #[test]
+    fn test_unregistered_tools_are_only_counted() {
+        let monitor = ToolContractMonitor::new();
+        let (edge, attributes) = tool_span(1, "{not json");
+        assert!(monitor.record(&edge, &attributes).is_none());
+        assert!(!monitor.stats()[0].registered);
+    }
+
+    #[test]
+    fn test_invalid_schema_rejected_and_registry_persisted() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join(TOOL_REGISTRY_FILE);
+
+        let monitor = ToolContractMonitor::with_storage(&path);
+        let mut bad = weather_tool();
+        bad.input_schema = serde_json::json!({ "type": 12 });
+        assert!(matches!(
+            monitor.register(bad),
+            Err(ToolRegistryError::InvalidDefinition { .. })
+        ));
+        monitor.register(weather_tool()).unwrap();
+
+        let reloaded = ToolContractMonitor::with_storage(&path);
+        assert_eq!(reloaded.registry().list_latest().len(), 1);
+    }
+}
@@ -57,11 +57,16 @@
 //! 3. **Rate Limiting**: Implements token bucket algorithm with hierarchical limits
 //!    (global → per-kind → per-tool).
 
+mod contracts;
 mod executor;
 mod mcp_adapter;
 mod native_handlers;
 mod registry;
 
+pub use contracts::{
+    get_tool_stats, get_tool_violations, list_tools, register_tool, ToolContractMonitor,
+    ToolContractViolation, ToolStats, TOOL_REGISTRY_FILE,
+};
 pub use executor::{ToolExecutor, ToolExecutorConfig};
 pub use mcp_adapter::McpToolAdapter;
 pub use native_handlers::{NativeHandler, NativeHandlerRegistry};
@@ -0,0 +1,604 @@
+// Copyright 2025 AgentReplay (https://github.com/agentreplay)
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program. If not, see <https://www.gnu.org/licenses/>.
+
+//! Provider API key vault
+//!
+//! Tenant admins store provider API keys once under an alias. Keys are
+//! sealed with AES-256-GCM under the server's master key (see
+//! [`VaultConfig`]) before they reach storage, bound to their tenant and
+//! alias so a ciphertext cannot be replayed under another name.
+//!
+//! The LLM gateway (`/api/v1/chat`), the LLM-judge evaluators and trace
+//! replay accept a `key_alias` and resolve it here instead of using the
+//! server-wide provider keys. Every call made with a vault key is recorded
+//! as a [`KeyUsageRecord`], which `GET /api/v1/analytics/cost/keys` rolls
+//! up per alias.
+
+use agentreplay_core::clock::now_us;
+use std::collections::BTreeMap;
+use std::io::Write;
+use std::path::Path;
+
+use aes_gcm::aead::{Aead, KeyInit, Payload};
+use aes_gcm::{Aes256Gcm, Key, Nonce};
+use agentreplay_query::Agentreplay;
+use agentreplay_storage::{KeyUsageRecord, ProviderKeyRecord};
+use anyhow::Context;
+use axum::{
+    extract::{Path as UrlPath, Query, State},
+    http::StatusCode,
+    Extension, Json,
+};
+use base64::{engine::general_purpose::STANDARD as BASE64, Engine};
+use serde::{Deserialize, Serialize};
+use tracing::{info, warn};
+
+use crate::api::query::ApiError;
+use crate::api::AppState;
+use crate::auth::{AuthContext, Role};
+use crate::config::VaultConfig;
+use crate::otel_genai::{GenAIPayload, ModelPricing};
+
+/// Providers whose keys the vault accepts
+pub const VAULT_PROVIDERS: &[&str] = &["openai", "anthropic", "deepseek"];
+
+const NONCE_LEN: usize = 12;
+const MAX_ALIAS_LEN: usize = 64;
+const DEFAULT_LOOKBACK_US: u64 = 30 * 24 * 3_600_000_000; // 30 days
+
+/// A vault key decrypted for one provider call
+#[derive(Clone)]
+pub struct ResolvedKey {
+    pub alias: String,
+    pub provider: String,
+    pub api_key: String,
+    pub base_url: Option<String>,
+}
+
+impl std::fmt::Debug for ResolvedKey {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("ResolvedKey")
+            .field("alias", &self.alias)
+            .field("provider", &self.provider)
+            .field("api_key", &"<redacted>")
+            .field("base_url", &self.base_url)
+            .finish()
+    }
+}
+
+/// Seals and opens provider keys under the server's master key
+pub struct KeyVault {
+    cipher: Aes256Gcm,
+}
+
+impl KeyVault {
+    pub fn new(master_key: &[u8; 32]) -> Self {
+        Self {
+            cipher: Aes256Gcm::new(Key::<Aes256Gcm>::from_slice(master_key)),
+        }
+    }
+
+    /// Vault using the configured master key, else the key stored in the
+    /// key file (generated on first start)
+    pub fn open(config: &VaultConfig, data_dir: &Path) -> anyhow::Result<Self> {
+        if let Some(encoded) = &config.master_key {
+            return Ok(Self::new(&decode_master_key(encoded)?));
+        }
+
+        let path = config.key_file(data_dir);
+        if path.exists() {
+            let encoded = std::fs::read_to_string(&path)
+                .with_context(|| format!("Failed to read vault key {:?}", path))?;
+            return Ok(Self::new(&decode_master_key(encoded.trim())?));
+        }
+
+        let master_key: [u8; 32] = rand::random();
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        // Created owner-only, so the key is never readable by others, not
+        // even briefly
+        let mut options = std::fs::OpenOptions::new();
+        options.write(true).create_new(true);
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::OpenOptionsExt;
+            options.mode(0o600);
+        }
+        options
+            .open(&path)
+            .and_then(|mut file| file.write_all(BASE64.encode(master_key).as_bytes()))
+            .with_context(|| format!("Failed to write vault key {:?}", path))?;
+        info!("Generated vault master key at {:?}", path);
+        Ok(Self::new(&master_key))
+    }
+
+    /// Associated data binding a ciphertext to its tenant and alias
+    fn aad(tenant_id: u64, alias: &str) -> Vec<u8> {
+        format!("{:016x}/{}", tenant_id, alias).into_bytes()
+    }
+
+    /// Encrypt a key for storage; `created_at` is kept when rotating
+    pub fn seal(
+        &self,
+        tenant_id: u64,
+        alias: &str,
+        provider: &str,
+        api_key: &str,
+        base_url: Option<String>,
+        created_at: Option<u64>,
+    ) -> anyhow::Result<ProviderKeyRecord> {
+        let nonce: [u8; NONCE_LEN] = rand::random();
+        let aad = Self::aad(tenant_id, alias);
+        let ciphertext = self
+            .cipher
+            .encrypt(
+                Nonce::from_slice(&nonce),
+                Payload {
+                    msg: api_key.as_bytes(),
+                    aad: &aad,
+                },
+            )
+            .map_err(|_| anyhow::anyhow!("Failed to encrypt key {}", alias))?;
+
+        let now = now_us();
+        Ok(ProviderKeyRecord {
+            tenant_id,
+            alias: alias.to_string(),
+            provider: provider.to_string(),
+            base_url,
+            nonce: nonce.to_vec(),
+            ciphertext,
+            key_hint: key_hint(api_key),
+            created_at: created_at.unwrap_or(now),
+            updated_at: now,
+        })
+    }
+
+    /// Decrypt a stored key
+    pub fn unseal(&self, record: &ProviderKeyRecord) -> anyhow::Result<ResolvedKey> {
+        if record.nonce.len() != NONCE_LEN {
+            anyhow::bail!("Key {} has a malformed nonce", record.alias);
+        }
+        let aad = Self::aad(record.tenant_id, &record.alias);
+        let plaintext = self
+            .cipher
+            .decrypt(
+                Nonce::from_slice(&record.nonce),
+                Payload {
+                    msg: &record.ciphertext,
+                    aad: &aad,
+                },
+            )
+            .map_err(|_| {
+                anyhow::anyhow!(
+                    "Key {} cannot be decrypted (was the vault master key changed?)",
+                    record.alias
+                )
+            })?;
+        Ok(ResolvedKey {
+            alias: record.alias.clone(),
+            provider: record.provider.clone(),
+            api_key: String::from_utf8(plaintext)?,
+            base_url: record.base_url.clone(),
+        })
+    }
+
+    /// Look up and decrypt a tenant's key by alias
+    pub fn resolve(
+        &self,
+        db: &Agentreplay,
+        tenant_id: u64,
+        alias: &str,
+    ) -> Result<ResolvedKey, ApiError> {
+        let record = db
+            .get_provider_key(tenant_id, alias)
+            .map_err(|e| ApiError::Internal(e.to_string()))?
+            .ok_or_else(|| ApiError::NotFound(format!("No vault key named '{}'", alias)))?;
+        self.unseal(&record)
+            .map_err(|e| ApiError::Internal(e.to_string()))
+    }
+
+    /// Attribute a provider call made with a vault key
+    ///
+    /// Failures are logged: losing a usage record must not fail the call.
+    pub fn record_usage(
+        &self,
+        db: &Agentreplay,
+        tenant_id: u64,
+        key: &ResolvedKey,
+        source: &str,
+        model: &str,
+        input_tokens: u64,
+        output_tokens: u64,
+    ) {
+        let usage = KeyUsageRecord {
+            tenant_id,
+            alias: key.alias.clone(),
+            source: source.to_string(),
+            provider: key.provider.clone(),
+            model: model.to_string(),
+            input_tokens,
+            output_tokens,
+            cost_usd: usage_cost(&key.provider, model, input_tokens, output_tokens),
+            timestamp: now_us(),
+        };
+        if let Err(e) = db.put_key_usage(&usage) {
+            warn!(alias = %key.alias, error = %e, "Failed to record vault key usage");
+        }
+    }
+}
+
+pub(crate) fn decode_master_key(encoded: &str) -> anyhow::Result<[u8; 32]> {
+    let bytes = BASE64
+        .decode(encoded)
+        .context("Vault master key is not valid base64")?;
+    bytes
+        .try_into()
+        .map_err(|_| anyhow::anyhow!("Vault master key must be 32 bytes"))
+}
+
+/// Last four characters of a key, enough to tell keys apart
+fn key_hint(api_key: &str) -> String {
+    let chars: Vec<char> = api_key.chars().collect();
+    if chars.len() <= 8 {
+        return "****".to_string();
+    }
+    format!("...{}", chars[chars.len() - 4..].iter().collect::<String>())
+}
+
+fn usage_cost(provider: &str, model: &str, input_tokens: u64, output_tokens: u64) -> f64 {
+    let payload = GenAIPayload {
+        input_tokens: Some(input_tokens.min(u32::MAX as u64) as u32),
+        output_tokens: Some(output_tokens.min(u32::MAX as u64) as u32),
+        ..Default::default()
+    };
+    payload.calculate_cost(&ModelPricing::for_model(provider, model))
+}
+
+fn validate_alias(alias: &str) -> Result<(), ApiError> {
+    let valid = !alias.is_empty()
+        && alias.len() <= MAX_ALIAS_LEN
+        && alias
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.'));
+    if valid {
+        Ok(())
+    } else {
+        Err(ApiError::BadRequest(format!(
+            "Key alias must be 1-{} characters of letters, digits, '-', '_' or '.'",
+            MAX_ALIAS_LEN
+        )))
+    }
+}
+
+// ============================================================================
+// API Handlers
+// ============================================================================
+
+#[derive(Debug, Deserialize)]
+pub struct PutProviderKeyRequest {
+    pub provider: String,
+    pub api_key: String,
+    /// OpenAI-compatible endpoint to use instead of api.openai.com
+    #[serde(default)]
+    pub base_url: Option<String>,
+}
+
+/// A vault key as shown to admins - never the secret itself
+#[derive(Debug, Serialize)]
+pub struct ProviderKeyInfo {
+    pub alias: String,
+    pub provider: String,
+    pub base_url: Option<String>,
+    pub key_hint: String,
+    pub created_at: u64,
+    pub updated_at: u64,
+}
+
+impl From<ProviderKeyRecord> for ProviderKeyInfo {
+    fn from(record: ProviderKeyRecord) -> Self {
+        Self {
+            alias: record.alias,
+            provider: record.provider,
+            base_url: record.base_url,
+            key_hint: record.key_hint,
+            created_at: record.created_at,
+            updated_at: record.updated_at,
+        }
+    }
+}
+
+/// GET /api/v1/vault/keys
+pub async fn list_provider_keys(
+    State(state): State<AppState>,
+    Extension(auth): Extension<AuthContext>,
+) -> Result<Json<Vec<ProviderKeyInfo>>, ApiError> {
+    let keys = state
+        .db
+        .list_provider_keys(auth.tenant_id)
+        .map_err(|e| ApiError::Internal(e.to_string()))?;
+    Ok(Json(keys.into_iter().map(ProviderKeyInfo::from).collect()))
+}
+
+/// PUT /api/v1/vault/keys/:alias
+///
+/// Store a provider key under an alias, or rotate the key behind it.
+pub async fn put_provider_key(
+    State(state): State<AppState>,
+    Extension(auth): Extension<AuthContext>,
+    UrlPath(alias): UrlPath<String>,
+    Json(req): Json<PutProviderKeyRequest>,
+) -> Result<Json<ProviderKeyInfo>, ApiError> {
+    auth.require_role(Role::Admin)?;
+    validate_alias(&alias)?;
+    let provider = req.provider.to_lowercase();
+    if !VAULT_PROVIDERS.contains(&provider.as_str()) {
+        return Err(ApiError::BadRequest(format!(
+            "Unsupported provider '{}' (expected one of {})",
+            req.provider,
+            VAULT_PROVIDERS.join(", ")
+        )));
+    }
+    if req.api_key.trim().is_empty() {
+        return Err(ApiError::BadRequest("api_key must not be empty".into()));
+    }
+    if let Some(base_url) = &req.base_url {
+        if provider != "openai" {
+            return Err(ApiError::BadRequest(
+                "base_url is only supported for openai keys".into(),
+            ));
+        }
+        url::Url::parse(base_url)
+            .map_err(|e| ApiError::BadRequest(format!("Invalid base_url: {}", e)))?;
+    }
+
+    let existing = state
+        .db
+        .get_provider_key(auth.tenant_id, &alias)
+        .map_err(|e| ApiError::Internal(e.to_string()))?;
+    let record = state
+        .vault
+        .seal(
+            auth.tenant_id,
+            &alias,
+            &provider,
+            req.api_key.trim(),
+            req.base_url,
+            existing.map(|key| key.created_at),
+        )
+        .map_err(|e| ApiError::Internal(e.to_string()))?;
+    state
+        .db
+        .put_provider_key(&record)
+        .map_err(|e| ApiError::Internal(e.to_string()))?;
+
+    info!(tenant_id = auth.tenant_id, alias = %alias, provider = %provider, "Stored vault key");
+    Ok(Json(record.into()))
+}
+
+/// DELETE /api/v1/vault/keys/:alias
+pub async fn delete_provider_key(
+    State(state): State<AppState>,
+    Extension(auth): Extension<AuthContext>,
+    UrlPath(alias): UrlPath<String>,
+) -> Result<StatusCode, ApiError> {
+    auth.require_role(Role::Admin)?;
+    let deleted = state
+        .db
+        .delete_provider_key(auth.tenant_id, &alias)
+        .map_err(|e| ApiError::Internal(e.to_string()))?;
+    if !deleted {
+        return Err(ApiError::NotFound(format!(
+            "No vault key named '{}'",
+            alias
+        )));
+    }
+    info!(tenant_id = auth.tenant_id, alias = %alias, "Deleted vault key");
+    Ok(StatusCode::NO_CONTENT)
+}
+
+#[derive(Debug, Deserialize)]
+pub struct KeyCostParams {
+    pub start_ts: Option<u64>,
+    pub end_ts: Option<u64>,
+}
+
+/// Usage of a group of provider calls
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct KeyUsageStats {
+    pub requests: u64,
+    pub input_tokens: u64,
+    pub output_tokens: u64,
+    pub cost_usd: f64,
+}
+
+impl KeyUsageStats {
+    fn record(&mut self, usage: &KeyUsageRecord) {
+        self.requests += 1;
+        self.input_tokens += usage.input_tokens;
+        self.output_tokens += usage.output_tokens;
+        self.cost_usd += usage.cost_usd;
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub struct KeyCost {
+    pub alias: String,
+    pub provider: String,
+    #[serde(flatten)]
+    pub total: KeyUsageStats,
+    /// Per calling subsystem ("gateway", "evaluator", "replay")
+    pub by_source: BTreeMap<String, KeyUsageStats>,
+    pub by_model: BTreeMap<String, KeyUsageStats>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct KeyCostReport {
+    pub start_ts: u64,
+    pub end_ts: u64,
+    pub total_cost_usd: f64,
+    /// Most expensive first
+    pub keys: Vec<KeyCost>,
+}
+
+fn key_cost_report(usage: &[KeyUsageRecord], start_ts: u64, end_ts: u64) -> KeyCostReport {
+    let mut keys: BTreeMap<String, KeyCost> = BTreeMap::new();
+    for record in usage {
+        let key = keys.entry(record.alias.clone()).or_insert_with(|| KeyCost {
+            alias: record.alias.clone(),
+            provider: record.provider.clone(),
+            total: KeyUsageStats::default(),
+            by_source: BTreeMap::new(),
+            by_model: BTreeMap::new(),
+        });
+        key.total.record(record);
+        key.by_source
+            .entry(record.source.clone())
+            .or_default()
+            .record(record);
+        key.by_model
+            .entry(record.model.clone())
+            .or_default()
+            .record(record);
+    }
+
+    let mut keys: Vec<KeyCost> = keys.into_values().collect();
+    keys.sort_by(|a, b| b.total.cost_usd.total_cmp(&a.total.cost_usd));
+    KeyCostReport {
+        start_ts,
+        end_ts,
+        total_cost_usd: keys.iter().map(|key| key.total.cost_usd).sum(),
+        keys,
+    }
+}
+
+/// GET /api/v1/analytics/cost/keys
+///
+/// Provider usage and cost attributed to each vault key.
+pub async fn get_key_costs(
+    State(state): State<AppState>,
+    Extension(auth): Extension<AuthContext>,
+    Query(params): Query<KeyCostParams>,
+) -> Result<Json<KeyCostReport>, ApiError> {
+    let end_ts = params.end_ts.unwrap_or_else(now_us);
+    let start_ts = params
+        .start_ts
+        .unwrap_or(end_ts.saturating_sub(DEFAULT_LOOKBACK_US));
+    if end_ts <= start_ts {
+        return Err(ApiError::BadRequest(
+            "end_ts must be greater than start_ts".into(),
+        ));
+    }
+
+    let usage = state
+        .db
+        .list_key_usage(auth.tenant_id, start_ts, end_ts)
+        .map_err(|e| ApiError::Internal(e.to_string()))?;
+    Ok(Json(key_cost_report(&usage, start_ts, end_ts)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_seal_roundtrip() {
+        let vault = KeyVault::new(&[7; 32]);
+        let record = vault
+            .seal(1, "openai-prod", "openai", "sk-test-1234567890", None, None)
+            .unwrap();
+        assert_eq!(record.key_hint, "...7890");
+
+        let key = vault.unseal(&record).unwrap();
+        assert_eq!(key.api_key, "sk-test-1234567890");
+        assert_eq!(key.provider, "openai");
+
+        // Bound to tenant and alias
+        let mut moved = record.clone();
+        moved.alias = "other".to_string();
+        assert!(vault.unseal(&moved).is_err());
+        moved = record.clone();
+        moved.tenant_id = 2;
+        assert!(vault.unseal(&moved).is_err());
+
+        // Another master key cannot open it
+        assert!(KeyVault::new(&[8; 32]).unseal(&record).is_err());
+    }
+
+    #[test]
+    fn test_open_generates_key_file() {
+        let dir = tempfile::tempdir().unwrap();
+        let config = VaultConfig::default();
+        let record = KeyVault::open(&config, dir.path())
+            .unwrap()
+            .seal(1, "a", "openai", "sk-abcdefghijk", None, None)
+            .unwrap();
+        assert!(config.key_file(dir.path()).exists());
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            let mode = std::fs::metadata(config.key_file(dir.path()))
+                .unwrap()
+                .permissions()
+                .mode();
+            assert_eq!(mode & 0o777, 0o600);
+        }
+
+        // Reopening uses the same key
+        let reopened = KeyVault::open(&config, dir.path()).unwrap();
+        assert_eq!(reopened.unseal(&record).unwrap().api_key, "sk-abcdefghijk");
+    }
+
+    #[test]
+    fn test_key_cost_report() {
+        let usage = |alias: &str, source: &str, cost_usd: f64| KeyUsageRecord {
+            tenant_id: 1,
+            alias: alias.to_string(),
+            source: source.to_string(),
+            provider: "openai".to_string(),
+            model: "gpt-4o".to_string(),
+            input_tokens: 100,
+            output_tokens: 10,
+            cost_usd,
+            timestamp: 0,
+        };
+        let report = key_cost_report(
+            &[
+                usage("dev", "gateway", 0.1),
+                usage("prod", "gateway", 0.5),
+                usage("prod", "evaluator", 0.25),
+            ],
+            0,
+            1,
+        );
+
+        assert!((report.total_cost_usd - 0.85).abs() < 1e-9);
+        assert_eq!(report.keys[0].alias, "prod");
+        assert_eq!(report.keys[0].total.requests, 2);
+        assert_eq!(report.keys[0].total.input_tokens, 200);
+        assert_eq!(report.keys[0].by_source["evaluator"].requests, 1);
+        assert_eq!(report.keys[0].by_model["gpt-4o"].requests, 2);
+        assert_eq!(report.keys[1].alias, "dev");
+    }
+
+    #[test]
+    fn test_validate_alias() {
+        assert!(validate_alias("openai-prod_2.0").is_ok());
+        assert!(validate_alias("").is_err());
+        assert!(validate_alias("team/key").is_err());
+        assert!(validate_alias(&"a".repeat(65)).is_err());
+    }
+}
@@ -0,0 +1,477 @@
+// Copyright 2025 AgentReplay (https://github.com/agentreplay)
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program. If not, see <https://www.gnu.org/licenses/>.
+
+//! Rate-of-change alerts on per-project ingest volume
+//!
+//! A sudden spike in spans usually means a retry loop or runaway agent; a
+//! project that goes silent usually means broken instrumentation. Ingested
+//! spans are counted per project and minute, and a periodic check compares
+//! the last window with the project's baseline (see [`VolumeAlertConfig`]).
+//! Alerts fire on state changes only - spike, drop, and recovery - and go
+//! to the configured notification channels and `on_alert` scripts.
+//!
+//! Counts are kept in memory, so after a restart a project needs a few
+//! windows of history before it can alert.
+
+use std::collections::{HashMap, VecDeque};
+use std::sync::Arc;
+use std::time::Duration;
+
+use agentreplay_core::clock::now_us;
+use agentreplay_core::AgentFlowEdge;
+use axum::{extract::State, Json};
+use parking_lot::Mutex;
+use serde::Serialize;
+use tracing::{info, warn};
+
+use crate::api::query::ApiError;
+use crate::api::AppState;
+use crate::auth::AuthContext;
+use crate::config::VolumeAlertConfig;
+use crate::scripting::{dispatch_notifications, ScriptHook, ScriptNotification};
+
+const MINUTE_US: u64 = 60_000_000;
+
+/// Alerts kept for `GET /api/v1/alerts/volume`
+const MAX_RECENT_ALERTS: usize = 200;
+
+/// Baseline windows of history required before a project can alert
+const MIN_HISTORY_WINDOWS: u64 = 3;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum VolumeAlertKind {
+    Spike,
+    Drop,
+    Recovered,
+}
+
+/// Outcome of comparing a window with its baseline
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Verdict {
+    /// Baseline too small (or history too short) to judge
+    Quiet,
+    Normal,
+    Spike,
+    Drop,
+}
+
+fn classify(current: u64, baseline: f64, config: &VolumeAlertConfig) -> Verdict {
+    if baseline < config.min_baseline {
+        Verdict::Quiet
+    } else if current as f64 >= baseline * config.spike_factor {
+        Verdict::Spike
+    } else if current as f64 <= baseline * config.drop_factor {
+        Verdict::Drop
+    } else {
+        Verdict::Normal
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct VolumeAlert {
+    pub tenant_id: u64,
+    pub project_id: u16,
+    pub kind: VolumeAlertKind,
+    /// "critical" for drops, "warning" for spikes, "info" for recoveries
+    pub severity: String,
+    /// Spans in the last window
+    pub current: u64,
+    /// Average spans per window over the baseline period
+    pub baseline: f64,
+    pub window_minutes: u64,
+    pub message: String,
+    pub triggered_at: u64,
+}
+
+/// Per-minute span counts of one project
+struct ProjectVolume {
+    /// Counts per minute, oldest first; the last entry is `last_minute`
+    minutes: VecDeque<u64>,
+    last_minute: u64,
+    first_minute: u64,
+    /// Active anomaly, if any
+    state: Option<VolumeAlertKind>,
+}
+
+impl ProjectVolume {
+    fn new(minute: u64) -> Self {
+        Self {
+            minutes: VecDeque::from([0]),
+            last_minute: minute,
+            first_minute: minute,
+            state: None,
+        }
+    }
+
+    /// Extend the ring with empty minutes up to `minute`
+    fn advance(&mut self, minute: u64, capacity: usize) {
+        let missing = minute.saturating_sub(self.last_minute);
+        if missing as usize >= capacity {
+            self.minutes.clear();
+            self.minutes.push_back(0);
+        } else {
+            self.minutes.extend((0..missing).map(|_| 0));
+        }
+        self.last_minute = self.last_minute.max(minute);
+        while self.minutes.len() > capacity {
+            self.minutes.pop_front();
+        }
+    }
+
+    fn count(&self, minute: u64) -> u64 {
+        let age = self.last_minute.saturating_sub(minute) as usize;
+        if minute > self.last_minute || age >= self.minutes.len() {
+            return 0;
+        }
+        self.minutes[self.minutes.len() - 1 - age]
+    }
+
+    /// Spans in the window ending before `now_minute` and the baseline
+    /// average per window, or None while history is too short
+    fn window_and_baseline(
+        &self,
+        now_minute: u64,
+        config: &VolumeAlertConfig,
+    ) -> Option<(u64, f64)> {
+        let window = config.window_minutes;
+        let window_start = now_minute.checked_sub(window)?;
+        let baseline_start = window_start
+            .saturating_sub(config.baseline_hours * 60)
+            .max(self.first_minute);
+        let history = window_start.saturating_sub(baseline_start);
+        if history < window * MIN_HISTORY_WINDOWS.min(config.baseline_hours * 60 / window) {
+            return None;
+        }
+        let current = (window_start..now_minute).map(|m| self.count(m)).sum();
+        let baseline_total: u64 = (baseline_start..window_start).map(|m| self.count(m)).sum();
+        Some((
+            current,
+            baseline_total as f64 * window as f64 / history as f64,
+        ))
+    }
+}
+
+/// Counts ingested spans per project and raises volume alerts
+pub struct VolumeMonitor {
+    config: VolumeAlertConfig,
+    projects: Mutex<HashMap<(u64, u16), ProjectVolume>>,
+    recent: Mutex<VecDeque<VolumeAlert>>,
+}
+
+impl VolumeMonitor {
+    pub fn new(config: VolumeAlertConfig) -> Self {
+        Self {
+            config,
+            projects: Mutex::new(HashMap::new()),
+            recent: Mutex::new(VecDeque::new()),
+        }
+    }
+
+    pub fn config(&self) -> &VolumeAlertConfig {
+        &self.config
+    }
+
+    fn capacity(&self) -> usize {
+        (self.config.baseline_hours * 60 + self.config.window_minutes) as usize + 1
+    }
+
+    /// Count an ingested span
+    pub fn record(&self, edge: &AgentFlowEdge) {
+        self.record_at(edge.tenant_id, edge.project_id, now_us());
+    }
+
+    fn record_at(&self, tenant_id: u64, project_id: u16, at_us: u64) {
+        if !self.config.enabled {
+            return;
+        }
+        let minute = at_us / MINUTE_US;
+        let capacity = self.capacity();
+        let mut projects = self.projects.lock();
+        let volume = projects
+            .entry((tenant_id, project_id))
+            .or_insert_with(|| ProjectVolume::new(minute));
+        volume.advance(minute, capacity);
+        let age = (volume.last_minute - minute) as usize;
+        if let Some(index) = volume.minutes.len().checked_sub(1 + age) {
+            volume.minutes[index] += 1;
+        }
+    }
+
+    /// Compare every project's last window with its baseline, returning
+    /// alerts for projects whose state changed
+    pub fn check(&self, at_us: u64) -> Vec<VolumeAlert> {
+        let now_minute = at_us / MINUTE_US;
+        let capacity = self.capacity();
+        let mut alerts = Vec::new();
+        let mut projects = self.projects.lock();
+        projects.retain(|&(tenant_id, project_id), volume| {
+            volume.advance(now_minute, capacity);
+            let Some((current, baseline)) = volume.window_and_baseline(now_minute, &self.config)
+            else {
+                return true;
+            };
+            let verdict = classify(current, baseline, &self.config);
+            let next = match verdict {
+                Verdict::Spike => Some(VolumeAlertKind::Spike),
+                Verdict::Drop => Some(VolumeAlertKind::Drop),
+                Verdict::Normal | Verdict::Quiet => None,
+            };
+            if next != volume.state {
+                // A project that went quiet for good has not recovered
+                if next.is_some() || verdict == Verdict::Normal {
+                    let kind = next.unwrap_or(VolumeAlertKind::Recovered);
+                    alerts.push(self.alert(tenant_id, project_id, kind, current, baseline, at_us));
+                }
+                volume.state = next;
+            }
+            // Forget projects with no traffic left in the ring
+            volume.state.is_some() || volume.minutes.iter().any(|&c| c > 0)
+        });
+        drop(projects);
+
+        let mut recent = self.recent.lock();
+        for alert in &alerts {
+            if recent.len() == MAX_RECENT_ALERTS {
+                recent.pop_front();
+            }
+            recent.push_back(alert.clone());
+        }
+        alerts
+    }
+
+    fn alert(
+        &self,
+        tenant_id: u64,
+        project_id: u16,
+        kind: VolumeAlertKind,
+        current: u64,
+        baseline: f64,
+        at_us: u64,
+    ) -> VolumeAlert {
+        let window = self.config.window_minutes;
+        let (severity, message) = match kind {
+            VolumeAlertKind::Spike => (
+                "warning",
+                format!(
+                    "Ingest spike in project {}: {} spans in the last {} min, {:.1}x the baseline of {:.0}",
+                    project_id,
+                    current,
+                    window,
+                    current as f64 / baseline,
+                    baseline
+                ),
+            ),
+            VolumeAlertKind::Drop => (
+                "critical",
+                format!(
+                    "Ingest drop in project {}: {} spans in the last {} min against a baseline of {:.0}",
+                    project_id, current, window, baseline
+                ),
+            ),
+            VolumeAlertKind::Recovered => (
+                "info",
+                format!(
+                    "Ingest volume of project {} back to normal: {} spans in the last {} min (baseline {:.0})",
+                    project_id, current, window, baseline
+                ),
+            ),
+        };
+        VolumeAlert {
+            tenant_id,
+            project_id,
+            kind,
+            severity: severity.to_string(),
+            current,
+            baseline,
+            window_minutes: window,
+            message,
+            triggered_at: at_us,
+        }
+    }
+
+    /// Alerts raised for a tenant, newest first
+    pub fn recent_alerts(&self, tenant_id: u64) -> Vec<VolumeAlert> {
+        self.recent
+            .lock()
+            .iter()
+            .rev()
+            .filter(|alert| alert.tenant_id == tenant_id)
+            .cloned()
+            .collect()
+    }
+
+    /// Projects of a tenant with an active anomaly
+    pub fn active(&self, tenant_id: u64) -> Vec<(u16, VolumeAlertKind)> {
+        let mut active: Vec<(u16, VolumeAlertKind)> = self
+            .projects
+            .lock()
+            .iter()
+            .filter(|((tenant, _), _)| *tenant == tenant_id)
+            .filter_map(|(&(_, project_id), volume)| volume.state.map(|kind| (project_id, kind)))
+            .collect();
+        active.sort_by_key(|(project_id, _)| *project_id);
+        active
+    }
+
+    /// Run the periodic check, delivering alerts until the server stops
+    pub fn spawn(self: &Arc<Self>, state: AppState) {
+        let monitor = Arc::clone(self);
+        tokio::spawn(async move {
+            let period = Duration::from_secs(monitor.config.check_interval_minutes * 60);
+            let mut interval = tokio::time::interval(period);
+            interval.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+            let log = monitor.config.channels.iter().any(|c| c == "log");
+            loop {
+                interval.tick().await;
+                for alert in monitor.check(now_us()) {
+                    match alert.kind {
+                        _ if !log => {}
+                        VolumeAlertKind::Recovered => info!("{}", alert.message),
+                        _ => warn!("{}", alert.message),
+                    }
+                    state
+                        .scripts
+                        .fire(alert.tenant_id, ScriptHook::OnAlert, &alert);
+                    dispatch_notifications(
+                        ScriptHook::OnAlert,
+                        monitor
+                            .config
+                            .channels
+                            .iter()
+                            .filter(|channel| channel.as_str() != "log")
+                            .map(|channel| ScriptNotification {
+                                channel: channel.clone(),
+                                message: alert.message.clone(),
+                            })
+                            .collect(),
+                    );
+                }
+            }
+        });
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub struct ActiveVolumeAlert {
+    pub project_id: u16,
+    pub kind: VolumeAlertKind,
+}
+
+#[derive(Debug, Serialize)]
+pub struct VolumeAlertsResponse {
+    pub enabled: bool,
+    pub active: Vec<ActiveVolumeAlert>,
+    /// Newest first
+    pub alerts: Vec<VolumeAlert>,
+}
+
+/// GET /api/v1/alerts/volume
+///
+/// Projects currently spiking or silent, and recently raised volume alerts.
+pub async fn get_volume_alerts(
+    State(state): State<AppState>,
+    axum::Extension(auth): axum::Extension<AuthContext>,
+) -> Result<Json<VolumeAlertsResponse>, ApiError> {
+    let monitor = &state.volume_monitor;
+    Ok(Json(VolumeAlertsResponse {
+        enabled: monitor.config().enabled,
+        active: monitor
+            .active(auth.tenant_id)
+            .into_iter()
+            .map(|(project_id, kind)| ActiveVolumeAlert { project_id, kind })
+            .collect(),
+        alerts: monitor.recent_alerts(auth.tenant_id),
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn config() -> VolumeAlertConfig {
+        VolumeAlertConfig {
+            window_minutes: 10,
+            baseline_hours: 1,
+            ..Default::default()
+        }
+    }
+
+    /// Record `per_minute` spans in each minute of `[from, to)`
+    fn traffic(monitor: &VolumeMonitor, project_id: u16, from: u64, to: u64, per_minute: u64) {
+        for minute in from..to {
+            for _ in 0..per_minute {
+                monitor.record_at(1, project_id, minute * MINUTE_US);
+            }
+        }
+    }
+
+    #[test]
+    fn test_classify() {
+        let config = config();
+        assert_eq!(classify(100, 5.0, &config), Verdict::Quiet);
+        assert_eq!(classify(100, 20.0, &config), Verdict::Spike);
+        assert_eq!(classify(30, 20.0, &config), Verdict::Normal);
+        assert_eq!(classify(0, 20.0, &config), Verdict::Drop);
+    }
+
+    #[test]
+    fn test_spike_and_recovery() {
+        let monitor = VolumeMonitor::new(config());
+        traffic(&monitor, 7, 0, 60, 2);
+        assert!(monitor.check(60 * MINUTE_US).is_empty());
+
+        // 10x the usual rate over the last window
+        traffic(&monitor, 7, 60, 70, 20);
+        let alerts = monitor.check(70 * MINUTE_US);
+        assert_eq!(alerts.len(), 1);
+        assert_eq!(alerts[0].kind, VolumeAlertKind::Spike);
+        assert_eq!(alerts[0].current, 200);
+        assert!((alerts[0].baseline - 20.0).abs() < 1e-9);
+        assert_eq!(monitor.active(1), vec![(7, VolumeAlertKind::Spike)]);
+        // Still spiking: no repeat alert
+        traffic(&monitor, 7, 70, 71, 20);
+        assert!(monitor.check(71 * MINUTE_US).is_empty());
+
+        traffic(&monitor, 7, 71, 85, 2);
+        let alerts = monitor.check(85 * MINUTE_US);
+        assert_eq!(alerts[0].kind, VolumeAlertKind::Recovered);
+        assert!(monitor.active(1).is_empty());
+        assert_eq!(monitor.recent_alerts(1).len(), 2);
+        assert!(monitor.recent_alerts(2).is_empty());
+    }
+
+    #[test]
+    fn test_drop_to_zero() {
+        let monitor = VolumeMonitor::new(config());
+        traffic(&monitor, 3, 0, 60, 3);
+        // A quiet project never alerts
+        traffic(&monitor, 4, 0, 60, 0);
+        monitor.record_at(1, 4, 0);
+
+        let alerts = monitor.check(70 * MINUTE_US);
+        assert_eq!(alerts.len(), 1);
+        assert_eq!(alerts[0].project_id, 3);
+        assert_eq!(alerts[0].kind, VolumeAlertKind::Drop);
+        assert_eq!(alerts[0].severity, "critical");
+    }
+
+    #[test]
+    fn test_short_history_does_not_alert() {
+        let monitor = VolumeMonitor::new(config());
+        traffic(&monitor, 1, 0, 15, 5);
+        assert!(monitor.check(20 * MINUTE_US).is_empty());
+    }
+}
@@ -372,6 +372,7 @@ impl AFFWriter {
         let _payload_length = payload.len() as u32;
 
         edge.has_payload = 1;
+        edge.checksum = edge.compute_checksum();
         // Note: payload_offset in edge is relative to payload segment, not absolute file offset
         // We'll adjust this when writing the file
 
@@ -423,6 +424,19 @@ impl AFFWriter {
     }
 }
 
+/// Result of scanning an AFF file edge by edge
+#[derive(Debug, Default)]
+pub struct AFFScan {
+    /// Whether the header checksum matched
+    pub header_valid: bool,
+    /// Edges that decoded and passed their checksum
+    pub edges: Vec<AgentFlowEdge>,
+    /// Arena index and reason for each edge that failed
+    pub corrupt: Vec<(u64, String)>,
+    /// Number of edges the header promises but the file does not contain
+    pub missing: u64,
+}
+
 /// AFF file reader
 pub struct AFFReader {
     file: BufReader<File>,
@@ -491,6 +505,37 @@ impl AFFReader {
     pub fn edge_count(&self) -> u64 {
         self.header.edge_count
     }
+
+    /// Read every edge, collecting checksum failures instead of stopping
+    ///
+    /// Used to salvage the intact edges of a damaged file. A truncated arena
+    /// is reported through [`AFFScan::missing`].
+    pub fn scan(&mut self) -> Result<AFFScan> {
+        let mut scan = AFFScan {
+            header_valid: self.header.verify_checksum(),
+            ..Default::default()
+        };
+
+        self.file
+            .seek(SeekFrom::Start(self.header.edge_arena_offset))?;
+
+        for index in 0..self.header.edge_count {
+            let mut edge_bytes = [0u8; 128];
+            if let Err(e) = self.file.read_exact(&mut edge_bytes) {
+                if e.kind() == std::io::ErrorKind::UnexpectedEof {
+                    scan.missing = self.header.edge_count - index;
+                    break;
+                }
+                return Err(e.into());
+            }
+            match AgentFlowEdge::from_bytes(&edge_bytes) {
+                Ok(edge) => scan.edges.push(edge),
+                Err(e) => scan.corrupt.push((index, e.to_string())),
+            }
+        }
+
+        Ok(scan)
+    }
 }
 
 #[cfg(test)]
@@ -578,4 +623,32 @@ mod tests {
             assert!(header.payload_length > 0);
         }
     }
+
+    #[test]
+    fn test_aff_scan_reports_corrupt_edges() {
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("damaged.aff");
+
+        {
+            let mut writer = AFFWriter::new(&path).unwrap();
+            for i in 0..4 {
+                let edge = AgentFlowEdge::new(1, 0, i, i, SpanType::ToolCall, 0);
+                writer.add_edge_with_payload(edge, b"result").unwrap();
+            }
+            writer.finish().unwrap();
+        }
+
+        // Flip a byte inside the second edge
+        let mut bytes = std::fs::read(&path).unwrap();
+        bytes[256 + 128 + 40] ^= 0xff;
+        std::fs::write(&path, &bytes).unwrap();
+
+        let mut reader = AFFReader::open(&path).unwrap();
+        let scan = reader.scan().unwrap();
+        assert!(scan.header_valid);
+        assert_eq!(scan.edges.len(), 3);
+        assert_eq!(scan.corrupt.len(), 1);
+        assert_eq!(scan.corrupt[0].0, 1);
+        assert_eq!(scan.missing, 0);
+    }
 }
@@ -0,0 +1,304 @@
+// Copyright 2025 AgentReplay (https://github.com/agentreplay)
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program. If not, see <https://www.gnu.org/licenses/>.
+
+//! Cold archive tier for old traces
+//!
+//! Traces past a cutoff can be moved out of the hot store into a
+//! [`StorageBackend`] (a local directory or a mounted object store). Each
+//! archive run writes one chunk per tenant and records its time range in a
+//! manifest, so queries can tell which ranges are cold and restore them:
+//!
+//! ```text
+//! archive/manifest.json           ranges: tenant, [start, end], chunk key
+//! archive/chunks/<range id>.chunk zstd(bincode(edges + payloads)) + blake3
+//! ```
+//!
+//! Chunks are written before the manifest entry that references them, and the
+//! manifest entry is dropped before its chunk is deleted, so a crash never
+//! leaves a range pointing at a missing chunk.
+
+use crate::backend::StorageBackend;
+use agentreplay_core::clock::now_secs;
+use agentreplay_core::{AgentFlowEdge, AgentreplayError, Result};
+use parking_lot::{Mutex, RwLock};
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+
+/// Backend key of the archive manifest
+pub const ARCHIVE_MANIFEST_KEY: &str = "archive/manifest.json";
+
+/// Backend prefix of archived chunks
+pub const ARCHIVE_CHUNK_PREFIX: &str = "archive/chunks/";
+
+const CHUNK_MAGIC: &[u8; 4] = b"ARAC";
+const CHUNK_VERSION: u8 = 1;
+const CHECKSUM_LEN: usize = 32;
+
+/// A contiguous time range of one tenant's traces held in the archive
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ArchivedRange {
+    pub id: String,
+    pub tenant_id: u64,
+    /// Earliest edge timestamp in the chunk (microseconds)
+    pub start_us: u64,
+    /// Latest edge timestamp in the chunk (microseconds)
+    pub end_us: u64,
+    pub edge_count: usize,
+    pub object_key: String,
+    /// When the range was archived (seconds since epoch)
+    pub archived_at: u64,
+}
+
+impl ArchivedRange {
+    /// Whether this range holds any of the tenant's traces in `[start_us, end_us]`
+    pub fn overlaps(&self, tenant_id: u64, start_us: u64, end_us: u64) -> bool {
+        self.tenant_id == tenant_id && self.start_us <= end_us && start_us <= self.end_us
+    }
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct ArchiveManifest {
+    ranges: Vec<ArchivedRange>,
+}
+
+/// Contents of one archived range
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct ArchiveChunk {
+    pub edges: Vec<AgentFlowEdge>,
+    pub payloads: Vec<(u128, Vec<u8>)>,
+}
+
+impl ArchiveChunk {
+    /// Serialize, compress and append a blake3 checksum
+    pub fn encode(&self) -> Result<Vec<u8>> {
+        let body =
+            bincode::serialize(self).map_err(|e| AgentreplayError::Serialization(e.to_string()))?;
+        let compressed = zstd::encode_all(body.as_slice(), 3)?;
+
+        let mut buf = Vec::with_capacity(5 + compressed.len() + CHECKSUM_LEN);
+        buf.extend_from_slice(CHUNK_MAGIC);
+        buf.push(CHUNK_VERSION);
+        buf.extend_from_slice(&compressed);
+        let checksum = blake3::hash(&buf);
+        buf.extend_from_slice(checksum.as_bytes());
+        Ok(buf)
+    }
+
+    pub fn decode(bytes: &[u8]) -> Result<Self> {
+        if bytes.len() < 5 + CHECKSUM_LEN || &bytes[..4] != CHUNK_MAGIC {
+            return Err(AgentreplayError::Corruption(
+                "Not an archive chunk".to_string(),
+            ));
+        }
+        let (content, checksum) = bytes.split_at(bytes.len() - CHECKSUM_LEN);
+        if blake3::hash(content).as_bytes() != checksum {
+            return Err(AgentreplayError::Corruption(
+                "Archive chunk checksum mismatch".to_string(),
+            ));
+        }
+        if content[4] != CHUNK_VERSION {
+            return Err(AgentreplayError::Corruption(format!(
+                "Unsupported archive chunk version {}",
+                content[4]
+            )));
+        }
+
+        let body = zstd::decode_all(&content[5..])?;
+        bincode::deserialize(&body).map_err(|e| AgentreplayError::Serialization(e.to_string()))
+    }
+}
+
+/// Archive of cold trace ranges on a [`StorageBackend`]
+pub struct TraceArchive {
+    backend: Arc<dyn StorageBackend>,
+    manifest: RwLock<ArchiveManifest>,
+    /// Serializes manifest rewrites
+    write_lock: Mutex<()>,
+}
+
+impl TraceArchive {
+    /// Open the archive, loading its manifest if one exists
+    pub fn open(backend: Arc<dyn StorageBackend>) -> Result<Self> {
+        let manifest = if backend.exists(ARCHIVE_MANIFEST_KEY)? {
+            let bytes = backend.get(ARCHIVE_MANIFEST_KEY)?;
+            serde_json::from_slice(&bytes).map_err(|e| {
+                AgentreplayError::Corruption(format!("Invalid archive manifest: {}", e))
+            })?
+        } else {
+            ArchiveManifest::default()
+        };
+
+        Ok(Self {
+            backend,
+            manifest: RwLock::new(manifest),
+            write_lock: Mutex::new(()),
+        })
+    }
+
+    /// All archived ranges, oldest first
+    pub fn ranges(&self) -> Vec<ArchivedRange> {
+        let mut ranges = self.manifest.read().ranges.clone();
+        ranges.sort_by_key(|r| (r.start_us, r.tenant_id));
+        ranges
+    }
+
+    pub fn get_range(&self, id: &str) -> Option<ArchivedRange> {
+        self.manifest
+            .read()
+            .ranges
+            .iter()
+            .find(|r| r.id == id)
+            .cloned()
+    }
+
+    /// Archived ranges holding any of the tenant's traces in `[start_us, end_us]`
+    pub fn overlapping(&self, tenant_id: u64, start_us: u64, end_us: u64) -> Vec<ArchivedRange> {
+        self.manifest
+            .read()
+            .ranges
+            .iter()
+            .filter(|r| r.overlaps(tenant_id, start_us, end_us))
+            .cloned()
+            .collect()
+    }
+
+    /// Write a tenant's chunk and register its range
+    pub fn store(&self, tenant_id: u64, chunk: &ArchiveChunk) -> Result<ArchivedRange> {
+        let start_us = chunk.edges.iter().map(|e| e.timestamp_us).min();
+        let end_us = chunk.edges.iter().map(|e| e.timestamp_us).max();
+        let (Some(start_us), Some(end_us)) = (start_us, end_us) else {
+            return Err(AgentreplayError::InvalidArgument(
+                "Cannot archive an empty chunk".to_string(),
+            ));
+        };
+        if let Some(edge) = chunk.edges.iter().find(|e| e.tenant_id != tenant_id) {
+            return Err(AgentreplayError::InvalidArgument(format!(
+                "Edge {:#x} belongs to tenant {}, not {}",
+                edge.edge_id, edge.tenant_id, tenant_id
+            )));
+        }
+
+        let id = uuid::Uuid::new_v4().simple().to_string();
+        let range = ArchivedRange {
+            object_key: format!("{}{}.chunk", ARCHIVE_CHUNK_PREFIX, id),
+            id,
+            tenant_id,
+            start_us,
+            end_us,
+            edge_count: chunk.edges.len(),
+            archived_at: now_secs(),
+        };
+
+        self.backend.put(&range.object_key, &chunk.encode()?)?;
+
+        let _guard = self.write_lock.lock();
+        let mut ranges = self.manifest.read().ranges.clone();
+        ranges.push(range.clone());
+        self.save_manifest(ranges)?;
+        Ok(range)
+    }
+
+    /// Read and verify the chunk of an archived range
+    pub fn load(&self, range: &ArchivedRange) -> Result<ArchiveChunk> {
+        let bytes = self.backend.get(&range.object_key)?;
+        ArchiveChunk::decode(&bytes)
+    }
+
+    /// Forget a range (once it has been restored) and delete its chunk
+    ///
+    /// Returns false if the range is not in the archive.
+    pub fn remove(&self, id: &str) -> Result<bool> {
+        let _guard = self.write_lock.lock();
+        let mut ranges = self.manifest.read().ranges.clone();
+        let Some(pos) = ranges.iter().position(|r| r.id == id) else {
+            return Ok(false);
+        };
+        let range = ranges.remove(pos);
+        self.save_manifest(ranges)?;
+        self.backend.delete(&range.object_key)?;
+        Ok(true)
+    }
+
+    fn save_manifest(&self, ranges: Vec<ArchivedRange>) -> Result<()> {
+        let manifest = ArchiveManifest { ranges };
+        let bytes = serde_json::to_vec_pretty(&manifest)
+            .map_err(|e| AgentreplayError::Serialization(e.to_string()))?;
+        self.backend.put(ARCHIVE_MANIFEST_KEY, &bytes)?;
+        self.backend.sync()?;
+        *self.manifest.write() = manifest;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::backend::LocalFsBackend;
+    use agentreplay_core::SpanType;
+    use tempfile::TempDir;
+
+    fn chunk(tenant_id: u64, timestamps: &[u64]) -> ArchiveChunk {
+        let edges = timestamps
+            .iter()
+            .map(|&ts| {
+                let mut edge = AgentFlowEdge::new(tenant_id, 0, 1, 1, SpanType::Root, 0);
+                edge.timestamp_us = ts;
+                edge.checksum = edge.compute_checksum();
+                edge
+            })
+            .collect::<Vec<_>>();
+        let payloads = vec![(edges[0].edge_id, b"{\"input\":\"hi\"}".to_vec())];
+        ArchiveChunk { edges, payloads }
+    }
+
+    #[test]
+    fn test_chunk_roundtrip_and_checksum() {
+        let original = chunk(1, &[10, 20]);
+        let mut bytes = original.encode().unwrap();
+        assert_eq!(ArchiveChunk::decode(&bytes).unwrap(), original);
+
+        bytes[8] ^= 0xff;
+        assert!(matches!(
+            ArchiveChunk::decode(&bytes),
+            Err(AgentreplayError::Corruption(_))
+        ));
+    }
+
+    #[test]
+    fn test_store_overlap_and_remove() {
+        let dir = TempDir::new().unwrap();
+        let backend: Arc<dyn StorageBackend> = Arc::new(LocalFsBackend::new(dir.path()).unwrap());
+
+        let archive = TraceArchive::open(backend.clone()).unwrap();
+        let range = archive.store(1, &chunk(1, &[100, 200, 300])).unwrap();
+        assert_eq!(
+            (range.start_us, range.end_us, range.edge_count),
+            (100, 300, 3)
+        );
+        assert!(archive.store(2, &chunk(1, &[100])).is_err());
+
+        // Manifest survives reopening
+        let archive = TraceArchive::open(backend).unwrap();
+        assert_eq!(archive.overlapping(1, 250, 1_000), vec![range.clone()]);
+        assert!(archive.overlapping(1, 301, 1_000).is_empty());
+        assert!(archive.overlapping(2, 0, 1_000).is_empty());
+        assert_eq!(archive.load(&range).unwrap().edges.len(), 3);
+
+        assert!(archive.remove(&range.id).unwrap());
+        assert!(!archive.remove(&range.id).unwrap());
+        assert!(archive.ranges().is_empty());
+        assert!(archive.load(&range).is_err());
+    }
+}
@@ -0,0 +1,1338 @@
+// Copyright 2025 AgentReplay (https://github.com/agentreplay)
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program. If not, see <https://www.gnu.org/licenses/>.
+
+//! Online, snapshot-consistent backups
+//!
+//! Backups are taken while ingestion continues:
+//!
+//! 1. **Barrier** (under the storage write lock): pending writes are committed,
+//!    the memtable is flushed and fsynced, the WAL length is recorded and every
+//!    data file is hard-linked into a staging directory next to the data
+//!    directory. The WAL is copied up to the recorded length. Only metadata
+//!    work happens under the lock, so writers stall for milliseconds rather
+//!    than for the whole copy.
+//! 2. **Archive** (no lock): staged files are hashed (blake3) and streamed into
+//!    `<id>.tar.zst`, followed by a `BACKUP_MANIFEST.json` entry. The manifest
+//!    is also written next to the archive as `<id>.json` for listing.
+//! 3. **Validate**: a hard link shares the live file's inode, so a file
+//!    rewritten in place while archiving would leak newer data into the
+//!    backup. Linked files' sizes and mtimes are re-checked afterwards; on a
+//!    change the backup is retried, and the last attempt copies every file
+//!    under the lock instead of linking.
+//! 4. **Verify** (optional): the archive is extracted to a scratch directory,
+//!    checked file by file against the manifest, then opened and
+//!    integrity-checked, so a verified backup is known to restore.
+//!
+//! **Incremental backups** build on the newest backup of the same data
+//! directory: files whose blake3 hash is already part of the parent's
+//! snapshot are listed in the manifest as `inherited` instead of being
+//! archived again, so unchanged segments are stored once per chain. A chain
+//! (a full backup and the incrementals built on it) is capped at
+//! [`BackupOptions::max_chain_length`] backups, after which a new full backup
+//! starts the next chain. Verifying or restoring an incremental backup reads
+//! every archive in its chain; [`BackupManager::apply_retention`] only ever
+//! deletes whole chains.
+//!
+//! Restores are staged in `<data_dir>.restore-pending` (verified the same way)
+//! and swapped in by [`BackupManager::apply_pending_restore`] before the
+//! database is opened. The replaced directory is kept as
+//! `<data_dir>.pre-restore-<unix secs>`.
+
+use crate::dir_lock::{read_lock_owner, LOCK_FILE_NAME};
+use crate::replication::SnapshotFile;
+use crate::sochdb_unified::{AgentReplayStorage, IntegrityReport};
+use agentreplay_core::clock::now_us;
+use agentreplay_core::{AgentreplayError, Result};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::fs::{self, File};
+use std::io::{self, Read, Write};
+use std::path::{Component, Path, PathBuf};
+use std::time::SystemTime;
+use tracing::{info, warn};
+
+/// Archive layout version written to [`BackupMetadata::format_version`]
+pub const BACKUP_FORMAT_VERSION: u32 = 1;
+
+/// File extension of backup archives
+pub const ARCHIVE_EXTENSION: &str = "tar.zst";
+
+/// Archive entry holding the manifest (always the last entry)
+const MANIFEST_ENTRY: &str = "BACKUP_MANIFEST.json";
+
+/// SochDB write-ahead log, copied up to the barrier's WAL position
+const WAL_FILE: &str = "wal.log";
+
+/// Snapshot attempts before giving up; the last one copies under the lock
+const SNAPSHOT_ATTEMPTS: usize = 3;
+
+/// Top-level directories that hold other databases or non-data files
+///
+/// Project databases are backed up separately. On restore these are carried
+/// over from the directory being replaced.
+const EXCLUDED_DIRS: &[&str] = &[
+    "projects",
+    agentreplay_core::diagnostics::DIAGNOSTICS_DIR_NAME,
+];
+
+/// Default for [`BackupOptions::max_chain_length`]
+pub const DEFAULT_MAX_CHAIN_LENGTH: u32 = 7;
+
+/// Suffix of the staged restore directory
+const PENDING_RESTORE_SUFFIX: &str = "restore-pending";
+
+/// Description of one backup archive
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct BackupMetadata {
+    pub id: String,
+    /// Unix seconds
+    pub timestamp: u64,
+    pub timestamp_us: u64,
+    pub created_at: String,
+    /// Archive size
+    pub size_bytes: u64,
+    /// Valid trace records found by verification (0 if not verified)
+    pub edge_count: u64,
+    pub file_count: usize,
+    pub database_version: String,
+    #[serde(default)]
+    pub format_version: u32,
+    /// WAL length captured at the snapshot barrier
+    #[serde(default)]
+    pub wal_position: u64,
+    /// Whether the archive was extracted, opened and integrity-checked
+    #[serde(default)]
+    pub verified: bool,
+    /// Files stored in this archive
+    #[serde(default)]
+    pub files: Vec<SnapshotFile>,
+    /// Backup this one builds on; `None` for a full backup
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub parent: Option<String>,
+    /// Number of ancestors (0 for a full backup)
+    #[serde(default)]
+    pub chain_depth: u32,
+    /// Unchanged files restored from an ancestor archive (matched by hash)
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub inherited: Vec<SnapshotFile>,
+    /// Canonical path of the data directory that was backed up
+    #[serde(default)]
+    pub source_dir: String,
+}
+
+impl BackupMetadata {
+    /// Logical size of the backed-up files, including inherited ones
+    pub fn total_file_bytes(&self) -> u64 {
+        self.files
+            .iter()
+            .chain(&self.inherited)
+            .map(|f| f.size)
+            .sum()
+    }
+
+    pub fn is_incremental(&self) -> bool {
+        self.parent.is_some()
+    }
+
+    /// Hashes of every file in the snapshot
+    fn snapshot_hashes(&self) -> HashSet<&str> {
+        self.files
+            .iter()
+            .chain(&self.inherited)
+            .map(|f| f.hash.as_str())
+            .collect()
+    }
+}
+
+/// Options for [`BackupManager::create_backup`]
+#[derive(Debug, Clone)]
+pub struct BackupOptions {
+    /// Extract and open the archive after writing it
+    pub verify: bool,
+    /// Only archive files that changed since the newest backup of the same
+    /// data directory
+    pub incremental: bool,
+    /// Backups per chain, including the full backup; an incremental backup
+    /// that would exceed it is taken as a full backup instead
+    pub max_chain_length: u32,
+}
+
+impl Default for BackupOptions {
+    fn default() -> Self {
+        Self {
+            verify: true,
+            incremental: false,
+            max_chain_length: DEFAULT_MAX_CHAIN_LENGTH,
+        }
+    }
+}
+
+/// Which backup chains [`BackupManager::apply_retention`] keeps
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BackupRetention {
+    /// Newest chains kept per data directory (at least 1)
+    pub keep_chains: usize,
+}
+
+/// A full backup and the incremental backups built on it
+#[derive(Debug, Clone, Serialize)]
+pub struct BackupChain {
+    /// Id of the full backup (or of the missing ancestor if incomplete)
+    pub root: String,
+    pub source_dir: String,
+    /// Backup ids, oldest first
+    pub backups: Vec<String>,
+    /// Archive bytes of all backups in the chain
+    pub size_bytes: u64,
+    pub latest_timestamp_us: u64,
+    /// False if an ancestor archive is missing, making the chain unrestorable
+    pub complete: bool,
+}
+
+/// Result of checking an archive against its manifest
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct BackupVerification {
+    pub backup_id: Option<String>,
+    pub valid: bool,
+    /// Backups read, newest first (more than one for incremental backups)
+    pub chain: Vec<String>,
+    pub files_checked: usize,
+    /// In the manifest but not in the archive (or, if inherited, in no
+    /// ancestor)
+    pub missing: Vec<String>,
+    /// Size or hash differs from the manifest (`<backup id>/<path>` for
+    /// ancestors)
+    pub corrupted: Vec<String>,
+    /// In the archive but not in the manifest (`<backup id>/<path>` for
+    /// ancestors)
+    pub unexpected: Vec<String>,
+    /// Set by deep verification, which opens the extracted copy
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub integrity: Option<IntegrityReport>,
+    pub edge_count: u64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+/// Result of staging or applying a restore
+#[derive(Debug, Clone, Serialize)]
+pub struct RestoreOutcome {
+    pub backup_id: String,
+    pub data_dir: String,
+    /// Staged and waiting for [`BackupManager::apply_pending_restore`]
+    pub pending: bool,
+    /// Where the replaced data directory was moved
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub previous_dir: Option<String>,
+    pub verification: BackupVerification,
+}
+
+/// One file captured at the barrier
+struct StagedFile {
+    relative: String,
+    path: PathBuf,
+    size: u64,
+    modified: Option<SystemTime>,
+    /// Hard link to the live file (may change underneath us)
+    linked: bool,
+}
+
+/// Directory removed when dropped
+struct ScratchDir(PathBuf);
+
+impl ScratchDir {
+    fn create(path: PathBuf) -> Result<Self> {
+        if path.exists() {
+            fs::remove_dir_all(&path)?;
+        }
+        fs::create_dir_all(&path)?;
+        Ok(Self(path))
+    }
+
+    fn path(&self) -> &Path {
+        &self.0
+    }
+}
+
+impl Drop for ScratchDir {
+    fn drop(&mut self) {
+        if let Err(e) = fs::remove_dir_all(&self.0) {
+            if e.kind() != io::ErrorKind::NotFound {
+                warn!("Failed to remove scratch directory {:?}: {}", self.0, e);
+            }
+        }
+    }
+}
+
+/// Reader that hashes everything read through it
+struct HashingReader<R> {
+    inner: R,
+    hasher: blake3::Hasher,
+    bytes: u64,
+}
+
+impl<R: Read> HashingReader<R> {
+    fn new(inner: R) -> Self {
+        Self {
+            inner,
+            hasher: blake3::Hasher::new(),
+            bytes: 0,
+        }
+    }
+
+    fn finish(self) -> (u64, String) {
+        (self.bytes, self.hasher.finalize().to_hex().to_string())
+    }
+}
+
+impl<R: Read> Read for HashingReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let n = self.inner.read(buf)?;
+        self.hasher.update(&buf[..n]);
+        self.bytes += n as u64;
+        Ok(n)
+    }
+}
+
+/// Sibling of `dir` named `<dir name><suffix>`
+fn sibling(dir: &Path, suffix: &str) -> PathBuf {
+    let name = dir
+        .file_name()
+        .map(|n| n.to_string_lossy().to_string())
+        .unwrap_or_else(|| "data".to_string());
+    dir.with_file_name(format!("{}{}", name, suffix))
+}
+
+/// Where a restore for `data_dir` is staged
+pub fn pending_restore_dir(data_dir: &Path) -> PathBuf {
+    sibling(data_dir, &format!(".{}", PENDING_RESTORE_SUFFIX))
+}
+
+fn is_excluded_file(name: &str) -> bool {
+    crate::replication::EXCLUDED_FILES.contains(&name)
+        || name == LOCK_FILE_NAME
+        || name.ends_with(".tmp")
+}
+
+/// Data files under `dir`, relative to `root`, skipping `skip` (e.g. a backup
+/// directory placed inside the data directory)
+fn collect_data_files(root: &Path, dir: &Path, skip: &Path, out: &mut Vec<String>) -> Result<()> {
+    for entry in fs::read_dir(dir)? {
+        let entry = entry?;
+        let path = entry.path();
+        if path == skip {
+            continue;
+        }
+        let name = entry.file_name().to_string_lossy().to_string();
+        if entry.file_type()?.is_dir() {
+            if dir == root && EXCLUDED_DIRS.contains(&name.as_str()) {
+                continue;
+            }
+            collect_data_files(root, &path, skip, out)?;
+            continue;
+        }
+        if is_excluded_file(&name) {
+            continue;
+        }
+        if let Ok(relative) = path.strip_prefix(root) {
+            out.push(relative.to_string_lossy().replace('\\', "/"));
+        }
+    }
+    Ok(())
+}
+
+/// Copy the first `len` bytes of `source`
+fn copy_prefix(source: &Path, dest: &Path, len: u64) -> Result<()> {
+    let mut reader = File::open(source)?.take(len);
+    let mut writer = File::create(dest)?;
+    let copied = io::copy(&mut reader, &mut writer)?;
+    if copied != len {
+        return Err(AgentreplayError::Internal(format!(
+            "{:?} is shorter ({} bytes) than its recorded length {}",
+            source, copied, len
+        )));
+    }
+    Ok(())
+}
+
+/// Capture a consistent set of data files into `staging`
+///
+/// Returns the WAL position at the barrier and the staged files.
+fn stage_snapshot(
+    storage: &AgentReplayStorage,
+    staging: &Path,
+    skip: &Path,
+    link: bool,
+) -> Result<(u64, Vec<StagedFile>)> {
+    let data_dir = fs::canonicalize(storage.data_dir())?;
+    storage.with_write_barrier(|wal_position| {
+        let mut files = Vec::new();
+        collect_data_files(&data_dir, &data_dir, skip, &mut files)?;
+        files.sort();
+
+        let mut staged = Vec::with_capacity(files.len());
+        for relative in files {
+            let source = data_dir.join(&relative);
+            let dest = staging.join(&relative);
+            if let Some(parent) = dest.parent() {
+                fs::create_dir_all(parent)?;
+            }
+            let linked = if relative == WAL_FILE {
+                copy_prefix(&source, &dest, wal_position)?;
+                false
+            } else if link && fs::hard_link(&source, &dest).is_ok() {
+                true
+            } else {
+                fs::copy(&source, &dest)?;
+                false
+            };
+            let metadata = fs::metadata(&dest)?;
+            staged.push(StagedFile {
+                relative,
+                path: dest,
+                size: metadata.len(),
+                modified: metadata.modified().ok(),
+                linked,
+            });
+        }
+        Ok((wal_position, staged))
+    })
+}
+
+/// Linked files that changed after the barrier
+fn changed_files(staged: &[StagedFile]) -> Vec<String> {
+    staged
+        .iter()
+        .filter(|f| f.linked)
+        .filter(|f| match fs::metadata(&f.path) {
+            Ok(metadata) => metadata.len() != f.size || metadata.modified().ok() != f.modified,
+            Err(_) => true,
+        })
+        .map(|f| f.relative.clone())
+        .collect()
+}
+
+/// blake3 hex digest of the first `size` bytes of `path`
+fn hash_file(path: &Path, size: u64) -> Result<String> {
+    let mut reader = HashingReader::new(File::open(path)?.take(size));
+    io::copy(&mut reader, &mut io::sink())?;
+    Ok(reader.finish().1)
+}
+
+/// Staged files whose content is already in `parent`'s snapshot
+fn inherited_files(staged: &[StagedFile], parent: &BackupMetadata) -> Result<Vec<SnapshotFile>> {
+    let available = parent.snapshot_hashes();
+    let mut inherited = Vec::new();
+    for file in staged {
+        let hash = hash_file(&file.path, file.size)?;
+        if available.contains(hash.as_str()) {
+            inherited.push(SnapshotFile {
+                path: file.relative.clone(),
+                size: file.size,
+                hash,
+            });
+        }
+    }
+    Ok(inherited)
+}
+
+fn append_entry<W: Write>(
+    builder: &mut tar::Builder<W>,
+    path: &str,
+    size: u64,
+    data: impl Read,
+) -> io::Result<()> {
+    let mut header = tar::Header::new_gnu();
+    header.set_size(size);
+    header.set_mode(0o644);
+    header.set_mtime(now_us() / 1_000_000);
+    builder.append_data(&mut header, path, data)
+}
+
+/// Stream staged files not listed in `metadata.inherited` and the manifest
+/// into `archive`, filling in `metadata.files`
+fn write_archive(
+    staged: &[StagedFile],
+    metadata: &mut BackupMetadata,
+    archive: &Path,
+) -> Result<()> {
+    let encoder = zstd::stream::write::Encoder::new(File::create(archive)?, 3)?;
+    let mut builder = tar::Builder::new(encoder);
+
+    let inherited: HashSet<&str> = metadata.inherited.iter().map(|f| f.path.as_str()).collect();
+    let mut files = Vec::with_capacity(staged.len() - inherited.len());
+    for file in staged
+        .iter()
+        .filter(|f| !inherited.contains(f.relative.as_str()))
+    {
+        // Never read past the size recorded at the barrier
+        let mut reader = HashingReader::new(File::open(&file.path)?.take(file.size));
+        append_entry(&mut builder, &file.relative, file.size, &mut reader)?;
+        let (bytes, hash) = reader.finish();
+        if bytes != file.size {
+            return Err(AgentreplayError::Internal(format!(
+                "{} shrank while being archived",
+                file.relative
+            )));
+        }
+        files.push(SnapshotFile {
+            path: file.relative.clone(),
+            size: bytes,
+            hash,
+        });
+    }
+    metadata.files = files;
+    metadata.file_count = metadata.files.len();
+
+    let manifest = serde_json::to_vec_pretty(metadata)
+        .map_err(|e| AgentreplayError::Serialization(e.to_string()))?;
+    append_entry(
+        &mut builder,
+        MANIFEST_ENTRY,
+        manifest.len() as u64,
+        manifest.as_slice(),
+    )?;
+
+    let file = builder.into_inner()?.finish()?;
+    file.sync_all()?;
+    Ok(())
+}
+
+/// Archive entries: relative path -> (size, blake3 hex)
+type ArchiveContents = BTreeMap<String, (u64, String)>;
+
+/// Reject archive paths that could escape the extraction directory
+fn safe_relative_path(path: &Path) -> Option<String> {
+    let normal = path
+        .components()
+        .all(|c| matches!(c, Component::Normal(_) | Component::CurDir));
+    (normal && path.components().next().is_some())
+        .then(|| path.to_string_lossy().replace('\\', "/"))
+}
+
+/// Compare archive contents (`found`: path -> (size, hash)) with the manifest
+fn compare_with_manifest(
+    manifest: &[SnapshotFile],
+    found: &ArchiveContents,
+    verification: &mut BackupVerification,
+) {
+    for file in manifest {
+        match found.get(&file.path) {
+            None => verification.missing.push(file.path.clone()),
+            Some((size, hash)) if *size != file.size || *hash != file.hash => {
+                verification.corrupted.push(file.path.clone())
+            }
+            Some(_) => verification.files_checked += 1,
+        }
+    }
+    for path in found.keys() {
+        if !manifest.iter().any(|f| &f.path == path) {
+            verification.unexpected.push(path.clone());
+        }
+    }
+    verification.valid = verification.missing.is_empty()
+        && verification.corrupted.is_empty()
+        && verification.unexpected.is_empty();
+}
+
+/// Archive of backup `id` in the same directory as `archive`
+fn ancestor_archive(archive: &Path, id: &str) -> PathBuf {
+    archive.with_file_name(format!("{}.{}", id, ARCHIVE_EXTENSION))
+}
+
+/// Check every ancestor of `manifest` and resolve its inherited files
+///
+/// With `dest` set, inherited files are copied there from the ancestor that
+/// stores them. Inherited files no ancestor provides are reported missing.
+fn resolve_chain(
+    archive: &Path,
+    manifest: &BackupMetadata,
+    dest: Option<&Path>,
+    verification: &mut BackupVerification,
+) -> Result<()> {
+    let mut needed: HashMap<&str, Vec<&str>> = HashMap::new();
+    for file in &manifest.inherited {
+        needed.entry(&file.hash).or_default().push(&file.path);
+    }
+
+    let mut parent = manifest.parent.clone();
+    while let Some(id) = parent {
+        if verification.chain.contains(&id) {
+            verification.error = Some(format!("backup chain loops back to '{}'", id));
+            break;
+        }
+        verification.chain.push(id.clone());
+        let scratch = dest
+            .map(|dest| ScratchDir::create(sibling(dest, &format!(".chain-{}", id))))
+            .transpose()?;
+        let read = read_archive(
+            &ancestor_archive(archive, &id),
+            scratch.as_ref().map(|s| s.path()),
+        );
+        let (ancestor, found) = match read {
+            Ok((Some(ancestor), found)) => (ancestor, found),
+            Ok((None, _)) => {
+                verification.error = Some(format!("ancestor backup '{}' has no manifest", id));
+                break;
+            }
+            Err(e) => {
+                verification.error = Some(format!("ancestor backup '{}' is unreadable: {}", id, e));
+                break;
+            }
+        };
+
+        let mut own = BackupVerification::default();
+        compare_with_manifest(&ancestor.files, &found, &mut own);
+        verification.files_checked += own.files_checked;
+        let prefixed = |paths: Vec<String>| paths.into_iter().map(|p| format!("{}/{}", id, p));
+        verification.corrupted.extend(prefixed(own.corrupted));
+        verification.unexpected.extend(prefixed(own.unexpected));
+
+        for (path, (_, hash)) in &found {
+            let Some(targets) = needed.remove(hash.as_str()) else {
+                continue;
+            };
+            if let (Some(dest), Some(scratch)) = (dest, &scratch) {
+                for target in targets {
+                    let target = dest.join(target);
+                    if let Some(parent) = target.parent() {
+                        fs::create_dir_all(parent)?;
+                    }
+                    fs::copy(scratch.path().join(path), target)?;
+                }
+            }
+        }
+        parent = ancestor.parent;
+    }
+
+    let mut unresolved: Vec<String> = needed.into_values().flatten().map(String::from).collect();
+    unresolved.sort();
+    verification.missing.extend(unresolved);
+    verification.valid = verification.error.is_none()
+        && verification.missing.is_empty()
+        && verification.corrupted.is_empty()
+        && verification.unexpected.is_empty();
+    Ok(())
+}
+
+/// Read an archive, hashing every entry and optionally extracting it to `dest`
+fn read_archive(
+    archive: &Path,
+    dest: Option<&Path>,
+) -> Result<(Option<BackupMetadata>, ArchiveContents)> {
+    let decoder = zstd::stream::read::Decoder::new(File::open(archive)?)?;
+    let mut entries = tar::Archive::new(decoder);
+    let mut manifest = None;
+    let mut found = BTreeMap::new();
+
+    for entry in entries.entries()? {
+        let mut entry = entry?;
+        let entry_path = entry.path()?.into_owned();
+        let Some(relative) = safe_relative_path(&entry_path) else {
+            return Err(AgentreplayError::Corruption(format!(
+                "archive entry {:?} escapes the backup root",
+                entry_path
+            )));
+        };
+
+        if relative == MANIFEST_ENTRY {
+            let mut bytes = Vec::new();
+            entry.read_to_end(&mut bytes)?;
+            manifest =
+                Some(serde_json::from_slice(&bytes).map_err(|e| {
+                    AgentreplayError::Serialization(format!("backup manifest: {}", e))
+                })?);
+            continue;
+        }
+
+        let mut reader = HashingReader::new(&mut entry);
+        match dest {
+            Some(dest) => {
+                let target = dest.join(&relative);
+                if let Some(parent) = target.parent() {
+                    fs::create_dir_all(parent)?;
+                }
+                io::copy(&mut reader, &mut File::create(&target)?)?;
+            }
+            None => {
+                io::copy(&mut reader, &mut io::sink())?;
+            }
+        }
+        found.insert(relative, reader.finish());
+    }
+    Ok((manifest, found))
+}
+
+/// Check an archive and, if `extract_to` is set, extract, open and
+/// integrity-check it there
+fn verify_archive(archive: &Path, extract_to: Option<&Path>) -> Result<BackupVerification> {
+    let mut verification = BackupVerification::default();
+    let (manifest, found) = match read_archive(archive, extract_to) {
+        Ok(contents) => contents,
+        Err(e) => {
+            verification.error = Some(format!("unreadable archive: {}", e));
+            return Ok(verification);
+        }
+    };
+    let Some(manifest) = manifest else {
+        verification.error = Some("archive has no manifest".to_string());
+        return Ok(verification);
+    };
+    verification.backup_id = Some(manifest.id.clone());
+    verification.chain.push(manifest.id.clone());
+    compare_with_manifest(&manifest.files, &found, &mut verification);
+    if verification.valid && manifest.is_incremental() {
+        resolve_chain(archive, &manifest, extract_to, &mut verification)?;
+    }
+
+    if let (true, Some(dir)) = (verification.valid, extract_to) {
+        // Dropped (and shut down) before the directory is used further
+        let opened = AgentReplayStorage::open(dir).and_then(|storage| storage.verify_integrity());
+        match opened {
+            Ok(report) => {
+                verification.valid = report.is_clean();
+                verification.edge_count = report.valid_edges;
+                verification.integrity = Some(report);
+            }
+            Err(e) => {
+                verification.valid = false;
+                verification.error = Some(format!("restored copy failed to open: {}", e));
+            }
+        }
+    }
+    Ok(verification)
+}
+
+/// Creates, lists, verifies and restores backup archives in one directory
+pub struct BackupManager {
+    backup_dir: PathBuf,
+}
+
+impl BackupManager {
+    pub fn new<P: AsRef<Path>>(backup_dir: P) -> Self {
+        BackupManager {
+            backup_dir: backup_dir.as_ref().to_path_buf(),
+        }
+    }
+
+    pub fn backup_dir(&self) -> &Path {
+        &self.backup_dir
+    }
+
+    /// Archive path for backup `id`
+    pub fn archive_path(&self, id: &str) -> PathBuf {
+        self.backup_dir
+            .join(format!("{}.{}", id, ARCHIVE_EXTENSION))
+    }
+
+    fn metadata_path(&self, id: &str) -> PathBuf {
+        self.backup_dir.join(format!("{}.json", id))
+    }
+
+    /// Take an online backup of `storage` without pausing ingestion
+    ///
+    /// `name` defaults to a timestamp. See the module docs for how
+    /// consistency is guaranteed and when a backup is incremental.
+    pub fn create_backup(
+        &self,
+        storage: &AgentReplayStorage,
+        name: Option<&str>,
+        options: &BackupOptions,
+    ) -> Result<BackupMetadata> {
+        let now = chrono::Utc::now();
+        let id = match name {
+            Some(name) => name.to_string(),
+            None => format!("backup-{}", now.format("%Y%m%dT%H%M%S%3fZ")),
+        };
+        if id.is_empty() || id.contains(['/', '\\']) || id.starts_with('.') {
+            return Err(AgentreplayError::InvalidArgument(format!(
+                "invalid backup name '{}'",
+                id
+            )));
+        }
+        let archive = self.archive_path(&id);
+        if archive.exists() {
+            return Err(AgentreplayError::InvalidArgument(format!(
+                "backup '{}' already exists",
+                id
+            )));
+        }
+        fs::create_dir_all(&self.backup_dir)?;
+        // Skipped if the backup directory is inside the data directory
+        let skip = fs::canonicalize(&self.backup_dir)?;
+        let source_dir = fs::canonicalize(storage.data_dir())?.display().to_string();
+        let parent = if options.incremental {
+            Self::list_backups(&self.backup_dir)?
+                .into_iter()
+                .find(|b| b.source_dir == source_dir)
+                .filter(|b| b.chain_depth + 1 < options.max_chain_length)
+        } else {
+            None
+        };
+        let partial = self
+            .backup_dir
+            .join(format!("{}.{}.tmp", id, ARCHIVE_EXTENSION));
+
+        for attempt in 1..=SNAPSHOT_ATTEMPTS {
+            let link = attempt < SNAPSHOT_ATTEMPTS;
+            let staging = ScratchDir::create(sibling(
+                storage.data_dir(),
+                &format!(".backup-{}-{}", id, attempt),
+            ))?;
+            let (wal_position, staged) = stage_snapshot(storage, staging.path(), &skip, link)?;
+
+            let timestamp_us = now_us();
+            let mut metadata = BackupMetadata {
+                id: id.clone(),
+                timestamp: timestamp_us / 1_000_000,
+                timestamp_us,
+                created_at: now.to_rfc3339(),
+                database_version: env!("CARGO_PKG_VERSION").to_string(),
+                format_version: BACKUP_FORMAT_VERSION,
+                wal_position,
+                source_dir: source_dir.clone(),
+                ..Default::default()
+            };
+            if let Some(parent) = &parent {
+                metadata.parent = Some(parent.id.clone());
+                metadata.chain_depth = parent.chain_depth + 1;
+                metadata.inherited = inherited_files(&staged, parent)?;
+            }
+            if let Err(e) = write_archive(&staged, &mut metadata, &partial) {
+                let _ = fs::remove_file(&partial);
+                return Err(e);
+            }
+
+            let changed = changed_files(&staged);
+            if !changed.is_empty() {
+                warn!(
+                    attempt,
+                    "Files changed in place while archiving backup '{}', retrying: {:?}",
+                    id,
+                    changed
+                );
+                fs::remove_file(&partial)?;
+                continue;
+            }
+            drop(staging);
+            fs::rename(&partial, &archive)?;
+            metadata.size_bytes = fs::metadata(&archive)?.len();
+
+            if options.verify {
+                let scratch = ScratchDir::create(sibling(
+                    storage.data_dir(),
+                    &format!(".backup-{}-verify", id),
+                ))?;
+                let verification = verify_archive(&archive, Some(scratch.path()))?;
+                if !verification.valid {
+                    let _ = fs::remove_file(&archive);
+                    return Err(AgentreplayError::Corruption(format!(
+                        "backup '{}' failed verification: {}",
+                        id,
+                        verification
+                            .error
+                            .clone()
+                            .unwrap_or_else(|| format!("{:?}", verification))
+                    )));
+                }
+                metadata.verified = true;
+                metadata.edge_count = verification.edge_count;
+            }
+
+            let sidecar = serde_json::to_vec_pretty(&metadata)
+                .map_err(|e| AgentreplayError::Serialization(e.to_string()))?;
+            fs::write(self.metadata_path(&id), sidecar)?;
+            info!(
+                backup = %id,
+                parent = metadata.parent.as_deref().unwrap_or("-"),
+                files = metadata.file_count,
+                inherited = metadata.inherited.len(),
+                bytes = metadata.size_bytes,
+                wal_position,
+                verified = metadata.verified,
+                "Online backup created"
+            );
+            return Ok(metadata);
+        }
+
+        Err(AgentreplayError::Internal(format!(
+            "data files kept changing while backup '{}' was archived",
+            id
+        )))
+    }
+
+    /// Backups in `location`, newest first
+    pub fn list_backups<P: AsRef<Path>>(location: P) -> Result<Vec<BackupMetadata>> {
+        let manager = Self::new(location);
+        if !manager.backup_dir.exists() {
+            return Ok(Vec::new());
+        }
+        let mut backups = Vec::new();
+        for entry in fs::read_dir(&manager.backup_dir)? {
+            let path = entry?.path();
+            if path.extension().and_then(|e| e.to_str()) != Some("json") {
+                continue;
+            }
+            let Ok(bytes) = fs::read(&path) else {
+                continue;
+            };
+            match serde_json::from_slice::<BackupMetadata>(&bytes) {
+                Ok(metadata) if manager.archive_path(&metadata.id).exists() => {
+                    backups.push(metadata)
+                }
+                Ok(_) => {}
+                Err(e) => warn!("Skipping unreadable backup metadata {:?}: {}", path, e),
+            }
+        }
+        backups.sort_by_key(|b| std::cmp::Reverse(b.timestamp_us));
+        Ok(backups)
+    }
+
+    /// Check every file in an archive against its manifest
+    ///
+    /// For an incremental backup every ancestor archive is checked too, and
+    /// each inherited file must be found in one of them. A `deep` check also
+    /// extracts the (reassembled) backup to a scratch directory, opens it and
+    /// runs an integrity check, as a restore would.
+    pub fn verify_backup<P: AsRef<Path>>(archive: P, deep: bool) -> Result<BackupVerification> {
+        let archive = archive.as_ref();
+        if !deep {
+            return verify_archive(archive, None);
+        }
+        let scratch = ScratchDir::create(sibling(archive, ".verify"))?;
+        verify_archive(archive, Some(scratch.path()))
+    }
+
+    /// Extract and verify a backup into the pending restore directory for
+    /// `data_dir`, to be swapped in by [`Self::apply_pending_restore`]
+    ///
+    /// Safe while the database is open: nothing in `data_dir` is touched.
+    pub fn stage_restore<P: AsRef<Path>, Q: AsRef<Path>>(
+        archive: P,
+        data_dir: Q,
+    ) -> Result<RestoreOutcome> {
+        let data_dir = data_dir.as_ref();
+        let pending = pending_restore_dir(data_dir);
+        let staging = ScratchDir::create(sibling(&pending, ".tmp"))?;
+
+        let verification = verify_archive(archive.as_ref(), Some(staging.path()))?;
+        if !verification.valid {
+            return Err(AgentreplayError::Corruption(format!(
+                "backup {:?} failed restore verification: {}",
+                archive.as_ref(),
+                verification
+                    .error
+                    .clone()
+                    .unwrap_or_else(|| format!("{:?}", verification))
+            )));
+        }
+
+        if pending.exists() {
+            fs::remove_dir_all(&pending)?;
+        }
+        fs::rename(staging.path(), &pending)?;
+        info!(
+            "Backup {:?} verified and staged at {:?}",
+            archive.as_ref(),
+            pending
+        );
+        Ok(RestoreOutcome {
+            backup_id: verification.backup_id.clone().unwrap_or_default(),
+            data_dir: data_dir.display().to_string(),
+            pending: true,
+            previous_dir: None,
+            verification,
+        })
+    }
+
+    /// Swap a staged restore into place; call before opening the database
+    ///
+    /// Returns where the replaced directory was moved, or `None` if no restore
+    /// was pending. Directories backups skip (project databases, diagnostics)
+    /// are moved into the restored directory. Fails if another process holds
+    /// the data directory lock.
+    pub fn apply_pending_restore<P: AsRef<Path>>(data_dir: P) -> Result<Option<PathBuf>> {
+        let data_dir = data_dir.as_ref();
+        let pending = pending_restore_dir(data_dir);
+        if !pending.exists() {
+            return Ok(None);
+        }
+        if let Some(owner) = read_lock_owner(data_dir)?.filter(|owner| !owner.is_stale()) {
+            return Err(AgentreplayError::InvalidArgument(format!(
+                "cannot apply restore while {:?} is in use by {:?}",
+                data_dir, owner
+            )));
+        }
+
+        let previous = sibling(data_dir, &format!(".pre-restore-{}", now_us() / 1_000_000));
+        if data_dir.exists() {
+            fs::rename(data_dir, &previous)?;
+        }
+        fs::rename(&pending, data_dir)?;
+
+        if previous.exists() {
+            for name in EXCLUDED_DIRS {
+                let kept = previous.join(name);
+                if kept.exists() && !data_dir.join(name).exists() {
+                    fs::rename(&kept, data_dir.join(name))?;
+                }
+            }
+        }
+        info!(
+            "Applied staged restore to {:?} (previous data kept at {:?})",
+            data_dir, previous
+        );
+        Ok(previous.exists().then_some(previous))
+    }
+
+    /// Stage and immediately apply a restore (database must be closed)
+    pub fn restore_backup<P: AsRef<Path>, Q: AsRef<Path>>(
+        archive: P,
+        data_dir: Q,
+    ) -> Result<RestoreOutcome> {
+        let mut outcome = Self::stage_restore(archive, data_dir.as_ref())?;
+        let previous = Self::apply_pending_restore(data_dir.as_ref())?;
+        outcome.pending = false;
+        outcome.previous_dir = previous.map(|p| p.display().to_string());
+        Ok(outcome)
+    }
+
+    /// Backup chains in this directory, newest first
+    pub fn list_chains(&self) -> Result<Vec<BackupChain>> {
+        let backups = Self::list_backups(&self.backup_dir)?;
+        let by_id: HashMap<&str, &BackupMetadata> =
+            backups.iter().map(|b| (b.id.as_str(), b)).collect();
+
+        let mut chains: BTreeMap<String, BackupChain> = BTreeMap::new();
+        // Oldest first, so every chain lists its backups in order
+        for backup in backups.iter().rev() {
+            let mut root = backup;
+            let mut complete = true;
+            let mut steps = 0;
+            while let Some(parent) = &root.parent {
+                match by_id.get(parent.as_str()) {
+                    Some(ancestor) if steps <= backups.len() => {
+                        root = ancestor;
+                        steps += 1;
+                    }
+                    _ => {
+                        complete = false;
+                        break;
+                    }
+                }
+            }
+            let root_id = match (complete, &root.parent) {
+                (false, Some(missing)) => missing.clone(),
+                _ => root.id.clone(),
+            };
+            let chain = chains
+                .entry(root_id.clone())
+                .or_insert_with(|| BackupChain {
+                    root: root_id,
+                    source_dir: backup.source_dir.clone(),
+                    backups: Vec::new(),
+                    size_bytes: 0,
+                    latest_timestamp_us: 0,
+                    complete,
+                });
+            chain.backups.push(backup.id.clone());
+            chain.size_bytes += backup.size_bytes;
+            chain.latest_timestamp_us = chain.latest_timestamp_us.max(backup.timestamp_us);
+        }
+
+        let mut chains: Vec<BackupChain> = chains.into_values().collect();
+        chains.sort_by_key(|c| std::cmp::Reverse(c.latest_timestamp_us));
+        Ok(chains)
+    }
+
+    /// Delete the oldest complete chains beyond `policy.keep_chains` per data
+    /// directory, returning the deleted backup ids
+    ///
+    /// Incomplete chains are left for inspection; they cannot be restored and
+    /// show up as invalid when verified.
+    pub fn apply_retention(&self, policy: &BackupRetention) -> Result<Vec<String>> {
+        if policy.keep_chains == 0 {
+            return Err(AgentreplayError::InvalidArgument(
+                "backup retention must keep at least one chain".to_string(),
+            ));
+        }
+        let mut kept: HashMap<String, usize> = HashMap::new();
+        let mut deleted = Vec::new();
+        for chain in self.list_chains()?.into_iter().filter(|c| c.complete) {
+            let count = kept.entry(chain.source_dir.clone()).or_default();
+            *count += 1;
+            if *count <= policy.keep_chains {
+                continue;
+            }
+            // Newest first, so no backup is removed before its dependents
+            for id in chain.backups.iter().rev() {
+                self.remove_backup_files(id)?;
+                deleted.push(id.clone());
+            }
+        }
+        if !deleted.is_empty() {
+            info!(
+                deleted = deleted.len(),
+                keep_chains = policy.keep_chains,
+                "Applied backup retention"
+            );
+        }
+        Ok(deleted)
+    }
+
+    /// Delete backup `id` (archive and metadata)
+    ///
+    /// Fails if an incremental backup builds on it; delete those first or use
+    /// [`Self::apply_retention`].
+    pub fn delete_backup(&self, id: &str) -> Result<()> {
+        if !self.archive_path(id).exists() {
+            return Err(AgentreplayError::NotFound(format!("backup '{}'", id)));
+        }
+        let dependents: Vec<String> = Self::list_backups(&self.backup_dir)?
+            .into_iter()
+            .filter(|b| b.parent.as_deref() == Some(id))
+            .map(|b| b.id)
+            .collect();
+        if !dependents.is_empty() {
+            return Err(AgentreplayError::InvalidArgument(format!(
+                "backup '{}' is the base of incremental backups {:?}",
+                id, dependents
+            )));
+        }
+        self.remove_backup_files(id)
+    }
+
+    fn remove_backup_files(&self, id: &str) -> Result<()> {
+        fs::remove_file(self.archive_path(id))?;
+        let metadata = self.metadata_path(id);
+        if metadata.exists() {
+            fs::remove_file(metadata)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use agentreplay_core::{AgentFlowEdge, SpanType};
+    use tempfile::TempDir;
+
+    fn edge(id: u128) -> AgentFlowEdge {
+        let mut edge = AgentFlowEdge::new(1, 0, 1, 1, SpanType::Root, 0);
+        edge.edge_id = id;
+        edge.timestamp_us = 1_000_000 + id as u64;
+        edge.checksum = edge.compute_checksum();
+        edge
+    }
+
+    fn count_edges(dir: &Path) -> u64 {
+        let storage = AgentReplayStorage::open(dir).unwrap();
+        storage.verify_integrity().unwrap().valid_edges
+    }
+
+    #[test]
+    fn test_online_backup_and_restore() {
+        let root = TempDir::new().unwrap();
+        let data_dir = root.path().join("data");
+        let backups = BackupManager::new(root.path().join("backups"));
+
+        let storage = AgentReplayStorage::open(&data_dir).unwrap();
+        let edges: Vec<_> = (1..=5).map(edge).collect();
+        storage.put_batch(&edges).unwrap();
+
+        let metadata = backups
+            .create_backup(&storage, Some("nightly"), &BackupOptions::default())
+            .unwrap();
+        assert!(metadata.verified);
+        assert_eq!(metadata.edge_count, 5);
+        assert!(metadata.file_count > 0);
+
+        // Writes after the barrier are not part of the backup
+        storage.put(edge(6)).unwrap();
+        storage.sync().unwrap();
+
+        let listed = BackupManager::list_backups(backups.backup_dir()).unwrap();
+        assert_eq!(listed.len(), 1);
+        assert_eq!(listed[0].id, "nightly");
+        assert!(backups
+            .create_backup(&storage, Some("nightly"), &BackupOptions::default())
+            .is_err());
+
+        let archive = backups.archive_path("nightly");
+        assert!(BackupManager::verify_backup(&archive, false).unwrap().valid);
+
+        // Staging is safe while open; applying waits for the database to close
+        let staged = BackupManager::stage_restore(&archive, &data_dir).unwrap();
+        assert!(staged.pending);
+        fs::create_dir_all(data_dir.join("projects/project_1")).unwrap();
+        drop(storage);
+
+        let previous = BackupManager::apply_pending_restore(&data_dir)
+            .unwrap()
+            .unwrap();
+        assert!(previous.exists());
+        assert!(data_dir.join("projects/project_1").exists());
+        assert_eq!(count_edges(&data_dir), 5);
+        assert!(BackupManager::apply_pending_restore(&data_dir)
+            .unwrap()
+            .is_none());
+    }
+
+    #[test]
+    fn test_incremental_backup_chain() {
+        let root = TempDir::new().unwrap();
+        let data_dir = root.path().join("data");
+        let backups = BackupManager::new(root.path().join("backups"));
+        let incremental = BackupOptions {
+            incremental: true,
+            max_chain_length: 3,
+            ..Default::default()
+        };
+
+        let storage = AgentReplayStorage::open(&data_dir).unwrap();
+        storage
+            .put_batch(&(1..=5).map(edge).collect::<Vec<_>>())
+            .unwrap();
+        let full = backups
+            .create_backup(&storage, Some("full"), &incremental)
+            .unwrap();
+        assert!(!full.is_incremental());
+
+        storage
+            .put_batch(&(6..=8).map(edge).collect::<Vec<_>>())
+            .unwrap();
+        let inc = backups
+            .create_backup(&storage, Some("inc-1"), &incremental)
+            .unwrap();
+        assert_eq!(inc.parent.as_deref(), Some("full"));
+        assert_eq!(inc.chain_depth, 1);
+        assert_eq!(inc.edge_count, 8);
+
+        // The chain is capped at three backups
+        let inc2 = backups
+            .create_backup(&storage, Some("inc-2"), &incremental)
+            .unwrap();
+        assert_eq!(inc2.chain_depth, 2);
+        let next = backups
+            .create_backup(&storage, Some("full-2"), &incremental)
+            .unwrap();
+        assert!(!next.is_incremental());
+
+        let verification =
+            BackupManager::verify_backup(backups.archive_path("inc-2"), false).unwrap();
+        assert!(verification.valid, "{:?}", verification);
+        assert_eq!(verification.chain, vec!["inc-2", "inc-1", "full"]);
+
+        assert!(backups.delete_backup("full").is_err());
+        let chains = backups.list_chains().unwrap();
+        assert_eq!(chains.len(), 2);
+        assert_eq!(chains[0].root, "full-2");
+        assert_eq!(chains[1].backups, vec!["full", "inc-1", "inc-2"]);
+
+        drop(storage);
+        BackupManager::restore_backup(backups.archive_path("inc-2"), &data_dir).unwrap();
+        assert_eq!(count_edges(&data_dir), 8);
+
+        let deleted = backups
+            .apply_retention(&BackupRetention { keep_chains: 1 })
+            .unwrap();
+        assert_eq!(deleted, vec!["inc-2", "inc-1", "full"]);
+        assert_eq!(
+            BackupManager::list_backups(backups.backup_dir())
+                .unwrap()
+                .len(),
+            1
+        );
+    }
+
+    #[test]
+    fn test_missing_ancestor_fails_verification() {
+        let root = TempDir::new().unwrap();
+        let data_dir = root.path().join("data");
+        let backups = BackupManager::new(root.path().join("backups"));
+        let options = BackupOptions {
+            incremental: true,
+            verify: false,
+            ..Default::default()
+        };
+
+        let storage = AgentReplayStorage::open(&data_dir).unwrap();
+        storage
+            .put_batch(&(1..=5).map(edge).collect::<Vec<_>>())
+            .unwrap();
+        backups
+            .create_backup(&storage, Some("full"), &options)
+            .unwrap();
+        storage.put(edge(6)).unwrap();
+        backups
+            .create_backup(&storage, Some("inc"), &options)
+            .unwrap();
+
+        fs::remove_file(backups.archive_path("full")).unwrap();
+        let verification =
+            BackupManager::verify_backup(backups.archive_path("inc"), false).unwrap();
+        assert!(!verification.valid);
+        assert!(verification.error.is_some());
+        assert!(!backups.list_chains().unwrap()[0].complete);
+        assert!(backups
+            .apply_retention(&BackupRetention { keep_chains: 1 })
+            .unwrap()
+            .is_empty());
+    }
+
+    #[test]
+    fn test_compare_with_manifest() {
+        let manifest = vec![
+            SnapshotFile {
+                path: "a.sst".to_string(),
+                size: 3,
+                hash: "aaa".to_string(),
+            },
+            SnapshotFile {
+                path: "b.sst".to_string(),
+                size: 3,
+                hash: "bbb".to_string(),
+            },
+            SnapshotFile {
+                path: "c.sst".to_string(),
+                size: 3,
+                hash: "ccc".to_string(),
+            },
+        ];
+        let mut found = BTreeMap::new();
+        found.insert("a.sst".to_string(), (3, "aaa".to_string()));
+        found.insert("b.sst".to_string(), (3, "tampered".to_string()));
+        found.insert("extra.sst".to_string(), (1, "x".to_string()));
+
+        let mut verification = BackupVerification::default();
+        compare_with_manifest(&manifest, &found, &mut verification);
+        assert!(!verification.valid);
+        assert_eq!(verification.files_checked, 1);
+        assert_eq!(verification.corrupted, vec!["b.sst"]);
+        assert_eq!(verification.missing, vec!["c.sst"]);
+        assert_eq!(verification.unexpected, vec!["extra.sst"]);
+    }
+
+    #[test]
+    fn test_safe_relative_path() {
+        assert_eq!(
+            safe_relative_path(Path::new("sst/000001.sst")).as_deref(),
+            Some("sst/000001.sst")
+        );
+        assert!(safe_relative_path(Path::new("../etc/passwd")).is_none());
+        assert!(safe_relative_path(Path::new("/etc/passwd")).is_none());
+    }
+}
@@ -0,0 +1,460 @@
+// Copyright 2025 AgentReplay (https://github.com/agentreplay)
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program. If not, see <https://www.gnu.org/licenses/>.
+
+//! Persistent Benchmark Store
+//!
+//! Records benchmark runs (throughput, latency percentiles, recall) together
+//! with a fingerprint of the machine they ran on, so that a new run can be
+//! compared against the previous run of the same suite on the same machine.
+//!
+//! Uses the same append-only, CRC-checked log format as the eval store.
+
+use agentreplay_core::clock::now_us;
+use serde::{Deserialize, Serialize};
+use std::fs::{File, OpenOptions};
+use std::io::{BufReader, BufWriter, Read, Write};
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+const BENCH_STORE_MAGIC: &[u8; 4] = b"BNCH";
+const BENCH_STORE_VERSION: u32 = 1;
+const ENTRY_TYPE_RUN: u8 = 1;
+
+/// Default relative change (in percent) treated as a regression
+pub const DEFAULT_REGRESSION_THRESHOLD_PCT: f64 = 10.0;
+
+/// Identifies the machine a benchmark ran on
+///
+/// Runs are only compared against baselines with the same `fingerprint`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct MachineFingerprint {
+    pub hostname: String,
+    pub os: String,
+    pub arch: String,
+    pub cpu_count: usize,
+    /// Stable hash of the fields above
+    pub fingerprint: String,
+}
+
+impl MachineFingerprint {
+    /// Fingerprint the current machine
+    pub fn current() -> Self {
+        let hostname = std::env::var("HOSTNAME")
+            .or_else(|_| std::env::var("COMPUTERNAME"))
+            .ok()
+            .or_else(|| {
+                std::fs::read_to_string("/etc/hostname")
+                    .ok()
+                    .map(|h| h.trim().to_string())
+            })
+            .unwrap_or_else(|| "unknown".to_string());
+        let cpu_count = std::thread::available_parallelism()
+            .map(|n| n.get())
+            .unwrap_or(1);
+
+        Self::new(
+            hostname,
+            std::env::consts::OS.to_string(),
+            std::env::consts::ARCH.to_string(),
+            cpu_count,
+        )
+    }
+
+    pub fn new(hostname: String, os: String, arch: String, cpu_count: usize) -> Self {
+        let mut hasher = blake3::Hasher::new();
+        hasher.update(hostname.as_bytes());
+        hasher.update(os.as_bytes());
+        hasher.update(arch.as_bytes());
+        hasher.update(&(cpu_count as u64).to_le_bytes());
+        let fingerprint = hasher.finalize().to_hex()[..16].to_string();
+
+        Self {
+            hostname,
+            os,
+            arch,
+            cpu_count,
+            fingerprint,
+        }
+    }
+}
+
+/// A single measured value within a benchmark run
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BenchmarkMetric {
+    /// Metric name (e.g. "throughput", "latency_p99", "recall_at_10")
+    pub name: String,
+    pub value: f64,
+    /// Unit for display (e.g. "ops/s", "us", "ratio")
+    pub unit: String,
+    /// Whether larger values are better (throughput, recall) or worse (latency)
+    pub higher_is_better: bool,
+}
+
+impl BenchmarkMetric {
+    pub fn higher_is_better(name: &str, value: f64, unit: &str) -> Self {
+        Self {
+            name: name.to_string(),
+            value,
+            unit: unit.to_string(),
+            higher_is_better: true,
+        }
+    }
+
+    pub fn lower_is_better(name: &str, value: f64, unit: &str) -> Self {
+        Self {
+            name: name.to_string(),
+            value,
+            unit: unit.to_string(),
+            higher_is_better: false,
+        }
+    }
+}
+
+/// A recorded benchmark run
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BenchmarkRun {
+    pub run_id: String,
+    /// Benchmark suite name (e.g. "cli/write", "index/hnsw")
+    pub suite: String,
+    /// When the run finished (microseconds since epoch)
+    pub timestamp_us: u64,
+    pub machine: MachineFingerprint,
+    /// Free-form parameters (e.g. number of writes, vector dimension)
+    #[serde(default)]
+    pub parameters: serde_json::Value,
+    pub metrics: Vec<BenchmarkMetric>,
+}
+
+impl BenchmarkRun {
+    pub fn new(suite: impl Into<String>, parameters: serde_json::Value) -> Self {
+        let timestamp_us = now_us();
+
+        Self {
+            run_id: format!("bench-{}", uuid::Uuid::new_v4()),
+            suite: suite.into(),
+            timestamp_us,
+            machine: MachineFingerprint::current(),
+            parameters,
+            metrics: Vec::new(),
+        }
+    }
+
+    pub fn with_metric(mut self, metric: BenchmarkMetric) -> Self {
+        self.metrics.push(metric);
+        self
+    }
+
+    /// Add p50/p99/p999 latency metrics (in microseconds) from raw samples
+    pub fn with_latency_percentiles(mut self, prefix: &str, samples: &mut [Duration]) -> Self {
+        if samples.is_empty() {
+            return self;
+        }
+        samples.sort();
+        for (label, q) in [("p50", 0.50), ("p99", 0.99), ("p999", 0.999)] {
+            let idx = ((samples.len() as f64 * q) as usize).min(samples.len() - 1);
+            self.metrics.push(BenchmarkMetric::lower_is_better(
+                &format!("{}_{}", prefix, label),
+                samples[idx].as_secs_f64() * 1_000_000.0,
+                "us",
+            ));
+        }
+        self
+    }
+
+    pub fn metric(&self, name: &str) -> Option<&BenchmarkMetric> {
+        self.metrics.iter().find(|m| m.name == name)
+    }
+}
+
+/// Change of a single metric between a baseline and the current run
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MetricDelta {
+    pub name: String,
+    pub unit: String,
+    pub baseline: f64,
+    pub current: f64,
+    /// Relative change in percent (positive = value went up)
+    pub change_pct: f64,
+    /// True if the change is worse than the threshold in the bad direction
+    pub regression: bool,
+}
+
+/// Result of comparing a run against its baseline
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BenchmarkComparison {
+    pub suite: String,
+    pub baseline_run_id: String,
+    pub current_run_id: String,
+    pub threshold_pct: f64,
+    pub deltas: Vec<MetricDelta>,
+}
+
+impl BenchmarkComparison {
+    /// Compare `current` against `baseline`, metric by metric
+    pub fn between(baseline: &BenchmarkRun, current: &BenchmarkRun, threshold_pct: f64) -> Self {
+        let deltas = current
+            .metrics
+            .iter()
+            .filter_map(|metric| {
+                let base = baseline.metric(&metric.name)?;
+                let change_pct = if base.value.abs() > f64::EPSILON {
+                    (metric.value - base.value) / base.value.abs() * 100.0
+                } else {
+                    0.0
+                };
+                let regression = if metric.higher_is_better {
+                    change_pct < -threshold_pct
+                } else {
+                    change_pct > threshold_pct
+                };
+
+                Some(MetricDelta {
+                    name: metric.name.clone(),
+                    unit: metric.unit.clone(),
+                    baseline: base.value,
+                    current: metric.value,
+                    change_pct,
+                    regression,
+                })
+            })
+            .collect();
+
+        Self {
+            suite: current.suite.clone(),
+            baseline_run_id: baseline.run_id.clone(),
+            current_run_id: current.run_id.clone(),
+            threshold_pct,
+            deltas,
+        }
+    }
+
+    pub fn has_regressions(&self) -> bool {
+        self.deltas.iter().any(|d| d.regression)
+    }
+
+    pub fn regressions(&self) -> impl Iterator<Item = &MetricDelta> {
+        self.deltas.iter().filter(|d| d.regression)
+    }
+}
+
+/// Persistent store of benchmark runs
+pub struct BenchmarkStore {
+    log_path: PathBuf,
+}
+
+impl BenchmarkStore {
+    /// Open or create a benchmark store
+    pub fn open(data_dir: impl AsRef<Path>) -> std::io::Result<Self> {
+        let data_dir = data_dir.as_ref().to_path_buf();
+        std::fs::create_dir_all(&data_dir)?;
+
+        let log_path = data_dir.join("benchmarks.log");
+
+        if !log_path.exists() {
+            let mut file = File::create(&log_path)?;
+            file.write_all(BENCH_STORE_MAGIC)?;
+            file.write_all(&BENCH_STORE_VERSION.to_le_bytes())?;
+            file.flush()?;
+        }
+
+        Ok(Self { log_path })
+    }
+
+    /// Append a run to the store
+    pub fn record(&self, run: &BenchmarkRun) -> std::io::Result<()> {
+        let file = OpenOptions::new().append(true).open(&self.log_path)?;
+        let mut writer = BufWriter::new(file);
+
+        let data = serde_json::to_vec(run)
+            .map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidData, e))?;
+        let crc = crc32fast::hash(&data);
+
+        writer.write_all(&[ENTRY_TYPE_RUN])?;
+        writer.write_all(&(data.len() as u32).to_le_bytes())?;
+        writer.write_all(&data)?;
+        writer.write_all(&crc.to_le_bytes())?;
+        writer.flush()?;
+
+        Ok(())
+    }
+
+    /// All runs in insertion order
+    pub fn list_runs(&self) -> std::io::Result<Vec<BenchmarkRun>> {
+        let file = File::open(&self.log_path)?;
+        let mut reader = BufReader::new(file);
+        let mut runs = Vec::new();
+
+        let mut magic = [0u8; 4];
+        if reader.read_exact(&mut magic).is_err() {
+            return Ok(runs);
+        }
+        if &magic != BENCH_STORE_MAGIC {
+            tracing::warn!("Invalid benchmark store magic, ignoring contents");
+            return Ok(runs);
+        }
+
+        let mut version_bytes = [0u8; 4];
+        reader.read_exact(&mut version_bytes)?;
+        if u32::from_le_bytes(version_bytes) != BENCH_STORE_VERSION {
+            tracing::warn!("Benchmark store version mismatch, ignoring contents");
+            return Ok(runs);
+        }
+
+        loop {
+            let mut entry_type = [0u8; 1];
+            if reader.read_exact(&mut entry_type).is_err() {
+                break; // EOF
+            }
+
+            let mut len_bytes = [0u8; 4];
+            if reader.read_exact(&mut len_bytes).is_err() {
+                break;
+            }
+            let mut data = vec![0u8; u32::from_le_bytes(len_bytes) as usize];
+            if reader.read_exact(&mut data).is_err() {
+                break;
+            }
+
+            let mut crc_bytes = [0u8; 4];
+            if reader.read_exact(&mut crc_bytes).is_err() {
+                break;
+            }
+            if u32::from_le_bytes(crc_bytes) != crc32fast::hash(&data) {
+                tracing::warn!("CRC mismatch in benchmark store, skipping entry");
+                continue;
+            }
+
+            if entry_type[0] == ENTRY_TYPE_RUN {
+                if let Ok(run) = serde_json::from_slice::<BenchmarkRun>(&data) {
+                    runs.push(run);
+                }
+            }
+        }
+
+        Ok(runs)
+    }
+
+    /// Runs of one suite, optionally restricted to a machine fingerprint
+    pub fn runs_for_suite(
+        &self,
+        suite: &str,
+        fingerprint: Option<&str>,
+    ) -> std::io::Result<Vec<BenchmarkRun>> {
+        Ok(self
+            .list_runs()?
+            .into_iter()
+            .filter(|r| r.suite == suite)
+            .filter(|r| fingerprint.map_or(true, |f| r.machine.fingerprint == f))
+            .collect())
+    }
+
+    /// Most recent run of the same suite on the same machine, excluding `run` itself
+    pub fn previous_run(&self, run: &BenchmarkRun) -> std::io::Result<Option<BenchmarkRun>> {
+        Ok(self
+            .runs_for_suite(&run.suite, Some(&run.machine.fingerprint))?
+            .into_iter()
+            .filter(|r| r.run_id != run.run_id && r.timestamp_us <= run.timestamp_us)
+            .max_by_key(|r| r.timestamp_us))
+    }
+
+    /// Compare a run against the previous run on the same machine
+    pub fn compare_to_previous(
+        &self,
+        run: &BenchmarkRun,
+        threshold_pct: f64,
+    ) -> std::io::Result<Option<BenchmarkComparison>> {
+        Ok(self
+            .previous_run(run)?
+            .map(|baseline| BenchmarkComparison::between(&baseline, run, threshold_pct)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    fn run_with(suite: &str, throughput: f64, p99_us: f64, ts: u64) -> BenchmarkRun {
+        let mut run = BenchmarkRun::new(suite, serde_json::json!({}))
+            .with_metric(BenchmarkMetric::higher_is_better(
+                "throughput",
+                throughput,
+                "ops/s",
+            ))
+            .with_metric(BenchmarkMetric::lower_is_better(
+                "latency_p99",
+                p99_us,
+                "us",
+            ));
+        run.timestamp_us = ts;
+        run
+    }
+
+    #[test]
+    fn test_record_and_list() {
+        let dir = TempDir::new().unwrap();
+        let store = BenchmarkStore::open(dir.path()).unwrap();
+
+        store
+            .record(&run_with("cli/write", 1000.0, 50.0, 1))
+            .unwrap();
+        store
+            .record(&run_with("index/hnsw", 500.0, 80.0, 2))
+            .unwrap();
+
+        let reopened = BenchmarkStore::open(dir.path()).unwrap();
+        assert_eq!(reopened.list_runs().unwrap().len(), 2);
+        assert_eq!(reopened.runs_for_suite("cli/write", None).unwrap().len(), 1);
+    }
+
+    #[test]
+    fn test_regression_detection() {
+        let dir = TempDir::new().unwrap();
+        let store = BenchmarkStore::open(dir.path()).unwrap();
+
+        let baseline = run_with("cli/write", 1000.0, 50.0, 1);
+        store.record(&baseline).unwrap();
+
+        // Throughput dropped 20%, latency improved
+        let current = run_with("cli/write", 800.0, 40.0, 2);
+        store.record(&current).unwrap();
+
+        let comparison = store
+            .compare_to_previous(&current, DEFAULT_REGRESSION_THRESHOLD_PCT)
+            .unwrap()
+            .unwrap();
+        assert_eq!(comparison.baseline_run_id, baseline.run_id);
+        assert!(comparison.has_regressions());
+
+        let regressions: Vec<_> = comparison.regressions().map(|d| d.name.as_str()).collect();
+        assert_eq!(regressions, vec!["throughput"]);
+    }
+
+    #[test]
+    fn test_other_machine_is_not_a_baseline() {
+        let dir = TempDir::new().unwrap();
+        let store = BenchmarkStore::open(dir.path()).unwrap();
+
+        let mut other = run_with("cli/write", 1000.0, 50.0, 1);
+        other.machine = MachineFingerprint::new("elsewhere".into(), "os".into(), "arch".into(), 1);
+        store.record(&other).unwrap();
+
+        let current = run_with("cli/write", 10.0, 5000.0, 2);
+        assert!(store
+            .compare_to_previous(&current, DEFAULT_REGRESSION_THRESHOLD_PCT)
+            .unwrap()
+            .is_none());
+    }
+}
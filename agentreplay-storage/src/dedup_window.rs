@@ -0,0 +1,125 @@
+// Copyright 2025 AgentReplay (https://github.com/agentreplay)
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program. If not, see <https://www.gnu.org/licenses/>.
+
+//! Time-windowed deduplication filter
+//!
+//! Remembers recently seen keys (e.g. client span IDs) for a sliding window
+//! using two generations of bloom filters. A key inserted at time `t` is
+//! reported as seen until at least `t + window` and at most `t + 2 * window`.
+//!
+//! Like any bloom filter this can report false positives, so callers should
+//! treat a hit as "possibly a retry" and confirm against storage. A miss is
+//! definitive and lets the common (non-retry) path skip the storage lookup.
+
+use crate::bloom::BloomFilter;
+use parking_lot::Mutex;
+use std::hash::Hash;
+use std::time::{Duration, Instant};
+
+/// Target false positive rate per generation
+const DEDUP_FALSE_POSITIVE_RATE: f64 = 0.001;
+
+struct Generations {
+    current: BloomFilter,
+    previous: BloomFilter,
+    rotated_at: Instant,
+}
+
+/// Sliding-window "recently seen" filter backed by rotating bloom filters
+pub struct DedupWindow {
+    window: Duration,
+    expected_items: usize,
+    generations: Mutex<Generations>,
+}
+
+impl DedupWindow {
+    /// Create a filter remembering keys for `window`
+    ///
+    /// `expected_items` is the number of keys expected per window; exceeding
+    /// it raises the false positive rate but never causes false negatives.
+    pub fn new(window: Duration, expected_items: usize) -> Self {
+        let expected_items = expected_items.max(1);
+        Self {
+            window,
+            expected_items,
+            generations: Mutex::new(Generations {
+                current: BloomFilter::new(expected_items, DEDUP_FALSE_POSITIVE_RATE),
+                previous: BloomFilter::new(expected_items, DEDUP_FALSE_POSITIVE_RATE),
+                rotated_at: Instant::now(),
+            }),
+        }
+    }
+
+    pub fn window(&self) -> Duration {
+        self.window
+    }
+
+    /// Record `key` and report whether it was (possibly) seen within the window
+    pub fn check_and_insert<T: Hash>(&self, key: &T) -> bool {
+        let mut gens = self.generations.lock();
+        self.rotate_if_due(&mut gens);
+
+        let seen = gens.current.contains(key) || gens.previous.contains(key);
+        if !gens.current.contains(key) {
+            gens.current.insert(key);
+        }
+        seen
+    }
+
+    /// Whether `key` was (possibly) seen within the window, without recording it
+    pub fn might_contain<T: Hash>(&self, key: &T) -> bool {
+        let mut gens = self.generations.lock();
+        self.rotate_if_due(&mut gens);
+        gens.current.contains(key) || gens.previous.contains(key)
+    }
+
+    fn rotate_if_due(&self, gens: &mut Generations) {
+        if gens.rotated_at.elapsed() < self.window {
+            return;
+        }
+        let fresh = BloomFilter::new(self.expected_items, DEDUP_FALSE_POSITIVE_RATE);
+        gens.previous = std::mem::replace(&mut gens.current, fresh);
+        gens.rotated_at = Instant::now();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_check_and_insert() {
+        let window = DedupWindow::new(Duration::from_secs(60), 1_000);
+
+        assert!(!window.check_and_insert(&42u128));
+        assert!(window.check_and_insert(&42u128));
+        assert!(window.might_contain(&42u128));
+        assert!(!window.might_contain(&43u128));
+    }
+
+    #[test]
+    fn test_keys_expire_after_two_windows() {
+        let window = DedupWindow::new(Duration::from_millis(20), 1_000);
+        window.check_and_insert(&7u128);
+
+        // First rotation: key moves to the previous generation
+        std::thread::sleep(Duration::from_millis(25));
+        assert!(window.might_contain(&7u128));
+
+        // Second rotation: key is forgotten
+        std::thread::sleep(Duration::from_millis(25));
+        assert!(!window.might_contain(&7u128));
+    }
+}
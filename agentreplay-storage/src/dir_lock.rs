@@ -0,0 +1,320 @@
+// Copyright 2025 AgentReplay (https://github.com/agentreplay)
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program. If not, see <https://www.gnu.org/licenses/>.
+
+//! Cooperative lock on a data directory
+//!
+//! The server and the desktop app can both point at the same data directory;
+//! two processes writing it at once corrupt the WAL and the metric rollups.
+//! The first process to open a directory creates `agentreplay.lock` in it
+//! (with `create_new`, so only one creator wins) holding a [`LockOwner`]
+//! record, and refreshes its heartbeat while running. Others find the lock
+//! held and either give up or continue read-only.
+//!
+//! A lock is considered stale — and is taken over — when its heartbeat is
+//! older than [`STALE_AFTER`], or when it names a process on this host that
+//! no longer exists (Linux only). The file is removed when the
+//! [`DataDirLock`] is dropped, unless another process has taken it over.
+
+use crate::benchmark_store::MachineFingerprint;
+use agentreplay_core::clock::now_secs;
+use agentreplay_core::{AgentreplayError, Result};
+use serde::{Deserialize, Serialize};
+use std::io::{ErrorKind, Write};
+use std::path::{Path, PathBuf};
+use std::sync::mpsc;
+use std::thread::JoinHandle;
+use std::time::Duration;
+
+/// Lock file created in the data directory
+pub const LOCK_FILE_NAME: &str = "agentreplay.lock";
+
+/// How often the holder refreshes its heartbeat
+pub const HEARTBEAT_INTERVAL: Duration = Duration::from_secs(10);
+
+/// Heartbeat age after which a lock is considered abandoned
+pub const STALE_AFTER: Duration = Duration::from_secs(30);
+
+/// The process holding a data directory lock
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct LockOwner {
+    pub pid: u32,
+    pub hostname: String,
+    /// Application that took the lock (e.g. "server", "desktop")
+    pub app: String,
+    pub version: String,
+    pub acquired_at: u64,
+    pub heartbeat_at: u64,
+}
+
+impl LockOwner {
+    fn current(app: &str, version: &str) -> Self {
+        let now = now_secs();
+        Self {
+            pid: std::process::id(),
+            hostname: MachineFingerprint::current().hostname,
+            app: app.to_string(),
+            version: version.to_string(),
+            acquired_at: now,
+            heartbeat_at: now,
+        }
+    }
+
+    /// Same lock generation: the heartbeat changes, the rest does not
+    fn same_holder(&self, other: &LockOwner) -> bool {
+        self.pid == other.pid
+            && self.hostname == other.hostname
+            && self.acquired_at == other.acquired_at
+    }
+
+    /// Whether the owner is gone and the lock may be taken over
+    pub fn is_stale(&self) -> bool {
+        let heartbeat_age = now_secs().saturating_sub(self.heartbeat_at);
+        if heartbeat_age > STALE_AFTER.as_secs() {
+            return true;
+        }
+        self.hostname == MachineFingerprint::current().hostname && !process_alive(self.pid)
+    }
+}
+
+#[cfg(target_os = "linux")]
+fn process_alive(pid: u32) -> bool {
+    Path::new("/proc").join(pid.to_string()).exists()
+}
+
+#[cfg(not(target_os = "linux"))]
+fn process_alive(_pid: u32) -> bool {
+    true
+}
+
+/// Result of trying to lock a data directory
+#[derive(Debug)]
+pub enum DirLockOutcome {
+    Acquired(DataDirLock),
+    HeldBy(LockOwner),
+}
+
+/// Read the lock file of `data_dir`, if any
+pub fn read_lock_owner(data_dir: impl AsRef<Path>) -> Result<Option<LockOwner>> {
+    read_owner(&data_dir.as_ref().join(LOCK_FILE_NAME))
+}
+
+fn read_owner(path: &Path) -> Result<Option<LockOwner>> {
+    match std::fs::read(path) {
+        // A lock file cut short by a crash mid-write is treated as abandoned
+        Ok(bytes) => Ok(serde_json::from_slice(&bytes).ok()),
+        Err(e) if e.kind() == ErrorKind::NotFound => Ok(None),
+        Err(e) => Err(e.into()),
+    }
+}
+
+/// Replace the lock file contents atomically
+fn write_owner(path: &Path, owner: &LockOwner) -> Result<()> {
+    let bytes = serde_json::to_vec_pretty(owner)
+        .map_err(|e| AgentreplayError::Serialization(e.to_string()))?;
+    let tmp = path.with_extension("lock.tmp");
+    std::fs::write(&tmp, bytes)?;
+    std::fs::rename(&tmp, path)?;
+    Ok(())
+}
+
+/// Exclusive lock on a data directory, released on drop
+#[derive(Debug)]
+pub struct DataDirLock {
+    path: PathBuf,
+    owner: LockOwner,
+    stop_tx: Option<mpsc::Sender<()>>,
+    heartbeat: Option<JoinHandle<()>>,
+}
+
+impl DataDirLock {
+    /// Lock `data_dir` for this process, taking over a stale lock
+    ///
+    /// `app` and `version` are recorded in the lock file so other processes
+    /// can tell the user who holds it.
+    pub fn acquire(data_dir: impl AsRef<Path>, app: &str, version: &str) -> Result<DirLockOutcome> {
+        let data_dir = data_dir.as_ref();
+        std::fs::create_dir_all(data_dir)?;
+        let path = data_dir.join(LOCK_FILE_NAME);
+        let owner = LockOwner::current(app, version);
+
+        // Second attempt runs after removing a stale lock
+        for _ in 0..2 {
+            match std::fs::OpenOptions::new()
+                .write(true)
+                .create_new(true)
+                .open(&path)
+            {
+                Ok(mut file) => {
+                    let bytes = serde_json::to_vec_pretty(&owner)
+                        .map_err(|e| AgentreplayError::Serialization(e.to_string()))?;
+                    file.write_all(&bytes)?;
+                    file.sync_all()?;
+                    return Ok(DirLockOutcome::Acquired(Self::start(path, owner)));
+                }
+                Err(e) if e.kind() == ErrorKind::AlreadyExists => match read_owner(&path)? {
+                    Some(holder) if !holder.is_stale() => {
+                        return Ok(DirLockOutcome::HeldBy(holder));
+                    }
+                    holder => {
+                        tracing::warn!(
+                            "Taking over stale data directory lock {:?} (was {:?})",
+                            path,
+                            holder
+                        );
+                        match std::fs::remove_file(&path) {
+                            Ok(()) => {}
+                            Err(e) if e.kind() == ErrorKind::NotFound => {}
+                            Err(e) => return Err(e.into()),
+                        }
+                    }
+                },
+                Err(e) => return Err(e.into()),
+            }
+        }
+
+        // Lost a takeover race against another process
+        match read_owner(&path)? {
+            Some(holder) => Ok(DirLockOutcome::HeldBy(holder)),
+            None => Err(AgentreplayError::Internal(format!(
+                "Could not lock data directory {:?}",
+                data_dir
+            ))),
+        }
+    }
+
+    fn start(path: PathBuf, owner: LockOwner) -> Self {
+        let (stop_tx, stop_rx) = mpsc::channel::<()>();
+        let heartbeat_path = path.clone();
+        let mut heartbeat_owner = owner.clone();
+        let heartbeat = std::thread::Builder::new()
+            .name("data-dir-lock".to_string())
+            .spawn(move || {
+                while let Err(mpsc::RecvTimeoutError::Timeout) =
+                    stop_rx.recv_timeout(HEARTBEAT_INTERVAL)
+                {
+                    match read_owner(&heartbeat_path) {
+                        Ok(Some(current)) if current.same_holder(&heartbeat_owner) => {}
+                        Ok(holder) => {
+                            tracing::error!(
+                                "Data directory lock {:?} was taken over by {:?}",
+                                heartbeat_path,
+                                holder
+                            );
+                            return;
+                        }
+                        Err(e) => {
+                            tracing::warn!("Failed to read data directory lock: {}", e);
+                            continue;
+                        }
+                    }
+                    heartbeat_owner.heartbeat_at = now_secs();
+                    if let Err(e) = write_owner(&heartbeat_path, &heartbeat_owner) {
+                        tracing::warn!("Failed to refresh data directory lock: {}", e);
+                    }
+                }
+            })
+            .map_err(|e| tracing::error!("Failed to start lock heartbeat thread: {}", e))
+            .ok();
+
+        Self {
+            path,
+            owner,
+            stop_tx: Some(stop_tx),
+            heartbeat,
+        }
+    }
+
+    /// Ownership record written to the lock file
+    pub fn owner(&self) -> &LockOwner {
+        &self.owner
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+}
+
+impl Drop for DataDirLock {
+    fn drop(&mut self) {
+        drop(self.stop_tx.take());
+        if let Some(heartbeat) = self.heartbeat.take() {
+            let _ = heartbeat.join();
+        }
+        // Leave the file alone if another process took the lock over
+        if let Ok(Some(current)) = read_owner(&self.path) {
+            if current.same_holder(&self.owner) {
+                let _ = std::fs::remove_file(&self.path);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    fn acquired(outcome: DirLockOutcome) -> DataDirLock {
+        match outcome {
+            DirLockOutcome::Acquired(lock) => lock,
+            DirLockOutcome::HeldBy(owner) => panic!("lock held by {:?}", owner),
+        }
+    }
+
+    #[test]
+    fn test_lock_excludes_second_instance() {
+        let dir = TempDir::new().unwrap();
+        let lock = acquired(DataDirLock::acquire(dir.path(), "server", "1.0.0").unwrap());
+        assert_eq!(lock.owner().pid, std::process::id());
+
+        match DataDirLock::acquire(dir.path(), "desktop", "1.0.0").unwrap() {
+            DirLockOutcome::HeldBy(owner) => {
+                assert_eq!(owner.app, "server");
+                assert_eq!(owner.pid, std::process::id());
+            }
+            DirLockOutcome::Acquired(_) => panic!("lock acquired twice"),
+        }
+        assert_eq!(read_lock_owner(dir.path()).unwrap().unwrap().app, "server");
+
+        drop(lock);
+        assert!(read_lock_owner(dir.path()).unwrap().is_none());
+        let lock = acquired(DataDirLock::acquire(dir.path(), "desktop", "1.0.0").unwrap());
+        assert_eq!(lock.owner().app, "desktop");
+    }
+
+    #[test]
+    fn test_stale_lock_is_taken_over() {
+        let dir = TempDir::new().unwrap();
+        let path = dir.path().join(LOCK_FILE_NAME);
+
+        let mut abandoned = LockOwner::current("server", "0.9.0");
+        abandoned.heartbeat_at -= STALE_AFTER.as_secs() + 1;
+        write_owner(&path, &abandoned).unwrap();
+        assert!(abandoned.is_stale());
+        let lock = acquired(DataDirLock::acquire(dir.path(), "desktop", "1.0.0").unwrap());
+
+        // The old holder's file is gone; dropping a lock that was taken over
+        // must not delete the new holder's file
+        let mut newer = lock.owner().clone();
+        newer.acquired_at += 1;
+        write_owner(&path, &newer).unwrap();
+        drop(lock);
+        assert_eq!(read_lock_owner(dir.path()).unwrap(), Some(newer));
+
+        // Truncated lock files do not block startup
+        std::fs::write(&path, b"{\"pid\":").unwrap();
+        acquired(DataDirLock::acquire(dir.path(), "server", "1.0.0").unwrap());
+    }
+}
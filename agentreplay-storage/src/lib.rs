@@ -44,37 +44,72 @@ pub mod sochdb_unified;
 // Auxiliary modules that don't depend on LSM
 pub mod aff;
 pub mod analytics_bucket;
+pub mod archive;
 pub mod backend;
+pub mod backup;
+pub mod benchmark_store;
 pub mod bloom;
 pub mod compression;
+pub mod dedup_window;
+pub mod dir_lock;
 pub mod eval_store;
 pub mod event_store;
+pub mod logprobs;
 pub mod metrics_agg;
 pub mod memory_agent_store;
 pub mod observation_store;
 pub mod pending_queue;
+pub mod replication;
 pub mod response_git;
 pub mod sharded_metrics;
 pub mod sketches;
+pub mod wal_shipping;
 
 // Re-export core types from sochdb_unified
 pub use sochdb_unified::{
-    AgentReplayStorage, AgentReplayStorageConfig, MetricsBucket, StorageStats, SyncMode,
-    CacheStats, LevelStats, HealthCheckResult, CleanupStats, DashboardSummary,
+    AgentReplayStorage, AgentReplayStorageConfig, AnnotationLabel, AnnotationQueueItem,
+    AnnotationQueueRecord, LabelValue, RubricDimension, RubricScale,
+    MetricsBucket, StorageStats, SyncMode,
+    CacheStats, LevelStats, HealthCheckResult, CleanupStats, CompactionPhase, CompactionRun,
+    CompactionStats, DashboardSummary,
+    ConversationLink, CorruptRecord, CorruptionKind, EdgeEnrichment, FeedbackRecord, GoalVerdict, IndexRebuildStats, KeyUsageRecord,
+    IntegrityReport, ProviderKeyRecord, SessionAnalysis, SessionRollup, SpanHighlightRecord,
     decode_trace_key, deserialize_edge, encode_metrics_key, encode_payload_key,
-    encode_trace_key, serialize_edge,
+    encode_rollup_key, encode_trace_key, serialize_edge,
+};
+pub use agentreplay_core::storage_metrics::{
+    FsyncLatencyReport, GroupCommitReport, WriteTelemetry, WriteTelemetryReport,
 };
 
 // Re-export auxiliary module types
-pub use aff::{AFFHeader, AFFReader, AFFWriter, AFF_MAGIC, AFF_VERSION};
+pub use aff::{AFFHeader, AFFReader, AFFScan, AFFWriter, AFF_MAGIC, AFF_VERSION};
+pub use archive::{ArchiveChunk, ArchivedRange, TraceArchive};
 pub use analytics_bucket::{
     AggregatedMetrics, AnalyticsBucket, BloomFilterStats, QueryExecutionStats, QueryFilters,
 };
 pub use backend::{LocalFsBackend, ObjectMetadata, StorageBackend};
+pub use backup::{
+    BackupChain, BackupManager, BackupMetadata, BackupOptions, BackupRetention,
+    BackupVerification, RestoreOutcome, DEFAULT_MAX_CHAIN_LENGTH,
+};
+pub use benchmark_store::{
+    BenchmarkComparison, BenchmarkMetric, BenchmarkRun, BenchmarkStore, MachineFingerprint,
+    MetricDelta,
+};
 pub use compression::{CompressionEngine, CompressionStats, StorageTier};
+pub use dedup_window::DedupWindow;
+pub use dir_lock::{read_lock_owner, DataDirLock, DirLockOutcome, LockOwner, LOCK_FILE_NAME};
 pub use eval_store::{EvalAggregateStats, EvalMetricEntry, EvalStore, EvalSummary};
 pub use event_store::EventStore;
-pub use metrics_agg::{BucketKey, BucketStats, MetricsAggregator, MetricsSummary};
+pub use logprobs::{
+    decode_logprob_summary, decode_logprobs, encode_logprobs, LogprobSummary, SpanLogprobs,
+    TokenLogprob, LOW_CONFIDENCE_TOKEN_PROB,
+};
+pub use metrics_agg::{
+    plan_rollup_segments, BucketKey, BucketStats, MetricsAggregator, MetricsSummary, RollupBucket,
+    RollupDims, RollupFilter, RollupGranularity, RollupSample, RollupSegment, RollupSummary,
+    RollupTables,
+};
 pub use memory_agent_store::{MemoryAgentStoreError, PersistentMemoryStore, SessionDeleteStats};
 pub use response_git::{
     Author, Blob, Branch, Commit, CommitDiff, ContentType, DiffConfig, DiffEngine, DiffHunk,
@@ -88,6 +123,14 @@ pub use sketches::{AdaptiveSketch, CountMinSketch, DDSketch, ExponentialHistogra
 // Observation and queue storage for memory agent
 pub use observation_store::{ObservationKey, ObservationQuery, ObservationStore, ObservationStoreError, StoredObservation};
 pub use pending_queue::{ClaimResult, PendingMessage, PendingMessageQueue, PendingQueueConfig, QueueError};
+pub use replication::{
+    latest_manifest, LeaseManager, LeaseOutcome, PublishStats, SnapshotFile, SnapshotManifest,
+    SnapshotPublisher, SnapshotSyncer, SyncOutcome, WriterLease,
+};
+pub use wal_shipping::{
+    read_wal_head, ShippedSegment, TimelineEntry, WalEntry, WalHead, WalReceiver, WalRecord,
+    WalSegment, WalShipper,
+};
 
 // Compatibility aliases for migration from old storage layer
 // UnifiedStorage is now AgentReplayStorage
@@ -120,48 +163,3 @@ pub struct VersionSetStatsSnapshot {
     pub total_versions: u64,
     pub active_readers: u64,
 }
-
-/// Backup manager (stub for compatibility)
-pub struct BackupManager {
-    _backup_dir: std::path::PathBuf,
-}
-
-impl BackupManager {
-    pub fn new<P: AsRef<std::path::Path>>(backup_dir: P) -> Self {
-        BackupManager { _backup_dir: backup_dir.as_ref().to_path_buf() }
-    }
-
-    pub fn create_backup<P: AsRef<std::path::Path>>(&self, _destination: P) -> std::io::Result<BackupMetadata> {
-        Ok(BackupMetadata::default())
-    }
-
-    pub fn list_backups<P: AsRef<std::path::Path>>(_location: P) -> std::io::Result<Vec<BackupMetadata>> {
-        Ok(Vec::new())
-    }
-
-    pub fn restore_backup<P: AsRef<std::path::Path>>(&self, _backup_path: P) -> std::io::Result<()> {
-        Ok(())
-    }
-
-    pub fn delete_backup(&self, _backup_id: &str) -> std::io::Result<()> {
-        Ok(())
-    }
-
-    pub fn verify_backup<P: AsRef<std::path::Path>>(_backup_path: P) -> std::io::Result<bool> {
-        Ok(true)
-    }
-}
-
-/// Backup metadata (stub for compatibility)
-#[derive(Debug, Clone, Default, serde::Serialize, serde::Deserialize)]
-pub struct BackupMetadata {
-    pub id: String,
-    pub timestamp: u64,
-    pub timestamp_us: u64,
-    pub created_at: String,
-    pub size_bytes: u64,
-    pub edge_count: u64,
-    pub file_count: usize,
-    pub database_version: String,
-}
-
@@ -0,0 +1,287 @@
+// Copyright 2025 AgentReplay (https://github.com/agentreplay)
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program. If not, see <https://www.gnu.org/licenses/>.
+
+//! Compact per-span token logprobs
+//!
+//! Providers that return token log probabilities (OpenAI `logprobs`, Gemini
+//! `logprobsResult`, vLLM and compatible servers) can have them captured at
+//! ingest. A response of a few hundred tokens with top-5 alternatives is tens
+//! of kilobytes as JSON, so the stored record is:
+//!
+//! ```text
+//! bincode(StoredLogprobs { version, summary, data: zstd(bincode(CompactTokens)) })
+//! ```
+//!
+//! Logprobs are quantized to thousandths of a nat (`u16`, so values below
+//! -65.535 are clamped), which is far below the noise of any confidence
+//! analysis. The [`LogprobSummary`] sits outside the compressed block so
+//! analytics can read it without decompressing the tokens.
+
+use agentreplay_core::{AgentreplayError, Result};
+use serde::{Deserialize, Serialize};
+
+/// Encoding version of [`StoredLogprobs`]
+const LOGPROBS_VERSION: u8 = 1;
+
+/// Quantization step: logprobs are stored as `round(-logprob * 1000)`
+const LOGPROB_SCALE: f32 = 1000.0;
+
+/// zstd level for the token block
+const ZSTD_LEVEL: i32 = 3;
+
+/// Tokens with a probability below this count as low-confidence
+pub const LOW_CONFIDENCE_TOKEN_PROB: f64 = 0.5;
+
+/// One generated token with its log probability and top-k alternatives
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TokenLogprob {
+    pub token: String,
+    pub logprob: f32,
+    /// Most likely alternatives at this position, as `(token, logprob)`
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub top_logprobs: Vec<(String, f32)>,
+}
+
+/// Confidence statistics of one span's output
+#[derive(Debug, Clone, Copy, PartialEq, Default, Serialize, Deserialize)]
+pub struct LogprobSummary {
+    pub token_count: u32,
+    /// Mean token logprob (natural log)
+    pub mean_logprob: f32,
+    /// Logprob of the least likely token
+    pub min_logprob: f32,
+    /// Tokens with probability below [`LOW_CONFIDENCE_TOKEN_PROB`]
+    pub low_confidence_tokens: u32,
+}
+
+impl LogprobSummary {
+    /// Summarize a token sequence; None if it is empty
+    pub fn from_tokens(tokens: &[TokenLogprob]) -> Option<Self> {
+        if tokens.is_empty() {
+            return None;
+        }
+        let low_confidence_logprob = LOW_CONFIDENCE_TOKEN_PROB.ln() as f32;
+        let sum: f64 = tokens.iter().map(|t| t.logprob as f64).sum();
+        Some(Self {
+            token_count: tokens.len() as u32,
+            mean_logprob: (sum / tokens.len() as f64) as f32,
+            min_logprob: tokens
+                .iter()
+                .map(|t| t.logprob)
+                .fold(f32::INFINITY, f32::min),
+            low_confidence_tokens: tokens
+                .iter()
+                .filter(|t| t.logprob < low_confidence_logprob)
+                .count() as u32,
+        })
+    }
+
+    /// Geometric mean token probability, `exp(mean_logprob)`, in [0, 1]
+    pub fn sequence_confidence(&self) -> f64 {
+        (self.mean_logprob as f64).exp()
+    }
+
+    /// `exp(-mean_logprob)`
+    pub fn perplexity(&self) -> f64 {
+        (-self.mean_logprob as f64).exp()
+    }
+
+    /// Fraction of tokens below [`LOW_CONFIDENCE_TOKEN_PROB`]
+    pub fn low_confidence_ratio(&self) -> f64 {
+        if self.token_count == 0 {
+            return 0.0;
+        }
+        self.low_confidence_tokens as f64 / self.token_count as f64
+    }
+}
+
+/// Decoded logprobs of one span
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct SpanLogprobs {
+    pub summary: LogprobSummary,
+    pub tokens: Vec<TokenLogprob>,
+}
+
+#[derive(Serialize, Deserialize)]
+struct StoredLogprobs {
+    version: u8,
+    summary: LogprobSummary,
+    /// zstd-compressed [`CompactTokens`]
+    data: Vec<u8>,
+}
+
+#[derive(Serialize, Deserialize)]
+struct CompactTokens {
+    tokens: Vec<String>,
+    logprobs: Vec<u16>,
+    top_logprobs: Vec<Vec<(String, u16)>>,
+}
+
+fn quantize(logprob: f32) -> u16 {
+    (-logprob * LOGPROB_SCALE)
+        .round()
+        .clamp(0.0, u16::MAX as f32) as u16
+}
+
+fn dequantize(q: u16) -> f32 {
+    -(q as f32) / LOGPROB_SCALE
+}
+
+fn serialization_error(e: impl std::fmt::Display) -> AgentreplayError {
+    AgentreplayError::Serialization(e.to_string())
+}
+
+/// Encode a token sequence for storage, returning the record and its summary
+pub fn encode_logprobs(tokens: &[TokenLogprob]) -> Result<(Vec<u8>, LogprobSummary)> {
+    let summary = LogprobSummary::from_tokens(tokens).ok_or_else(|| {
+        AgentreplayError::InvalidArgument("No token logprobs to store".to_string())
+    })?;
+    let compact = CompactTokens {
+        tokens: tokens.iter().map(|t| t.token.clone()).collect(),
+        logprobs: tokens.iter().map(|t| quantize(t.logprob)).collect(),
+        top_logprobs: tokens
+            .iter()
+            .map(|t| {
+                t.top_logprobs
+                    .iter()
+                    .map(|(token, logprob)| (token.clone(), quantize(*logprob)))
+                    .collect()
+            })
+            .collect(),
+    };
+    let raw = bincode::serialize(&compact).map_err(serialization_error)?;
+    let data = zstd::encode_all(raw.as_slice(), ZSTD_LEVEL)?;
+    let record = bincode::serialize(&StoredLogprobs {
+        version: LOGPROBS_VERSION,
+        summary,
+        data,
+    })
+    .map_err(serialization_error)?;
+    Ok((record, summary))
+}
+
+fn decode_record(bytes: &[u8]) -> Result<StoredLogprobs> {
+    let record: StoredLogprobs = bincode::deserialize(bytes).map_err(serialization_error)?;
+    if record.version != LOGPROBS_VERSION {
+        return Err(AgentreplayError::Serialization(format!(
+            "Unsupported logprobs record version {}",
+            record.version
+        )));
+    }
+    Ok(record)
+}
+
+/// Read only the summary of a stored record
+pub fn decode_logprob_summary(bytes: &[u8]) -> Result<LogprobSummary> {
+    decode_record(bytes).map(|record| record.summary)
+}
+
+/// Decode a stored record
+pub fn decode_logprobs(bytes: &[u8]) -> Result<SpanLogprobs> {
+    let record = decode_record(bytes)?;
+    let raw = zstd::decode_all(record.data.as_slice())?;
+    let compact: CompactTokens = bincode::deserialize(&raw).map_err(serialization_error)?;
+    if compact.logprobs.len() != compact.tokens.len()
+        || compact.top_logprobs.len() != compact.tokens.len()
+    {
+        return Err(AgentreplayError::Serialization(
+            "Corrupt logprobs record: column lengths differ".to_string(),
+        ));
+    }
+
+    let tokens = compact
+        .tokens
+        .into_iter()
+        .zip(compact.logprobs)
+        .zip(compact.top_logprobs)
+        .map(|((token, logprob), top)| TokenLogprob {
+            token,
+            logprob: dequantize(logprob),
+            top_logprobs: top
+                .into_iter()
+                .map(|(token, logprob)| (token, dequantize(logprob)))
+                .collect(),
+        })
+        .collect();
+    Ok(SpanLogprobs {
+        summary: record.summary,
+        tokens,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn token(token: &str, logprob: f32, top: &[(&str, f32)]) -> TokenLogprob {
+        TokenLogprob {
+            token: token.to_string(),
+            logprob,
+            top_logprobs: top.iter().map(|(t, lp)| (t.to_string(), *lp)).collect(),
+        }
+    }
+
+    #[test]
+    fn test_roundtrip_and_summary() {
+        let tokens = vec![
+            token("Paris", -0.01, &[("Paris", -0.01), ("Lyon", -4.8)]),
+            token(" is", -0.2, &[]),
+            token(" big", -1.5, &[("big", -1.5), (" large", -0.9)]),
+            token("!", -120.0, &[]),
+        ];
+        let (bytes, summary) = encode_logprobs(&tokens).unwrap();
+        assert_eq!(summary.token_count, 4);
+        assert_eq!(summary.low_confidence_tokens, 2);
+        assert_eq!(summary.min_logprob, -120.0);
+        assert!((summary.mean_logprob - (-121.71 / 4.0)).abs() < 1e-4);
+        assert_eq!(decode_logprob_summary(&bytes).unwrap(), summary);
+
+        let decoded = decode_logprobs(&bytes).unwrap();
+        assert_eq!(decoded.summary, summary);
+        assert_eq!(decoded.tokens.len(), 4);
+        assert_eq!(decoded.tokens[0].top_logprobs[1].0, "Lyon");
+        for (original, restored) in tokens.iter().zip(&decoded.tokens).take(3) {
+            assert_eq!(original.token, restored.token);
+            assert!((original.logprob - restored.logprob).abs() <= 0.0005);
+        }
+        // Clamped to the quantization range
+        assert!((decoded.tokens[3].logprob - (-65.535)).abs() < 1e-3);
+
+        assert!(encode_logprobs(&[]).is_err());
+    }
+
+    #[test]
+    fn test_compact_size() {
+        let tokens: Vec<TokenLogprob> = (0..500)
+            .map(|i| {
+                token(
+                    &format!(" word{}", i % 50),
+                    -(i % 7) as f32 * 0.3,
+                    &[(" alpha", -0.4), (" beta", -1.2), (" gamma", -2.5)],
+                )
+            })
+            .collect();
+        let json = serde_json::to_vec(&tokens).unwrap();
+        let (bytes, summary) = encode_logprobs(&tokens).unwrap();
+        assert!(
+            bytes.len() * 10 < json.len(),
+            "{} vs {}",
+            bytes.len(),
+            json.len()
+        );
+        assert!(summary.sequence_confidence() > 0.0 && summary.sequence_confidence() < 1.0);
+        assert!((summary.perplexity() * summary.sequence_confidence() - 1.0).abs() < 1e-9);
+    }
+}
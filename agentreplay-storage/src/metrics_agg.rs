@@ -21,10 +21,22 @@
 //! **Performance Impact:**
 //! - Before: O(N) full scan for each analytics query (100k traces = ~10 seconds)
 //! - After: O(buckets) where buckets = time_range / bucket_size (typically < 1000)
-
-use agentreplay_core::AgentFlowEdge;
-use parking_lot::RwLock;
-use std::collections::BTreeMap;
+//!
+//! ## Materialized rollups
+//!
+//! [`RollupTables`] keep per-minute, per-hour and per-day rollups of request
+//! counts, tokens, errors and latency sketches for every
+//! (tenant, project, agent, model) combination. A window query reads the
+//! coarsest buckets that fit entirely inside it and scans raw edges only for
+//! the partial minutes at either end (see [`plan_rollup_segments`]), so results
+//! are exact for any window, including the still-filling current minute.
+
+use crate::sketches::DDSketch;
+use agentreplay_core::{AgentFlowEdge, Result};
+use parking_lot::{Mutex, RwLock};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::sync::atomic::{AtomicU64, Ordering};
 use std::sync::Arc;
 
 /// Time bucket size in microseconds (1 minute default)
@@ -399,6 +411,525 @@ impl MetricsSummary {
     }
 }
 
+// ============================================================================
+// Materialized Rollups
+// ============================================================================
+
+const MINUTE_US: u64 = 60 * 1_000_000;
+const HOUR_US: u64 = 60 * MINUTE_US;
+const DAY_US: u64 = 24 * HOUR_US;
+
+/// Minute rollups older than this are pruned (windows fall back to hour rollups)
+pub const MINUTE_ROLLUP_RETENTION_US: u64 = 2 * DAY_US;
+/// Hour rollups older than this are pruned (windows fall back to day rollups)
+pub const HOUR_ROLLUP_RETENTION_US: u64 = 90 * DAY_US;
+
+/// Bucket width of a rollup table
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum RollupGranularity {
+    Minute,
+    Hour,
+    Day,
+}
+
+impl RollupGranularity {
+    pub const ALL: [RollupGranularity; 3] = [Self::Minute, Self::Hour, Self::Day];
+
+    pub fn size_us(self) -> u64 {
+        match self {
+            Self::Minute => MINUTE_US,
+            Self::Hour => HOUR_US,
+            Self::Day => DAY_US,
+        }
+    }
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::Minute => "minute",
+            Self::Hour => "hour",
+            Self::Day => "day",
+        }
+    }
+
+    pub fn parse(s: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|g| g.as_str() == s)
+    }
+
+    fn index(self) -> usize {
+        self as usize
+    }
+
+    fn align(self, timestamp_us: u64) -> u64 {
+        (timestamp_us / self.size_us()) * self.size_us()
+    }
+}
+
+/// Dimensions a rollup row is grouped by
+#[derive(Debug, Clone, Default, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize)]
+pub struct RollupDims {
+    pub tenant_id: u64,
+    pub project_id: u16,
+    pub agent_id: u64,
+    /// Model name, empty when the edge carried none
+    pub model: String,
+}
+
+/// Aggregates of one rollup row
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct RollupBucket {
+    pub request_count: u64,
+    pub error_count: u64,
+    pub total_tokens: u64,
+    pub total_duration_us: u64,
+    /// Latency distribution (microseconds) for percentiles
+    pub latency_us: DDSketch,
+    /// Tokens of the edges whose cost was stored after a recompute
+    pub repriced_tokens: u64,
+    /// Stored cost of those edges (micro-USD)
+    pub repriced_cost_micros: u64,
+}
+
+impl RollupBucket {
+    pub fn record(&mut self, sample: &RollupSample) {
+        self.request_count += 1;
+        if sample.is_error {
+            self.error_count += 1;
+        }
+        self.total_tokens += sample.tokens;
+        self.total_duration_us += sample.duration_us;
+        self.latency_us.add(sample.duration_us as f64);
+        if let Some(cost_micros) = sample.cost_micros {
+            self.repriced_tokens += sample.tokens;
+            self.repriced_cost_micros += cost_micros;
+        }
+    }
+
+    pub fn merge(&mut self, other: &RollupBucket) {
+        self.request_count += other.request_count;
+        self.error_count += other.error_count;
+        self.total_tokens += other.total_tokens;
+        self.total_duration_us += other.total_duration_us;
+        self.latency_us.merge(&other.latency_us);
+        self.repriced_tokens += other.repriced_tokens;
+        self.repriced_cost_micros += other.repriced_cost_micros;
+    }
+
+    /// Tokens whose cost has to be derived from a price, i.e. of edges
+    /// without a stored cost
+    pub fn unpriced_tokens(&self) -> u64 {
+        self.total_tokens.saturating_sub(self.repriced_tokens)
+    }
+
+    /// Replace the cost counted for one recorded edge of `tokens` tokens
+    ///
+    /// `previous_micros` is the cost stored for it before, if any.
+    fn reprice(&mut self, tokens: u64, previous_micros: Option<u64>, cost_micros: u64) {
+        match previous_micros {
+            Some(previous) => {
+                self.repriced_cost_micros = self.repriced_cost_micros.saturating_sub(previous)
+            }
+            None => self.repriced_tokens += tokens,
+        }
+        self.repriced_cost_micros += cost_micros;
+    }
+
+    /// Latency at quantile `q` in microseconds (0 when empty)
+    pub fn latency_quantile_us(&self, q: f64) -> f64 {
+        if self.latency_us.is_empty() {
+            0.0
+        } else {
+            self.latency_us.quantile(q)
+        }
+    }
+}
+
+/// One edge as seen by the rollups
+#[derive(Debug, Clone)]
+pub struct RollupSample {
+    pub dims: RollupDims,
+    pub timestamp_us: u64,
+    pub duration_us: u64,
+    pub tokens: u64,
+    pub is_error: bool,
+    /// Cost stored for the edge after a recompute (micro-USD)
+    pub cost_micros: Option<u64>,
+}
+
+impl RollupSample {
+    pub fn from_edge(edge: &AgentFlowEdge, model: &str) -> Self {
+        Self {
+            dims: RollupDims {
+                tenant_id: edge.tenant_id,
+                project_id: edge.project_id,
+                agent_id: edge.agent_id,
+                model: model.to_string(),
+            },
+            timestamp_us: edge.timestamp_us,
+            duration_us: edge.duration_us as u64,
+            tokens: edge.token_count as u64,
+            is_error: edge.get_span_type() == agentreplay_core::SpanType::Error,
+            cost_micros: None,
+        }
+    }
+}
+
+/// Which rollup rows a window query covers
+#[derive(Debug, Clone, Default)]
+pub struct RollupFilter {
+    pub tenant_id: u64,
+    pub project_id: Option<u16>,
+    pub agent_id: Option<u64>,
+    pub model: Option<String>,
+}
+
+impl RollupFilter {
+    pub fn matches(&self, dims: &RollupDims) -> bool {
+        dims.tenant_id == self.tenant_id
+            && self.project_id.map_or(true, |p| p == dims.project_id)
+            && self.agent_id.map_or(true, |a| a == dims.agent_id)
+            && self.model.as_ref().map_or(true, |m| *m == dims.model)
+    }
+}
+
+/// Part of a query window, served from a rollup table or from raw edges
+///
+/// Ranges are half-open: `[start_us, end_us)`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RollupSegment {
+    Rollup {
+        granularity: RollupGranularity,
+        start_us: u64,
+        end_us: u64,
+    },
+    Raw {
+        start_us: u64,
+        end_us: u64,
+    },
+}
+
+/// Split `[start_us, end_us)` into the fewest rollup segments plus raw edges
+///
+/// Whole buckets are used coarsest first (climbing from minutes to hours to
+/// days and back down); whatever does not cover a whole minute at either end
+/// is left to a raw scan.
+pub fn plan_rollup_segments(start_us: u64, end_us: u64) -> Vec<RollupSegment> {
+    let mut segments = Vec::new();
+    if start_us >= end_us {
+        return segments;
+    }
+
+    let first_minute = start_us.div_ceil(MINUTE_US).saturating_mul(MINUTE_US);
+    if first_minute >= end_us || end_us - first_minute < MINUTE_US {
+        segments.push(RollupSegment::Raw { start_us, end_us });
+        return segments;
+    }
+    if start_us < first_minute {
+        segments.push(RollupSegment::Raw {
+            start_us,
+            end_us: first_minute,
+        });
+    }
+    let mut cursor = first_minute;
+
+    // Climb: fill up to the next hour, then the next day, while they fit
+    for (fine, coarse) in [
+        (RollupGranularity::Minute, RollupGranularity::Hour),
+        (RollupGranularity::Hour, RollupGranularity::Day),
+    ] {
+        let boundary = cursor.div_ceil(coarse.size_us()) * coarse.size_us();
+        if boundary > end_us {
+            break;
+        }
+        if cursor < boundary {
+            segments.push(RollupSegment::Rollup {
+                granularity: fine,
+                start_us: cursor,
+                end_us: boundary,
+            });
+            cursor = boundary;
+        }
+    }
+
+    // Descend: the largest whole buckets that still fit
+    for granularity in [
+        RollupGranularity::Day,
+        RollupGranularity::Hour,
+        RollupGranularity::Minute,
+    ] {
+        let stop = granularity.align(end_us);
+        if cursor % granularity.size_us() == 0 && stop > cursor {
+            segments.push(RollupSegment::Rollup {
+                granularity,
+                start_us: cursor,
+                end_us: stop,
+            });
+            cursor = stop;
+        }
+    }
+
+    if cursor < end_us {
+        segments.push(RollupSegment::Raw {
+            start_us: cursor,
+            end_us,
+        });
+    }
+    segments
+}
+
+/// Rollup totals for one query window
+#[derive(Debug, Clone, Default)]
+pub struct RollupSummary {
+    pub totals: RollupBucket,
+    pub by_project: BTreeMap<u16, RollupBucket>,
+    pub by_agent: BTreeMap<u64, RollupBucket>,
+    pub by_model: BTreeMap<String, RollupBucket>,
+    /// Edges read from raw storage for the partial buckets at the window edges
+    pub raw_edges: u64,
+}
+
+impl RollupSummary {
+    /// Combine summaries of disjoint edge sets (e.g. separate project databases)
+    pub fn merge(&mut self, other: &RollupSummary) {
+        self.totals.merge(&other.totals);
+        for (project_id, bucket) in &other.by_project {
+            self.by_project
+                .entry(*project_id)
+                .or_default()
+                .merge(bucket);
+        }
+        for (agent_id, bucket) in &other.by_agent {
+            self.by_agent.entry(*agent_id).or_default().merge(bucket);
+        }
+        for (model, bucket) in &other.by_model {
+            self.by_model
+                .entry(model.clone())
+                .or_default()
+                .merge(bucket);
+        }
+        self.raw_edges += other.raw_edges;
+    }
+
+    fn add(&mut self, dims: &RollupDims, bucket: &RollupBucket) {
+        self.totals.merge(bucket);
+        self.by_project
+            .entry(dims.project_id)
+            .or_default()
+            .merge(bucket);
+        self.by_agent
+            .entry(dims.agent_id)
+            .or_default()
+            .merge(bucket);
+        self.by_model
+            .entry(dims.model.clone())
+            .or_default()
+            .merge(bucket);
+    }
+}
+
+type RollupTable = BTreeMap<u64, HashMap<RollupDims, RollupBucket>>;
+
+/// Rows of one rollup bucket, as persisted by the storage layer
+pub type RollupRows = Vec<(RollupDims, RollupBucket)>;
+
+/// Continuously maintained minute / hour / day rollup tables
+pub struct RollupTables {
+    tables: [RwLock<RollupTable>; 3],
+    /// Buckets changed since the last [`RollupTables::take_dirty`]
+    dirty: Mutex<BTreeSet<(RollupGranularity, u64)>>,
+    /// Start of the oldest bucket still kept, per granularity
+    retained_from: [AtomicU64; 3],
+}
+
+impl RollupTables {
+    pub fn new() -> Self {
+        Self {
+            tables: Default::default(),
+            dirty: Mutex::new(BTreeSet::new()),
+            retained_from: Default::default(),
+        }
+    }
+
+    /// Add one edge to every rollup table
+    pub fn record(&self, sample: &RollupSample) {
+        let mut dirty = self.dirty.lock();
+        for granularity in RollupGranularity::ALL {
+            let bucket_ts = granularity.align(sample.timestamp_us);
+            self.tables[granularity.index()]
+                .write()
+                .entry(bucket_ts)
+                .or_default()
+                .entry(sample.dims.clone())
+                .or_default()
+                .record(sample);
+            dirty.insert((granularity, bucket_ts));
+        }
+    }
+
+    /// Count the cost stored for an already recorded edge
+    ///
+    /// `sample.cost_micros` is the new cost, `previous_micros` the one stored
+    /// before, if any. Buckets that were pruned are left alone.
+    pub fn reprice(&self, sample: &RollupSample, previous_micros: Option<u64>) {
+        let Some(cost_micros) = sample.cost_micros else {
+            return;
+        };
+        let mut dirty = self.dirty.lock();
+        for granularity in RollupGranularity::ALL {
+            let bucket_ts = granularity.align(sample.timestamp_us);
+            let mut table = self.tables[granularity.index()].write();
+            let Some(bucket) = table
+                .get_mut(&bucket_ts)
+                .and_then(|rows| rows.get_mut(&sample.dims))
+            else {
+                continue;
+            };
+            bucket.reprice(sample.tokens, previous_micros, cost_micros);
+            dirty.insert((granularity, bucket_ts));
+        }
+    }
+
+    /// Summarize `[start_us, end_us)` for `filter`
+    ///
+    /// `raw` returns the samples of a half-open sub-range that is not covered
+    /// by whole rollup buckets.
+    pub fn summarize<F>(
+        &self,
+        start_us: u64,
+        end_us: u64,
+        filter: &RollupFilter,
+        mut raw: F,
+    ) -> Result<RollupSummary>
+    where
+        F: FnMut(u64, u64) -> Result<Vec<RollupSample>>,
+    {
+        let mut summary = RollupSummary::default();
+        let mut raw_ranges = Vec::new();
+
+        for segment in plan_rollup_segments(start_us, end_us) {
+            match segment {
+                RollupSegment::Rollup {
+                    granularity,
+                    start_us,
+                    end_us,
+                } => {
+                    // Pruned buckets are no longer available at this granularity
+                    let retained = self.retained_from[granularity.index()].load(Ordering::Acquire);
+                    if start_us < retained {
+                        raw_ranges.push((start_us, end_us.min(retained)));
+                    }
+                    let from = start_us.max(retained);
+                    if from >= end_us {
+                        continue;
+                    }
+                    let table = self.tables[granularity.index()].read();
+                    for rows in table.range(from..end_us).map(|(_, rows)| rows) {
+                        for (dims, bucket) in rows.iter().filter(|(d, _)| filter.matches(d)) {
+                            summary.add(dims, bucket);
+                        }
+                    }
+                }
+                RollupSegment::Raw { start_us, end_us } => raw_ranges.push((start_us, end_us)),
+            }
+        }
+
+        for (start_us, end_us) in raw_ranges.into_iter().filter(|(s, e)| s < e) {
+            for sample in raw(start_us, end_us)? {
+                if sample.timestamp_us < start_us
+                    || sample.timestamp_us >= end_us
+                    || !filter.matches(&sample.dims)
+                {
+                    continue;
+                }
+                let mut bucket = RollupBucket::default();
+                bucket.record(&sample);
+                summary.add(&sample.dims, &bucket);
+                summary.raw_edges += 1;
+            }
+        }
+
+        Ok(summary)
+    }
+
+    /// Drop buckets that start before `before_us`, returning their timestamps
+    pub fn prune(&self, granularity: RollupGranularity, before_us: u64) -> Vec<u64> {
+        let before_us = granularity.align(before_us);
+        let mut table = self.tables[granularity.index()].write();
+        let kept = table.split_off(&before_us);
+        let pruned: Vec<u64> = std::mem::replace(&mut *table, kept).into_keys().collect();
+        self.retained_from[granularity.index()].fetch_max(before_us, Ordering::AcqRel);
+        pruned
+    }
+
+    /// Buckets changed since the previous call, for persistence
+    pub fn take_dirty(&self) -> Vec<(RollupGranularity, u64, RollupRows)> {
+        let dirty = std::mem::take(&mut *self.dirty.lock());
+        dirty
+            .into_iter()
+            .filter_map(|(granularity, bucket_ts)| {
+                let table = self.tables[granularity.index()].read();
+                let rows = table.get(&bucket_ts)?;
+                Some((
+                    granularity,
+                    bucket_ts,
+                    rows.iter().map(|(d, b)| (d.clone(), b.clone())).collect(),
+                ))
+            })
+            .collect()
+    }
+
+    /// Restore a persisted bucket
+    pub fn load_bucket(&self, granularity: RollupGranularity, bucket_ts: u64, rows: RollupRows) {
+        self.tables[granularity.index()]
+            .write()
+            .insert(bucket_ts, rows.into_iter().collect());
+    }
+
+    /// Edges recorded per bucket, oldest first, as `(start_us, end_us, edges)`
+    ///
+    /// Hour buckets where they are still kept, day buckets before them. The
+    /// counts are of recorded edges; deleted ones are not subtracted.
+    pub fn edge_counts(&self) -> Vec<(u64, u64, u64)> {
+        let hours = self.tables[RollupGranularity::Hour.index()].read();
+        let days = self.tables[RollupGranularity::Day.index()].read();
+        // Hours of the day the hour table starts in are covered by its day bucket
+        let boundary = hours
+            .keys()
+            .next()
+            .map(|first| RollupGranularity::Day.align(first + DAY_US - 1))
+            .unwrap_or(u64::MAX);
+        let edges = |rows: &HashMap<RollupDims, RollupBucket>| {
+            rows.values()
+                .map(|bucket| bucket.request_count)
+                .sum::<u64>()
+        };
+        days.range(..boundary)
+            .map(|(ts, rows)| (*ts, ts + DAY_US, edges(rows)))
+            .chain(
+                hours
+                    .range(boundary..)
+                    .map(|(ts, rows)| (*ts, ts + HOUR_US, edges(rows))),
+            )
+            .filter(|(_, _, edges)| *edges > 0)
+            .collect()
+    }
+
+    /// Number of buckets per granularity (minute, hour, day)
+    pub fn bucket_counts(&self) -> (usize, usize, usize) {
+        (
+            self.tables[0].read().len(),
+            self.tables[1].read().len(),
+            self.tables[2].read().len(),
+        )
+    }
+}
+
+impl Default for RollupTables {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -438,4 +969,212 @@ mod tests {
         let session_edges = agg.get_session_edges(42);
         assert_eq!(session_edges, vec![1]);
     }
+
+    fn sample(timestamp_us: u64, model: &str, duration_us: u64) -> RollupSample {
+        RollupSample {
+            dims: RollupDims {
+                tenant_id: 1,
+                project_id: 2,
+                agent_id: 3,
+                model: model.to_string(),
+            },
+            timestamp_us,
+            duration_us,
+            tokens: 10,
+            is_error: duration_us > 1_000_000,
+            cost_micros: None,
+        }
+    }
+
+    #[test]
+    fn test_plan_rollup_segments() {
+        let start = DAY_US + 90 * MINUTE_US + 5;
+        let end = 3 * DAY_US + 2 * HOUR_US + 3 * MINUTE_US + 7;
+        let segments = plan_rollup_segments(start, end);
+
+        let granularity = |g| RollupSegment::Rollup {
+            granularity: g,
+            start_us: 0,
+            end_us: 0,
+        };
+        let shape: Vec<_> = segments
+            .iter()
+            .map(|s| match *s {
+                RollupSegment::Rollup { granularity: g, .. } => granularity(g),
+                RollupSegment::Raw { .. } => RollupSegment::Raw {
+                    start_us: 0,
+                    end_us: 0,
+                },
+            })
+            .collect();
+        let raw = RollupSegment::Raw {
+            start_us: 0,
+            end_us: 0,
+        };
+        assert_eq!(
+            shape,
+            vec![
+                raw,
+                granularity(RollupGranularity::Minute),
+                granularity(RollupGranularity::Hour),
+                granularity(RollupGranularity::Day),
+                granularity(RollupGranularity::Hour),
+                granularity(RollupGranularity::Minute),
+                raw,
+            ]
+        );
+
+        // Segments tile the window without gaps
+        let mut cursor = start;
+        for segment in &segments {
+            let (s, e) = match *segment {
+                RollupSegment::Rollup {
+                    start_us, end_us, ..
+                }
+                | RollupSegment::Raw { start_us, end_us } => (start_us, end_us),
+            };
+            assert_eq!(s, cursor);
+            cursor = e;
+        }
+        assert_eq!(cursor, end);
+
+        assert_eq!(
+            plan_rollup_segments(10, 20),
+            vec![RollupSegment::Raw {
+                start_us: 10,
+                end_us: 20
+            }]
+        );
+    }
+
+    #[test]
+    fn test_rollup_summary_merges_raw_edges() {
+        let tables = RollupTables::new();
+        let samples = vec![
+            sample(HOUR_US + 30 * 1_000_000, "gpt-4o", 100_000),
+            sample(HOUR_US + 5 * MINUTE_US, "gpt-4o", 200_000),
+            sample(2 * HOUR_US + 10 * MINUTE_US, "claude-3", 2_000_000),
+            sample(
+                2 * HOUR_US + 11 * MINUTE_US + 20 * 1_000_000,
+                "claude-3",
+                300_000,
+            ),
+        ];
+        for s in &samples {
+            tables.record(s);
+        }
+
+        let filter = RollupFilter {
+            tenant_id: 1,
+            ..Default::default()
+        };
+        let start = HOUR_US + 10 * 1_000_000;
+        let end = 2 * HOUR_US + 11 * MINUTE_US + 30 * 1_000_000;
+        let raw = |from: u64, to: u64| {
+            Ok(samples
+                .iter()
+                .filter(|s| s.timestamp_us >= from && s.timestamp_us < to)
+                .cloned()
+                .collect())
+        };
+        let summary = tables.summarize(start, end, &filter, raw).unwrap();
+
+        assert_eq!(summary.totals.request_count, 4);
+        assert_eq!(summary.totals.error_count, 1);
+        assert_eq!(summary.totals.total_tokens, 40);
+        assert_eq!(summary.raw_edges, 2);
+        assert_eq!(summary.by_model["claude-3"].request_count, 2);
+
+        // Rows outside the filter are skipped
+        let other_tenant = RollupFilter {
+            tenant_id: 9,
+            ..Default::default()
+        };
+        let empty = tables
+            .summarize(0, 3 * HOUR_US, &other_tenant, |_, _| Ok(Vec::new()))
+            .unwrap();
+        assert_eq!(empty.totals.request_count, 0);
+
+        assert_eq!(
+            tables.prune(RollupGranularity::Minute, 2 * HOUR_US).len(),
+            2
+        );
+        let summary = tables.summarize(start, end, &filter, raw).unwrap();
+        assert_eq!(summary.totals.request_count, 4);
+        assert_eq!(summary.raw_edges, 3);
+        assert_eq!(tables.take_dirty().len(), 5);
+        assert!(tables.take_dirty().is_empty());
+    }
+
+    #[test]
+    fn test_repriced_cost_replaces_derived_cost() {
+        let tables = RollupTables::new();
+        let first = sample(HOUR_US + MINUTE_US, "gpt-4o", 1_000);
+        tables.record(&first);
+        tables.record(&sample(HOUR_US + 2 * MINUTE_US, "gpt-4o", 1_000));
+        tables.take_dirty();
+
+        let repriced = RollupSample {
+            cost_micros: Some(500),
+            ..first.clone()
+        };
+        tables.reprice(&repriced, None);
+        // Recomputing again replaces the cost rather than adding to it
+        tables.reprice(
+            &RollupSample {
+                cost_micros: Some(700),
+                ..first
+            },
+            Some(500),
+        );
+        assert_eq!(tables.take_dirty().len(), 3);
+
+        let filter = RollupFilter {
+            tenant_id: 1,
+            ..Default::default()
+        };
+        let summary = tables
+            .summarize(HOUR_US, 2 * HOUR_US, &filter, |_, _| Ok(Vec::new()))
+            .unwrap();
+        assert_eq!(summary.totals.repriced_tokens, 10);
+        assert_eq!(summary.totals.repriced_cost_micros, 700);
+        assert_eq!(summary.totals.unpriced_tokens(), 10);
+
+        // Raw edges carry their stored cost
+        let summary = tables
+            .summarize(HOUR_US + 1, HOUR_US + 2, &filter, |_, _| {
+                Ok(vec![RollupSample {
+                    timestamp_us: HOUR_US + 1,
+                    cost_micros: Some(42),
+                    ..sample(0, "gpt-4o", 1_000)
+                }])
+            })
+            .unwrap();
+        assert_eq!(summary.totals.repriced_cost_micros, 42);
+        assert_eq!(summary.totals.unpriced_tokens(), 0);
+    }
+
+    #[test]
+    fn test_edge_counts_fall_back_to_days() {
+        let tables = RollupTables::new();
+        for ts in [
+            HOUR_US,
+            DAY_US + 2 * HOUR_US,
+            2 * DAY_US + 5 * HOUR_US,
+            2 * DAY_US + 5 * HOUR_US + MINUTE_US,
+        ] {
+            tables.record(&sample(ts, "gpt-4o", 1_000));
+        }
+        tables.prune(RollupGranularity::Hour, DAY_US + HOUR_US);
+
+        // Day 1 still has hour buckets, but only from its third hour
+        assert_eq!(
+            tables.edge_counts(),
+            vec![
+                (0, DAY_US, 1),
+                (DAY_US, 2 * DAY_US, 1),
+                (2 * DAY_US + 5 * HOUR_US, 2 * DAY_US + 6 * HOUR_US, 2),
+            ]
+        );
+    }
 }
@@ -0,0 +1,563 @@
+// Copyright 2025 AgentReplay (https://github.com/agentreplay)
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program. If not, see <https://www.gnu.org/licenses/>.
+
+//! Snapshot replication through a shared [`StorageBackend`]
+//!
+//! Multi-node deployments run a single writer and any number of read
+//! replicas that share an object store (or shared filesystem):
+//!
+//! ```text
+//! writer:  data_dir ──publish──► segments/<blake3>      (content addressed)
+//!                                snapshots/<seq>.json   (manifest)
+//!                                snapshots/LATEST
+//! replica: snapshots/LATEST ──sync──► replica_dir/gen-<seq>/
+//! ```
+//!
+//! - **Writer lease**: `cluster/writer.lease` names the node allowed to ingest
+//!   and publish. A lease expires unless renewed; every takeover bumps the
+//!   epoch, and publishing re-checks the stored epoch so a writer that lost its
+//!   lease (e.g. after a long GC pause) cannot overwrite a newer writer's
+//!   snapshots (fencing).
+//! - **Snapshots**: files are uploaded by content hash, so unchanged segments
+//!   are uploaded and downloaded only once.
+//! - **Replicas** materialize each snapshot into a fresh generation directory,
+//!   verify every file's hash and only then flip the local `CURRENT` pointer.
+
+use crate::backend::StorageBackend;
+use agentreplay_core::clock::now_secs;
+use agentreplay_core::{AgentreplayError, Result};
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::Duration;
+
+/// Backend key of the writer lease
+pub const LEASE_KEY: &str = "cluster/writer.lease";
+
+/// Backend key of the most recent snapshot manifest
+pub const LATEST_MANIFEST_KEY: &str = "snapshots/LATEST";
+
+/// Local file (in the replica root) naming the active generation
+const CURRENT_FILE: &str = "CURRENT";
+
+/// Files never shipped in snapshots (process locks, in-flight temp files)
+pub(crate) const EXCLUDED_FILES: &[&str] = &["LOCK", "LOCKFILE", crate::dir_lock::LOCK_FILE_NAME];
+
+/// The single-writer lease stored in the shared backend
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct WriterLease {
+    pub node_id: String,
+    /// Incremented on every change of holder (fencing token)
+    pub epoch: u64,
+    pub acquired_at: u64,
+    pub expires_at: u64,
+}
+
+impl WriterLease {
+    pub fn is_expired(&self) -> bool {
+        now_secs() >= self.expires_at
+    }
+}
+
+/// Result of trying to acquire or renew the lease
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum LeaseOutcome {
+    /// This node holds the lease
+    Acquired(WriterLease),
+    /// Another node holds an unexpired lease
+    HeldBy(WriterLease),
+}
+
+/// Acquires, renews and releases the writer lease for one node
+pub struct LeaseManager {
+    backend: Arc<dyn StorageBackend>,
+    node_id: String,
+    ttl: Duration,
+}
+
+impl LeaseManager {
+    pub fn new(
+        backend: Arc<dyn StorageBackend>,
+        node_id: impl Into<String>,
+        ttl: Duration,
+    ) -> Self {
+        Self {
+            backend,
+            node_id: node_id.into(),
+            ttl,
+        }
+    }
+
+    pub fn node_id(&self) -> &str {
+        &self.node_id
+    }
+
+    /// Read the current lease, if any
+    pub fn current(&self) -> Result<Option<WriterLease>> {
+        if !self.backend.exists(LEASE_KEY)? {
+            return Ok(None);
+        }
+        let bytes = self.backend.get(LEASE_KEY)?;
+        serde_json::from_slice(&bytes)
+            .map(Some)
+            .map_err(|e| AgentreplayError::Serialization(format!("writer lease: {}", e)))
+    }
+
+    /// Take the lease if it is free, expired or already ours (renewal)
+    ///
+    /// The backend has no compare-and-swap, so after writing the lease is read
+    /// back: if another node raced us, whoever's write landed last wins and
+    /// the loser reports `HeldBy`.
+    pub fn try_acquire(&self) -> Result<LeaseOutcome> {
+        let current = self.current()?;
+        let epoch = match &current {
+            Some(lease) if lease.node_id == self.node_id => lease.epoch,
+            Some(lease) if !lease.is_expired() => return Ok(LeaseOutcome::HeldBy(lease.clone())),
+            Some(lease) => lease.epoch + 1,
+            None => 1,
+        };
+
+        let now = now_secs();
+        let lease = WriterLease {
+            node_id: self.node_id.clone(),
+            epoch,
+            acquired_at: match &current {
+                Some(held) if held.node_id == self.node_id => held.acquired_at,
+                _ => now,
+            },
+            expires_at: now + self.ttl.as_secs().max(1),
+        };
+        let bytes = serde_json::to_vec(&lease)
+            .map_err(|e| AgentreplayError::Serialization(e.to_string()))?;
+        self.backend.put(LEASE_KEY, &bytes)?;
+        self.backend.sync()?;
+
+        match self.current()? {
+            Some(stored) if stored == lease => Ok(LeaseOutcome::Acquired(lease)),
+            Some(stored) => Ok(LeaseOutcome::HeldBy(stored)),
+            None => Err(AgentreplayError::Internal(
+                "writer lease vanished after write".to_string(),
+            )),
+        }
+    }
+
+    /// Verify `lease` is still the stored lease (fencing check before writes)
+    pub fn verify(&self, lease: &WriterLease) -> Result<()> {
+        match self.current()? {
+            Some(stored) if stored.node_id == lease.node_id && stored.epoch == lease.epoch => {
+                Ok(())
+            }
+            Some(stored) => Err(AgentreplayError::InvalidArgument(format!(
+                "writer lease lost to node '{}' (epoch {})",
+                stored.node_id, stored.epoch
+            ))),
+            None => Err(AgentreplayError::InvalidArgument(
+                "writer lease no longer exists".to_string(),
+            )),
+        }
+    }
+
+    /// Give up the lease if this node holds it
+    ///
+    /// The lease is expired rather than deleted so the next holder still gets
+    /// a higher epoch.
+    pub fn release(&self) -> Result<()> {
+        if let Some(mut lease) = self.current()? {
+            if lease.node_id == self.node_id {
+                lease.expires_at = now_secs();
+                let bytes = serde_json::to_vec(&lease)
+                    .map_err(|e| AgentreplayError::Serialization(e.to_string()))?;
+                self.backend.put(LEASE_KEY, &bytes)?;
+                self.backend.sync()?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// One file in a snapshot
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SnapshotFile {
+    /// Path relative to the data directory
+    pub path: String,
+    pub size: u64,
+    /// blake3 hex digest; also the segment's backend key
+    pub hash: String,
+}
+
+/// Description of a published snapshot
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SnapshotManifest {
+    pub seq: u64,
+    pub epoch: u64,
+    pub writer_node_id: String,
+    pub created_at: u64,
+    pub files: Vec<SnapshotFile>,
+}
+
+impl SnapshotManifest {
+    pub fn total_bytes(&self) -> u64 {
+        self.files.iter().map(|f| f.size).sum()
+    }
+}
+
+fn segment_key(hash: &str) -> String {
+    format!("segments/{}", hash)
+}
+
+fn manifest_key(seq: u64) -> String {
+    format!("snapshots/{:020}.json", seq)
+}
+
+/// Read the latest published manifest from the backend
+pub fn latest_manifest(backend: &dyn StorageBackend) -> Result<Option<SnapshotManifest>> {
+    if !backend.exists(LATEST_MANIFEST_KEY)? {
+        return Ok(None);
+    }
+    let bytes = backend.get(LATEST_MANIFEST_KEY)?;
+    serde_json::from_slice(&bytes)
+        .map(Some)
+        .map_err(|e| AgentreplayError::Serialization(format!("snapshot manifest: {}", e)))
+}
+
+/// Statistics for one publish
+#[derive(Debug, Clone, Serialize)]
+pub struct PublishStats {
+    pub seq: u64,
+    pub files: usize,
+    pub uploaded_files: usize,
+    pub uploaded_bytes: u64,
+}
+
+/// Uploads snapshots of the writer's data directory
+pub struct SnapshotPublisher {
+    backend: Arc<dyn StorageBackend>,
+    source_dir: PathBuf,
+}
+
+impl SnapshotPublisher {
+    pub fn new(backend: Arc<dyn StorageBackend>, source_dir: impl AsRef<Path>) -> Self {
+        Self {
+            backend,
+            source_dir: source_dir.as_ref().to_path_buf(),
+        }
+    }
+
+    /// Publish the current contents of the data directory
+    ///
+    /// Callers should checkpoint the database first so the files on disk are
+    /// consistent. Fails without touching `LATEST` if `lease` is no longer held.
+    pub fn publish(&self, leases: &LeaseManager, lease: &WriterLease) -> Result<PublishStats> {
+        leases.verify(lease)?;
+
+        let mut files = Vec::new();
+        collect_files(&self.source_dir, &self.source_dir, &mut files)?;
+        files.sort();
+
+        let mut manifest_files = Vec::with_capacity(files.len());
+        let mut uploaded_files = 0;
+        let mut uploaded_bytes = 0;
+        for relative in files {
+            let data = std::fs::read(self.source_dir.join(&relative))?;
+            let hash = blake3::hash(&data).to_hex().to_string();
+            let key = segment_key(&hash);
+            if !self.backend.exists(&key)? {
+                self.backend.put(&key, &data)?;
+                uploaded_files += 1;
+                uploaded_bytes += data.len() as u64;
+            }
+            manifest_files.push(SnapshotFile {
+                path: relative,
+                size: data.len() as u64,
+                hash,
+            });
+        }
+
+        let seq = latest_manifest(self.backend.as_ref())?
+            .map(|m| m.seq + 1)
+            .unwrap_or(1);
+        let manifest = SnapshotManifest {
+            seq,
+            epoch: lease.epoch,
+            writer_node_id: lease.node_id.clone(),
+            created_at: now_secs(),
+            files: manifest_files,
+        };
+        let bytes = serde_json::to_vec(&manifest)
+            .map_err(|e| AgentreplayError::Serialization(e.to_string()))?;
+        self.backend.put(&manifest_key(seq), &bytes)?;
+        self.backend.sync()?;
+
+        // Fencing: only advance LATEST if nobody took over while uploading
+        leases.verify(lease)?;
+        self.backend.put(LATEST_MANIFEST_KEY, &bytes)?;
+        self.backend.sync()?;
+
+        Ok(PublishStats {
+            seq,
+            files: manifest.files.len(),
+            uploaded_files,
+            uploaded_bytes,
+        })
+    }
+}
+
+fn collect_files(root: &Path, dir: &Path, out: &mut Vec<String>) -> Result<()> {
+    if !dir.exists() {
+        return Ok(());
+    }
+    for entry in std::fs::read_dir(dir)? {
+        let entry = entry?;
+        let path = entry.path();
+        if entry.file_type()?.is_dir() {
+            collect_files(root, &path, out)?;
+            continue;
+        }
+
+        let name = entry.file_name().to_string_lossy().to_string();
+        if EXCLUDED_FILES.contains(&name.as_str()) || name.ends_with(".tmp") {
+            continue;
+        }
+        if let Ok(relative) = path.strip_prefix(root) {
+            out.push(relative.to_string_lossy().replace('\\', "/"));
+        }
+    }
+    Ok(())
+}
+
+/// Result of a replica sync
+#[derive(Debug, Clone, Serialize)]
+pub struct SyncOutcome {
+    /// Snapshot now materialized locally (None if nothing was published yet)
+    pub seq: Option<u64>,
+    /// Whether a new generation was materialized by this call
+    pub updated: bool,
+    /// Seconds between the snapshot's creation and now
+    pub lag_secs: Option<u64>,
+    pub downloaded_bytes: u64,
+}
+
+/// Materializes published snapshots into local generation directories
+pub struct SnapshotSyncer {
+    backend: Arc<dyn StorageBackend>,
+    root: PathBuf,
+}
+
+impl SnapshotSyncer {
+    pub fn new(backend: Arc<dyn StorageBackend>, root: impl AsRef<Path>) -> Self {
+        Self {
+            backend,
+            root: root.as_ref().to_path_buf(),
+        }
+    }
+
+    /// Active local generation: (snapshot seq, directory)
+    pub fn current_generation(&self) -> Option<(u64, PathBuf)> {
+        let seq = std::fs::read_to_string(self.root.join(CURRENT_FILE))
+            .ok()?
+            .trim()
+            .parse()
+            .ok()?;
+        Some((seq, self.generation_dir(seq)))
+    }
+
+    fn generation_dir(&self, seq: u64) -> PathBuf {
+        self.root.join(format!("gen-{:020}", seq))
+    }
+
+    /// Download the latest snapshot if it is newer than the active generation
+    pub fn sync(&self) -> Result<SyncOutcome> {
+        let current = self.current_generation().map(|(seq, _)| seq);
+        let Some(manifest) = latest_manifest(self.backend.as_ref())? else {
+            return Ok(SyncOutcome {
+                seq: current,
+                updated: false,
+                lag_secs: None,
+                downloaded_bytes: 0,
+            });
+        };
+        let lag_secs = Some(now_secs().saturating_sub(manifest.created_at));
+
+        if current.is_some_and(|seq| seq >= manifest.seq) {
+            return Ok(SyncOutcome {
+                seq: current,
+                updated: false,
+                lag_secs,
+                downloaded_bytes: 0,
+            });
+        }
+
+        let target = self.generation_dir(manifest.seq);
+        let staging = self.root.join(format!("gen-{:020}.tmp", manifest.seq));
+        if staging.exists() {
+            std::fs::remove_dir_all(&staging)?;
+        }
+
+        let previous = self.current_generation().map(|(_, dir)| dir);
+        let mut downloaded_bytes = 0;
+        for file in &manifest.files {
+            let dest = staging.join(&file.path);
+            if let Some(parent) = dest.parent() {
+                std::fs::create_dir_all(parent)?;
+            }
+
+            // Reuse identical files from the previous generation
+            let reused = previous
+                .as_ref()
+                .map(|dir| dir.join(&file.path))
+                .filter(|p| {
+                    std::fs::read(p)
+                        .map(|data| blake3::hash(&data).to_hex().as_str() == file.hash)
+                        .unwrap_or(false)
+                });
+            if let Some(source) = reused {
+                std::fs::copy(source, &dest)?;
+                continue;
+            }
+
+            let data = self.backend.get(&segment_key(&file.hash))?;
+            if blake3::hash(&data).to_hex().as_str() != file.hash {
+                return Err(AgentreplayError::Corruption(format!(
+                    "segment for '{}' in snapshot {} failed hash verification",
+                    file.path, manifest.seq
+                )));
+            }
+            downloaded_bytes += data.len() as u64;
+            std::fs::write(&dest, &data)?;
+        }
+
+        if target.exists() {
+            std::fs::remove_dir_all(&target)?;
+        }
+        std::fs::rename(&staging, &target)?;
+
+        let current_tmp = self.root.join(format!("{}.tmp", CURRENT_FILE));
+        std::fs::write(&current_tmp, manifest.seq.to_string())?;
+        std::fs::rename(&current_tmp, self.root.join(CURRENT_FILE))?;
+
+        Ok(SyncOutcome {
+            seq: Some(manifest.seq),
+            updated: true,
+            lag_secs,
+            downloaded_bytes,
+        })
+    }
+
+    /// Remove generations older than the active one, except `keep` most recent
+    pub fn prune(&self, keep: usize) -> Result<usize> {
+        let Some((current, _)) = self.current_generation() else {
+            return Ok(0);
+        };
+
+        let mut generations: Vec<u64> = std::fs::read_dir(&self.root)?
+            .filter_map(|entry| entry.ok())
+            .filter_map(|entry| {
+                let name = entry.file_name().to_string_lossy().to_string();
+                name.strip_prefix("gen-")?.parse::<u64>().ok()
+            })
+            .filter(|seq| *seq < current)
+            .collect();
+        generations.sort_unstable_by(|a, b| b.cmp(a));
+
+        let mut removed = 0;
+        for seq in generations.into_iter().skip(keep) {
+            std::fs::remove_dir_all(self.generation_dir(seq))?;
+            removed += 1;
+        }
+        Ok(removed)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::backend::LocalFsBackend;
+    use tempfile::TempDir;
+
+    fn shared() -> (TempDir, Arc<dyn StorageBackend>) {
+        let dir = TempDir::new().unwrap();
+        let backend = Arc::new(LocalFsBackend::new(dir.path()).unwrap());
+        (dir, backend)
+    }
+
+    #[test]
+    fn test_single_writer_lease() {
+        let (_dir, backend) = shared();
+        let a = LeaseManager::new(backend.clone(), "node-a", Duration::from_secs(30));
+        let b = LeaseManager::new(backend.clone(), "node-b", Duration::from_secs(30));
+
+        let LeaseOutcome::Acquired(lease) = a.try_acquire().unwrap() else {
+            panic!("node-a should acquire a free lease");
+        };
+        assert_eq!(lease.epoch, 1);
+        assert!(
+            matches!(b.try_acquire().unwrap(), LeaseOutcome::HeldBy(held) if held.node_id == "node-a")
+        );
+
+        // Renewal keeps the epoch
+        assert!(
+            matches!(a.try_acquire().unwrap(), LeaseOutcome::Acquired(renewed) if renewed.epoch == 1)
+        );
+
+        // After release node-b takes over with a new epoch, fencing node-a
+        a.release().unwrap();
+        assert!(
+            matches!(b.try_acquire().unwrap(), LeaseOutcome::Acquired(taken) if taken.epoch == 2)
+        );
+        assert!(a.verify(&lease).is_err());
+    }
+
+    #[test]
+    fn test_publish_and_sync() {
+        let (_shared_dir, backend) = shared();
+        let data_dir = TempDir::new().unwrap();
+        let replica_dir = TempDir::new().unwrap();
+        std::fs::create_dir_all(data_dir.path().join("sst")).unwrap();
+        std::fs::write(data_dir.path().join("sst/000001.sst"), b"segment-1").unwrap();
+        std::fs::write(data_dir.path().join("MANIFEST"), b"v1").unwrap();
+        std::fs::write(data_dir.path().join("LOCK"), b"").unwrap();
+
+        let leases = LeaseManager::new(backend.clone(), "writer", Duration::from_secs(30));
+        let LeaseOutcome::Acquired(lease) = leases.try_acquire().unwrap() else {
+            panic!("lease should be free");
+        };
+        let publisher = SnapshotPublisher::new(backend.clone(), data_dir.path());
+        let syncer = SnapshotSyncer::new(backend.clone(), replica_dir.path());
+
+        let first = publisher.publish(&leases, &lease).unwrap();
+        assert_eq!(first.files, 2);
+        assert_eq!(first.uploaded_files, 2);
+
+        let outcome = syncer.sync().unwrap();
+        assert!(outcome.updated);
+        let (seq, dir) = syncer.current_generation().unwrap();
+        assert_eq!(seq, 1);
+        assert_eq!(
+            std::fs::read(dir.join("sst/000001.sst")).unwrap(),
+            b"segment-1"
+        );
+        assert!(!dir.join("LOCK").exists());
+
+        // Only the changed file is uploaded again
+        std::fs::write(data_dir.path().join("MANIFEST"), b"v2").unwrap();
+        let second = publisher.publish(&leases, &lease).unwrap();
+        assert_eq!(second.seq, 2);
+        assert_eq!(second.uploaded_files, 1);
+
+        assert!(syncer.sync().unwrap().updated);
+        assert!(!syncer.sync().unwrap().updated);
+        assert_eq!(syncer.prune(0).unwrap(), 1);
+    }
+}
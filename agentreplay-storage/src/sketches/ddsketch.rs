@@ -44,7 +44,7 @@ const MAX_INDEXABLE_VALUE: f64 = 1e300;
 ///
 /// Provides relative accuracy guarantees: for quantile q, returned value v satisfies:
 ///     actual * (1 - α) ≤ v ≤ actual * (1 + α)
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
 pub struct DDSketch {
     /// Relative accuracy parameter (e.g., 0.01 for 1%)
     #[allow(dead_code)]
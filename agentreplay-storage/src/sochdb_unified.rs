@@ -44,7 +44,17 @@
 //! - Metrics: `metrics/{granularity}/{tenant_id}/{project_id}/{timestamp:020}`
 //! - Graph: `graph/{direction}/{node_id:032x}/{related_id:032x}`
 
-use agentreplay_core::{AgentFlowEdge, AgentreplayError, Result};
+use crate::metrics_agg::{
+    RollupFilter, RollupGranularity, RollupRows, RollupSample, RollupSummary, RollupTables,
+    HOUR_ROLLUP_RETENTION_US, MINUTE_ROLLUP_RETENTION_US,
+};
+use crate::logprobs::{
+    decode_logprob_summary, decode_logprobs, encode_logprobs, LogprobSummary, SpanLogprobs,
+    TokenLogprob,
+};
+use agentreplay_core::clock::now_us;
+use agentreplay_core::{AgentFlowEdge, AgentreplayError, Result, SpanLink};
+use agentreplay_core::storage_metrics::{WriteTelemetry, WriteTelemetryReport};
 use parking_lot::RwLock;
 use sochdb::EmbeddedConnection as Connection;
 use sochdb_storage::{PackedRow, PackedColumnDef, PackedColumnType, PackedTableSchema};
@@ -108,6 +118,8 @@ pub const PAYLOAD_PREFIX: &str = "payloads";
 pub const METRICS_PREFIX: &str = "metrics";
 /// Key prefix for graph edges
 pub const GRAPH_PREFIX: &str = "graph";
+/// Key prefix for materialized rollups
+pub const ROLLUP_PREFIX: &str = "rollups";
 
 /// Encode a trace key from edge components
 pub fn encode_trace_key(tenant_id: u64, project_id: u16, timestamp_us: u64, edge_id: u128) -> String {
@@ -150,6 +162,11 @@ pub fn encode_metrics_key(granularity: &str, tenant_id: u64, project_id: u16, ti
     )
 }
 
+/// Encode a rollup bucket key
+pub fn encode_rollup_key(granularity: RollupGranularity, bucket_ts: u64) -> String {
+    format!("{}/{}/{:020}", ROLLUP_PREFIX, granularity.as_str(), bucket_ts)
+}
+
 /// Create a scan prefix for a tenant/project time range
 pub fn trace_scan_prefix(tenant_id: u64, project_id: u16) -> String {
     format!("{}/{}/{}/", TRACE_PREFIX, tenant_id, project_id)
@@ -429,6 +446,369 @@ impl HealthCheckResult {
     }
 }
 
+/// Structured fields derived from raw span attributes at ingest time
+///
+/// Stored per edge next to the filter attributes (`idx/enriched/{edge_id}`)
+/// so list filters on these fields need no payload I/O.
+#[derive(Debug, Clone, Default, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub struct EdgeEnrichment {
+    /// Model family, e.g. "gpt", "claude", "gemini"
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub model_family: Option<String>,
+    /// Normalized provider, e.g. "openai", "anthropic", "google"
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub provider: Option<String>,
+    /// Client SDK that sent the span
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub sdk_name: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub sdk_version: Option<String>,
+    /// ISO 3166-1 alpha-2 country of the calling client
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub country: Option<String>,
+}
+
+impl EdgeEnrichment {
+    pub fn is_empty(&self) -> bool {
+        *self == Self::default()
+    }
+}
+
+/// Goal-completion verdict of an analyzed session
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum GoalVerdict {
+    Completed,
+    NotCompleted,
+    /// No completion signal either way
+    Unknown,
+}
+
+/// Topics and goal completion of a finished session
+///
+/// Written per session (`idx/session_analysis/{session_id}`) by the server's
+/// session analyzer and aggregated into goal-completion funnels.
+#[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
+pub struct SessionAnalysis {
+    pub session_id: u64,
+    pub tenant_id: u64,
+    pub project_id: u16,
+    pub started_at: u64,
+    /// Timestamp of the last span covered by the analysis
+    pub ended_at: u64,
+    pub turn_count: u32,
+    /// Main topics, most prominent first
+    pub topics: Vec<String>,
+    /// 0 when the session ends on its opening topics, 1 when it drifted to
+    /// unrelated ones
+    pub topic_drift: f32,
+    pub goal: GoalVerdict,
+    /// Why the verdict was reached
+    pub reason: String,
+    /// Prompt versions used in the session, as `name@version`
+    #[serde(default)]
+    pub prompt_versions: Vec<String>,
+    /// "heuristic" or "llm"
+    pub method: String,
+    pub analyzed_at: u64,
+}
+
+/// Running totals of a session, updated as its spans are ingested
+///
+/// Written per session (`idx/session_summary/{tenant_id}/{session_id}`) so
+/// session lists can be served and sorted without scanning spans.
+#[derive(Debug, Clone, Default, PartialEq, serde::Serialize, serde::Deserialize)]
+pub struct SessionRollup {
+    pub session_id: u64,
+    pub tenant_id: u64,
+    pub project_id: u16,
+    pub agent_id: u64,
+    pub started_at: u64,
+    /// Start of the most recent span
+    pub last_span_at: u64,
+    /// End of the latest-ending span
+    pub ended_at: u64,
+    pub span_count: u64,
+    /// LLM calls (spans with a model)
+    pub turn_count: u32,
+    pub total_tokens: u64,
+    pub cost_usd: f64,
+    pub error_count: u32,
+    /// Sum of span durations
+    pub span_duration_us: u64,
+    /// Models used, in order of first use
+    pub models: Vec<String>,
+    /// Latest score per eval metric recorded on the session's spans
+    #[serde(default)]
+    pub eval_scores: std::collections::BTreeMap<String, f64>,
+    pub updated_at: u64,
+}
+
+impl SessionRollup {
+    /// Wall-clock duration from the first span's start to the last span's end
+    pub fn duration_us(&self) -> u64 {
+        self.ended_at.saturating_sub(self.started_at)
+    }
+}
+
+/// Marker written once every pre-existing session has a [`SessionRollup`]
+const SESSION_SUMMARIES_READY_KEY: &str = "meta/session_summaries_ready";
+
+/// Membership of a session (or, for spans without a session, a single
+/// trace) in a logical conversation
+///
+/// Written when a span carrying a correlation attribute is ingested, under
+/// `idx/conversation/{tenant_id}/{hex(conversation_id)}/…`.
+#[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
+pub struct ConversationLink {
+    pub conversation_id: String,
+    pub tenant_id: u64,
+    pub project_id: u16,
+    /// 0 when the link is to a single trace
+    pub session_id: u64,
+    /// First span seen with the correlation attribute
+    pub edge_id: u128,
+    /// End user the conversation belongs to, when reported
+    #[serde(default)]
+    pub user_id: Option<String>,
+    pub linked_at: u64,
+}
+
+fn conversation_prefix(tenant_id: u64, conversation_id: &str) -> String {
+    format!(
+        "idx/conversation/{:016x}/{}/",
+        tenant_id,
+        hex::encode(conversation_id.as_bytes())
+    )
+}
+
+/// Structured end-user feedback on a trace
+///
+/// Stored under `idx/feedback/{tenant_id}/{edge_id}/{created_at}`, so a
+/// trace can collect feedback from several users over time.
+#[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
+pub struct FeedbackRecord {
+    pub edge_id: u128,
+    pub tenant_id: u64,
+    pub project_id: u16,
+    /// 1 for thumbs up, -1 for thumbs down
+    #[serde(default)]
+    pub thumbs: Option<i8>,
+    /// Star rating from 1 to 5
+    #[serde(default)]
+    pub rating: Option<u8>,
+    #[serde(default)]
+    pub tags: Vec<String>,
+    #[serde(default)]
+    pub comment: Option<String>,
+    #[serde(default)]
+    pub user_id: Option<String>,
+    pub created_at: u64,
+}
+
+impl FeedbackRecord {
+    /// Feedback as a score in [0, 1]: the rating when given, else the thumbs
+    pub fn score(&self) -> Option<f64> {
+        match (self.rating, self.thumbs) {
+            (Some(rating), _) => Some((rating.clamp(1, 5) - 1) as f64 / 4.0),
+            (None, Some(thumbs)) => Some(if thumbs > 0 { 1.0 } else { 0.0 }),
+            (None, None) => None,
+        }
+    }
+}
+
+/// A provider API key stored in the tenant's vault
+///
+/// The secret is sealed by the server before it reaches storage; only the
+/// nonce, ciphertext and a short hint of the plaintext are persisted.
+#[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
+pub struct ProviderKeyRecord {
+    pub tenant_id: u64,
+    pub alias: String,
+    /// Provider the key belongs to ("openai", "anthropic", ...)
+    pub provider: String,
+    /// Override of the provider's API endpoint
+    #[serde(default)]
+    pub base_url: Option<String>,
+    pub nonce: Vec<u8>,
+    pub ciphertext: Vec<u8>,
+    /// Last characters of the key, for display
+    pub key_hint: String,
+    pub created_at: u64,
+    pub updated_at: u64,
+}
+
+/// One provider call made with a vault key, for cost attribution
+#[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
+pub struct KeyUsageRecord {
+    pub tenant_id: u64,
+    pub alias: String,
+    /// Subsystem that made the call ("gateway", "evaluator", "replay")
+    pub source: String,
+    pub provider: String,
+    pub model: String,
+    pub input_tokens: u64,
+    pub output_tokens: u64,
+    pub cost_usd: f64,
+    pub timestamp: u64,
+}
+
+/// Scale of one rubric dimension of an annotation queue
+#[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum RubricScale {
+    /// One label out of a fixed set
+    Categorical { labels: Vec<String> },
+    /// An integer score in `[min, max]`
+    Ordinal { min: i32, max: i32 },
+}
+
+/// A question reviewers answer for every item of a queue
+#[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
+pub struct RubricDimension {
+    pub name: String,
+    #[serde(default)]
+    pub description: Option<String>,
+    pub scale: RubricScale,
+}
+
+/// A human review queue of traces
+#[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
+pub struct AnnotationQueueRecord {
+    pub id: String,
+    pub tenant_id: u64,
+    pub name: String,
+    #[serde(default)]
+    pub description: Option<String>,
+    pub rubric: Vec<RubricDimension>,
+    pub reviewers: Vec<String>,
+    /// Reviewers labeling each item (more than one measures agreement)
+    pub reviews_per_item: u32,
+    /// Filter the queue was populated from, as submitted
+    #[serde(default)]
+    pub filter: serde_json::Value,
+    pub created_at: u64,
+}
+
+/// A trace waiting for review in an annotation queue
+#[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
+pub struct AnnotationQueueItem {
+    pub queue_id: String,
+    pub edge_id: u128,
+    pub project_id: u16,
+    /// Reviewers expected to label this item
+    pub assignees: Vec<String>,
+    pub added_at: u64,
+}
+
+/// A rubric answer: an ordinal score or a categorical label
+#[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
+#[serde(untagged)]
+pub enum LabelValue {
+    Score(i32),
+    Label(String),
+}
+
+/// One reviewer's labels for one queue item
+#[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
+pub struct AnnotationLabel {
+    pub queue_id: String,
+    pub edge_id: u128,
+    pub reviewer: String,
+    /// Rubric dimension → answer
+    pub labels: std::collections::BTreeMap<String, LabelValue>,
+    #[serde(default)]
+    pub comment: Option<String>,
+    pub created_at: u64,
+}
+
+/// Span highlights one evaluator attached to one span
+#[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
+pub struct SpanHighlightRecord {
+    pub span_id: u128,
+    pub evaluator_id: String,
+    pub highlights: Vec<agentreplay_core::SpanHighlight>,
+    pub created_at: u64,
+}
+
+/// Disambiguates usage records written in the same microsecond
+static KEY_USAGE_SEQ: std::sync::atomic::AtomicU64 = std::sync::atomic::AtomicU64::new(0);
+
+/// Secondary index key prefixes derived from the primary trace records
+const SECONDARY_INDEX_PREFIXES: &[&str] = &["idx/edge/", "idx/session/", "idx/project/", "idx/tenant/"];
+
+/// What is wrong with a primary trace record
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CorruptionKind {
+    /// Key does not follow `traces/{tenant}/{project}/{timestamp}/{edge_id}`
+    MalformedKey,
+    /// Value does not deserialize into an edge
+    Undecodable,
+    /// Edge checksum does not match its contents
+    ChecksumMismatch,
+    /// Edge fields disagree with the key it is stored under
+    KeyMismatch,
+}
+
+/// A primary trace record that failed validation
+#[derive(Debug, Clone, serde::Serialize)]
+pub struct CorruptRecord {
+    pub key: String,
+    pub kind: CorruptionKind,
+    pub detail: String,
+}
+
+/// Result of [`AgentReplayStorage::verify_integrity`]
+#[derive(Debug, Clone, Default, serde::Serialize)]
+pub struct IntegrityReport {
+    pub records_scanned: u64,
+    pub valid_edges: u64,
+    pub corrupt: Vec<CorruptRecord>,
+    /// Edge-ID index entries pointing at missing, corrupt or moved records
+    pub dangling_index_entries: u64,
+    /// Valid edges that cannot be found through the edge-ID index
+    pub unindexed_edges: u64,
+}
+
+impl IntegrityReport {
+    pub fn is_clean(&self) -> bool {
+        self.corrupt.is_empty() && self.dangling_index_entries == 0 && self.unindexed_edges == 0
+    }
+}
+
+/// Result of [`AgentReplayStorage::rebuild_secondary_indexes`]
+#[derive(Debug, Clone, Default, serde::Serialize)]
+pub struct IndexRebuildStats {
+    pub entries_removed: u64,
+    pub edges_indexed: u64,
+    /// Corrupt primary records left out of the rebuilt indexes
+    pub records_skipped: u64,
+}
+
+/// Validate one primary trace record
+fn check_trace_record(key: &str, value: &[u8]) -> std::result::Result<AgentFlowEdge, (CorruptionKind, String)> {
+    let (tenant_id, project_id, timestamp_us, edge_id) = decode_trace_key(key)
+        .ok_or((CorruptionKind::MalformedKey, "unparseable trace key".to_string()))?;
+    let edge = deserialize_edge(value)
+        .map_err(|e| (CorruptionKind::Undecodable, e.to_string()))?;
+    if !edge.verify_checksum() {
+        return Err((
+            CorruptionKind::ChecksumMismatch,
+            format!("stored {:#018x}, computed {:#018x}", edge.checksum, edge.compute_checksum()),
+        ));
+    }
+    if (edge.tenant_id, edge.project_id, edge.timestamp_us, edge.edge_id)
+        != (tenant_id, project_id, timestamp_us, edge_id)
+    {
+        return Err((
+            CorruptionKind::KeyMismatch,
+            format!("record holds edge {:#x} of tenant {}", edge.edge_id, edge.tenant_id),
+        ));
+    }
+    Ok(edge)
+}
+
 /// Cleanup operation statistics
 #[derive(Debug, Clone, Default)]
 pub struct CleanupStats {
@@ -440,6 +820,81 @@ pub struct CleanupStats {
     pub bytes_reclaimed: u64,
 }
 
+/// Step of a manual compaction in progress
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CompactionPhase {
+    /// Deleting payloads whose edge no longer exists
+    RemovingOrphans,
+    /// Flushing the memtable and truncating the WAL
+    Checkpointing,
+}
+
+/// Outcome of a manual compaction
+#[derive(Debug, Clone, Default, PartialEq, serde::Serialize, serde::Deserialize)]
+pub struct CompactionRun {
+    pub started_at_us: u64,
+    pub duration_ms: u64,
+    pub disk_bytes_before: u64,
+    pub disk_bytes_after: u64,
+    pub wal_bytes_before: u64,
+    pub wal_bytes_after: u64,
+    pub orphan_payloads_removed: u64,
+    /// Set when a phase failed; the run stopped there
+    pub error: Option<String>,
+}
+
+impl CompactionRun {
+    pub fn bytes_reclaimed(&self) -> u64 {
+        self.disk_bytes_before.saturating_sub(self.disk_bytes_after)
+    }
+}
+
+/// Compaction progress and space reclamation statistics
+///
+/// SochDB merges its segments on its own; a manual compaction covers what
+/// AgentReplay can reclaim itself (orphaned payloads, WAL) and checkpoints so
+/// SochDB can drop tombstoned records.
+#[derive(Debug, Clone, Default, PartialEq, serde::Serialize, serde::Deserialize)]
+pub struct CompactionStats {
+    /// Whether a manual compaction is running
+    pub in_progress: bool,
+    pub phase: Option<CompactionPhase>,
+    pub started_at_us: Option<u64>,
+    /// Size of the data directory
+    pub disk_bytes: u64,
+    pub wal_bytes: u64,
+    /// Bytes of live trace and payload records (keys + values)
+    pub logical_bytes: u64,
+    /// `disk_bytes / logical_bytes` (0 when there is no live data)
+    pub space_amplification: f64,
+    pub live_edges: u64,
+    pub payload_count: u64,
+    /// Tombstones written since the storage was opened
+    pub tombstones_written: u64,
+    /// Tombstones written relative to live + tombstoned edges
+    pub tombstone_ratio: f64,
+    pub orphan_payloads: u64,
+    pub last_run: Option<CompactionRun>,
+}
+
+/// Ratio of on-disk bytes to live data bytes
+pub fn space_amplification(disk_bytes: u64, logical_bytes: u64) -> f64 {
+    if logical_bytes == 0 {
+        0.0
+    } else {
+        disk_bytes as f64 / logical_bytes as f64
+    }
+}
+
+/// Progress of the current manual compaction and the last finished one
+#[derive(Debug, Default)]
+struct CompactionTracker {
+    phase: Option<CompactionPhase>,
+    started_at_us: Option<u64>,
+    last_run: Option<CompactionRun>,
+}
+
 // ============================================================================
 // Dashboard Summary (Task 9: Eliminate redundant scans)
 // ============================================================================
@@ -476,6 +931,9 @@ impl DashboardSummary {
     /// Record a new edge into the summary
     pub fn record_edge(&mut self, edge: &AgentFlowEdge) {
         self.total_traces += 1;
+        if edge.get_span_type() == agentreplay_core::SpanType::Error {
+            self.error_count += 1;
+        }
         self.total_tokens += edge.token_count as u64;
         self.total_duration_us += edge.duration_us as u64;
         if edge.timestamp_us > self.last_edge_ts {
@@ -639,8 +1097,16 @@ pub struct AgentReplayStorage {
     hour_buckets: RwLock<BTreeMap<(u64, u16, u64), MetricsBucket>>,
     /// Pre-computed dashboard summary (Task 9: O(1) dashboard queries)
     dashboard_summary: RwLock<DashboardSummary>,
+    /// Minute/hour/day rollups per project, agent and model for windowed dashboards
+    rollups: RollupTables,
     /// Statistics
     stats: StorageStatsAtomic,
+    /// Manual compaction progress
+    compaction: RwLock<CompactionTracker>,
+    /// Write amplification, fsync latency and group commit batch sizes
+    write_telemetry: WriteTelemetry,
+    /// WAL size at the last telemetry sample, to attribute WAL growth
+    wal_bytes_seen: AtomicU64,
     /// Shutdown flag
     shutdown: AtomicBool,
     /// Semantic query cache for repeated LLM context queries
@@ -752,12 +1218,21 @@ impl AgentReplayStorage {
             minute_buckets: RwLock::new(BTreeMap::new()),
             hour_buckets: RwLock::new(BTreeMap::new()),
             dashboard_summary: RwLock::new(DashboardSummary::default()),
+            rollups: RollupTables::new(),
             stats: StorageStatsAtomic::default(),
+            compaction: RwLock::new(CompactionTracker::default()),
+            write_telemetry: WriteTelemetry::default(),
+            wal_bytes_seen: AtomicU64::new(0),
             shutdown: AtomicBool::new(false),
             semantic_cache_enabled: true, // Enable semantic caching by default
             columnar_edges_enabled: true, // Enable columnar storage by default
         };
         
+        // Only count WAL written from here on
+        storage
+            .wal_bytes_seen
+            .store(storage.wal_size_bytes(), Ordering::Relaxed);
+
         // Load persisted metrics from disk to warm up the cache
         if let Err(e) = storage.load_initial_metrics() {
             warn!("Failed to load initial metrics from disk: {}", e);
@@ -805,6 +1280,45 @@ impl AgentReplayStorage {
         }
         
         info!("Loaded {} metrics buckets from disk", count);
+
+        let results = self.connection.scan(&format!("{}/", ROLLUP_PREFIX))
+            .map_err(|e| AgentreplayError::Internal(format!("Failed to scan rollups: {}", e)))?;
+        let mut rollups = 0;
+        for (key, value) in results {
+            // Key format: rollups/{granularity}/{ts}
+            let parts: Vec<&str> = key.split('/').collect();
+            if parts.len() < 3 { continue; }
+
+            let (Some(granularity), Ok(bucket_ts)) =
+                (RollupGranularity::parse(parts[1]), parts[2].parse::<u64>()) else {
+                continue;
+            };
+            match bincode::deserialize::<RollupRows>(&value) {
+                Ok(rows) => {
+                    self.rollups.load_bucket(granularity, bucket_ts, rows);
+                    rollups += 1;
+                }
+                Err(e) => warn!(key = %key, error = %e, "Skipping unreadable rollup bucket"),
+            }
+        }
+
+        info!("Loaded {} rollup buckets from disk", rollups);
+
+        // Databases written before rollups existed only have metrics buckets;
+        // buckets in an older row format fail to load and are rebuilt the same way
+        if rollups == 0 && count > 0 {
+            let edges = self.range_scan(0, u64::MAX)?;
+            for edge in &edges {
+                self.rollups.record(&self.stored_rollup_sample(edge));
+            }
+            info!("Backfilled rollups from {} stored edges", edges.len());
+        }
+        // Day buckets are never pruned, so only stores with persisted rollups
+        // can have pruned ranges to serve from raw edges
+        if rollups > 0 && self.prune_rollups() {
+            self.connection.commit()
+                .map_err(|e| AgentreplayError::Internal(format!("Rollup prune commit failed: {}", e)))?;
+        }
         Ok(())
     }
 
@@ -843,9 +1357,7 @@ impl AgentReplayStorage {
     pub fn put_durable(&self, edge: AgentFlowEdge) -> Result<()> {
         let _write_guard = self.write_lock.write();
         self.put_internal(edge)?;
-        
-        let _ = self.connection.commit()
-            .map_err(|e| AgentreplayError::Internal(format!("SochDB commit failed: {}", e)))?;
+        self.commit_recorded(1)?;
 
         Ok(())
     }
@@ -857,11 +1369,25 @@ impl AgentReplayStorage {
         
         self.connection.put(&key, &data)
             .map_err(|e| AgentreplayError::Internal(format!("SochDB put failed: {}", e)))?;
+        self.record_logical_write(data.len());
         
         // PackedRow columnar storage REMOVED — it duplicated the bincode edge
         // with ~63 bytes overhead per span (was never read by any query path).
         // For 2.5M spans this saves ~158 MB of WAL + 2.5M BTreeMap entries in memory.
         
+        self.put_secondary_indexes(&key, &edge)?;
+
+        // Record metrics in in-memory buckets
+        self.record_metrics(&edge);
+
+        self.stats.puts.fetch_add(1, Ordering::Relaxed);
+        self.stats.edges.fetch_add(1, Ordering::Relaxed);
+        
+        Ok(())
+    }
+
+    /// Write the secondary index entries for an edge stored under `key`
+    fn put_secondary_indexes(&self, key: &str, edge: &AgentFlowEdge) -> Result<()> {
         // ====================================================================
         // Secondary indexes stored in SochDB for persistence across restarts
         // ====================================================================
@@ -887,12 +1413,6 @@ impl AgentReplayStorage {
         self.connection.put(&tenant_ts_key, &[])
             .map_err(|e| AgentreplayError::Internal(format!("SochDB tenant index update failed: {}", e)))?;
 
-        // Record metrics in in-memory buckets
-        self.record_metrics(&edge);
-
-        self.stats.puts.fetch_add(1, Ordering::Relaxed);
-        self.stats.edges.fetch_add(1, Ordering::Relaxed);
-        
         Ok(())
     }
 
@@ -959,7 +1479,559 @@ impl AgentReplayStorage {
                 }
             }
         }
-        Ok(result)
+        Ok(result)
+    }
+
+    /// Store the cost of an edge recomputed after ingest (micro-USD)
+    ///
+    /// Key format: `idx/cost/{edge_id:032x}` → little-endian `u64`. The
+    /// rollups count this cost for the edge from then on instead of one
+    /// derived from its tokens. Storing the same cost again is a no-op.
+    /// Returns false when the edge does not exist.
+    pub fn put_edge_cost(&self, edge_id: u128, cost_micros: u64) -> Result<bool> {
+        let Some(edge) = self.get(edge_id)? else {
+            return Ok(false);
+        };
+        let previous = self.get_edge_cost(edge_id)?;
+        let key = format!("idx/cost/{:032x}", edge_id);
+        self.connection.put(&key, &cost_micros.to_le_bytes())
+            .map_err(|e| AgentreplayError::Internal(format!("SochDB put cost failed: {}", e)))?;
+        self.rollups
+            .reprice(&self.stored_rollup_sample(&edge), previous);
+        Ok(true)
+    }
+
+    /// Cost stored for an edge by [`Self::put_edge_cost`], if any
+    pub fn get_edge_cost(&self, edge_id: u128) -> Result<Option<u64>> {
+        let key = format!("idx/cost/{:032x}", edge_id);
+        let data = self.connection.get(&key)
+            .map_err(|e| AgentreplayError::Internal(format!("SochDB get cost failed: {}", e)))?;
+        Ok(data.and_then(|bytes| Some(u64::from_le_bytes(bytes.as_slice().try_into().ok()?))))
+    }
+
+    /// Store the ingest-time enrichment of an edge
+    ///
+    /// Key format: `idx/enriched/{edge_id:032x}` → JSON [`EdgeEnrichment`]
+    pub fn put_edge_enrichment(&self, edge_id: u128, enrichment: &EdgeEnrichment) -> Result<()> {
+        let key = format!("idx/enriched/{:032x}", edge_id);
+        let value = serde_json::to_vec(enrichment)
+            .map_err(|e| AgentreplayError::Serialization(e.to_string()))?;
+        self.connection.put(&key, &value)
+            .map_err(|e| AgentreplayError::Internal(format!("SochDB put enrichment failed: {}", e)))?;
+        Ok(())
+    }
+
+    /// Get the ingest-time enrichment of an edge, if it has one
+    pub fn get_edge_enrichment(&self, edge_id: u128) -> Result<Option<EdgeEnrichment>> {
+        let key = format!("idx/enriched/{:032x}", edge_id);
+        let Some(data) = self.connection.get(&key)
+            .map_err(|e| AgentreplayError::Internal(format!("SochDB get enrichment failed: {}", e)))? else {
+            return Ok(None);
+        };
+        serde_json::from_slice(&data)
+            .map(Some)
+            .map_err(|e| AgentreplayError::Serialization(e.to_string()))
+    }
+
+    /// Store the analysis of a session, replacing any earlier one
+    ///
+    /// Key format: `idx/session_analysis/{session_id:016x}` → JSON [`SessionAnalysis`]
+    pub fn put_session_analysis(&self, analysis: &SessionAnalysis) -> Result<()> {
+        let key = format!("idx/session_analysis/{:016x}", analysis.session_id);
+        let value = serde_json::to_vec(analysis)
+            .map_err(|e| AgentreplayError::Serialization(e.to_string()))?;
+        self.connection.put(&key, &value).map_err(|e| {
+            AgentreplayError::Internal(format!("SochDB put session analysis failed: {}", e))
+        })?;
+        Ok(())
+    }
+
+    /// Get the stored analysis of a session, if it has been analyzed
+    pub fn get_session_analysis(&self, session_id: u64) -> Result<Option<SessionAnalysis>> {
+        let key = format!("idx/session_analysis/{:016x}", session_id);
+        let Some(data) = self.connection.get(&key).map_err(|e| {
+            AgentreplayError::Internal(format!("SochDB get session analysis failed: {}", e))
+        })?
+        else {
+            return Ok(None);
+        };
+        serde_json::from_slice(&data)
+            .map(Some)
+            .map_err(|e| AgentreplayError::Serialization(e.to_string()))
+    }
+
+    /// Store the summary of a session, replacing any earlier one
+    ///
+    /// Key format: `idx/session_summary/{tenant_id:016x}/{session_id:016x}` →
+    /// JSON [`SessionRollup`]
+    pub fn put_session_summary(&self, summary: &SessionRollup) -> Result<()> {
+        let key = format!(
+            "idx/session_summary/{:016x}/{:016x}",
+            summary.tenant_id, summary.session_id
+        );
+        let value = serde_json::to_vec(summary)
+            .map_err(|e| AgentreplayError::Serialization(e.to_string()))?;
+        self.connection.put(&key, &value).map_err(|e| {
+            AgentreplayError::Internal(format!("SochDB put session summary failed: {}", e))
+        })?;
+        Ok(())
+    }
+
+    /// Get the summary of a tenant's session
+    pub fn get_session_summary(
+        &self,
+        tenant_id: u64,
+        session_id: u64,
+    ) -> Result<Option<SessionRollup>> {
+        let key = format!("idx/session_summary/{:016x}/{:016x}", tenant_id, session_id);
+        let Some(data) = self.connection.get(&key).map_err(|e| {
+            AgentreplayError::Internal(format!("SochDB get session summary failed: {}", e))
+        })?
+        else {
+            return Ok(None);
+        };
+        serde_json::from_slice(&data)
+            .map(Some)
+            .map_err(|e| AgentreplayError::Serialization(e.to_string()))
+    }
+
+    /// All session summaries of a tenant
+    pub fn list_session_summaries(&self, tenant_id: u64) -> Result<Vec<SessionRollup>> {
+        let prefix = format!("idx/session_summary/{:016x}/", tenant_id);
+        let entries = self.connection.scan(&prefix).map_err(|e| {
+            AgentreplayError::Internal(format!("SochDB scan session summaries failed: {}", e))
+        })?;
+        let mut summaries = Vec::with_capacity(entries.len());
+        for (key, value) in entries {
+            match serde_json::from_slice(&value) {
+                Ok(summary) => summaries.push(summary),
+                Err(e) => warn!(key = %key, error = %e, "Skipping unreadable session summary"),
+            }
+        }
+        Ok(summaries)
+    }
+
+    /// Whether every session ingested before summaries existed has been summarized
+    pub fn session_summaries_ready(&self) -> Result<bool> {
+        self.connection
+            .get(SESSION_SUMMARIES_READY_KEY)
+            .map(|value| value.is_some())
+            .map_err(|e| {
+                AgentreplayError::Internal(format!("SochDB get summary marker failed: {}", e))
+            })
+    }
+
+    /// Record that the session summary backfill has completed
+    pub fn mark_session_summaries_ready(&self) -> Result<()> {
+        self.connection
+            .put(SESSION_SUMMARIES_READY_KEY, b"1")
+            .map_err(|e| {
+                AgentreplayError::Internal(format!("SochDB put summary marker failed: {}", e))
+            })
+    }
+
+    /// Link a session or trace to a conversation (idempotent)
+    ///
+    /// Key format: `idx/conversation/{tenant_id:016x}/{hex(conversation_id)}/`
+    /// followed by `s{session_id:016x}`, or `e{edge_id:032x}` for spans
+    /// without a session → JSON [`ConversationLink`]
+    pub fn put_conversation_link(&self, link: &ConversationLink) -> Result<()> {
+        let prefix = conversation_prefix(link.tenant_id, &link.conversation_id);
+        let key = if link.session_id != 0 {
+            format!("{}s{:016x}", prefix, link.session_id)
+        } else {
+            format!("{}e{:032x}", prefix, link.edge_id)
+        };
+        let value =
+            serde_json::to_vec(link).map_err(|e| AgentreplayError::Serialization(e.to_string()))?;
+        self.connection.put(&key, &value).map_err(|e| {
+            AgentreplayError::Internal(format!("SochDB put conversation link failed: {}", e))
+        })?;
+        Ok(())
+    }
+
+    /// Sessions and traces linked to a tenant's conversation
+    pub fn list_conversation_links(
+        &self,
+        tenant_id: u64,
+        conversation_id: &str,
+    ) -> Result<Vec<ConversationLink>> {
+        let prefix = conversation_prefix(tenant_id, conversation_id);
+        let entries = self.connection.scan(&prefix).map_err(|e| {
+            AgentreplayError::Internal(format!("SochDB scan conversation links failed: {}", e))
+        })?;
+        let mut links = Vec::with_capacity(entries.len());
+        for (key, value) in entries {
+            match serde_json::from_slice(&value) {
+                Ok(link) => links.push(link),
+                Err(e) => warn!(key = %key, error = %e, "Skipping unreadable conversation link"),
+            }
+        }
+        Ok(links)
+    }
+
+    /// Store feedback on a trace
+    ///
+    /// Key format:
+    /// `idx/feedback/{tenant_id:016x}/{edge_id:032x}/{created_at:016x}` →
+    /// JSON [`FeedbackRecord`]
+    pub fn put_feedback(&self, feedback: &FeedbackRecord) -> Result<()> {
+        let key = format!(
+            "idx/feedback/{:016x}/{:032x}/{:016x}",
+            feedback.tenant_id, feedback.edge_id, feedback.created_at
+        );
+        let value = serde_json::to_vec(feedback)
+            .map_err(|e| AgentreplayError::Serialization(e.to_string()))?;
+        self.connection.put(&key, &value).map_err(|e| {
+            AgentreplayError::Internal(format!("SochDB put feedback failed: {}", e))
+        })?;
+        Ok(())
+    }
+
+    /// Feedback of a tenant, on one trace or (with `edge_id` None) on all
+    pub fn list_feedback(
+        &self,
+        tenant_id: u64,
+        edge_id: Option<u128>,
+    ) -> Result<Vec<FeedbackRecord>> {
+        let prefix = match edge_id {
+            Some(edge_id) => format!("idx/feedback/{:016x}/{:032x}/", tenant_id, edge_id),
+            None => format!("idx/feedback/{:016x}/", tenant_id),
+        };
+        let entries = self.connection.scan(&prefix).map_err(|e| {
+            AgentreplayError::Internal(format!("SochDB scan feedback failed: {}", e))
+        })?;
+        let mut feedback = Vec::with_capacity(entries.len());
+        for (key, value) in entries {
+            match serde_json::from_slice(&value) {
+                Ok(record) => feedback.push(record),
+                Err(e) => warn!(key = %key, error = %e, "Skipping unreadable feedback"),
+            }
+        }
+        Ok(feedback)
+    }
+
+    /// Store (or replace) a vault key
+    ///
+    /// Key format: `idx/provider_keys/{tenant_id:016x}/{alias}` → JSON
+    /// [`ProviderKeyRecord`]
+    pub fn put_provider_key(&self, key: &ProviderKeyRecord) -> Result<()> {
+        let storage_key = format!("idx/provider_keys/{:016x}/{}", key.tenant_id, key.alias);
+        let value =
+            serde_json::to_vec(key).map_err(|e| AgentreplayError::Serialization(e.to_string()))?;
+        self.connection.put(&storage_key, &value).map_err(|e| {
+            AgentreplayError::Internal(format!("SochDB put provider key failed: {}", e))
+        })?;
+        Ok(())
+    }
+
+    /// Get a tenant's vault key by alias
+    pub fn get_provider_key(
+        &self,
+        tenant_id: u64,
+        alias: &str,
+    ) -> Result<Option<ProviderKeyRecord>> {
+        let storage_key = format!("idx/provider_keys/{:016x}/{}", tenant_id, alias);
+        let value = self.connection.get(&storage_key).map_err(|e| {
+            AgentreplayError::Internal(format!("SochDB get provider key failed: {}", e))
+        })?;
+        value
+            .map(|data| {
+                serde_json::from_slice(&data)
+                    .map_err(|e| AgentreplayError::Serialization(e.to_string()))
+            })
+            .transpose()
+    }
+
+    /// All vault keys of a tenant, ordered by alias
+    pub fn list_provider_keys(&self, tenant_id: u64) -> Result<Vec<ProviderKeyRecord>> {
+        let prefix = format!("idx/provider_keys/{:016x}/", tenant_id);
+        let mut entries = self.connection.scan(&prefix).map_err(|e| {
+            AgentreplayError::Internal(format!("SochDB scan provider keys failed: {}", e))
+        })?;
+        entries.sort_by(|a, b| a.0.cmp(&b.0));
+        let mut keys = Vec::with_capacity(entries.len());
+        for (key, value) in entries {
+            match serde_json::from_slice(&value) {
+                Ok(record) => keys.push(record),
+                Err(e) => warn!(key = %key, error = %e, "Skipping unreadable provider key"),
+            }
+        }
+        Ok(keys)
+    }
+
+    /// Remove a vault key; returns whether it existed
+    pub fn delete_provider_key(&self, tenant_id: u64, alias: &str) -> Result<bool> {
+        if self.get_provider_key(tenant_id, alias)?.is_none() {
+            return Ok(false);
+        }
+        let storage_key = format!("idx/provider_keys/{:016x}/{}", tenant_id, alias);
+        self.connection.delete(&storage_key).map_err(|e| {
+            AgentreplayError::Internal(format!("SochDB delete provider key failed: {}", e))
+        })?;
+        Ok(true)
+    }
+
+    /// Record a provider call made with a vault key
+    ///
+    /// Key format: `idx/key_usage/{tenant_id:016x}/{timestamp:016x}/{seq:016x}`
+    /// → JSON [`KeyUsageRecord`]
+    pub fn put_key_usage(&self, usage: &KeyUsageRecord) -> Result<()> {
+        let seq = KEY_USAGE_SEQ.fetch_add(1, std::sync::atomic::Ordering::Relaxed);
+        let key = format!(
+            "idx/key_usage/{:016x}/{:016x}/{:016x}",
+            usage.tenant_id, usage.timestamp, seq
+        );
+        let value = serde_json::to_vec(usage)
+            .map_err(|e| AgentreplayError::Serialization(e.to_string()))?;
+        self.connection.put(&key, &value).map_err(|e| {
+            AgentreplayError::Internal(format!("SochDB put key usage failed: {}", e))
+        })?;
+        Ok(())
+    }
+
+    /// Vault key usage of a tenant within `[start_ts, end_ts)`
+    pub fn list_key_usage(
+        &self,
+        tenant_id: u64,
+        start_ts: u64,
+        end_ts: u64,
+    ) -> Result<Vec<KeyUsageRecord>> {
+        let prefix = format!("idx/key_usage/{:016x}/", tenant_id);
+        let entries = self.connection.scan(&prefix).map_err(|e| {
+            AgentreplayError::Internal(format!("SochDB scan key usage failed: {}", e))
+        })?;
+        let mut usage = Vec::new();
+        for (key, value) in entries {
+            match serde_json::from_slice::<KeyUsageRecord>(&value) {
+                Ok(record) if record.timestamp >= start_ts && record.timestamp < end_ts => {
+                    usage.push(record)
+                }
+                Ok(_) => {}
+                Err(e) => warn!(key = %key, error = %e, "Skipping unreadable key usage"),
+            }
+        }
+        Ok(usage)
+    }
+
+    /// Store (or replace) an annotation queue
+    ///
+    /// Key format: `idx/annotation_queues/{tenant_id:016x}/{queue_id}` → JSON
+    /// [`AnnotationQueueRecord`]; items and labels live under
+    /// `idx/annotation_items/{queue_id}/` and `idx/annotation_labels/{queue_id}/`.
+    pub fn put_annotation_queue(&self, queue: &AnnotationQueueRecord) -> Result<()> {
+        let key = format!(
+            "idx/annotation_queues/{:016x}/{}",
+            queue.tenant_id, queue.id
+        );
+        let value = serde_json::to_vec(queue)
+            .map_err(|e| AgentreplayError::Serialization(e.to_string()))?;
+        self.connection.put(&key, &value).map_err(|e| {
+            AgentreplayError::Internal(format!("SochDB put annotation queue failed: {}", e))
+        })?;
+        Ok(())
+    }
+
+    /// Get a tenant's annotation queue
+    pub fn get_annotation_queue(
+        &self,
+        tenant_id: u64,
+        queue_id: &str,
+    ) -> Result<Option<AnnotationQueueRecord>> {
+        let key = format!("idx/annotation_queues/{:016x}/{}", tenant_id, queue_id);
+        let value = self.connection.get(&key).map_err(|e| {
+            AgentreplayError::Internal(format!("SochDB get annotation queue failed: {}", e))
+        })?;
+        value
+            .map(|data| {
+                serde_json::from_slice(&data)
+                    .map_err(|e| AgentreplayError::Serialization(e.to_string()))
+            })
+            .transpose()
+    }
+
+    /// All annotation queues of a tenant
+    pub fn list_annotation_queues(&self, tenant_id: u64) -> Result<Vec<AnnotationQueueRecord>> {
+        self.scan_json(
+            &format!("idx/annotation_queues/{:016x}/", tenant_id),
+            "annotation queue",
+        )
+    }
+
+    /// Store (or replace) an item of an annotation queue
+    pub fn put_annotation_item(&self, item: &AnnotationQueueItem) -> Result<()> {
+        let key = format!(
+            "idx/annotation_items/{}/{:032x}",
+            item.queue_id, item.edge_id
+        );
+        let value =
+            serde_json::to_vec(item).map_err(|e| AgentreplayError::Serialization(e.to_string()))?;
+        self.connection.put(&key, &value).map_err(|e| {
+            AgentreplayError::Internal(format!("SochDB put annotation item failed: {}", e))
+        })?;
+        Ok(())
+    }
+
+    /// Items of an annotation queue, ordered by edge ID
+    pub fn list_annotation_items(&self, queue_id: &str) -> Result<Vec<AnnotationQueueItem>> {
+        self.scan_json(
+            &format!("idx/annotation_items/{}/", queue_id),
+            "annotation item",
+        )
+    }
+
+    /// Store (or replace) a reviewer's labels for a queue item
+    pub fn put_annotation_label(&self, label: &AnnotationLabel) -> Result<()> {
+        let key = format!(
+            "idx/annotation_labels/{}/{:032x}/{}",
+            label.queue_id, label.edge_id, label.reviewer
+        );
+        let value = serde_json::to_vec(label)
+            .map_err(|e| AgentreplayError::Serialization(e.to_string()))?;
+        self.connection.put(&key, &value).map_err(|e| {
+            AgentreplayError::Internal(format!("SochDB put annotation label failed: {}", e))
+        })?;
+        Ok(())
+    }
+
+    /// All labels recorded in an annotation queue
+    pub fn list_annotation_labels(&self, queue_id: &str) -> Result<Vec<AnnotationLabel>> {
+        self.scan_json(
+            &format!("idx/annotation_labels/{}/", queue_id),
+            "annotation label",
+        )
+    }
+
+    /// Store (or replace) an evaluator's highlights for a span
+    ///
+    /// Key format: `idx/span_highlights/{span_id:032x}/{evaluator_id}`
+    pub fn put_span_highlights(&self, record: &SpanHighlightRecord) -> Result<()> {
+        let key = format!(
+            "idx/span_highlights/{:032x}/{}",
+            record.span_id, record.evaluator_id
+        );
+        let value = serde_json::to_vec(record)
+            .map_err(|e| AgentreplayError::Serialization(e.to_string()))?;
+        self.connection.put(&key, &value).map_err(|e| {
+            AgentreplayError::Internal(format!("SochDB put span highlights failed: {}", e))
+        })?;
+        Ok(())
+    }
+
+    /// Highlights attached to a span, one record per evaluator
+    pub fn list_span_highlights(&self, span_id: u128) -> Result<Vec<SpanHighlightRecord>> {
+        self.scan_json(
+            &format!("idx/span_highlights/{:032x}/", span_id),
+            "span highlights",
+        )
+    }
+
+    /// Deserialize every JSON record under a prefix, skipping unreadable ones
+    fn scan_json<T: serde::de::DeserializeOwned>(
+        &self,
+        prefix: &str,
+        what: &str,
+    ) -> Result<Vec<T>> {
+        let mut entries = self.connection.scan(prefix).map_err(|e| {
+            AgentreplayError::Internal(format!("SochDB scan {} failed: {}", what, e))
+        })?;
+        // Scan order is not guaranteed; return records in key order
+        entries.sort_by(|a, b| a.0.cmp(&b.0));
+        let mut records = Vec::with_capacity(entries.len());
+        for (key, value) in entries {
+            match serde_json::from_slice(&value) {
+                Ok(record) => records.push(record),
+                Err(e) => warn!(key = %key, error = %e, "Skipping unreadable {}", what),
+            }
+        }
+        Ok(records)
+    }
+
+    /// Store the captured token logprobs of an edge
+    ///
+    /// Key format: `idx/logprobs/{edge_id:032x}` → compact record (see
+    /// [`crate::logprobs`]). Returns the confidence summary that was stored.
+    pub fn put_edge_logprobs(
+        &self,
+        edge_id: u128,
+        tokens: &[TokenLogprob],
+    ) -> Result<LogprobSummary> {
+        let (value, summary) = encode_logprobs(tokens)?;
+        let key = format!("idx/logprobs/{:032x}", edge_id);
+        self.connection.put(&key, &value).map_err(|e| {
+            AgentreplayError::Internal(format!("SochDB put logprobs failed: {}", e))
+        })?;
+        Ok(summary)
+    }
+
+    /// Get the captured token logprobs of an edge, if any
+    pub fn get_edge_logprobs(&self, edge_id: u128) -> Result<Option<SpanLogprobs>> {
+        self.get_logprobs_record(edge_id)?
+            .map(|data| decode_logprobs(&data))
+            .transpose()
+    }
+
+    /// Get only the confidence summary of an edge's logprobs (no decompression)
+    pub fn get_logprob_summary(&self, edge_id: u128) -> Result<Option<LogprobSummary>> {
+        self.get_logprobs_record(edge_id)?
+            .map(|data| decode_logprob_summary(&data))
+            .transpose()
+    }
+
+    fn get_logprobs_record(&self, edge_id: u128) -> Result<Option<Vec<u8>>> {
+        let key = format!("idx/logprobs/{:032x}", edge_id);
+        self.connection
+            .get(&key)
+            .map_err(|e| AgentreplayError::Internal(format!("SochDB get logprobs failed: {}", e)))
+    }
+
+    /// Store the span links of an edge (replaces earlier links)
+    ///
+    /// Key format: `idx/links/{edge_id:032x}` → JSON [`SpanLink`] list, plus
+    /// `idx/linked_from/{linked_span_id:032x}/{edge_id:032x}` per link so
+    /// fan-in spans can be found from the spans they merge.
+    pub fn put_edge_links(&self, edge_id: u128, links: &[SpanLink]) -> Result<()> {
+        let key = format!("idx/links/{:032x}", edge_id);
+        let value =
+            serde_json::to_vec(links).map_err(|e| AgentreplayError::Serialization(e.to_string()))?;
+        self.connection.put(&key, &value).map_err(|e| {
+            AgentreplayError::Internal(format!("SochDB put span links failed: {}", e))
+        })?;
+
+        for link in links {
+            let key = format!("idx/linked_from/{:032x}/{:032x}", link.span_id, edge_id);
+            self.connection.put(&key, link.kind.as_str().as_bytes()).map_err(|e| {
+                AgentreplayError::Internal(format!("SochDB put span link index failed: {}", e))
+            })?;
+        }
+        Ok(())
+    }
+
+    /// Get the span links of an edge (empty if it has none)
+    pub fn get_edge_links(&self, edge_id: u128) -> Result<Vec<SpanLink>> {
+        let key = format!("idx/links/{:032x}", edge_id);
+        let Some(data) = self.connection.get(&key).map_err(|e| {
+            AgentreplayError::Internal(format!("SochDB get span links failed: {}", e))
+        })?
+        else {
+            return Ok(Vec::new());
+        };
+        serde_json::from_slice(&data).map_err(|e| AgentreplayError::Serialization(e.to_string()))
+    }
+
+    /// IDs of the edges that link to `edge_id`
+    pub fn get_linking_edges(&self, edge_id: u128) -> Result<Vec<u128>> {
+        let prefix = format!("idx/linked_from/{:032x}/", edge_id);
+        let entries = self.connection.scan(&prefix).map_err(|e| {
+            AgentreplayError::Internal(format!("SochDB scan span link index failed: {}", e))
+        })?;
+        Ok(entries
+            .iter()
+            .filter_map(|(key, _)| {
+                let id = key.strip_prefix(&prefix)?;
+                u128::from_str_radix(id, 16).ok()
+            })
+            .collect())
     }
 
     /// Put a batch of edges (high-throughput bulk ingestion)
@@ -984,8 +2056,7 @@ impl AgentReplayStorage {
         
         // Explicit commit at end of batch for durability
         // This is more efficient than per-operation commit
-        let _ = self.connection.commit()
-            .map_err(|e| AgentreplayError::Internal(format!("SochDB commit failed: {}", e)))?;
+        self.commit_recorded(edges.len() as u64)?;
         
         // Use debug level to avoid per-batch log overhead under high throughput
         tracing::debug!(batch_size = edges.len(), "Batch ingestion complete");
@@ -1034,6 +2105,7 @@ impl AgentReplayStorage {
             let compressed = compress_payload(data);
             self.connection.put(&key, &compressed)
                 .map_err(|e| AgentreplayError::Internal(format!("SochDB put payload failed: {}", e)))?;
+            self.record_logical_write(data.len());
         }
 
         // Write edges with all indexes
@@ -1042,8 +2114,7 @@ impl AgentReplayStorage {
         }
 
         // Single commit for the entire batch (payloads + edges + indexes)
-        let _ = self.connection.commit()
-            .map_err(|e| AgentreplayError::Internal(format!("SochDB commit failed: {}", e)))?;
+        self.commit_recorded((edges.len() + payloads.len()) as u64)?;
 
         tracing::debug!(edges = edges.len(), payloads = payloads.len(), "Batch with payloads complete");
         Ok(())
@@ -1606,9 +2677,8 @@ impl AgentReplayStorage {
         let compressed = compress_payload(data);
         self.connection.put(&key, &compressed)
             .map_err(|e| AgentreplayError::Internal(format!("SochDB put payload failed: {}", e)))?;
-            
-        let _ = self.connection.commit()
-            .map_err(|e| AgentreplayError::Internal(format!("SochDB commit failed: {}", e)))?;
+        self.record_logical_write(data.len());
+        self.commit_recorded(1)?;
 
         Ok(())
     }
@@ -1629,10 +2699,10 @@ impl AgentReplayStorage {
             let compressed = compress_payload(data);
             self.connection.put(&key, &compressed)
                 .map_err(|e| AgentreplayError::Internal(format!("SochDB put payload failed: {}", e)))?;
+            self.record_logical_write(data.len());
         }
 
-        let _ = self.connection.commit()
-            .map_err(|e| AgentreplayError::Internal(format!("SochDB commit failed: {}", e)))?;
+        self.commit_recorded(payloads.len() as u64)?;
 
         Ok(())
     }
@@ -1680,6 +2750,54 @@ impl AgentReplayStorage {
             let mut summary = self.dashboard_summary.write();
             summary.record_edge(edge);
         }
+
+        self.rollups.record(&self.rollup_sample(edge));
+    }
+
+    /// Rollup view of an edge; the model comes from its filter attributes,
+    /// which writers store before the edge
+    fn rollup_sample(&self, edge: &AgentFlowEdge) -> RollupSample {
+        let (_, model, _) = self.get_edge_attrs(edge.edge_id).unwrap_or_default();
+        RollupSample::from_edge(edge, &model)
+    }
+
+    /// Rollup view of a stored edge, with the cost a recompute stored for it
+    fn stored_rollup_sample(&self, edge: &AgentFlowEdge) -> RollupSample {
+        RollupSample {
+            cost_micros: self.get_edge_cost(edge.edge_id).unwrap_or_default(),
+            ..self.rollup_sample(edge)
+        }
+    }
+
+    /// Edges recorded per hour, or per day before hour rollups are kept,
+    /// as `(start_us, end_us, edges)` oldest first
+    ///
+    /// Cheap enough to call often, unlike scanning the edges; deleted edges
+    /// are still counted.
+    pub fn edge_counts_by_bucket(&self) -> Vec<(u64, u64, u64)> {
+        self.rollups.edge_counts()
+    }
+
+    /// Summarize `[start_ts, end_ts)` from the rollup tables
+    ///
+    /// Whole minute/hour/day buckets inside the window are read from the
+    /// rollups; the partial minutes at either end are aggregated from raw
+    /// edges, so the result is exact for any window.
+    pub fn summarize_rollups(
+        &self,
+        start_ts: u64,
+        end_ts: u64,
+        filter: &RollupFilter,
+    ) -> Result<RollupSummary> {
+        self.rollups.summarize(start_ts, end_ts, filter, |from, to| {
+            let edges = self.range_scan_filtered(
+                from,
+                to.saturating_sub(1),
+                Some(filter.tenant_id),
+                filter.project_id,
+            )?;
+            Ok(edges.iter().map(|edge| self.stored_rollup_sample(edge)).collect())
+        })
     }
 
     /// Get the pre-computed dashboard summary (Task 9)
@@ -1929,6 +3047,16 @@ impl AgentReplayStorage {
             wrote = true;
         }
 
+        // Persist changed rollup buckets and prune the fine-grained tables
+        for (granularity, bucket_ts, rows) in self.rollups.take_dirty() {
+            let value = bincode::serialize(&rows)
+                .map_err(|e| AgentreplayError::Serialization(e.to_string()))?;
+            self.connection.put(&encode_rollup_key(granularity, bucket_ts), &value)
+                .map_err(|e| AgentreplayError::Internal(format!("Rollup flush failed: {}", e)))?;
+            wrote = true;
+        }
+        wrote |= self.prune_rollups();
+
         // Only commit if we actually wrote metrics (avoid "No active transaction" error)
         if wrote {
             let _ = self.connection.commit()
@@ -1938,14 +3066,106 @@ impl AgentReplayStorage {
         Ok(())
     }
 
+    /// Drop rollup buckets past their retention, returning whether any were
+    /// deleted
+    ///
+    /// Queries serve pruned ranges from raw edges, so this also runs on open
+    /// to tell the reloaded tables what was pruned before.
+    fn prune_rollups(&self) -> bool {
+        let now_us = now_us();
+        let mut pruned = false;
+        for (granularity, retention_us) in [
+            (RollupGranularity::Minute, MINUTE_ROLLUP_RETENTION_US),
+            (RollupGranularity::Hour, HOUR_ROLLUP_RETENTION_US),
+        ] {
+            for bucket_ts in self.rollups.prune(granularity, now_us.saturating_sub(retention_us)) {
+                let _ = self.connection.delete(&encode_rollup_key(granularity, bucket_ts));
+                pruned = true;
+            }
+        }
+        pruned
+    }
+
     /// Sync data to disk
     pub fn sync(&self) -> Result<()> {
         // Force fsync on the underlying connection
+        let started = std::time::Instant::now();
+        self.connection.fsync()
+            .map_err(|e| AgentreplayError::Internal(format!("SochDB fsync failed: {}", e)))?;
+        self.write_telemetry.fsync.record(started.elapsed());
+        Ok(())
+    }
+
+    /// Run `f` with writes blocked after committing, flushing the memtable
+    /// and fsyncing; `f` receives the WAL length at that point
+    ///
+    /// Used by online backups to capture a consistent set of files. Keep `f`
+    /// to metadata work: ingestion waits on the write lock meanwhile.
+    pub(crate) fn with_write_barrier<T>(&self, f: impl FnOnce(u64) -> Result<T>) -> Result<T> {
+        let _write_guard = self.write_lock.write();
+        // Most writes commit as they go; with none pending there is no
+        // transaction to commit
+        match self.connection.commit() {
+            Ok(_) | Err(sochdb::ClientError::Transaction(_)) => {}
+            Err(e) => {
+                return Err(AgentreplayError::Internal(format!("SochDB commit failed: {}", e)))
+            }
+        }
+        self.connection.checkpoint()
+            .map_err(|e| AgentreplayError::Internal(format!("SochDB checkpoint failed: {}", e)))?;
         self.connection.fsync()
             .map_err(|e| AgentreplayError::Internal(format!("SochDB fsync failed: {}", e)))?;
+        f(self.wal_size_bytes())
+    }
+
+    /// Commit pending writes, recording fsync latency and group commit size
+    ///
+    /// `writes` is the number of records the commit covers (edges and
+    /// payloads; index entries are not counted separately).
+    fn commit_recorded(&self, writes: u64) -> Result<()> {
+        let started = std::time::Instant::now();
+        self.connection.commit()
+            .map_err(|e| AgentreplayError::Internal(format!("SochDB commit failed: {}", e)))?;
+        self.write_telemetry.fsync.record(started.elapsed());
+        self.write_telemetry.group_commit.record_commit(writes);
+        self.record_wal_growth();
         Ok(())
     }
 
+    /// Attribute WAL growth since the last sample to physical writes
+    fn record_wal_growth(&self) {
+        let wal_bytes = self.wal_size_bytes();
+        let previous = self.wal_bytes_seen.swap(wal_bytes, Ordering::Relaxed);
+        // A smaller WAL was truncated by a checkpoint since the last sample,
+        // so everything in it now was written after that
+        let written = if wal_bytes >= previous {
+            wal_bytes - previous
+        } else {
+            wal_bytes
+        };
+        self.write_telemetry
+            .write_amplification
+            .record_wal_write(written);
+    }
+
+    /// Count bytes handed to storage by callers (before compression)
+    fn record_logical_write(&self, bytes: usize) {
+        self.write_telemetry
+            .write_amplification
+            .record_logical_write(bytes as u64);
+    }
+
+    /// Write path telemetry since open: WAL vs logical bytes, fsync latency
+    /// percentiles and group commit batch sizes
+    ///
+    /// WAL bytes are sampled from the WAL file size at each commit, so writes
+    /// SochDB's group commit flushes on its own are picked up at the next
+    /// explicit commit (or by this call).
+    pub fn write_telemetry(&self) -> WriteTelemetryReport {
+        self.record_wal_growth();
+        self.write_telemetry.report()
+    }
+
     /// Get storage statistics with real observability metrics
     ///
     /// **Real Metrics:** Unlike the previous implementation, this actually computes:
@@ -2160,6 +3380,183 @@ impl AgentReplayStorage {
         Ok(stats)
     }
 
+    /// Compaction progress, space amplification and tombstone counts
+    ///
+    /// Scans every trace and payload record to measure the live data size,
+    /// so this is a diagnostics call, not something to poll rapidly.
+    pub fn compaction_stats(&self) -> Result<CompactionStats> {
+        let mut stats = CompactionStats::default();
+        for (prefix, count) in [
+            (TRACE_PREFIX, &mut stats.live_edges),
+            (PAYLOAD_PREFIX, &mut stats.payload_count),
+        ] {
+            let records = self.connection.scan(&format!("{}/", prefix))
+                .map_err(|e| AgentreplayError::Internal(format!("SochDB scan failed: {}", e)))?;
+            for (key, value) in records {
+                *count += 1;
+                stats.logical_bytes += (key.len() + value.len()) as u64;
+            }
+        }
+
+        stats.disk_bytes = self.compute_disk_bytes();
+        stats.wal_bytes = self.wal_size_bytes();
+        stats.space_amplification = space_amplification(stats.disk_bytes, stats.logical_bytes);
+        stats.tombstones_written = self.stats.deletes.load(Ordering::Relaxed);
+        let total = stats.live_edges + stats.tombstones_written;
+        if total > 0 {
+            stats.tombstone_ratio = stats.tombstones_written as f64 / total as f64;
+        }
+        stats.orphan_payloads = self.health_check().orphan_payloads;
+
+        let tracker = self.compaction.read();
+        stats.in_progress = tracker.phase.is_some();
+        stats.phase = tracker.phase;
+        stats.started_at_us = tracker.started_at_us;
+        stats.last_run = tracker.last_run.clone();
+        Ok(stats)
+    }
+
+    /// Run a manual compaction: remove orphaned payloads, then checkpoint
+    ///
+    /// Fails with `InvalidArgument` if one is already running. Progress is
+    /// visible through [`Self::compaction_stats`] while it runs.
+    pub fn compact(&self) -> Result<CompactionRun> {
+        let started_at_us = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .map(|d| d.as_micros() as u64)
+            .unwrap_or(0);
+        {
+            let mut tracker = self.compaction.write();
+            if tracker.phase.is_some() {
+                return Err(AgentreplayError::InvalidArgument(
+                    "A compaction is already in progress".to_string(),
+                ));
+            }
+            tracker.phase = Some(CompactionPhase::RemovingOrphans);
+            tracker.started_at_us = Some(started_at_us);
+        }
+
+        let start = std::time::Instant::now();
+        let mut run = CompactionRun {
+            started_at_us,
+            disk_bytes_before: self.compute_disk_bytes(),
+            wal_bytes_before: self.wal_size_bytes(),
+            ..Default::default()
+        };
+        match self.cleanup_orphans() {
+            Ok(cleanup) => {
+                run.orphan_payloads_removed = cleanup.payloads_deleted;
+                self.compaction.write().phase = Some(CompactionPhase::Checkpointing);
+                if let Err(e) = self.checkpoint() {
+                    run.error = Some(e.to_string());
+                }
+            }
+            Err(e) => run.error = Some(e.to_string()),
+        }
+        run.duration_ms = start.elapsed().as_millis() as u64;
+        run.disk_bytes_after = self.compute_disk_bytes();
+        run.wal_bytes_after = self.wal_size_bytes();
+
+        info!(
+            reclaimed_bytes = run.bytes_reclaimed(),
+            orphan_payloads_removed = run.orphan_payloads_removed,
+            duration_ms = run.duration_ms,
+            "Manual compaction finished"
+        );
+        let mut tracker = self.compaction.write();
+        tracker.phase = None;
+        tracker.started_at_us = None;
+        tracker.last_run = Some(run.clone());
+        Ok(run)
+    }
+
+    /// Validate every primary trace record
+    ///
+    /// Each record must have a well-formed key, deserialize, carry a valid
+    /// checksum and agree with its key (tenant, project, timestamp, edge ID).
+    /// The edge-ID index is then cross-checked against the valid records.
+    pub fn verify_integrity(&self) -> Result<IntegrityReport> {
+        let mut report = IntegrityReport::default();
+        let records = self.connection.scan(&format!("{}/", TRACE_PREFIX))
+            .map_err(|e| AgentreplayError::Internal(format!("SochDB scan failed: {}", e)))?;
+
+        let mut valid: HashMap<u128, String> = HashMap::new();
+        for (key, value) in records {
+            report.records_scanned += 1;
+            match check_trace_record(&key, &value) {
+                Ok(edge) => {
+                    report.valid_edges += 1;
+                    valid.insert(edge.edge_id, key);
+                }
+                Err((kind, detail)) => report.corrupt.push(CorruptRecord { key, kind, detail }),
+            }
+        }
+
+        let index_entries = self.connection.scan("idx/edge/")
+            .map_err(|e| AgentreplayError::Internal(format!("SochDB scan failed: {}", e)))?;
+        let mut indexed = 0u64;
+        for (key, value) in index_entries {
+            let edge_id = key
+                .strip_prefix("idx/edge/")
+                .and_then(|id| u128::from_str_radix(id, 16).ok());
+            let target = std::str::from_utf8(&value).ok();
+            match edge_id.and_then(|id| valid.get(&id)) {
+                Some(primary) if Some(primary.as_str()) == target => indexed += 1,
+                _ => report.dangling_index_entries += 1,
+            }
+        }
+        report.unindexed_edges = report.valid_edges.saturating_sub(indexed);
+
+        Ok(report)
+    }
+
+    /// Delete raw records by key (used to drop corrupt records found by fsck)
+    pub fn remove_raw_records(&self, keys: &[String]) -> Result<usize> {
+        let _write_guard = self.write_lock.write();
+        for key in keys {
+            self.connection.delete(key)
+                .map_err(|e| AgentreplayError::Internal(format!("SochDB delete failed: {}", e)))?;
+        }
+        self.connection.commit()
+            .map_err(|e| AgentreplayError::Internal(format!("SochDB commit failed: {}", e)))?;
+        Ok(keys.len())
+    }
+
+    /// Rebuild the edge, session, project and tenant/time indexes
+    ///
+    /// Drops every existing entry and re-derives them from the primary trace
+    /// records that pass [`Self::verify_integrity`]'s checks.
+    pub fn rebuild_secondary_indexes(&self) -> Result<IndexRebuildStats> {
+        let _write_guard = self.write_lock.write();
+        let mut stats = IndexRebuildStats::default();
+
+        for prefix in SECONDARY_INDEX_PREFIXES {
+            let entries = self.connection.scan(prefix)
+                .map_err(|e| AgentreplayError::Internal(format!("SochDB scan failed: {}", e)))?;
+            for (key, _) in entries {
+                self.connection.delete(&key)
+                    .map_err(|e| AgentreplayError::Internal(format!("SochDB delete failed: {}", e)))?;
+                stats.entries_removed += 1;
+            }
+        }
+
+        let records = self.connection.scan(&format!("{}/", TRACE_PREFIX))
+            .map_err(|e| AgentreplayError::Internal(format!("SochDB scan failed: {}", e)))?;
+        for (key, value) in records {
+            match check_trace_record(&key, &value) {
+                Ok(edge) => {
+                    self.put_secondary_indexes(&key, &edge)?;
+                    stats.edges_indexed += 1;
+                }
+                Err(_) => stats.records_skipped += 1,
+            }
+        }
+
+        self.connection.commit()
+            .map_err(|e| AgentreplayError::Internal(format!("SochDB commit failed: {}", e)))?;
+        Ok(stats)
+    }
+
     /// Check if shutdown was requested
     pub fn is_shutdown(&self) -> bool {
         self.shutdown.load(Ordering::Acquire)
@@ -2313,6 +3710,7 @@ impl AgentReplayStorage {
     /// where trace data can be re-collected from external sources.
     pub fn checkpoint(&self) -> Result<()> {
         let _write_guard = self.write_lock.write();
+        self.record_wal_growth();
         let wal_before = self.wal_size_bytes();
         self.connection.checkpoint()
             .map_err(|e| AgentreplayError::Internal(format!("SochDB checkpoint failed: {}", e)))?;
@@ -2322,6 +3720,7 @@ impl AgentReplayStorage {
             tracing::warn!("WAL truncation failed (non-fatal): {}", e);
         } else {
             let wal_after = self.wal_size_bytes();
+            self.wal_bytes_seen.store(wal_after, Ordering::Relaxed);
             tracing::info!(
                 wal_before_mb = wal_before / (1024 * 1024),
                 wal_after_mb = wal_after / (1024 * 1024),
@@ -2420,6 +3819,36 @@ mod tests {
         assert_eq!(retrieved.unwrap().edge_id, 1);
     }
 
+    #[test]
+    fn test_verify_integrity_and_rebuild() {
+        let tmp_dir = TempDir::new().unwrap();
+        let storage = AgentReplayStorage::open(tmp_dir.path()).unwrap();
+        let mut good = create_test_edge(0x1, 1_000, 1, 0);
+        good.checksum = good.compute_checksum();
+        storage.put(good).unwrap();
+
+        // Flip the token count without fixing the checksum
+        let mut bad = create_test_edge(0x2, 2_000, 1, 0);
+        bad.checksum = bad.compute_checksum();
+        bad.token_count += 1;
+        let bad_key = encode_trace_key(1, 0, 2_000, 0x2);
+        storage.connection.put(&bad_key, &serialize_edge(&bad).unwrap()).unwrap();
+        storage.connection.put("idx/edge/00000000000000000000000000000003", b"traces/1/0/x/3").unwrap();
+
+        let report = storage.verify_integrity().unwrap();
+        assert_eq!(report.records_scanned, 2);
+        assert_eq!(report.valid_edges, 1);
+        assert_eq!(report.corrupt.len(), 1);
+        assert_eq!(report.corrupt[0].kind, CorruptionKind::ChecksumMismatch);
+        assert_eq!(report.dangling_index_entries, 1);
+
+        storage.remove_raw_records(&[bad_key]).unwrap();
+        let stats = storage.rebuild_secondary_indexes().unwrap();
+        assert_eq!(stats.edges_indexed, 1);
+        assert!(storage.verify_integrity().unwrap().is_clean());
+        assert!(storage.get(0x1).unwrap().is_some());
+    }
+
     #[test]
     fn test_storage_batch() {
         let tmp_dir = TempDir::new().unwrap();
@@ -2470,6 +3899,388 @@ mod tests {
         assert_eq!(retrieved.unwrap(), payload);
     }
 
+    #[test]
+    fn test_edge_enrichment_store() {
+        let tmp_dir = TempDir::new().unwrap();
+        let storage = AgentReplayStorage::open(tmp_dir.path()).unwrap();
+
+        assert_eq!(storage.get_edge_enrichment(7).unwrap(), None);
+
+        let enrichment = EdgeEnrichment {
+            model_family: Some("claude".to_string()),
+            provider: Some("anthropic".to_string()),
+            sdk_version: Some("0.4.1".to_string()),
+            ..Default::default()
+        };
+        storage.put_edge_enrichment(7, &enrichment).unwrap();
+        assert_eq!(storage.get_edge_enrichment(7).unwrap(), Some(enrichment));
+    }
+
+    #[test]
+    fn test_session_analysis_store() {
+        let tmp_dir = TempDir::new().unwrap();
+        let storage = AgentReplayStorage::open(tmp_dir.path()).unwrap();
+
+        assert_eq!(storage.get_session_analysis(42).unwrap(), None);
+
+        let mut analysis = SessionAnalysis {
+            session_id: 42,
+            tenant_id: 1,
+            project_id: 3,
+            started_at: 1_000,
+            ended_at: 5_000,
+            turn_count: 4,
+            topics: vec!["refund".to_string(), "order".to_string()],
+            topic_drift: 0.25,
+            goal: GoalVerdict::Unknown,
+            reason: "no completion signal".to_string(),
+            prompt_versions: vec!["support@3".to_string()],
+            method: "heuristic".to_string(),
+            analyzed_at: 6_000,
+        };
+        storage.put_session_analysis(&analysis).unwrap();
+        assert_eq!(
+            storage.get_session_analysis(42).unwrap().as_ref(),
+            Some(&analysis)
+        );
+
+        analysis.goal = GoalVerdict::Completed;
+        storage.put_session_analysis(&analysis).unwrap();
+        assert_eq!(
+            storage.get_session_analysis(42).unwrap().map(|a| a.goal),
+            Some(GoalVerdict::Completed)
+        );
+    }
+
+    #[test]
+    fn test_session_summary_store() {
+        let tmp_dir = TempDir::new().unwrap();
+        let storage = AgentReplayStorage::open(tmp_dir.path()).unwrap();
+
+        assert_eq!(storage.get_session_summary(1, 42).unwrap(), None);
+        assert!(!storage.session_summaries_ready().unwrap());
+
+        let mut summary = SessionRollup {
+            session_id: 42,
+            tenant_id: 1,
+            started_at: 1_000,
+            last_span_at: 4_000,
+            ended_at: 5_500,
+            span_count: 3,
+            cost_usd: 0.25,
+            models: vec!["gpt-4o".to_string()],
+            ..Default::default()
+        };
+        storage.put_session_summary(&summary).unwrap();
+        summary.session_id = 43;
+        storage.put_session_summary(&summary).unwrap();
+        summary.tenant_id = 2;
+        storage.put_session_summary(&summary).unwrap();
+
+        let stored = storage.get_session_summary(1, 42).unwrap().unwrap();
+        assert_eq!(stored.duration_us(), 4_500);
+        assert_eq!(stored.models, vec!["gpt-4o".to_string()]);
+        assert_eq!(storage.list_session_summaries(1).unwrap().len(), 2);
+        assert_eq!(storage.list_session_summaries(2).unwrap().len(), 1);
+
+        storage.mark_session_summaries_ready().unwrap();
+        assert!(storage.session_summaries_ready().unwrap());
+    }
+
+    #[test]
+    fn test_conversation_links() {
+        let tmp_dir = TempDir::new().unwrap();
+        let storage = AgentReplayStorage::open(tmp_dir.path()).unwrap();
+
+        let mut link = ConversationLink {
+            conversation_id: "chat-1".to_string(),
+            tenant_id: 1,
+            project_id: 0,
+            session_id: 7,
+            edge_id: 0xabc,
+            user_id: Some("u-1".to_string()),
+            linked_at: 1_000,
+        };
+        storage.put_conversation_link(&link).unwrap();
+        // Re-linking the same session is a no-op
+        storage.put_conversation_link(&link).unwrap();
+        link.session_id = 0;
+        storage.put_conversation_link(&link).unwrap();
+        // A conversation whose ID extends another's stays separate
+        link.conversation_id = "chat-10".to_string();
+        storage.put_conversation_link(&link).unwrap();
+
+        let links = storage.list_conversation_links(1, "chat-1").unwrap();
+        assert_eq!(links.len(), 2);
+        assert!(links.iter().any(|l| l.session_id == 7));
+        assert!(links
+            .iter()
+            .any(|l| l.session_id == 0 && l.edge_id == 0xabc));
+        assert_eq!(
+            storage.list_conversation_links(1, "chat-10").unwrap().len(),
+            1
+        );
+        assert!(storage
+            .list_conversation_links(2, "chat-1")
+            .unwrap()
+            .is_empty());
+    }
+
+    #[test]
+    fn test_feedback_store() {
+        let tmp_dir = TempDir::new().unwrap();
+        let storage = AgentReplayStorage::open(tmp_dir.path()).unwrap();
+
+        let mut feedback = FeedbackRecord {
+            edge_id: 0x10,
+            tenant_id: 1,
+            project_id: 2,
+            thumbs: Some(1),
+            rating: None,
+            tags: vec!["helpful".to_string()],
+            comment: None,
+            user_id: Some("u-1".to_string()),
+            created_at: 1_000,
+        };
+        assert_eq!(feedback.score(), Some(1.0));
+        storage.put_feedback(&feedback).unwrap();
+        feedback.thumbs = Some(-1);
+        feedback.rating = Some(2);
+        feedback.created_at = 2_000;
+        assert_eq!(feedback.score(), Some(0.25));
+        storage.put_feedback(&feedback).unwrap();
+        feedback.edge_id = 0x20;
+        storage.put_feedback(&feedback).unwrap();
+
+        assert_eq!(storage.list_feedback(1, Some(0x10)).unwrap().len(), 2);
+        assert_eq!(storage.list_feedback(1, Some(0x20)).unwrap().len(), 1);
+        assert_eq!(storage.list_feedback(1, None).unwrap().len(), 3);
+        assert!(storage.list_feedback(2, None).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_provider_key_store() {
+        let tmp_dir = TempDir::new().unwrap();
+        let storage = AgentReplayStorage::open(tmp_dir.path()).unwrap();
+
+        let mut key = ProviderKeyRecord {
+            tenant_id: 1,
+            alias: "openai-prod".to_string(),
+            provider: "openai".to_string(),
+            base_url: None,
+            nonce: vec![0; 12],
+            ciphertext: vec![1, 2, 3],
+            key_hint: "...abcd".to_string(),
+            created_at: 1_000,
+            updated_at: 1_000,
+        };
+        storage.put_provider_key(&key).unwrap();
+        key.alias = "anthropic".to_string();
+        storage.put_provider_key(&key).unwrap();
+
+        assert_eq!(
+            storage
+                .get_provider_key(1, "openai-prod")
+                .unwrap()
+                .unwrap()
+                .ciphertext,
+            vec![1, 2, 3]
+        );
+        assert!(storage
+            .get_provider_key(2, "openai-prod")
+            .unwrap()
+            .is_none());
+        let aliases: Vec<String> = storage
+            .list_provider_keys(1)
+            .unwrap()
+            .into_iter()
+            .map(|k| k.alias)
+            .collect();
+        assert_eq!(aliases, vec!["anthropic", "openai-prod"]);
+
+        assert!(storage.delete_provider_key(1, "anthropic").unwrap());
+        assert!(!storage.delete_provider_key(1, "anthropic").unwrap());
+        assert_eq!(storage.list_provider_keys(1).unwrap().len(), 1);
+
+        let usage = |timestamp| KeyUsageRecord {
+            tenant_id: 1,
+            alias: "openai-prod".to_string(),
+            source: "gateway".to_string(),
+            provider: "openai".to_string(),
+            model: "gpt-4o".to_string(),
+            input_tokens: 10,
+            output_tokens: 5,
+            cost_usd: 0.01,
+            timestamp,
+        };
+        // Same timestamp twice: both kept
+        storage.put_key_usage(&usage(100)).unwrap();
+        storage.put_key_usage(&usage(100)).unwrap();
+        storage.put_key_usage(&usage(300)).unwrap();
+        assert_eq!(storage.list_key_usage(1, 0, 200).unwrap().len(), 2);
+        assert_eq!(storage.list_key_usage(1, 0, 400).unwrap().len(), 3);
+        assert!(storage.list_key_usage(2, 0, 400).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_annotation_queue_store() {
+        let tmp_dir = TempDir::new().unwrap();
+        let storage = AgentReplayStorage::open(tmp_dir.path()).unwrap();
+
+        let queue = AnnotationQueueRecord {
+            id: "q1".to_string(),
+            tenant_id: 1,
+            name: "Low faithfulness".to_string(),
+            description: None,
+            rubric: vec![RubricDimension {
+                name: "correct".to_string(),
+                description: None,
+                scale: RubricScale::Categorical {
+                    labels: vec!["yes".to_string(), "no".to_string()],
+                },
+            }],
+            reviewers: vec!["alice".to_string(), "bob".to_string()],
+            reviews_per_item: 2,
+            filter: serde_json::Value::Null,
+            created_at: 1_000,
+        };
+        storage.put_annotation_queue(&queue).unwrap();
+        assert_eq!(storage.get_annotation_queue(1, "q1").unwrap(), Some(queue));
+        assert!(storage.get_annotation_queue(2, "q1").unwrap().is_none());
+        assert_eq!(storage.list_annotation_queues(1).unwrap().len(), 1);
+
+        for edge_id in [0x2, 0x1] {
+            storage
+                .put_annotation_item(&AnnotationQueueItem {
+                    queue_id: "q1".to_string(),
+                    edge_id,
+                    project_id: 0,
+                    assignees: vec!["alice".to_string(), "bob".to_string()],
+                    added_at: 1_000,
+                })
+                .unwrap();
+        }
+        let items = storage.list_annotation_items("q1").unwrap();
+        assert_eq!(
+            items.iter().map(|i| i.edge_id).collect::<Vec<_>>(),
+            vec![1, 2]
+        );
+
+        let mut label = AnnotationLabel {
+            queue_id: "q1".to_string(),
+            edge_id: 0x1,
+            reviewer: "alice".to_string(),
+            labels: [("correct".to_string(), LabelValue::Label("yes".to_string()))]
+                .into_iter()
+                .collect(),
+            comment: None,
+            created_at: 2_000,
+        };
+        storage.put_annotation_label(&label).unwrap();
+        // Relabeling replaces the reviewer's previous answer
+        label
+            .labels
+            .insert("correct".to_string(), LabelValue::Label("no".to_string()));
+        storage.put_annotation_label(&label).unwrap();
+        label.reviewer = "bob".to_string();
+        storage.put_annotation_label(&label).unwrap();
+
+        let labels = storage.list_annotation_labels("q1").unwrap();
+        assert_eq!(labels.len(), 2);
+        assert_eq!(
+            labels[0].labels["correct"],
+            LabelValue::Label("no".to_string())
+        );
+        assert!(storage.list_annotation_labels("q2").unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_span_highlights_store() {
+        use agentreplay_core::actionable_feedback::Severity;
+        use agentreplay_core::SpanHighlight;
+
+        let tmp_dir = TempDir::new().unwrap();
+        let storage = AgentReplayStorage::open(tmp_dir.path()).unwrap();
+
+        let mut record = SpanHighlightRecord {
+            span_id: 0x42,
+            evaluator_id: "hallucination_v1".to_string(),
+            highlights: vec![SpanHighlight::new(
+                0x42,
+                SpanHighlight::OUTPUT,
+                0..10,
+                Severity::Major,
+                "Contradicted by context",
+            )],
+            created_at: 1_000,
+        };
+        storage.put_span_highlights(&record).unwrap();
+        // Re-running an evaluator replaces its highlights
+        record.highlights.clear();
+        storage.put_span_highlights(&record).unwrap();
+        record.evaluator_id = "tool_correctness_v1".to_string();
+        storage.put_span_highlights(&record).unwrap();
+
+        let records = storage.list_span_highlights(0x42).unwrap();
+        assert_eq!(records.len(), 2);
+        assert!(records.iter().all(|r| r.highlights.is_empty()));
+        assert!(storage.list_span_highlights(0x43).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_edge_logprobs_store() {
+        let tmp_dir = TempDir::new().unwrap();
+        let storage = AgentReplayStorage::open(tmp_dir.path()).unwrap();
+
+        assert!(storage.get_edge_logprobs(9).unwrap().is_none());
+        assert!(storage.get_logprob_summary(9).unwrap().is_none());
+
+        let tokens = vec![
+            TokenLogprob {
+                token: "Yes".to_string(),
+                logprob: -0.05,
+                top_logprobs: vec![("No".to_string(), -3.1)],
+            },
+            TokenLogprob {
+                token: ".".to_string(),
+                logprob: -0.9,
+                top_logprobs: Vec::new(),
+            },
+        ];
+        let summary = storage.put_edge_logprobs(9, &tokens).unwrap();
+        assert_eq!(summary.low_confidence_tokens, 1);
+        assert_eq!(storage.get_logprob_summary(9).unwrap(), Some(summary));
+        let stored = storage.get_edge_logprobs(9).unwrap().unwrap();
+        assert_eq!(stored.tokens[0].top_logprobs[0].0, "No");
+    }
+
+    #[test]
+    fn test_edge_links_store() {
+        use agentreplay_core::LinkKind;
+
+        let tmp_dir = TempDir::new().unwrap();
+        let storage = AgentReplayStorage::open(tmp_dir.path()).unwrap();
+
+        assert!(storage.get_edge_links(10).unwrap().is_empty());
+
+        // Synthesis span 10 merges the results of sub-agents 1 and 2
+        let links = vec![
+            SpanLink::new(1, LinkKind::FanIn),
+            SpanLink::new(2, LinkKind::FanIn),
+        ];
+        storage.put_edge_links(10, &links).unwrap();
+        storage
+            .put_edge_links(11, &[SpanLink::new(1, LinkKind::FollowsFrom)])
+            .unwrap();
+
+        assert_eq!(storage.get_edge_links(10).unwrap(), links);
+        let mut linking = storage.get_linking_edges(1).unwrap();
+        linking.sort();
+        assert_eq!(linking, vec![10, 11]);
+        assert_eq!(storage.get_linking_edges(2).unwrap(), vec![10]);
+        assert!(storage.get_linking_edges(10).unwrap().is_empty());
+    }
+
     #[test]
     fn test_metrics() {
         let tmp_dir = TempDir::new().unwrap();
@@ -2485,6 +4296,32 @@ mod tests {
         assert_eq!(metrics.total_tokens, 500);
     }
 
+    #[test]
+    fn test_rollup_window_summary() {
+        let tmp_dir = TempDir::new().unwrap();
+        let storage = AgentReplayStorage::open(tmp_dir.path()).unwrap();
+
+        storage.put_edge_attrs(3, Some("openai"), Some("gpt-4o"), None).unwrap();
+        for i in 0..5 {
+            let edge = create_test_edge(i, i as u64 * 30_000_000, 1, 1);
+            storage.put(edge).unwrap();
+        }
+
+        let filter = RollupFilter { tenant_id: 1, ..Default::default() };
+        let summary = storage.summarize_rollups(10_000_000, 150_000_000, &filter).unwrap();
+        assert_eq!(summary.totals.request_count, 4);
+        assert_eq!(summary.raw_edges, 2);
+        assert_eq!(summary.by_model["gpt-4o"].request_count, 1);
+
+        // Rollups survive a restart; pruned minute buckets are served from raw edges
+        storage.flush_metrics().unwrap();
+        drop(storage);
+        let storage = AgentReplayStorage::open(tmp_dir.path()).unwrap();
+        let summary = storage.summarize_rollups(0, 150_000_000, &filter).unwrap();
+        assert_eq!(summary.totals.request_count, 5);
+        assert_eq!(summary.totals.total_tokens, 500);
+    }
+
     #[test]
     fn test_stats() {
         let tmp_dir = TempDir::new().unwrap();
@@ -2500,6 +4337,61 @@ mod tests {
         assert_eq!(stats.total_edges, 3);
     }
 
+    #[test]
+    fn test_compaction_stats_and_compact() {
+        let tmp_dir = TempDir::new().unwrap();
+        let storage = AgentReplayStorage::open(tmp_dir.path()).unwrap();
+        for i in 1..=3 {
+            storage.put(create_test_edge(i, i as u64 * 1000, 1, 1)).unwrap();
+        }
+        storage.put_payload(1, b"kept payload").unwrap();
+        // Payload whose edge never existed
+        storage.put_payload(99, b"orphan payload").unwrap();
+        storage.delete(3, 1).unwrap();
+
+        let stats = storage.compaction_stats().unwrap();
+        assert!(!stats.in_progress);
+        assert_eq!(stats.live_edges, 2);
+        assert_eq!(stats.payload_count, 2);
+        assert_eq!(stats.tombstones_written, 1);
+        assert_eq!(stats.orphan_payloads, 1);
+        assert!(stats.logical_bytes > 0);
+        assert!(stats.last_run.is_none());
+
+        let run = storage.compact().unwrap();
+        assert_eq!(run.orphan_payloads_removed, 1);
+        assert!(run.error.is_none());
+
+        let stats = storage.compaction_stats().unwrap();
+        assert_eq!(stats.orphan_payloads, 0);
+        assert_eq!(stats.payload_count, 1);
+        assert_eq!(stats.last_run, Some(run));
+    }
+
+    #[test]
+    fn test_space_amplification() {
+        assert_eq!(space_amplification(300, 100), 3.0);
+        assert_eq!(space_amplification(300, 0), 0.0);
+    }
+
+    #[test]
+    fn test_write_telemetry() {
+        let tmp_dir = TempDir::new().unwrap();
+        let storage = AgentReplayStorage::open(tmp_dir.path()).unwrap();
+        let edges: Vec<_> = (1..=4)
+            .map(|i| create_test_edge(i, i as u64 * 1000, 1, 1))
+            .collect();
+        storage.put_batch(&edges).unwrap();
+        storage.put_payload(1, b"payload").unwrap();
+
+        let report = storage.write_telemetry();
+        assert_eq!(report.group_commit.commits, 2);
+        assert_eq!(report.group_commit.total_writes, 5);
+        assert_eq!(report.group_commit.max_batch_size, 4);
+        assert_eq!(report.fsync.count, 2);
+        assert!(report.write_amplification.total_logical > 0);
+    }
+
     #[test]
     fn test_semantic_cache() {
         let tmp_dir = TempDir::new().unwrap();
@@ -0,0 +1,734 @@
+// Copyright 2025 AgentReplay (https://github.com/agentreplay)
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program. If not, see <https://www.gnu.org/licenses/>.
+
+//! Write-ahead log shipping to a warm standby
+//!
+//! SochDB's own WAL is an internal format that is truncated on checkpoint, so
+//! what gets shipped is a logical log of engine writes (edges, payloads,
+//! vectors, deletes). The primary buffers records, numbers them with a
+//! monotonically increasing LSN and periodically seals them into a segment in
+//! a shared [`StorageBackend`]; the standby polls for segments past its
+//! applied LSN and replays them:
+//!
+//! ```text
+//! primary: writes ──append──► buffer ──seal──► wal/segments/<first_lsn>-<epoch>.seg
+//!                                              wal/HEAD
+//! standby: wal/HEAD ──poll──► segments after applied LSN ──apply──► local db
+//! ```
+//!
+//! - **Segments** carry a blake3 checksum over their contents and are never
+//!   overwritten.
+//! - **Timeline**: every primary start (and every promotion) bumps the epoch
+//!   and records the LSN the new epoch starts at in `wal/HEAD`. A segment is
+//!   only valid if its epoch owns its LSN range, so anything a fenced primary
+//!   manages to upload after a promotion is ignored by readers.
+//! - Shipping is asynchronous: writes acknowledged within the last seal
+//!   interval may be missing on the standby after a failover.
+
+use crate::backend::StorageBackend;
+use agentreplay_core::clock::now_secs;
+use agentreplay_core::{AgentFlowEdge, AgentreplayError, Result};
+use parking_lot::Mutex;
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+
+/// Backend key of the shipping head (timeline and last shipped LSN)
+pub const WAL_HEAD_KEY: &str = "wal/HEAD";
+
+/// Backend prefix of sealed segments
+pub const WAL_SEGMENT_PREFIX: &str = "wal/segments/";
+
+const SEGMENT_MAGIC: &[u8; 4] = b"ARWS";
+const SEGMENT_VERSION: u8 = 1;
+/// magic + version + epoch + created_at + record count
+const SEGMENT_HEADER_LEN: usize = 4 + 1 + 8 + 8 + 4;
+const CHECKSUM_LEN: usize = 32;
+
+const TAG_EDGE: u8 = 1;
+const TAG_PAYLOAD: u8 = 2;
+const TAG_VECTOR: u8 = 3;
+const TAG_DELETE: u8 = 4;
+const TAG_DELETE_PROJECT: u8 = 5;
+const TAG_COST: u8 = 6;
+
+/// One logical write
+#[derive(Debug, Clone, PartialEq)]
+pub enum WalEntry {
+    Edge(AgentFlowEdge),
+    Payload { edge_id: u128, data: Vec<u8> },
+    Vector { edge_id: u128, values: Vec<f32> },
+    Delete { edge_id: u128, tenant_id: u64 },
+    DeleteProject { project_id: u16 },
+    Cost { edge_id: u128, cost_micros: u64 },
+}
+
+impl WalEntry {
+    fn encoded_len(&self) -> usize {
+        match self {
+            WalEntry::Edge(_) => 128,
+            WalEntry::Payload { data, .. } => 16 + data.len(),
+            WalEntry::Vector { values, .. } => 16 + values.len() * 4,
+            WalEntry::Delete { .. } => 24,
+            WalEntry::DeleteProject { .. } => 2,
+            WalEntry::Cost { .. } => 24,
+        }
+    }
+}
+
+/// A write together with its log sequence number
+#[derive(Debug, Clone, PartialEq)]
+pub struct WalRecord {
+    pub lsn: u64,
+    pub entry: WalEntry,
+}
+
+/// A sealed batch of consecutive records
+#[derive(Debug, Clone, PartialEq)]
+pub struct WalSegment {
+    pub epoch: u64,
+    pub created_at: u64,
+    pub records: Vec<WalRecord>,
+}
+
+impl WalSegment {
+    pub fn first_lsn(&self) -> u64 {
+        self.records.first().map_or(0, |r| r.lsn)
+    }
+
+    pub fn last_lsn(&self) -> u64 {
+        self.records.last().map_or(0, |r| r.lsn)
+    }
+
+    /// Serialize with a trailing blake3 checksum
+    pub fn encode(&self) -> Vec<u8> {
+        let body_len: usize = self
+            .records
+            .iter()
+            .map(|r| 1 + 8 + 4 + r.entry.encoded_len())
+            .sum();
+        let mut buf = Vec::with_capacity(SEGMENT_HEADER_LEN + body_len + CHECKSUM_LEN);
+        buf.extend_from_slice(SEGMENT_MAGIC);
+        buf.push(SEGMENT_VERSION);
+        buf.extend_from_slice(&self.epoch.to_le_bytes());
+        buf.extend_from_slice(&self.created_at.to_le_bytes());
+        buf.extend_from_slice(&(self.records.len() as u32).to_le_bytes());
+
+        for record in &self.records {
+            let tag = match &record.entry {
+                WalEntry::Edge(_) => TAG_EDGE,
+                WalEntry::Payload { .. } => TAG_PAYLOAD,
+                WalEntry::Vector { .. } => TAG_VECTOR,
+                WalEntry::Delete { .. } => TAG_DELETE,
+                WalEntry::DeleteProject { .. } => TAG_DELETE_PROJECT,
+                WalEntry::Cost { .. } => TAG_COST,
+            };
+            buf.push(tag);
+            buf.extend_from_slice(&record.lsn.to_le_bytes());
+            buf.extend_from_slice(&(record.entry.encoded_len() as u32).to_le_bytes());
+            match &record.entry {
+                WalEntry::Edge(edge) => buf.extend_from_slice(&edge.to_bytes()),
+                WalEntry::Payload { edge_id, data } => {
+                    buf.extend_from_slice(&edge_id.to_le_bytes());
+                    buf.extend_from_slice(data);
+                }
+                WalEntry::Vector { edge_id, values } => {
+                    buf.extend_from_slice(&edge_id.to_le_bytes());
+                    for value in values {
+                        buf.extend_from_slice(&value.to_le_bytes());
+                    }
+                }
+                WalEntry::Delete { edge_id, tenant_id } => {
+                    buf.extend_from_slice(&edge_id.to_le_bytes());
+                    buf.extend_from_slice(&tenant_id.to_le_bytes());
+                }
+                WalEntry::DeleteProject { project_id } => {
+                    buf.extend_from_slice(&project_id.to_le_bytes());
+                }
+                WalEntry::Cost {
+                    edge_id,
+                    cost_micros,
+                } => {
+                    buf.extend_from_slice(&edge_id.to_le_bytes());
+                    buf.extend_from_slice(&cost_micros.to_le_bytes());
+                }
+            }
+        }
+
+        let checksum = blake3::hash(&buf);
+        buf.extend_from_slice(checksum.as_bytes());
+        buf
+    }
+
+    /// Parse and verify a segment produced by [`WalSegment::encode`]
+    pub fn decode(bytes: &[u8]) -> Result<Self> {
+        let corrupt = |what: &str| AgentreplayError::Corruption(format!("WAL segment: {}", what));
+        if bytes.len() < SEGMENT_HEADER_LEN + CHECKSUM_LEN {
+            return Err(corrupt("truncated header"));
+        }
+        let (body, checksum) = bytes.split_at(bytes.len() - CHECKSUM_LEN);
+        if blake3::hash(body).as_bytes() != checksum {
+            return Err(corrupt("checksum mismatch"));
+        }
+        if &body[..4] != SEGMENT_MAGIC {
+            return Err(corrupt("bad magic"));
+        }
+        if body[4] != SEGMENT_VERSION {
+            return Err(corrupt(&format!("unsupported version {}", body[4])));
+        }
+
+        let mut cursor = Cursor { buf: body, pos: 5 };
+        let epoch = cursor.u64()?;
+        let created_at = cursor.u64()?;
+        let count = cursor.u32()? as usize;
+
+        let mut records = Vec::with_capacity(count.min(1 << 16));
+        for _ in 0..count {
+            let tag = cursor.take(1)?[0];
+            let lsn = cursor.u64()?;
+            let len = cursor.u32()? as usize;
+            let data = cursor.take(len)?;
+            let entry = match tag {
+                TAG_EDGE => {
+                    let raw: &[u8; 128] = data.try_into().map_err(|_| corrupt("edge length"))?;
+                    WalEntry::Edge(AgentFlowEdge::from_bytes(raw)?)
+                }
+                TAG_PAYLOAD if len >= 16 => WalEntry::Payload {
+                    edge_id: u128_le(&data[..16]),
+                    data: data[16..].to_vec(),
+                },
+                TAG_VECTOR if len >= 16 && (len - 16) % 4 == 0 => WalEntry::Vector {
+                    edge_id: u128_le(&data[..16]),
+                    values: data[16..]
+                        .chunks_exact(4)
+                        .map(|c| f32::from_le_bytes([c[0], c[1], c[2], c[3]]))
+                        .collect(),
+                },
+                TAG_DELETE if len == 24 => WalEntry::Delete {
+                    edge_id: u128_le(&data[..16]),
+                    tenant_id: u64::from_le_bytes(data[16..24].try_into().unwrap_or_default()),
+                },
+                TAG_DELETE_PROJECT if len == 2 => WalEntry::DeleteProject {
+                    project_id: u16::from_le_bytes([data[0], data[1]]),
+                },
+                TAG_COST if len == 24 => WalEntry::Cost {
+                    edge_id: u128_le(&data[..16]),
+                    cost_micros: u64::from_le_bytes(data[16..24].try_into().unwrap_or_default()),
+                },
+                _ => return Err(corrupt(&format!("bad record (tag {}, {} bytes)", tag, len))),
+            };
+            records.push(WalRecord { lsn, entry });
+        }
+        if cursor.pos != body.len() {
+            return Err(corrupt("trailing bytes"));
+        }
+
+        Ok(Self {
+            epoch,
+            created_at,
+            records,
+        })
+    }
+}
+
+fn u128_le(bytes: &[u8]) -> u128 {
+    let mut raw = [0u8; 16];
+    raw.copy_from_slice(&bytes[..16]);
+    u128::from_le_bytes(raw)
+}
+
+struct Cursor<'a> {
+    buf: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> Cursor<'a> {
+    fn take(&mut self, len: usize) -> Result<&'a [u8]> {
+        let end = self
+            .pos
+            .checked_add(len)
+            .filter(|end| *end <= self.buf.len())
+            .ok_or_else(|| AgentreplayError::Corruption("WAL segment: truncated record".into()))?;
+        let slice = &self.buf[self.pos..end];
+        self.pos = end;
+        Ok(slice)
+    }
+
+    fn u32(&mut self) -> Result<u32> {
+        let raw = self.take(4)?;
+        Ok(u32::from_le_bytes([raw[0], raw[1], raw[2], raw[3]]))
+    }
+
+    fn u64(&mut self) -> Result<u64> {
+        let mut raw = [0u8; 8];
+        raw.copy_from_slice(self.take(8)?);
+        Ok(u64::from_le_bytes(raw))
+    }
+}
+
+/// Start of one epoch on the timeline
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TimelineEntry {
+    pub epoch: u64,
+    /// First LSN written in this epoch
+    pub start_lsn: u64,
+    pub node_id: String,
+    pub started_at: u64,
+}
+
+/// Shipping state stored at [`WAL_HEAD_KEY`]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct WalHead {
+    /// Epochs in ascending order; the last one is the current primary
+    pub timeline: Vec<TimelineEntry>,
+    /// Last LSN sealed into a segment (0 if none)
+    pub last_lsn: u64,
+    pub updated_at: u64,
+}
+
+impl WalHead {
+    pub fn epoch(&self) -> u64 {
+        self.timeline.last().map_or(0, |t| t.epoch)
+    }
+
+    /// Whether `epoch` owns the LSN range `first..=last`
+    pub fn owns(&self, epoch: u64, first: u64, last: u64) -> bool {
+        let Some(idx) = self.timeline.iter().position(|t| t.epoch == epoch) else {
+            return false;
+        };
+        let end = self.timeline.get(idx + 1).map(|t| t.start_lsn);
+        let before_next = match end {
+            Some(end) => last < end,
+            None => true,
+        };
+        first >= self.timeline[idx].start_lsn && before_next
+    }
+}
+
+/// Read the shipping head from the backend
+pub fn read_wal_head(backend: &dyn StorageBackend) -> Result<Option<WalHead>> {
+    if !backend.exists(WAL_HEAD_KEY)? {
+        return Ok(None);
+    }
+    let bytes = backend.get(WAL_HEAD_KEY)?;
+    serde_json::from_slice(&bytes)
+        .map(Some)
+        .map_err(|e| AgentreplayError::Serialization(format!("WAL head: {}", e)))
+}
+
+fn write_wal_head(backend: &dyn StorageBackend, head: &WalHead) -> Result<()> {
+    let bytes =
+        serde_json::to_vec(head).map_err(|e| AgentreplayError::Serialization(e.to_string()))?;
+    backend.put(WAL_HEAD_KEY, &bytes)?;
+    backend.sync()
+}
+
+fn segment_key(first_lsn: u64, epoch: u64) -> String {
+    format!("{}{:020}-{:010}.seg", WAL_SEGMENT_PREFIX, first_lsn, epoch)
+}
+
+/// Parse `(first_lsn, epoch)` from a segment key
+fn parse_segment_key(key: &str) -> Option<(u64, u64)> {
+    let name = key.rsplit('/').next()?.strip_suffix(".seg")?;
+    let (lsn, epoch) = name.split_once('-')?;
+    Some((lsn.parse().ok()?, epoch.parse().ok()?))
+}
+
+/// All segment keys as `(first_lsn, epoch, key)`, in LSN order
+fn list_segments(backend: &dyn StorageBackend) -> Result<Vec<(u64, u64, String)>> {
+    let mut segments: Vec<(u64, u64, String)> = backend
+        .list(WAL_SEGMENT_PREFIX)?
+        .into_iter()
+        .filter_map(|meta| {
+            let (lsn, epoch) = parse_segment_key(&meta.key)?;
+            Some((lsn, epoch, meta.key))
+        })
+        .collect();
+    segments.sort();
+    Ok(segments)
+}
+
+/// A segment written by [`WalShipper::seal`]
+#[derive(Debug, Clone, Serialize)]
+pub struct ShippedSegment {
+    pub key: String,
+    pub first_lsn: u64,
+    pub last_lsn: u64,
+    pub records: usize,
+    pub bytes: usize,
+}
+
+struct ShipperState {
+    next_lsn: u64,
+    buffer: Vec<WalRecord>,
+    buffered_bytes: usize,
+    shipped_lsn: u64,
+}
+
+/// Primary side: buffers writes and seals them into segments
+pub struct WalShipper {
+    backend: Arc<dyn StorageBackend>,
+    node_id: String,
+    epoch: u64,
+    max_segment_bytes: usize,
+    state: Mutex<ShipperState>,
+}
+
+impl WalShipper {
+    /// Start a new epoch on the timeline and resume numbering after the head
+    ///
+    /// Any previous primary is fenced from this point: its later segments fall
+    /// outside its epoch's LSN range, and its next seal fails.
+    pub fn start(
+        backend: Arc<dyn StorageBackend>,
+        node_id: impl Into<String>,
+        max_segment_bytes: usize,
+    ) -> Result<Self> {
+        let node_id = node_id.into();
+        let mut head = read_wal_head(backend.as_ref())?.unwrap_or(WalHead {
+            timeline: Vec::new(),
+            last_lsn: 0,
+            updated_at: 0,
+        });
+        let epoch = head.epoch() + 1;
+        let next_lsn = head.last_lsn + 1;
+        head.timeline.push(TimelineEntry {
+            epoch,
+            start_lsn: next_lsn,
+            node_id: node_id.clone(),
+            started_at: now_secs(),
+        });
+        head.updated_at = now_secs();
+        write_wal_head(backend.as_ref(), &head)?;
+
+        Ok(Self {
+            backend,
+            node_id,
+            epoch,
+            max_segment_bytes: max_segment_bytes.max(1),
+            state: Mutex::new(ShipperState {
+                next_lsn,
+                buffer: Vec::new(),
+                buffered_bytes: 0,
+                shipped_lsn: head.last_lsn,
+            }),
+        })
+    }
+
+    pub fn node_id(&self) -> &str {
+        &self.node_id
+    }
+
+    pub fn epoch(&self) -> u64 {
+        self.epoch
+    }
+
+    /// Assign LSNs to `entries` and buffer them; returns the last LSN assigned
+    pub fn append(&self, entries: impl IntoIterator<Item = WalEntry>) -> u64 {
+        let mut state = self.state.lock();
+        for entry in entries {
+            let lsn = state.next_lsn;
+            state.next_lsn += 1;
+            state.buffered_bytes += 13 + entry.encoded_len();
+            state.buffer.push(WalRecord { lsn, entry });
+        }
+        state.next_lsn - 1
+    }
+
+    /// Last LSN assigned to a write
+    pub fn last_lsn(&self) -> u64 {
+        self.state.lock().next_lsn - 1
+    }
+
+    /// Last LSN sealed into a segment
+    pub fn shipped_lsn(&self) -> u64 {
+        self.state.lock().shipped_lsn
+    }
+
+    pub fn pending_records(&self) -> usize {
+        self.state.lock().buffer.len()
+    }
+
+    /// Whether the buffer has grown past the segment size limit
+    pub fn should_seal(&self) -> bool {
+        self.state.lock().buffered_bytes >= self.max_segment_bytes
+    }
+
+    /// Seal buffered records into a segment and advance the head
+    ///
+    /// Fails (and keeps the records buffered) if another node has started a
+    /// newer epoch.
+    pub fn seal(&self) -> Result<Option<ShippedSegment>> {
+        let records = {
+            let mut state = self.state.lock();
+            if state.buffer.is_empty() {
+                return Ok(None);
+            }
+            state.buffered_bytes = 0;
+            std::mem::take(&mut state.buffer)
+        };
+
+        match self.write_segment(&records) {
+            Ok(shipped) => {
+                self.state.lock().shipped_lsn = shipped.last_lsn;
+                Ok(Some(shipped))
+            }
+            Err(e) => {
+                // Put the records back in front of anything appended meanwhile
+                let mut state = self.state.lock();
+                let newer = std::mem::replace(&mut state.buffer, records);
+                state.buffer.extend(newer);
+                state.buffered_bytes = state
+                    .buffer
+                    .iter()
+                    .map(|r| 13 + r.entry.encoded_len())
+                    .sum();
+                Err(e)
+            }
+        }
+    }
+
+    fn write_segment(&self, records: &[WalRecord]) -> Result<ShippedSegment> {
+        self.verify_epoch()?;
+
+        let segment = WalSegment {
+            epoch: self.epoch,
+            created_at: now_secs(),
+            records: records.to_vec(),
+        };
+        let bytes = segment.encode();
+        let key = segment_key(segment.first_lsn(), self.epoch);
+        self.backend.put(&key, &bytes)?;
+        self.backend.sync()?;
+
+        // Fencing: only advance the head if nobody was promoted meanwhile
+        let mut head = self.verify_epoch()?;
+        head.last_lsn = segment.last_lsn();
+        head.updated_at = now_secs();
+        write_wal_head(self.backend.as_ref(), &head)?;
+
+        Ok(ShippedSegment {
+            key,
+            first_lsn: segment.first_lsn(),
+            last_lsn: segment.last_lsn(),
+            records: records.len(),
+            bytes: bytes.len(),
+        })
+    }
+
+    fn verify_epoch(&self) -> Result<WalHead> {
+        let head = read_wal_head(self.backend.as_ref())?
+            .ok_or_else(|| AgentreplayError::Internal("WAL head vanished".to_string()))?;
+        if head.epoch() != self.epoch {
+            return Err(AgentreplayError::InvalidArgument(format!(
+                "WAL shipping fenced: epoch {} superseded by epoch {}",
+                self.epoch,
+                head.epoch()
+            )));
+        }
+        Ok(head)
+    }
+
+    /// Delete all but the `keep` most recent segments
+    pub fn prune(&self, keep: usize) -> Result<usize> {
+        let segments = list_segments(self.backend.as_ref())?;
+        let excess = segments.len().saturating_sub(keep);
+        for (_, _, key) in segments.iter().take(excess) {
+            self.backend.delete(key)?;
+        }
+        Ok(excess)
+    }
+}
+
+/// Standby side: reads segments past the applied LSN
+pub struct WalReceiver {
+    backend: Arc<dyn StorageBackend>,
+}
+
+impl WalReceiver {
+    pub fn new(backend: Arc<dyn StorageBackend>) -> Self {
+        Self { backend }
+    }
+
+    pub fn head(&self) -> Result<Option<WalHead>> {
+        read_wal_head(self.backend.as_ref())
+    }
+
+    /// Up to `max_segments` valid segments with records after `applied_lsn`
+    ///
+    /// Records at or below `applied_lsn` are dropped, segments from fenced
+    /// epochs are skipped, and a missing range (e.g. pruned before the
+    /// standby caught up) is reported as an error rather than silently
+    /// skipped.
+    pub fn pending(&self, applied_lsn: u64, max_segments: usize) -> Result<Vec<WalSegment>> {
+        let Some(head) = self.head()? else {
+            return Ok(Vec::new());
+        };
+        if head.last_lsn <= applied_lsn {
+            return Ok(Vec::new());
+        }
+
+        let mut expected = applied_lsn + 1;
+        let mut out = Vec::new();
+        for (first_lsn, epoch, key) in list_segments(self.backend.as_ref())? {
+            if out.len() >= max_segments || first_lsn > head.last_lsn {
+                break;
+            }
+            let mut segment = WalSegment::decode(&self.backend.get(&key)?)?;
+            if segment.records.is_empty()
+                || segment.last_lsn() < expected
+                || !head.owns(epoch, first_lsn, segment.last_lsn())
+            {
+                continue;
+            }
+            if first_lsn > expected {
+                return Err(AgentreplayError::NotFound(format!(
+                    "WAL records {}..{} are no longer available; re-seed the standby",
+                    expected, first_lsn
+                )));
+            }
+            segment.records.retain(|r| r.lsn >= expected);
+            expected = segment.last_lsn() + 1;
+            out.push(segment);
+        }
+        Ok(out)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::backend::LocalFsBackend;
+    use tempfile::TempDir;
+
+    fn shared() -> (TempDir, Arc<dyn StorageBackend>) {
+        let dir = TempDir::new().unwrap();
+        let backend = Arc::new(LocalFsBackend::new(dir.path()).unwrap());
+        (dir, backend)
+    }
+
+    fn payload(edge_id: u128) -> WalEntry {
+        WalEntry::Payload {
+            edge_id,
+            data: format!("{{\"id\":{}}}", edge_id).into_bytes(),
+        }
+    }
+
+    #[test]
+    fn test_segment_roundtrip_and_checksum() {
+        let segment = WalSegment {
+            epoch: 3,
+            created_at: 42,
+            records: vec![
+                WalRecord {
+                    lsn: 7,
+                    entry: payload(1),
+                },
+                WalRecord {
+                    lsn: 8,
+                    entry: WalEntry::Vector {
+                        edge_id: 1,
+                        values: vec![0.5, -1.0],
+                    },
+                },
+                WalRecord {
+                    lsn: 9,
+                    entry: WalEntry::Delete {
+                        edge_id: 1,
+                        tenant_id: 2,
+                    },
+                },
+                WalRecord {
+                    lsn: 10,
+                    entry: WalEntry::DeleteProject { project_id: 4 },
+                },
+                WalRecord {
+                    lsn: 11,
+                    entry: WalEntry::Cost {
+                        edge_id: 1,
+                        cost_micros: 1_250,
+                    },
+                },
+            ],
+        };
+        let mut bytes = segment.encode();
+        assert_eq!(WalSegment::decode(&bytes).unwrap(), segment);
+
+        bytes[SEGMENT_HEADER_LEN + 3] ^= 0xff;
+        assert!(matches!(
+            WalSegment::decode(&bytes),
+            Err(AgentreplayError::Corruption(_))
+        ));
+    }
+
+    #[test]
+    fn test_ship_and_receive() {
+        let (_dir, backend) = shared();
+        let shipper = WalShipper::start(backend.clone(), "primary", 1 << 20).unwrap();
+        let receiver = WalReceiver::new(backend.clone());
+
+        shipper.append([payload(1), payload(2)]);
+        shipper.seal().unwrap().unwrap();
+        assert_eq!(shipper.append([payload(3)]), 3);
+        shipper.seal().unwrap();
+        assert!(shipper.seal().unwrap().is_none());
+
+        let pending = receiver.pending(0, 10).unwrap();
+        assert_eq!(pending.len(), 2);
+        assert_eq!(pending[1].first_lsn(), 3);
+
+        // Partially applied segments are trimmed
+        let pending = receiver.pending(1, 10).unwrap();
+        assert_eq!(pending[0].records.len(), 1);
+        assert_eq!(pending[0].first_lsn(), 2);
+        assert!(receiver.pending(3, 10).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_promotion_fences_old_primary() {
+        let (_dir, backend) = shared();
+        let old = WalShipper::start(backend.clone(), "primary", 1 << 20).unwrap();
+        old.append([payload(1)]);
+        old.seal().unwrap();
+        old.append([payload(2)]);
+
+        // Standby promotes after applying LSN 1
+        let new = WalShipper::start(backend.clone(), "standby", 1 << 20).unwrap();
+        assert_eq!(new.epoch(), 2);
+        assert!(old.seal().is_err());
+        assert_eq!(old.pending_records(), 1);
+
+        new.append([payload(3)]);
+        new.seal().unwrap();
+        let pending = WalReceiver::new(backend).pending(1, 10).unwrap();
+        assert_eq!(pending.len(), 1);
+        assert_eq!(pending[0].epoch, 2);
+        assert_eq!(pending[0].records[0].entry, payload(3));
+    }
+
+    #[test]
+    fn test_pruned_gap_is_reported() {
+        let (_dir, backend) = shared();
+        let shipper = WalShipper::start(backend.clone(), "primary", 1 << 20).unwrap();
+        for id in 1..=3 {
+            shipper.append([payload(id)]);
+            shipper.seal().unwrap();
+        }
+        assert_eq!(shipper.prune(1).unwrap(), 2);
+
+        let receiver = WalReceiver::new(backend);
+        assert!(receiver.pending(0, 10).is_err());
+        assert_eq!(receiver.pending(2, 10).unwrap().len(), 1);
+    }
+}
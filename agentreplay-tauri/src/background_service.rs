@@ -0,0 +1,86 @@
+// Copyright 2025 AgentReplay (https://github.com/agentreplay)
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program. If not, see <https://www.gnu.org/licenses/>.
+
+//! Background service setting
+//!
+//! Registers `agentreplay-server` as a per-user service serving this app's
+//! database (same as `agentreplay service install`), so traces are captured
+//! while the desktop app is closed. While the service owns the database the
+//! app opens it read-only.
+
+use agentreplay_core::service::{self, ServiceSpec, ServiceStatus};
+use tauri::State;
+
+use crate::AppState;
+
+/// Whether the background service is installed and running
+#[tauri::command]
+pub async fn get_background_service_status() -> Result<ServiceStatus, String> {
+    service::status().map_err(|e| e.to_string())
+}
+
+/// Register the background service for this app's database
+///
+/// While this app holds the database the server couldn't open it, so the
+/// service is only started right away if the app is in read-only mode;
+/// otherwise it starts at next login.
+#[tauri::command]
+pub async fn install_background_service(
+    state: State<'_, AppState>,
+) -> Result<ServiceStatus, String> {
+    let server_bin = service::find_server_binary().ok_or_else(|| {
+        format!(
+            "{} not found next to the app or on PATH",
+            service::SERVER_BINARY
+        )
+    })?;
+    let http_addr = {
+        let config = state.config.read();
+        format!(
+            "{}:{}",
+            config.ingestion_server.host, config.ingestion_server.port
+        )
+    };
+    let spec = ServiceSpec {
+        server_bin,
+        data_dir: state.db_path.clone(),
+        http_addr,
+        config: None,
+    };
+    let start = !state.data_lock.accepts_writes();
+
+    let status = tauri::async_runtime::spawn_blocking(move || service::install(&spec, start))
+        .await
+        .map_err(|e| e.to_string())?
+        .map_err(|e| e.to_string())?;
+    tracing::info!(
+        "Background service registered with {} (running: {})",
+        status.manager.name(),
+        status.running
+    );
+    Ok(status)
+}
+
+/// Stop and remove the background service
+#[tauri::command]
+pub async fn uninstall_background_service() -> Result<ServiceStatus, String> {
+    tauri::async_runtime::spawn_blocking(|| {
+        service::uninstall()?;
+        service::status()
+    })
+    .await
+    .map_err(|e| e.to_string())?
+    .map_err(|e| e.to_string())
+}
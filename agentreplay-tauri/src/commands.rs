@@ -1062,6 +1062,14 @@ pub struct BackupInfo {
 pub async fn create_backup(
     state: State<'_, AppState>,
     app_handle: tauri::AppHandle,
+) -> Result<BackupInfo, String> {
+    backup_database(&app_handle, &state.db_path)
+}
+
+/// Copy the database directory into `<app data>/backups`
+pub fn backup_database(
+    app_handle: &tauri::AppHandle,
+    db_path: &std::path::Path,
 ) -> Result<BackupInfo, String> {
     let backup_dir = app_handle
         .path()
@@ -1076,7 +1084,7 @@ pub async fn create_backup(
     let backup_path = backup_dir.join(&backup_id);
 
     // Copy database directory to backup location
-    copy_dir_recursive(&state.db_path, &backup_path)
+    copy_dir_recursive(db_path, &backup_path)
         .map_err(|e| format!("Failed to copy database: {}", e))?;
 
     // Calculate backup size
@@ -1960,6 +1968,34 @@ pub struct EvaluationResult {
     pub error: Option<String>,
 }
 
+/// Store an evaluator's span highlights, one record per highlighted span
+fn store_highlights(
+    db: &agentreplay_query::Agentreplay,
+    evaluator_id: &str,
+    highlights: &[agentreplay_core::SpanHighlight],
+) {
+    let created_at = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|d| d.as_micros() as u64)
+        .unwrap_or(0);
+    let mut by_span: std::collections::BTreeMap<u128, Vec<agentreplay_core::SpanHighlight>> =
+        std::collections::BTreeMap::new();
+    for highlight in highlights {
+        by_span.entry(highlight.span_id).or_default().push(highlight.clone());
+    }
+    for (span_id, highlights) in by_span {
+        let record = agentreplay_storage::SpanHighlightRecord {
+            span_id,
+            evaluator_id: evaluator_id.to_string(),
+            highlights,
+            created_at,
+        };
+        if let Err(e) = db.put_span_highlights(&record) {
+            tracing::warn!("Failed to persist span highlights: {}", e);
+        }
+    }
+}
+
 #[tauri::command]
 pub async fn run_evaluation(
     params: RunEvaluationParams,
@@ -1984,6 +2020,61 @@ pub async fn run_evaluation(
     // Extract input/output from payloads
     let (input, output, context) = extract_trace_io(&state.db, &edges);
 
+    // Token logprobs of the latest span that captured them (the final answer)
+    let mut metadata = std::collections::HashMap::new();
+    let mut by_recency: Vec<_> = edges.iter().collect();
+    by_recency.sort_by_key(|edge| std::cmp::Reverse(edge.timestamp_us));
+    if let Some(logprobs) = by_recency
+        .iter()
+        .find_map(|edge| state.db.get_edge_logprobs(edge.edge_id).ok().flatten())
+    {
+        if let Ok(tokens) = serde_json::to_value(&logprobs.tokens) {
+            metadata.insert(
+                agentreplay_evals::evaluators::confidence::LOGPROBS_METADATA_KEY.to_string(),
+                tokens,
+            );
+        }
+    }
+
+    // Output schema or function signatures the latest span was asked to follow
+    {
+        use agentreplay_evals::evaluators::structured_output::{
+            FUNCTION_SIGNATURE_METADATA_KEY, OUTPUT_SCHEMA_METADATA_KEY,
+        };
+        let sources = [
+            (
+                OUTPUT_SCHEMA_METADATA_KEY,
+                [
+                    "agentreplay.output_schema",
+                    "gen_ai.request.response_format",
+                    "response_format",
+                ],
+            ),
+            (
+                FUNCTION_SIGNATURE_METADATA_KEY,
+                [
+                    "agentreplay.function_signature",
+                    "gen_ai.request.tools",
+                    "tools",
+                ],
+            ),
+        ];
+        for (key, attributes) in sources {
+            let found = by_recency.iter().find_map(|edge| {
+                let bytes = state.db.get_payload(edge.edge_id).ok().flatten()?;
+                let payload: serde_json::Value = serde_json::from_slice(&bytes).ok()?;
+                attributes
+                    .iter()
+                    .filter_map(|attribute| payload.get(*attribute))
+                    .find(|value| value.is_object() || value.is_array() || value.is_string())
+                    .cloned()
+            });
+            if let Some(value) = found {
+                metadata.insert(key.to_string(), value);
+            }
+        }
+    }
+
     // Build TraceContext
     let trace_context = agentreplay_evals::TraceContext {
         trace_id: edge_id,
@@ -1991,7 +2082,7 @@ pub async fn run_evaluation(
         input,
         output,
         context,
-        metadata: std::collections::HashMap::new(),
+        metadata,
         eval_trace: None,
         timestamp_us: root_edge.timestamp_us,
     };
@@ -2008,6 +2099,7 @@ pub async fn run_evaluation(
             .map_err(|e| format!("Evaluation failed: {}", e))?;
         
         for (id, result) in eval_results {
+            store_highlights(&state.db, &id, &result.highlights);
             results.push(EvaluationResult {
                 evaluator_id: id,
                 passed: result.passed,
@@ -2047,6 +2139,7 @@ pub async fn run_evaluation(
                     // Run evaluation
                     match geval.evaluate(&trace_context).await {
                         Ok(eval_result) => {
+                            store_highlights(&state.db, &format!("{}_geval", eval_type), &eval_result.highlights);
                             results.push(EvaluationResult {
                                 evaluator_id: format!("{}_geval", eval_type),
                                 passed: eval_result.passed,
@@ -2110,6 +2203,7 @@ pub async fn run_evaluation(
             .map_err(|e| format!("Evaluation failed: {}", e))?;
         
         for (id, result) in eval_results {
+            store_highlights(&state.db, &id, &result.highlights);
             results.push(EvaluationResult {
                 evaluator_id: id,
                 passed: result.passed,
@@ -2722,6 +2816,40 @@ pub async fn check_for_updates(app_handle: tauri::AppHandle) -> Result<UpdateInf
     })
 }
 
+// ============================================================================
+// Diagnostics Commands
+// ============================================================================
+
+/// Write a diagnostic snapshot (log tail + config summary) and return its path
+#[tauri::command]
+pub async fn collect_diagnostics(
+    diagnostics: State<'_, Arc<agentreplay_core::DiagnosticsCollector>>,
+) -> Result<String, String> {
+    let bundle = diagnostics.collect(None);
+    let path = diagnostics
+        .write_bundle(&bundle)
+        .map_err(|e| format!("Failed to write diagnostic bundle: {}", e))?;
+    Ok(path.display().to_string())
+}
+
+/// Upload a previously written diagnostic bundle (opt-in, user supplied URL)
+///
+/// `path` must name a bundle in the diagnostics directory; other files are
+/// refused so the webview cannot send arbitrary files off the machine.
+#[tauri::command]
+pub async fn upload_diagnostics(
+    diagnostics: State<'_, Arc<agentreplay_core::DiagnosticsCollector>>,
+    path: String,
+    url: String,
+) -> Result<(), String> {
+    let bundle = diagnostics
+        .bundle_path(&path)
+        .map_err(|e| format!("Invalid diagnostic bundle: {}", e))?;
+    agentreplay_core::diagnostics::upload_bundle(&bundle, &url)
+        .await
+        .map_err(|e| e.to_string())
+}
+
 // ============================================================================
 // Model Comparison Commands
 // ============================================================================
@@ -3309,6 +3437,8 @@ pub async fn get_insights_summary(
             InsightType::FailurePattern { .. } => "failure_pattern",
             InsightType::PerformanceRegression { .. } => "performance_regression",
             InsightType::TrafficAnomaly { .. } => "traffic_anomaly",
+            InsightType::ToolContractViolation { .. } => "tool_contract_violation",
+            InsightType::DatasetDrift { .. } => "dataset_drift",
         };
         *by_type.entry(type_name.to_string()).or_insert(0) += 1;
     }
@@ -3377,6 +3507,7 @@ mod tests {
             ui: crate::UiConfig {
                 theme: "dark".to_string(),
                 default_time_range_hours: 24,
+                close_to_tray: false,
             },
             server_export: crate::ServerExportConfig {
                 enabled: false,
@@ -3391,6 +3522,7 @@ mod tests {
                 max_connections: 1000,
             },
             retention: crate::RetentionServerConfig::default(),
+            notifications: crate::notifications::NotificationConfig::default(),
         };
 
         let (tx, _rx) = tokio::sync::mpsc::channel(100);
@@ -3821,6 +3953,14 @@ pub async fn get_write_amplification_stats(state: State<'_, AppState>) -> Result
     })
 }
 
+/// Who holds the database directory lock, and whether this app runs read-only
+#[tauri::command]
+pub async fn get_data_dir_lock(
+    state: State<'_, AppState>,
+) -> Result<agentreplay_server::instance_lock::InstanceLockStatus, CommandError> {
+    Ok(state.data_lock.status())
+}
+
 /// Comprehensive storage health dashboard (combines Gap #1, #2, #3, #10)
 #[derive(Serialize)]
 pub struct StorageHealthDashboard {
@@ -0,0 +1,249 @@
+// Copyright 2025 AgentReplay (https://github.com/agentreplay)
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program. If not, see <https://www.gnu.org/licenses/>.
+
+//! Editor hook installer
+//!
+//! Detects coding agents (Claude Code, Cursor, VS Code) and installs the
+//! tracing hooks from the bundled `agentreplay-hooks` plugin. Installs are
+//! tracked with a receipt next to the plugin receipts, so they show up in
+//! `plugin_bundle_verify` and can be updated or uninstalled cleanly.
+
+use std::collections::HashMap;
+use std::path::PathBuf;
+
+use agentreplay_plugins::manifest::InstallScope;
+use agentreplay_plugins::{
+    bundle, BundleUninstallResult, InstallExecutionResult, InstallReceipt, PluginManifest,
+    VariableContext, VerifyResult,
+};
+use serde::{Deserialize, Serialize};
+use tauri::{AppHandle, Manager, State};
+
+use crate::AppState;
+
+/// Directory of the hooks bundle, relative to the app resources
+const HOOKS_BUNDLE_DIR: &str = "agentreplay-hooks";
+
+/// Detection and install state of one editor
+#[derive(Debug, Clone, Serialize)]
+pub struct EditorHookStatus {
+    pub target_id: String,
+    pub display_name: String,
+    /// Whether the editor looks installed on this machine
+    pub detected: bool,
+    /// Whether hooks are installed (a receipt exists)
+    pub installed: bool,
+    pub scopes: Vec<InstallScope>,
+    pub installed_scope: Option<InstallScope>,
+    /// RFC 3339 install time
+    pub installed_at: Option<String>,
+    /// Verification of the installed hooks, if any
+    pub verification: Option<VerifyResult>,
+}
+
+/// Request to install hooks for an editor
+#[derive(Debug, Clone, Deserialize)]
+pub struct EditorHooksInstallRequest {
+    pub target_id: String,
+    /// "user" (default), "project" or "local"
+    #[serde(default)]
+    pub scope: Option<String>,
+    /// Required for project and local scope
+    #[serde(default)]
+    pub project_dir: Option<String>,
+}
+
+/// Outcome of an install, verified right after it ran
+#[derive(Debug, Clone, Serialize)]
+pub struct EditorHooksInstallResult {
+    pub execution: InstallExecutionResult,
+    pub verification: VerifyResult,
+}
+
+/// Hooks bundle shipped with the app, falling back to the source tree in dev builds
+fn bundle_dir(app: &AppHandle) -> PathBuf {
+    app.path()
+        .resource_dir()
+        .map(|dir| dir.join(HOOKS_BUNDLE_DIR))
+        .ok()
+        .filter(|dir| dir.is_dir())
+        .unwrap_or_else(|| {
+            PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+                .join("../agentreplay-plugins")
+                .join(HOOKS_BUNDLE_DIR)
+        })
+}
+
+fn load_bundle(app: &AppHandle) -> Result<(PluginManifest, PathBuf), String> {
+    let dir = bundle_dir(app);
+    let manifest = PluginManifest::from_directory(&dir)
+        .map_err(|e| format!("Failed to load hooks bundle from {}: {}", dir.display(), e))?;
+    Ok((manifest, dir))
+}
+
+fn load_receipt(
+    state: &AppState,
+    plugin_id: &str,
+    target_id: &str,
+) -> Result<Option<InstallReceipt>, String> {
+    let path = InstallReceipt::receipt_path(&state.db_path, plugin_id, target_id);
+    if !path.exists() {
+        return Ok(None);
+    }
+    InstallReceipt::load(&path)
+        .map(Some)
+        .map_err(|e| e.to_string())
+}
+
+/// Detect supported editors and whether their hooks are installed
+#[tauri::command]
+pub async fn detect_editor_hooks(
+    app: AppHandle,
+    state: State<'_, AppState>,
+) -> Result<Vec<EditorHookStatus>, String> {
+    let (manifest, _) = load_bundle(&app)?;
+    let hooks = manifest
+        .bundle
+        .as_ref()
+        .ok_or_else(|| "Hooks plugin has no bundle configuration".to_string())?;
+
+    let context = VariableContext::new();
+    let mut statuses = Vec::with_capacity(hooks.targets.len());
+    for target in &hooks.targets {
+        let detected = bundle::detect_target(target, &context)
+            .map(|d| d.detected)
+            .unwrap_or(false);
+        let receipt = load_receipt(&state, &manifest.plugin.id, &target.id)?;
+        statuses.push(EditorHookStatus {
+            target_id: target.id.clone(),
+            display_name: target.display_name.clone(),
+            detected,
+            installed: receipt.is_some(),
+            scopes: target.scopes.clone(),
+            installed_scope: receipt.as_ref().map(|r| r.scope),
+            installed_at: receipt.as_ref().map(|r| r.installed_at.to_rfc3339()),
+            verification: receipt.as_ref().map(bundle::verify_installation),
+        });
+    }
+    Ok(statuses)
+}
+
+/// Install (or update) the hooks for an editor
+#[tauri::command]
+pub async fn install_editor_hooks(
+    request: EditorHooksInstallRequest,
+    app: AppHandle,
+    state: State<'_, AppState>,
+) -> Result<EditorHooksInstallResult, String> {
+    let (manifest, dir) = load_bundle(&app)?;
+    let scope = match request.scope.as_deref() {
+        Some(scope) => crate::plugins::parse_install_scope(scope)?,
+        None => InstallScope::User,
+    };
+
+    let mut variables = HashMap::new();
+    match request.project_dir {
+        Some(project_dir) => {
+            variables.insert("project_dir".to_string(), project_dir);
+        }
+        None if scope != InstallScope::User => {
+            return Err(format!(
+                "A project directory is required for {:?} scope",
+                scope
+            ));
+        }
+        None => {}
+    }
+
+    let plan = bundle::create_install_plan(&manifest, &request.target_id, scope, &dir, variables)
+        .map_err(|e| e.to_string())?;
+    let (execution, receipt) =
+        bundle::install_bundle_target(&plan, &manifest.plugin.id, &state.db_path)
+            .await
+            .map_err(|e| e.to_string())?;
+
+    let verification = bundle::verify_installation(&receipt);
+    tracing::info!(
+        "Installed {} hooks ({:?} scope, valid: {})",
+        request.target_id,
+        scope,
+        verification.valid
+    );
+    Ok(EditorHooksInstallResult {
+        execution,
+        verification,
+    })
+}
+
+/// Check that installed hooks are still in place
+#[tauri::command]
+pub async fn verify_editor_hooks(
+    target_id: String,
+    app: AppHandle,
+    state: State<'_, AppState>,
+) -> Result<VerifyResult, String> {
+    let (manifest, _) = load_bundle(&app)?;
+    let receipt = load_receipt(&state, &manifest.plugin.id, &target_id)?
+        .ok_or_else(|| format!("Hooks are not installed for '{}'", target_id))?;
+    Ok(bundle::verify_installation(&receipt))
+}
+
+/// Remove installed hooks, restoring any files they replaced
+#[tauri::command]
+pub async fn uninstall_editor_hooks(
+    target_id: String,
+    app: AppHandle,
+    state: State<'_, AppState>,
+) -> Result<BundleUninstallResult, String> {
+    let (manifest, _) = load_bundle(&app)?;
+    let result = bundle::uninstall_bundle_target(&state.db_path, &manifest.plugin.id, &target_id)
+        .map_err(|e| e.to_string())?;
+    tracing::info!(
+        "Uninstalled {} hooks (success: {})",
+        target_id,
+        result.success
+    );
+    Ok(result)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_hooks_bundle_manifest() {
+        let dir = PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+            .join("../agentreplay-plugins")
+            .join(HOOKS_BUNDLE_DIR);
+        let manifest = PluginManifest::from_directory(&dir).unwrap();
+        manifest.validate().unwrap();
+
+        for target in ["claude_code", "cursor", "vscode"] {
+            let plan = bundle::create_install_plan(
+                &manifest,
+                target,
+                InstallScope::User,
+                &dir,
+                HashMap::new(),
+            )
+            .unwrap();
+            assert!(
+                !plan.install_ops.is_empty(),
+                "{} has no install ops",
+                target
+            );
+        }
+    }
+}
@@ -38,24 +38,30 @@ use tracing_subscriber::{layer::SubscriberExt, util::SubscriberInitExt};
 use tauri_nspanel::tauri_panel;
 
 // Import Agentreplay crates
-use agentreplay_core::{AgentFlowEdge, SavedViewRegistry};
+use agentreplay_core::{AgentFlowEdge, DiagnosticsCollector, LogSource, SavedViewRegistry};
 use agentreplay_query::Agentreplay;
 
 mod windows;
+mod background_service;
 mod commands;
+mod editor_hooks;
 mod error;
 mod health;
 mod menu;
 mod otlp_server;
 mod project_store;
 mod server;
+mod size_retention;
 mod memory;
+mod notifications;
 mod sse;
 mod llm;
 mod llm_service;
 mod comparison_engine;
 mod plugins;
 mod sysinfo_state;
+#[cfg(desktop)]
+mod tray;
 
 /// Load LLM config from persistent storage
 fn load_persisted_llm_config() -> Option<llm::LLMConfig> {
@@ -137,6 +143,10 @@ pub struct IngestionQueue {
     worker_done_rx: Arc<tokio::sync::RwLock<Option<tokio::sync::oneshot::Receiver<()>>>>,
     /// Throttle counter for queue-full log messages (avoid log storm)
     drop_count: std::sync::atomic::AtomicU64,
+    /// Backpressure policy applied to whole batches before they are queued
+    admission: Arc<agentreplay_server::admission::QueueAdmission>,
+    /// Set from the tray; new traces are rejected with 503 while paused
+    paused: Arc<std::sync::atomic::AtomicBool>,
 }
 
 impl IngestionQueue {
@@ -153,6 +163,9 @@ impl IngestionQueue {
     }
 
     fn send_item(&self, item: IngestionItem) -> Result<(), String> {
+        if self.is_paused() {
+            return Err("Ingestion paused".to_string());
+        }
         self.tx.try_send(item).map_err(|e| match e {
             tokio::sync::mpsc::error::TrySendError::Full(_) => {
                 // Throttle: only log every 1000th drop to avoid log storm
@@ -172,6 +185,53 @@ impl IngestionQueue {
         })
     }
 
+    /// Number of items waiting to be written
+    pub fn depth(&self) -> usize {
+        self.tx.max_capacity() - self.tx.capacity()
+    }
+
+    /// Maximum number of queued items
+    pub fn capacity(&self) -> usize {
+        self.tx.max_capacity()
+    }
+
+    /// Check whether a batch of `count` items may be queued
+    ///
+    /// Above the high watermark the batch is rejected (429 + Retry-After) or
+    /// sampled, rather than failing item by item once the queue is full.
+    /// `drain_rate` is the recent write rate in items/sec (0 if unknown).
+    pub fn admit(
+        &self,
+        count: usize,
+        drain_rate: f64,
+    ) -> Result<
+        agentreplay_server::admission::QueueDecision,
+        agentreplay_server::admission::RejectionReason,
+    > {
+        if self.is_paused() {
+            return Err(agentreplay_server::admission::RejectionReason::Paused {
+                retry_after_ms: 5000,
+            });
+        }
+        self.admission
+            .admit(count, self.depth(), self.capacity(), drain_rate)
+    }
+
+    /// Whether ingestion is paused
+    pub fn is_paused(&self) -> bool {
+        self.paused.load(std::sync::atomic::Ordering::Relaxed)
+    }
+
+    /// Pause or resume ingestion; items already queued are still written
+    pub fn set_paused(&self, paused: bool) {
+        self.paused.store(paused, std::sync::atomic::Ordering::Relaxed);
+    }
+
+    /// Queue depth and admission counters
+    pub fn metrics(&self) -> agentreplay_server::admission::QueueMetrics {
+        self.admission.metrics(self.depth(), self.capacity())
+    }
+
     pub fn shutdown(&self) {
         self.shutdown_tx.notify_one();
     }
@@ -213,6 +273,8 @@ impl Clone for IngestionQueue {
             shutdown_tx: Arc::clone(&self.shutdown_tx),
             worker_done_rx: Arc::clone(&self.worker_done_rx),
             drop_count: std::sync::atomic::AtomicU64::new(0),
+            admission: Arc::clone(&self.admission),
+            paused: Arc::clone(&self.paused),
         }
     }
 }
@@ -237,6 +299,10 @@ pub struct AppState {
     pub online_evaluator: Option<Arc<agentreplay_evals::OnlineEvaluator>>,
     /// Shutdown token for graceful server shutdown coordination
     pub shutdown_token: tokio_util::sync::CancellationToken,
+    /// Lock on the database directory (read-only when another process holds it)
+    pub data_lock: Arc<agentreplay_server::instance_lock::InstanceLock>,
+    /// Routes eval failure and budget alerts to desktop/in-app notifications
+    pub notifier: Arc<notifications::Notifier>,
 }
 
 /// Desktop application configuration
@@ -249,6 +315,9 @@ pub struct AppConfig {
     /// Retention/TTL configuration for automatic data cleanup
     #[serde(default)]
     pub retention: RetentionServerConfig,
+    /// Desktop/in-app notifications for eval failures and budget thresholds
+    #[serde(default)]
+    pub notifications: notifications::NotificationConfig,
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
@@ -262,6 +331,9 @@ pub struct DatabaseConfig {
 pub struct UiConfig {
     pub theme: String, // "light", "dark", "system"
     pub default_time_range_hours: u32,
+    /// Hide the window to the system tray on close instead of quitting
+    #[serde(default)]
+    pub close_to_tray: bool,
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
@@ -289,6 +361,9 @@ pub struct RetentionServerConfig {
     pub retention_days: Option<u32>,
     /// Cleanup interval in hours (default: 24 = once daily)
     pub cleanup_interval_hours: u32,
+    /// Maximum database size in MB; the oldest traces are evicted beyond it (None or 0 = unlimited)
+    #[serde(default)]
+    pub max_database_size_mb: Option<u64>,
 }
 
 impl Default for RetentionServerConfig {
@@ -297,6 +372,7 @@ impl Default for RetentionServerConfig {
             enabled: true, // Enable by default for 30-day retention
             retention_days: Some(30), // 30 days default (0 or None = unlimited)
             cleanup_interval_hours: 24, // Run daily
+            max_database_size_mb: None,
         }
     }
 }
@@ -312,6 +388,7 @@ impl Default for AppConfig {
             ui: UiConfig {
                 theme: "light".to_string(),
                 default_time_range_hours: 24,
+                close_to_tray: false,
             },
             server_export: ServerExportConfig {
                 enabled: false,
@@ -326,6 +403,7 @@ impl Default for AppConfig {
                 max_connections: 1000,
             },
             retention: RetentionServerConfig::default(),
+            notifications: notifications::NotificationConfig::default(),
         }
     }
 }
@@ -413,6 +491,18 @@ impl AppConfig {
         Ok(config)
     }
 
+    /// Configuration summary for diagnostic bundles, with secrets redacted
+    pub fn diagnostic_summary(&self) -> serde_json::Value {
+        let mut redacted = self.clone();
+        if redacted.server_export.api_key.is_some() {
+            redacted.server_export.api_key = Some("<redacted>".to_string());
+        }
+        if redacted.ingestion_server.auth_token.is_some() {
+            redacted.ingestion_server.auth_token = Some("<redacted>".to_string());
+        }
+        serde_json::to_value(redacted).unwrap_or(serde_json::Value::Null)
+    }
+
     pub fn save(&self, app_handle: &tauri::AppHandle) -> Result<()> {
         let config_path = Self::config_path(app_handle)?;
         let json = serde_json::to_string_pretty(self)?;
@@ -445,11 +535,68 @@ fn get_db_path(app_handle: &tauri::AppHandle) -> Result<PathBuf> {
     Ok(db_path)
 }
 
+/// Ask whether to keep a read-only window open while another process owns the database
+fn prompt_read_only(
+    app_handle: &tauri::AppHandle,
+    data_lock: &agentreplay_server::instance_lock::InstanceLock,
+) {
+    use tauri_plugin_dialog::{DialogExt, MessageDialogButtons, MessageDialogKind};
+
+    let holder = match data_lock.status().owner {
+        Some(owner) => format!(
+            "Agentreplay {} {} (pid {} on {})",
+            owner.app, owner.version, owner.pid, owner.hostname
+        ),
+        None => "another Agentreplay process".to_string(),
+    };
+    let app_for_exit = app_handle.clone();
+    app_handle
+        .dialog()
+        .message(format!(
+            "The database is in use by {}.\n\nYou can browse existing traces read-only; \
+             ingestion and cleanup stay with the other process. Quit it and restart \
+             this app to make changes.",
+            holder
+        ))
+        .title("Database in use")
+        .kind(MessageDialogKind::Warning)
+        .buttons(MessageDialogButtons::OkCancelCustom(
+            "Open read-only".to_string(),
+            "Quit".to_string(),
+        ))
+        .show(move |open_read_only| {
+            if !open_read_only {
+                app_for_exit.exit(0);
+            }
+        });
+}
+
 /// Initialize application state
 fn initialize_app_state(app_handle: &tauri::AppHandle) -> Result<AppState> {
     // Get database path
     let db_path = get_db_path(app_handle)?;
 
+    // Claim the database directory; a server already using it leaves us read-only
+    let data_lock = match agentreplay_storage::DataDirLock::acquire(
+        &db_path,
+        "desktop",
+        env!("CARGO_PKG_VERSION"),
+    )? {
+        agentreplay_storage::DirLockOutcome::Acquired(lock) => {
+            agentreplay_server::instance_lock::InstanceLock::held(&db_path, lock)
+        }
+        agentreplay_storage::DirLockOutcome::HeldBy(owner) => {
+            tracing::warn!(
+                "Database directory is in use by {} (pid {} on {}); opening read-only",
+                owner.app,
+                owner.pid,
+                owner.hostname
+            );
+            agentreplay_server::instance_lock::InstanceLock::read_only(&db_path)
+        }
+    };
+    let data_lock = Arc::new(data_lock);
+
     // Open Agentreplay database with high-performance WAL mode
     tracing::info!("Opening Agentreplay database at: {:?}", db_path);
     tracing::info!("Using high-performance WAL mode (Group Commit)");
@@ -485,7 +632,10 @@ fn initialize_app_state(app_handle: &tauri::AppHandle) -> Result<AppState> {
 
     // Register built-in local evaluators (no LLM required)
     {
-        use agentreplay_evals::evaluators::{LatencyBenchmark, CostAnalyzer, TrajectoryEfficiencyEvaluator};
+        use agentreplay_evals::evaluators::{
+            CostAnalyzer, LatencyBenchmark, LowConfidenceDetector, RagSuiteEvaluator,
+            StructuredOutputEvaluator, TrajectoryEfficiencyEvaluator,
+        };
         
         // Latency evaluator - analyzes timing and performance
         if let Err(e) = eval_registry.register(Arc::new(LatencyBenchmark::new())) {
@@ -501,6 +651,21 @@ fn initialize_app_state(app_handle: &tauri::AppHandle) -> Result<AppState> {
         if let Err(e) = eval_registry.register(Arc::new(TrajectoryEfficiencyEvaluator::new())) {
             tracing::warn!("Failed to register trajectory evaluator: {}", e);
         }
+
+        // Low-confidence detector - flags answers with low token logprobs
+        if let Err(e) = eval_registry.register(Arc::new(LowConfidenceDetector::new())) {
+            tracing::warn!("Failed to register low-confidence evaluator: {}", e);
+        }
+
+        // Structured output validator - checks JSON outputs against the trace's schema
+        if let Err(e) = eval_registry.register(Arc::new(StructuredOutputEvaluator::new())) {
+            tracing::warn!("Failed to register structured output evaluator: {}", e);
+        }
+
+        // RAG suite - attributes answer sentences and quotes to retrieved chunks
+        if let Err(e) = eval_registry.register(Arc::new(RagSuiteEvaluator::new())) {
+            tracing::warn!("Failed to register RAG suite evaluator: {}", e);
+        }
         
         let count = eval_registry.list_evaluators().len();
         tracing::info!("Evaluation registry initialized ({} local evaluators registered)", count);
@@ -517,6 +682,8 @@ fn initialize_app_state(app_handle: &tauri::AppHandle) -> Result<AppState> {
         shutdown_tx: Arc::clone(&shutdown_notify),
         worker_done_rx: Arc::new(tokio::sync::RwLock::new(Some(worker_done_rx))),
         drop_count: std::sync::atomic::AtomicU64::new(0),
+        admission: Arc::new(agentreplay_server::admission::QueueAdmission::default()),
+        paused: Arc::new(std::sync::atomic::AtomicBool::new(false)),
     });
 
     // Create broadcast channel for real-time trace streaming (SSE) - must come BEFORE worker spawn
@@ -704,6 +871,11 @@ fn initialize_app_state(app_handle: &tauri::AppHandle) -> Result<AppState> {
     );
     tracing::info!("Eval store initialized at {:?}", eval_store_path);
 
+    let notifier = Arc::new(notifications::Notifier::new(
+        app_handle.clone(),
+        Arc::clone(&config),
+    ));
+
     Ok(AppState {
         db,
         db_path,
@@ -722,6 +894,8 @@ fn initialize_app_state(app_handle: &tauri::AppHandle) -> Result<AppState> {
         // This avoids automatic evaluation overhead unless explicitly enabled
         online_evaluator: None,
         shutdown_token,
+        data_lock,
+        notifier,
     })
 }
 
@@ -804,6 +978,70 @@ async fn flush_batch(
     }
 }
 
+/// Stop the servers, flush the ingestion queue, close the database and exit
+///
+/// Used when the main window is closed and by the tray's Quit action.
+async fn shutdown_and_exit(state: AppState) {
+    tracing::info!("Starting async cleanup...");
+
+    // 1. Signal all servers to shutdown gracefully
+    state.shutdown_token.cancel();
+    tracing::info!("Server shutdown signal sent (HTTP + OTLP)");
+
+    // 2. Signal ingestion worker to shutdown
+    state.ingestion_queue.shutdown();
+    tracing::info!("Ingestion queue shutdown signal sent");
+
+    // 3. Wait for servers to finish in-flight requests (up to 2 seconds)
+    tokio::time::sleep(tokio::time::Duration::from_secs(2)).await;
+    tracing::info!("Server graceful shutdown period complete");
+
+    // 4. CRITICAL FIX: Actually wait for worker to complete, not just sleep
+    // This ensures all pending edges are flushed before we close the database
+    // Timeout of 5 seconds should be sufficient for even large batches
+    let worker_completed = state
+        .ingestion_queue
+        .wait_for_shutdown(tokio::time::Duration::from_secs(5))
+        .await;
+
+    if worker_completed {
+        tracing::info!("Ingestion worker shutdown completed, proceeding to database close");
+    } else {
+        tracing::warn!("Ingestion worker did not complete in time, proceeding anyway");
+    }
+
+    // 3. Close database (in a spawn_blocking to avoid blocking runtime)
+    let db_clone = Arc::clone(&state.db);
+    let close_result = tokio::task::spawn_blocking(move || db_clone.close()).await;
+
+    match close_result {
+        Ok(Ok(())) => {
+            tracing::info!("Database closed successfully");
+        }
+        Ok(Err(e)) => {
+            tracing::error!("Failed to close database: {}", e);
+        }
+        Err(e) => {
+            tracing::error!("Database close task panicked: {}", e);
+        }
+    }
+
+    tracing::info!("Cleanup complete, closing window");
+
+    // Force process exit after graceful cleanup
+    // This ensures all background tasks (servers, retention worker) terminate
+    tracing::info!("All cleanup complete, forcing process exit");
+
+    // Use libc::_exit for immediate termination without running atexit handlers
+    // This bypasses any blocking cleanup that might hang the process
+    #[cfg(unix)]
+    unsafe {
+        libc::_exit(0);
+    }
+    #[cfg(windows)]
+    std::process::exit(0);
+}
+
 fn main() {
     // Initialize tracing with file output for production troubleshooting
     let log_dir = std::env::var("HOME")
@@ -829,15 +1067,26 @@ fn main() {
     tracing::info!("Starting Agentreplay Desktop Application");
     tracing::info!("Logs are being written to: {:?}", log_file);
 
+    // Write crash bundles (panic + log tail + config summary) next to the log file
+    let diagnostics = Arc::new(DiagnosticsCollector::new(
+        "desktop",
+        env!("CARGO_PKG_VERSION"),
+        log_dir.join(agentreplay_core::diagnostics::DIAGNOSTICS_DIR_NAME),
+        LogSource::File(log_file.clone()),
+    ));
+    diagnostics.clone().install_panic_hook();
+
     let mut builder = tauri::Builder::default()
         .plugin(tauri_plugin_shell::init())
         .plugin(tauri_plugin_dialog::init())
         .plugin(tauri_plugin_fs::init())
         .plugin(tauri_plugin_notification::init())
+        .plugin(tauri_plugin_clipboard_manager::init())
         .plugin(tauri_plugin_os::init())
         .plugin(tauri_plugin_store::Builder::new().build())
         .plugin(tauri_plugin_window_state::Builder::default().build())
-        .plugin(tauri_plugin_updater::Builder::new().build());
+        .plugin(tauri_plugin_updater::Builder::new().build())
+        .manage(diagnostics);
 
     // Add nspanel plugin for macOS
     #[cfg(target_os = "macos")]
@@ -880,6 +1129,10 @@ fn main() {
 
             // Manage state in Tauri
             app.manage(state.clone());
+
+            // Include the (redacted) loaded config in crash bundles
+            app.state::<Arc<DiagnosticsCollector>>()
+                .set_config_summary(state.config.read().diagnostic_summary());
             
             // Initialize system info state for real-time metrics
             let sysinfo_state = sysinfo_state::SysInfoState::new();
@@ -899,9 +1152,18 @@ fn main() {
             });
             app.manage(plugin_state);
 
+            // Another process owns the database: offer read-only or quit, and
+            // leave ingestion and retention to the owner
+            let read_only = !state.data_lock.accepts_writes();
+            if read_only {
+                prompt_read_only(app.handle(), &state.data_lock);
+            }
+
             // Start embedded HTTP server if enabled
             let ingestion_enabled = state.config.read().ingestion_server.enabled;
-            if ingestion_enabled {
+            if read_only {
+                tracing::info!("Ingestion servers not started: database is read-only");
+            } else if ingestion_enabled {
                 let host = state.config.read().ingestion_server.host.clone();
                 let port = state.config.read().ingestion_server.port;
                 let server_state = state.clone();
@@ -945,37 +1207,39 @@ fn main() {
                 }
             });
 
-            // Start OTLP gRPC server on port 47117
-            let otlp_grpc_state = state.clone();
-            tracing::info!("Starting OTLP gRPC server on 127.0.0.1:47117");
-            tauri::async_runtime::spawn(async move {
-                match otlp_server::start_otlp_grpc_server(otlp_grpc_state).await {
-                    Ok(_) => {
-                        tracing::info!("OTLP gRPC server stopped");
-                    }
-                    Err(e) => {
-                        tracing::error!("OTLP gRPC server failed: {}", e);
+            if !read_only {
+                // Start OTLP gRPC server on port 47117
+                let otlp_grpc_state = state.clone();
+                tracing::info!("Starting OTLP gRPC server on 127.0.0.1:47117");
+                tauri::async_runtime::spawn(async move {
+                    match otlp_server::start_otlp_grpc_server(otlp_grpc_state).await {
+                        Ok(_) => {
+                            tracing::info!("OTLP gRPC server stopped");
+                        }
+                        Err(e) => {
+                            tracing::error!("OTLP gRPC server failed: {}", e);
+                        }
                     }
-                }
-            });
+                });
 
-            // Start OTLP HTTP server on port 4318
-            let otlp_http_state = state.clone();
-            tracing::info!("Starting OTLP HTTP server on 127.0.0.1:4318");
-            tauri::async_runtime::spawn(async move {
-                match otlp_server::start_otlp_http_server(otlp_http_state).await {
-                    Ok(_) => {
-                        tracing::info!("OTLP HTTP server stopped");
-                    }
-                    Err(e) => {
-                        tracing::error!("OTLP HTTP server failed: {}", e);
+                // Start OTLP HTTP server on port 4318
+                let otlp_http_state = state.clone();
+                tracing::info!("Starting OTLP HTTP server on 127.0.0.1:4318");
+                tauri::async_runtime::spawn(async move {
+                    match otlp_server::start_otlp_http_server(otlp_http_state).await {
+                        Ok(_) => {
+                            tracing::info!("OTLP HTTP server stopped");
+                        }
+                        Err(e) => {
+                            tracing::error!("OTLP HTTP server failed: {}", e);
+                        }
                     }
-                }
-            });
+                });
+            }
 
             // Start background retention worker for automatic TTL cleanup
             let retention_config = state.config.read().retention.clone();
-            if retention_config.enabled {
+            if retention_config.enabled && !read_only {
                 let retention_db = Arc::clone(&state.db);
                 let retention_days = retention_config.retention_days;
                 let interval_hours = retention_config.cleanup_interval_hours;
@@ -1039,33 +1303,12 @@ fn main() {
             } else {
                 tracing::info!("Retention worker disabled in configuration");
             }
+            if !read_only {
+                size_retention::spawn_worker(state.clone());
+            }
             // Setup tray icon if supported
             #[cfg(desktop)]
-            {
-                use tauri::tray::{MouseButton, MouseButtonState, TrayIconBuilder};
-
-                let _tray = TrayIconBuilder::new()
-                    .icon(app.default_window_icon().unwrap().clone())
-                    .on_tray_icon_event(|tray, event| {
-                        if let tauri::tray::TrayIconEvent::Click {
-                            button: MouseButton::Left,
-                            button_state: MouseButtonState::Up,
-                            ..
-                        } = event
-                        {
-                            let app = tray.app_handle();
-
-                            // Show and focus the main window
-                            if let Some(window) = app.get_webview_window("main") {
-                                let _ = window.show();
-                                let _ = window.set_focus();
-                                let _ = window.unminimize();
-                            }
-                        }
-                    })
-                    .build(app)
-                    .expect("Failed to build tray icon");
-            }
+            tray::setup(app, &state, read_only).expect("Failed to build tray icon");
 
             // CRITICAL FIX: Register cleanup handler for graceful shutdown
             // In Tauri v2, we use window close event handlers for cleanup
@@ -1144,83 +1387,23 @@ fn main() {
                 //     }
                 // }
 
-                let window_label = window.label().to_string();
-                let app_handle_for_cleanup = app.handle().clone();
-
+                let close_window = window.clone();
                 window.on_window_event(move |event| {
+                    if let tauri::WindowEvent::Focused(true) = event {
+                        cleanup_state.notifier.on_window_focused();
+                    }
                     if let tauri::WindowEvent::CloseRequested { api, .. } = event {
-                        tracing::info!("Window close requested, starting graceful shutdown...");
-
                         // Prevent default close behavior temporarily
                         api.prevent_close();
 
-                        // Clone state for async task
-                        let cleanup_state_clone = cleanup_state.clone();
-                        let window_label_clone = window_label.clone();
-                        let app_handle_clone = app_handle_for_cleanup.clone();
-
-                        // Spawn async cleanup task
-                        tauri::async_runtime::spawn(async move {
-                            tracing::info!("Starting async cleanup...");
-
-                            // 1. Signal all servers to shutdown gracefully
-                            cleanup_state_clone.shutdown_token.cancel();
-                            tracing::info!("Server shutdown signal sent (HTTP + OTLP)");
-
-                            // 2. Signal ingestion worker to shutdown
-                            cleanup_state_clone.ingestion_queue.shutdown();
-                            tracing::info!("Ingestion queue shutdown signal sent");
-
-                            // 3. Wait for servers to finish in-flight requests (up to 2 seconds)
-                            tokio::time::sleep(tokio::time::Duration::from_secs(2)).await;
-                            tracing::info!("Server graceful shutdown period complete");
-
-                            // 4. CRITICAL FIX: Actually wait for worker to complete, not just sleep
-                            // This ensures all pending edges are flushed before we close the database
-                            // Timeout of 5 seconds should be sufficient for even large batches
-                            let worker_completed = cleanup_state_clone
-                                .ingestion_queue
-                                .wait_for_shutdown(tokio::time::Duration::from_secs(5))
-                                .await;
-                            
-                            if worker_completed {
-                                tracing::info!("Ingestion worker shutdown completed, proceeding to database close");
-                            } else {
-                                tracing::warn!("Ingestion worker did not complete in time, proceeding anyway");
-                            }
-
-                            // 3. Close database (in a spawn_blocking to avoid blocking runtime)
-                            let db_clone = Arc::clone(&cleanup_state_clone.db);
-                            let close_result =
-                                tokio::task::spawn_blocking(move || db_clone.close()).await;
-
-                            match close_result {
-                                Ok(Ok(())) => {
-                                    tracing::info!("Database closed successfully");
-                                }
-                                Ok(Err(e)) => {
-                                    tracing::error!("Failed to close database: {}", e);
-                                }
-                                Err(e) => {
-                                    tracing::error!("Database close task panicked: {}", e);
-                                }
-                            }
+                        // Keep running in the tray; it has its own Quit action
+                        if cleanup_state.config.read().ui.close_to_tray {
+                            let _ = close_window.hide();
+                            return;
+                        }
 
-                            tracing::info!("Cleanup complete, closing window");
-
-                            // Force process exit after graceful cleanup
-                            // This ensures all background tasks (servers, retention worker) terminate
-                            tracing::info!("All cleanup complete, forcing process exit");
-                            
-                            // Use libc::_exit for immediate termination without running atexit handlers
-                            // This bypasses any blocking cleanup that might hang the process
-                            #[cfg(unix)]
-                            unsafe {
-                                libc::_exit(0);
-                            }
-                            #[cfg(windows)]
-                            std::process::exit(0);
-                        });
+                        tracing::info!("Window close requested, starting graceful shutdown...");
+                        tauri::async_runtime::spawn(shutdown_and_exit(cleanup_state.clone()));
                     }
                 });
             }
@@ -1286,6 +1469,9 @@ fn main() {
             sysinfo_state::get_static_system_info,
             // Update commands
             commands::check_for_updates,
+            // Diagnostics commands
+            commands::collect_diagnostics,
+            commands::upload_diagnostics,
             // Reset/Delete commands
             commands::reset_all_data,
             // Model comparison commands
@@ -1320,12 +1506,25 @@ fn main() {
             plugins::plugin_bundle_variables,
             plugins::plugin_bundle_plan,
             plugins::plugin_bundle_execute,
+            // Background service
+            background_service::get_background_service_status,
+            background_service::install_background_service,
+            background_service::uninstall_background_service,
+            // Editor hook installer
+            editor_hooks::detect_editor_hooks,
+            editor_hooks::install_editor_hooks,
+            editor_hooks::verify_editor_hooks,
+            editor_hooks::uninstall_editor_hooks,
+            // Notifications
+            notifications::get_recent_notifications,
+            notifications::send_test_notification,
             // Storage health commands (Gap #1, #2, #3, #10)
             commands::get_mvcc_stats,
             commands::get_tombstone_gc_stats,
             commands::get_bloom_filter_stats,
             commands::get_write_amplification_stats,
             commands::get_storage_health,
+            commands::get_data_dir_lock,
             // I/O performance commands (Gap #8)
             commands::get_io_performance_mode,
             commands::list_io_performance_modes,
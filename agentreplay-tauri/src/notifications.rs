@@ -0,0 +1,564 @@
+// Copyright 2025 AgentReplay (https://github.com/agentreplay)
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program. If not, see <https://www.gnu.org/licenses/>.
+
+//! Notifications
+//!
+//! Alerts raised by the app (failing evaluations, daily budget thresholds)
+//! are routed to pluggable sinks chosen per severity in
+//! `AppConfig::notifications`: native desktop notifications and an in-app
+//! toast. Each notification carries a deep link into the UI; since desktop
+//! notifications can't carry a click action, the link of the last one is
+//! opened when the window is next focused.
+
+use std::collections::{HashMap, VecDeque};
+use std::sync::Arc;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+use agentreplay_core::clock::now_ms;
+use agentreplay_core::ModelPricingRegistry;
+use parking_lot::{Mutex, RwLock};
+use serde::{Deserialize, Serialize};
+use tauri::{AppHandle, Emitter, Manager, State};
+use tauri_plugin_notification::NotificationExt;
+use tokio::sync::broadcast::error::RecvError;
+
+use crate::{AppConfig, AppState};
+
+/// Event carrying an [`AppNotification`] to the webview
+pub const NOTIFICATION_EVENT: &str = "app-notification";
+/// Event asking the webview to navigate to a notification's link
+pub const OPEN_LINK_EVENT: &str = "notification-open";
+
+/// Notifications kept for `get_recent_notifications`
+const RECENT_LIMIT: usize = 50;
+/// How long after a desktop notification focusing the window opens its link
+const PENDING_LINK_TTL: Duration = Duration::from_secs(5 * 60);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum NotificationSeverity {
+    Info,
+    Warning,
+    Critical,
+}
+
+/// A notification raised by the app
+#[derive(Debug, Clone, Serialize)]
+pub struct AppNotification {
+    /// What raised it, e.g. "eval_failure" or "budget"
+    pub kind: String,
+    pub severity: NotificationSeverity,
+    pub title: String,
+    pub body: String,
+    /// UI route to open, e.g. `/projects/1/traces/42`
+    pub link: Option<String>,
+    pub trace_id: Option<String>,
+    pub created_at_ms: u64,
+    /// Notifications with the same key are suppressed during the cooldown
+    #[serde(skip)]
+    pub dedupe_key: String,
+}
+
+impl AppNotification {
+    pub fn new(
+        kind: &str,
+        severity: NotificationSeverity,
+        title: impl Into<String>,
+        body: impl Into<String>,
+    ) -> Self {
+        let created_at_ms = now_ms();
+        Self {
+            kind: kind.to_string(),
+            severity,
+            title: title.into(),
+            body: body.into(),
+            link: None,
+            trace_id: None,
+            created_at_ms,
+            dedupe_key: kind.to_string(),
+        }
+    }
+
+    /// Link the notification to a trace; also dedupes per trace
+    pub fn with_trace(mut self, project_id: u16, trace_id: u128) -> Self {
+        self.link = Some(trace_link(project_id, trace_id));
+        self.trace_id = Some(trace_id.to_string());
+        self.dedupe_key = format!("{}:{}", self.kind, trace_id);
+        self
+    }
+
+    pub fn with_link(mut self, link: String) -> Self {
+        self.link = Some(link);
+        self
+    }
+
+    pub fn with_dedupe_key(mut self, key: String) -> Self {
+        self.dedupe_key = key;
+        self
+    }
+}
+
+/// UI route of a trace (trace ids are decimal in routes, as in the trace list)
+pub fn trace_link(project_id: u16, trace_id: u128) -> String {
+    format!("/projects/{}/traces/{}", project_id, trace_id)
+}
+
+/// Notification settings
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct NotificationConfig {
+    pub enabled: bool,
+    /// Sinks per severity (e.g. "desktop", "in_app"); empty mutes the severity
+    pub info: Vec<String>,
+    pub warning: Vec<String>,
+    pub critical: Vec<String>,
+    /// Notify when an evaluation fails a trace
+    pub eval_failures: bool,
+    /// Daily LLM spend that raises a warning at 80% and a critical alert at 100%
+    pub daily_budget_usd: Option<f64>,
+    /// Minimum time between notifications with the same key
+    pub cooldown_secs: u64,
+}
+
+impl Default for NotificationConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            info: vec![InAppSink::NAME.to_string()],
+            warning: vec![DesktopSink::NAME.to_string(), InAppSink::NAME.to_string()],
+            critical: vec![DesktopSink::NAME.to_string(), InAppSink::NAME.to_string()],
+            eval_failures: true,
+            daily_budget_usd: None,
+            cooldown_secs: 300,
+        }
+    }
+}
+
+impl NotificationConfig {
+    /// Sinks a notification of this severity goes to
+    pub fn sinks_for(&self, severity: NotificationSeverity) -> &[String] {
+        if !self.enabled {
+            return &[];
+        }
+        match severity {
+            NotificationSeverity::Info => &self.info,
+            NotificationSeverity::Warning => &self.warning,
+            NotificationSeverity::Critical => &self.critical,
+        }
+    }
+}
+
+/// Delivers notifications somewhere (OS notification center, webview, ...)
+pub trait NotificationSink: Send + Sync {
+    /// Name used in the per-severity sink lists
+    fn name(&self) -> &'static str;
+    fn deliver(&self, app: &AppHandle, notification: &AppNotification) -> Result<(), String>;
+}
+
+/// Native notification via `tauri_plugin_notification`
+pub struct DesktopSink;
+
+impl DesktopSink {
+    pub const NAME: &'static str = "desktop";
+}
+
+impl NotificationSink for DesktopSink {
+    fn name(&self) -> &'static str {
+        Self::NAME
+    }
+
+    fn deliver(&self, app: &AppHandle, notification: &AppNotification) -> Result<(), String> {
+        app.notification()
+            .builder()
+            .title(&notification.title)
+            .body(&notification.body)
+            .show()
+            .map_err(|e| e.to_string())
+    }
+}
+
+/// Toast in the app window, via [`NOTIFICATION_EVENT`]
+pub struct InAppSink;
+
+impl InAppSink {
+    pub const NAME: &'static str = "in_app";
+}
+
+impl NotificationSink for InAppSink {
+    fn name(&self) -> &'static str {
+        Self::NAME
+    }
+
+    fn deliver(&self, app: &AppHandle, notification: &AppNotification) -> Result<(), String> {
+        app.emit(NOTIFICATION_EVENT, notification)
+            .map_err(|e| e.to_string())
+    }
+}
+
+/// Suppresses repeats of the same key within a cooldown
+#[derive(Debug, Default)]
+struct Cooldown {
+    last_sent: HashMap<String, Instant>,
+}
+
+impl Cooldown {
+    fn allow(&mut self, key: &str, now: Instant, cooldown: Duration) -> bool {
+        self.last_sent
+            .retain(|_, at| now.duration_since(*at) < cooldown);
+        if self.last_sent.contains_key(key) {
+            return false;
+        }
+        self.last_sent.insert(key.to_string(), now);
+        true
+    }
+}
+
+#[derive(Default)]
+struct NotifierState {
+    cooldown: Cooldown,
+    recent: VecDeque<AppNotification>,
+    /// Link of the last desktop notification shown while the window was unfocused
+    pending_link: Option<(Instant, String)>,
+}
+
+/// Routes notifications to the sinks configured for their severity
+pub struct Notifier {
+    app: AppHandle,
+    config: Arc<RwLock<AppConfig>>,
+    sinks: RwLock<Vec<Arc<dyn NotificationSink>>>,
+    state: Mutex<NotifierState>,
+}
+
+impl Notifier {
+    /// Notifier with the desktop and in-app sinks registered
+    pub fn new(app: AppHandle, config: Arc<RwLock<AppConfig>>) -> Self {
+        let notifier = Self {
+            app,
+            config,
+            sinks: RwLock::new(Vec::new()),
+            state: Mutex::new(NotifierState::default()),
+        };
+        notifier.register_sink(Arc::new(DesktopSink));
+        notifier.register_sink(Arc::new(InAppSink));
+        notifier
+    }
+
+    /// Add a sink, replacing any registered under the same name
+    pub fn register_sink(&self, sink: Arc<dyn NotificationSink>) {
+        let mut sinks = self.sinks.write();
+        sinks.retain(|s| s.name() != sink.name());
+        sinks.push(sink);
+    }
+
+    pub fn config(&self) -> NotificationConfig {
+        self.config.read().notifications.clone()
+    }
+
+    /// Deliver a notification, unless muted for its severity or in cooldown
+    pub fn notify(&self, notification: AppNotification) {
+        let config = self.config();
+        let targets = config.sinks_for(notification.severity);
+        if targets.is_empty() {
+            return;
+        }
+        {
+            let mut state = self.state.lock();
+            let cooldown = Duration::from_secs(config.cooldown_secs);
+            if !state
+                .cooldown
+                .allow(&notification.dedupe_key, Instant::now(), cooldown)
+            {
+                tracing::debug!("Notification '{}' suppressed", notification.dedupe_key);
+                return;
+            }
+            if state.recent.len() == RECENT_LIMIT {
+                state.recent.pop_front();
+            }
+            state.recent.push_back(notification.clone());
+        }
+
+        let sinks = self.sinks.read().clone();
+        for sink in sinks
+            .iter()
+            .filter(|s| targets.iter().any(|t| t == s.name()))
+        {
+            if let Err(e) = sink.deliver(&self.app, &notification) {
+                tracing::warn!("Failed to deliver notification via {}: {}", sink.name(), e);
+                continue;
+            }
+            if sink.name() == DesktopSink::NAME && !self.window_focused() {
+                if let Some(link) = &notification.link {
+                    self.state.lock().pending_link = Some((Instant::now(), link.clone()));
+                }
+            }
+        }
+    }
+
+    /// Most recent notifications, newest first
+    pub fn recent(&self) -> Vec<AppNotification> {
+        self.state.lock().recent.iter().rev().cloned().collect()
+    }
+
+    /// Open the link of a desktop notification shown while the window was away
+    ///
+    /// Clicking a desktop notification focuses the app, so this is how its
+    /// deep link gets followed.
+    pub fn on_window_focused(&self) {
+        let pending = self.state.lock().pending_link.take();
+        if let Some((at, link)) = pending {
+            if at.elapsed() < PENDING_LINK_TTL {
+                let _ = self.app.emit(OPEN_LINK_EVENT, link);
+            }
+        }
+    }
+
+    fn window_focused(&self) -> bool {
+        self.app
+            .get_webview_window("main")
+            .and_then(|w| w.is_focused().ok())
+            .unwrap_or(false)
+    }
+}
+
+/// Notify about a trace that failed an evaluation
+pub fn notify_eval_failure(
+    state: &AppState,
+    trace_id: u128,
+    evaluator: &str,
+    score: Option<f64>,
+    detail: &str,
+) {
+    if !state.notifier.config().eval_failures {
+        return;
+    }
+    let project_id = state
+        .db
+        .get(trace_id)
+        .ok()
+        .flatten()
+        .map(|edge| edge.project_id)
+        .unwrap_or(0);
+
+    // A very low score is more likely a broken agent than a borderline answer
+    let severity = match score {
+        Some(score) if score > 0.3 => NotificationSeverity::Warning,
+        _ => NotificationSeverity::Critical,
+    };
+    let body = match score {
+        Some(score) => format!("Trace {} scored {:.2}: {}", trace_id, score, detail),
+        None => format!("Trace {}: {}", trace_id, detail),
+    };
+    state.notifier.notify(
+        AppNotification::new(
+            "eval_failure",
+            severity,
+            format!("Evaluation failed ({})", evaluator),
+            body,
+        )
+        .with_trace(project_id, trace_id),
+    );
+}
+
+/// Daily spend and the highest threshold already alerted for it
+#[derive(Debug, Default)]
+struct BudgetTracker {
+    day: Option<chrono::NaiveDate>,
+    spent_usd: f64,
+    alerted: Option<NotificationSeverity>,
+}
+
+impl BudgetTracker {
+    /// Add spend and return a severity when it crosses a new threshold today
+    fn add(
+        &mut self,
+        day: chrono::NaiveDate,
+        cost_usd: f64,
+        budget_usd: f64,
+    ) -> Option<NotificationSeverity> {
+        if self.day != Some(day) {
+            *self = Self {
+                day: Some(day),
+                ..Self::default()
+            };
+        }
+        self.spent_usd += cost_usd;
+
+        let level = if self.spent_usd >= budget_usd {
+            NotificationSeverity::Critical
+        } else if self.spent_usd >= budget_usd * 0.8 {
+            NotificationSeverity::Warning
+        } else {
+            return None;
+        };
+        if self.alerted == Some(level) || self.alerted == Some(NotificationSeverity::Critical) {
+            return None;
+        }
+        self.alerted = Some(level);
+        Some(level)
+    }
+}
+
+/// Watch ingested spans and alert when the daily budget is crossed
+///
+/// Spend is counted from spans ingested since the app started, priced the
+/// same way as the analytics timeseries.
+pub fn spawn_budget_monitor(state: AppState, pricing: Arc<ModelPricingRegistry>) {
+    let mut rx = state.trace_broadcaster.subscribe();
+    tauri::async_runtime::spawn(async move {
+        let mut tracker = BudgetTracker::default();
+        loop {
+            let edge = tokio::select! {
+                received = rx.recv() => match received {
+                    Ok(edge) => edge,
+                    Err(RecvError::Lagged(skipped)) => {
+                        tracing::debug!("Budget monitor skipped {} spans", skipped);
+                        continue;
+                    }
+                    Err(RecvError::Closed) => break,
+                },
+                _ = state.shutdown_token.cancelled() => break,
+            };
+
+            let Some(budget) = state.notifier.config().daily_budget_usd else {
+                continue;
+            };
+            if budget <= 0.0 || edge.token_count == 0 {
+                continue;
+            }
+            let (_, model, _) = state.db.get_edge_attrs(edge.edge_id).unwrap_or_default();
+            if model.is_empty() {
+                continue;
+            }
+            let cost = pricing.calculate_cost(&model, 0, edge.token_count).await;
+
+            let today = chrono::Local::now().date_naive();
+            if let Some(severity) = tracker.add(today, cost, budget) {
+                let title = match severity {
+                    NotificationSeverity::Critical => "Daily budget exceeded",
+                    _ => "Daily budget at 80%",
+                };
+                state.notifier.notify(
+                    AppNotification::new(
+                        "budget",
+                        severity,
+                        title,
+                        format!(
+                            "${:.2} of ${:.2} spent today (last span: {})",
+                            tracker.spent_usd, budget, model
+                        ),
+                    )
+                    .with_link(format!("/projects/{}/costs", edge.project_id))
+                    .with_dedupe_key(format!("budget:{}:{:?}", today, severity)),
+                );
+            }
+        }
+    });
+}
+
+/// Recent notifications, newest first
+#[tauri::command]
+pub async fn get_recent_notifications(
+    state: State<'_, AppState>,
+) -> Result<Vec<AppNotification>, String> {
+    Ok(state.notifier.recent())
+}
+
+/// Send a test notification through the sinks configured for a severity
+#[tauri::command]
+pub async fn send_test_notification(
+    severity: NotificationSeverity,
+    state: State<'_, AppState>,
+) -> Result<(), String> {
+    state.notifier.notify(
+        AppNotification::new(
+            "test",
+            severity,
+            "Agentreplay",
+            format!("Test {:?} notification", severity).to_lowercase(),
+        )
+        .with_dedupe_key(format!("test:{}", now_nanos())),
+    );
+    Ok(())
+}
+
+fn now_nanos() -> u128 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_nanos())
+        .unwrap_or(0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_sinks_for_severity() {
+        let mut config = NotificationConfig::default();
+        assert_eq!(config.sinks_for(NotificationSeverity::Info), ["in_app"]);
+        assert_eq!(
+            config.sinks_for(NotificationSeverity::Critical),
+            ["desktop", "in_app"]
+        );
+
+        config.warning.clear();
+        assert!(config.sinks_for(NotificationSeverity::Warning).is_empty());
+
+        config.enabled = false;
+        assert!(config.sinks_for(NotificationSeverity::Critical).is_empty());
+    }
+
+    #[test]
+    fn test_cooldown() {
+        let start = Instant::now();
+        let window = Duration::from_secs(60);
+        let mut cooldown = Cooldown::default();
+        assert!(cooldown.allow("eval_failure:1", start, window));
+        assert!(!cooldown.allow("eval_failure:1", start + Duration::from_secs(30), window));
+        assert!(cooldown.allow("eval_failure:2", start + Duration::from_secs(30), window));
+        assert!(cooldown.allow("eval_failure:1", start + Duration::from_secs(61), window));
+    }
+
+    #[test]
+    fn test_budget_thresholds() {
+        let day = chrono::NaiveDate::from_ymd_opt(2025, 6, 1).unwrap();
+        let mut tracker = BudgetTracker::default();
+        assert_eq!(tracker.add(day, 5.0, 10.0), None);
+        assert_eq!(
+            tracker.add(day, 3.5, 10.0),
+            Some(NotificationSeverity::Warning)
+        );
+        assert_eq!(tracker.add(day, 1.0, 10.0), None);
+        assert_eq!(
+            tracker.add(day, 1.0, 10.0),
+            Some(NotificationSeverity::Critical)
+        );
+        assert_eq!(tracker.add(day, 1.0, 10.0), None);
+
+        // A new day starts from zero
+        let next = day.succ_opt().unwrap();
+        assert_eq!(tracker.add(next, 1.0, 10.0), None);
+        assert_eq!(tracker.spent_usd, 1.0);
+    }
+
+    #[test]
+    fn test_config_defaults_when_missing() {
+        let config: NotificationConfig =
+            serde_json::from_str(r#"{"daily_budget_usd": 5.0}"#).unwrap();
+        assert!(config.enabled);
+        assert_eq!(config.daily_budget_usd, Some(5.0));
+        assert_eq!(config.cooldown_secs, 300);
+    }
+}
@@ -205,7 +205,7 @@ async fn handle_otlp_http(
     AxumState(state): AxumState<ServerState>,
     headers: HeaderMap,
     body: Bytes,
-) -> Result<impl IntoResponse, (StatusCode, String)> {
+) -> Result<axum::response::Response, (StatusCode, String)> {
     let content_type = headers
         .get("content-type")
         .and_then(|v| v.to_str().ok())
@@ -235,10 +235,27 @@ async fn handle_otlp_http(
         }
     };
 
+    // Backpressure: OTLP exporters retry 429 responses after Retry-After
+    let batch_size: usize = request
+        .resource_spans
+        .iter()
+        .flat_map(|rs| &rs.scope_spans)
+        .map(|ss| ss.spans.len())
+        .sum();
+    let drain_rate = state.tauri_state.connection_stats.read().ingestion_rate_per_min / 60.0;
+    let decision = match state.tauri_state.ingestion_queue.admit(batch_size, drain_rate) {
+        Ok(decision) => decision,
+        Err(reason) => {
+            warn!("OTLP HTTP: ingestion queue near capacity, rejecting {} spans", batch_size);
+            return Ok(reason.into_response());
+        }
+    };
+
     // Process spans (same logic as gRPC)
     let mut total_spans = 0;
     let mut accepted = 0;
     let mut rejected = 0;
+    let mut span_idx = 0;
 
     // Extract project_id from headers if present
     let header_project_id = headers
@@ -260,6 +277,11 @@ async fn handle_otlp_http(
             total_spans += scope_span.spans.len();
 
             for span in &scope_span.spans {
+                let keep = decision.keeps(span_idx);
+                span_idx += 1;
+                if !keep {
+                    continue; // Sampled out under load
+                }
                 match convert_otel_span_to_edge(span, &resource_attrs) {
                     Ok(edge) => {
                         // Serialize payload (if any) to bundle with the queue item
@@ -294,7 +316,7 @@ async fn handle_otlp_http(
         total_spans, accepted, rejected
     );
 
-    Ok((StatusCode::OK, Json(serde_json::json!({}))))
+    Ok((StatusCode::OK, Json(serde_json::json!({}))).into_response())
 }
 
 /// Extract resource attributes and map to tenant/project
@@ -550,7 +550,7 @@ pub async fn plugin_bundle_execute(
 }
 
 /// Helper to parse install scope from string
-fn parse_install_scope(scope: &str) -> Result<agentreplay_plugins::manifest::InstallScope, String> {
+pub(crate) fn parse_install_scope(scope: &str) -> Result<agentreplay_plugins::manifest::InstallScope, String> {
     use agentreplay_plugins::manifest::InstallScope;
     match scope.to_lowercase().as_str() {
         "user" => Ok(InstallScope::User),
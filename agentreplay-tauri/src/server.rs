@@ -35,6 +35,7 @@ use agentreplay_core::{
     CodingAgent, CodingObservation, CodingSession, SessionState, SessionSummary, ToolAction,
     generate_observation_id, generate_session_id,
 };
+use agentreplay_core::model_pricing::PRICING_SYNC_INTERVAL;
 use agentreplay_storage::VersionStore;
 use governor::{Quota, RateLimiter};
 use serde::{Deserialize, Serialize};
@@ -129,33 +130,27 @@ pub async fn start_embedded_server(host: String, port: u16, tauri_state: AppStat
         .unwrap_or_else(|| PathBuf::from("."))
         .join("agentreplay");
     
-    // Initialize pricing registry with LiteLLM sync
+    // Initialize pricing registry from builtins, the cached LiteLLM data,
+    // recorded price history and saved custom overrides
     let pricing_registry = Arc::new(ModelPricingRegistry::new(data_dir.clone()));
+    if let Err(e) = pricing_registry.initialize().await {
+        warn!("Failed to load model pricing: {}", e);
+    }
     
-    // Start background sync of LiteLLM pricing (non-blocking, with retry)
+    // Keep LiteLLM pricing fresh in the background (syncs when the cache is
+    // older than a day, retrying failures)
     let registry_clone = Arc::clone(&pricing_registry);
+    let sync_shutdown = shutdown_token.clone();
     tokio::spawn(async move {
-        // Try up to 3 times with increasing delays
-        for attempt in 1..=3 {
-            match registry_clone.sync_from_litellm().await {
-                Ok(_) => {
-                    info!("Successfully synced LiteLLM model pricing");
-                    return;
-                }
-                Err(e) => {
-                    if attempt < 3 {
-                        // Silent retry - just debug log
-                        tracing::debug!("LiteLLM pricing sync attempt {} failed: {}, retrying...", attempt, e);
-                        tokio::time::sleep(tokio::time::Duration::from_secs(5 * attempt as u64)).await;
-                    } else {
-                        // Final attempt - log at info level (not warn) since defaults work fine
-                        info!("Using default model pricing (LiteLLM sync unavailable: {})", e);
-                    }
-                }
-            }
+        tokio::select! {
+            _ = registry_clone.run_auto_sync(PRICING_SYNC_INTERVAL) => {}
+            _ = sync_shutdown.cancelled() => {}
         }
     });
 
+    // Alert when today's LLM spend crosses the configured budget
+    crate::notifications::spawn_budget_monitor(tauri_state.clone(), Arc::clone(&pricing_registry));
+
     // Create rate limiter for ingestion endpoints
     // Token bucket: refills at RATE_LIMIT_SPANS_PER_MINUTE per minute, burst up to RATE_LIMIT_BURST_SIZE
     let rate_limiter = Arc::new(RateLimiter::direct(Quota::per_minute(
@@ -197,6 +192,8 @@ pub async fn start_embedded_server(host: String, port: u16, tauri_state: AppStat
         .route("/api/v1/analytics/timeseries", get(analytics_timeseries_handler))
         // Storage debug endpoint
         .route("/api/v1/storage/dump", get(storage_dump_handler))
+        .route("/api/v1/storage/retention", get(storage_retention_handler))
+        .route("/api/v1/storage/compaction", get(compaction_stats_handler).post(trigger_compaction_handler))
         // Playground endpoint (now with real LLM support)
         .route("/api/v1/playground/run", post(playground_run_handler))
         // LLM model management endpoints
@@ -209,7 +206,8 @@ pub async fn start_embedded_server(host: String, port: u16, tauri_state: AppStat
         // Pricing endpoints
         .route("/api/v1/pricing/models", get(list_pricing_handler).post(import_pricing_handler))
         .route("/api/v1/pricing/models/all", get(list_all_pricing_handler))
-        .route("/api/v1/pricing/sync", post(sync_pricing_handler))
+        .route("/api/v1/pricing/sync", get(pricing_sync_status_handler).post(sync_pricing_handler))
+        .route("/api/v1/pricing/history/:model_id", get(pricing_history_handler))
         .route("/api/v1/pricing/calculate", post(calculate_pricing_handler))
         .route("/api/v1/pricing/custom", get(list_custom_pricing_handler).post(add_custom_pricing_handler).put(update_custom_pricing_handler))
         .route("/api/v1/pricing/custom/:model_id", delete(delete_custom_pricing_handler))
@@ -860,10 +858,89 @@ pub async fn start_mcp_server(host: String, port: u16, tauri_state: AppState) ->
         semantic_governor: None,
         eval_cache: None,
         ingestion_actor: None,
+        ingestion_admission: Arc::new(agentreplay_server::admission::QueueAdmission::default()),
+        ingestion_idempotency: Arc::new(agentreplay_server::ingestion::IdempotencyGuard::default()),
+        open_spans: Arc::new(agentreplay_server::ingestion::OpenSpanTracker::default()),
+        session_registry: Arc::new(agentreplay_server::session_registry::SessionRegistry::new(
+            tauri_state.db_path.join("session_registry.json"),
+        )),
+        clock_skew: Some(Arc::new(agentreplay_server::ingestion::ClockSkewCorrector::default())),
+        cluster: None,
+        replication: None,
+        archive: None,
+        scripts: Arc::new(agentreplay_server::scripting::ScriptManager::new(
+            tauri_state.db_path.join("scripts.json"),
+        )),
+        ingest_pipeline: Arc::new(agentreplay_server::ingestion::IngestPipeline::new(
+            agentreplay_server::ingestion::default_pipeline(),
+            agentreplay_server::ingestion::TransformRegistry::new(
+                tauri_state.db_path.join("transforms"),
+            ),
+        )),
+        instance_lock: Some(tauri_state.data_lock.clone()),
+        session_analyzer: Arc::new(agentreplay_server::session_analysis::SessionAnalyzer::new(
+            Default::default(),
+            None,
+        )),
+        heavy_hitters: Arc::new(agentreplay_server::heavy_hitters::HeavyHitters::new()),
+        failures: Arc::new(agentreplay_server::failures::FailureTracker::new()),
+        tool_contracts: Arc::new(agentreplay_server::tool_registry::ToolContractMonitor::new()),
+        knowledge_graph: Arc::new(
+            agentreplay_server::knowledge_graph::KnowledgeGraphIndexer::new(),
+        ),
+        session_summarizer: Arc::new(agentreplay_server::session_summary::SessionSummarizer::new()),
+        conversations: Arc::new(agentreplay_server::conversations::ConversationLinker::new(
+            Default::default(),
+        )),
+        volume_monitor: Arc::new(agentreplay_server::volume_alerts::VolumeMonitor::new(
+            Default::default(),
+        )),
+        drift_detector: Arc::new(agentreplay_server::drift::DriftDetector::new(
+            Default::default(),
+        )),
+        guardrails: Arc::new(agentreplay_server::guardrails::GuardrailEngine::default()),
+        shadow: Arc::new(Default::default()),
+        response_cache: Arc::new(Default::default()),
+        simulator: Arc::new(agentreplay_server::simulation::Simulator::new(
+            Default::default(),
+        )),
+        benchmarks: Arc::new(Default::default()),
+        vault: Arc::new(agentreplay_server::vault::KeyVault::open(
+            &Default::default(),
+            &tauri_state.db_path,
+        )?),
+        hydration: Arc::new(Default::default()),
+        nl_query_feedback: Arc::new(Default::default()),
+        reports: Arc::new(Default::default()),
+        api_tokens: Arc::new(Default::default()),
+        rate_limiter: Arc::new(Default::default()),
+        query_queue: Arc::new(agentreplay_server::scaling::QueryQueue::new(
+            Default::default(),
+        )),
+        import_jobs: Arc::new(Default::default()),
+        jobs: Arc::new(Default::default()),
+        shutdown: Arc::new(Default::default()),
+        config_reloader: Arc::new(Default::default()),
+        project_templates: Arc::new(agentreplay_server::project_templates::ProjectTemplateStore::new(
+            tauri_state.db_path.join(agentreplay_server::project_templates::TEMPLATES_FILE),
+        )),
+        agent_configs: Arc::new(agentreplay_server::agent_config::AgentConfigStore::new(
+            tauri_state.db_path.join(agentreplay_server::agent_config::AGENT_CONFIGS_FILE),
+        )),
+        pricing: Arc::new(ModelPricingRegistry::with_builtins()),
+        billing: Arc::new(agentreplay_server::billing::BillingStore::new(
+            tauri_state.db_path.join(agentreplay_server::billing::BILLING_FILE),
+        )),
+        quotas: Arc::new(Default::default()),
+        access_policies: Arc::new(Default::default()),
+        request_metrics: Arc::new(Default::default()),
     };
 
     // Create MCP Router
-    let mcp_router = agentreplay_server::mcp::mcp_router(server_app_state, causal_index);
+    // The desktop app is single-user and stores everything under the default tenant
+    let mcp_tenant = agentreplay_server::config::McpConfig::default().tenant();
+    let mcp_router =
+        agentreplay_server::mcp::mcp_router(server_app_state, causal_index, mcp_tenant);
     
     // Add CORS
     let app = mcp_router.layer(
@@ -1481,6 +1558,7 @@ async fn health_check_detailed(AxumState(state): AxumState<ServerState>) -> impl
         - state.start_time;
 
     let stats = state.tauri_state.connection_stats.read();
+    let write_path = state.tauri_state.db.write_telemetry();
 
     Json(serde_json::json!({
         "status": "healthy",
@@ -1488,7 +1566,8 @@ async fn health_check_detailed(AxumState(state): AxumState<ServerState>) -> impl
         "uptime_seconds": uptime,
         "storage": {
             "reachable": true,
-            "total_edges": stats.total_traces_received
+            "total_edges": stats.total_traces_received,
+            "write_path": write_path
         },
         "api": {
             "requests_total": stats.total_traces_received,
@@ -1557,6 +1636,60 @@ async fn delete_session_handler(
     }))).into_response()
 }        
 
+/// GET /api/v1/storage/retention - Database size against the size cap and
+/// what size-based eviction would reclaim
+async fn storage_retention_handler(AxumState(state): AxumState<ServerState>) -> impl IntoResponse {
+    let max_bytes = crate::size_retention::max_database_bytes(&state.tauri_state);
+    let db = Arc::clone(&state.tauri_state.db);
+    match tokio::task::spawn_blocking(move || db.estimate_size_retention(max_bytes)).await {
+        Ok(Ok(estimate)) => Json(estimate).into_response(),
+        Ok(Err(e)) => (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            Json(serde_json::json!({"error": e.to_string()})),
+        ).into_response(),
+        Err(e) => (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            Json(serde_json::json!({"error": e.to_string()})),
+        ).into_response(),
+    }
+}
+
+/// GET /api/v1/storage/compaction - Compaction progress, space amplification and tombstone counts
+async fn compaction_stats_handler(AxumState(state): AxumState<ServerState>) -> impl IntoResponse {
+    let db = Arc::clone(&state.tauri_state.db);
+    match tokio::task::spawn_blocking(move || db.compaction_stats()).await {
+        Ok(Ok(stats)) => Json(stats).into_response(),
+        Ok(Err(e)) => (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            Json(serde_json::json!({"error": e.to_string()})),
+        ).into_response(),
+        Err(e) => (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            Json(serde_json::json!({"error": e.to_string()})),
+        ).into_response(),
+    }
+}
+
+/// POST /api/v1/storage/compaction - Run a manual compaction (409 if one is running)
+async fn trigger_compaction_handler(AxumState(state): AxumState<ServerState>) -> impl IntoResponse {
+    let db = Arc::clone(&state.tauri_state.db);
+    match tokio::task::spawn_blocking(move || db.compact()).await {
+        Ok(Ok(run)) => Json(run).into_response(),
+        Ok(Err(agentreplay_core::AgentreplayError::InvalidArgument(msg))) => (
+            StatusCode::CONFLICT,
+            Json(serde_json::json!({"error": msg})),
+        ).into_response(),
+        Ok(Err(e)) => (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            Json(serde_json::json!({"error": format!("Compaction failed: {}", e)})),
+        ).into_response(),
+        Err(e) => (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            Json(serde_json::json!({"error": e.to_string()})),
+        ).into_response(),
+    }
+}
+
 /// Storage dump endpoint - returns raw storage records for debugging
 async fn storage_dump_handler(
     AxumState(state): AxumState<ServerState>,
@@ -1648,7 +1781,8 @@ async fn get_stats(AxumState(state): AxumState<ServerState>) -> impl IntoRespons
         "total_traces_received": stats.total_traces_received,
         "last_trace_time": stats.last_trace_time,
         "server_uptime_secs": uptime,
-        "ingestion_rate_per_min": stats.ingestion_rate_per_min
+        "ingestion_rate_per_min": stats.ingestion_rate_per_min,
+        "ingestion_queue": state.tauri_state.ingestion_queue.metrics()
     }))
 }
 
@@ -1762,7 +1896,7 @@ fn convert_legacy_to_span(legacy: &LegacyTraceRequest) -> AgentreplaySpan {
 async fn ingest_traces(
     AxumState(state): AxumState<ServerState>,
     Json(request): Json<FlexibleIngestRequest>,
-) -> Result<impl IntoResponse, (StatusCode, String)> {
+) -> Result<axum::response::Response, (StatusCode, String)> {
     // Handle both formats
     let request = match request {
         FlexibleIngestRequest::NewFormat(req) => req,
@@ -1799,11 +1933,31 @@ async fn ingest_traces(
         ));
     }
 
+    // Backpressure: reject or sample the batch when the ingestion queue is nearly full
+    let drain_rate = state.tauri_state.connection_stats.read().ingestion_rate_per_min / 60.0;
+    let decision = match state
+        .tauri_state
+        .ingestion_queue
+        .admit(request.spans.len(), drain_rate)
+    {
+        Ok(decision) => decision,
+        Err(reason) => {
+            warn!(
+                "Ingestion queue near capacity, rejecting {} spans",
+                request.spans.len()
+            );
+            return Ok(reason.into_response());
+        }
+    };
+
     let mut edges = Vec::new();
     let mut errors = Vec::new();
     let mut edge_attributes: Vec<(AgentFlowEdge, HashMap<String, String>)> = Vec::new();
 
     for (idx, span) in request.spans.iter().enumerate() {
+        if !decision.keeps(idx) {
+            continue; // Sampled out under load
+        }
         match convert_span_to_edge(span) {
             Ok(edge) => {
                 edge_attributes.push((edge, span.attributes.clone()));
@@ -1895,7 +2049,8 @@ async fn ingest_traces(
             rejected,
             errors,
         }),
-    ))
+    )
+        .into_response())
 }
 
 /// Convert AgentreplaySpan to AgentFlowEdge (simplified version from agentreplay-server)
@@ -3669,8 +3824,8 @@ async fn analytics_timeseries_handler(
 
         if !attr_model.is_empty() && edge.token_count > 0 {
             // Fast path: use denormalized model + edge token_count for cost
-            let cost = state.pricing_registry.calculate_cost(
-                &attr_model, 0, edge.token_count
+            let cost = state.pricing_registry.calculate_cost_at(
+                &attr_model, edge.timestamp_us / 1_000_000, 0, edge.token_count
             ).await;
             entry.3 += cost;
             total_cost_sum += cost;
@@ -3698,7 +3853,10 @@ async fn analytics_timeseries_handler(
                         .unwrap_or(0) as u32;
                     
                     if input_tokens > 0 || output_tokens > 0 {
-                        let cost = state.pricing_registry.calculate_cost(model, input_tokens, output_tokens).await;
+                        let at = edge.timestamp_us / 1_000_000;
+                        let cost = state.pricing_registry
+                            .calculate_cost_at(model, at, input_tokens, output_tokens)
+                            .await;
                         entry.3 += cost;
                         total_cost_sum += cost;
                     }
@@ -4601,20 +4759,22 @@ async fn list_all_pricing_handler(AxumState(state): AxumState<ServerState>) -> i
 }
 
 /// GET /api/v1/pricing/custom - List custom pricing overrides
+///
+/// Each model can have several, each applying from its `effective_from`
+/// (Unix seconds, absent = always) until the next one starts.
 async fn list_custom_pricing_handler(AxumState(state): AxumState<ServerState>) -> impl IntoResponse {
-    let all_models = state.pricing_registry.list_models().await;
+    let overrides = state.pricing_registry.list_custom_overrides().await;
     
-    // Filter to only custom priority entries
-    let custom_entries: Vec<serde_json::Value> = all_models.iter()
-        .filter(|(_, pricing)| pricing.source.as_deref() == Some("Custom") || 
-                               pricing.source.as_deref() == Some("custom"))
-        .map(|(model_id, pricing)| {
+    let custom_entries: Vec<serde_json::Value> = overrides.iter()
+        .map(|o| {
             serde_json::json!({
-                "model_id": model_id,
-                "provider": pricing.litellm_provider.clone().unwrap_or_else(|| "custom".to_string()),
-                "input_cost_per_token": pricing.input_cost_per_token,
-                "output_cost_per_token": pricing.output_cost_per_token,
-                "max_tokens": pricing.max_tokens,
+                "model_id": o.model_id,
+                "provider": o.litellm_provider.clone().unwrap_or_else(|| "custom".to_string()),
+                "input_cost_per_token": o.input_cost_per_token,
+                "output_cost_per_token": o.output_cost_per_token,
+                "max_tokens": o.max_tokens,
+                "source": o.source,
+                "effective_from": o.effective_from,
             })
         }).collect();
     
@@ -4634,13 +4794,17 @@ struct CustomPricingRequest {
     output_cost_per_token: f64,
     #[serde(default)]
     max_tokens: Option<u32>,
+    /// Unix seconds from which the price applies; absent = always
+    #[serde(default)]
+    effective_from: Option<u64>,
 }
 
-/// POST /api/v1/pricing/custom - Add custom pricing override
-async fn add_custom_pricing_handler(
-    AxumState(state): AxumState<ServerState>,
-    Json(req): Json<CustomPricingRequest>,
-) -> impl IntoResponse {
+/// Store a custom override from a request and persist all overrides
+async fn save_custom_pricing(
+    state: &ServerState,
+    req: CustomPricingRequest,
+    verb: &str,
+) -> axum::response::Response {
     use agentreplay_core::model_pricing::CustomPricingOverride;
     
     let override_data = CustomPricingOverride {
@@ -4650,9 +4814,16 @@ async fn add_custom_pricing_handler(
         max_tokens: req.max_tokens,
         litellm_provider: Some(req.provider.clone()),
         source: Some("Custom".to_string()),
+        effective_from: req.effective_from,
     };
     
-    state.pricing_registry.add_custom_override(override_data).await;
+    if let Err(e) = state.pricing_registry.add_custom_override(override_data).await {
+        return (
+            StatusCode::BAD_REQUEST,
+            Json(serde_json::json!({"error": e.to_string()})),
+        )
+            .into_response();
+    }
     
     // Save custom pricing to file
     if let Err(e) = state.pricing_registry.save_custom_overrides().await {
@@ -4661,48 +4832,56 @@ async fn add_custom_pricing_handler(
     
     Json(serde_json::json!({
         "success": true,
-        "message": format!("Added custom pricing for {}", req.model_id),
+        "message": format!("{} custom pricing for {}", verb, req.model_id),
+        "effective_from": req.effective_from,
     }))
     .into_response()
 }
 
+/// POST /api/v1/pricing/custom - Add custom pricing override
+async fn add_custom_pricing_handler(
+    AxumState(state): AxumState<ServerState>,
+    Json(req): Json<CustomPricingRequest>,
+) -> impl IntoResponse {
+    save_custom_pricing(&state, req, "Added").await
+}
+
 /// PUT /api/v1/pricing/custom - Update custom pricing override
+///
+/// Replaces the override of the model with the same `effective_from`.
 async fn update_custom_pricing_handler(
     AxumState(state): AxumState<ServerState>,
     Json(req): Json<CustomPricingRequest>,
 ) -> impl IntoResponse {
-    use agentreplay_core::model_pricing::CustomPricingOverride;
-    
-    let override_data = CustomPricingOverride {
-        model_id: req.model_id.clone(),
-        input_cost_per_token: req.input_cost_per_token,
-        output_cost_per_token: req.output_cost_per_token,
-        max_tokens: req.max_tokens,
-        litellm_provider: Some(req.provider.clone()),
-        source: Some("Custom".to_string()),
-    };
-    
-    state.pricing_registry.add_custom_override(override_data).await;
-    
-    // Save custom pricing to file
-    if let Err(e) = state.pricing_registry.save_custom_overrides().await {
-        tracing::warn!("Failed to persist custom pricing: {}", e);
-    }
-    
-    Json(serde_json::json!({
-        "success": true,
-        "message": format!("Updated custom pricing for {}", req.model_id),
-    }))
-    .into_response()
+    save_custom_pricing(&state, req, "Updated").await
+}
+
+/// Query params for deleting custom pricing
+#[derive(Debug, Deserialize)]
+struct DeleteCustomPricingQuery {
+    /// Only delete the override starting at this time; absent = all
+    #[serde(default)]
+    effective_from: Option<u64>,
 }
 
 /// DELETE /api/v1/pricing/custom/:model_id - Delete custom pricing override
 async fn delete_custom_pricing_handler(
     AxumState(state): AxumState<ServerState>,
     axum::extract::Path(model_id): axum::extract::Path<String>,
+    axum::extract::Query(params): axum::extract::Query<DeleteCustomPricingQuery>,
 ) -> impl IntoResponse {
     // Remove from registry
-    state.pricing_registry.remove_custom_override(&model_id).await;
+    let removed = state
+        .pricing_registry
+        .remove_custom_override(&model_id, params.effective_from)
+        .await;
+    if removed == 0 {
+        return (
+            StatusCode::NOT_FOUND,
+            Json(serde_json::json!({"error": format!("No custom pricing for {}", model_id)})),
+        )
+            .into_response();
+    }
     
     // Save changes to file
     if let Err(e) = state.pricing_registry.save_custom_overrides().await {
@@ -4712,6 +4891,49 @@ async fn delete_custom_pricing_handler(
     Json(serde_json::json!({
         "success": true,
         "message": format!("Deleted custom pricing for {}", model_id),
+        "removed": removed,
+    }))
+    .into_response()
+}
+
+/// GET /api/v1/pricing/history/:model_id - Price timeline of a model
+///
+/// Upstream price changes recorded by syncs plus custom overrides, which
+/// is what costs of past requests are computed with.
+async fn pricing_history_handler(
+    AxumState(state): AxumState<ServerState>,
+    axum::extract::Path(model_id): axum::extract::Path<String>,
+) -> impl IntoResponse {
+    let upstream = state.pricing_registry.price_history(&model_id).await;
+    let overrides: Vec<_> = state
+        .pricing_registry
+        .list_custom_overrides()
+        .await
+        .into_iter()
+        .filter(|o| o.model_id == model_id)
+        .collect();
+    let current = state.pricing_registry.get_pricing(&model_id).await;
+
+    Json(serde_json::json!({
+        "model_id": model_id,
+        "current": current,
+        "upstream": upstream,
+        "overrides": overrides,
+    }))
+    .into_response()
+}
+
+/// GET /api/v1/pricing/sync - Status of the background LiteLLM sync
+async fn pricing_sync_status_handler(AxumState(state): AxumState<ServerState>) -> impl IntoResponse {
+    let metadata = state.pricing_registry.metadata().await;
+    let next_sync_at = metadata
+        .last_sync_at
+        .map(|last| last + PRICING_SYNC_INTERVAL.as_secs());
+
+    Json(serde_json::json!({
+        "metadata": metadata,
+        "interval_seconds": PRICING_SYNC_INTERVAL.as_secs(),
+        "next_sync_at": next_sync_at,
     }))
     .into_response()
 }
@@ -4753,6 +4975,9 @@ struct CalculatePricingRequest {
     model_id: String,
     input_tokens: u32,
     output_tokens: u32,
+    /// Price as of this time (Unix seconds); defaults to now
+    #[serde(default)]
+    timestamp: Option<u64>,
 }
 
 /// POST /api/v1/pricing/calculate - Calculate cost for token usage
@@ -4771,12 +4996,15 @@ async fn calculate_pricing_handler(
         pricing_found: bool,
     }
 
+    let at = req.timestamp.unwrap_or_else(|| {
+        SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default().as_secs()
+    });
     let cost = state
         .pricing_registry
-        .calculate_cost(&req.model_id, req.input_tokens, req.output_tokens)
+        .calculate_cost_at(&req.model_id, at, req.input_tokens, req.output_tokens)
         .await;
 
-    let pricing = state.pricing_registry.get_pricing(&req.model_id).await;
+    let pricing = state.pricing_registry.get_pricing_at(&req.model_id, at).await;
 
     let (input_cost, output_cost) = if let Some(p) = &pricing {
         (
@@ -4838,10 +5066,22 @@ async fn import_pricing_handler(
             max_tokens,
             litellm_provider: provider.map(|s| s.to_string()),
             source: Some("model_code.json".to_string()),
+            effective_from: None,
         };
         
-        state.pricing_registry.add_custom_override(override_data).await;
-        imported += 1;
+        match state.pricing_registry.add_custom_override(override_data).await {
+            Ok(()) => imported += 1,
+            Err(e) => {
+                debug!("Skipping imported pricing for {}: {}", model_name, e);
+                skipped += 1;
+            }
+        }
+    }
+    
+    if imported > 0 {
+        if let Err(e) = state.pricing_registry.save_custom_overrides().await {
+            warn!("Failed to persist imported pricing: {}", e);
+        }
     }
     
     Json(serde_json::json!({
@@ -5001,8 +5241,11 @@ async fn analytics_costs_handler(
             cached_count += 1;
         }
         
-        // Calculate cost using pricing registry
-        let cost = state.pricing_registry.calculate_cost(&model, input_tokens, output_tokens).await;
+        // Calculate cost with the pricing in effect when the call was made
+        let at = edge.timestamp_us / 1_000_000;
+        let cost = state.pricing_registry
+            .calculate_cost_at(&model, at, input_tokens, output_tokens)
+            .await;
         
         // Update model aggregates
         let entry = model_costs.entry(model).or_insert((0.0, 0, 0, 0));
@@ -5368,6 +5611,20 @@ impl From<Insight> for InsightView {
                 "traffic_anomaly".to_string(),
                 vec![format!("Expected ~{} requests, got {}", expected_count, actual_count)],
             ),
+            InsightType::ToolContractViolation { tool_name, violation_count, sample_error } => (
+                "tool_contract_violation".to_string(),
+                vec![format!("{} call(s) to '{}' broke its schema: {}", violation_count, tool_name, sample_error)],
+            ),
+            InsightType::DatasetDrift {
+                datasets, coverage, ..
+            } => (
+                "dataset_drift".to_string(),
+                vec![format!(
+                    "Only {:.0}% of recent inputs are covered by {}",
+                    coverage * 100.0,
+                    datasets.join(", ")
+                )],
+            ),
         };
 
         InsightView {
@@ -5494,6 +5751,8 @@ async fn get_insights_summary_handler(
             InsightType::FailurePattern { .. } => "failure_pattern",
             InsightType::PerformanceRegression { .. } => "performance_regression",
             InsightType::TrafficAnomaly { .. } => "traffic_anomaly",
+            InsightType::ToolContractViolation { .. } => "tool_contract_violation",
+            InsightType::DatasetDrift { .. } => "dataset_drift",
         };
         *by_type.entry(type_name.to_string()).or_insert(0) += 1;
     }
@@ -6263,6 +6522,9 @@ async fn add_run_result_handler(
     };
 
     let timestamp = current_timestamp_us();
+    let failure_detail = (!req.passed).then(|| {
+        req.error.clone().unwrap_or_else(|| "Test case failed".to_string())
+    });
 
     // Build the result
     let mut result = if req.passed {
@@ -6293,6 +6555,16 @@ async fn add_run_result_handler(
         Err(e) => return (StatusCode::INTERNAL_SERVER_ERROR, Json(serde_json::json!({"error": e.to_string()}))).into_response(),
     };
 
+    if let (Some(trace_id), Some(detail)) = (trace_id, failure_detail) {
+        crate::notifications::notify_eval_failure(
+            &state.tauri_state,
+            trace_id,
+            "eval run",
+            None,
+            &detail,
+        );
+    }
+
     Json(AddRunResultResponse {
         success: true,
         total_results,
@@ -6844,6 +7116,16 @@ async fn run_geval_handler(
     
     let duration = start.elapsed();
     let pass_threshold = 0.7;
+
+    if overall_score < pass_threshold {
+        crate::notifications::notify_eval_failure(
+            &state.tauri_state,
+            trace_id,
+            "g-eval",
+            Some(overall_score),
+            &format!("below the {:.2} pass threshold", pass_threshold),
+        );
+    }
     
     // Add note about evaluation method
     let explanation = if used_llm {
@@ -8136,6 +8418,7 @@ async fn eval_pipeline_golden_handler(
             expected_output: Some(tc.expected_output),
             metadata: tc.metadata.unwrap_or_default(),
             task_definition_v2: None,
+            reference_trajectory: None,
         };
         dataset.add_test_case(test_case);
         added_count += 1;
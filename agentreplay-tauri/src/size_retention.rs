@@ -0,0 +1,153 @@
+// Copyright 2025 AgentReplay (https://github.com/agentreplay)
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program. If not, see <https://www.gnu.org/licenses/>.
+
+//! Size-based retention
+//!
+//! Keeps the database under `retention.max_database_size_mb` by evicting the
+//! oldest traces (see `Agentreplay::evict_to_size`). Runs alongside the TTL
+//! retention worker but checks far more often, since a busy agent can fill a
+//! laptop disk well within the daily TTL interval.
+
+use std::time::{Duration, Instant};
+
+use crate::AppState;
+
+const CHECK_INTERVAL: Duration = Duration::from_secs(10 * 60);
+
+/// Longest wait for storage to reclaim what the compaction after an
+/// eviction left behind before evicting again
+const RECLAIM_TIMEOUT: Duration = Duration::from_secs(2 * 60 * 60);
+
+/// Growth past the size at the last eviction, as a fraction of the cap,
+/// that is new data to evict rather than space still to be reclaimed
+const REARM_GROWTH: f64 = 0.05;
+
+/// Directory size measured after the last eviction, and when
+#[derive(Debug, Clone, Copy)]
+struct LastEviction {
+    disk_bytes: u64,
+    at: Instant,
+}
+
+/// Size cap in bytes from the current config, if any
+pub fn max_database_bytes(state: &AppState) -> Option<u64> {
+    let config = state.config.read();
+    if !config.retention.enabled {
+        return None;
+    }
+    config
+        .retention
+        .max_database_size_mb
+        .filter(|mb| *mb > 0)
+        .map(|mb| mb * 1024 * 1024)
+}
+
+/// Whether space freed by a previous eviction is still being reclaimed
+///
+/// Eviction compacts right away, but storage may free part of the space only
+/// once it merges its segments on its own. Until the directory drops below
+/// the size measured after the last eviction, evicting again would just
+/// remove more traces for the same excess. The wait ends anyway once the
+/// directory grew well past that size, since that is new data, or after
+/// [`RECLAIM_TIMEOUT`], in case the space never comes back.
+fn awaiting_reclaim(
+    last: Option<LastEviction>,
+    disk_bytes: u64,
+    max_bytes: u64,
+    now: Instant,
+) -> bool {
+    let Some(last) = last else {
+        return false;
+    };
+    let growth = (max_bytes as f64 * REARM_GROWTH) as u64;
+    disk_bytes >= last.disk_bytes
+        && disk_bytes < last.disk_bytes.saturating_add(growth)
+        && now.duration_since(last.at) < RECLAIM_TIMEOUT
+}
+
+/// Check the database size periodically and evict when over the cap
+///
+/// The cap is re-read on every check, so changing it in settings applies
+/// without a restart.
+pub fn spawn_worker(state: AppState) {
+    tauri::async_runtime::spawn(async move {
+        let mut interval = tokio::time::interval(CHECK_INTERVAL);
+        let mut last_eviction = None;
+        loop {
+            tokio::select! {
+                _ = interval.tick() => {}
+                _ = state.shutdown_token.cancelled() => break,
+            }
+
+            let Some(max_bytes) = max_database_bytes(&state) else {
+                continue;
+            };
+            let disk_bytes = state.db.disk_usage_bytes();
+            if disk_bytes <= max_bytes {
+                last_eviction = None;
+                continue;
+            }
+            if awaiting_reclaim(last_eviction, disk_bytes, max_bytes, Instant::now()) {
+                tracing::debug!(
+                    disk_bytes,
+                    "Database over size limit; waiting for storage to reclaim evicted traces"
+                );
+                continue;
+            }
+
+            match state.db.evict_to_size(max_bytes).await {
+                Ok(stats) => {
+                    // Re-measure: the next eviction is sized from what is left
+                    let disk_bytes = state.db.disk_usage_bytes();
+                    tracing::info!(
+                        evicted = stats.traces_deleted,
+                        freed_bytes = stats.disk_freed_bytes,
+                        disk_bytes,
+                        "Size-based retention evicted oldest traces"
+                    );
+                    last_eviction = (disk_bytes > max_bytes).then(|| LastEviction {
+                        disk_bytes,
+                        at: Instant::now(),
+                    });
+                }
+                Err(e) => tracing::error!("Size-based retention failed: {}", e),
+            }
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_awaiting_reclaim() {
+        let at = Instant::now();
+        let last = Some(LastEviction {
+            disk_bytes: 500,
+            at,
+        });
+        let soon = at + Duration::from_secs(60);
+        assert!(!awaiting_reclaim(None, 500, 400, soon));
+        assert!(awaiting_reclaim(last, 500, 400, soon));
+        assert!(awaiting_reclaim(last, 510, 400, soon));
+        assert!(!awaiting_reclaim(last, 450, 400, soon));
+
+        // Growth by 5% of the cap is new data
+        assert!(!awaiting_reclaim(last, 520, 400, soon));
+        // Compaction that never comes does not block eviction for good
+        assert!(!awaiting_reclaim(last, 500, 400, at + RECLAIM_TIMEOUT));
+    }
+}
@@ -0,0 +1,341 @@
+// Copyright 2025 AgentReplay (https://github.com/agentreplay)
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program. If not, see <https://www.gnu.org/licenses/>.
+
+//! System Tray
+//!
+//! Shows whether the ingestion server is up and how many traces/min it is
+//! receiving, with quick actions so the app can keep running with its
+//! window hidden (see `ui.close_to_tray`).
+
+use std::time::{Duration, Instant};
+
+use tauri::menu::{Menu, MenuItem, PredefinedMenuItem};
+use tauri::tray::{MouseButton, MouseButtonState, TrayIcon, TrayIconBuilder, TrayIconEvent};
+use tauri::{AppHandle, Manager, Wry};
+use tauri_plugin_clipboard_manager::ClipboardExt;
+use tauri_plugin_notification::NotificationExt;
+
+use crate::AppState;
+
+const REFRESH_INTERVAL: Duration = Duration::from_secs(5);
+
+/// Ingestion server state shown in the tray
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum ServerStatus {
+    Running,
+    Paused,
+    /// Enabled but not accepting connections
+    Stopped,
+    Disabled,
+    /// Another process owns the database
+    ReadOnly,
+}
+
+impl ServerStatus {
+    fn label(self) -> &'static str {
+        match self {
+            ServerStatus::Running => "Running",
+            ServerStatus::Paused => "Paused",
+            ServerStatus::Stopped => "Not running",
+            ServerStatus::Disabled => "Disabled",
+            ServerStatus::ReadOnly => "Read-only",
+        }
+    }
+}
+
+/// Traces/min from successive samples of the running trace count
+#[derive(Debug, Default)]
+struct RateSampler {
+    last: Option<(Instant, u64)>,
+}
+
+impl RateSampler {
+    fn sample(&mut self, now: Instant, total: u64) -> f64 {
+        let rate = match self.last {
+            Some((at, previous)) => {
+                let secs = now.duration_since(at).as_secs_f64();
+                if secs > 0.0 {
+                    total.saturating_sub(previous) as f64 * 60.0 / secs
+                } else {
+                    0.0
+                }
+            }
+            None => 0.0,
+        };
+        self.last = Some((now, total));
+        rate
+    }
+}
+
+fn status_text(status: ServerStatus, traces_per_min: f64) -> String {
+    match status {
+        ServerStatus::Running | ServerStatus::Paused => format!(
+            "Ingestion: {} · {:.0} traces/min",
+            status.label(),
+            traces_per_min
+        ),
+        _ => format!("Ingestion: {}", status.label()),
+    }
+}
+
+/// Address to connect to for a bind address; wildcard binds are reached via loopback
+fn connect_host(host: &str) -> &str {
+    match host {
+        "0.0.0.0" | "::" => "127.0.0.1",
+        host => host,
+    }
+}
+
+/// Endpoint SDKs should send traces to
+fn ingest_endpoint(state: &AppState) -> String {
+    let config = state.config.read();
+    format!(
+        "http://{}:{}",
+        connect_host(&config.ingestion_server.host),
+        config.ingestion_server.port
+    )
+}
+
+fn show_main_window(app: &AppHandle) {
+    if let Some(window) = app.get_webview_window("main") {
+        let _ = window.show();
+        let _ = window.unminimize();
+        let _ = window.set_focus();
+    }
+}
+
+fn notify(app: &AppHandle, body: &str) {
+    if let Err(e) = app
+        .notification()
+        .builder()
+        .title("Agentreplay")
+        .body(body)
+        .show()
+    {
+        tracing::warn!("Failed to show notification: {}", e);
+    }
+}
+
+fn create_backup(app: &AppHandle) {
+    let app = app.clone();
+    tauri::async_runtime::spawn(async move {
+        let db_path = app.state::<AppState>().db_path.clone();
+        let handle = app.clone();
+        let result = tauri::async_runtime::spawn_blocking(move || {
+            crate::commands::backup_database(&handle, &db_path)
+        })
+        .await;
+
+        match result {
+            Ok(Ok(backup)) => {
+                tracing::info!("Tray backup created at {}", backup.path);
+                notify(&app, &format!("Backup created: {}", backup.backup_id));
+            }
+            Ok(Err(e)) => {
+                tracing::error!("Tray backup failed: {}", e);
+                notify(&app, &format!("Backup failed: {}", e));
+            }
+            Err(e) => tracing::error!("Tray backup task panicked: {}", e),
+        }
+    });
+}
+
+/// Build the tray icon and start refreshing its status line
+///
+/// `read_only` is true when another process owns the database, in which
+/// case no ingestion server runs here and pausing is disabled.
+pub fn setup(app: &tauri::App, state: &AppState, read_only: bool) -> tauri::Result<()> {
+    let status_item = MenuItem::with_id(
+        app,
+        "tray_status",
+        "Ingestion: starting…",
+        false,
+        None::<&str>,
+    )?;
+    let pause_item = MenuItem::with_id(
+        app,
+        "tray_toggle_ingestion",
+        "Pause Ingestion",
+        !read_only,
+        None::<&str>,
+    )?;
+    let menu = Menu::with_items(
+        app,
+        &[
+            &status_item,
+            &PredefinedMenuItem::separator(app)?,
+            &pause_item,
+            &MenuItem::with_id(
+                app,
+                "tray_open_dashboard",
+                "Open Dashboard",
+                true,
+                None::<&str>,
+            )?,
+            &MenuItem::with_id(
+                app,
+                "tray_backup",
+                "Create Backup",
+                !read_only,
+                None::<&str>,
+            )?,
+            &MenuItem::with_id(
+                app,
+                "tray_copy_endpoint",
+                "Copy Ingest Endpoint",
+                true,
+                None::<&str>,
+            )?,
+            &PredefinedMenuItem::separator(app)?,
+            &MenuItem::with_id(app, "tray_quit", "Quit Agentreplay", true, None::<&str>)?,
+        ],
+    )?;
+
+    let pause_for_menu = pause_item.clone();
+    let tray = TrayIconBuilder::with_id("agentreplay")
+        .icon(app.default_window_icon().unwrap().clone())
+        .tooltip("Agentreplay")
+        .menu(&menu)
+        .show_menu_on_left_click(false)
+        .on_menu_event(move |app, event| {
+            let state = app.state::<AppState>();
+            match event.id().as_ref() {
+                "tray_toggle_ingestion" => {
+                    let paused = !state.ingestion_queue.is_paused();
+                    state.ingestion_queue.set_paused(paused);
+                    tracing::info!(
+                        "Ingestion {} from tray",
+                        if paused { "paused" } else { "resumed" }
+                    );
+                    let _ = pause_for_menu.set_text(if paused {
+                        "Resume Ingestion"
+                    } else {
+                        "Pause Ingestion"
+                    });
+                }
+                "tray_open_dashboard" => show_main_window(app),
+                "tray_backup" => create_backup(app),
+                "tray_copy_endpoint" => {
+                    let endpoint = ingest_endpoint(&state);
+                    match app.clipboard().write_text(endpoint.clone()) {
+                        Ok(()) => notify(app, &format!("Copied {}", endpoint)),
+                        Err(e) => tracing::warn!("Failed to copy ingest endpoint: {}", e),
+                    }
+                }
+                "tray_quit" => {
+                    tauri::async_runtime::spawn(crate::shutdown_and_exit(state.inner().clone()));
+                }
+                _ => {}
+            }
+        })
+        .on_tray_icon_event(|tray, event| {
+            if let TrayIconEvent::Click {
+                button: MouseButton::Left,
+                button_state: MouseButtonState::Up,
+                ..
+            } = event
+            {
+                show_main_window(tray.app_handle());
+            }
+        })
+        .build(app)?;
+
+    spawn_refresh(tray, status_item, state.clone(), read_only);
+    Ok(())
+}
+
+/// Refresh the status line and tooltip every few seconds
+///
+/// Also keeps `ConnectionStats::ingestion_rate_per_min` current, which the
+/// queue admission uses as its drain rate.
+fn spawn_refresh(tray: TrayIcon, status_item: MenuItem<Wry>, state: AppState, read_only: bool) {
+    tauri::async_runtime::spawn(async move {
+        let mut sampler = RateSampler::default();
+        let mut interval = tokio::time::interval(REFRESH_INTERVAL);
+        loop {
+            tokio::select! {
+                _ = interval.tick() => {}
+                _ = state.shutdown_token.cancelled() => break,
+            }
+
+            let total = state.connection_stats.read().total_traces_received;
+            let rate = sampler.sample(Instant::now(), total);
+            state.connection_stats.write().ingestion_rate_per_min = rate;
+
+            let (enabled, host, port) = {
+                let config = state.config.read();
+                (
+                    config.ingestion_server.enabled,
+                    config.ingestion_server.host.clone(),
+                    config.ingestion_server.port,
+                )
+            };
+            let status = if read_only {
+                ServerStatus::ReadOnly
+            } else if !enabled {
+                ServerStatus::Disabled
+            } else if !is_listening(&host, port).await {
+                ServerStatus::Stopped
+            } else if state.ingestion_queue.is_paused() {
+                ServerStatus::Paused
+            } else {
+                ServerStatus::Running
+            };
+
+            let text = status_text(status, rate);
+            let _ = status_item.set_text(&text);
+            let _ = tray.set_tooltip(Some(format!("Agentreplay\n{}", text)));
+        }
+    });
+}
+
+async fn is_listening(host: &str, port: u16) -> bool {
+    matches!(
+        tokio::time::timeout(
+            Duration::from_secs(1),
+            tokio::net::TcpStream::connect((connect_host(host), port))
+        )
+        .await,
+        Ok(Ok(_))
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_rate_sampler() {
+        let start = Instant::now();
+        let mut sampler = RateSampler::default();
+        assert_eq!(sampler.sample(start, 100), 0.0);
+        assert_eq!(sampler.sample(start + Duration::from_secs(30), 150), 100.0);
+        assert_eq!(sampler.sample(start + Duration::from_secs(60), 150), 0.0);
+        // Counter reset (e.g. stats cleared) never reports a negative rate
+        assert_eq!(sampler.sample(start + Duration::from_secs(90), 10), 0.0);
+    }
+
+    #[test]
+    fn test_status_text() {
+        assert_eq!(
+            status_text(ServerStatus::Running, 42.4),
+            "Ingestion: Running · 42 traces/min"
+        );
+        assert_eq!(
+            status_text(ServerStatus::Disabled, 0.0),
+            "Ingestion: Disabled"
+        );
+    }
+}
@@ -19,6 +19,7 @@ import SetupWizard from '../components/SetupWizard';
 import '../app/globals.css';
 import { initTheme } from './lib/theme';
 import { useProjects } from './context/project-context';
+import { useToast, ToastType } from './context/toast-context';
 import { AppModeProvider } from './context/app-mode-context';
 import Traces from './pages/Traces';
 import TraceDetail from './pages/TraceDetail';
@@ -65,6 +66,59 @@ function usePathPersistence() {
   }, [location, currentProject]);
 }
 
+interface AppNotification {
+  kind: string;
+  severity: 'info' | 'warning' | 'critical';
+  title: string;
+  body: string;
+  link: string | null;
+}
+
+const NOTIFICATION_TOAST_TYPE: Record<AppNotification['severity'], ToastType> = {
+  info: 'info',
+  warning: 'warning',
+  critical: 'error',
+};
+
+// Show in-app notifications from the desktop app and follow their deep links
+function useAppNotifications() {
+  const navigate = useNavigate();
+  const { addToast } = useToast();
+
+  useEffect(() => {
+    if (!('__TAURI__' in window || '__TAURI_INTERNALS__' in window)) return;
+
+    let disposed = false;
+    const unlisteners: Array<() => void> = [];
+    (async () => {
+      const { listen } = await import('@tauri-apps/api/event');
+      const subscriptions = await Promise.all([
+        listen<AppNotification>('app-notification', ({ payload }) => {
+          const link = payload.link;
+          addToast(
+            `${payload.title}: ${payload.body}`,
+            NOTIFICATION_TOAST_TYPE[payload.severity],
+            payload.severity === 'critical' ? 10000 : 6000,
+            link ? { label: 'Open', onClick: () => navigate(link) } : undefined
+          );
+        }),
+        // Window focused after a desktop notification was shown
+        listen<string>('notification-open', ({ payload }) => navigate(payload)),
+      ]);
+      if (disposed) {
+        subscriptions.forEach(unlisten => unlisten());
+      } else {
+        unlisteners.push(...subscriptions);
+      }
+    })();
+
+    return () => {
+      disposed = true;
+      unlisteners.forEach(unlisten => unlisten());
+    };
+  }, [navigate, addToast]);
+}
+
 function ProjectLanding() {
   const { currentProject, loading, connectionError } = useProjects();
 
@@ -115,6 +169,7 @@ function SetupWizardWrapper() {
 // Component to handle side-effects inside Router context
 function AppContent() {
   usePathPersistence();
+  useAppNotifications();
 
   return (
     <Routes>
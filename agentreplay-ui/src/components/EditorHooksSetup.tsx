@@ -0,0 +1,197 @@
+// Copyright 2025 Sushanth (https://github.com/sushanthpy)
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+import { useCallback, useEffect, useState } from 'react';
+import { AlertTriangle, CheckCircle2, Loader2, RefreshCcw } from 'lucide-react';
+import { Button } from '../../components/ui/button';
+import { cn } from '../../lib/utils';
+
+interface VerifyResult {
+  valid: boolean;
+  verified_files: number;
+  missing_files: number;
+  modified_files: number;
+  missing_entries: number;
+  issues: string[];
+}
+
+interface EditorHookStatus {
+  target_id: string;
+  display_name: string;
+  detected: boolean;
+  installed: boolean;
+  scopes: string[];
+  installed_scope: string | null;
+  installed_at: string | null;
+  verification: VerifyResult | null;
+}
+
+interface EditorHooksInstallResult {
+  execution: { success: boolean; failed_operations: { error: string }[] };
+  verification: VerifyResult;
+}
+
+interface EditorHooksSetupProps {
+  /** Called after hooks were installed or removed */
+  onChange?: () => void;
+  className?: string;
+}
+
+async function invokeCommand<T>(command: string, args?: Record<string, unknown>): Promise<T> {
+  const { invoke } = await import('@tauri-apps/api/core');
+  return invoke<T>(command, args);
+}
+
+/**
+ * Detects coding agents on this machine and installs the Agentreplay
+ * tracing hooks for them (desktop app only).
+ */
+export default function EditorHooksSetup({ onChange, className }: EditorHooksSetupProps) {
+  const [editors, setEditors] = useState<EditorHookStatus[]>([]);
+  const [loading, setLoading] = useState(true);
+  const [busy, setBusy] = useState<string | null>(null);
+  const [error, setError] = useState<string | null>(null);
+
+  const refresh = useCallback(async () => {
+    setLoading(true);
+    try {
+      setEditors(await invokeCommand<EditorHookStatus[]>('detect_editor_hooks'));
+      setError(null);
+    } catch (err) {
+      setError(String(err));
+    } finally {
+      setLoading(false);
+    }
+  }, []);
+
+  useEffect(() => {
+    refresh();
+  }, [refresh]);
+
+  const install = async (targetId: string) => {
+    setBusy(targetId);
+    try {
+      const result = await invokeCommand<EditorHooksInstallResult>('install_editor_hooks', {
+        request: { target_id: targetId },
+      });
+      if (!result.execution.success) {
+        setError(result.execution.failed_operations.map(f => f.error).join('; '));
+      } else if (!result.verification.valid) {
+        setError(result.verification.issues.join('; '));
+      } else {
+        setError(null);
+      }
+      onChange?.();
+    } catch (err) {
+      setError(String(err));
+    } finally {
+      setBusy(null);
+      refresh();
+    }
+  };
+
+  const uninstall = async (targetId: string) => {
+    setBusy(targetId);
+    try {
+      await invokeCommand('uninstall_editor_hooks', { targetId });
+      setError(null);
+      onChange?.();
+    } catch (err) {
+      setError(String(err));
+    } finally {
+      setBusy(null);
+      refresh();
+    }
+  };
+
+  if (loading && editors.length === 0) {
+    return (
+      <div className={cn('flex justify-center py-2', className)}>
+        <Loader2 className="w-4 h-4 animate-spin text-muted-foreground" />
+      </div>
+    );
+  }
+
+  return (
+    <div className={cn('text-left space-y-2', className)}>
+      <div className="flex items-center justify-between">
+        <p className="text-xs font-medium text-foreground">Trace your coding agents</p>
+        <button
+          className="p-1 rounded hover:bg-accent"
+          onClick={refresh}
+          title="Detect again"
+        >
+          <RefreshCcw className="w-3 h-3" />
+        </button>
+      </div>
+      {editors.map(editor => {
+        const healthy = editor.installed && editor.verification?.valid;
+        return (
+          <div
+            key={editor.target_id}
+            className="flex items-center justify-between gap-2 p-2 border rounded"
+          >
+            <div className="min-w-0">
+              <p className="text-xs font-medium text-foreground">{editor.display_name}</p>
+              <p className="text-[10px] flex items-center gap-1">
+                {healthy ? (
+                  <>
+                    <CheckCircle2 className="w-3 h-3 text-success" />
+                    Hooks installed
+                  </>
+                ) : editor.installed ? (
+                  <>
+                    <AlertTriangle className="w-3 h-3 text-warning" />
+                    Hooks changed since install
+                  </>
+                ) : editor.detected ? (
+                  'Detected'
+                ) : (
+                  'Not detected'
+                )}
+              </p>
+            </div>
+            <div className="flex gap-1 shrink-0">
+              {editor.installed && (
+                <Button
+                  size="sm"
+                  variant="ghost"
+                  disabled={busy !== null}
+                  onClick={() => uninstall(editor.target_id)}
+                >
+                  Remove
+                </Button>
+              )}
+              <Button
+                size="sm"
+                variant={healthy ? 'outline' : 'default'}
+                disabled={busy !== null || (!editor.detected && !editor.installed)}
+                onClick={() => install(editor.target_id)}
+              >
+                {busy === editor.target_id ? (
+                  <Loader2 className="w-3 h-3 animate-spin" />
+                ) : editor.installed ? (
+                  healthy ? 'Update' : 'Repair'
+                ) : (
+                  'Install'
+                )}
+              </Button>
+            </div>
+          </div>
+        );
+      })}
+      {error && <p className="text-[10px] text-error break-words">{error}</p>}
+    </div>
+  );
+}
@@ -17,16 +17,22 @@ import { generateId } from '../lib/utils';
 
 export type ToastType = 'success' | 'error' | 'warning' | 'info';
 
+export interface ToastAction {
+  label: string;
+  onClick: () => void;
+}
+
 export interface Toast {
   id: string;
   message: string;
   type: ToastType;
   duration?: number;
+  action?: ToastAction;
 }
 
 interface ToastContextValue {
   toasts: Toast[];
-  addToast: (message: string, type?: ToastType, duration?: number) => void;
+  addToast: (message: string, type?: ToastType, duration?: number, action?: ToastAction) => void;
   removeToast: (id: string) => void;
   // Convenience methods
   success: (message: string) => void;
@@ -46,9 +52,9 @@ export function ToastProvider({ children }: { children: ReactNode }) {
     setToasts((prev) => prev.filter((t) => t.id !== id));
   }, []);
 
-  const addToast = useCallback((message: string, type: ToastType = 'info', duration = DEFAULT_DURATION) => {
+  const addToast = useCallback((message: string, type: ToastType = 'info', duration = DEFAULT_DURATION, action?: ToastAction) => {
     const id = generateId();
-    const toast: Toast = { id, message, type, duration };
+    const toast: Toast = { id, message, type, duration, action };
 
     setToasts((prev) => [...prev, toast]);
 
@@ -115,6 +121,18 @@ function ToastItem({ toast, onRemove }: { toast: Toast; onRemove: (id: string) =
     >
       <span className="text-lg">{icon}</span>
       <span className="flex-1 text-sm font-medium">{toast.message}</span>
+      {toast.action && (
+        <button
+          onClick={(e) => {
+            e.stopPropagation();
+            toast.action?.onClick();
+            onRemove(toast.id);
+          }}
+          className="text-sm font-semibold underline whitespace-nowrap"
+        >
+          {toast.action.label}
+        </button>
+      )}
       <button
         onClick={(e) => {
           e.stopPropagation();
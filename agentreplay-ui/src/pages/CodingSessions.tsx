@@ -37,6 +37,7 @@ import { useProjects } from '../context/project-context';
 import { cn } from '../../lib/utils';
 import { formatDistanceToNow, format } from 'date-fns';
 import Tooltip from '../components/Tooltip';
+import EditorHooksSetup from '../components/EditorHooksSetup';
 
 // Types for coding sessions
 interface CodingSession {
@@ -105,6 +106,9 @@ const agentColors: Record<string, string> = {
   'other': 'bg-gray-500/15 text-gray-600',
 };
 
+const isTauri = typeof window !== 'undefined' &&
+  ('__TAURI__' in window || '__TAURI_INTERNALS__' in window);
+
 const actionIcons: Record<string, React.ReactNode> = {
   read: <Eye className="w-3 h-3" />,
   edit: <FileEdit className="w-3 h-3" />,
@@ -342,6 +346,7 @@ export default function CodingSessions() {
               <Code className="w-8 h-8 mx-auto mb-2 opacity-50" />
               <p>No coding sessions yet</p>
               <p className="text-xs mt-1">Sessions will appear when you use a coding agent</p>
+              {isTauri && <EditorHooksSetup className="mt-4" onChange={fetchSessions} />}
             </div>
           ) : (
             <div className="divide-y divide-border">
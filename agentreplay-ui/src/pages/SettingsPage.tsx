@@ -48,7 +48,8 @@ import {
   Zap,
   Gauge,
   Play,
-  Info
+  Info,
+  Bell
 } from 'lucide-react';
 
 // Service status interface
@@ -515,6 +516,240 @@ function BackupsList({ onMessage, onRefreshNeeded }: {
   );
 }
 
+// BackgroundService Component - runs the ingestion server as an OS service (desktop only)
+function BackgroundService({ onMessage }: {
+  onMessage: (msg: { type: 'success' | 'error'; text: string }) => void;
+}) {
+  const [status, setStatus] = useState<{
+    manager: 'launchd' | 'systemd' | 'task_scheduler';
+    installed: boolean;
+    running: boolean;
+    definition: string;
+  } | null>(null);
+  const [unsupported, setUnsupported] = useState<string | null>(null);
+  const [busy, setBusy] = useState(false);
+
+  const loadStatus = useCallback(async () => {
+    try {
+      const { invoke } = await import('@tauri-apps/api/core');
+      setStatus(await invoke('get_background_service_status'));
+    } catch (error) {
+      setUnsupported(String(error));
+    }
+  }, []);
+
+  useEffect(() => {
+    loadStatus();
+  }, [loadStatus]);
+
+  const toggle = async (enabled: boolean) => {
+    setBusy(true);
+    try {
+      const { invoke } = await import('@tauri-apps/api/core');
+      const next: typeof status = await invoke(
+        enabled ? 'install_background_service' : 'uninstall_background_service'
+      );
+      setStatus(next);
+      onMessage({
+        type: 'success',
+        text: enabled
+          ? next?.running
+            ? 'Background service started'
+            : 'Background service registered; it starts at next login'
+          : 'Background service removed',
+      });
+    } catch (error) {
+      onMessage({ type: 'error', text: `Background service: ${error}` });
+    } finally {
+      setBusy(false);
+    }
+  };
+
+  if (unsupported) {
+    return null;
+  }
+
+  return (
+    <div className="bg-card rounded-lg border border-border p-6">
+      <div className="flex items-center gap-3 mb-4">
+        <Play className="w-6 h-6 text-green-600 dark:text-green-400" />
+        <h2 className="text-xl font-semibold text-foreground">Background Service</h2>
+      </div>
+      <label className="flex items-center gap-3 cursor-pointer">
+        <input
+          type="checkbox"
+          checked={status?.installed ?? false}
+          disabled={busy || !status}
+          onChange={(e) => toggle(e.target.checked)}
+          className="w-5 h-5 rounded border-border bg-background text-blue-600 focus:ring-blue-500"
+        />
+        <span className="text-sm text-muted-foreground">
+          Capture traces when the app is closed (starts the server at login)
+        </span>
+      </label>
+      {status?.installed && (
+        <p className="text-xs text-muted-foreground mt-3">
+          {status.running ? 'Running' : 'Not running'} &middot; {status.definition}
+        </p>
+      )}
+      <p className="text-xs text-muted-foreground mt-3">
+        While the service is running, the app opens the database read-only.
+        Same as <code>agentreplay service install</code>.
+      </p>
+    </div>
+  );
+}
+
+type NotificationSeverity = 'info' | 'warning' | 'critical';
+
+interface NotificationSettings {
+  enabled: boolean;
+  info: string[];
+  warning: string[];
+  critical: string[];
+  eval_failures: boolean;
+  daily_budget_usd: number | null;
+  cooldown_secs: number;
+}
+
+const NOTIFICATION_SINKS: Array<{ id: string; label: string }> = [
+  { id: 'desktop', label: 'Desktop' },
+  { id: 'in_app', label: 'In-app' },
+];
+
+// NotificationSettingsCard Component - eval failure and budget alerts (desktop only)
+function NotificationSettingsCard({ onMessage }: {
+  onMessage: (msg: { type: 'success' | 'error'; text: string }) => void;
+}) {
+  const [appConfig, setAppConfig] = useState<{ notifications: NotificationSettings } | null>(null);
+  const [unsupported, setUnsupported] = useState(false);
+  const [saving, setSaving] = useState(false);
+
+  useEffect(() => {
+    (async () => {
+      try {
+        const { invoke } = await import('@tauri-apps/api/core');
+        setAppConfig(await invoke('get_config'));
+      } catch {
+        setUnsupported(true);
+      }
+    })();
+  }, []);
+
+  const save = async (notifications: NotificationSettings) => {
+    if (!appConfig) return;
+    const next = { ...appConfig, notifications };
+    setAppConfig(next);
+    setSaving(true);
+    try {
+      const { invoke } = await import('@tauri-apps/api/core');
+      await invoke('update_config', { newConfig: next });
+    } catch (error) {
+      onMessage({ type: 'error', text: `Failed to save notification settings: ${error}` });
+    } finally {
+      setSaving(false);
+    }
+  };
+
+  const sendTest = async (severity: NotificationSeverity) => {
+    try {
+      const { invoke } = await import('@tauri-apps/api/core');
+      await invoke('send_test_notification', { severity });
+    } catch (error) {
+      onMessage({ type: 'error', text: `Test notification failed: ${error}` });
+    }
+  };
+
+  if (unsupported || !appConfig) {
+    return null;
+  }
+
+  const settings = appConfig.notifications;
+  const toggleSink = (severity: NotificationSeverity, sink: string, enabled: boolean) => {
+    const sinks = settings[severity].filter(s => s !== sink);
+    save({ ...settings, [severity]: enabled ? [...sinks, sink] : sinks });
+  };
+
+  return (
+    <div className="bg-card rounded-lg border border-border p-6">
+      <div className="flex items-center gap-3 mb-4">
+        <Bell className="w-6 h-6 text-amber-600 dark:text-amber-400" />
+        <h2 className="text-xl font-semibold text-foreground">Notifications</h2>
+        {saving && <Loader2 className="w-4 h-4 animate-spin text-muted-foreground" />}
+      </div>
+      <div className="space-y-4">
+        <label className="flex items-center gap-3 cursor-pointer">
+          <input
+            type="checkbox"
+            checked={settings.enabled}
+            onChange={(e) => save({ ...settings, enabled: e.target.checked })}
+            className="w-5 h-5 rounded border-border bg-background text-blue-600 focus:ring-blue-500"
+          />
+          <span className="text-sm text-muted-foreground">Enable notifications</span>
+        </label>
+        <label className="flex items-center gap-3 cursor-pointer">
+          <input
+            type="checkbox"
+            checked={settings.eval_failures}
+            disabled={!settings.enabled}
+            onChange={(e) => save({ ...settings, eval_failures: e.target.checked })}
+            className="w-5 h-5 rounded border-border bg-background text-blue-600 focus:ring-blue-500"
+          />
+          <span className="text-sm text-muted-foreground">Notify when an evaluation fails a trace</span>
+        </label>
+        <div>
+          <label className="block text-sm font-medium text-foreground mb-2">Daily Budget (USD)</label>
+          <input
+            type="number"
+            min={0}
+            step={0.5}
+            placeholder="No budget"
+            value={settings.daily_budget_usd ?? ''}
+            disabled={!settings.enabled}
+            onChange={(e) => save({
+              ...settings,
+              daily_budget_usd: e.target.value === '' ? null : parseFloat(e.target.value),
+            })}
+            className="w-full px-3 py-2 bg-background border border-border rounded-lg text-foreground"
+          />
+          <p className="text-xs text-muted-foreground mt-1">
+            Warns at 80% and alerts at 100% of today's LLM spend
+          </p>
+        </div>
+        <div>
+          <p className="text-sm font-medium text-foreground mb-2">Delivery by severity</p>
+          <div className="space-y-2">
+            {(['info', 'warning', 'critical'] as NotificationSeverity[]).map(severity => (
+              <div key={severity} className="flex items-center gap-4">
+                <span className="w-20 text-sm capitalize text-foreground">{severity}</span>
+                {NOTIFICATION_SINKS.map(sink => (
+                  <label key={sink.id} className="flex items-center gap-2 cursor-pointer">
+                    <input
+                      type="checkbox"
+                      checked={settings[severity].includes(sink.id)}
+                      disabled={!settings.enabled}
+                      onChange={(e) => toggleSink(severity, sink.id, e.target.checked)}
+                      className="w-4 h-4 rounded border-border bg-background text-blue-600 focus:ring-blue-500"
+                    />
+                    <span className="text-sm text-muted-foreground">{sink.label}</span>
+                  </label>
+                ))}
+                <button
+                  onClick={() => sendTest(severity)}
+                  disabled={!settings.enabled}
+                  className="ml-auto text-xs text-primary hover:underline disabled:opacity-50"
+                >
+                  Send test
+                </button>
+              </div>
+            ))}
+          </div>
+        </div>
+      </div>
+    </div>
+  );
+}
+
 export function SettingsPage() {
   // Read tab from URL query params
   const [searchParams] = useSearchParams();
@@ -1831,6 +2066,9 @@ export function SettingsPage() {
                     </label>
                   </div>
                 </div>
+
+                <BackgroundService onMessage={setMessage} />
+                <NotificationSettingsCard onMessage={setMessage} />
               </div>
             )}
 
@@ -33,6 +33,144 @@ interface Project {
   name: string;
 }
 
+interface SizeRetentionEstimate {
+  disk_bytes: number;
+  max_bytes: number | null;
+  live_traces: number;
+  avg_trace_bytes: number;
+  excess_bytes: number;
+  evictable_traces: number;
+  reclaimable_bytes: number;
+  eviction_cutoff_us: number | null;
+  oldest_trace_us: number | null;
+}
+
+const formatBytes = (bytes: number) => {
+  if (bytes === 0) return '0 B';
+  const k = 1024;
+  const sizes = ['B', 'KB', 'MB', 'GB', 'TB'];
+  const i = Math.floor(Math.log(bytes) / Math.log(k));
+  return parseFloat((bytes / Math.pow(k, i)).toFixed(2)) + ' ' + sizes[i];
+};
+
+// Database size against the size cap, with the cap editable in the desktop app
+function SizeRetentionCard() {
+  const [estimate, setEstimate] = useState<SizeRetentionEstimate | null>(null);
+  const [appConfig, setAppConfig] = useState<{ retention: { max_database_size_mb: number | null } } | null>(null);
+  const [capMb, setCapMb] = useState('');
+
+  const fetchEstimate = async () => {
+    try {
+      const response = await axios.get(`${API_BASE_URL}/api/v1/storage/retention`);
+      setEstimate(response.data);
+    } catch (err) {
+      console.error('Failed to fetch retention estimate:', err);
+    }
+  };
+
+  useEffect(() => {
+    fetchEstimate();
+    (async () => {
+      try {
+        const { invoke } = await import('@tauri-apps/api/core');
+        const config: NonNullable<typeof appConfig> = await invoke('get_config');
+        setAppConfig(config);
+        setCapMb(config.retention.max_database_size_mb?.toString() ?? '');
+      } catch {
+        // Not running in the desktop app: the cap is read-only here
+      }
+    })();
+  }, []);
+
+  const saveCap = async () => {
+    if (!appConfig) return;
+    const mb = capMb === '' ? null : Math.max(0, parseInt(capMb, 10)) || null;
+    const next = { ...appConfig, retention: { ...appConfig.retention, max_database_size_mb: mb } };
+    try {
+      const { invoke } = await import('@tauri-apps/api/core');
+      await invoke('update_config', { newConfig: next });
+      setAppConfig(next);
+      fetchEstimate();
+    } catch (err) {
+      console.error('Failed to save size limit:', err);
+    }
+  };
+
+  if (!estimate) return null;
+
+  const usedPercent = estimate.max_bytes
+    ? Math.min(100, (estimate.disk_bytes / estimate.max_bytes) * 100)
+    : null;
+
+  return (
+    <div className="mb-6 bg-surface rounded-xl border border-border p-6">
+      <div className="flex items-center gap-3 mb-4">
+        <HardDrive className="w-5 h-5 text-primary" />
+        <h2 className="text-lg font-semibold text-textPrimary">Disk Usage</h2>
+      </div>
+      <div className="grid grid-cols-2 md:grid-cols-4 gap-4 text-sm">
+        <div>
+          <p className="text-textTertiary">Database size</p>
+          <p className="text-textPrimary font-medium">
+            {formatBytes(estimate.disk_bytes)}
+            {estimate.max_bytes ? ` of ${formatBytes(estimate.max_bytes)}` : ''}
+          </p>
+        </div>
+        <div>
+          <p className="text-textTertiary">Traces</p>
+          <p className="text-textPrimary font-medium">
+            {estimate.live_traces.toLocaleString()} (~{formatBytes(estimate.avg_trace_bytes)} each)
+          </p>
+        </div>
+        <div>
+          <p className="text-textTertiary">Oldest trace</p>
+          <p className="text-textPrimary font-medium">
+            {estimate.oldest_trace_us
+              ? formatDistanceToNow(new Date(estimate.oldest_trace_us / 1000), { addSuffix: true })
+              : '—'}
+          </p>
+        </div>
+        <div>
+          <p className="text-textTertiary">Reclaimable by eviction</p>
+          <p className="text-textPrimary font-medium">
+            {estimate.evictable_traces > 0
+              ? `${formatBytes(estimate.reclaimable_bytes)} (${estimate.evictable_traces.toLocaleString()} oldest traces)`
+              : 'Nothing to evict'}
+          </p>
+        </div>
+      </div>
+      {usedPercent !== null && (
+        <div className="mt-4 h-2 rounded-full bg-surface-elevated overflow-hidden">
+          <div
+            className={`h-full ${estimate.excess_bytes > 0 ? 'bg-red-500' : usedPercent > 80 ? 'bg-yellow-500' : 'bg-primary'}`}
+            style={{ width: `${usedPercent}%` }}
+          />
+        </div>
+      )}
+      {appConfig && (
+        <div className="mt-4 flex items-center gap-3 text-sm">
+          <label className="text-textSecondary">Size limit (MB)</label>
+          <input
+            type="number"
+            min={0}
+            placeholder="Unlimited"
+            value={capMb}
+            onChange={(e) => setCapMb(e.target.value)}
+            className="w-32 px-3 py-1.5 bg-background border border-border rounded-lg text-textPrimary"
+          />
+          <button
+            onClick={saveCap}
+            className="px-3 py-1.5 bg-primary text-background rounded-lg hover:bg-primary-hover transition-colors"
+          >
+            Save
+          </button>
+          <span className="text-textTertiary">Oldest traces are evicted when the database grows past the limit</span>
+        </div>
+      )}
+    </div>
+  );
+}
+
 export default function Storage() {
   const { projectId } = useParams<{ projectId: string }>();
   const navigate = useNavigate();
@@ -159,6 +297,8 @@ export default function Storage() {
           </div>
         )}
 
+        <SizeRetentionCard />
+
         {/* No Project Selected */}
         {!selectedProjectId && !loading && (
           <div className="bg-surface rounded-xl border border-border p-12 text-center">
@@ -17,7 +17,7 @@
 //! Demonstrates core functionality of the Agentreplay Rust SDK.
 
 use std::collections::HashMap;
-use agentreplay_client::{
+use agentreplay::{
     ClientConfig, CreateGenAITraceOptions, CreateToolTraceOptions, CreateTraceOptions, Message,
     SpanType, UpdateTraceOptions, AgentreplayClient,
 };
@@ -74,7 +74,6 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
                 m.insert("step".into(), serde_json::json!("analyze_request"));
                 m
             }),
-            ..Default::default()
         })
         .await
     {
@@ -128,7 +127,7 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
 
     // 4. Track a tool call
     println!("4. Creating tool trace...");
-    let tool_trace = match client
+    let _tool_trace = match client
         .create_tool_trace(CreateToolTraceOptions {
             agent_id: 1,
             session_id: Some(session_id),
@@ -181,7 +180,6 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
                 );
                 m
             }),
-            ..Default::default()
         })
         .await
     {
@@ -0,0 +1,165 @@
+// Copyright 2025 Sushanth (https://github.com/sushanthpy)
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! `/api/v1/analytics`
+
+use crate::client::{AgentreplayClient, Result};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+/// Parameters for [`AgentreplayClient::timeseries`].
+///
+/// Times are microseconds since the epoch.
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct TimeseriesParams {
+    /// `latency`, `tokens` or `trace_count`
+    pub metric: String,
+    pub start_time: u64,
+    pub end_time: u64,
+    /// `minute`, `hour` or `day` (server default: `hour`)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub granularity: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub project_id: Option<u16>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub agent_id: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub model: Option<String>,
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default)]
+pub struct DataPoint {
+    pub timestamp: u64,
+    pub value: f64,
+    pub count: usize,
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default)]
+pub struct TimeseriesSummary {
+    pub total: f64,
+    pub average: f64,
+    pub min: f64,
+    pub max: f64,
+    pub std_dev: f64,
+    /// `increasing`, `decreasing` or `stable`
+    pub trend: String,
+    pub percent_change: f64,
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default)]
+pub struct Timeseries {
+    pub metric: String,
+    pub granularity: String,
+    pub data_points: Vec<DataPoint>,
+    pub summary: TimeseriesSummary,
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default)]
+pub struct TrendAnalysis {
+    pub metric: String,
+    pub period_days: u32,
+    pub current_value: f64,
+    pub previous_value: f64,
+    pub percent_change: f64,
+    pub trend: String,
+    pub forecast_next_day: Option<f64>,
+    pub forecast_next_week: Option<f64>,
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default)]
+pub struct LatencyStats {
+    pub total_ms: f64,
+    pub count: u32,
+    pub avg_ms: f64,
+    pub min_ms: f64,
+    pub max_ms: f64,
+}
+
+/// Where a session spent its time, by span type.
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default)]
+pub struct LatencyBreakdown {
+    pub total_ms: f64,
+    pub breakdown: HashMap<String, LatencyStats>,
+    pub recommendations: Vec<String>,
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default)]
+pub struct ModelCost {
+    pub cost_usd: f64,
+    pub call_count: u32,
+    pub input_tokens: u32,
+    pub output_tokens: u32,
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default)]
+pub struct TokenUsageSummary {
+    pub total_input_tokens: u32,
+    pub total_output_tokens: u32,
+    pub total_cached_tokens: u32,
+}
+
+/// What a session cost, by model.
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default)]
+pub struct CostBreakdown {
+    pub total_cost_usd: f64,
+    pub by_model: HashMap<String, ModelCost>,
+    pub token_usage: TokenUsageSummary,
+}
+
+impl AgentreplayClient {
+    /// Aggregate `params.metric` into time buckets.
+    pub async fn timeseries(&self, params: &TimeseriesParams) -> Result<Timeseries> {
+        self.get_json("/api/v1/analytics/timeseries", params).await
+    }
+
+    /// Compare a metric over the last `days` days (server default: 7) with
+    /// the period before.
+    pub async fn trends(&self, metric: &str, days: Option<u32>) -> Result<TrendAnalysis> {
+        #[derive(Serialize)]
+        struct Query<'a> {
+            metric: &'a str,
+            #[serde(skip_serializing_if = "Option::is_none")]
+            days: Option<u32>,
+        }
+        self.get_json("/api/v1/analytics/trends", &Query { metric, days })
+            .await
+    }
+
+    /// Latency of a session broken down by span type.
+    pub async fn latency_breakdown(&self, session_id: u64) -> Result<LatencyBreakdown> {
+        self.get_json(
+            "/api/v1/analytics/latency-breakdown",
+            &[("session_id", session_id)],
+        )
+        .await
+    }
+
+    /// Cost of a session broken down by model.
+    pub async fn cost_breakdown(&self, session_id: u64) -> Result<CostBreakdown> {
+        self.get_json(
+            "/api/v1/analytics/cost-breakdown",
+            &[("session_id", session_id)],
+        )
+        .await
+    }
+}
@@ -0,0 +1,367 @@
+// Copyright 2025 Sushanth (https://github.com/sushanthpy)
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! `/api/v1/evals/runs` and `/api/v1/evals/datasets`
+
+use super::Ack;
+use crate::client::{AgentreplayClient, Result};
+use crate::pagination::Page;
+use reqwest::Method;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+// ============================================================================
+// Eval runs
+// ============================================================================
+
+/// Filters for [`AgentreplayClient::list_eval_runs`].
+#[derive(Debug, Clone, Serialize)]
+pub struct EvalRunListParams {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub dataset_id: Option<String>,
+    /// `running`, `completed`, `failed` or `stopped`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub status: Option<String>,
+    /// Substring of the model name
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub model: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub agent_id: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub start_time: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub end_time: Option<u64>,
+    /// Comma-separated tags
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub tags: Option<String>,
+    /// `started_at`, `completed_at`, `pass_rate` or `name`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub sort_by: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub sort_order: Option<String>,
+    /// 1-based page number
+    pub page: usize,
+    pub page_size: usize,
+}
+
+impl Default for EvalRunListParams {
+    fn default() -> Self {
+        Self {
+            dataset_id: None,
+            status: None,
+            model: None,
+            agent_id: None,
+            start_time: None,
+            end_time: None,
+            tags: None,
+            sort_by: None,
+            sort_order: None,
+            page: 1,
+            page_size: 20,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default)]
+pub struct EvalRun {
+    pub id: String,
+    pub dataset_id: String,
+    pub name: String,
+    pub agent_id: String,
+    pub model: String,
+    pub schema_version: String,
+    pub status: String,
+    pub started_at: u64,
+    pub completed_at: Option<u64>,
+    pub test_case_count: usize,
+    pub passed_count: usize,
+    pub failed_count: usize,
+    pub pass_rate: f64,
+}
+
+/// Outcome of one test case trial within a run.
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default)]
+pub struct EvalRunResult {
+    pub test_case_id: String,
+    pub trial_id: u32,
+    pub seed: Option<u64>,
+    pub trace_id: Option<String>,
+    pub eval_metrics: HashMap<String, f64>,
+    pub grader_results: Vec<serde_json::Value>,
+    pub overall: Option<serde_json::Value>,
+    pub passed: bool,
+    pub error: Option<String>,
+    pub timestamp_us: u64,
+    pub cost_usd: Option<f64>,
+    pub latency_ms: Option<u64>,
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default)]
+pub struct EvalRunDetail {
+    pub id: String,
+    pub dataset_id: String,
+    pub name: String,
+    pub agent_id: String,
+    pub model: String,
+    pub schema_version: String,
+    pub status: String,
+    pub started_at: u64,
+    pub completed_at: Option<u64>,
+    pub results: Vec<EvalRunResult>,
+    pub task_aggregates: Vec<serde_json::Value>,
+    pub aggregated_metrics: HashMap<String, f64>,
+    pub passed_count: usize,
+    pub failed_count: usize,
+    pub pass_rate: f64,
+    pub config: HashMap<String, String>,
+}
+
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct CreateEvalRun {
+    pub dataset_id: String,
+    pub name: String,
+    pub agent_id: String,
+    pub model: String,
+    pub config: HashMap<String, String>,
+}
+
+/// A test case result reported to a run.
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct EvalRunResultInput {
+    pub test_case_id: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub trial_id: Option<u32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub seed: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub trace_id: Option<String>,
+    pub eval_metrics: HashMap<String, f64>,
+    pub grader_results: Vec<serde_json::Value>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub overall: Option<serde_json::Value>,
+    pub passed: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub cost_usd: Option<f64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub latency_ms: Option<u64>,
+}
+
+#[derive(Debug, Deserialize)]
+struct EvalRunsResponse {
+    #[serde(default)]
+    runs: Vec<EvalRun>,
+    #[serde(default)]
+    total: usize,
+}
+
+// ============================================================================
+// Datasets
+// ============================================================================
+
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default)]
+pub struct Dataset {
+    pub id: String,
+    pub name: String,
+    pub description: String,
+    pub test_case_count: usize,
+    pub created_at: u64,
+    pub updated_at: u64,
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default)]
+pub struct TestCase {
+    pub id: String,
+    pub input: String,
+    pub expected_output: Option<String>,
+    pub metadata: HashMap<String, String>,
+    pub task_definition_v2: Option<serde_json::Value>,
+    pub reference_trajectory: Option<serde_json::Value>,
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default)]
+pub struct DatasetDetail {
+    pub id: String,
+    pub name: String,
+    pub description: String,
+    pub test_cases: Vec<TestCase>,
+    pub created_at: u64,
+    pub updated_at: u64,
+}
+
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct TestCaseInput {
+    pub input: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub expected_output: Option<String>,
+    pub metadata: HashMap<String, String>,
+}
+
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct CreateDataset {
+    pub name: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+    pub test_cases: Vec<TestCaseInput>,
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default)]
+pub struct CreatedDataset {
+    pub dataset_id: String,
+    pub name: String,
+    pub description: String,
+}
+
+/// An example appended to an existing dataset.
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct ExampleInput {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub example_id: Option<String>,
+    pub input: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub expected_output: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub context: Option<String>,
+    pub metadata: HashMap<String, String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct DatasetsResponse {
+    #[serde(default)]
+    datasets: Vec<Dataset>,
+}
+
+#[derive(Debug, Deserialize)]
+struct AddExamplesResponse {
+    #[serde(default)]
+    added_count: usize,
+}
+
+impl AgentreplayClient {
+    /// List eval runs matching `params`, one page at a time.
+    pub async fn list_eval_runs(
+        &self,
+        params: &EvalRunListParams,
+    ) -> Result<Page<EvalRun, EvalRunListParams>> {
+        let response: EvalRunsResponse = self.get_json("/api/v1/evals/runs", params).await?;
+        let seen = params.page.max(1) * params.page_size;
+        let next =
+            (!response.runs.is_empty() && seen < response.total).then(|| EvalRunListParams {
+                page: params.page.max(1) + 1,
+                ..params.clone()
+            });
+        Ok(Page {
+            items: response.runs,
+            total: response.total,
+            next,
+        })
+    }
+
+    /// Start an eval run against a dataset.
+    pub async fn create_eval_run(&self, run: &CreateEvalRun) -> Result<EvalRunDetail> {
+        self.send_json(Method::POST, "/api/v1/evals/runs", run)
+            .await
+    }
+
+    /// Get a run with all its results.
+    pub async fn get_eval_run(&self, run_id: &str) -> Result<EvalRunDetail> {
+        self.send_empty(Method::GET, &format!("/api/v1/evals/runs/{}", run_id))
+            .await
+    }
+
+    /// Report the result of one test case; returns the updated run.
+    pub async fn add_eval_run_result(
+        &self,
+        run_id: &str,
+        result: &EvalRunResultInput,
+    ) -> Result<EvalRunDetail> {
+        let path = format!("/api/v1/evals/runs/{}/results", run_id);
+        self.send_json(Method::POST, &path, result).await
+    }
+
+    /// Move a run to `completed`, `failed` or `stopped`.
+    pub async fn update_eval_run_status(
+        &self,
+        run_id: &str,
+        status: &str,
+    ) -> Result<EvalRunDetail> {
+        let path = format!("/api/v1/evals/runs/{}/status", run_id);
+        self.send_json(
+            Method::POST,
+            &path,
+            &serde_json::json!({ "status": status }),
+        )
+        .await
+    }
+
+    pub async fn delete_eval_run(&self, run_id: &str) -> Result<Ack> {
+        self.send_empty(Method::DELETE, &format!("/api/v1/evals/runs/{}", run_id))
+            .await
+    }
+
+    /// List all eval datasets.
+    pub async fn list_datasets(&self) -> Result<Vec<Dataset>> {
+        let response: DatasetsResponse = self
+            .send_empty(Method::GET, "/api/v1/evals/datasets")
+            .await?;
+        Ok(response.datasets)
+    }
+
+    pub async fn create_dataset(&self, dataset: &CreateDataset) -> Result<CreatedDataset> {
+        self.send_json(Method::POST, "/api/v1/evals/datasets", dataset)
+            .await
+    }
+
+    /// Get a dataset with its test cases.
+    pub async fn get_dataset(&self, dataset_id: &str) -> Result<DatasetDetail> {
+        self.send_empty(
+            Method::GET,
+            &format!("/api/v1/evals/datasets/{}", dataset_id),
+        )
+        .await
+    }
+
+    pub async fn delete_dataset(&self, dataset_id: &str) -> Result<Ack> {
+        self.send_empty(
+            Method::DELETE,
+            &format!("/api/v1/evals/datasets/{}", dataset_id),
+        )
+        .await
+    }
+
+    /// Append examples to a dataset; returns how many were added.
+    pub async fn add_dataset_examples(
+        &self,
+        dataset_id: &str,
+        examples: &[ExampleInput],
+    ) -> Result<usize> {
+        let path = format!("/api/v1/evals/datasets/{}/examples", dataset_id);
+        let response: AddExamplesResponse = self
+            .send_json(
+                Method::POST,
+                &path,
+                &serde_json::json!({ "examples": examples }),
+            )
+            .await?;
+        Ok(response.added_count)
+    }
+}
@@ -0,0 +1,193 @@
+// Copyright 2025 Sushanth (https://github.com/sushanthpy)
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! `/api/v1/experiments`
+
+use super::Ack;
+use crate::client::{AgentreplayClient, Result};
+use reqwest::Method;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default)]
+pub struct Variant {
+    pub id: String,
+    pub name: String,
+    pub description: String,
+    pub config: HashMap<String, serde_json::Value>,
+}
+
+/// An A/B experiment over prompt or model variants.
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default)]
+pub struct Experiment {
+    pub id: String,
+    pub name: String,
+    pub description: String,
+    pub variants: Vec<Variant>,
+    /// `draft`, `running`, `paused`, `completed` or `stopped`
+    pub status: String,
+    /// Share of traffic per variant ID
+    pub traffic_split: HashMap<String, f64>,
+    pub metrics: Vec<String>,
+    pub start_time: Option<u64>,
+    pub end_time: Option<u64>,
+    pub created_at: u64,
+    pub updated_at: u64,
+    pub auto_stop: Option<serde_json::Value>,
+}
+
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct VariantInput {
+    pub name: String,
+    pub description: String,
+    pub config: HashMap<String, serde_json::Value>,
+}
+
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct CreateExperiment {
+    pub name: String,
+    pub description: String,
+    pub variants: Vec<VariantInput>,
+    pub metrics: Vec<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub auto_stop: Option<serde_json::Value>,
+}
+
+/// Changes to an experiment; unset fields are left as they are.
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct UpdateExperiment {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub traffic_split: Option<HashMap<String, f64>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub auto_stop: Option<serde_json::Value>,
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default)]
+pub struct MetricStats {
+    pub mean: f64,
+    pub std_dev: f64,
+    pub min: f64,
+    pub max: f64,
+    pub count: usize,
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default)]
+pub struct VariantStats {
+    pub variant_id: String,
+    pub variant_name: String,
+    pub sample_count: usize,
+    pub metrics: HashMap<String, MetricStats>,
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default)]
+pub struct ExperimentStats {
+    pub experiment_id: String,
+    pub variant_stats: HashMap<String, VariantStats>,
+    pub winner: Option<String>,
+    pub confidence: Option<f64>,
+}
+
+#[derive(Debug, Deserialize)]
+struct ExperimentsResponse {
+    #[serde(default)]
+    experiments: Vec<Experiment>,
+}
+
+impl AgentreplayClient {
+    /// List experiments, optionally only those with `status`.
+    pub async fn list_experiments(&self, status: Option<&str>) -> Result<Vec<Experiment>> {
+        let query: Vec<(&str, &str)> = status.map(|s| ("status", s)).into_iter().collect();
+        let response: ExperimentsResponse = self.get_json("/api/v1/experiments", &query).await?;
+        Ok(response.experiments)
+    }
+
+    pub async fn create_experiment(&self, experiment: &CreateExperiment) -> Result<Experiment> {
+        self.send_json(Method::POST, "/api/v1/experiments", experiment)
+            .await
+    }
+
+    pub async fn get_experiment(&self, experiment_id: &str) -> Result<Experiment> {
+        self.send_empty(
+            Method::GET,
+            &format!("/api/v1/experiments/{}", experiment_id),
+        )
+        .await
+    }
+
+    pub async fn update_experiment(
+        &self,
+        experiment_id: &str,
+        update: &UpdateExperiment,
+    ) -> Result<Experiment> {
+        let path = format!("/api/v1/experiments/{}", experiment_id);
+        self.send_json(Method::PUT, &path, update).await
+    }
+
+    pub async fn delete_experiment(&self, experiment_id: &str) -> Result<Ack> {
+        self.send_empty(
+            Method::DELETE,
+            &format!("/api/v1/experiments/{}", experiment_id),
+        )
+        .await
+    }
+
+    /// Start routing traffic to the variants; `traffic_split` maps variant
+    /// IDs to shares that sum to 1.
+    pub async fn start_experiment(
+        &self,
+        experiment_id: &str,
+        traffic_split: &HashMap<String, f64>,
+    ) -> Result<Experiment> {
+        let path = format!("/api/v1/experiments/{}/start", experiment_id);
+        let body = serde_json::json!({ "traffic_split": traffic_split });
+        self.send_json(Method::POST, &path, &body).await
+    }
+
+    pub async fn stop_experiment(&self, experiment_id: &str) -> Result<Experiment> {
+        let path = format!("/api/v1/experiments/{}/stop", experiment_id);
+        self.send_empty(Method::POST, &path).await
+    }
+
+    /// Record the metrics a variant achieved on one trace.
+    pub async fn record_experiment_result(
+        &self,
+        experiment_id: &str,
+        variant_id: &str,
+        trace_id: &str,
+        metrics: &HashMap<String, f64>,
+    ) -> Result<serde_json::Value> {
+        let path = format!("/api/v1/experiments/{}/results", experiment_id);
+        let body = serde_json::json!({
+            "variant_id": variant_id,
+            "trace_id": trace_id,
+            "metrics": metrics,
+        });
+        self.send_json(Method::POST, &path, &body).await
+    }
+
+    /// Per-variant metric statistics and the current winner, if any.
+    pub async fn experiment_stats(&self, experiment_id: &str) -> Result<ExperimentStats> {
+        let path = format!("/api/v1/experiments/{}/stats", experiment_id);
+        self.send_empty(Method::GET, &path).await
+    }
+}
@@ -0,0 +1,43 @@
+// Copyright 2025 Sushanth (https://github.com/sushanthpy)
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Typed bindings for the management API.
+//!
+//! Each module adds methods to [`AgentreplayClient`](crate::AgentreplayClient)
+//! for one group of REST endpoints, with request and response types that
+//! mirror the server's JSON. Response types default missing fields, so they
+//! keep working against servers that omit or add fields.
+
+mod analytics;
+mod evals;
+mod experiments;
+mod prompts;
+mod sessions;
+mod traces;
+
+pub use analytics::*;
+pub use evals::*;
+pub use experiments::*;
+pub use prompts::*;
+pub use sessions::*;
+pub use traces::*;
+
+/// `{"success": ..., "message": ...}` acknowledgement returned by deletes
+/// and state changes.
+#[derive(Debug, Clone, Default, serde::Deserialize)]
+#[serde(default)]
+pub struct Ack {
+    pub success: bool,
+    pub message: Option<String>,
+}
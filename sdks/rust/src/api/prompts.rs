@@ -0,0 +1,162 @@
+// Copyright 2025 Sushanth (https://github.com/sushanthpy)
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! `/api/v1/prompts`
+
+use super::Ack;
+use crate::client::{AgentreplayClient, Result};
+use reqwest::Method;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default)]
+pub struct Prompt {
+    pub id: String,
+    pub name: String,
+    pub description: String,
+    pub template: String,
+    /// Variables referenced by the template
+    pub variables: Vec<String>,
+    pub tags: Vec<String>,
+    pub version: u32,
+    pub created_at: u64,
+    pub updated_at: u64,
+    pub created_by: String,
+}
+
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct CreatePrompt {
+    pub name: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+    pub template: String,
+    pub tags: Vec<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub metadata: Option<HashMap<String, serde_json::Value>>,
+}
+
+/// Changes to a prompt; unset fields are left as they are. Changing the
+/// template creates a new version.
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct UpdatePrompt {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub template: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub tags: Option<Vec<String>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub metadata: Option<HashMap<String, serde_json::Value>>,
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default)]
+pub struct PromptVersion {
+    pub version: u32,
+    pub template: String,
+    pub created_at: u64,
+    pub created_by: String,
+    pub change_summary: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct PromptsResponse {
+    #[serde(default)]
+    prompts: Vec<Prompt>,
+}
+
+#[derive(Debug, Deserialize)]
+struct VersionsResponse {
+    #[serde(default)]
+    versions: Vec<PromptVersion>,
+}
+
+#[derive(Debug, Deserialize)]
+struct RenderResponse {
+    rendered: String,
+}
+
+impl AgentreplayClient {
+    /// List prompts, optionally filtered by tag or a search string.
+    pub async fn list_prompts(
+        &self,
+        tag: Option<&str>,
+        search: Option<&str>,
+    ) -> Result<Vec<Prompt>> {
+        #[derive(Serialize)]
+        struct Query<'a> {
+            #[serde(skip_serializing_if = "Option::is_none")]
+            tag: Option<&'a str>,
+            #[serde(skip_serializing_if = "Option::is_none")]
+            search: Option<&'a str>,
+        }
+        let response: PromptsResponse = self
+            .get_json("/api/v1/prompts", &Query { tag, search })
+            .await?;
+        Ok(response.prompts)
+    }
+
+    pub async fn create_prompt(&self, prompt: &CreatePrompt) -> Result<Prompt> {
+        self.send_json(Method::POST, "/api/v1/prompts", prompt)
+            .await
+    }
+
+    pub async fn get_prompt(&self, prompt_id: &str) -> Result<Prompt> {
+        self.send_empty(Method::GET, &format!("/api/v1/prompts/{}", prompt_id))
+            .await
+    }
+
+    pub async fn update_prompt(&self, prompt_id: &str, update: &UpdatePrompt) -> Result<Prompt> {
+        let path = format!("/api/v1/prompts/{}", prompt_id);
+        self.send_json(Method::PUT, &path, update).await
+    }
+
+    pub async fn delete_prompt(&self, prompt_id: &str) -> Result<Ack> {
+        self.send_empty(Method::DELETE, &format!("/api/v1/prompts/{}", prompt_id))
+            .await
+    }
+
+    /// Render a prompt's current template with `variables`.
+    pub async fn render_prompt(
+        &self,
+        prompt_id: &str,
+        variables: &HashMap<String, serde_json::Value>,
+    ) -> Result<String> {
+        let path = format!("/api/v1/prompts/{}/render", prompt_id);
+        let response: RenderResponse = self
+            .send_json(
+                Method::POST,
+                &path,
+                &serde_json::json!({ "variables": variables }),
+            )
+            .await?;
+        Ok(response.rendered)
+    }
+
+    /// Version history of a prompt.
+    pub async fn prompt_versions(&self, prompt_id: &str) -> Result<Vec<PromptVersion>> {
+        let path = format!("/api/v1/prompts/{}/versions", prompt_id);
+        let response: VersionsResponse = self.send_empty(Method::GET, &path).await?;
+        Ok(response.versions)
+    }
+
+    /// Restore an earlier version of a prompt's template.
+    pub async fn rollback_prompt(&self, prompt_id: &str, version: u32) -> Result<Prompt> {
+        let path = format!("/api/v1/prompts/{}/rollback/{}", prompt_id, version);
+        self.send_empty(Method::POST, &path).await
+    }
+}
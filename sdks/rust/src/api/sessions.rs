@@ -0,0 +1,120 @@
+// Copyright 2025 Sushanth (https://github.com/sushanthpy)
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! `/api/v1/sessions`
+
+use crate::client::{AgentreplayClient, Result};
+use crate::pagination::{next_offset, Page};
+use reqwest::Method;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+/// Filters for [`AgentreplayClient::list_sessions`].
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct SessionListParams {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub start_ts: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub end_ts: Option<u64>,
+    /// Page size (server default: 100)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub limit: Option<usize>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub offset: Option<usize>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub project_id: Option<u16>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub search: Option<String>,
+    /// `active` or `ended`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub status: Option<String>,
+    /// `recent`, `cost`, `duration` or `tokens`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub sort: Option<String>,
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default)]
+pub struct SessionInfo {
+    pub session_id: u64,
+    pub project_id: u16,
+    pub agent_id: u64,
+    pub started_at: u64,
+    pub last_message_at: u64,
+    pub message_count: usize,
+    pub total_tokens: u32,
+    pub total_duration_ms: u32,
+    pub trace_ids: Vec<String>,
+    pub status: String,
+    pub duration_ms: Option<u64>,
+    pub cost_usd: Option<f64>,
+    pub error_count: Option<u32>,
+    pub models: Vec<String>,
+    pub eval_scores: BTreeMap<String, f64>,
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default)]
+pub struct SessionTrace {
+    pub trace_id: String,
+    pub span_id: String,
+    pub timestamp_us: u64,
+    pub span_type: u32,
+    pub duration_us: u32,
+    pub token_count: u32,
+    pub status: String,
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default)]
+pub struct SessionDetail {
+    pub session: SessionInfo,
+    pub traces: Vec<SessionTrace>,
+}
+
+#[derive(Debug, Deserialize)]
+struct SessionsResponse {
+    #[serde(default)]
+    sessions: Vec<SessionInfo>,
+    #[serde(default)]
+    total: usize,
+}
+
+impl AgentreplayClient {
+    /// List sessions matching `params`, one page at a time.
+    pub async fn list_sessions(
+        &self,
+        params: &SessionListParams,
+    ) -> Result<Page<SessionInfo, SessionListParams>> {
+        let response: SessionsResponse = self.get_json("/api/v1/sessions", params).await?;
+        let offset = params.offset.unwrap_or(0);
+        let next = next_offset(offset, response.sessions.len(), response.total).map(|offset| {
+            SessionListParams {
+                offset: Some(offset),
+                ..params.clone()
+            }
+        });
+        Ok(Page {
+            items: response.sessions,
+            total: response.total,
+            next,
+        })
+    }
+
+    /// Get a session with its spans.
+    pub async fn get_session(&self, session_id: u64) -> Result<SessionDetail> {
+        self.send_empty(Method::GET, &format!("/api/v1/sessions/{}", session_id))
+            .await
+    }
+}
@@ -0,0 +1,149 @@
+// Copyright 2025 Sushanth (https://github.com/sushanthpy)
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! `/api/v1/traces`
+
+use crate::client::{AgentreplayClient, Result};
+use crate::pagination::{next_offset, Page};
+use serde::{Deserialize, Serialize};
+
+/// Filters for [`AgentreplayClient::list_traces`].
+///
+/// Timestamps are microseconds since the epoch.
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct TraceListParams {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub start_ts: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub end_ts: Option<u64>,
+    /// Page size (server default: 100)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub limit: Option<usize>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub offset: Option<usize>,
+    /// Opaque cursor from a previous page; takes precedence over `offset`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub cursor: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub project_id: Option<u16>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub session_id: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub agent_id: Option<u64>,
+    /// `dev`, `staging`, `prod` or `test`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub environment: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub has_errors: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub min_latency_ms: Option<f64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub max_latency_ms: Option<f64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub min_cost: Option<f64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub max_cost: Option<f64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub full_text_search: Option<String>,
+    /// `timestamp`, `duration`, `cost` or `tokens`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub sort_by: Option<String>,
+    /// `asc` or `desc`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub sort_order: Option<String>,
+    /// Comma-separated fields to return (default: all)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub fields: Option<String>,
+    /// `metadata`, `preview` or `full`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub hydration: Option<String>,
+}
+
+/// A span as returned by the trace listing.
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default)]
+pub struct TraceSummary {
+    pub trace_id: String,
+    pub span_id: String,
+    pub parent_span_id: Option<String>,
+    pub tenant_id: u64,
+    pub project_id: u16,
+    pub agent_id: u64,
+    pub agent_name: String,
+    pub session_id: u64,
+    pub span_type: String,
+    pub environment: String,
+    pub timestamp_us: u64,
+    pub duration_us: u64,
+    pub token_count: u32,
+    pub status: Option<String>,
+    pub metadata: Option<serde_json::Value>,
+    pub provider: Option<String>,
+    pub model: Option<String>,
+    pub cost: Option<f64>,
+    pub confidence: Option<f32>,
+    pub route: Option<String>,
+    pub input_preview: Option<String>,
+    pub output_preview: Option<String>,
+    pub display_name: Option<String>,
+    pub tags: Option<Vec<String>>,
+    pub enrichment: Option<serde_json::Value>,
+}
+
+#[derive(Debug, Deserialize)]
+struct TracesResponse {
+    #[serde(default)]
+    traces: Vec<TraceSummary>,
+    #[serde(default)]
+    total: usize,
+    #[serde(default)]
+    offset: usize,
+    next_cursor: Option<String>,
+}
+
+impl AgentreplayClient {
+    /// List traces matching `params`, one page at a time.
+    ///
+    /// Timestamp-ordered listings continue by cursor, other orders by
+    /// offset. Archived ranges may be restored in the background, in which
+    /// case the page only covers hot data.
+    pub async fn list_traces(
+        &self,
+        params: &TraceListParams,
+    ) -> Result<Page<TraceSummary, TraceListParams>> {
+        let response: TracesResponse = self.get_json("/api/v1/traces", params).await?;
+
+        let next =
+            match response.next_cursor {
+                Some(cursor) => Some(TraceListParams {
+                    cursor: Some(cursor),
+                    offset: None,
+                    ..params.clone()
+                }),
+                // Cursor walks end when the server stops handing out cursors
+                None if params.cursor.is_some() => None,
+                None => next_offset(response.offset, response.traces.len(), response.total).map(
+                    |offset| TraceListParams {
+                        offset: Some(offset),
+                        ..params.clone()
+                    },
+                ),
+            };
+        Ok(Page {
+            items: response.traces,
+            total: response.total,
+            next,
+        })
+    }
+}
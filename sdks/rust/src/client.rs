@@ -16,8 +16,11 @@
 //!
 //! High-performance async client for the Agentreplay observability platform.
 
+use crate::transport::{self, RetryPolicy};
 use crate::types::*;
-use reqwest::Client as HttpClient;
+use reqwest::{Client as HttpClient, Method, RequestBuilder};
+use serde::de::DeserializeOwned;
+use serde::Serialize;
 use serde_json::json;
 use std::collections::HashMap;
 use std::sync::atomic::{AtomicI64, Ordering};
@@ -34,12 +37,70 @@ pub enum AgentreplayError {
     SerializationError(#[from] serde_json::Error),
 
     #[error("API error ({status}): {message}")]
-    ApiError { status: u16, message: String },
+    ApiError {
+        status: u16,
+        message: String,
+        /// Machine-readable error code, e.g. `not_found` or `quota_exceeded`
+        code: Option<String>,
+        /// Server-side correlation ID, for matching the failure in server logs
+        correlation_id: Option<String>,
+    },
 
     #[error("Invalid feedback value: must be -1, 0, or 1")]
     InvalidFeedback,
 }
 
+impl AgentreplayError {
+    /// HTTP status of an API error.
+    pub fn status(&self) -> Option<u16> {
+        match self {
+            AgentreplayError::ApiError { status, .. } => Some(*status),
+            AgentreplayError::RequestError(e) => e.status().map(|s| s.as_u16()),
+            _ => None,
+        }
+    }
+
+    /// Machine-readable code of an API error.
+    pub fn code(&self) -> Option<&str> {
+        match self {
+            AgentreplayError::ApiError { code, .. } => code.as_deref(),
+            _ => None,
+        }
+    }
+
+    pub fn is_not_found(&self) -> bool {
+        self.status() == Some(404)
+    }
+
+    /// Build an API error from a non-success response.
+    ///
+    /// Problem-details bodies (`code`, `detail`, `correlation_id`) are
+    /// parsed; anything else is kept verbatim as the message.
+    async fn from_response(response: reqwest::Response) -> Self {
+        let status = response.status().as_u16();
+        let header_id = response
+            .headers()
+            .get("x-request-id")
+            .and_then(|v| v.to_str().ok())
+            .map(str::to_string);
+        let text = response.text().await.unwrap_or_default();
+
+        let body: Option<serde_json::Value> = serde_json::from_str(&text).ok();
+        let field = |name: &str| {
+            body.as_ref()
+                .and_then(|b| b.get(name))
+                .and_then(|v| v.as_str())
+                .map(str::to_string)
+        };
+        AgentreplayError::ApiError {
+            status,
+            message: field("detail").or_else(|| field("error")).unwrap_or(text),
+            code: field("code"),
+            correlation_id: field("correlation_id").or(header_id),
+        }
+    }
+}
+
 /// Result type for Agentreplay operations.
 pub type Result<T> = std::result::Result<T, AgentreplayError>;
 
@@ -56,6 +117,12 @@ pub struct ClientConfig {
     pub agent_id: i64,
     /// Request timeout (default: 30 seconds)
     pub timeout: Duration,
+    /// API key sent as `X-API-Key`
+    pub api_key: Option<String>,
+    /// Token sent as `Authorization: Bearer ...`
+    pub bearer_token: Option<String>,
+    /// Retries for transient failures (default: 3 with exponential backoff)
+    pub retry: RetryPolicy,
 }
 
 impl ClientConfig {
@@ -67,6 +134,9 @@ impl ClientConfig {
             project_id: 0,
             agent_id: 1,
             timeout: Duration::from_secs(30),
+            api_key: None,
+            bearer_token: None,
+            retry: RetryPolicy::default(),
         }
     }
 
@@ -87,6 +157,24 @@ impl ClientConfig {
         self.timeout = timeout;
         self
     }
+
+    /// Authenticate with an API key.
+    pub fn with_api_key(mut self, api_key: impl Into<String>) -> Self {
+        self.api_key = Some(api_key.into());
+        self
+    }
+
+    /// Authenticate with a bearer token (JWT or OIDC access token).
+    pub fn with_bearer_token(mut self, token: impl Into<String>) -> Self {
+        self.bearer_token = Some(token.into());
+        self
+    }
+
+    /// Set the retry policy.
+    pub fn with_retry_policy(mut self, retry: RetryPolicy) -> Self {
+        self.retry = retry;
+        self
+    }
 }
 
 /// Agentreplay client for Rust applications.
@@ -157,41 +245,105 @@ impl AgentreplayClient {
     }
 
     /// Make an HTTP request to the Agentreplay server.
-    async fn request<T: serde::de::DeserializeOwned>(
+    async fn request<T: DeserializeOwned>(
         &self,
         method: reqwest::Method,
         path: &str,
         body: Option<serde_json::Value>,
         params: Option<&[(&str, String)]>,
     ) -> Result<T> {
-        let url = format!("{}{}", self.config.url.trim_end_matches('/'), path);
-
-        let mut request = self.http_client.request(method, &url);
-        request = request
-            .header("Content-Type", "application/json")
-            .header("X-Tenant-ID", self.config.tenant_id.to_string());
-
+        let mut request = self.builder(method.clone(), path);
         if let Some(params) = params {
             request = request.query(params);
         }
-
         if let Some(body) = body {
             request = request.json(&body);
         }
+        self.execute(&method, request).await
+    }
 
-        let response = request.send().await?;
-        let status = response.status();
+    /// GET `path` with `query` as URL parameters.
+    pub(crate) async fn get_json<Q, T>(&self, path: &str, query: &Q) -> Result<T>
+    where
+        Q: Serialize + ?Sized,
+        T: DeserializeOwned,
+    {
+        let request = self.builder(Method::GET, path).query(query);
+        self.execute(&Method::GET, request).await
+    }
+
+    /// Send `body` as JSON to `path`.
+    pub(crate) async fn send_json<B, T>(&self, method: Method, path: &str, body: &B) -> Result<T>
+    where
+        B: Serialize + ?Sized,
+        T: DeserializeOwned,
+    {
+        let request = self.builder(method.clone(), path).json(body);
+        self.execute(&method, request).await
+    }
 
-        if !status.is_success() {
-            let message = response.text().await.unwrap_or_default();
-            return Err(AgentreplayError::ApiError {
-                status: status.as_u16(),
-                message,
-            });
+    /// Request `path` without a body.
+    pub(crate) async fn send_empty<T: DeserializeOwned>(
+        &self,
+        method: Method,
+        path: &str,
+    ) -> Result<T> {
+        let request = self.builder(method.clone(), path);
+        self.execute(&method, request).await
+    }
+
+    fn builder(&self, method: Method, path: &str) -> RequestBuilder {
+        let url = format!("{}{}", self.config.url.trim_end_matches('/'), path);
+        let mut request = self
+            .http_client
+            .request(method, &url)
+            .header("Content-Type", "application/json")
+            .header("X-Tenant-ID", self.config.tenant_id.to_string());
+        if let Some(api_key) = &self.config.api_key {
+            request = request.header("X-API-Key", api_key);
+        }
+        if let Some(token) = &self.config.bearer_token {
+            request = request.bearer_auth(token);
         }
+        request
+    }
 
-        let result = response.json().await?;
-        Ok(result)
+    /// Send `request`, retrying transient failures per the retry policy.
+    async fn execute<T: DeserializeOwned>(
+        &self,
+        method: &Method,
+        request: RequestBuilder,
+    ) -> Result<T> {
+        let policy = &self.config.retry;
+        let mut attempt = 0;
+        let mut pending = Some(request);
+
+        while let Some(current) = pending.take() {
+            // Keep a copy for the next attempt; streaming bodies can't be replayed
+            pending = current.try_clone();
+            let can_retry = pending.is_some() && attempt < policy.max_retries;
+
+            let wait = match current.send().await {
+                Ok(response) if response.status().is_success() => {
+                    return Ok(response.json().await?);
+                }
+                Ok(response) => {
+                    if !(can_retry && policy.retries_status(method, response.status())) {
+                        return Err(AgentreplayError::from_response(response).await);
+                    }
+                    policy.backoff(attempt, transport::retry_after(&response))
+                }
+                Err(e) => {
+                    if !(can_retry && policy.retries_error(method, &e)) {
+                        return Err(e.into());
+                    }
+                    policy.backoff(attempt, None)
+                }
+            };
+            tokio::time::sleep(wait).await;
+            attempt += 1;
+        }
+        unreachable!("the last attempt always returns")
     }
 
     /// Create a new trace span.
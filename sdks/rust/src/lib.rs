@@ -71,9 +71,43 @@
 //! # Ok(())
 //! # }
 //! ```
+//!
+//! ## Management API
+//!
+//! Traces, sessions, analytics, eval runs, datasets, prompts and experiments
+//! have typed methods. Transient failures are retried per
+//! [`ClientConfig::retry`], and list endpoints return a [`Page`] that
+//! [`collect_pages`] can walk.
+//!
+//! ```no_run
+//! use agentreplay::{AgentreplayClient, ClientConfig, EvalRunListParams};
+//!
+//! # async fn example() -> agentreplay::Result<()> {
+//! let client = AgentreplayClient::new(
+//!     ClientConfig::new("http://localhost:47100", 1).with_api_key("ar_..."),
+//! );
+//!
+//! let page = client
+//!     .list_eval_runs(&EvalRunListParams {
+//!         status: Some("failed".into()),
+//!         ..Default::default()
+//!     })
+//!     .await?;
+//! for run in &page.items {
+//!     println!("{} {:.0}%", run.name, run.pass_rate * 100.0);
+//! }
+//! # Ok(())
+//! # }
+//! ```
 
+mod api;
 mod client;
+mod pagination;
+mod transport;
 mod types;
 
+pub use api::*;
 pub use client::{ClientConfig, Result, AgentreplayClient, AgentreplayError};
+pub use pagination::{collect_pages, Page};
+pub use transport::RetryPolicy;
 pub use types::*;
@@ -0,0 +1,121 @@
+// Copyright 2025 Sushanth (https://github.com/sushanthpy)
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Pagination helpers.
+//!
+//! Paginated list methods return a [`Page`] whose `next` field holds the
+//! parameters for the following page, whatever the endpoint's scheme
+//! (offset, cursor or page number).
+
+use crate::client::Result;
+use std::future::Future;
+
+/// One page of a list endpoint.
+#[derive(Debug, Clone)]
+pub struct Page<T, P> {
+    pub items: Vec<T>,
+    /// Matching items as reported by the server
+    pub total: usize,
+    /// Parameters for the next page; `None` on the last page
+    pub next: Option<P>,
+}
+
+impl<T, P> Page<T, P> {
+    /// Whether another page follows.
+    pub fn has_next(&self) -> bool {
+        self.next.is_some()
+    }
+}
+
+/// Fetch pages starting at `first` until the last one, or until `max_items`
+/// items have been collected.
+///
+/// ```no_run
+/// use agentreplay::{collect_pages, AgentreplayClient, ClientConfig, SessionListParams};
+///
+/// # async fn example() -> agentreplay::Result<()> {
+/// let client = AgentreplayClient::new(ClientConfig::new("http://localhost:47100", 1));
+/// let sessions = collect_pages(SessionListParams::default(), Some(1_000), |params| {
+///     let client = &client;
+///     async move { client.list_sessions(&params).await }
+/// })
+/// .await?;
+/// println!("{} sessions", sessions.len());
+/// # Ok(())
+/// # }
+/// ```
+pub async fn collect_pages<T, P, F, Fut>(
+    first: P,
+    max_items: Option<usize>,
+    mut fetch: F,
+) -> Result<Vec<T>>
+where
+    F: FnMut(P) -> Fut,
+    Fut: Future<Output = Result<Page<T, P>>>,
+{
+    let mut items = Vec::new();
+    let mut next = Some(first);
+    while let Some(params) = next {
+        let page = fetch(params).await?;
+        // An empty page ends the walk even if the server offers another
+        next = if page.items.is_empty() {
+            None
+        } else {
+            page.next
+        };
+        items.extend(page.items);
+        if let Some(max) = max_items {
+            if items.len() >= max {
+                items.truncate(max);
+                break;
+            }
+        }
+    }
+    Ok(items)
+}
+
+/// `next` for offset-paginated endpoints.
+pub(crate) fn next_offset(offset: usize, returned: usize, total: usize) -> Option<usize> {
+    let next = offset + returned;
+    (returned > 0 && next < total).then_some(next)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_collect_pages() {
+        let data: Vec<u32> = (0..25).collect();
+        let fetch = |offset: usize| {
+            let data = &data;
+            async move {
+                let items: Vec<u32> = data.iter().skip(offset).take(10).copied().collect();
+                Ok(Page {
+                    total: data.len(),
+                    next: next_offset(offset, items.len(), data.len()),
+                    items,
+                })
+            }
+        };
+
+        assert_eq!(collect_pages(0, None, fetch).await.unwrap(), data);
+        assert_eq!(
+            collect_pages(0, Some(12), fetch).await.unwrap(),
+            data[..12].to_vec()
+        );
+        assert_eq!(next_offset(20, 5, 25), None);
+        assert_eq!(next_offset(0, 0, 25), None);
+    }
+}
@@ -0,0 +1,145 @@
+// Copyright 2025 Sushanth (https://github.com/sushanthpy)
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Retry policy for the HTTP transport.
+//!
+//! Requests that fail before the server processed them (connection errors,
+//! `429` and `503` responses) are retried for every method. Timeouts and
+//! gateway errors (`502`, `504`) are only retried for idempotent methods,
+//! since the server may already have acted on the request.
+
+use rand::Rng;
+use reqwest::{Method, StatusCode};
+use std::time::Duration;
+
+/// When and how often failed requests are retried.
+#[derive(Debug, Clone)]
+pub struct RetryPolicy {
+    /// Retries after the first attempt (0 disables retrying)
+    pub max_retries: u32,
+    /// Backoff before the first retry; doubles with each further retry
+    pub initial_backoff: Duration,
+    /// Upper bound for a single wait, including server `Retry-After` hints
+    pub max_backoff: Duration,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self {
+            max_retries: 3,
+            initial_backoff: Duration::from_millis(200),
+            max_backoff: Duration::from_secs(10),
+        }
+    }
+}
+
+impl RetryPolicy {
+    /// A policy that never retries.
+    pub fn none() -> Self {
+        Self {
+            max_retries: 0,
+            ..Default::default()
+        }
+    }
+
+    /// Whether a response with `status` is worth retrying.
+    pub(crate) fn retries_status(&self, method: &Method, status: StatusCode) -> bool {
+        match status {
+            StatusCode::TOO_MANY_REQUESTS | StatusCode::SERVICE_UNAVAILABLE => true,
+            StatusCode::BAD_GATEWAY | StatusCode::GATEWAY_TIMEOUT => is_idempotent(method),
+            _ => false,
+        }
+    }
+
+    /// Whether a request that failed with `error` is worth retrying.
+    pub(crate) fn retries_error(&self, method: &Method, error: &reqwest::Error) -> bool {
+        error.is_connect() || (error.is_timeout() && is_idempotent(method))
+    }
+
+    /// Wait before retry number `attempt` (0-based).
+    ///
+    /// Uses the server's `Retry-After` hint when there is one, otherwise
+    /// exponential backoff with jitter; capped at `max_backoff` either way.
+    pub(crate) fn backoff(&self, attempt: u32, retry_after: Option<Duration>) -> Duration {
+        let wait = retry_after.unwrap_or_else(|| {
+            let ceiling = self
+                .initial_backoff
+                .saturating_mul(2u32.saturating_pow(attempt))
+                .min(self.max_backoff);
+            // Jitter over the upper half keeps clients from retrying in lockstep
+            let ceiling_ms = ceiling.as_millis() as u64;
+            Duration::from_millis(rand::thread_rng().gen_range(ceiling_ms / 2..=ceiling_ms))
+        });
+        wait.min(self.max_backoff)
+    }
+}
+
+fn is_idempotent(method: &Method) -> bool {
+    matches!(
+        *method,
+        Method::GET | Method::HEAD | Method::PUT | Method::DELETE | Method::OPTIONS
+    )
+}
+
+/// `Retry-After` header in seconds.
+pub(crate) fn retry_after(response: &reqwest::Response) -> Option<Duration> {
+    response
+        .headers()
+        .get(reqwest::header::RETRY_AFTER)?
+        .to_str()
+        .ok()?
+        .trim()
+        .parse()
+        .ok()
+        .map(Duration::from_secs)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_retryable_statuses() {
+        let policy = RetryPolicy::default();
+        assert!(policy.retries_status(&Method::POST, StatusCode::TOO_MANY_REQUESTS));
+        assert!(policy.retries_status(&Method::POST, StatusCode::SERVICE_UNAVAILABLE));
+        assert!(policy.retries_status(&Method::GET, StatusCode::BAD_GATEWAY));
+        assert!(!policy.retries_status(&Method::POST, StatusCode::BAD_GATEWAY));
+        assert!(!policy.retries_status(&Method::GET, StatusCode::NOT_FOUND));
+        assert!(!policy.retries_status(&Method::GET, StatusCode::INTERNAL_SERVER_ERROR));
+    }
+
+    #[test]
+    fn test_backoff_bounds() {
+        let policy = RetryPolicy {
+            max_retries: 5,
+            initial_backoff: Duration::from_millis(100),
+            max_backoff: Duration::from_secs(1),
+        };
+        for _ in 0..20 {
+            let first = policy.backoff(0, None);
+            assert!(first >= Duration::from_millis(50) && first <= Duration::from_millis(100));
+            let late = policy.backoff(10, None);
+            assert!(late >= Duration::from_millis(500) && late <= Duration::from_secs(1));
+        }
+        assert_eq!(
+            policy.backoff(0, Some(Duration::from_millis(300))),
+            Duration::from_millis(300)
+        );
+        assert_eq!(
+            policy.backoff(0, Some(Duration::from_secs(30))),
+            Duration::from_secs(1)
+        );
+    }
+}